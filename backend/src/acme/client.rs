@@ -0,0 +1,358 @@
+//! RFC 8555 client: JWS-signed requests with a P-256 account key, HTTP-01
+//! authorizations, finalization and certificate download
+
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::StatusCode;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Status checks of an authorization or order before giving up
+const POLL_ATTEMPTS: u32 = 30;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+    error: Option<Problem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Authorization {
+    status: String,
+    #[serde(default)]
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    #[serde(default)]
+    token: String,
+    error: Option<Problem>,
+}
+
+/// RFC 7807 problem document of a failed request
+#[derive(Debug, Deserialize)]
+struct Problem {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    detail: String,
+}
+
+impl Problem {
+    fn describe(&self) -> String {
+        match self.kind.strip_prefix("urn:ietf:params:acme:error:") {
+            Some("rateLimited") => format!("rate limited by the CA: {}", self.detail),
+            Some(kind) => format!("{}: {}", kind, self.detail),
+            None => self.detail.clone(),
+        }
+    }
+}
+
+/// Where an HTTP-01 challenge wants `key_authorization` served
+pub struct PendingChallenge {
+    pub token: String,
+    pub key_authorization: String,
+}
+
+/// An account session against one ACME directory
+pub struct AcmeClient {
+    http: reqwest::Client,
+    directory: Directory,
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+    /// Account URL once registered (the JWS `kid`)
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl AcmeClient {
+    /// Read the directory; `account_key` is the PKCS#8 P-256 account key
+    pub async fn connect(
+        http: reqwest::Client,
+        directory_url: &str,
+        account_key: &[u8],
+    ) -> Result<Self, String> {
+        let rng = SystemRandom::new();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, account_key, &rng)
+            .map_err(|e| format!("invalid ACME account key: {}", e))?;
+        let directory = http
+            .get(directory_url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("ACME directory {}: {}", directory_url, e))?
+            .json::<Directory>()
+            .await
+            .map_err(|e| format!("ACME directory {}: {}", directory_url, e))?;
+        Ok(Self {
+            http,
+            directory,
+            key,
+            rng,
+            kid: None,
+            nonce: None,
+        })
+    }
+
+    /// New PKCS#8 account key
+    pub fn generate_account_key() -> Result<Vec<u8>, String> {
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .map(|doc| doc.as_ref().to_vec())
+            .map_err(|_| "could not generate an ACME account key".to_string())
+    }
+
+    /// Register the account (or look up the existing one for this key);
+    /// returns the account URL
+    pub async fn register(&mut self, contact_email: Option<&str>) -> Result<String, String> {
+        let mut payload = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = contact_email {
+            payload["contact"] = json!([format!("mailto:{}", email)]);
+        }
+        let url = self.directory.new_account.clone();
+        let response = self.post(&url, Some(&payload)).await?;
+        let kid = response
+            .location
+            .ok_or("ACME account response without Location")?;
+        self.kid = Some(kid.clone());
+        Ok(kid)
+    }
+
+    /// Order a certificate for `hostname`, answering its HTTP-01 challenge
+    /// through `serve` (called with `true` to publish the challenge, then
+    /// with `false` once validation is over); returns the PEM chain
+    pub async fn order_certificate(
+        &mut self,
+        hostname: &str,
+        csr_der: &[u8],
+        serve: impl Fn(&PendingChallenge, bool),
+    ) -> Result<String, String> {
+        let url = self.directory.new_order.clone();
+        let response = self
+            .post(
+                &url,
+                Some(&json!({ "identifiers": [{ "type": "dns", "value": hostname }] })),
+            )
+            .await?;
+        let order: Order = response.json()?;
+        let order_url = response
+            .location
+            .ok_or("ACME order response without Location")?;
+
+        for authorization_url in &order.authorizations {
+            let authorization: Authorization = self.post(authorization_url, None).await?.json()?;
+            if authorization.status == "valid" {
+                continue;
+            }
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|c| c.kind == "http-01")
+                .ok_or_else(|| format!("the CA offers no http-01 challenge for {}", hostname))?;
+            let pending = PendingChallenge {
+                token: challenge.token.clone(),
+                key_authorization: format!("{}.{}", challenge.token, self.thumbprint()),
+            };
+            serve(&pending, true);
+            let validated = self
+                .validate(authorization_url, &challenge.url, hostname)
+                .await;
+            serve(&pending, false);
+            validated?;
+        }
+
+        let csr = URL_SAFE_NO_PAD.encode(csr_der);
+        let mut order: Order = self
+            .post(&order.finalize, Some(&json!({ "csr": csr })))
+            .await?
+            .json()?;
+        let mut attempts = 0;
+        while order.status != "valid" {
+            if order.status == "invalid" || attempts >= POLL_ATTEMPTS {
+                return Err(match &order.error {
+                    Some(problem) => problem.describe(),
+                    None => format!("ACME order for {} is {}", hostname, order.status),
+                });
+            }
+            attempts += 1;
+            tokio::time::sleep(POLL_INTERVAL).await;
+            order = self.post(&order_url, None).await?.json()?;
+        }
+
+        let certificate_url = order
+            .certificate
+            .ok_or("valid ACME order without a certificate URL")?;
+        let response = self.post(&certificate_url, None).await?;
+        String::from_utf8(response.body).map_err(|_| "certificate is not PEM".to_string())
+    }
+
+    /// Trigger the challenge and wait for the authorization to settle
+    async fn validate(
+        &mut self,
+        authorization_url: &str,
+        challenge_url: &str,
+        hostname: &str,
+    ) -> Result<(), String> {
+        self.post(challenge_url, Some(&json!({}))).await?;
+        for _ in 0..POLL_ATTEMPTS {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let authorization: Authorization = self.post(authorization_url, None).await?.json()?;
+            match authorization.status.as_str() {
+                "valid" => return Ok(()),
+                "pending" | "processing" => continue,
+                status => {
+                    let problem = authorization
+                        .challenges
+                        .iter()
+                        .find_map(|c| c.error.as_ref())
+                        .map(Problem::describe);
+                    return Err(format!(
+                        "authorization of {} {}: {}",
+                        hostname,
+                        status,
+                        problem.unwrap_or_else(|| "no detail from the CA".to_string())
+                    ));
+                }
+            }
+        }
+        Err(format!("authorization of {} did not complete", hostname))
+    }
+
+    fn jwk(&self) -> Value {
+        // Uncompressed point: 0x04 || x || y
+        let point = self.key.public_key().as_ref();
+        json!({
+            "crv": "P-256",
+            "kty": "EC",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+        })
+    }
+
+    /// RFC 7638 thumbprint (members in lexicographic order, no whitespace)
+    fn thumbprint(&self) -> String {
+        let canonical = serde_json::to_string(&self.jwk()).unwrap_or_default();
+        let digest = ring::digest::digest(&ring::digest::SHA256, canonical.as_bytes());
+        URL_SAFE_NO_PAD.encode(digest.as_ref())
+    }
+
+    async fn fresh_nonce(&mut self) -> Result<String, String> {
+        if let Some(nonce) = self.nonce.take() {
+            return Ok(nonce);
+        }
+        let response = self
+            .http
+            .head(&self.directory.new_nonce)
+            .send()
+            .await
+            .map_err(|e| format!("ACME nonce: {}", e))?;
+        replay_nonce(response.headers()).ok_or_else(|| "ACME server sent no nonce".to_string())
+    }
+
+    /// Signed POST (`payload` None = POST-as-GET); a rejected nonce is
+    /// retried once with the fresh one
+    async fn post(&mut self, url: &str, payload: Option<&Value>) -> Result<AcmeResponse, String> {
+        let mut retried = false;
+        loop {
+            let nonce = self.fresh_nonce().await?;
+            let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+            match &self.kid {
+                Some(kid) => protected["kid"] = json!(kid),
+                None => protected["jwk"] = self.jwk(),
+            }
+            let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+            let payload = payload
+                .map(|p| URL_SAFE_NO_PAD.encode(p.to_string()))
+                .unwrap_or_default();
+            let signature = self
+                .key
+                .sign(&self.rng, format!("{}.{}", protected, payload).as_bytes())
+                .map_err(|_| "could not sign the ACME request".to_string())?;
+            let body = json!({
+                "protected": protected,
+                "payload": payload,
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            });
+
+            let response = self
+                .http
+                .post(url)
+                .header(CONTENT_TYPE, "application/jose+json")
+                .body(body.to_string())
+                .send()
+                .await
+                .map_err(|e| format!("ACME request {}: {}", url, e))?;
+            self.nonce = replay_nonce(response.headers());
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| format!("ACME request {}: {}", url, e))?
+                .to_vec();
+
+            if status.is_success() {
+                return Ok(AcmeResponse { location, body });
+            }
+            let problem: Option<Problem> = serde_json::from_slice(&body).ok();
+            if !retried
+                && problem
+                    .as_ref()
+                    .is_some_and(|p| p.kind.ends_with(":badNonce"))
+            {
+                retried = true;
+                continue;
+            }
+            return Err(match problem {
+                Some(problem) => problem.describe(),
+                None if status == StatusCode::TOO_MANY_REQUESTS => {
+                    "rate limited by the CA".to_string()
+                }
+                None => format!("ACME request {} failed: {}", url, status),
+            });
+        }
+    }
+}
+
+struct AcmeResponse {
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+impl AcmeResponse {
+    fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_slice(&self.body).map_err(|e| format!("unexpected ACME response: {}", e))
+    }
+}
+
+fn replay_nonce(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get("replay-nonce")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
@@ -0,0 +1,95 @@
+//! PKCS#10 certificate signing request of a P-256 key for one DNS name
+
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+/// 2.5.4.3
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+/// 1.2.840.10045.2.1
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// 1.2.840.10045.3.1.7 (P-256)
+const OID_PRIME256V1: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+/// 1.2.840.113549.1.9.14
+const OID_EXTENSION_REQUEST: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x0E];
+/// 2.5.29.17
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+/// 1.2.840.10045.4.3.2
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+
+/// New P-256 certificate key (PKCS#8 DER)
+pub fn generate_key() -> Result<Vec<u8>, String> {
+    EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+        .map(|doc| doc.as_ref().to_vec())
+        .map_err(|_| "could not generate a certificate key".to_string())
+}
+
+/// DER CSR for `hostname` (CN and the only subjectAltName), signed by
+/// `pkcs8`
+pub fn build(hostname: &str, pkcs8: &[u8]) -> Result<Vec<u8>, String> {
+    let rng = SystemRandom::new();
+    let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8, &rng)
+        .map_err(|e| format!("invalid certificate key: {}", e))?;
+    let info = request_info(hostname, key.public_key().as_ref());
+    let signature = key
+        .sign(&rng, &info)
+        .map_err(|_| "could not sign the CSR".to_string())?;
+    Ok(sequence(&[
+        info,
+        sequence(&[oid(OID_ECDSA_WITH_SHA256)]),
+        bit_string(signature.as_ref()),
+    ]))
+}
+
+/// certificationRequestInfo: version 0, CN=hostname, the key, and a
+/// subjectAltName extension request
+fn request_info(hostname: &str, public_key: &[u8]) -> Vec<u8> {
+    let subject = sequence(&[tlv(
+        0x31,
+        &sequence(&[oid(OID_COMMON_NAME), tlv(0x0C, hostname.as_bytes())]),
+    )]);
+    let key_info = sequence(&[
+        sequence(&[oid(OID_EC_PUBLIC_KEY), oid(OID_PRIME256V1)]),
+        bit_string(public_key),
+    ]);
+    let alt_names = sequence(&[tlv(0x82, hostname.as_bytes())]);
+    let extensions = sequence(&[sequence(&[
+        oid(OID_SUBJECT_ALT_NAME),
+        tlv(0x04, &alt_names),
+    ])]);
+    let attributes = tlv(
+        0xA0,
+        &sequence(&[oid(OID_EXTENSION_REQUEST), tlv(0x31, &extensions)]),
+    );
+    sequence(&[tlv(0x02, &[0]), subject, key_info, attributes])
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &parts.concat())
+}
+
+fn oid(encoded: &[u8]) -> Vec<u8> {
+    tlv(0x06, encoded)
+}
+
+/// BIT STRING without unused bits
+fn bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut content = Vec::with_capacity(bytes.len() + 1);
+    content.push(0);
+    content.extend_from_slice(bytes);
+    tlv(0x03, &content)
+}
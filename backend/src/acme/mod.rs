@@ -0,0 +1,490 @@
+//! ACME (Let's Encrypt) certificates for DDNS hostnames
+//!
+//! `POST /api/ddns/:id/acme/issue` starts an order for the config's hostname.
+//! The HTTP-01 challenge is answered by the proxy fallback from an in-memory
+//! token store (`challenge_response`) on the instance running the order, so
+//! port 80 of the hostname must reach that instance. Before ordering, the
+//! hostname is checked to resolve to the config's last pushed address; an
+//! order that would fail validation is not sent to the CA.
+//!
+//! Issued certificates are kept in MySQL (`acme_certificates`), optionally
+//! also written under `[acme] cert_dir`, and installed into the HTTPS
+//! listener's certificate store on every instance (`sync_loop`). The leader
+//! renews them `renew_before_days` before expiry; a failed order keeps the
+//! previous certificate in service, is recorded in the certificate's and the
+//! DDNS config's `last_error`, and is notified to Discord.
+
+mod client;
+mod csr;
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, SubsecRound, Utc};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::PrivatePkcs8KeyDer;
+use tokio::time::interval;
+
+use crate::config::AcmeConfig;
+use crate::db::AppState;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{AcmeCertificate, DdnsConfig};
+use crate::notify::DiscordNotifier;
+use crate::tls::TlsCertStore;
+use client::AcmeClient;
+
+/// Path prefix of HTTP-01 validation requests
+pub const CHALLENGE_PREFIX: &str = "/.well-known/acme-challenge/";
+
+/// How often the leader looks for certificates due for renewal
+const RENEW_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A failed renewal is retried after this long
+const RENEW_RETRY_AFTER: chrono::Duration = chrono::Duration::hours(12);
+
+/// How often each instance picks up certificates issued elsewhere
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct AcmeManager {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    config: AcmeConfig,
+    tls: Arc<TlsCertStore>,
+    http: reqwest::Client,
+    /// HTTP-01 key authorizations by token while validations run
+    challenges: RwLock<HashMap<String, String>>,
+    /// DDNS configs with an order running on this instance
+    running: Mutex<HashSet<i32>>,
+}
+
+impl AcmeManager {
+    pub fn new(
+        app_state: AppState,
+        notifier: Arc<DiscordNotifier>,
+        config: AcmeConfig,
+        tls: Arc<TlsCertStore>,
+    ) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("LacisProxyGateway/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            app_state,
+            notifier,
+            config,
+            tls,
+            http,
+            challenges: RwLock::new(HashMap::new()),
+            running: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Key authorization to answer a validation request for `path`
+    pub fn challenge_response(&self, path: &str) -> Option<String> {
+        let token = path.strip_prefix(CHALLENGE_PREFIX)?;
+        self.challenges.read().unwrap().get(token).cloned()
+    }
+
+    /// Start an order for `ddns`'s hostname in the background
+    pub async fn begin(self: &Arc<Self>, ddns: DdnsConfig) -> Result<(), AppError> {
+        if ddns.hostname.starts_with("*.") {
+            return Err(AppError::BadRequest(
+                "Wildcard hostnames need DNS-01 validation, which is not supported".to_string(),
+            ));
+        }
+        if !self.running.lock().unwrap().insert(ddns.id) {
+            return Err(AppError::coded(
+                ErrorCode::AcmeOrderInProgress,
+                format!(
+                    "A certificate order for {} is already running",
+                    ddns.hostname
+                ),
+            ));
+        }
+        if let Err(e) = self
+            .app_state
+            .mysql
+            .begin_acme_order(ddns.id, &ddns.hostname)
+            .await
+        {
+            self.running.lock().unwrap().remove(&ddns.id);
+            return Err(e);
+        }
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.run_order(&ddns).await;
+            manager.running.lock().unwrap().remove(&ddns.id);
+        });
+        Ok(())
+    }
+
+    async fn run_order(&self, ddns: &DdnsConfig) {
+        tracing::info!("[ACME] Ordering a certificate for {}", ddns.hostname);
+        let error = match self.issue(ddns).await {
+            Ok(not_after) => {
+                tracing::info!(
+                    "[ACME] Certificate for {} issued (expires {})",
+                    ddns.hostname,
+                    not_after
+                );
+                return;
+            }
+            Err(e) => e,
+        };
+
+        tracing::error!("[ACME] Order for {} failed: {}", ddns.hostname, error);
+        let mysql = &self.app_state.mysql;
+        if let Err(e) = mysql.fail_acme_order(ddns.id, &error).await {
+            tracing::error!("[ACME] Failed to record the failed order: {}", e);
+        }
+        if let Err(e) = mysql.set_ddns_acme_error(ddns.id, &error).await {
+            tracing::error!("[ACME] Failed to record the error on DDNS config: {}", e);
+        }
+        // The certificate still served, if any
+        let expires = self.tls_expiry(&ddns.hostname);
+        self.notifier
+            .notify_acme_failure(&ddns.hostname, &error, expires)
+            .await;
+    }
+
+    fn tls_expiry(&self, hostname: &str) -> Option<DateTime<Utc>> {
+        self.tls
+            .status()
+            .into_iter()
+            .find(|c| {
+                c.source == "acme" && c.names.iter().any(|n| n.eq_ignore_ascii_case(hostname))
+            })
+            .and_then(|c| c.certificate.map(|info| info.not_after))
+    }
+
+    /// Run one order to completion; returns the new certificate's expiry
+    async fn issue(&self, ddns: &DdnsConfig) -> Result<DateTime<Utc>, String> {
+        check_dns(ddns).await?;
+
+        let account_key = self.account_key().await?;
+        let mut client =
+            AcmeClient::connect(self.http.clone(), &self.config.directory_url, &account_key)
+                .await?;
+        client
+            .register(self.config.contact_email.as_deref())
+            .await?;
+
+        let certificate_key = csr::generate_key()?;
+        let csr = csr::build(&ddns.hostname, &certificate_key)?;
+        let cert_pem = client
+            .order_certificate(&ddns.hostname, &csr, |challenge, publish| {
+                let mut challenges = self.challenges.write().unwrap();
+                if publish {
+                    challenges.insert(challenge.token.clone(), challenge.key_authorization.clone());
+                } else {
+                    challenges.remove(&challenge.token);
+                }
+            })
+            .await?;
+        let key_pem = pem("PRIVATE KEY", &certificate_key);
+
+        let issued_at = Utc::now().trunc_subsecs(0);
+        let info = self
+            .tls
+            .install_issued(&ddns.hostname, &cert_pem, &key_pem, issued_at)
+            .map_err(|e| format!("the CA returned an unusable certificate: {}", e))?;
+        self.app_state
+            .mysql
+            .store_acme_certificate(
+                ddns.id,
+                &cert_pem,
+                &key_pem,
+                info.not_before,
+                info.not_after,
+                issued_at,
+            )
+            .await
+            .map_err(|e| format!("certificate issued but not stored: {}", e))?;
+        if let Some(dir) = &self.config.cert_dir {
+            if let Err(e) = write_files(dir, &ddns.hostname, &cert_pem, &key_pem) {
+                tracing::error!("[ACME] Could not write the certificate files: {}", e);
+            }
+        }
+        Ok(info.not_after)
+    }
+
+    /// PKCS#8 account key for the configured directory (created on first use)
+    async fn account_key(&self) -> Result<Vec<u8>, String> {
+        let mysql = &self.app_state.mysql;
+        let directory = &self.config.directory_url;
+        let stored = mysql
+            .get_acme_account_key(directory)
+            .await
+            .map_err(|e| e.to_string())?;
+        let key_pem = match stored {
+            Some(key_pem) => key_pem,
+            None => {
+                let key = AcmeClient::generate_account_key()?;
+                mysql
+                    .insert_acme_account_key(directory, &pem("PRIVATE KEY", &key))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // Another instance may have stored its key first
+                mysql
+                    .get_acme_account_key(directory)
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .ok_or("ACME account key not stored")?
+            }
+        };
+        PrivatePkcs8KeyDer::from_pem_slice(key_pem.as_bytes())
+            .map(|key| key.secret_pkcs8_der().to_vec())
+            .map_err(|e| format!("stored ACME account key: {}", e))
+    }
+
+    /// Install stored certificates not yet (or no longer) served here
+    pub async fn sync_installed(&self) {
+        let certificates = match self.app_state.mysql.list_acme_certificates().await {
+            Ok(certificates) => certificates,
+            Err(e) => {
+                tracing::warn!("[ACME] Stored certificates not loaded: {}", e);
+                return;
+            }
+        };
+        for cert in certificates {
+            let (Some(cert_pem), Some(key_pem), Some(issued_at)) =
+                (&cert.cert_pem, &cert.key_pem, cert.issued_at)
+            else {
+                continue;
+            };
+            if self.tls.issued_at(&cert.hostname) == Some(issued_at) {
+                continue;
+            }
+            if let Err(e) = self
+                .tls
+                .install_issued(&cert.hostname, cert_pem, key_pem, issued_at)
+            {
+                tracing::error!(
+                    "[ACME] Stored certificate for {} not usable: {}",
+                    cert.hostname,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Keep this instance's HTTPS listener in step with the stored
+    /// certificates (per instance)
+    pub async fn sync_loop(self: Arc<Self>) {
+        let mut tick = interval(SYNC_INTERVAL);
+        loop {
+            tick.tick().await;
+            self.sync_installed().await;
+        }
+    }
+
+    /// Renew certificates entering the renewal window (leader only)
+    pub async fn start(self: Arc<Self>) {
+        let mut tick = interval(RENEW_CHECK_INTERVAL);
+        loop {
+            tick.tick().await;
+            if let Err(e) = self.renew_due().await {
+                tracing::error!("[ACME] Renewal check failed: {}", e);
+            }
+        }
+    }
+
+    async fn renew_due(self: &Arc<Self>) -> Result<(), AppError> {
+        let now = Utc::now();
+        let renew_before = chrono::Duration::days(self.config.renew_before_days);
+        for cert in self.app_state.mysql.list_acme_certificates().await? {
+            if !renewal_due(&cert, renew_before, now) {
+                continue;
+            }
+            let Some(ddns) = self.app_state.mysql.get_ddns(cert.ddns_config_id).await? else {
+                continue;
+            };
+            tracing::info!(
+                "[ACME] Renewing the certificate for {} (expires {:?})",
+                ddns.hostname,
+                cert.not_after
+            );
+            if let Err(e) = self.begin(ddns).await {
+                tracing::warn!("[ACME] Renewal not started: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Issued, inside the renewal window, and not attempted within
+/// `RENEW_RETRY_AFTER` (an order that is still pending counts as attempted)
+fn renewal_due(cert: &AcmeCertificate, renew_before: chrono::Duration, now: DateTime<Utc>) -> bool {
+    let Some(not_after) = cert.not_after else {
+        return false;
+    };
+    cert.cert_pem.is_some()
+        && not_after - renew_before <= now
+        && cert
+            .last_attempt_at
+            .is_none_or(|at| now - at >= RENEW_RETRY_AFTER)
+}
+
+/// Refuse to order when the hostname does not resolve to the address the
+/// DDNS config last pushed (validation would fail and count against the
+/// CA's limits)
+async fn check_dns(ddns: &DdnsConfig) -> Result<(), String> {
+    let expected: Vec<IpAddr> = [&ddns.last_ip, &ddns.last_ipv6]
+        .into_iter()
+        .flatten()
+        .filter_map(|ip| ip.parse().ok())
+        .collect();
+    if expected.is_empty() {
+        return Ok(());
+    }
+    let resolved: Vec<IpAddr> = tokio::net::lookup_host((ddns.hostname.as_str(), 80))
+        .await
+        .map_err(|e| format!("DNS not pointing at this gateway: {}: {}", ddns.hostname, e))?
+        .map(|addr| addr.ip())
+        .collect();
+    if resolved.iter().any(|ip| expected.contains(ip)) {
+        return Ok(());
+    }
+    let list = |ips: &[IpAddr]| {
+        ips.iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Err(format!(
+        "DNS not pointing at this gateway: {} resolves to [{}], expected [{}]",
+        ddns.hostname,
+        list(&resolved),
+        list(&expected)
+    ))
+}
+
+fn pem(label: &str, der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        out.push_str(&String::from_utf8_lossy(line));
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+/// `<dir>/<hostname>/fullchain.pem` and `privkey.pem` (0600)
+fn write_files(dir: &str, hostname: &str, cert_pem: &str, key_pem: &str) -> std::io::Result<()> {
+    let dir = std::path::Path::new(dir).join(hostname);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("fullchain.pem"), cert_pem)?;
+    let key_path = dir.join("privkey.pem");
+    std::fs::write(&key_path, key_pem)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cert(not_after_days: i64, last_attempt_hours_ago: Option<i64>) -> AcmeCertificate {
+        let now = Utc::now();
+        AcmeCertificate {
+            id: 1,
+            ddns_config_id: 1,
+            hostname: "gateway.example.com".to_string(),
+            status: "valid".to_string(),
+            cert_pem: Some("pem".to_string()),
+            key_pem: Some("pem".to_string()),
+            not_before: None,
+            not_after: Some(now + chrono::Duration::days(not_after_days)),
+            issued_at: None,
+            last_attempt_at: last_attempt_hours_ago.map(|h| now - chrono::Duration::hours(h)),
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn renewal_starts_inside_the_window_and_backs_off() {
+        let now = Utc::now();
+        let window = chrono::Duration::days(30);
+        assert!(!renewal_due(&cert(60, Some(24 * 30)), window, now));
+        assert!(renewal_due(&cert(29, Some(24 * 60)), window, now));
+        assert!(renewal_due(&cert(29, None), window, now));
+        // A failed renewal waits before the next attempt
+        assert!(!renewal_due(&cert(20, Some(1)), window, now));
+
+        let mut never_issued = cert(0, None);
+        never_issued.cert_pem = None;
+        assert!(!renewal_due(&never_issued, window, now));
+    }
+
+    #[test]
+    fn csr_is_signed_by_the_certificate_key() {
+        use ring::signature::{
+            EcdsaKeyPair, KeyPair, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1,
+            ECDSA_P256_SHA256_ASN1_SIGNING,
+        };
+
+        /// (header + content, content, rest) of the DER element at `der`
+        fn element(der: &[u8]) -> (&[u8], &[u8], &[u8]) {
+            let (len, header) = match der[1] {
+                n if n < 0x80 => (n as usize, 2),
+                n => {
+                    let bytes = (n & 0x7F) as usize;
+                    let len = der[2..2 + bytes]
+                        .iter()
+                        .fold(0, |acc, b| (acc << 8) | *b as usize);
+                    (len, 2 + bytes)
+                }
+            };
+            let end = header + len;
+            (&der[..end], &der[header..end], &der[end..])
+        }
+
+        let key = csr::generate_key().unwrap();
+        let der = csr::build("gateway.example.com", &key).unwrap();
+        let (_, csr, rest) = element(&der);
+        assert!(rest.is_empty());
+        let (info, _, rest) = element(csr);
+        let (_, _, rest) = element(rest);
+        let (_, signature, _) = element(rest);
+
+        let public_key = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_ASN1_SIGNING,
+            &key,
+            &ring::rand::SystemRandom::new(),
+        )
+        .unwrap()
+        .public_key()
+        .as_ref()
+        .to_vec();
+        // BIT STRING content starts with the unused-bits count
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, &public_key)
+            .verify(info, &signature[1..])
+            .unwrap();
+        let hostname = b"gateway.example.com";
+        assert_eq!(
+            info.windows(hostname.len())
+                .filter(|w| *w == hostname)
+                .count(),
+            2,
+            "CN and subjectAltName"
+        );
+
+        // The PEM form of the key is what gets stored and served
+        let stored =
+            PrivatePkcs8KeyDer::from_pem_slice(pem("PRIVATE KEY", &key).as_bytes()).unwrap();
+        assert_eq!(stored.secret_pkcs8_der(), key.as_slice());
+    }
+}
@@ -0,0 +1,1002 @@
+//! Access-log alert rules
+//!
+//! Rules live in the `alert_rules` collection and are evaluated every minute
+//! (leader only) against the access logs of their trailing window:
+//!
+//! - `filter`: conditions over access log fields joined by `and`, e.g.
+//!   `status == 401 and path ~ /api/auth/*` or `path ~ /admin* and country_code != JP`
+//! - `metric`: `count` (matching requests), `error_rate` (percent of matching
+//!   requests with status >= 500) or `unique_ips`
+//! - the rule is breached when the metric is above `threshold` and at least
+//!   `min_requests` requests matched
+//!
+//! Daily metrics read the per-IP rollups (`ip_daily_stats`) instead of raw
+//! logs: `ip_daily_requests`, `ip_daily_errors` or `ip_daily_security_events`
+//! breach when some IP was above `threshold` on each of the last `days`
+//! completed UTC days (e.g. more than 10000 requests/day for 3 days). Their
+//! filter may only reference `ip`; `window_minutes` and `min_requests` do not
+//! apply.
+//!
+//! A breach writes an `alert_rule` security event and notifies the rule's
+//! target. While the breach lasts the rule stays `firing` but does not fire
+//! again until `cooldown_minutes` have passed. Evaluation failures are kept
+//! in the rule's status.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Bson, Document};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use crate::db::mongo::MongoDb;
+use crate::db::AppState;
+use crate::models::Severity;
+use crate::notify::DiscordNotifier;
+
+/// Longest evaluation window (one day)
+pub const MAX_WINDOW_MINUTES: u32 = 1440;
+/// Longest cooldown (one week)
+pub const MAX_COOLDOWN_MINUTES: u32 = 10080;
+/// Most consecutive days a daily metric can span
+pub const MAX_DAILY_DAYS: u32 = 30;
+/// Source IPs listed in events and test results
+const TOP_IPS: usize = 5;
+
+/// Field an expression may reference, and how its values are typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Number,
+    Text,
+    /// Text compared in upper case (methods, country codes)
+    Code,
+}
+
+const FIELDS: &[(&str, FieldKind)] = &[
+    ("ip", FieldKind::Text),
+    ("method", FieldKind::Code),
+    ("path", FieldKind::Text),
+    ("target", FieldKind::Text),
+    ("status", FieldKind::Number),
+    ("route_id", FieldKind::Number),
+    ("response_time_ms", FieldKind::Number),
+    ("request_size", FieldKind::Number),
+    ("response_size", FieldKind::Number),
+    ("user_agent", FieldKind::Text),
+    ("referer", FieldKind::Text),
+    ("country_code", FieldKind::Code),
+    ("http_version", FieldKind::Code),
+    ("upstream_error", FieldKind::Text),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// Glob match (`*` any run, `?` any character), anchored at both ends
+    Glob,
+    NotGlob,
+    In,
+    NotIn,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl From<&Value> for Bson {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => Bson::Int64(*n),
+            Value::Text(s) => Bson::String(s.clone()),
+        }
+    }
+}
+
+/// One `field op value` clause
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    field: &'static str,
+    op: Op,
+    values: Vec<Value>,
+}
+
+impl Condition {
+    fn to_document(&self) -> Document {
+        let first = Bson::from(&self.values[0]);
+        let list: Vec<Bson> = self.values.iter().map(Bson::from).collect();
+        let glob = || match &self.values[0] {
+            Value::Text(s) => glob_to_regex(s),
+            Value::Number(n) => n.to_string(),
+        };
+        let condition = match self.op {
+            Op::Eq => first,
+            Op::Ne => Bson::Document(doc! { "$ne": first }),
+            Op::Gt => Bson::Document(doc! { "$gt": first }),
+            Op::Ge => Bson::Document(doc! { "$gte": first }),
+            Op::Lt => Bson::Document(doc! { "$lt": first }),
+            Op::Le => Bson::Document(doc! { "$lte": first }),
+            Op::Glob => Bson::Document(doc! { "$regex": glob() }),
+            Op::NotGlob => Bson::Document(doc! { "$not": { "$regex": glob() } }),
+            Op::In => Bson::Document(doc! { "$in": list }),
+            Op::NotIn => Bson::Document(doc! { "$nin": list }),
+        };
+        let mut document = Document::new();
+        document.insert(self.field, condition);
+        document
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut literal = String::new();
+    for c in glob.chars() {
+        if c == '*' || c == '?' {
+            regex.push_str(&regex::escape(&literal));
+            literal.clear();
+            regex.push_str(if c == '*' { ".*" } else { "." });
+        } else {
+            literal.push(c);
+        }
+    }
+    regex.push_str(&regex::escape(&literal));
+    regex.push('$');
+    regex
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(String),
+    Open,
+    Close,
+    Comma,
+}
+
+const OP_CHARS: &str = "=!<>~";
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => text.extend(chars.next()),
+                    Some(c) => text.push(c),
+                    None => return Err("unterminated quoted value".to_string()),
+                }
+            }
+            tokens.push(Token::Quoted(text));
+        } else if c == '(' || c == ')' || c == ',' {
+            chars.next();
+            tokens.push(match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Comma,
+            });
+        } else {
+            let operator = OP_CHARS.contains(c);
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                let boundary = c.is_whitespace() || "(),\"".contains(c);
+                if boundary || OP_CHARS.contains(c) != operator {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(if operator {
+                Token::Op(word)
+            } else {
+                Token::Word(word)
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a filter expression; an empty expression matches every request
+fn parse_filter(expr: &str) -> Result<Vec<Condition>, String> {
+    let mut tokens = tokenize(expr)?.into_iter().peekable();
+    let mut conditions = Vec::new();
+    while tokens.peek().is_some() {
+        if !conditions.is_empty() {
+            match tokens.next() {
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("and") => {}
+                other => return Err(format!("expected 'and', found {}", describe(other))),
+            }
+        }
+
+        let name = match tokens.next() {
+            Some(Token::Word(w)) => w.to_ascii_lowercase(),
+            other => return Err(format!("expected a field, found {}", describe(other))),
+        };
+        let (field, kind) = FIELDS
+            .iter()
+            .find(|(f, _)| *f == name)
+            .copied()
+            .ok_or_else(|| {
+                let known: Vec<&str> = FIELDS.iter().map(|(f, _)| *f).collect();
+                format!("unknown field '{}' (fields: {})", name, known.join(", "))
+            })?;
+
+        let op = match tokens.next() {
+            Some(Token::Op(op)) => match op.as_str() {
+                "==" | "=" => Op::Eq,
+                "!=" => Op::Ne,
+                ">" => Op::Gt,
+                ">=" => Op::Ge,
+                "<" => Op::Lt,
+                "<=" => Op::Le,
+                "~" => Op::Glob,
+                "!~" => Op::NotGlob,
+                _ => return Err(format!("unknown operator '{}'", op)),
+            },
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("in") => Op::In,
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("not") => match tokens.next() {
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("in") => Op::NotIn,
+                other => return Err(format!("expected 'in', found {}", describe(other))),
+            },
+            other => {
+                return Err(format!(
+                    "expected an operator after '{}', found {}",
+                    field,
+                    describe(other)
+                ))
+            }
+        };
+        match (kind, op) {
+            (FieldKind::Number, Op::Glob | Op::NotGlob) => {
+                return Err(format!("'{}' is numeric and cannot use ~", field))
+            }
+            (FieldKind::Text | FieldKind::Code, Op::Gt | Op::Ge | Op::Lt | Op::Le) => {
+                return Err(format!(
+                    "'{}' is text and only supports ==, !=, ~, !~, in",
+                    field
+                ))
+            }
+            _ => {}
+        }
+
+        let raw_values = if matches!(op, Op::In | Op::NotIn) {
+            if tokens.next() != Some(Token::Open) {
+                return Err(format!("expected '(' after in for '{}'", field));
+            }
+            let mut values = Vec::new();
+            loop {
+                match tokens.next() {
+                    Some(Token::Word(v)) | Some(Token::Quoted(v)) => values.push(v),
+                    other => return Err(format!("expected a value, found {}", describe(other))),
+                }
+                match tokens.next() {
+                    Some(Token::Comma) => {}
+                    Some(Token::Close) => break,
+                    other => return Err(format!("expected ',' or ')', found {}", describe(other))),
+                }
+            }
+            values
+        } else {
+            match tokens.next() {
+                Some(Token::Word(v)) | Some(Token::Quoted(v)) => vec![v],
+                other => {
+                    return Err(format!(
+                        "expected a value for '{}', found {}",
+                        field,
+                        describe(other)
+                    ))
+                }
+            }
+        };
+
+        let values = raw_values
+            .into_iter()
+            .map(|v| match kind {
+                FieldKind::Number => v
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| format!("'{}' expects a number, got '{}'", field, v)),
+                FieldKind::Text => Ok(Value::Text(v)),
+                FieldKind::Code => Ok(Value::Text(v.to_ascii_uppercase())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        conditions.push(Condition { field, op, values });
+    }
+    Ok(conditions)
+}
+
+fn describe(token: Option<Token>) -> String {
+    match token {
+        None => "end of expression".to_string(),
+        Some(Token::Word(w)) | Some(Token::Op(w)) => format!("'{}'", w),
+        Some(Token::Quoted(q)) => format!("\"{}\"", q),
+        Some(Token::Open) => "'('".to_string(),
+        Some(Token::Close) => "')'".to_string(),
+        Some(Token::Comma) => "','".to_string(),
+    }
+}
+
+/// Compile a filter expression to Mongo `$match` clauses (to be `$and`-ed)
+pub fn compile_filter(expr: &str) -> Result<Vec<Document>, String> {
+    Ok(parse_filter(expr)?
+        .iter()
+        .map(Condition::to_document)
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    #[default]
+    Count,
+    /// Percent of matching requests with status >= 500
+    ErrorRate,
+    UniqueIps,
+    /// Requests per IP and day (`ip_daily_stats`)
+    IpDailyRequests,
+    /// Status >= 400 responses per IP and day
+    IpDailyErrors,
+    /// Security events per IP and day
+    IpDailySecurityEvents,
+}
+
+impl AlertMetric {
+    /// `ip_daily_stats` field of a daily metric
+    pub fn daily_field(&self) -> Option<&'static str> {
+        match self {
+            Self::IpDailyRequests => Some("requests"),
+            Self::IpDailyErrors => Some("errors"),
+            Self::IpDailySecurityEvents => Some("security_events"),
+            Self::Count | Self::ErrorRate | Self::UniqueIps => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    /// Not evaluated yet
+    #[default]
+    Pending,
+    Ok,
+    Firing,
+    Error,
+}
+
+/// Outcome of the last evaluations, written by the engine only
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertRuleStatus {
+    #[serde(default)]
+    pub state: AlertState,
+    #[serde(default)]
+    pub last_evaluated_at: Option<String>,
+    #[serde(default)]
+    pub last_value: Option<f64>,
+    #[serde(default)]
+    pub last_matched: Option<u64>,
+    #[serde(default)]
+    pub last_fired_at: Option<String>,
+    #[serde(default)]
+    pub fire_count: u64,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// An alert rule document (collection `alert_rules`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub rule_id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Filter expression over access log fields (see module docs)
+    pub filter: String,
+    pub metric: AlertMetric,
+    pub window_minutes: u32,
+    /// Consecutive completed days for daily metrics
+    #[serde(default = "default_days")]
+    pub days: u32,
+    pub threshold: f64,
+    #[serde(default)]
+    pub min_requests: u64,
+    pub severity: Severity,
+    /// "none", "discord" (the configured webhook) or an https webhook URL
+    pub notify: String,
+    pub cooldown_minutes: u32,
+    #[serde(default)]
+    pub status: AlertRuleStatus,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_window_minutes() -> u32 {
+    5
+}
+
+fn default_days() -> u32 {
+    1
+}
+
+fn default_severity() -> Severity {
+    Severity::Medium
+}
+
+fn default_notify() -> String {
+    "discord".to_string()
+}
+
+fn default_cooldown_minutes() -> u32 {
+    15
+}
+
+/// POST /api/security/alert-rules body
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateAlertRuleRequest {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub filter: String,
+    #[serde(default)]
+    pub metric: AlertMetric,
+    #[serde(default = "default_window_minutes")]
+    pub window_minutes: u32,
+    #[serde(default = "default_days")]
+    pub days: u32,
+    pub threshold: f64,
+    #[serde(default)]
+    pub min_requests: u64,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    #[serde(default = "default_notify")]
+    pub notify: String,
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: u32,
+}
+
+/// PUT /api/security/alert-rules/:id body (None = unchanged)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateAlertRuleRequest {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub filter: Option<String>,
+    pub metric: Option<AlertMetric>,
+    pub window_minutes: Option<u32>,
+    pub days: Option<u32>,
+    pub threshold: Option<f64>,
+    pub min_requests: Option<u64>,
+    pub severity: Option<Severity>,
+    pub notify: Option<String>,
+    pub cooldown_minutes: Option<u32>,
+}
+
+impl AlertRule {
+    pub fn new(req: CreateAlertRuleRequest) -> Result<Self, String> {
+        let now = Utc::now().to_rfc3339();
+        let rule = Self {
+            rule_id: uuid::Uuid::new_v4().to_string(),
+            name: req.name.trim().to_string(),
+            enabled: req.enabled,
+            filter: req.filter.trim().to_string(),
+            metric: req.metric,
+            window_minutes: req.window_minutes,
+            days: req.days,
+            threshold: req.threshold,
+            min_requests: req.min_requests,
+            severity: req.severity,
+            notify: req.notify.trim().to_string(),
+            cooldown_minutes: req.cooldown_minutes,
+            status: AlertRuleStatus::default(),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        rule.validate()?;
+        Ok(rule)
+    }
+
+    /// Apply an update and re-validate the result
+    pub fn apply(&mut self, req: UpdateAlertRuleRequest) -> Result<(), String> {
+        if let Some(name) = req.name {
+            self.name = name.trim().to_string();
+        }
+        if let Some(enabled) = req.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(filter) = req.filter {
+            self.filter = filter.trim().to_string();
+        }
+        if let Some(metric) = req.metric {
+            self.metric = metric;
+        }
+        if let Some(window) = req.window_minutes {
+            self.window_minutes = window;
+        }
+        if let Some(days) = req.days {
+            self.days = days;
+        }
+        if let Some(threshold) = req.threshold {
+            self.threshold = threshold;
+        }
+        if let Some(min_requests) = req.min_requests {
+            self.min_requests = min_requests;
+        }
+        if let Some(severity) = req.severity {
+            self.severity = severity;
+        }
+        if let Some(notify) = req.notify {
+            self.notify = notify.trim().to_string();
+        }
+        if let Some(cooldown) = req.cooldown_minutes {
+            self.cooldown_minutes = cooldown;
+        }
+        self.updated_at = Utc::now().to_rfc3339();
+        self.validate()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name is required".to_string());
+        }
+        let conditions =
+            parse_filter(&self.filter).map_err(|e| format!("invalid filter: {}", e))?;
+        if self.metric.daily_field().is_some() {
+            if conditions.iter().any(|c| c.field != "ip") {
+                return Err("daily metrics can only filter on ip".to_string());
+            }
+            if !(1..=MAX_DAILY_DAYS).contains(&self.days) {
+                return Err(format!("days must be between 1 and {}", MAX_DAILY_DAYS));
+            }
+        }
+        if !(1..=MAX_WINDOW_MINUTES).contains(&self.window_minutes) {
+            return Err(format!(
+                "window_minutes must be between 1 and {}",
+                MAX_WINDOW_MINUTES
+            ));
+        }
+        if !self.threshold.is_finite() || self.threshold < 0.0 {
+            return Err("threshold must be a non-negative number".to_string());
+        }
+        if self.metric == AlertMetric::ErrorRate && self.threshold >= 100.0 {
+            return Err("error_rate threshold is a percentage below 100".to_string());
+        }
+        if self.cooldown_minutes > MAX_COOLDOWN_MINUTES {
+            return Err(format!(
+                "cooldown_minutes must be at most {}",
+                MAX_COOLDOWN_MINUTES
+            ));
+        }
+        if !matches!(self.notify.as_str(), "none" | "discord")
+            && !self.notify.starts_with("https://")
+        {
+            return Err("notify must be \"none\", \"discord\" or an https webhook URL".to_string());
+        }
+        Ok(())
+    }
+
+    /// Metric value for the window
+    pub fn value(&self, stats: &WindowStats) -> f64 {
+        match self.metric {
+            AlertMetric::Count => stats.matched as f64,
+            AlertMetric::UniqueIps => stats.unique_ips as f64,
+            AlertMetric::ErrorRate if stats.matched == 0 => 0.0,
+            AlertMetric::ErrorRate => stats.errors as f64 * 100.0 / stats.matched as f64,
+            // Sustained level of the worst IP (top_ips is sorted)
+            AlertMetric::IpDailyRequests
+            | AlertMetric::IpDailyErrors
+            | AlertMetric::IpDailySecurityEvents => {
+                stats.top_ips.first().map_or(0.0, |ip| ip.count as f64)
+            }
+        }
+    }
+
+    pub fn is_breached(&self, stats: &WindowStats) -> bool {
+        if self.metric.daily_field().is_some() {
+            return self.value(stats) > self.threshold;
+        }
+        stats.matched >= self.min_requests && self.value(stats) > self.threshold
+    }
+
+    /// Evaluated span: the trailing window, or the last `days` completed
+    /// UTC days for daily metrics
+    pub fn window(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        if self.metric.daily_field().is_none() {
+            return (
+                now - chrono::Duration::minutes(self.window_minutes as i64),
+                now,
+            );
+        }
+        let today = now.date_naive().and_time(chrono::NaiveTime::MIN).and_utc();
+        (today - chrono::Duration::days(self.days as i64), today)
+    }
+
+    /// End of the cooldown started by the last firing, if still running
+    pub fn cooldown_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let fired = self.status.last_fired_at.as_deref()?;
+        let fired = DateTime::parse_from_rfc3339(fired)
+            .ok()?
+            .with_timezone(&Utc);
+        let until = fired + chrono::Duration::minutes(self.cooldown_minutes as i64);
+        (until > now).then_some(until)
+    }
+
+    /// Human-readable condition, for notifications
+    pub fn describe_condition(&self) -> String {
+        let metric = match self.metric {
+            AlertMetric::Count => "requests",
+            AlertMetric::ErrorRate => "error rate %",
+            AlertMetric::UniqueIps => "unique IPs",
+            AlertMetric::IpDailyRequests => "requests/day of one IP",
+            AlertMetric::IpDailyErrors => "errors/day of one IP",
+            AlertMetric::IpDailySecurityEvents => "security events/day of one IP",
+        };
+        if self.metric.daily_field().is_some() {
+            return format!(
+                "{} > {} for {} consecutive day(s)",
+                metric, self.threshold, self.days
+            );
+        }
+        format!(
+            "{} > {} in {} min",
+            metric, self.threshold, self.window_minutes
+        )
+    }
+}
+
+/// Requests from one source IP within the window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpCount {
+    pub ip: String,
+    pub count: u64,
+}
+
+/// Aggregated access logs of one rule window (for daily metrics: `matched`
+/// and `unique_ips` count the IPs above the threshold every day, `top_ips`
+/// holds their lowest daily value)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowStats {
+    pub matched: u64,
+    /// Matching requests with status >= 500
+    pub errors: u64,
+    pub unique_ips: u64,
+    pub top_ips: Vec<IpCount>,
+}
+
+/// What an evaluation decided
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertAction {
+    /// Not breached
+    None,
+    /// Breached: event + notification
+    Fire,
+    /// Breached, but the rule fired within its cooldown
+    Suppressed,
+}
+
+/// Result of evaluating a rule against its window
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvaluation {
+    pub rule_id: String,
+    pub window_from: String,
+    pub window_to: String,
+    pub stats: WindowStats,
+    pub value: f64,
+    pub threshold: f64,
+    pub breached: bool,
+    pub action: AlertAction,
+    pub cooldown_until: Option<String>,
+}
+
+pub fn decide(rule: &AlertRule, stats: WindowStats, now: DateTime<Utc>) -> AlertEvaluation {
+    let (from, to) = rule.window(now);
+    let breached = rule.is_breached(&stats);
+    let cooldown_until = rule.cooldown_until(now);
+    let action = match (breached, cooldown_until) {
+        (false, _) => AlertAction::None,
+        (true, Some(_)) => AlertAction::Suppressed,
+        (true, None) => AlertAction::Fire,
+    };
+    AlertEvaluation {
+        rule_id: rule.rule_id.clone(),
+        window_from: from.to_rfc3339(),
+        window_to: to.to_rfc3339(),
+        value: rule.value(&stats),
+        threshold: rule.threshold,
+        breached,
+        action,
+        cooldown_until: cooldown_until.map(|t| t.to_rfc3339()),
+        stats,
+    }
+}
+
+/// Aggregate the rule's window and decide, without side effects
+pub async fn evaluate_rule(
+    mongo: &MongoDb,
+    rule: &AlertRule,
+    now: DateTime<Utc>,
+) -> Result<AlertEvaluation, String> {
+    let conditions = compile_filter(&rule.filter).map_err(|e| format!("invalid filter: {}", e))?;
+    let (from, to) = rule.window(now);
+    let stats = match rule.metric.daily_field() {
+        Some(field) => {
+            let days: Vec<String> = from
+                .date_naive()
+                .iter_days()
+                .take_while(|d| *d < to.date_naive())
+                .map(crate::ip_stats::day_key)
+                .collect();
+            let (offenders, top_ips) = mongo
+                .sustained_ip_daily(field, &days, rule.threshold, conditions, TOP_IPS)
+                .await?;
+            WindowStats {
+                matched: offenders,
+                errors: 0,
+                unique_ips: offenders,
+                top_ips,
+            }
+        }
+        None => {
+            mongo
+                .alert_window_stats(conditions, from, to, TOP_IPS)
+                .await?
+        }
+    };
+    Ok(decide(rule, stats, now))
+}
+
+/// Background evaluator for alert rules
+pub struct AlertRuleEngine {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+}
+
+impl AlertRuleEngine {
+    pub fn new(app_state: AppState, notifier: Arc<DiscordNotifier>) -> Self {
+        Self {
+            app_state,
+            notifier,
+        }
+    }
+
+    /// Start the evaluation loop (every 60s)
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting alert rule engine...");
+
+        let mut interval_timer = interval(Duration::from_secs(60));
+
+        loop {
+            interval_timer.tick().await;
+
+            let rules = match self.app_state.mongo.list_alert_rules().await {
+                Ok(rules) => rules,
+                Err(e) => {
+                    tracing::error!("Failed to load alert rules: {}", e);
+                    continue;
+                }
+            };
+            for rule in rules.iter().filter(|r| r.enabled) {
+                self.run_rule(rule).await;
+            }
+        }
+    }
+
+    async fn run_rule(&self, rule: &AlertRule) {
+        let now = Utc::now();
+        let mut status = rule.status.clone();
+        status.last_evaluated_at = Some(now.to_rfc3339());
+
+        match evaluate_rule(&self.app_state.mongo, rule, now).await {
+            Ok(evaluation) => {
+                status.last_value = Some(evaluation.value);
+                status.last_matched = Some(evaluation.stats.matched);
+                status.last_error = None;
+                status.state = if evaluation.breached {
+                    AlertState::Firing
+                } else {
+                    AlertState::Ok
+                };
+                if evaluation.action == AlertAction::Fire {
+                    status.last_fired_at = Some(now.to_rfc3339());
+                    status.fire_count += 1;
+                    self.fire(rule, &evaluation).await;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Alert rule '{}' evaluation failed: {}", rule.name, e);
+                status.state = AlertState::Error;
+                status.last_error = Some(e);
+            }
+        }
+
+        if let Err(e) = self
+            .app_state
+            .mongo
+            .set_alert_rule_status(&rule.rule_id, &status)
+            .await
+        {
+            tracing::error!("Failed to store alert rule '{}' status: {}", rule.name, e);
+        }
+    }
+
+    async fn fire(&self, rule: &AlertRule, evaluation: &AlertEvaluation) {
+        tracing::warn!(
+            "Alert rule '{}' fired: {} ({})",
+            rule.name,
+            evaluation.value,
+            rule.describe_condition()
+        );
+        if let Err(e) = self.app_state.mongo.log_alert_rule(rule, evaluation).await {
+            tracing::error!("Failed to log alert rule event: {}", e);
+        }
+        self.notifier.notify_alert_rule(rule, evaluation).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(metric: AlertMetric, threshold: f64) -> AlertRule {
+        AlertRule::new(CreateAlertRuleRequest {
+            name: "test".to_string(),
+            enabled: true,
+            filter: "route_id == 12".to_string(),
+            metric,
+            window_minutes: 15,
+            days: 1,
+            threshold,
+            min_requests: 10,
+            severity: Severity::High,
+            notify: "none".to_string(),
+            cooldown_minutes: 30,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn compiles_filter_expressions() {
+        assert_eq!(
+            compile_filter(r#"status == 401 and path ~ /api/auth/*"#).unwrap(),
+            vec![
+                doc! { "status": 401_i64 },
+                doc! { "path": { "$regex": "^/api/auth/.*$" } },
+            ]
+        );
+        assert_eq!(
+            compile_filter("path ~ /admin* AND country_code != jp").unwrap(),
+            vec![
+                doc! { "path": { "$regex": "^/admin.*$" } },
+                doc! { "country_code": { "$ne": "JP" } },
+            ]
+        );
+        assert_eq!(
+            compile_filter(
+                r#"method not in (post, put) and status>=500 and user_agent !~ "*Uptime Kuma*""#
+            )
+            .unwrap(),
+            vec![
+                doc! { "method": { "$nin": ["POST", "PUT"] } },
+                doc! { "status": { "$gte": 500_i64 } },
+                doc! { "user_agent": { "$not": { "$regex": "^.*Uptime Kuma.*$" } } },
+            ]
+        );
+        assert!(compile_filter("").unwrap().is_empty());
+
+        for bad in [
+            "host == example.com",
+            "status ~ 4*",
+            "path > /a",
+            "status == abc",
+            "status == 401 path ~ /x",
+            "status in (401, 403",
+            "path == \"/open",
+            "status",
+        ] {
+            assert!(compile_filter(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn breach_respects_min_requests_and_cooldown() {
+        let stats = |matched, errors| WindowStats {
+            matched,
+            errors,
+            ..Default::default()
+        };
+        let mut error_rate = rule(AlertMetric::ErrorRate, 10.0);
+        let now = Utc::now();
+
+        // 1 of 5 failed: 20%, but below min_requests
+        assert_eq!(
+            decide(&error_rate, stats(5, 1), now).action,
+            AlertAction::None
+        );
+        assert_eq!(
+            decide(&error_rate, stats(20, 2), now).action,
+            AlertAction::None
+        );
+        assert_eq!(
+            decide(&error_rate, stats(20, 3), now).action,
+            AlertAction::Fire
+        );
+
+        error_rate.status.last_fired_at = Some((now - chrono::Duration::minutes(10)).to_rfc3339());
+        let suppressed = decide(&error_rate, stats(20, 3), now);
+        assert_eq!(suppressed.action, AlertAction::Suppressed);
+        assert!(suppressed.cooldown_until.is_some());
+
+        error_rate.status.last_fired_at = Some((now - chrono::Duration::minutes(31)).to_rfc3339());
+        assert_eq!(
+            decide(&error_rate, stats(20, 3), now).action,
+            AlertAction::Fire
+        );
+
+        assert!(AlertRule::new(CreateAlertRuleRequest {
+            notify: "http://example.com/hook".to_string(),
+            ..serde_json::from_value(serde_json::json!({"name": "x", "threshold": 1})).unwrap()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn daily_metrics_use_completed_days_of_the_worst_ip() {
+        let mut sustained = AlertRule::new(
+            serde_json::from_value(serde_json::json!({
+                "name": "heavy hitters",
+                "metric": "ip_daily_requests",
+                "days": 3,
+                "threshold": 10000,
+                "min_requests": 10,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let now = DateTime::parse_from_rfc3339("2026-03-04T15:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let (from, to) = sustained.window(now);
+        assert_eq!(from.to_rfc3339(), "2026-03-01T00:00:00+00:00");
+        assert_eq!(to.to_rfc3339(), "2026-03-04T00:00:00+00:00");
+
+        // min_requests does not apply: one offender is enough
+        let offenders = WindowStats {
+            matched: 1,
+            unique_ips: 1,
+            top_ips: vec![IpCount {
+                ip: "203.0.113.9".to_string(),
+                count: 12_000,
+            }],
+            ..Default::default()
+        };
+        let evaluation = decide(&sustained, offenders, now);
+        assert_eq!(evaluation.value, 12_000.0);
+        assert_eq!(evaluation.action, AlertAction::Fire);
+        assert_eq!(
+            decide(&sustained, WindowStats::default(), now).action,
+            AlertAction::None
+        );
+
+        sustained.filter = "status == 500".to_string();
+        assert!(sustained.validate().is_err());
+        sustained.filter = "ip !~ 10.*".to_string();
+        sustained.days = MAX_DAILY_DAYS + 1;
+        assert!(sustained.validate().is_err());
+    }
+}
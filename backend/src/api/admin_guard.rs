@@ -1,7 +1,10 @@
 //! Internet access guard - controls API access based on network origin and settings
 //!
-//! Allows: Private networks (192.168.0.0/16, 10.0.0.0/8, 172.16.0.0/12, 127.0.0.0/8, ::1) always pass
-//! Public networks: Only allowed when internet_access_enabled setting is true
+//! Decided by the compiled network policy (`crate::network_policy`):
+//! Blocked IPs: refused
+//! LAN (private ranges, 127.0.0.0/8, ::1, network_lan_extra) and network_admin_allowlist: pass
+//! Other public networks: Only allowed when internet_access_enabled setting is true
+//! The source is the socket peer; forwarding headers count only from trusted proxies
 //! Authentication is handled separately by auth_middleware
 
 use axum::{
@@ -12,8 +15,10 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use chrono::Utc;
+use std::net::SocketAddr;
 
+use crate::network_policy::{Decision, NetworkPolicy, Reason, Surface, Verdict};
 use crate::proxy::ProxyState;
 
 /// Middleware that controls access based on network origin.
@@ -25,76 +30,84 @@ pub async fn internet_access_guard(
     req: Request<Body>,
     next: Next,
 ) -> Response {
-    let client_ip_str = extract_client_ip(req.headers(), addr);
-
-    if is_private_network(&client_ip_str) {
-        return next.run(req).await;
-    }
-
-    // Check internet_access_enabled setting (DB > config fallback)
-    let enabled = state
-        .app_state
-        .mysql
-        .get_setting_bool("internet_access_enabled")
-        .await
-        .unwrap_or(state.auth_config.internet_access_enabled);
-
-    if enabled {
-        next.run(req).await
-    } else {
-        tracing::warn!(
-            "Internet access denied for external IP: {} (path: {})",
-            client_ip_str,
-            req.uri().path()
-        );
-        (
-            StatusCode::FORBIDDEN,
-            Json(serde_json::json!({
-                "error": "Internet access is disabled",
-                "status": 403
-            })),
-        )
-            .into_response()
-    }
-}
-
-/// Check if an IP address belongs to a private/local network (RFC 1918 + loopback)
-pub fn is_private_network(ip_str: &str) -> bool {
-    match ip_str.parse::<IpAddr>() {
-        Ok(IpAddr::V4(ipv4)) => {
-            ipv4.is_private()       // 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16
-                || ipv4.is_loopback()   // 127.0.0.0/8
-                || ipv4 == Ipv4Addr::UNSPECIFIED // 0.0.0.0
+    let (client_ip_str, decision) =
+        admin_access(&state.network_policy.load_policy(), req.headers(), addr);
+
+    match decision.verdict {
+        Verdict::Allow => next.run(req).await,
+        Verdict::Flag => {
+            tracing::debug!(
+                "API access from public IP {} ({}): {}",
+                client_ip_str,
+                decision.reason.as_str(),
+                req.uri().path()
+            );
+            next.run(req).await
         }
-        Ok(IpAddr::V6(ipv6)) => {
-            ipv6.is_loopback() // ::1
+        Verdict::Deny => {
+            tracing::warn!(
+                "API access denied for {} ({}, path: {})",
+                client_ip_str,
+                decision.reason.as_str(),
+                req.uri().path()
+            );
+            denied_response(decision.reason)
         }
-        Err(_) => false,
     }
 }
 
-/// Extract client IP from headers or connection (same logic as proxy handler)
-pub fn extract_client_ip(headers: &HeaderMap, addr: SocketAddr) -> String {
-    if let Some(xff) = headers.get("x-forwarded-for") {
-        if let Ok(s) = xff.to_str() {
-            if let Some(ip) = s.split(',').next() {
-                return ip.trim().to_string();
-            }
-        }
-    }
+/// Client address of a request (from the socket peer, see
+/// `NetworkPolicy::client_ip`) and its admin surface decision
+fn admin_access(
+    policy: &NetworkPolicy,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> (String, Decision) {
+    let client_ip = policy.client_ip(headers, peer);
+    let decision = policy.evaluate_str(&client_ip, Surface::Admin, Utc::now());
+    (client_ip, decision)
+}
 
-    if let Some(xri) = headers.get("x-real-ip") {
-        if let Ok(s) = xri.to_str() {
-            return s.to_string();
-        }
-    }
+fn denied_response(reason: Reason) -> Response {
+    let error = match reason {
+        Reason::Blocked => "Access denied",
+        _ => "Internet access is disabled",
+    };
+    (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({
+            "error": error,
+            "status": 403
+        })),
+    )
+        .into_response()
+}
+
+/// Whether a client may reach admin-only surfaces: not blocked, and LAN,
+/// allowlisted, or public while internet_access_enabled is set.
+///
+/// Shared by this middleware and `admin_network_only` proxy routes.
+pub fn is_admin_network_allowed(state: &ProxyState, client_ip: &str) -> bool {
+    !state
+        .network_policy
+        .load_policy()
+        .evaluate_str(client_ip, Surface::Admin, Utc::now())
+        .is_denied()
+}
 
-    addr.ip().to_string()
+/// Check if an IP address belongs to a private/local network (RFC 1918 + loopback)
+#[cfg(test)]
+pub fn is_private_network(ip_str: &str) -> bool {
+    ip_str
+        .parse::<std::net::IpAddr>()
+        .map(crate::network_policy::is_private)
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::network_policy::PolicySettings;
 
     #[test]
     fn test_private_networks_allowed() {
@@ -132,4 +145,26 @@ mod tests {
         assert!(!is_private_network("not-an-ip"));
         assert!(!is_private_network(""));
     }
+
+    #[test]
+    fn test_spoofed_forwarded_for_from_public_peer_denied() {
+        let policy = NetworkPolicy::compile(&[], &PolicySettings::default(), false);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "192.168.1.1".parse().unwrap());
+        let peer: SocketAddr = "203.0.113.50:51000".parse().unwrap();
+
+        let (client_ip, decision) = admin_access(&policy, &headers, peer);
+        assert_eq!(client_ip, "203.0.113.50");
+        assert!(decision.is_denied());
+        assert_eq!(
+            denied_response(decision.reason).status(),
+            StatusCode::FORBIDDEN
+        );
+
+        // The same header through the local nginx is honoured
+        let local: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        let (client_ip, decision) = admin_access(&policy, &headers, local);
+        assert_eq!(client_ip, "192.168.1.1");
+        assert!(!decision.is_denied());
+    }
 }
@@ -0,0 +1,364 @@
+//! Admin API request recorder
+//!
+//! Off unless an admin turns it on with `PUT /api/admin/api-trace/recorder`,
+//! and then only until a deadline (setting `api_trace_until`, at most
+//! `MAX_DURATION_MINUTES` ahead), so it cannot be left on by accident. While
+//! on, the middleware on the protected `/api` routes stores method, path,
+//! query, request body, status, latency and actor of every request in the
+//! capped collection `api_trace` (bounded by size; the oldest entries go
+//! first). GET requests also keep their response body, which the replay
+//! endpoint compares with a fresh response. Credentials in queries and JSON
+//! bodies are masked (`crate::masking`) before anything is stored.
+//!
+//! While recording, every traced response carries `X-LPG-API-Trace` with the
+//! deadline. Each instance re-reads the setting every `RELOAD_INTERVAL`.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, HeaderValue, Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use crate::db::mysql::MySqlDb;
+use crate::error::AppError;
+use crate::masking::{mask_credentials, mask_query, MASK};
+use crate::models::AuthUser;
+use crate::proxy::store_forward::stamp;
+use crate::proxy::ProxyState;
+
+/// Deadline of the recorder (RFC 3339; empty = off)
+pub const SETTING_API_TRACE_UNTIL: &str = "api_trace_until";
+/// Admin who last turned the recorder on
+pub const SETTING_API_TRACE_ENABLED_BY: &str = "api_trace_enabled_by";
+
+/// Set on traced responses while recording
+pub const TRACE_HEADER: &str = "x-lpg-api-trace";
+/// Marks replayed requests, which are not recorded again
+pub const REPLAY_HEADER: &str = "x-lpg-api-trace-replay";
+
+pub const DEFAULT_DURATION_MINUTES: i64 = 60;
+pub const MAX_DURATION_MINUTES: i64 = 24 * 60;
+
+/// Request bodies above this are not recorded
+const MAX_REQUEST_BODY: u64 = 64 * 1024;
+/// GET response bodies above this are not recorded
+pub const MAX_RESPONSE_BODY: u64 = 256 * 1024;
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One recorded request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiTraceEntry {
+    pub trace_id: String,
+    pub at: String,
+    pub method: String,
+    pub path: String,
+    /// Query string, credentials masked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Request body (JSON with credentials masked, or form/text)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    pub status: i32,
+    pub latency_ms: i64,
+    pub actor: String,
+    pub actor_permission: i32,
+    pub client_ip: String,
+    /// Response body of GET requests, credentials masked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    /// Why a body was left out (too large, size unknown, binary)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// Whether the recorder is on, and until when
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecorderState {
+    pub enabled: bool,
+    pub until: Option<DateTime<Utc>>,
+    pub enabled_by: Option<String>,
+}
+
+/// Per-instance copy of the recorder settings
+#[derive(Default)]
+pub struct ApiTraceRecorder {
+    setting: RwLock<(Option<DateTime<Utc>>, Option<String>)>,
+}
+
+impl ApiTraceRecorder {
+    pub fn state(&self, now: DateTime<Utc>) -> RecorderState {
+        let (until, enabled_by) = self.setting.read().unwrap().clone();
+        RecorderState {
+            enabled: until.is_some_and(|u| u > now),
+            until,
+            enabled_by,
+        }
+    }
+
+    pub fn set(&self, until: Option<DateTime<Utc>>, enabled_by: Option<String>) {
+        *self.setting.write().unwrap() = (until, enabled_by);
+    }
+
+    /// Read the settings into this instance
+    pub async fn reload(&self, mysql: &MySqlDb) -> Result<(), AppError> {
+        let until = mysql
+            .get_setting(SETTING_API_TRACE_UNTIL)
+            .await?
+            .and_then(|v| DateTime::parse_from_rfc3339(v.trim()).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let enabled_by = mysql
+            .get_setting(SETTING_API_TRACE_ENABLED_BY)
+            .await?
+            .filter(|v| !v.is_empty());
+        self.set(until, enabled_by);
+        Ok(())
+    }
+}
+
+/// Persist the recorder state and apply it on this instance
+pub async fn save(
+    recorder: &ApiTraceRecorder,
+    mysql: &MySqlDb,
+    until: Option<DateTime<Utc>>,
+    enabled_by: Option<&str>,
+) -> Result<(), AppError> {
+    let until_value = until.map(stamp).unwrap_or_default();
+    mysql
+        .upsert_setting(
+            SETTING_API_TRACE_UNTIL,
+            Some(&until_value),
+            Some("Admin API request recorder deadline (empty = off)"),
+        )
+        .await?;
+    mysql
+        .upsert_setting(
+            SETTING_API_TRACE_ENABLED_BY,
+            Some(enabled_by.unwrap_or_default()),
+            Some("Admin who turned the API request recorder on"),
+        )
+        .await?;
+    recorder.set(until, enabled_by.map(|s| s.to_string()));
+    Ok(())
+}
+
+/// Re-read the recorder settings every `RELOAD_INTERVAL` so turning it on
+/// or off on another instance applies here (per instance)
+pub async fn reload_loop(recorder: Arc<ApiTraceRecorder>, mysql: Arc<MySqlDb>) {
+    let mut tick = interval(RELOAD_INTERVAL);
+    loop {
+        tick.tick().await;
+        if let Err(e) = recorder.reload(&mysql).await {
+            tracing::warn!("Failed to reload API trace recorder state: {}", e);
+        }
+    }
+}
+
+/// Stored form of a body: masked JSON, form data with masked fields or
+/// UTF-8 text; Err explains why it was left out
+pub fn sanitize_body(bytes: &[u8], headers: &HeaderMap) -> Result<Option<String>, String> {
+    if bytes.is_empty() {
+        return Ok(None);
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(bytes) {
+        mask_credentials(&mut value);
+        return Ok(Some(value.to_string()));
+    }
+    let text =
+        std::str::from_utf8(bytes).map_err(|_| format!("binary body ({} bytes)", bytes.len()))?;
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        return Ok(Some(mask_query(text)));
+    }
+    if content_type.starts_with("text/") {
+        return Ok(Some(text.to_string()));
+    }
+    Err(format!("{} body ({} bytes)", content_type, bytes.len()))
+}
+
+/// Buffer a body whose exact size is known and at most `limit`; otherwise
+/// the body is passed on untouched with the reason it was not captured
+async fn capture(body: Body, limit: u64) -> (Body, Result<Bytes, String>) {
+    match body.size_hint().exact() {
+        Some(0) => (body, Ok(Bytes::new())),
+        Some(size) if size <= limit => match axum::body::to_bytes(body, limit as usize).await {
+            Ok(bytes) => (Body::from(bytes.clone()), Ok(bytes)),
+            Err(e) => (Body::empty(), Err(format!("body unreadable: {}", e))),
+        },
+        Some(size) => (body, Err(format!("body too large ({} bytes)", size))),
+        None => (body, Err("body of unknown size (streamed)".to_string())),
+    }
+}
+
+fn mark(response: &mut Response, until: DateTime<Utc>) {
+    if let Ok(value) = HeaderValue::from_str(&format!("recording; until={}", stamp(until))) {
+        response.headers_mut().insert(TRACE_HEADER, value);
+    }
+}
+
+/// Middleware recording protected `/api` requests while the recorder is on
+/// (runs after `require_auth`, so the actor is known)
+pub async fn record(
+    State(state): State<ProxyState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let recorder = state.api_trace.state(Utc::now());
+    let Some(until) = recorder.until.filter(|_| recorder.enabled) else {
+        return next.run(req).await;
+    };
+    if req.headers().contains_key(REPLAY_HEADER) {
+        let mut response = next.run(req).await;
+        mark(&mut response, until);
+        return response;
+    }
+
+    let started = Instant::now();
+    let at = stamp(Utc::now());
+    let (parts, body) = req.into_parts();
+    let method = parts.method.clone();
+    let path = parts.uri.path().to_string();
+    let query = parts.uri.query().map(mask_query);
+    let (actor, actor_permission) = parts
+        .extensions
+        .get::<AuthUser>()
+        .map_or((String::new(), 0), |u| (u.sub.clone(), u.permission));
+    let client_ip = state
+        .network_policy
+        .load_policy()
+        .client_ip(&parts.headers, addr);
+    let mut notes = Vec::new();
+
+    let (body, captured) = capture(body, MAX_REQUEST_BODY).await;
+    let request_body = match captured.and_then(|b| sanitize_body(&b, &parts.headers)) {
+        Ok(body) => body,
+        Err(note) => {
+            notes.push(format!("request {}", note));
+            None
+        }
+    };
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    let latency_ms = started.elapsed().as_millis() as i64;
+    let status = response.status().as_u16() as i32;
+
+    let (response_parts, body) = response.into_parts();
+    let (body, response_body) = if method == Method::GET {
+        let (body, captured) = capture(body, MAX_RESPONSE_BODY).await;
+        let stored = match captured.and_then(|b| sanitize_body(&b, &response_parts.headers)) {
+            Ok(body) => body,
+            Err(note) => {
+                notes.push(format!("response {}", note));
+                None
+            }
+        };
+        (body, stored)
+    } else {
+        (body, None)
+    };
+    let mut response = Response::from_parts(response_parts, body);
+    mark(&mut response, until);
+
+    let entry = ApiTraceEntry {
+        trace_id: uuid::Uuid::new_v4().to_string(),
+        at,
+        method: method.to_string(),
+        path,
+        query,
+        request_body,
+        status,
+        latency_ms,
+        actor,
+        actor_permission,
+        client_ip,
+        response_body,
+        notes,
+    };
+    let mongo = state.app_state.mongo.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mongo.insert_api_trace(&entry).await {
+            tracing::warn!("Failed to record API trace: {}", e);
+        }
+    });
+
+    response
+}
+
+/// Whether a recorded query lost values to masking (such a request cannot
+/// be replayed as it was sent)
+pub fn query_was_masked(query: Option<&str>) -> bool {
+    query.is_some_and(|q| {
+        q.split('&')
+            .any(|pair| pair.ends_with(&format!("={}", MASK)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_headers(content_type: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn bodies_are_masked_before_storing() {
+        let json = json_headers("application/json");
+        let stored = sanitize_body(br#"{"name":"r1","password":"hunter2"}"#, &json)
+            .unwrap()
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(value["password"], MASK);
+        assert_eq!(value["name"], "r1");
+
+        let form = json_headers("application/x-www-form-urlencoded");
+        assert_eq!(
+            sanitize_body(b"user=a&token=b", &form).unwrap().unwrap(),
+            format!("user=a&token={}", MASK)
+        );
+
+        assert_eq!(sanitize_body(b"", &json).unwrap(), None);
+        let binary = json_headers("application/octet-stream");
+        assert!(sanitize_body(&[0xff, 0xfe, 0x00], &binary).is_err());
+        assert!(sanitize_body(b"plain", &binary).is_err());
+    }
+
+    #[test]
+    fn recorder_turns_itself_off_at_the_deadline() {
+        let now = Utc::now();
+        let recorder = ApiTraceRecorder::default();
+        assert!(!recorder.state(now).enabled);
+
+        recorder.set(
+            Some(now + chrono::Duration::minutes(5)),
+            Some("admin".to_string()),
+        );
+        assert!(recorder.state(now).enabled);
+        let expired = recorder.state(now + chrono::Duration::minutes(6));
+        assert!(!expired.enabled);
+        assert_eq!(expired.enabled_by.as_deref(), Some("admin"));
+    }
+
+    #[test]
+    fn masked_queries_are_detected() {
+        assert!(query_was_masked(Some(&mask_query("a=1&token=x"))));
+        assert!(!query_was_masked(Some("a=1")));
+        assert!(!query_was_masked(None));
+    }
+}
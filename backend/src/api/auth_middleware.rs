@@ -6,11 +6,16 @@
 //!
 //! Bearer token takes priority over cookie when both are present.
 //! Both use the same JWT format (SessionClaims) and verification logic.
+//!
+//! Permission floors are editable at runtime. Each session carries the floors
+//! in effect when it was issued, and every request is checked against the
+//! stricter of those and the live values: raising a floor applies on the next
+//! request, lowering one only benefits sessions issued after the change.
 
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{HeaderMap, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
@@ -18,7 +23,7 @@ use axum::{
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 
 use crate::error::AppError;
-use crate::models::{AuthUser, SessionClaims};
+use crate::models::{AuthUser, PermissionFloors, SessionClaims};
 use crate::proxy::ProxyState;
 
 /// Middleware that requires a valid session (Bearer token or Cookie).
@@ -29,33 +34,67 @@ pub async fn require_auth(
     mut req: Request<Body>,
     next: Next,
 ) -> Response {
+    match authenticate(&state, req.headers()).await {
+        Ok(user) => {
+            req.extensions_mut().insert(user);
+            next.run(req).await
+        }
+        Err(response) => response,
+    }
+}
+
+/// Resolve the session of a request, as `require_auth` does, for handlers
+/// outside the protected group that accept sessions alongside another
+/// credential. Err is the 401/403 response to send.
+pub async fn authenticate(state: &ProxyState, headers: &HeaderMap) -> Result<AuthUser, Response> {
     // Bearer token takes priority (AI agent / CLI), then fall back to cookie (browser)
-    let token =
-        extract_bearer_token(req.headers()).or_else(|| extract_session_cookie(req.headers()));
-
-    match token {
-        Some(t) => match decode_session(&t, &state.auth_config.jwt_secret) {
-            Ok(claims) => {
-                req.extensions_mut().insert(AuthUser::from(claims));
-                next.run(req).await
-            }
-            Err(e) => {
-                tracing::debug!("Invalid session token: {}", e);
-                unauthorized_response()
-            }
-        },
-        None => unauthorized_response(),
+    let token = extract_bearer_token(headers)
+        .or_else(|| extract_session_cookie(headers))
+        .ok_or_else(unauthorized_response)?;
+
+    let claims = {
+        let keys = state.jwt_keys.read().await;
+        decode_with_any(&token, &keys.verification_secrets(chrono::Utc::now()))
     }
+    .map_err(|e| {
+        tracing::debug!("Invalid session token: {}", e);
+        unauthorized_response()
+    })?;
+
+    let live = *state.permission_floors.read().await;
+    let floors = effective_floors(&live, claims.floors.as_ref());
+    let mut user = AuthUser::from(claims);
+    user.floors = floors;
+
+    if user.permission < floors.login {
+        tracing::debug!(
+            "Session for {} below login floor: {} < {}",
+            user.sub,
+            user.permission,
+            floors.login
+        );
+        return Err(AppError::Forbidden(format!(
+            "Insufficient permission: {} (required: {})",
+            user.permission, floors.login
+        ))
+        .into_response());
+    }
+
+    Ok(user)
 }
 
 /// Check that the authenticated user has sufficient permission level.
 ///
-/// Permission hierarchy:
+/// Permission hierarchy (default floors, see `PermissionFloors`):
 ///   - read   (>= 0):   GET endpoints, dashboard, stats, logs
 ///   - operate (>= 50):  sync triggers, diagnostics, DDNS update
 ///   - admin  (>= 80):  route/DDNS create/update, settings, nginx ops
 ///   - dangerous (== 100): DELETE operations, API key creation
+///
+/// `required` names the group by its default level; the actual floor comes
+/// from the floors resolved for this request.
 pub fn require_permission(user: &AuthUser, required: i32) -> Result<(), AppError> {
+    let required = user.floors.for_level(required);
     if user.permission < required {
         Err(AppError::Forbidden(format!(
             "Insufficient permission: {} (required: {})",
@@ -66,8 +105,23 @@ pub fn require_permission(user: &AuthUser, required: i32) -> Result<(), AppError
     }
 }
 
+/// Combine live floors with the floors captured in the session.
+///
+/// Sessions issued before floors were recorded are held to the historical
+/// group defaults (with the live login floor).
+fn effective_floors(
+    live: &PermissionFloors,
+    issued: Option<&PermissionFloors>,
+) -> PermissionFloors {
+    let issued = issued.copied().unwrap_or(PermissionFloors {
+        login: live.login,
+        ..PermissionFloors::default()
+    });
+    live.stricter(&issued)
+}
+
 /// Extract Bearer token from Authorization header
-fn extract_bearer_token(headers: &axum::http::HeaderMap) -> Option<String> {
+fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
     headers
         .get("authorization")
         .and_then(|v| v.to_str().ok())
@@ -76,7 +130,7 @@ fn extract_bearer_token(headers: &axum::http::HeaderMap) -> Option<String> {
 }
 
 /// Extract lpg_session value from Cookie header
-fn extract_session_cookie(headers: &axum::http::HeaderMap) -> Option<String> {
+fn extract_session_cookie(headers: &HeaderMap) -> Option<String> {
     headers
         .get_all("cookie")
         .iter()
@@ -92,6 +146,22 @@ fn extract_session_cookie(headers: &axum::http::HeaderMap) -> Option<String> {
         })
 }
 
+/// Decode with the first of `secrets` that verifies the token (the signing
+/// secret, then one replaced by a recent rotation)
+fn decode_with_any(
+    token: &str,
+    secrets: &[&str],
+) -> Result<SessionClaims, jsonwebtoken::errors::Error> {
+    let mut last_error = None;
+    for secret in secrets {
+        match decode_session(token, secret) {
+            Ok(claims) => return Ok(claims),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| jsonwebtoken::errors::ErrorKind::InvalidSignature.into()))
+}
+
 /// Decode and validate a session JWT (HS256)
 fn decode_session(token: &str, secret: &str) -> Result<SessionClaims, jsonwebtoken::errors::Error> {
     let mut validation = Validation::new(Algorithm::HS256);
@@ -181,6 +251,8 @@ mod tests {
             lacis_id: None,
             permission: 100,
             auth_method: "local".to_string(),
+            auth_provider: None,
+            floors: PermissionFloors::default(),
         };
         assert!(require_permission(&user, 80).is_ok());
         assert!(require_permission(&user, 100).is_ok());
@@ -193,6 +265,8 @@ mod tests {
             lacis_id: None,
             permission: 50,
             auth_method: "lacisoath".to_string(),
+            auth_provider: None,
+            floors: PermissionFloors::default(),
         };
         assert!(require_permission(&user, 80).is_err());
         assert!(require_permission(&user, 100).is_err());
@@ -205,6 +279,8 @@ mod tests {
             lacis_id: None,
             permission: 80,
             auth_method: "lacisoath".to_string(),
+            auth_provider: None,
+            floors: PermissionFloors::default(),
         };
         assert!(require_permission(&user, 80).is_ok());
         assert!(require_permission(&user, 81).is_err());
@@ -220,7 +296,9 @@ mod tests {
             lacis_id: None,
             permission: 100,
             auth_method: "local".to_string(),
+            auth_provider: None,
             exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            floors: None,
         };
 
         let token = encode(
@@ -244,7 +322,9 @@ mod tests {
             lacis_id: None,
             permission: 100,
             auth_method: "local".to_string(),
+            auth_provider: None,
             exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            floors: None,
         };
 
         let token = encode(
@@ -266,7 +346,9 @@ mod tests {
             lacis_id: None,
             permission: 100,
             auth_method: "local".to_string(),
+            auth_provider: None,
             exp: 1000, // expired long ago
+            floors: None,
         };
 
         let token = encode(
@@ -278,4 +360,91 @@ mod tests {
 
         assert!(decode_session(&token, "secret").is_err());
     }
+
+    #[test]
+    fn test_require_permission_uses_resolved_floor() {
+        let user = AuthUser {
+            sub: "test@example.com".to_string(),
+            lacis_id: None,
+            permission: 60,
+            auth_method: "lacisoath".to_string(),
+            auth_provider: None,
+            floors: PermissionFloors {
+                admin: 60,
+                ..PermissionFloors::default()
+            },
+        };
+        assert!(require_permission(&user, 80).is_ok());
+        assert!(require_permission(&user, 100).is_err());
+        // Literal levels outside the groups are unaffected
+        assert!(require_permission(&user, 61).is_err());
+    }
+
+    #[test]
+    fn test_effective_floors_raise_applies_immediately() {
+        let issued = PermissionFloors::default();
+        let live = PermissionFloors {
+            operate: 70,
+            login: 90,
+            ..PermissionFloors::default()
+        };
+        let floors = effective_floors(&live, Some(&issued));
+        assert_eq!(floors.operate, 70);
+        assert_eq!(floors.login, 90);
+    }
+
+    #[test]
+    fn test_effective_floors_lower_not_retroactive() {
+        // Session issued while admin floor was 80; floor later lowered to 60
+        let issued = PermissionFloors::default();
+        let live = PermissionFloors {
+            admin: 60,
+            login: 50,
+            ..PermissionFloors::default()
+        };
+        let floors = effective_floors(&live, Some(&issued));
+        assert_eq!(floors.admin, 80);
+        assert_eq!(floors.login, 80);
+
+        // A session issued after the change gets the lower floor
+        let floors = effective_floors(&live, Some(&live));
+        assert_eq!(floors.admin, 60);
+        assert_eq!(floors.login, 50);
+    }
+
+    #[test]
+    fn test_effective_floors_legacy_session() {
+        let live = PermissionFloors {
+            admin: 60,
+            login: 50,
+            ..PermissionFloors::default()
+        };
+        let floors = effective_floors(&live, None);
+        assert_eq!(floors.admin, 80);
+        assert_eq!(floors.login, 50);
+    }
+
+    #[test]
+    fn test_decode_with_any_accepts_replaced_secret() {
+        let claims = SessionClaims {
+            sub: "admin@example.com".to_string(),
+            lacis_id: None,
+            permission: 100,
+            auth_method: "local".to_string(),
+            auth_provider: None,
+            exp: (chrono::Utc::now().timestamp() + 3600) as usize,
+            floors: None,
+        };
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"old-secret"),
+        )
+        .unwrap();
+
+        let decoded = decode_with_any(&token, &["new-secret", "old-secret"]).unwrap();
+        assert_eq!(decoded.sub, "admin@example.com");
+        assert!(decode_with_any(&token, &["new-secret"]).is_err());
+        assert!(decode_with_any(&token, &[]).is_err());
+    }
 }
@@ -10,10 +10,12 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::error::AppError;
-use crate::models::AuthUser;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{AuthUser, DdnsConfig};
 use crate::proxy::ProxyState;
 
+use super::settings::mask_settings;
+
 /// Query parameter to select which sections to include
 #[derive(Debug, Deserialize)]
 pub struct AgentContextQuery {
@@ -30,6 +32,14 @@ pub struct EndpointInfo {
     pub description: String,
 }
 
+/// One entry of the error code table
+#[derive(Debug, Serialize)]
+pub struct ErrorCodeInfo {
+    pub code: ErrorCode,
+    pub status: u16,
+    pub description: String,
+}
+
 /// System-level context (always included)
 #[derive(Debug, Serialize)]
 pub struct SystemContext {
@@ -38,6 +48,7 @@ pub struct SystemContext {
     pub server_health: String,
     pub auth_user: AuthUser,
     pub available_endpoints: Vec<EndpointInfo>,
+    pub error_codes: Vec<ErrorCodeInfo>,
 }
 
 /// Full agent context response
@@ -77,7 +88,7 @@ pub async fn get_agent_context(
     // System context (always included)
     let uptime_seconds = state.app_state.start_time.elapsed().as_secs();
 
-    let available_endpoints = build_endpoint_list(user.permission);
+    let available_endpoints = build_endpoint_list(&user);
 
     let system = SystemContext {
         version: env!("CARGO_PKG_VERSION").to_string(),
@@ -85,6 +96,14 @@ pub async fn get_agent_context(
         server_health: "ok".to_string(),
         auth_user: user,
         available_endpoints,
+        error_codes: ErrorCode::ALL
+            .iter()
+            .map(|code| ErrorCodeInfo {
+                code: *code,
+                status: code.status().as_u16(),
+                description: code.description().to_string(),
+            })
+            .collect(),
     };
 
     // Collect requested sections
@@ -102,15 +121,8 @@ pub async fn get_agent_context(
 
     let ddns = if sections.contains(&"ddns".to_string()) {
         let d = state.app_state.mysql.list_ddns().await.unwrap_or_default();
-        // Mask sensitive fields
-        let masked: Vec<_> = d
-            .into_iter()
-            .map(|mut c| {
-                c.password = c.password.as_ref().map(|_| "********".to_string());
-                c.api_token = c.api_token.as_ref().map(|_| "********".to_string());
-                c
-            })
-            .collect();
+        // Masked the same way as GET /api/ddns
+        let masked: Vec<_> = d.into_iter().map(DdnsConfig::masked).collect();
         Some(serde_json::to_value(masked).unwrap_or_default())
     } else {
         None
@@ -217,17 +229,8 @@ pub async fn get_agent_context(
             .list_settings()
             .await
             .unwrap_or_default();
-        // Mask Discord webhook URL
-        let masked: Vec<_> = s
-            .into_iter()
-            .map(|mut setting| {
-                if setting.setting_key == "discord_webhook_url" && setting.setting_value.is_some() {
-                    setting.setting_value = Some("********".to_string());
-                }
-                setting
-            })
-            .collect();
-        Some(serde_json::to_value(masked).unwrap_or_default())
+        // Masked the same way as GET /api/settings
+        Some(serde_json::to_value(mask_settings(s)).unwrap_or_default())
     } else {
         None
     };
@@ -256,23 +259,64 @@ fn ep(method: &str, path: &str, perm: i32, desc: &str) -> EndpointInfo {
 
 /// Build list of available endpoints filtered by user permission.
 /// This list MUST stay in sync with api/mod.rs route definitions (SSoT check).
-fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
+fn build_endpoint_list(user: &AuthUser) -> Vec<EndpointInfo> {
     let all_endpoints = vec![
         // ======== Read (>= 0) — GET endpoints, no mutation ========
         // Auth
         ep("GET", "/api/auth/me", 0, "Current user info"),
         // Routes
-        ep("GET", "/api/routes", 0, "List proxy routes"),
+        ep(
+            "GET",
+            "/api/routes",
+            0,
+            "List proxy routes (dns_mismatch set when the DDNS hostname diverged)",
+        ),
+        ep(
+            "GET",
+            "/api/routes/test",
+            0,
+            "Dry-run route matching (?path=&host=&method=), reports 404/405 outcomes",
+        ),
         ep("GET", "/api/routes/:id", 0, "Get single route"),
         ep("GET", "/api/routes/status", 0, "All routes health status"),
+        ep(
+            "GET",
+            "/api/routes/security-headers/report",
+            0,
+            "Security headers missing on recent responses per route",
+        ),
         ep(
             "GET",
             "/api/routes/:id/status",
             0,
-            "Single route health status",
+            "Single route health status per target, with upstream pool hits, timeouts and time to first byte",
         ),
         ep("GET", "/api/routes/:id/logs", 0, "Route access logs"),
+        ep(
+            "GET",
+            "/api/routes/:id/traces",
+            0,
+            "Route request trace percentiles and slowest traces",
+        ),
+        ep(
+            "GET",
+            "/api/routes/:id/canary-stats",
+            0,
+            "Canary vs primary requests, 5xx rate and latency percentiles",
+        ),
+        ep(
+            "GET",
+            "/api/routes/:id/versions",
+            0,
+            "Route configuration history with per-version diffs",
+        ),
         ep("GET", "/api/server-routes", 0, "Routes with subnet info"),
+        ep(
+            "GET",
+            "/api/server-routes/analysis",
+            0,
+            "Route target TCP reachability (cached 1 min, ?refresh=true needs 50) and subnet conflicts",
+        ),
         // DDNS
         ep("GET", "/api/ddns", 0, "List DDNS configurations"),
         ep("GET", "/api/ddns/:id", 0, "Get single DDNS config"),
@@ -280,7 +324,7 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             "GET",
             "/api/ddns/integrated",
             0,
-            "DDNS with Omada WAN IP comparison",
+            "DDNS with Omada WAN IP comparison, failover state and DNS dependency check",
         ),
         ep(
             "GET",
@@ -288,6 +332,20 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             0,
             "Port forwarding rules for DDNS",
         ),
+        // Local DNS
+        ep("GET", "/api/dns/overrides", 0, "List local DNS overrides"),
+        ep(
+            "GET",
+            "/api/dns/suggestions",
+            0,
+            "Active DDNS hostnames without a local DNS override",
+        ),
+        ep(
+            "GET",
+            "/api/dns/status",
+            0,
+            "LAN DNS responder health, per-record query counters and recent queries",
+        ),
         // Security
         ep("GET", "/api/security/blocked-ips", 0, "List blocked IPs"),
         ep("GET", "/api/security/events", 0, "List security events"),
@@ -297,12 +355,42 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             0,
             "Security events by IP",
         ),
+        ep(
+            "GET",
+            "/api/security/ip/:ip",
+            0,
+            "IP profile: recent events, topology devices (with claimants) and daily stats (?days=)",
+        ),
         ep(
             "GET",
             "/api/security/events/search",
             0,
             "Advanced security event search",
         ),
+        ep(
+            "GET",
+            "/api/security/alert-rules",
+            0,
+            "List access-log alert rules with evaluation status",
+        ),
+        ep(
+            "GET",
+            "/api/security/alert-rules/:id",
+            0,
+            "Get an alert rule",
+        ),
+        ep(
+            "GET",
+            "/api/security/rate-limits",
+            0,
+            "List per-client-IP rate limit rules with tracked client counts",
+        ),
+        ep(
+            "GET",
+            "/api/security/geo-rules",
+            0,
+            "Get the country allow/deny rules",
+        ),
         // Settings
         ep("GET", "/api/settings", 0, "List all settings"),
         ep("GET", "/api/settings/restart", 0, "Get restart settings"),
@@ -319,15 +407,81 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             "GET",
             "/api/dashboard/access-log/search",
             0,
-            "Advanced access log search",
+            "Advanced access log search (custom_field + custom_value match a custom log field exactly)",
         ),
         ep(
             "GET",
             "/api/dashboard/access-log/export",
             0,
-            "Export access log CSV",
+            "Export access log (CSV or JSON, streamed)",
+        ),
+        ep(
+            "POST",
+            "/api/dashboard/access-log/delete",
+            100,
+            "Bulk delete access logs by filter (confirm, background job)",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/access-log/delete/:job_id",
+            100,
+            "Bulk access log delete progress",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/in-flight",
+            0,
+            "In-flight proxied requests and WebSocket tunnels",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/notification-queue",
+            0,
+            "Outbound notification queue depth, drops and rate limits",
+        ),
+        ep(
+            "DELETE",
+            "/api/dashboard/in-flight/:id",
+            80,
+            "Abort an in-flight request or tunnel",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/websockets",
+            0,
+            "Open WebSocket sessions with byte counters and totals",
+        ),
+        ep(
+            "DELETE",
+            "/api/dashboard/websockets/:id",
+            80,
+            "Force-close a WebSocket session",
         ),
         ep("GET", "/api/dashboard/health", 0, "Health status"),
+        ep(
+            "GET",
+            "/api/maintenance/windows",
+            0,
+            "List maintenance windows with their suppression tallies",
+        ),
+        ep(
+            "GET",
+            "/api/maintenance/windows/:id",
+            0,
+            "Get a maintenance window",
+        ),
+        ep(
+            "GET",
+            "/api/maintenance/active",
+            0,
+            "Maintenance windows active now",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/dependencies",
+            0,
+            "Latest dependency probe results (MySQL, MongoDB, Omada, OpenWrt, Aranea, Discord)",
+        ),
         ep(
             "GET",
             "/api/dashboard/status-distribution",
@@ -343,27 +497,88 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
         ep("GET", "/api/dashboard/top-ips", 0, "Top IP addresses"),
         ep("GET", "/api/dashboard/top-paths", 0, "Top request paths"),
         ep("GET", "/api/dashboard/error-summary", 0, "Error summary"),
+        ep(
+            "GET",
+            "/api/dashboard/protocol-summary",
+            0,
+            "Requests by HTTP version",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/custom-field-summary",
+            0,
+            "Requests/error rate per value of a route's custom log field (route_id, field; top values only)",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/hostname-usage",
+            0,
+            "Monthly requests/bytes/errors/peak hourly rate per DDNS hostname (month=YYYY-MM)",
+        ),
+        ep(
+            "GET",
+            "/api/dashboard/hostname-usage/export",
+            0,
+            "Monthly per-hostname usage as CSV",
+        ),
+        ep(
+            "POST",
+            "/api/dashboard/hostname-usage/rollup",
+            80,
+            "Recompute a month's per-hostname usage after late log ingestion (audit-logged)",
+        ),
         ep(
             "GET",
             "/api/dashboard/ssl-status",
             0,
-            "SSL certificate status",
+            "Certificates of the built-in HTTPS listener ([server.tls] files and ACME-issued): subject, names, expiry and load errors, plus ACME order status per DDNS config",
         ),
         ep(
             "GET",
             "/api/dashboard/server-health",
             0,
-            "Server health metrics",
+            "Server health metrics (?history=true for last hour)",
+        ),
+        // Devices
+        ep(
+            "GET",
+            "/api/devices/search",
+            0,
+            "Search Omada/OpenWrt/external clients and topology nodes by MAC, IP, hostname or label (?q=&limit=&device_class=)",
+        ),
+        ep(
+            "GET",
+            "/api/devices/inventory/export",
+            0,
+            "Export topology nodes as CSV (?device_class=&node_type=&source=&fid=)",
+        ),
+        ep(
+            "GET",
+            "/api/devices/class-rules",
+            0,
+            "Device classification rules in effect (edit via the device_class_rules setting)",
         ),
         // Omada
-        ep("GET", "/api/omada/controllers", 0, "List Omada controllers"),
+        ep("GET", "/api/omada/controllers", 0, "List Omada controllers with sync health (consecutive_failures, offline_since)"),
         ep(
             "GET",
             "/api/omada/controllers/:id",
             0,
             "Get single controller",
         ),
+        ep(
+            "GET",
+            "/api/omada/controllers/:id/sites",
+            0,
+            "List controller sites with facility mapping and device counts",
+        ),
         ep("GET", "/api/omada/devices", 0, "List Omada devices"),
+        ep(
+            "GET",
+            "/api/omada/devices/:mac/ports",
+            0,
+            "Switch port status (link, speed, PoE, clients)",
+        ),
         ep("GET", "/api/omada/clients", 0, "List Omada clients"),
         ep(
             "GET",
@@ -389,13 +604,36 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             "External devices summary",
         ),
         // WireGuard
-        ep("GET", "/api/wireguard/peers", 0, "List WireGuard peers"),
+        ep(
+            "GET",
+            "/api/wireguard/peers",
+            0,
+            "List WireGuard peers (expired flag; ?expiring_within_days=N)",
+        ),
+        ep(
+            "GET",
+            "/api/wireguard/all-peers",
+            0,
+            "WireGuard peers from LPG and Omada merged by public key (conflicts flagged)",
+        ),
         ep(
             "GET",
             "/api/wireguard/interfaces",
             0,
             "List WireGuard interfaces",
         ),
+        ep(
+            "GET",
+            "/api/wireguard/profiles",
+            0,
+            "List WireGuard config profiles",
+        ),
+        ep(
+            "GET",
+            "/api/wireguard/profiles/:id",
+            0,
+            "Get WireGuard config profile",
+        ),
         // Aranea
         ep("GET", "/api/aranea/devices", 0, "List aranea devices"),
         ep(
@@ -414,6 +652,18 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             0,
             "Network topology (CelestialGlobe)",
         ),
+        ep(
+            "GET",
+            "/api/topology/watch",
+            0,
+            "Long-poll topology changes (?since=<revision>&timeout=<sec>)",
+        ),
+        ep(
+            "GET",
+            "/api/topology/nodes/:id/detail",
+            0,
+            "Everything known about a node: source record, state history, events, access logs, children",
+        ),
         // Audit & logs
         ep("GET", "/api/audit", 0, "Audit logs"),
         ep("GET", "/api/logs/operations", 0, "Operation logs"),
@@ -425,7 +675,7 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
         ),
         ep("GET", "/api/my-ip", 0, "Detect client/server IP"),
         // Nginx (read)
-        ep("GET", "/api/nginx/status", 0, "Nginx status"),
+        ep("GET", "/api/nginx/status", 0, "Nginx status, stub_status metrics and config drift (in_sync and route changes since generation)"),
         ep("GET", "/api/nginx/config", 0, "Nginx config content"),
         ep(
             "GET",
@@ -440,8 +690,63 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             0,
             "Agent context (this endpoint)",
         ),
+        // Self-service (>= 0) — acts only on the caller's own claims
+        ep(
+            "POST",
+            "/api/topology/nodes/:id/claim",
+            0,
+            "Claim a topology node as your device (409 if claimed by someone else)",
+        ),
+        ep(
+            "DELETE",
+            "/api/topology/nodes/:id/claim",
+            0,
+            "Release your claim on a topology node",
+        ),
         // ======== Operate (>= 50) — sync triggers, diagnostics, network tools ========
         ep("POST", "/api/tools/sync/omada", 50, "Trigger Omada sync"),
+        ep(
+            "PUT",
+            "/api/topology/positions",
+            50,
+            "Store node position overrides ({base_generation, positions}; 409 LAYOUT_GENERATION_CONFLICT when stale)",
+        ),
+        ep(
+            "POST",
+            "/api/topology/layout/recalc",
+            50,
+            "Drop unpinned position overrides ({base_generation, scope: all|subtree, root_id}; 409 when stale)",
+        ),
+        ep(
+            "POST",
+            "/api/security/alert-rules/:id/test",
+            50,
+            "Evaluate an alert rule against its last window without firing",
+        ),
+        ep(
+            "PUT",
+            "/api/routes/:id/trace",
+            50,
+            "Enable/disable route request tracing (auto-expiring)",
+        ),
+        ep(
+            "POST",
+            "/api/routes/:id/canary/kill",
+            50,
+            "Canary kill switch: all traffic back to the primary target",
+        ),
+        ep(
+            "POST",
+            "/api/routes/:id/maintenance",
+            50,
+            "Maintenance mode on/off (omit enabled to toggle): 503 page with the message, health checks paused",
+        ),
+        ep(
+            "POST",
+            "/api/routes/:id/cache/purge",
+            50,
+            "Drop a route's cached GET responses (force-refresh after a deployment)",
+        ),
         ep(
             "POST",
             "/api/tools/sync/openwrt",
@@ -460,6 +765,12 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             50,
             "Trigger all DDNS updates",
         ),
+        ep(
+            "POST",
+            "/api/tools/logs/prune",
+            80,
+            "Prune logs past their retention",
+        ),
         ep(
             "POST",
             "/api/tools/network/ping",
@@ -533,84 +844,423 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
         ),
         // ======== Admin (>= 80) — CRUD create/update, config changes ========
         ep("POST", "/api/routes", 80, "Create proxy route"),
-        ep("PUT", "/api/routes/:id", 80, "Update proxy route"),
-        ep("POST", "/api/ddns", 80, "Create DDNS configuration"),
-        ep("PUT", "/api/ddns/:id", 80, "Update DDNS configuration"),
         ep(
             "PUT",
-            "/api/ddns/:id/link-omada",
+            "/api/routes/sync",
             80,
-            "Link DDNS to Omada controller",
+            "Converge routes on a declarative set (?dry_run, ?strict; ?prune needs 100)",
         ),
         ep(
-            "POST",
-            "/api/security/blocked-ips",
+            "GET",
+            "/api/routes/:id/queue",
             80,
-            "Block an IP address",
+            "Store-and-forward queue of a route (pending/failed/delivered, bodies omitted)",
         ),
-        ep("PUT", "/api/settings/:key", 80, "Update setting"),
         ep(
-            "PUT",
-            "/api/settings/restart",
+            "GET",
+            "/api/routes/:id/transform",
             80,
-            "Update restart settings",
+            "Route transformation script and its execution metrics (runs, failures, timeouts, timings)",
         ),
         ep(
-            "POST",
-            "/api/settings/test-discord",
-            80,
-            "Test Discord notification",
+            "GET",
+            "/api/routes/:id/log-fields",
+            0,
+            "Custom access log field rules of a route (header / path regex group / query parameter)",
         ),
         ep(
             "POST",
-            "/api/omada/controllers",
+            "/api/routes/:id/queue/:queue_id/replay",
             80,
-            "Register Omada controller",
+            "Replay a queued request now",
         ),
         ep(
-            "POST",
-            "/api/openwrt/routers",
+            "PUT",
+            "/api/routes/:id",
             80,
-            "Register OpenWrt router",
+            "Update proxy route; activation probes the target (409 on failure, ?warm=true activates with 503 until healthy)",
         ),
         ep(
             "POST",
-            "/api/external/devices",
+            "/api/routes/:id/rollback/:version",
             80,
-            "Register external device",
+            "Restore an earlier route configuration as a new version",
         ),
-        ep("POST", "/api/aranea/register", 80, "Register aranea device"),
         ep(
-            "POST",
-            "/api/lacis-id/assign/:device_id",
+            "PUT",
+            "/api/routes/:id/status-page",
             80,
-            "Assign LacisID to device",
+            "List/unlist a route on the public status page (/status, /status.json) with a display name",
         ),
-        ep("POST", "/api/wireguard/peers", 80, "Create WireGuard peer"),
         ep(
-            "PUT",
-            "/api/wireguard/peers/:id",
+            "GET",
+            "/api/routes/pending",
             80,
-            "Update WireGuard peer",
+            "List proposed route changes",
         ),
         ep(
             "POST",
-            "/api/nginx/enable-full-proxy",
+            "/api/routes/pending/:id/approve",
             80,
-            "Enable full proxy mode",
+            "Approve and apply a proposed route change (?warm=true as for route updates)",
         ),
-        ep("POST", "/api/nginx/reload", 80, "Reload nginx"),
-        ep("POST", "/api/nginx/test", 80, "Test nginx config"),
-        ep("PUT", "/api/nginx/body-size", 80, "Update nginx body size"),
         ep(
-            "PUT",
-            "/api/nginx/template-settings",
+            "POST",
+            "/api/routes/pending/:id/reject",
             80,
-            "Update nginx template settings",
+            "Reject a proposed route change",
         ),
         ep(
             "POST",
-            "/api/nginx/regenerate",
+            "/api/ddns",
+            80,
+            "Create DDNS configuration (?validate=true tests the credentials first)",
+        ),
+        ep(
+            "POST",
+            "/api/ddns/test",
+            80,
+            "Test DDNS credentials without changing the record ({config_id} or a create body)",
+        ),
+        ep(
+            "PUT",
+            "/api/ddns/:id",
+            80,
+            "Update DDNS configuration (incl. failover secondary)",
+        ),
+        ep(
+            "PUT",
+            "/api/ddns/:id/link-omada",
+            80,
+            "Link DDNS to Omada controller",
+        ),
+        ep(
+            "POST",
+            "/api/ddns/:id/report-token",
+            80,
+            "Issue report-ip token (webhook IP source)",
+        ),
+        ep(
+            "POST",
+            "/api/ddns/:id/acme/issue",
+            80,
+            "Order a Let's Encrypt certificate for the hostname (HTTP-01, 202; result in ssl-status)",
+        ),
+        ep(
+            "POST",
+            "/api/dns/overrides",
+            80,
+            "Add a local DNS override (hostname -> LAN IP or target address)",
+        ),
+        ep("PUT", "/api/dns/overrides/:id", 80, "Update a local DNS override"),
+        ep(
+            "DELETE",
+            "/api/dns/overrides/:id",
+            80,
+            "Delete a local DNS override",
+        ),
+        ep(
+            "POST",
+            "/api/security/blocked-ips",
+            80,
+            "Block an IP address",
+        ),
+        ep(
+            "POST",
+            "/api/security/quick-block",
+            80,
+            "Block an IP from an access log entry or security event (unblock needs 100 + confirm)",
+        ),
+        ep(
+            "POST",
+            "/api/security/alert-rules",
+            80,
+            "Create an alert rule (filter expression or daily per-IP metric, threshold, cooldown)",
+        ),
+        ep(
+            "PUT",
+            "/api/security/alert-rules/:id",
+            80,
+            "Update an alert rule",
+        ),
+        ep(
+            "DELETE",
+            "/api/security/alert-rules/:id",
+            80,
+            "Delete an alert rule",
+        ),
+        ep(
+            "POST",
+            "/api/security/rate-limits",
+            80,
+            "Create a rate limit rule (path prefix, requests per window, action log|block)",
+        ),
+        ep(
+            "PUT",
+            "/api/security/rate-limits/:id",
+            80,
+            "Update a rate limit rule",
+        ),
+        ep(
+            "DELETE",
+            "/api/security/rate-limits/:id",
+            80,
+            "Delete a rate limit rule",
+        ),
+        ep(
+            "PUT",
+            "/api/security/geo-rules",
+            80,
+            "Replace the country rules (enabled, mode deny|allow, countries, unresolved_action)",
+        ),
+        ep(
+            "POST",
+            "/api/maintenance/windows",
+            80,
+            "Create a maintenance window (one-off or weekly; suppress or pause health alerts for routes/teams)",
+        ),
+        ep(
+            "PUT",
+            "/api/maintenance/windows/:id",
+            80,
+            "Update a maintenance window",
+        ),
+        ep(
+            "DELETE",
+            "/api/maintenance/windows/:id",
+            80,
+            "Delete a maintenance window",
+        ),
+        ep("PUT", "/api/settings/:key", 80, "Update setting"),
+        ep(
+            "PUT",
+            "/api/settings/restart",
+            80,
+            "Update restart settings",
+        ),
+        ep(
+            "POST",
+            "/api/settings/test-discord",
+            80,
+            "Test Discord notification",
+        ),
+        ep(
+            "POST",
+            "/api/omada/controllers",
+            80,
+            "Register Omada controller",
+        ),
+        ep(
+            "GET",
+            "/api/omada/controllers/:id/webhook",
+            80,
+            "Get Omada webhook configuration",
+        ),
+        ep(
+            "POST",
+            "/api/omada/controllers/:id/webhook/secret",
+            80,
+            "Generate/rotate Omada webhook secret",
+        ),
+        ep(
+            "PUT",
+            "/api/omada/controllers/:id/sites/:site_id",
+            80,
+            "Set Omada site facility (fid) mapping",
+        ),
+        ep(
+            "PUT",
+            "/api/topology/nodes/:id/claim",
+            80,
+            "Assign or clear a topology node claim for any user",
+        ),
+        ep(
+            "POST",
+            "/api/topology/nodes/dedupe-macs",
+            80,
+            "Merge topology nodes whose ids differ only by MAC formatting (dry_run supported)",
+        ),
+        ep(
+            "GET",
+            "/api/topology/shares",
+            80,
+            "List read-only topology shares with usage (tokens never returned)",
+        ),
+        ep(
+            "POST",
+            "/api/topology/shares",
+            80,
+            "Create read-only topology share for a view and detail level (token shown once)",
+        ),
+        ep(
+            "DELETE",
+            "/api/topology/shares/:id",
+            80,
+            "Revoke topology share (confirm required)",
+        ),
+        ep(
+            "POST",
+            "/api/openwrt/routers",
+            80,
+            "Register OpenWrt router",
+        ),
+        ep(
+            "POST",
+            "/api/external/devices",
+            80,
+            "Register external device",
+        ),
+        ep(
+            "PUT",
+            "/api/openwrt/routers/:id",
+            80,
+            "Set router poll interval / pause",
+        ),
+        ep(
+            "PUT",
+            "/api/external/devices/:id",
+            80,
+            "Edit external device (poll interval, pause)",
+        ),
+        ep(
+            "POST",
+            "/api/aranea/register",
+            80,
+            "Register aranea device (devices may use X-Registration-Token instead of a session)",
+        ),
+        ep(
+            "GET",
+            "/api/aranea/registration-tokens",
+            80,
+            "List device registration tokens (secrets never returned)",
+        ),
+        ep(
+            "GET",
+            "/api/aranea/registration-candidates",
+            80,
+            "List topology infra nodes awaiting aranea registration",
+        ),
+        ep(
+            "POST",
+            "/api/aranea/register-batch",
+            80,
+            "Register selected candidates via araneaDeviceGate (dry_run supported)",
+        ),
+        ep(
+            "GET",
+            "/api/aranea/reports",
+            0,
+            "Stored facility reports with delivery state (?fid=&limit=)",
+        ),
+        ep(
+            "POST",
+            "/api/aranea/reports/trigger",
+            80,
+            "Generate facility reports now and push them to mobes2.0 (body: fid?, hours?)",
+        ),
+        ep(
+            "GET",
+            "/api/aranea/config",
+            80,
+            "Effective aranea config (secrets masked, runtime overrides listed)",
+        ),
+        ep(
+            "GET",
+            "/api/aranea/health",
+            80,
+            "Authenticated mobes2.0 call with latency and recent call outcomes",
+        ),
+        ep(
+            "POST",
+            "/api/lacis-id/assign/:device_id",
+            80,
+            "Assign LacisID to device",
+        ),
+        ep(
+            "GET",
+            "/api/admin/cluster",
+            80,
+            "Cluster leader and instance role",
+        ),
+        ep(
+            "GET",
+            "/api/admin/migrations",
+            80,
+            "Startup migrations (applied/pending)",
+        ),
+        ep(
+            "GET",
+            "/api/admin/storage",
+            80,
+            "MongoDB collection sizes, growth, declared vs existing indexes and disk projection",
+        ),
+        ep(
+            "GET",
+            "/api/admin/ingest-writes",
+            80,
+            "Syncer ingestion write pacing and per-source cycle stats",
+        ),
+        ep("GET", "/api/admin/log-level", 80, "Active log filter"),
+        ep(
+            "POST",
+            "/api/wireguard/peers",
+            80,
+            "Create WireGuard peer (optional expires_at)",
+        ),
+        ep(
+            "POST",
+            "/api/wireguard/peers/:id/extend",
+            80,
+            "Extend WireGuard peer expiry (re-enables an expired peer)",
+        ),
+        ep(
+            "PUT",
+            "/api/wireguard/peers/:id",
+            80,
+            "Update WireGuard peer",
+        ),
+        ep(
+            "PUT",
+            "/api/wireguard/all-peers/:id",
+            80,
+            "Update WireGuard peer by unified id (controller and LPG record)",
+        ),
+        ep(
+            "POST",
+            "/api/wireguard/all-peers/:id/resolve",
+            80,
+            "Resolve WireGuard peer address conflict (winner: lpg|omada)",
+        ),
+        ep(
+            "POST",
+            "/api/wireguard/profiles",
+            80,
+            "Create WireGuard config profile",
+        ),
+        ep(
+            "PUT",
+            "/api/wireguard/profiles/:id",
+            80,
+            "Update WireGuard config profile",
+        ),
+        ep(
+            "POST",
+            "/api/nginx/enable-full-proxy",
+            80,
+            "Enable full proxy mode",
+        ),
+        ep("POST", "/api/nginx/reload", 80, "Reload nginx"),
+        ep("POST", "/api/nginx/test", 80, "Test nginx config"),
+        ep("PUT", "/api/nginx/body-size", 80, "Update the request body limit (proxy and nginx)"),
+        ep(
+            "PUT",
+            "/api/nginx/template-settings",
+            80,
+            "Update nginx template settings",
+        ),
+        ep(
+            "POST",
+            "/api/nginx/regenerate",
             80,
             "Regenerate nginx config",
         ),
@@ -621,6 +1271,30 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             100,
             "Delete proxy route (confirm required)",
         ),
+        ep(
+            "PUT",
+            "/api/routes/:id/transform",
+            100,
+            "Set/remove route transformation script (compiled on save; enabled=false stages it)",
+        ),
+        ep(
+            "PUT",
+            "/api/routes/:id/log-fields",
+            80,
+            "Replace custom access log field rules (Authorization/Cookie headers are never logged)",
+        ),
+        ep(
+            "PUT",
+            "/api/routes/:id/store-forward",
+            100,
+            "Set/clear route store-and-forward policy (confirm required to enable)",
+        ),
+        ep(
+            "DELETE",
+            "/api/routes/:id/queue/:queue_id",
+            100,
+            "Discard a queued request (confirm required)",
+        ),
         ep(
             "DELETE",
             "/api/ddns/:id",
@@ -657,17 +1331,217 @@ fn build_endpoint_list(permission: i32) -> Vec<EndpointInfo> {
             100,
             "Delete WireGuard peer (confirm required)",
         ),
+        ep(
+            "DELETE",
+            "/api/wireguard/all-peers/:id",
+            100,
+            "Delete WireGuard peer by unified id (confirm required)",
+        ),
+        ep(
+            "DELETE",
+            "/api/wireguard/profiles/:id",
+            100,
+            "Delete WireGuard config profile (confirm required)",
+        ),
+        ep(
+            "POST",
+            "/api/aranea/registration-tokens",
+            100,
+            "Issue device registration token scoped to fid and productTypes (plaintext shown once)",
+        ),
+        ep(
+            "POST",
+            "/api/aranea/registration-tokens/:id/rotate",
+            100,
+            "Rotate device registration token secret",
+        ),
+        ep(
+            "DELETE",
+            "/api/aranea/registration-tokens/:id",
+            100,
+            "Revoke device registration token (confirm required)",
+        ),
+        ep(
+            "PUT",
+            "/api/aranea/config",
+            100,
+            "Override aranea config at runtime, applied without restart (reset: back to file)",
+        ),
         ep("POST", "/api/auth/api-key", 100, "Issue API key"),
+        ep(
+            "GET",
+            "/api/auth/permission-floors",
+            100,
+            "Get permission floors",
+        ),
+        ep(
+            "PUT",
+            "/api/auth/permission-floors",
+            100,
+            "Update permission floors",
+        ),
+        ep(
+            "GET",
+            "/api/auth/providers",
+            100,
+            "List LacisOath identity providers (secrets masked)",
+        ),
+        ep(
+            "POST",
+            "/api/auth/providers",
+            100,
+            "Add LacisOath identity provider",
+        ),
+        ep(
+            "PUT",
+            "/api/auth/providers/:id",
+            100,
+            "Update LacisOath identity provider",
+        ),
+        ep(
+            "DELETE",
+            "/api/auth/providers/:id",
+            100,
+            "Delete LacisOath identity provider (confirm required)",
+        ),
+        ep(
+            "POST",
+            "/api/admin/cluster/failover",
+            100,
+            "Force leadership failover (confirm required)",
+        ),
+        ep(
+            "POST",
+            "/api/admin/migrations/:id/run",
+            100,
+            "Run a startup migration; force re-runs an applied one (confirm required)",
+        ),
+        ep(
+            "POST",
+            "/api/admin/storage/compact/:collection",
+            100,
+            "Compact a MongoDB collection to reclaim disk space (confirm required)",
+        ),
+        ep(
+            "GET",
+            "/api/admin/api-trace/recorder",
+            100,
+            "API request recorder state (on/off, deadline, who turned it on, entries kept)",
+        ),
+        ep(
+            "PUT",
+            "/api/admin/api-trace/recorder",
+            100,
+            "Turn the API request recorder on for duration_minutes (max 1440) or off",
+        ),
+        ep(
+            "GET",
+            "/api/admin/api-trace",
+            100,
+            "Recorded admin API requests (?actor=&path=&method=&from=&to=&limit=)",
+        ),
+        ep(
+            "GET",
+            "/api/admin/api-trace/:id",
+            100,
+            "Recorded request with masked request/response bodies",
+        ),
+        ep(
+            "POST",
+            "/api/admin/api-trace/:id/replay",
+            100,
+            "Re-run a recorded GET as the caller and compare with the recorded response",
+        ),
+        ep(
+            "POST",
+            "/api/admin/log-level",
+            100,
+            "Change log filter at runtime",
+        ),
+        ep(
+            "POST",
+            "/api/admin/rotate-jwt-secret",
+            100,
+            "Replace the session signing secret (?confirm=true; old one accepted until sessions expire)",
+        ),
         ep(
             "POST",
             "/api/settings/restart/trigger",
             100,
-            "Trigger service restart",
+            "Trigger service restart (drains first unless force)",
         ),
     ];
 
     all_endpoints
         .into_iter()
-        .filter(|e| permission >= e.required_permission)
+        .filter(|e| user.permission >= user.floors.for_level(e.required_permission))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aranea::config::SETTING_ARANEA_CONFIG;
+    use crate::jwt_keys;
+    use crate::models::Setting;
+
+    fn setting(key: &str, value: &str) -> Setting {
+        Setting {
+            id: 1,
+            setting_key: key.to_string(),
+            setting_value: Some(value.to_string()),
+            description: None,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn settings_section_never_returns_signing_secrets() {
+        let settings = vec![
+            setting(jwt_keys::SETTING_SECRET, "current-signing-secret"),
+            setting(jwt_keys::SETTING_PREVIOUS, "previous-signing-secret"),
+            setting("discord_webhook_url", "https://discord.example/hook"),
+            setting("internet_access_enabled", "true"),
+        ];
+        let json = serde_json::to_string(&mask_settings(settings)).unwrap();
+        for secret in [
+            "current-signing-secret",
+            "previous-signing-secret",
+            "discord.example",
+        ] {
+            assert!(!json.contains(secret), "{} leaked: {}", secret, json);
+        }
+        assert!(json.contains("\"true\""));
+    }
+
+    #[test]
+    fn settings_section_masks_the_aranea_override() {
+        let stored = serde_json::json!({
+            "tid": "T1",
+            "tenant_cic": "123456",
+            "signing_key": "aranea-signing-key",
+        });
+        let settings = vec![setting(SETTING_ARANEA_CONFIG, &stored.to_string())];
+        let json = serde_json::to_string(&mask_settings(settings)).unwrap();
+        for secret in ["123456", "aranea-signing-key"] {
+            assert!(!json.contains(secret), "{} leaked: {}", secret, json);
+        }
+        assert!(json.contains("T1"));
+    }
+
+    #[test]
+    fn ddns_configs_are_masked_including_the_report_token() {
+        let request: crate::models::CreateDdnsRequest = serde_json::from_value(serde_json::json!({
+            "provider": "cloudflare",
+            "hostname": "home.example.com",
+            "api_token": "cf-api-token",
+        }))
+        .unwrap();
+        let mut config = request.unsaved_config();
+        config.report_token = Some("report-bearer-token".to_string());
+        let json = serde_json::to_string(&config.masked()).unwrap();
+        for secret in ["cf-api-token", "report-bearer-token"] {
+            assert!(!json.contains(secret), "{} leaked: {}", secret, json);
+        }
+    }
+}
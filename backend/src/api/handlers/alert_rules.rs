@@ -0,0 +1,213 @@
+//! Access-log alert rule handlers
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+
+use crate::alert_rules::{
+    compile_filter, evaluate_rule, AlertAction, AlertRule, CreateAlertRuleRequest,
+    UpdateAlertRuleRequest,
+};
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::AuthUser;
+use crate::proxy::ProxyState;
+
+use super::SuccessResponse;
+
+async fn load_rule(state: &ProxyState, id: &str) -> Result<AlertRule, AppError> {
+    state
+        .app_state
+        .mongo
+        .get_alert_rule(id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::AlertRuleNotFound,
+                format!("Alert rule {} not found", id),
+            )
+        })
+}
+
+/// GET /api/security/alert-rules - List alert rules with their status
+pub async fn list_alert_rules(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let rules = state
+        .app_state
+        .mongo
+        .list_alert_rules()
+        .await
+        .map_err(AppError::database)?;
+    Ok(Json(rules))
+}
+
+/// GET /api/security/alert-rules/:id - Get one alert rule
+pub async fn get_alert_rule(
+    State(state): State<ProxyState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(load_rule(&state, &id).await?))
+}
+
+/// POST /api/security/alert-rules - Create an alert rule (admin: permission >= 80)
+pub async fn create_alert_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<CreateAlertRuleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let rule = AlertRule::new(payload).map_err(AppError::BadRequest)?;
+    state
+        .app_state
+        .mongo
+        .insert_alert_rule(&rule)
+        .await
+        .map_err(AppError::database)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "alert_rule",
+            None,
+            "create",
+            Some("rule"),
+            None,
+            Some(&format!(
+                "{} ({}): {}",
+                rule.name, rule.rule_id, rule.filter
+            )),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// PUT /api/security/alert-rules/:id - Update an alert rule (admin: permission >= 80)
+pub async fn update_alert_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateAlertRuleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mut rule = load_rule(&state, &id).await?;
+    let old = serde_json::to_string(&rule).unwrap_or_default();
+    rule.apply(payload).map_err(AppError::BadRequest)?;
+
+    let found = state
+        .app_state
+        .mongo
+        .update_alert_rule(&rule)
+        .await
+        .map_err(AppError::database)?;
+    if !found {
+        return Err(AppError::coded(
+            ErrorCode::AlertRuleNotFound,
+            format!("Alert rule {} not found", id),
+        ));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "alert_rule",
+            None,
+            "update",
+            Some("rule"),
+            Some(&old),
+            Some(&serde_json::to_string(&rule).unwrap_or_default()),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(rule))
+}
+
+/// DELETE /api/security/alert-rules/:id - Delete an alert rule (admin: permission >= 80)
+pub async fn delete_alert_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let rule = load_rule(&state, &id).await?;
+    state
+        .app_state
+        .mongo
+        .delete_alert_rule(&id)
+        .await
+        .map_err(AppError::database)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "alert_rule",
+            None,
+            "delete",
+            Some("rule"),
+            Some(&format!(
+                "{} ({}): {}",
+                rule.name, rule.rule_id, rule.filter
+            )),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(SuccessResponse::new("Alert rule deleted")))
+}
+
+/// POST /api/security/alert-rules/:id/test - Evaluate a rule against its last
+/// window without firing, and report what the engine would do (operator: permission >= 50)
+pub async fn test_alert_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    let rule = load_rule(&state, &id).await?;
+    let query = compile_filter(&rule.filter)
+        .map_err(|e| AppError::BadRequest(format!("invalid filter: {}", e)))?;
+    let evaluation = evaluate_rule(&state.app_state.mongo, &rule, Utc::now())
+        .await
+        .map_err(AppError::database)?;
+
+    let would = match evaluation.action {
+        AlertAction::None => "nothing (not breached)".to_string(),
+        AlertAction::Suppressed => "nothing (breached, in cooldown)".to_string(),
+        AlertAction::Fire if !rule.enabled => "nothing (breached, rule disabled)".to_string(),
+        AlertAction::Fire if rule.notify == "none" => {
+            format!("record a {:?} security event", rule.severity).to_lowercase()
+        }
+        AlertAction::Fire => format!(
+            "record a {} security event and notify {}",
+            format!("{:?}", rule.severity).to_lowercase(),
+            rule.notify
+        ),
+    };
+
+    Ok(Json(serde_json::json!({
+        "rule": rule,
+        "condition": rule.describe_condition(),
+        "query": query,
+        "evaluation": evaluation,
+        "would": would,
+    })))
+}
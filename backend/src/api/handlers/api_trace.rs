@@ -0,0 +1,295 @@
+//! Admin API request recorder handlers (see `crate::api::api_trace`)
+
+use std::net::SocketAddr;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, Method, Request},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+
+use crate::api::api_trace::{
+    self, query_was_masked, sanitize_body, ApiTraceEntry, RecorderState, DEFAULT_DURATION_MINUTES,
+    MAX_DURATION_MINUTES, MAX_RESPONSE_BODY, REPLAY_HEADER,
+};
+use crate::api::auth_middleware::require_permission;
+use crate::db::mongo::api_trace::{ApiTraceQuery, API_TRACE_CAP_BYTES};
+use crate::error::AppError;
+use crate::models::AuthUser;
+use crate::proxy::store_forward::stamp;
+use crate::proxy::ProxyState;
+
+/// Recorder state with what it has kept
+#[derive(Debug, Serialize)]
+pub struct ApiTraceRecorderStatus {
+    #[serde(flatten)]
+    pub state: RecorderState,
+    pub entries: u64,
+    pub cap_bytes: u64,
+    pub max_duration_minutes: i64,
+}
+
+async fn recorder_status(state: &ProxyState) -> Result<ApiTraceRecorderStatus, AppError> {
+    Ok(ApiTraceRecorderStatus {
+        state: state.api_trace.state(Utc::now()),
+        entries: state
+            .app_state
+            .mongo
+            .count_api_trace()
+            .await
+            .map_err(AppError::database)?,
+        cap_bytes: API_TRACE_CAP_BYTES,
+        max_duration_minutes: MAX_DURATION_MINUTES,
+    })
+}
+
+/// GET /api/admin/api-trace/recorder - Whether the recorder is on, until
+/// when and by whom (dangerous: permission == 100)
+pub async fn get_api_trace_recorder(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    Ok(Json(recorder_status(&state).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateApiTraceRecorderRequest {
+    pub enabled: bool,
+    /// Minutes until the recorder turns itself off (default 60, max 1440)
+    pub duration_minutes: Option<i64>,
+}
+
+/// PUT /api/admin/api-trace/recorder - Turn the recorder on until a deadline
+/// or off (dangerous: permission == 100)
+pub async fn update_api_trace_recorder(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<UpdateApiTraceRecorderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let mysql = &state.app_state.mysql;
+
+    let until = if req.enabled {
+        let minutes = req.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+        if !(1..=MAX_DURATION_MINUTES).contains(&minutes) {
+            return Err(AppError::validation(
+                "duration_minutes",
+                format!("must be 1-{}", MAX_DURATION_MINUTES),
+            ));
+        }
+        Some(Utc::now() + Duration::minutes(minutes))
+    } else {
+        None
+    };
+    let previous = state.api_trace.state(Utc::now());
+    api_trace::save(
+        &state.api_trace,
+        mysql,
+        until,
+        req.enabled.then_some(user.sub.as_str()),
+    )
+    .await?;
+
+    if req.enabled {
+        tracing::warn!(
+            "API request recorder turned on by {} until {}",
+            user.sub,
+            until.map(stamp).unwrap_or_default()
+        );
+    } else {
+        tracing::info!("API request recorder turned off by {}", user.sub);
+    }
+    let _ = mysql
+        .log_audit(
+            "api_trace",
+            None,
+            if req.enabled { "enable" } else { "disable" },
+            Some("until"),
+            previous
+                .until
+                .filter(|_| previous.enabled)
+                .map(stamp)
+                .as_deref(),
+            until.map(stamp).as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(recorder_status(&state).await?))
+}
+
+/// GET /api/admin/api-trace - Recorded requests, newest first, without
+/// bodies (?actor=&path=&method=&from=&to=&limit=; dangerous: permission == 100)
+pub async fn list_api_trace(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ApiTraceQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let entries = state
+        .app_state
+        .mongo
+        .search_api_trace(&query)
+        .await
+        .map_err(AppError::database)?;
+    Ok(Json(serde_json::json!({
+        "recorder": recorder_status(&state).await?,
+        "entries": entries,
+    })))
+}
+
+async fn load_api_trace(state: &ProxyState, id: &str) -> Result<ApiTraceEntry, AppError> {
+    state
+        .app_state
+        .mongo
+        .get_api_trace(id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| AppError::NotFound(format!("API trace {} not found", id)))
+}
+
+/// GET /api/admin/api-trace/:id - One recorded request with its bodies
+/// (dangerous: permission == 100)
+pub async fn get_api_trace(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    Ok(Json(load_api_trace(&state, &id).await?))
+}
+
+/// One side of a replay comparison
+#[derive(Debug, Serialize)]
+pub struct ApiTraceReplaySide {
+    pub at: String,
+    pub actor: String,
+    pub status: i32,
+    /// Masked JSON body (text bodies as a string; None when not captured)
+    pub body: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTraceReplay {
+    pub trace_id: String,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub original: ApiTraceReplaySide,
+    pub replay: ApiTraceReplaySide,
+    pub status_changed: bool,
+    /// None when either body was not captured
+    pub body_changed: Option<bool>,
+}
+
+fn parse_body(body: Option<String>) -> Option<serde_json::Value> {
+    body.map(|b| serde_json::from_str(&b).unwrap_or(serde_json::Value::String(b)))
+}
+
+/// POST /api/admin/api-trace/:id/replay - Re-run a recorded GET request as
+/// the calling admin and return the recorded and fresh responses side by
+/// side; mutations are never replayed (dangerous: permission == 100)
+pub async fn replay_api_trace(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let entry = load_api_trace(&state, &id).await?;
+    if entry.method != Method::GET.as_str() {
+        return Err(AppError::BadRequest(format!(
+            "Only GET requests are replayed ({} {} is a mutation)",
+            entry.method, entry.path
+        )));
+    }
+    if query_was_masked(entry.query.as_deref()) {
+        return Err(AppError::BadRequest(
+            "The recorded query had credentials masked; it cannot be replayed as sent".to_string(),
+        ));
+    }
+
+    let uri = match entry.query.as_deref() {
+        Some(query) => format!("{}?{}", entry.path, query),
+        None => entry.path.clone(),
+    };
+    // Same session as this request, so the replay runs as the calling admin
+    let mut builder = Request::builder()
+        .method(Method::GET)
+        .uri(&uri)
+        .header(REPLAY_HEADER, &entry.trace_id);
+    for name in [header::AUTHORIZATION, header::COOKIE, header::ACCEPT] {
+        if let Some(value) = headers.get(&name) {
+            builder = builder.header(name, value);
+        }
+    }
+    let mut request = builder
+        .body(Body::empty())
+        .map_err(|e| AppError::InternalError(format!("Build replay request: {}", e)))?;
+    request.extensions_mut().insert(ConnectInfo(addr));
+
+    let at = stamp(Utc::now());
+    let response = crate::api::routes(state.clone())
+        .with_state(state.clone())
+        .oneshot(request)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Replay failed: {}", e)))?;
+    let status = response.status().as_u16() as i32;
+    let (parts, body) = response.into_parts();
+    let mut notes = Vec::new();
+    let body = match axum::body::to_bytes(body, MAX_RESPONSE_BODY as usize).await {
+        Ok(bytes) => match sanitize_body(&bytes, &parts.headers) {
+            Ok(body) => parse_body(body),
+            Err(note) => {
+                notes.push(format!("response {}", note));
+                None
+            }
+        },
+        Err(_) => {
+            notes.push(format!(
+                "response body too large (over {} bytes)",
+                MAX_RESPONSE_BODY
+            ));
+            None
+        }
+    };
+
+    let original = ApiTraceReplaySide {
+        at: entry.at,
+        actor: entry.actor,
+        status: entry.status,
+        body: parse_body(entry.response_body),
+        notes: entry.notes,
+    };
+    let replay = ApiTraceReplaySide {
+        at,
+        actor: user.sub,
+        status,
+        body,
+        notes,
+    };
+    let body_changed = match (&original.body, &replay.body) {
+        (Some(a), Some(b)) => Some(a != b),
+        _ => None,
+    };
+    Ok(Json(ApiTraceReplay {
+        trace_id: entry.trace_id,
+        method: entry.method,
+        path: entry.path,
+        query: entry.query,
+        status_changed: original.status != replay.status,
+        body_changed,
+        original,
+        replay,
+    }))
+}
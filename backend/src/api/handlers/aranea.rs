@@ -1,24 +1,66 @@
 //! araneaSDK API handlers - proxy to mobes2.0 Cloud Functions
 
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use axum::{
-    extract::{Path, State},
-    response::IntoResponse,
+    extract::{rejection::JsonRejection, ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
-use crate::api::auth_middleware::require_permission;
+use crate::api::auth_middleware::{authenticate, require_permission};
 use crate::aranea::client::AraneaDeviceRegistration;
-use crate::error::AppError;
-use crate::models::AuthUser;
+use crate::aranea::config::{self as aranea_config, AraneaConfigOverride, AraneaConfigView};
+use crate::aranea::registration;
+use crate::aranea::reports::FacilityReporter;
+use crate::aranea::tokens;
+use crate::db::mongo::OperatorInfo;
+use crate::error::{AppError, ErrorCode};
+use crate::mac::MacAddr;
+use crate::models::{
+    AraneaRegistrationToken, AuthUser, ConfirmQuery, ConfirmRequired,
+    CreateRegistrationTokenRequest,
+};
 use crate::proxy::ProxyState;
 
-/// POST /api/aranea/register - Register a device via araneaDeviceGate (admin: permission >= 80)
+use super::SuccessResponse;
+
+/// POST /api/aranea/register - Register a device via araneaDeviceGate
+///
+/// Either an admin session (permission >= 80, manual registration) or an
+/// `X-Registration-Token` (device-initiated, scoped to the token's fid and
+/// productTypes, rate limited per client IP and per token).
 pub async fn aranea_register_device(
     State(state): State<ProxyState>,
-    Extension(user): Extension<AuthUser>,
-    Json(payload): Json<AraneaDeviceRegistration>,
-) -> Result<impl IntoResponse, AppError> {
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    payload: Result<Json<AraneaDeviceRegistration>, JsonRejection>,
+) -> Result<Response, AppError> {
+    if let Some(presented) = headers.get(tokens::TOKEN_HEADER) {
+        let presented = presented.to_str().map_err(|_| {
+            AppError::coded(
+                ErrorCode::RegistrationTokenInvalid,
+                "Malformed registration token",
+            )
+        })?;
+        let client_ip = state.network_policy.load_policy().client_ip(&headers, addr);
+        return register_with_token(&state, presented, &client_ip, payload).await;
+    }
+
+    // Manual registration: same session rules as the protected routes
+    let user = match authenticate(&state, &headers).await {
+        Ok(user) => user,
+        Err(response) => return Ok(response),
+    };
     require_permission(&user, 80)?;
+    let Json(mut payload) = payload.map_err(|e| AppError::BadRequest(e.body_text()))?;
+    payload.mac = MacAddr::parse(&payload.mac)
+        .map_err(AppError::BadRequest)?
+        .into_string();
 
     let result = state
         .aranea_client
@@ -26,7 +68,147 @@ pub async fn aranea_register_device(
         .await
         .map_err(|e| AppError::InternalError(e))?;
 
-    Ok(Json(result))
+    Ok(Json(result).into_response())
+}
+
+/// 429 with Retry-After for the registration limits
+fn registration_rate_limited(what: &str) -> Response {
+    let mut response = AppError::coded(
+        ErrorCode::RateLimited,
+        format!("Too many registrations {}", what),
+    )
+    .with_details(serde_json::json!({ "retry_after_secs": 60 }))
+    .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from_static("60"));
+    response
+}
+
+async fn register_with_token(
+    state: &ProxyState,
+    presented: &str,
+    client_ip: &str,
+    payload: Result<Json<AraneaDeviceRegistration>, JsonRejection>,
+) -> Result<Response, AppError> {
+    let mysql = &state.app_state.mysql;
+    let ip_limit = mysql
+        .get_setting_i32(
+            "aranea_register_ip_rate_per_minute",
+            tokens::DEFAULT_IP_RATE_PER_MINUTE,
+        )
+        .await
+        .unwrap_or(tokens::DEFAULT_IP_RATE_PER_MINUTE)
+        .max(1) as u32;
+    // Before the lookup, so guessing tokens is throttled too
+    if !state.registration_limiter.allow_ip(client_ip, ip_limit) {
+        tracing::warn!(
+            "[Aranea] Registration attempts from {} rate limited",
+            client_ip
+        );
+        return Ok(registration_rate_limited("from this address"));
+    }
+
+    let invalid = || {
+        AppError::coded(
+            ErrorCode::RegistrationTokenInvalid,
+            "Registration token is unknown, revoked, expired or used up",
+        )
+    };
+    let token = match mysql
+        .find_registration_token(&tokens::hash(presented))
+        .await?
+    {
+        Some(token) if tokens::is_live(&token, Utc::now()) => token,
+        found => {
+            tracing::warn!(
+                "[Aranea] Rejected registration from {}: {}",
+                client_ip,
+                found
+                    .map(|t| format!("token {} no longer usable", t.name))
+                    .unwrap_or_else(|| "unknown token".to_string())
+            );
+            return Err(invalid());
+        }
+    };
+    let actor = tokens::actor(&token);
+
+    let Json(mut payload) = payload.map_err(|e| AppError::BadRequest(e.body_text()))?;
+    payload.mac = MacAddr::parse(&payload.mac)
+        .map_err(AppError::BadRequest)?
+        .into_string();
+    if let Err(violation) = tokens::apply_scope(&token, &mut payload) {
+        let _ = mysql
+            .log_audit(
+                "aranea_registration",
+                Some(token.id),
+                "register_rejected",
+                None,
+                None,
+                Some(&format!("{}: {}", payload.mac, violation)),
+                &actor,
+                Some(client_ip),
+            )
+            .await;
+        return Err(AppError::coded(
+            ErrorCode::RegistrationTokenScope,
+            violation,
+        ));
+    }
+
+    let token_limit = mysql
+        .get_setting_i32(
+            "aranea_register_token_rate_per_minute",
+            tokens::DEFAULT_TOKEN_RATE_PER_MINUTE,
+        )
+        .await
+        .unwrap_or(tokens::DEFAULT_TOKEN_RATE_PER_MINUTE)
+        .max(1) as u32;
+    if !state
+        .registration_limiter
+        .allow_token(token.id, token_limit)
+    {
+        tracing::warn!(
+            "[Aranea] Registrations with token {} rate limited",
+            token.name
+        );
+        return Ok(registration_rate_limited("with this token"));
+    }
+
+    // Counted before the upstream call; a concurrent revocation or the last
+    // use going elsewhere shows up here
+    if !mysql
+        .consume_registration_token(token.id, client_ip)
+        .await?
+    {
+        return Err(invalid());
+    }
+
+    let result = state
+        .aranea_client
+        .register_device(&payload)
+        .await
+        .map_err(AppError::InternalError)?;
+
+    let _ = mysql
+        .log_audit(
+            "aranea_registration",
+            Some(token.id),
+            "register",
+            None,
+            None,
+            Some(&format!(
+                "{} {} fid {}",
+                payload.mac,
+                payload.product_type,
+                payload.fid.as_deref().unwrap_or("")
+            )),
+            &actor,
+            Some(client_ip),
+        )
+        .await;
+
+    Ok(Json(result).into_response())
 }
 
 /// GET /api/aranea/devices - List devices via deviceStateReport (list mode)
@@ -63,3 +245,612 @@ pub async fn aranea_summary(
     let summary = state.aranea_client.get_config_summary();
     Ok(Json(summary))
 }
+
+/// GET /api/aranea/registration-candidates - Infra nodes awaiting araneaDeviceGate registration (admin: permission >= 80)
+pub async fn aranea_registration_candidates(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let nodes = state
+        .app_state
+        .mongo
+        .get_all_user_object_details()
+        .await
+        .map_err(AppError::database)?;
+
+    let candidates: Vec<registration::RegistrationCandidate> = nodes
+        .iter()
+        .filter(|n| registration::is_candidate(n))
+        .map(registration::to_candidate)
+        .collect();
+    let ready = candidates.iter().filter(|c| c.issues.is_empty()).count();
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "configured": state.aranea_client.is_configured(),
+        "total": candidates.len(),
+        "ready": ready,
+        "candidates": candidates,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterBatchRequest {
+    /// user_object_detail ids (candidate LacisIDs) to register
+    pub ids: Vec<String>,
+    /// Validate payloads only; nothing is sent upstream or written
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// POST /api/aranea/register-batch - Register selected candidates via araneaDeviceGate (admin: permission >= 80)
+///
+/// Devices are processed one by one and each success is committed
+/// immediately, so a failure part-way leaves earlier registrations in place.
+pub async fn aranea_register_batch(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<RegisterBatchRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    if req.ids.is_empty() {
+        return Err(AppError::BadRequest("ids must not be empty".to_string()));
+    }
+    if !req.dry_run && !state.aranea_client.is_configured() {
+        return Err(AppError::BadRequest(
+            "Aranea not configured: missing tid/tenant_lacis_id/tenant_cic".to_string(),
+        ));
+    }
+
+    let op_id = if req.dry_run {
+        String::new()
+    } else {
+        state
+            .app_state
+            .mongo
+            .start_operation_log_with_operator(
+                "aranea_register_batch",
+                "api",
+                None,
+                Some(OperatorInfo {
+                    sub: user.sub.clone(),
+                    auth_method: user.auth_method.clone(),
+                    permission: user.permission,
+                }),
+            )
+            .await
+            .unwrap_or_default()
+    };
+    let start = std::time::Instant::now();
+
+    let mut results = Vec::with_capacity(req.ids.len());
+    let mut registered = 0u32;
+    let mut failed = 0u32;
+
+    for id in &req.ids {
+        let outcome = register_one(&state, &user, id, req.dry_run).await;
+        match outcome {
+            Ok(result) => {
+                registered += 1;
+                results.push(result);
+            }
+            Err(e) => {
+                failed += 1;
+                results.push(serde_json::json!({ "id": id, "ok": false, "error": e }));
+            }
+        }
+    }
+
+    if !op_id.is_empty() {
+        let _ = state
+            .app_state
+            .mongo
+            .complete_operation_log(
+                &op_id,
+                Some(&serde_json::json!({ "registered": registered, "failed": failed })),
+                start.elapsed().as_millis() as u64,
+            )
+            .await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": failed == 0,
+        "dry_run": req.dry_run,
+        "registered": if req.dry_run { 0 } else { registered },
+        "valid": if req.dry_run { registered } else { 0 },
+        "failed": failed,
+        "results": results,
+    })))
+}
+
+/// Validate, register and commit a single candidate
+async fn register_one(
+    state: &ProxyState,
+    user: &AuthUser,
+    id: &str,
+    dry_run: bool,
+) -> Result<serde_json::Value, String> {
+    let mongo = &state.app_state.mongo;
+    let node = mongo
+        .get_user_object_detail_by_id(id)
+        .await?
+        .ok_or_else(|| "Node not found".to_string())?;
+    if !registration::is_candidate(&node) {
+        return Err("Node is not an unregistered infra device".to_string());
+    }
+
+    let payload = registration::build_payload(&node);
+    let issues = registration::validate_payload(&payload);
+    if !issues.is_empty() {
+        return Err(issues.join("; "));
+    }
+    let (source, device_id) = registration::assign_target(&node)
+        .ok_or_else(|| "Cannot resolve source device from source_ref_id".to_string())?;
+
+    if dry_run {
+        return Ok(serde_json::json!({ "id": id, "ok": true, "payload": payload }));
+    }
+
+    let response = state.aranea_client.register_device(&payload).await?;
+    let lacis_id = registration::extract_lacis_id(&response)
+        .ok_or_else(|| "araneaDeviceGate response did not include a lacisId".to_string())?;
+
+    let source_updated = mongo
+        .assign_lacis_id(&source, &device_id, &lacis_id)
+        .await?;
+    mongo.set_user_object_detail_lacis_id(id, &lacis_id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "aranea_device",
+            None,
+            "register",
+            Some("lacis_id"),
+            Some(id),
+            Some(&lacis_id),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(serde_json::json!({
+        "id": id,
+        "ok": true,
+        "lacis_id": lacis_id,
+        "source": source,
+        "source_updated": source_updated,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FacilityReportQuery {
+    pub fid: Option<String>,
+    /// Default 20, at most 200
+    pub limit: Option<i64>,
+}
+
+/// GET /api/aranea/reports - Stored facility reports with their delivery state, newest first
+pub async fn aranea_list_reports(
+    State(state): State<ProxyState>,
+    Query(query): Query<FacilityReportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let reports = state
+        .app_state
+        .mongo
+        .list_facility_reports(
+            query.fid.as_deref(),
+            query.limit.unwrap_or(20).clamp(1, 200),
+        )
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "configured": state.aranea_client.can_push_facility_reports(),
+        "reports": reports,
+    })))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TriggerReportRequest {
+    /// Only this facility (default: all)
+    pub fid: Option<String>,
+    /// Period length ending now (default: the report interval, or 24)
+    pub hours: Option<i64>,
+}
+
+/// POST /api/aranea/reports/trigger - Generate facility reports now and push them in the background (admin: permission >= 80)
+pub async fn aranea_trigger_reports(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    body: Option<Json<TriggerReportRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+
+    let reporter = Arc::new(FacilityReporter::new(
+        state.app_state.clone(),
+        state.aranea_client.clone(),
+    ));
+    let hours = match req.hours {
+        Some(h) if h > 0 => h,
+        Some(_) => return Err(AppError::validation("hours", "must be positive")),
+        None => match reporter.interval_hours().await {
+            0 => 24,
+            h => h as i64,
+        },
+    };
+
+    let reports = reporter
+        .generate(hours, req.fid.as_deref(), "manual")
+        .await
+        .map_err(AppError::InternalError)?;
+    if let Some(fid) = &req.fid {
+        if reports.is_empty() {
+            return Err(AppError::NotFound(format!("Unknown facility {}", fid)));
+        }
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "facility_report",
+            None,
+            "trigger",
+            None,
+            None,
+            Some(req.fid.as_deref().unwrap_or("*")),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    let summary: Vec<_> = reports
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "report_id": r.report_id,
+                "fid": r.fid,
+                "delivery": r.delivery,
+            })
+        })
+        .collect();
+    let delivery = reporter.clone();
+    tokio::spawn(async move { delivery.deliver(reports).await });
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "period_hours": hours,
+        "reports": summary,
+    })))
+}
+
+// ============================================================================
+// Device registration tokens
+// ============================================================================
+
+const MAX_TOKEN_NAME_LEN: usize = 100;
+const MAX_FID_LEN: usize = 50;
+
+/// A token with its plaintext, returned only when issued or rotated
+#[derive(Debug, Serialize)]
+pub struct IssuedRegistrationToken {
+    #[serde(flatten)]
+    pub token: AraneaRegistrationToken,
+    /// Send as X-Registration-Token; not retrievable later
+    pub registration_token: String,
+}
+
+async fn load_registration_token(
+    state: &ProxyState,
+    id: i32,
+) -> Result<AraneaRegistrationToken, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_registration_token(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::RegistrationTokenNotFound,
+                format!("Registration token {} not found", id),
+            )
+        })
+}
+
+/// GET /api/aranea/registration-tokens - List device registration tokens,
+/// without their secrets (admin: permission >= 80)
+pub async fn list_registration_tokens(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let tokens = state.app_state.mysql.list_registration_tokens().await?;
+    Ok(Json(tokens))
+}
+
+/// POST /api/aranea/registration-tokens - Issue a token scoped to a facility
+/// and productTypes; the plaintext is returned once (dangerous: permission == 100)
+pub async fn create_registration_token(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateRegistrationTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_TOKEN_NAME_LEN {
+        return Err(AppError::validation(
+            "name",
+            format!("must be 1-{} characters", MAX_TOKEN_NAME_LEN),
+        ));
+    }
+    let fid = req.fid.trim();
+    if fid.is_empty() || fid.len() > MAX_FID_LEN {
+        return Err(AppError::validation(
+            "fid",
+            format!("must be 1-{} characters", MAX_FID_LEN),
+        ));
+    }
+    let mut product_types: Vec<String> = req
+        .product_types
+        .iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    product_types.sort();
+    product_types.dedup();
+    if product_types.is_empty() {
+        return Err(AppError::validation(
+            "product_types",
+            "at least one productType is required",
+        ));
+    }
+    let expires_at = match req.expires_in_hours {
+        Some(hours) if hours <= 0 => {
+            return Err(AppError::validation("expires_in_hours", "must be positive"))
+        }
+        Some(hours) => Some(Utc::now() + chrono::Duration::hours(hours)),
+        None => None,
+    };
+    if req.max_uses.is_some_and(|max| max < 1) {
+        return Err(AppError::validation("max_uses", "must be at least 1"));
+    }
+
+    let mysql = &state.app_state.mysql;
+    if mysql
+        .list_registration_tokens()
+        .await?
+        .iter()
+        .any(|t| t.name == name)
+    {
+        return Err(AppError::validation("name", "already in use"));
+    }
+
+    let (plaintext, hash, prefix) = tokens::generate();
+    let id = mysql
+        .create_registration_token(
+            name,
+            fid,
+            &product_types,
+            &hash,
+            &prefix,
+            expires_at,
+            req.max_uses,
+            &user.sub,
+        )
+        .await?;
+    let token = load_registration_token(&state, id).await?;
+
+    let _ = mysql
+        .log_audit(
+            "aranea_registration_token",
+            Some(id),
+            "create",
+            None,
+            None,
+            Some(&format!(
+                "{} fid {} [{}]",
+                token.name,
+                token.fid,
+                token.product_types.join(", ")
+            )),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedRegistrationToken {
+            token,
+            registration_token: plaintext,
+        }),
+    ))
+}
+
+/// POST /api/aranea/registration-tokens/:id/rotate - Replace a token's
+/// secret, invalidating the old one (dangerous: permission == 100)
+pub async fn rotate_registration_token(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let existing = load_registration_token(&state, id).await?;
+    let (plaintext, hash, prefix) = tokens::generate();
+    if !state
+        .app_state
+        .mysql
+        .rotate_registration_token(id, &hash, &prefix)
+        .await?
+    {
+        return Err(AppError::BadRequest(format!(
+            "Registration token {} is revoked",
+            existing.name
+        )));
+    }
+    let token = load_registration_token(&state, id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "aranea_registration_token",
+            Some(id),
+            "rotate",
+            Some("token_prefix"),
+            Some(&existing.token_prefix),
+            Some(&token.token_prefix),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(IssuedRegistrationToken {
+        token,
+        registration_token: plaintext,
+    }))
+}
+
+/// DELETE /api/aranea/registration-tokens/:id - Revoke a token; applies to
+/// the next registration (dangerous: permission == 100)
+pub async fn revoke_registration_token(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(confirm): Query<ConfirmQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let token = load_registration_token(&state, id).await?;
+    if !confirm.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "revoke_registration_token".to_string(),
+            target: format!("Registration token #{} ({})", id, token.name),
+            warning: format!(
+                "Devices of facility {} can no longer register with this token.",
+                token.fid
+            ),
+            confirm_required: true,
+        })));
+    }
+
+    if !state.app_state.mysql.revoke_registration_token(id).await? {
+        return Err(AppError::BadRequest(format!(
+            "Registration token {} is already revoked",
+            token.name
+        )));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "aranea_registration_token",
+            Some(id),
+            "revoke",
+            None,
+            Some(&token.name),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!(SuccessResponse::new(
+        "Registration token revoked"
+    ))))
+}
+
+/// GET /api/aranea/config - Effective aranea config with secrets masked and
+/// the fields overridden at runtime (admin: permission >= 80)
+pub async fn get_aranea_config(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let stored = aranea_config::load_override(&state.app_state.mysql).await?;
+    let client = &state.aranea_client;
+    Ok(Json(AraneaConfigView::new(
+        client.config(),
+        &stored,
+        client.is_configured(),
+    )))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAraneaConfigRequest {
+    /// Fields to set; a secret sent back masked keeps its value
+    #[serde(flatten)]
+    pub changes: AraneaConfigOverride,
+    /// Drop every earlier override first (back to the config file)
+    #[serde(default)]
+    pub reset: bool,
+}
+
+/// PUT /api/aranea/config - Override aranea config fields at runtime; stored
+/// in settings and applied to the live client (dangerous: permission == 100)
+pub async fn update_aranea_config(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<UpdateAraneaConfigRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let mysql = &state.app_state.mysql;
+    let client = &state.aranea_client;
+
+    let previous = aranea_config::load_override(mysql).await?;
+    let mut stored = if req.reset {
+        AraneaConfigOverride::default()
+    } else {
+        previous.clone()
+    };
+    let changed = req.changes.fields().join(", ");
+    stored.merge(req.changes);
+
+    let errors = aranea_config::validate(&stored.apply(client.file_config()));
+    if !errors.is_empty() {
+        return Err(AppError::Validation(errors));
+    }
+
+    aranea_config::save_override(mysql, &stored).await?;
+    client.apply_override(&stored).await;
+
+    let encode = |o: &AraneaConfigOverride| serde_json::to_string(&o.clone().masked()).ok();
+    let _ = mysql
+        .log_audit(
+            "aranea_config",
+            None,
+            if req.reset { "reset" } else { "update" },
+            (!changed.is_empty()).then_some(changed.as_str()),
+            encode(&previous).as_deref(),
+            encode(&stored).as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(AraneaConfigView::new(
+        client.config(),
+        &stored,
+        client.is_configured(),
+    )))
+}
+
+/// GET /api/aranea/health - Lightweight authenticated call to mobes2.0 with
+/// latency, plus the client's recent call outcomes (admin: permission >= 80)
+pub async fn aranea_health(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    Ok(Json(state.aranea_client.check_health().await))
+}
@@ -2,24 +2,31 @@
 //!
 //! - POST /api/auth/login/local     - Local email+password login
 //! - POST /api/auth/login/lacisoath - OAuth 2.0 Authorization Code login (mobes 2.0)
-//! - GET  /api/auth/lacisoath-config - OAuth 2.0 client config (public, no secrets)
+//! - GET  /api/auth/lacisoath-config - OAuth 2.0 provider configs (public, no secrets)
+//! - GET/POST /api/auth/providers     - LacisOath identity providers (permission 100)
+//! - PUT/DELETE /api/auth/providers/:id
 //! - GET  /api/auth/me               - Get current authenticated user
 //! - POST /api/auth/logout            - Clear session cookie
+//! - GET  /api/auth/permission-floors - Current permission floors
+//! - PUT  /api/auth/permission-floors - Update permission floors (permission 100)
+//! - POST /api/admin/rotate-jwt-secret - Replace the session signing secret (permission 100)
 
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::{header::SET_COOKIE, StatusCode},
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use jsonwebtoken::{encode, EncodingKey, Header};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::api::auth_middleware::require_permission;
 use crate::error::AppError;
+use crate::jwt_keys::{self, JwtKeys};
 use crate::models::{
-    ApiKeyRequest, ApiKeyResponse, AuthResponse, AuthUser, LacisOathLoginRequest,
-    LocalLoginRequest, SessionClaims,
+    ApiKeyRequest, ApiKeyResponse, AuthResponse, AuthUser, ConfirmQuery, ConfirmRequired,
+    CreateLacisOathProviderRequest, LacisOathLoginRequest, LacisOathProvider, LocalLoginRequest,
+    PermissionFloors, SessionClaims, UpdateLacisOathProviderRequest, CONFIG_LACISOATH_PROVIDER,
 };
 use crate::proxy::ProxyState;
 
@@ -64,9 +71,13 @@ pub async fn login_local(
         lacis_id: None,
         permission: 100, // local admin gets max permission
         auth_method: "local".to_string(),
+        auth_provider: None,
+        floors: PermissionFloors::default(),
     };
 
-    let cookie = create_session_cookie(&user, auth)?;
+    let floors = *state.permission_floors.read().await;
+    let keys = state.jwt_keys.read().await.clone();
+    let cookie = create_session_cookie(&user, auth, &keys, &floors)?;
     let body = AuthResponse {
         ok: true,
         user: user.clone(),
@@ -76,29 +87,27 @@ pub async fn login_local(
 }
 
 /// POST /api/auth/login/lacisoath
-/// OAuth 2.0 Authorization Code Flow: exchange code for token via mobes externalAuthToken
+/// OAuth 2.0 Authorization Code Flow: exchange code for token via the chosen
+/// provider's externalAuthToken, then check the user against that provider
 pub async fn login_lacisoath(
     State(state): State<ProxyState>,
     Json(req): Json<LacisOathLoginRequest>,
 ) -> Result<Response, AppError> {
     let auth = &state.auth_config;
 
-    // Verify OAuth 2.0 client is configured
-    if auth.lacisoath_client_id.is_empty() || auth.lacisoath_client_secret.is_empty() {
-        return Err(AppError::InternalError(
-            "LacisOath is not configured".to_string(),
-        ));
-    }
+    let providers = all_providers(&state).await?;
+    let provider =
+        select_provider(&providers, req.provider.as_deref()).map_err(AppError::BadRequest)?;
 
     // Step 1: Exchange authorization code for token
     let client = reqwest::Client::new();
     let token_resp = client
-        .post(&auth.lacisoath_token_url)
+        .post(&provider.token_url)
         .json(&serde_json::json!({
             "grant_type": "authorization_code",
             "code": req.code,
-            "client_id": auth.lacisoath_client_id,
-            "client_secret": auth.lacisoath_client_secret,
+            "client_id": provider.client_id,
+            "client_secret": provider.client_secret,
             "redirect_uri": req.redirect_uri,
         }))
         .send()
@@ -107,7 +116,11 @@ pub async fn login_lacisoath(
 
     if !token_resp.status().is_success() {
         let body = token_resp.text().await.unwrap_or_default();
-        tracing::warn!("LacisOath token exchange failed: {}", body);
+        tracing::warn!(
+            "LacisOath token exchange failed (provider {}): {}",
+            provider.provider_id,
+            body
+        );
         return Err(AppError::BadRequest(format!(
             "Authentication failed: {}",
             body
@@ -122,44 +135,31 @@ pub async fn login_lacisoath(
 
     let user_info = &token_data.data.user_info;
 
-    // Step 3: Permission check
-    if user_info.permission < auth.lacisoath_required_permission {
-        tracing::warn!(
-            "LacisOath login denied: permission {} < required {}",
-            user_info.permission,
-            auth.lacisoath_required_permission
-        );
-        return Err(AppError::BadRequest(format!(
-            "Insufficient permission: {} (required: {})",
-            user_info.permission, auth.lacisoath_required_permission
-        )));
-    }
-
-    // Step 4: FID check
-    let has_fid = user_info
-        .fid
-        .iter()
-        .any(|f| f == &auth.lacisoath_required_fid || f == "0000");
-    if !has_fid {
+    // Step 3: Permission, facility and tenant checks (live login floor
+    // plus the provider's own requirements)
+    let floors = *state.permission_floors.read().await;
+    if let Err(reason) = check_provider_user(provider, user_info, floors.login) {
         tracing::warn!(
-            "LacisOath login denied: fid {:?} does not contain {} or 0000",
-            user_info.fid,
-            auth.lacisoath_required_fid
+            "LacisOath login denied for {} (provider {}): {}",
+            user_info.lacis_id,
+            provider.provider_id,
+            reason
         );
-        return Err(AppError::BadRequest(
-            "Access not authorized for this facility".to_string(),
-        ));
+        return Err(AppError::BadRequest(reason));
     }
 
-    // Step 5: Create session
+    // Step 4: Create session
     let user = AuthUser {
         sub: user_info.lacis_id.clone(),
         lacis_id: Some(user_info.lacis_id.clone()),
         permission: user_info.permission,
         auth_method: "lacisoath".to_string(),
+        auth_provider: Some(provider.provider_id.clone()),
+        floors,
     };
 
-    let cookie = create_session_cookie(&user, auth)?;
+    let keys = state.jwt_keys.read().await.clone();
+    let cookie = create_session_cookie(&user, auth, &keys, &floors)?;
     let body = AuthResponse {
         ok: true,
         user: user.clone(),
@@ -169,16 +169,44 @@ pub async fn login_lacisoath(
 }
 
 /// GET /api/auth/lacisoath-config
-/// Returns OAuth 2.0 client config for frontend (no secrets exposed)
+/// Returns the enabled providers for the login page's picker (no secrets).
+/// The top-level fields describe the default (first) provider.
 pub async fn lacisoath_config(State(state): State<ProxyState>) -> impl IntoResponse {
-    let auth = &state.auth_config;
-    let enabled = !auth.lacisoath_client_id.is_empty() && !auth.lacisoath_client_secret.is_empty();
+    let providers = match all_providers(&state).await {
+        Ok(providers) => providers,
+        Err(e) => {
+            tracing::warn!("Failed to load LacisOath providers: {}", e);
+            LacisOathProvider::from_config(&state.auth_config)
+                .into_iter()
+                .collect()
+        }
+    };
+    let enabled: Vec<serde_json::Value> = providers
+        .iter()
+        .filter(|p| p.enabled)
+        .map(|p| {
+            serde_json::json!({
+                "id": p.provider_id,
+                "display_name": p.display_name,
+                "client_id": p.client_id,
+                "auth_url": p.auth_url,
+                "redirect_uri": p.redirect_uri,
+            })
+        })
+        .collect();
+    let default = providers.iter().find(|p| p.enabled);
 
     Json(serde_json::json!({
-        "enabled": enabled,
-        "client_id": if enabled { &auth.lacisoath_client_id } else { "" },
-        "auth_url": &auth.lacisoath_auth_url,
-        "redirect_uri": &auth.lacisoath_redirect_uri,
+        "enabled": default.is_some(),
+        "client_id": default.map(|p| p.client_id.as_str()).unwrap_or(""),
+        "auth_url": default
+            .map(|p| p.auth_url.as_str())
+            .unwrap_or(&state.auth_config.lacisoath_auth_url),
+        "redirect_uri": default
+            .map(|p| p.redirect_uri.as_str())
+            .unwrap_or(&state.auth_config.lacisoath_redirect_uri),
+        "default_provider": default.map(|p| p.provider_id.as_str()),
+        "providers": enabled,
     }))
 }
 
@@ -226,13 +254,15 @@ pub async fn create_api_key(
         lacis_id: user.lacis_id.clone(),
         permission: user.permission,
         auth_method: "api_key".to_string(),
+        auth_provider: user.auth_provider.clone(),
         exp: expires_at.timestamp() as usize,
+        floors: Some(*state.permission_floors.read().await),
     };
 
     let token = encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(state.auth_config.jwt_secret.as_bytes()),
+        &EncodingKey::from_secret(state.jwt_keys.read().await.signing_secret().as_bytes()),
     )
     .map_err(|e| AppError::InternalError(format!("JWT encode error: {}", e)))?;
 
@@ -250,14 +280,406 @@ pub async fn create_api_key(
     }))
 }
 
+/// GET /api/auth/permission-floors
+/// Return the live permission floors
+pub async fn get_permission_floors(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let floors = *state.permission_floors.read().await;
+    Ok(Json(floors))
+}
+
+/// PUT /api/auth/permission-floors
+/// Update permission floors. Raised floors apply to existing sessions on their
+/// next request; lowered floors only apply to sessions issued afterwards.
+pub async fn update_permission_floors(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<PermissionFloors>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    req.validate().map_err(AppError::BadRequest)?;
+
+    state.app_state.mysql.save_permission_floors(&req).await?;
+
+    let old = {
+        let mut floors = state.permission_floors.write().await;
+        std::mem::replace(&mut *floors, req)
+    };
+
+    let old_json = serde_json::to_string(&old).unwrap_or_default();
+    let new_json = serde_json::to_string(&req).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "settings",
+            None,
+            "update",
+            Some("permission_floors"),
+            Some(&old_json),
+            Some(&new_json),
+            "api",
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Permission Floors Updated",
+            &format!("{} → {} (by {})", old_json, new_json, user.sub),
+        )
+        .await;
+
+    tracing::info!("Permission floors updated by {}: {}", user.sub, new_json);
+
+    Ok(Json(req))
+}
+
+#[derive(Debug, Serialize)]
+pub struct JwtSecretRotation {
+    pub rotated_at: String,
+    /// Sessions signed with the replaced secret are accepted until then
+    pub previous_valid_until: String,
+    /// The replaced secret was the shipped default
+    pub replaced_default: bool,
+}
+
+/// POST /api/admin/rotate-jwt-secret?confirm=true
+/// Sign with a newly generated secret from now on; the replaced one is still
+/// accepted for `session_duration_hours` (permission 100). API keys have to
+/// be re-issued before then.
+pub async fn rotate_jwt_secret(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ConfirmQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let grace = chrono::Duration::hours(state.auth_config.session_duration_hours as i64);
+    if !query.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "rotate_jwt_secret".to_string(),
+            target: "session signing secret".to_string(),
+            warning: format!(
+                "Existing sessions stay valid for up to {} hours; API keys stop working \
+                 then and must be re-issued.",
+                grace.num_hours()
+            ),
+            confirm_required: true,
+        })));
+    }
+
+    let now = chrono::Utc::now();
+    let (old, rotated) = {
+        let mut keys = state.jwt_keys.write().await;
+        let rotated = keys.rotate(jwt_keys::generate_secret(), now + grace);
+        rotated.save(&state.app_state.mysql).await?;
+        (std::mem::replace(&mut *keys, rotated.clone()), rotated)
+    };
+    let replaced_default = old.is_default();
+
+    // The secrets themselves never reach the audit log
+    let describe = |keys: &JwtKeys| {
+        if keys.is_default() {
+            "default"
+        } else if keys.is_rotated() {
+            "generated"
+        } else {
+            "configured"
+        }
+    };
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "settings",
+            None,
+            "rotate",
+            Some(jwt_keys::SETTING_SECRET),
+            Some(describe(&old)),
+            Some(describe(&rotated)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Session Signing Secret Rotated",
+            &format!(
+                "Previous secret accepted until {} (by {})",
+                (now + grace).to_rfc3339(),
+                user.sub
+            ),
+        )
+        .await;
+
+    tracing::info!(
+        "Session signing secret rotated by {} (replaced {} secret)",
+        user.sub,
+        describe(&old)
+    );
+
+    Ok(Json(serde_json::json!(JwtSecretRotation {
+        rotated_at: now.to_rfc3339(),
+        previous_valid_until: (now + grace).to_rfc3339(),
+        replaced_default,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteProviderQuery {
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// GET /api/auth/providers
+/// All LacisOath providers, secrets masked (permission 100)
+pub async fn list_lacisoath_providers(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let providers: Vec<LacisOathProvider> = all_providers(&state)
+        .await?
+        .into_iter()
+        .map(LacisOathProvider::masked)
+        .collect();
+    Ok(Json(providers))
+}
+
+/// POST /api/auth/providers
+/// Add a LacisOath provider (permission 100)
+pub async fn create_lacisoath_provider(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateLacisOathProviderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let id = req.provider_id.trim();
+    validate_provider_id(id).map_err(AppError::BadRequest)?;
+    if state
+        .app_state
+        .mysql
+        .get_lacisoath_provider(id)
+        .await?
+        .is_some()
+    {
+        return Err(AppError::BadRequest(format!(
+            "Provider {} already exists",
+            id
+        )));
+    }
+    let provider = LacisOathProvider {
+        provider_id: id.to_string(),
+        display_name: req.display_name.trim().to_string(),
+        client_id: req.client_id.trim().to_string(),
+        client_secret: req.client_secret.clone(),
+        auth_url: req.auth_url.trim().to_string(),
+        token_url: req.token_url.trim().to_string(),
+        redirect_uri: req.redirect_uri.trim().to_string(),
+        expected_tid: req.expected_tid.clone(),
+        required_permission: req.required_permission,
+        required_fid: req.required_fid.trim().to_string(),
+        enabled: req.enabled,
+        from_config: false,
+        created_at: None,
+        updated_at: None,
+    };
+    validate_provider(&provider).map_err(AppError::BadRequest)?;
+
+    state
+        .app_state
+        .mysql
+        .create_lacisoath_provider(&req)
+        .await?;
+
+    let new_json = serde_json::to_string(&provider.clone().masked()).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "lacisoath_provider",
+            None,
+            "create",
+            Some(id),
+            None,
+            Some(&new_json),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    tracing::info!("LacisOath provider {} added by {}", id, user.sub);
+    Ok(Json(provider.masked()))
+}
+
+/// PUT /api/auth/providers/:id
+/// Update a stored provider; the `[auth]` provider is read-only (permission 100)
+pub async fn update_lacisoath_provider(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateLacisOathProviderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    if id == CONFIG_LACISOATH_PROVIDER {
+        return Err(AppError::BadRequest(
+            "The default provider is defined in the config file".to_string(),
+        ));
+    }
+    let old = state
+        .app_state
+        .mysql
+        .get_lacisoath_provider(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Provider {} not found", id)))?;
+
+    let mut updated = old.clone();
+    if let Some(v) = &req.display_name {
+        updated.display_name = v.trim().to_string();
+    }
+    if let Some(v) = &req.client_id {
+        updated.client_id = v.trim().to_string();
+    }
+    if let Some(v) = req
+        .client_secret
+        .as_ref()
+        .filter(|v| !v.is_empty() && v.as_str() != "********")
+    {
+        updated.client_secret = v.clone();
+    }
+    if let Some(v) = &req.auth_url {
+        updated.auth_url = v.trim().to_string();
+    }
+    if let Some(v) = &req.token_url {
+        updated.token_url = v.trim().to_string();
+    }
+    if let Some(v) = &req.redirect_uri {
+        updated.redirect_uri = v.trim().to_string();
+    }
+    if let Some(v) = &req.expected_tid {
+        updated.expected_tid = v
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+    }
+    if let Some(v) = req.required_permission {
+        updated.required_permission = v;
+    }
+    if let Some(v) = &req.required_fid {
+        updated.required_fid = v.trim().to_string();
+    }
+    if let Some(v) = req.enabled {
+        updated.enabled = v;
+    }
+    validate_provider(&updated).map_err(AppError::BadRequest)?;
+
+    state
+        .app_state
+        .mysql
+        .update_lacisoath_provider(&updated)
+        .await?;
+
+    let old_json = serde_json::to_string(&old.masked()).unwrap_or_default();
+    let new_json = serde_json::to_string(&updated.clone().masked()).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "lacisoath_provider",
+            None,
+            "update",
+            Some(&id),
+            Some(&old_json),
+            Some(&new_json),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    tracing::info!("LacisOath provider {} updated by {}", id, user.sub);
+    Ok(Json(updated.masked()))
+}
+
+/// DELETE /api/auth/providers/:id
+/// Remove a stored provider; its sessions stay valid until they expire
+/// (dangerous: permission == 100, confirm required)
+pub async fn delete_lacisoath_provider(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteProviderQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    if id == CONFIG_LACISOATH_PROVIDER {
+        return Err(AppError::BadRequest(
+            "The default provider is defined in the config file".to_string(),
+        ));
+    }
+    let provider = state
+        .app_state
+        .mysql
+        .get_lacisoath_provider(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Provider {} not found", id)))?;
+
+    if !query.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "delete_lacisoath_provider".to_string(),
+            target: format!("{} ({})", provider.display_name, id),
+            warning: "Users of this provider can no longer log in. \
+                      Existing sessions stay valid until they expire."
+                .to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    state.app_state.mysql.delete_lacisoath_provider(&id).await?;
+
+    let old_json = serde_json::to_string(&provider.masked()).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "lacisoath_provider",
+            None,
+            "delete",
+            Some(&id),
+            Some(&old_json),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    tracing::info!("LacisOath provider {} deleted by {}", id, user.sub);
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
 // ============================================================================
 // Helper functions
 // ============================================================================
 
-/// Create a session JWT and format as Set-Cookie header value
+/// Create a session JWT and format as Set-Cookie header value.
+/// `floors` is recorded in the session so later lowering is not retroactive.
 fn create_session_cookie(
     user: &AuthUser,
     auth: &crate::config::AuthConfig,
+    keys: &JwtKeys,
+    floors: &PermissionFloors,
 ) -> Result<String, AppError> {
     let exp = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::hours(auth.session_duration_hours as i64))
@@ -269,13 +691,15 @@ fn create_session_cookie(
         lacis_id: user.lacis_id.clone(),
         permission: user.permission,
         auth_method: user.auth_method.clone(),
+        auth_provider: user.auth_provider.clone(),
         exp,
+        floors: Some(*floors),
     };
 
     let token = encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(auth.jwt_secret.as_bytes()),
+        &EncodingKey::from_secret(keys.signing_secret().as_bytes()),
     )
     .map_err(|e| AppError::InternalError(format!("JWT encode error: {}", e)))?;
 
@@ -287,6 +711,108 @@ fn create_session_cookie(
     ))
 }
 
+/// Every provider: the `[auth]` client first, then the stored ones
+async fn all_providers(state: &ProxyState) -> Result<Vec<LacisOathProvider>, AppError> {
+    let mut providers: Vec<LacisOathProvider> = LacisOathProvider::from_config(&state.auth_config)
+        .into_iter()
+        .collect();
+    providers.extend(state.app_state.mysql.list_lacisoath_providers().await?);
+    Ok(providers)
+}
+
+/// The requested enabled provider, or the first enabled one
+fn select_provider<'a>(
+    providers: &'a [LacisOathProvider],
+    requested: Option<&str>,
+) -> Result<&'a LacisOathProvider, String> {
+    let mut enabled = providers.iter().filter(|p| p.enabled);
+    match requested.map(str::trim).filter(|id| !id.is_empty()) {
+        Some(id) => enabled
+            .find(|p| p.provider_id == id)
+            .ok_or_else(|| format!("Unknown identity provider: {}", id)),
+        None => enabled
+            .next()
+            .ok_or_else(|| "LacisOath is not configured".to_string()),
+    }
+}
+
+/// Permission (login floor and provider minimum), facility and tenant checks
+fn check_provider_user(
+    provider: &LacisOathProvider,
+    user_info: &LacisOathUserInfo,
+    login_floor: i32,
+) -> Result<(), String> {
+    let required = provider
+        .required_permission
+        .map_or(login_floor, |p| p.max(login_floor));
+    if user_info.permission < required {
+        return Err(format!(
+            "Insufficient permission: {} (required: {})",
+            user_info.permission, required
+        ));
+    }
+
+    let has_fid = user_info
+        .fid
+        .iter()
+        .any(|f| f == &provider.required_fid || f == "0000");
+    if !has_fid {
+        return Err("Access not authorized for this facility".to_string());
+    }
+
+    if let Some(tid) = &provider.expected_tid {
+        if user_info.tid.as_deref() != Some(tid.as_str()) {
+            return Err("Access not authorized for this tenant".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn validate_provider_id(id: &str) -> Result<(), String> {
+    if id.is_empty()
+        || id.len() > 50
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err("provider_id must be 1-50 of a-z, 0-9, '-', '_'".to_string());
+    }
+    if id == CONFIG_LACISOATH_PROVIDER {
+        return Err(format!(
+            "provider_id '{}' is reserved for the config file provider",
+            id
+        ));
+    }
+    Ok(())
+}
+
+fn validate_provider(provider: &LacisOathProvider) -> Result<(), String> {
+    if provider.display_name.is_empty() {
+        return Err("display_name is required".to_string());
+    }
+    if provider.client_id.is_empty() || provider.client_secret.is_empty() {
+        return Err("client_id and client_secret are required".to_string());
+    }
+    for (field, url) in [
+        ("auth_url", &provider.auth_url),
+        ("token_url", &provider.token_url),
+        ("redirect_uri", &provider.redirect_uri),
+    ] {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(format!("{} must be an http(s) URL", field));
+        }
+    }
+    if let Some(p) = provider.required_permission {
+        if !(0..=100).contains(&p) {
+            return Err("required_permission must be between 0 and 100".to_string());
+        }
+    }
+    if provider.required_fid.is_empty() {
+        return Err("required_fid is required".to_string());
+    }
+    Ok(())
+}
+
 // ============================================================================
 // OAuth 2.0 Token Exchange Response Types
 // ============================================================================
@@ -314,4 +840,92 @@ struct LacisOathUserInfo {
     lacis_id: String,
     permission: i32,
     fid: Vec<String>,
+    /// Tenant of the user (checked against the provider's expected_tid)
+    #[serde(default)]
+    tid: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(id: &str, enabled: bool) -> LacisOathProvider {
+        LacisOathProvider {
+            provider_id: id.to_string(),
+            display_name: id.to_string(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            auth_url: "https://auth.example/login".to_string(),
+            token_url: "https://auth.example/token".to_string(),
+            redirect_uri: "https://lpg.example/login".to_string(),
+            expected_tid: None,
+            required_permission: None,
+            required_fid: "9966".to_string(),
+            enabled,
+            from_config: false,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn user(permission: i32, fid: &[&str], tid: Option<&str>) -> LacisOathUserInfo {
+        LacisOathUserInfo {
+            lacis_id: "user".to_string(),
+            permission,
+            fid: fid.iter().map(|f| f.to_string()).collect(),
+            tid: tid.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn selects_requested_or_first_enabled_provider() {
+        let providers = vec![
+            provider("default", false),
+            provider("staging", true),
+            provider("production", true),
+        ];
+        assert_eq!(
+            select_provider(&providers, None).unwrap().provider_id,
+            "staging"
+        );
+        assert_eq!(
+            select_provider(&providers, Some("production"))
+                .unwrap()
+                .provider_id,
+            "production"
+        );
+        assert!(select_provider(&providers, Some("default")).is_err());
+        assert!(select_provider(&providers, Some("missing")).is_err());
+        assert!(select_provider(&[], None).is_err());
+    }
+
+    #[test]
+    fn checks_user_against_provider_requirements() {
+        let mut p = provider("staging", true);
+        assert!(check_provider_user(&p, &user(80, &["9966"], None), 80).is_ok());
+        assert!(check_provider_user(&p, &user(70, &["9966"], None), 80).is_err());
+        assert!(check_provider_user(&p, &user(80, &["0000"], None), 80).is_ok());
+        assert!(check_provider_user(&p, &user(80, &["1234"], None), 80).is_err());
+
+        // The provider minimum only ever raises the login floor
+        p.required_permission = Some(90);
+        assert!(check_provider_user(&p, &user(80, &["9966"], None), 80).is_err());
+        p.required_permission = Some(50);
+        assert!(check_provider_user(&p, &user(70, &["9966"], None), 80).is_err());
+
+        p.required_permission = None;
+        p.expected_tid = Some("T1".to_string());
+        assert!(check_provider_user(&p, &user(80, &["9966"], Some("T1")), 80).is_ok());
+        assert!(check_provider_user(&p, &user(80, &["9966"], Some("T2")), 80).is_err());
+        assert!(check_provider_user(&p, &user(80, &["9966"], None), 80).is_err());
+    }
+
+    #[test]
+    fn provider_ids_are_slugs_and_default_is_reserved() {
+        assert!(validate_provider_id("staging-2").is_ok());
+        assert!(validate_provider_id("Staging").is_err());
+        assert!(validate_provider_id("").is_err());
+        assert!(validate_provider_id(CONFIG_LACISOATH_PROVIDER).is_err());
+        assert!(provider("x", true).masked().client_secret == "********");
+    }
 }
@@ -0,0 +1,78 @@
+//! Cluster (leader election) handlers
+
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::{AuthUser, ConfirmRequired};
+use crate::proxy::ProxyState;
+
+/// Body for POST /api/admin/cluster/failover
+#[derive(Debug, Deserialize, Default)]
+pub struct ClusterFailoverRequest {
+    #[serde(default)]
+    pub confirm: bool,
+    /// How long this instance stays out of the election (default 3x lease TTL)
+    pub standdown_secs: Option<u64>,
+}
+
+/// GET /api/admin/cluster - Current leader, this instance's role and heartbeat
+/// (admin: permission >= 80)
+pub async fn get_cluster_status(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    Ok(Json(state.cluster.status().await))
+}
+
+/// POST /api/admin/cluster/failover - Hand leadership to another instance
+/// (dangerous: permission == 100, confirm required)
+pub async fn force_cluster_failover(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    body: Option<Json<ClusterFailoverRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+
+    if !req.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "cluster_failover".to_string(),
+            target: "cluster leadership".to_string(),
+            warning: "This instance stops DDNS, syncers, health checks and the restart scheduler until another instance takes over the lease.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    let standdown = req
+        .standdown_secs
+        .map(std::time::Duration::from_secs)
+        .unwrap_or_else(|| state.cluster.default_standdown());
+
+    state
+        .cluster
+        .force_failover(standdown)
+        .map_err(AppError::BadRequest)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "cluster",
+            None,
+            "failover",
+            None,
+            None,
+            Some(&format!("stand down {}s", standdown.as_secs())),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Failover initiated",
+        "standdown_secs": standdown.as_secs(),
+    })))
+}
@@ -1,21 +1,39 @@
 //! Dashboard handlers
 
 use axum::{
-    extract::{ConnectInfo, Query, State},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, Query, State},
     http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
-    Json,
+    Extension, Json,
 };
-use chrono::Utc;
+use chrono::{Timelike, Utc};
+use futures::{future, stream, StreamExt, TryStreamExt};
+use mongodb::bson;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
-use crate::api::admin_guard::extract_client_ip;
-use crate::error::AppError;
-use crate::models::{AccessLogSearchQuery, DashboardStats, RouteHealth};
+use crate::api::auth_middleware::require_permission;
+use crate::db::MongoDb;
+use crate::error::{AppError, ErrorCode};
+use crate::health::dependencies::DependencyHealth;
+use crate::maintenance;
+use crate::models::{
+    AccessLog, AccessLogDeleteFilter, AccessLogDeleteJob, AccessLogSearchQuery, AcmeCertificate,
+    AuthUser, ConfirmQuery, ConfirmRequired, DashboardDataSources, DashboardStats,
+    DataSourceStatus, HealthCheck, HourlyComparisonBucket, HourlyStat, HourlyStatsComparison,
+    PeriodDeltas, PeriodTotals, RouteHealth, StatsComparison,
+};
+use crate::proxy::failover::{self, TargetHealth};
+use crate::proxy::log_fields::is_valid_field_name;
+use crate::proxy::tunnel::RouteTunnelSummary;
+use crate::proxy::upstream::RouteUpstreamStatus;
 use crate::proxy::ProxyState;
+use crate::sysmetrics::{self, HistorySample, LoadAverages, ProcessStats};
+use crate::tls::CertificateStatus;
 
 use super::security::PaginationQuery;
+use super::SuccessResponse;
 
 /// GET /api/my-ip - Get the client's IP address, server's global IP, and IP history
 ///
@@ -26,7 +44,7 @@ pub async fn get_my_ip(
     headers: HeaderMap,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
 ) -> impl IntoResponse {
-    let ip = extract_client_ip(&headers, addr);
+    let ip = state.network_policy.load_policy().client_ip(&headers, addr);
 
     // サーバーのグローバルIPをDDNS last_ipから動的に取得（ハードコード禁止）
     let server_ip = state
@@ -75,6 +93,20 @@ pub async fn get_my_ip(
 pub struct DashboardStatsQuery {
     pub exclude_ips: Option<String>,
     pub exclude_lan: Option<bool>,
+    /// "previous" adds the same metrics for the preceding equivalent window
+    pub compare: Option<String>,
+}
+
+/// Parse the `compare` parameter; only "previous" is supported
+fn wants_previous(compare: &Option<String>) -> Result<bool, AppError> {
+    match compare.as_deref() {
+        None | Some("") => Ok(false),
+        Some("previous") => Ok(true),
+        Some(other) => Err(AppError::BadRequest(format!(
+            "Unsupported compare value '{}' (expected 'previous')",
+            other
+        ))),
+    }
 }
 
 /// Dashboard pagination query with IP exclusion parameters
@@ -88,52 +120,285 @@ pub struct DashboardPaginationQuery {
     pub exclude_lan: Option<bool>,
 }
 
+/// Which database a dashboard input comes from
+#[derive(Clone, Copy)]
+enum DataSource {
+    Mysql,
+    Mongo,
+}
+
+/// Inputs that could not be read, by field and by database
+#[derive(Default)]
+struct ReadErrors {
+    fields: std::collections::BTreeMap<String, String>,
+    mysql: Vec<String>,
+    mongo: Vec<String>,
+}
+
+impl ReadErrors {
+    /// The value, or None with the error recorded against `field`
+    fn take<T>(
+        &mut self,
+        field: &str,
+        source: DataSource,
+        result: Result<T, AppError>,
+    ) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let message = e.to_string();
+                tracing::warn!("Dashboard: {} unavailable: {}", field, message);
+                match source {
+                    DataSource::Mysql => self.mysql.push(format!("{}: {}", field, message)),
+                    DataSource::Mongo => self.mongo.push(format!("{}: {}", field, message)),
+                }
+                self.fields.insert(field.to_string(), message);
+                None
+            }
+        }
+    }
+
+    /// Databases are pinged only when one of their queries failed
+    async fn into_sources(
+        self,
+        state: &ProxyState,
+    ) -> (
+        std::collections::BTreeMap<String, String>,
+        DashboardDataSources,
+    ) {
+        let mysql_reachable = self.mysql.is_empty() || state.app_state.mysql.ping().await.is_ok();
+        let mongo_reachable = self.mongo.is_empty() || state.app_state.mongo.ping().await.is_ok();
+        (
+            self.fields,
+            DashboardDataSources {
+                mysql: DataSourceStatus {
+                    reachable: mysql_reachable,
+                    errors: self.mysql,
+                },
+                mongo: DataSourceStatus {
+                    reachable: mongo_reachable,
+                    errors: self.mongo,
+                },
+            },
+        )
+    }
+}
+
+/// Overall health from the latest check per route. "unknown" when the
+/// checks or the route count could not be read, or when routes are active
+/// but none has been checked yet.
+fn overall_health(checks: Option<&[HealthCheck]>, active_routes: Option<u32>) -> &'static str {
+    let (Some(checks), Some(active_routes)) = (checks, active_routes) else {
+        return "unknown";
+    };
+    if checks.is_empty() && active_routes > 0 {
+        return "unknown";
+    }
+    let unhealthy_count = checks.iter().filter(|c| !c.healthy).count();
+    if unhealthy_count == 0 {
+        "healthy"
+    } else if unhealthy_count < checks.len() / 2 {
+        "degraded"
+    } else {
+        "unhealthy"
+    }
+}
+
 /// GET /api/dashboard/stats - Get dashboard statistics
 pub async fn get_dashboard_stats(
     State(state): State<ProxyState>,
     Query(query): Query<DashboardStatsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let total_requests_today = state
-        .app_state
-        .mongo
-        .get_today_request_count(&query.exclude_ips, &query.exclude_lan)
-        .await
-        .unwrap_or(0);
-    let active_routes = state
-        .app_state
-        .mysql
-        .count_active_routes()
-        .await
-        .unwrap_or(0);
-    let active_ddns = state.app_state.mysql.count_active_ddns().await.unwrap_or(0);
-    let blocked_ips = state.app_state.mysql.count_blocked_ips().await.unwrap_or(0);
+    let compare = wants_previous(&query.compare)?;
+    let mut errors = ReadErrors::default();
+    let mysql = &state.app_state.mysql;
+    let mongo = &state.app_state.mongo;
+
+    let total_requests_today = errors.take(
+        "total_requests_today",
+        DataSource::Mongo,
+        mongo
+            .get_today_request_count(&query.exclude_ips, &query.exclude_lan)
+            .await,
+    );
+    let active_routes = errors.take(
+        "active_routes",
+        DataSource::Mysql,
+        mysql.count_active_routes().await,
+    );
+    let active_ddns = errors.take(
+        "active_ddns",
+        DataSource::Mysql,
+        mysql.count_active_ddns().await,
+    );
+    let blocked_ips = errors.take(
+        "blocked_ips",
+        DataSource::Mysql,
+        mysql.count_blocked_ips().await,
+    );
 
     // Determine overall health based on latest health checks
-    let health_checks = state
-        .app_state
-        .mongo
-        .get_latest_health_status()
-        .await
-        .unwrap_or_default();
-    let unhealthy_count = health_checks.iter().filter(|c| !c.healthy).count();
-    let server_health = if unhealthy_count == 0 {
-        "healthy"
-    } else if unhealthy_count < health_checks.len() / 2 {
-        "degraded"
+    let health_checks = errors.take(
+        "server_health",
+        DataSource::Mongo,
+        mongo.get_latest_health_status().await,
+    );
+    let server_health = overall_health(health_checks.as_deref(), active_routes);
+
+    // Today so far vs the same span of yesterday
+    let comparison = if compare {
+        let now = Utc::now();
+        let today_start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        Some(
+            compare_windows(
+                &state,
+                today_start,
+                now,
+                chrono::Duration::days(1),
+                &query.exclude_ips,
+                &query.exclude_lan,
+            )
+            .await?
+            .1,
+        )
     } else {
-        "unhealthy"
+        None
     };
 
+    let (field_errors, data_sources) = errors.into_sources(&state).await;
     Ok(Json(DashboardStats {
-        total_requests_today,
-        active_routes,
-        active_ddns,
-        blocked_ips,
+        total_requests_today: total_requests_today.unwrap_or(0),
+        active_routes: active_routes.unwrap_or(0),
+        active_ddns: active_ddns.unwrap_or(0),
+        blocked_ips: blocked_ips.unwrap_or(0),
         server_health: server_health.to_string(),
         uptime_seconds: state.app_state.uptime_seconds(),
+        comparison,
+        field_errors,
+        data_sources,
+        local_dns: state.local_dns.health(),
+        default_jwt_secret: state.jwt_keys.read().await.is_default(),
     }))
 }
 
+/// Run the hourly aggregation for [from, to] and the same window shifted
+/// back by `shift`, returning both hourly series and the window totals
+async fn compare_windows(
+    state: &ProxyState,
+    from: chrono::DateTime<Utc>,
+    to: chrono::DateTime<Utc>,
+    shift: chrono::Duration,
+    exclude_ips: &Option<String>,
+    exclude_lan: &Option<bool>,
+) -> Result<((Vec<HourlyStat>, Vec<HourlyStat>), StatsComparison), AppError> {
+    let mongo = &state.app_state.mongo;
+    let current = mongo
+        .get_hourly_stats(from, to, exclude_ips, exclude_lan)
+        .await?;
+    let previous = mongo
+        .get_hourly_stats(from - shift, to - shift, exclude_ips, exclude_lan)
+        .await?;
+
+    let current_totals = summarize(&current);
+    let previous_totals = summarize(&previous);
+    let comparison = StatsComparison {
+        current_from: from,
+        current_to: to,
+        previous_from: from - shift,
+        previous_to: to - shift,
+        current: current_totals,
+        previous: previous_totals,
+        delta: deltas(&current_totals, &previous_totals),
+    };
+
+    Ok(((current, previous), comparison))
+}
+
+/// Sum hourly buckets into window totals (response time weighted by requests)
+fn summarize(stats: &[HourlyStat]) -> PeriodTotals {
+    let total_requests: u64 = stats.iter().map(|s| s.total_requests).sum();
+    let error_count = stats.iter().map(|s| s.error_count).sum();
+    let weighted: f64 = stats
+        .iter()
+        .map(|s| s.avg_response_time_ms * s.total_requests as f64)
+        .sum();
+    PeriodTotals {
+        total_requests,
+        error_count,
+        avg_response_time_ms: if total_requests > 0 {
+            weighted / total_requests as f64
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Percentage change, or None when there is no previous value to compare to
+fn pct_delta(current: f64, previous: f64) -> Option<f64> {
+    if previous == 0.0 {
+        return None;
+    }
+    Some(((current - previous) / previous * 1000.0).round() / 10.0)
+}
+
+fn deltas(current: &PeriodTotals, previous: &PeriodTotals) -> PeriodDeltas {
+    PeriodDeltas {
+        total_requests_pct: pct_delta(
+            current.total_requests as f64,
+            previous.total_requests as f64,
+        ),
+        error_count_pct: pct_delta(current.error_count as f64, previous.error_count as f64),
+        avg_response_time_pct: pct_delta(
+            current.avg_response_time_ms,
+            previous.avg_response_time_ms,
+        ),
+    }
+}
+
+fn hour_key(t: chrono::DateTime<Utc>) -> String {
+    t.format("%Y-%m-%dT%H:00:00Z").to_string()
+}
+
+/// Lay both series on the same hourly grid (missing hours are zero) so
+/// bucket i of the previous window lines up with bucket i of the current one
+fn align_hourly(
+    current: &[HourlyStat],
+    previous: &[HourlyStat],
+    from: chrono::DateTime<Utc>,
+    to: chrono::DateTime<Utc>,
+    shift: chrono::Duration,
+) -> Vec<HourlyComparisonBucket> {
+    let lookup = |stats: &[HourlyStat], key: &str| {
+        stats
+            .iter()
+            .find(|s| s.hour == key)
+            .map(|s| summarize(std::slice::from_ref(s)))
+            .unwrap_or_default()
+    };
+
+    let mut buckets = Vec::new();
+    let mut hour = from
+        .date_naive()
+        .and_hms_opt(from.hour(), 0, 0)
+        .unwrap()
+        .and_utc();
+    while hour <= to {
+        let key = hour_key(hour);
+        let previous_key = hour_key(hour - shift);
+        let cur = lookup(current, &key);
+        let prev = lookup(previous, &previous_key);
+        buckets.push(HourlyComparisonBucket {
+            hour: key,
+            previous_hour: previous_key,
+            current: cur,
+            previous: prev,
+            delta: deltas(&cur, &prev),
+        });
+        hour += chrono::Duration::hours(1);
+    }
+    buckets
+}
+
 /// GET /api/dashboard/access-log - Get recent access logs
 pub async fn get_access_log(
     State(state): State<ProxyState>,
@@ -158,32 +423,115 @@ pub async fn get_health_status(
     State(state): State<ProxyState>,
 ) -> Result<impl IntoResponse, AppError> {
     let routes = state.app_state.mysql.list_active_routes().await?;
-    let health_checks = state.app_state.mongo.get_latest_health_status().await?;
+    let (global_interval, _, _) = state
+        .app_state
+        .mysql
+        .get_health_check_settings()
+        .await
+        .unwrap_or((60, 5000, 3));
+    // Without check data every route is "unknown", not healthy
+    let health_checks = state
+        .app_state
+        .mongo
+        .get_latest_health_status()
+        .await
+        .map_err(|e| e.to_string());
+    let windows = state
+        .app_state
+        .mongo
+        .list_maintenance_windows()
+        .await
+        .unwrap_or_default();
+    let active = maintenance::active_windows(&windows, Utc::now());
 
     let mut route_health: Vec<RouteHealth> = Vec::new();
 
     for route in routes {
-        let check = health_checks.iter().find(|c| c.route_id == route.id);
-        let consecutive_failures = state
-            .app_state
-            .mongo
-            .count_consecutive_failures(route.id)
-            .await
-            .unwrap_or(0);
+        let check = health_checks
+            .as_ref()
+            .ok()
+            .and_then(|checks| checks.iter().find(|c| c.route_id == route.id));
+        let failures = match &health_checks {
+            Ok(_) => state
+                .app_state
+                .mongo
+                .count_consecutive_failures(route.id)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.clone()),
+        };
+        let status = match (check, &failures) {
+            (Some(c), _) if !c.healthy => "unhealthy",
+            (Some(_), Ok(_)) => "healthy",
+            _ => "unknown",
+        };
+
+        let in_maintenance = maintenance::covering(&route, &windows, &active)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        // grpc.health.v1 checks probe no path
+        let probe_path = route
+            .grpc_health_check()
+            .is_none()
+            .then(|| route.health_check_path().to_string());
+        let check_interval_sec = route.health_check_interval(global_interval);
 
         route_health.push(RouteHealth {
             route_id: route.id,
             path: route.path,
             target: route.target,
+            owner_name: route.owner_name,
+            owner_contact: route.owner_contact,
+            team: route.team,
             healthy: check.map(|c| c.healthy).unwrap_or(true),
+            status: status.to_string(),
             last_check: check.map(|c| c.timestamp),
-            consecutive_failures,
+            consecutive_failures: *failures.as_ref().unwrap_or(&0),
+            probe_path,
+            check_interval_sec,
+            error: failures.err(),
+            maintenance: in_maintenance,
         });
     }
 
     Ok(Json(route_health))
 }
 
+/// Dependency probe state
+#[derive(Debug, Serialize)]
+pub struct DependenciesStatus {
+    /// "healthy", "degraded" (a probe is failing) or "unknown" (no results yet)
+    pub status: String,
+    pub dependencies: Vec<DependencyHealth>,
+}
+
+/// GET /api/dashboard/dependencies - Latest probe result per dependency
+/// (databases, controllers, routers, Aranea, Discord); reads the stored
+/// results, it does not probe
+pub async fn get_dependencies_health(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let dependencies = state
+        .app_state
+        .mongo
+        .latest_dependency_health()
+        .await
+        .map_err(AppError::database)?;
+    let status = if dependencies.is_empty() {
+        "unknown"
+    } else if dependencies.iter().all(|d| d.healthy) {
+        "healthy"
+    } else {
+        "degraded"
+    };
+    Ok(Json(DependenciesStatus {
+        status: status.to_string(),
+        dependencies,
+    }))
+}
+
 /// Detailed route status with metrics
 #[derive(Debug, Serialize)]
 pub struct RouteDetailedStatus {
@@ -191,6 +539,14 @@ pub struct RouteDetailedStatus {
     pub path: String,
     pub target: String,
     pub active: bool,
+    /// Activated before the target was healthy; answering 503 until it is
+    pub warming: bool,
+    /// Answering the maintenance page; health checks are paused
+    pub maintenance_mode: bool,
+    pub maintenance_message: Option<String>,
+    pub owner_name: Option<String>,
+    pub owner_contact: Option<String>,
+    pub team: Option<String>,
     pub healthy: bool,
     pub last_check: Option<chrono::DateTime<chrono::Utc>>,
     pub consecutive_failures: u32,
@@ -200,6 +556,33 @@ pub struct RouteDetailedStatus {
     pub requests_last_hour: u64,
     pub error_rate_percent: f64,
     pub avg_response_time_ms: f64,
+    /// Proxy protections fired since startup, keyed by protection name
+    pub protection_violations: std::collections::HashMap<String, u64>,
+    pub admin_network_only: bool,
+    /// Requests answered 404 today because the source was outside the admin networks
+    pub admin_network_rejected_today: u64,
+    /// WebSocket tunnel totals since startup (None = no tunnel yet)
+    pub websocket: Option<RouteTunnelSummary>,
+    /// Upstream client, pool and timeout counters since startup
+    pub upstream: RouteUpstreamStatus,
+    /// Health of the target and of each fallback target
+    pub targets: Vec<TargetHealth>,
+}
+
+/// Today's admin_network_only rejections (routes without the flag may still
+/// have some from before it was cleared)
+async fn admin_network_rejections(state: &ProxyState, route: &crate::models::ProxyRoute) -> u64 {
+    state
+        .app_state
+        .mongo
+        .count_route_marker_today(route.id, crate::proxy::ADMIN_NETWORK_DENIED)
+        .await
+        .unwrap_or(0)
+}
+
+fn tunnel_summary(state: &ProxyState, route_id: i32) -> Option<RouteTunnelSummary> {
+    let active = state.in_flight.count_route_kind(route_id, "websocket");
+    state.tunnel_stats.for_route(route_id, active)
 }
 
 /// GET /api/routes/status - Get detailed status for all routes
@@ -208,6 +591,12 @@ pub async fn get_all_routes_status(
 ) -> Result<impl IntoResponse, AppError> {
     let routes = state.app_state.mysql.list_routes().await?;
     let health_checks = state.app_state.mongo.get_latest_health_status().await?;
+    let fallback_checks = state
+        .app_state
+        .mongo
+        .list_fallback_checks(None)
+        .await
+        .unwrap_or_default();
 
     let mut detailed_status: Vec<RouteDetailedStatus> = Vec::new();
 
@@ -233,6 +622,12 @@ pub async fn get_all_routes_status(
             path: route.path.clone(),
             target: route.target.clone(),
             active: route.active,
+            warming: state.route_warmup.is_warming(route.id),
+            maintenance_mode: route.maintenance_mode,
+            maintenance_message: route.maintenance_message.clone(),
+            owner_name: route.owner_name.clone(),
+            owner_contact: route.owner_contact.clone(),
+            team: route.team.clone(),
             healthy: check.map(|c| c.healthy).unwrap_or(true),
             last_check: check.map(|c| c.timestamp),
             consecutive_failures,
@@ -242,6 +637,12 @@ pub async fn get_all_routes_status(
             requests_last_hour: stats.requests_last_hour,
             error_rate_percent: stats.error_rate_percent,
             avg_response_time_ms: stats.avg_response_time_ms,
+            protection_violations: state.proxy_violations.for_route(route.id),
+            admin_network_only: route.admin_network_only,
+            admin_network_rejected_today: admin_network_rejections(&state, &route).await,
+            websocket: tunnel_summary(&state, route.id),
+            upstream: state.upstream.for_route(&route),
+            targets: failover::target_health(&route, check, consecutive_failures, &fallback_checks),
         });
     }
 
@@ -253,12 +654,9 @@ pub async fn get_route_status(
     State(state): State<ProxyState>,
     axum::extract::Path(id): axum::extract::Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
-    let route = state
-        .app_state
-        .mysql
-        .get_route(id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Route with id {} not found", id)))?;
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
     let health_checks = state.app_state.mongo.get_latest_health_status().await?;
     let check = health_checks.iter().find(|c| c.route_id == route.id);
     let consecutive_failures = state
@@ -267,6 +665,12 @@ pub async fn get_route_status(
         .count_consecutive_failures(route.id)
         .await
         .unwrap_or(0);
+    let fallback_checks = state
+        .app_state
+        .mongo
+        .list_fallback_checks(Some(route.id))
+        .await
+        .unwrap_or_default();
 
     let stats = state
         .app_state
@@ -280,6 +684,12 @@ pub async fn get_route_status(
         path: route.path.clone(),
         target: route.target.clone(),
         active: route.active,
+        warming: state.route_warmup.is_warming(route.id),
+        maintenance_mode: route.maintenance_mode,
+        maintenance_message: route.maintenance_message.clone(),
+        owner_name: route.owner_name.clone(),
+        owner_contact: route.owner_contact.clone(),
+        team: route.team.clone(),
         healthy: check.map(|c| c.healthy).unwrap_or(true),
         last_check: check.map(|c| c.timestamp),
         consecutive_failures,
@@ -289,6 +699,12 @@ pub async fn get_route_status(
         requests_last_hour: stats.requests_last_hour,
         error_rate_percent: stats.error_rate_percent,
         avg_response_time_ms: stats.avg_response_time_ms,
+        protection_violations: state.proxy_violations.for_route(route.id),
+        admin_network_only: route.admin_network_only,
+        admin_network_rejected_today: admin_network_rejections(&state, &route).await,
+        websocket: tunnel_summary(&state, route.id),
+        upstream: state.upstream.for_route(&route),
+        targets: failover::target_health(&route, check, consecutive_failures, &fallback_checks),
     };
 
     Ok(Json(detailed_status))
@@ -300,12 +716,9 @@ pub async fn get_route_logs(
     axum::extract::Path(id): axum::extract::Path<i32>,
     Query(pagination): Query<PaginationQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let route = state
-        .app_state
-        .mysql
-        .get_route(id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Route with id {} not found", id)))?;
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
     let logs = state
         .app_state
         .mongo
@@ -349,9 +762,15 @@ pub struct LogFilterQuery {
 }
 
 /// SSL Certificate Status
+///
+/// The top-level fields describe the default certificate of the built-in
+/// HTTPS listener (the first one loaded); `certificates` lists all of them
+/// and `acme` the orders of DDNS hostnames (see `crate::acme`).
 #[derive(Debug, Serialize)]
 pub struct SslStatus {
     pub enabled: bool,
+    /// HTTPS port when `[server.tls]` is enabled
+    pub port: Option<u16>,
     pub domain: Option<String>,
     pub issuer: Option<String>,
     pub valid_from: Option<String>,
@@ -360,6 +779,8 @@ pub struct SslStatus {
     pub auto_renew: bool,
     pub last_renewal: Option<String>,
     pub next_renewal_attempt: Option<String>,
+    pub certificates: Vec<CertificateStatus>,
+    pub acme: Vec<AcmeCertificate>,
 }
 
 fn default_limit() -> i64 {
@@ -396,99 +817,28 @@ pub async fn get_filtered_access_log(
     Ok(Json(logs))
 }
 
-/// GET /api/dashboard/ssl-status - Get SSL certificate status
-pub async fn get_ssl_status() -> impl IntoResponse {
-    let cert_path = "/etc/letsencrypt/live/akbdevs.dnsalias.com/fullchain.pem";
-    let renewal_conf_path = "/etc/letsencrypt/renewal/akbdevs.dnsalias.com.conf";
-
-    // Check if SSL is configured
-    let ssl_enabled = std::path::Path::new(cert_path).exists();
-
-    if !ssl_enabled {
-        return Json(SslStatus {
-            enabled: false,
-            domain: None,
-            issuer: None,
-            valid_from: None,
-            valid_until: None,
-            days_remaining: None,
-            auto_renew: false,
-            last_renewal: None,
-            next_renewal_attempt: None,
-        });
-    }
-
-    // Parse certificate using openssl command
-    let cert_info = std::process::Command::new("openssl")
-        .args([
-            "x509", "-in", cert_path, "-noout", "-dates", "-issuer", "-subject",
-        ])
-        .output();
-
-    let mut domain = Some("akbdevs.dnsalias.com".to_string());
-    let mut issuer = None;
-    let mut valid_from = None;
-    let mut valid_until = None;
-    let mut days_remaining = None;
-
-    if let Ok(output) = cert_info {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            if line.starts_with("notBefore=") {
-                valid_from = Some(line.replace("notBefore=", ""));
-            } else if line.starts_with("notAfter=") {
-                valid_until = Some(line.replace("notAfter=", ""));
-            } else if line.starts_with("issuer=") {
-                // Extract CN from issuer
-                if let Some(cn_part) = line.split("CN = ").nth(1) {
-                    issuer = Some(cn_part.split(',').next().unwrap_or(cn_part).to_string());
-                }
-            } else if line.starts_with("subject=") {
-                // Extract CN from subject for domain
-                if let Some(cn_part) = line.split("CN = ").nth(1) {
-                    domain = Some(cn_part.split(',').next().unwrap_or(cn_part).to_string());
-                }
-            }
-        }
-    }
-
-    // Calculate days remaining
-    if let Some(ref until) = valid_until {
-        let end_date = std::process::Command::new("date")
-            .args(["-d", until, "+%s"])
-            .output();
-        let now = std::process::Command::new("date").args(["+%s"]).output();
-
-        if let (Ok(end_out), Ok(now_out)) = (end_date, now) {
-            let end_secs: i64 = String::from_utf8_lossy(&end_out.stdout)
-                .trim()
-                .parse()
-                .unwrap_or(0);
-            let now_secs: i64 = String::from_utf8_lossy(&now_out.stdout)
-                .trim()
-                .parse()
-                .unwrap_or(0);
-            if end_secs > 0 && now_secs > 0 {
-                days_remaining = Some((end_secs - now_secs) / 86400);
-            }
-        }
-    }
-
-    // Check auto-renew status (certbot timer)
-    let auto_renew = std::process::Command::new("systemctl")
-        .args(["is-active", "certbot.timer"])
-        .output()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "active")
-        .unwrap_or(false);
-
-    // Get last renewal from renewal conf modification time
-    let last_renewal = std::fs::metadata(renewal_conf_path)
-        .ok()
-        .and_then(|m| m.modified().ok())
-        .map(|t| {
-            let datetime: chrono::DateTime<chrono::Utc> = t.into();
-            datetime.format("%Y-%m-%d %H:%M:%S UTC").to_string()
-        });
+/// GET /api/dashboard/ssl-status - Certificates of the HTTPS listener, as
+/// read from their files
+pub async fn get_ssl_status(State(state): State<ProxyState>) -> impl IntoResponse {
+    let certificates = state.tls.status();
+    let default = certificates
+        .iter()
+        .find(|c| c.certificate.is_some())
+        .or(certificates.first());
+    let info = default.and_then(|c| c.certificate.as_ref());
+    let format_time =
+        |t: chrono::DateTime<chrono::Utc>| t.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+
+    // ACME certificates renew themselves; file certificates need certbot
+    let auto_renew = state.tls.is_enabled()
+        && (default.is_some_and(|c| c.source == "acme")
+            || std::process::Command::new("systemctl")
+                .args(["is-active", "certbot.timer"])
+                .output()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "active")
+                .unwrap_or(false));
+
+    let days_remaining = default.and_then(|c| c.days_remaining);
 
     // Next renewal attempt (certbot renews 30 days before expiry)
     let next_renewal_attempt = days_remaining.map(|days| {
@@ -499,16 +849,38 @@ pub async fn get_ssl_status() -> impl IntoResponse {
         }
     });
 
+    let acme = state
+        .app_state
+        .mysql
+        .list_acme_certificates()
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("ACME certificates not listed: {}", e);
+            Vec::new()
+        });
+
     Json(SslStatus {
-        enabled: true,
-        domain,
-        issuer,
-        valid_from,
-        valid_until,
+        enabled: state.tls.is_enabled(),
+        port: state.tls.is_enabled().then(|| state.tls.port()),
+        domain: info.and_then(|i| {
+            i.common_name
+                .clone()
+                .or_else(|| i.dns_names.first().cloned())
+        }),
+        issuer: info.map(|i| {
+            i.issuer_common_name
+                .clone()
+                .unwrap_or_else(|| i.issuer.clone())
+        }),
+        valid_from: info.map(|i| format_time(i.not_before)),
+        valid_until: info.map(|i| format_time(i.not_after)),
         days_remaining,
         auto_renew,
-        last_renewal,
+        // The served certificate file's modification time (ACME issue time)
+        last_renewal: default.and_then(|c| c.modified_at).map(format_time),
         next_renewal_attempt,
+        certificates,
+        acme,
     })
 }
 
@@ -520,20 +892,23 @@ pub struct ServerHealth {
     pub kernel: String,
     pub uptime: String,
     pub uptime_seconds: u64,
-    pub load_average: LoadAverage,
+    pub load_average: LoadAverages,
     pub cpu: CpuInfo,
     pub memory: MemoryInfo,
     pub swap: SwapInfo,
     pub disk: Vec<DiskInfo>,
+    /// Usage percentage at which a mount is flagged `over_threshold`
+    pub disk_warn_percent: f64,
     pub network: NetworkInfo,
     pub processes: ProcessInfo,
-}
-
-#[derive(Debug, Serialize)]
-pub struct LoadAverage {
-    pub one_min: f64,
-    pub five_min: f64,
-    pub fifteen_min: f64,
+    /// The LPG process itself
+    pub lpg_process: ProcessStats,
+    pub sampled_at: String,
+    /// Window CPU and throughput figures were averaged over
+    pub sample_window_secs: f64,
+    /// Last hour at 30s resolution (only with `?history=true`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<HistorySample>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -541,6 +916,7 @@ pub struct CpuInfo {
     pub model: String,
     pub cores: u32,
     pub usage_percent: f64,
+    pub per_core_percent: Vec<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -549,6 +925,8 @@ pub struct MemoryInfo {
     pub used_mb: u64,
     pub free_mb: u64,
     pub available_mb: u64,
+    pub buffers_mb: u64,
+    pub cached_mb: u64,
     pub usage_percent: f64,
 }
 
@@ -563,11 +941,13 @@ pub struct SwapInfo {
 #[derive(Debug, Serialize)]
 pub struct DiskInfo {
     pub mount_point: String,
+    pub device: String,
     pub filesystem: String,
     pub total_gb: f64,
     pub used_gb: f64,
     pub free_gb: f64,
     pub usage_percent: f64,
+    pub over_threshold: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -582,6 +962,8 @@ pub struct NetworkInterface {
     pub ip: Option<String>,
     pub rx_bytes: u64,
     pub tx_bytes: u64,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -591,6 +973,11 @@ pub struct ProcessInfo {
     pub sleeping: u32,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ServerHealthQuery {
+    pub history: Option<bool>,
+}
+
 fn run_command(cmd: &str, args: &[&str]) -> String {
     std::process::Command::new(cmd)
         .args(args)
@@ -599,184 +986,25 @@ fn run_command(cmd: &str, args: &[&str]) -> String {
         .unwrap_or_default()
 }
 
-fn parse_meminfo() -> (MemoryInfo, SwapInfo) {
-    let content = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
-    let mut mem_total = 0u64;
-    let mut mem_free = 0u64;
-    let mut mem_available = 0u64;
-    let mut buffers = 0u64;
-    let mut cached = 0u64;
-    let mut swap_total = 0u64;
-    let mut swap_free = 0u64;
-
-    for line in content.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            let value: u64 = parts[1].parse().unwrap_or(0);
-            match parts[0] {
-                "MemTotal:" => mem_total = value,
-                "MemFree:" => mem_free = value,
-                "MemAvailable:" => mem_available = value,
-                "Buffers:" => buffers = value,
-                "Cached:" => cached = value,
-                "SwapTotal:" => swap_total = value,
-                "SwapFree:" => swap_free = value,
-                _ => {}
-            }
-        }
-    }
-
-    let mem_used = mem_total.saturating_sub(mem_free + buffers + cached);
-    let swap_used = swap_total.saturating_sub(swap_free);
-
-    let memory = MemoryInfo {
-        total_mb: mem_total / 1024,
-        used_mb: mem_used / 1024,
-        free_mb: mem_free / 1024,
-        available_mb: mem_available / 1024,
-        usage_percent: if mem_total > 0 {
-            (mem_used as f64 / mem_total as f64) * 100.0
-        } else {
-            0.0
-        },
-    };
-
-    let swap = SwapInfo {
-        total_mb: swap_total / 1024,
-        used_mb: swap_used / 1024,
-        free_mb: swap_free / 1024,
-        usage_percent: if swap_total > 0 {
-            (swap_used as f64 / swap_total as f64) * 100.0
-        } else {
-            0.0
-        },
-    };
-
-    (memory, swap)
-}
-
-fn read_proc_stat() -> (u64, u64) {
-    let content = std::fs::read_to_string("/proc/stat").unwrap_or_default();
-    if let Some(line) = content.lines().next() {
-        let parts: Vec<u64> = line
-            .split_whitespace()
-            .skip(1)
-            .filter_map(|s| s.parse().ok())
-            .collect();
-        if parts.len() >= 4 {
-            let idle = parts[3] + parts.get(4).copied().unwrap_or(0); // idle + iowait
-            let total: u64 = parts.iter().sum();
-            return (idle, total);
-        }
-    }
-    (0, 0)
+fn bytes_to_gb(bytes: u64) -> f64 {
+    (bytes as f64 / 1_073_741_824.0 * 10.0).round() / 10.0
 }
 
-async fn get_cpu_usage() -> f64 {
-    // Read /proc/stat twice with 500ms interval for stable measurement
-    let (idle1, total1) = read_proc_stat();
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-    let (idle2, total2) = read_proc_stat();
-
-    let idle_delta = idle2.saturating_sub(idle1);
-    let total_delta = total2.saturating_sub(total1);
-
-    if total_delta > 0 {
-        ((total_delta - idle_delta) as f64 / total_delta as f64) * 100.0
-    } else {
-        0.0
-    }
-}
-
-fn get_disk_info() -> Vec<DiskInfo> {
-    let output = run_command(
-        "df",
-        &["-BG", "--output=target,fstype,size,used,avail,pcent"],
-    );
-    let mut disks = Vec::new();
-
-    for line in output.lines().skip(1) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 6 {
-            let mount = parts[0];
-            // Skip virtual filesystems
-            if mount.starts_with("/dev")
-                || mount == "/"
-                || mount.starts_with("/home")
-                || mount.starts_with("/var")
-            {
-                let parse_gb = |s: &str| -> f64 { s.trim_end_matches('G').parse().unwrap_or(0.0) };
-                let parse_percent =
-                    |s: &str| -> f64 { s.trim_end_matches('%').parse().unwrap_or(0.0) };
-
-                disks.push(DiskInfo {
-                    mount_point: mount.to_string(),
-                    filesystem: parts[1].to_string(),
-                    total_gb: parse_gb(parts[2]),
-                    used_gb: parse_gb(parts[3]),
-                    free_gb: parse_gb(parts[4]),
-                    usage_percent: parse_percent(parts[5]),
-                });
-            }
-        }
-    }
-
-    // If no disks found, try simpler approach
-    if disks.is_empty() {
-        let output = run_command("df", &["-h", "/"]);
-        for line in output.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 6 {
-                disks.push(DiskInfo {
-                    mount_point: "/".to_string(),
-                    filesystem: parts[0].to_string(),
-                    total_gb: 0.0,
-                    used_gb: 0.0,
-                    free_gb: 0.0,
-                    usage_percent: parts[4].trim_end_matches('%').parse().unwrap_or(0.0),
-                });
-            }
-        }
-    }
-
-    disks
-}
-
-fn get_network_interfaces() -> Vec<NetworkInterface> {
-    let mut interfaces = Vec::new();
-    let net_dev = std::fs::read_to_string("/proc/net/dev").unwrap_or_default();
-
-    for line in net_dev.lines().skip(2) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 10 {
-            let name = parts[0].trim_end_matches(':');
-            if name != "lo" {
-                let rx_bytes: u64 = parts[1].parse().unwrap_or(0);
-                let tx_bytes: u64 = parts[9].parse().unwrap_or(0);
-
-                // Get IP address
-                let ip_output = run_command("ip", &["-4", "addr", "show", name]);
-                let ip = ip_output
-                    .lines()
-                    .find(|l| l.contains("inet "))
-                    .and_then(|l| l.split_whitespace().nth(1))
-                    .map(|s| s.split('/').next().unwrap_or(s).to_string());
-
-                interfaces.push(NetworkInterface {
-                    name: name.to_string(),
-                    ip,
-                    rx_bytes,
-                    tx_bytes,
-                });
-            }
-        }
-    }
-
-    interfaces
+fn interface_ip(name: &str) -> Option<String> {
+    run_command("ip", &["-4", "addr", "show", name])
+        .lines()
+        .find(|l| l.contains("inet "))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .map(|s| s.split('/').next().unwrap_or(s).to_string())
 }
 
 /// GET /api/dashboard/server-health - Get detailed server health metrics
-pub async fn get_server_health() -> impl IntoResponse {
+///
+/// `?history=true` adds the last hour of 30s samples from the collector.
+pub async fn get_server_health(
+    State(state): State<ProxyState>,
+    Query(query): Query<ServerHealthQuery>,
+) -> impl IntoResponse {
     // Hostname
     let hostname = run_command("hostname", &[]);
 
@@ -820,18 +1048,9 @@ pub async fn get_server_health() -> impl IntoResponse {
         format!("{}m", mins)
     };
 
-    // Load average
-    let loadavg = std::fs::read_to_string("/proc/loadavg").unwrap_or_default();
-    let load_parts: Vec<f64> = loadavg
-        .split_whitespace()
-        .take(3)
-        .filter_map(|s| s.parse().ok())
-        .collect();
-    let load_average = LoadAverage {
-        one_min: load_parts.first().copied().unwrap_or(0.0),
-        five_min: load_parts.get(1).copied().unwrap_or(0.0),
-        fifteen_min: load_parts.get(2).copied().unwrap_or(0.0),
-    };
+    // Shared collector: CPU, load, memory, disk, network throughput, LPG process
+    let disk_warn_percent = sysmetrics::disk_warn_percent(&state.app_state.mysql).await;
+    let snapshot = state.system_metrics.current(disk_warn_percent).await;
 
     // CPU info
     let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
@@ -840,26 +1059,61 @@ pub async fn get_server_health() -> impl IntoResponse {
         .find(|l| l.starts_with("model name"))
         .map(|l| l.split(':').nth(1).unwrap_or("").trim().to_string())
         .unwrap_or_else(|| "Unknown".to_string());
-    let cpu_cores = cpuinfo
-        .lines()
-        .filter(|l| l.starts_with("processor"))
-        .count() as u32;
-    let cpu_usage = get_cpu_usage().await;
 
     let cpu = CpuInfo {
         model: cpu_model,
-        cores: cpu_cores,
-        usage_percent: (cpu_usage * 10.0).round() / 10.0,
+        cores: snapshot.cpu.per_core_percent.len() as u32,
+        usage_percent: snapshot.cpu.usage_percent,
+        per_core_percent: snapshot.cpu.per_core_percent,
     };
 
     // Memory and Swap
-    let (memory, swap) = parse_meminfo();
+    let mem = snapshot.memory;
+    let memory = MemoryInfo {
+        total_mb: mem.total_mb,
+        used_mb: mem.used_mb,
+        free_mb: mem.free_mb,
+        available_mb: mem.available_mb,
+        buffers_mb: mem.buffers_mb,
+        cached_mb: mem.cached_mb,
+        usage_percent: mem.usage_percent,
+    };
+    let swap = SwapInfo {
+        total_mb: mem.swap_total_mb,
+        used_mb: mem.swap_used_mb,
+        free_mb: mem.swap_free_mb,
+        usage_percent: mem.swap_usage_percent,
+    };
 
     // Disk
-    let disk = get_disk_info();
+    let disk = snapshot
+        .disks
+        .into_iter()
+        .map(|d| DiskInfo {
+            mount_point: d.mount_point,
+            device: d.device,
+            filesystem: d.filesystem,
+            total_gb: bytes_to_gb(d.total_bytes),
+            used_gb: bytes_to_gb(d.used_bytes),
+            free_gb: bytes_to_gb(d.free_bytes),
+            usage_percent: d.usage_percent,
+            over_threshold: d.over_threshold,
+        })
+        .collect();
 
     // Network
-    let interfaces = get_network_interfaces();
+    let interfaces = snapshot
+        .network
+        .into_iter()
+        .map(|i| NetworkInterface {
+            ip: interface_ip(&i.name),
+            name: i.name,
+            rx_bytes: i.rx_bytes,
+            tx_bytes: i.tx_bytes,
+            rx_bps: i.rx_bps,
+            tx_bps: i.tx_bps,
+        })
+        .collect();
     let connections: u32 = run_command("ss", &["-tun"])
         .lines()
         .count()
@@ -884,19 +1138,29 @@ pub async fn get_server_health() -> impl IntoResponse {
         sleeping: total_procs.saturating_sub(running),
     };
 
+    let history = query
+        .history
+        .unwrap_or(false)
+        .then(|| state.system_metrics.history());
+
     Json(ServerHealth {
         hostname,
         os,
         kernel,
         uptime,
         uptime_seconds,
-        load_average,
+        load_average: snapshot.load,
         cpu,
         memory,
         swap,
         disk,
+        disk_warn_percent,
         network,
         processes,
+        lpg_process: snapshot.process,
+        sampled_at: snapshot.timestamp,
+        sample_window_secs: snapshot.window_secs,
+        history,
     })
 }
 
@@ -911,6 +1175,11 @@ pub struct TimeRangeQuery {
     pub limit: Option<i64>,
     pub exclude_ips: Option<String>,
     pub exclude_lan: Option<bool>,
+    /// "previous" overlays the preceding window (hourly stats only)
+    pub compare: Option<String>,
+    /// Resolve each IP's country now and add the country rule verdict
+    /// (top IPs only)
+    pub resolve_country: Option<bool>,
 }
 
 /// GET /api/dashboard/access-log/search - Advanced log search
@@ -923,6 +1192,379 @@ pub async fn search_access_log(
     Ok(Json(result))
 }
 
+/// Documents removed per delete batch
+const ACCESS_LOG_DELETE_BATCH: i64 = 2000;
+/// Pause between batches so proxy log writes are not starved
+const ACCESS_LOG_DELETE_PAUSE: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// POST /api/dashboard/access-log/delete - Bulk delete access logs by filter
+///
+/// Requires a narrowing filter (ip, path prefix, status or date bound) and
+/// `?confirm=true`. Deletion runs in the background in batches; poll the
+/// returned job id. Dashboard stats aggregate `access_logs` on read; the
+/// rollups kept from them (`ip_daily_stats`, `hostname_usage_monthly`) are
+/// recomputed for the deleted span once the job ends.
+pub async fn delete_access_logs(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(confirm): Query<ConfirmQuery>,
+    Json(filter): Json<AccessLogDeleteFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    if !filter.is_narrowed() {
+        return Err(AppError::BadRequest(
+            "Refusing unbounded delete: specify an ip, a path below /, a status range or an end date in the past"
+                .to_string(),
+        ));
+    }
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from > to {
+            return Err(AppError::BadRequest("from must be before to".to_string()));
+        }
+    }
+
+    let mongo_filter = MongoDb::build_access_log_delete_filter(&filter);
+    let matched = state
+        .app_state
+        .mongo
+        .count_access_logs_matching(&mongo_filter)
+        .await?;
+
+    if !confirm.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "delete_access_logs".to_string(),
+            target: format!("{} access log entries", matched),
+            warning: "Matching access log entries will be permanently deleted.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    let span = state
+        .app_state
+        .mongo
+        .access_log_time_span(&mongo_filter)
+        .await?;
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let job = AccessLogDeleteJob {
+        job_id: job_id.clone(),
+        status: "running".to_string(),
+        filter,
+        matched,
+        deleted: 0,
+        batches: 0,
+        started_by: user.sub.clone(),
+        started_at: Utc::now().to_rfc3339(),
+        finished_at: None,
+        error: None,
+    };
+    {
+        let mut jobs = state.access_log_delete_jobs.write().await;
+        if jobs.values().any(|j| j.status == "running") {
+            return Err(AppError::BadRequest(
+                "Another access log delete is still running".to_string(),
+            ));
+        }
+        jobs.insert(job_id.clone(), job);
+    }
+
+    tokio::spawn(run_access_log_delete(
+        state.clone(),
+        job_id.clone(),
+        mongo_filter,
+        span,
+    ));
+
+    Ok(Json(serde_json::json!({
+        "job_id": job_id,
+        "matched": matched,
+        "status": "running",
+    })))
+}
+
+/// Background loop for a bulk delete job (`span`: first and last timestamp
+/// of the matched logs)
+async fn run_access_log_delete(
+    state: ProxyState,
+    job_id: String,
+    filter: mongodb::bson::Document,
+    span: Option<(String, String)>,
+) {
+    let mut error = None;
+    loop {
+        match state
+            .app_state
+            .mongo
+            .delete_access_logs_batch(&filter, ACCESS_LOG_DELETE_BATCH)
+            .await
+        {
+            Ok(0) => break,
+            Ok(n) => {
+                if let Some(job) = state.access_log_delete_jobs.write().await.get_mut(&job_id) {
+                    job.deleted += n;
+                    job.batches += 1;
+                }
+                tokio::time::sleep(ACCESS_LOG_DELETE_PAUSE).await;
+            }
+            Err(e) => {
+                error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+
+    // Also after a failure: part of the span may be gone
+    if let Some((first, last)) = span {
+        refresh_access_log_rollups(&state, &first, &last).await;
+    }
+
+    let finished = {
+        let mut jobs = state.access_log_delete_jobs.write().await;
+        let Some(job) = jobs.get_mut(&job_id) else {
+            return;
+        };
+        job.status = if error.is_some() {
+            "failed"
+        } else {
+            "completed"
+        }
+        .to_string();
+        job.finished_at = Some(Utc::now().to_rfc3339());
+        job.error = error;
+        job.clone()
+    };
+
+    tracing::info!(
+        "Access log delete {} {}: {} deleted in {} batches",
+        finished.job_id,
+        finished.status,
+        finished.deleted,
+        finished.batches
+    );
+    let filter_json = serde_json::to_string(&finished.filter).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "access_log",
+            None,
+            "bulk_delete",
+            Some("filter"),
+            Some(&filter_json),
+            Some(&format!(
+                "{} deleted ({})",
+                finished.deleted, finished.status
+            )),
+            &finished.started_by,
+            None,
+        )
+        .await;
+}
+
+/// Recompute the rollups covering the days of `first..=last` (RFC 3339
+/// timestamps) after their access logs were deleted
+async fn refresh_access_log_rollups(state: &ProxyState, first: &str, last: &str) {
+    let day = |ts: &str| chrono::NaiveDate::parse_from_str(ts.get(..10)?, "%Y-%m-%d").ok();
+    let (Some(first), Some(last)) = (day(first), day(last)) else {
+        return;
+    };
+    match crate::ip_stats::recompute_days(&state.app_state, first, last).await {
+        Ok(days) => tracing::info!("Recomputed {} days of IP daily stats", days),
+        Err(e) => tracing::warn!("IP daily stats recompute after delete failed: {}", e),
+    }
+    match crate::hostname_usage::recompute_months(&state.app_state, first, last).await {
+        Ok(months) => tracing::info!("Recomputed {} months of hostname usage", months),
+        Err(e) => tracing::warn!("Hostname usage recompute after delete failed: {}", e),
+    }
+}
+
+/// GET /api/dashboard/access-log/delete/:job_id - Bulk delete progress
+pub async fn get_access_log_delete_job(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let job = state
+        .access_log_delete_jobs
+        .read()
+        .await
+        .get(&job_id)
+        .cloned()
+        .ok_or_else(|| AppError::NotFound(format!("Delete job {} not found", job_id)))?;
+
+    Ok(Json(job))
+}
+
+/// Max entries returned by GET /api/dashboard/in-flight
+const IN_FLIGHT_LIST_CAP: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct InFlightQuery {
+    pub limit: Option<usize>,
+}
+
+/// GET /api/dashboard/in-flight - Requests and tunnels currently proxied (oldest first)
+pub async fn list_in_flight(
+    State(state): State<ProxyState>,
+    Query(query): Query<InFlightQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(100).clamp(1, IN_FLIGHT_LIST_CAP);
+    let requests = state.in_flight.list(limit);
+
+    Json(serde_json::json!({
+        "total": state.in_flight.count(),
+        "requests": requests,
+    }))
+}
+
+/// GET /api/dashboard/notification-queue - Outbound notification queue depth,
+/// drop counts and per-destination rate limit state
+pub async fn get_notification_queue(State(state): State<ProxyState>) -> impl IntoResponse {
+    Json(state.notifier.queue().stats())
+}
+
+/// DELETE /api/dashboard/in-flight/:id - Abort a request or tunnel (cancels the upstream call)
+pub async fn abort_in_flight(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let entry = state
+        .in_flight
+        .list(usize::MAX)
+        .into_iter()
+        .find(|r| r.id == id);
+    if !state.in_flight.abort(id) {
+        return Err(AppError::NotFound(format!(
+            "In-flight request {} not found (already finished?)",
+            id
+        )));
+    }
+
+    let description = entry
+        .map(|r| format!("{} {} {} from {}", r.kind, r.method, r.path, r.client_ip))
+        .unwrap_or_else(|| id.to_string());
+    tracing::warn!(
+        "In-flight request {} aborted by {}: {}",
+        id,
+        user.sub,
+        description
+    );
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "in_flight",
+            None,
+            "abort",
+            None,
+            Some(&description),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(SuccessResponse::new(format!(
+        "Abort signalled for in-flight request {}",
+        id
+    ))))
+}
+
+/// GET /api/dashboard/websockets - Open WebSocket sessions (oldest first)
+/// with live byte totals and the closed-session totals since startup
+pub async fn list_websockets(State(state): State<ProxyState>) -> impl IntoResponse {
+    let sessions: Vec<_> = state
+        .in_flight
+        .list(usize::MAX)
+        .into_iter()
+        .filter(|r| r.kind == "websocket")
+        .collect();
+    let (bytes_up, bytes_down) =
+        sessions
+            .iter()
+            .filter_map(|r| r.tunnel.as_ref())
+            .fold((0u64, 0u64), |(up, down), t| {
+                (
+                    up + t.bytes_client_to_upstream,
+                    down + t.bytes_upstream_to_client,
+                )
+            });
+
+    Json(serde_json::json!({
+        "active": sessions.len(),
+        "bytes_client_to_upstream": bytes_up,
+        "bytes_upstream_to_client": bytes_down,
+        "closed": state.tunnel_stats.totals(sessions.len()),
+        "sessions": sessions,
+    }))
+}
+
+/// DELETE /api/dashboard/websockets/:id - Force-close a WebSocket session
+/// (both peers get a close frame; the session is logged as aborted)
+pub async fn close_websocket(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let not_found = || {
+        AppError::NotFound(format!(
+            "WebSocket session {} not found (already closed?)",
+            id
+        ))
+    };
+    let session = state
+        .in_flight
+        .list(usize::MAX)
+        .into_iter()
+        .find(|r| r.id == id && r.kind == "websocket")
+        .ok_or_else(not_found)?;
+    if !state.in_flight.abort(id) {
+        return Err(not_found());
+    }
+
+    let description = format!(
+        "{} from {} (route {})",
+        session.path,
+        session.client_ip,
+        session
+            .route_id
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    tracing::warn!(
+        "WebSocket session {} closed by {}: {}",
+        id,
+        user.sub,
+        description
+    );
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "websocket",
+            session.route_id,
+            "close",
+            None,
+            Some(&description),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(SuccessResponse::new(format!(
+        "Close signalled for WebSocket session {}",
+        id
+    ))))
+}
+
 /// GET /api/dashboard/hourly-stats - Hourly aggregation
 pub async fn get_hourly_stats(
     State(state): State<ProxyState>,
@@ -939,13 +1581,30 @@ pub async fn get_hourly_stats(
         .and_then(|s| s.parse::<chrono::DateTime<Utc>>().ok())
         .unwrap_or_else(Utc::now);
 
+    if wants_previous(&query.compare)? {
+        // Shift by whole hours so both series share the same bucket grid
+        let span_hours = ((to - from).num_seconds().max(1) + 3599) / 3600;
+        let shift = chrono::Duration::hours(span_hours);
+        let ((current, previous), totals) = compare_windows(
+            &state,
+            from,
+            to,
+            shift,
+            &query.exclude_ips,
+            &query.exclude_lan,
+        )
+        .await?;
+        let buckets = align_hourly(&current, &previous, from, to, shift);
+        return Ok(Json(HourlyStatsComparison { buckets, totals }).into_response());
+    }
+
     let stats = state
         .app_state
         .mongo
         .get_hourly_stats(from, to, &query.exclude_ips, &query.exclude_lan)
         .await?;
 
-    Ok(Json(stats))
+    Ok(Json(stats).into_response())
 }
 
 /// GET /api/dashboard/top-ips - Top IPs by request count
@@ -965,12 +1624,34 @@ pub async fn get_top_ips(
         .unwrap_or_else(Utc::now);
     let limit = query.limit.unwrap_or(20);
 
-    let entries = state
+    let mut entries = state
         .app_state
         .mongo
         .get_top_ips(from, to, limit, &query.exclude_ips, &query.exclude_lan)
         .await?;
 
+    // As the proxy sees the IP today (logged GeoIP data may predate a
+    // database update)
+    if query.resolve_country == Some(true) {
+        for entry in &mut entries {
+            let geo = state
+                .geoip
+                .as_ref()
+                .and_then(|reader| reader.lookup(&entry.key));
+            let action = state
+                .geo_filter
+                .action(geo.as_ref().and_then(|g| g.country_code.as_deref()));
+            if let Some(geo) = geo {
+                entry.country_code = geo.country_code;
+                entry.country = geo.country;
+                entry.city = geo.city;
+                entry.latitude = geo.latitude;
+                entry.longitude = geo.longitude;
+            }
+            entry.geo_action = Some(action.as_str().to_string());
+        }
+    }
+
     Ok(Json(entries))
 }
 
@@ -1025,68 +1706,419 @@ pub async fn get_error_summary(
     Ok(Json(summary))
 }
 
-/// GET /api/dashboard/access-log/export - CSV export
+/// Values returned by the custom field summary when no limit is given
+const CUSTOM_FIELD_SUMMARY_DEFAULT_VALUES: i64 = 50;
+/// Most values the custom field summary returns (cardinality cap)
+const CUSTOM_FIELD_SUMMARY_MAX_VALUES: i64 = 500;
+/// Longest window the custom field summary aggregates over
+const CUSTOM_FIELD_SUMMARY_MAX_DAYS: i64 = 31;
+
+#[derive(Debug, Deserialize)]
+pub struct CustomFieldSummaryQuery {
+    pub route_id: i32,
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// GET /api/dashboard/custom-field-summary - Requests, error rate and
+/// latency of one route per value of a custom log field (last 24h by
+/// default, at most 31 days). Only the busiest `limit` values are listed.
+pub async fn get_custom_field_summary(
+    State(state): State<ProxyState>,
+    Query(query): Query<CustomFieldSummaryQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if !is_valid_field_name(&query.field) {
+        return Err(AppError::validation("field", "not a custom log field name"));
+    }
+    let to = query
+        .to
+        .as_deref()
+        .and_then(|s| s.parse::<chrono::DateTime<Utc>>().ok())
+        .unwrap_or_else(Utc::now);
+    let earliest = to - chrono::Duration::days(CUSTOM_FIELD_SUMMARY_MAX_DAYS);
+    let from = query
+        .from
+        .as_deref()
+        .and_then(|s| s.parse::<chrono::DateTime<Utc>>().ok())
+        .unwrap_or_else(|| to - chrono::Duration::hours(24))
+        .max(earliest);
+    let limit = query
+        .limit
+        .unwrap_or(CUSTOM_FIELD_SUMMARY_DEFAULT_VALUES)
+        .clamp(1, CUSTOM_FIELD_SUMMARY_MAX_VALUES);
+
+    let summary = state
+        .app_state
+        .mongo
+        .custom_field_summary(query.route_id, &query.field, from, to, limit)
+        .await?;
+
+    Ok(Json(summary))
+}
+
+/// GET /api/dashboard/protocol-summary - Requests by HTTP version
+pub async fn get_protocol_summary(
+    State(state): State<ProxyState>,
+    Query(query): Query<TimeRangeQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let from = query
+        .from
+        .as_deref()
+        .and_then(|s| s.parse::<chrono::DateTime<Utc>>().ok())
+        .unwrap_or_else(|| Utc::now() - chrono::Duration::hours(24));
+    let to = query
+        .to
+        .as_deref()
+        .and_then(|s| s.parse::<chrono::DateTime<Utc>>().ok())
+        .unwrap_or_else(Utc::now);
+
+    let summary = state
+        .app_state
+        .mongo
+        .get_protocol_summary(from, to, &query.exclude_ips, &query.exclude_lan)
+        .await?;
+
+    Ok(Json(summary))
+}
+
+/// Rows an export returns at most
+const EXPORT_MAX_ROWS: i64 = 1_000_000;
+/// Rows written per body chunk
+const EXPORT_CHUNK_ROWS: usize = 500;
+
+const EXPORT_CSV_HEADER: &str =
+    "timestamp,ip,method,path,status,response_time_ms,user_agent,referer,\
+     http_version,tls_version,tls_cipher,country_code,country,city,latitude,longitude\n";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// One JSON array of access log documents
+    Json,
+}
+
+/// Export options on top of the search filters
+#[derive(Debug, Deserialize)]
+pub struct AccessLogExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Row cap (missing or 0 = EXPORT_MAX_ROWS)
+    pub limit: Option<i64>,
+}
+
+/// CSV line of a log (protocol and GeoIP columns empty when unknown)
+fn csv_row(log: &AccessLog) -> String {
+    let text = |v: &Option<String>| csv_text(v.as_deref().unwrap_or(""));
+    let number = |v: Option<f64>| v.map(|n| n.to_string()).unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+        log.timestamp.to_rfc3339(),
+        csv_escape(&log.ip),
+        csv_text(&log.method),
+        csv_text(&log.path),
+        log.status,
+        log.response_time_ms,
+        text(&log.user_agent),
+        text(&log.referer),
+        text(&log.http_version),
+        text(&log.tls_version),
+        text(&log.tls_cipher),
+        text(&log.country_code),
+        text(&log.country),
+        text(&log.city),
+        number(log.latitude),
+        number(log.longitude),
+    )
+}
+
+/// Download name with the date range, e.g. access_logs_20240501_20240531.csv
+fn export_filename(
+    from: Option<chrono::DateTime<Utc>>,
+    to: Option<chrono::DateTime<Utc>>,
+    now: chrono::DateTime<Utc>,
+    format: ExportFormat,
+) -> String {
+    let day = |d: chrono::DateTime<Utc>| d.format("%Y%m%d").to_string();
+    format!(
+        "access_logs_{}_{}.{}",
+        from.map(day).unwrap_or_else(|| "start".to_string()),
+        day(to.unwrap_or(now)),
+        match format {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    )
+}
+
+/// GET /api/dashboard/access-log/export - CSV (default) or JSON export
+///
+/// Rows are streamed from a MongoDB cursor as they are read, so large ranges
+/// are never held in memory.
 pub async fn export_access_log(
     State(state): State<ProxyState>,
     Query(query): Query<AccessLogSearchQuery>,
+    Query(export): Query<AccessLogExportQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Limit to 10000 for export
-    let mut export_query = AccessLogSearchQuery {
-        from: query.from,
-        to: query.to,
-        method: query.method,
-        status_min: query.status_min,
-        status_max: query.status_max,
-        ip: query.ip,
-        path: query.path,
-        limit: query.limit.min(10000),
-        offset: 0,
-        exclude_ips: query.exclude_ips,
-        exclude_lan: query.exclude_lan,
+    let limit = match export.limit {
+        Some(n) if n > 0 => n.min(EXPORT_MAX_ROWS),
+        _ => EXPORT_MAX_ROWS,
     };
-    if export_query.limit == 0 {
-        export_query.limit = 10000;
-    }
-
-    let result = state
+    let cursor = state
         .app_state
         .mongo
-        .search_access_logs(&export_query)
+        .access_log_export_cursor(&query, limit)
         .await?;
 
-    // Build CSV
-    let mut csv =
-        String::from("timestamp,ip,method,path,status,response_time_ms,user_agent,referer\n");
-    for log in &result.logs {
-        csv.push_str(&format!(
-            "{},{},{},{},{},{},{},{}\n",
-            log.timestamp.to_rfc3339(),
-            csv_escape(&log.ip),
-            csv_escape(&log.method),
-            csv_escape(&log.path),
-            log.status,
-            log.response_time_ms,
-            csv_escape(log.user_agent.as_deref().unwrap_or("")),
-            csv_escape(log.referer.as_deref().unwrap_or("")),
-        ));
-    }
+    let format = export.format;
+    let rows = cursor
+        .try_filter_map(|doc| async move { Ok(bson::from_document::<AccessLog>(doc).ok()) })
+        .enumerate()
+        .map(move |(i, log)| {
+            log.map(|log| match format {
+                ExportFormat::Csv => csv_row(&log),
+                ExportFormat::Json => format!(
+                    "{}{}",
+                    if i == 0 { "\n" } else { ",\n" },
+                    serde_json::to_string(&log).unwrap_or_else(|_| "null".to_string())
+                ),
+            })
+        })
+        .ready_chunks(EXPORT_CHUNK_ROWS)
+        .map(|rows| {
+            rows.into_iter()
+                .collect::<Result<String, _>>()
+                .map(Bytes::from)
+        });
 
+    let (head, tail, content_type) = match format {
+        ExportFormat::Csv => (EXPORT_CSV_HEADER, "", "text/csv; charset=utf-8"),
+        ExportFormat::Json => ("[", "\n]\n", "application/json"),
+    };
+    let body = stream::once(future::ready(Ok(Bytes::from_static(head.as_bytes()))))
+        .chain(rows)
+        .chain(stream::once(future::ready(Ok(Bytes::from_static(
+            tail.as_bytes(),
+        )))));
+
+    let filename = export_filename(query.from, query.to, Utc::now(), format);
     let headers = [
-        (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+        (header::CONTENT_TYPE, content_type.to_string()),
         (
             header::CONTENT_DISPOSITION,
-            "attachment; filename=\"access_logs.csv\"",
+            format!("attachment; filename=\"{}\"", filename),
         ),
     ];
 
-    Ok((StatusCode::OK, headers, csv))
+    Ok((StatusCode::OK, headers, Body::from_stream(body)))
 }
 
-/// Escape a field for CSV (wrap in quotes if it contains comma, quote, or newline)
-fn csv_escape(s: &str) -> String {
-    if s.contains(',') || s.contains('"') || s.contains('\n') {
+/// Escape a field for CSV (wrap in quotes if it contains comma, quote, or line break)
+pub(crate) fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
         format!("\"{}\"", s.replace('"', "\"\""))
     } else {
         s.to_string()
     }
 }
+
+/// `csv_escape` for text a client controls (path, User-Agent, Referer): a
+/// cell a spreadsheet would evaluate as a formula gets a leading `'`, quoted
+fn csv_text(s: &str) -> String {
+    if s.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("\"'{}\"", s.replace('"', "\"\""))
+    } else {
+        csv_escape(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(hour: &str, total: u64, errors: u64, avg: f64) -> HourlyStat {
+        HourlyStat {
+            hour: hour.to_string(),
+            total_requests: total,
+            error_count: errors,
+            avg_response_time_ms: avg,
+        }
+    }
+
+    #[test]
+    fn health_is_unknown_without_inputs() {
+        let check = |healthy| HealthCheck {
+            timestamp: Utc::now(),
+            route_id: 1,
+            target: "http://127.0.0.1".to_string(),
+            healthy,
+            response_time_ms: None,
+            status_code: None,
+            error: None,
+        };
+        assert_eq!(overall_health(None, Some(3)), "unknown");
+        assert_eq!(overall_health(Some(&[check(true)]), None), "unknown");
+        // Active routes nobody has checked yet
+        assert_eq!(overall_health(Some(&[]), Some(2)), "unknown");
+        assert_eq!(overall_health(Some(&[]), Some(0)), "healthy");
+        assert_eq!(overall_health(Some(&[check(true)]), Some(1)), "healthy");
+        assert_eq!(overall_health(Some(&[check(false)]), Some(1)), "unhealthy");
+    }
+
+    #[test]
+    fn delete_filter_requires_narrowing() {
+        let mut filter = AccessLogDeleteFilter {
+            method: Some("GET".to_string()),
+            ip: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.is_narrowed());
+
+        // Catch-all values bound nothing
+        filter.path = Some("/".to_string());
+        filter.status_min = Some(0);
+        filter.status_max = Some(599);
+        filter.from = Some("1970-01-01T00:00:00Z".parse().unwrap());
+        assert!(!filter.is_narrowed());
+        filter.to = Some(Utc::now() + chrono::Duration::days(1));
+        assert!(!filter.is_narrowed());
+
+        filter.to = Some(Utc::now() - chrono::Duration::days(30));
+        assert!(filter.is_narrowed());
+        filter.to = None;
+        filter.status_min = Some(500);
+        assert!(filter.is_narrowed());
+        filter.status_min = None;
+
+        filter.path = Some("/api/v1.0".to_string());
+        assert!(filter.is_narrowed());
+        let doc = MongoDb::build_access_log_delete_filter(&filter);
+        let path = doc.get_document("path").unwrap();
+        assert_eq!(path.get_str("$regex").unwrap(), r"^/api/v1\.0");
+        assert!(doc.get("ip").is_none());
+    }
+
+    #[test]
+    fn pct_delta_is_null_without_previous() {
+        assert_eq!(pct_delta(150.0, 100.0), Some(50.0));
+        assert_eq!(pct_delta(50.0, 200.0), Some(-75.0));
+        assert_eq!(pct_delta(10.0, 0.0), None);
+        assert_eq!(pct_delta(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn summarize_weights_response_time() {
+        let totals = summarize(&[
+            stat("2026-02-06T10:00:00Z", 3, 1, 10.0),
+            stat("2026-02-06T11:00:00Z", 1, 0, 50.0),
+        ]);
+        assert_eq!(totals.total_requests, 4);
+        assert_eq!(totals.error_count, 1);
+        assert_eq!(totals.avg_response_time_ms, 20.0);
+        assert_eq!(summarize(&[]).avg_response_time_ms, 0.0);
+    }
+
+    #[test]
+    fn hourly_buckets_align_across_windows() {
+        let from = "2026-02-06T10:30:00Z"
+            .parse::<chrono::DateTime<Utc>>()
+            .unwrap();
+        let to = "2026-02-06T12:10:00Z"
+            .parse::<chrono::DateTime<Utc>>()
+            .unwrap();
+        let shift = chrono::Duration::hours(2);
+        let current = vec![stat("2026-02-06T12:00:00Z", 8, 0, 5.0)];
+        let previous = vec![
+            stat("2026-02-06T08:00:00Z", 2, 0, 5.0),
+            stat("2026-02-06T10:00:00Z", 4, 2, 5.0),
+        ];
+
+        let buckets = align_hourly(&current, &previous, from, to, shift);
+        let hours: Vec<_> = buckets.iter().map(|b| b.hour.as_str()).collect();
+        assert_eq!(
+            hours,
+            [
+                "2026-02-06T10:00:00Z",
+                "2026-02-06T11:00:00Z",
+                "2026-02-06T12:00:00Z"
+            ]
+        );
+        assert_eq!(buckets[0].previous_hour, "2026-02-06T08:00:00Z");
+        assert_eq!(buckets[0].current.total_requests, 0);
+        assert_eq!(buckets[0].previous.total_requests, 2);
+        assert_eq!(buckets[0].delta.total_requests_pct, Some(-100.0));
+        assert_eq!(buckets[1].delta.total_requests_pct, None);
+        assert_eq!(buckets[2].delta.total_requests_pct, Some(100.0));
+        assert_eq!(buckets[2].delta.error_count_pct, Some(-100.0));
+    }
+
+    #[test]
+    fn csv_rows_escape_fields_and_include_geoip() {
+        let log: AccessLog = serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-05-01T10:00:00Z",
+            "ip": "203.0.113.7",
+            "method": "GET",
+            "path": "/a,b",
+            "route_id": null,
+            "target": null,
+            "status": 200,
+            "response_time_ms": 12,
+            "request_size": null,
+            "response_size": null,
+            "user_agent": "say \"hi\"\r\n",
+            "referer": null,
+            "country_code": "JP",
+            "city": "Tokyo",
+            "latitude": 35.5,
+        }))
+        .unwrap();
+        assert_eq!(
+            csv_row(&log),
+            "2024-05-01T10:00:00+00:00,203.0.113.7,GET,\"/a,b\",200,12,\"say \"\"hi\"\"\r\n\",,,,,JP,,Tokyo,35.5,\n"
+        );
+        assert_eq!(EXPORT_CSV_HEADER.split(',').count(), 16);
+    }
+
+    #[test]
+    fn csv_rows_neutralize_formulas_in_client_fields() {
+        let log: AccessLog = serde_json::from_value(serde_json::json!({
+            "timestamp": "2024-05-01T10:00:00Z",
+            "ip": "203.0.113.7",
+            "method": "GET",
+            "path": "@SUM(1+1)",
+            "route_id": null,
+            "target": null,
+            "status": 200,
+            "response_time_ms": 12,
+            "request_size": null,
+            "response_size": null,
+            "user_agent": "=HYPERLINK(\"http://evil.example\",\"x\")",
+            "referer": "-1+1",
+        }))
+        .unwrap();
+        assert_eq!(
+            csv_row(&log),
+            "2024-05-01T10:00:00+00:00,203.0.113.7,GET,\"'@SUM(1+1)\",200,12,\
+             \"'=HYPERLINK(\"\"http://evil.example\"\",\"\"x\"\")\",\"'-1+1\",,,,,,,,\n"
+        );
+        assert_eq!(csv_text("\tcmd"), "\"'\tcmd\"");
+        assert_eq!(csv_text("plain"), "plain");
+    }
+
+    #[test]
+    fn export_filename_names_the_range() {
+        let now = "2024-06-02T08:00:00Z".parse().unwrap();
+        let from = Some("2024-05-01T00:00:00Z".parse().unwrap());
+        assert_eq!(
+            export_filename(from, None, now, ExportFormat::Csv),
+            "access_logs_20240501_20240602.csv"
+        );
+        assert_eq!(
+            export_filename(None, Some(now), now, ExportFormat::Json),
+            "access_logs_start_20240602.json"
+        );
+    }
+}
@@ -1,18 +1,24 @@
 //! DDNS configuration handlers
 
+use std::net::{IpAddr, SocketAddr};
+
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
 
 use crate::api::auth_middleware::require_permission;
-use crate::error::AppError;
+use crate::ddns::failover::{validate_failover_link, MAX_FAILOVER_THRESHOLD};
+use crate::ddns::DdnsTestResult;
+use crate::error::{AppError, ErrorCode};
 use crate::models::{
-    AuthUser, ConfirmQuery, ConfirmRequired, CreateDdnsRequest, DdnsProvider, LinkOmadaRequest,
+    AuthUser, ConfirmQuery, ConfirmRequired, CreateDdnsQuery, CreateDdnsRequest, DdnsConfig,
+    DdnsIpSource, DdnsProvider, DdnsTestRequest, LinkOmadaRequest, ReportIpRequest,
     UpdateDdnsRequest,
 };
+use crate::omada::webhook;
 use crate::proxy::ProxyState;
 
 use super::SuccessResponse;
@@ -21,15 +27,7 @@ use super::SuccessResponse;
 pub async fn list_ddns(State(state): State<ProxyState>) -> Result<impl IntoResponse, AppError> {
     let configs = state.app_state.mysql.list_ddns().await?;
 
-    // Mask sensitive fields for response
-    let masked: Vec<_> = configs
-        .into_iter()
-        .map(|mut c| {
-            c.password = c.password.as_ref().map(|_| "********".to_string());
-            c.api_token = c.api_token.as_ref().map(|_| "********".to_string());
-            c
-        })
-        .collect();
+    let masked: Vec<_> = configs.into_iter().map(DdnsConfig::masked).collect();
 
     Ok(Json(masked))
 }
@@ -39,29 +37,120 @@ pub async fn get_ddns(
     State(state): State<ProxyState>,
     Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
-    let mut config = state
-        .app_state
-        .mysql
-        .get_ddns(id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("DDNS config {} not found", id)))?;
-
-    // Mask sensitive fields
-    config.password = config.password.as_ref().map(|_| "********".to_string());
-    config.api_token = config.api_token.as_ref().map(|_| "********".to_string());
+    let config = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
 
-    Ok(Json(config))
+    Ok(Json(config.masked()))
 }
 
 /// POST /api/ddns - Create a new DDNS configuration (admin: permission >= 80)
+///
+/// `?validate=true` runs the credential test first and rejects the config
+/// when the provider does not accept it.
 pub async fn create_ddns(
     State(state): State<ProxyState>,
     Extension(user): Extension<AuthUser>,
+    Query(query): Query<CreateDdnsQuery>,
     Json(payload): Json<CreateDdnsRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
 
-    // Validate based on provider
+    validate_credentials(&payload)?;
+    validate_cloudflare_options(payload.provider, payload.proxied, payload.ttl)?;
+    validate_failover(
+        &state,
+        None,
+        payload.secondary_config_id,
+        payload.failover_threshold,
+    )
+    .await?;
+
+    validate_ip_source(
+        &state,
+        payload.ip_source,
+        payload.openwrt_router_id.as_deref(),
+    )
+    .await?;
+
+    let validation = if query.validate {
+        let result = state
+            .ddns_updater
+            .test_config(&payload.unsaved_config())
+            .await;
+        if !result.ok {
+            return Err(test_failure(&result));
+        }
+        Some(result)
+    } else {
+        None
+    };
+
+    // Webhook reporters need a token; it is only shown in this response
+    let report_token = (payload.ip_source == DdnsIpSource::Webhook).then(webhook::generate_secret);
+
+    let id = state
+        .app_state
+        .mysql
+        .create_ddns(&payload, report_token.as_deref())
+        .await?;
+
+    tracing::info!(
+        "Created DDNS config: {} ({:?}, ip source {})",
+        payload.hostname,
+        payload.provider,
+        payload.ip_source
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "message": "DDNS configuration created",
+            "id": id,
+            "report_token": report_token,
+            "validation": validation,
+        })),
+    ))
+}
+
+/// POST /api/ddns/test - Check credentials against the provider without
+/// changing the record or storing anything (admin: permission >= 80)
+///
+/// Body: `{"config_id": N}` for a stored config, or a create body.
+pub async fn test_ddns(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<DdnsTestRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let config = match payload {
+        DdnsTestRequest::Stored { config_id } => state
+            .app_state
+            .mysql
+            .get_ddns(config_id)
+            .await?
+            .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::DdnsConfigNotFound,
+                format!("DDNS config {} not found", config_id),
+            )
+        })?,
+        DdnsTestRequest::New(request) => {
+            validate_credentials(&request)?;
+            validate_cloudflare_options(request.provider, request.proxied, request.ttl)?;
+            request.unsaved_config()
+        }
+    };
+
+    Ok(Json(state.ddns_updater.test_config(&config).await))
+}
+
+/// Provider credentials the config cannot work without
+fn validate_credentials(payload: &CreateDdnsRequest) -> Result<(), AppError> {
     match payload.provider {
         DdnsProvider::DynDns | DdnsProvider::NoIp => {
             if payload.username.is_none() || payload.password.is_none() {
@@ -71,26 +160,33 @@ pub async fn create_ddns(
             }
         }
         DdnsProvider::Cloudflare => {
-            if payload.api_token.is_none() || payload.zone_id.is_none() {
+            // The zone is looked up from the hostname when zone_id is omitted
+            if payload.api_token.is_none() {
                 return Err(AppError::BadRequest(
-                    "API token and zone ID required for Cloudflare".to_string(),
+                    "API token required for Cloudflare".to_string(),
                 ));
             }
         }
     }
+    Ok(())
+}
 
-    let id = state.app_state.mysql.create_ddns(&payload).await?;
-
-    tracing::info!(
-        "Created DDNS config: {} ({:?})",
-        payload.hostname,
-        payload.provider
-    );
-
-    Ok((
-        StatusCode::CREATED,
-        Json(SuccessResponse::with_id("DDNS configuration created", id)),
-    ))
+/// Rejection of a config whose credential test failed
+fn test_failure(result: &DdnsTestResult) -> AppError {
+    let code = if result.auth_ok == Some(false) {
+        ErrorCode::DdnsProviderAuthFailed
+    } else {
+        ErrorCode::DdnsUpdateFailed
+    };
+    AppError::coded(
+        code,
+        format!(
+            "DDNS validation failed via {} ({}): {}",
+            result.provider,
+            result.method,
+            result.error.as_deref().unwrap_or("unknown error")
+        ),
+    )
 }
 
 /// PUT /api/ddns/:id - Update a DDNS configuration (admin: permission >= 80)
@@ -102,14 +198,141 @@ pub async fn update_ddns(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
 
+    let existing = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
+    let ip_source = payload.ip_source.unwrap_or(existing.ip_source);
+    let openwrt_router_id = payload
+        .openwrt_router_id
+        .as_deref()
+        .or(existing.openwrt_router_id.as_deref())
+        .filter(|r| !r.is_empty());
+    validate_ip_source(&state, ip_source, openwrt_router_id).await?;
+    validate_cloudflare_options(
+        existing.provider,
+        payload.proxied.unwrap_or(existing.proxied),
+        payload.ttl.unwrap_or(existing.ttl),
+    )?;
+    validate_failover(
+        &state,
+        Some(id),
+        payload
+            .secondary_config_id
+            .unwrap_or(existing.secondary_config_id),
+        payload
+            .failover_threshold
+            .unwrap_or(existing.failover_threshold),
+    )
+    .await?;
+
     let updated = state.app_state.mysql.update_ddns(id, &payload).await?;
+    if !updated {
+        return Err(AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        ));
+    }
 
-    if updated {
-        tracing::info!("Updated DDNS config {}", id);
-        Ok(Json(SuccessResponse::new("DDNS configuration updated")))
+    // Switching to webhook without a token: issue one (shown only here)
+    let report_token = if ip_source == DdnsIpSource::Webhook && existing.report_token.is_none() {
+        let token = webhook::generate_secret();
+        state
+            .app_state
+            .mysql
+            .set_ddns_report_token(id, &token)
+            .await?;
+        Some(token)
     } else {
-        Err(AppError::NotFound(format!("DDNS config {} not found", id)))
+        None
+    };
+
+    tracing::info!("Updated DDNS config {}", id);
+    Ok(Json(serde_json::json!({
+        "message": "DDNS configuration updated",
+        "report_token": report_token,
+    })))
+}
+
+/// proxied/ttl apply to Cloudflare records only; ttl is 1 (auto) or 60-86400
+fn validate_cloudflare_options(
+    provider: DdnsProvider,
+    proxied: Option<bool>,
+    ttl: Option<i32>,
+) -> Result<(), AppError> {
+    if provider != DdnsProvider::Cloudflare {
+        if proxied.is_some() || ttl.is_some() {
+            return Err(AppError::BadRequest(
+                "proxied and ttl are only supported for Cloudflare".to_string(),
+            ));
+        }
+        return Ok(());
+    }
+    if let Some(ttl) = ttl {
+        if ttl != 1 && !(60..=86400).contains(&ttl) {
+            return Err(AppError::BadRequest(
+                "ttl must be 1 (auto) or between 60 and 86400 seconds".to_string(),
+            ));
+        }
     }
+    Ok(())
+}
+
+/// Secondary must exist and form a single-level link (see ddns::failover)
+async fn validate_failover(
+    state: &ProxyState,
+    id: Option<i32>,
+    secondary_config_id: Option<i32>,
+    failover_threshold: i32,
+) -> Result<(), AppError> {
+    if !(1..=MAX_FAILOVER_THRESHOLD).contains(&failover_threshold) {
+        return Err(AppError::BadRequest(format!(
+            "failover_threshold must be between 1 and {}",
+            MAX_FAILOVER_THRESHOLD
+        )));
+    }
+    let Some(secondary) = secondary_config_id else {
+        return Ok(());
+    };
+    let links: Vec<(i32, Option<i32>)> = state
+        .app_state
+        .mysql
+        .list_ddns()
+        .await?
+        .iter()
+        .map(|c| (c.id, c.secondary_config_id))
+        .collect();
+    validate_failover_link(id, secondary, &links).map_err(AppError::BadRequest)
+}
+
+/// Router source needs a known OpenWrt router when one is named (the Omada
+/// link is set separately via link-omada)
+async fn validate_ip_source(
+    state: &ProxyState,
+    ip_source: DdnsIpSource,
+    openwrt_router_id: Option<&str>,
+) -> Result<(), AppError> {
+    if ip_source != DdnsIpSource::Router {
+        return Ok(());
+    }
+    if let Some(router_id) = openwrt_router_id {
+        let exists = state
+            .app_state
+            .mongo
+            .get_openwrt_router(router_id)
+            .await
+            .map_err(AppError::database)?
+            .is_some();
+        if !exists {
+            return Err(AppError::NotFound(format!(
+                "OpenWrt router {} not found",
+                router_id
+            )));
+        }
+    }
+    Ok(())
 }
 
 /// DELETE /api/ddns/:id - Delete a DDNS configuration (dangerous: permission == 100, confirm required)
@@ -144,7 +367,10 @@ pub async fn delete_ddns(
             "DDNS configuration deleted"
         ))))
     } else {
-        Err(AppError::NotFound(format!("DDNS config {} not found", id)))
+        Err(AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        ))
     }
 }
 
@@ -156,38 +382,206 @@ pub async fn trigger_ddns_update(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 50)?;
 
+    let config = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
+
+    // Trigger actual DDNS update via ddns module
+    tracing::info!("Manual DDNS update triggered for {}", config.hostname);
+
+    let outcome = state.ddns_updater.update_single(id).await.map_err(|e| {
+        let code = if crate::ddns::is_auth_failure(&e) {
+            ErrorCode::DdnsProviderAuthFailed
+        } else {
+            ErrorCode::DdnsUpdateFailed
+        };
+        AppError::coded(code, format!("DDNS update failed: {}", e))
+    })?;
+
+    Ok(Json(SuccessResponse::new(match outcome.note() {
+        Some(note) => format!("DDNS update completed successfully ({})", note),
+        None => "DDNS update completed successfully".to_string(),
+    })))
+}
+
+/// POST /api/ddns/:id/report-ip - Push the current address for a webhook-source
+/// config (report token via `Authorization: Bearer` or `X-DDNS-Token`, no session)
+pub async fn report_ddns_ip(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Option<Json<ReportIpRequest>>,
+) -> Result<impl IntoResponse, AppError> {
     let config = state
         .app_state
         .mysql
         .get_ddns(id)
         .await?
-        .ok_or_else(|| AppError::NotFound(format!("DDNS config {} not found", id)))?;
+        .ok_or(AppError::Unauthorized)?;
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-ddns-token").and_then(|v| v.to_str().ok()));
+    let authorized = match (&config.report_token, provided) {
+        (Some(expected), Some(provided)) => {
+            webhook::constant_time_eq(provided.trim().as_bytes(), expected.as_bytes())
+        }
+        _ => false,
+    };
+    if !authorized {
+        tracing::warn!("[DDNS] Rejected report-ip for config {}: bad token", id);
+        return Err(AppError::Unauthorized);
+    }
 
-    // Trigger actual DDNS update via ddns module
-    tracing::info!("Manual DDNS update triggered for {}", config.hostname);
+    if config.ip_source != DdnsIpSource::Webhook {
+        return Err(AppError::BadRequest(format!(
+            "DDNS config {} uses the {} IP source",
+            id, config.ip_source
+        )));
+    }
+
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let raw_ip = req
+        .ip
+        .filter(|ip| !ip.trim().is_empty())
+        .unwrap_or_else(|| state.network_policy.load_policy().client_ip(&headers, addr));
+    let ip: IpAddr = crate::ip::parse_ip(&raw_ip)
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid IP address: {}", raw_ip)))?;
+    if crate::ip::is_lan(ip) {
+        return Err(AppError::BadRequest(format!(
+            "{} is not a public address",
+            ip
+        )));
+    }
+    let ip = ip.to_string();
 
-    state
+    let updated = state
         .ddns_updater
-        .update_single(id)
+        .report_ip(&config, &ip)
         .await
-        .map_err(|e| AppError::InternalError(format!("DDNS update failed: {}", e)))?;
+        .map_err(AppError::InternalError)?;
+
+    Ok(Json(serde_json::json!({
+        "ip": ip,
+        "updated": updated,
+        "previous_ip": config.last_ip,
+    })))
+}
+
+/// POST /api/ddns/:id/report-token - Issue a new report-ip token, invalidating
+/// the old one (admin: permission >= 80)
+pub async fn rotate_ddns_report_token(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let token = webhook::generate_secret();
+    if !state
+        .app_state
+        .mysql
+        .set_ddns_report_token(id, &token)
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        ));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "ddns",
+            Some(id),
+            "rotate_report_token",
+            None,
+            None,
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Report token issued",
+        "report_token": token,
+    })))
+}
+
+/// POST /api/ddns/:id/acme/issue - Order a Let's Encrypt certificate for the
+/// config's hostname in the background; progress and result are shown in
+/// GET /api/dashboard/ssl-status (admin: permission >= 80)
+pub async fn issue_ddns_acme(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let config = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
+    let hostname = config.hostname.clone();
+    state.acme.begin(config).await?;
 
-    Ok(Json(SuccessResponse::new(
-        "DDNS update completed successfully",
-    )))
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "ddns",
+            Some(id),
+            "acme_issue",
+            None,
+            None,
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": format!("Certificate order for {} started", hostname),
+            "hostname": hostname,
+        })),
+    ))
 }
 
-/// GET /api/ddns/integrated - List DDNS configs with Omada WAN IP comparison
+/// GET /api/ddns/integrated - List DDNS configs with Omada WAN IP comparison,
+/// failover state and the latest DNS dependency check
 pub async fn list_ddns_integrated(
     State(state): State<ProxyState>,
 ) -> Result<impl IntoResponse, AppError> {
     let configs = state.app_state.mysql.list_ddns().await?;
+    let summaries: Vec<(i32, Option<i32>, DdnsProvider, String)> = configs
+        .iter()
+        .map(|c| (c.id, c.secondary_config_id, c.provider, c.hostname.clone()))
+        .collect();
     let controllers = state
         .app_state
         .mongo
         .list_omada_controllers()
         .await
         .unwrap_or_default();
+    let dns_checks = state
+        .app_state
+        .mongo
+        .list_ddns_dns_checks()
+        .await
+        .unwrap_or_default();
 
     let mut results = Vec::new();
 
@@ -227,8 +621,46 @@ pub async fn list_ddns_integrated(
             }
         }
 
-        // DNS resolve for the hostname
-        let resolved_ip = resolve_hostname(&config.hostname).await;
+        // Stored dependency check when the hostname is route-linked, else a
+        // live lookup
+        let dns_check = dns_checks
+            .iter()
+            .find(|c| c.ddns_config_id == config.id && c.hostname == config.hostname);
+        let resolved_ip = match dns_check {
+            Some(check) => check.resolved.first().cloned(),
+            None => resolve_hostname(&config.hostname).await,
+        };
+
+        // Failover link and the provider currently receiving updates
+        let secondary = config
+            .secondary_config_id
+            .and_then(|sid| summaries.iter().find(|(id, ..)| *id == sid));
+        let primary_config_id = summaries
+            .iter()
+            .find(|(_, sid, ..)| *sid == Some(config.id))
+            .map(|(id, ..)| *id);
+        let (active_config_id, active_provider) = match secondary {
+            Some((sid, _, provider, _)) if config.failover_active => (*sid, *provider),
+            _ => (config.id, config.provider),
+        };
+        let failover = serde_json::json!({
+            "role": if secondary.is_some() {
+                Some("primary")
+            } else if primary_config_id.is_some() {
+                Some("secondary")
+            } else {
+                None
+            },
+            "secondary": secondary.map(|(id, _, provider, hostname)| serde_json::json!({
+                "id": id,
+                "provider": provider,
+                "hostname": hostname,
+            })),
+            "primary_config_id": primary_config_id,
+            "active": config.failover_active && secondary.is_some(),
+            "active_config_id": active_config_id,
+            "active_provider": active_provider,
+        });
 
         // Check mismatch
         let ip_mismatch = match (&omada_wan_ip, &resolved_ip) {
@@ -247,11 +679,22 @@ pub async fn list_ddns_integrated(
                 "zone_id": config.zone_id,
                 "update_interval_sec": config.update_interval_sec,
                 "last_ip": config.last_ip,
+                "update_ipv6": config.update_ipv6,
+                "last_ipv6": config.last_ipv6,
                 "last_update": config.last_update,
                 "last_error": config.last_error,
                 "status": config.status,
                 "omada_controller_id": config.omada_controller_id,
                 "omada_site_id": config.omada_site_id,
+                "ip_source": config.ip_source,
+                "openwrt_router_id": config.openwrt_router_id,
+                "report_token": config.report_token.as_ref().map(|_| "********"),
+                "reported_ip": config.reported_ip,
+                "reported_at": config.reported_at,
+                "secondary_config_id": config.secondary_config_id,
+                "failover_threshold": config.failover_threshold,
+                "consecutive_failures": config.consecutive_failures,
+                "failover_active": config.failover_active,
                 "created_at": config.created_at,
                 "updated_at": config.updated_at,
             },
@@ -260,6 +703,9 @@ pub async fn list_ddns_integrated(
             "ip_mismatch": ip_mismatch,
             "port_forwarding": port_forwarding,
             "linked_controller": linked_controller,
+            "failover": failover,
+            "dns_check": dns_check,
+            "dns_mismatch": dns_check.and_then(|c| c.indicator()),
         }));
     }
 
@@ -276,12 +722,12 @@ pub async fn link_ddns_omada(
     require_permission(&user, 80)?;
 
     // Verify DDNS config exists
-    let _config = state
-        .app_state
-        .mysql
-        .get_ddns(id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("DDNS config {} not found", id)))?;
+    let _config = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
 
     // If linking, verify the controller exists
     if let Some(ref ctrl_id) = payload.omada_controller_id {
@@ -319,7 +765,10 @@ pub async fn link_ddns_omada(
         );
         Ok(Json(SuccessResponse::new("DDNS Omada link updated")))
     } else {
-        Err(AppError::NotFound(format!("DDNS config {} not found", id)))
+        Err(AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        ))
     }
 }
 
@@ -328,12 +777,12 @@ pub async fn get_ddns_port_forwards(
     State(state): State<ProxyState>,
     Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
-    let config = state
-        .app_state
-        .mysql
-        .get_ddns(id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("DDNS config {} not found", id)))?;
+    let config = state.app_state.mysql.get_ddns(id).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::DdnsConfigNotFound,
+            format!("DDNS config {} not found", id),
+        )
+    })?;
 
     let ctrl_id = config.omada_controller_id.as_deref().ok_or_else(|| {
         AppError::BadRequest("DDNS config is not linked to an Omada controller".to_string())
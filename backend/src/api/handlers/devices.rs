@@ -0,0 +1,507 @@
+//! Unified device search, inventory export and device class handlers
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::db::mongo::device_search::DeviceSearchDocs;
+use crate::db::mongo::user_object_detail::UserObjectDetail;
+use crate::device_class::{DeviceClass, DeviceClassRules, SETTING_DEVICE_CLASS_RULES};
+use crate::error::AppError;
+use crate::omada::client::normalize_mac;
+use crate::proxy::ProxyState;
+
+use super::dashboard::csv_escape;
+
+/// Default and maximum number of devices returned
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 200;
+
+/// Query parameters for GET /api/devices/search
+#[derive(Debug, Deserialize)]
+pub struct DeviceSearchQuery {
+    /// MAC (any format, full or partial), IP, hostname or label substring
+    pub q: String,
+    pub limit: Option<usize>,
+    /// Only devices whose topology node has this class
+    pub device_class: Option<DeviceClass>,
+}
+
+/// One device, deduplicated by normalized MAC
+#[derive(Debug, Serialize)]
+pub struct DeviceSearchHit {
+    pub mac: String,
+    /// Topology label, else the best source name
+    pub label: Option<String>,
+    pub ip: Option<String>,
+    /// Online in any source
+    pub online: bool,
+    /// Latest last-seen/updated time across sources (RFC3339)
+    pub last_seen: Option<String>,
+    /// user_object_detail _id, for linking to the topology node
+    pub topology_node_id: Option<String>,
+    /// Class of the topology node (None without one, or for infrastructure)
+    pub device_class: Option<String>,
+    pub sources: Vec<DeviceSearchSource>,
+}
+
+/// Per-source detail of a hit
+#[derive(Debug, Serialize)]
+pub struct DeviceSearchSource {
+    /// omada, openwrt, external or topology
+    pub source: &'static str,
+    /// Controller, router or external device id (parent node id for topology)
+    pub owner_id: String,
+    pub owner_name: Option<String>,
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    /// online/offline (topology: state_type)
+    pub state: String,
+    pub last_seen: Option<String>,
+    pub ssid: Option<String>,
+}
+
+/// Owner display names by id
+#[derive(Debug, Default)]
+pub struct OwnerNames {
+    pub controllers: HashMap<String, String>,
+    pub routers: HashMap<String, String>,
+    pub external: HashMap<String, String>,
+}
+
+fn state(active: bool) -> String {
+    if active { "online" } else { "offline" }.to_string()
+}
+
+/// Merge per-collection matches into hits keyed by normalized MAC.
+///
+/// `linked` are topology nodes for MACs that only matched a source collection.
+pub fn merge_device_hits(
+    docs: DeviceSearchDocs,
+    linked: Vec<UserObjectDetail>,
+    owners: &OwnerNames,
+) -> Vec<DeviceSearchHit> {
+    let mut hits: BTreeMap<String, DeviceSearchHit> = BTreeMap::new();
+    let mut add = |mac: &str, source: DeviceSearchSource| {
+        let mac = normalize_mac(mac);
+        let hit = hits.entry(mac.clone()).or_insert_with(|| DeviceSearchHit {
+            mac,
+            label: None,
+            ip: None,
+            online: false,
+            last_seen: None,
+            topology_node_id: None,
+            device_class: None,
+            sources: Vec::new(),
+        });
+        hit.online |= source.state == "online" || source.state == "StaticOnline";
+        if hit.ip.is_none() {
+            hit.ip = source.ip.clone();
+        }
+        if hit.label.is_none() {
+            hit.label = source.name.clone();
+        }
+        // RFC3339 strings in UTC compare chronologically
+        if source.last_seen > hit.last_seen {
+            hit.last_seen = source.last_seen.clone();
+        }
+        hit.sources.push(source);
+    };
+
+    let mut topology: Vec<UserObjectDetail> = docs.topology;
+    for node in linked {
+        if !topology.iter().any(|t| t.id == node.id) {
+            topology.push(node);
+        }
+    }
+    // Topology first so its label and node id win
+    let mut node_ids: Vec<(String, String, Option<String>)> = Vec::new();
+    for node in topology {
+        node_ids.push((
+            normalize_mac(&node.mac),
+            node.id.clone(),
+            node.device_class.clone(),
+        ));
+        add(
+            &node.mac,
+            DeviceSearchSource {
+                source: "topology",
+                owner_id: node.parent_id,
+                owner_name: None,
+                name: Some(node.label),
+                ip: node.ip,
+                state: node.state_type,
+                last_seen: Some(node.updated_at),
+                ssid: node.ssid,
+            },
+        );
+    }
+    for cli in docs.omada {
+        add(
+            &cli.mac,
+            DeviceSearchSource {
+                source: "omada",
+                owner_name: owners.controllers.get(&cli.controller_id).cloned(),
+                owner_id: cli.controller_id,
+                name: cli.name.or(cli.host_name),
+                ip: cli.ip,
+                state: state(cli.active),
+                last_seen: cli.last_seen_at.or(Some(cli.synced_at)),
+                ssid: cli.ssid,
+            },
+        );
+    }
+    for cli in docs.openwrt {
+        add(
+            &cli.mac,
+            DeviceSearchSource {
+                source: "openwrt",
+                owner_name: owners.routers.get(&cli.router_id).cloned(),
+                owner_id: cli.router_id,
+                name: cli.hostname,
+                ip: Some(cli.ip),
+                state: state(cli.active),
+                last_seen: Some(cli.last_seen_at),
+                ssid: cli.ssid,
+            },
+        );
+    }
+    for cli in docs.external {
+        add(
+            &cli.mac,
+            DeviceSearchSource {
+                source: "external",
+                owner_name: owners.external.get(&cli.device_id).cloned(),
+                owner_id: cli.device_id,
+                name: cli.hostname,
+                ip: cli.ip,
+                state: state(cli.active),
+                last_seen: Some(cli.last_seen_at),
+                ssid: cli.ssid,
+            },
+        );
+    }
+
+    for (mac, id, class) in node_ids {
+        if let Some(hit) = hits.get_mut(&mac) {
+            if hit.topology_node_id.is_none() {
+                hit.topology_node_id = Some(id);
+                hit.device_class = class;
+            }
+        }
+    }
+
+    let mut hits: Vec<DeviceSearchHit> = hits.into_values().collect();
+    // Online first, then most recently seen
+    hits.sort_by(|a, b| {
+        b.online
+            .cmp(&a.online)
+            .then_with(|| b.last_seen.cmp(&a.last_seen))
+    });
+    hits
+}
+
+/// GET /api/devices/search?q=&limit=&device_class= - Search clients and
+/// topology nodes by MAC, IP, hostname or label
+pub async fn search_devices(
+    State(state): State<ProxyState>,
+    Query(query): Query<DeviceSearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let q = query.q.trim();
+    if q.chars().count() < 2 {
+        return Err(AppError::BadRequest(
+            "q must be at least 2 characters".to_string(),
+        ));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let mongo = &state.app_state.mongo;
+    let docs = mongo
+        .search_devices(q, limit as i64)
+        .await
+        .map_err(AppError::database)?;
+
+    // Link source-only matches to their topology node
+    let mut unlinked: Vec<String> = docs
+        .omada
+        .iter()
+        .map(|c| c.mac.as_str())
+        .chain(docs.openwrt.iter().map(|c| c.mac.as_str()))
+        .chain(docs.external.iter().map(|c| c.mac.as_str()))
+        .map(normalize_mac)
+        .filter(|mac| !docs.topology.iter().any(|t| normalize_mac(&t.mac) == *mac))
+        .collect();
+    unlinked.sort();
+    unlinked.dedup();
+    let linked = mongo
+        .get_user_object_details_by_macs(&unlinked)
+        .await
+        .map_err(AppError::database)?;
+
+    let owners = OwnerNames {
+        controllers: mongo
+            .list_omada_controllers()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| (c.controller_id, c.display_name))
+            .collect(),
+        routers: mongo
+            .list_openwrt_routers()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| (r.router_id, r.display_name))
+            .collect(),
+        external: mongo
+            .list_external_devices()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d| (d.device_id, d.display_name))
+            .collect(),
+    };
+
+    let mut hits = merge_device_hits(docs, linked, &owners);
+    if let Some(class) = query.device_class {
+        hits.retain(|h| h.device_class.as_deref() == Some(class.as_str()));
+    }
+    let truncated = hits.len() > limit;
+    hits.truncate(limit);
+
+    Ok(Json(serde_json::json!({
+        "query": q,
+        "count": hits.len(),
+        "truncated": truncated,
+        "devices": hits,
+    })))
+}
+
+/// Query parameters for GET /api/devices/inventory/export
+#[derive(Debug, Default, Deserialize)]
+pub struct InventoryExportQuery {
+    pub device_class: Option<DeviceClass>,
+    pub node_type: Option<String>,
+    pub source: Option<String>,
+    pub fid: Option<String>,
+}
+
+impl InventoryExportQuery {
+    fn matches(&self, node: &UserObjectDetail) -> bool {
+        self.device_class
+            .is_none_or(|c| node.device_class.as_deref() == Some(c.as_str()))
+            && self.node_type.as_ref().is_none_or(|t| node.node_type == *t)
+            && self.source.as_ref().is_none_or(|s| node.source == *s)
+            && self
+                .fid
+                .as_ref()
+                .is_none_or(|f| node.fid.as_ref() == Some(f))
+    }
+}
+
+/// Topology nodes as CSV, one row per node
+pub fn inventory_csv(nodes: &[UserObjectDetail], query: &InventoryExportQuery) -> String {
+    let mut csv = String::from(
+        "id,mac,label,node_type,device_class,state_type,ip,hostname,vendor,source,connection_type,parent_id,fid,facility_name,updated_at\n",
+    );
+    for n in nodes.iter().filter(|n| query.matches(n)) {
+        let vendor = n
+            .metadata
+            .get("vendor")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let row = [
+            n.id.as_str(),
+            &n.mac,
+            &n.label,
+            &n.node_type,
+            n.device_class.as_deref().unwrap_or(""),
+            &n.state_type,
+            n.ip.as_deref().unwrap_or(""),
+            n.hostname.as_deref().unwrap_or(""),
+            vendor,
+            &n.source,
+            &n.connection_type,
+            &n.parent_id,
+            n.fid.as_deref().unwrap_or(""),
+            n.facility_name.as_deref().unwrap_or(""),
+            &n.updated_at,
+        ];
+        let row: Vec<String> = row.iter().map(|f| csv_escape(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// GET /api/devices/inventory/export?device_class=&node_type=&source=&fid= -
+/// Export topology nodes as CSV
+pub async fn export_device_inventory(
+    State(state): State<ProxyState>,
+    Query(query): Query<InventoryExportQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut nodes = state
+        .app_state
+        .mongo
+        .get_all_user_object_details()
+        .await
+        .map_err(AppError::database)?;
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    let csv = inventory_csv(&nodes, &query);
+
+    let headers = [
+        (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+        (
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"device_inventory.csv\"",
+        ),
+    ];
+    Ok((StatusCode::OK, headers, csv))
+}
+
+/// GET /api/devices/class-rules - Classification rules in effect, and whether
+/// they come from the `device_class_rules` setting or the built-in table
+pub async fn get_device_class_rules(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let raw = state
+        .app_state
+        .mysql
+        .get_setting(SETTING_DEVICE_CLASS_RULES)
+        .await?;
+    let (rules, customized, error) = match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(raw) => match DeviceClassRules::parse(raw) {
+            Ok(rules) => (rules, true, None),
+            Err(e) => (DeviceClassRules::default(), false, Some(e)),
+        },
+        None => (DeviceClassRules::default(), false, None),
+    };
+
+    Ok(Json(serde_json::json!({
+        "setting": SETTING_DEVICE_CLASS_RULES,
+        "customized": customized,
+        "setting_error": error,
+        "classes": DeviceClass::ALL,
+        "rules": rules.rules,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::mongo::openwrt::OpenWrtClientDoc;
+
+    fn openwrt_client(mac: &str, active: bool, last_seen: &str) -> OpenWrtClientDoc {
+        serde_json::from_value(serde_json::json!({
+            "mac": mac,
+            "router_id": "R1",
+            "ip": "192.168.8.20",
+            "hostname": "laptop",
+            "lacis_id": null,
+            "active": active,
+            "last_seen_at": last_seen,
+            "synced_at": last_seen,
+            "created_at": last_seen,
+            "updated_at": last_seen,
+        }))
+        .unwrap()
+    }
+
+    fn topology_node(id: &str, mac: &str) -> UserObjectDetail {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "mac": mac,
+            "lacis_id": null,
+            "device_type": "NetworkDevice",
+            "parent_id": "GW",
+            "sort_order": 0,
+            "node_type": "client",
+            "state_type": "offline",
+            "label": "Alice laptop",
+            "label_customized": true,
+            "ip": "192.168.8.20",
+            "hostname": null,
+            "source": "openwrt",
+            "source_ref_id": null,
+            "connection_type": "wireless",
+            "product_type": null,
+            "product_code": null,
+            "network_device_type": null,
+            "candidate_lacis_id": null,
+            "fid": null,
+            "facility_name": null,
+            "ssid": null,
+            "metadata": {},
+            "aranea_lacis_id": null,
+            "created_at": "2026-01-01T00:00:00+00:00",
+            "updated_at": "2026-01-01T00:00:00+00:00",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn inventory_export_filters_by_class() {
+        let mut camera = topology_node("AABBCCDDEEFF", "AABBCCDDEEFF");
+        camera.device_class = Some("camera".to_string());
+        camera.label = "Lobby, east".to_string();
+        let mut laptop = topology_node("001122334455", "001122334455");
+        laptop.device_class = Some("laptop".to_string());
+
+        let query = InventoryExportQuery {
+            device_class: Some(DeviceClass::Camera),
+            ..Default::default()
+        };
+        let csv = inventory_csv(&[camera, laptop], &query);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("id,mac,label,node_type,device_class"));
+        assert!(lines[1].starts_with("AABBCCDDEEFF,AABBCCDDEEFF,\"Lobby, east\",client,camera,"));
+    }
+
+    #[test]
+    fn hits_are_deduplicated_by_mac_and_linked_to_topology() {
+        let docs = DeviceSearchDocs {
+            openwrt: vec![
+                openwrt_client("AABBCCDDEEFF", true, "2026-02-01T00:00:00+00:00"),
+                openwrt_client("001122334455", false, "2026-01-15T00:00:00+00:00"),
+            ],
+            ..Default::default()
+        };
+        let mut owners = OwnerNames::default();
+        owners
+            .routers
+            .insert("R1".to_string(), "Lobby router".to_string());
+
+        let hits = merge_device_hits(
+            docs,
+            vec![topology_node("AABBCCDDEEFF", "AABBCCDDEEFF")],
+            &owners,
+        );
+        assert_eq!(hits.len(), 2);
+
+        let first = &hits[0];
+        assert_eq!(first.mac, "AABBCCDDEEFF");
+        assert!(first.online);
+        assert_eq!(first.label.as_deref(), Some("Alice laptop"));
+        assert_eq!(first.topology_node_id.as_deref(), Some("AABBCCDDEEFF"));
+        assert_eq!(
+            first.last_seen.as_deref(),
+            Some("2026-02-01T00:00:00+00:00")
+        );
+        assert_eq!(first.sources.len(), 2);
+        assert_eq!(first.sources[1].owner_name.as_deref(), Some("Lobby router"));
+
+        assert_eq!(hits[1].mac, "001122334455");
+        assert!(!hits[1].online);
+        assert!(hits[1].topology_node_id.is_none());
+    }
+}
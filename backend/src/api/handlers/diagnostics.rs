@@ -697,7 +697,7 @@ async fn check_aranea(state: &ProxyState) -> Vec<DiagnosticCheck> {
     let mut checks = Vec::new();
 
     let start = Instant::now();
-    let config = &state.aranea_client.config;
+    let config = state.aranea_client.config();
     let tid_ok = !config.tid.is_empty();
     let lacis_id_ok = !config.tenant_lacis_id.is_empty();
 
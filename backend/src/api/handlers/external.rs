@@ -10,11 +10,17 @@ use axum::{
 use serde::Deserialize;
 
 use crate::api::auth_middleware::require_permission;
+use crate::db::mongo::external::ExternalDeviceUpdate;
 use crate::error::AppError;
 use crate::external::ExternalDeviceManager;
+use crate::mac::MacAddr;
 use crate::models::{AuthUser, ConfirmQuery, ConfirmRequired};
+use crate::poll_schedule::PollOutcome;
 use crate::proxy::ProxyState;
 
+/// Allowed per-device poll interval (0 = syncer default)
+const POLL_INTERVAL_RANGE: std::ops::RangeInclusive<u32> = 15..=86400;
+
 // ============================================================================
 // Request types
 // ============================================================================
@@ -53,12 +59,13 @@ pub async fn register_device(
     Json(req): Json<RegisterDeviceRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
+    let mac = MacAddr::parse(&req.mac).map_err(AppError::BadRequest)?;
 
     match state
         .external_manager
         .register_device(
             &req.display_name,
-            &req.mac,
+            mac.as_str(),
             &req.ip,
             &req.protocol,
             req.username.as_deref(),
@@ -113,6 +120,46 @@ pub async fn get_device(
     }
 }
 
+/// PUT /api/external/devices/:id - Edit device settings, poll interval and
+/// pause state (admin: permission >= 80)
+pub async fn update_device(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(req): Json<ExternalDeviceUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    if let Some(interval) = req.poll_interval_sec {
+        if interval != 0 && !POLL_INTERVAL_RANGE.contains(&interval) {
+            return Err(AppError::BadRequest(format!(
+                "poll_interval_sec must be 0 (default) or {}..={}",
+                POLL_INTERVAL_RANGE.start(),
+                POLL_INTERVAL_RANGE.end()
+            )));
+        }
+    }
+
+    let mongo = &state.app_state.mongo;
+    let updated = mongo
+        .update_external_device_settings(&id, &req)
+        .await
+        .map_err(AppError::database)?;
+    if !updated {
+        return Err(AppError::NotFound(format!("Device {} not found", id)));
+    }
+
+    let device = mongo
+        .get_external_device(&id)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "device": device,
+    })))
+}
+
 /// DELETE /api/external/devices/:id - Remove a device
 pub async fn delete_device(
     State(state): State<ProxyState>,
@@ -177,10 +224,14 @@ pub async fn poll_device(
     );
 
     match syncer.poll_one(&id).await {
-        Ok(()) => Ok(Json(serde_json::json!({
+        Ok(PollOutcome::Polled) => Ok(Json(serde_json::json!({
             "ok": true,
             "message": format!("Device {} polled", id),
         }))),
+        Ok(PollOutcome::Paused) => Ok(Json(serde_json::json!({
+            "ok": false,
+            "error": format!("Device {} is paused", id),
+        }))),
         Err(e) => Ok(Json(serde_json::json!({
             "ok": false,
             "error": e,
@@ -0,0 +1,46 @@
+//! Fallback handler: ACME HTTP-01 challenges, admin frontend files
+//! ([frontend] mode), then the proxy
+
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method},
+    response::{IntoResponse, Response},
+};
+
+use crate::proxy::{self, ProxyState};
+
+/// Everything the API routes did not match - the key authorization of a
+/// running ACME order (see `crate::acme`), the admin frontend when it owns
+/// the path (see `crate::frontend`), otherwise the reverse proxy
+pub async fn serve_frontend_or_proxy(
+    State(state): State<ProxyState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+) -> Response {
+    if req.method() == Method::GET {
+        if let Some(key_authorization) = state.acme.challenge_response(req.uri().path()) {
+            return ([(header::CONTENT_TYPE, "text/plain")], key_authorization).into_response();
+        }
+    }
+    if state.frontend.is_enabled() {
+        // Decided under the router lock; files are read after it is
+        // released so a slow disk does not hold up route reloads
+        let router = state.router.read().await;
+        let claimed = state
+            .frontend
+            .claims(&router, req.method(), req.uri().path(), req.headers());
+        drop(router);
+        if claimed {
+            if let Some(response) = state
+                .frontend
+                .respond(req.method(), req.uri().path(), req.headers())
+                .await
+            {
+                return response;
+            }
+        }
+    }
+    proxy::proxy_handler(State(state), ConnectInfo(addr), req).await
+}
@@ -0,0 +1,63 @@
+//! Country rule handlers (/api/security/geo-rules)
+
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use serde::Serialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::AuthUser;
+use crate::proxy::geo_rules::{self, GeoRules};
+use crate::proxy::ProxyState;
+
+/// Rules as enforced, and whether countries can be resolved at all
+#[derive(Debug, Serialize)]
+pub struct GeoRulesView {
+    #[serde(flatten)]
+    pub rules: GeoRules,
+    /// Without a GeoIP database every client is unresolved
+    pub geoip_loaded: bool,
+}
+
+fn view(state: &ProxyState, rules: GeoRules) -> GeoRulesView {
+    GeoRulesView {
+        rules,
+        geoip_loaded: state.geoip.is_some(),
+    }
+}
+
+/// GET /api/security/geo-rules - Country allow/deny rules
+pub async fn get_geo_rules(State(state): State<ProxyState>) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(view(&state, state.geo_filter.rules())))
+}
+
+/// PUT /api/security/geo-rules - Replace the country rules; stored in
+/// settings and applied to the proxy at once (admin: permission >= 80)
+pub async fn update_geo_rules(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<GeoRules>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let mysql = &state.app_state.mysql;
+
+    let rules = req.normalized().map_err(AppError::Validation)?;
+    let previous = geo_rules::load(mysql).await?;
+    geo_rules::save(mysql, &rules).await?;
+    state.geo_filter.set_rules(rules.clone());
+
+    let encode = |r: &GeoRules| serde_json::to_string(r).ok();
+    let _ = mysql
+        .log_audit(
+            "geo_rules",
+            None,
+            "update",
+            None,
+            encode(&previous).as_deref(),
+            encode(&rules).as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(view(&state, rules)))
+}
@@ -0,0 +1,181 @@
+//! Monthly per-DDNS-hostname usage for billing (/api/dashboard/hostname-usage)
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::hostname_usage::{
+    self, first_of_month, month_key, next_month, parse_month, HostnameUsage, UsageTotals,
+    ROLLUP_JOB,
+};
+use crate::models::AuthUser;
+use crate::proxy::ProxyState;
+
+use super::dashboard::csv_escape;
+
+#[derive(Debug, Deserialize)]
+pub struct HostnameUsageQuery {
+    /// "YYYY-MM" (default: current month)
+    pub month: Option<String>,
+}
+
+/// Body for POST /api/dashboard/hostname-usage/rollup
+#[derive(Debug, Deserialize)]
+pub struct HostnameUsageRollupRequest {
+    /// "YYYY-MM"
+    pub month: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HostnameUsageReport {
+    pub month: String,
+    /// Closed and rolled up for the last time; rows only change by re-run
+    pub complete: bool,
+    /// When the rows were last computed (None = never rolled up)
+    pub updated_at: Option<String>,
+    pub totals: UsageTotals,
+    /// Busiest hostname first; "unassigned" = routes without a DDNS config
+    pub hostnames: Vec<HostnameUsage>,
+}
+
+/// First day of the requested month; the current or a past one
+fn requested_month(month: Option<&str>) -> Result<NaiveDate, AppError> {
+    let current = first_of_month(Utc::now().date_naive());
+    let month = match month {
+        Some(s) => {
+            parse_month(s).ok_or_else(|| AppError::validation("month", "expected YYYY-MM"))?
+        }
+        None => current,
+    };
+    if month > current {
+        return Err(AppError::validation("month", "month is in the future"));
+    }
+    Ok(month)
+}
+
+async fn report(state: &ProxyState, month: NaiveDate) -> Result<HostnameUsageReport, AppError> {
+    let mongo = &state.app_state.mongo;
+    let rows = mongo
+        .get_hostname_usage(&month_key(month))
+        .await
+        .map_err(AppError::database)?;
+    let completed = mongo
+        .rollup_completed_day(ROLLUP_JOB)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(HostnameUsageReport {
+        month: month_key(month),
+        complete: completed.is_some_and(|day| day >= next_month(month).pred_opt().unwrap_or(month)),
+        updated_at: rows.iter().filter_map(|r| r.updated_at.clone()).max(),
+        totals: UsageTotals::of(&rows),
+        hostnames: rows,
+    })
+}
+
+/// GET /api/dashboard/hostname-usage - Requests, bytes, errors and peak
+/// hourly rate per DDNS hostname for a month (from the monthly rollup)
+pub async fn get_hostname_usage(
+    State(state): State<ProxyState>,
+    Query(query): Query<HostnameUsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let month = requested_month(query.month.as_deref())?;
+    Ok(Json(report(&state, month).await?))
+}
+
+/// GET /api/dashboard/hostname-usage/export - The same as CSV
+pub async fn export_hostname_usage(
+    State(state): State<ProxyState>,
+    Query(query): Query<HostnameUsageQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let month = requested_month(query.month.as_deref())?;
+    let report = report(&state, month).await?;
+
+    let mut csv = String::from(
+        "month,hostname,requests,bytes_in,bytes_out,errors_4xx,errors_5xx,peak_hourly_requests,peak_hour,route_ids\n",
+    );
+    for row in &report.hostnames {
+        let route_ids: Vec<String> = row.route_ids.iter().map(|id| id.to_string()).collect();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            row.month,
+            csv_escape(&row.hostname),
+            row.requests,
+            row.bytes_in,
+            row.bytes_out,
+            row.errors_4xx,
+            row.errors_5xx,
+            row.peak_hourly_requests,
+            row.peak_hour.as_deref().unwrap_or(""),
+            csv_escape(&route_ids.join(" ")),
+        ));
+    }
+
+    let disposition = format!(
+        "attachment; filename=\"hostname_usage_{}.csv\"",
+        report.month
+    );
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        csv,
+    ))
+}
+
+/// POST /api/dashboard/hostname-usage/rollup - Recompute a month (e.g. after
+/// late log ingestion); audit-logged with the totals before and after
+/// (admin: permission >= 80)
+pub async fn rerun_hostname_usage_rollup(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<HostnameUsageRollupRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let month = requested_month(Some(&req.month))?;
+    let key = month_key(month);
+
+    let before = state
+        .app_state
+        .mongo
+        .get_hostname_usage(&key)
+        .await
+        .map_err(AppError::database)?;
+    let after = hostname_usage::roll_up_month(&state.app_state, month)
+        .await
+        .map_err(AppError::database)?;
+
+    let totals =
+        |rows: &[HostnameUsage]| serde_json::to_string(&UsageTotals::of(rows)).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "hostname_usage",
+            None,
+            "recompute",
+            Some(&key),
+            Some(&totals(&before)),
+            Some(&totals(&after)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    tracing::info!(
+        "Hostname usage for {} recomputed by {} ({} hostnames)",
+        key,
+        user.sub,
+        after.len()
+    );
+    Ok(Json(report(&state, month).await?))
+}
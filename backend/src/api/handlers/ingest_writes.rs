@@ -0,0 +1,18 @@
+//! Ingestion write load handlers
+
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::AuthUser;
+use crate::proxy::ProxyState;
+
+/// GET /api/admin/ingest-writes - Ingestion pool size, pacing and the last
+/// write cycle of each syncer source on this instance (admin: permission >= 80)
+pub async fn get_ingest_writes(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    Ok(Json(state.app_state.mongo.ingest_writes_report()))
+}
@@ -9,9 +9,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::api::auth_middleware::require_permission;
 use crate::error::AppError;
-use crate::lacis_id::{
-    compute_network_device_lacis_id, default_product_code, normalize_mac_for_lacis_id,
-};
+use crate::lacis_id::{compute_network_device_lacis_id, default_product_code};
+use crate::mac::MacAddr;
 use crate::models::AuthUser;
 use crate::proxy::ProxyState;
 
@@ -136,13 +135,8 @@ pub async fn lacis_id_compute(
     Json(payload): Json<ComputeLacisIdRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let product_code = payload.product_code.as_deref().unwrap_or("0000");
-    let normalized = normalize_mac_for_lacis_id(&payload.mac);
+    let mac = MacAddr::parse(&payload.mac).map_err(AppError::BadRequest)?;
 
-    if normalized.len() != 12 {
-        return Err(AppError::BadRequest(
-            "Invalid MAC address: must be 12 hex characters after normalization".to_string(),
-        ));
-    }
     if payload.product_type.len() != 3 {
         return Err(AppError::BadRequest(
             "Product type must be exactly 3 digits".to_string(),
@@ -150,11 +144,11 @@ pub async fn lacis_id_compute(
     }
 
     let lacis_id =
-        compute_network_device_lacis_id(&payload.product_type, &payload.mac, product_code);
+        compute_network_device_lacis_id(&payload.product_type, mac.as_str(), product_code);
 
     Ok(Json(serde_json::json!({
         "lacis_id": lacis_id,
-        "mac": normalized,
+        "mac": mac.as_str(),
         "product_type": payload.product_type,
         "product_code": product_code,
         "length": lacis_id.len(),
@@ -181,6 +175,14 @@ pub async fn lacis_id_assign(
             "LacisID must be exactly 20 characters".to_string(),
         ));
     }
+    // Omada devices are keyed by MAC
+    let device_id = if payload.source == "omada" {
+        MacAddr::parse(&device_id)
+            .map_err(AppError::BadRequest)?
+            .into_string()
+    } else {
+        device_id
+    };
 
     let updated = state
         .app_state
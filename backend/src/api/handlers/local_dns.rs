@@ -0,0 +1,302 @@
+//! Local DNS override handlers (/api/dns/*)
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::local_dns::normalize_hostname;
+use crate::models::{AuthUser, CreateDnsOverrideRequest, DnsOverride, UpdateDnsOverrideRequest};
+use crate::proxy::ProxyState;
+
+use super::SuccessResponse;
+
+const MAX_COMMENT_LEN: usize = 500;
+
+/// Query for GET /api/dns/status
+#[derive(Debug, Deserialize)]
+pub struct DnsStatusQuery {
+    /// Recent queries to include (default 50, max 200)
+    pub recent: Option<usize>,
+}
+
+/// An active DDNS hostname without an override
+#[derive(Debug, Serialize)]
+pub struct DnsOverrideSuggestion {
+    pub hostname: String,
+    pub ddns_config_id: i32,
+    /// Public address the name currently resolves to
+    pub public_ip: Option<String>,
+    /// What the override would answer (the LPG's LAN IP)
+    pub target: Option<String>,
+}
+
+async fn load_override(state: &ProxyState, id: i32) -> Result<DnsOverride, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_dns_override(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::DnsOverrideNotFound,
+                format!("DNS override {} not found", id),
+            )
+        })
+}
+
+fn validate_hostname(input: &str) -> Result<String, AppError> {
+    normalize_hostname(input).map_err(|e| AppError::validation("hostname", e))
+}
+
+/// Canonical address text; empty = LAN IP
+fn validate_target(input: Option<&str>) -> Result<Option<String>, AppError> {
+    match input.map(str::trim).filter(|t| !t.is_empty()) {
+        None => Ok(None),
+        Some(t) => t
+            .parse::<IpAddr>()
+            .map(|ip| Some(ip.to_string()))
+            .map_err(|_| AppError::validation("target", "must be an IPv4 or IPv6 address")),
+    }
+}
+
+fn validate_comment(input: Option<&str>) -> Result<Option<String>, AppError> {
+    let comment = input.map(str::trim).filter(|c| !c.is_empty());
+    if comment.is_some_and(|c| c.chars().count() > MAX_COMMENT_LEN) {
+        return Err(AppError::validation(
+            "comment",
+            format!("must be at most {} characters", MAX_COMMENT_LEN),
+        ));
+    }
+    Ok(comment.map(str::to_string))
+}
+
+async fn ensure_unique(
+    state: &ProxyState,
+    hostname: &str,
+    id: Option<i32>,
+) -> Result<(), AppError> {
+    let taken = state
+        .app_state
+        .mysql
+        .list_dns_overrides()
+        .await?
+        .iter()
+        .any(|o| o.hostname == hostname && Some(o.id) != id);
+    if taken {
+        return Err(AppError::validation(
+            "hostname",
+            format!("{} already has an override", hostname),
+        ));
+    }
+    Ok(())
+}
+
+fn describe(record: &DnsOverride) -> String {
+    format!(
+        "{} -> {}{}",
+        record.hostname,
+        record.target.as_deref().unwrap_or("LAN IP"),
+        if record.enabled { "" } else { " (disabled)" }
+    )
+}
+
+async fn reload(state: &ProxyState) {
+    if let Err(e) = state.local_dns.reload(&state.app_state.mysql).await {
+        tracing::error!("Failed to reload DNS overrides: {}", e);
+    }
+}
+
+/// GET /api/dns/overrides - List the override table
+pub async fn list_dns_overrides(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(state.app_state.mysql.list_dns_overrides().await?))
+}
+
+/// POST /api/dns/overrides - Add an override (admin: permission >= 80)
+pub async fn create_dns_override(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateDnsOverrideRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let hostname = validate_hostname(&req.hostname)?;
+    let target = validate_target(req.target.as_deref())?;
+    let comment = validate_comment(req.comment.as_deref())?;
+    let source = match req.source.as_deref() {
+        None | Some("manual") => "manual",
+        Some("ddns") => "ddns",
+        Some(_) => return Err(AppError::validation("source", "must be manual or ddns")),
+    };
+    ensure_unique(&state, &hostname, None).await?;
+
+    let id = state
+        .app_state
+        .mysql
+        .create_dns_override(
+            &hostname,
+            target.as_deref(),
+            req.enabled.unwrap_or(true),
+            source,
+            comment.as_deref(),
+        )
+        .await?;
+    let record = load_override(&state, id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "dns_override",
+            Some(id),
+            "create",
+            None,
+            None,
+            Some(&describe(&record)),
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+/// PUT /api/dns/overrides/:id - Update an override (admin: permission >= 80)
+pub async fn update_dns_override(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateDnsOverrideRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mut record = load_override(&state, id).await?;
+    let old = describe(&record);
+    if let Some(hostname) = &req.hostname {
+        record.hostname = validate_hostname(hostname)?;
+        ensure_unique(&state, &record.hostname, Some(id)).await?;
+    }
+    if let Some(target) = &req.target {
+        record.target = validate_target(target.as_deref())?;
+    }
+    if let Some(enabled) = req.enabled {
+        record.enabled = enabled;
+    }
+    if req.comment.is_some() {
+        record.comment = validate_comment(req.comment.as_deref())?;
+    }
+
+    if !state.app_state.mysql.update_dns_override(&record).await? {
+        return Err(AppError::coded(
+            ErrorCode::DnsOverrideNotFound,
+            format!("DNS override {} not found", id),
+        ));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "dns_override",
+            Some(id),
+            "update",
+            None,
+            Some(&old),
+            Some(&describe(&record)),
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok(Json(load_override(&state, id).await?))
+}
+
+/// DELETE /api/dns/overrides/:id - Remove an override (admin: permission >= 80)
+pub async fn delete_dns_override(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let record = load_override(&state, id).await?;
+    state.app_state.mysql.delete_dns_override(id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "dns_override",
+            Some(id),
+            "delete",
+            None,
+            Some(&describe(&record)),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok(Json(SuccessResponse::new("DNS override deleted")))
+}
+
+/// GET /api/dns/suggestions - Active DDNS hostnames that have no override yet
+pub async fn get_dns_suggestions(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let mysql = &state.app_state.mysql;
+    let existing: HashSet<String> = mysql
+        .list_dns_overrides()
+        .await?
+        .into_iter()
+        .map(|o| o.hostname)
+        .collect();
+    let lan_ip = state.local_dns.lan_ip().map(|ip| ip.to_string());
+
+    let mut seen = HashSet::new();
+    let suggestions: Vec<DnsOverrideSuggestion> = mysql
+        .list_active_ddns()
+        .await?
+        .into_iter()
+        .filter_map(|ddns| {
+            let hostname = normalize_hostname(&ddns.hostname).ok()?;
+            if existing.contains(&hostname) || !seen.insert(hostname.clone()) {
+                return None;
+            }
+            Some(DnsOverrideSuggestion {
+                hostname,
+                ddns_config_id: ddns.id,
+                public_ip: ddns.last_ip,
+                target: lan_ip.clone(),
+            })
+        })
+        .collect();
+
+    Ok(Json(suggestions))
+}
+
+/// GET /api/dns/status - Listener health, per-record counters and recent queries
+pub async fn get_dns_status(
+    State(state): State<ProxyState>,
+    Query(query): Query<DnsStatusQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let dns = &state.local_dns;
+    Ok(Json(serde_json::json!({
+        "health": dns.health(),
+        "records": dns.record_hits(),
+        "recent_queries": dns.recent_queries(query.recent.unwrap_or(50).min(200)),
+    })))
+}
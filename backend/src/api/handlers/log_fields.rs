@@ -0,0 +1,116 @@
+//! Custom access log field handlers (/api/routes/:id/log-fields)
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{AuthUser, ProxyRoute};
+use crate::proxy::log_fields::{RouteLogFields, DENIED_HEADERS, MAX_FIELDS, MAX_VALUE_CHARS};
+use crate::proxy::ProxyState;
+
+use super::routes::record_route_version;
+
+/// Body for PUT /api/routes/:id/log-fields
+#[derive(Debug, Deserialize)]
+pub struct LogFieldsRequest {
+    /// Rules by field name; null or an empty object removes them
+    pub fields: Option<RouteLogFields>,
+}
+
+async fn load_route(state: &ProxyState, id: i32) -> Result<ProxyRoute, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_route(id)
+        .await?
+        .ok_or_else(|| AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id)))
+}
+
+fn limits() -> serde_json::Value {
+    serde_json::json!({
+        "max_fields": MAX_FIELDS,
+        "max_value_chars": MAX_VALUE_CHARS,
+        "denied_headers": DENIED_HEADERS,
+    })
+}
+
+/// GET /api/routes/:id/log-fields - Custom access log field rules
+pub async fn get_route_log_fields(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let route = load_route(&state, id).await?;
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "fields": route.log_fields().unwrap_or_default(),
+        "limits": limits(),
+    })))
+}
+
+/// PUT /api/routes/:id/log-fields - Replace the route's custom access log
+/// field rules (admin: permission >= 80)
+pub async fn set_route_log_fields(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<LogFieldsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let route = load_route(&state, id).await?;
+
+    let rules = req
+        .fields
+        .unwrap_or_default()
+        .normalize()
+        .map_err(|e| AppError::validation("fields", e))?;
+    let column = rules.to_column();
+
+    if !state
+        .app_state
+        .mysql
+        .set_route_log_fields(id, column.as_deref())
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "log_fields", None).await;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "update",
+            Some("log_fields"),
+            route.log_fields.as_deref(),
+            column.as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after log_fields change: {}", e);
+    }
+
+    tracing::info!(
+        "Custom log fields on route {} set to [{}] (by {})",
+        id,
+        rules.0.keys().cloned().collect::<Vec<_>>().join(", "),
+        user.sub
+    );
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "fields": rules,
+        "limits": limits(),
+    })))
+}
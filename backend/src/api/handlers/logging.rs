@@ -0,0 +1,84 @@
+//! Runtime log level handlers
+
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::logging;
+use crate::models::AuthUser;
+use crate::proxy::ProxyState;
+
+/// Body for POST /api/admin/log-level
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelRequest {
+    /// EnvFilter directives, e.g. "lacis_proxy_gateway=debug,tower_http=info"
+    #[serde(default)]
+    pub level: Option<String>,
+    /// Restore the level the process started with
+    #[serde(default)]
+    pub reset: bool,
+}
+
+/// GET /api/admin/log-level - Active and startup filter directives
+/// (admin: permission >= 80)
+pub async fn get_log_level(
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let state = logging::level_state()
+        .ok_or_else(|| AppError::InternalError("Logging is not initialized".to_string()))?;
+    Ok(Json(state))
+}
+
+/// POST /api/admin/log-level - Change the log filter without a restart
+/// (dangerous: permission == 100)
+pub async fn set_log_level(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<SetLogLevelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let previous = match (req.reset, req.level.as_deref()) {
+        (true, _) => logging::reset_level(),
+        (false, Some(level)) => logging::set_level(level),
+        (false, None) => {
+            return Err(AppError::BadRequest(
+                "level or reset is required".to_string(),
+            ))
+        }
+    }
+    .map_err(AppError::BadRequest)?;
+
+    let current = logging::level_state()
+        .map(|s| s.current)
+        .unwrap_or_default();
+    tracing::info!(
+        "Log level changed by {}: {} -> {}",
+        user.sub,
+        previous,
+        current
+    );
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "logging",
+            None,
+            "set_level",
+            Some("level"),
+            Some(&previous),
+            Some(&current),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Log level updated",
+        "previous": previous,
+        "current": current,
+    })))
+}
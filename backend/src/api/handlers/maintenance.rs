@@ -0,0 +1,246 @@
+//! Maintenance window handlers (/api/maintenance/*)
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::maintenance::{
+    self, CreateMaintenanceWindowRequest, MaintenanceMode, MaintenanceWindow,
+    UpdateMaintenanceWindowRequest,
+};
+use crate::models::AuthUser;
+use crate::proxy::ProxyState;
+
+use super::SuccessResponse;
+
+/// An active window, for the maintenance banner
+#[derive(Debug, Serialize)]
+pub struct ActiveMaintenanceWindow {
+    pub window_id: String,
+    pub name: String,
+    pub mode: MaintenanceMode,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// Active routes the window covers
+    pub affected_routes: usize,
+    /// Tallied so far by the health checker
+    pub suppressed_failures: u64,
+    pub suppressed_alerts: u64,
+}
+
+async fn load_window(state: &ProxyState, id: &str) -> Result<MaintenanceWindow, AppError> {
+    state
+        .app_state
+        .mongo
+        .get_maintenance_window(id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::MaintenanceWindowNotFound,
+                format!("Maintenance window {} not found", id),
+            )
+        })
+}
+
+fn describe(window: &MaintenanceWindow) -> String {
+    format!(
+        "{} ({}): {}",
+        window.name,
+        window.window_id,
+        serde_json::to_string(&window.schedule).unwrap_or_default()
+    )
+}
+
+/// GET /api/maintenance/windows - List maintenance windows with their status
+pub async fn list_maintenance_windows(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let windows = state
+        .app_state
+        .mongo
+        .list_maintenance_windows()
+        .await
+        .map_err(AppError::database)?;
+    Ok(Json(windows))
+}
+
+/// GET /api/maintenance/windows/:id - Get one maintenance window
+pub async fn get_maintenance_window(
+    State(state): State<ProxyState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(load_window(&state, &id).await?))
+}
+
+/// GET /api/maintenance/active - Windows active now (polled for the banner)
+pub async fn get_active_maintenance(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let windows = state
+        .app_state
+        .mongo
+        .list_maintenance_windows()
+        .await
+        .map_err(AppError::database)?;
+    let now = Utc::now();
+    let active = maintenance::active_windows(&windows, now);
+    let routes = if active.is_empty() {
+        Vec::new()
+    } else {
+        state.app_state.mysql.list_active_routes().await?
+    };
+
+    let windows: Vec<ActiveMaintenanceWindow> = active
+        .into_iter()
+        .filter_map(|a| {
+            let window = windows.iter().find(|w| w.window_id == a.window_id)?;
+            // The tally belongs to this occurrence once the checker opened it
+            let tallied = window.status.occurrence_start == Some(a.starts_at);
+            Some(ActiveMaintenanceWindow {
+                affected_routes: routes.iter().filter(|r| window.covers(r)).count(),
+                suppressed_failures: if tallied {
+                    window.status.suppressed_failures
+                } else {
+                    0
+                },
+                suppressed_alerts: if tallied {
+                    window.status.suppressed_alerts
+                } else {
+                    0
+                },
+                window_id: a.window_id,
+                name: a.name,
+                mode: a.mode,
+                starts_at: a.starts_at,
+                ends_at: a.ends_at,
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "active": !windows.is_empty(),
+        "windows": windows,
+        "checked_at": now,
+    })))
+}
+
+/// POST /api/maintenance/windows - Create a maintenance window (admin: permission >= 80)
+pub async fn create_maintenance_window(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<CreateMaintenanceWindowRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let window = MaintenanceWindow::new(payload).map_err(AppError::BadRequest)?;
+    state
+        .app_state
+        .mongo
+        .insert_maintenance_window(&window)
+        .await
+        .map_err(AppError::database)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "maintenance_window",
+            None,
+            "create",
+            Some("window"),
+            None,
+            Some(&describe(&window)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(window)))
+}
+
+/// PUT /api/maintenance/windows/:id - Update a maintenance window (admin: permission >= 80)
+pub async fn update_maintenance_window(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateMaintenanceWindowRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mut window = load_window(&state, &id).await?;
+    let old = serde_json::to_string(&window).unwrap_or_default();
+    window.apply(payload).map_err(AppError::BadRequest)?;
+
+    let found = state
+        .app_state
+        .mongo
+        .update_maintenance_window(&window)
+        .await
+        .map_err(AppError::database)?;
+    if !found {
+        return Err(AppError::coded(
+            ErrorCode::MaintenanceWindowNotFound,
+            format!("Maintenance window {} not found", id),
+        ));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "maintenance_window",
+            None,
+            "update",
+            Some("window"),
+            Some(&old),
+            Some(&serde_json::to_string(&window).unwrap_or_default()),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(window))
+}
+
+/// DELETE /api/maintenance/windows/:id - Delete a maintenance window; an
+/// active occurrence ends without a summary (admin: permission >= 80)
+pub async fn delete_maintenance_window(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let window = load_window(&state, &id).await?;
+    state
+        .app_state
+        .mongo
+        .delete_maintenance_window(&id)
+        .await
+        .map_err(AppError::database)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "maintenance_window",
+            None,
+            "delete",
+            Some("window"),
+            Some(&describe(&window)),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(SuccessResponse::new("Maintenance window deleted")))
+}
@@ -0,0 +1,106 @@
+//! Startup migration handlers
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::{AuthUser, ConfirmRequired};
+use crate::proxy::ProxyState;
+
+/// Body for POST /api/admin/migrations/:id/run
+#[derive(Debug, Deserialize, Default)]
+pub struct RunMigrationRequest {
+    /// Re-run even if already recorded, bypassing the step's own guard
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// GET /api/admin/migrations - Registered migrations with applied/pending state
+/// (admin: permission >= 80)
+pub async fn list_migrations(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let migrations = state
+        .migrations
+        .status()
+        .await
+        .map_err(AppError::InternalError)?;
+    let pending = migrations.iter().filter(|m| m.pending).count();
+
+    Ok(Json(serde_json::json!({
+        "migrations": migrations,
+        "total": migrations.len(),
+        "pending": pending,
+    })))
+}
+
+/// POST /api/admin/migrations/:id/run - Run one migration now; an already
+/// recorded one needs `force` (dangerous: permission == 100, confirm required)
+pub async fn run_migration(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(migration_id): Path<String>,
+    body: Option<Json<RunMigrationRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+
+    let status = state
+        .migrations
+        .status()
+        .await
+        .map_err(AppError::InternalError)?
+        .into_iter()
+        .find(|m| m.migration_id == migration_id)
+        .ok_or_else(|| AppError::NotFound(format!("Migration {} not found", migration_id)))?;
+    if !status.pending && !req.force {
+        return Err(AppError::BadRequest(format!(
+            "Migration {} already ran; set force to re-run it",
+            migration_id
+        )));
+    }
+
+    if !req.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "run_migration".to_string(),
+            target: migration_id,
+            warning: "Forced runs skip the migration's own guard and may overwrite data it wrote before (e.g. topology labels rebuilt from cg_node_order).".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    let report = state
+        .migrations
+        .run_one(&migration_id, req.force)
+        .await
+        .ok_or_else(|| AppError::NotFound(format!("Migration {} not found", migration_id)))?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "schema_migration",
+            None,
+            if req.force { "force_run" } else { "run" },
+            None,
+            Some(&migration_id),
+            Some(&format!("{}: {}", report.outcome, report.detail)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "ok": report.outcome != "failed",
+        "report": report,
+    })))
+}
@@ -1,42 +1,84 @@
 //! HTTP handlers module
 
 pub mod agent;
+mod alert_rules;
+mod api_trace;
 pub mod aranea;
 mod audit;
 pub mod auth;
+mod cluster;
 mod dashboard;
 mod ddns;
+mod devices;
 mod diagnostics;
 pub mod external;
+mod frontend;
+mod geo_rules;
+mod hostname_usage;
+mod ingest_writes;
 mod lacis_id;
+mod local_dns;
+mod log_fields;
+mod logging;
+mod maintenance;
+mod migrations;
 mod nginx;
 mod omada;
 pub mod openwrt;
+mod rate_limits;
+mod route_approvals;
 mod routes;
 mod security;
 mod settings;
+mod status_page;
+mod storage;
+mod store_forward;
 mod tools;
 mod topology;
+mod topology_shares;
+mod transform;
 pub mod wireguard;
 
 pub use self::agent::*;
+pub use self::alert_rules::*;
+pub use self::api_trace::*;
 pub use self::aranea::*;
 pub use self::audit::*;
+pub use self::cluster::*;
 pub use self::dashboard::*;
 pub use self::ddns::*;
+pub use self::devices::*;
 pub use self::diagnostics::*;
+pub use self::frontend::*;
+pub use self::geo_rules::*;
+pub use self::hostname_usage::*;
+pub use self::ingest_writes::*;
 pub use self::lacis_id::*;
+pub use self::local_dns::*;
+pub use self::log_fields::*;
+pub use self::logging::*;
+pub use self::maintenance::*;
+pub use self::migrations::*;
 pub use self::nginx::*;
 pub use self::omada::*;
+pub use self::rate_limits::*;
+pub use self::route_approvals::*;
 pub use self::routes::*;
 pub use self::security::*;
 pub use self::settings::*;
+pub use self::status_page::*;
+pub use self::storage::*;
+pub use self::store_forward::*;
 pub use self::tools::*;
 pub use self::topology::*;
+pub use self::topology_shares::*;
+pub use self::transform::*;
 
-use axum::{response::IntoResponse, Json};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::Serialize;
 
+use crate::proxy::ProxyState;
+
 /// Health check response
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -54,6 +96,43 @@ pub async fn health_check() -> impl IntoResponse {
     })
 }
 
+/// Readiness response
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    /// "ready" | "not_ready"
+    pub status: &'static str,
+    pub mysql: bool,
+    pub mongo: bool,
+    /// Unsafe configuration that does not affect readiness
+    /// ("default_jwt_secret")
+    pub warnings: Vec<&'static str>,
+}
+
+/// GET /readyz - 200 when both databases answer, 503 otherwise
+pub async fn readiness_check(State(state): State<ProxyState>) -> impl IntoResponse {
+    let mysql = state.app_state.mysql.ping().await.is_ok();
+    let mongo = state.app_state.mongo.ping().await.is_ok();
+    let mut warnings = Vec::new();
+    if state.jwt_keys.read().await.is_default() {
+        warnings.push("default_jwt_secret");
+    }
+
+    let ready = mysql && mongo;
+    (
+        if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        Json(ReadinessResponse {
+            status: if ready { "ready" } else { "not_ready" },
+            mysql,
+            mongo,
+            warnings,
+        }),
+    )
+}
+
 /// Generic success response
 #[derive(Serialize)]
 pub struct SuccessResponse {
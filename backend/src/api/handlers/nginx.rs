@@ -7,8 +7,11 @@ use tokio::fs;
 
 use crate::api::auth_middleware::require_permission;
 use crate::db::mysql::MySqlDb;
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode};
 use crate::models::AuthUser;
+use crate::nginx_drift::{self, GeneratedInputs, NginxInputDrift};
+use crate::nginx_log::{self, NginxLogIngestStatus};
+use crate::nginx_stats::{self, NginxMetrics};
 use crate::proxy::ProxyState;
 
 use super::SuccessResponse;
@@ -68,9 +71,37 @@ pub struct NginxStatus {
     pub config_valid: bool,
     pub proxy_mode: String, // "selective" or "full_proxy"
     pub config_path: Option<String>,
+    /// Last successful reload by this instance (None: not since LPG started)
     pub last_reload: Option<String>,
     pub error: Option<String>,
     pub client_max_body_size: Option<String>,
+    /// Request body limit the built-in proxy enforces (routes may override)
+    pub proxy_max_body_bytes: usize,
+    /// Ingestion of nginx's JSON access log (full proxy mode)
+    pub access_log_ingest: NginxLogIngestStatus,
+    /// stub_status counters, requests per second and per-site requests
+    pub metrics: NginxMetrics,
+    pub config_drift: NginxConfigDrift,
+}
+
+/// Config file on disk vs. what nginx runs and what LPG would generate
+#[derive(Serialize)]
+pub struct NginxConfigDrift {
+    /// SHA-256 of the config file on disk
+    pub disk_hash: Option<String>,
+    /// Hash of the file at the last reload (None: not reloaded since LPG started)
+    pub running_hash: Option<String>,
+    /// false: the file changed after the last reload, nginx runs an older config
+    pub running_matches_disk: Option<bool>,
+    /// Hash of the config generated from the current template settings
+    pub generated_hash: String,
+    /// false: edited by hand, or settings saved without regenerating
+    /// (full proxy mode only)
+    pub disk_matches_generated: Option<bool>,
+    /// Routes, DDNS hostnames and template settings now vs. at generation
+    /// (`in_sync`, `changes`)
+    #[serde(flatten)]
+    pub inputs: NginxInputDrift,
 }
 
 /// Nginx config update request
@@ -81,9 +112,9 @@ pub struct UpdateNginxConfigRequest {
     pub server_name: Option<String>,
 }
 
-/// GET /api/nginx/status - Get nginx status
+/// GET /api/nginx/status - Get nginx status, runtime metrics and config drift
 pub async fn get_nginx_status(
-    State(_state): State<ProxyState>,
+    State(state): State<ProxyState>,
 ) -> Result<impl IntoResponse, AppError> {
     let running = check_nginx_running().await;
     let (config_valid, error) = test_nginx_config().await;
@@ -91,14 +122,49 @@ pub async fn get_nginx_status(
     let config_path = find_config_path().await;
     let client_max_body_size = get_client_max_body_size().await;
 
+    let settings = load_template_settings_from_db(&state.app_state.mysql).await?;
+    let generated_hash =
+        nginx_stats::config_hash(&generate_full_proxy_config_from_settings(&settings));
+    let disk_hash = match &config_path {
+        Some(path) => fs::read_to_string(path)
+            .await
+            .ok()
+            .map(|content| nginx_stats::config_hash(&content)),
+        None => None,
+    };
+    let mut inputs = nginx_input_drift(&state).await?;
+    if proxy_mode != "full_proxy" {
+        inputs.in_sync = None;
+    }
+    let last_reload = state.nginx_stats.last_reload();
+    let running_hash = last_reload
+        .as_ref()
+        .filter(|r| r.config_path == config_path)
+        .and_then(|r| r.config_hash.clone());
+    let config_drift = NginxConfigDrift {
+        running_matches_disk: running_hash
+            .as_ref()
+            .map(|running| disk_hash.as_ref() == Some(running)),
+        disk_matches_generated: (proxy_mode == "full_proxy")
+            .then(|| disk_hash.as_ref() == Some(&generated_hash)),
+        disk_hash,
+        running_hash,
+        generated_hash,
+        inputs,
+    };
+
     Ok(Json(NginxStatus {
         running,
         config_valid,
         proxy_mode,
         config_path,
-        last_reload: None,
+        last_reload: last_reload.map(|r| r.at.to_rfc3339()),
         error,
         client_max_body_size,
+        proxy_max_body_bytes: state.proxy_limits.read().await.max_request_body_bytes,
+        access_log_ingest: state.nginx_log.status(),
+        metrics: state.nginx_stats.metrics(),
+        config_drift,
     }))
 }
 
@@ -120,46 +186,8 @@ pub async fn enable_full_proxy(
     db.set_setting("nginx_backend_port", Some(&backend_port.to_string()))
         .await?;
 
-    // Load full template settings from DB (now includes updated server_name/port)
-    let settings = load_template_settings_from_db(db).await?;
-
-    // Find existing config or create new
-    let config_path = find_config_path()
-        .await
-        .unwrap_or_else(|| format!("{}/lacis-proxy", NGINX_SITES_AVAILABLE));
-
-    // Generate full proxy config from DB settings
-    let config = generate_full_proxy_config_from_settings(&settings);
-
-    // Backup existing config
-    if let Ok(existing) = fs::read_to_string(&config_path).await {
-        let backup_path = format!("{}.backup.{}", config_path, chrono::Utc::now().timestamp());
-        let _ = fs::write(&backup_path, &existing).await;
-        tracing::info!("Backed up existing config to {}", backup_path);
-    }
-
-    // Write new config
-    fs::write(&config_path, &config)
-        .await
-        .map_err(|e| AppError::InternalError(format!("Failed to write nginx config: {}", e)))?;
-
-    // Ensure symlink in sites-enabled
-    let enabled_path = format!("{}/lacis-proxy", NGINX_SITES_ENABLED);
-    if !std::path::Path::new(&enabled_path).exists() {
-        let _ = std::os::unix::fs::symlink(&config_path, &enabled_path);
-    }
-
-    // Test config
-    let (valid, error) = test_nginx_config().await;
-    if !valid {
-        return Err(AppError::BadRequest(format!(
-            "Nginx config test failed: {}",
-            error.unwrap_or_default()
-        )));
-    }
-
-    // Reload nginx
-    reload_nginx().await?;
+    // Generate from DB settings (now including server_name/port), test and reload
+    apply_full_proxy_config(&state).await?;
 
     // Send notification
     state
@@ -196,7 +224,7 @@ pub async fn reload_nginx_handler(
         )));
     }
 
-    reload_nginx().await?;
+    reload_nginx(&state).await?;
 
     state
         .notifier
@@ -277,7 +305,8 @@ pub(crate) async fn test_nginx_config() -> (bool, Option<String>) {
     }
 }
 
-async fn reload_nginx() -> Result<(), AppError> {
+/// Reload nginx and record the reload (time and config hash) for the status
+async fn reload_nginx(state: &ProxyState) -> Result<(), AppError> {
     let output = Command::new("sudo")
         .args(["systemctl", "reload", "nginx"])
         .output()
@@ -291,6 +320,10 @@ async fn reload_nginx() -> Result<(), AppError> {
         )));
     }
 
+    state
+        .nginx_stats
+        .record_reload(find_config_path().await)
+        .await;
     Ok(())
 }
 
@@ -350,7 +383,8 @@ pub struct UpdateBodySizeRequest {
     pub size: String, // e.g., "50M", "100M", "1G"
 }
 
-/// PUT /api/nginx/body-size - Update client_max_body_size
+/// PUT /api/nginx/body-size - Update the request body limit of the built-in
+/// proxy and nginx's client_max_body_size
 pub async fn update_body_size(
     State(state): State<ProxyState>,
     Extension(user): Extension<AuthUser>,
@@ -371,14 +405,65 @@ pub async fn update_body_size(
         ));
     }
     let numeric_part = &size[..size.len() - 1];
-    if numeric_part.parse::<u32>().is_err() {
+    let Ok(value) = numeric_part.parse::<u32>() else {
         return Err(AppError::BadRequest("Invalid size format".to_string()));
+    };
+    if value == 0 {
+        return Err(AppError::BadRequest(
+            "Size must be greater than 0".to_string(),
+        ));
     }
 
-    let config_path = find_config_path()
-        .await
-        .ok_or_else(|| AppError::NotFound("Nginx config not found".to_string()))?;
+    let mb = match size.chars().last() {
+        Some('K') => u64::from(value).div_ceil(1024),
+        Some('G') => u64::from(value) * 1024,
+        _ => u64::from(value),
+    };
+    if mb > i32::MAX as u64 {
+        return Err(AppError::BadRequest("Size is too large".to_string()));
+    }
+
+    // The built-in proxy enforces it itself (whole MB, routes may override)
+    state
+        .app_state
+        .mysql
+        .upsert_setting(
+            "proxy_max_request_body_mb",
+            Some(&mb.to_string()),
+            Some("Max client request body in MB (empty = [server] max_body_mb)"),
+        )
+        .await?;
+    if let Err(e) = state.reload_proxy_limits().await {
+        tracing::error!("Failed to reload proxy limits: {}", e);
+    }
+
+    // nginx in front of LPG gets the same limit
+    if let Some(config_path) = find_config_path().await {
+        write_nginx_body_size(&state, config_path, &size).await?;
+    }
+
+    state
+        .notifier
+        .notify_config_change(
+            "Body Size Limit Updated",
+            &format!("Request body limit changed to {}", size),
+        )
+        .await;
+
+    tracing::info!("Updated request body limit to {}", size);
 
+    Ok(Json(SuccessResponse::new(format!(
+        "Body size limit updated to {}",
+        size
+    ))))
+}
+
+/// Set client_max_body_size in each location block, then test and reload nginx
+async fn write_nginx_body_size(
+    state: &ProxyState,
+    config_path: String,
+    size: &str,
+) -> Result<(), AppError> {
     let content = fs::read_to_string(&config_path)
         .await
         .map_err(|e| AppError::InternalError(format!("Failed to read config: {}", e)))?;
@@ -461,22 +546,9 @@ pub async fn update_body_size(
         )));
     }
 
-    reload_nginx().await?;
+    reload_nginx(state).await?;
 
-    state
-        .notifier
-        .notify_config_change(
-            "Nginx Body Size Updated",
-            &format!("client_max_body_size changed to {}", size),
-        )
-        .await;
-
-    tracing::info!("Updated client_max_body_size to {}", size);
-
-    Ok(Json(SuccessResponse::new(&format!(
-        "Body size limit updated to {}",
-        size
-    ))))
+    Ok(())
 }
 
 // ============================================================================
@@ -610,21 +682,48 @@ pub async fn regenerate_nginx_config(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
 
+    apply_full_proxy_config(&state).await?;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Nginx Config Regenerated",
+            "Nginx configuration regenerated from template settings and reloaded.",
+        )
+        .await;
+
+    tracing::info!("Regenerated nginx config from DB template settings");
+
+    Ok(Json(SuccessResponse::new(
+        "Nginx config regenerated and reloaded successfully",
+    )))
+}
+
+// ============================================================================
+// Config generation
+// ============================================================================
+
+/// Write the full proxy config generated from the template settings, test
+/// and reload it, and record the inputs it was generated from. When routes,
+/// DDNS hostnames or settings change while it is written, the config would
+/// be stale the moment it loads: the previous file is restored, nginx is not
+/// reloaded and the call fails with NGINX_CONFIG_STALE.
+pub async fn apply_full_proxy_config(state: &ProxyState) -> Result<GeneratedInputs, AppError> {
     let db = &state.app_state.mysql;
     let settings = load_template_settings_from_db(db).await?;
+    let config = generate_full_proxy_config_from_settings(&settings);
+    let inputs = nginx_drift::current_inputs(db, nginx_stats::config_hash(&config)).await?;
 
     // Find existing config or create new
     let config_path = find_config_path()
         .await
         .unwrap_or_else(|| format!("{}/lacis-proxy", NGINX_SITES_AVAILABLE));
 
-    // Generate config from DB settings
-    let config = generate_full_proxy_config_from_settings(&settings);
-
     // Backup existing config
-    if let Ok(existing) = fs::read_to_string(&config_path).await {
+    let existing = fs::read_to_string(&config_path).await.ok();
+    if let Some(existing) = &existing {
         let backup_path = format!("{}.backup.{}", config_path, chrono::Utc::now().timestamp());
-        let _ = fs::write(&backup_path, &existing).await;
+        let _ = fs::write(&backup_path, existing).await;
         tracing::info!("Backed up existing config to {}", backup_path);
     }
 
@@ -648,22 +747,57 @@ pub async fn regenerate_nginx_config(
         )));
     }
 
-    // Reload nginx
-    reload_nginx().await?;
-
-    state
-        .notifier
-        .notify_config_change(
-            "Nginx Config Regenerated",
-            "Nginx configuration regenerated from template settings and reloaded.",
+    let settings = load_template_settings_from_db(db).await?;
+    let template_hash =
+        nginx_stats::config_hash(&generate_full_proxy_config_from_settings(&settings));
+    let now = nginx_drift::current_inputs(db, template_hash).await?;
+    if now != inputs {
+        match &existing {
+            Some(existing) => {
+                let _ = fs::write(&config_path, existing).await;
+            }
+            None => {
+                let _ = fs::remove_file(&config_path).await;
+            }
+        }
+        return Err(AppError::coded(
+            ErrorCode::NginxConfigStale,
+            "Routes, DDNS hostnames or template settings changed while the nginx config was generated; nothing was applied, retry",
         )
-        .await;
+        .with_details(serde_json::json!({
+            "changes": nginx_drift::compare(&inputs, &now),
+        })));
+    }
 
-    tracing::info!("Regenerated nginx config from DB template settings");
+    // Reload nginx
+    reload_nginx(state).await?;
+
+    let generated = match nginx_drift::save_generated(&config_path, inputs.clone()).await {
+        Ok(generated) => generated,
+        Err(e) => {
+            tracing::warn!("{}", e);
+            GeneratedInputs {
+                generated_at: chrono::Utc::now().to_rfc3339(),
+                inputs_hash: inputs.hash(),
+                inputs,
+            }
+        }
+    };
+    Ok(generated)
+}
 
-    Ok(Json(SuccessResponse::new(
-        "Nginx config regenerated and reloaded successfully",
-    )))
+/// Current nginx inputs vs. the ones the config on disk was generated from
+pub async fn nginx_input_drift(state: &ProxyState) -> Result<NginxInputDrift, AppError> {
+    let db = &state.app_state.mysql;
+    let settings = load_template_settings_from_db(db).await?;
+    let template_hash =
+        nginx_stats::config_hash(&generate_full_proxy_config_from_settings(&settings));
+    let current = nginx_drift::current_inputs(db, template_hash).await?;
+    let generated = match find_config_path().await {
+        Some(path) => nginx_drift::load_generated(&path).await,
+        None => None,
+    };
+    Ok(nginx_drift::drift(generated.as_ref(), &current))
 }
 
 // ============================================================================
@@ -828,15 +962,25 @@ fn generate_full_proxy_config_from_settings(s: &NginxTemplateSettings) -> String
     let connect_timeout = s.proxy_connect_timeout;
     let send_timeout = s.proxy_send_timeout;
     let read_timeout = s.proxy_read_timeout;
+    let access_log_format = nginx_log::LOG_FORMAT;
+    let access_log_path = nginx_log::ACCESS_LOG_PATH;
+    let stub_status_server = nginx_stats::stub_status_server_block();
 
     format!(
         r#"# LacisProxyGateway2 - Full Proxy Mode
 # Generated automatically from template settings - DO NOT EDIT MANUALLY
 # All routing is managed through the LacisProxyGateway2 UI
 
+# JSON access log, ingested by LacisProxyGateway2 for requests it never sees
+{access_log_format}
+
+{stub_status_server}
+
 server {{
     listen 80;
     server_name {server_name};
+    access_log /var/log/nginx/access.log;
+    access_log {access_log_path} lpg_json;
 
     # Redirect HTTP to HTTPS
     return 301 https://$host$request_uri;
@@ -845,6 +989,8 @@ server {{
 server {{
     listen 443 ssl http2;
     server_name {server_name};
+    access_log /var/log/nginx/access.log;
+    access_log {access_log_path} lpg_json;
 
     # SSL Configuration (managed by certbot)
     ssl_certificate /etc/letsencrypt/live/{server_name}/fullchain.pem;
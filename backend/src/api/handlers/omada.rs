@@ -3,16 +3,21 @@
 //! Controller management (CRUD), data viewing, sync triggers, and legacy compatibility.
 
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
+    http::HeaderMap,
     response::IntoResponse,
     Extension, Json,
 };
 use serde::Deserialize;
 
 use crate::api::auth_middleware::require_permission;
-use crate::error::AppError;
+use crate::db::mongo::operation_logs::OperationLogDoc;
+use crate::error::{AppError, ErrorCode};
+use crate::mac::MacAddr;
 use crate::models::{AuthUser, ConfirmQuery, ConfirmRequired};
 use crate::omada::manager::OmadaManager;
+use crate::omada::webhook;
 use crate::omada::OmadaClient;
 use crate::proxy::ProxyState;
 
@@ -35,6 +40,15 @@ pub struct TestConnectionRequest {
     pub client_secret: String,
 }
 
+/// Body for PUT /api/omada/controllers/:id/sites/:site_id
+#[derive(Deserialize)]
+pub struct UpdateSiteFacilityRequest {
+    /// Facility ID; null or empty clears the mapping
+    pub fid: Option<String>,
+    /// Defaults to the Aranea facility name when omitted
+    pub fid_display_name: Option<String>,
+}
+
 #[derive(Deserialize, Default)]
 pub struct OmadaDeviceQuery {
     pub controller_id: Option<String>,
@@ -58,6 +72,21 @@ pub struct OmadaWgQuery {
 // Controller management
 // ============================================================================
 
+/// `{ok: false}` body for a controller that could not be reached or
+/// authenticated, with its error code
+fn controller_failure(error: &str) -> serde_json::Value {
+    let code = if crate::omada::client::is_token_rejection(error) {
+        ErrorCode::OmadaTokenExpired
+    } else {
+        ErrorCode::OmadaUnavailable
+    };
+    serde_json::json!({
+        "ok": false,
+        "error": error,
+        "code": code,
+    })
+}
+
 /// POST /api/omada/controllers - Register a new controller (admin: permission >= 80)
 pub async fn register_controller(
     State(state): State<ProxyState>,
@@ -80,10 +109,7 @@ pub async fn register_controller(
             "ok": true,
             "controller": doc,
         }))),
-        Err(e) => Ok(Json(serde_json::json!({
-            "ok": false,
-            "error": e,
-        }))),
+        Err(e) => Ok(Json(controller_failure(&e))),
     }
 }
 
@@ -114,6 +140,7 @@ pub async fn get_controller(
         Ok(None) => Json(serde_json::json!({
             "ok": false,
             "error": "Controller not found",
+            "code": ErrorCode::ControllerNotFound,
         })),
         Err(e) => Json(serde_json::json!({
             "ok": false,
@@ -182,19 +209,383 @@ pub async fn sync_controller(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 50)?;
 
-    let syncer =
-        crate::omada::OmadaSyncer::new(state.omada_manager.clone(), state.app_state.mongo.clone(), state.app_state.mysql.clone());
+    let syncer = crate::omada::OmadaSyncer::new(
+        state.omada_manager.clone(),
+        state.app_state.mongo.clone(),
+        state.app_state.mysql.clone(),
+    )
+    .with_notifier(state.notifier.clone());
 
     match syncer.sync_one(&id).await {
         Ok(()) => Ok(Json(serde_json::json!({
             "ok": true,
             "message": format!("Controller {} synced", id),
         }))),
-        Err(e) => Ok(Json(serde_json::json!({
-            "ok": false,
-            "error": e,
-        }))),
+        Err(e) => Ok(Json(controller_failure(&e))),
+    }
+}
+
+// ============================================================================
+// Webhook
+// ============================================================================
+
+/// POST /api/omada/webhook/:controller_id - Inbound controller event (shared secret, no session)
+pub async fn omada_webhook(
+    State(state): State<ProxyState>,
+    Path(controller_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    let mongo = &state.app_state.mongo;
+
+    let secret = mongo
+        .get_omada_webhook_secret(&controller_id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or(AppError::Unauthorized)?;
+
+    let event = webhook::parse_event(&body).map_err(AppError::BadRequest)?;
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if !webhook::verify(
+        &secret.secret,
+        &body,
+        header("x-webhook-secret"),
+        header("x-webhook-signature"),
+        event.shard_secret.as_deref(),
+    ) {
+        tracing::warn!(
+            "[OmadaWebhook] Rejected event for {}: bad secret",
+            controller_id
+        );
+        return Err(AppError::Unauthorized);
+    }
+
+    if let Some(ts) = event.timestamp_ms {
+        if chrono::Utc::now().timestamp_millis() - ts > webhook::MAX_EVENT_AGE_MS {
+            return Err(AppError::BadRequest("Stale event".to_string()));
+        }
+    }
+
+    if !state
+        .omada_manager
+        .webhook_dedup()
+        .check_and_mark(&controller_id, &event.event_id)
+    {
+        return Ok(Json(serde_json::json!({
+            "ok": true,
+            "duplicate": true,
+        })));
+    }
+
+    let started = std::time::Instant::now();
+    let result = webhook::apply_event(mongo, &state.app_state.mysql, &controller_id, &event).await;
+
+    let (status, applied, error) = match &result {
+        Ok(applied) => ("success", applied.clone(), None),
+        Err(e) => {
+            tracing::warn!("[OmadaWebhook] Apply failed for {}: {}", controller_id, e);
+            // Marked up front so concurrent deliveries stay deduplicated;
+            // a failed apply must not swallow the controller's retry
+            state
+                .omada_manager
+                .webhook_dedup()
+                .unmark(&controller_id, &event.event_id);
+            ("error", Vec::new(), Some(e.clone()))
+        }
+    };
+
+    // Keep the raw event for audit, minus the shared secret
+    let mut raw = event.raw.clone();
+    if let Some(obj) = raw.as_object_mut() {
+        obj.remove("shardSecret");
+    }
+    let _ = mongo
+        .insert_operation_log(&OperationLogDoc {
+            operation_id: uuid::Uuid::new_v4().to_string(),
+            operation_type: "omada_webhook".to_string(),
+            initiated_by: "webhook".to_string(),
+            target: Some(controller_id.clone()),
+            status: status.to_string(),
+            result: Some(serde_json::json!({
+                "event_id": &event.event_id,
+                "kind": event.kind.as_str(),
+                "mac": &event.mac,
+                "applied": &applied,
+                "raw": raw,
+            })),
+            error,
+            duration_ms: Some(started.elapsed().as_millis() as u64),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            operator: None,
+        })
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "ok": result.is_ok(),
+        "kind": event.kind.as_str(),
+        "applied": applied,
+    })))
+}
+
+/// GET /api/omada/controllers/:id/webhook - Webhook configuration (secret not shown)
+pub async fn get_controller_webhook(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let secret = state
+        .app_state
+        .mongo
+        .get_omada_webhook_secret(&id)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "controller_id": id,
+        "path": format!("/api/omada/webhook/{}", id),
+        "configured": secret.is_some(),
+        "created_at": secret.as_ref().map(|s| &s.created_at),
+        "rotated_at": secret.as_ref().map(|s| &s.rotated_at),
+    })))
+}
+
+/// POST /api/omada/controllers/:id/webhook/secret - Generate or rotate the webhook secret (admin: permission >= 80)
+/// The secret is returned only once.
+pub async fn rotate_controller_webhook_secret(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mongo = &state.app_state.mongo;
+    if mongo
+        .get_omada_controller(&id)
+        .await
+        .map_err(AppError::database)?
+        .is_none()
+    {
+        return Err(AppError::coded(
+            ErrorCode::ControllerNotFound,
+            format!("Controller {} not found", id),
+        ));
+    }
+
+    let secret = webhook::generate_secret();
+    let doc = mongo
+        .set_omada_webhook_secret(&id, &secret)
+        .await
+        .map_err(AppError::database)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "omada_controller",
+            None,
+            "rotate_webhook_secret",
+            Some("webhook_secret"),
+            None,
+            Some(&id),
+            "api",
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "controller_id": id,
+        "path": format!("/api/omada/webhook/{}", id),
+        "secret": doc.secret,
+        "rotated_at": doc.rotated_at,
+    })))
+}
+
+/// GET /api/omada/controllers/:id/sites - Sites with their facility mapping
+/// and device/client counts
+pub async fn list_controller_sites(
+    State(state): State<ProxyState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let mongo = &state.app_state.mongo;
+    let ctrl = mongo
+        .get_omada_controller(&id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::ControllerNotFound,
+                format!("Controller {} not found", id),
+            )
+        })?;
+    let devices = mongo
+        .get_omada_devices(Some(&id), None)
+        .await
+        .map_err(AppError::database)?;
+    let clients = mongo
+        .get_omada_clients(Some(&id), None, None)
+        .await
+        .map_err(AppError::database)?;
+
+    let sites: Vec<_> = ctrl
+        .sites
+        .iter()
+        .map(|site| {
+            let site_devices = devices.iter().filter(|d| d.site_id == site.site_id);
+            let site_clients = clients.iter().filter(|c| c.site_id == site.site_id);
+            serde_json::json!({
+                "site_id": site.site_id,
+                "name": site.name,
+                "region": site.region,
+                "fid": site.fid,
+                "fid_display_name": site.fid_display_name,
+                "device_count": site_devices.clone().count(),
+                "online_device_count": site_devices.filter(|d| d.status == 1).count(),
+                "client_count": site_clients.clone().count(),
+                "active_client_count": site_clients.filter(|c| c.active).count(),
+            })
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "controller_id": id,
+        "unmapped": ctrl.sites.iter().filter(|s| s.fid.is_none()).count(),
+        "sites": sites,
+    })))
+}
+
+/// PUT /api/omada/controllers/:id/sites/:site_id - Set the site's facility
+/// (admin: permission >= 80). The fid is checked against the Aranea facility
+/// list when one is configured; topology entries of the site follow at once.
+pub async fn update_controller_site(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, site_id)): Path<(String, String)>,
+    Json(req): Json<UpdateSiteFacilityRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mongo = &state.app_state.mongo;
+    let ctrl = mongo
+        .get_omada_controller(&id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::ControllerNotFound,
+                format!("Controller {} not found", id),
+            )
+        })?;
+    let site = ctrl
+        .sites
+        .iter()
+        .find(|s| s.site_id == site_id)
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Site {} not found on controller {}", site_id, id))
+        })?;
+
+    let fid = req.fid.as_deref().map(str::trim).filter(|f| !f.is_empty());
+    let mut display_name = req
+        .fid_display_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string);
+
+    if let Some(fid) = fid {
+        let facilities =
+            state.aranea_client.list_facilities().await.map_err(|e| {
+                AppError::InternalError(format!("Facility list unavailable: {}", e))
+            })?;
+        if let Some(facilities) = facilities {
+            let facility = facilities.iter().find(|f| f.fid == fid).ok_or_else(|| {
+                let valid: Vec<&str> = facilities.iter().map(|f| f.fid.as_str()).collect();
+                AppError::BadRequest(format!(
+                    "Unknown fid {}; valid fids: {}",
+                    fid,
+                    valid.join(", ")
+                ))
+            })?;
+            if display_name.is_none() {
+                display_name = facility.name.clone();
+            }
+        }
+        // Same facility, no new name: keep the current one
+        if display_name.is_none() && site.fid.as_deref() == Some(fid) {
+            display_name = site.fid_display_name.clone();
+        }
     }
+
+    if site.fid.as_deref() == fid && site.fid_display_name == display_name {
+        return Ok(Json(serde_json::json!({
+            "ok": true,
+            "changed": false,
+            "site_id": site_id,
+            "fid": fid,
+            "fid_display_name": display_name,
+        })));
+    }
+
+    mongo
+        .set_omada_site_facility(&id, &site_id, fid, display_name.as_deref())
+        .await
+        .map_err(AppError::database)?;
+    let entries_updated = mongo
+        .set_user_object_detail_facility_for_site(&id, &site_id, fid, display_name.as_deref())
+        .await
+        .map_err(AppError::database)?;
+
+    // Facility attribution feeds billing: keep before/after in the audit log
+    let mapping = |fid: Option<&str>, name: Option<&str>| {
+        serde_json::json!({
+            "controller_id": id,
+            "site_id": site_id,
+            "fid": fid,
+            "fid_display_name": name,
+        })
+        .to_string()
+    };
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "omada_site",
+            None,
+            "update_facility",
+            Some("fid"),
+            Some(&mapping(
+                site.fid.as_deref(),
+                site.fid_display_name.as_deref(),
+            )),
+            Some(&mapping(fid, display_name.as_deref())),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    tracing::info!(
+        "Omada site {}/{} facility {:?} -> {:?} by {} ({} topology entries updated)",
+        id,
+        site_id,
+        site.fid,
+        fid,
+        user.sub,
+        entries_updated
+    );
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "changed": true,
+        "site_id": site_id,
+        "fid": fid,
+        "fid_display_name": display_name,
+        "entries_updated": entries_updated,
+    })))
 }
 
 // ============================================================================
@@ -224,6 +615,44 @@ pub async fn get_omada_devices(
     }
 }
 
+/// GET /api/omada/devices/:mac/ports - Switch port table from the last sync
+pub async fn get_omada_device_ports(
+    State(state): State<ProxyState>,
+    Path(mac): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mac = MacAddr::parse(&mac).map_err(AppError::BadRequest)?;
+    Ok(
+        match state
+            .app_state
+            .mongo
+            .get_omada_device_by_mac(mac.as_str())
+            .await
+        {
+            Ok(Some(device)) if device.device_type == "switch" => Json(serde_json::json!({
+                "ok": true,
+                "mac": device.mac,
+                "name": device.name,
+                "controller_id": device.controller_id,
+                "summary": crate::omada::ports::summarize(&device.ports),
+                "ports": device.ports,
+                "ports_synced_at": device.ports_synced_at,
+            })),
+            Ok(Some(device)) => Json(serde_json::json!({
+                "ok": false,
+                "error": format!("Device {} is a {}, not a switch", device.mac, device.device_type),
+            })),
+            Ok(None) => Json(serde_json::json!({
+                "ok": false,
+                "error": format!("Device {} not found", mac),
+            })),
+            Err(e) => Json(serde_json::json!({
+                "ok": false,
+                "error": e,
+            })),
+        },
+    )
+}
+
 /// GET /api/omada/clients - All clients
 pub async fn get_omada_clients(
     State(state): State<ProxyState>,
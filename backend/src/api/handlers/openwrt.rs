@@ -10,11 +10,17 @@ use axum::{
 use serde::Deserialize;
 
 use crate::api::auth_middleware::require_permission;
+use crate::db::mongo::openwrt::OpenWrtRouterUpdate;
 use crate::error::AppError;
+use crate::mac::MacAddr;
 use crate::models::{AuthUser, ConfirmQuery, ConfirmRequired};
 use crate::openwrt::OpenWrtManager;
+use crate::poll_schedule::PollOutcome;
 use crate::proxy::ProxyState;
 
+/// Allowed per-router poll interval (0 = syncer default)
+const POLL_INTERVAL_RANGE: std::ops::RangeInclusive<u32> = 10..=86400;
+
 // ============================================================================
 // Request types
 // ============================================================================
@@ -55,12 +61,13 @@ pub async fn register_router(
     Json(req): Json<RegisterRouterRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
+    let mac = MacAddr::parse(&req.mac).map_err(AppError::BadRequest)?;
 
     match state
         .openwrt_manager
         .register_router(
             &req.display_name,
-            &req.mac,
+            mac.as_str(),
             &req.ip,
             req.port.unwrap_or(22),
             &req.username,
@@ -116,6 +123,46 @@ pub async fn get_router(
     }
 }
 
+/// PUT /api/openwrt/routers/:id - Edit display name, poll interval and pause
+/// state (admin: permission >= 80)
+pub async fn update_router(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(req): Json<OpenWrtRouterUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    if let Some(interval) = req.poll_interval_sec {
+        if interval != 0 && !POLL_INTERVAL_RANGE.contains(&interval) {
+            return Err(AppError::BadRequest(format!(
+                "poll_interval_sec must be 0 (default) or {}..={}",
+                POLL_INTERVAL_RANGE.start(),
+                POLL_INTERVAL_RANGE.end()
+            )));
+        }
+    }
+
+    let mongo = &state.app_state.mongo;
+    let updated = mongo
+        .update_openwrt_router_settings(&id, &req)
+        .await
+        .map_err(AppError::database)?;
+    if !updated {
+        return Err(AppError::NotFound(format!("Router {} not found", id)));
+    }
+
+    let router = mongo
+        .get_openwrt_router(&id)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "router": router,
+    })))
+}
+
 /// DELETE /api/openwrt/routers/:id - Remove a router
 pub async fn delete_router(
     State(state): State<ProxyState>,
@@ -181,10 +228,14 @@ pub async fn poll_router(
     );
 
     match syncer.poll_one(&id).await {
-        Ok(()) => Ok(Json(serde_json::json!({
+        Ok(PollOutcome::Polled) => Ok(Json(serde_json::json!({
             "ok": true,
             "message": format!("Router {} polled", id),
         }))),
+        Ok(PollOutcome::Paused) => Ok(Json(serde_json::json!({
+            "ok": false,
+            "error": format!("Router {} is paused", id),
+        }))),
         Err(e) => Ok(Json(serde_json::json!({
             "ok": false,
             "error": e,
@@ -0,0 +1,289 @@
+//! Rate limit rule handlers (/api/security/rate-limits)
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode, FieldError};
+use crate::models::{
+    AuthUser, CreateRateLimitRuleRequest, RateLimitRule, UpdateRateLimitRuleRequest,
+};
+use crate::proxy::rate_limit::{self, RateLimitAction};
+use crate::proxy::ProxyState;
+
+use super::SuccessResponse;
+
+const MAX_COMMENT_LEN: usize = 500;
+
+/// A rule with the number of clients it currently tracks
+#[derive(Debug, Serialize)]
+pub struct RateLimitRuleStatus {
+    #[serde(flatten)]
+    pub rule: RateLimitRule,
+    pub tracked_clients: usize,
+}
+
+async fn load_rule(state: &ProxyState, id: i32) -> Result<RateLimitRule, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_rate_limit_rule(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::RateLimitRuleNotFound,
+                format!("Rate limit rule {} not found", id),
+            )
+        })
+}
+
+/// Trim the prefix and comment; every invalid field at once
+fn validate(rule: &mut RateLimitRule) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    rule.path_prefix = rule.path_prefix.trim().to_string();
+    if !rule.path_prefix.starts_with('/') || rule.path_prefix.len() > 255 {
+        errors.push(FieldError::new(
+            "path_prefix",
+            "must start with '/' and be at most 255 characters",
+        ));
+    }
+    if rule.requests_per_window < 1 {
+        errors.push(FieldError::new("requests_per_window", "must be at least 1"));
+    }
+    if !(1..=rate_limit::MAX_WINDOW_SECS).contains(&rule.window_sec) {
+        errors.push(FieldError::new(
+            "window_sec",
+            format!("must be between 1 and {}", rate_limit::MAX_WINDOW_SECS),
+        ));
+    }
+    if let Some(secs) = rule.block_duration_sec {
+        if rule.action != RateLimitAction::Block {
+            errors.push(FieldError::new(
+                "block_duration_sec",
+                "only applies to action block",
+            ));
+        } else if !(1..=rate_limit::MAX_BLOCK_SECS).contains(&secs) {
+            errors.push(FieldError::new(
+                "block_duration_sec",
+                format!("must be between 1 and {}", rate_limit::MAX_BLOCK_SECS),
+            ));
+        }
+    }
+    rule.comment = rule
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string);
+    if rule
+        .comment
+        .as_ref()
+        .is_some_and(|c| c.chars().count() > MAX_COMMENT_LEN)
+    {
+        errors.push(FieldError::new(
+            "comment",
+            format!("must be at most {} characters", MAX_COMMENT_LEN),
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+fn describe(rule: &RateLimitRule) -> String {
+    let action = match (rule.action, rule.block_duration_sec) {
+        (RateLimitAction::Block, Some(secs)) => format!("block {}s", secs),
+        (action, _) => action.as_str().to_string(),
+    };
+    format!(
+        "{} {}/{}s {}{}{}",
+        rule.path_prefix,
+        rule.requests_per_window,
+        rule.window_sec,
+        action,
+        if rule.exclude_lan { "" } else { " incl. LAN" },
+        if rule.enabled { "" } else { " (disabled)" }
+    )
+}
+
+async fn reload(state: &ProxyState) {
+    if let Err(e) = state.reload_rate_limits().await {
+        tracing::error!("Failed to reload rate limit rules: {}", e);
+    }
+}
+
+/// GET /api/security/rate-limits - List rules with their tracked client counts
+pub async fn list_rate_limit_rules(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let tracked = state.rate_limiter.tracked_clients();
+    let rules: Vec<RateLimitRuleStatus> = state
+        .app_state
+        .mysql
+        .list_rate_limit_rules()
+        .await?
+        .into_iter()
+        .map(|rule| RateLimitRuleStatus {
+            tracked_clients: tracked.get(&rule.id).copied().unwrap_or(0),
+            rule,
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "enabled": state.rate_limiter.is_enabled(),
+        "rules": rules,
+    })))
+}
+
+/// POST /api/security/rate-limits - Add a rule (admin: permission >= 80)
+pub async fn create_rate_limit_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateRateLimitRuleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let now = Utc::now();
+    let mut rule = RateLimitRule {
+        id: 0,
+        path_prefix: req.path_prefix,
+        requests_per_window: req.requests_per_window,
+        window_sec: req.window_sec,
+        action: req.action,
+        block_duration_sec: req.block_duration_sec,
+        exclude_lan: req.exclude_lan.unwrap_or(true),
+        enabled: req.enabled.unwrap_or(true),
+        comment: req.comment,
+        created_at: now,
+        updated_at: now,
+    };
+    validate(&mut rule)?;
+
+    let id = state.app_state.mysql.create_rate_limit_rule(&rule).await?;
+    let rule = load_rule(&state, id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "rate_limit_rule",
+            Some(id),
+            "create",
+            None,
+            None,
+            Some(&describe(&rule)),
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+/// PUT /api/security/rate-limits/:id - Update a rule (admin: permission >= 80)
+pub async fn update_rate_limit_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateRateLimitRuleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mut rule = load_rule(&state, id).await?;
+    let old = describe(&rule);
+    if let Some(path_prefix) = req.path_prefix {
+        rule.path_prefix = path_prefix;
+    }
+    if let Some(requests) = req.requests_per_window {
+        rule.requests_per_window = requests;
+    }
+    if let Some(window_sec) = req.window_sec {
+        rule.window_sec = window_sec;
+    }
+    if let Some(action) = req.action {
+        rule.action = action;
+        // Switching to log drops the block duration unless one is sent
+        if action == RateLimitAction::Log && req.block_duration_sec.is_none() {
+            rule.block_duration_sec = None;
+        }
+    }
+    if let Some(block_duration_sec) = req.block_duration_sec {
+        rule.block_duration_sec = block_duration_sec;
+    }
+    if let Some(exclude_lan) = req.exclude_lan {
+        rule.exclude_lan = exclude_lan;
+    }
+    if let Some(enabled) = req.enabled {
+        rule.enabled = enabled;
+    }
+    if req.comment.is_some() {
+        rule.comment = req.comment;
+    }
+    validate(&mut rule)?;
+
+    if !state.app_state.mysql.update_rate_limit_rule(&rule).await? {
+        return Err(AppError::coded(
+            ErrorCode::RateLimitRuleNotFound,
+            format!("Rate limit rule {} not found", id),
+        ));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "rate_limit_rule",
+            Some(id),
+            "update",
+            None,
+            Some(&old),
+            Some(&describe(&rule)),
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok(Json(load_rule(&state, id).await?))
+}
+
+/// DELETE /api/security/rate-limits/:id - Remove a rule (admin: permission >= 80)
+pub async fn delete_rate_limit_rule(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let rule = load_rule(&state, id).await?;
+    state.app_state.mysql.delete_rate_limit_rule(id).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "rate_limit_rule",
+            Some(id),
+            "delete",
+            None,
+            Some(&describe(&rule)),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+    reload(&state).await;
+
+    Ok(Json(SuccessResponse::new("Rate limit rule deleted")))
+}
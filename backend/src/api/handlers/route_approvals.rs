@@ -0,0 +1,277 @@
+//! Route change approval handlers
+//!
+//! When `route_approval_required` is enabled, route mutations by users below
+//! permission 80 are stored in `route_pending_changes`; admins review them here.
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::{
+    AuthUser, CreateRouteRequest, ReviewPendingChangeRequest, RoutePendingChange,
+    UpdateRouteRequest,
+};
+use crate::proxy::ProxyState;
+
+use super::routes::{
+    activation_precheck, apply_checked_update_route, apply_create_route, validate_create_route,
+    validate_update_route,
+};
+use super::SuccessResponse;
+
+/// Query parameters for POST /api/routes/pending/:id/approve
+#[derive(Debug, Deserialize)]
+pub struct ApproveChangeQuery {
+    /// Activate in the warming state if the activation pre-check fails
+    #[serde(default)]
+    pub warm: bool,
+}
+
+/// Query parameters for GET /api/routes/pending
+#[derive(Debug, Deserialize)]
+pub struct PendingChangesQuery {
+    /// "pending" (default), "approved", "rejected" or "all"
+    pub status: Option<String>,
+}
+
+/// GET /api/routes/pending - List proposed route changes (admin: permission >= 80)
+pub async fn list_pending_route_changes(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<PendingChangesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let status = match query.status.as_deref().unwrap_or("pending") {
+        "all" => None,
+        s @ ("pending" | "approved" | "rejected") => Some(s),
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "Invalid status '{}': expected pending, approved, rejected or all",
+                other
+            )))
+        }
+    };
+
+    let mut changes = state
+        .app_state
+        .mysql
+        .list_route_pending_changes(status)
+        .await?;
+    // Proposed upstream auth passwords are applied on approval, never shown
+    for change in &mut changes {
+        crate::masking::mask_credentials(&mut change.payload);
+    }
+    Ok(Json(changes))
+}
+
+async fn load_open_change(state: &ProxyState, id: i32) -> Result<RoutePendingChange, AppError> {
+    let change = state
+        .app_state
+        .mysql
+        .get_route_pending_change(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Pending change {} not found", id)))?;
+    if change.status != "pending" {
+        return Err(AppError::BadRequest(format!(
+            "Pending change {} is already {}",
+            id, change.status
+        )));
+    }
+    Ok(change)
+}
+
+/// Apply an approved change through the same path as a direct admin edit
+async fn apply_change(
+    state: &ProxyState,
+    approver: &AuthUser,
+    change: &RoutePendingChange,
+    warm: bool,
+) -> Result<i32, AppError> {
+    let invalid = |e: serde_json::Error| {
+        AppError::InternalError(format!("Stored change #{} is invalid: {}", change.id, e))
+    };
+
+    match change.action.as_str() {
+        "create" => {
+            let payload: CreateRouteRequest =
+                serde_json::from_value(change.payload.clone()).map_err(invalid)?;
+            validate_create_route(state, &payload).await?;
+            let id = apply_create_route(state, &approver.sub, &payload).await?;
+            let _ = state
+                .app_state
+                .mysql
+                .set_route_pending_change_route(change.id, id)
+                .await;
+            Ok(id)
+        }
+        "update" => {
+            let route_id = change.route_id.ok_or_else(|| {
+                AppError::InternalError(format!("Stored change #{} has no route", change.id))
+            })?;
+            let payload: UpdateRouteRequest =
+                serde_json::from_value(change.payload.clone()).map_err(invalid)?;
+            let old_route = validate_update_route(state, route_id, &payload).await?;
+            let activation = activation_precheck(state, old_route.as_ref(), &payload, warm)
+                .await
+                .map_err(|probe| {
+                    AppError::BadRequest(format!(
+                        "Activation refused: {} failed the health pre-check ({}); approve with ?warm=true to activate anyway",
+                        probe.target,
+                        probe.error.as_deref().unwrap_or("unhealthy")
+                    ))
+                })?;
+            apply_checked_update_route(
+                state,
+                approver,
+                route_id,
+                &payload,
+                old_route,
+                activation.as_ref(),
+            )
+            .await?;
+            Ok(route_id)
+        }
+        other => Err(AppError::InternalError(format!(
+            "Stored change #{} has unknown action '{}'",
+            change.id, other
+        ))),
+    }
+}
+
+/// POST /api/routes/pending/:id/approve - Approve and apply a proposed change
+/// (admin: permission >= 80)
+pub async fn approve_route_change(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(query): Query<ApproveChangeQuery>,
+    body: Option<Json<ReviewPendingChangeRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let comment = body.and_then(|Json(b)| b.comment);
+
+    let change = load_open_change(&state, id).await?;
+
+    // Claim first so two reviewers cannot apply the same change
+    if !state
+        .app_state
+        .mysql
+        .review_route_pending_change(id, "approved", &user.sub, comment.as_deref())
+        .await?
+    {
+        return Err(AppError::BadRequest(format!(
+            "Pending change {} was reviewed concurrently",
+            id
+        )));
+    }
+
+    let route_id = match apply_change(&state, &user, &change, query.warm).await {
+        Ok(route_id) => route_id,
+        Err(e) => {
+            // Leave it in the queue so it can be fixed up or rejected
+            let _ = state.app_state.mysql.reopen_route_pending_change(id).await;
+            return Err(e);
+        }
+    };
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route_pending_change",
+            Some(id),
+            "approve",
+            Some(&change.action),
+            Some(&format!("proposed by {}", change.proposed_by)),
+            Some(&format!("approved by {} (route #{})", user.sub, route_id)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Route Change Approved",
+            &format!(
+                "Pending change #{} ({} by {}) approved by {}",
+                id, change.action, change.proposed_by, user.sub
+            ),
+        )
+        .await;
+
+    tracing::info!(
+        "Approved route change #{} (proposed by {}, approved by {})",
+        id,
+        change.proposed_by,
+        user.sub
+    );
+
+    Ok(Json(SuccessResponse::with_id("Change approved", route_id)))
+}
+
+/// POST /api/routes/pending/:id/reject - Reject a proposed change
+/// (admin: permission >= 80)
+pub async fn reject_route_change(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    body: Option<Json<ReviewPendingChangeRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let comment = body.and_then(|Json(b)| b.comment);
+
+    let change = load_open_change(&state, id).await?;
+
+    if !state
+        .app_state
+        .mysql
+        .review_route_pending_change(id, "rejected", &user.sub, comment.as_deref())
+        .await?
+    {
+        return Err(AppError::BadRequest(format!(
+            "Pending change {} was reviewed concurrently",
+            id
+        )));
+    }
+
+    let rejected = match comment.as_deref() {
+        Some(c) => format!("rejected by {}: {}", user.sub, c),
+        None => format!("rejected by {}", user.sub),
+    };
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route_pending_change",
+            Some(id),
+            "reject",
+            Some(&change.action),
+            Some(&format!("proposed by {}", change.proposed_by)),
+            Some(&rejected),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Route Change Rejected",
+            &format!(
+                "Pending change #{} ({} by {}) {}",
+                id, change.action, change.proposed_by, rejected
+            ),
+        )
+        .await;
+
+    tracing::info!("Rejected route change #{} ({})", id, rejected);
+
+    Ok(Json(SuccessResponse::with_id("Change rejected", id)))
+}
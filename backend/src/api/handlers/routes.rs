@@ -1,21 +1,84 @@
 //! Proxy routes handlers
 
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Extension, Json,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::api::auth_middleware::require_permission;
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode, FieldError};
+use crate::health::reachability::{
+    is_ambiguous, subnet_claims, target_host_port, KnownNetwork, TcpProbe,
+};
+use crate::health::{
+    probe_grpc_target, probe_target, probe_url, TargetProbe, MAX_HEALTH_CHECK_INTERVAL_SEC,
+    MIN_HEALTH_CHECK_INTERVAL_SEC,
+};
 use crate::models::{
-    AuthUser, ConfirmQuery, ConfirmRequired, CreateRouteRequest, UpdateRouteRequest,
+    AuthUser, ConfirmRequired, CreateRouteRequest, ProxyRoute, RouteSecurityHeaders,
+    UpdateRouteRequest,
+};
+use crate::proxy::canary::{CanarySideStats, CanaryStats};
+use crate::proxy::failover::{fallback_targets_column, normalize_fallback_targets};
+use crate::proxy::header_rewrite::RouteHeaderRewrite;
+use crate::proxy::methods::{allowed_methods_column, normalize_allowed_methods};
+use crate::proxy::security_headers::{EffectiveSecurityHeaders, TRACKED_HEADERS};
+use crate::proxy::{
+    cache, failover, limits, maintenance_page, trace, upstream, upstream_auth, ProxyState,
 };
-use crate::proxy::ProxyState;
+use crate::route_sync::{
+    diff_route, resolve_host, route_key, RouteKey, RouteSyncRequest, SyncAction, SyncError,
+    SyncSkip, SyncStatus,
+};
+use crate::route_versions::{self, DEFAULT_RETENTION, MIN_RETENTION};
 
 use super::SuccessResponse;
 
+/// Default retention for soft-deleted routes when the setting is missing
+const DEFAULT_ROUTE_DELETED_RETENTION_DAYS: i32 = 30;
+
+/// Query parameters for GET /api/routes
+#[derive(Debug, Deserialize)]
+pub struct ListRoutesQuery {
+    /// Include soft-deleted routes
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+/// Query parameters for PUT /api/routes/:id
+#[derive(Debug, Deserialize)]
+pub struct UpdateRouteQuery {
+    /// If activating a route fails the target pre-check, activate it anyway
+    /// in the warming state (503 until the first healthy check) instead of refusing
+    #[serde(default)]
+    pub warm: bool,
+}
+
+/// Query parameters for DELETE /api/routes/:id
+#[derive(Debug, Deserialize)]
+pub struct DeleteRouteQuery {
+    #[serde(default)]
+    pub confirm: bool,
+    /// Permanently delete instead of soft-deleting
+    #[serde(default)]
+    pub hard: bool,
+}
+
+/// Query parameters for POST /api/routes/purge
+#[derive(Debug, Deserialize)]
+pub struct PurgeRoutesQuery {
+    #[serde(default)]
+    pub confirm: bool,
+    /// Purge routes soft-deleted at least this many days ago (default: retention setting)
+    pub older_than_days: Option<i32>,
+}
+
 /// GET /api/server-routes - List routes with subnet matching info
 pub async fn list_server_routes(
     State(state): State<ProxyState>,
@@ -101,7 +164,13 @@ pub async fn list_server_routes(
             "priority": route.priority,
             "timeout_ms": route.timeout_ms,
             "websocket_support": route.websocket_support,
+            "grpc": route.grpc,
+            "admin_network_only": route.admin_network_only,
+            "expect_continue": route.expect_continue(),
             "ddns_config_id": route.ddns_config_id,
+            "owner_name": route.owner_name,
+            "owner_contact": route.owner_contact,
+            "team": route.team,
             "subnet": subnet_info,
             "fid": fid,
             "tid": tid,
@@ -111,10 +180,245 @@ pub async fn list_server_routes(
     Ok(Json(server_routes))
 }
 
-/// GET /api/routes - List all proxy routes
-pub async fn list_routes(State(state): State<ProxyState>) -> Result<impl IntoResponse, AppError> {
+/// Query parameters for GET /api/server-routes/analysis
+#[derive(Debug, Deserialize)]
+pub struct ServerRoutesAnalysisQuery {
+    /// Probe again instead of using results cached within the last minute (operator)
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Networks the gateway knows about: Omada site LANs (/24 around the
+/// gateway, as in list_server_routes), OpenWrt LANs and WireGuard peer
+/// allowed addresses
+async fn known_networks(state: &ProxyState) -> Vec<KnownNetwork> {
+    let mongo = &state.app_state.mongo;
+    let controllers = mongo.list_omada_controllers().await.unwrap_or_default();
+    let site_name = |controller_id: &str, site_id: &str| {
+        controllers
+            .iter()
+            .find(|c| c.controller_id == controller_id)
+            .and_then(|c| c.sites.iter().find(|s| s.site_id == site_id))
+            .map(|s| s.name.clone())
+            .unwrap_or_else(|| site_id.to_string())
+    };
+
+    let mut networks = Vec::new();
+    for dev in mongo
+        .get_omada_devices(None, None)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|d| d.device_type == "gateway")
+    {
+        let Some(ip) = &dev.ip else { continue };
+        networks.extend(KnownNetwork::new(
+            ip,
+            24,
+            "omada_site",
+            format!("omada:{}/{}", dev.controller_id, dev.site_id),
+            format!(
+                "Omada site {} ({})",
+                site_name(&dev.controller_id, &dev.site_id),
+                dev.name
+            ),
+        ));
+    }
+    for router in mongo.list_openwrt_routers().await.unwrap_or_default() {
+        let Some(lan_ip) = &router.lan_ip else {
+            continue;
+        };
+        networks.extend(KnownNetwork::new(
+            lan_ip,
+            24,
+            "openwrt_lan",
+            format!("openwrt:{}", router.router_id),
+            format!("OpenWrt {} LAN", router.display_name),
+        ));
+    }
+    for peer in mongo
+        .get_omada_wg_peers(None, None)
+        .await
+        .unwrap_or_default()
+    {
+        for allowed in &peer.allow_address {
+            networks.extend(KnownNetwork::new(
+                allowed,
+                32,
+                "omada_wg_peer",
+                format!(
+                    "omada_wg:{}/{}/{}",
+                    peer.controller_id, peer.site_id, peer.peer_id
+                ),
+                format!(
+                    "WireGuard peer {} ({}, site {})",
+                    peer.name,
+                    peer.interface_name,
+                    site_name(&peer.controller_id, &peer.site_id)
+                ),
+            ));
+        }
+    }
+    networks
+}
+
+/// GET /api/server-routes/analysis - Probe each distinct route target over TCP
+/// (cached for a minute) and flag targets claimed by more than one known network
+pub async fn server_routes_analysis(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ServerRoutesAnalysisQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if query.refresh {
+        require_permission(&user, 50)?;
+    }
+
     let routes = state.app_state.mysql.list_routes().await?;
-    Ok(Json(routes))
+    let mut targets: Vec<(String, u16)> = routes
+        .iter()
+        .filter_map(|r| target_host_port(&r.target))
+        .collect();
+    targets.sort();
+    targets.dedup();
+
+    let (probes, networks) = tokio::join!(
+        state.target_probes.probe_all(&targets, query.refresh),
+        known_networks(&state)
+    );
+    let probes: HashMap<(String, u16), TcpProbe> = probes
+        .into_iter()
+        .map(|p| ((p.host.clone(), p.port), p))
+        .collect();
+
+    let mut analyzed = Vec::new();
+    // Conflicting networks per target IP, with the routes pointing there
+    let mut conflicts: BTreeMap<String, (Vec<KnownNetwork>, Vec<i32>)> = BTreeMap::new();
+    for route in &routes {
+        let host_port = target_host_port(&route.target);
+        let probe = host_port.as_ref().and_then(|key| probes.get(key));
+        let target_ip = host_port
+            .as_ref()
+            .and_then(|(host, _)| host.parse::<IpAddr>().ok())
+            .or_else(|| probe.and_then(|p| p.resolved_ip.as_deref()?.parse().ok()));
+        let claims = target_ip
+            .map(|ip| subnet_claims(ip, &networks))
+            .unwrap_or_default();
+        let ambiguous = is_ambiguous(&claims);
+        if let (true, Some(ip)) = (ambiguous, target_ip) {
+            let entry = conflicts
+                .entry(ip.to_string())
+                .or_insert_with(|| (claims.clone(), Vec::new()));
+            entry.1.push(route.id);
+        }
+
+        analyzed.push(serde_json::json!({
+            "id": route.id,
+            "path": route.path,
+            "target": route.target,
+            "active": route.active,
+            "target_ip": target_ip.map(|ip| ip.to_string()),
+            "reachability": probe,
+            "claims": claims,
+            "ambiguous": ambiguous,
+        }));
+    }
+
+    let reachable = probes.values().filter(|p| p.reachable).count();
+    Ok(Json(serde_json::json!({
+        "routes": analyzed,
+        "targets_probed": probes.len(),
+        "reachable": reachable,
+        "unreachable": probes.len() - reachable,
+        "known_networks": networks,
+        "conflicts": conflicts
+            .into_iter()
+            .map(|(ip, (networks, route_ids))| serde_json::json!({
+                "target_ip": ip,
+                "networks": networks,
+                "route_ids": route_ids,
+            }))
+            .collect::<Vec<_>>(),
+    })))
+}
+
+/// GET /api/routes - List proxy routes (`?include_deleted=true` adds soft-deleted ones)
+pub async fn list_routes(
+    State(state): State<ProxyState>,
+    Query(query): Query<ListRoutesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let routes = if query.include_deleted {
+        state
+            .app_state
+            .mysql
+            .list_routes_including_deleted()
+            .await?
+    } else {
+        state.app_state.mysql.list_routes().await?
+    };
+
+    // Flag routes whose DDNS hostname diverged (see ddns::dns_watch)
+    let dns_checks = state
+        .app_state
+        .mongo
+        .list_ddns_dns_checks()
+        .await
+        .unwrap_or_default();
+    let mut body = Vec::with_capacity(routes.len());
+    for route in routes {
+        let indicator = route.ddns_config_id.and_then(|id| {
+            dns_checks
+                .iter()
+                .find(|c| c.ddns_config_id == id)
+                .and_then(|c| c.indicator())
+        });
+        let mut value =
+            serde_json::to_value(&route).map_err(|e| AppError::InternalError(e.to_string()))?;
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(
+                "dns_mismatch".to_string(),
+                indicator.unwrap_or(serde_json::Value::Null),
+            );
+            obj.insert(
+                "upstream_auth_configured".to_string(),
+                route.upstream_auth_configured().into(),
+            );
+        }
+        body.push(value);
+    }
+    Ok(Json(body))
+}
+
+/// Query parameters for GET /api/routes/test
+#[derive(Debug, Deserialize)]
+pub struct RouteTestQuery {
+    pub path: String,
+    pub host: Option<String>,
+    /// Defaults to GET
+    pub method: Option<String>,
+}
+
+/// GET /api/routes/test - Dry-run route matching for a path, host and method
+pub async fn test_route(
+    State(state): State<ProxyState>,
+    Query(query): Query<RouteTestQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    if !query.path.starts_with('/') {
+        return Err(AppError::validation("path", "must start with /"));
+    }
+    let method = normalize_allowed_methods(&[query.method.unwrap_or_else(|| "GET".to_string())])
+        .map_err(|e| AppError::validation("method", e))?
+        .remove(0);
+
+    let result = state
+        .dry_run_route(&query.path, query.host.as_deref(), &method)
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "path": query.path,
+        "host": query.host,
+        "method": method,
+        "result": result,
+    })))
 }
 
 /// GET /api/routes/:id - Get a single route
@@ -122,37 +426,494 @@ pub async fn get_route(
     State(state): State<ProxyState>,
     Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
-    let route = state
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+
+    let effective = effective_security_headers(&state, &route).await;
+    let mut body =
+        serde_json::to_value(&route).map_err(|e| AppError::InternalError(e.to_string()))?;
+    body["security_headers_effective"] = serde_json::json!(effective);
+    body["upstream_auth_configured"] = route.upstream_auth_configured().into();
+
+    Ok(Json(body))
+}
+
+/// Global security header policy merged with the route's override
+async fn effective_security_headers(
+    state: &ProxyState,
+    route: &ProxyRoute,
+) -> EffectiveSecurityHeaders {
+    EffectiveSecurityHeaders::merge(
+        &*state.security_headers.read().await,
+        RouteSecurityHeaders::from_column(route.security_headers.as_deref()).as_ref(),
+    )
+}
+
+/// GET /api/routes/security-headers/report - Tracked headers missing on recent responses
+///
+/// Samples are the last responses actually sent per route (after injection),
+/// kept in memory since startup.
+pub async fn get_security_headers_report(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let routes = state.app_state.mysql.list_routes().await?;
+
+    let mut report = Vec::with_capacity(routes.len());
+    for route in &routes {
+        let effective = effective_security_headers(&state, route).await;
+        let sample = state.security_header_samples.for_route(route.id);
+        // Missing headers the current policy would not add either
+        let uncovered: Vec<&str> = sample
+            .missing
+            .keys()
+            .map(String::as_str)
+            .filter(|name| {
+                !(effective.enabled && effective.headers.iter().any(|h| h.name == *name))
+            })
+            .collect();
+        report.push(serde_json::json!({
+            "route_id": route.id,
+            "path": route.path,
+            "active": route.active,
+            "policy_enabled": effective.enabled,
+            "policy_source": effective.source,
+            "samples": sample.samples,
+            "missing": sample.missing,
+            "uncovered_by_policy": uncovered,
+        }));
+    }
+
+    Ok(Json(serde_json::json!({
+        "tracked_headers": TRACKED_HEADERS,
+        "routes": report,
+    })))
+}
+
+/// Setting: route mutations by non-admins are queued for approval
+pub const SETTING_ROUTE_APPROVAL_REQUIRED: &str = "route_approval_required";
+
+/// Whether a route mutation by `user` must go through the approval queue.
+///
+/// Admins (permission >= 80) always apply changes directly. When
+/// `route_approval_required` is enabled, operators (>= 50) may propose
+/// changes instead; otherwise the admin requirement stands.
+pub(crate) async fn route_change_needs_approval(
+    state: &ProxyState,
+    user: &AuthUser,
+) -> Result<bool, AppError> {
+    let admin = require_permission(user, 80);
+    if admin.is_ok() {
+        return Ok(false);
+    }
+
+    let approval_enabled = state
         .app_state
         .mysql
-        .get_route(id)
+        .get_setting_bool(SETTING_ROUTE_APPROVAL_REQUIRED)
+        .await
+        .unwrap_or(false);
+    if approval_enabled {
+        require_permission(user, 50)?;
+        Ok(true)
+    } else {
+        admin.map(|_| false)
+    }
+}
+
+fn validate_target(target: &str, errors: &mut Vec<FieldError>) {
+    if !target.starts_with("http://") && !target.starts_with("https://") {
+        errors.push(FieldError::new(
+            "target",
+            "Target must be a valid HTTP(S) URL",
+        ));
+    }
+}
+
+fn validate_path(path: &str, errors: &mut Vec<FieldError>) {
+    if !path.starts_with('/') {
+        errors.push(FieldError::new("path", "Path must start with /"));
+    }
+}
+
+/// Canary share must be 0-100 and the canary target an HTTP(S) URL
+fn validate_canary(target: Option<&str>, percent: Option<i32>, errors: &mut Vec<FieldError>) {
+    if let Some(target) = target.filter(|t| !t.trim().is_empty()) {
+        if !target.starts_with("http://") && !target.starts_with("https://") {
+            errors.push(FieldError::new(
+                "canary_target",
+                "Canary target must be a valid HTTP(S) URL",
+            ));
+        }
+    }
+    if percent.is_some_and(|p| !(0..=100).contains(&p)) {
+        errors.push(FieldError::new(
+            "canary_percent",
+            "Canary percent must be between 0 and 100",
+        ));
+    }
+}
+
+/// A grpc.health.v1 service needs a gRPC route and must fit the column
+fn validate_grpc(grpc: bool, health_service: Option<&str>, errors: &mut Vec<FieldError>) {
+    let Some(service) = health_service else {
+        return;
+    };
+    if !grpc {
+        errors.push(FieldError::new(
+            "grpc_health_service",
+            "gRPC health checks require grpc to be enabled",
+        ));
+    }
+    if service.trim().len() > 255 || service.chars().any(char::is_control) {
+        errors.push(FieldError::new(
+            "grpc_health_service",
+            "Service name must be at most 255 characters without control characters",
+        ));
+    }
+}
+
+/// A health check path must be an absolute path without whitespace; the
+/// interval must lie within the scheduler's bounds
+fn validate_health_check(
+    path: Option<&str>,
+    interval_sec: Option<i32>,
+    errors: &mut Vec<FieldError>,
+) {
+    if let Some(path) = path.map(str::trim).filter(|p| !p.is_empty()) {
+        if !path.starts_with('/')
+            || path.len() > 255
+            || path.chars().any(|c| c.is_whitespace() || c.is_control())
+        {
+            errors.push(FieldError::new(
+                "health_check_path",
+                "Health check path must start with / and be at most 255 characters without whitespace",
+            ));
+        }
+    }
+    if let Some(seconds) = interval_sec.filter(|s| *s != 0) {
+        if !(MIN_HEALTH_CHECK_INTERVAL_SEC..=MAX_HEALTH_CHECK_INTERVAL_SEC).contains(&seconds) {
+            errors.push(FieldError::new(
+                "health_check_interval_sec",
+                format!(
+                    "Health check interval must be between {} and {} seconds",
+                    MIN_HEALTH_CHECK_INTERVAL_SEC, MAX_HEALTH_CHECK_INTERVAL_SEC
+                ),
+            ));
+        }
+    }
+}
+
+/// Response cache TTL: 0 (off) up to a day
+fn validate_cache_ttl(ttl: Option<i32>, errors: &mut Vec<FieldError>) {
+    if ttl.is_some_and(|t| !(0..=cache::MAX_TTL_SECS).contains(&t)) {
+        errors.push(FieldError::new(
+            "cache_ttl_sec",
+            format!(
+                "Cache TTL must be between 0 and {} seconds",
+                cache::MAX_TTL_SECS
+            ),
+        ));
+    }
+}
+
+/// Upstream Basic auth: a valid user, and no password without one
+fn validate_upstream_auth(
+    user: Option<&str>,
+    password: Option<&str>,
+    errors: &mut Vec<FieldError>,
+) {
+    let user = user.map(str::trim).filter(|u| !u.is_empty());
+    if let Some(Err(e)) = user.map(upstream_auth::validate_user) {
+        errors.push(FieldError::new("upstream_auth_user", e));
+    }
+    if let Some(password) = password.filter(|p| !p.is_empty()) {
+        if user.is_none() {
+            errors.push(FieldError::new(
+                "upstream_auth_password",
+                "An upstream auth password requires upstream_auth_user",
+            ));
+        }
+        if let Err(e) = upstream_auth::validate_password(password) {
+            errors.push(FieldError::new("upstream_auth_password", e));
+        }
+    }
+}
+
+/// VALIDATION_FAILED with every collected field error
+fn field_errors(errors: Vec<FieldError>) -> Result<(), AppError> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+/// Validate a route creation request (also used when approving a proposal)
+pub(crate) async fn validate_create_route(
+    state: &ProxyState,
+    payload: &CreateRouteRequest,
+) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+    validate_path(&payload.path, &mut errors);
+    validate_target(&payload.target, &mut errors);
+    validate_security_headers(payload.security_headers.as_ref(), &mut errors);
+    validate_allowed_methods(payload.allowed_methods.as_deref(), &mut errors);
+    validate_canary(
+        payload.canary_target.as_deref(),
+        payload.canary_percent,
+        &mut errors,
+    );
+    validate_grpc(
+        payload.grpc,
+        payload.grpc_health_service.as_deref(),
+        &mut errors,
+    );
+    validate_health_check(
+        payload.health_check_path.as_deref(),
+        payload.health_check_interval_sec,
+        &mut errors,
+    );
+    validate_upstream_auth(
+        payload.upstream_auth_user.as_deref(),
+        payload.upstream_auth_password.as_deref(),
+        &mut errors,
+    );
+    validate_cache_ttl(Some(payload.cache_ttl_sec), &mut errors);
+    validate_header_rewrite(payload.header_rewrite.as_ref(), &mut errors);
+    validate_upstream_tuning(
+        payload.connect_timeout_ms,
+        payload.max_idle_per_host,
+        &mut errors,
+    );
+    validate_failover(
+        payload.fallback_targets.as_deref(),
+        Some(payload.retry_count),
+        &mut errors,
+    );
+    validate_maintenance_message(payload.maintenance_message.as_deref(), &mut errors);
+    validate_body_limit(payload.max_body_bytes, &mut errors);
+    field_errors(errors)?;
+
+    check_deleted_route_conflict(state, &payload.path, payload.ddns_config_id).await
+}
+
+/// A soft-deleted route still holds its path / DDNS slot (the unique key
+/// covers deleted rows), so taking it fails until that route is purged
+async fn check_deleted_route_conflict(
+    state: &ProxyState,
+    path: &str,
+    ddns_config_id: Option<i32>,
+) -> Result<(), AppError> {
+    if let Some(deleted) = state
+        .app_state
+        .mysql
+        .find_deleted_route_conflict(path, ddns_config_id)
         .await?
-        .ok_or_else(|| AppError::NotFound(format!("Route {} not found", id)))?;
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteDeleted,
+            format!(
+                "Deleted route #{} uses path {}; restore or purge it first",
+                deleted.id, path
+            ),
+        )
+        .with_details(serde_json::json!({ "route_id": deleted.id })));
+    }
 
-    Ok(Json(route))
+    Ok(())
 }
 
-/// POST /api/routes - Create a new route (admin: permission >= 80)
-pub async fn create_route(
-    State(state): State<ProxyState>,
-    Extension(user): Extension<AuthUser>,
-    Json(payload): Json<CreateRouteRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    require_permission(&user, 80)?;
+/// Reject security header overrides with invalid header names or values
+fn validate_security_headers(value: Option<&RouteSecurityHeaders>, errors: &mut Vec<FieldError>) {
+    if let Some(Err(e)) = value.map(|v| v.clone().normalized()) {
+        errors.push(FieldError::new("security_headers", e));
+    }
+}
+
+/// Upstream tuning: a connect timeout of at most 5 minutes (0 = shared
+/// client) and a bounded idle pool
+fn validate_upstream_tuning(
+    connect_timeout_ms: Option<i32>,
+    max_idle_per_host: Option<i32>,
+    errors: &mut Vec<FieldError>,
+) {
+    if connect_timeout_ms.is_some_and(|ms| !(0..=upstream::MAX_CONNECT_TIMEOUT_MS).contains(&ms)) {
+        errors.push(FieldError::new(
+            "connect_timeout_ms",
+            format!(
+                "Connect timeout must be between 0 and {} ms",
+                upstream::MAX_CONNECT_TIMEOUT_MS
+            ),
+        ));
+    }
+    if max_idle_per_host.is_some_and(|n| !(0..=upstream::MAX_IDLE_PER_HOST).contains(&n)) {
+        errors.push(FieldError::new(
+            "max_idle_per_host",
+            format!(
+                "Idle connections per host must be between 0 and {}",
+                upstream::MAX_IDLE_PER_HOST
+            ),
+        ));
+    }
+}
+
+/// Maintenance page text of a bounded length
+fn validate_maintenance_message(message: Option<&str>, errors: &mut Vec<FieldError>) {
+    if message.is_some_and(|m| m.trim().chars().count() > maintenance_page::MAX_MESSAGE_LEN) {
+        errors.push(FieldError::new(
+            "maintenance_message",
+            format!(
+                "Maintenance message must be at most {} characters",
+                maintenance_page::MAX_MESSAGE_LEN
+            ),
+        ));
+    }
+}
+
+/// Request body limit of at most 128 MiB (0 = the global limit)
+fn validate_body_limit(max_body_bytes: Option<i64>, errors: &mut Vec<FieldError>) {
+    if max_body_bytes.is_some_and(|bytes| !(0..=limits::MAX_ROUTE_BODY_BYTES).contains(&bytes)) {
+        errors.push(FieldError::new(
+            "max_body_bytes",
+            format!(
+                "Body limit must be between 0 and {} bytes",
+                limits::MAX_ROUTE_BODY_BYTES
+            ),
+        ));
+    }
+}
+
+/// Fallback targets: HTTP(S) URLs, a bounded list; retries bounded per target
+fn validate_failover(
+    fallback_targets: Option<&[String]>,
+    retry_count: Option<i32>,
+    errors: &mut Vec<FieldError>,
+) {
+    if let Some(Err(e)) = fallback_targets.map(normalize_fallback_targets) {
+        errors.push(FieldError::new("fallback_targets", e));
+    }
+    if retry_count.is_some_and(|n| !(0..=failover::MAX_RETRY_COUNT).contains(&n)) {
+        errors.push(FieldError::new(
+            "retry_count",
+            format!(
+                "Retry count must be between 0 and {}",
+                failover::MAX_RETRY_COUNT
+            ),
+        ));
+    }
+}
 
-    // Validate path format
-    if !payload.path.starts_with('/') {
-        return Err(AppError::BadRequest("Path must start with /".to_string()));
+/// Reject header rewrite rules naming invalid or gateway-managed headers
+fn validate_header_rewrite(value: Option<&RouteHeaderRewrite>, errors: &mut Vec<FieldError>) {
+    if let Some(Err(e)) = value.map(|v| v.clone().normalized()) {
+        errors.push(FieldError::new("header_rewrite", e));
     }
+}
+
+/// Reject empty method lists and methods that are not valid HTTP tokens
+fn validate_allowed_methods(value: Option<&[String]>, errors: &mut Vec<FieldError>) {
+    if let Some(Err(e)) = value.map(normalize_allowed_methods) {
+        errors.push(FieldError::new("allowed_methods", e));
+    }
+}
 
-    // Validate target URL
-    if !payload.target.starts_with("http://") && !payload.target.starts_with("https://") {
-        return Err(AppError::BadRequest(
-            "Target must be a valid HTTP(S) URL".to_string(),
+/// Validate a route update request; returns the current route
+pub(crate) async fn validate_update_route(
+    state: &ProxyState,
+    id: i32,
+    payload: &UpdateRouteRequest,
+) -> Result<Option<ProxyRoute>, AppError> {
+    let old_route = state.app_state.mysql.get_route(id).await?;
+    if old_route.as_ref().is_some_and(|r| r.deleted_at.is_some()) {
+        return Err(AppError::coded(
+            ErrorCode::RouteDeleted,
+            format!("Route {} is deleted; restore it before updating", id),
         ));
     }
 
-    let id = state.app_state.mysql.create_route(&payload).await?;
+    let mut errors = Vec::new();
+    if let Some(ref path) = payload.path {
+        validate_path(path, &mut errors);
+    }
+    if let Some(ref target) = payload.target {
+        validate_target(target, &mut errors);
+    }
+    validate_security_headers(payload.security_headers.as_ref(), &mut errors);
+    validate_allowed_methods(
+        payload.allowed_methods.as_ref().and_then(|m| m.as_deref()),
+        &mut errors,
+    );
+    validate_canary(
+        payload.canary_target.as_ref().and_then(|t| t.as_deref()),
+        payload.canary_percent.flatten(),
+        &mut errors,
+    );
+    if let Some(Some(service)) = &payload.grpc_health_service {
+        let grpc = payload
+            .grpc
+            .or(old_route.as_ref().map(|r| r.grpc))
+            .unwrap_or(false);
+        validate_grpc(grpc, Some(service), &mut errors);
+    }
+    validate_health_check(
+        payload
+            .health_check_path
+            .as_ref()
+            .and_then(|p| p.as_deref()),
+        payload.health_check_interval_sec.flatten(),
+        &mut errors,
+    );
+    if payload.upstream_auth_user.is_some() || payload.upstream_auth_password.is_some() {
+        let user = match &payload.upstream_auth_user {
+            Some(v) => v.as_deref(),
+            None => old_route
+                .as_ref()
+                .and_then(|r| r.upstream_auth_user.as_deref()),
+        };
+        validate_upstream_auth(user, payload.upstream_auth_password.as_deref(), &mut errors);
+    }
+    validate_cache_ttl(payload.cache_ttl_sec, &mut errors);
+    validate_header_rewrite(payload.header_rewrite.as_ref(), &mut errors);
+    validate_upstream_tuning(
+        payload.connect_timeout_ms.flatten(),
+        payload.max_idle_per_host.flatten(),
+        &mut errors,
+    );
+    validate_failover(
+        payload.fallback_targets.as_ref().and_then(|t| t.as_deref()),
+        payload.retry_count,
+        &mut errors,
+    );
+    validate_maintenance_message(
+        payload
+            .maintenance_message
+            .as_ref()
+            .and_then(|m| m.as_deref()),
+        &mut errors,
+    );
+    validate_body_limit(payload.max_body_bytes.flatten(), &mut errors);
+    field_errors(errors)?;
+
+    if let Some(old) = &old_route {
+        let path = payload.path.as_deref().unwrap_or(&old.path);
+        let ddns_config_id = payload.ddns_config_id.unwrap_or(old.ddns_config_id);
+        if path != old.path || ddns_config_id != old.ddns_config_id {
+            check_deleted_route_conflict(state, path, ddns_config_id).await?;
+        }
+    }
+
+    Ok(old_route)
+}
+
+/// Insert a validated route, then version, audit, notify and reload
+pub(crate) async fn apply_create_route(
+    state: &ProxyState,
+    actor: &str,
+    payload: &CreateRouteRequest,
+) -> Result<i32, AppError> {
+    let id = state.app_state.mysql.create_route(payload).await?;
+    record_route_version(state, id, actor, "create", None).await;
 
     // Log audit
     let _ = state
@@ -165,7 +926,7 @@ pub async fn create_route(
             None,
             None,
             Some(&format!("{} -> {}", payload.path, payload.target)),
-            "api",
+            actor,
             None,
         )
         .await;
@@ -185,166 +946,698 @@ pub async fn create_route(
     }
 
     tracing::info!("Created route {} -> {}", payload.path, payload.target);
-
-    Ok((
-        StatusCode::CREATED,
-        Json(SuccessResponse::with_id("Route created", id)),
-    ))
+    Ok(id)
 }
 
-/// PUT /api/routes/:id - Update a route (admin: permission >= 80)
-pub async fn update_route(
-    State(state): State<ProxyState>,
-    Extension(user): Extension<AuthUser>,
-    Path(id): Path<i32>,
-    Json(payload): Json<UpdateRouteRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    require_permission(&user, 80)?;
+/// Apply a validated route update, then audit, notify (incl. the owner) and reload
+pub(crate) async fn apply_update_route(
+    state: &ProxyState,
+    actor: &AuthUser,
+    id: i32,
+    payload: &UpdateRouteRequest,
+    old_route: Option<ProxyRoute>,
+) -> Result<(), AppError> {
+    let updated = state.app_state.mysql.update_route(id, payload).await?;
 
-    // Get old route for audit log
-    let old_route = state.app_state.mysql.get_route(id).await?;
+    if !updated {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(state, id, &actor.sub, "update", None).await;
 
-    // Validate path format if provided
-    if let Some(ref path) = payload.path {
-        if !path.starts_with('/') {
-            return Err(AppError::BadRequest("Path must start with /".to_string()));
+    // Log audit for each changed field
+    if let Some(ref old) = old_route {
+        let mut changes = Vec::new();
+
+        if let Some(ref new_path) = payload.path {
+            if &old.path != new_path {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("path"),
+                        Some(&old.path),
+                        Some(new_path),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!("path: `{}` → `{}`", old.path, new_path));
+            }
         }
-    }
 
-    // Validate target URL if provided
-    if let Some(ref target) = payload.target {
-        if !target.starts_with("http://") && !target.starts_with("https://") {
-            return Err(AppError::BadRequest(
-                "Target must be a valid HTTP(S) URL".to_string(),
-            ));
+        if let Some(ref new_target) = payload.target {
+            if &old.target != new_target {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("target"),
+                        Some(&old.target),
+                        Some(new_target),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!("target: `{}` → `{}`", old.target, new_target));
+            }
         }
-    }
 
-    let updated = state.app_state.mysql.update_route(id, &payload).await?;
-
-    if updated {
-        // Log audit for each changed field
-        if let Some(ref old) = old_route {
-            let mut changes = Vec::new();
-
-            if let Some(ref new_path) = payload.path {
-                if &old.path != new_path {
-                    let _ = state
-                        .app_state
-                        .mysql
-                        .log_audit(
-                            "route",
-                            Some(id),
-                            "update",
-                            Some("path"),
-                            Some(&old.path),
-                            Some(new_path),
-                            "api",
-                            None,
-                        )
-                        .await;
-                    changes.push(format!("path: `{}` → `{}`", old.path, new_path));
-                }
+        if let Some(new_active) = payload.active {
+            if old.active != new_active {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("active"),
+                        Some(&old.active.to_string()),
+                        Some(&new_active.to_string()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!("active: `{}` → `{}`", old.active, new_active));
             }
+        }
 
-            if let Some(ref new_target) = payload.target {
-                if &old.target != new_target {
-                    let _ = state
-                        .app_state
-                        .mysql
-                        .log_audit(
-                            "route",
-                            Some(id),
-                            "update",
-                            Some("target"),
-                            Some(&old.target),
-                            Some(new_target),
-                            "api",
-                            None,
-                        )
-                        .await;
-                    changes.push(format!("target: `{}` → `{}`", old.target, new_target));
-                }
+        if let Some(new_ws) = payload.websocket_support {
+            if old.websocket_support != new_ws {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("websocket_support"),
+                        Some(&old.websocket_support.to_string()),
+                        Some(&new_ws.to_string()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "websocket_support: `{}` → `{}`",
+                    old.websocket_support, new_ws
+                ));
             }
+        }
 
-            if let Some(new_active) = payload.active {
-                if old.active != new_active {
-                    let _ = state
-                        .app_state
-                        .mysql
-                        .log_audit(
-                            "route",
-                            Some(id),
-                            "update",
-                            Some("active"),
-                            Some(&old.active.to_string()),
-                            Some(&new_active.to_string()),
-                            "api",
-                            None,
-                        )
-                        .await;
-                    changes.push(format!("active: `{}` → `{}`", old.active, new_active));
-                }
+        if let Some(new_grpc) = payload.grpc {
+            if old.grpc != new_grpc {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("grpc"),
+                        Some(&old.grpc.to_string()),
+                        Some(&new_grpc.to_string()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!("grpc: `{}` → `{}`", old.grpc, new_grpc));
             }
+        }
 
-            if let Some(new_ws) = payload.websocket_support {
-                if old.websocket_support != new_ws {
-                    let _ = state
-                        .app_state
-                        .mysql
-                        .log_audit(
-                            "route",
-                            Some(id),
-                            "update",
-                            Some("websocket_support"),
-                            Some(&old.websocket_support.to_string()),
-                            Some(&new_ws.to_string()),
-                            "api",
-                            None,
-                        )
-                        .await;
-                    changes.push(format!(
-                        "websocket_support: `{}` → `{}`",
-                        old.websocket_support, new_ws
-                    ));
-                }
+        if let Some(new_admin_only) = payload.admin_network_only {
+            if old.admin_network_only != new_admin_only {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("admin_network_only"),
+                        Some(&old.admin_network_only.to_string()),
+                        Some(&new_admin_only.to_string()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "admin_network_only: `{}` → `{}`",
+                    old.admin_network_only, new_admin_only
+                ));
             }
+        }
 
-            // Send Discord notification if there were changes
-            if !changes.is_empty() {
-                state
-                    .notifier
-                    .notify_config_change(
-                        "Route Updated",
-                        &format!(
-                            "Route `{}` (ID: {}) modified:\n{}",
-                            old.path,
-                            id,
-                            changes.join("\n")
-                        ),
+        if let Some(new_percent) = payload.canary_percent {
+            let old_percent = old.canary_percent.unwrap_or(0);
+            let new_percent = new_percent.unwrap_or(0);
+            if old_percent != new_percent {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("canary_percent"),
+                        Some(&old_percent.to_string()),
+                        Some(&new_percent.to_string()),
+                        "api",
+                        None,
                     )
                     .await;
+                changes.push(format!(
+                    "canary_percent: `{}%` → `{}%`",
+                    old_percent, new_percent
+                ));
             }
         }
 
-        // Reload proxy routes
-        if let Err(e) = state.reload_routes().await {
-            tracing::error!("Failed to reload routes after update: {}", e);
+        if let Some(new_headers) = &payload.security_headers {
+            let new_value = new_headers.to_column();
+            if old.security_headers != new_value {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("security_headers"),
+                        old.security_headers.as_deref(),
+                        new_value.as_deref(),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "security_headers: `{}` → `{}`",
+                    old.security_headers.as_deref().unwrap_or("global"),
+                    new_value.as_deref().unwrap_or("global")
+                ));
+            }
         }
 
-        tracing::info!("Updated route {}", id);
-        Ok(Json(SuccessResponse::new("Route updated")))
-    } else {
-        Err(AppError::NotFound(format!("Route {} not found", id)))
-    }
-}
+        if let Some(new_rules) = &payload.header_rewrite {
+            let new_value = new_rules.to_column();
+            if old.header_rewrite != new_value {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("header_rewrite"),
+                        old.header_rewrite.as_deref(),
+                        new_value.as_deref(),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "header_rewrite: `{}` → `{}`",
+                    old.header_rewrite.as_deref().unwrap_or("none"),
+                    new_value.as_deref().unwrap_or("none")
+                ));
+            }
+        }
 
-/// DELETE /api/routes/:id - Delete a route (dangerous: permission == 100, confirm required)
+        if let Some(new_methods) = &payload.allowed_methods {
+            let new_value = allowed_methods_column(new_methods.as_deref());
+            if old.allowed_methods != new_value {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("allowed_methods"),
+                        old.allowed_methods.as_deref(),
+                        new_value.as_deref(),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "allowed_methods: `{}` → `{}`",
+                    old.allowed_methods.as_deref().unwrap_or("all"),
+                    new_value.as_deref().unwrap_or("all")
+                ));
+            }
+        }
+
+        if let Some(enabled) = payload.maintenance_mode {
+            if old.maintenance_mode != enabled {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("maintenance_mode"),
+                        Some(&old.maintenance_mode.to_string()),
+                        Some(&enabled.to_string()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "maintenance_mode: `{}` → `{}`",
+                    old.maintenance_mode, enabled
+                ));
+            }
+        }
+
+        if let Some(new_limit) = payload.max_body_bytes {
+            let new_value = new_limit.filter(|bytes| *bytes > 0);
+            if old.max_body_bytes != new_value {
+                let old_text = old.max_body_bytes.map(|b| b.to_string());
+                let new_text = new_value.map(|b| b.to_string());
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("max_body_bytes"),
+                        old_text.as_deref(),
+                        new_text.as_deref(),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "max_body_bytes: `{}` → `{}`",
+                    old_text.as_deref().unwrap_or("global"),
+                    new_text.as_deref().unwrap_or("global")
+                ));
+            }
+        }
+
+        if let Some(new_targets) = &payload.fallback_targets {
+            let new_value = fallback_targets_column(new_targets.as_deref());
+            if old.fallback_targets != new_value {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("fallback_targets"),
+                        old.fallback_targets.as_deref(),
+                        new_value.as_deref(),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "fallback_targets: `{}` → `{}`",
+                    old.fallback_targets.as_deref().unwrap_or("none"),
+                    new_value.as_deref().unwrap_or("none")
+                ));
+            }
+        }
+
+        if let Some(new_mode) = payload.expect_continue {
+            if old.expect_continue() != new_mode {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some("expect_continue"),
+                        Some(old.expect_continue().as_str()),
+                        Some(new_mode.as_str()),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!(
+                    "expect_continue: `{}` → `{}`",
+                    old.expect_continue().as_str(),
+                    new_mode.as_str()
+                ));
+            }
+        }
+
+        // Ownership fields (empty string clears)
+        for (field, old_value, new_value) in [
+            ("owner_name", &old.owner_name, &payload.owner_name),
+            ("owner_contact", &old.owner_contact, &payload.owner_contact),
+            ("team", &old.team, &payload.team),
+        ] {
+            let Some(new_value) = new_value.as_deref().map(str::trim) else {
+                continue;
+            };
+            let old_value = old_value.as_deref().unwrap_or("");
+            if old_value != new_value {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .log_audit(
+                        "route",
+                        Some(id),
+                        "update",
+                        Some(field),
+                        Some(old_value),
+                        Some(new_value),
+                        "api",
+                        None,
+                    )
+                    .await;
+                changes.push(format!("{}: `{}` → `{}`", field, old_value, new_value));
+            }
+        }
+
+        // Send Discord notification if there were changes
+        if !changes.is_empty() {
+            let description = format!(
+                "Route `{}` (ID: {}) modified:\n{}",
+                old.path,
+                id,
+                changes.join("\n")
+            );
+            state
+                .notifier
+                .notify_config_change("Route Updated", &description)
+                .await;
+
+            if !old.is_owned_by(actor) {
+                state
+                    .notifier
+                    .notify_route_owner(
+                        old,
+                        "Your Route Was Modified",
+                        &format!("{}\n\nChanged by {}", description, actor.sub),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    // Reload proxy routes
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after update: {}", e);
+    }
+
+    tracing::info!("Updated route {}", id);
+    Ok(())
+}
+
+/// Result of the inactive→active pre-check
+#[derive(Debug, Serialize)]
+pub struct ActivationCheck {
+    pub probe: TargetProbe,
+    /// Activated in the warming state (503 until the first healthy check)
+    pub warming: bool,
+}
+
+/// Probe the target when `payload` activates an inactive route.
+///
+/// Returns the failed probe when the target is not ready and `warm` is off.
+pub(crate) async fn activation_precheck(
+    state: &ProxyState,
+    old_route: Option<&ProxyRoute>,
+    payload: &UpdateRouteRequest,
+    warm: bool,
+) -> Result<Option<ActivationCheck>, TargetProbe> {
+    let Some(old) = old_route.filter(|r| !r.active && payload.active == Some(true)) else {
+        return Ok(None);
+    };
+    let target = payload.target.as_deref().unwrap_or(&old.target);
+    let grpc = payload.grpc.unwrap_or(old.grpc);
+    let grpc_health_service = match &payload.grpc_health_service {
+        Some(v) => v.as_deref(),
+        None => old.grpc_health_service.as_deref(),
+    };
+    let probe = match grpc_health_service.filter(|_| grpc) {
+        Some(service) => {
+            probe_grpc_target(&state.app_state, &state.grpc_client, target, service.trim()).await
+        }
+        None => {
+            let path = match &payload.health_check_path {
+                Some(v) => v.as_deref(),
+                None => old.health_check_path.as_deref(),
+            };
+            let url = probe_url(target, path.unwrap_or("/"));
+            let authorization = old.updated_upstream_authorization(payload);
+            probe_target(
+                &state.app_state,
+                &state.http_client,
+                &url,
+                authorization.as_deref(),
+            )
+            .await
+        }
+    };
+    if !probe.healthy && !warm {
+        tracing::warn!(
+            "Refused activating route {}: {} failed the pre-check ({})",
+            old.id,
+            target,
+            probe.error.as_deref().unwrap_or("unhealthy")
+        );
+        return Err(probe);
+    }
+    let warming = !probe.healthy;
+    Ok(Some(ActivationCheck { probe, warming }))
+}
+
+/// Apply a validated update that passed the activation pre-check: set up the
+/// warm-up state before the route goes live and nudge the health checker
+pub(crate) async fn apply_checked_update_route(
+    state: &ProxyState,
+    actor: &AuthUser,
+    id: i32,
+    payload: &UpdateRouteRequest,
+    old_route: Option<ProxyRoute>,
+    activation: Option<&ActivationCheck>,
+) -> Result<(), AppError> {
+    let warmup = &state.route_warmup;
+    match activation {
+        Some(check) if check.warming => warmup.start(id),
+        Some(_) => {
+            warmup.finish(id);
+        }
+        None => {}
+    }
+
+    if let Err(e) = apply_update_route(state, actor, id, payload, old_route).await {
+        if activation.is_some_and(|c| c.warming) {
+            warmup.finish(id);
+        }
+        return Err(e);
+    }
+
+    if let Some(check) = activation {
+        if check.warming {
+            let _ = state
+                .app_state
+                .mysql
+                .log_audit(
+                    "route",
+                    Some(id),
+                    "activate_warming",
+                    Some("active"),
+                    None,
+                    Some(check.probe.error.as_deref().unwrap_or("unhealthy")),
+                    &actor.sub,
+                    None,
+                )
+                .await;
+        }
+        warmup.nudge();
+    }
+    Ok(())
+}
+
+/// Queue a proposed route change and tell admins (and the route owner)
+async fn propose_route_change(
+    state: &ProxyState,
+    user: &AuthUser,
+    route: Option<&ProxyRoute>,
+    action: &str,
+    payload: serde_json::Value,
+) -> Result<Response, AppError> {
+    let route_id = route.map(|r| r.id);
+    let pending_id = state
+        .app_state
+        .mysql
+        .create_route_pending_change(route_id, action, &payload, &user.sub, user.permission)
+        .await?;
+
+    let subject = match route {
+        Some(r) => format!("route #{} `{}`", r.id, r.path),
+        None => format!(
+            "new route `{}`",
+            payload.get("path").and_then(|v| v.as_str()).unwrap_or("?")
+        ),
+    };
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route_pending_change",
+            Some(pending_id),
+            "propose",
+            Some(action),
+            None,
+            Some(&format!("{} proposed by {}", subject, user.sub)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    let description = format!(
+        "{} proposed a change ({}) to {} — pending approval #{}",
+        user.sub, action, subject, pending_id
+    );
+    state
+        .notifier
+        .notify_config_change("Route Change Proposed", &description)
+        .await;
+
+    if let Some(r) = route.filter(|r| !r.is_owned_by(user)) {
+        state
+            .notifier
+            .notify_route_owner(r, "Change Proposed For Your Route", &description)
+            .await;
+    }
+
+    tracing::info!(
+        "Queued route {} by {} as pending change #{}",
+        action,
+        user.sub,
+        pending_id
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Change queued for approval",
+            "pending_id": pending_id,
+            "pending_approval": true,
+        })),
+    )
+        .into_response())
+}
+
+/// POST /api/routes - Create a new route (admin: permission >= 80)
+///
+/// With `route_approval_required`, operators get 202 and a pending change instead.
+pub async fn create_route(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(payload): Json<CreateRouteRequest>,
+) -> Result<Response, AppError> {
+    let needs_approval = route_change_needs_approval(&state, &user).await?;
+
+    validate_create_route(&state, &payload).await?;
+
+    if needs_approval {
+        let json =
+            serde_json::to_value(&payload).map_err(|e| AppError::InternalError(e.to_string()))?;
+        return propose_route_change(&state, &user, None, "create", json).await;
+    }
+
+    let id = apply_create_route(&state, &user.sub, &payload).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SuccessResponse::with_id("Route created", id)),
+    )
+        .into_response())
+}
+
+/// PUT /api/routes/:id - Update a route (admin: permission >= 80)
+///
+/// With `route_approval_required`, operators get 202 and a pending change instead.
+/// Activating an inactive route probes its target first: 409 with the probe
+/// when it fails, unless `?warm=true` activates it in the warming state.
+pub async fn update_route(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(query): Query<UpdateRouteQuery>,
+    Json(payload): Json<UpdateRouteRequest>,
+) -> Result<Response, AppError> {
+    let needs_approval = route_change_needs_approval(&state, &user).await?;
+
+    let old_route = validate_update_route(&state, id, &payload).await?;
+
+    if needs_approval {
+        let route = old_route.ok_or_else(|| {
+            AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+        })?;
+        let json =
+            serde_json::to_value(&payload).map_err(|e| AppError::InternalError(e.to_string()))?;
+        return propose_route_change(&state, &user, Some(&route), "update", json).await;
+    }
+
+    // Activating an inactive route: probe the target first
+    let activation = match activation_precheck(&state, old_route.as_ref(), &payload, query.warm)
+        .await
+    {
+        Ok(activation) => activation,
+        Err(probe) => {
+            return Ok((
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "error": format!(
+                            "Activation refused: {} failed the health pre-check ({})",
+                            probe.target,
+                            probe.error.as_deref().unwrap_or("unhealthy")
+                        ),
+                        "status": StatusCode::CONFLICT.as_u16(),
+                        "probe": probe,
+                        "hint": "Retry with ?warm=true to activate and serve 503 until the target is healthy",
+                    })),
+                )
+                    .into_response());
+        }
+    };
+
+    apply_checked_update_route(&state, &user, id, &payload, old_route, activation.as_ref()).await?;
+
+    match activation {
+        Some(activation) => Ok(Json(serde_json::json!({
+            "message": if activation.warming {
+                "Route updated; activated in warming state until the target is healthy"
+            } else {
+                "Route updated"
+            },
+            "activation": activation,
+        }))
+        .into_response()),
+        None => Ok(Json(SuccessResponse::new("Route updated")).into_response()),
+    }
+}
+
+/// DELETE /api/routes/:id - Delete a route (dangerous: permission == 100, confirm required)
+///
+/// Soft-deletes by default; `?hard=true` removes the row permanently.
 pub async fn delete_route(
     State(state): State<ProxyState>,
     Extension(user): Extension<AuthUser>,
     Path(id): Path<i32>,
-    Query(confirm): Query<ConfirmQuery>,
+    Query(query): Query<DeleteRouteQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 100)?;
 
@@ -352,33 +1645,50 @@ pub async fn delete_route(
     let route = state.app_state.mysql.get_route(id).await?;
 
     // Confirm guard: return impact info if confirm is not set
-    if !confirm.confirm {
+    if !query.confirm {
         let target_info = route
             .as_ref()
             .map(|r| format!("route #{} ({} → {})", id, r.path, r.target))
             .unwrap_or_else(|| format!("route #{}", id));
 
+        let (action, warning) = if query.hard {
+            (
+                "hard_delete_route",
+                "This will permanently remove the proxy route. It cannot be restored.",
+            )
+        } else {
+            (
+                "delete_route",
+                "This will remove the proxy route. Active connections will be dropped. \
+                 The route can be restored until it is purged.",
+            )
+        };
+
         return Ok(Json(serde_json::json!(ConfirmRequired {
-            action: "delete_route".to_string(),
+            action: action.to_string(),
             target: target_info,
-            warning: "This will remove the proxy route. Active connections will be dropped."
-                .to_string(),
+            warning: warning.to_string(),
             confirm_required: true,
         })));
     }
 
-    let deleted = state.app_state.mysql.delete_route(id).await?;
+    let deleted = if query.hard {
+        state.app_state.mysql.delete_route(id).await?
+    } else {
+        state.app_state.mysql.soft_delete_route(id).await?
+    };
 
     if deleted {
         // Log audit
         if let Some(ref r) = route {
+            let action = if query.hard { "delete" } else { "soft_delete" };
             let _ = state
                 .app_state
                 .mysql
                 .log_audit(
                     "route",
                     Some(id),
-                    "delete",
+                    action,
                     None,
                     Some(&format!("{} -> {}", r.path, r.target)),
                     None,
@@ -388,13 +1698,29 @@ pub async fn delete_route(
                 .await;
 
             // Send Discord notification
+            let title = if query.hard {
+                "Route Deleted (permanent)"
+            } else {
+                "Route Deleted"
+            };
             state
                 .notifier
                 .notify_config_change(
-                    "Route Deleted",
+                    title,
                     &format!("Route removed: `{}` → `{}`", r.path, r.target),
                 )
                 .await;
+
+            if !r.is_owned_by(&user) {
+                state
+                    .notifier
+                    .notify_route_owner(
+                        r,
+                        "Your Route Was Deleted",
+                        &format!("Route `{}` was deleted by {}", r.path, user.sub),
+                    )
+                    .await;
+            }
         }
 
         // Reload proxy routes
@@ -402,11 +1728,966 @@ pub async fn delete_route(
             tracing::error!("Failed to reload routes after delete: {}", e);
         }
 
-        tracing::info!("Deleted route {}", id);
+        tracing::info!("Deleted route {} (hard: {})", id, query.hard);
         Ok(Json(serde_json::json!(SuccessResponse::new(
             "Route deleted"
         ))))
     } else {
-        Err(AppError::NotFound(format!("Route {} not found", id)))
+        Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ))
     }
 }
+
+/// POST /api/routes/:id/restore - Restore a soft-deleted route (admin: permission >= 80)
+pub async fn restore_route(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let route = state
+        .app_state
+        .mysql
+        .get_route(id)
+        .await?
+        .filter(|r| r.deleted_at.is_some())
+        .ok_or_else(|| AppError::NotFound(format!("Deleted route {} not found", id)))?;
+
+    // A live route may have taken the path while this one was deleted
+    if let Some(conflict) = state
+        .app_state
+        .mysql
+        .find_live_route_conflict(&route.path, route.ddns_config_id, id)
+        .await?
+    {
+        return Err(AppError::BadRequest(format!(
+            "Path {} is now used by route #{} ({})",
+            route.path, conflict.id, conflict.target
+        )));
+    }
+
+    if !state.app_state.mysql.restore_route(id).await? {
+        return Err(AppError::NotFound(format!(
+            "Deleted route {} not found",
+            id
+        )));
+    }
+    record_route_version(&state, id, &user.sub, "restore", None).await;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "restore",
+            None,
+            None,
+            Some(&format!("{} -> {}", route.path, route.target)),
+            "api",
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "Route Restored",
+            &format!("Route restored: `{}` → `{}`", route.path, route.target),
+        )
+        .await;
+
+    if !route.is_owned_by(&user) {
+        state
+            .notifier
+            .notify_route_owner(
+                &route,
+                "Your Route Was Restored",
+                &format!("Route `{}` was restored by {}", route.path, user.sub),
+            )
+            .await;
+    }
+
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after restore: {}", e);
+    }
+
+    tracing::info!("Restored route {}", id);
+    Ok(Json(SuccessResponse::with_id("Route restored", id)))
+}
+
+/// POST /api/routes/purge - Permanently remove old soft-deleted routes
+/// (dangerous: permission == 100, confirm required)
+pub async fn purge_deleted_routes(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<PurgeRoutesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let days = match query.older_than_days {
+        Some(days) => days.max(0),
+        None => route_deleted_retention_days(&state.app_state.mysql).await,
+    };
+
+    if !query.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "purge_deleted_routes".to_string(),
+            target: format!("routes deleted {} or more days ago", days),
+            warning: "Purged routes are permanently removed and cannot be restored.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    let purged = purge_and_audit(&state.app_state.mysql, days, "api").await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "Deleted routes purged",
+        "purged": purged,
+    })))
+}
+
+/// Retention for soft-deleted routes from settings
+pub async fn route_deleted_retention_days(mysql: &crate::db::MySqlDb) -> i32 {
+    mysql
+        .get_setting_i32(
+            "route_deleted_retention_days",
+            DEFAULT_ROUTE_DELETED_RETENTION_DAYS,
+        )
+        .await
+        .unwrap_or(DEFAULT_ROUTE_DELETED_RETENTION_DAYS)
+        .max(0)
+}
+
+/// Purge soft-deleted routes older than `days`, writing a "purge" audit entry per route.
+/// Returns the number of purged routes.
+pub async fn purge_and_audit(
+    mysql: &crate::db::MySqlDb,
+    days: i32,
+    changed_by: &str,
+) -> Result<usize, AppError> {
+    let purged = mysql.purge_deleted_routes(days).await?;
+
+    for r in &purged {
+        let _ = mysql
+            .log_audit(
+                "route",
+                Some(r.id),
+                "purge",
+                None,
+                Some(&format!("{} -> {}", r.path, r.target)),
+                None,
+                changed_by,
+                None,
+            )
+            .await;
+    }
+
+    if !purged.is_empty() {
+        tracing::info!("Purged {} soft-deleted routes", purged.len());
+    }
+
+    Ok(purged.len())
+}
+
+/// Query parameters for PUT /api/routes/sync
+#[derive(Debug, Deserialize)]
+pub struct SyncRoutesQuery {
+    /// Soft-delete live routes missing from the desired set (permission 100)
+    #[serde(default)]
+    pub prune: bool,
+    /// Return the plan without applying it
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Apply nothing when any entry fails validation
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// PUT /api/routes/sync - Converge the live routes on a declarative set
+/// (admin: permission >= 80, 100 with `prune`)
+///
+/// Entries are keyed by path + host and diffed server-side; see route_sync.
+/// Invalid entries are reported and skipped unless `strict` is set, which
+/// answers 422 with the plan and applies nothing. Creations, updates and
+/// deletions are applied in that order and the pass stops at the first
+/// failed write. One reload, audit entry (with the plan) and notification
+/// cover the whole sync; there is no activation pre-check or approval queue.
+pub async fn sync_routes(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<SyncRoutesQuery>,
+    Json(payload): Json<RouteSyncRequest>,
+) -> Result<Response, AppError> {
+    require_permission(&user, if query.prune { 100 } else { 80 })?;
+
+    let mysql = &state.app_state.mysql;
+    let live = mysql.list_routes().await?;
+    let ddns = mysql.list_ddns().await?;
+    let hostnames: HashMap<String, i32> = ddns
+        .iter()
+        .map(|c| (c.hostname.to_ascii_lowercase(), c.id))
+        .collect();
+    let host_of = |ddns_id: Option<i32>| {
+        ddns_id.and_then(|id| ddns.iter().find(|c| c.id == id).map(|c| c.hostname.clone()))
+    };
+
+    let mut actions: Vec<SyncAction> = Vec::new();
+    let mut creates: Vec<CreateRouteRequest> = Vec::new();
+    let mut updates: Vec<UpdateRouteRequest> = Vec::new();
+    let mut skipped: Vec<SyncSkip> = Vec::new();
+    let mut errors: Vec<SyncError> = Vec::new();
+    let mut desired: HashMap<RouteKey, usize> = HashMap::new();
+
+    for (index, spec) in payload.routes.into_iter().enumerate() {
+        let path = spec.route.path.clone();
+        let mut reject = |host: Option<String>, error: AppError| {
+            errors.push(SyncError {
+                index,
+                path: path.clone(),
+                host,
+                error: error.body(),
+            });
+        };
+
+        let ddns_id = match resolve_host(&spec, &hostnames) {
+            Ok(id) => id,
+            Err(e) => {
+                reject(spec.host.clone(), AppError::validation("host", e));
+                continue;
+            }
+        };
+        let host = host_of(ddns_id);
+        let key: RouteKey = (path.clone(), ddns_id);
+        if let Some(first) = desired.get(&key) {
+            reject(
+                host,
+                AppError::validation(
+                    "path",
+                    format!("duplicates entry {} (same path and host)", first),
+                ),
+            );
+            continue;
+        }
+        desired.insert(key.clone(), index);
+
+        let matches: Vec<&ProxyRoute> = live.iter().filter(|r| route_key(r) == key).collect();
+        match matches.as_slice() {
+            [] => {
+                let mut route = spec.route;
+                route.ddns_config_id = ddns_id;
+                if let Err(e) = validate_create_route(&state, &route).await {
+                    reject(host, e);
+                    continue;
+                }
+                actions.push(SyncAction {
+                    action: "create",
+                    index: Some(index),
+                    path,
+                    host,
+                    route_id: None,
+                    changes: Vec::new(),
+                    status: SyncStatus::Planned,
+                    error: None,
+                });
+                creates.push(route);
+            }
+            [current] => {
+                let (update, changes) = diff_route(current, &spec.route);
+                if changes.is_empty() {
+                    skipped.push(SyncSkip {
+                        index: Some(index),
+                        path,
+                        host,
+                        route_id: Some(current.id),
+                        reason: "unchanged".to_string(),
+                    });
+                    continue;
+                }
+                if let Err(e) = validate_update_route(&state, current.id, &update).await {
+                    reject(host, e);
+                    continue;
+                }
+                actions.push(SyncAction {
+                    action: "update",
+                    index: Some(index),
+                    path,
+                    host,
+                    route_id: Some(current.id),
+                    changes,
+                    status: SyncStatus::Planned,
+                    error: None,
+                });
+                updates.push(update);
+            }
+            several => {
+                let ids: Vec<String> = several.iter().map(|r| format!("#{}", r.id)).collect();
+                reject(
+                    host,
+                    AppError::validation(
+                        "path",
+                        format!("routes {} share this path and host", ids.join(", ")),
+                    ),
+                );
+            }
+        }
+    }
+
+    for route in live.iter().filter(|r| !desired.contains_key(&route_key(r))) {
+        if query.prune {
+            actions.push(SyncAction {
+                action: "delete",
+                index: None,
+                path: route.path.clone(),
+                host: host_of(route.ddns_config_id),
+                route_id: Some(route.id),
+                changes: Vec::new(),
+                status: SyncStatus::Planned,
+                error: None,
+            });
+        } else {
+            skipped.push(SyncSkip {
+                index: None,
+                path: route.path.clone(),
+                host: host_of(route.ddns_config_id),
+                route_id: Some(route.id),
+                reason: "not in the desired set (prune is off)".to_string(),
+            });
+        }
+    }
+
+    let aborted = query.strict && !errors.is_empty();
+    if !query.dry_run && !aborted {
+        let mut creates = creates.into_iter();
+        let mut updates = updates.into_iter();
+        let mut failed = false;
+        for action in actions.iter_mut() {
+            if failed {
+                action.status = SyncStatus::NotApplied;
+                continue;
+            }
+            let result = match action.action {
+                "create" => {
+                    let route = creates.next().expect("one creation per create action");
+                    mysql.create_route(&route).await.map(|id| {
+                        action.route_id = Some(id);
+                    })
+                }
+                "update" => {
+                    let update = updates.next().expect("one update per update action");
+                    let id = action.route_id.unwrap_or_default();
+                    mysql.update_route(id, &update).await.map(|_| ())
+                }
+                _ => {
+                    let id = action.route_id.unwrap_or_default();
+                    mysql.soft_delete_route(id).await.map(|_| ())
+                }
+            };
+            match result {
+                Ok(()) => {
+                    action.status = SyncStatus::Applied;
+                    if let (Some(id), "create" | "update") = (action.route_id, action.action) {
+                        record_route_version(&state, id, &user.sub, "sync", None).await;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Route sync stopped at {} {}: {}",
+                        action.action,
+                        action.path,
+                        e
+                    );
+                    action.error = Some(e.body());
+                    action.status = SyncStatus::Failed;
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    let count = |name: &str| actions.iter().filter(|a| a.action == name).count();
+    let applied = actions
+        .iter()
+        .filter(|a| a.status == SyncStatus::Applied)
+        .count();
+    let summary = serde_json::json!({
+        "create": count("create"),
+        "update": count("update"),
+        "delete": count("delete"),
+        "unchanged": skipped.iter().filter(|s| s.reason == "unchanged").count(),
+        "skipped": skipped.len(),
+        "errors": errors.len(),
+        "applied": applied,
+    });
+    let body = serde_json::json!({
+        "dry_run": query.dry_run,
+        "prune": query.prune,
+        "strict": query.strict,
+        "aborted": aborted,
+        "summary": summary,
+        "actions": actions,
+        "skipped": skipped,
+        "errors": errors,
+    });
+
+    if !query.dry_run && !aborted {
+        let _ = mysql
+            .log_audit(
+                "route",
+                None,
+                "sync",
+                None,
+                None,
+                Some(&body.to_string()),
+                &user.sub,
+                None,
+            )
+            .await;
+
+        if applied > 0 {
+            state
+                .notifier
+                .notify_config_change(
+                    "Routes Synced",
+                    &format!(
+                        "{} applied a route sync: {} created, {} updated, {} deleted, {} rejected",
+                        user.sub,
+                        count("create"),
+                        count("update"),
+                        count("delete"),
+                        errors.len()
+                    ),
+                )
+                .await;
+
+            if let Err(e) = state.reload_routes().await {
+                tracing::error!("Failed to reload routes after sync: {}", e);
+            }
+        }
+        tracing::info!(
+            "Route sync by {}: {} of {} actions applied, {} entries rejected",
+            user.sub,
+            applied,
+            actions.len(),
+            errors.len()
+        );
+    }
+
+    let status = if aborted {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(body)).into_response())
+}
+
+/// Body for PUT /api/routes/:id/trace
+#[derive(Debug, Deserialize)]
+pub struct RouteTraceRequest {
+    pub enabled: bool,
+    /// Trace window in seconds (default 600, max 86400)
+    pub duration_secs: Option<u64>,
+}
+
+/// Query parameters for GET /api/routes/:id/traces
+#[derive(Debug, Deserialize)]
+pub struct RouteTracesQuery {
+    /// Number of slowest traces to return (default 20, max 200)
+    pub slowest: Option<usize>,
+}
+
+/// PUT /api/routes/:id/trace - Enable/disable per-phase request tracing for a
+/// route; tracing switches itself off after `duration_secs` (operate: permission >= 50)
+pub async fn set_route_trace(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<RouteTraceRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+
+    let tracer = &state.route_tracer;
+    if !req.enabled {
+        tracer.disable(id);
+        tracing::info!("Request tracing disabled for route {}", id);
+        return Ok(Json(serde_json::json!({
+            "route_id": id,
+            "enabled": false,
+        })));
+    }
+
+    let secs = req
+        .duration_secs
+        .unwrap_or(trace::DEFAULT_TRACE_SECS)
+        .clamp(1, trace::MAX_TRACE_SECS);
+    let expires_at = tracer.enable(id, std::time::Duration::from_secs(secs));
+    tracing::info!(
+        "Request tracing enabled for route {} until {} (by {})",
+        id,
+        expires_at,
+        user.sub
+    );
+
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "enabled": true,
+        "expires_at": expires_at,
+        "capacity": trace::TRACE_CAPACITY,
+    })))
+}
+
+/// GET /api/routes/:id/traces - Per-phase p50/p95/p99 and the slowest traced requests
+pub async fn get_route_traces(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+    Query(query): Query<RouteTracesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let slowest = query.slowest.unwrap_or(20).min(200);
+    Ok(Json(state.route_tracer.summary(id, slowest)))
+}
+
+/// Latest requests per side the canary latency percentiles are computed from
+const CANARY_LATENCY_SAMPLES: i64 = 20_000;
+
+/// Query parameters for GET /api/routes/:id/canary-stats
+#[derive(Debug, Deserialize)]
+pub struct CanaryStatsQuery {
+    /// Window in minutes (default 60, 5 to 10080)
+    pub minutes: Option<i64>,
+}
+
+/// GET /api/routes/:id/canary-stats - Requests, 5xx rate and latency
+/// percentiles of the primary and canary targets over a window
+pub async fn get_route_canary_stats(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+    Query(query): Query<CanaryStatsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+
+    let minutes = query.minutes.unwrap_or(60).clamp(5, 10_080);
+    let from = chrono::Utc::now() - chrono::Duration::minutes(minutes);
+    let mongo = &state.app_state.mongo;
+    let side = |target: String| async move {
+        let (requests, errors) = mongo.count_route_target_requests(id, &target, from).await?;
+        let latencies = mongo
+            .get_route_target_latencies(id, &target, from, CANARY_LATENCY_SAMPLES)
+            .await?;
+        Ok::<_, AppError>(CanarySideStats::new(&target, requests, errors, latencies))
+    };
+
+    let primary = side(route.target.clone()).await?;
+    let canary = match route.canary_target.clone().filter(|t| !t.is_empty()) {
+        Some(target) => Some(side(target).await?),
+        None => None,
+    };
+
+    Ok(Json(CanaryStats {
+        route_id: id,
+        minutes,
+        canary_percent: route.canary().map(|(_, p)| p as i32).unwrap_or(0),
+        canary_sticky: route.canary_sticky,
+        primary,
+        canary,
+    }))
+}
+
+/// POST /api/routes/:id/canary/kill - Kill switch: send all traffic of a
+/// route back to its primary target (operate: permission >= 50)
+pub async fn kill_route_canary(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+    let old_percent = route.canary_percent.unwrap_or(0);
+
+    if !state.app_state.mysql.disable_route_canary(id).await? {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "canary_kill", None).await;
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after canary kill: {}", e);
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "canary_kill",
+            Some("canary_percent"),
+            Some(&old_percent.to_string()),
+            Some("0"),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if old_percent > 0 {
+        state
+            .notifier
+            .notify_config_change(
+                "Canary Killed",
+                &format!(
+                    "Route `{}`: canary `{}` disabled ({}% → 0%) by {}",
+                    route.path,
+                    route.canary_target.as_deref().unwrap_or("-"),
+                    old_percent,
+                    user.sub
+                ),
+            )
+            .await;
+    }
+    tracing::warn!(
+        "Canary of route {} disabled by {} (was {}%)",
+        id,
+        user.sub,
+        old_percent
+    );
+
+    Ok(Json(SuccessResponse::new(format!(
+        "Canary of route {} disabled",
+        id
+    ))))
+}
+
+/// Body for POST /api/routes/:id/maintenance
+#[derive(Debug, Deserialize)]
+pub struct RouteMaintenanceRequest {
+    /// Omitted = switch to the other state
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Page text; omitted keeps the current one, `null` or empty returns to
+    /// the default text
+    #[serde(default, deserialize_with = "crate::models::nullable")]
+    pub message: Option<Option<String>>,
+}
+
+/// POST /api/routes/:id/maintenance - Put a route into maintenance mode (or
+/// take it out): requests get the maintenance page, health checks pause
+/// (operate: permission >= 50)
+pub async fn set_route_maintenance(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<RouteMaintenanceRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+    let mut errors = Vec::new();
+    validate_maintenance_message(req.message.as_ref().and_then(|m| m.as_deref()), &mut errors);
+    field_errors(errors)?;
+
+    let enabled = req.enabled.unwrap_or(!route.maintenance_mode);
+    let message = match &req.message {
+        Some(message) => message.as_deref(),
+        None => route.maintenance_message.as_deref(),
+    };
+    if !state
+        .app_state
+        .mysql
+        .set_route_maintenance(id, enabled, message)
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "maintenance", None).await;
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after maintenance change: {}", e);
+    }
+
+    if route.maintenance_mode != enabled {
+        let _ = state
+            .app_state
+            .mysql
+            .log_audit(
+                "route",
+                Some(id),
+                "maintenance",
+                Some("maintenance_mode"),
+                Some(&route.maintenance_mode.to_string()),
+                Some(&enabled.to_string()),
+                &user.sub,
+                None,
+            )
+            .await;
+        state
+            .notifier
+            .notify_config_change(
+                if enabled {
+                    "Route Maintenance Started"
+                } else {
+                    "Route Maintenance Ended"
+                },
+                &format!(
+                    "Route `{}` {} maintenance mode by {}",
+                    route.path,
+                    if enabled { "entered" } else { "left" },
+                    user.sub
+                ),
+            )
+            .await;
+        tracing::warn!(
+            "Route {} maintenance mode {} by {}",
+            id,
+            if enabled { "enabled" } else { "disabled" },
+            user.sub
+        );
+    }
+
+    let route = state.app_state.mysql.get_route(id).await?;
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "maintenance_mode": enabled,
+        "maintenance_message": route.and_then(|r| r.maintenance_message),
+    })))
+}
+
+/// POST /api/routes/:id/cache/purge - Drop a route's cached responses so
+/// the next requests go to the target (operate: permission >= 50)
+pub async fn purge_route_cache(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+    let purged = state.response_cache.purge_route(id);
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "cache_purge",
+            None,
+            None,
+            Some(&purged.to_string()),
+            &user.sub,
+            None,
+        )
+        .await;
+    tracing::info!(
+        "Response cache of route {} ({}) purged by {}: {} entries",
+        id,
+        route.path,
+        user.sub,
+        purged
+    );
+
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "cache_ttl_sec": route.cache_ttl_sec,
+        "purged": purged,
+        "cache": state.response_cache.stats(),
+    })))
+}
+
+/// Versions kept per route (`route_version_retention`)
+async fn route_version_retention(mysql: &crate::db::MySqlDb) -> i32 {
+    mysql
+        .get_setting_i32("route_version_retention", DEFAULT_RETENTION)
+        .await
+        .unwrap_or(DEFAULT_RETENTION)
+        .max(MIN_RETENTION)
+}
+
+/// Store the route's current configuration as its next version. Best
+/// effort: a failure is logged and never fails the write that caused it.
+pub(crate) async fn record_route_version(
+    state: &ProxyState,
+    id: i32,
+    actor: &str,
+    reason: &str,
+    restored_version: Option<i32>,
+) -> Option<i32> {
+    let mysql = &state.app_state.mysql;
+    let route = match mysql.get_route(id).await {
+        Ok(Some(route)) => route,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::warn!("Route {} version not recorded: {}", id, e);
+            return None;
+        }
+    };
+    let keep = route_version_retention(mysql).await;
+    match mysql
+        .record_route_version(
+            id,
+            &route_versions::snapshot(&route),
+            actor,
+            reason,
+            restored_version,
+            keep,
+        )
+        .await
+    {
+        Ok(version) => Some(version),
+        Err(e) => {
+            tracing::warn!("Route {} version not recorded: {}", id, e);
+            None
+        }
+    }
+}
+
+/// GET /api/routes/:id/versions - Configuration history, newest first, each
+/// with its changes against the previous version
+pub async fn list_route_versions(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let mysql = &state.app_state.mysql;
+    if mysql.get_route(id).await?.is_none() {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    let versions = mysql.list_route_versions(id).await?;
+
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "retention": route_version_retention(mysql).await,
+        "versions": route_versions::with_diffs(versions),
+    })))
+}
+
+/// POST /api/routes/:id/rollback/:version - Restore an earlier configuration
+/// as a new version (admin: permission >= 80)
+pub async fn rollback_route(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, version)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mysql = &state.app_state.mysql;
+    let current = mysql
+        .get_route(id)
+        .await?
+        .filter(|r| r.deleted_at.is_none())
+        .ok_or_else(|| {
+            AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+        })?;
+    let stored = mysql.get_route_version(id, version).await?.ok_or_else(|| {
+        AppError::coded(
+            ErrorCode::RouteVersionNotFound,
+            format!("Route {} has no version {}", id, version),
+        )
+    })?;
+    let mut restored: ProxyRoute = serde_json::from_value(stored.config.clone()).map_err(|e| {
+        AppError::InternalError(format!(
+            "Route {} version {} is invalid: {}",
+            id, version, e
+        ))
+    })?;
+    restored.id = id;
+
+    let changes = route_versions::diff(&route_versions::snapshot(&current), &stored.config);
+    if changes.is_empty() {
+        return Err(AppError::BadRequest(format!(
+            "Route {} already matches version {}",
+            id, version
+        )));
+    }
+
+    // Another route may have taken the path since
+    if let Some(conflict) = mysql
+        .find_live_route_conflict(&restored.path, restored.ddns_config_id, id)
+        .await?
+    {
+        return Err(AppError::BadRequest(format!(
+            "Path {} is now used by route #{} ({})",
+            restored.path, conflict.id, conflict.target
+        )));
+    }
+    check_deleted_route_conflict(&state, &restored.path, restored.ddns_config_id).await?;
+
+    let previous = mysql
+        .list_route_versions(id)
+        .await?
+        .first()
+        .map(|v| v.version);
+    if !mysql.restore_route_config(&restored).await? {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    let new_version = record_route_version(&state, id, &user.sub, "rollback", Some(version)).await;
+
+    let _ = mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "rollback",
+            Some("version"),
+            previous.map(|v| format!("v{}", v)).as_deref(),
+            Some(&match new_version {
+                Some(new) => format!("v{} (restored from v{})", new, version),
+                None => format!("restored from v{}", version),
+            }),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after rollback: {}", e);
+    }
+
+    let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+    let description = format!(
+        "Route `{}` rolled back to version {} by {} ({})",
+        current.path,
+        version,
+        user.sub,
+        fields.join(", ")
+    );
+    state
+        .notifier
+        .notify_config_change("Route Rolled Back", &description)
+        .await;
+    if !current.is_owned_by(&user) {
+        state
+            .notifier
+            .notify_route_owner(&current, "Your Route Was Rolled Back", &description)
+            .await;
+    }
+
+    tracing::info!("Rolled back route {} to version {}", id, version);
+    Ok(Json(serde_json::json!({
+        "message": format!("Route {} rolled back to version {}", id, version),
+        "version": new_version,
+        "restored_version": version,
+        "changes": changes,
+    })))
+}
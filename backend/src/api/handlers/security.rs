@@ -1,20 +1,30 @@
 //! Security handlers (blocked IPs, security events)
 
+use std::collections::HashMap;
+
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
 
 use crate::api::auth_middleware::require_permission;
+use crate::blocklist;
+use crate::db::mongo::user_object_detail::NodeClaim;
 use crate::error::AppError;
+use crate::ip_stats;
 use crate::models::{
-    AuthUser, BlockIpRequest, ConfirmQuery, ConfirmRequired, SecurityEventSearchQuery,
+    AuthUser, BlockIpRequest, BlockedIp, ConfirmQuery, ConfirmRequired, SecurityEvent,
+    SecurityEventSearchQuery,
 };
+use crate::network_policy::{Reason, Surface};
 use crate::proxy::ProxyState;
 
+use super::dashboard::csv_escape;
 use super::SuccessResponse;
 
 #[derive(Debug, Deserialize)]
@@ -45,10 +55,15 @@ pub async fn block_ip(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
 
-    // Validate IP format (basic check)
+    // Validate IP / CIDR format and store in canonical form
     if payload.ip.is_empty() {
         return Err(AppError::BadRequest("IP address is required".to_string()));
     }
+    let network = blocklist::validate_network(&payload.ip).map_err(AppError::BadRequest)?;
+    let payload = BlockIpRequest {
+        ip: blocklist::canonical_network(&network),
+        ..payload
+    };
 
     // Check if already blocked
     if state.app_state.mysql.is_ip_blocked(&payload.ip).await? {
@@ -71,6 +86,10 @@ pub async fn block_ip(
         )
         .await?;
 
+    if let Err(e) = state.rebuild_network_policy().await {
+        tracing::error!("Failed to rebuild network policy after block: {}", e);
+    }
+
     tracing::warn!("Blocked IP: {}", payload.ip);
 
     Ok((
@@ -110,6 +129,9 @@ pub async fn unblock_ip(
     let deleted = state.app_state.mysql.unblock_ip(id).await?;
 
     if deleted {
+        if let Err(e) = state.rebuild_network_policy().await {
+            tracing::error!("Failed to rebuild network policy after unblock: {}", e);
+        }
         if let Some(b) = &blocked {
             tracing::info!("Unblocked IP: {}", b.ip);
         }
@@ -121,6 +143,455 @@ pub async fn unblock_ip(
     }
 }
 
+/// Where a quick-block was started from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuickBlockContext {
+    AccessLog { id: String },
+    SecurityEvent { id: String },
+}
+
+impl QuickBlockContext {
+    /// Stored on the block record, e.g. `access_log:<id>`
+    pub fn reference(&self) -> String {
+        match self {
+            Self::AccessLog { id } => format!("access_log:{}", id),
+            Self::SecurityEvent { id } => format!("security_event:{}", id),
+        }
+    }
+}
+
+/// Block duration presets offered next to the quick-block action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum BlockDurationPreset {
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "24h")]
+    OneDay,
+    #[serde(rename = "7d")]
+    OneWeek,
+    #[serde(rename = "30d")]
+    ThirtyDays,
+    #[serde(rename = "permanent")]
+    Permanent,
+}
+
+impl BlockDurationPreset {
+    pub fn expires_at(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let duration = match self {
+            Self::OneHour => chrono::Duration::hours(1),
+            Self::OneDay => chrono::Duration::days(1),
+            Self::OneWeek => chrono::Duration::days(7),
+            Self::ThirtyDays => chrono::Duration::days(30),
+            Self::Permanent => return None,
+        };
+        Some(now + duration)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickBlockAction {
+    #[default]
+    Block,
+    Unblock,
+}
+
+/// Body for POST /api/security/quick-block
+#[derive(Debug, Deserialize)]
+pub struct QuickBlockRequest {
+    /// IP or CIDR; must contain the address of the referenced entry
+    pub ip: String,
+    pub context: QuickBlockContext,
+    #[serde(default)]
+    pub action: QuickBlockAction,
+    /// Block only (default permanent)
+    #[serde(default)]
+    pub duration: Option<BlockDurationPreset>,
+    /// Unblock only
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Response of POST /api/security/quick-block
+#[derive(Debug, Serialize)]
+pub struct QuickBlockResponse {
+    pub action: QuickBlockAction,
+    /// The created block, or the one removed by an unblock
+    pub block: BlockedIp,
+    pub context_ref: String,
+    /// Requests from the referenced address in the last QUICK_BLOCK_RECENT_HOURS
+    pub recent_requests: u64,
+    pub recent_window_hours: i64,
+}
+
+/// Window behind the request count of an access log context
+const QUICK_BLOCK_CONTEXT_MINUTES: i64 = 5;
+/// Window of the "recent requests" impact count
+const QUICK_BLOCK_RECENT_HOURS: i64 = 24;
+
+/// Reason for a block made from an access log entry
+fn access_log_block_reason(path: &str, requests: u64) -> String {
+    format!(
+        "blocked from access log: {} request{} to {} in {}m",
+        requests,
+        if requests == 1 { "" } else { "s" },
+        path,
+        QUICK_BLOCK_CONTEXT_MINUTES
+    )
+}
+
+/// Reason for a block made from a security event
+fn security_event_block_reason(event: &SecurityEvent) -> String {
+    let event_type = serde_json::to_value(event.event_type)
+        .ok()
+        .and_then(|v| v.as_str().map(|s| s.replace('_', " ")))
+        .unwrap_or_default();
+    let severity = serde_json::to_value(event.severity)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let mut reason = format!(
+        "blocked from security event: {} ({}) at {}",
+        event_type,
+        severity,
+        event.timestamp.format("%Y-%m-%d %H:%M UTC")
+    );
+    if let Some(detail) = event.details.get("reason").and_then(|v| v.as_str()) {
+        reason.push_str(": ");
+        reason.push_str(detail);
+    }
+    reason
+}
+
+/// The referenced entry's address and the composed block reason
+async fn resolve_quick_block_context(
+    state: &ProxyState,
+    context: &QuickBlockContext,
+) -> Result<(String, String), AppError> {
+    let mongo = &state.app_state.mongo;
+    match context {
+        QuickBlockContext::AccessLog { id } => {
+            let log = mongo
+                .get_access_log(id)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Access log {} not found", id)))?;
+            let from = log.timestamp - chrono::Duration::minutes(QUICK_BLOCK_CONTEXT_MINUTES);
+            let requests = mongo
+                .count_access_logs_matching(&doc! {
+                    "ip": &log.ip,
+                    "path": &log.path,
+                    "timestamp": { "$gte": from.to_rfc3339(), "$lte": log.timestamp.to_rfc3339() },
+                })
+                .await?;
+            Ok((
+                log.ip.clone(),
+                access_log_block_reason(&log.path, requests.max(1)),
+            ))
+        }
+        QuickBlockContext::SecurityEvent { id } => {
+            let event = mongo
+                .get_security_event(id)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("Security event {} not found", id)))?;
+            let ip = event.ip.clone().ok_or_else(|| {
+                AppError::BadRequest(format!("Security event {} has no IP address", id))
+            })?;
+            Ok((ip, security_event_block_reason(&event)))
+        }
+    }
+}
+
+/// POST /api/security/quick-block - Block (admin: permission >= 80) or unblock
+/// (dangerous: permission == 100, confirm required) an IP straight from an
+/// access log entry or security event
+pub async fn quick_block(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<QuickBlockRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(
+        &user,
+        match req.action {
+            QuickBlockAction::Block => 80,
+            QuickBlockAction::Unblock => 100,
+        },
+    )?;
+
+    // A broad network covers any context IP, so the prefix floor applies to
+    // blocks; unblocking still accepts whatever is already stored
+    let network = match req.action {
+        QuickBlockAction::Block => blocklist::validate_network(&req.ip),
+        QuickBlockAction::Unblock => blocklist::parse_network(&req.ip)
+            .ok_or_else(|| format!("Invalid IP or CIDR: {}", req.ip)),
+    }
+    .map_err(AppError::BadRequest)?;
+    let ip = blocklist::canonical_network(&network);
+    let context_ref = req.context.reference();
+    let (context_ip, reason) = resolve_quick_block_context(&state, &req.context).await?;
+    let context_addr: std::net::IpAddr = context_ip
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("{} has no valid IP address", context_ref)))?;
+    if !network.contains(context_addr) {
+        return Err(AppError::BadRequest(format!(
+            "{} does not cover {} from {}",
+            ip, context_ip, context_ref
+        )));
+    }
+
+    let mysql = &state.app_state.mysql;
+    let block = match req.action {
+        QuickBlockAction::Block => {
+            if mysql.is_ip_blocked(&ip).await? {
+                return Err(AppError::BadRequest(format!(
+                    "IP {} is already blocked",
+                    ip
+                )));
+            }
+            let request = BlockIpRequest {
+                ip: ip.clone(),
+                reason: Some(reason.clone()),
+                expires_at: req.duration.and_then(|d| d.expires_at(Utc::now())),
+            };
+            mysql
+                .block_ip_with_context(&request, "manual", Some(&context_ref))
+                .await?;
+            state
+                .app_state
+                .mongo
+                .log_ip_blocked(&ip, &reason, crate::models::Severity::Medium)
+                .await?;
+            mysql
+                .get_active_block_by_ip(&ip)
+                .await?
+                .ok_or_else(|| AppError::InternalError(format!("Block of {} not stored", ip)))?
+        }
+        QuickBlockAction::Unblock => {
+            let block = mysql
+                .get_active_block_by_ip(&ip)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("IP {} is not blocked", ip)))?;
+            if !req.confirm {
+                return Ok(Json(serde_json::json!(ConfirmRequired {
+                    action: "quick_unblock".to_string(),
+                    target: format!("blocked IP #{} ({})", block.id, block.ip),
+                    warning:
+                        "This will unblock the IP address, allowing it to access the system again."
+                            .to_string(),
+                    confirm_required: true,
+                })));
+            }
+            mysql.unblock_ip(block.id).await?;
+            block
+        }
+    };
+
+    if let Err(e) = state.rebuild_network_policy().await {
+        tracing::error!("Failed to rebuild network policy after quick-block: {}", e);
+    }
+
+    let (action, old_value, new_value) = match req.action {
+        QuickBlockAction::Block => ("quick_block", None, Some(format!("{} ({})", ip, reason))),
+        QuickBlockAction::Unblock => ("quick_unblock", Some(ip.clone()), None),
+    };
+    let _ = mysql
+        .log_audit(
+            "blocked_ip",
+            Some(block.id),
+            action,
+            Some(&context_ref),
+            old_value.as_deref(),
+            new_value.as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+    tracing::warn!("{} {} from {} by {}", action, ip, context_ref, user.sub);
+
+    let since = Utc::now() - chrono::Duration::hours(QUICK_BLOCK_RECENT_HOURS);
+    let recent_requests = state
+        .app_state
+        .mongo
+        .count_access_logs_matching(&doc! {
+            "ip": &context_ip,
+            "timestamp": { "$gte": since.to_rfc3339() },
+        })
+        .await?;
+
+    Ok(Json(serde_json::json!(QuickBlockResponse {
+        action: req.action,
+        block,
+        context_ref,
+        recent_requests,
+        recent_window_hours: QUICK_BLOCK_RECENT_HOURS,
+    })))
+}
+
+/// Query parameters for blocked IP import
+#[derive(Debug, Deserialize)]
+pub struct ImportBlockedIpsQuery {
+    /// Validate and report without writing
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Reason applied to lines that do not carry their own
+    pub reason: Option<String>,
+}
+
+/// Per-line import result
+#[derive(Debug, Serialize)]
+pub struct ImportLineResult {
+    pub line: usize,
+    pub input: String,
+    /// "added", "updated", "unchanged", "duplicate", or "invalid"
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Blocked IP import response
+#[derive(Debug, Serialize)]
+pub struct ImportBlockedIpsResponse {
+    pub dry_run: bool,
+    pub added: usize,
+    pub updated: usize,
+    /// Already blocked by another source (manual, auto, feed); left as is
+    pub unchanged: usize,
+    pub duplicate: usize,
+    pub invalid: usize,
+    pub results: Vec<ImportLineResult>,
+}
+
+/// POST /api/security/blocked-ips/import - Bulk import blocked IPs (admin: permission >= 80)
+///
+/// Body is a newline-separated list or CSV (`ip_or_cidr[,reason[,expires_at]]`).
+/// Entries from an earlier import are updated; entries blocked by another
+/// source are left unchanged. With `?dry_run=true` nothing is written.
+pub async fn import_blocked_ips(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<ImportBlockedIpsQuery>,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let existing: HashMap<String, String> = state
+        .app_state
+        .mysql
+        .list_blocked_ips()
+        .await?
+        .into_iter()
+        .map(|b| (b.ip, b.blocked_by))
+        .collect();
+
+    let mut results = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let (mut added, mut updated, mut unchanged, mut duplicate, mut invalid) = (0, 0, 0, 0, 0);
+
+    for (idx, raw) in body.lines().enumerate() {
+        let entry = match blocklist::parse_line(raw) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => continue,
+            Err(e) => {
+                invalid += 1;
+                results.push(ImportLineResult {
+                    line: idx + 1,
+                    input: raw.trim().to_string(),
+                    status: "invalid",
+                    ip: None,
+                    error: Some(e),
+                });
+                continue;
+            }
+        };
+
+        let status = if !seen.insert(entry.ip.clone()) {
+            duplicate += 1;
+            "duplicate"
+        } else if let Some(blocked_by) = existing.get(&entry.ip) {
+            if blocked_by == "import" {
+                updated += 1;
+                "updated"
+            } else {
+                unchanged += 1;
+                "unchanged"
+            }
+        } else {
+            added += 1;
+            "added"
+        };
+
+        if !query.dry_run && matches!(status, "added" | "updated") {
+            let request = BlockIpRequest {
+                ip: entry.ip.clone(),
+                reason: entry.reason.clone().or_else(|| query.reason.clone()),
+                expires_at: entry.expires_at,
+            };
+            state.app_state.mysql.block_ip(&request, "import").await?;
+        }
+
+        results.push(ImportLineResult {
+            line: idx + 1,
+            input: raw.trim().to_string(),
+            status,
+            ip: Some(entry.ip),
+            error: None,
+        });
+    }
+
+    if !query.dry_run && (added > 0 || updated > 0) {
+        if let Err(e) = state.rebuild_network_policy().await {
+            tracing::error!("Failed to rebuild network policy after import: {}", e);
+        }
+        tracing::warn!(
+            "Blocked IP import: {} added, {} updated, {} unchanged, {} invalid",
+            added,
+            updated,
+            unchanged,
+            invalid
+        );
+    }
+
+    Ok(Json(ImportBlockedIpsResponse {
+        dry_run: query.dry_run,
+        added,
+        updated,
+        unchanged,
+        duplicate,
+        invalid,
+        results,
+    }))
+}
+
+/// GET /api/security/blocked-ips/export - Export blocked IPs as CSV (import-compatible)
+pub async fn export_blocked_ips(
+    State(state): State<ProxyState>,
+) -> Result<impl IntoResponse, AppError> {
+    let ips = state.app_state.mysql.list_blocked_ips().await?;
+
+    let mut csv = String::from("ip,reason,expires_at,blocked_by\n");
+    for b in &ips {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_escape(&b.ip),
+            csv_escape(b.reason.as_deref().unwrap_or("")),
+            b.expires_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            csv_escape(&b.blocked_by),
+        ));
+    }
+
+    let headers = [
+        (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+        (
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"blocked_ips.csv\"",
+        ),
+    ];
+
+    Ok((StatusCode::OK, headers, csv))
+}
+
 /// GET /api/security/events - List security events
 pub async fn list_security_events(
     State(state): State<ProxyState>,
@@ -148,6 +619,72 @@ pub async fn get_security_events_by_ip(
     Ok(Json(events))
 }
 
+/// Topology device currently holding a profiled IP
+#[derive(Debug, Serialize)]
+pub struct IpProfileDevice {
+    pub node_id: String,
+    pub label: String,
+    pub mac: String,
+    pub node_type: String,
+    pub claimed_by: Option<NodeClaim>,
+}
+
+/// Query parameters for GET /api/security/ip/:ip
+#[derive(Debug, Deserialize)]
+pub struct IpProfileQuery {
+    /// Days in the daily series, ending today (default 30, max 365)
+    pub days: Option<i32>,
+}
+
+/// GET /api/security/ip/:ip - IP profile: block status, recent events, the
+/// topology devices (with claimants) reporting this address and its daily
+/// request statistics (zero-filled, oldest first)
+pub async fn get_ip_profile(
+    State(state): State<ProxyState>,
+    Path(ip): Path<String>,
+    Query(query): Query<IpProfileQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mongo = &state.app_state.mongo;
+    let days = query
+        .days
+        .unwrap_or(30)
+        .clamp(1, ip_stats::MAX_RETENTION_DAYS);
+    let today = Utc::now().date_naive();
+    let stats = mongo
+        .get_ip_daily_stats(&ip, today - chrono::Duration::days(days as i64 - 1))
+        .await
+        .map_err(AppError::database)?;
+    let daily = ip_stats::daily_series(&ip, stats, today, days);
+    let events = mongo.get_security_events_by_ip(&ip, 100).await?;
+    let devices: Vec<IpProfileDevice> = mongo
+        .get_user_object_details_by_ip(&ip)
+        .await
+        .map_err(AppError::database)?
+        .into_iter()
+        .map(|d| IpProfileDevice {
+            node_id: d.id,
+            label: d.label,
+            mac: d.mac,
+            node_type: d.node_type,
+            claimed_by: d.claimed_by,
+        })
+        .collect();
+    let blocked = state
+        .network_policy
+        .load_policy()
+        .evaluate_str(&ip, Surface::Proxy, Utc::now())
+        .reason
+        == Reason::Blocked;
+
+    Ok(Json(serde_json::json!({
+        "ip": ip,
+        "blocked": blocked,
+        "devices": devices,
+        "events": events,
+        "daily": daily,
+    })))
+}
+
 /// GET /api/security/events/search - Advanced security event search
 pub async fn search_security_events(
     State(state): State<ProxyState>,
@@ -156,3 +693,56 @@ pub async fn search_security_events(
     let events = state.app_state.mongo.search_security_events(&query).await?;
     Ok(Json(events))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SecurityEventType, Severity};
+
+    #[test]
+    fn quick_block_request_parses_context_and_preset() {
+        let req: QuickBlockRequest = serde_json::from_value(serde_json::json!({
+            "ip": "203.0.113.7",
+            "context": { "type": "access_log", "id": "6531f0c2a1b2c3d4e5f60718" },
+            "duration": "24h",
+        }))
+        .unwrap();
+        assert_eq!(req.action, QuickBlockAction::Block);
+        assert_eq!(
+            req.context.reference(),
+            "access_log:6531f0c2a1b2c3d4e5f60718"
+        );
+        let now = Utc::now();
+        assert_eq!(
+            req.duration.unwrap().expires_at(now),
+            Some(now + chrono::Duration::days(1))
+        );
+        assert_eq!(BlockDurationPreset::Permanent.expires_at(now), None);
+    }
+
+    #[test]
+    fn block_reasons_describe_the_context() {
+        assert_eq!(
+            access_log_block_reason("/wp-login.php", 37),
+            "blocked from access log: 37 requests to /wp-login.php in 5m"
+        );
+        assert_eq!(
+            access_log_block_reason("/", 1),
+            "blocked from access log: 1 request to / in 5m"
+        );
+
+        let event = SecurityEvent {
+            id: None,
+            timestamp: "2026-10-18T09:30:00Z".parse().unwrap(),
+            event_type: SecurityEventType::RateLimitExceeded,
+            ip: Some("203.0.113.7".to_string()),
+            details: serde_json::json!({ "requests": 600 }),
+            severity: Severity::High,
+            notified: false,
+        };
+        assert_eq!(
+            security_event_block_reason(&event),
+            "blocked from security event: rate limit exceeded (high) at 2026-10-18 09:30 UTC"
+        );
+    }
+}
@@ -7,10 +7,20 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::api::api_trace::{SETTING_API_TRACE_ENABLED_BY, SETTING_API_TRACE_UNTIL};
 use crate::api::auth_middleware::require_permission;
-use crate::error::AppError;
-use crate::models::AuthUser;
+use crate::aranea::config::{AraneaConfigOverride, SETTING_ARANEA_CONFIG};
+use crate::device_class::{DeviceClassRules, SETTING_DEVICE_CLASS_RULES};
+use crate::error::{AppError, ErrorCode};
+use crate::jwt_keys;
+use crate::models::{AuthUser, SecurityHeadersPolicy, Setting};
+use crate::network_policy::{self, SETTING_INTERNET_ACCESS};
+use crate::new_device::{NewDevicePolicy, SETTING_NEW_DEVICE_ALERTS};
+use crate::proxy::geo_rules::SETTING_GEO_RULES;
+use crate::proxy::rate_limit;
+use crate::proxy::security_headers::SETTING_SECURITY_HEADERS;
 use crate::proxy::ProxyState;
+use crate::restart::{self, notify_restart, DEFAULT_DRAIN_GRACE_SEC};
 
 use super::SuccessResponse;
 
@@ -22,6 +32,8 @@ pub struct RestartSettings {
     pub auto_restart_enabled: bool,
     pub cpu_threshold: u32,
     pub ram_threshold: u32,
+    /// Max seconds to wait for in-flight requests and syncs before restarting
+    pub drain_grace_sec: u64,
 }
 
 /// Restart settings update request
@@ -32,24 +44,40 @@ pub struct UpdateRestartSettingsRequest {
     pub auto_restart_enabled: Option<bool>,
     pub cpu_threshold: Option<u32>,
     pub ram_threshold: Option<u32>,
+    pub drain_grace_sec: Option<u64>,
 }
 
+/// Upper bound for `drain_grace_sec`
+const MAX_DRAIN_GRACE_SEC: u64 = 3600;
+
 /// GET /api/settings - List all settings
 pub async fn list_settings(State(state): State<ProxyState>) -> Result<impl IntoResponse, AppError> {
     let settings = state.app_state.mysql.list_settings().await?;
+    Ok(Json(mask_settings(settings)))
+}
 
-    // Mask the Discord webhook URL
-    let masked: Vec<_> = settings
+/// Settings as any logged-in user may read them: the Discord webhook URL,
+/// the session signing secrets and the aranea secrets are masked
+pub fn mask_settings(settings: Vec<Setting>) -> Vec<Setting> {
+    settings
         .into_iter()
         .map(|mut s| {
-            if s.setting_key == "discord_webhook_url" && s.setting_value.is_some() {
+            let secret = s.setting_key == "discord_webhook_url"
+                || (jwt_keys::is_secret_setting(&s.setting_key)
+                    && s.setting_key != jwt_keys::SETTING_PREVIOUS_UNTIL);
+            if secret && s.setting_value.is_some() {
                 s.setting_value = Some("********".to_string());
             }
+            if s.setting_key == SETTING_ARANEA_CONFIG {
+                s.setting_value = s
+                    .setting_value
+                    .as_deref()
+                    .and_then(|v| serde_json::from_str::<AraneaConfigOverride>(v).ok())
+                    .and_then(|o| serde_json::to_string(&o.masked()).ok());
+            }
             s
         })
-        .collect();
-
-    Ok(Json(masked))
+        .collect()
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,10 +94,72 @@ pub async fn update_setting(
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
 
+    // Permission floors are cached and validated; they have their own endpoint
+    if key.starts_with("permission_floor_") {
+        return Err(AppError::BadRequest(
+            "Use PUT /api/auth/permission-floors to change permission floors".to_string(),
+        ));
+    }
+    if jwt_keys::is_secret_setting(&key) {
+        return Err(AppError::BadRequest(
+            "Use POST /api/admin/rotate-jwt-secret to change the session signing secret"
+                .to_string(),
+        ));
+    }
+    if key == SETTING_API_TRACE_UNTIL || key == SETTING_API_TRACE_ENABLED_BY {
+        return Err(AppError::BadRequest(
+            "Use PUT /api/admin/api-trace/recorder to turn the API request recorder on or off"
+                .to_string(),
+        ));
+    }
+    if key == SETTING_ARANEA_CONFIG {
+        return Err(AppError::BadRequest(
+            "Use PUT /api/aranea/config to change the aranea configuration".to_string(),
+        ));
+    }
+    if key == SETTING_GEO_RULES {
+        return Err(AppError::BadRequest(
+            "Use PUT /api/security/geo-rules to change the country rules".to_string(),
+        ));
+    }
+
     // Validate setting key exists
     let existing = state.app_state.mysql.get_setting(&key).await;
     if existing.is_err() {
-        return Err(AppError::NotFound(format!("Setting {} not found", key)));
+        return Err(AppError::coded(
+            ErrorCode::SettingNotFound,
+            format!("Setting {} not found", key),
+        ));
+    }
+
+    if key == SETTING_SECURITY_HEADERS {
+        if let Some(raw) = payload.value.as_deref() {
+            SecurityHeadersPolicy::parse(raw).map_err(|e| {
+                AppError::BadRequest(format!("Invalid security header policy: {}", e))
+            })?;
+        }
+    }
+
+    if key == SETTING_NEW_DEVICE_ALERTS {
+        if let Some(raw) = payload.value.as_deref() {
+            NewDevicePolicy::parse(raw).map_err(|e| {
+                AppError::BadRequest(format!("Invalid new device alert policy: {}", e))
+            })?;
+        }
+    }
+
+    if key == SETTING_DEVICE_CLASS_RULES {
+        if let Some(raw) = payload.value.as_deref() {
+            DeviceClassRules::parse(raw)
+                .map_err(|e| AppError::BadRequest(format!("Invalid device class rules: {}", e)))?;
+        }
+    }
+
+    if network_policy::is_policy_setting(&key) && key != SETTING_INTERNET_ACCESS {
+        if let Some(raw) = payload.value.as_deref() {
+            network_policy::parse_networks(raw)
+                .map_err(|e| AppError::BadRequest(format!("Invalid {}: {}", key, e)))?;
+        }
     }
 
     let updated = state
@@ -80,9 +170,32 @@ pub async fn update_setting(
 
     if updated {
         tracing::info!("Updated setting: {}", key);
+        if key.starts_with("proxy_") {
+            if let Err(e) = state.reload_proxy_limits().await {
+                tracing::error!("Failed to reload proxy limits: {}", e);
+            }
+        }
+        if key == rate_limit::SETTING_ENABLED {
+            if let Err(e) = state.reload_rate_limits().await {
+                tracing::error!("Failed to reload rate limit rules: {}", e);
+            }
+        }
+        if key == SETTING_SECURITY_HEADERS {
+            if let Err(e) = state.reload_security_headers().await {
+                tracing::error!("Failed to reload security header policy: {}", e);
+            }
+        }
+        if network_policy::is_policy_setting(&key) {
+            if let Err(e) = state.rebuild_network_policy().await {
+                tracing::error!("Failed to rebuild network policy: {}", e);
+            }
+        }
         Ok(Json(SuccessResponse::new("Setting updated")))
     } else {
-        Err(AppError::NotFound(format!("Setting {} not found", key)))
+        Err(AppError::coded(
+            ErrorCode::SettingNotFound,
+            format!("Setting {} not found", key),
+        ))
     }
 }
 
@@ -139,6 +252,7 @@ pub async fn get_restart_settings(
         auto_restart_enabled: false,
         cpu_threshold: 90,
         ram_threshold: 90,
+        drain_grace_sec: DEFAULT_DRAIN_GRACE_SEC,
     };
 
     for setting in settings {
@@ -172,6 +286,11 @@ pub async fn get_restart_settings(
                     restart_settings.ram_threshold = v.parse().unwrap_or(90);
                 }
             }
+            "restart_drain_grace_sec" => {
+                if let Some(v) = setting.setting_value {
+                    restart_settings.drain_grace_sec = v.parse().unwrap_or(DEFAULT_DRAIN_GRACE_SEC);
+                }
+            }
             _ => {}
         }
     }
@@ -246,6 +365,20 @@ pub async fn update_restart_settings(
             .await?;
     }
 
+    if let Some(grace) = payload.drain_grace_sec {
+        if grace > MAX_DRAIN_GRACE_SEC {
+            return Err(AppError::BadRequest(format!(
+                "Drain grace period must be 0-{} seconds",
+                MAX_DRAIN_GRACE_SEC
+            )));
+        }
+        state
+            .app_state
+            .mysql
+            .set_setting("restart_drain_grace_sec", Some(&grace.to_string()))
+            .await?;
+    }
+
     tracing::info!("Restart settings updated");
     Ok(Json(SuccessResponse::new("Restart settings updated")))
 }
@@ -255,6 +388,9 @@ pub async fn update_restart_settings(
 pub struct RestartServiceRequest {
     /// Which service to restart: "backend", "frontend", "all"
     pub service: Option<String>,
+    /// Skip the pre-restart drain and restart immediately
+    #[serde(default)]
+    pub force: bool,
 }
 
 /// POST /api/settings/restart/trigger - Manually trigger service restart (dangerous: permission == 100)
@@ -266,29 +402,46 @@ pub async fn trigger_manual_restart(
     require_permission(&user, 100)?;
 
     let service = payload.service.as_deref().unwrap_or("backend");
-    tracing::warn!("Manual restart triggered via API for service: {}", service);
-
-    // Send Discord notification
-    if let Ok(Some(webhook_url)) = state.app_state.mysql.get_discord_webhook_url().await {
-        let client = reqwest::Client::new();
-        let _ = client
-            .post(&webhook_url)
-            .json(&serde_json::json!({
-                "embeds": [{
-                    "title": "Service Restart Triggered",
-                    "description": format!("Service restart initiated: {}", service),
-                    "color": 15105570,
-                    "timestamp": chrono::Utc::now().to_rfc3339(),
-                    "footer": {"text": "LacisProxyGateway2"}
-                }]
-            }))
-            .send()
-            .await;
-    }
-
+    // Only the backend carries proxied traffic and syncs
+    let drain = !payload.force && matches!(service, "backend" | "all");
+    tracing::warn!(
+        "Manual restart triggered via API for service: {} (force={})",
+        service,
+        payload.force
+    );
+
+    let mysql = state.app_state.mysql.clone();
+    let drain_gate = state.drain.clone();
+    let in_flight = state.in_flight.clone();
     // Execute restart in background
     let service_name = service.to_string();
     tokio::spawn(async move {
+        if drain {
+            let grace = restart::drain_grace(&mysql).await;
+            if let Err(report) = drain_gate.drain(&in_flight, grace).await {
+                notify_restart(
+                    &mysql,
+                    "Service Restart Aborted",
+                    &format!(
+                        "Manual restart of {} aborted: drain did not complete within {}s ({}). Retry with force to skip the wait.",
+                        service_name,
+                        grace.as_secs(),
+                        report.describe()
+                    ),
+                    15158332,
+                )
+                .await;
+                return;
+            }
+        }
+
+        notify_restart(
+            &mysql,
+            "Service Restart Triggered",
+            &format!("Service restart initiated: {}", service_name),
+            15105570,
+        )
+        .await;
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
         match service_name.as_str() {
@@ -321,10 +474,18 @@ pub async fn trigger_manual_restart(
                 tracing::error!("Unknown service: {}", service_name);
             }
         }
+
+        // Still running: the restart did not take effect, reopen the proxy
+        drain_gate.cancel();
     });
 
     Ok(Json(SuccessResponse::new(&format!(
-        "Restart initiated for: {}",
-        service
+        "Restart initiated for: {}{}",
+        service,
+        if drain {
+            " (after draining in-flight activity)"
+        } else {
+            ""
+        }
     ))))
 }
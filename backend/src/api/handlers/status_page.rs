@@ -0,0 +1,237 @@
+//! Public status page handlers (GET /status, GET /status.json) and the
+//! per-route opt-in (PUT /api/routes/:id/status-page)
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header, Method, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::AuthUser;
+use crate::proxy::{self, ProxyState};
+use crate::status_page::{self, StatusSnapshot, CACHE_TTL, DEFAULT_RATE_LIMIT_PER_MINUTE};
+
+use super::routes::record_route_version;
+
+/// Longest public display name
+const MAX_NAME_LEN: usize = 100;
+
+/// Body for PUT /api/routes/:id/status-page
+#[derive(Debug, Deserialize)]
+pub struct StatusPageRouteRequest {
+    pub show: bool,
+    /// Required when `show` is true
+    pub display_name: Option<String>,
+}
+
+/// Path and Host of a public status request; None for methods other than
+/// GET/HEAD, which always go to the proxy
+fn status_request_target(req: &Request) -> Option<(String, Option<String>)> {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return None;
+    }
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    Some((req.uri().path().to_string(), host))
+}
+
+/// Whether the request is answered here: only when no proxy route covers the
+/// path (a backend's own /status wins)
+async fn claims_request(state: &ProxyState, target: Option<(String, Option<String>)>) -> bool {
+    let Some((path, host)) = target else {
+        return false;
+    };
+    state
+        .router
+        .read()
+        .await
+        .match_route(&path, host.as_deref())
+        .is_none()
+}
+
+/// Snapshot for a public request; Err is the response to send instead
+/// (None when the page is disabled and the request goes to the proxy)
+async fn public_snapshot(
+    state: &ProxyState,
+    client_ip: &str,
+) -> Result<Arc<StatusSnapshot>, Option<Response>> {
+    let mysql = &state.app_state.mysql;
+    if !mysql
+        .get_setting_bool("status_page_enabled")
+        .await
+        .unwrap_or(false)
+    {
+        return Err(None);
+    }
+
+    let per_minute = mysql
+        .get_setting_i32(
+            "status_page_rate_limit_per_minute",
+            DEFAULT_RATE_LIMIT_PER_MINUTE,
+        )
+        .await
+        .unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE)
+        .max(1) as u32;
+    if !state.status_page.allow(client_ip, per_minute) {
+        return Err(Some(
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, "60")],
+                "Too many requests",
+            )
+                .into_response(),
+        ));
+    }
+
+    state
+        .status_page
+        .snapshot(&state.app_state)
+        .await
+        .map_err(|e| {
+            // The public page never shows internal errors
+            tracing::warn!("Status page snapshot failed: {}", e);
+            Some(
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Status temporarily unavailable",
+                )
+                    .into_response(),
+            )
+        })
+}
+
+fn cache_control() -> (header::HeaderName, String) {
+    (
+        header::CACHE_CONTROL,
+        format!("public, max-age={}", CACHE_TTL.as_secs()),
+    )
+}
+
+/// GET /status - Public HTML status page (proxied like any path when disabled,
+/// for other methods, or when a route covers /status)
+pub async fn get_status_page(
+    State(state): State<ProxyState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+) -> Response {
+    let target = status_request_target(&req);
+    if !claims_request(&state, target).await {
+        return proxy::proxy_handler(State(state), ConnectInfo(addr), req).await;
+    }
+    let client_ip = state
+        .network_policy
+        .load_policy()
+        .client_ip(req.headers(), addr);
+    match public_snapshot(&state, &client_ip).await {
+        Ok(snapshot) => {
+            ([cache_control()], Html(status_page::render_html(&snapshot))).into_response()
+        }
+        Err(Some(response)) => response,
+        Err(None) => proxy::proxy_handler(State(state), ConnectInfo(addr), req).await,
+    }
+}
+
+/// GET /status.json - Public status as JSON (proxied like /status otherwise)
+pub async fn get_status_json(
+    State(state): State<ProxyState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+) -> Response {
+    let target = status_request_target(&req);
+    if !claims_request(&state, target).await {
+        return proxy::proxy_handler(State(state), ConnectInfo(addr), req).await;
+    }
+    let client_ip = state
+        .network_policy
+        .load_policy()
+        .client_ip(req.headers(), addr);
+    match public_snapshot(&state, &client_ip).await {
+        Ok(snapshot) => ([cache_control()], Json(snapshot.as_ref())).into_response(),
+        Err(Some(response)) => response,
+        Err(None) => proxy::proxy_handler(State(state), ConnectInfo(addr), req).await,
+    }
+}
+
+/// PUT /api/routes/:id/status-page - List or unlist a route on the public
+/// status page (admin: permission >= 80)
+pub async fn set_route_status_page(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<StatusPageRouteRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let route = state.app_state.mysql.get_route(id).await?.ok_or_else(|| {
+        AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+    })?;
+
+    let name = req
+        .display_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    if let Some(name) = name {
+        if name.chars().count() > MAX_NAME_LEN {
+            return Err(AppError::validation(
+                "display_name",
+                format!("must be at most {} characters", MAX_NAME_LEN),
+            ));
+        }
+    } else if req.show {
+        return Err(AppError::validation(
+            "display_name",
+            "required to show the route on the status page",
+        ));
+    }
+
+    if !state
+        .app_state
+        .mysql
+        .set_route_status_page(id, req.show, name)
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "status_page", None).await;
+
+    let old = format!(
+        "{} ({})",
+        route.show_on_status_page,
+        route.status_page_name.as_deref().unwrap_or("")
+    );
+    let new = format!("{} ({})", req.show, name.unwrap_or(""));
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "update",
+            Some("status_page"),
+            Some(&old),
+            Some(&new),
+            &user.sub,
+            None,
+        )
+        .await;
+    state.status_page.invalidate().await;
+
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "show_on_status_page": req.show,
+        "status_page_name": name,
+    })))
+}
@@ -0,0 +1,191 @@
+//! MongoDB storage handlers (collection sizes, indexes, compaction)
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use mongodb::bson::Bson;
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::AppError;
+use crate::models::{AuthUser, ConfirmRequired};
+use crate::proxy::ProxyState;
+use crate::storage_stats::{self, collection_growth, format_bytes};
+
+/// Body for POST /api/admin/storage/compact/:collection
+#[derive(Debug, Deserialize, Default)]
+pub struct CompactCollectionRequest {
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// GET /api/admin/storage - Collection sizes and growth, declared vs existing
+/// indexes, and the disk projection (admin: permission >= 80)
+pub async fn get_storage(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let mongo = &state.app_state.mongo;
+
+    let samples = storage_stats::recent_samples(&state.app_state)
+        .await
+        .map_err(AppError::InternalError)?;
+    let mut index_reports = mongo
+        .check_declared_indexes(false)
+        .await
+        .map_err(AppError::InternalError)?;
+
+    let mut collections = Vec::new();
+    let mut total_bytes = 0;
+    for name in mongo
+        .list_collections()
+        .await
+        .map_err(AppError::InternalError)?
+    {
+        let (sample, index_sizes) = match mongo.collection_storage(&name).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                tracing::warn!("Storage stats skipped {}: {}", name, e);
+                continue;
+            }
+        };
+        if let Some(report) = index_reports.iter_mut().find(|r| r.collection == name) {
+            for index in &mut report.indexes {
+                index.size_bytes = match index_sizes.get(&index.name) {
+                    Some(Bson::Int32(v)) => Some(*v as u64),
+                    Some(Bson::Int64(v)) => Some(*v as u64),
+                    Some(Bson::Double(v)) => Some(*v as u64),
+                    _ => None,
+                };
+            }
+        }
+        total_bytes += sample.disk_bytes();
+        collections.push(serde_json::json!({
+            "growth_bytes_per_day": collection_growth(&samples, &name),
+            "disk_bytes": sample.disk_bytes(),
+            "disk": format_bytes(sample.disk_bytes()),
+            "name": sample.name,
+            "count": sample.count,
+            "size": sample.size,
+            "storage_size": sample.storage_size,
+            "index_size": sample.index_size,
+        }));
+    }
+    collections.sort_by_key(|c| std::cmp::Reverse(c["disk_bytes"].as_u64().unwrap_or(0)));
+
+    let storage = mongo.storage_stats().await.ok();
+    let (warning_days, max_bytes) = storage_stats::projection_settings(&state.app_state).await;
+    let projection = storage_stats::project(
+        &samples,
+        total_bytes,
+        storage.as_ref(),
+        max_bytes,
+        warning_days,
+    );
+
+    let mut warnings: Vec<String> = projection.warning.iter().cloned().collect();
+    for report in &index_reports {
+        if !report.missing.is_empty() {
+            warnings.push(format!(
+                "{} is missing {} declared index(es)",
+                report.collection,
+                report.missing.len()
+            ));
+        }
+        for mismatch in &report.mismatched {
+            warnings.push(format!(
+                "Index {} on {} differs from its declaration",
+                mismatch.name, report.collection
+            ));
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "collections": collections,
+        "total_bytes": total_bytes,
+        "indexes": index_reports,
+        "filesystem": storage.map(|s| serde_json::json!({
+            "used_bytes": s.fs_used_size,
+            "total_bytes": s.fs_total_size,
+            "used_percent": s.fs_used_percent(),
+        })),
+        "projection": projection,
+        "samples": samples.len(),
+        "warnings": warnings,
+    })))
+}
+
+/// POST /api/admin/storage/compact/:collection - Reclaim a collection's free
+/// space; blocks writes to it while running (dangerous: permission == 100,
+/// confirm required)
+pub async fn compact_collection(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(collection): Path<String>,
+    body: Option<Json<CompactCollectionRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let req = body.map(|Json(b)| b).unwrap_or_default();
+    let mongo = &state.app_state.mongo;
+
+    if !mongo
+        .list_collections()
+        .await
+        .map_err(AppError::InternalError)?
+        .contains(&collection)
+    {
+        return Err(AppError::NotFound(format!(
+            "Collection {} not found",
+            collection
+        )));
+    }
+
+    if !req.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "compact_collection".to_string(),
+            target: collection,
+            warning: "Compaction blocks the collection while it runs; ingestion and logging into it stall until it finishes.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    let (before, _) = mongo
+        .collection_storage(&collection)
+        .await
+        .map_err(AppError::InternalError)?;
+    let result = mongo
+        .compact_collection(&collection)
+        .await
+        .map_err(AppError::InternalError)?;
+    let (after, _) = mongo
+        .collection_storage(&collection)
+        .await
+        .map_err(AppError::InternalError)?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "mongo_collection",
+            None,
+            "compact",
+            Some("disk_bytes"),
+            Some(&before.disk_bytes().to_string()),
+            Some(&after.disk_bytes().to_string()),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "collection": collection,
+        "before_bytes": before.disk_bytes(),
+        "after_bytes": after.disk_bytes(),
+        "reclaimed_bytes": before.disk_bytes().saturating_sub(after.disk_bytes()),
+        "result": result,
+    })))
+}
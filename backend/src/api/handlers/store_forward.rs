@@ -0,0 +1,293 @@
+//! Store-and-forward policy and queue handlers (/api/routes/:id/store-forward,
+//! /api/routes/:id/queue)
+
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{AuthUser, ConfirmRequired, ProxyRoute};
+use crate::proxy::store_forward::{self, stamp, RouteStoreForward, MAX_BODY_BYTES_CAP};
+use crate::proxy::ProxyState;
+
+use super::routes::record_route_version;
+use super::SuccessResponse;
+
+/// Body for PUT /api/routes/:id/store-forward
+#[derive(Debug, Deserialize)]
+pub struct StoreForwardRequest {
+    /// New policy; null switches store-and-forward off
+    pub policy: Option<RouteStoreForward>,
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Query parameters for GET /api/routes/:id/queue
+#[derive(Debug, Deserialize)]
+pub struct ForwardQueueQuery {
+    /// "pending" | "failed" | "delivered" (default: all)
+    pub status: Option<String>,
+    /// Default 100, max 500
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiscardQueuedQuery {
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+async fn load_route(state: &ProxyState, id: i32) -> Result<ProxyRoute, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_route(id)
+        .await?
+        .ok_or_else(|| AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id)))
+}
+
+/// PUT /api/routes/:id/store-forward - Set or clear the route's store-and-forward
+/// policy; enabling it requires confirm (dangerous: permission == 100)
+pub async fn set_route_store_forward(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<StoreForwardRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let route = load_route(&state, id).await?;
+
+    let policy = req
+        .policy
+        .as_ref()
+        .map(|p| {
+            p.normalize()
+                .map_err(|e| AppError::BadRequest(format!("store_forward: {}", e)))
+        })
+        .transpose()?;
+
+    if let (Some(policy), false) = (&policy, req.confirm) {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "enable_store_forward".to_string(),
+            target: format!("route #{} ({} → {})", id, route.path, route.target),
+            warning: format!(
+                "Matching {} requests will be answered 202 and replayed later when the \
+                 upstream is unreachable or answers 502/503. A replay can deliver a request \
+                 the upstream already processed, so the consumer must be idempotent. \
+                 Bodies up to {} bytes are stored in MongoDB until delivered or {}s pass.",
+                policy.methods.join("/"),
+                policy.max_body_bytes,
+                policy.ttl_secs
+            ),
+            confirm_required: true,
+        })));
+    }
+
+    let column = policy.as_ref().and_then(RouteStoreForward::to_column);
+    if !state
+        .app_state
+        .mysql
+        .set_route_store_forward(id, column.as_deref())
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "store_forward", None).await;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "update",
+            Some("store_forward"),
+            route.store_forward.as_deref(),
+            column.as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after store_forward change: {}", e);
+    }
+
+    tracing::info!(
+        "Store-and-forward {} on route {} (by {})",
+        if policy.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        id,
+        user.sub
+    );
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "store_forward": policy,
+        "max_body_bytes_cap": MAX_BODY_BYTES_CAP,
+    })))
+}
+
+/// GET /api/routes/:id/queue - Queued requests of a route, bodies omitted and
+/// credentials masked (admin: permission >= 80)
+pub async fn get_route_queue(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(query): Query<ForwardQueueQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let route = load_route(&state, id).await?;
+
+    let status = query.status.as_deref().filter(|s| !s.is_empty());
+    if let Some(s) = status {
+        if !matches!(s, "pending" | "failed" | "delivered") {
+            return Err(AppError::BadRequest(format!("Unknown status: {}", s)));
+        }
+    }
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+    let mongo = &state.app_state.mongo;
+    let items = mongo
+        .list_forward_items(id, status, limit)
+        .await
+        .map_err(AppError::database)?;
+    let pending = mongo
+        .count_forward_items(id, "pending")
+        .await
+        .map_err(AppError::database)?;
+    let failed = mongo
+        .count_forward_items(id, "failed")
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "store_forward": route.store_forward(),
+        "pending": pending,
+        "failed": failed,
+        "items": items.into_iter().map(|i| i.redacted()).collect::<Vec<_>>(),
+    })))
+}
+
+/// POST /api/routes/:id/queue/:queue_id/replay - Deliver a pending or failed
+/// item now (admin: permission >= 80)
+pub async fn replay_queued_request(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, queue_id)): Path<(i32, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mongo = &state.app_state.mongo;
+    let existing = mongo
+        .get_forward_item(&queue_id)
+        .await
+        .map_err(AppError::database)?
+        .filter(|item| item.route_id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Queued request {} not found", queue_id)))?;
+    if existing.status == "delivered" {
+        return Err(AppError::BadRequest(format!(
+            "Queued request {} was already delivered",
+            queue_id
+        )));
+    }
+
+    let now = Utc::now();
+    let item = mongo
+        .claim_forward_item_by_id(&queue_id, &stamp(now), &store_forward::manual_lease(now))
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::BadRequest(format!("Queued request {} is being replayed", queue_id))
+        })?;
+
+    let status = store_forward::replay(&state.app_state, &state.http_client, &item)
+        .await
+        .map_err(AppError::InternalError)?;
+    tracing::info!(
+        "Manual replay of {} on route {} by {}: {}",
+        queue_id,
+        id,
+        user.sub,
+        status
+    );
+
+    let item = mongo
+        .get_forward_item(&queue_id)
+        .await
+        .map_err(AppError::database)?
+        .map(|i| i.redacted());
+    Ok(Json(serde_json::json!({
+        "queue_id": queue_id,
+        "status": status,
+        "item": item,
+    })))
+}
+
+/// DELETE /api/routes/:id/queue/:queue_id - Discard a queued request
+/// (dangerous: permission == 100, confirm required)
+pub async fn discard_queued_request(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path((id, queue_id)): Path<(i32, String)>,
+    Query(query): Query<DiscardQueuedQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let mongo = &state.app_state.mongo;
+    let item = mongo
+        .get_forward_item(&queue_id)
+        .await
+        .map_err(AppError::database)?
+        .filter(|item| item.route_id == id)
+        .ok_or_else(|| AppError::NotFound(format!("Queued request {} not found", queue_id)))?;
+
+    if !query.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "discard_queued_request".to_string(),
+            target: format!("{} {} ({})", item.method, item.path, queue_id),
+            warning: format!(
+                "The {} request will be removed and never delivered.",
+                item.status
+            ),
+            confirm_required: true,
+        })));
+    }
+
+    mongo
+        .delete_forward_item(&queue_id)
+        .await
+        .map_err(AppError::database)?;
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "discard_queued_request",
+            Some("store_forward"),
+            Some(&format!(
+                "{} {} ({}, {})",
+                item.method, item.path, queue_id, item.status
+            )),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!(SuccessResponse::new(
+        "Queued request discarded"
+    ))))
+}
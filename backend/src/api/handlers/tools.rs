@@ -10,7 +10,9 @@ use serde::Deserialize;
 use crate::api::auth_middleware::require_permission;
 use crate::db::mongo::{OperationLogQuery, OperatorInfo};
 use crate::error::AppError;
+use crate::log_retention::LogPruner;
 use crate::models::AuthUser;
+use crate::poll_schedule::PollOutcome;
 use crate::proxy::ProxyState;
 
 /// Helper to build OperatorInfo from AuthUser
@@ -38,8 +40,12 @@ pub async fn tool_sync_omada(
 
     let start = std::time::Instant::now();
 
-    let syncer =
-        crate::omada::OmadaSyncer::new(state.omada_manager.clone(), state.app_state.mongo.clone(), state.app_state.mysql.clone());
+    let syncer = crate::omada::OmadaSyncer::new(
+        state.omada_manager.clone(),
+        state.app_state.mongo.clone(),
+        state.app_state.mysql.clone(),
+    )
+    .with_notifier(state.notifier.clone());
 
     let controller_ids = state.omada_manager.list_controller_ids().await;
     let mut results = Vec::new();
@@ -100,10 +106,12 @@ pub async fn tool_sync_openwrt(
     let router_ids = state.openwrt_manager.list_router_ids().await;
     let mut ok_count = 0u32;
     let mut err_count = 0u32;
+    let mut paused_count = 0u32;
 
     for rid in &router_ids {
         match syncer.poll_one(rid).await {
-            Ok(()) => ok_count += 1,
+            Ok(PollOutcome::Polled) => ok_count += 1,
+            Ok(PollOutcome::Paused) => paused_count += 1,
             Err(_) => err_count += 1,
         }
     }
@@ -115,7 +123,7 @@ pub async fn tool_sync_openwrt(
             .mongo
             .complete_operation_log(
                 &op_id,
-                Some(&serde_json::json!({ "ok": ok_count, "errors": err_count })),
+                Some(&serde_json::json!({ "ok": ok_count, "errors": err_count, "paused": paused_count })),
                 duration,
             )
             .await;
@@ -125,6 +133,7 @@ pub async fn tool_sync_openwrt(
         "ok": true,
         "polled": ok_count,
         "errors": err_count,
+        "paused": paused_count,
         "duration_ms": duration,
     })))
 }
@@ -154,10 +163,12 @@ pub async fn tool_sync_external(
     let device_ids = state.external_manager.list_device_ids().await;
     let mut ok_count = 0u32;
     let mut err_count = 0u32;
+    let mut paused_count = 0u32;
 
     for did in &device_ids {
         match syncer.poll_one(did).await {
-            Ok(()) => ok_count += 1,
+            Ok(PollOutcome::Polled) => ok_count += 1,
+            Ok(PollOutcome::Paused) => paused_count += 1,
             Err(_) => err_count += 1,
         }
     }
@@ -169,7 +180,7 @@ pub async fn tool_sync_external(
             .mongo
             .complete_operation_log(
                 &op_id,
-                Some(&serde_json::json!({ "ok": ok_count, "errors": err_count })),
+                Some(&serde_json::json!({ "ok": ok_count, "errors": err_count, "paused": paused_count })),
                 duration,
             )
             .await;
@@ -179,6 +190,7 @@ pub async fn tool_sync_external(
         "ok": true,
         "polled": ok_count,
         "errors": err_count,
+        "paused": paused_count,
         "duration_ms": duration,
     })))
 }
@@ -216,7 +228,7 @@ pub async fn tool_ddns_update_all(
 
     for config in &configs {
         match state.ddns_updater.update_single(config.id).await {
-            Ok(()) => ok_count += 1,
+            Ok(_) => ok_count += 1,
             Err(_) => err_count += 1,
         }
     }
@@ -239,6 +251,28 @@ pub async fn tool_ddns_update_all(
     })))
 }
 
+/// POST /api/tools/logs/prune - Prune access logs, security events and
+/// health checks past their retention now (admin: permission >= 80)
+pub async fn tool_logs_prune(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let start = std::time::Instant::now();
+    let results = LogPruner::new(state.app_state.clone())
+        .run("api", Some(operator_from(&user)))
+        .await
+        .ok_or_else(|| AppError::BadRequest("Log pruning is already running".to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "ok": results.iter().all(|r| r.error.is_none()),
+        "deleted": results.iter().map(|r| r.deleted).sum::<u64>(),
+        "results": results,
+        "duration_ms": start.elapsed().as_millis() as u64,
+    })))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PingRequest {
     pub host: String,
@@ -2,7 +2,8 @@
 //!
 //! Reads user_object_detail (SSoT) and returns topology data.
 //! Layout computation is done entirely on the frontend (DFS O(n)).
-//! No position data is stored or returned from the backend.
+//! The backend only stores position overrides (dragged or pinned nodes),
+//! guarded by the layout generation returned in the v2 metadata.
 //!
 //! Architecture:
 //! - Backend: user_object_detail → nodes + edges (no position)
@@ -14,13 +15,16 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::api::auth_middleware::require_permission;
-use crate::db::mongo::topology::{LogicDeviceDoc, TopologyStateDoc};
-use crate::db::mongo::user_object_detail::UserObjectDetail;
-use crate::error::AppError;
+use crate::db::mongo::topology::{LayoutClaim, LogicDeviceDoc, NodePosition, TopologyStateDoc};
+use crate::db::mongo::topology_revision::TopologyChangeKind;
+use crate::db::mongo::user_object_detail::{ClaimOutcome, NodeClaim, UserObjectDetail};
+use crate::error::{AppError, ErrorCode};
+use crate::mac::canonical_node_id;
 use crate::models::{AuthUser, ConfirmQuery, ConfirmRequired};
+use crate::omada::health;
 use crate::proxy::ProxyState;
 use crate::user_object_ingester::logic_device_pseudo_mac;
 
@@ -51,6 +55,8 @@ pub struct TopologyNodeV2 {
     pub device_type: Option<String>,
     pub product_type: Option<String>,
     pub network_device_type: Option<String>,
+    /// Clients only: phone, laptop, printer, camera, iot, server, unknown
+    pub device_class: Option<String>,
     pub status: String,
     pub state_type: String,
     pub metadata: serde_json::Value,
@@ -60,6 +66,7 @@ pub struct TopologyNodeV2 {
     pub connection_type: String,
     pub fid: Option<String>,
     pub facility_name: Option<String>,
+    pub claimed_by: Option<NodeClaim>,
 }
 
 #[derive(Debug, Serialize)]
@@ -77,6 +84,16 @@ pub struct TopologyMetadata {
     pub controllers: usize,
     pub routers: usize,
     pub logic_devices: usize,
+    /// Nodes reported offline by their source
+    pub offline_nodes: usize,
+    /// Nodes whose Omada controller is offline, state unknown
+    pub unknown_nodes: usize,
+    /// Classified clients per device_class
+    pub device_classes: BTreeMap<String, usize>,
+    /// Topology revision this snapshot includes (pass to /api/topology/watch)
+    pub revision: i64,
+    /// Layout generation to send with position batches and layout recalcs
+    pub layout_generation: i64,
     pub generated_at: String,
 }
 
@@ -138,6 +155,14 @@ pub struct TopologyV2Query {
     pub collapsed: bool,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TopologyWatchQuery {
+    /// Last revision the client has seen (omit to just read the current one)
+    pub since: Option<i64>,
+    /// Seconds to hold the request (default 30, max 120)
+    pub timeout: Option<u64>,
+}
+
 fn default_view() -> String {
     "full".to_string()
 }
@@ -165,6 +190,51 @@ pub struct UpdateOrderRequest {
     pub new_order: u32,
 }
 
+/// Maximum positions in one batch
+const MAX_POSITION_BATCH: usize = 5000;
+
+#[derive(Debug, Deserialize)]
+pub struct PositionUpdate {
+    pub node_id: String,
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePositionsRequest {
+    /// Layout generation the positions were computed from
+    pub base_generation: i64,
+    pub positions: Vec<PositionUpdate>,
+}
+
+/// Part of the graph a layout recalc resets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum LayoutScope {
+    All,
+    /// `root_id` and all its descendants
+    Subtree {
+        root_id: String,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecalcLayoutRequest {
+    /// Layout generation the recalc was requested from
+    pub base_generation: i64,
+    #[serde(flatten)]
+    pub scope: LayoutScope,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignClaimRequest {
+    /// Owner to assign; null clears the claim
+    pub lacis_id: Option<String>,
+    pub display_name: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateLogicDeviceRequest {
     pub label: String,
@@ -221,12 +291,14 @@ struct RawNode {
     device_type: Option<String>,
     product_type: Option<String>,
     network_device_type: Option<String>,
+    device_class: Option<String>,
     status: String,
     state_type: String,
     metadata: serde_json::Value,
     connection_type: String,
     fid: Option<String>,
     facility_name: Option<String>,
+    claimed_by: Option<NodeClaim>,
 }
 
 #[derive(Clone)]
@@ -298,12 +370,14 @@ async fn build_raw_topology(state: &ProxyState) -> (Vec<RawNode>, Vec<RawEdge>,
             device_type: Some(entry.device_type.clone()),
             product_type: entry.product_type.clone(),
             network_device_type: entry.network_device_type.clone(),
+            device_class: entry.device_class.clone(),
             status: entry.state_type.clone(),
             state_type: entry.state_type.clone(),
             metadata: entry.metadata.clone(),
             connection_type: entry.connection_type.clone(),
             fid: entry.fid.clone(),
             facility_name: entry.facility_name.clone(),
+            claimed_by: entry.claimed_by.clone(),
         });
 
         // Create edge from parent to this node
@@ -337,12 +411,14 @@ async fn build_raw_topology(state: &ProxyState) -> (Vec<RawNode>, Vec<RawEdge>,
         device_type: None,
         product_type: None,
         network_device_type: None,
+        device_class: None,
         status: "online".to_string(),
         state_type: "online".to_string(),
         metadata: serde_json::json!({}),
         connection_type: "wired".to_string(),
         fid: None,
         facility_name: None,
+        claimed_by: None,
     });
 
     (nodes, edges, device_count, client_count)
@@ -474,8 +550,18 @@ pub async fn get_topology_v2(
     State(state): State<ProxyState>,
     Query(query): Query<TopologyV2Query>,
 ) -> Result<impl IntoResponse, AppError> {
+    Ok(Json(build_topology_v2(&state, &query).await))
+}
+
+/// The v2 payload for a view; shared with the public share endpoint
+pub(crate) async fn build_topology_v2(
+    state: &ProxyState,
+    query: &TopologyV2Query,
+) -> TopologyV2Response {
     let mongo = &state.app_state.mongo;
-    let (raw_nodes, raw_edges, device_count, client_count) = build_raw_topology(&state).await;
+    // Read before building so no change between the two is missed
+    let revision = mongo.topology_revision().await.unwrap_or(0);
+    let (raw_nodes, raw_edges, device_count, client_count) = build_raw_topology(state).await;
 
     // Load collapsed state
     let topo_state = mongo
@@ -485,6 +571,7 @@ pub async fn get_topology_v2(
             key: "global".to_string(),
             collapsed_node_ids: Vec::new(),
             last_layout_at: String::new(),
+            layout_generation: 0,
         });
     let collapsed_set: HashSet<String> = topo_state.collapsed_node_ids.iter().cloned().collect();
 
@@ -573,6 +660,15 @@ pub async fn get_topology_v2(
         .len();
     let routers = raw_nodes.iter().filter(|n| n.node_type == "router").count();
     let logic_device_count = raw_nodes.iter().filter(|n| n.source == "manual").count();
+    let offline_nodes = raw_nodes.iter().filter(|n| n.status == "offline").count();
+    let unknown_nodes = raw_nodes
+        .iter()
+        .filter(|n| n.status == health::UNKNOWN_CONTROLLER_OFFLINE)
+        .count();
+    let mut device_classes: BTreeMap<String, usize> = BTreeMap::new();
+    for class in raw_nodes.iter().filter_map(|n| n.device_class.as_ref()) {
+        *device_classes.entry(class.clone()).or_default() += 1;
+    }
 
     let nodes: Vec<TopologyNodeV2> = raw_nodes
         .iter()
@@ -600,6 +696,7 @@ pub async fn get_topology_v2(
                 device_type: n.device_type.clone(),
                 product_type: n.product_type.clone(),
                 network_device_type: n.network_device_type.clone(),
+                device_class: n.device_class.clone(),
                 status: n.status.clone(),
                 state_type: n.state_type.clone(),
                 metadata: n.metadata.clone(),
@@ -609,6 +706,7 @@ pub async fn get_topology_v2(
                 connection_type: n.connection_type.clone(),
                 fid: n.fid.clone(),
                 facility_name: n.facility_name.clone(),
+                claimed_by: n.claimed_by.clone(),
             }
         })
         .collect();
@@ -625,7 +723,7 @@ pub async fn get_topology_v2(
         })
         .collect();
 
-    Ok(Json(TopologyV2Response {
+    TopologyV2Response {
         nodes,
         edges,
         metadata: TopologyMetadata {
@@ -634,12 +732,94 @@ pub async fn get_topology_v2(
             controllers,
             routers,
             logic_devices: logic_device_count,
+            offline_nodes,
+            unknown_nodes,
+            device_classes,
+            revision,
+            layout_generation: topo_state.layout_generation,
             generated_at: chrono::Utc::now().to_rfc3339(),
         },
         view_config: ViewConfig {
             collapsed_node_ids: topo_state.collapsed_node_ids,
         },
-    }))
+    }
+}
+
+const WATCH_DEFAULT_TIMEOUT_SECS: u64 = 30;
+const WATCH_MAX_TIMEOUT_SECS: u64 = 120;
+/// Re-read the revision while waiting, for writes made by other instances
+const WATCH_POLL_SECS: u64 = 5;
+/// Raw change rows read per response
+const WATCH_MAX_ROWS: i64 = 5000;
+
+/// GET /api/topology/watch — long-poll for topology changes after `since`.
+/// Returns at once when the revision is already newer, otherwise holds the
+/// request until it advances or `timeout` elapses. Waiting does not hold a
+/// Mongo connection.
+pub async fn watch_topology(
+    State(state): State<ProxyState>,
+    Query(query): Query<TopologyWatchQuery>,
+) -> Result<axum::response::Response, AppError> {
+    let mongo = &state.app_state.mongo;
+
+    let Some(since) = query.since else {
+        let revision = mongo
+            .topology_revision()
+            .await
+            .map_err(AppError::database)?;
+        return Ok(Json(serde_json::json!({
+            "revision": revision,
+            "changes": [],
+            "truncated": false,
+            "timed_out": false,
+        }))
+        .into_response());
+    };
+
+    let Ok(_slot) = state.topology_watchers.clone().try_acquire_owned() else {
+        return Ok((
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "error": "Too many topology watchers",
+                "status": 429,
+            })),
+        )
+            .into_response());
+    };
+
+    let timeout = std::time::Duration::from_secs(
+        query
+            .timeout
+            .unwrap_or(WATCH_DEFAULT_TIMEOUT_SECS)
+            .min(WATCH_MAX_TIMEOUT_SECS),
+    );
+    let deadline = tokio::time::Instant::now() + timeout;
+    // Subscribe before the first read so a bump in between still wakes us
+    let mut revisions = mongo.subscribe_topology_revision();
+
+    let mut timed_out = false;
+    loop {
+        let changes = mongo
+            .topology_changes_since(since, WATCH_MAX_ROWS)
+            .await
+            .map_err(AppError::database)?;
+        if changes.revision > since || timed_out {
+            return Ok(Json(serde_json::json!({
+                "revision": changes.revision,
+                "changes": changes.changes,
+                "truncated": changes.truncated,
+                "timed_out": timed_out,
+            }))
+            .into_response());
+        }
+
+        let poll = tokio::time::sleep(std::time::Duration::from_secs(WATCH_POLL_SECS));
+        tokio::select! {
+            _ = revisions.changed() => {}
+            _ = poll => {}
+            _ = tokio::time::sleep_until(deadline) => timed_out = true,
+        }
+    }
 }
 
 /// PUT /api/topology/nodes/:id/label — update node label via user_object_detail SSoT
@@ -649,6 +829,7 @@ pub async fn update_node_label(
     Path(node_id): Path<String>,
     Json(req): Json<UpdateLabelRequest>,
 ) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
     require_permission(&user, 50)?;
 
     let label = req.label.trim();
@@ -666,7 +847,10 @@ pub async fn update_node_label(
         .await
         .map_err(|e| AppError::InternalError(e))?
         .ok_or_else(|| {
-            AppError::NotFound(format!("Node '{}' not found", node_id))
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
         })?;
 
     // Update label with customized=true
@@ -688,6 +872,7 @@ pub async fn delete_node_label(
     Extension(user): Extension<AuthUser>,
     Path(node_id): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
     require_permission(&user, 50)?;
 
     let mongo = &state.app_state.mongo;
@@ -697,7 +882,10 @@ pub async fn delete_node_label(
         .await
         .map_err(|e| AppError::InternalError(e))?
         .ok_or_else(|| {
-            AppError::NotFound(format!("Node '{}' not found", node_id))
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
         })?;
 
     mongo
@@ -719,6 +907,7 @@ pub async fn update_node_parent(
     Path(node_id): Path<String>,
     Json(req): Json<UpdateParentRequest>,
 ) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
     require_permission(&user, 80)?;
 
     let mongo = &state.app_state.mongo;
@@ -729,10 +918,13 @@ pub async fn update_node_parent(
         .await
         .map_err(|e| AppError::InternalError(e))?
         .ok_or_else(|| {
-            AppError::NotFound(format!("Node '{}' not found", node_id))
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
         })?;
 
-    let new_parent_id = &req.new_parent_id;
+    let new_parent_id = &canonical_node_id(&req.new_parent_id);
 
     // Validate: new parent must be "INTERNET" or exist and be eligible
     if new_parent_id != "INTERNET" {
@@ -798,6 +990,7 @@ pub async fn update_node_order(
     Path(node_id): Path<String>,
     Json(req): Json<UpdateOrderRequest>,
 ) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
     require_permission(&user, 50)?;
 
     let mongo = &state.app_state.mongo;
@@ -808,7 +1001,10 @@ pub async fn update_node_order(
         .await
         .map_err(|e| AppError::InternalError(e))?
         .ok_or_else(|| {
-            AppError::NotFound(format!("Node '{}' not found", node_id))
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
         })?;
 
     mongo
@@ -829,6 +1025,7 @@ pub async fn toggle_node_collapse(
     Path(node_id): Path<String>,
     Json(req): Json<CollapseRequest>,
 ) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
     state
         .app_state
         .mongo
@@ -843,6 +1040,585 @@ pub async fn toggle_node_collapse(
     })))
 }
 
+/// Claim the generation after `base`, or fail with 409 and the current one
+async fn claim_layout(state: &ProxyState, base: i64) -> Result<i64, AppError> {
+    match state
+        .app_state
+        .mongo
+        .claim_layout_generation(base)
+        .await
+        .map_err(AppError::database)?
+    {
+        LayoutClaim::Claimed(generation) => Ok(generation),
+        LayoutClaim::Stale(current) => Err(AppError::coded(
+            ErrorCode::LayoutGenerationConflict,
+            format!(
+                "Layout generation {} is stale (current {}); reload the topology",
+                base, current
+            ),
+        )
+        .with_details(serde_json::json!({
+            "base_generation": base,
+            "current_generation": current,
+        }))),
+    }
+}
+
+/// `root` and its descendants, from child → parent links
+fn subtree_ids(root: &str, parents: &HashMap<String, String>) -> Vec<String> {
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (child, parent) in parents {
+        children
+            .entry(parent.as_str())
+            .or_default()
+            .push(child.as_str());
+    }
+
+    let mut ids = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        // Guards against parent cycles in the stored links
+        if !seen.insert(id) {
+            continue;
+        }
+        ids.push(id.to_string());
+        if let Some(kids) = children.get(id) {
+            stack.extend(kids.iter().copied());
+        }
+    }
+    ids
+}
+
+/// PUT /api/topology/positions — store position overrides for a batch of
+/// nodes; 409 when `base_generation` is stale
+pub async fn update_node_positions(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<UpdatePositionsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    if req.positions.is_empty() {
+        return Err(AppError::BadRequest(
+            "positions must not be empty".to_string(),
+        ));
+    }
+    if req.positions.len() > MAX_POSITION_BATCH {
+        return Err(AppError::BadRequest(format!(
+            "At most {} positions per batch",
+            MAX_POSITION_BATCH
+        )));
+    }
+    if let Some(p) = req
+        .positions
+        .iter()
+        .find(|p| !p.x.is_finite() || !p.y.is_finite())
+    {
+        return Err(AppError::BadRequest(format!(
+            "Position of '{}' is not a finite number",
+            p.node_id
+        )));
+    }
+
+    let generation = claim_layout(&state, req.base_generation).await?;
+    let positions: Vec<NodePosition> = req
+        .positions
+        .iter()
+        .map(|p| NodePosition {
+            node_id: canonical_node_id(&p.node_id),
+            x: p.x,
+            y: p.y,
+            pinned: p.pinned,
+            layout_generation: generation,
+        })
+        .collect();
+    state
+        .app_state
+        .mongo
+        .batch_upsert_node_positions(&positions)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "updated": positions.len(),
+        "layout_generation": generation,
+    })))
+}
+
+/// POST /api/topology/layout/recalc — drop unpinned position overrides of
+/// the whole graph or one subtree so the computed layout applies again;
+/// 409 when `base_generation` is stale
+pub async fn recalc_layout(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<RecalcLayoutRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 50)?;
+
+    let mongo = &state.app_state.mongo;
+    let scope_ids = match &req.scope {
+        LayoutScope::All => None,
+        LayoutScope::Subtree { root_id } => {
+            let root_id = canonical_node_id(root_id);
+            let entries = mongo
+                .get_all_user_object_details()
+                .await
+                .map_err(AppError::database)?;
+            if !entries.iter().any(|e| e.id == root_id) {
+                return Err(AppError::coded(
+                    ErrorCode::NodeNotFound,
+                    format!("Node '{}' not found", root_id),
+                ));
+            }
+            let parents: HashMap<String, String> =
+                entries.into_iter().map(|e| (e.id, e.parent_id)).collect();
+            Some(subtree_ids(&root_id, &parents))
+        }
+    };
+
+    let generation = claim_layout(&state, req.base_generation).await?;
+    let cleared = mongo
+        .clear_unpinned_node_positions(scope_ids.as_deref())
+        .await
+        .map_err(AppError::database)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    mongo
+        .set_last_layout_at(&now)
+        .await
+        .map_err(AppError::database)?;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "scope": req.scope,
+        "nodes_in_scope": scope_ids.as_ref().map(Vec::len),
+        "cleared": cleared,
+        "layout_generation": generation,
+        "last_layout_at": now,
+    })))
+}
+
+/// Bounds for GET /api/topology/nodes/:id/detail sub-queries
+const DETAIL_HISTORY_LIMIT: i64 = 50;
+const DETAIL_EVENTS_LIMIT: i64 = 20;
+const DETAIL_ACCESS_LOG_LIMIT: i64 = 50;
+const DETAIL_CHILDREN_LIMIT: i64 = 500;
+/// Per sub-query; a slow part is reported in `unavailable` instead of
+/// holding up the whole response
+const DETAIL_SUBQUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Child summary in a node detail
+#[derive(Debug, Serialize)]
+pub struct NodeDetailChild {
+    pub id: String,
+    pub label: String,
+    pub node_type: String,
+    pub state_type: String,
+    pub ip: Option<String>,
+    pub mac: String,
+}
+
+/// LacisID registration status of a node
+#[derive(Debug, Serialize)]
+pub struct NodeLacisStatus {
+    /// registered, candidate or none
+    pub status: &'static str,
+    pub lacis_id: Option<String>,
+    pub candidate_lacis_id: Option<String>,
+    pub aranea_lacis_id: Option<String>,
+}
+
+impl NodeLacisStatus {
+    fn of(node: &UserObjectDetail) -> Self {
+        let status = if node.lacis_id.as_deref().is_some_and(|id| !id.is_empty()) {
+            "registered"
+        } else if crate::aranea::registration::is_candidate(node) {
+            "candidate"
+        } else {
+            "none"
+        };
+        Self {
+            status,
+            lacis_id: node.lacis_id.clone(),
+            candidate_lacis_id: node.candidate_lacis_id.clone(),
+            aranea_lacis_id: node.aranea_lacis_id.clone(),
+        }
+    }
+}
+
+/// Run one detail sub-query under the timeout; failures become the empty
+/// value and are reported by name
+async fn detail_part<T: Default, E: std::fmt::Display>(
+    part: &'static str,
+    query: impl std::future::Future<Output = Result<T, E>>,
+) -> (T, Option<&'static str>) {
+    match tokio::time::timeout(DETAIL_SUBQUERY_TIMEOUT, query).await {
+        Ok(Ok(value)) => (value, None),
+        Ok(Err(e)) => {
+            tracing::warn!("[Topology] Node detail {} failed: {}", part, e);
+            (T::default(), Some(part))
+        }
+        Err(_) => {
+            tracing::warn!("[Topology] Node detail {} timed out", part);
+            (T::default(), Some(part))
+        }
+    }
+}
+
+/// GET /api/topology/nodes/:id/detail — everything known about a node
+///
+/// Combines the user_object_detail document with its raw source record,
+/// recent state changes, security events and access logs for its IP, LacisID
+/// status, claim and children. Sub-queries run concurrently and are bounded;
+/// missing data is an empty list (or null), and parts that failed or timed out
+/// are listed in `unavailable`.
+pub async fn get_node_detail(
+    State(state): State<ProxyState>,
+    Path(node_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
+    let started = std::time::Instant::now();
+    let mongo = &state.app_state.mongo;
+    let mysql = &state.app_state.mysql;
+
+    let node = mongo
+        .get_user_object_detail_by_id(&node_id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node '{}' not found", node_id),
+            )
+        })?;
+    let ip = node.ip.as_deref().filter(|ip| !ip.is_empty());
+    let source_ref = node.source_ref_id.as_deref();
+
+    let (
+        (source_record, source_err),
+        (state_history, history_err),
+        (security_events, events_err),
+        (access_logs, logs_err),
+        (children, children_err),
+    ) = tokio::join!(
+        detail_part("source_record", async {
+            match source_ref {
+                Some(source_ref) => mongo.get_node_source_record(source_ref).await,
+                None => Ok(None),
+            }
+        }),
+        detail_part(
+            "state_history",
+            mysql.list_device_state_history(&node_id, DETAIL_HISTORY_LIMIT)
+        ),
+        detail_part("security_events", async {
+            match ip {
+                Some(ip) => {
+                    mongo
+                        .get_security_events_by_ip(ip, DETAIL_EVENTS_LIMIT)
+                        .await
+                }
+                None => Ok(Vec::new()),
+            }
+        }),
+        detail_part("access_logs", async {
+            match ip {
+                Some(ip) => {
+                    mongo
+                        .get_access_logs_by_ip(ip, DETAIL_ACCESS_LOG_LIMIT)
+                        .await
+                }
+                None => Ok(Vec::new()),
+            }
+        }),
+        detail_part(
+            "children",
+            mongo.get_user_object_detail_children(&node_id, DETAIL_CHILDREN_LIMIT)
+        ),
+    );
+    let unavailable: Vec<&str> = [source_err, history_err, events_err, logs_err, children_err]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let children: Vec<NodeDetailChild> = children
+        .into_iter()
+        .map(|c| NodeDetailChild {
+            id: c.id,
+            label: c.label,
+            node_type: c.node_type,
+            state_type: c.state_type,
+            ip: c.ip,
+            mac: c.mac,
+        })
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "node_id": node_id,
+        "lacis": NodeLacisStatus::of(&node),
+        "claim": node.claimed_by.clone(),
+        "source_record": source_record,
+        "state_history": state_history,
+        "security_events": security_events,
+        "access_logs": access_logs,
+        "children_truncated": children.len() as i64 >= DETAIL_CHILDREN_LIMIT,
+        "children": children,
+        "node": node,
+        "unavailable": unavailable,
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+    })))
+}
+
+const CLAIM_DISPLAY_NAME_MAX: usize = 64;
+
+/// Trimmed display name, falling back to `default`
+fn claim_display_name(requested: Option<&str>, default: &str) -> Result<String, AppError> {
+    let name = requested.map(str::trim).unwrap_or(default);
+    if name.is_empty() || name.chars().count() > CLAIM_DISPLAY_NAME_MAX {
+        return Err(AppError::BadRequest(format!(
+            "Display name must be 1-{} characters",
+            CLAIM_DISPLAY_NAME_MAX
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// 409 naming the current owner, so the conflict is settled between people
+fn claim_conflict(node_id: &str, claim: &NodeClaim) -> axum::response::Response {
+    (
+        axum::http::StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "error": format!("Node '{}' is already claimed by {}", node_id, claim.display_name),
+            "status": 409,
+            "claimed_by": claim,
+        })),
+    )
+        .into_response()
+}
+
+async fn audit_claim(
+    state: &ProxyState,
+    action: &str,
+    node_id: &str,
+    old: Option<&NodeClaim>,
+    new: Option<&NodeClaim>,
+    changed_by: &str,
+) {
+    let describe = |claim: Option<&NodeClaim>| {
+        serde_json::json!({
+            "node_id": node_id,
+            "lacis_id": claim.map(|c| c.lacis_id.as_str()),
+            "display_name": claim.map(|c| c.display_name.as_str()),
+        })
+        .to_string()
+    };
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "topology_node",
+            None,
+            action,
+            Some("claimed_by"),
+            Some(&describe(old)),
+            Some(&describe(new)),
+            changed_by,
+            None,
+        )
+        .await;
+}
+
+/// POST /api/topology/nodes/:id/claim — bind a node to the caller's LacisID.
+/// 409 with the current owner when someone else holds it. The name shown
+/// to others is the session subject; only admins name owners freely
+/// (`assign_node_claim`).
+pub async fn claim_node(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(node_id): Path<String>,
+) -> Result<axum::response::Response, AppError> {
+    let node_id = canonical_node_id(&node_id);
+    require_permission(&user, 0)?;
+
+    let lacis_id = user.lacis_id.clone().ok_or_else(|| {
+        AppError::BadRequest("Claiming a device requires a LacisOath login".to_string())
+    })?;
+    let mongo = &state.app_state.mongo;
+    let previous = mongo
+        .get_user_object_detail_by_id(&node_id)
+        .await
+        .map_err(AppError::database)?
+        .and_then(|n| n.claimed_by);
+    let claim = NodeClaim {
+        lacis_id,
+        display_name: user.sub.clone(),
+        claimed_at: chrono::Utc::now().to_rfc3339(),
+        assigned_by: None,
+    };
+
+    match mongo
+        .claim_user_object_detail(&node_id, &claim)
+        .await
+        .map_err(AppError::database)?
+    {
+        ClaimOutcome::Claimed => {}
+        ClaimOutcome::Conflict(existing) => return Ok(claim_conflict(&node_id, &existing)),
+        ClaimOutcome::NotFound => {
+            return Err(AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            ))
+        }
+    }
+
+    audit_claim(
+        &state,
+        "claim",
+        &node_id,
+        previous.as_ref(),
+        Some(&claim),
+        &user.sub,
+    )
+    .await;
+    tracing::info!(
+        "Node {} claimed by {} ({})",
+        node_id,
+        claim.lacis_id,
+        claim.display_name
+    );
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "node_id": node_id,
+        "claimed_by": claim,
+    }))
+    .into_response())
+}
+
+/// DELETE /api/topology/nodes/:id/claim — release the caller's own claim
+pub async fn release_node_claim(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(node_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
+    require_permission(&user, 0)?;
+
+    let mongo = &state.app_state.mongo;
+    let node = mongo
+        .get_user_object_detail_by_id(&node_id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
+        })?;
+
+    let Some(claim) = node.claimed_by else {
+        return Ok(Json(serde_json::json!({
+            "ok": true,
+            "node_id": node_id,
+            "released": false,
+        })));
+    };
+    if user.lacis_id.as_deref() != Some(claim.lacis_id.as_str()) {
+        return Err(AppError::Forbidden(format!(
+            "Node '{}' is claimed by {}; only they or an admin can release it",
+            node_id, claim.display_name
+        )));
+    }
+
+    // Cleared only while it is still the caller's claim
+    let released = mongo
+        .release_user_object_detail_claim(&node_id, &claim.lacis_id)
+        .await
+        .map_err(AppError::database)?;
+    if released {
+        audit_claim(&state, "release", &node_id, Some(&claim), None, &user.sub).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "node_id": node_id,
+        "released": released,
+    })))
+}
+
+/// PUT /api/topology/nodes/:id/claim — admin: assign a claim to any user, or
+/// clear it with `lacis_id: null`
+pub async fn assign_node_claim(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(node_id): Path<String>,
+    Json(req): Json<AssignClaimRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let node_id = canonical_node_id(&node_id);
+    require_permission(&user, 80)?;
+
+    let mongo = &state.app_state.mongo;
+    let node = mongo
+        .get_user_object_detail_by_id(&node_id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::NodeNotFound,
+                format!("Node \'{}\' not found", node_id),
+            )
+        })?;
+
+    let claim = match req.lacis_id.as_deref().map(str::trim) {
+        Some("") => {
+            return Err(AppError::BadRequest(
+                "lacis_id must not be empty".to_string(),
+            ));
+        }
+        Some(lacis_id) => Some(NodeClaim {
+            lacis_id: lacis_id.to_string(),
+            display_name: claim_display_name(req.display_name.as_deref(), lacis_id)?,
+            claimed_at: chrono::Utc::now().to_rfc3339(),
+            assigned_by: Some(user.sub.clone()),
+        }),
+        None => None,
+    };
+
+    mongo
+        .set_user_object_detail_claim(&node_id, claim.as_ref())
+        .await
+        .map_err(AppError::database)?;
+    let action = if claim.is_some() {
+        "assign_claim"
+    } else {
+        "clear_claim"
+    };
+    audit_claim(
+        &state,
+        action,
+        &node_id,
+        node.claimed_by.as_ref(),
+        claim.as_ref(),
+        &user.sub,
+    )
+    .await;
+    tracing::info!(
+        "Node {} claim {:?} -> {:?} by {}",
+        node_id,
+        node.claimed_by.as_ref().map(|c| &c.lacis_id),
+        claim.as_ref().map(|c| &c.lacis_id),
+        user.sub
+    );
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "node_id": node_id,
+        "claimed_by": claim,
+    })))
+}
+
 /// POST /api/topology/logic-devices — create logic device
 /// Also adds to user_object_detail SSoT and cg_logic_devices (metadata)
 pub async fn create_logic_device(
@@ -902,6 +1678,7 @@ pub async fn create_logic_device(
         state_type: "StaticOnline".to_string(),
         label: req.label,
         label_customized: false,
+        claimed_by: None,
         ip: req.ip,
         hostname: None,
         source: "manual".to_string(),
@@ -914,6 +1691,7 @@ pub async fn create_logic_device(
         fid: None,
         facility_name: None,
         ssid: None,
+        device_class: None,
         metadata: serde_json::json!({
             "location": &req.location,
             "note": &req.note,
@@ -980,6 +1758,16 @@ pub async fn update_logic_device(
         )));
     }
 
+    // The node itself lives in user_object_detail under the pseudo-MAC
+    if let Some(uuid) = id.strip_prefix("logic:") {
+        let node_id = logic_device_pseudo_mac(uuid);
+        state
+            .app_state
+            .mongo
+            .note_topology_change(&[&node_id], TopologyChangeKind::Updated)
+            .await;
+    }
+
     Ok(Json(serde_json::json!({
         "ok": true,
         "id": id,
@@ -1070,3 +1858,121 @@ fn extract_ip_from_url(url: &str) -> String {
         .unwrap_or(url)
         .to_string()
 }
+
+#[derive(Debug, Deserialize)]
+pub struct DedupeMacsQuery {
+    /// Report the planned merges without writing
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// POST /api/topology/nodes/dedupe-macs — merge nodes whose ids differ only by
+/// MAC formatting into the canonical id (admin: permission >= 80)
+pub async fn dedupe_mac_nodes(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Query(query): Query<DedupeMacsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let mongo = &state.app_state.mongo;
+    let nodes = mongo
+        .get_all_user_object_details()
+        .await
+        .map_err(AppError::database)?;
+    let merges = crate::node_dedup::plan_mac_merges(&nodes);
+
+    let mut errors = Vec::new();
+    if !query.dry_run {
+        for merge in &merges {
+            if let Err(e) = crate::node_dedup::apply_merge(mongo, merge).await {
+                tracing::error!(
+                    "[Topology] MAC dedupe of {} failed: {}",
+                    merge.canonical_id,
+                    e
+                );
+                errors.push(serde_json::json!({ "canonical_id": merge.canonical_id, "error": e }));
+                continue;
+            }
+            let _ = state
+                .app_state
+                .mysql
+                .log_audit(
+                    "topology_node",
+                    None,
+                    "merge_mac_duplicates",
+                    Some("_id"),
+                    Some(&merge.removed_ids.join(",")),
+                    Some(&merge.canonical_id),
+                    &user.sub,
+                    None,
+                )
+                .await;
+        }
+        tracing::info!(
+            "[Topology] MAC dedupe: {} merge(s), {} failed",
+            merges.len(),
+            errors.len()
+        );
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": errors.is_empty(),
+        "dry_run": query.dry_run,
+        "merges": merges,
+        "removed": merges.iter().map(|m| m.removed_ids.len()).sum::<usize>(),
+        "errors": errors,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(c, p)| (c.to_string(), p.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn subtree_covers_root_and_descendants_only() {
+        let parents = links(&[
+            ("gw", "INTERNET"),
+            ("sw1", "gw"),
+            ("ap1", "sw1"),
+            ("cl1", "ap1"),
+            ("sw2", "gw"),
+        ]);
+        let mut ids = subtree_ids("sw1", &parents);
+        ids.sort();
+        assert_eq!(ids, vec!["ap1", "cl1", "sw1"]);
+        assert_eq!(subtree_ids("cl1", &parents), vec!["cl1"]);
+    }
+
+    #[test]
+    fn subtree_stops_at_parent_cycles() {
+        let parents = links(&[("a", "b"), ("b", "a")]);
+        let mut ids = subtree_ids("a", &parents);
+        ids.sort();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn recalc_scope_is_tagged() {
+        let req: RecalcLayoutRequest =
+            serde_json::from_str(r#"{"base_generation":3,"scope":"subtree","root_id":"sw1"}"#)
+                .unwrap();
+        assert_eq!(req.base_generation, 3);
+        assert_eq!(
+            req.scope,
+            LayoutScope::Subtree {
+                root_id: "sw1".to_string()
+            }
+        );
+        let req: RecalcLayoutRequest =
+            serde_json::from_str(r#"{"base_generation":0,"scope":"all"}"#).unwrap();
+        assert_eq!(req.scope, LayoutScope::All);
+    }
+}
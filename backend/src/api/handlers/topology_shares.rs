@@ -0,0 +1,537 @@
+//! Read-only topology shares: management under /api/topology/shares and the
+//! unauthenticated GET /api/public/topology?token=...
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{
+    AuthUser, ConfirmQuery, ConfirmRequired, CreateTopologyShareRequest, TopologyShare,
+};
+use crate::node_order;
+use crate::proxy::ProxyState;
+use crate::topology_share::{
+    self, ShareDetail, ShareView, DEFAULT_EXPIRES_IN_HOURS, DEFAULT_IP_RATE_PER_MINUTE,
+    DEFAULT_TOKEN_RATE_PER_MINUTE, MAX_EXPIRES_IN_HOURS,
+};
+
+use super::topology::{build_topology_v2, TopologyV2Query, TopologyV2Response};
+use super::SuccessResponse;
+
+const MAX_SHARE_NAME_LEN: usize = 100;
+const MAX_FID_LEN: usize = 50;
+/// Path the plaintext token is appended to
+const PUBLIC_PATH: &str = "/api/public/topology";
+
+/// A share with its plaintext, returned only when created
+#[derive(Debug, Serialize)]
+pub struct IssuedTopologyShare {
+    #[serde(flatten)]
+    pub share: TopologyShare,
+    /// Not retrievable later
+    pub token: String,
+    /// Path and query serving the share, relative to LPG's public origin
+    pub public_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PublicTopologyQuery {
+    #[serde(default)]
+    pub token: String,
+}
+
+async fn load_topology_share(state: &ProxyState, id: i32) -> Result<TopologyShare, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_topology_share(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::TopologyShareNotFound,
+                format!("Topology share {} not found", id),
+            )
+        })
+}
+
+/// GET /api/topology/shares - List topology shares, without their tokens
+/// (admin: permission >= 80)
+pub async fn list_topology_shares(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let shares = state.app_state.mysql.list_topology_shares().await?;
+    Ok(Json(shares))
+}
+
+/// POST /api/topology/shares - Issue a read-only token for one topology
+/// view; the plaintext is returned once (admin: permission >= 80)
+pub async fn create_topology_share(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateTopologyShareRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > MAX_SHARE_NAME_LEN {
+        return Err(AppError::validation(
+            "name",
+            format!("must be 1-{} characters", MAX_SHARE_NAME_LEN),
+        ));
+    }
+    let fid = req
+        .fid
+        .as_deref()
+        .map(str::trim)
+        .filter(|fid| !fid.is_empty());
+    match (req.view, fid) {
+        (ShareView::Site, None) => {
+            return Err(AppError::validation("fid", "required for the site view"))
+        }
+        (ShareView::Site, Some(fid)) if fid.len() > MAX_FID_LEN => {
+            return Err(AppError::validation(
+                "fid",
+                format!("must be at most {} characters", MAX_FID_LEN),
+            ))
+        }
+        (ShareView::Full, Some(_)) => {
+            return Err(AppError::validation(
+                "fid",
+                "only allowed for the site view",
+            ))
+        }
+        _ => {}
+    }
+    let hours = req.expires_in_hours.unwrap_or(DEFAULT_EXPIRES_IN_HOURS);
+    if !(1..=MAX_EXPIRES_IN_HOURS).contains(&hours) {
+        return Err(AppError::validation(
+            "expires_in_hours",
+            format!("must be 1-{}", MAX_EXPIRES_IN_HOURS),
+        ));
+    }
+
+    let mysql = &state.app_state.mysql;
+    if mysql
+        .list_topology_shares()
+        .await?
+        .iter()
+        .any(|s| s.name == name && s.revoked_at.is_none())
+    {
+        return Err(AppError::validation("name", "already in use"));
+    }
+
+    let (plaintext, hash, prefix) = topology_share::generate();
+    let id = mysql
+        .create_topology_share(
+            name,
+            req.view,
+            fid,
+            req.detail,
+            &hash,
+            &prefix,
+            Utc::now() + chrono::Duration::hours(hours),
+            &user.sub,
+        )
+        .await?;
+    let share = load_topology_share(&state, id).await?;
+
+    let _ = mysql
+        .log_audit(
+            "topology_share",
+            Some(id),
+            "create",
+            None,
+            None,
+            Some(&format!(
+                "{} view {}{} detail {} until {}",
+                share.name,
+                share.view.as_str(),
+                share
+                    .fid
+                    .as_deref()
+                    .map(|fid| format!(" fid {}", fid))
+                    .unwrap_or_default(),
+                share.detail.as_str(),
+                share.expires_at.to_rfc3339()
+            )),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    let public_path = format!("{}?token={}", PUBLIC_PATH, plaintext);
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedTopologyShare {
+            share,
+            token: plaintext,
+            public_path,
+        }),
+    ))
+}
+
+/// DELETE /api/topology/shares/:id - Revoke a share; applies to the next
+/// fetch (admin: permission >= 80)
+pub async fn revoke_topology_share(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(confirm): Query<ConfirmQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let share = load_topology_share(&state, id).await?;
+    if !confirm.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "revoke_topology_share".to_string(),
+            target: format!("Topology share #{} ({})", id, share.name),
+            warning: "Pages embedding this share will stop showing the topology.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    if !state.app_state.mysql.revoke_topology_share(id).await? {
+        return Err(AppError::BadRequest(format!(
+            "Topology share {} is already revoked",
+            share.name
+        )));
+    }
+    state.topology_shares.forget(id);
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "topology_share",
+            Some(id),
+            "revoke",
+            None,
+            Some(&share.name),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!(SuccessResponse::new(
+        "Topology share revoked"
+    ))))
+}
+
+/// Headers of every public response: never cached, sniffed, indexed or
+/// framed, and no referrer leaking the token of an embedding page
+fn harden(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (header::CACHE_CONTROL, "no-store"),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        (header::REFERRER_POLICY, "no-referrer"),
+        (
+            header::CONTENT_SECURITY_POLICY,
+            "default-src 'none'; frame-ancestors 'none'",
+        ),
+        (header::HeaderName::from_static("x-robots-tag"), "noindex"),
+    ] {
+        headers.insert(name, HeaderValue::from_static(value));
+    }
+    headers.remove(header::SET_COOKIE);
+    response
+}
+
+fn public_error(status: StatusCode, message: &'static str) -> Response {
+    let mut response = (status, Json(serde_json::json!({ "error": message }))).into_response();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from_static("60"));
+    }
+    harden(response)
+}
+
+/// GET /api/public/topology?token=... - Topology of one share, without an
+/// LPG session. Unknown, expired and revoked tokens all get the same 404.
+pub async fn get_public_topology(
+    State(state): State<ProxyState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<PublicTopologyQuery>,
+) -> Response {
+    let mysql = &state.app_state.mysql;
+    let guard = &state.topology_shares;
+
+    // Per IP first, so token guessing is limited before any lookup
+    let client_ip = state.network_policy.load_policy().client_ip(&headers, addr);
+    let ip_rate = mysql
+        .get_setting_i32(
+            "topology_share_ip_rate_per_minute",
+            DEFAULT_IP_RATE_PER_MINUTE,
+        )
+        .await
+        .unwrap_or(DEFAULT_IP_RATE_PER_MINUTE)
+        .max(1) as u32;
+    if !guard.allow(&format!("ip:{}", client_ip), ip_rate) {
+        return public_error(StatusCode::TOO_MANY_REQUESTS, "Too many requests");
+    }
+
+    let token = query.token.trim();
+    let share = if token.is_empty() {
+        None
+    } else {
+        match mysql
+            .find_topology_share(&topology_share::hash(token))
+            .await
+        {
+            Ok(share) => share,
+            Err(e) => {
+                tracing::warn!("Topology share lookup failed: {}", e);
+                return public_error(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Topology temporarily unavailable",
+                );
+            }
+        }
+    };
+    let Some(share) = share.filter(|s| topology_share::is_live(s, Utc::now())) else {
+        return public_error(StatusCode::NOT_FOUND, "Unknown or expired share");
+    };
+
+    let token_rate = mysql
+        .get_setting_i32(
+            "topology_share_token_rate_per_minute",
+            DEFAULT_TOKEN_RATE_PER_MINUTE,
+        )
+        .await
+        .unwrap_or(DEFAULT_TOKEN_RATE_PER_MINUTE)
+        .max(1) as u32;
+    if !guard.allow(&format!("token:{}", share.id), token_rate) {
+        return public_error(StatusCode::TOO_MANY_REQUESTS, "Too many requests");
+    }
+
+    let payload = match guard.cached(share.id) {
+        Some(payload) => payload,
+        None => {
+            let query = TopologyV2Query {
+                view: share.view.as_str().to_string(),
+                fid: share.fid.clone(),
+                collapsed: true,
+            };
+            let topology = build_topology_v2(&state, &query).await;
+            let payload = match serde_json::to_value(scope_payload(topology, share.detail)) {
+                Ok(payload) => Arc::new(payload),
+                Err(e) => {
+                    tracing::warn!("Topology share payload failed: {}", e);
+                    return public_error(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Topology temporarily unavailable",
+                    );
+                }
+            };
+            guard.store(share.id, payload.clone());
+            payload
+        }
+    };
+
+    if let Err(e) = mysql.record_topology_share_use(share.id).await {
+        tracing::warn!("Topology share {} usage not recorded: {}", share.id, e);
+    }
+
+    harden(Json(payload.as_ref()).into_response())
+}
+
+/// Reduce a v2 payload to what a share exposes. Node ids become opaque
+/// (they embed MACs), and lacis_ids, claims and metadata (source
+/// references, controller ids) are always removed; MACs and IPs are kept
+/// only at `labels_ips`. Below that, clients labeled from their MAC (no
+/// name of their own) become "client N".
+fn scope_payload(mut topology: TopologyV2Response, detail: ShareDetail) -> TopologyV2Response {
+    let opaque: HashMap<String, String> = topology
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.clone(), format!("n{}", i + 1)))
+        .collect();
+
+    let mut unnamed_clients = 0;
+    for node in &mut topology.nodes {
+        node.id = opaque[&node.id].clone();
+        node.parent_id = node
+            .parent_id
+            .as_ref()
+            .and_then(|pid| opaque.get(pid).cloned());
+        node.lacis_id = None;
+        node.candidate_lacis_id = None;
+        node.claimed_by = None;
+        node.metadata = serde_json::Value::Null;
+        if detail == ShareDetail::Labels {
+            if node.node_type == "client"
+                && node_order::is_mac_label(&node.label, node.mac.as_deref().unwrap_or(""))
+            {
+                unnamed_clients += 1;
+                node.label = format!("client {}", unnamed_clients);
+            }
+            node.mac = None;
+            node.ip = None;
+        }
+    }
+    for edge in &mut topology.edges {
+        edge.from = opaque.get(&edge.from).cloned().unwrap_or_default();
+        edge.to = opaque.get(&edge.to).cloned().unwrap_or_default();
+    }
+    topology.view_config.collapsed_node_ids = topology
+        .view_config
+        .collapsed_node_ids
+        .iter()
+        .filter_map(|id| opaque.get(id).cloned())
+        .collect();
+    topology
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::handlers::topology::{
+        TopologyEdge, TopologyMetadata, TopologyNodeV2, ViewConfig,
+    };
+
+    fn node(id: &str, label: &str, parent_id: Option<&str>) -> TopologyNodeV2 {
+        TopologyNodeV2 {
+            id: id.to_string(),
+            label: label.to_string(),
+            node_type: "switch".to_string(),
+            mac: Some("AA:BB:CC:00:11:22".to_string()),
+            ip: Some("192.168.3.10".to_string()),
+            source: "omada".to_string(),
+            parent_id: parent_id.map(str::to_string),
+            order: 0,
+            lacis_id: Some("3022AABBCC0011220001".to_string()),
+            candidate_lacis_id: Some("3022AABBCC0011220001".to_string()),
+            device_type: None,
+            product_type: None,
+            network_device_type: None,
+            device_class: None,
+            status: "online".to_string(),
+            state_type: "online".to_string(),
+            metadata: serde_json::json!({ "source_ref_id": "ctrl-1" }),
+            collapsed: false,
+            collapsed_child_count: 0,
+            descendant_count: 0,
+            connection_type: "wired".to_string(),
+            fid: Some("0150".to_string()),
+            facility_name: None,
+            claimed_by: None,
+        }
+    }
+
+    fn topology() -> TopologyV2Response {
+        TopologyV2Response {
+            nodes: vec![
+                node("device:AABBCC001122", "Core switch", None),
+                node(
+                    "client:AABBCC001133",
+                    "Printer",
+                    Some("device:AABBCC001122"),
+                ),
+            ],
+            edges: vec![TopologyEdge {
+                from: "device:AABBCC001122".to_string(),
+                to: "client:AABBCC001133".to_string(),
+                edge_type: "wired".to_string(),
+                label: None,
+            }],
+            metadata: TopologyMetadata {
+                total_devices: 1,
+                total_clients: 1,
+                controllers: 1,
+                routers: 0,
+                logic_devices: 0,
+                offline_nodes: 0,
+                unknown_nodes: 0,
+                device_classes: Default::default(),
+                revision: 1,
+                layout_generation: 1,
+                generated_at: String::new(),
+            },
+            view_config: ViewConfig {
+                collapsed_node_ids: vec![
+                    "device:AABBCC001122".to_string(),
+                    "device:elsewhere".to_string(),
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn labels_level_strips_identifiers_and_addresses() {
+        let scoped = scope_payload(topology(), ShareDetail::Labels);
+        let json = serde_json::to_string(&scoped).unwrap();
+        for secret in ["AABBCC", "192.168.3.10", "3022", "ctrl-1"] {
+            assert!(!json.contains(secret), "{} leaked: {}", secret, json);
+        }
+        assert_eq!(scoped.nodes[0].id, "n1");
+        assert_eq!(scoped.nodes[1].parent_id.as_deref(), Some("n1"));
+        assert_eq!(scoped.edges[0].from, "n1");
+        assert_eq!(scoped.edges[0].to, "n2");
+        assert_eq!(scoped.view_config.collapsed_node_ids, vec!["n1"]);
+        assert_eq!(scoped.nodes[1].label, "Printer");
+    }
+
+    #[test]
+    fn labels_level_hides_mac_derived_client_labels() {
+        let with_clients = || {
+            let mut topology = topology();
+            topology.nodes[1].node_type = "client".to_string();
+            for (mac, label) in [
+                ("AA:BB:CC:00:11:44", "AA:BB:CC:00:11:44"),
+                ("AA-BB-CC-00-11-55", "Apple (00:11:55)"),
+            ] {
+                let mut client = node(
+                    &format!("client:{}", mac),
+                    label,
+                    Some("device:AABBCC001122"),
+                );
+                client.node_type = "client".to_string();
+                client.mac = Some(mac.to_string());
+                topology.nodes.push(client);
+            }
+            topology
+        };
+
+        let scoped = scope_payload(with_clients(), ShareDetail::Labels);
+        let labels: Vec<&str> = scoped.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["Core switch", "Printer", "client 1", "client 2"]
+        );
+        let json = serde_json::to_string(&scoped).unwrap();
+        for secret in ["00:11:44", "00:11:55"] {
+            assert!(!json.contains(secret), "{} leaked: {}", secret, json);
+        }
+
+        // labels_ips shows the MAC anyway
+        let scoped = scope_payload(with_clients(), ShareDetail::LabelsIps);
+        assert_eq!(scoped.nodes[3].label, "Apple (00:11:55)");
+    }
+
+    #[test]
+    fn labels_ips_level_keeps_addresses_only() {
+        let scoped = scope_payload(topology(), ShareDetail::LabelsIps);
+        assert_eq!(scoped.nodes[0].ip.as_deref(), Some("192.168.3.10"));
+        assert!(scoped.nodes[0].mac.is_some());
+        assert!(scoped.nodes[0].lacis_id.is_none());
+        assert!(scoped.nodes[0].metadata.is_null());
+        assert_eq!(scoped.nodes[0].id, "n1");
+    }
+}
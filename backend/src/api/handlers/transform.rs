@@ -0,0 +1,135 @@
+//! Transformation script handlers (/api/routes/:id/transform)
+
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::api::auth_middleware::require_permission;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{AuthUser, ProxyRoute};
+use crate::proxy::transform::{RouteTransform, MAX_BODY_KB, MAX_SCRIPT_BYTES, MAX_TIMEOUT_MS};
+use crate::proxy::ProxyState;
+
+use super::routes::record_route_version;
+
+/// Body for PUT /api/routes/:id/transform
+#[derive(Debug, Deserialize)]
+pub struct TransformRequest {
+    /// New policy; null removes the script
+    pub policy: Option<RouteTransform>,
+}
+
+async fn load_route(state: &ProxyState, id: i32) -> Result<ProxyRoute, AppError> {
+    state
+        .app_state
+        .mysql
+        .get_route(id)
+        .await?
+        .ok_or_else(|| AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id)))
+}
+
+fn limits() -> serde_json::Value {
+    serde_json::json!({
+        "max_script_bytes": MAX_SCRIPT_BYTES,
+        "max_timeout_ms": MAX_TIMEOUT_MS,
+        "max_body_kb": MAX_BODY_KB,
+    })
+}
+
+/// Validation error on the policy field the message is about
+fn policy_error(message: String) -> AppError {
+    let field = ["timeout_ms", "body_kb"]
+        .into_iter()
+        .find(|f| message.starts_with(f))
+        .unwrap_or("script");
+    AppError::validation(format!("policy.{}", field), message)
+}
+
+/// GET /api/routes/:id/transform - Script policy and execution metrics
+/// (admin: permission >= 80; scripts may embed signing keys)
+pub async fn get_route_transform(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let route = load_route(&state, id).await?;
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "transform": route.transform(),
+        "metrics": state.route_transforms.metrics(id),
+        "limits": limits(),
+    })))
+}
+
+/// PUT /api/routes/:id/transform - Set or remove the route's transformation
+/// script; it is compiled first and compile errors are returned as validation
+/// errors (dangerous: permission == 100)
+pub async fn set_route_transform(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<TransformRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let route = load_route(&state, id).await?;
+
+    let policy = req
+        .policy
+        .as_ref()
+        .map(|p| p.normalize().map_err(policy_error))
+        .transpose()?;
+    let column = policy.as_ref().and_then(RouteTransform::to_column);
+
+    if !state
+        .app_state
+        .mysql
+        .set_route_transform(id, column.as_deref())
+        .await?
+    {
+        return Err(AppError::coded(
+            ErrorCode::RouteNotFound,
+            format!("Route {} not found", id),
+        ));
+    }
+    record_route_version(&state, id, &user.sub, "transform", None).await;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "route",
+            Some(id),
+            "update",
+            Some("transform"),
+            route.transform.as_deref(),
+            column.as_deref(),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    state.route_transforms.forget(id);
+    if let Err(e) = state.reload_routes().await {
+        tracing::error!("Failed to reload routes after transform change: {}", e);
+    }
+
+    tracing::info!(
+        "Transformation script {} on route {} (by {})",
+        match &policy {
+            Some(p) if p.enabled => "enabled",
+            Some(_) => "staged",
+            None => "removed",
+        },
+        id,
+        user.sub
+    );
+    Ok(Json(serde_json::json!({
+        "route_id": id,
+        "transform": policy,
+        "limits": limits(),
+    })))
+}
@@ -1,21 +1,34 @@
 //! WireGuard API handlers
 //!
-//! Key generation, peer CRUD (via Omada OpenAPI), config file generation.
+//! Key generation, peer CRUD (via Omada OpenAPI), config profiles and
+//! config file generation.
 
 use axum::{
     extract::{Path, Query, State},
     response::IntoResponse,
     Extension, Json,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
 use crate::api::auth_middleware::require_permission;
-use crate::error::AppError;
-use crate::models::{AuthUser, ConfirmRequired};
+use crate::error::{AppError, ErrorCode};
+use crate::models::{
+    AuthUser, ConfirmQuery, ConfirmRequired, CreateWgProfileRequest, UpdateWgProfileRequest,
+    WgConfigProfile,
+};
 use crate::omada::client::{CreateWgPeerRequest, UpdateWgPeerRequest};
 use crate::proxy::ProxyState;
+use crate::wireguard::expiry::{self, format_expiry};
+use crate::wireguard::unified::{self, UnifiedPeer};
 use crate::wireguard::{config as wg_config, keygen};
 
+use super::SuccessResponse;
+
+/// Profiles only shape newly generated configs; distributed files are static
+const PROFILE_CHANGE_NOTE: &str = "Configs generated before this change are not updated; \
+     regenerate and redistribute them to apply the new profile.";
+
 // ============================================================================
 // Request types
 // ============================================================================
@@ -30,6 +43,10 @@ pub struct CreatePeerApiRequest {
     pub allow_address: Vec<String>,
     pub keep_alive: Option<i32>,
     pub comment: Option<String>,
+    /// Config profile the peer is provisioned with (None = default profile)
+    pub profile_id: Option<i32>,
+    /// Disable the peer automatically after this time
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize)]
@@ -40,6 +57,37 @@ pub struct UpdatePeerApiRequest {
     pub allow_address: Option<Vec<String>>,
     pub keep_alive: Option<i32>,
     pub comment: Option<String>,
+    /// New expiry; null removes it
+    #[serde(default, deserialize_with = "crate::models::nullable")]
+    pub expires_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// Body for POST /api/wireguard/peers/:id/extend (exactly one field)
+#[derive(Deserialize)]
+pub struct ExtendPeerRequest {
+    /// New expiry time
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Days added to the current expiry (or to now, once expired)
+    pub days: Option<i64>,
+}
+
+/// Body for PUT /api/wireguard/all-peers/:id (None = unchanged)
+#[derive(Deserialize)]
+pub struct UpdateUnifiedPeerRequest {
+    pub name: Option<String>,
+    pub allow_address: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+    /// Omada only
+    pub keep_alive: Option<i32>,
+    /// Omada only
+    pub comment: Option<String>,
+}
+
+/// Body for POST /api/wireguard/all-peers/:id/resolve
+#[derive(Deserialize)]
+pub struct ResolvePeerConflictRequest {
+    /// "lpg" (push LPG's addresses to the controller) or "omada" (adopt the controller's)
+    pub winner: String,
 }
 
 #[derive(Deserialize)]
@@ -56,6 +104,128 @@ pub struct WgInterfaceQuery {
     pub site_id: Option<String>,
 }
 
+#[derive(Deserialize, Default)]
+pub struct WgPeersQuery {
+    pub controller_id: Option<String>,
+    pub site_id: Option<String>,
+    /// Only peers that expire within this many days (and have not yet)
+    pub expiring_within_days: Option<i64>,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Load the requested profile, or the default one when `profile_id` is None.
+/// Returns None only when no default profile row exists.
+async fn resolve_profile(
+    state: &ProxyState,
+    profile_id: Option<i32>,
+) -> Result<Option<WgConfigProfile>, AppError> {
+    match profile_id {
+        Some(id) => state
+            .app_state
+            .mysql
+            .get_wg_profile(id)
+            .await?
+            .map(Some)
+            .ok_or_else(|| {
+                AppError::coded(
+                    ErrorCode::WireguardProfileNotFound,
+                    format!("WireGuard profile {} not found", id),
+                )
+            }),
+        None => state.app_state.mysql.get_default_wg_profile().await,
+    }
+}
+
+fn validate_expiry(expires_at: Option<DateTime<Utc>>) -> Result<(), AppError> {
+    match expires_at {
+        Some(at) if at <= Utc::now() => Err(AppError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Mirror an Omada-side change into LPG's record of the peer (if it has one)
+async fn update_peer_record(
+    state: &ProxyState,
+    omada_peer_id: &str,
+    name: Option<&str>,
+    allow_address: Option<&[String]>,
+    enabled: Option<bool>,
+) {
+    let Ok(Some(peer)) = state.app_state.mongo.get_omada_wg_peer(omada_peer_id).await else {
+        return;
+    };
+    if let Err(e) = state
+        .app_state
+        .mysql
+        .update_wg_peer_record(&peer.public_key, name, allow_address, enabled)
+        .await
+    {
+        tracing::warn!("Failed to update record of peer {}: {}", peer.name, e);
+    }
+}
+
+/// Audit an expiry change on a peer
+async fn audit_peer_expiry(
+    state: &ProxyState,
+    action: &str,
+    peer: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+    user: &AuthUser,
+) {
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wireguard_peer",
+            None,
+            action,
+            Some("expires_at"),
+            Some(&format!("{}: {}", peer, old.unwrap_or("none"))),
+            Some(&format!("{}: {}", peer, new.unwrap_or("none"))),
+            &user.sub,
+            None,
+        )
+        .await;
+}
+
+fn validate_profile(profile: &WgConfigProfile) -> Result<(), AppError> {
+    if profile.name.trim().is_empty() {
+        return Err(AppError::BadRequest("Profile name is required".to_string()));
+    }
+    if profile.allowed_ips.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "allowed_ips template is required".to_string(),
+        ));
+    }
+    for template in [Some(profile.allowed_ips.as_str()), profile.dns.as_deref()]
+        .into_iter()
+        .flatten()
+    {
+        wg_config::validate_template(template).map_err(AppError::BadRequest)?;
+    }
+    if let Some(k) = profile.persistent_keepalive {
+        if !(0..=65535).contains(&k) {
+            return Err(AppError::BadRequest(
+                "persistent_keepalive must be between 0 and 65535".to_string(),
+            ));
+        }
+    }
+    if let Some(mtu) = profile.mtu {
+        if !(576..=9000).contains(&mtu) {
+            return Err(AppError::BadRequest(
+                "mtu must be between 576 and 9000".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Handlers
 // ============================================================================
@@ -77,6 +247,7 @@ pub async fn create_peer(
     Json(req): Json<CreatePeerApiRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
+    validate_expiry(req.expires_at)?;
 
     let client = match state.omada_manager.get_client(&req.controller_id).await {
         Some(c) => c,
@@ -88,17 +259,41 @@ pub async fn create_peer(
         }
     };
 
+    let profile = resolve_profile(&state, req.profile_id).await?;
+    let keep_alive = req.keep_alive.or_else(|| {
+        profile
+            .as_ref()
+            .and_then(|p| p.persistent_keepalive)
+            .filter(|k| *k > 0)
+    });
+
     let omada_req = CreateWgPeerRequest {
-        name: req.name,
+        name: req.name.clone(),
         interface_id: req.interface_id,
-        public_key: req.public_key,
-        allow_address: req.allow_address,
-        keep_alive: req.keep_alive,
+        public_key: req.public_key.clone(),
+        allow_address: req.allow_address.clone(),
+        keep_alive,
         comment: req.comment,
     };
 
     match client.create_wireguard_peer(&req.site_id, &omada_req).await {
         Ok(result) => {
+            if let Err(e) = state
+                .app_state
+                .mysql
+                .record_wg_peer(
+                    &req.public_key,
+                    &req.controller_id,
+                    &req.site_id,
+                    &req.name,
+                    profile.as_ref().map(|p| p.id),
+                    &req.allow_address,
+                )
+                .await
+            {
+                tracing::warn!("Failed to record peer {}: {}", req.name, e);
+            }
+
             let syncer = crate::omada::OmadaSyncer::new(
                 state.omada_manager.clone(),
                 state.app_state.mongo.clone(),
@@ -106,6 +301,40 @@ pub async fn create_peer(
             );
             let _ = syncer.sync_one(&req.controller_id).await;
 
+            // The synced document is found by key; Omada's create response has no stable id field
+            if let Some(expires_at) = req.expires_at.map(format_expiry) {
+                match state
+                    .app_state
+                    .mongo
+                    .set_omada_wg_peer_expiry_by_key(
+                        &req.controller_id,
+                        &req.site_id,
+                        &req.public_key,
+                        Some(&expires_at),
+                    )
+                    .await
+                {
+                    Ok(true) => {
+                        audit_peer_expiry(
+                            &state,
+                            "set_expiry",
+                            &req.name,
+                            None,
+                            Some(&expires_at),
+                            &user,
+                        )
+                        .await
+                    }
+                    Ok(false) => tracing::warn!(
+                        "Peer {} not found after sync; expiry not recorded",
+                        req.name
+                    ),
+                    Err(e) => {
+                        tracing::warn!("Failed to record expiry for peer {}: {}", req.name, e)
+                    }
+                }
+            }
+
             Ok(Json(serde_json::json!({
                 "ok": true,
                 "peer": result,
@@ -126,6 +355,7 @@ pub async fn update_peer(
     Json(req): Json<UpdatePeerApiRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     require_permission(&user, 80)?;
+    validate_expiry(req.expires_at.flatten())?;
 
     let client = match state.omada_manager.get_client(&req.controller_id).await {
         Some(c) => c,
@@ -138,8 +368,9 @@ pub async fn update_peer(
     };
 
     let omada_req = UpdateWgPeerRequest {
-        name: req.name,
-        allow_address: req.allow_address,
+        name: req.name.clone(),
+        status: None,
+        allow_address: req.allow_address.clone(),
         keep_alive: req.keep_alive,
         comment: req.comment,
     };
@@ -149,6 +380,43 @@ pub async fn update_peer(
         .await
     {
         Ok(()) => {
+            if req.name.is_some() || req.allow_address.is_some() {
+                update_peer_record(
+                    &state,
+                    &peer_id,
+                    req.name.as_deref(),
+                    req.allow_address.as_deref(),
+                    None,
+                )
+                .await;
+            }
+            if let Some(expires_at) = req.expires_at {
+                let old = state
+                    .app_state
+                    .mongo
+                    .get_omada_wg_peer(&peer_id)
+                    .await
+                    .ok()
+                    .flatten();
+                let expires_at = expires_at.map(format_expiry);
+                state
+                    .app_state
+                    .mongo
+                    .set_omada_wg_peer_expiry(&peer_id, expires_at.as_deref())
+                    .await
+                    .map_err(AppError::database)?;
+                let name = old.as_ref().map_or(peer_id.as_str(), |p| p.name.as_str());
+                audit_peer_expiry(
+                    &state,
+                    "set_expiry",
+                    name,
+                    old.as_ref().and_then(|p| p.expires_at.as_deref()),
+                    expires_at.as_deref(),
+                    &user,
+                )
+                .await;
+            }
+
             let syncer = crate::omada::OmadaSyncer::new(
                 state.omada_manager.clone(),
                 state.app_state.mongo.clone(),
@@ -197,8 +465,25 @@ pub async fn delete_peer(
         }
     };
 
+    let public_key = state
+        .app_state
+        .mongo
+        .get_omada_wg_peer(&peer_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|p| p.public_key);
+
     match client.delete_wireguard_peer(&q.site_id, &peer_id).await {
         Ok(()) => {
+            if let Some(public_key) = public_key {
+                let _ = state
+                    .app_state
+                    .mysql
+                    .delete_wg_peer_record(&public_key)
+                    .await;
+            }
+
             let syncer = crate::omada::OmadaSyncer::new(
                 state.omada_manager.clone(),
                 state.app_state.mongo.clone(),
@@ -218,15 +503,425 @@ pub async fn delete_peer(
     }
 }
 
+/// POST /api/wireguard/peers/:id/extend - Renew a peer's expiry; a peer the
+/// expiry check disabled is re-enabled (admin: permission >= 80)
+pub async fn extend_peer(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(peer_id): Path<String>,
+    Json(req): Json<ExtendPeerRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let peer = state
+        .app_state
+        .mongo
+        .get_omada_wg_peer(&peer_id)
+        .await
+        .map_err(AppError::database)?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardPeerNotFound,
+                format!("WireGuard peer {} not found", peer_id),
+            )
+        })?;
+    let expires_at = expiry::extended_expiry(
+        peer.expires_at.as_deref(),
+        req.expires_at,
+        req.days,
+        Utc::now(),
+    )
+    .map_err(AppError::BadRequest)?;
+    let expires_at = format_expiry(expires_at);
+
+    // Re-enable first: if the controller refuses, the peer stays expired
+    let reenable = peer.expired_at.is_some() && !peer.status;
+    if reenable {
+        expiry::set_peer_enabled(&state.omada_manager, &peer, true)
+            .await
+            .map_err(|e| AppError::ProxyError(format!("Failed to re-enable peer: {}", e)))?;
+    }
+    state
+        .app_state
+        .mongo
+        .set_omada_wg_peer_expiry(&peer_id, Some(&expires_at))
+        .await
+        .map_err(AppError::database)?;
+    audit_peer_expiry(
+        &state,
+        if reenable {
+            "extend_reenable"
+        } else {
+            "extend"
+        },
+        &peer.name,
+        peer.expires_at.as_deref(),
+        Some(&expires_at),
+        &user,
+    )
+    .await;
+
+    if reenable {
+        let syncer = crate::omada::OmadaSyncer::new(
+            state.omada_manager.clone(),
+            state.app_state.mongo.clone(),
+            state.app_state.mysql.clone(),
+        );
+        let _ = syncer.sync_one(&peer.controller_id).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "peer_id": peer_id,
+        "expires_at": expires_at,
+        "previous_expires_at": peer.expires_at,
+        "reenabled": reenable,
+    })))
+}
+
+// ============================================================================
+// Unified peers (LPG records + Omada copies)
+// ============================================================================
+
+async fn load_unified_peers(state: &ProxyState) -> Result<Vec<UnifiedPeer>, AppError> {
+    let records = state.app_state.mysql.list_wg_peer_records().await?;
+    let copies = state
+        .app_state
+        .mongo
+        .get_omada_wg_peers(None, None)
+        .await
+        .map_err(AppError::database)?;
+    Ok(unified::merge_peers(&records, &copies, Utc::now()))
+}
+
+/// Load a unified peer for mutation; conflicts must be resolved first
+async fn load_mutable_peer(state: &ProxyState, id: &str) -> Result<UnifiedPeer, AppError> {
+    let peer = load_unified_peers(state)
+        .await?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardPeerNotFound,
+                format!("WireGuard peer {} not found", id),
+            )
+        })?;
+    if peer.conflict.is_some() {
+        return Err(AppError::BadRequest(format!(
+            "Peer {} has different allowed addresses in LPG and Omada; resolve the conflict first",
+            peer.name
+        )));
+    }
+    Ok(peer)
+}
+
+async fn sync_controller(state: &ProxyState, controller_id: &str) {
+    let syncer = crate::omada::OmadaSyncer::new(
+        state.omada_manager.clone(),
+        state.app_state.mongo.clone(),
+        state.app_state.mysql.clone(),
+    );
+    let _ = syncer.sync_one(controller_id).await;
+}
+
+/// Apply an update to the controller copy of a peer
+async fn update_omada_copy(
+    state: &ProxyState,
+    peer: &UnifiedPeer,
+    omada_peer_id: &str,
+    req: &UpdateWgPeerRequest,
+) -> Result<(), AppError> {
+    let client = state
+        .omada_manager
+        .get_client(&peer.controller_id)
+        .await
+        .ok_or_else(|| {
+            AppError::ProxyError(format!("Controller {} not found", peer.controller_id))
+        })?;
+    client
+        .update_wireguard_peer(&peer.site_id, omada_peer_id, req)
+        .await
+        .map_err(AppError::ProxyError)
+}
+
+/// GET /api/wireguard/all-peers - LPG-provisioned and controller peers merged
+/// by public key, with where each is managed and address conflicts
+pub async fn get_all_peers(
+    State(state): State<ProxyState>,
+    Query(q): Query<WgInterfaceQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut peers = load_unified_peers(&state).await?;
+    if let Some(controller_id) = &q.controller_id {
+        peers.retain(|p| &p.controller_id == controller_id);
+    }
+    if let Some(site_id) = &q.site_id {
+        peers.retain(|p| &p.site_id == site_id);
+    }
+
+    let count = |m: unified::ManagedBy| peers.iter().filter(|p| p.managed_by == m).count();
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "total": peers.len(),
+        "by_source": {
+            "lpg": count(unified::ManagedBy::Lpg),
+            "omada": count(unified::ManagedBy::Omada),
+            "both": count(unified::ManagedBy::Both),
+        },
+        "conflicts": peers.iter().filter(|p| p.conflict.is_some()).count(),
+        "peers": peers,
+    })))
+}
+
+/// PUT /api/wireguard/all-peers/:id - Update a peer wherever it is managed:
+/// the controller copy via Omada OpenAPI and LPG's record (admin: permission >= 80)
+pub async fn update_unified_peer(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateUnifiedPeerRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+    let peer = load_mutable_peer(&state, &id).await?;
+
+    match &peer.omada_peer_id {
+        Some(omada_peer_id) => {
+            let omada_req = UpdateWgPeerRequest {
+                name: req.name.clone(),
+                status: req.enabled,
+                allow_address: req.allow_address.clone(),
+                keep_alive: req.keep_alive,
+                comment: req.comment.clone(),
+            };
+            update_omada_copy(&state, &peer, omada_peer_id, &omada_req).await?;
+        }
+        None if req.keep_alive.is_some() || req.comment.is_some() => {
+            return Err(AppError::BadRequest(
+                "keep_alive and comment exist only on the controller; this peer is not on one"
+                    .to_string(),
+            ));
+        }
+        None => {}
+    }
+    if peer.managed_by != unified::ManagedBy::Omada {
+        state
+            .app_state
+            .mysql
+            .update_wg_peer_record(
+                &peer.public_key,
+                req.name.as_deref(),
+                req.allow_address.as_deref(),
+                req.enabled,
+            )
+            .await?;
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wireguard_peer",
+            None,
+            "update",
+            None,
+            Some(&peer.name),
+            Some(&format!(
+                "name={:?} allow_address={:?} enabled={:?} keep_alive={:?} ({})",
+                req.name,
+                req.allow_address,
+                req.enabled,
+                req.keep_alive,
+                serde_json::json!(peer.managed_by)
+            )),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if peer.omada_peer_id.is_some() {
+        sync_controller(&state, &peer.controller_id).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "id": peer.id,
+        "managed_by": peer.managed_by,
+    })))
+}
+
+/// DELETE /api/wireguard/all-peers/:id - Delete a peer from its controller
+/// and LPG's records (dangerous: permission == 100, confirm required)
+pub async fn delete_unified_peer(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Query(confirm): Query<ConfirmQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+    let peer = load_mutable_peer(&state, &id).await?;
+
+    if !confirm.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "delete_wireguard_peer".to_string(),
+            target: format!("WireGuard peer {}", peer.name),
+            warning: "This will delete the WireGuard peer from the Omada controller and LPG's records. VPN connectivity will be lost.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    if let Some(omada_peer_id) = &peer.omada_peer_id {
+        let client = state
+            .omada_manager
+            .get_client(&peer.controller_id)
+            .await
+            .ok_or_else(|| {
+                AppError::ProxyError(format!("Controller {} not found", peer.controller_id))
+            })?;
+        client
+            .delete_wireguard_peer(&peer.site_id, omada_peer_id)
+            .await
+            .map_err(AppError::ProxyError)?;
+    }
+    state
+        .app_state
+        .mysql
+        .delete_wg_peer_record(&peer.public_key)
+        .await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wireguard_peer",
+            None,
+            "delete",
+            None,
+            Some(&format!("{} ({})", peer.name, peer.public_key)),
+            None,
+            &user.sub,
+            None,
+        )
+        .await;
+
+    if peer.omada_peer_id.is_some() {
+        sync_controller(&state, &peer.controller_id).await;
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "message": format!("Peer {} deleted", peer.name),
+    })))
+}
+
+/// POST /api/wireguard/all-peers/:id/resolve - Settle an allowed-address
+/// conflict by choosing the side that wins (admin: permission >= 80)
+pub async fn resolve_peer_conflict(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<String>,
+    Json(req): Json<ResolvePeerConflictRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let peer = load_unified_peers(&state)
+        .await?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardPeerNotFound,
+                format!("WireGuard peer {} not found", id),
+            )
+        })?;
+    let (Some(conflict), Some(omada_peer_id)) = (&peer.conflict, &peer.omada_peer_id) else {
+        return Err(AppError::BadRequest(format!(
+            "Peer {} has no conflict to resolve",
+            peer.name
+        )));
+    };
+
+    let allow_address = match req.winner.as_str() {
+        "lpg" => {
+            let omada_req = UpdateWgPeerRequest {
+                name: None,
+                status: None,
+                allow_address: Some(conflict.lpg_allow_address.clone()),
+                keep_alive: None,
+                comment: None,
+            };
+            update_omada_copy(&state, &peer, omada_peer_id, &omada_req).await?;
+            sync_controller(&state, &peer.controller_id).await;
+            &conflict.lpg_allow_address
+        }
+        "omada" => {
+            state
+                .app_state
+                .mysql
+                .update_wg_peer_record(
+                    &peer.public_key,
+                    None,
+                    Some(&conflict.omada_allow_address),
+                    None,
+                )
+                .await?;
+            &conflict.omada_allow_address
+        }
+        other => {
+            return Err(AppError::BadRequest(format!(
+                "winner must be \"lpg\" or \"omada\", got \"{}\"",
+                other
+            )))
+        }
+    };
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wireguard_peer",
+            None,
+            "resolve_conflict",
+            Some("allow_address"),
+            Some(&format!(
+                "{}: lpg={:?} omada={:?}",
+                peer.name, conflict.lpg_allow_address, conflict.omada_allow_address
+            )),
+            Some(&format!("{} wins: {:?}", req.winner, allow_address)),
+            &user.sub,
+            None,
+        )
+        .await;
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "id": peer.id,
+        "winner": req.winner,
+        "allow_address": allow_address,
+    })))
+}
+
 /// POST /api/wireguard/config - Generate a WireGuard client config file
 pub async fn generate_config(
+    State(state): State<ProxyState>,
     Json(params): Json<wg_config::WgClientConfigParams>,
-) -> Json<serde_json::Value> {
-    let config_str = wg_config::generate_config(&params);
-    Json(serde_json::json!({
-        "ok": true,
-        "config": config_str,
-    }))
+) -> Result<Json<serde_json::Value>, AppError> {
+    let profile = resolve_profile(&state, params.profile_id).await?;
+    let template = profile
+        .as_ref()
+        .map(wg_config::ProfileTemplate::from)
+        .unwrap_or(wg_config::ProfileTemplate::LEGACY);
+
+    match wg_config::render_config(&params, &template) {
+        Ok(config_str) => Ok(Json(serde_json::json!({
+            "ok": true,
+            "config": config_str,
+            "profile_id": profile.as_ref().map(|p| p.id),
+            "profile_name": profile.as_ref().map(|p| p.name.as_str()),
+        }))),
+        Err(e) => Ok(Json(serde_json::json!({
+            "ok": false,
+            "error": e,
+        }))),
+    }
 }
 
 /// GET /api/wireguard/interfaces - WG interfaces (aggregated from peers)
@@ -287,10 +982,11 @@ pub async fn get_interfaces(
     }
 }
 
-/// GET /api/wireguard/peers - List all peers (reuses omada_wg_peers)
+/// GET /api/wireguard/peers - List all peers (reuses omada_wg_peers).
+/// `?expiring_within_days=N` keeps peers expiring in the next N days.
 pub async fn get_peers(
     State(state): State<ProxyState>,
-    Query(q): Query<WgInterfaceQuery>,
+    Query(q): Query<WgPeersQuery>,
 ) -> Json<serde_json::Value> {
     match state
         .app_state
@@ -298,14 +994,305 @@ pub async fn get_peers(
         .get_omada_wg_peers(q.controller_id.as_deref(), q.site_id.as_deref())
         .await
     {
-        Ok(peers) => Json(serde_json::json!({
-            "ok": true,
-            "peers": peers,
-            "total": peers.len(),
-        })),
+        Ok(mut peers) => {
+            let now = Utc::now();
+            if let Some(days) = q.expiring_within_days {
+                peers.retain(|p| expiry::expires_within(p, now, days));
+            }
+            // Attach the profile each peer was provisioned with (if recorded)
+            let profiles = state
+                .app_state
+                .mysql
+                .list_wg_peer_profiles()
+                .await
+                .unwrap_or_default();
+            let list: Vec<serde_json::Value> = peers
+                .iter()
+                .map(|peer| {
+                    let mut v = serde_json::json!(peer);
+                    if let Some(obj) = v.as_object_mut() {
+                        let profile = profiles.get(&peer.public_key);
+                        obj.insert(
+                            "profile_id".to_string(),
+                            serde_json::json!(profile.map(|(id, _)| id)),
+                        );
+                        obj.insert(
+                            "profile_name".to_string(),
+                            serde_json::json!(profile.map(|(_, name)| name)),
+                        );
+                        obj.insert(
+                            "expired".to_string(),
+                            serde_json::json!(expiry::is_expired(peer, now)),
+                        );
+                    }
+                    v
+                })
+                .collect();
+            Json(serde_json::json!({
+                "ok": true,
+                "peers": list,
+                "total": list.len(),
+            }))
+        }
         Err(e) => Json(serde_json::json!({
             "ok": false,
             "error": e,
         })),
     }
 }
+
+/// GET /api/wireguard/profiles - List config profiles
+pub async fn list_profiles(State(state): State<ProxyState>) -> Result<impl IntoResponse, AppError> {
+    let profiles = state.app_state.mysql.list_wg_profiles().await?;
+    Ok(Json(profiles))
+}
+
+/// GET /api/wireguard/profiles/:id - Get a config profile with its peer count
+pub async fn get_profile(
+    State(state): State<ProxyState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let profile = state
+        .app_state
+        .mysql
+        .get_wg_profile(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardProfileNotFound,
+                format!("WireGuard profile {} not found", id),
+            )
+        })?;
+    let peer_count = state.app_state.mysql.count_wg_profile_peers(id).await?;
+
+    Ok(Json(serde_json::json!({
+        "profile": profile,
+        "peer_count": peer_count,
+    })))
+}
+
+/// POST /api/wireguard/profiles - Create a config profile (admin: permission >= 80)
+pub async fn create_profile(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<CreateWgProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let now = chrono::Utc::now();
+    validate_profile(&WgConfigProfile {
+        id: 0,
+        name: req.name.clone(),
+        description: req.description.clone(),
+        dns: req.dns.clone(),
+        allowed_ips: req.allowed_ips.clone(),
+        persistent_keepalive: req.persistent_keepalive,
+        mtu: req.mtu,
+        scripts_allowed: req.scripts_allowed,
+        is_default: false,
+        created_at: now,
+        updated_at: now,
+    })?;
+
+    let id = state.app_state.mysql.create_wg_profile(&req).await?;
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wg_profile",
+            Some(id),
+            "create",
+            None,
+            None,
+            Some(&req.name),
+            "api",
+            None,
+        )
+        .await;
+
+    tracing::info!("Created WireGuard profile {} ({})", id, req.name);
+
+    Ok((
+        axum::http::StatusCode::CREATED,
+        Json(SuccessResponse::with_id("WireGuard profile created", id)),
+    ))
+}
+
+/// PUT /api/wireguard/profiles/:id - Update a config profile (admin: permission >= 80)
+pub async fn update_profile(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateWgProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 80)?;
+
+    let old = state
+        .app_state
+        .mysql
+        .get_wg_profile(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardProfileNotFound,
+                format!("WireGuard profile {} not found", id),
+            )
+        })?;
+
+    let mut updated = old.clone();
+    if let Some(name) = &req.name {
+        updated.name = name.trim().to_string();
+    }
+    if let Some(description) = &req.description {
+        updated.description = Some(description.clone()).filter(|d| !d.is_empty());
+    }
+    if let Some(dns) = &req.dns {
+        updated.dns = Some(dns.clone()).filter(|d| !d.is_empty());
+    }
+    if let Some(allowed_ips) = &req.allowed_ips {
+        updated.allowed_ips = allowed_ips.clone();
+    }
+    if let Some(k) = req.persistent_keepalive {
+        updated.persistent_keepalive = Some(k).filter(|k| *k >= 0);
+    }
+    if let Some(mtu) = req.mtu {
+        updated.mtu = Some(mtu).filter(|m| *m > 0);
+    }
+    if let Some(scripts_allowed) = req.scripts_allowed {
+        updated.scripts_allowed = scripts_allowed;
+    }
+    validate_profile(&updated)?;
+
+    state.app_state.mysql.update_wg_profile(&updated).await?;
+
+    let old_json = serde_json::to_string(&old).unwrap_or_default();
+    let new_json = serde_json::to_string(&updated).unwrap_or_default();
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wg_profile",
+            Some(id),
+            "update",
+            None,
+            Some(&old_json),
+            Some(&new_json),
+            "api",
+            None,
+        )
+        .await;
+
+    let peer_count = state
+        .app_state
+        .mysql
+        .count_wg_profile_peers(id)
+        .await
+        .unwrap_or(0);
+
+    state
+        .notifier
+        .notify_config_change(
+            "WireGuard Profile Updated",
+            &format!(
+                "Profile '{}' updated ({} peers provisioned with it). {}",
+                updated.name, peer_count, PROFILE_CHANGE_NOTE
+            ),
+        )
+        .await;
+
+    tracing::info!("Updated WireGuard profile {} ({})", id, updated.name);
+
+    Ok(Json(serde_json::json!({
+        "message": "WireGuard profile updated",
+        "id": id,
+        "affected_peers": peer_count,
+        "note": PROFILE_CHANGE_NOTE,
+    })))
+}
+
+/// DELETE /api/wireguard/profiles/:id - Delete a config profile
+/// (dangerous: permission == 100, confirm required)
+pub async fn delete_profile(
+    State(state): State<ProxyState>,
+    Extension(user): Extension<AuthUser>,
+    Path(id): Path<i32>,
+    Query(confirm): Query<ConfirmQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    require_permission(&user, 100)?;
+
+    let profile = state
+        .app_state
+        .mysql
+        .get_wg_profile(id)
+        .await?
+        .ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::WireguardProfileNotFound,
+                format!("WireGuard profile {} not found", id),
+            )
+        })?;
+
+    if profile.is_default {
+        return Err(AppError::BadRequest(
+            "The default WireGuard profile cannot be deleted".to_string(),
+        ));
+    }
+
+    let peer_count = state.app_state.mysql.count_wg_profile_peers(id).await?;
+    if peer_count > 0 {
+        return Err(AppError::BadRequest(format!(
+            "Profile '{}' is recorded for {} peers and cannot be deleted",
+            profile.name, peer_count
+        )));
+    }
+
+    if !confirm.confirm {
+        return Ok(Json(serde_json::json!(ConfirmRequired {
+            action: "delete_wireguard_profile".to_string(),
+            target: format!("WireGuard profile #{} ({})", id, profile.name),
+            warning: "This will remove the config profile. Configs already generated from it are not affected.".to_string(),
+            confirm_required: true,
+        })));
+    }
+
+    if !state.app_state.mysql.delete_wg_profile(id).await? {
+        return Err(AppError::NotFound(format!(
+            "WireGuard profile {} not found",
+            id
+        )));
+    }
+
+    let _ = state
+        .app_state
+        .mysql
+        .log_audit(
+            "wg_profile",
+            Some(id),
+            "delete",
+            None,
+            Some(&profile.name),
+            None,
+            "api",
+            None,
+        )
+        .await;
+
+    state
+        .notifier
+        .notify_config_change(
+            "WireGuard Profile Deleted",
+            &format!(
+                "Profile '{}' deleted. {}",
+                profile.name, PROFILE_CHANGE_NOTE
+            ),
+        )
+        .await;
+
+    tracing::info!("Deleted WireGuard profile {} ({})", id, profile.name);
+
+    Ok(Json(serde_json::json!({
+        "message": "WireGuard profile deleted",
+        "note": PROFILE_CHANGE_NOTE,
+    })))
+}
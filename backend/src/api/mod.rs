@@ -1,12 +1,13 @@
 //! API module - HTTP handlers and routes
 
 pub(crate) mod admin_guard;
+pub(crate) mod api_trace;
 pub(crate) mod auth_middleware;
 pub mod handlers;
 
 use axum::{
     middleware,
-    routing::{delete, get, post, put},
+    routing::{any, delete, get, post, put},
     Router,
 };
 
@@ -14,11 +15,18 @@ use crate::proxy::ProxyState;
 
 pub fn routes(state: ProxyState) -> Router<ProxyState> {
     // ========================================================================
-    // Group 1: Public routes - no auth, no network guard (health checks)
+    // Group 1: Public routes - no auth, no network guard (health checks,
+    // status page, topology shares; the status page falls through to the
+    // proxy when disabled, for non-GET methods, or when a route covers it)
     // ========================================================================
     let public = Router::new()
         .route("/health", get(handlers::health_check))
-        .route("/api/health", get(handlers::health_check));
+        .route("/api/health", get(handlers::health_check))
+        .route("/readyz", get(handlers::readiness_check))
+        .route("/status", any(handlers::get_status_page))
+        .route("/status.json", any(handlers::get_status_json))
+        // Read-only, token-scoped topology (rate limited, GET only)
+        .route("/api/public/topology", get(handlers::get_public_topology));
 
     // ========================================================================
     // Group 2: Auth endpoints - internet_access_guard only (no require_auth)
@@ -34,6 +42,19 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/auth/lacisoath-config",
             get(handlers::auth::lacisoath_config),
         )
+        // Omada controller push events (authenticated by per-controller secret)
+        .route(
+            "/api/omada/webhook/:controller_id",
+            post(handlers::omada_webhook),
+        )
+        // DDNS webhook IP source (authenticated by per-config report token)
+        .route("/api/ddns/:id/report-ip", post(handlers::report_ddns_ip))
+        // Device registration (X-Registration-Token, or an admin session
+        // checked in the handler)
+        .route(
+            "/api/aranea/register",
+            post(handlers::aranea_register_device),
+        )
         .layer(middleware::from_fn_with_state(
             state.clone(),
             admin_guard::internet_access_guard,
@@ -48,37 +69,152 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
         .route("/api/auth/me", get(handlers::auth::auth_me))
         .route("/api/auth/logout", post(handlers::auth::auth_logout))
         .route("/api/auth/api-key", post(handlers::auth::create_api_key))
+        .route(
+            "/api/auth/permission-floors",
+            get(handlers::auth::get_permission_floors)
+                .put(handlers::auth::update_permission_floors),
+        )
+        .route(
+            "/api/auth/providers",
+            get(handlers::auth::list_lacisoath_providers)
+                .post(handlers::auth::create_lacisoath_provider),
+        )
+        .route(
+            "/api/auth/providers/:id",
+            put(handlers::auth::update_lacisoath_provider)
+                .delete(handlers::auth::delete_lacisoath_provider),
+        )
         // Server routes (enhanced with subnet info)
         .route("/api/server-routes", get(handlers::list_server_routes))
+        .route(
+            "/api/server-routes/analysis",
+            get(handlers::server_routes_analysis),
+        )
         // Proxy routes management
         .route("/api/routes", get(handlers::list_routes))
         .route("/api/routes", post(handlers::create_route))
         .route("/api/routes/status", get(handlers::get_all_routes_status))
+        .route("/api/routes/test", get(handlers::test_route))
+        .route(
+            "/api/routes/security-headers/report",
+            get(handlers::get_security_headers_report),
+        )
+        .route("/api/routes/purge", post(handlers::purge_deleted_routes))
+        .route("/api/routes/sync", put(handlers::sync_routes))
+        .route(
+            "/api/routes/pending",
+            get(handlers::list_pending_route_changes),
+        )
+        .route(
+            "/api/routes/pending/:id/approve",
+            post(handlers::approve_route_change),
+        )
+        .route(
+            "/api/routes/pending/:id/reject",
+            post(handlers::reject_route_change),
+        )
         .route("/api/routes/:id", get(handlers::get_route))
         .route("/api/routes/:id", put(handlers::update_route))
         .route("/api/routes/:id", delete(handlers::delete_route))
+        .route("/api/routes/:id/restore", post(handlers::restore_route))
         .route("/api/routes/:id/status", get(handlers::get_route_status))
         .route("/api/routes/:id/logs", get(handlers::get_route_logs))
+        .route("/api/routes/:id/trace", put(handlers::set_route_trace))
+        .route("/api/routes/:id/traces", get(handlers::get_route_traces))
+        .route(
+            "/api/routes/:id/canary-stats",
+            get(handlers::get_route_canary_stats),
+        )
+        .route(
+            "/api/routes/:id/canary/kill",
+            post(handlers::kill_route_canary),
+        )
+        .route(
+            "/api/routes/:id/maintenance",
+            post(handlers::set_route_maintenance),
+        )
+        .route(
+            "/api/routes/:id/cache/purge",
+            post(handlers::purge_route_cache),
+        )
+        .route(
+            "/api/routes/:id/versions",
+            get(handlers::list_route_versions),
+        )
+        .route(
+            "/api/routes/:id/rollback/:version",
+            post(handlers::rollback_route),
+        )
+        .route(
+            "/api/routes/:id/store-forward",
+            put(handlers::set_route_store_forward),
+        )
+        .route(
+            "/api/routes/:id/transform",
+            get(handlers::get_route_transform).put(handlers::set_route_transform),
+        )
+        .route(
+            "/api/routes/:id/log-fields",
+            get(handlers::get_route_log_fields).put(handlers::set_route_log_fields),
+        )
+        .route(
+            "/api/routes/:id/status-page",
+            put(handlers::set_route_status_page),
+        )
+        .route("/api/routes/:id/queue", get(handlers::get_route_queue))
+        .route(
+            "/api/routes/:id/queue/:queue_id/replay",
+            post(handlers::replay_queued_request),
+        )
+        .route(
+            "/api/routes/:id/queue/:queue_id",
+            delete(handlers::discard_queued_request),
+        )
         // DDNS management
         .route("/api/ddns", get(handlers::list_ddns))
         .route("/api/ddns", post(handlers::create_ddns))
+        .route("/api/ddns/test", post(handlers::test_ddns))
         .route("/api/ddns/:id", get(handlers::get_ddns))
         .route("/api/ddns/:id", put(handlers::update_ddns))
         .route("/api/ddns/:id", delete(handlers::delete_ddns))
         .route("/api/ddns/:id/update", post(handlers::trigger_ddns_update))
         .route("/api/ddns/integrated", get(handlers::list_ddns_integrated))
         .route("/api/ddns/:id/link-omada", put(handlers::link_ddns_omada))
+        .route(
+            "/api/ddns/:id/report-token",
+            post(handlers::rotate_ddns_report_token),
+        )
+        .route("/api/ddns/:id/acme/issue", post(handlers::issue_ddns_acme))
         .route(
             "/api/ddns/:id/port-forwards",
             get(handlers::get_ddns_port_forwards),
         )
+        // Local DNS overrides
+        .route("/api/dns/overrides", get(handlers::list_dns_overrides))
+        .route("/api/dns/overrides", post(handlers::create_dns_override))
+        .route("/api/dns/overrides/:id", put(handlers::update_dns_override))
+        .route(
+            "/api/dns/overrides/:id",
+            delete(handlers::delete_dns_override),
+        )
+        .route("/api/dns/suggestions", get(handlers::get_dns_suggestions))
+        .route("/api/dns/status", get(handlers::get_dns_status))
         // Security
         .route("/api/security/blocked-ips", get(handlers::list_blocked_ips))
         .route("/api/security/blocked-ips", post(handlers::block_ip))
+        .route(
+            "/api/security/blocked-ips/import",
+            post(handlers::import_blocked_ips),
+        )
+        .route(
+            "/api/security/blocked-ips/export",
+            get(handlers::export_blocked_ips),
+        )
         .route(
             "/api/security/blocked-ips/:id",
             delete(handlers::unblock_ip),
         )
+        .route("/api/security/quick-block", post(handlers::quick_block))
         .route("/api/security/events", get(handlers::list_security_events))
         .route(
             "/api/security/events/ip/:ip",
@@ -88,6 +224,46 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/security/events/search",
             get(handlers::search_security_events),
         )
+        .route("/api/security/ip/:ip", get(handlers::get_ip_profile))
+        .route("/api/security/alert-rules", get(handlers::list_alert_rules))
+        .route(
+            "/api/security/alert-rules",
+            post(handlers::create_alert_rule),
+        )
+        .route(
+            "/api/security/alert-rules/:id",
+            get(handlers::get_alert_rule),
+        )
+        .route(
+            "/api/security/alert-rules/:id",
+            put(handlers::update_alert_rule),
+        )
+        .route(
+            "/api/security/alert-rules/:id",
+            delete(handlers::delete_alert_rule),
+        )
+        .route(
+            "/api/security/alert-rules/:id/test",
+            post(handlers::test_alert_rule),
+        )
+        .route(
+            "/api/security/rate-limits",
+            get(handlers::list_rate_limit_rules),
+        )
+        .route(
+            "/api/security/rate-limits",
+            post(handlers::create_rate_limit_rule),
+        )
+        .route(
+            "/api/security/rate-limits/:id",
+            put(handlers::update_rate_limit_rule),
+        )
+        .route(
+            "/api/security/rate-limits/:id",
+            delete(handlers::delete_rate_limit_rule),
+        )
+        .route("/api/security/geo-rules", get(handlers::get_geo_rules))
+        .route("/api/security/geo-rules", put(handlers::update_geo_rules))
         // Settings
         .route("/api/settings", get(handlers::list_settings))
         .route("/api/settings/:key", put(handlers::update_setting))
@@ -107,6 +283,34 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
         )
         // Audit
         .route("/api/audit", get(handlers::get_audit_logs))
+        // Cluster (leader election)
+        .route("/api/admin/cluster", get(handlers::get_cluster_status))
+        .route(
+            "/api/admin/cluster/failover",
+            post(handlers::force_cluster_failover),
+        )
+        // Startup migrations
+        .route("/api/admin/migrations", get(handlers::list_migrations))
+        .route(
+            "/api/admin/migrations/:id/run",
+            post(handlers::run_migration),
+        )
+        // MongoDB storage (sizes, indexes, compaction)
+        .route("/api/admin/storage", get(handlers::get_storage))
+        .route(
+            "/api/admin/storage/compact/:collection",
+            post(handlers::compact_collection),
+        )
+        // Syncer ingestion write load
+        .route("/api/admin/ingest-writes", get(handlers::get_ingest_writes))
+        // Logging (runtime level)
+        .route("/api/admin/log-level", get(handlers::get_log_level))
+        .route("/api/admin/log-level", post(handlers::set_log_level))
+        // Session signing secret
+        .route(
+            "/api/admin/rotate-jwt-secret",
+            post(handlers::auth::rotate_jwt_secret),
+        )
         // My IP (client IP detection)
         .route("/api/my-ip", get(handlers::get_my_ip))
         // Dashboard
@@ -124,7 +328,57 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/dashboard/access-log/export",
             get(handlers::export_access_log),
         )
+        .route(
+            "/api/dashboard/access-log/delete",
+            post(handlers::delete_access_logs),
+        )
+        .route(
+            "/api/dashboard/access-log/delete/:job_id",
+            get(handlers::get_access_log_delete_job),
+        )
+        .route("/api/dashboard/in-flight", get(handlers::list_in_flight))
+        .route(
+            "/api/dashboard/notification-queue",
+            get(handlers::get_notification_queue),
+        )
+        .route(
+            "/api/dashboard/in-flight/:id",
+            delete(handlers::abort_in_flight),
+        )
+        .route("/api/dashboard/websockets", get(handlers::list_websockets))
+        .route(
+            "/api/dashboard/websockets/:id",
+            delete(handlers::close_websocket),
+        )
         .route("/api/dashboard/health", get(handlers::get_health_status))
+        .route(
+            "/api/maintenance/windows",
+            get(handlers::list_maintenance_windows),
+        )
+        .route(
+            "/api/maintenance/windows",
+            post(handlers::create_maintenance_window),
+        )
+        .route(
+            "/api/maintenance/windows/:id",
+            get(handlers::get_maintenance_window),
+        )
+        .route(
+            "/api/maintenance/windows/:id",
+            put(handlers::update_maintenance_window),
+        )
+        .route(
+            "/api/maintenance/windows/:id",
+            delete(handlers::delete_maintenance_window),
+        )
+        .route(
+            "/api/maintenance/active",
+            get(handlers::get_active_maintenance),
+        )
+        .route(
+            "/api/dashboard/dependencies",
+            get(handlers::get_dependencies_health),
+        )
         .route(
             "/api/dashboard/status-distribution",
             get(handlers::get_status_distribution),
@@ -139,6 +393,26 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/dashboard/error-summary",
             get(handlers::get_error_summary),
         )
+        .route(
+            "/api/dashboard/protocol-summary",
+            get(handlers::get_protocol_summary),
+        )
+        .route(
+            "/api/dashboard/custom-field-summary",
+            get(handlers::get_custom_field_summary),
+        )
+        .route(
+            "/api/dashboard/hostname-usage",
+            get(handlers::get_hostname_usage),
+        )
+        .route(
+            "/api/dashboard/hostname-usage/export",
+            get(handlers::export_hostname_usage),
+        )
+        .route(
+            "/api/dashboard/hostname-usage/rollup",
+            post(handlers::rerun_hostname_usage_rollup),
+        )
         .route("/api/dashboard/ssl-status", get(handlers::get_ssl_status))
         .route(
             "/api/dashboard/server-health",
@@ -163,8 +437,37 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/omada/controllers/:id/sync",
             post(handlers::sync_controller),
         )
+        .route(
+            "/api/omada/controllers/:id/webhook",
+            get(handlers::get_controller_webhook),
+        )
+        .route(
+            "/api/omada/controllers/:id/sites",
+            get(handlers::list_controller_sites),
+        )
+        .route(
+            "/api/omada/controllers/:id/sites/:site_id",
+            put(handlers::update_controller_site),
+        )
+        .route(
+            "/api/omada/controllers/:id/webhook/secret",
+            post(handlers::rotate_controller_webhook_secret),
+        )
         // Omada: Data viewing
         .route("/api/omada/devices", get(handlers::get_omada_devices))
+        .route(
+            "/api/omada/devices/:mac/ports",
+            get(handlers::get_omada_device_ports),
+        )
+        .route("/api/devices/search", get(handlers::search_devices))
+        .route(
+            "/api/devices/inventory/export",
+            get(handlers::export_device_inventory),
+        )
+        .route(
+            "/api/devices/class-rules",
+            get(handlers::get_device_class_rules),
+        )
         .route("/api/omada/clients", get(handlers::get_omada_clients))
         .route("/api/omada/wireguard", get(handlers::get_omada_wireguard))
         .route("/api/omada/summary", get(handlers::get_omada_summary))
@@ -189,6 +492,10 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/openwrt/routers/:id",
             delete(handlers::openwrt::delete_router),
         )
+        .route(
+            "/api/openwrt/routers/:id",
+            put(handlers::openwrt::update_router),
+        )
         .route(
             "/api/openwrt/routers/:id/poll",
             post(handlers::openwrt::poll_router),
@@ -219,6 +526,26 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/wireguard/peers/:id",
             delete(handlers::wireguard::delete_peer),
         )
+        .route(
+            "/api/wireguard/peers/:id/extend",
+            post(handlers::wireguard::extend_peer),
+        )
+        .route(
+            "/api/wireguard/all-peers",
+            get(handlers::wireguard::get_all_peers),
+        )
+        .route(
+            "/api/wireguard/all-peers/:id",
+            put(handlers::wireguard::update_unified_peer),
+        )
+        .route(
+            "/api/wireguard/all-peers/:id",
+            delete(handlers::wireguard::delete_unified_peer),
+        )
+        .route(
+            "/api/wireguard/all-peers/:id/resolve",
+            post(handlers::wireguard::resolve_peer_conflict),
+        )
         .route(
             "/api/wireguard/config",
             post(handlers::wireguard::generate_config),
@@ -227,6 +554,26 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/wireguard/interfaces",
             get(handlers::wireguard::get_interfaces),
         )
+        .route(
+            "/api/wireguard/profiles",
+            get(handlers::wireguard::list_profiles),
+        )
+        .route(
+            "/api/wireguard/profiles",
+            post(handlers::wireguard::create_profile),
+        )
+        .route(
+            "/api/wireguard/profiles/:id",
+            get(handlers::wireguard::get_profile),
+        )
+        .route(
+            "/api/wireguard/profiles/:id",
+            put(handlers::wireguard::update_profile),
+        )
+        .route(
+            "/api/wireguard/profiles/:id",
+            delete(handlers::wireguard::delete_profile),
+        )
         // External: Device management
         .route(
             "/api/external/devices",
@@ -248,6 +595,10 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/external/devices/:id",
             delete(handlers::external::delete_device),
         )
+        .route(
+            "/api/external/devices/:id",
+            put(handlers::external::update_device),
+        )
         .route(
             "/api/external/devices/:id/poll",
             post(handlers::external::poll_device),
@@ -263,6 +614,20 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
         // Topology (CelestialGlobe)
         .route("/api/topology", get(handlers::get_topology))
         .route("/api/topology/v2", get(handlers::get_topology_v2))
+        .route("/api/topology/watch", get(handlers::watch_topology))
+        .route(
+            "/api/topology/positions",
+            put(handlers::update_node_positions),
+        )
+        .route("/api/topology/layout/recalc", post(handlers::recalc_layout))
+        .route(
+            "/api/topology/nodes/dedupe-macs",
+            post(handlers::dedupe_mac_nodes),
+        )
+        .route(
+            "/api/topology/nodes/:id/detail",
+            get(handlers::get_node_detail),
+        )
         .route(
             "/api/topology/nodes/:id/label",
             put(handlers::update_node_label).delete(handlers::delete_node_label),
@@ -279,6 +644,12 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/topology/nodes/:id/order",
             put(handlers::update_node_order),
         )
+        .route(
+            "/api/topology/nodes/:id/claim",
+            post(handlers::claim_node)
+                .put(handlers::assign_node_claim)
+                .delete(handlers::release_node_claim),
+        )
         .route(
             "/api/topology/logic-devices",
             post(handlers::create_logic_device),
@@ -291,10 +662,30 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/topology/logic-devices/:id",
             delete(handlers::delete_logic_device),
         )
+        .route(
+            "/api/topology/shares",
+            get(handlers::list_topology_shares).post(handlers::create_topology_share),
+        )
+        .route(
+            "/api/topology/shares/:id",
+            delete(handlers::revoke_topology_share),
+        )
         // araneaSDK
         .route(
-            "/api/aranea/register",
-            post(handlers::aranea_register_device),
+            "/api/aranea/registration-tokens",
+            get(handlers::list_registration_tokens),
+        )
+        .route(
+            "/api/aranea/registration-tokens",
+            post(handlers::create_registration_token),
+        )
+        .route(
+            "/api/aranea/registration-tokens/:id/rotate",
+            post(handlers::rotate_registration_token),
+        )
+        .route(
+            "/api/aranea/registration-tokens/:id",
+            delete(handlers::revoke_registration_token),
         )
         .route("/api/aranea/devices", get(handlers::aranea_list_devices))
         .route(
@@ -302,6 +693,35 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             get(handlers::aranea_get_device_state),
         )
         .route("/api/aranea/summary", get(handlers::aranea_summary))
+        .route(
+            "/api/aranea/config",
+            get(handlers::get_aranea_config).put(handlers::update_aranea_config),
+        )
+        .route("/api/aranea/health", get(handlers::aranea_health))
+        // Admin API request recorder
+        .route("/api/admin/api-trace", get(handlers::list_api_trace))
+        .route(
+            "/api/admin/api-trace/recorder",
+            get(handlers::get_api_trace_recorder).put(handlers::update_api_trace_recorder),
+        )
+        .route("/api/admin/api-trace/:id", get(handlers::get_api_trace))
+        .route(
+            "/api/admin/api-trace/:id/replay",
+            post(handlers::replay_api_trace),
+        )
+        .route(
+            "/api/aranea/registration-candidates",
+            get(handlers::aranea_registration_candidates),
+        )
+        .route(
+            "/api/aranea/register-batch",
+            post(handlers::aranea_register_batch),
+        )
+        .route("/api/aranea/reports", get(handlers::aranea_list_reports))
+        .route(
+            "/api/aranea/reports/trigger",
+            post(handlers::aranea_trigger_reports),
+        )
         // Tools: sync triggers + network diagnostics
         .route("/api/tools/sync/omada", post(handlers::tool_sync_omada))
         .route("/api/tools/sync/openwrt", post(handlers::tool_sync_openwrt))
@@ -313,6 +733,7 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/tools/ddns/update-all",
             post(handlers::tool_ddns_update_all),
         )
+        .route("/api/tools/logs/prune", post(handlers::tool_logs_prune))
         .route("/api/tools/network/ping", post(handlers::tool_network_ping))
         .route("/api/tools/network/dns", post(handlers::tool_network_dns))
         .route("/api/tools/diagnostics", post(handlers::run_diagnostics))
@@ -356,7 +777,12 @@ pub fn routes(state: ProxyState) -> Router<ProxyState> {
             "/api/nginx/regenerate",
             post(handlers::regenerate_nginx_config),
         )
-        // Apply middleware layers (order: inner first, so require_auth runs before internet_access_guard)
+        // Apply middleware layers (order: inner first, so require_auth runs before internet_access_guard
+        // and the request recorder sees the authenticated user)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            api_trace::record,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware::require_auth,
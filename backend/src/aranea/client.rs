@@ -3,13 +3,28 @@
 //! Proxies requests to:
 //! - araneaDeviceGate: device registration
 //! - deviceStateReport: device state querying
+//! - facility reports (see `reports`)
+//!
+//! All outbound calls go through `post_signed`, which attaches
+//! X-Lacis-Timestamp / X-Lacis-Signature when `signing_enabled` is set and
+//! records the outcome for `health`.
+//!
+//! The config is the file's `[aranea]` section with the stored override
+//! (see `config`) applied; it can change at runtime, so read it through
+//! `config()`.
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
+use super::config::AraneaConfigOverride;
+use super::health::{AraneaHealth, CallStats, CallWindow};
+use super::reports::FacilityReport;
+use super::signing;
 use crate::config::AraneaConfig;
 
 /// Cached araneaDevice entry: MAC → prefix-3 LacisID
@@ -23,9 +38,16 @@ pub struct AraneaDeviceCacheEntry {
 #[derive(Clone)]
 pub struct AraneaClient {
     http_client: reqwest::Client,
-    pub config: AraneaConfig,
+    /// `[aranea]` section of the config file
+    file_config: AraneaConfig,
+    /// Effective config (file + stored override)
+    config: Arc<std::sync::RwLock<AraneaConfig>>,
     /// MAC → araneaDevice LacisID cache (prefix-3). Updated on startup + every 60 min.
     device_cache: Arc<RwLock<HashMap<String, AraneaDeviceCacheEntry>>>,
+    /// Seconds to add to local time when signing (learned from skew rejections)
+    clock_offset: Arc<AtomicI64>,
+    /// Outcomes of recent outbound calls
+    calls: Arc<Mutex<CallWindow>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -43,6 +65,10 @@ struct DeviceGateRequest {
     product_code: String,
     #[serde(rename = "deviceType")]
     device_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fid: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -65,6 +91,10 @@ pub struct AraneaDeviceRegistration {
     pub product_type: String,
     pub product_code: String,
     pub device_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fid: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +106,62 @@ pub struct AraneaDeviceState {
     pub last_seen: Option<String>,
 }
 
+/// Facility known to mobes2.0 (target of Omada site fid mappings)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AraneaFacility {
+    pub fid: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FacilityReportRequest<'a> {
+    tid: String,
+    #[serde(rename = "lacisId")]
+    lacis_id: String,
+    #[serde(rename = "userId")]
+    user_id: String,
+    cic: String,
+    report: &'a FacilityReport,
+}
+
+#[derive(Debug, Serialize)]
+struct FacilityListRequest {
+    tid: String,
+    #[serde(rename = "lacisId")]
+    lacis_id: String,
+    #[serde(rename = "userId")]
+    user_id: String,
+    cic: String,
+}
+
+/// Parse `{ "facilities": [ { "fid": "...", "name": "..." }, ... ] }`
+/// (also accepts a bare array and `facilityName`)
+fn parse_facilities(response: &serde_json::Value) -> Vec<AraneaFacility> {
+    let list = response
+        .get("facilities")
+        .unwrap_or(response)
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    list.iter()
+        .filter_map(|f| {
+            let fid = f.get("fid").and_then(|v| v.as_str())?.trim();
+            if fid.is_empty() {
+                return None;
+            }
+            let name = f
+                .get("name")
+                .or_else(|| f.get("facilityName"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            Some(AraneaFacility {
+                fid: fid.to_string(),
+                name,
+            })
+        })
+        .collect()
+}
+
 impl AraneaClient {
     pub fn new(config: AraneaConfig) -> Self {
         let http_client = reqwest::Client::builder()
@@ -83,18 +169,196 @@ impl AraneaClient {
             .build()
             .unwrap_or_default();
 
+        if config.signing_enabled && config.signing_key.is_empty() {
+            tracing::warn!("[AraneaClient] signing_enabled is set but signing_key is empty");
+        }
+
         Self {
             http_client,
-            config,
+            file_config: config.clone(),
+            config: Arc::new(std::sync::RwLock::new(config)),
             device_cache: Arc::new(RwLock::new(HashMap::new())),
+            clock_offset: Arc::new(AtomicI64::new(0)),
+            calls: Arc::new(Mutex::new(CallWindow::default())),
+        }
+    }
+
+    /// Effective config
+    pub fn config(&self) -> AraneaConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// `[aranea]` section of the config file
+    pub fn file_config(&self) -> &AraneaConfig {
+        &self.file_config
+    }
+
+    /// Apply a stored override on top of the file config. Returns whether
+    /// the effective config changed; a different tenant drops the device
+    /// cache, which belongs to the old one.
+    pub async fn apply_override(&self, config_override: &AraneaConfigOverride) -> bool {
+        let next = config_override.apply(&self.file_config);
+        let previous = {
+            let mut config = self.config.write().unwrap();
+            if *config == next {
+                return false;
+            }
+            std::mem::replace(&mut *config, next.clone())
+        };
+        if previous.tid != next.tid || previous.tenant_lacis_id != next.tenant_lacis_id {
+            self.device_cache.write().await.clear();
+        }
+        if previous.signing_key != next.signing_key {
+            self.clock_offset.store(0, Ordering::Relaxed);
+        }
+        if next.signing_enabled && next.signing_key.is_empty() {
+            tracing::warn!("[AraneaClient] signing_enabled is set but signing_key is empty");
+        }
+        tracing::info!(
+            "[AraneaClient] Config updated (tid: {}, device_state_url: {})",
+            next.tid,
+            next.device_state_url
+        );
+        true
+    }
+
+    /// Recent call outcomes
+    pub fn call_stats(&self) -> CallStats {
+        self.calls.lock().unwrap().stats(chrono::Utc::now())
+    }
+
+    /// Lightweight authenticated call (device state query for the tenant's
+    /// own LacisID) to check that mobes2.0 is reachable with this config
+    pub async fn check_health(&self) -> AraneaHealth {
+        let config = self.config();
+        let configured = self.is_configured();
+        let (reachable, latency_ms, error) = if configured {
+            let start = Instant::now();
+            let result = self.get_device_states(Some(&config.tenant_lacis_id)).await;
+            let latency_ms = start.elapsed().as_millis() as u64;
+            (Some(result.is_ok()), Some(latency_ms), result.err())
+        } else {
+            (
+                None,
+                None,
+                Some("Aranea not configured: missing tid/tenant_lacis_id/tenant_cic".to_string()),
+            )
+        };
+        AraneaHealth {
+            configured,
+            reachable,
+            endpoint: config.device_state_url,
+            latency_ms,
+            error,
+            checked_at: chrono::Utc::now(),
+            recent: self.call_stats(),
+        }
+    }
+
+    /// POST a JSON payload, signing it when enabled, and record the outcome
+    async fn post_signed<T: Serialize>(
+        &self,
+        url: &str,
+        payload: &T,
+        label: &str,
+    ) -> Result<serde_json::Value, String> {
+        let start = Instant::now();
+        let result = self.send_signed(url, payload, label).await;
+        self.calls.lock().unwrap().record(
+            chrono::Utc::now(),
+            start.elapsed().as_millis() as u64,
+            result.as_ref().map(|_| ()).map_err(|e| e.as_str()),
+        );
+        result
+    }
+
+    /// If the upstream rejects the signature with 401/403 and reports its own
+    /// clock via X-Lacis-Server-Time, the offset is remembered and the request
+    /// is retried once with a corrected timestamp.
+    async fn send_signed<T: Serialize>(
+        &self,
+        url: &str,
+        payload: &T,
+        label: &str,
+    ) -> Result<serde_json::Value, String> {
+        let config = self.config();
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| format!("{} request encode failed: {}", label, e))?;
+        let path = reqwest::Url::parse(url)
+            .map(|u| u.path().to_string())
+            .map_err(|e| format!("{} invalid URL: {}", label, e))?;
+
+        let mut retried = false;
+        loop {
+            let mut req = self
+                .http_client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+
+            if config.signing_enabled {
+                let timestamp =
+                    chrono::Utc::now().timestamp() + self.clock_offset.load(Ordering::Relaxed);
+                let signature = signing::sign(&config.signing_key, timestamp, "POST", &path, &body);
+                req = req
+                    .header(signing::TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(signing::SIGNATURE_HEADER, signature);
+            }
+
+            let resp = req
+                .send()
+                .await
+                .map_err(|e| format!("{} request failed: {}", label, e))?;
+            let status = resp.status();
+
+            if config.signing_enabled
+                && !retried
+                && (status == reqwest::StatusCode::UNAUTHORIZED
+                    || status == reqwest::StatusCode::FORBIDDEN)
+            {
+                let server_time = resp
+                    .headers()
+                    .get(signing::SERVER_TIME_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<i64>().ok());
+                if let Some(server_time) = server_time {
+                    let offset = server_time - chrono::Utc::now().timestamp();
+                    self.clock_offset.store(offset, Ordering::Relaxed);
+                    tracing::warn!(
+                        "[AraneaClient] {} rejected signature ({}); clock offset now {}s, retrying",
+                        label,
+                        status,
+                        offset
+                    );
+                    retried = true;
+                    continue;
+                }
+            }
+
+            let body: serde_json::Value = resp
+                .json()
+                .await
+                .map_err(|e| format!("{} response parse failed: {}", label, e))?;
+
+            return if status.is_success() {
+                Ok(body)
+            } else {
+                Err(format!(
+                    "{} returned {}: {}",
+                    label,
+                    status,
+                    serde_json::to_string(&body).unwrap_or_default()
+                ))
+            };
         }
     }
 
     /// Check if aranea is configured (has required tenant info)
     pub fn is_configured(&self) -> bool {
-        !self.config.tid.is_empty()
-            && !self.config.tenant_lacis_id.is_empty()
-            && !self.config.tenant_cic.is_empty()
+        let config = self.config.read().unwrap();
+        !config.tid.is_empty()
+            && !config.tenant_lacis_id.is_empty()
+            && !config.tenant_cic.is_empty()
     }
 
     /// Register a device via araneaDeviceGate Cloud Function
@@ -108,40 +372,22 @@ impl AraneaClient {
             );
         }
 
+        let config = self.config();
         let payload = DeviceGateRequest {
-            tid: self.config.tid.clone(),
-            lacis_id: self.config.tenant_lacis_id.clone(),
-            user_id: self.config.tenant_user_id.clone(),
-            cic: self.config.tenant_cic.clone(),
+            tid: config.tid.clone(),
+            lacis_id: config.tenant_lacis_id.clone(),
+            user_id: config.tenant_user_id.clone(),
+            cic: config.tenant_cic.clone(),
             mac: reg.mac.clone(),
             product_type: reg.product_type.clone(),
             product_code: reg.product_code.clone(),
             device_type: reg.device_type.clone(),
+            model: reg.model.clone(),
+            fid: reg.fid.clone(),
         };
 
-        let resp = self
-            .http_client
-            .post(&self.config.device_gate_url)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| format!("araneaDeviceGate request failed: {}", e))?;
-
-        let status = resp.status();
-        let body: serde_json::Value = resp
-            .json()
+        self.post_signed(&config.device_gate_url, &payload, "araneaDeviceGate")
             .await
-            .map_err(|e| format!("araneaDeviceGate response parse failed: {}", e))?;
-
-        if status.is_success() {
-            Ok(body)
-        } else {
-            Err(format!(
-                "araneaDeviceGate returned {}: {}",
-                status,
-                serde_json::to_string(&body).unwrap_or_default()
-            ))
-        }
     }
 
     /// Query device states via deviceStateReport Cloud Function
@@ -159,38 +405,66 @@ impl AraneaClient {
             "list"
         };
 
+        let config = self.config();
         let payload = DeviceStateRequest {
-            tid: self.config.tid.clone(),
-            lacis_id: self.config.tenant_lacis_id.clone(),
-            user_id: self.config.tenant_user_id.clone(),
-            cic: self.config.tenant_cic.clone(),
+            tid: config.tid.clone(),
+            lacis_id: config.tenant_lacis_id.clone(),
+            user_id: config.tenant_user_id.clone(),
+            cic: config.tenant_cic.clone(),
             target_lacis_id: target_lacis_id.map(|s| s.to_string()),
             mode: mode.to_string(),
         };
 
-        let resp = self
-            .http_client
-            .post(&self.config.device_state_url)
-            .json(&payload)
-            .send()
+        self.post_signed(&config.device_state_url, &payload, "deviceStateReport")
             .await
-            .map_err(|e| format!("deviceStateReport request failed: {}", e))?;
+    }
 
-        let status = resp.status();
-        let body: serde_json::Value = resp
-            .json()
-            .await
-            .map_err(|e| format!("deviceStateReport response parse failed: {}", e))?;
+    /// Facilities for the tenant; None when aranea or the facility list URL
+    /// is not configured (callers then skip fid validation)
+    pub async fn list_facilities(&self) -> Result<Option<Vec<AraneaFacility>>, String> {
+        let config = self.config();
+        if !self.is_configured() || config.facility_list_url.is_empty() {
+            return Ok(None);
+        }
 
-        if status.is_success() {
-            Ok(body)
-        } else {
-            Err(format!(
-                "deviceStateReport returned {}: {}",
-                status,
-                serde_json::to_string(&body).unwrap_or_default()
-            ))
+        let payload = FacilityListRequest {
+            tid: config.tid.clone(),
+            lacis_id: config.tenant_lacis_id.clone(),
+            user_id: config.tenant_user_id.clone(),
+            cic: config.tenant_cic.clone(),
+        };
+
+        let response = self
+            .post_signed(&config.facility_list_url, &payload, "facilityList")
+            .await?;
+        Ok(Some(parse_facilities(&response)))
+    }
+
+    /// Whether facility reports can be pushed (tenant and report URL set)
+    pub fn can_push_facility_reports(&self) -> bool {
+        self.is_configured() && !self.config.read().unwrap().facility_report_url.is_empty()
+    }
+
+    /// Push one facility report
+    pub async fn push_facility_report(
+        &self,
+        report: &FacilityReport,
+    ) -> Result<serde_json::Value, String> {
+        if !self.can_push_facility_reports() {
+            return Err("Aranea facility reports not configured".to_string());
         }
+
+        let config = self.config();
+        let payload = FacilityReportRequest {
+            tid: config.tid.clone(),
+            lacis_id: config.tenant_lacis_id.clone(),
+            user_id: config.tenant_user_id.clone(),
+            cic: config.tenant_cic.clone(),
+            report,
+        };
+
+        self.post_signed(&config.facility_report_url, &payload, "facilityReport")
+            .await
     }
 
     /// Refresh the MAC → araneaDevice cache by fetching all device states.
@@ -261,12 +535,50 @@ impl AraneaClient {
 
     /// Get aranea config summary (for frontend display)
     pub fn get_config_summary(&self) -> serde_json::Value {
+        let config = self.config();
         serde_json::json!({
             "configured": self.is_configured(),
-            "tid": if self.config.tid.is_empty() { None } else { Some(&self.config.tid) },
-            "tenant_user_id": if self.config.tenant_user_id.is_empty() { None } else { Some(&self.config.tenant_user_id) },
-            "device_gate_url": &self.config.device_gate_url,
-            "device_state_url": &self.config.device_state_url,
+            "tid": if config.tid.is_empty() { None } else { Some(&config.tid) },
+            "tenant_user_id": if config.tenant_user_id.is_empty() { None } else { Some(&config.tenant_user_id) },
+            "device_gate_url": &config.device_gate_url,
+            "device_state_url": &config.device_state_url,
+            "signing_enabled": config.signing_enabled,
+            "facility_report_url": if config.facility_report_url.is_empty() { None } else { Some(&config.facility_report_url) },
+            "clock_offset_sec": self.clock_offset.load(Ordering::Relaxed),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn facilities_parse_both_shapes() {
+        let wrapped = serde_json::json!({
+            "facilities": [
+                { "fid": "0150", "name": "Akihabara" },
+                { "fid": "0151", "facilityName": "Kanda" },
+                { "fid": "" },
+                { "name": "no fid" }
+            ]
+        });
+        assert_eq!(
+            parse_facilities(&wrapped),
+            vec![
+                AraneaFacility {
+                    fid: "0150".to_string(),
+                    name: Some("Akihabara".to_string()),
+                },
+                AraneaFacility {
+                    fid: "0151".to_string(),
+                    name: Some("Kanda".to_string()),
+                },
+            ]
+        );
+
+        let bare = serde_json::json!([{ "fid": "0152" }]);
+        assert_eq!(parse_facilities(&bare)[0].fid, "0152");
+        assert!(parse_facilities(&serde_json::json!({ "ok": true })).is_empty());
+    }
+}
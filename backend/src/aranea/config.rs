@@ -0,0 +1,334 @@
+//! Runtime aranea configuration
+//!
+//! The `[aranea]` section of the config file is the base. `PUT
+//! /api/aranea/config` stores the fields it changes as JSON in the
+//! `aranea_config` setting, which overrides the file field by field and is
+//! applied to the live client without a restart. Every instance re-reads the
+//! setting every `RELOAD_INTERVAL`, so the change reaches the whole cluster.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use super::AraneaClient;
+use crate::config::AraneaConfig;
+use crate::db::mysql::MySqlDb;
+use crate::error::{AppError, FieldError};
+
+/// Settings key holding the stored override
+pub const SETTING_ARANEA_CONFIG: &str = "aranea_config";
+
+/// Shown instead of a secret
+pub const MASK: &str = "********";
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Fields overriding the config file (None = the file value applies)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AraneaConfigOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_lacis_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_cic: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_gate_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_state_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facility_list_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facility_report_url: Option<String>,
+}
+
+impl AraneaConfigOverride {
+    /// Effective config: `base` with the overridden fields replaced
+    pub fn apply(&self, base: &AraneaConfig) -> AraneaConfig {
+        let pick = |value: &Option<String>, file: &String| {
+            value
+                .as_ref()
+                .map_or_else(|| file.clone(), |v| v.trim().to_string())
+        };
+        AraneaConfig {
+            tid: pick(&self.tid, &base.tid),
+            tenant_lacis_id: pick(&self.tenant_lacis_id, &base.tenant_lacis_id),
+            tenant_user_id: pick(&self.tenant_user_id, &base.tenant_user_id),
+            tenant_cic: pick(&self.tenant_cic, &base.tenant_cic),
+            device_gate_url: pick(&self.device_gate_url, &base.device_gate_url),
+            device_state_url: pick(&self.device_state_url, &base.device_state_url),
+            signing_key: pick(&self.signing_key, &base.signing_key),
+            signing_enabled: self.signing_enabled.unwrap_or(base.signing_enabled),
+            facility_list_url: pick(&self.facility_list_url, &base.facility_list_url),
+            facility_report_url: pick(&self.facility_report_url, &base.facility_report_url),
+        }
+    }
+
+    /// Later fields win; a secret sent back as [`MASK`] keeps its value
+    pub fn merge(&mut self, update: AraneaConfigOverride) {
+        fn set(slot: &mut Option<String>, value: Option<String>) {
+            if let Some(value) = value {
+                *slot = Some(value);
+            }
+        }
+        fn set_secret(slot: &mut Option<String>, value: Option<String>) {
+            if let Some(value) = value.filter(|v| v != MASK) {
+                *slot = Some(value);
+            }
+        }
+        set(&mut self.tid, update.tid);
+        set(&mut self.tenant_lacis_id, update.tenant_lacis_id);
+        set(&mut self.tenant_user_id, update.tenant_user_id);
+        set_secret(&mut self.tenant_cic, update.tenant_cic);
+        set(&mut self.device_gate_url, update.device_gate_url);
+        set(&mut self.device_state_url, update.device_state_url);
+        set_secret(&mut self.signing_key, update.signing_key);
+        if update.signing_enabled.is_some() {
+            self.signing_enabled = update.signing_enabled;
+        }
+        set(&mut self.facility_list_url, update.facility_list_url);
+        set(&mut self.facility_report_url, update.facility_report_url);
+    }
+
+    /// Names of the overridden fields
+    pub fn fields(&self) -> Vec<&'static str> {
+        [
+            ("tid", self.tid.is_some()),
+            ("tenant_lacis_id", self.tenant_lacis_id.is_some()),
+            ("tenant_user_id", self.tenant_user_id.is_some()),
+            ("tenant_cic", self.tenant_cic.is_some()),
+            ("device_gate_url", self.device_gate_url.is_some()),
+            ("device_state_url", self.device_state_url.is_some()),
+            ("signing_key", self.signing_key.is_some()),
+            ("signing_enabled", self.signing_enabled.is_some()),
+            ("facility_list_url", self.facility_list_url.is_some()),
+            ("facility_report_url", self.facility_report_url.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, set)| *set)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    /// Copy safe to log or return from the API
+    pub fn masked(mut self) -> Self {
+        for secret in [&mut self.tenant_cic, &mut self.signing_key] {
+            if secret.as_ref().is_some_and(|s| !s.is_empty()) {
+                *secret = Some(MASK.to_string());
+            }
+        }
+        self
+    }
+}
+
+/// Effective config as returned by `GET /api/aranea/config` (secrets
+/// masked)
+#[derive(Debug, Clone, Serialize)]
+pub struct AraneaConfigView {
+    pub is_configured: bool,
+    pub tid: String,
+    pub tenant_lacis_id: String,
+    pub tenant_user_id: String,
+    pub tenant_cic: String,
+    /// Endpoint of the health check (device state queries)
+    pub endpoint: String,
+    pub device_gate_url: String,
+    pub device_state_url: String,
+    pub signing_key: String,
+    pub signing_enabled: bool,
+    pub facility_list_url: String,
+    pub facility_report_url: String,
+    /// Fields set at runtime rather than in the config file
+    pub overridden: Vec<&'static str>,
+}
+
+impl AraneaConfigView {
+    pub fn new(
+        config: AraneaConfig,
+        config_override: &AraneaConfigOverride,
+        is_configured: bool,
+    ) -> Self {
+        let mask = |secret: String| {
+            if secret.is_empty() {
+                secret
+            } else {
+                MASK.to_string()
+            }
+        };
+        Self {
+            is_configured,
+            tid: config.tid,
+            tenant_lacis_id: config.tenant_lacis_id,
+            tenant_user_id: config.tenant_user_id,
+            tenant_cic: mask(config.tenant_cic),
+            endpoint: config.device_state_url.clone(),
+            device_gate_url: config.device_gate_url,
+            device_state_url: config.device_state_url,
+            signing_key: mask(config.signing_key),
+            signing_enabled: config.signing_enabled,
+            facility_list_url: config.facility_list_url,
+            facility_report_url: config.facility_report_url,
+            overridden: config_override.fields(),
+        }
+    }
+}
+
+/// Invalid fields of an effective config (an unparseable URL would only
+/// fail at the next call)
+pub fn validate(config: &AraneaConfig) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    for (name, url, required) in [
+        ("device_gate_url", &config.device_gate_url, true),
+        ("device_state_url", &config.device_state_url, true),
+        ("facility_list_url", &config.facility_list_url, false),
+        ("facility_report_url", &config.facility_report_url, false),
+    ] {
+        if url.is_empty() {
+            if required {
+                errors.push(FieldError::new(name, "must not be empty"));
+            }
+            continue;
+        }
+        match reqwest::Url::parse(url) {
+            Ok(u) if u.scheme() == "https" || u.scheme() == "http" => {}
+            _ => errors.push(FieldError::new(name, "must be an http(s) URL")),
+        }
+    }
+    if config.signing_enabled && config.signing_key.is_empty() {
+        errors.push(FieldError::new(
+            "signing_key",
+            "must be set while signing_enabled is on",
+        ));
+    }
+    errors
+}
+
+/// Stored override; an unreadable value is ignored (and logged) so the file
+/// config keeps working
+pub async fn load_override(mysql: &MySqlDb) -> Result<AraneaConfigOverride, AppError> {
+    let raw = mysql.get_setting(SETTING_ARANEA_CONFIG).await?;
+    Ok(
+        match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            None => AraneaConfigOverride::default(),
+            Some(raw) => serde_json::from_str(raw).unwrap_or_else(|e| {
+                tracing::warn!(
+                    "[Aranea] Ignoring unreadable {}: {}",
+                    SETTING_ARANEA_CONFIG,
+                    e
+                );
+                AraneaConfigOverride::default()
+            }),
+        },
+    )
+}
+
+/// Persist the override
+pub async fn save_override(
+    mysql: &MySqlDb,
+    config_override: &AraneaConfigOverride,
+) -> Result<(), AppError> {
+    let raw = serde_json::to_string(config_override)
+        .map_err(|e| AppError::InternalError(format!("Encode aranea config: {}", e)))?;
+    mysql
+        .upsert_setting(
+            SETTING_ARANEA_CONFIG,
+            Some(&raw),
+            Some("Aranea settings overriding the [aranea] config section (JSON)"),
+        )
+        .await
+}
+
+/// Apply the stored override to the client; true when the config changed
+pub async fn reload(client: &AraneaClient, mysql: &MySqlDb) -> Result<bool, AppError> {
+    let config_override = load_override(mysql).await?;
+    Ok(client.apply_override(&config_override).await)
+}
+
+/// Re-read the override every `RELOAD_INTERVAL` so a change made on another
+/// instance takes effect here (per instance)
+pub async fn reload_loop(client: Arc<AraneaClient>, mysql: Arc<MySqlDb>) {
+    let mut tick = interval(RELOAD_INTERVAL);
+    tick.tick().await;
+    loop {
+        tick.tick().await;
+        if let Err(e) = reload(&client, &mysql).await {
+            tracing::warn!("Failed to reload aranea config: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_config() -> AraneaConfig {
+        AraneaConfig {
+            tid: "T1".to_string(),
+            tenant_cic: "111111".to_string(),
+            signing_key: "file-key".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn override_replaces_only_set_fields() {
+        let config_override = AraneaConfigOverride {
+            tid: Some(" T2 ".to_string()),
+            signing_enabled: Some(true),
+            ..Default::default()
+        };
+        let effective = config_override.apply(&file_config());
+        assert_eq!(effective.tid, "T2");
+        assert_eq!(effective.tenant_cic, "111111");
+        assert_eq!(effective.signing_key, "file-key");
+        assert!(effective.signing_enabled);
+        assert_eq!(
+            effective.device_state_url,
+            AraneaConfig::default().device_state_url
+        );
+        assert_eq!(config_override.fields(), vec!["tid", "signing_enabled"]);
+    }
+
+    #[test]
+    fn masked_secrets_are_kept_on_merge() {
+        let mut stored = AraneaConfigOverride {
+            signing_key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        stored.merge(AraneaConfigOverride {
+            tid: Some("T3".to_string()),
+            signing_key: Some(MASK.to_string()),
+            tenant_cic: Some("222222".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(stored.signing_key.as_deref(), Some("secret"));
+        assert_eq!(stored.tenant_cic.as_deref(), Some("222222"));
+        assert_eq!(stored.tid.as_deref(), Some("T3"));
+
+        let masked = stored.masked();
+        assert_eq!(masked.signing_key.as_deref(), Some(MASK));
+        assert_eq!(masked.tenant_cic.as_deref(), Some(MASK));
+        assert_eq!(masked.tid.as_deref(), Some("T3"));
+    }
+
+    #[test]
+    fn validation_rejects_bad_urls_and_keyless_signing() {
+        assert!(validate(&file_config()).is_empty());
+        let config = AraneaConfig {
+            device_gate_url: "ftp://example.com/gate".to_string(),
+            facility_list_url: "not a url".to_string(),
+            signing_enabled: true,
+            signing_key: String::new(),
+            ..Default::default()
+        };
+        assert_eq!(validate(&config).len(), 3);
+    }
+}
@@ -0,0 +1,129 @@
+//! Connectivity of the aranea client
+//!
+//! Every outbound call records its outcome in a rolling window (last hour,
+//! at most `MAX_CALLS` entries) kept by the client, so `GET
+//! /api/aranea/health` and the dependency probe report recent error counts
+//! and the last success/failure without a store of their own.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Length of the rolling window
+pub const WINDOW_SECS: i64 = 3600;
+/// Calls kept in the window at most
+const MAX_CALLS: usize = 1000;
+
+#[derive(Debug, Clone)]
+struct CallRecord {
+    at: DateTime<Utc>,
+    ok: bool,
+    latency_ms: u64,
+}
+
+/// Outcomes of recent calls
+#[derive(Debug, Default)]
+pub struct CallWindow {
+    calls: VecDeque<CallRecord>,
+    last_success_at: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// Summary of the window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CallStats {
+    pub window_secs: i64,
+    pub calls: usize,
+    pub errors: usize,
+    pub avg_latency_ms: Option<u64>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+impl CallWindow {
+    pub fn record(&mut self, at: DateTime<Utc>, latency_ms: u64, result: Result<(), &str>) {
+        match result {
+            Ok(()) => self.last_success_at = Some(at),
+            Err(e) => {
+                self.last_failure_at = Some(at);
+                self.last_error = Some(e.to_string());
+            }
+        }
+        self.calls.push_back(CallRecord {
+            at,
+            ok: result.is_ok(),
+            latency_ms,
+        });
+        while self.calls.len() > MAX_CALLS {
+            self.calls.pop_front();
+        }
+        self.prune(at);
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let oldest = now - Duration::seconds(WINDOW_SECS);
+        while self.calls.front().is_some_and(|c| c.at < oldest) {
+            self.calls.pop_front();
+        }
+    }
+
+    pub fn stats(&self, now: DateTime<Utc>) -> CallStats {
+        let oldest = now - Duration::seconds(WINDOW_SECS);
+        let recent: Vec<_> = self.calls.iter().filter(|c| c.at >= oldest).collect();
+        let avg_latency_ms = (!recent.is_empty())
+            .then(|| recent.iter().map(|c| c.latency_ms).sum::<u64>() / recent.len() as u64);
+        CallStats {
+            window_secs: WINDOW_SECS,
+            calls: recent.len(),
+            errors: recent.iter().filter(|c| !c.ok).count(),
+            avg_latency_ms,
+            last_success_at: self.last_success_at,
+            last_failure_at: self.last_failure_at,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Result of `GET /api/aranea/health`
+#[derive(Debug, Clone, Serialize)]
+pub struct AraneaHealth {
+    pub configured: bool,
+    /// Whether the check call succeeded (None = not configured, not called)
+    pub reachable: Option<bool>,
+    pub endpoint: String,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    /// Recent calls, including the check itself
+    pub recent: CallStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_counts_recent_calls_and_keeps_last_outcomes() {
+        let now = Utc::now();
+        let mut window = CallWindow::default();
+        window.record(now - Duration::seconds(WINDOW_SECS + 10), 500, Err("old"));
+        window.record(now - Duration::seconds(60), 100, Ok(()));
+        window.record(now - Duration::seconds(30), 300, Err("HTTP 503"));
+
+        let stats = window.stats(now);
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.avg_latency_ms, Some(200));
+        assert_eq!(stats.last_success_at, Some(now - Duration::seconds(60)));
+        assert_eq!(stats.last_failure_at, Some(now - Duration::seconds(30)));
+        assert_eq!(stats.last_error.as_deref(), Some("HTTP 503"));
+
+        let later = window.stats(now + Duration::seconds(WINDOW_SECS));
+        assert_eq!(later.calls, 0);
+        assert_eq!(later.avg_latency_ms, None);
+        assert!(later.last_success_at.is_some());
+    }
+}
@@ -1,4 +1,10 @@
 //! Aranea SDK module - proxy to mobes2.0 Cloud Functions
 
 pub mod client;
+pub mod config;
+pub mod health;
+pub mod registration;
+pub mod reports;
+pub mod signing;
+pub mod tokens;
 pub use client::AraneaClient;
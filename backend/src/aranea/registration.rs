@@ -0,0 +1,216 @@
+//! araneaDeviceGate registration pipeline helpers
+//!
+//! Turns topology infra nodes (user_object_detail entries that have a
+//! candidate LacisID but no confirmed one) into registration payloads, and
+//! extracts the issued LacisID from the araneaDeviceGate response.
+
+use serde::Serialize;
+
+use super::client::AraneaDeviceRegistration;
+use crate::db::mongo::user_object_detail::UserObjectDetail;
+use crate::lacis_id::{default_product_code, normalize_mac_for_lacis_id};
+
+/// Sources whose device collections accept `assign_lacis_id`
+const ASSIGNABLE_SOURCES: [&str; 3] = ["omada", "openwrt", "external"];
+
+/// A topology node awaiting registration, with the payload that would be sent
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrationCandidate {
+    pub id: String,
+    pub label: String,
+    pub source: String,
+    pub node_type: String,
+    pub candidate_lacis_id: String,
+    pub facility_name: Option<String>,
+    pub payload: AraneaDeviceRegistration,
+    /// Payload problems that would block registration (empty = ready)
+    pub issues: Vec<String>,
+}
+
+/// Whether a node is an unregistered infra device from an assignable source
+pub fn is_candidate(node: &UserObjectDetail) -> bool {
+    node.candidate_lacis_id.is_some()
+        && node.lacis_id.as_deref().is_none_or(str::is_empty)
+        && ASSIGNABLE_SOURCES.contains(&node.source.as_str())
+}
+
+/// Build the registration payload for a node, pre-filled from topology data
+pub fn build_payload(node: &UserObjectDetail) -> AraneaDeviceRegistration {
+    let network_device_type = node
+        .network_device_type
+        .clone()
+        .unwrap_or_else(|| node.node_type.clone());
+    AraneaDeviceRegistration {
+        mac: normalize_mac_for_lacis_id(&node.mac),
+        product_type: node.product_type.clone().unwrap_or_default(),
+        product_code: node
+            .product_code
+            .clone()
+            .unwrap_or_else(|| default_product_code(&network_device_type).to_string()),
+        device_type: network_device_type,
+        model: node
+            .metadata
+            .get("model")
+            .and_then(|v| v.as_str())
+            .filter(|m| !m.is_empty())
+            .map(String::from),
+        fid: node.fid.clone().filter(|f| !f.is_empty()),
+    }
+}
+
+pub fn to_candidate(node: &UserObjectDetail) -> RegistrationCandidate {
+    let payload = build_payload(node);
+    RegistrationCandidate {
+        id: node.id.clone(),
+        label: node.label.clone(),
+        source: node.source.clone(),
+        node_type: node.node_type.clone(),
+        candidate_lacis_id: node.candidate_lacis_id.clone().unwrap_or_default(),
+        facility_name: node.facility_name.clone(),
+        issues: validate_payload(&payload),
+        payload,
+    }
+}
+
+/// Local payload checks (LacisID field formats). There is no schema registry
+/// in LPG, so these mirror the candidate LacisID composition rules.
+pub fn validate_payload(reg: &AraneaDeviceRegistration) -> Vec<String> {
+    let mut issues = Vec::new();
+    if reg.mac.len() != 12 || !reg.mac.chars().all(|c| c.is_ascii_hexdigit()) {
+        issues.push(format!("mac must be 12 hex digits (got '{}')", reg.mac));
+    }
+    if reg.product_type.len() != 3 || !reg.product_type.chars().all(|c| c.is_ascii_digit()) {
+        issues.push(format!(
+            "product_type must be 3 digits (got '{}')",
+            reg.product_type
+        ));
+    }
+    if reg.product_code.len() != 4 || !reg.product_code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        issues.push(format!(
+            "product_code must be 4 alphanumeric characters (got '{}')",
+            reg.product_code
+        ));
+    }
+    if reg.device_type.trim().is_empty() {
+        issues.push("device_type is required".to_string());
+    }
+    issues
+}
+
+/// Device id used by `MongoDb::assign_lacis_id` for this node's source.
+///
+/// source_ref_id is "{source}:{ref}:dev:{mac}"; omada devices are keyed by
+/// MAC, openwrt/external by router/device id.
+pub fn assign_target(node: &UserObjectDetail) -> Option<(String, String)> {
+    let source_ref = node.source_ref_id.as_deref()?;
+    let rest = source_ref.strip_prefix(&format!("{}:", node.source))?;
+    let (reference, mac) = rest.rsplit_once(":dev:")?;
+    let device_id = match node.source.as_str() {
+        "omada" => mac,
+        "openwrt" | "external" => reference,
+        _ => return None,
+    };
+    if device_id.is_empty() {
+        return None;
+    }
+    Some((node.source.clone(), device_id.to_string()))
+}
+
+/// Extract the issued LacisID from an araneaDeviceGate response.
+///
+/// Accepts `lacisId`/`lacis_id` at the top level or under `data`/`device`.
+pub fn extract_lacis_id(response: &serde_json::Value) -> Option<String> {
+    let lookup = |v: &serde_json::Value| {
+        v.get("lacisId")
+            .or_else(|| v.get("lacis_id"))
+            .and_then(|id| id.as_str())
+            .filter(|id| !id.is_empty())
+            .map(String::from)
+    };
+    lookup(response)
+        .or_else(|| response.get("data").and_then(lookup))
+        .or_else(|| response.get("device").and_then(lookup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(source: &str, source_ref: &str) -> UserObjectDetail {
+        UserObjectDetail {
+            id: "4101AABBCCDDEEFF0000".to_string(),
+            mac: "AA:BB:CC:DD:EE:FF".to_string(),
+            lacis_id: None,
+            device_type: "NetworkDevice".to_string(),
+            parent_id: "INTERNET".to_string(),
+            sort_order: 0,
+            node_type: "gateway".to_string(),
+            state_type: "online".to_string(),
+            label: "gw".to_string(),
+            label_customized: false,
+            claimed_by: None,
+            ip: None,
+            hostname: None,
+            source: source.to_string(),
+            source_ref_id: Some(source_ref.to_string()),
+            connection_type: "wired".to_string(),
+            product_type: Some("101".to_string()),
+            product_code: Some("0000".to_string()),
+            network_device_type: Some("Router".to_string()),
+            candidate_lacis_id: Some("4101AABBCCDDEEFF0000".to_string()),
+            fid: Some("0150".to_string()),
+            facility_name: None,
+            ssid: None,
+            device_class: None,
+            metadata: serde_json::json!({ "model": "ER605" }),
+            aranea_lacis_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn payload_and_assign_target_from_node() {
+        let omada = node("omada", "omada:ctrl-1:dev:AA-BB-CC-DD-EE-FF");
+        assert!(is_candidate(&omada));
+        let payload = build_payload(&omada);
+        assert_eq!(payload.mac, "AABBCCDDEEFF");
+        assert_eq!(payload.device_type, "Router");
+        assert_eq!(payload.model.as_deref(), Some("ER605"));
+        assert_eq!(payload.fid.as_deref(), Some("0150"));
+        assert!(validate_payload(&payload).is_empty());
+        assert_eq!(
+            assign_target(&omada),
+            Some(("omada".to_string(), "AA-BB-CC-DD-EE-FF".to_string()))
+        );
+
+        let openwrt = node("openwrt", "openwrt:r-1:dev:AABBCCDDEEFF");
+        assert_eq!(
+            assign_target(&openwrt),
+            Some(("openwrt".to_string(), "r-1".to_string()))
+        );
+
+        let mut registered = node("external", "external:d-1:dev:AABBCCDDEEFF");
+        registered.lacis_id = Some("4101AABBCCDDEEFF0000".to_string());
+        assert!(!is_candidate(&registered));
+
+        let mut bad = payload.clone();
+        bad.product_type = "1".to_string();
+        assert_eq!(validate_payload(&bad).len(), 1);
+    }
+
+    #[test]
+    fn lacis_id_from_gate_response() {
+        let top = serde_json::json!({ "ok": true, "lacisId": "4101AABBCCDDEEFF0000" });
+        let nested = serde_json::json!({ "data": { "lacis_id": "4101AABBCCDDEEFF0001" } });
+        assert_eq!(
+            extract_lacis_id(&top).as_deref(),
+            Some("4101AABBCCDDEEFF0000")
+        );
+        assert_eq!(
+            extract_lacis_id(&nested).as_deref(),
+            Some("4101AABBCCDDEEFF0001")
+        );
+        assert_eq!(extract_lacis_id(&serde_json::json!({ "ok": true })), None);
+    }
+}
@@ -0,0 +1,548 @@
+//! Facility reports for mobes2.0
+//!
+//! One report per facility (fid) summarizes the topology over a period:
+//! device counts by node type, LacisID coverage, client counts by device
+//! class, the state changes recorded in `device_state_history` and the
+//! nodes first seen in the period. Facilities come from the Aranea facility
+//! list, the Omada site mappings and the nodes themselves, so a facility
+//! without devices still gets an (empty) report.
+//!
+//! Reports are pushed to `aranea.facility_report_url` every
+//! `facility_report_interval_hours` (0 = off) by a leader-only task, or on
+//! demand via `POST /api/aranea/reports/trigger`. Every report is stored in
+//! `facility_reports` with its delivery state before it is sent; failed
+//! deliveries are retried with exponential backoff. The newest
+//! `facility_report_retention` reports are kept.
+//!
+//! The payload is versioned (`schema`, `schema_version`); fields are only
+//! added within a version.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::aranea::AraneaClient;
+use crate::db::mongo::user_object_detail::UserObjectDetail;
+use crate::db::mysql::device_state::DeviceStateChange;
+use crate::db::AppState;
+
+pub const SCHEMA: &str = "lpg.facility_report";
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Report interval when the setting is missing
+pub const DEFAULT_INTERVAL_HOURS: i32 = 24;
+/// Reports kept when the setting is missing
+pub const DEFAULT_RETENTION: i32 = 200;
+/// Delivery attempts per report
+const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry; doubled per attempt
+const RETRY_BASE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FacilityReport {
+    pub schema: String,
+    pub schema_version: u32,
+    pub report_id: String,
+    pub fid: String,
+    pub facility_name: Option<String>,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub devices: DeviceCounts,
+    pub clients: ClientCounts,
+    pub state_changes: Vec<ReportStateChange>,
+    pub new_devices: Vec<ReportNode>,
+}
+
+/// Infrastructure nodes (everything that is not a client)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCounts {
+    pub total: u64,
+    pub online: u64,
+    /// By node type (gateway, switch, ap, ...)
+    pub by_type: BTreeMap<String, u64>,
+    pub with_lacis_id: u64,
+    pub without_lacis_id: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientCounts {
+    pub total: u64,
+    pub online: u64,
+    /// By device class (phone, laptop, ...; "unknown" when unclassified)
+    pub by_class: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportStateChange {
+    pub node_id: String,
+    pub label: String,
+    pub state_type: String,
+    pub previous_state: Option<String>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportNode {
+    pub node_id: String,
+    pub mac: String,
+    pub label: String,
+    pub node_type: String,
+    pub created_at: String,
+}
+
+/// Delivery state of a stored report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Attempts exhausted
+    Failed,
+    /// No report URL configured; stored only
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A `facility_reports` document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredFacilityReport {
+    pub report_id: String,
+    pub fid: String,
+    pub generated_at: DateTime<Utc>,
+    /// "schedule" or "manual"
+    pub trigger: String,
+    pub delivery: Delivery,
+    pub report: FacilityReport,
+}
+
+fn is_online(state_type: &str) -> bool {
+    matches!(state_type, "online" | "StaticOnline")
+}
+
+fn is_client(node: &UserObjectDetail) -> bool {
+    node.node_type == "client"
+}
+
+/// Build one report per facility. `facilities` maps every known fid to its
+/// name; fids that only appear on nodes are added.
+pub fn build_reports(
+    nodes: &[UserObjectDetail],
+    changes: &[DeviceStateChange],
+    facilities: &BTreeMap<String, Option<String>>,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+    generated_at: DateTime<Utc>,
+) -> Vec<FacilityReport> {
+    let mut names = facilities.clone();
+    for node in nodes {
+        if let Some(fid) = node.fid.as_deref().filter(|f| !f.is_empty()) {
+            let name = names.entry(fid.to_string()).or_default();
+            if name.is_none() {
+                name.clone_from(&node.facility_name);
+            }
+        }
+    }
+
+    let start = period_start.to_rfc3339();
+    let end = period_end.to_rfc3339();
+
+    names
+        .into_iter()
+        .map(|(fid, facility_name)| {
+            let members: Vec<&UserObjectDetail> = nodes
+                .iter()
+                .filter(|n| n.fid.as_deref() == Some(fid.as_str()))
+                .collect();
+            let ids: BTreeSet<&str> = members.iter().map(|n| n.id.as_str()).collect();
+
+            let mut devices = DeviceCounts::default();
+            let mut clients = ClientCounts::default();
+            for node in &members {
+                if is_client(node) {
+                    clients.total += 1;
+                    clients.online += is_online(&node.state_type) as u64;
+                    let class = node.device_class.as_deref().unwrap_or("unknown");
+                    *clients.by_class.entry(class.to_string()).or_default() += 1;
+                } else {
+                    devices.total += 1;
+                    devices.online += is_online(&node.state_type) as u64;
+                    *devices.by_type.entry(node.node_type.clone()).or_default() += 1;
+                    if node.lacis_id.is_some() {
+                        devices.with_lacis_id += 1;
+                    } else {
+                        devices.without_lacis_id += 1;
+                    }
+                }
+            }
+
+            let state_changes = changes
+                .iter()
+                .filter(|c| ids.contains(c.device_id.as_str()))
+                .map(|c| ReportStateChange {
+                    node_id: c.device_id.clone(),
+                    label: members
+                        .iter()
+                        .find(|n| n.id == c.device_id)
+                        .map(|n| n.label.clone())
+                        .unwrap_or_default(),
+                    state_type: c.state_type.clone(),
+                    previous_state: c.previous_state.clone(),
+                    changed_at: c.changed_at.and_utc(),
+                })
+                .collect();
+
+            let new_devices = members
+                .iter()
+                .filter(|n| n.created_at >= start && n.created_at < end)
+                .map(|n| ReportNode {
+                    node_id: n.id.clone(),
+                    mac: n.mac.clone(),
+                    label: n.label.clone(),
+                    node_type: n.node_type.clone(),
+                    created_at: n.created_at.clone(),
+                })
+                .collect();
+
+            FacilityReport {
+                schema: SCHEMA.to_string(),
+                schema_version: SCHEMA_VERSION,
+                report_id: uuid::Uuid::new_v4().to_string(),
+                fid,
+                facility_name,
+                period_start,
+                period_end,
+                generated_at,
+                devices,
+                clients,
+                state_changes,
+                new_devices,
+            }
+        })
+        .collect()
+}
+
+/// Wait before retry `attempt` (1-based)
+pub fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE * 2u32.pow(attempt.saturating_sub(1).min(5))
+}
+
+/// Generates, stores and delivers facility reports
+pub struct FacilityReporter {
+    app_state: AppState,
+    aranea_client: Arc<AraneaClient>,
+}
+
+impl FacilityReporter {
+    pub fn new(app_state: AppState, aranea_client: Arc<AraneaClient>) -> Self {
+        Self {
+            app_state,
+            aranea_client,
+        }
+    }
+
+    /// Configured interval in hours (0 = scheduled reports off)
+    pub async fn interval_hours(&self) -> i32 {
+        self.app_state
+            .mysql
+            .get_setting_i32("facility_report_interval_hours", DEFAULT_INTERVAL_HOURS)
+            .await
+            .unwrap_or(DEFAULT_INTERVAL_HOURS)
+            .max(0)
+    }
+
+    /// Start the schedule loop; the first reports go out one interval after
+    /// the task starts
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting facility reporter...");
+
+        loop {
+            let hours = self.interval_hours().await;
+            if hours == 0 {
+                // Off; look at the setting again later
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                continue;
+            }
+            tokio::time::sleep(Duration::from_secs(hours as u64 * 3600)).await;
+
+            match self.generate(hours as i64, None, "schedule").await {
+                Ok(reports) => self.deliver(reports).await,
+                Err(e) => tracing::warn!("Facility report generation failed: {}", e),
+            }
+        }
+    }
+
+    /// Build and store the reports of the last `hours` hours (one facility
+    /// with `fid`); they are stored pending (or skipped without a report URL)
+    pub async fn generate(
+        &self,
+        hours: i64,
+        fid: Option<&str>,
+        trigger: &str,
+    ) -> Result<Vec<StoredFacilityReport>, String> {
+        let now = Utc::now();
+        let period_start = now - chrono::Duration::hours(hours.max(1));
+        let mongo = &self.app_state.mongo;
+
+        let nodes = mongo.get_all_user_object_details().await?;
+        let changes = self
+            .app_state
+            .mysql
+            .list_device_state_changes(period_start.naive_utc(), now.naive_utc())
+            .await?;
+        let facilities = self.known_facilities().await;
+
+        let deliverable = self.aranea_client.can_push_facility_reports();
+        let mut stored = Vec::new();
+        for report in build_reports(&nodes, &changes, &facilities, period_start, now, now)
+            .into_iter()
+            .filter(|r| fid.is_none_or(|f| r.fid == f))
+        {
+            let doc = StoredFacilityReport {
+                report_id: report.report_id.clone(),
+                fid: report.fid.clone(),
+                generated_at: report.generated_at,
+                trigger: trigger.to_string(),
+                delivery: Delivery {
+                    status: if deliverable {
+                        DeliveryStatus::Pending
+                    } else {
+                        DeliveryStatus::Skipped
+                    },
+                    attempts: 0,
+                    last_error: None,
+                    delivered_at: None,
+                },
+                report,
+            };
+            mongo.insert_facility_report(&doc).await?;
+            stored.push(doc);
+        }
+
+        let keep = self
+            .app_state
+            .mysql
+            .get_setting_i32("facility_report_retention", DEFAULT_RETENTION)
+            .await
+            .unwrap_or(DEFAULT_RETENTION)
+            .max(1);
+        if let Err(e) = mongo.trim_facility_reports(keep as i64).await {
+            tracing::warn!("Failed to trim facility reports: {}", e);
+        }
+
+        tracing::info!(
+            "Generated {} facility report(s) ({})",
+            stored.len(),
+            trigger
+        );
+        Ok(stored)
+    }
+
+    /// Push pending reports, retrying failures with exponential backoff
+    /// until delivered or out of attempts
+    pub async fn deliver(&self, reports: Vec<StoredFacilityReport>) {
+        let mut pending: Vec<StoredFacilityReport> = reports
+            .into_iter()
+            .filter(|r| r.delivery.status == DeliveryStatus::Pending)
+            .collect();
+        let mongo = &self.app_state.mongo;
+
+        while !pending.is_empty() {
+            let mut retry = Vec::new();
+            for mut stored in pending {
+                stored.delivery.attempts += 1;
+                match self
+                    .aranea_client
+                    .push_facility_report(&stored.report)
+                    .await
+                {
+                    Ok(_) => {
+                        stored.delivery.status = DeliveryStatus::Delivered;
+                        stored.delivery.last_error = None;
+                        stored.delivery.delivered_at = Some(Utc::now());
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "Facility report {} ({}) delivery attempt {} failed: {}",
+                            stored.report_id,
+                            stored.fid,
+                            stored.delivery.attempts,
+                            e
+                        );
+                        stored.delivery.last_error = Some(e);
+                        if stored.delivery.attempts >= MAX_ATTEMPTS {
+                            stored.delivery.status = DeliveryStatus::Failed;
+                        }
+                    }
+                }
+                if let Err(e) = mongo
+                    .update_facility_report_delivery(&stored.report_id, &stored.delivery)
+                    .await
+                {
+                    tracing::warn!("Failed to record facility report delivery: {}", e);
+                }
+                if stored.delivery.status == DeliveryStatus::Pending {
+                    retry.push(stored);
+                }
+            }
+
+            if let Some(attempts) = retry.iter().map(|r| r.delivery.attempts).max() {
+                tokio::time::sleep(retry_delay(attempts)).await;
+            }
+            pending = retry;
+        }
+    }
+
+    /// fid → name from the Aranea facility list and the Omada site mappings
+    async fn known_facilities(&self) -> BTreeMap<String, Option<String>> {
+        let mut facilities = BTreeMap::new();
+
+        match self.aranea_client.list_facilities().await {
+            Ok(Some(list)) => {
+                for f in list {
+                    facilities.insert(f.fid, f.name);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Facility report: facility list unavailable: {}", e),
+        }
+
+        match self.app_state.mongo.list_omada_controllers().await {
+            Ok(controllers) => {
+                for site in controllers.iter().flat_map(|c| &c.sites) {
+                    if let Some(fid) = site.fid.as_deref().filter(|f| !f.is_empty()) {
+                        let name = facilities.entry(fid.to_string()).or_insert(None);
+                        if name.is_none() {
+                            name.clone_from(&site.fid_display_name);
+                        }
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("Facility report: Omada sites unavailable: {}", e),
+        }
+
+        facilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn node(id: &str, fid: &str, node_type: &str, state: &str) -> UserObjectDetail {
+        UserObjectDetail {
+            id: id.to_string(),
+            mac: id.to_string(),
+            lacis_id: None,
+            device_type: "NetworkDevice".to_string(),
+            parent_id: "INTERNET".to_string(),
+            sort_order: 0,
+            node_type: node_type.to_string(),
+            state_type: state.to_string(),
+            label: format!("node {}", id),
+            label_customized: false,
+            claimed_by: None,
+            ip: None,
+            hostname: None,
+            source: "omada".to_string(),
+            source_ref_id: None,
+            connection_type: "wired".to_string(),
+            product_type: None,
+            product_code: None,
+            network_device_type: None,
+            candidate_lacis_id: None,
+            fid: Some(fid.to_string()),
+            facility_name: Some(format!("Facility {}", fid)),
+            ssid: None,
+            device_class: None,
+            metadata: serde_json::Value::Null,
+            aranea_lacis_id: None,
+            created_at: "2026-03-01T00:00:00+00:00".to_string(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn reports_per_facility_including_empty_ones() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let end = start + chrono::Duration::hours(24);
+
+        let mut gw = node("GW1", "0150", "gateway", "online");
+        gw.lacis_id = Some("4101AABBCCDDEEFF0000".to_string());
+        let ap = node("AP1", "0150", "ap", "offline");
+        let mut phone = node("C1", "0150", "client", "online");
+        phone.device_class = Some("phone".to_string());
+        phone.created_at = "2026-03-02T01:00:00+00:00".to_string();
+        let laptop = node("C2", "0150", "client", "offline");
+        let other = node("GW2", "0200", "gateway", "online");
+
+        let changes = vec![DeviceStateChange {
+            device_id: "AP1".to_string(),
+            state_type: "offline".to_string(),
+            previous_state: Some("online".to_string()),
+            changed_at: (start + chrono::Duration::hours(1)).naive_utc(),
+        }];
+        let mut facilities = BTreeMap::new();
+        facilities.insert("0999".to_string(), Some("Empty site".to_string()));
+
+        let reports = build_reports(
+            &[gw, ap, phone, laptop, other],
+            &changes,
+            &facilities,
+            start,
+            end,
+            end,
+        );
+        let fids: Vec<&str> = reports.iter().map(|r| r.fid.as_str()).collect();
+        assert_eq!(fids, ["0150", "0200", "0999"]);
+
+        let main = &reports[0];
+        assert_eq!(main.schema_version, SCHEMA_VERSION);
+        assert_eq!(main.facility_name.as_deref(), Some("Facility 0150"));
+        assert_eq!(main.devices.total, 2);
+        assert_eq!(main.devices.online, 1);
+        assert_eq!(main.devices.by_type["ap"], 1);
+        assert_eq!(
+            (main.devices.with_lacis_id, main.devices.without_lacis_id),
+            (1, 1)
+        );
+        assert_eq!((main.clients.total, main.clients.online), (2, 1));
+        assert_eq!(main.clients.by_class["phone"], 1);
+        assert_eq!(main.clients.by_class["unknown"], 1);
+        assert_eq!(main.state_changes.len(), 1);
+        assert_eq!(main.state_changes[0].label, "node AP1");
+        let new: Vec<&str> = main
+            .new_devices
+            .iter()
+            .map(|n| n.node_id.as_str())
+            .collect();
+        assert_eq!(new, ["C1"]);
+
+        // Other facilities' changes do not leak in
+        assert!(reports[1].state_changes.is_empty());
+
+        let empty = &reports[2];
+        assert_eq!(empty.facility_name.as_deref(), Some("Empty site"));
+        assert_eq!(empty.devices, DeviceCounts::default());
+        assert_eq!(empty.clients, ClientCounts::default());
+    }
+
+    #[test]
+    fn retry_backoff_doubles_and_caps() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(4), Duration::from_secs(240));
+        assert_eq!(retry_delay(20), Duration::from_secs(960));
+    }
+}
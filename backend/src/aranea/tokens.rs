@@ -0,0 +1,217 @@
+//! Device registration tokens
+//!
+//! Devices registering themselves present `X-Registration-Token` instead of
+//! an admin session. A token is scoped to one facility (fid) and a list of
+//! productTypes, may expire or be limited to a number of uses, and is stored
+//! only as its SHA-256 hash. Tokens are looked up on every request, so a
+//! revocation applies to the next registration.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use super::client::AraneaDeviceRegistration;
+use crate::models::AraneaRegistrationToken;
+use crate::omada::webhook;
+
+/// Header carrying the plaintext token
+pub const TOKEN_HEADER: &str = "x-registration-token";
+/// Marks registration tokens in logs and secret scanners
+const TOKEN_MARKER: &str = "lpgreg_";
+/// Characters of the plaintext kept for display
+const PREFIX_LEN: usize = 12;
+
+pub const DEFAULT_TOKEN_RATE_PER_MINUTE: i32 = 10;
+pub const DEFAULT_IP_RATE_PER_MINUTE: i32 = 20;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Map size at which expired windows are swept
+const RATE_SWEEP_AT: usize = 1024;
+
+/// New plaintext token (shown once) with its hash and display prefix
+pub fn generate() -> (String, String, String) {
+    let token = format!("{}{}", TOKEN_MARKER, webhook::generate_secret());
+    let hash = hash(&token);
+    let prefix = token[..PREFIX_LEN].to_string();
+    (token, hash, prefix)
+}
+
+/// Stored form of a token
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Whether a token may still be used: not revoked, expired or used up (the
+/// use itself is counted atomically by `consume_registration_token`)
+pub fn is_live(token: &AraneaRegistrationToken, now: DateTime<Utc>) -> bool {
+    token.revoked_at.is_none()
+        && token.expires_at.is_none_or(|at| at > now)
+        && token.max_uses.is_none_or(|max| token.use_count < max)
+}
+
+/// Check a registration against the token's scope, filling in the token's
+/// fid when the device sent none. Err names the violation.
+pub fn apply_scope(
+    token: &AraneaRegistrationToken,
+    payload: &mut AraneaDeviceRegistration,
+) -> Result<(), String> {
+    match payload.fid.as_deref().map(str::trim) {
+        None | Some("") => payload.fid = Some(token.fid.clone()),
+        Some(fid) if fid == token.fid => {}
+        Some(fid) => {
+            return Err(format!(
+                "fid {} is outside the token's facility {}",
+                fid, token.fid
+            ))
+        }
+    }
+    if !token
+        .product_types
+        .iter()
+        .any(|allowed| allowed == payload.product_type.trim())
+    {
+        return Err(format!(
+            "productType {} is not allowed for this token",
+            payload.product_type
+        ));
+    }
+    Ok(())
+}
+
+/// Audit actor for registrations made with `token`
+pub fn actor(token: &AraneaRegistrationToken) -> String {
+    format!("token:{}", token.name)
+}
+
+/// Fixed one-minute windows per client IP and per token
+#[derive(Default)]
+pub struct RegistrationLimiter {
+    requests: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RegistrationLimiter {
+    /// Count an attempt from `ip` (checked before the token is looked up)
+    pub fn allow_ip(&self, ip: &str, per_minute: u32) -> bool {
+        self.allow(format!("ip:{}", ip), per_minute)
+    }
+
+    /// Count a registration with token `id`
+    pub fn allow_token(&self, id: i32, per_minute: u32) -> bool {
+        self.allow(format!("token:{}", id), per_minute)
+    }
+
+    fn allow(&self, key: String, per_minute: u32) -> bool {
+        let mut requests = self.requests.lock().unwrap_or_else(|p| p.into_inner());
+        if requests.len() >= RATE_SWEEP_AT {
+            requests.retain(|_, (start, _)| start.elapsed() < RATE_WINDOW);
+        }
+        let entry = requests.entry(key).or_insert((Instant::now(), 0));
+        if entry.0.elapsed() >= RATE_WINDOW {
+            *entry = (Instant::now(), 0);
+        }
+        entry.1 += 1;
+        entry.1 <= per_minute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token() -> AraneaRegistrationToken {
+        AraneaRegistrationToken {
+            id: 1,
+            name: "plant-a".to_string(),
+            fid: "0150".to_string(),
+            product_types: vec!["ar-is04a".to_string(), "ar-is05a".to_string()],
+            token_prefix: "lpgreg_abcde".to_string(),
+            expires_at: None,
+            max_uses: None,
+            use_count: 0,
+            last_used_at: None,
+            last_used_ip: None,
+            revoked_at: None,
+            created_by: "admin@example.com".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn payload(product_type: &str, fid: Option<&str>) -> AraneaDeviceRegistration {
+        AraneaDeviceRegistration {
+            mac: "AA:BB:CC:DD:EE:FF".to_string(),
+            product_type: product_type.to_string(),
+            product_code: "0001".to_string(),
+            device_type: "sensor".to_string(),
+            model: None,
+            fid: fid.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn generated_tokens_are_unique_and_hash_to_their_stored_form() {
+        let (a, hash_a, prefix_a) = generate();
+        let (b, hash_b, _) = generate();
+        assert_ne!(a, b);
+        assert!(a.starts_with(TOKEN_MARKER));
+        assert_eq!(prefix_a.len(), PREFIX_LEN);
+        assert!(a.starts_with(&prefix_a));
+        assert_eq!(hash(&a), hash_a);
+        assert_eq!(hash(&format!(" {}\n", a)), hash_a);
+        assert_ne!(hash_a, hash_b);
+        assert_eq!(hash_a.len(), 64);
+    }
+
+    #[test]
+    fn revoked_expired_and_used_up_tokens_are_not_live() {
+        let now = Utc::now();
+        let mut t = token();
+        assert!(is_live(&t, now));
+        t.expires_at = Some(now - chrono::Duration::minutes(1));
+        assert!(!is_live(&t, now));
+        t.expires_at = Some(now + chrono::Duration::minutes(1));
+        t.max_uses = Some(3);
+        t.use_count = 3;
+        assert!(!is_live(&t, now));
+        t.use_count = 2;
+        assert!(is_live(&t, now));
+        t.revoked_at = Some(now);
+        assert!(!is_live(&t, now));
+    }
+
+    #[test]
+    fn scope_fills_missing_fid_and_rejects_other_facilities() {
+        let token = token();
+        let mut missing = payload("ar-is04a", None);
+        assert!(apply_scope(&token, &mut missing).is_ok());
+        assert_eq!(missing.fid.as_deref(), Some("0150"));
+
+        let mut same = payload("ar-is05a", Some("0150"));
+        assert!(apply_scope(&token, &mut same).is_ok());
+
+        let mut other = payload("ar-is04a", Some("0999"));
+        let err = apply_scope(&token, &mut other).unwrap_err();
+        assert!(err.contains("0999"));
+    }
+
+    #[test]
+    fn scope_rejects_product_types_outside_the_list() {
+        let mut p = payload("ar-is99z", Some("0150"));
+        let err = apply_scope(&token(), &mut p).unwrap_err();
+        assert!(err.contains("ar-is99z"));
+    }
+
+    #[test]
+    fn limiter_counts_ips_and_tokens_separately() {
+        let limiter = RegistrationLimiter::default();
+        assert!(limiter.allow_ip("192.0.2.1", 2));
+        assert!(limiter.allow_ip("192.0.2.1", 2));
+        assert!(!limiter.allow_ip("192.0.2.1", 2));
+        assert!(limiter.allow_ip("192.0.2.2", 2));
+        assert!(limiter.allow_token(1, 1));
+        assert!(!limiter.allow_token(1, 1));
+        assert!(limiter.allow_token(2, 1));
+    }
+}
@@ -0,0 +1,232 @@
+//! Threat feed subscription
+//!
+//! Feeds are configured in the `threat_feeds` setting as a JSON array:
+//! `[{"name": "spamhaus-drop", "url": "https://www.spamhaus.org/drop/drop.txt"}]`
+//!
+//! Each fetch is diffed against the rows currently owned by `feed:<name>`:
+//! new entries are inserted, entries that left the feed are deleted. Rows
+//! created by any other source (manual, import, auto) are never modified.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use super::{parse_line, BlockEntryInput};
+use crate::db::AppState;
+use crate::network_policy::NetworkPolicyStore;
+use crate::notify::DiscordNotifier;
+
+/// Threat feed definition from the `threat_feeds` setting
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThreatFeedConfig {
+    pub name: String,
+    pub url: String,
+    /// Reason recorded for entries that do not carry their own
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Per-feed fetch bookkeeping
+#[derive(Default)]
+struct FeedState {
+    last_fetch: Option<Instant>,
+    consecutive_failures: u32,
+}
+
+/// Background syncer for threat feed subscriptions
+pub struct ThreatFeedSyncer {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    network_policy: Arc<NetworkPolicyStore>,
+    client: reqwest::Client,
+    feeds: Mutex<HashMap<String, FeedState>>,
+}
+
+impl ThreatFeedSyncer {
+    pub fn new(
+        app_state: AppState,
+        notifier: Arc<DiscordNotifier>,
+        network_policy: Arc<NetworkPolicyStore>,
+    ) -> Self {
+        Self {
+            app_state,
+            notifier,
+            network_policy,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            feeds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start the feed loop (checks every 60s which feeds are due)
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting threat feed syncer...");
+
+        let mut interval_timer = interval(Duration::from_secs(60));
+
+        loop {
+            interval_timer.tick().await;
+
+            if let Err(e) = self.sync_due_feeds().await {
+                tracing::error!("Threat feed cycle failed: {}", e);
+            }
+        }
+    }
+
+    /// Fetch every configured feed whose interval has elapsed
+    async fn sync_due_feeds(&self) -> anyhow::Result<()> {
+        let mysql = &self.app_state.mysql;
+
+        let feeds: Vec<ThreatFeedConfig> = match mysql.get_setting("threat_feeds").await? {
+            Some(raw) if !raw.trim().is_empty() => serde_json::from_str(&raw)
+                .map_err(|e| anyhow::anyhow!("Invalid threat_feeds setting: {}", e))?,
+            _ => return Ok(()),
+        };
+        if feeds.is_empty() {
+            return Ok(());
+        }
+
+        let interval_sec = mysql
+            .get_setting_i32("threat_feed_interval_sec", 3600)
+            .await?
+            .max(60) as u64;
+        let failure_threshold = mysql
+            .get_setting_i32("threat_feed_failure_threshold", 3)
+            .await?
+            .max(1) as u32;
+
+        let mut changed = false;
+        let mut states = self.feeds.lock().await;
+
+        for feed in &feeds {
+            let feed_state = states.entry(feed.name.clone()).or_default();
+            let due = feed_state
+                .last_fetch
+                .map(|t| t.elapsed() >= Duration::from_secs(interval_sec))
+                .unwrap_or(true);
+            if !due {
+                continue;
+            }
+            feed_state.last_fetch = Some(Instant::now());
+
+            match self.sync_feed(feed).await {
+                Ok((added, removed)) => {
+                    feed_state.consecutive_failures = 0;
+                    if added > 0 || removed > 0 {
+                        changed = true;
+                        tracing::info!(
+                            "Threat feed {}: {} added, {} removed",
+                            feed.name,
+                            added,
+                            removed
+                        );
+                    }
+                }
+                Err(e) => {
+                    feed_state.consecutive_failures += 1;
+                    tracing::warn!(
+                        "Threat feed {} fetch failed ({} consecutive): {}",
+                        feed.name,
+                        feed_state.consecutive_failures,
+                        e
+                    );
+                    if feed_state.consecutive_failures == failure_threshold {
+                        self.notifier
+                            .notify_threat_feed_failure(
+                                &feed.name,
+                                &feed.url,
+                                feed_state.consecutive_failures,
+                                &e.to_string(),
+                            )
+                            .await;
+                    }
+                }
+            }
+        }
+
+        // Forget state for feeds removed from settings
+        let configured: HashSet<&str> = feeds.iter().map(|f| f.name.as_str()).collect();
+        states.retain(|name, _| configured.contains(name.as_str()));
+        drop(states);
+
+        if changed {
+            self.network_policy.rebuild(mysql).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetch one feed and apply the diff. Returns (added, removed).
+    async fn sync_feed(&self, feed: &ThreatFeedConfig) -> anyhow::Result<(u64, u64)> {
+        let response = self.client.get(&feed.url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        let body = response.text().await?;
+
+        let mut entries: HashMap<String, BlockEntryInput> = HashMap::new();
+        let mut invalid = 0usize;
+        for line in body.lines() {
+            match parse_line(line) {
+                Ok(Some(mut entry)) => {
+                    if entry.reason.is_none() {
+                        entry.reason = feed.reason.clone();
+                    }
+                    entries.entry(entry.ip.clone()).or_insert(entry);
+                }
+                Ok(None) => {}
+                Err(_) => invalid += 1,
+            }
+        }
+        if invalid > 0 {
+            tracing::debug!(
+                "Threat feed {}: skipped {} invalid lines",
+                feed.name,
+                invalid
+            );
+        }
+
+        // An empty parse is treated as a broken fetch rather than "feed cleared"
+        if entries.is_empty() {
+            anyhow::bail!("feed returned no valid entries");
+        }
+
+        let source = format!("feed:{}", feed.name);
+        let existing: HashSet<String> = self
+            .app_state
+            .mysql
+            .list_blocked_ips_by_source(&source)
+            .await?
+            .into_iter()
+            .collect();
+
+        let to_add: Vec<BlockEntryInput> = entries
+            .values()
+            .filter(|e| !existing.contains(&e.ip))
+            .cloned()
+            .collect();
+        let to_remove: Vec<String> = existing
+            .into_iter()
+            .filter(|ip| !entries.contains_key(ip))
+            .collect();
+
+        let added = self
+            .app_state
+            .mysql
+            .insert_blocked_ips_if_absent(&to_add, &source)
+            .await?;
+        let removed = self
+            .app_state
+            .mysql
+            .delete_blocked_ips_by_source(&source, &to_remove)
+            .await?;
+
+        Ok((added, removed))
+    }
+}
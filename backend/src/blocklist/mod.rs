@@ -0,0 +1,389 @@
+//! Blocked IP matching and threat feed ingestion
+//!
+//! Blocked entries (single IPs or CIDR ranges) are compiled into one hash table
+//! per prefix length, so a lookup costs one probe per distinct prefix length
+//! regardless of how many thousands of entries are loaded. The compiled list
+//! is part of the `NetworkPolicy` snapshot (see `crate::network_policy`).
+
+mod feed;
+
+pub use self::feed::ThreatFeedSyncer;
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use ipnetwork::IpNetwork;
+
+use crate::models::BlockedIp;
+
+/// Shortest IPv4 prefix accepted for a block (anything wider is almost certainly a mistake)
+const MIN_PREFIX_V4: u8 = 8;
+/// Shortest IPv6 prefix accepted for a block
+const MIN_PREFIX_V6: u8 = 16;
+
+/// Expiry of a compiled entry (None = permanent)
+type Expiry = Option<DateTime<Utc>>;
+
+/// Compiled in-memory block list
+#[derive(Default)]
+pub struct BlockList {
+    /// (prefix length, network address → expiry), longest prefix first
+    v4: Vec<(u8, HashMap<u32, Expiry>)>,
+    v6: Vec<(u8, HashMap<u128, Expiry>)>,
+    len: usize,
+}
+
+impl BlockList {
+    /// Compile a block list from database rows. Unparseable rows are skipped.
+    pub fn new(entries: &[BlockedIp]) -> Self {
+        let mut v4: HashMap<u8, HashMap<u32, Expiry>> = HashMap::new();
+        let mut v6: HashMap<u8, HashMap<u128, Expiry>> = HashMap::new();
+        let mut len = 0;
+
+        for entry in entries {
+            let network = match parse_network(&entry.ip) {
+                Some(n) => n,
+                None => {
+                    tracing::warn!("Skipping unparseable blocked IP entry: {}", entry.ip);
+                    continue;
+                }
+            };
+
+            match network {
+                IpNetwork::V4(net) => {
+                    let table = v4.entry(net.prefix()).or_default();
+                    merge_expiry(table.entry(u32::from(net.network())), entry.expires_at);
+                }
+                IpNetwork::V6(net) => {
+                    let table = v6.entry(net.prefix()).or_default();
+                    merge_expiry(table.entry(u128::from(net.network())), entry.expires_at);
+                }
+            }
+            len += 1;
+        }
+
+        let mut v4: Vec<_> = v4.into_iter().collect();
+        v4.sort_by_key(|(prefix, _)| std::cmp::Reverse(*prefix));
+        let mut v6: Vec<_> = v6.into_iter().collect();
+        v6.sort_by_key(|(prefix, _)| std::cmp::Reverse(*prefix));
+
+        Self { v4, v6, len }
+    }
+
+    /// Check whether an IP is covered by an entry not expired at `now`
+    pub fn is_blocked_at(&self, ip: IpAddr, now: DateTime<Utc>) -> bool {
+        let active = |expiry: &Expiry| expiry.map(|t| t > now).unwrap_or(true);
+
+        match ip {
+            IpAddr::V4(v4) => {
+                let addr = u32::from(v4);
+                self.v4.iter().any(|(prefix, table)| {
+                    table
+                        .get(&(addr & mask_v4(*prefix)))
+                        .map(active)
+                        .unwrap_or(false)
+                })
+            }
+            IpAddr::V6(v6) => {
+                // IPv4-mapped addresses (::ffff:a.b.c.d) are matched against IPv4 entries
+                if let Some(v4) = v6.to_ipv4_mapped() {
+                    return self.is_blocked_at(IpAddr::V4(v4), now);
+                }
+                let addr = u128::from(v6);
+                self.v6.iter().any(|(prefix, table)| {
+                    table
+                        .get(&(addr & mask_v6(*prefix)))
+                        .map(active)
+                        .unwrap_or(false)
+                })
+            }
+        }
+    }
+
+    /// Check an IP given as a string (unparseable input is never blocked)
+    #[cfg(test)]
+    pub fn is_blocked_str(&self, ip: &str) -> bool {
+        crate::ip::parse_ip(ip)
+            .map(|addr| self.is_blocked_at(addr, Utc::now()))
+            .unwrap_or(false)
+    }
+
+    /// Number of compiled entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Keep the longest-lived expiry when two rows compile to the same network
+fn merge_expiry<K>(slot: std::collections::hash_map::Entry<'_, K, Expiry>, expires_at: Expiry) {
+    use std::collections::hash_map::Entry;
+    match slot {
+        Entry::Vacant(v) => {
+            v.insert(expires_at);
+        }
+        Entry::Occupied(mut o) => {
+            let merged = match (*o.get(), expires_at) {
+                (None, _) | (_, None) => None,
+                (Some(a), Some(b)) => Some(a.max(b)),
+            };
+            o.insert(merged);
+        }
+    }
+}
+
+fn mask_v4(prefix: u8) -> u32 {
+    if prefix == 0 {
+        0
+    } else {
+        u32::MAX << (32 - prefix as u32)
+    }
+}
+
+fn mask_v6(prefix: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        u128::MAX << (128 - prefix as u32)
+    }
+}
+
+/// Parse an IP or CIDR string into a normalized network (host bits
+/// cleared); IPv4-mapped IPv6 (`::ffff:a.b.c.d`, /96 or longer) becomes IPv4
+pub fn parse_network(input: &str) -> Option<IpNetwork> {
+    let network: IpNetwork = input.trim().parse().ok()?;
+    if let IpNetwork::V6(net) = network {
+        if let (Some(v4), true) = (net.ip().to_ipv4_mapped(), net.prefix() >= 96) {
+            return IpNetwork::new(IpAddr::V4(v4), net.prefix() - 96)
+                .ok()
+                .and_then(|n| IpNetwork::new(n.network(), n.prefix()).ok());
+        }
+    }
+    IpNetwork::new(network.network(), network.prefix()).ok()
+}
+
+/// Canonical string form: bare address for single hosts, CIDR otherwise
+pub fn canonical_network(network: &IpNetwork) -> String {
+    let host_prefix = match network {
+        IpNetwork::V4(_) => 32,
+        IpNetwork::V6(_) => 128,
+    };
+    if network.prefix() == host_prefix {
+        network.ip().to_string()
+    } else {
+        format!("{}/{}", network.network(), network.prefix())
+    }
+}
+
+/// Parse a block target (IP or CIDR), rejecting prefixes shorter than
+/// /8 (IPv4) or /16 (IPv6). Every path that creates a block goes through
+/// this: the block list is checked before the LAN and admin allowlist rules,
+/// so a `/0` entry would lock out every client.
+pub fn validate_network(input: &str) -> Result<IpNetwork, String> {
+    let network = parse_network(input).ok_or_else(|| format!("Invalid IP or CIDR: {}", input))?;
+    let min_prefix = match network {
+        IpNetwork::V4(_) => MIN_PREFIX_V4,
+        IpNetwork::V6(_) => MIN_PREFIX_V6,
+    };
+    if network.prefix() < min_prefix {
+        return Err(format!(
+            "Prefix /{} is too broad (minimum /{})",
+            network.prefix(),
+            min_prefix
+        ));
+    }
+    Ok(network)
+}
+
+/// A validated block list entry from an import body or threat feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntryInput {
+    /// Canonical IP or CIDR
+    pub ip: String,
+    pub reason: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Parse one line of a block list.
+///
+/// Accepted forms:
+/// - `1.2.3.4`
+/// - `1.2.3.0/24 ; SBL123` (feed style; text after `;` or `#` becomes the reason)
+/// - `1.2.3.4,reason,2026-12-31T00:00:00Z` (CSV: ip, optional reason, optional expiry)
+///
+/// Returns Ok(None) for blank lines, comments, and the `ip,reason,expires_at` header.
+pub fn parse_line(line: &str) -> Result<Option<BlockEntryInput>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+        return Ok(None);
+    }
+
+    let fields = split_csv_line(line);
+    let first = fields.first().map(|s| s.trim()).unwrap_or("");
+    if first.eq_ignore_ascii_case("ip") {
+        return Ok(None);
+    }
+
+    // Feed style: "<ip> ; comment" or "<ip> # comment"
+    let (ip_part, inline_comment) = match first.find([';', '#', ' ', '\t']) {
+        Some(idx) => {
+            let comment = first[idx..]
+                .trim_start_matches([';', '#', ' ', '\t'])
+                .trim();
+            (&first[..idx], Some(comment).filter(|c| !c.is_empty()))
+        }
+        None => (first, None),
+    };
+
+    let network = validate_network(ip_part)?;
+
+    let reason = fields
+        .get(1)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| inline_comment.map(|s| s.to_string()));
+
+    let expires_at = match fields.get(2).map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        Some(raw) => Some(parse_expiry(raw)?),
+        None => None,
+    };
+
+    Ok(Some(BlockEntryInput {
+        ip: canonical_network(&network),
+        reason,
+        expires_at,
+    }))
+}
+
+/// Parse an expiry as RFC 3339 or a bare `YYYY-MM-DD` date (midnight UTC)
+fn parse_expiry(raw: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc())
+        .ok_or_else(|| format!("Invalid expiry (use RFC 3339 or YYYY-MM-DD): {}", raw))
+}
+
+/// Split a CSV line on commas, honouring double-quoted fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str, expires_at: Expiry) -> BlockedIp {
+        BlockedIp {
+            id: 0,
+            ip: ip.to_string(),
+            reason: None,
+            blocked_by: "manual".to_string(),
+            expires_at,
+            created_at: Utc::now(),
+            context_ref: None,
+        }
+    }
+
+    #[test]
+    fn test_single_ip_and_cidr_match() {
+        let list = BlockList::new(&[entry("203.0.113.7", None), entry("198.51.100.0/24", None)]);
+
+        assert!(list.is_blocked_str("203.0.113.7"));
+        assert!(!list.is_blocked_str("203.0.113.8"));
+        assert!(list.is_blocked_str("198.51.100.1"));
+        assert!(list.is_blocked_str("198.51.100.255"));
+        assert!(!list.is_blocked_str("198.51.101.1"));
+        assert!(list.is_blocked_str("::ffff:198.51.100.9"));
+        assert!(!list.is_blocked_str("not-an-ip"));
+    }
+
+    #[test]
+    fn test_ipv6_cidr_match() {
+        let list = BlockList::new(&[entry("2001:db8::/32", None)]);
+
+        assert!(list.is_blocked_str("2001:db8::1"));
+        assert!(!list.is_blocked_str("2001:db9::1"));
+    }
+
+    #[test]
+    fn test_mixed_notation_entries_and_lookups() {
+        let list = BlockList::new(&[
+            entry("2001:DB8:0:0::5", None),
+            entry("::ffff:192.0.2.1", None),
+            entry("::ffff:198.51.100.0/120", None),
+        ]);
+
+        assert!(list.is_blocked_str("2001:db8::5"));
+        assert!(list.is_blocked_str("[2001:0db8::0005]"));
+        assert!(list.is_blocked_str("192.0.2.1"));
+        assert!(list.is_blocked_str("::ffff:192.0.2.1"));
+        assert!(list.is_blocked_str("198.51.100.77"));
+        assert!(!list.is_blocked_str("198.51.101.77"));
+
+        let mapped = parse_network("::ffff:198.51.100.0/120").unwrap();
+        assert_eq!(canonical_network(&mapped), "198.51.100.0/24");
+        let host = parse_network("2001:DB8:0:0::5").unwrap();
+        assert_eq!(canonical_network(&host), "2001:db8::5");
+    }
+
+    #[test]
+    fn test_expired_entries_ignored() {
+        let past = Some(Utc::now() - chrono::Duration::hours(1));
+        let future = Some(Utc::now() + chrono::Duration::hours(1));
+        let list = BlockList::new(&[entry("192.0.2.1", past), entry("192.0.2.2", future)]);
+
+        assert!(!list.is_blocked_str("192.0.2.1"));
+        assert!(list.is_blocked_str("192.0.2.2"));
+    }
+
+    #[test]
+    fn test_parse_line_forms() {
+        assert_eq!(parse_line("").unwrap(), None);
+        assert_eq!(parse_line("# comment").unwrap(), None);
+        assert_eq!(parse_line("ip,reason,expires_at").unwrap(), None);
+
+        let e = parse_line("1.2.3.0/24 ; SBL123").unwrap().unwrap();
+        assert_eq!(e.ip, "1.2.3.0/24");
+        assert_eq!(e.reason.as_deref(), Some("SBL123"));
+
+        let e = parse_line("1.2.3.77/24").unwrap().unwrap();
+        assert_eq!(e.ip, "1.2.3.0/24");
+
+        let e = parse_line("5.6.7.8,\"scanner, repeated\",2030-01-01")
+            .unwrap()
+            .unwrap();
+        assert_eq!(e.ip, "5.6.7.8");
+        assert_eq!(e.reason.as_deref(), Some("scanner, repeated"));
+        assert!(e.expires_at.is_some());
+    }
+
+    #[test]
+    fn test_parse_line_rejects_invalid() {
+        assert!(parse_line("999.1.1.1").is_err());
+        assert!(parse_line("10.0.0.0/4").is_err());
+        assert!(parse_line("0.0.0.0/0").is_err());
+        assert!(parse_line("::/0").is_err());
+        assert!(parse_line("1.2.3.4,reason,tomorrow").is_err());
+    }
+}
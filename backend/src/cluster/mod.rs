@@ -0,0 +1,476 @@
+//! Multi-instance coordination
+//!
+//! Several instances may serve proxy traffic and the admin API, but singleton
+//! background tasks (DDNS updater, syncers, health checker, restart
+//! scheduler, ...) must only run once. Instances compete for a MongoDB lease
+//! document; the holder renews it every heartbeat and runs the tasks
+//! registered with the [`TaskSupervisor`]. A lease not renewed within the TTL
+//! is taken over by another instance. With `[cluster] enabled = false` the
+//! instance is standalone and always runs the tasks.
+//!
+//! Lease calls are bounded by the heartbeat interval. A leader that cannot
+//! renew stops its tasks one heartbeat plus `max_clock_skew_secs` before the
+//! lease could expire, and a takeover waits `max_clock_skew_secs` past the
+//! expiry, so two instances never run the tasks at once.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use tokio::sync::{Notify, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::config::ClusterConfig;
+use crate::db::mongo::cluster::{ClusterInstanceDoc, LeaderLeaseDoc, LeaseAttempt};
+use crate::db::MongoDb;
+use crate::notify::DiscordNotifier;
+
+type TaskFactory = Box<dyn Fn() -> JoinHandle<()> + Send + Sync>;
+
+/// Starts and stops the singleton background tasks on leadership changes
+#[derive(Default)]
+pub struct TaskSupervisor {
+    tasks: Vec<(&'static str, TaskFactory)>,
+    running: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl TaskSupervisor {
+    /// Register a task; `spawn` is called on every leadership acquisition
+    pub fn register<F>(&mut self, name: &'static str, spawn: F)
+    where
+        F: Fn() -> JoinHandle<()> + Send + Sync + 'static,
+    {
+        self.tasks.push((name, Box::new(spawn)));
+    }
+
+    fn start_all(&mut self) {
+        if !self.running.is_empty() {
+            return;
+        }
+        for (name, spawn) in &self.tasks {
+            self.running.push((name, spawn()));
+        }
+        tracing::info!("Started {} singleton background tasks", self.running.len());
+    }
+
+    /// Abort running tasks; they stop at their next await point
+    fn stop_all(&mut self) {
+        for (name, handle) in self.running.drain(..) {
+            handle.abort();
+            tracing::info!("Stopped background task {}", name);
+        }
+    }
+
+    fn running_names(&self) -> Vec<&'static str> {
+        self.running
+            .iter()
+            .filter(|(_, h)| !h.is_finished())
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct ClusterState {
+    lease: Option<LeaderLeaseDoc>,
+    last_heartbeat: Option<DateTime<Utc>>,
+    last_renewed: Option<Instant>,
+    leader_since: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// GET /api/admin/cluster payload
+#[derive(Debug, Serialize)]
+pub struct ClusterStatus {
+    pub enabled: bool,
+    pub instance_id: String,
+    pub hostname: String,
+    /// "standalone", "leader" or "follower"
+    pub role: &'static str,
+    pub leader: Option<LeaderInfo>,
+    pub leader_since: Option<DateTime<Utc>>,
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub standing_down_for_secs: Option<u64>,
+    pub lease_ttl_secs: u64,
+    pub heartbeat_interval_secs: u64,
+    pub running_tasks: Vec<&'static str>,
+    pub instances: Vec<ClusterInstanceDoc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderInfo {
+    pub instance_id: String,
+    pub hostname: String,
+    pub term: i64,
+    pub acquired_at: String,
+    pub heartbeat_at: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<&LeaderLeaseDoc> for LeaderInfo {
+    fn from(lease: &LeaderLeaseDoc) -> Self {
+        Self {
+            instance_id: lease.holder.clone(),
+            hostname: lease.hostname.clone(),
+            term: lease.term,
+            acquired_at: lease.acquired_at.clone(),
+            heartbeat_at: lease.heartbeat_at.clone(),
+            expires_at: Utc.timestamp_millis_opt(lease.expires_at_ms).single(),
+        }
+    }
+}
+
+/// Leader election state for this instance
+pub struct ClusterCoordinator {
+    config: ClusterConfig,
+    instance_id: String,
+    hostname: String,
+    started_at: String,
+    mongo: Arc<MongoDb>,
+    notifier: Arc<DiscordNotifier>,
+    is_leader: AtomicBool,
+    state: RwLock<ClusterState>,
+    supervisor: Mutex<TaskSupervisor>,
+    standdown_until: Mutex<Option<Instant>>,
+    wake: Notify,
+}
+
+impl ClusterCoordinator {
+    pub fn new(config: ClusterConfig, mongo: Arc<MongoDb>, notifier: Arc<DiscordNotifier>) -> Self {
+        let hostname = local_hostname();
+        let instance_id = if config.instance_id.trim().is_empty() {
+            hostname.clone()
+        } else {
+            config.instance_id.trim().to_string()
+        };
+
+        Self {
+            config,
+            instance_id,
+            hostname,
+            started_at: Utc::now().to_rfc3339(),
+            mongo,
+            notifier,
+            is_leader: AtomicBool::new(false),
+            state: RwLock::new(ClusterState::default()),
+            supervisor: Mutex::new(TaskSupervisor::default()),
+            standdown_until: Mutex::new(None),
+            wake: Notify::new(),
+        }
+    }
+
+    /// Whether this instance currently runs the singleton tasks
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::Relaxed)
+    }
+
+    /// Register a singleton background task (see [`TaskSupervisor::register`])
+    pub fn register_task<F>(&self, name: &'static str, spawn: F)
+    where
+        F: Fn() -> JoinHandle<()> + Send + Sync + 'static,
+    {
+        self.supervisor.lock().unwrap().register(name, spawn);
+    }
+
+    fn role(&self) -> &'static str {
+        if !self.config.enabled {
+            "standalone"
+        } else if self.is_leader() {
+            "leader"
+        } else {
+            "follower"
+        }
+    }
+
+    fn ttl(&self) -> Duration {
+        Duration::from_secs(self.config.lease_ttl_secs.max(2))
+    }
+
+    fn heartbeat_interval(&self) -> Duration {
+        // Renew well within the TTL even if misconfigured
+        let max = self.ttl() / 2;
+        Duration::from_secs(self.config.heartbeat_interval_secs.max(1)).min(max)
+    }
+
+    fn skew_margin(&self) -> Duration {
+        Duration::from_secs(self.config.max_clock_skew_secs)
+    }
+
+    /// How long after its last successful renewal the leader keeps its tasks
+    fn renewal_deadline(&self) -> Duration {
+        renewal_deadline(self.ttl(), self.heartbeat_interval(), self.skew_margin())
+    }
+
+    /// Wait before the next tick: a heartbeat, or less when the leader would
+    /// otherwise pass its renewal deadline asleep
+    async fn next_wait(&self) -> Duration {
+        let heartbeat = self.heartbeat_interval();
+        if !self.is_leader() {
+            return heartbeat;
+        }
+        match self.state.read().await.last_renewed {
+            Some(t) => heartbeat.min(self.renewal_deadline().saturating_sub(t.elapsed())),
+            None => heartbeat,
+        }
+    }
+
+    /// A lease operation, bounded by the heartbeat interval
+    async fn lease_call<T>(
+        &self,
+        call: impl std::future::Future<Output = Result<T, String>>,
+    ) -> Result<T, String> {
+        let limit = self.heartbeat_interval();
+        tokio::time::timeout(limit, call)
+            .await
+            .unwrap_or_else(|_| Err(format!("timed out after {}s", limit.as_secs())))
+    }
+
+    fn standing_down(&self) -> Option<Duration> {
+        let mut until = self.standdown_until.lock().unwrap();
+        match *until {
+            Some(t) if t > Instant::now() => Some(t - Instant::now()),
+            Some(_) => {
+                *until = None;
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Election loop (standalone: start the tasks and return)
+    pub async fn run(self: Arc<Self>) {
+        if !self.config.enabled {
+            self.is_leader.store(true, Ordering::Relaxed);
+            self.state.write().await.leader_since = Some(Utc::now());
+            self.supervisor.lock().unwrap().start_all();
+            return;
+        }
+
+        tracing::info!(
+            "Cluster mode enabled: instance {} (lease TTL {}s, heartbeat {}s)",
+            self.instance_id,
+            self.ttl().as_secs(),
+            self.heartbeat_interval().as_secs()
+        );
+
+        loop {
+            self.tick().await;
+            let wait = self.next_wait().await;
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    async fn tick(&self) {
+        let was_leader = self.is_leader();
+
+        if self.standing_down().is_some() {
+            if was_leader {
+                self.step_down("forced failover").await;
+                if let Err(e) = self
+                    .lease_call(self.mongo.release_leader_lease(&self.instance_id))
+                    .await
+                {
+                    tracing::warn!("Failed to release leader lease: {}", e);
+                }
+            }
+            let lease = self
+                .lease_call(self.mongo.get_leader_lease())
+                .await
+                .ok()
+                .flatten();
+            self.state.write().await.lease = lease;
+            self.heartbeat_instance().await;
+            return;
+        }
+
+        // The lease expiry is counted from about when the request is sent
+        let sent = Instant::now();
+        let attempt = self
+            .lease_call(self.mongo.try_acquire_leader_lease(
+                &self.instance_id,
+                &self.hostname,
+                self.ttl().as_millis() as i64,
+                self.skew_margin().as_millis() as i64,
+            ))
+            .await;
+
+        match attempt {
+            Ok(LeaseAttempt::Renewed(lease)) => {
+                {
+                    let mut state = self.state.write().await;
+                    state.lease = Some(lease.clone());
+                    state.last_renewed = Some(sent);
+                    state.last_error = None;
+                }
+                if !was_leader {
+                    // Restarted while our lease was still live
+                    self.become_leader(&lease, None, false).await;
+                }
+            }
+            Ok(LeaseAttempt::Acquired(lease, previous)) => {
+                {
+                    let mut state = self.state.write().await;
+                    state.lease = Some(lease.clone());
+                    state.last_renewed = Some(sent);
+                    state.last_error = None;
+                }
+                self.become_leader(&lease, previous, true).await;
+            }
+            Ok(LeaseAttempt::HeldBy(lease)) => {
+                {
+                    let mut state = self.state.write().await;
+                    state.lease = Some(lease.clone());
+                    state.last_error = None;
+                }
+                if was_leader {
+                    self.step_down(&format!("lease taken over by {}", lease.holder))
+                        .await;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Leader lease heartbeat failed: {}", e);
+                let expired = {
+                    let mut state = self.state.write().await;
+                    state.last_error = Some(e);
+                    state
+                        .last_renewed
+                        .map(|t| t.elapsed() >= self.renewal_deadline())
+                        .unwrap_or(true)
+                };
+                // Stop before another instance may take over: avoid split-brain
+                if was_leader && expired {
+                    self.step_down("lease could not be renewed").await;
+                }
+            }
+        }
+
+        self.heartbeat_instance().await;
+    }
+
+    async fn become_leader(&self, lease: &LeaderLeaseDoc, previous: Option<String>, notify: bool) {
+        self.is_leader.store(true, Ordering::Relaxed);
+        self.state.write().await.leader_since = Some(Utc::now());
+        tracing::warn!(
+            "Instance {} acquired leadership (term {}, previous: {})",
+            self.instance_id,
+            lease.term,
+            previous.as_deref().unwrap_or("none")
+        );
+        self.supervisor.lock().unwrap().start_all();
+
+        // Only the new leader notifies, so each change is reported once
+        if notify {
+            self.notifier
+                .notify_leadership_change(&self.instance_id, previous.as_deref(), lease.term)
+                .await;
+        }
+    }
+
+    async fn step_down(&self, reason: &str) {
+        self.is_leader.store(false, Ordering::Relaxed);
+        self.state.write().await.leader_since = None;
+        tracing::warn!("Instance {} lost leadership: {}", self.instance_id, reason);
+        self.supervisor.lock().unwrap().stop_all();
+    }
+
+    async fn heartbeat_instance(&self) {
+        let now = Utc::now();
+        let doc = ClusterInstanceDoc {
+            instance_id: self.instance_id.clone(),
+            hostname: self.hostname.clone(),
+            role: self.role().to_string(),
+            started_at: self.started_at.clone(),
+            heartbeat_at: now.to_rfc3339(),
+        };
+        match self.mongo.upsert_cluster_instance(&doc).await {
+            Ok(()) => self.state.write().await.last_heartbeat = Some(now),
+            Err(e) => tracing::warn!("Cluster instance heartbeat failed: {}", e),
+        }
+    }
+
+    /// Hand leadership to another instance: stop the tasks, release the lease
+    /// and do not compete for it during `standdown`.
+    pub fn force_failover(&self, standdown: Duration) -> Result<(), String> {
+        if !self.config.enabled {
+            return Err("Cluster mode is not enabled".to_string());
+        }
+        if !self.is_leader() {
+            return Err(format!("Instance {} is not the leader", self.instance_id));
+        }
+        *self.standdown_until.lock().unwrap() = Some(Instant::now() + standdown);
+        self.wake.notify_one();
+        tracing::warn!(
+            "Forced failover requested: {} stands down for {}s",
+            self.instance_id,
+            standdown.as_secs()
+        );
+        Ok(())
+    }
+
+    /// Default stand-down after a forced failover (long enough for a takeover)
+    pub fn default_standdown(&self) -> Duration {
+        self.ttl() * 3
+    }
+
+    pub async fn status(&self) -> ClusterStatus {
+        let instances = if self.config.enabled {
+            self.mongo
+                .list_cluster_instances()
+                .await
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let state = self.state.read().await;
+
+        ClusterStatus {
+            enabled: self.config.enabled,
+            instance_id: self.instance_id.clone(),
+            hostname: self.hostname.clone(),
+            role: self.role(),
+            leader: state.lease.as_ref().map(LeaderInfo::from),
+            leader_since: state.leader_since,
+            last_heartbeat: state.last_heartbeat,
+            last_error: state.last_error.clone(),
+            standing_down_for_secs: self.standing_down().map(|d| d.as_secs()),
+            lease_ttl_secs: self.ttl().as_secs(),
+            heartbeat_interval_secs: self.heartbeat_interval().as_secs(),
+            running_tasks: self.supervisor.lock().unwrap().running_names(),
+            instances,
+        }
+    }
+}
+
+fn local_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "lpg".to_string())
+}
+
+/// Renewal deadline for a lease `ttl`: one heartbeat (the last renewal may
+/// time out) and the clock skew margin before the lease could expire
+fn renewal_deadline(ttl: Duration, heartbeat: Duration, skew: Duration) -> Duration {
+    ttl.saturating_sub(heartbeat + skew)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leader_stops_before_a_takeover_is_possible() {
+        let secs = Duration::from_secs;
+        assert_eq!(renewal_deadline(secs(30), secs(10), secs(2)), secs(18));
+        // Misconfigured margins stop the tasks on the first failed renewal
+        assert_eq!(renewal_deadline(secs(2), secs(1), secs(5)), Duration::ZERO);
+    }
+}
@@ -11,6 +11,20 @@ pub struct Config {
     pub auth: AuthConfig,
     #[serde(default)]
     pub aranea: AraneaConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub migrations: MigrationsConfig,
+    #[serde(default)]
+    pub dns: DnsConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub frontend: FrontendConfig,
+    #[serde(default)]
+    pub acme: AcmeConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,12 +35,60 @@ pub struct ServerConfig {
     pub port: u16,
     #[serde(default)]
     pub geoip_db_path: Option<String>,
+    /// Memory bound of the per-route response cache (see proxy::cache)
+    #[serde(default = "default_response_cache_max_mb")]
+    pub response_cache_max_mb: usize,
+    /// Request body limit in MB while the `proxy_max_request_body_mb` setting
+    /// is empty; routes may set their own (see proxy::limits)
+    #[serde(default = "default_max_body_mb")]
+    pub max_body_mb: usize,
+    /// Built-in HTTPS listener (see `crate::tls`)
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+/// `[server.tls]`: HTTPS served by LPG itself on a second port
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_tls_port")]
+    pub port: u16,
+    /// Certificates selected by SNI; the first one answers clients that
+    /// send no (or an unknown) server name
+    #[serde(default)]
+    pub certificates: Vec<TlsCertificateConfig>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_tls_port(),
+            certificates: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsCertificateConfig {
+    /// PEM certificate chain (leaf first)
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+    /// Server names served with this certificate (`*.example.com` allowed);
+    /// empty = the names in the certificate
+    #[serde(default)]
+    pub hosts: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DatabaseConfig {
     pub mysql_url: Option<String>,
     pub mongodb_url: Option<String>,
+    /// Connections reserved for syncer ingestion writes (separate pool)
+    #[serde(default = "default_mongodb_ingest_pool_size")]
+    pub mongodb_ingest_pool_size: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -50,6 +112,10 @@ pub struct AuthConfig {
     pub lacisoath_required_fid: String,
     #[serde(default)]
     pub internet_access_enabled: bool,
+    /// Refuse to start with the default jwt_secret while internet access is
+    /// enabled (see `crate::jwt_keys`)
+    #[serde(default)]
+    pub refuse_default_jwt_secret: bool,
     // OAuth 2.0 (mobes 2.0 external auth)
     #[serde(default)]
     pub lacisoath_client_id: String,
@@ -73,6 +139,7 @@ impl Default for AuthConfig {
             lacisoath_required_permission: default_required_permission(),
             lacisoath_required_fid: default_required_fid(),
             internet_access_enabled: false,
+            refuse_default_jwt_secret: false,
             lacisoath_client_id: String::new(),
             lacisoath_client_secret: String::new(),
             lacisoath_auth_url: default_lacisoath_auth_url(),
@@ -83,7 +150,7 @@ impl Default for AuthConfig {
 }
 
 fn default_jwt_secret() -> String {
-    "CHANGE_ME_IN_PRODUCTION".to_string()
+    crate::jwt_keys::DEFAULT_JWT_SECRET.to_string()
 }
 
 fn default_session_hours() -> u64 {
@@ -110,6 +177,10 @@ fn default_lacisoath_token_url() -> String {
     "https://asia-northeast1-mobesorder.cloudfunctions.net/externalAuthToken".to_string()
 }
 
+fn default_mongodb_ingest_pool_size() -> u32 {
+    crate::db::mongo::ingest_writes::DEFAULT_INGEST_POOL_SIZE
+}
+
 fn default_host() -> String {
     "0.0.0.0".to_string()
 }
@@ -118,7 +189,19 @@ fn default_port() -> u16 {
     8081
 }
 
-#[derive(Debug, Clone, Deserialize)]
+fn default_response_cache_max_mb() -> usize {
+    crate::proxy::cache::DEFAULT_MAX_MB
+}
+
+fn default_max_body_mb() -> usize {
+    crate::proxy::limits::DEFAULT_MAX_REQUEST_BODY_MB
+}
+
+fn default_tls_port() -> u16 {
+    8443
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct AraneaConfig {
     #[serde(default)]
     pub tid: String,
@@ -132,6 +215,18 @@ pub struct AraneaConfig {
     pub device_gate_url: String,
     #[serde(default = "default_aranea_device_state_url")]
     pub device_state_url: String,
+    /// Shared HMAC key for X-Lacis-Signature
+    #[serde(default)]
+    pub signing_key: String,
+    /// Sign outbound requests (off = unsigned, for the migration period)
+    #[serde(default)]
+    pub signing_enabled: bool,
+    /// Facility list endpoint (empty = Omada site fids are not validated)
+    #[serde(default)]
+    pub facility_list_url: String,
+    /// Facility report endpoint (empty = reports are stored, not pushed)
+    #[serde(default)]
+    pub facility_report_url: String,
 }
 
 impl Default for AraneaConfig {
@@ -143,6 +238,10 @@ impl Default for AraneaConfig {
             tenant_cic: String::new(),
             device_gate_url: default_aranea_device_gate_url(),
             device_state_url: default_aranea_device_state_url(),
+            signing_key: String::new(),
+            signing_enabled: false,
+            facility_list_url: String::new(),
+            facility_report_url: String::new(),
         }
     }
 }
@@ -155,6 +254,318 @@ fn default_aranea_device_state_url() -> String {
     "https://asia-northeast1-mobesorder.cloudfunctions.net/deviceStateReport".to_string()
 }
 
+/// Multi-instance leader election (MongoDB lease)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClusterConfig {
+    /// Off = single instance, background tasks always run
+    #[serde(default)]
+    pub enabled: bool,
+    /// Stable instance name (default: hostname)
+    #[serde(default)]
+    pub instance_id: String,
+    /// A lease not renewed for this long may be taken over
+    #[serde(default = "default_cluster_lease_ttl")]
+    pub lease_ttl_secs: u64,
+    #[serde(default = "default_cluster_heartbeat")]
+    pub heartbeat_interval_secs: u64,
+    /// Clock difference tolerated between instances (lease expiry is
+    /// compared against each instance's own clock)
+    #[serde(default = "default_cluster_clock_skew")]
+    pub max_clock_skew_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            instance_id: String::new(),
+            lease_ttl_secs: default_cluster_lease_ttl(),
+            heartbeat_interval_secs: default_cluster_heartbeat(),
+            max_clock_skew_secs: default_cluster_clock_skew(),
+        }
+    }
+}
+
+fn default_cluster_lease_ttl() -> u64 {
+    30
+}
+
+fn default_cluster_heartbeat() -> u64 {
+    10
+}
+
+fn default_cluster_clock_skew() -> u64 {
+    2
+}
+
+/// Startup migrations (see `crate::migrations`)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MigrationsConfig {
+    /// Start anyway when a non-critical migration fails (critical ones always halt)
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+/// Built-in LAN DNS responder (see `crate::local_dns`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct DnsConfig {
+    /// Off by default; only meant for LAN clients
+    #[serde(default)]
+    pub enabled: bool,
+    /// UDP and TCP listen address
+    #[serde(default = "default_dns_listen")]
+    pub listen: String,
+    /// Resolver for everything that is not overridden
+    #[serde(default = "default_dns_upstream")]
+    pub upstream: String,
+    /// Answer for overrides without a target (default: the address used
+    /// to reach the upstream resolver)
+    #[serde(default)]
+    pub lan_ip: Option<String>,
+    /// Client networks served (IP or CIDR); empty = private ranges and loopback
+    #[serde(default)]
+    pub allowed_networks: Vec<String>,
+    /// TTL of override answers
+    #[serde(default = "default_dns_ttl")]
+    pub ttl_secs: u32,
+    #[serde(default = "default_dns_upstream_timeout")]
+    pub upstream_timeout_ms: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_dns_listen(),
+            upstream: default_dns_upstream(),
+            lan_ip: None,
+            allowed_networks: Vec::new(),
+            ttl_secs: default_dns_ttl(),
+            upstream_timeout_ms: default_dns_upstream_timeout(),
+        }
+    }
+}
+
+fn default_dns_listen() -> String {
+    "0.0.0.0:53".to_string()
+}
+
+fn default_dns_upstream() -> String {
+    "1.1.1.1:53".to_string()
+}
+
+fn default_dns_ttl() -> u32 {
+    60
+}
+
+fn default_dns_upstream_timeout() -> u64 {
+    2000
+}
+
+/// Outbound notification queue (see `crate::notify::queue`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifyConfig {
+    /// Queued notifications kept; when full the oldest lowest-severity one goes
+    #[serde(default = "default_notify_queue_capacity")]
+    pub queue_capacity: usize,
+    /// Attempts per notification on network errors and 5xx
+    #[serde(default = "default_notify_max_attempts")]
+    pub max_attempts: u32,
+    /// Discord webhooks (Discord's per-webhook limits)
+    #[serde(default = "default_notify_discord_burst")]
+    pub discord_burst: u32,
+    #[serde(default = "default_notify_discord_per_minute")]
+    pub discord_per_minute: u32,
+    /// Any other webhook URL
+    #[serde(default = "default_notify_webhook_burst")]
+    pub webhook_burst: u32,
+    #[serde(default = "default_notify_webhook_per_minute")]
+    pub webhook_per_minute: u32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_notify_queue_capacity(),
+            max_attempts: default_notify_max_attempts(),
+            discord_burst: default_notify_discord_burst(),
+            discord_per_minute: default_notify_discord_per_minute(),
+            webhook_burst: default_notify_webhook_burst(),
+            webhook_per_minute: default_notify_webhook_per_minute(),
+        }
+    }
+}
+
+fn default_notify_queue_capacity() -> usize {
+    500
+}
+
+fn default_notify_max_attempts() -> u32 {
+    5
+}
+
+fn default_notify_discord_burst() -> u32 {
+    5
+}
+
+fn default_notify_discord_per_minute() -> u32 {
+    30
+}
+
+fn default_notify_webhook_burst() -> u32 {
+    10
+}
+
+fn default_notify_webhook_per_minute() -> u32 {
+    60
+}
+
+/// Admin frontend served by LPG itself (see `crate::frontend`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrontendConfig {
+    /// "disabled" (separate frontend server, default), "external" (static
+    /// export from `dist_dir`) or "embedded" (built with `embed-frontend`)
+    #[serde(default = "default_frontend_mode")]
+    pub mode: String,
+    /// Path prefix of the admin UI (Next.js basePath)
+    #[serde(default = "default_frontend_base_path")]
+    pub base_path: String,
+    /// Static export directory for mode "external"
+    #[serde(default = "default_frontend_dist_dir")]
+    pub dist_dir: String,
+    /// Host names the admin UI is served on; empty = any host
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+/// ACME certificates for DDNS hostnames (see `crate::acme`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct AcmeConfig {
+    /// ACME directory (Let's Encrypt production by default)
+    #[serde(default = "default_acme_directory_url")]
+    pub directory_url: String,
+    /// Account contact (expiry mail from the CA)
+    #[serde(default)]
+    pub contact_email: Option<String>,
+    /// Also write `<cert_dir>/<hostname>/fullchain.pem` and `privkey.pem`
+    /// (certificates are always kept in MySQL)
+    #[serde(default)]
+    pub cert_dir: Option<String>,
+    /// Renew this many days before expiry
+    #[serde(default = "default_acme_renew_before_days")]
+    pub renew_before_days: i64,
+}
+
+impl Default for AcmeConfig {
+    fn default() -> Self {
+        Self {
+            directory_url: default_acme_directory_url(),
+            contact_email: None,
+            cert_dir: None,
+            renew_before_days: default_acme_renew_before_days(),
+        }
+    }
+}
+
+fn default_acme_directory_url() -> String {
+    "https://acme-v02.api.letsencrypt.org/directory".to_string()
+}
+
+fn default_acme_renew_before_days() -> i64 {
+    30
+}
+
+impl Default for FrontendConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_frontend_mode(),
+            base_path: default_frontend_base_path(),
+            dist_dir: default_frontend_dist_dir(),
+            hosts: Vec::new(),
+        }
+    }
+}
+
+fn default_frontend_mode() -> String {
+    "disabled".to_string()
+}
+
+fn default_frontend_base_path() -> String {
+    "/LacisProxyGateway2".to_string()
+}
+
+fn default_frontend_dist_dir() -> String {
+    "../frontend/out".to_string()
+}
+
+/// Log output (format, file rotation, levels)
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    /// "pretty" (human-readable, default) or "json" (one object per line)
+    #[serde(default = "default_log_format")]
+    pub format: String,
+    /// Base filter directives; RUST_LOG overrides when set
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Per-module overrides, e.g. `"lacis_proxy_gateway::omada" = "debug"`
+    #[serde(default)]
+    pub modules: std::collections::BTreeMap<String, String>,
+    /// Also write to stdout (set false when only the file is wanted)
+    #[serde(default = "default_true")]
+    pub stdout: bool,
+    /// Log file path; no file output when unset
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// "daily", "hourly", "never" or "size"
+    #[serde(default = "default_log_rotation")]
+    pub rotation: String,
+    /// Size threshold for `rotation = "size"`
+    #[serde(default = "default_log_max_size_mb")]
+    pub max_size_mb: u64,
+    /// Rotated files to keep (0 = keep all)
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: default_log_format(),
+            level: default_log_level(),
+            modules: Default::default(),
+            stdout: true,
+            file_path: None,
+            rotation: default_log_rotation(),
+            max_size_mb: default_log_max_size_mb(),
+            max_files: default_log_max_files(),
+        }
+    }
+}
+
+fn default_log_format() -> String {
+    "pretty".to_string()
+}
+
+fn default_log_level() -> String {
+    "lacis_proxy_gateway=info,tower_http=debug".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_log_rotation() -> String {
+    "daily".to_string()
+}
+
+fn default_log_max_size_mb() -> u64 {
+    100
+}
+
+fn default_log_max_files() -> usize {
+    7
+}
+
 impl Config {
     pub fn load() -> anyhow::Result<Self> {
         let settings = config::Config::builder()
@@ -167,14 +578,25 @@ impl Config {
                 host: default_host(),
                 port: default_port(),
                 geoip_db_path: None,
+                response_cache_max_mb: default_response_cache_max_mb(),
+                max_body_mb: default_max_body_mb(),
+                tls: TlsConfig::default(),
             },
             database: DatabaseConfig {
                 mysql_url: None,
                 mongodb_url: None,
+                mongodb_ingest_pool_size: default_mongodb_ingest_pool_size(),
             },
             discord: None,
             auth: AuthConfig::default(),
             aranea: AraneaConfig::default(),
+            cluster: ClusterConfig::default(),
+            logging: LoggingConfig::default(),
+            migrations: MigrationsConfig::default(),
+            dns: DnsConfig::default(),
+            notify: NotifyConfig::default(),
+            frontend: FrontendConfig::default(),
+            acme: AcmeConfig::default(),
         });
 
         Ok(config)
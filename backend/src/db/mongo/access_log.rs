@@ -3,16 +3,26 @@
 use chrono::Utc;
 use futures::TryStreamExt;
 use mongodb::bson::{self, doc};
-use mongodb::options::FindOptions;
+use mongodb::options::{AggregateOptions, FindOptions};
+use mongodb::{Cursor, IndexModel};
 
 use crate::error::AppError;
 use crate::models::{
-    AccessLog, AccessLogSearchQuery, AccessLogSearchResult, ErrorSummary, HealthCheck, HourlyStat,
-    TopEntry,
+    AccessLog, AccessLogDeleteFilter, AccessLogSearchQuery, AccessLogSearchResult,
+    CustomFieldSummary, CustomFieldValueStat, ErrorSummary, HealthCheck, HourlyStat,
+    ProtocolSummary, TopEntry,
 };
+use crate::proxy::log_fields::is_valid_field_name;
 
 use super::MongoDb;
 
+/// Documents fetched per round trip by export cursors
+const EXPORT_BATCH_SIZE: u32 = 1000;
+
+/// Server-side bound of the recent route errors lookup (the health checker
+/// gives up on the failure context after 2s anyway)
+const ROUTE_ERRORS_MAX_TIME: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Build MongoDB filter conditions for IP exclusion
 fn build_ip_exclusion_conditions(
     exclude_ips: &Option<String>,
@@ -20,27 +30,28 @@ fn build_ip_exclusion_conditions(
 ) -> Vec<bson::Document> {
     let mut conditions = Vec::new();
 
-    // 特定IPの除外 ($nin)
+    // 特定IPの除外 ($nin、表記ゆれ・IPv4射影アドレスも含む)
     if let Some(ref ips) = exclude_ips {
-        let ip_list: Vec<&str> = ips
+        let bson_list: Vec<bson::Bson> = ips
             .split(',')
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
+            .flat_map(crate::ip::ip_variants)
+            .map(bson::Bson::String)
             .collect();
-        if !ip_list.is_empty() {
-            let bson_list: Vec<bson::Bson> = ip_list
-                .iter()
-                .map(|s| bson::Bson::String(s.to_string()))
-                .collect();
+        if !bson_list.is_empty() {
             conditions.push(doc! { "ip": { "$nin": bson_list } });
         }
     }
 
-    // LANアクセス除外 ($not $regex)
+    // LANアクセス除外 ($not $regex、IPv6 ULA/リンクローカル/ループバック含む)
     if exclude_lan == &Some(true) {
         conditions.push(doc! {
             "ip": {
-                "$not": { "$regex": "^(10\\.|172\\.(1[6-9]|2[0-9]|3[01])\\.|192\\.168\\.|127\\.)" }
+                "$not": bson::Regex {
+                    pattern: crate::ip::LAN_IP_REGEX.to_string(),
+                    options: "i".to_string(),
+                }
             }
         });
     }
@@ -78,12 +89,36 @@ impl MongoDb {
     pub async fn log_access(&self, log: &AccessLog) -> Result<(), AppError> {
         let collection = self.db.collection::<bson::Document>("access_logs");
 
-        let doc = bson::to_document(log).map_err(|e| AppError::InternalError(e.to_string()))?;
+        let doc = bson::to_document(log).map_err(|e| AppError::database(e.to_string()))?;
 
         collection
             .insert_one(doc, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Insert several access logs in one round trip
+    pub async fn log_access_batch(&self, logs: &[AccessLog]) -> Result<(), AppError> {
+        if logs.is_empty() {
+            return Ok(());
+        }
+        let collection = self.db.collection::<bson::Document>("access_logs");
+
+        let docs = logs
+            .iter()
+            .map(bson::to_document)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::database(e.to_string()))?;
+        let options = mongodb::options::InsertManyOptions::builder()
+            .ordered(false)
+            .build();
+
+        collection
+            .insert_many(docs, options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         Ok(())
     }
@@ -110,13 +145,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(filter, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut logs = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(log) = bson::from_document(doc) {
                 logs.push(log);
@@ -142,13 +177,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! { "path": { "$regex": path } }, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut logs = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(log) = bson::from_document(doc) {
                 logs.push(log);
@@ -172,15 +207,18 @@ impl MongoDb {
             .build();
 
         let mut cursor = collection
-            .find(doc! { "ip": ip }, options)
+            .find(
+                doc! { "ip": { "$in": crate::ip::ip_variants(ip) } },
+                options,
+            )
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut logs = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(log) = bson::from_document(doc) {
                 logs.push(log);
@@ -212,11 +250,38 @@ impl MongoDb {
         let count = collection
             .count_documents(filter, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         Ok(count)
     }
 
+    /// Count today's access log entries for a route carrying an upstream_error marker
+    pub async fn count_route_marker_today(
+        &self,
+        route_id: i32,
+        marker: &str,
+    ) -> Result<u64, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+
+        let today_start = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+
+        collection
+            .count_documents(
+                doc! {
+                    "timestamp": { "$gte": today_start.to_rfc3339() },
+                    "route_id": route_id,
+                    "upstream_error": marker,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| AppError::database(e.to_string()))
+    }
+
     /// Get request count by status code for today
     pub async fn get_today_status_distribution(
         &self,
@@ -243,13 +308,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut distribution = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             let status = doc.get_i32("_id").unwrap_or(0);
             let count = bson_to_u64(&doc, "count");
@@ -265,12 +330,12 @@ impl MongoDb {
     pub async fn save_health_check(&self, check: &HealthCheck) -> Result<(), AppError> {
         let collection = self.db.collection::<bson::Document>("health_checks");
 
-        let doc = bson::to_document(check).map_err(|e| AppError::InternalError(e.to_string()))?;
+        let doc = bson::to_document(check).map_err(|e| AppError::database(e.to_string()))?;
 
         collection
             .insert_one(doc, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         Ok(())
     }
@@ -291,13 +356,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! { "route_id": route_id }, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut checks = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(check) = bson::from_document(doc) {
                 checks.push(check);
@@ -323,13 +388,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut checks = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(check) = bson::from_document(doc) {
                 checks.push(check);
@@ -351,13 +416,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! { "route_id": route_id }, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut count = 0u32;
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(healthy) = doc.get_bool("healthy") {
                 if !healthy {
@@ -371,6 +436,66 @@ impl MongoDb {
         Ok(count)
     }
 
+    /// Get the most recent 5xx access log entries for a route (newest first).
+    /// Backed by the (route_id, timestamp, status) index (startup migration
+    /// 043_access_log_route_error_order): the newest entries of the route are
+    /// walked in index order without an in-memory sort; keep `limit` small.
+    pub async fn get_recent_route_errors(
+        &self,
+        route_id: i32,
+        limit: i64,
+    ) -> Result<Vec<AccessLog>, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .limit(limit)
+            .max_time(ROUTE_ERRORS_MAX_TIME)
+            .build();
+
+        let mut cursor = collection
+            .find(
+                doc! { "route_id": route_id, "status": { "$gte": 500 } },
+                options,
+            )
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+
+        let mut logs = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+        {
+            if let Ok(log) = bson::from_document(doc) {
+                logs.push(log);
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// Get the timestamp of the last successful health check for a route
+    pub async fn get_last_healthy_check_time(
+        &self,
+        route_id: i32,
+    ) -> Result<Option<chrono::DateTime<Utc>>, AppError> {
+        let collection = self.db.collection::<bson::Document>("health_checks");
+
+        let options = mongodb::options::FindOneOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .build();
+
+        let doc = collection
+            .find_one(doc! { "route_id": route_id, "healthy": true }, options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+
+        Ok(doc
+            .and_then(|d| bson::from_document::<HealthCheck>(d).ok())
+            .map(|check| check.timestamp))
+    }
+
     /// Get statistics for a specific route path
     pub async fn get_route_stats(&self, path: &str) -> Result<crate::models::RouteStats, AppError> {
         use chrono::Duration;
@@ -450,13 +575,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut avg_response_time_ms = 0.0;
         if let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(avg) = doc.get_f64("avg_response_time") {
                 avg_response_time_ms = avg;
@@ -484,7 +609,7 @@ impl MongoDb {
         let total = collection
             .count_documents(filter.clone(), None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         // Get paginated results
         let options = FindOptions::builder()
@@ -496,13 +621,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(filter, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut logs = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(log) = bson::from_document(doc) {
                 logs.push(log);
@@ -512,6 +637,405 @@ impl MongoDb {
         Ok(AccessLogSearchResult { logs, total })
     }
 
+    /// Cursor over the logs matching `query` (newest first, at most `limit`;
+    /// offset ignored) for streaming exports
+    pub async fn access_log_export_cursor(
+        &self,
+        query: &AccessLogSearchQuery,
+        limit: i64,
+    ) -> Result<Cursor<bson::Document>, AppError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .limit(limit)
+            .batch_size(EXPORT_BATCH_SIZE)
+            .build();
+
+        self.db
+            .collection::<bson::Document>("access_logs")
+            .find(Self::build_access_log_filter(query), options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))
+    }
+
+    /// Filter document for a bulk delete (search filter with a literal path prefix)
+    pub fn build_access_log_delete_filter(filter: &AccessLogDeleteFilter) -> bson::Document {
+        let query = AccessLogSearchQuery {
+            from: filter.from,
+            to: filter.to,
+            method: filter.method.clone(),
+            status_min: filter.status_min,
+            status_max: filter.status_max,
+            ip: filter.ip.as_ref().map(|ip| ip.trim().to_string()),
+            path: filter
+                .path
+                .as_deref()
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| format!("^{}", regex::escape(p))),
+            limit: 0,
+            offset: 0,
+            exclude_ips: None,
+            exclude_lan: None,
+            http_version: None,
+            custom_field: None,
+            custom_value: None,
+        };
+        Self::build_access_log_filter(&query)
+    }
+
+    /// Per-route time index used by the custom field summary (run by startup
+    /// migration 023_route_log_fields)
+    pub async fn ensure_access_log_route_index(&self) -> Result<(), String> {
+        self.db
+            .collection::<bson::Document>("access_logs")
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "route_id": 1, "timestamp": -1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create access_logs route index: {}", e))?;
+        Ok(())
+    }
+
+    /// Route / status / time index of the recent-errors lookup (run by startup
+    /// migration 041_access_log_route_errors; reordered by 043)
+    pub async fn ensure_access_log_route_error_index(&self) -> Result<(), String> {
+        self.db
+            .collection::<bson::Document>("access_logs")
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "route_id": 1, "status": 1, "timestamp": -1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create access_logs route error index: {}", e))?;
+        Ok(())
+    }
+
+    /// Route / time / status index of the recent-errors lookup, in equality /
+    /// sort / range order, replacing the route / status / time index (run by
+    /// startup migration 043_access_log_route_error_order); true when the
+    /// old index was dropped
+    pub async fn reorder_access_log_route_error_index(&self) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+        collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "route_id": 1, "timestamp": -1, "status": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create access_logs route error index: {}", e))?;
+        let legacy = "route_id_1_status_1_timestamp_-1";
+        if !collection
+            .list_index_names()
+            .await
+            .map_err(|e| format!("List access_logs indexes: {}", e))?
+            .iter()
+            .any(|name| name == legacy)
+        {
+            return Ok(false);
+        }
+        collection
+            .drop_index(legacy, None)
+            .await
+            .map_err(|e| format!("Drop access_logs index {}: {}", legacy, e))?;
+        Ok(true)
+    }
+
+    /// Requests of a route in `from..=to` grouped by one custom log field,
+    /// busiest values first. Only the top `max_values` values are returned;
+    /// the rest are summed into `other_requests`. `field` must already be a
+    /// valid field name (it becomes part of a document path).
+    pub async fn custom_field_summary(
+        &self,
+        route_id: i32,
+        field: &str,
+        from: chrono::DateTime<Utc>,
+        to: chrono::DateTime<Utc>,
+        max_values: i64,
+    ) -> Result<CustomFieldSummary, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+        let path = format!("custom_fields.{}", field);
+        let pipeline = vec![
+            doc! {
+                "$match": {
+                    "route_id": route_id,
+                    "timestamp": { "$gte": from.to_rfc3339(), "$lte": to.to_rfc3339() },
+                    &path: { "$type": "string" },
+                }
+            },
+            doc! {
+                "$group": {
+                    "_id": format!("${}", path),
+                    "requests": { "$sum": 1 },
+                    "errors": {
+                        "$sum": { "$cond": [{ "$gte": ["$status", 400] }, 1, 0] }
+                    },
+                    "avg_response_time": { "$avg": "$response_time_ms" },
+                }
+            },
+            doc! {
+                "$facet": {
+                    "values": [
+                        { "$sort": { "requests": -1, "_id": 1 } },
+                        { "$limit": max_values },
+                    ],
+                    "totals": [
+                        {
+                            "$group": {
+                                "_id": null,
+                                "distinct": { "$sum": 1 },
+                                "requests": { "$sum": "$requests" },
+                            }
+                        },
+                    ],
+                }
+            },
+        ];
+        let options = AggregateOptions::builder()
+            .allow_disk_use(true)
+            .max_time(std::time::Duration::from_secs(15))
+            .build();
+
+        let mut cursor = collection
+            .aggregate(pipeline, options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        let result = cursor
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+            .unwrap_or_default();
+
+        let values: Vec<CustomFieldValueStat> = result
+            .get_array("values")
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_document())
+                    .map(|doc| {
+                        let requests = bson_to_u64(doc, "requests");
+                        let errors = bson_to_u64(doc, "errors");
+                        CustomFieldValueStat {
+                            value: doc.get_str("_id").unwrap_or_default().to_string(),
+                            requests,
+                            errors,
+                            error_rate_percent: if requests > 0 {
+                                errors as f64 / requests as f64 * 100.0
+                            } else {
+                                0.0
+                            },
+                            avg_response_time_ms: doc.get_f64("avg_response_time").unwrap_or(0.0),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let totals = result
+            .get_array("totals")
+            .ok()
+            .and_then(|t| t.first())
+            .and_then(|t| t.as_document())
+            .cloned()
+            .unwrap_or_default();
+        let total_requests = bson_to_u64(&totals, "requests");
+        let distinct_values = bson_to_u64(&totals, "distinct");
+        let listed: u64 = values.iter().map(|v| v.requests).sum();
+
+        Ok(CustomFieldSummary {
+            route_id,
+            field: field.to_string(),
+            from: from.to_rfc3339(),
+            to: to.to_rfc3339(),
+            total_requests,
+            distinct_values,
+            truncated: distinct_values > values.len() as u64,
+            other_requests: total_requests.saturating_sub(listed),
+            values,
+        })
+    }
+
+    /// Get one access log entry by its document id (None for unknown or
+    /// malformed ids)
+    pub async fn get_access_log(&self, id: &str) -> Result<Option<AccessLog>, AppError> {
+        let Ok(oid) = bson::oid::ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let doc = self
+            .db
+            .collection::<bson::Document>("access_logs")
+            .find_one(doc! { "_id": oid }, None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        Ok(doc.and_then(|d| bson::from_document(d).ok()))
+    }
+
+    /// Requests and 5xx responses of a route since `from`, for one target
+    pub async fn count_route_target_requests(
+        &self,
+        route_id: i32,
+        target: &str,
+        from: chrono::DateTime<Utc>,
+    ) -> Result<(u64, u64), AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+        let pipeline = vec![
+            doc! {
+                "$match": {
+                    "route_id": route_id,
+                    "target": target,
+                    "timestamp": { "$gte": from.to_rfc3339() },
+                }
+            },
+            doc! {
+                "$group": {
+                    "_id": null,
+                    "requests": { "$sum": 1 },
+                    "errors": {
+                        "$sum": {
+                            "$cond": [{ "$gte": ["$status", 500] }, 1, 0]
+                        }
+                    }
+                }
+            },
+        ];
+
+        let mut cursor = collection
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        let totals = cursor
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+            .map(|doc| (bson_to_u64(&doc, "requests"), bson_to_u64(&doc, "errors")))
+            .unwrap_or_default();
+        Ok(totals)
+    }
+
+    /// Response times (ms) of the latest `limit` requests of a route to one
+    /// target since `from`
+    pub async fn get_route_target_latencies(
+        &self,
+        route_id: i32,
+        target: &str,
+        from: chrono::DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<u64>, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .limit(limit)
+            .projection(doc! { "_id": 0, "response_time_ms": 1 })
+            .build();
+        let mut cursor = collection
+            .find(
+                doc! {
+                    "route_id": route_id,
+                    "target": target,
+                    "timestamp": { "$gte": from.to_rfc3339() },
+                },
+                options,
+            )
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+
+        let mut latencies = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+        {
+            latencies.push(bson_to_u64(&doc, "response_time_ms"));
+        }
+        Ok(latencies)
+    }
+
+    /// Count access logs matching a raw filter document
+    pub async fn count_access_logs_matching(
+        &self,
+        filter: &bson::Document,
+    ) -> Result<u64, AppError> {
+        self.db
+            .collection::<bson::Document>("access_logs")
+            .count_documents(filter.clone(), None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))
+    }
+
+    /// Earliest and latest timestamp of the access logs matching `filter`
+    /// (None when nothing matches)
+    pub async fn access_log_time_span(
+        &self,
+        filter: &bson::Document,
+    ) -> Result<Option<(String, String)>, AppError> {
+        let pipeline = vec![
+            doc! { "$match": filter.clone() },
+            doc! { "$group": {
+                "_id": null,
+                "first": { "$min": "$timestamp" },
+                "last": { "$max": "$timestamp" },
+            } },
+        ];
+        let options = AggregateOptions::builder()
+            .max_time(std::time::Duration::from_secs(15))
+            .build();
+        let span = self
+            .db
+            .collection::<bson::Document>("access_logs")
+            .aggregate(pipeline, options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        Ok(span.and_then(|d| {
+            Some((
+                d.get_str("first").ok()?.to_string(),
+                d.get_str("last").ok()?.to_string(),
+            ))
+        }))
+    }
+
+    /// Delete up to `batch_size` access logs matching `filter`, oldest first.
+    /// Returns the number deleted (0 once nothing matches).
+    pub async fn delete_access_logs_batch(
+        &self,
+        filter: &bson::Document,
+        batch_size: i64,
+    ) -> Result<u64, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+
+        let options = FindOptions::builder()
+            .projection(doc! { "_id": 1 })
+            .sort(doc! { "timestamp": 1 })
+            .limit(batch_size)
+            .build();
+        let ids: Vec<bson::Bson> = collection
+            .find(filter.clone(), options)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+            .into_iter()
+            .filter_map(|d| d.get("_id").cloned())
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = collection
+            .delete_many(doc! { "_id": { "$in": ids } }, None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        Ok(result.deleted_count)
+    }
+
     /// Hourly aggregation: aggregate by hour within specified period
     pub async fn get_hourly_stats(
         &self,
@@ -552,13 +1076,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut stats = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             // _id is "2026-02-06T22" (first 13 chars), append ":00:00Z" for full ISO format
             let hour_prefix = doc.get_str("_id").unwrap_or("");
@@ -623,13 +1147,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut entries = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             let key = doc.get_str("_id").unwrap_or("").to_string();
             let count = bson_to_u64(&doc, "count");
@@ -648,6 +1172,7 @@ impl MongoDb {
                 city,
                 latitude,
                 longitude,
+                geo_action: None,
             });
         }
 
@@ -693,13 +1218,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut entries = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             let key = doc.get_str("_id").unwrap_or("").to_string();
             let count = bson_to_u64(&doc, "count");
@@ -713,12 +1238,70 @@ impl MongoDb {
                 city: None,
                 latitude: None,
                 longitude: None,
+                geo_action: None,
             });
         }
 
         Ok(entries)
     }
 
+    /// Request breakdown by client HTTP version
+    pub async fn get_protocol_summary(
+        &self,
+        from: chrono::DateTime<Utc>,
+        to: chrono::DateTime<Utc>,
+        exclude_ips: &Option<String>,
+        exclude_lan: &Option<bool>,
+    ) -> Result<Vec<ProtocolSummary>, AppError> {
+        let collection = self.db.collection::<bson::Document>("access_logs");
+
+        let mut match_doc = doc! {
+            "timestamp": {
+                "$gte": from.to_rfc3339(),
+                "$lte": to.to_rfc3339(),
+            }
+        };
+        apply_ip_exclusion(&mut match_doc, exclude_ips, exclude_lan);
+
+        let pipeline = vec![
+            doc! { "$match": match_doc },
+            doc! {
+                "$group": {
+                    "_id": { "$ifNull": ["$http_version", "unknown"] },
+                    "count": { "$sum": 1 },
+                    "error_count": {
+                        "$sum": {
+                            "$cond": [{ "$gte": ["$status", 400] }, 1, 0]
+                        }
+                    },
+                    "avg_response_time_ms": { "$avg": "$response_time_ms" }
+                }
+            },
+            doc! { "$sort": { "count": -1 } },
+        ];
+
+        let mut cursor = collection
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+
+        let mut summaries = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?
+        {
+            summaries.push(ProtocolSummary {
+                http_version: doc.get_str("_id").unwrap_or("unknown").to_string(),
+                count: bson_to_u64(&doc, "count"),
+                error_count: bson_to_u64(&doc, "error_count"),
+                avg_response_time_ms: doc.get_f64("avg_response_time_ms").unwrap_or(0.0),
+            });
+        }
+
+        Ok(summaries)
+    }
+
     /// Error (4xx/5xx) grouping summary
     pub async fn get_error_summary(
         &self,
@@ -768,13 +1351,13 @@ impl MongoDb {
         let mut cursor = collection
             .aggregate(pipeline, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut summaries = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             let status = doc.get_i32("_id").unwrap_or(0);
             let count = bson_to_u64(&doc, "count");
@@ -842,7 +1425,7 @@ impl MongoDb {
         // IP
         if let Some(ref ip) = query.ip {
             if !ip.is_empty() {
-                filter.insert("ip", ip.as_str());
+                filter.insert("ip", doc! { "$in": crate::ip::ip_variants(ip) });
             }
         }
 
@@ -853,6 +1436,23 @@ impl MongoDb {
             }
         }
 
+        // HTTP version ("unknown" = logged before the field existed)
+        if let Some(ref version) = query.http_version {
+            if version.eq_ignore_ascii_case("unknown") {
+                filter.insert("http_version", doc! { "$in": [bson::Bson::Null] });
+            } else if !version.is_empty() {
+                filter.insert("http_version", version.to_uppercase());
+            }
+        }
+
+        // Custom log field (exact match; invalid names are ignored since the
+        // name becomes part of a document path)
+        if let (Some(field), Some(value)) = (&query.custom_field, &query.custom_value) {
+            if is_valid_field_name(field) {
+                filter.insert(format!("custom_fields.{}", field), value.as_str());
+            }
+        }
+
         // IP exclusion filter
         apply_ip_exclusion(&mut filter, &query.exclude_ips, &query.exclude_lan);
 
@@ -0,0 +1,172 @@
+//! Access-log alert rules (collection `alert_rules`)
+//!
+//! Rule definitions are written by the API; `status` is written only by the
+//! evaluator, so updates never touch it and vice versa.
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::FindOptions;
+use serde::Deserialize;
+
+use super::MongoDb;
+use crate::alert_rules::{AlertRule, AlertRuleStatus, IpCount, WindowStats};
+
+/// Output of the window aggregation in `alert_window_stats`
+#[derive(Deserialize)]
+struct WindowAggregate {
+    matched: u64,
+    errors: u64,
+    unique_ips: u64,
+    top: Vec<IpCount>,
+}
+
+impl MongoDb {
+    /// List all rules, oldest first
+    pub async fn list_alert_rules(&self) -> Result<Vec<AlertRule>, String> {
+        let collection = self.db.collection::<AlertRule>("alert_rules");
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        let cursor = collection
+            .find(doc! {}, options)
+            .await
+            .map_err(|e| format!("List alert rules: {}", e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read alert rules: {}", e))
+    }
+
+    pub async fn get_alert_rule(&self, rule_id: &str) -> Result<Option<AlertRule>, String> {
+        let collection = self.db.collection::<AlertRule>("alert_rules");
+        collection
+            .find_one(doc! { "rule_id": rule_id }, None)
+            .await
+            .map_err(|e| format!("Get alert rule {}: {}", rule_id, e))
+    }
+
+    pub async fn insert_alert_rule(&self, rule: &AlertRule) -> Result<(), String> {
+        let collection = self.db.collection::<AlertRule>("alert_rules");
+        collection
+            .insert_one(rule, None)
+            .await
+            .map_err(|e| format!("Insert alert rule: {}", e))?;
+        Ok(())
+    }
+
+    /// Save a rule's definition (status and created_at are left as stored)
+    pub async fn update_alert_rule(&self, rule: &AlertRule) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>("alert_rules");
+        let mut fields =
+            bson::to_document(rule).map_err(|e| format!("Serialize alert rule: {}", e))?;
+        fields.remove("status");
+        fields.remove("created_at");
+
+        let result = collection
+            .update_one(
+                doc! { "rule_id": &rule.rule_id },
+                doc! { "$set": fields },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update alert rule {}: {}", rule.rule_id, e))?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn delete_alert_rule(&self, rule_id: &str) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>("alert_rules");
+        let result = collection
+            .delete_one(doc! { "rule_id": rule_id }, None)
+            .await
+            .map_err(|e| format!("Delete alert rule {}: {}", rule_id, e))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn set_alert_rule_status(
+        &self,
+        rule_id: &str,
+        status: &AlertRuleStatus,
+    ) -> Result<(), String> {
+        let collection = self.db.collection::<Document>("alert_rules");
+        let status =
+            bson::to_bson(status).map_err(|e| format!("Serialize alert rule status: {}", e))?;
+        collection
+            .update_one(
+                doc! { "rule_id": rule_id },
+                doc! { "$set": { "status": status } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update alert rule {} status: {}", rule_id, e))?;
+        Ok(())
+    }
+
+    /// Aggregate access logs in `[from, to]` matching `conditions`: request
+    /// and 5xx counts, distinct source IPs and the busiest `top` IPs
+    pub async fn alert_window_stats(
+        &self,
+        conditions: Vec<Document>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        top: usize,
+    ) -> Result<WindowStats, String> {
+        let collection = self.db.collection::<Document>("access_logs");
+
+        let mut clauses = vec![doc! {
+            "timestamp": { "$gte": from.to_rfc3339(), "$lte": to.to_rfc3339() }
+        }];
+        clauses.extend(conditions);
+
+        let pipeline = vec![
+            doc! { "$match": { "$and": clauses } },
+            doc! {
+                "$group": {
+                    "_id": "$ip",
+                    "count": { "$sum": 1 },
+                    "errors": { "$sum": { "$cond": [{ "$gte": ["$status", 500] }, 1, 0] } }
+                }
+            },
+            doc! { "$sort": { "count": -1 } },
+            doc! {
+                "$group": {
+                    "_id": null,
+                    "matched": { "$sum": "$count" },
+                    "errors": { "$sum": "$errors" },
+                    "unique_ips": { "$sum": 1 },
+                    "top": { "$push": { "ip": "$_id", "count": "$count" } }
+                }
+            },
+            doc! {
+                "$project": {
+                    "matched": 1,
+                    "errors": 1,
+                    "unique_ips": 1,
+                    "top": { "$slice": ["$top", top as i64] }
+                }
+            },
+        ];
+
+        let mut cursor = collection
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| format!("Aggregate access logs: {}", e))?;
+        let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| format!("Read access log aggregate: {}", e))?
+        else {
+            return Ok(WindowStats::default());
+        };
+
+        let window: WindowAggregate =
+            bson::from_document(doc).map_err(|e| format!("Decode access log aggregate: {}", e))?;
+        Ok(WindowStats {
+            matched: window.matched,
+            errors: window.errors,
+            unique_ips: window.unique_ips,
+            top_ips: window.top,
+        })
+    }
+}
@@ -0,0 +1,144 @@
+//! Admin API request recorder entries (capped collection `api_trace`)
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{CreateCollectionOptions, FindOptions, IndexOptions};
+use mongodb::IndexModel;
+use serde::Deserialize;
+
+use super::MongoDb;
+use crate::api::api_trace::ApiTraceEntry;
+use crate::proxy::store_forward::stamp;
+
+const COLLECTION: &str = "api_trace";
+
+/// Size bound of the capped collection
+pub const API_TRACE_CAP_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Server error code for an existing collection
+const NAMESPACE_EXISTS: i32 = 48;
+
+/// Search of `GET /api/admin/api-trace`
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiTraceQuery {
+    pub actor: Option<String>,
+    /// Path prefix
+    pub path: Option<String>,
+    pub method: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl MongoDb {
+    /// Create the capped collection and its indexes
+    pub async fn ensure_api_trace_collection(&self) -> Result<(), String> {
+        let options = CreateCollectionOptions::builder()
+            .capped(true)
+            .size(API_TRACE_CAP_BYTES)
+            .build();
+        if let Err(e) = self.db.create_collection(COLLECTION, options).await {
+            let exists = matches!(
+                *e.kind,
+                ErrorKind::Command(ref c) if c.code == NAMESPACE_EXISTS
+            );
+            if !exists {
+                return Err(format!("Create api_trace collection: {}", e));
+            }
+        }
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "trace_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder().keys(doc! { "at": -1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "actor": 1, "at": -1 })
+                .build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create api_trace indexes: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn insert_api_trace(&self, entry: &ApiTraceEntry) -> Result<(), String> {
+        self.db
+            .collection::<ApiTraceEntry>(COLLECTION)
+            .insert_one(entry, None)
+            .await
+            .map_err(|e| format!("Insert api_trace: {}", e))?;
+        Ok(())
+    }
+
+    /// Matching entries, newest first, without bodies
+    pub async fn search_api_trace(
+        &self,
+        query: &ApiTraceQuery,
+    ) -> Result<Vec<ApiTraceEntry>, String> {
+        let mut filter = Document::new();
+        if let Some(actor) = query.actor.as_deref().filter(|s| !s.is_empty()) {
+            filter.insert("actor", actor);
+        }
+        if let Some(path) = query
+            .path
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            filter.insert(
+                "path",
+                doc! { "$regex": format!("^{}", regex::escape(path)) },
+            );
+        }
+        if let Some(method) = query.method.as_deref().filter(|s| !s.is_empty()) {
+            filter.insert("method", method.to_ascii_uppercase());
+        }
+        let mut at = Document::new();
+        if let Some(from) = query.from {
+            at.insert("$gte", stamp(from));
+        }
+        if let Some(to) = query.to {
+            at.insert("$lte", stamp(to));
+        }
+        if !at.is_empty() {
+            filter.insert("at", at);
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "at": -1 })
+            .projection(doc! { "request_body": 0, "response_body": 0 })
+            .limit(query.limit.unwrap_or(100).clamp(1, 1000))
+            .build();
+        let cursor = self
+            .db
+            .collection::<ApiTraceEntry>(COLLECTION)
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Search api_trace: {}", e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read api_trace: {}", e))
+    }
+
+    pub async fn get_api_trace(&self, trace_id: &str) -> Result<Option<ApiTraceEntry>, String> {
+        self.db
+            .collection::<ApiTraceEntry>(COLLECTION)
+            .find_one(doc! { "trace_id": trace_id }, None)
+            .await
+            .map_err(|e| format!("Get api_trace: {}", e))
+    }
+
+    pub async fn count_api_trace(&self) -> Result<u64, String> {
+        self.db
+            .collection::<Document>(COLLECTION)
+            .estimated_document_count(None)
+            .await
+            .map_err(|e| format!("Count api_trace: {}", e))
+    }
+}
@@ -0,0 +1,204 @@
+//! MongoDB leader lease and instance heartbeats
+//!
+//! Collections: `cluster_lease` (single document `_id: "leader"`),
+//! `cluster_instances`
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc};
+use mongodb::options::UpdateOptions;
+use serde::{Deserialize, Serialize};
+
+use super::MongoDb;
+
+const LEASE_ID: &str = "leader";
+
+// ============================================================================
+// Document types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderLeaseDoc {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub holder: String,
+    pub hostname: String,
+    /// Incremented on every change of holder
+    pub term: i64,
+    pub acquired_at: String,
+    pub heartbeat_at: String,
+    /// Epoch millis (compared numerically for takeover)
+    pub expires_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterInstanceDoc {
+    pub instance_id: String,
+    pub hostname: String,
+    pub role: String,
+    pub started_at: String,
+    pub heartbeat_at: String,
+}
+
+/// Result of one acquire/renew attempt
+#[derive(Debug, Clone)]
+pub enum LeaseAttempt {
+    /// Still the holder; lease extended
+    Renewed(LeaderLeaseDoc),
+    /// Took over an expired (or missing) lease; carries the previous holder
+    Acquired(LeaderLeaseDoc, Option<String>),
+    /// Another instance holds a live lease
+    HeldBy(LeaderLeaseDoc),
+}
+
+// ============================================================================
+// MongoDB operations
+// ============================================================================
+
+impl MongoDb {
+    /// Current leader lease, if any
+    pub async fn get_leader_lease(&self) -> Result<Option<LeaderLeaseDoc>, String> {
+        self.db
+            .collection::<LeaderLeaseDoc>("cluster_lease")
+            .find_one(doc! { "_id": LEASE_ID }, None)
+            .await
+            .map_err(|e| format!("Get leader lease: {}", e))
+    }
+
+    /// Renew our lease, or take it over once it has been expired for
+    /// longer than `skew_ms` (the holder's clock may be behind ours)
+    pub async fn try_acquire_leader_lease(
+        &self,
+        instance_id: &str,
+        hostname: &str,
+        ttl_ms: i64,
+        skew_ms: i64,
+    ) -> Result<LeaseAttempt, String> {
+        let collection = self.db.collection::<bson::Document>("cluster_lease");
+        let now = Utc::now();
+        let now_ms = now.timestamp_millis();
+        let now_str = now.to_rfc3339();
+        let expires = now_ms + ttl_ms;
+        let takeover_before = now_ms - skew_ms;
+
+        // 1. Renew when we already hold it
+        let renewed = collection
+            .update_one(
+                doc! { "_id": LEASE_ID, "holder": instance_id },
+                doc! { "$set": { "heartbeat_at": &now_str, "expires_at_ms": expires } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Renew leader lease: {}", e))?;
+        if renewed.matched_count > 0 {
+            let lease = self.get_leader_lease().await?.ok_or("Lease vanished")?;
+            return Ok(LeaseAttempt::Renewed(lease));
+        }
+
+        let current = self.get_leader_lease().await?;
+
+        // 2. Take over an expired lease (conditional on the holder/term we saw)
+        if let Some(current) = current {
+            if current.expires_at_ms >= takeover_before {
+                return Ok(LeaseAttempt::HeldBy(current));
+            }
+            let taken = collection
+                .update_one(
+                    doc! {
+                        "_id": LEASE_ID,
+                        "term": current.term,
+                        "expires_at_ms": { "$lt": takeover_before },
+                    },
+                    doc! { "$set": {
+                        "holder": instance_id,
+                        "hostname": hostname,
+                        "term": current.term + 1,
+                        "acquired_at": &now_str,
+                        "heartbeat_at": &now_str,
+                        "expires_at_ms": expires,
+                    } },
+                    None,
+                )
+                .await
+                .map_err(|e| format!("Take over leader lease: {}", e))?;
+            if taken.matched_count == 0 {
+                // Someone else won the race
+                return match self.get_leader_lease().await? {
+                    Some(lease) => Ok(LeaseAttempt::HeldBy(lease)),
+                    None => Err("Lease vanished".to_string()),
+                };
+            }
+            let lease = self.get_leader_lease().await?.ok_or("Lease vanished")?;
+            let previous = Some(current.holder).filter(|h| !h.is_empty());
+            return Ok(LeaseAttempt::Acquired(lease, previous));
+        }
+
+        // 3. No lease yet: first writer wins (duplicate _id = lost the race)
+        let lease = LeaderLeaseDoc {
+            id: LEASE_ID.to_string(),
+            holder: instance_id.to_string(),
+            hostname: hostname.to_string(),
+            term: 1,
+            acquired_at: now_str.clone(),
+            heartbeat_at: now_str,
+            expires_at_ms: expires,
+        };
+        let bson_doc = bson::to_document(&lease).map_err(|e| format!("Serialize lease: {}", e))?;
+        match collection.insert_one(bson_doc, None).await {
+            Ok(_) => Ok(LeaseAttempt::Acquired(lease, None)),
+            Err(_) => match self.get_leader_lease().await? {
+                Some(lease) => Ok(LeaseAttempt::HeldBy(lease)),
+                None => Err("Leader lease insert failed".to_string()),
+            },
+        }
+    }
+
+    /// Expire our lease immediately so another instance can take over
+    pub async fn release_leader_lease(&self, instance_id: &str) -> Result<bool, String> {
+        let result = self
+            .db
+            .collection::<bson::Document>("cluster_lease")
+            .update_one(
+                doc! { "_id": LEASE_ID, "holder": instance_id },
+                doc! { "$set": { "expires_at_ms": 0_i64 } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Release leader lease: {}", e))?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    /// Record this instance's heartbeat and role
+    pub async fn upsert_cluster_instance(&self, doc: &ClusterInstanceDoc) -> Result<(), String> {
+        let collection = self.db.collection::<bson::Document>("cluster_instances");
+        let bson_doc =
+            bson::to_document(doc).map_err(|e| format!("Serialize cluster instance: {}", e))?;
+        let options = UpdateOptions::builder().upsert(true).build();
+        collection
+            .update_one(
+                doc! { "instance_id": &doc.instance_id },
+                doc! { "$set": bson_doc },
+                Some(options),
+            )
+            .await
+            .map_err(|e| format!("Upsert cluster instance {}: {}", doc.instance_id, e))?;
+
+        Ok(())
+    }
+
+    /// All known instances (including ones that stopped heartbeating)
+    pub async fn list_cluster_instances(&self) -> Result<Vec<ClusterInstanceDoc>, String> {
+        let cursor = self
+            .db
+            .collection::<ClusterInstanceDoc>("cluster_instances")
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| format!("List cluster instances: {}", e))?;
+
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("List cluster instances: {}", e))
+    }
+}
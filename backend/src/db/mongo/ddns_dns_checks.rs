@@ -0,0 +1,70 @@
+//! Latest DNS dependency check per DDNS config (collection `ddns_dns_checks`)
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{IndexOptions, ReplaceOptions};
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::ddns::dns_watch::DdnsDnsCheck;
+
+const COLLECTION: &str = "ddns_dns_checks";
+
+impl MongoDb {
+    pub async fn ensure_ddns_dns_check_indexes(&self) -> Result<(), String> {
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "ddns_config_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create ddns_dns_checks index: {}", e))?;
+        Ok(())
+    }
+
+    /// Replace the stored check of the config
+    pub async fn save_ddns_dns_check(&self, check: &DdnsDnsCheck) -> Result<(), String> {
+        let doc = bson::to_document(check).map_err(|e| format!("Encode: {}", e))?;
+        self.db
+            .collection::<Document>(COLLECTION)
+            .replace_one(
+                doc! { "ddns_config_id": check.ddns_config_id },
+                doc,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Save ddns_dns_checks: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn list_ddns_dns_checks(&self) -> Result<Vec<DdnsDnsCheck>, String> {
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| format!("Query ddns_dns_checks: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read ddns_dns_checks: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Drop checks of configs no longer linked to an active route
+    pub async fn retain_ddns_dns_checks(&self, ddns_config_ids: &[i32]) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(doc! { "ddns_config_id": { "$nin": ddns_config_ids } }, None)
+            .await
+            .map_err(|e| format!("Purge ddns_dns_checks: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
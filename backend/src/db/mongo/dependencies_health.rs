@@ -0,0 +1,121 @@
+//! Dependency probe results (collection `dependencies_health`)
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::health::dependencies::DependencyHealth;
+
+const COLLECTION: &str = "dependencies_health";
+
+/// Storage figures from `dbStats` (bytes)
+#[derive(Debug, Clone, Copy)]
+pub struct MongoStorage {
+    pub storage_size: u64,
+    pub data_size: u64,
+    /// Filesystem holding the data files (MongoDB 4.4+)
+    pub fs_used_size: Option<u64>,
+    pub fs_total_size: Option<u64>,
+}
+
+impl MongoStorage {
+    pub fn fs_used_percent(&self) -> Option<f64> {
+        match (self.fs_used_size, self.fs_total_size) {
+            (Some(used), Some(total)) if total > 0 => Some(used as f64 * 100.0 / total as f64),
+            _ => None,
+        }
+    }
+}
+
+/// dbStats reports sizes as int32, int64 or double depending on magnitude
+fn size_field(stats: &Document, key: &str) -> Option<u64> {
+    match stats.get(key)? {
+        Bson::Int32(v) => Some(*v as u64),
+        Bson::Int64(v) => Some(*v as u64),
+        Bson::Double(v) => Some(*v as u64),
+        _ => None,
+    }
+}
+
+impl MongoDb {
+    pub async fn ensure_dependencies_health_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "dependency": 1, "target": 1, "timestamp": -1 })
+                .build(),
+            IndexModel::builder().keys(doc! { "timestamp": 1 }).build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create dependencies_health indexes: {}", e))?;
+        Ok(())
+    }
+
+    /// Storage and filesystem usage of the gateway database
+    pub async fn storage_stats(&self) -> Result<MongoStorage, String> {
+        let stats = self
+            .db
+            .run_command(doc! { "dbStats": 1 }, None)
+            .await
+            .map_err(|e| format!("dbStats failed: {}", e))?;
+        Ok(MongoStorage {
+            storage_size: size_field(&stats, "storageSize").unwrap_or(0),
+            data_size: size_field(&stats, "dataSize").unwrap_or(0),
+            fs_used_size: size_field(&stats, "fsUsedSize"),
+            fs_total_size: size_field(&stats, "fsTotalSize"),
+        })
+    }
+
+    pub async fn save_dependency_health(&self, record: &DependencyHealth) -> Result<(), String> {
+        let doc = bson::to_document(record).map_err(|e| format!("Encode: {}", e))?;
+        self.db
+            .collection::<Document>(COLLECTION)
+            .insert_one(doc, None)
+            .await
+            .map_err(|e| format!("Save dependency health: {}", e))?;
+        Ok(())
+    }
+
+    /// Latest result of every dependency/target, ordered by dependency
+    pub async fn latest_dependency_health(&self) -> Result<Vec<DependencyHealth>, String> {
+        let pipeline = vec![
+            doc! { "$sort": { "timestamp": -1 } },
+            doc! {
+                "$group": {
+                    "_id": { "dependency": "$dependency", "target": "$target" },
+                    "latest": { "$first": "$$ROOT" },
+                }
+            },
+            doc! { "$replaceRoot": { "newRoot": "$latest" } },
+            doc! { "$sort": { "dependency": 1, "target": 1 } },
+        ];
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| format!("Aggregate dependencies_health: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read dependencies_health: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Drop records older than `before`
+    pub async fn purge_dependencies_health(&self, before: DateTime<Utc>) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(doc! { "timestamp": { "$lt": before.to_rfc3339() } }, None)
+            .await
+            .map_err(|e| format!("Purge dependencies_health: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
@@ -0,0 +1,258 @@
+//! Cross-collection device search (omada/openwrt/external clients and
+//! user_object_detail) backing GET /api/devices/search.
+//!
+//! A query is matched as a MAC (any separator, full or partial), an IP
+//! (exact, or prefix while typing) and a case-insensitive substring of the
+//! hostname/name/label fields of each collection.
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::options::{FindOptions, IndexOptions};
+use mongodb::IndexModel;
+
+use super::external::ExternalClientDoc;
+use super::omada::OmadaClientDoc;
+use super::openwrt::OpenWrtClientDoc;
+use super::user_object_detail::{doc_to_user_object_detail, UserObjectDetail};
+use super::MongoDb;
+
+/// Searched fields per collection (MAC, IP, then text fields); each gets an index
+const SEARCH_FIELDS: [(&str, &[&str]); 4] = [
+    ("omada_clients", &["mac", "ip", "host_name", "name"]),
+    ("openwrt_clients", &["mac", "ip", "hostname"]),
+    ("external_clients", &["mac", "ip", "hostname"]),
+    ("user_object_detail", &["mac", "ip", "hostname", "label"]),
+];
+
+/// Partial MACs shorter than this are only matched as text
+const MIN_PARTIAL_MAC_LEN: usize = 4;
+
+/// Raw matches per collection
+#[derive(Debug, Default)]
+pub struct DeviceSearchDocs {
+    pub omada: Vec<OmadaClientDoc>,
+    pub openwrt: Vec<OpenWrtClientDoc>,
+    pub external: Vec<ExternalClientDoc>,
+    pub topology: Vec<UserObjectDetail>,
+}
+
+/// Build the `$or` filter for one collection's fields
+pub fn device_search_filter(query: &str, fields: &[&str]) -> Document {
+    let query = query.trim();
+    let text = regex::escape(query);
+    let mut clauses: Vec<Bson> = Vec::new();
+
+    let is_ip = query.parse::<std::net::IpAddr>().is_ok();
+    // Dotted-decimal while typing, e.g. "192.168."
+    let is_ipv4_prefix =
+        query.contains('.') && query.chars().all(|c| c.is_ascii_digit() || c == '.');
+
+    // MAC: stored as 12 uppercase hex digits without separators
+    let hex: String = query
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .collect::<String>()
+        .to_uppercase();
+    if !is_ip
+        && !is_ipv4_prefix
+        && hex.len() >= MIN_PARTIAL_MAC_LEN
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        let mac = if hex.len() == 12 {
+            Bson::String(hex)
+        } else {
+            Bson::Document(doc! { "$regex": hex })
+        };
+        clauses.push(Bson::Document(doc! { "mac": mac }));
+    }
+
+    // IP: exact when complete, prefix while typing
+    if is_ip {
+        clauses.push(Bson::Document(doc! { "ip": query }));
+    } else if is_ipv4_prefix {
+        clauses.push(Bson::Document(
+            doc! { "ip": { "$regex": format!("^{}", text) } },
+        ));
+    }
+
+    // Text fields: case-insensitive substring
+    for field in fields.iter().filter(|f| !matches!(**f, "mac" | "ip")) {
+        clauses.push(Bson::Document(
+            doc! { *field: { "$regex": &text, "$options": "i" } },
+        ));
+    }
+
+    doc! { "$or": clauses }
+}
+
+impl MongoDb {
+    /// Create the indexes used by the device search (idempotent; run at startup)
+    pub async fn ensure_device_search_indexes(&self) -> Result<(), String> {
+        for (collection, fields) in SEARCH_FIELDS {
+            let indexes: Vec<IndexModel> = fields
+                .iter()
+                .map(|field| {
+                    IndexModel::builder()
+                        .keys(doc! { *field: 1 })
+                        .options(
+                            IndexOptions::builder()
+                                .name(format!("search_{}", field))
+                                .build(),
+                        )
+                        .build()
+                })
+                .collect();
+            self.db
+                .collection::<Document>(collection)
+                .create_indexes(indexes, None)
+                .await
+                .map_err(|e| format!("Create {} search indexes: {}", collection, e))?;
+        }
+        Ok(())
+    }
+
+    /// Search all device collections; at most `limit` documents per collection
+    pub async fn search_devices(
+        &self,
+        query: &str,
+        limit: i64,
+    ) -> Result<DeviceSearchDocs, String> {
+        let (omada, openwrt, external, topology) = tokio::try_join!(
+            self.search_device_collection(0, query, limit),
+            self.search_device_collection(1, query, limit),
+            self.search_device_collection(2, query, limit),
+            self.search_device_collection(3, query, limit),
+        )?;
+
+        Ok(DeviceSearchDocs {
+            omada: parse_docs(omada),
+            openwrt: parse_docs(openwrt),
+            external: parse_docs(external),
+            topology: topology
+                .iter()
+                .filter_map(|d| doc_to_user_object_detail(d).ok())
+                .collect(),
+        })
+    }
+
+    /// Topology nodes for the given normalized MACs (links for hits that
+    /// only matched a source collection)
+    pub async fn get_user_object_details_by_macs(
+        &self,
+        macs: &[String],
+    ) -> Result<Vec<UserObjectDetail>, String> {
+        if macs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let docs = self
+            .find_device_docs("user_object_detail", doc! { "mac": { "$in": macs } }, None)
+            .await?;
+        Ok(docs
+            .iter()
+            .filter_map(|d| doc_to_user_object_detail(d).ok())
+            .collect())
+    }
+
+    async fn search_device_collection(
+        &self,
+        index: usize,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<Document>, String> {
+        let (collection, fields) = SEARCH_FIELDS[index];
+        self.find_device_docs(collection, device_search_filter(query, fields), Some(limit))
+            .await
+    }
+
+    async fn find_device_docs(
+        &self,
+        collection: &str,
+        filter: Document,
+        limit: Option<i64>,
+    ) -> Result<Vec<Document>, String> {
+        let options = FindOptions::builder().limit(limit).build();
+        let cursor = self
+            .db
+            .collection::<Document>(collection)
+            .find(filter, Some(options))
+            .await
+            .map_err(|e| format!("Search {}: {}", collection, e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Cursor {}: {}", collection, e))
+    }
+}
+
+fn parse_docs<T: serde::de::DeserializeOwned>(docs: Vec<Document>) -> Vec<T> {
+    docs.into_iter()
+        .filter_map(|d| bson::from_document(d).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(filter: &Document) -> Vec<String> {
+        filter
+            .get_array("$or")
+            .unwrap()
+            .iter()
+            .map(|c| c.as_document().unwrap().keys().next().unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn mac_queries_match_normalized_mac() {
+        let filter = device_search_filter("aa:bb:cc:dd:ee:ff", &["mac", "ip", "hostname"]);
+        let or = filter.get_array("$or").unwrap();
+        assert_eq!(
+            or[0].as_document().unwrap().get_str("mac").unwrap(),
+            "AABBCCDDEEFF"
+        );
+        assert_eq!(fields(&filter), vec!["mac", "hostname"]);
+
+        let partial = device_search_filter("dd-ee-ff", &["mac", "ip", "hostname"]);
+        assert_eq!(
+            partial.get_array("$or").unwrap()[0]
+                .as_document()
+                .unwrap()
+                .get_document("mac")
+                .unwrap()
+                .get_str("$regex")
+                .unwrap(),
+            "DDEEFF"
+        );
+    }
+
+    #[test]
+    fn ip_and_text_queries() {
+        let exact = device_search_filter("192.168.1.20", &["mac", "ip", "label"]);
+        assert_eq!(fields(&exact), vec!["ip", "label"]);
+        assert_eq!(
+            exact.get_array("$or").unwrap()[0]
+                .as_document()
+                .unwrap()
+                .get_str("ip")
+                .unwrap(),
+            "192.168.1.20"
+        );
+
+        let prefix = device_search_filter("192.168.", &["mac", "ip", "label"]);
+        assert_eq!(fields(&prefix), vec!["ip", "label"]);
+
+        let text = device_search_filter("iPhone (2)", &["mac", "ip", "host_name", "name"]);
+        assert_eq!(fields(&text), vec!["host_name", "name"]);
+        assert_eq!(
+            text.get_array("$or").unwrap()[0]
+                .as_document()
+                .unwrap()
+                .get_document("host_name")
+                .unwrap()
+                .get_str("$regex")
+                .unwrap(),
+            r"iPhone \(2\)"
+        );
+    }
+}
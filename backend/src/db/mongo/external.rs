@@ -35,6 +35,12 @@ pub struct ExternalDeviceDoc {
     pub product_type: String,
     pub network_device_type: String,
     pub last_polled_at: Option<String>,
+    /// Poll interval override in seconds (None = syncer default)
+    #[serde(default)]
+    pub poll_interval_sec: Option<u32>,
+    /// Skipped by the syncer; cached clients and topology entries are kept
+    #[serde(default)]
+    pub paused: bool,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -46,6 +52,13 @@ pub struct ExternalClientDoc {
     pub ip: Option<String>,
     pub hostname: Option<String>,
     pub lacis_id: Option<String>,
+    /// Associated to a wireless interface / SSID at the last poll
+    #[serde(default)]
+    pub wireless: bool,
+    #[serde(default)]
+    pub ssid: Option<String>,
+    #[serde(default)]
+    pub band: Option<String>,
     pub active: bool,
     pub last_seen_at: String,
     pub synced_at: String,
@@ -53,6 +66,17 @@ pub struct ExternalClientDoc {
     pub updated_at: String,
 }
 
+/// Editable fields for PUT /api/external/devices/:id (None = unchanged)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExternalDeviceUpdate {
+    pub display_name: Option<String>,
+    pub ip: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub poll_interval_sec: Option<u32>,
+    pub paused: Option<bool>,
+}
+
 // ============================================================================
 // MongoDB operations
 // ============================================================================
@@ -109,6 +133,46 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Update editable device settings; returns false when the device is unknown.
+    /// `poll_interval_sec: Some(0)` resets to the syncer default. Pausing sets
+    /// status "paused"; resuming sets "offline" until the next poll.
+    pub async fn update_external_device_settings(
+        &self,
+        device_id: &str,
+        update: &ExternalDeviceUpdate,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("external_devices");
+        let mut set = doc! { "updated_at": Utc::now().to_rfc3339() };
+
+        if let Some(name) = &update.display_name {
+            set.insert("display_name", name);
+        }
+        if let Some(ip) = &update.ip {
+            set.insert("ip", ip);
+        }
+        if let Some(username) = &update.username {
+            set.insert("username", username);
+        }
+        if let Some(password) = &update.password {
+            set.insert("password", password);
+        }
+        if let Some(interval) = update.poll_interval_sec {
+            let value = (interval > 0).then_some(interval as i64);
+            set.insert("poll_interval_sec", value);
+        }
+        if let Some(paused) = update.paused {
+            set.insert("paused", paused);
+            set.insert("status", if paused { "paused" } else { "offline" });
+        }
+
+        let result = collection
+            .update_one(doc! { "device_id": device_id }, doc! { "$set": set }, None)
+            .await
+            .map_err(|e| format!("Update device {}: {}", device_id, e))?;
+
+        Ok(result.matched_count > 0)
+    }
+
     /// List all devices
     pub async fn list_external_devices(&self) -> Result<Vec<ExternalDeviceDoc>, String> {
         let collection = self.db.collection::<bson::Document>("external_devices");
@@ -206,6 +270,9 @@ impl MongoDb {
                     "device_id": device_id,
                     "ip": &client.ip,
                     "hostname": &client.hostname,
+                    "wireless": client.wireless(),
+                    "ssid": &client.ssid,
+                    "band": &client.band,
                     "active": true,
                     "last_seen_at": &now,
                     "synced_at": &now,
@@ -0,0 +1,119 @@
+//! Generated facility reports (collection `facility_reports`)
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::aranea::reports::{Delivery, StoredFacilityReport};
+
+const COLLECTION: &str = "facility_reports";
+
+impl MongoDb {
+    pub async fn ensure_facility_report_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder().keys(doc! { "report_id": 1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "fid": 1, "generated_at": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "generated_at": -1 })
+                .build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create facility_reports indexes: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn insert_facility_report(
+        &self,
+        report: &StoredFacilityReport,
+    ) -> Result<(), String> {
+        let doc = bson::to_document(report).map_err(|e| format!("Encode: {}", e))?;
+        self.db
+            .collection::<Document>(COLLECTION)
+            .insert_one(doc, None)
+            .await
+            .map_err(|e| format!("Save facility report: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn update_facility_report_delivery(
+        &self,
+        report_id: &str,
+        delivery: &Delivery,
+    ) -> Result<(), String> {
+        let delivery = bson::to_bson(delivery).map_err(|e| format!("Encode: {}", e))?;
+        self.db
+            .collection::<Document>(COLLECTION)
+            .update_one(
+                doc! { "report_id": report_id },
+                doc! { "$set": { "delivery": delivery } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update facility report delivery: {}", e))?;
+        Ok(())
+    }
+
+    /// Newest reports first, optionally of one facility
+    pub async fn list_facility_reports(
+        &self,
+        fid: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<StoredFacilityReport>, String> {
+        let filter = match fid {
+            Some(fid) => doc! { "fid": fid },
+            None => doc! {},
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "generated_at": -1 })
+            .limit(limit)
+            .build();
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Query facility_reports: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read facility_reports: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Keep the newest `keep` reports
+    pub async fn trim_facility_reports(&self, keep: i64) -> Result<u64, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let options = FindOptions::builder()
+            .sort(doc! { "generated_at": -1 })
+            .skip(keep as u64)
+            .projection(doc! { "_id": 1 })
+            .build();
+        let ids: Vec<Bson> = collection
+            .find(doc! {}, options)
+            .await
+            .map_err(|e| format!("Query facility_reports: {}", e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Read facility_reports: {}", e))?
+            .into_iter()
+            .filter_map(|d| d.get("_id").cloned())
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let result = collection
+            .delete_many(doc! { "_id": { "$in": ids } }, None)
+            .await
+            .map_err(|e| format!("Trim facility_reports: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
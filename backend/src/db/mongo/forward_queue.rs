@@ -0,0 +1,230 @@
+//! Store-and-forward queue (collection `forward_queue`)
+//!
+//! Items are claimed with a short lease before each replay so a manual
+//! replay and the background replayer never send the same item at once.
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument};
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::proxy::store_forward::{stamp, ForwardAttempt, ForwardQueueItem, ATTEMPT_HISTORY};
+
+const COLLECTION: &str = "forward_queue";
+
+impl MongoDb {
+    /// Indexes for the replayer and the per-route listing
+    pub async fn ensure_forward_queue_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "queue_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "next_attempt_at": 1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "route_id": 1, "created_at": -1 })
+                .build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create forward queue indexes: {}", e))?;
+        Ok(())
+    }
+
+    pub async fn insert_forward_item(&self, item: &ForwardQueueItem) -> Result<(), String> {
+        self.db
+            .collection::<ForwardQueueItem>(COLLECTION)
+            .insert_one(item, None)
+            .await
+            .map_err(|e| format!("Queue request: {}", e))?;
+        Ok(())
+    }
+
+    /// Items of a route, newest first, without bodies
+    pub async fn list_forward_items(
+        &self,
+        route_id: i32,
+        status: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<ForwardQueueItem>, String> {
+        let mut filter = doc! { "route_id": route_id };
+        if let Some(status) = status {
+            filter.insert("status", status);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .projection(doc! { "body_b64": 0 })
+            .limit(limit)
+            .build();
+
+        let cursor = self
+            .db
+            .collection::<ForwardQueueItem>(COLLECTION)
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("List forward queue: {}", e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read forward queue: {}", e))
+    }
+
+    /// Item counts of a route by status
+    pub async fn count_forward_items(&self, route_id: i32, status: &str) -> Result<u64, String> {
+        self.db
+            .collection::<Document>(COLLECTION)
+            .count_documents(doc! { "route_id": route_id, "status": status }, None)
+            .await
+            .map_err(|e| format!("Count forward queue: {}", e))
+    }
+
+    pub async fn get_forward_item(
+        &self,
+        queue_id: &str,
+    ) -> Result<Option<ForwardQueueItem>, String> {
+        self.db
+            .collection::<ForwardQueueItem>(COLLECTION)
+            .find_one(doc! { "queue_id": queue_id }, None)
+            .await
+            .map_err(|e| format!("Get queued request {}: {}", queue_id, e))
+    }
+
+    /// Claim the oldest due pending item that nobody holds
+    pub async fn claim_due_forward_item(
+        &self,
+        now: &str,
+        lease_until: &str,
+    ) -> Result<Option<ForwardQueueItem>, String> {
+        self.claim_forward_item(
+            doc! {
+                "status": "pending",
+                "next_attempt_at": { "$lte": now },
+                "$or": [{ "lease_until": null }, { "lease_until": { "$lt": now } }],
+            },
+            lease_until,
+        )
+        .await
+    }
+
+    /// Claim a specific undelivered item for a manual replay
+    pub async fn claim_forward_item_by_id(
+        &self,
+        queue_id: &str,
+        now: &str,
+        lease_until: &str,
+    ) -> Result<Option<ForwardQueueItem>, String> {
+        self.claim_forward_item(
+            doc! {
+                "queue_id": queue_id,
+                "status": { "$in": ["pending", "failed"] },
+                "$or": [{ "lease_until": null }, { "lease_until": { "$lt": now } }],
+            },
+            lease_until,
+        )
+        .await
+    }
+
+    async fn claim_forward_item(
+        &self,
+        filter: Document,
+        lease_until: &str,
+    ) -> Result<Option<ForwardQueueItem>, String> {
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "next_attempt_at": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        self.db
+            .collection::<ForwardQueueItem>(COLLECTION)
+            .find_one_and_update(
+                filter,
+                doc! { "$set": { "lease_until": lease_until } },
+                Some(options),
+            )
+            .await
+            .map_err(|e| format!("Claim queued request: {}", e))
+    }
+
+    /// Record an attempt and release the lease; delivered items drop their body
+    pub async fn record_forward_attempt(
+        &self,
+        queue_id: &str,
+        attempt: &ForwardAttempt,
+        status: &str,
+        next_attempt_at: &str,
+    ) -> Result<(), String> {
+        let attempt =
+            bson::to_bson(attempt).map_err(|e| format!("Serialize forward attempt: {}", e))?;
+        let mut set = doc! {
+            "status": status,
+            "next_attempt_at": next_attempt_at,
+            "lease_until": null,
+            "updated_at": stamp(Utc::now()),
+        };
+        if status == "delivered" {
+            set.insert("body_b64", "");
+        }
+        self.db
+            .collection::<Document>(COLLECTION)
+            .update_one(
+                doc! { "queue_id": queue_id },
+                doc! {
+                    "$set": set,
+                    "$inc": { "attempt_count": 1 },
+                    "$push": { "attempts": { "$each": [attempt], "$slice": -ATTEMPT_HISTORY } },
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Record attempt on {}: {}", queue_id, e))?;
+        Ok(())
+    }
+
+    /// Mark pending items past their TTL as failed
+    pub async fn expire_forward_items(&self, now: &str) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .update_many(
+                doc! {
+                    "status": "pending",
+                    "expires_at": { "$lte": now },
+                    "$or": [{ "lease_until": null }, { "lease_until": { "$lt": now } }],
+                },
+                doc! { "$set": { "status": "failed", "updated_at": now } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Expire forward queue: {}", e))?;
+        Ok(result.modified_count)
+    }
+
+    pub async fn delete_forward_item(&self, queue_id: &str) -> Result<bool, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_one(doc! { "queue_id": queue_id }, None)
+            .await
+            .map_err(|e| format!("Discard queued request {}: {}", queue_id, e))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    /// Drop delivered items last touched before `before`
+    pub async fn purge_delivered_forward_items(&self, before: &str) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(
+                doc! { "status": "delivered", "updated_at": { "$lt": before } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Purge delivered forward queue: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
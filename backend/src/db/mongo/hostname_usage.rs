@@ -0,0 +1,145 @@
+//! Monthly per-hostname usage rollups (collection `hostname_usage_monthly`)
+
+use chrono::{NaiveDate, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{AggregateOptions, FindOptions, IndexOptions, UpdateOptions};
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::hostname_usage::{next_month, HostnameUsage, RouteHour};
+use crate::ip_stats::day_key;
+
+const COLLECTION: &str = "hostname_usage_monthly";
+
+impl MongoDb {
+    pub async fn ensure_hostname_usage_indexes(&self) -> Result<(), String> {
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "month": 1, "hostname": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create hostname_usage_monthly index: {}", e))?;
+        Ok(())
+    }
+
+    /// Access log totals per route and hour of one month (routed requests only)
+    pub async fn aggregate_route_hours(&self, month: NaiveDate) -> Result<Vec<RouteHour>, String> {
+        let from = day_key(month);
+        let to = day_key(next_month(month));
+        let pipeline = vec![
+            doc! { "$match": {
+                "timestamp": { "$gte": &from, "$lt": &to },
+                "route_id": { "$type": "number" },
+            } },
+            doc! {
+                "$group": {
+                    "_id": {
+                        "route_id": "$route_id",
+                        "hour": { "$substrBytes": ["$timestamp", 0, 13] },
+                    },
+                    "requests": { "$sum": 1 },
+                    "bytes_in": { "$sum": { "$ifNull": ["$request_size", 0] } },
+                    "bytes_out": { "$sum": { "$ifNull": ["$response_size", 0] } },
+                    "errors_4xx": { "$sum": { "$cond": [
+                        { "$and": [{ "$gte": ["$status", 400] }, { "$lt": ["$status", 500] }] }, 1, 0
+                    ] } },
+                    "errors_5xx": { "$sum": { "$cond": [{ "$gte": ["$status", 500] }, 1, 0] } },
+                }
+            },
+            doc! {
+                "$project": {
+                    "_id": 0,
+                    "route_id": { "$toInt": "$_id.route_id" },
+                    "hour": "$_id.hour",
+                    "requests": { "$toLong": "$requests" },
+                    "bytes_in": { "$toLong": "$bytes_in" },
+                    "bytes_out": { "$toLong": "$bytes_out" },
+                    "errors_4xx": { "$toLong": "$errors_4xx" },
+                    "errors_5xx": { "$toLong": "$errors_5xx" },
+                }
+            },
+        ];
+        let options = AggregateOptions::builder().allow_disk_use(true).build();
+
+        Ok(self
+            .db
+            .collection::<Document>("access_logs")
+            .aggregate(pipeline, options)
+            .await
+            .map_err(|e| format!("Aggregate access logs for {}: {}", from, e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Read access log aggregate for {}: {}", from, e))?
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Replace all rows of `month` with `rows` (absolute values, safe to repeat)
+    pub async fn replace_hostname_usage(
+        &self,
+        month: &str,
+        rows: &[HostnameUsage],
+    ) -> Result<(), String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let hostnames: Vec<&str> = rows.iter().map(|r| r.hostname.as_str()).collect();
+        collection
+            .delete_many(
+                doc! { "month": month, "hostname": { "$nin": &hostnames } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Clear hostname_usage_monthly {}: {}", month, e))?;
+
+        let upsert = UpdateOptions::builder().upsert(true).build();
+        let updated_at = Utc::now().to_rfc3339();
+        for row in rows {
+            collection
+                .update_one(
+                    doc! { "month": month, "hostname": &row.hostname },
+                    doc! { "$set": {
+                        "requests": row.requests as i64,
+                        "bytes_in": row.bytes_in as i64,
+                        "bytes_out": row.bytes_out as i64,
+                        "errors_4xx": row.errors_4xx as i64,
+                        "errors_5xx": row.errors_5xx as i64,
+                        "peak_hourly_requests": row.peak_hourly_requests as i64,
+                        "peak_hour": &row.peak_hour,
+                        "route_ids": &row.route_ids,
+                        "updated_at": &updated_at,
+                    } },
+                    upsert.clone(),
+                )
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Write hostname_usage_monthly {} {}: {}",
+                        month, row.hostname, e
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Rows of `month`, busiest hostname first
+    pub async fn get_hostname_usage(&self, month: &str) -> Result<Vec<HostnameUsage>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "requests": -1, "hostname": 1 })
+            .projection(doc! { "_id": 0 })
+            .build();
+        self.db
+            .collection::<HostnameUsage>(COLLECTION)
+            .find(doc! { "month": month }, options)
+            .await
+            .map_err(|e| format!("Query hostname_usage_monthly: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read hostname_usage_monthly: {}", e))
+    }
+}
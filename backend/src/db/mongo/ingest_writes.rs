@@ -0,0 +1,336 @@
+//! Write path for bulk ingestion (syncers → user_object_detail, cg_node_order)
+//!
+//! Ingestion writes go to their own small connection pool
+//! (`database.mongodb_ingest_pool_size`), so a large sync cannot hold every
+//! connection the access-log writes and the admin API need. Entries are
+//! written as bulk `update` commands of at most `mongo_ingest_batch_size`
+//! statements with `mongo_ingest_batch_delay_ms` between batches, which
+//! spreads a 2000-client controller over a few seconds instead of a burst.
+//!
+//! Each ingest cycle reports its write counts and durations per source and
+//! collection (GET /api/admin/ingest-writes).
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Bson, Document};
+use serde::Serialize;
+
+use super::MongoDb;
+use crate::db::mysql::MySqlDb;
+
+pub const DEFAULT_INGEST_POOL_SIZE: u32 = 4;
+pub const DEFAULT_BATCH_SIZE: usize = 200;
+pub const DEFAULT_BATCH_DELAY_MS: u64 = 250;
+/// Upper bound of `mongo_ingest_batch_size` (a bulk command is one document)
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// Batch size and delay between batches (settings `mongo_ingest_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IngestPacing {
+    pub batch_size: usize,
+    pub batch_delay_ms: u64,
+}
+
+impl Default for IngestPacing {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_delay_ms: DEFAULT_BATCH_DELAY_MS,
+        }
+    }
+}
+
+impl IngestPacing {
+    pub fn new(batch_size: i32, batch_delay_ms: i32) -> Self {
+        Self {
+            batch_size: (batch_size.max(1) as usize).min(MAX_BATCH_SIZE),
+            batch_delay_ms: batch_delay_ms.max(0) as u64,
+        }
+    }
+
+    pub async fn load(mysql: &MySqlDb) -> Self {
+        let setting = |key: &'static str, default: i32| async move {
+            mysql.get_setting_i32(key, default).await.unwrap_or(default)
+        };
+        Self::new(
+            setting("mongo_ingest_batch_size", DEFAULT_BATCH_SIZE as i32).await,
+            setting("mongo_ingest_batch_delay_ms", DEFAULT_BATCH_DELAY_MS as i32).await,
+        )
+    }
+}
+
+/// Writes of one ingest cycle into one collection
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestCycleWrites {
+    /// Syncer and device, e.g. `omada:<controller_id>`
+    pub source: String,
+    pub collection: &'static str,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Time inside bulk commands (the rest is pacing and reads)
+    pub write_ms: u64,
+    pub statements: u64,
+    pub batches: u64,
+    pub inserted: u64,
+    pub write_errors: u64,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl IngestCycleWrites {
+    pub fn start(source: impl Into<String>, collection: &'static str) -> Self {
+        Self {
+            source: source.into(),
+            collection,
+            started_at: Utc::now(),
+            duration_ms: 0,
+            write_ms: 0,
+            statements: 0,
+            batches: 0,
+            inserted: 0,
+            write_errors: 0,
+            last_error: None,
+            started: Some(Instant::now()),
+        }
+    }
+}
+
+/// Latest cycle and running totals of one source/collection
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestWriteStats {
+    pub last_cycle: IngestCycleWrites,
+    pub cycles: u64,
+    pub total_statements: u64,
+    pub total_write_ms: u64,
+}
+
+/// GET /api/admin/ingest-writes
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestWritesReport {
+    pub pool_size: u32,
+    pub pacing: IngestPacing,
+    pub sources: Vec<IngestWriteStats>,
+}
+
+/// Pacing and per-source stats shared by all ingesters
+pub struct IngestWrites {
+    pool_size: u32,
+    pacing: RwLock<IngestPacing>,
+    stats: Mutex<BTreeMap<(String, &'static str), IngestWriteStats>>,
+}
+
+impl IngestWrites {
+    pub fn new(pool_size: u32) -> Self {
+        Self {
+            pool_size,
+            pacing: RwLock::new(IngestPacing::default()),
+            stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn record(&self, cycle: IngestCycleWrites) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (cycle.source.clone(), cycle.collection);
+        match stats.get_mut(&key) {
+            Some(s) => {
+                s.cycles += 1;
+                s.total_statements += cycle.statements;
+                s.total_write_ms += cycle.write_ms;
+                s.last_cycle = cycle;
+            }
+            None => {
+                stats.insert(
+                    key,
+                    IngestWriteStats {
+                        cycles: 1,
+                        total_statements: cycle.statements,
+                        total_write_ms: cycle.write_ms,
+                        last_cycle: cycle,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// `{q, u, upsert}` statement of a bulk `update` command
+pub(super) fn update_statement(filter: Document, update: Document, upsert: bool) -> Document {
+    doc! { "q": filter, "u": update, "upsert": upsert }
+}
+
+impl MongoDb {
+    /// Apply pacing settings to the following ingest cycles
+    pub fn set_ingest_pacing(&self, pacing: IngestPacing) {
+        *self
+            .ingest
+            .pacing
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = pacing;
+    }
+
+    pub fn ingest_pacing(&self) -> IngestPacing {
+        *self.ingest.pacing.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Close a cycle and keep its counts
+    pub fn finish_ingest_cycle(&self, mut cycle: IngestCycleWrites) {
+        if let Some(started) = cycle.started.take() {
+            cycle.duration_ms = started.elapsed().as_millis() as u64;
+        }
+        tracing::debug!(
+            "[Ingest] {} → {}: {} statements in {} batches, {} ms ({} ms writing)",
+            cycle.source,
+            cycle.collection,
+            cycle.statements,
+            cycle.batches,
+            cycle.duration_ms,
+            cycle.write_ms
+        );
+        self.ingest.record(cycle);
+    }
+
+    pub fn ingest_writes_report(&self) -> IngestWritesReport {
+        let stats = self.ingest.stats.lock().unwrap_or_else(|e| e.into_inner());
+        IngestWritesReport {
+            pool_size: self.ingest.pool_size,
+            pacing: self.ingest_pacing(),
+            sources: stats.values().cloned().collect(),
+        }
+    }
+
+    /// Wait out the batch delay before every batch but the first
+    pub(super) async fn pace_ingest_batch(&self, index: usize, pacing: IngestPacing) {
+        if index == 0 {
+            return;
+        }
+        if pacing.batch_delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(pacing.batch_delay_ms)).await;
+        } else {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Run one bulk `update` command on the ingestion pool and return the
+    /// indexes of the statements that inserted. Statement failures are
+    /// counted in `cycle`; only a failed command is an Err.
+    pub(super) async fn ingest_bulk_update(
+        &self,
+        collection: &str,
+        statements: Vec<Document>,
+        cycle: &mut IngestCycleWrites,
+    ) -> Result<Vec<usize>, String> {
+        if statements.is_empty() {
+            return Ok(Vec::new());
+        }
+        let count = statements.len() as u64;
+        let started = Instant::now();
+        let reply = self
+            .ingest_db
+            .run_command(
+                doc! {
+                    "update": collection,
+                    "updates": statements.into_iter().map(Bson::Document).collect::<Vec<_>>(),
+                    "ordered": false,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Bulk update of {} failed: {}", collection, e))?;
+
+        cycle.write_ms += started.elapsed().as_millis() as u64;
+        cycle.batches += 1;
+        cycle.statements += count;
+        let upserted: Vec<usize> = reply
+            .get_array("upserted")
+            .map(|u| u.iter().filter_map(upserted_index).collect())
+            .unwrap_or_default();
+        cycle.inserted += upserted.len() as u64;
+        if let Ok(errors) = reply.get_array("writeErrors") {
+            cycle.write_errors += errors.len() as u64;
+            if let Some(Bson::Document(first)) = errors.first() {
+                let message = first.get_str("errmsg").unwrap_or("unknown error");
+                tracing::warn!(
+                    "[Ingest] {} of {} statements on {} failed: {}",
+                    errors.len(),
+                    count,
+                    collection,
+                    message
+                );
+                cycle.last_error = Some(message.to_string());
+            }
+        }
+        Ok(upserted)
+    }
+}
+
+/// Statement index of an `upserted` entry of an `update` reply
+fn upserted_index(upserted: &Bson) -> Option<usize> {
+    match upserted.as_document()?.get("index")? {
+        Bson::Int32(i) => usize::try_from(*i).ok(),
+        Bson::Int64(i) => usize::try_from(*i).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pacing_is_clamped() {
+        assert_eq!(
+            IngestPacing::new(0, -5),
+            IngestPacing {
+                batch_size: 1,
+                batch_delay_ms: 0
+            }
+        );
+        assert_eq!(IngestPacing::new(5000, 100).batch_size, MAX_BATCH_SIZE);
+        assert_eq!(IngestPacing::new(200, 250), IngestPacing::default());
+    }
+
+    #[test]
+    fn upserted_entries_map_to_statement_indexes() {
+        let reply = doc! {
+            "n": 3,
+            "upserted": [
+                { "index": 0, "_id": "AABBCCDDEEFF" },
+                { "index": 2_i64, "_id": "112233445566" },
+                { "_id": "no-index" },
+            ],
+        };
+        let indexes: Vec<usize> = reply
+            .get_array("upserted")
+            .unwrap()
+            .iter()
+            .filter_map(upserted_index)
+            .collect();
+        assert_eq!(indexes, vec![0, 2]);
+    }
+
+    #[test]
+    fn stats_keep_the_last_cycle_and_totals() {
+        let writes = IngestWrites::new(2);
+        let cycle = |statements: u64| {
+            let mut c = IngestCycleWrites::start("omada:c1", "user_object_detail");
+            c.statements = statements;
+            c.write_ms = 10;
+            c
+        };
+        writes.record(cycle(300));
+        writes.record(cycle(200));
+        writes.record(IngestCycleWrites::start("omada:c1", "cg_node_order"));
+
+        let stats = writes.stats.lock().unwrap();
+        assert_eq!(stats.len(), 2);
+        let uod = &stats[&("omada:c1".to_string(), "user_object_detail")];
+        assert_eq!(uod.cycles, 2);
+        assert_eq!(uod.total_statements, 500);
+        assert_eq!(uod.total_write_ms, 20);
+        assert_eq!(uod.last_cycle.statements, 200);
+    }
+}
@@ -0,0 +1,328 @@
+//! Per-IP daily rollups (collections `ip_daily_stats`, `rollup_state`)
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{AggregateOptions, FindOptions, IndexOptions, UpdateOptions};
+use mongodb::IndexModel;
+use serde::Deserialize;
+
+use super::MongoDb;
+use crate::alert_rules::IpCount;
+use crate::ip_stats::{day_key, IpDailyStat};
+
+const COLLECTION: &str = "ip_daily_stats";
+const STATE_COLLECTION: &str = "rollup_state";
+/// Rollup job id in `rollup_state`
+pub const ROLLUP_JOB: &str = "ip_daily_stats";
+
+/// Access log aggregate of one IP and day
+#[derive(Debug, Deserialize)]
+struct AccessDay {
+    #[serde(rename = "_id")]
+    ip: String,
+    requests: u64,
+    errors: u64,
+    distinct_paths: u64,
+    bytes: u64,
+    #[serde(default)]
+    countries: Vec<String>,
+    first_seen: String,
+    last_seen: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventDay {
+    #[serde(rename = "_id")]
+    ip: String,
+    count: u64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SustainedAggregate {
+    offenders: u64,
+    #[serde(default)]
+    top: Vec<IpCount>,
+}
+
+impl MongoDb {
+    /// Indexes for rollups, profile series and daily alert rules
+    pub async fn ensure_ip_daily_stats_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "ip": 1, "day": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder().keys(doc! { "day": 1 }).build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create ip_daily_stats indexes: {}", e))?;
+        Ok(())
+    }
+
+    /// Last day a daily rollup job (`rollup_state` id) fully rolled up
+    pub async fn rollup_completed_day(&self, job: &str) -> Result<Option<NaiveDate>, String> {
+        let state = self
+            .db
+            .collection::<Document>(STATE_COLLECTION)
+            .find_one(doc! { "_id": job }, None)
+            .await
+            .map_err(|e| format!("Read rollup state: {}", e))?;
+        Ok(state
+            .as_ref()
+            .and_then(|d| d.get_str("completed_day").ok())
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()))
+    }
+
+    pub async fn set_rollup_completed_day(&self, job: &str, day: NaiveDate) -> Result<(), String> {
+        self.db
+            .collection::<Document>(STATE_COLLECTION)
+            .update_one(
+                doc! { "_id": job },
+                doc! { "$set": {
+                    "completed_day": day_key(day),
+                    "updated_at": Utc::now().to_rfc3339(),
+                } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Write rollup state: {}", e))?;
+        Ok(())
+    }
+
+    /// Recompute one day's rows (absolute values, safe to repeat; rows of IPs
+    /// no longer seen that day are removed); returns the number of IPs
+    pub async fn roll_up_ip_day(&self, day: NaiveDate) -> Result<usize, String> {
+        let from = day_key(day);
+        let to = day_key(day.succ_opt().unwrap_or(day));
+        let range = doc! { "timestamp": { "$gte": &from, "$lt": &to } };
+        let options = AggregateOptions::builder().allow_disk_use(true).build();
+
+        let pipeline = vec![
+            doc! { "$match": range.clone() },
+            doc! {
+                "$group": {
+                    "_id": "$ip",
+                    "requests": { "$sum": 1 },
+                    "errors": { "$sum": { "$cond": [{ "$gte": ["$status", 400] }, 1, 0] } },
+                    "paths": { "$addToSet": "$path" },
+                    "bytes": { "$sum": { "$add": [
+                        { "$ifNull": ["$request_size", 0] },
+                        { "$ifNull": ["$response_size", 0] },
+                    ] } },
+                    "countries": { "$addToSet": "$country_code" },
+                    "first_seen": { "$min": "$timestamp" },
+                    "last_seen": { "$max": "$timestamp" },
+                }
+            },
+            doc! {
+                "$project": {
+                    "requests": 1,
+                    "errors": 1,
+                    "distinct_paths": { "$size": "$paths" },
+                    "bytes": 1,
+                    "countries": { "$filter": { "input": "$countries", "cond": { "$ne": ["$$this", null] } } },
+                    "first_seen": 1,
+                    "last_seen": 1,
+                }
+            },
+        ];
+        let access: Vec<AccessDay> = self
+            .db
+            .collection::<Document>("access_logs")
+            .aggregate(pipeline, options.clone())
+            .await
+            .map_err(|e| format!("Aggregate access logs for {}: {}", from, e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Read access log aggregate for {}: {}", from, e))?
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect();
+
+        let mut event_match = range;
+        event_match.insert("ip", doc! { "$type": "string" });
+        let pipeline = vec![
+            doc! { "$match": event_match },
+            doc! { "$group": { "_id": "$ip", "count": { "$sum": 1 } } },
+        ];
+        let events: Vec<EventDay> = self
+            .db
+            .collection::<Document>("security_events")
+            .aggregate(pipeline, options)
+            .await
+            .map_err(|e| format!("Aggregate security events for {}: {}", from, e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Read security event aggregate for {}: {}", from, e))?
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect();
+
+        let mut rows: HashMap<String, IpDailyStat> = HashMap::new();
+        for a in &access {
+            rows.insert(
+                a.ip.clone(),
+                IpDailyStat {
+                    ip: a.ip.clone(),
+                    day: from.clone(),
+                    requests: a.requests,
+                    errors: a.errors,
+                    distinct_paths: a.distinct_paths,
+                    bytes: a.bytes,
+                    countries: a.countries.clone(),
+                    security_events: 0,
+                },
+            );
+        }
+        for e in events {
+            rows.entry(e.ip.clone())
+                .or_insert_with(|| IpDailyStat {
+                    ip: e.ip,
+                    day: from.clone(),
+                    ..Default::default()
+                })
+                .security_events = e.count;
+        }
+
+        let stats = self.db.collection::<Document>(COLLECTION);
+        // IPs left without logs or events that day (bulk deletes)
+        let ips: Vec<&str> = rows.keys().map(String::as_str).collect();
+        stats
+            .delete_many(doc! { "day": &from, "ip": { "$nin": &ips } }, None)
+            .await
+            .map_err(|e| format!("Clear ip_daily_stats {}: {}", from, e))?;
+        let upsert = UpdateOptions::builder().upsert(true).build();
+        let updated_at = Utc::now().to_rfc3339();
+        for row in rows.values() {
+            stats
+                .update_one(
+                    doc! { "ip": &row.ip, "day": &row.day },
+                    doc! { "$set": {
+                        "requests": row.requests as i64,
+                        "errors": row.errors as i64,
+                        "distinct_paths": row.distinct_paths as i64,
+                        "bytes": row.bytes as i64,
+                        "countries": &row.countries,
+                        "security_events": row.security_events as i64,
+                        "updated_at": &updated_at,
+                    } },
+                    upsert.clone(),
+                )
+                .await
+                .map_err(|e| format!("Write ip_daily_stats {} {}: {}", row.ip, row.day, e))?;
+        }
+
+        // Longitudinal first/last seen of client IPs ($min/$max: repeatable)
+        let history = self.db.collection::<Document>("ip_history");
+        for a in &access {
+            history
+                .update_one(
+                    doc! { "ip": &a.ip, "source": "client" },
+                    doc! {
+                        "$min": { "first_seen": &a.first_seen },
+                        "$max": { "last_seen": &a.last_seen },
+                    },
+                    upsert.clone(),
+                )
+                .await
+                .map_err(|e| format!("Update ip_history {}: {}", a.ip, e))?;
+        }
+
+        Ok(rows.len())
+    }
+
+    /// An IP's rows from `from` on, oldest first
+    pub async fn get_ip_daily_stats(
+        &self,
+        ip: &str,
+        from: NaiveDate,
+    ) -> Result<Vec<IpDailyStat>, String> {
+        let options = FindOptions::builder().sort(doc! { "day": 1 }).build();
+        self.db
+            .collection::<IpDailyStat>(COLLECTION)
+            .find(
+                doc! {
+                    "ip": { "$in": crate::ip::ip_variants(ip) },
+                    "day": { "$gte": day_key(from) },
+                },
+                options,
+            )
+            .await
+            .map_err(|e| format!("Query ip_daily_stats: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read ip_daily_stats: {}", e))
+    }
+
+    /// Drop rows of days before `cutoff`
+    pub async fn purge_ip_daily_stats(&self, cutoff: NaiveDate) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(doc! { "day": { "$lt": day_key(cutoff) } }, None)
+            .await
+            .map_err(|e| format!("Purge ip_daily_stats: {}", e))?;
+        Ok(result.deleted_count)
+    }
+
+    /// IPs whose `field` was above `threshold` on every one of `days`, with
+    /// their lowest value of those days (highest first, at most `top`), and
+    /// how many there are
+    pub async fn sustained_ip_daily(
+        &self,
+        field: &str,
+        days: &[String],
+        threshold: f64,
+        conditions: Vec<Document>,
+        top: usize,
+    ) -> Result<(u64, Vec<IpCount>), String> {
+        let mut above = Document::new();
+        above.insert(field, doc! { "$gt": threshold });
+        let mut clauses = vec![doc! { "day": { "$in": days } }, above];
+        clauses.extend(conditions);
+
+        let pipeline = vec![
+            doc! { "$match": { "$and": clauses } },
+            doc! {
+                "$group": {
+                    "_id": "$ip",
+                    "days": { "$sum": 1 },
+                    "low": { "$min": format!("${}", field) },
+                }
+            },
+            doc! { "$match": { "days": { "$gte": days.len() as i64 } } },
+            doc! { "$sort": { "low": -1 } },
+            doc! {
+                "$group": {
+                    "_id": null,
+                    "offenders": { "$sum": 1 },
+                    "top": { "$push": { "ip": "$_id", "count": "$low" } },
+                }
+            },
+            doc! { "$project": { "offenders": 1, "top": { "$slice": ["$top", top as i64] } } },
+        ];
+
+        let mut cursor = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| format!("Aggregate ip_daily_stats: {}", e))?;
+        let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| format!("Read ip_daily_stats aggregate: {}", e))?
+        else {
+            return Ok((0, Vec::new()));
+        };
+        let aggregate: SustainedAggregate = bson::from_document(doc)
+            .map_err(|e| format!("Decode ip_daily_stats aggregate: {}", e))?;
+        Ok((aggregate.offenders, aggregate.top))
+    }
+}
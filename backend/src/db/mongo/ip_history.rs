@@ -49,7 +49,7 @@ impl MongoDb {
         collection
             .update_one(filter, update, Some(options))
             .await
-            .map_err(|e| AppError::InternalError(format!("Failed to upsert ip_history: {}", e)))?;
+            .map_err(|e| AppError::database(format!("Failed to upsert ip_history: {}", e)))?;
 
         Ok(())
     }
@@ -67,13 +67,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(filter, Some(options))
             .await
-            .map_err(|e| AppError::InternalError(format!("Failed to query ip_history: {}", e)))?;
+            .map_err(|e| AppError::database(format!("Failed to query ip_history: {}", e)))?;
 
         let mut ips = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(ip) = doc.get_str("ip") {
                 ips.push(ip.to_string());
@@ -0,0 +1,51 @@
+//! Batched age-based deletes for the log collections (see crate::log_retention)
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::FindOptions;
+
+use super::MongoDb;
+
+impl MongoDb {
+    /// Delete up to `batch_size` documents of `collection` whose `timestamp`
+    /// is before `before`, oldest first. Returns the number deleted (0 once
+    /// nothing is left).
+    pub async fn delete_logs_before_batch(
+        &self,
+        collection: &str,
+        before: DateTime<Utc>,
+        batch_size: i64,
+    ) -> Result<u64, String> {
+        let coll = self.db.collection::<Document>(collection);
+        // Timestamps are stored in chrono's serde format (UTC, "Z")
+        let filter = doc! {
+            "timestamp": { "$lt": before.to_rfc3339_opts(SecondsFormat::AutoSi, true) }
+        };
+
+        let options = FindOptions::builder()
+            .projection(doc! { "_id": 1 })
+            .sort(doc! { "timestamp": 1 })
+            .limit(batch_size)
+            .build();
+        let ids: Vec<bson::Bson> = coll
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Find old {}: {}", collection, e))?
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| format!("Read old {}: {}", collection, e))?
+            .into_iter()
+            .filter_map(|d| d.get("_id").cloned())
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = coll
+            .delete_many(doc! { "_id": { "$in": ids } }, None)
+            .await
+            .map_err(|e| format!("Delete old {}: {}", collection, e))?;
+        Ok(result.deleted_count)
+    }
+}
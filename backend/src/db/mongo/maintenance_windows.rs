@@ -0,0 +1,109 @@
+//! Maintenance windows (collection `maintenance_windows`)
+//!
+//! Window definitions are written by the API; `status` is written only by the
+//! health checker, so updates never touch it and vice versa.
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::FindOptions;
+
+use super::MongoDb;
+use crate::maintenance::{MaintenanceWindow, MaintenanceWindowStatus};
+
+impl MongoDb {
+    /// List all windows, oldest first
+    pub async fn list_maintenance_windows(&self) -> Result<Vec<MaintenanceWindow>, String> {
+        let collection = self
+            .db
+            .collection::<MaintenanceWindow>("maintenance_windows");
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        let cursor = collection
+            .find(doc! {}, options)
+            .await
+            .map_err(|e| format!("List maintenance windows: {}", e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read maintenance windows: {}", e))
+    }
+
+    pub async fn get_maintenance_window(
+        &self,
+        window_id: &str,
+    ) -> Result<Option<MaintenanceWindow>, String> {
+        let collection = self
+            .db
+            .collection::<MaintenanceWindow>("maintenance_windows");
+        collection
+            .find_one(doc! { "window_id": window_id }, None)
+            .await
+            .map_err(|e| format!("Get maintenance window {}: {}", window_id, e))
+    }
+
+    pub async fn insert_maintenance_window(
+        &self,
+        window: &MaintenanceWindow,
+    ) -> Result<(), String> {
+        let collection = self
+            .db
+            .collection::<MaintenanceWindow>("maintenance_windows");
+        collection
+            .insert_one(window, None)
+            .await
+            .map_err(|e| format!("Insert maintenance window: {}", e))?;
+        Ok(())
+    }
+
+    /// Save a window's definition (status and created_at are left as stored)
+    pub async fn update_maintenance_window(
+        &self,
+        window: &MaintenanceWindow,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>("maintenance_windows");
+        let mut fields = bson::to_document(window)
+            .map_err(|e| format!("Serialize maintenance window: {}", e))?;
+        fields.remove("status");
+        fields.remove("created_at");
+
+        let result = collection
+            .update_one(
+                doc! { "window_id": &window.window_id },
+                doc! { "$set": fields },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update maintenance window {}: {}", window.window_id, e))?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn delete_maintenance_window(&self, window_id: &str) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>("maintenance_windows");
+        let result = collection
+            .delete_one(doc! { "window_id": window_id }, None)
+            .await
+            .map_err(|e| format!("Delete maintenance window {}: {}", window_id, e))?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn set_maintenance_window_status(
+        &self,
+        window_id: &str,
+        status: &MaintenanceWindowStatus,
+    ) -> Result<(), String> {
+        let collection = self.db.collection::<Document>("maintenance_windows");
+        let status = bson::to_bson(status)
+            .map_err(|e| format!("Serialize maintenance window status: {}", e))?;
+        collection
+            .update_one(
+                doc! { "window_id": window_id },
+                doc! { "$set": { "status": status } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update maintenance window {} status: {}", window_id, e))?;
+        Ok(())
+    }
+}
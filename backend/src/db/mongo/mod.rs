@@ -1,17 +1,39 @@
 //! MongoDB database module
 
 mod access_log;
+mod alert_rules;
+pub mod api_trace;
+pub mod cluster;
+mod ddns_dns_checks;
+pub mod dependencies_health;
+pub mod device_search;
 pub mod external;
+pub mod facility_reports;
+pub mod forward_queue;
+mod hostname_usage;
+pub mod ingest_writes;
+pub mod ip_daily_stats;
 mod ip_history;
+mod log_retention;
+mod maintenance_windows;
 pub mod omada;
 pub mod openwrt;
 pub mod operation_logs;
+mod route_target_health;
+pub mod route_uptime;
+pub mod schema_migrations;
 mod security_events;
+pub mod storage;
 pub mod topology;
+pub mod topology_revision;
 pub mod user_object_detail;
 
+use std::sync::Arc;
+
 use mongodb::bson::doc;
+use mongodb::options::ClientOptions;
 use mongodb::{Client, Database};
+use tokio::sync::watch;
 
 use crate::config::Config;
 
@@ -23,6 +45,11 @@ pub use self::security_events::*;
 #[derive(Clone)]
 pub struct MongoDb {
     db: Database,
+    /// Same database on the bounded ingestion pool (see ingest_writes)
+    ingest_db: Database,
+    ingest: Arc<ingest_writes::IngestWrites>,
+    /// Latest topology revision seen by this instance (see topology_revision)
+    topology_rev: Arc<watch::Sender<i64>>,
 }
 
 impl MongoDb {
@@ -43,9 +70,20 @@ impl MongoDb {
         db.run_command(mongodb::bson::doc! { "ping": 1 }, None)
             .await?;
 
+        let pool_size = config.database.mongodb_ingest_pool_size.max(1);
+        let mut ingest_options = ClientOptions::parse(url).await?;
+        ingest_options.max_pool_size = Some(pool_size);
+        ingest_options.app_name = Some("lpg-ingest".to_string());
+        let ingest_db = Client::with_options(ingest_options)?.database("lacis_proxy");
+
         tracing::info!("MongoDB connected successfully");
 
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            ingest_db,
+            ingest: Arc::new(ingest_writes::IngestWrites::new(pool_size)),
+            topology_rev: Arc::new(topology_revision::revision_channel()),
+        })
     }
 
     /// Get the database handle
@@ -53,6 +91,15 @@ impl MongoDb {
         &self.db
     }
 
+    /// Check the server answers
+    pub async fn ping(&self) -> Result<(), String> {
+        self.db
+            .run_command(doc! { "ping": 1 }, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("MongoDB ping failed: {}", e))
+    }
+
     /// Assign a lacis_id to a device in the appropriate collection.
     /// `source`: "omada" → omada_devices (match by mac), "openwrt" → openwrt_routers (match by router_id), "external" → external_devices (match by device_id)
     pub async fn assign_lacis_id(
@@ -5,11 +5,12 @@
 //! - `omada_devices`: Network infrastructure devices (gateway, switch, AP)
 //! - `omada_clients`: Connected client endpoints
 //! - `omada_wg_peers`: WireGuard peers
+//! - `omada_webhook_secrets`: Per-controller shared secrets for inbound webhooks
 
 use chrono::Utc;
 use futures::TryStreamExt;
 use mongodb::bson::{self, doc};
-use mongodb::options::{FindOptions, UpdateOptions};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
 use serde::{Deserialize, Serialize};
 
 use super::MongoDb;
@@ -33,11 +34,19 @@ pub struct OmadaControllerDoc {
     pub omadac_id: String,
     pub controller_ver: String,
     pub api_ver: String,
-    /// "connected" | "error" | "disconnected"
+    /// "connected" | "partial" | "error" | "offline" | "disconnected"
+    /// ("partial": synced, but some client pages could not be fetched;
+    /// "offline": `consecutive_failures` reached the offline threshold)
     pub status: String,
     pub last_error: Option<String>,
     pub sites: Vec<OmadaSiteMapping>,
     pub last_synced_at: Option<String>,
+    /// Failed syncs since the last successful one
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Set while the controller counts as offline
+    #[serde(default)]
+    pub offline_since: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -77,11 +86,32 @@ pub struct OmadaDeviceDoc {
     pub product_type: String,
     /// mobes2.0 NetworkDeviceType: "Router" | "Switch" | "AccessPoint" | "Unknown"
     pub network_device_type: String,
+    /// Per-port status (switches only; refreshed each sync)
+    #[serde(default)]
+    pub ports: Vec<OmadaSwitchPortDoc>,
+    #[serde(default)]
+    pub ports_synced_at: Option<String>,
     pub synced_at: String,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Switch port row stored on the switch's omada_devices document
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OmadaSwitchPortDoc {
+    pub port: i32,
+    pub name: Option<String>,
+    pub disabled: bool,
+    pub link_up: bool,
+    pub link_speed_mbps: Option<u32>,
+    pub full_duplex: Option<bool>,
+    pub poe_enabled: bool,
+    pub poe_power_w: f64,
+    pub profile_name: Option<String>,
+    /// Normalized MACs of wired clients on this port (from the client list)
+    pub client_macs: Vec<String>,
+}
+
 /// Connected client document (omada_clients collection)
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OmadaClientDoc {
@@ -139,11 +169,30 @@ pub struct OmadaWgPeerDoc {
     pub allow_address: Vec<String>,
     pub keep_alive: i32,
     pub comment: Option<String>,
+    /// LPG-side expiry (RFC 3339); the peer is disabled once it passes
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// When the expiry check disabled the peer
+    #[serde(default)]
+    pub expired_at: Option<String>,
+    /// Last failure to disable the expired peer on the controller
+    #[serde(default)]
+    pub expiry_error: Option<String>,
     pub synced_at: String,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Webhook shared secret (omada_webhook_secrets collection).
+/// Kept apart from the controller document so it never appears in listings.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OmadaWebhookSecretDoc {
+    pub controller_id: String,
+    pub secret: String,
+    pub created_at: String,
+    pub rotated_at: String,
+}
+
 /// Aggregated summary across all controllers
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OmadaSummaryDoc {
@@ -263,13 +312,44 @@ impl MongoDb {
 
         self.db
             .collection::<bson::Document>("omada_wg_peers")
-            .delete_many(filter, None)
+            .delete_many(filter.clone(), None)
             .await
             .map_err(|e| format!("Delete controller wg_peers: {}", e))?;
 
+        self.db
+            .collection::<bson::Document>("omada_webhook_secrets")
+            .delete_one(filter, None)
+            .await
+            .map_err(|e| format!("Delete controller webhook secret: {}", e))?;
+
         Ok(())
     }
 
+    /// Set (or clear) the facility mapping of one site. Returns false when
+    /// the controller has no such site.
+    pub async fn set_omada_site_facility(
+        &self,
+        controller_id: &str,
+        site_id: &str,
+        fid: Option<&str>,
+        fid_display_name: Option<&str>,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("omada_controllers");
+        let result = collection
+            .update_one(
+                doc! { "controller_id": controller_id, "sites.site_id": site_id },
+                doc! { "$set": {
+                    "sites.$.fid": fid,
+                    "sites.$.fid_display_name": fid_display_name,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Set site facility: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
     /// Update controller status and optional error message
     pub async fn update_omada_controller_status(
         &self,
@@ -285,9 +365,11 @@ impl MongoDb {
             "updated_at": &now,
         };
 
-        if status == "connected" {
+        if status == "connected" || status == "partial" {
             set_doc.insert("last_synced_at", &now);
             set_doc.insert("last_error", bson::Bson::Null);
+            set_doc.insert("consecutive_failures", 0);
+            set_doc.insert("offline_since", bson::Bson::Null);
         }
 
         if let Some(err) = error {
@@ -306,6 +388,59 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Count a failed sync; returns the consecutive failures including this
+    /// one. An offline controller keeps its "offline" status.
+    pub async fn record_omada_controller_failure(
+        &self,
+        controller_id: &str,
+        error: &str,
+    ) -> Result<u32, String> {
+        let collection = self
+            .db
+            .collection::<OmadaControllerDoc>("omada_controllers");
+        let updated = collection
+            .find_one_and_update(
+                doc! { "controller_id": controller_id },
+                vec![doc! { "$set": {
+                    "consecutive_failures": {
+                        "$add": [{ "$ifNull": ["$consecutive_failures", 0] }, 1]
+                    },
+                    "status": {
+                        "$cond": [{ "$eq": ["$status", "offline"] }, "offline", "error"]
+                    },
+                    "last_error": error,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }}],
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Record controller failure: {}", e))?;
+
+        Ok(updated.map(|c| c.consecutive_failures).unwrap_or(0))
+    }
+
+    /// Mark a controller offline (after repeated sync failures)
+    pub async fn set_omada_controller_offline(&self, controller_id: &str) -> Result<(), String> {
+        let collection = self.db.collection::<bson::Document>("omada_controllers");
+        let now = Utc::now().to_rfc3339();
+        collection
+            .update_one(
+                doc! { "controller_id": controller_id },
+                doc! { "$set": {
+                    "status": "offline",
+                    "offline_since": &now,
+                    "updated_at": &now,
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Mark controller offline: {}", e))?;
+
+        Ok(())
+    }
+
     // ========================================================================
     // Devices
     // ========================================================================
@@ -363,6 +498,42 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Replace a switch's port table
+    pub async fn set_omada_device_ports(
+        &self,
+        controller_id: &str,
+        mac: &str,
+        ports: &[OmadaSwitchPortDoc],
+    ) -> Result<(), String> {
+        let collection = self.db.collection::<bson::Document>("omada_devices");
+        let ports_bson = bson::to_bson(ports).map_err(|e| format!("Encode ports: {}", e))?;
+        collection
+            .update_one(
+                doc! { "mac": normalize_mac(mac), "controller_id": controller_id },
+                doc! { "$set": {
+                    "ports": ports_bson,
+                    "ports_synced_at": Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Set device ports {}: {}", mac, e))?;
+        Ok(())
+    }
+
+    /// Find a device by MAC (any controller)
+    pub async fn get_omada_device_by_mac(
+        &self,
+        mac: &str,
+    ) -> Result<Option<OmadaDeviceDoc>, String> {
+        let collection = self.db.collection::<bson::Document>("omada_devices");
+        let doc = collection
+            .find_one(doc! { "mac": normalize_mac(mac) }, None)
+            .await
+            .map_err(|e| format!("Get device {}: {}", mac, e))?;
+        Ok(doc.and_then(|d| bson::from_document(d).ok()))
+    }
+
     /// Get devices with optional filters
     pub async fn get_omada_devices(
         &self,
@@ -485,6 +656,32 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Mark a site's active clients inactive unless listed in `present_macs`
+    /// (only after a complete client fetch); returns the number marked
+    pub async fn mark_omada_clients_inactive_except(
+        &self,
+        controller_id: &str,
+        site_id: &str,
+        present_macs: &[String],
+    ) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<bson::Document>("omada_clients")
+            .update_many(
+                doc! {
+                    "controller_id": controller_id,
+                    "site_id": site_id,
+                    "active": true,
+                    "mac": { "$nin": present_macs },
+                },
+                doc! { "$set": { "active": false, "updated_at": Utc::now().to_rfc3339() } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Mark inactive clients: {}", e))?;
+        Ok(result.modified_count)
+    }
+
     /// Get clients with optional filters
     pub async fn get_omada_clients(
         &self,
@@ -615,6 +812,114 @@ impl MongoDb {
         Ok(peers)
     }
 
+    /// Get one WireGuard peer by Omada peer id
+    pub async fn get_omada_wg_peer(&self, peer_id: &str) -> Result<Option<OmadaWgPeerDoc>, String> {
+        let collection = self.db.collection::<bson::Document>("omada_wg_peers");
+        let doc = collection
+            .find_one(doc! { "peer_id": peer_id }, None)
+            .await
+            .map_err(|e| format!("Get wg_peer {}: {}", peer_id, e))?;
+        doc.map(|d| {
+            bson::from_document(d).map_err(|e| format!("Decode wg_peer {}: {}", peer_id, e))
+        })
+        .transpose()
+    }
+
+    /// Set or clear a peer's expiry. Clears the expired marker and last
+    /// expiry error, so an extended peer is treated as live again.
+    pub async fn set_omada_wg_peer_expiry(
+        &self,
+        peer_id: &str,
+        expires_at: Option<&str>,
+    ) -> Result<bool, String> {
+        self.set_wg_peer_expiry(doc! { "peer_id": peer_id }, expires_at)
+            .await
+    }
+
+    /// Set a peer's expiry by public key (for peers created before their
+    /// Omada id is known)
+    pub async fn set_omada_wg_peer_expiry_by_key(
+        &self,
+        controller_id: &str,
+        site_id: &str,
+        public_key: &str,
+        expires_at: Option<&str>,
+    ) -> Result<bool, String> {
+        self.set_wg_peer_expiry(
+            doc! { "controller_id": controller_id, "site_id": site_id, "public_key": public_key },
+            expires_at,
+        )
+        .await
+    }
+
+    async fn set_wg_peer_expiry(
+        &self,
+        filter: bson::Document,
+        expires_at: Option<&str>,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("omada_wg_peers");
+        let update = doc! {
+            "$set": {
+                "expires_at": expires_at,
+                "expired_at": null,
+                "expiry_error": null,
+                "updated_at": Utc::now().to_rfc3339(),
+            }
+        };
+        let result = collection
+            .update_one(filter, update, None)
+            .await
+            .map_err(|e| format!("Set wg_peer expiry: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Enabled peers whose expiry has passed
+    pub async fn list_expired_wg_peers(&self, now: &str) -> Result<Vec<OmadaWgPeerDoc>, String> {
+        let collection = self.db.collection::<bson::Document>("omada_wg_peers");
+        let mut cursor = collection
+            .find(
+                doc! { "status": true, "expires_at": { "$ne": null, "$lte": now } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Get expired wg_peers: {}", e))?;
+
+        let mut peers = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| format!("Cursor wg_peers: {}", e))?
+        {
+            if let Ok(peer) = bson::from_document(doc) {
+                peers.push(peer);
+            }
+        }
+        Ok(peers)
+    }
+
+    /// Record the result of disabling an expired peer: on success the peer
+    /// is stored disabled (the next sync confirms it), on failure the error
+    /// is kept for the listing
+    pub async fn record_wg_peer_expiry(
+        &self,
+        peer_id: &str,
+        error: Option<&str>,
+    ) -> Result<(), String> {
+        let collection = self.db.collection::<bson::Document>("omada_wg_peers");
+        let now = Utc::now().to_rfc3339();
+        let update = match error {
+            None => doc! {
+                "$set": { "status": false, "expired_at": &now, "expiry_error": null, "updated_at": &now }
+            },
+            Some(e) => doc! { "$set": { "expiry_error": e, "updated_at": &now } },
+        };
+        collection
+            .update_one(doc! { "peer_id": peer_id }, update, None)
+            .await
+            .map_err(|e| format!("Record wg_peer {} expiry: {}", peer_id, e))?;
+        Ok(())
+    }
+
     // ========================================================================
     // Summary (aggregated counts across all controllers)
     // ========================================================================
@@ -688,4 +993,107 @@ impl MongoDb {
             active_wg_peers,
         })
     }
+
+    // ========================================================================
+    // Webhook
+    // ========================================================================
+
+    /// Get the webhook secret for a controller
+    pub async fn get_omada_webhook_secret(
+        &self,
+        controller_id: &str,
+    ) -> Result<Option<OmadaWebhookSecretDoc>, String> {
+        let collection = self
+            .db
+            .collection::<bson::Document>("omada_webhook_secrets");
+
+        let doc = collection
+            .find_one(doc! { "controller_id": controller_id }, None)
+            .await
+            .map_err(|e| format!("Get webhook secret: {}", e))?;
+
+        match doc {
+            Some(d) => {
+                Ok(Some(bson::from_document(d).map_err(|e| {
+                    format!("Deserialize webhook secret: {}", e)
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Set (or rotate) the webhook secret for a controller
+    pub async fn set_omada_webhook_secret(
+        &self,
+        controller_id: &str,
+        secret: &str,
+    ) -> Result<OmadaWebhookSecretDoc, String> {
+        let collection = self
+            .db
+            .collection::<bson::Document>("omada_webhook_secrets");
+        let now = Utc::now().to_rfc3339();
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        collection
+            .update_one(
+                doc! { "controller_id": controller_id },
+                doc! {
+                    "$set": { "secret": secret, "rotated_at": &now },
+                    "$setOnInsert": { "controller_id": controller_id, "created_at": &now },
+                },
+                Some(options),
+            )
+            .await
+            .map_err(|e| format!("Set webhook secret: {}", e))?;
+
+        self.get_omada_webhook_secret(controller_id)
+            .await?
+            .ok_or_else(|| "Webhook secret not found after upsert".to_string())
+    }
+
+    /// Update a single device's online status (webhook fast path)
+    pub async fn set_omada_device_status(
+        &self,
+        controller_id: &str,
+        mac: &str,
+        status: i32,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("omada_devices");
+        let result = collection
+            .update_one(
+                doc! { "mac": normalize_mac(mac), "controller_id": controller_id },
+                doc! { "$set": {
+                    "status": status,
+                    "updated_at": Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update device status: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Update a single client's active flag (webhook fast path)
+    pub async fn set_omada_client_active(
+        &self,
+        controller_id: &str,
+        mac: &str,
+        active: bool,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("omada_clients");
+        let now = Utc::now().to_rfc3339();
+        let result = collection
+            .update_many(
+                doc! { "mac": normalize_mac(mac), "controller_id": controller_id },
+                doc! { "$set": {
+                    "active": active,
+                    "last_seen_at": &now,
+                    "updated_at": &now,
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Update client active: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
 }
@@ -41,6 +41,12 @@ pub struct OpenWrtRouterDoc {
     pub product_type: String,
     pub network_device_type: String,
     pub last_polled_at: Option<String>,
+    /// Poll interval override in seconds (None = syncer default)
+    #[serde(default)]
+    pub poll_interval_sec: Option<u32>,
+    /// Skipped by the syncer; cached clients and topology entries are kept
+    #[serde(default)]
+    pub paused: bool,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -52,6 +58,13 @@ pub struct OpenWrtClientDoc {
     pub ip: String,
     pub hostname: Option<String>,
     pub lacis_id: Option<String>,
+    /// Associated to a wireless interface / SSID at the last poll
+    #[serde(default)]
+    pub wireless: bool,
+    #[serde(default)]
+    pub ssid: Option<String>,
+    #[serde(default)]
+    pub band: Option<String>,
     pub active: bool,
     pub last_seen_at: String,
     pub synced_at: String,
@@ -59,6 +72,14 @@ pub struct OpenWrtClientDoc {
     pub updated_at: String,
 }
 
+/// Editable fields for PUT /api/openwrt/routers/:id (None = unchanged)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OpenWrtRouterUpdate {
+    pub display_name: Option<String>,
+    pub poll_interval_sec: Option<u32>,
+    pub paused: Option<bool>,
+}
+
 // ============================================================================
 // MongoDB operations
 // ============================================================================
@@ -125,6 +146,37 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Update poll schedule / pause state; returns false when the router is unknown.
+    /// `poll_interval_sec: Some(0)` resets to the syncer default. Pausing sets
+    /// status "paused"; resuming sets "offline" until the next poll.
+    pub async fn update_openwrt_router_settings(
+        &self,
+        router_id: &str,
+        update: &OpenWrtRouterUpdate,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<bson::Document>("openwrt_routers");
+        let mut set = doc! { "updated_at": Utc::now().to_rfc3339() };
+
+        if let Some(name) = &update.display_name {
+            set.insert("display_name", name);
+        }
+        if let Some(interval) = update.poll_interval_sec {
+            let value = (interval > 0).then_some(interval as i64);
+            set.insert("poll_interval_sec", value);
+        }
+        if let Some(paused) = update.paused {
+            set.insert("paused", paused);
+            set.insert("status", if paused { "paused" } else { "offline" });
+        }
+
+        let result = collection
+            .update_one(doc! { "router_id": router_id }, doc! { "$set": set }, None)
+            .await
+            .map_err(|e| format!("Update router {}: {}", router_id, e))?;
+
+        Ok(result.matched_count > 0)
+    }
+
     /// List all routers
     pub async fn list_openwrt_routers(&self) -> Result<Vec<OpenWrtRouterDoc>, String> {
         let collection = self.db.collection::<bson::Document>("openwrt_routers");
@@ -222,6 +274,9 @@ impl MongoDb {
                     "router_id": router_id,
                     "ip": &client.ip,
                     "hostname": &client.hostname,
+                    "wireless": client.wireless,
+                    "ssid": &client.ssid,
+                    "band": &client.band,
                     "active": true,
                     "last_seen_at": &now,
                     "synced_at": &now,
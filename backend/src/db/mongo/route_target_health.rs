@@ -0,0 +1,102 @@
+//! Latest health probe per fallback target (collection `route_target_health`)
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{IndexOptions, UpdateOptions};
+use mongodb::IndexModel;
+
+use super::MongoDb;
+use crate::health::TargetProbe;
+use crate::proxy::failover::FallbackCheck;
+
+const COLLECTION: &str = "route_target_health";
+
+impl MongoDb {
+    pub async fn ensure_route_target_health_indexes(&self) -> Result<(), String> {
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "route_id": 1, "target": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create route_target_health index: {}", e))?;
+        Ok(())
+    }
+
+    /// Store a fallback probe, counting consecutive failures
+    pub async fn save_fallback_check(
+        &self,
+        route_id: i32,
+        probe: &TargetProbe,
+    ) -> Result<(), String> {
+        let mut set = bson::to_document(probe).map_err(|e| format!("Encode: {}", e))?;
+        set.insert("route_id", route_id);
+        set.insert(
+            "checked_at",
+            bson::to_bson(&Utc::now()).map_err(|e| format!("Encode: {}", e))?,
+        );
+        let update = if probe.healthy {
+            set.insert("consecutive_failures", 0);
+            doc! { "$set": set }
+        } else {
+            doc! { "$set": set, "$inc": { "consecutive_failures": 1 } }
+        };
+        self.db
+            .collection::<Document>(COLLECTION)
+            .update_one(
+                doc! { "route_id": route_id, "target": &probe.target },
+                update,
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Save route_target_health: {}", e))?;
+        Ok(())
+    }
+
+    /// Fallback probes of one route, or of every route (None)
+    pub async fn list_fallback_checks(
+        &self,
+        route_id: Option<i32>,
+    ) -> Result<Vec<FallbackCheck>, String> {
+        let filter = match route_id {
+            Some(id) => doc! { "route_id": id },
+            None => doc! {},
+        };
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .find(filter, None)
+            .await
+            .map_err(|e| format!("Query route_target_health: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read route_target_health: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Drop probes of targets that are no longer fallbacks of the route
+    pub async fn retain_fallback_checks(
+        &self,
+        route_id: i32,
+        targets: &[String],
+    ) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(
+                doc! { "route_id": route_id, "target": { "$nin": targets } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Purge route_target_health: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
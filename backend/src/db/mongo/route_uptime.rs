@@ -0,0 +1,220 @@
+//! Per-route daily uptime rollups (collection `route_uptime_daily`) and
+//! status page reads of `health_checks`
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::options::{
+    AggregateOptions, FindOneOptions, FindOptions, IndexOptions, UpdateOptions,
+};
+use mongodb::IndexModel;
+use serde::Deserialize;
+
+use super::MongoDb;
+use crate::ip_stats::day_key;
+use crate::status_page::{CheckPoint, RouteUptimeDay};
+
+const COLLECTION: &str = "route_uptime_daily";
+const HEALTH_CHECKS: &str = "health_checks";
+
+/// Health check timestamps are stored in chrono's serde format (UTC, "Z")
+fn check_stamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[derive(Debug, Deserialize)]
+struct UptimeAggregate {
+    #[serde(rename = "_id")]
+    route_id: i32,
+    checks: u64,
+    healthy: u64,
+}
+
+impl MongoDb {
+    /// Rollup indexes plus the per-route health check index the status page reads
+    pub async fn ensure_route_uptime_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "route_id": 1, "day": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            IndexModel::builder().keys(doc! { "day": 1 }).build(),
+        ];
+        self.db
+            .collection::<Document>(COLLECTION)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Create route_uptime_daily indexes: {}", e))?;
+        self.db
+            .collection::<Document>(HEALTH_CHECKS)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "route_id": 1, "timestamp": -1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(|e| format!("Create health_checks index: {}", e))?;
+        Ok(())
+    }
+
+    /// Recompute one day's rows from the health checks (absolute values, safe
+    /// to repeat); returns the number of routes
+    pub async fn roll_up_route_uptime_day(&self, day: NaiveDate) -> Result<usize, String> {
+        let from = day_key(day);
+        let to = day_key(day.succ_opt().unwrap_or(day));
+        let pipeline = vec![
+            doc! { "$match": { "timestamp": { "$gte": &from, "$lt": &to } } },
+            doc! {
+                "$group": {
+                    "_id": "$route_id",
+                    "checks": { "$sum": 1 },
+                    "healthy": { "$sum": { "$cond": ["$healthy", 1, 0] } },
+                }
+            },
+        ];
+        let rows: Vec<UptimeAggregate> = self
+            .db
+            .collection::<Document>(HEALTH_CHECKS)
+            .aggregate(
+                pipeline,
+                AggregateOptions::builder().allow_disk_use(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Aggregate health checks for {}: {}", from, e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Read health check aggregate for {}: {}", from, e))?
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect();
+
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let upsert = UpdateOptions::builder().upsert(true).build();
+        let updated_at = Utc::now().to_rfc3339();
+        for row in &rows {
+            collection
+                .update_one(
+                    doc! { "route_id": row.route_id, "day": &from },
+                    doc! { "$set": {
+                        "checks": row.checks as i64,
+                        "healthy": row.healthy as i64,
+                        "updated_at": &updated_at,
+                    } },
+                    upsert.clone(),
+                )
+                .await
+                .map_err(|e| format!("Save route uptime for {}: {}", from, e))?;
+        }
+        Ok(rows.len())
+    }
+
+    /// Rollup rows of the given routes from `from` (inclusive)
+    pub async fn route_uptime_days(
+        &self,
+        route_ids: &[i32],
+        from: NaiveDate,
+    ) -> Result<Vec<RouteUptimeDay>, String> {
+        if route_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .find(
+                doc! { "route_id": { "$in": route_ids }, "day": { "$gte": day_key(from) } },
+                FindOptions::builder().sort(doc! { "day": 1 }).build(),
+            )
+            .await
+            .map_err(|e| format!("Query route_uptime_daily: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read route_uptime_daily: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// Drop rows of days before `before`
+    pub async fn purge_route_uptime(&self, before: NaiveDate) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .delete_many(doc! { "day": { "$lt": day_key(before) } }, None)
+            .await
+            .map_err(|e| format!("Purge route_uptime_daily: {}", e))?;
+        Ok(result.deleted_count)
+    }
+
+    /// A route's health check outcomes since `since`, oldest first
+    pub async fn route_checks_since(
+        &self,
+        route_id: i32,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<CheckPoint>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": 1 })
+            .projection(doc! { "_id": 0, "timestamp": 1, "healthy": 1 })
+            .build();
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(HEALTH_CHECKS)
+            .find(
+                doc! { "route_id": route_id, "timestamp": { "$gte": check_stamp(since) } },
+                options,
+            )
+            .await
+            .map_err(|e| format!("Query health_checks: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read health_checks: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    /// First failed check of the failure streak still running at `before`:
+    /// the first check after the last healthy one (None without checks)
+    pub async fn failure_streak_start(
+        &self,
+        route_id: i32,
+        before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, String> {
+        let collection = self.db.collection::<Document>(HEALTH_CHECKS);
+        let last_healthy = collection
+            .find_one(
+                doc! {
+                    "route_id": route_id,
+                    "healthy": true,
+                    "timestamp": { "$lt": check_stamp(before) },
+                },
+                FindOneOptions::builder()
+                    .sort(doc! { "timestamp": -1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Query health_checks: {}", e))?;
+
+        // Compare against the stored string itself, not a re-formatted time
+        let mut filter = doc! { "route_id": route_id };
+        if let Some(stamp) = last_healthy
+            .as_ref()
+            .and_then(|d| d.get_str("timestamp").ok())
+        {
+            filter.insert("timestamp", doc! { "$gt": stamp });
+        }
+        let first = collection
+            .find_one(
+                filter,
+                FindOneOptions::builder()
+                    .sort(doc! { "timestamp": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Query health_checks: {}", e))?
+            .and_then(|d| bson::from_document::<CheckPoint>(d).ok());
+        Ok(first.map(|c| c.timestamp))
+    }
+}
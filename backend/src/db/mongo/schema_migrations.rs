@@ -0,0 +1,78 @@
+//! Startup migration records (collection `schema_migrations`)
+//!
+//! One document per migration id (`_id`), rewritten on every run. The first
+//! run time is kept so "applied since" survives forced re-runs.
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc};
+use mongodb::options::UpdateOptions;
+use serde::{Deserialize, Serialize};
+
+use super::MongoDb;
+
+// ============================================================================
+// Document types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaMigrationDoc {
+    #[serde(rename = "_id")]
+    pub migration_id: String,
+    pub description: String,
+    /// "applied" | "skipped" | "failed"
+    pub outcome: String,
+    /// What the run did, or the error
+    pub detail: String,
+    /// Last run was a forced re-run
+    pub forced: bool,
+    pub run_count: i64,
+    pub duration_ms: i64,
+    pub first_run_at: String,
+    pub last_run_at: String,
+}
+
+// ============================================================================
+// MongoDB operations
+// ============================================================================
+
+impl MongoDb {
+    pub async fn list_schema_migrations(&self) -> Result<Vec<SchemaMigrationDoc>, String> {
+        let collection = self
+            .db
+            .collection::<SchemaMigrationDoc>("schema_migrations");
+        let cursor = collection
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| format!("List schema migrations: {}", e))?;
+        cursor
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read schema migrations: {}", e))
+    }
+
+    /// Record a run (first_run_at is set only by the first one)
+    pub async fn record_schema_migration(&self, record: &SchemaMigrationDoc) -> Result<(), String> {
+        let collection = self
+            .db
+            .collection::<SchemaMigrationDoc>("schema_migrations");
+        let mut fields =
+            bson::to_document(record).map_err(|e| format!("Serialize schema migration: {}", e))?;
+        fields.remove("_id");
+        fields.remove("first_run_at");
+        fields.remove("run_count");
+
+        collection
+            .update_one(
+                doc! { "_id": &record.migration_id },
+                doc! {
+                    "$set": fields,
+                    "$setOnInsert": { "first_run_at": &record.first_run_at },
+                    "$inc": { "run_count": 1_i64 },
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Record schema migration {}: {}", record.migration_id, e))?;
+        Ok(())
+    }
+}
@@ -5,8 +5,13 @@ use futures::TryStreamExt;
 use mongodb::bson::{self, doc};
 use mongodb::options::FindOptions;
 
+use crate::alert_rules::{AlertEvaluation, AlertRule};
 use crate::error::AppError;
-use crate::models::{SecurityEvent, SecurityEventSearchQuery, SecurityEventType, Severity};
+use crate::models::{
+    RateLimitRule, SecurityEvent, SecurityEventSearchQuery, SecurityEventType, Severity,
+};
+use crate::new_device::NewDeviceAlert;
+use crate::proxy::geo_rules::GeoMode;
 
 use super::MongoDb;
 
@@ -15,12 +20,12 @@ impl MongoDb {
     pub async fn log_security_event(&self, event: &SecurityEvent) -> Result<(), AppError> {
         let collection = self.db.collection::<bson::Document>("security_events");
 
-        let doc = bson::to_document(event).map_err(|e| AppError::InternalError(e.to_string()))?;
+        let doc = bson::to_document(event).map_err(|e| AppError::database(e.to_string()))?;
 
         collection
             .insert_one(doc, None)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         Ok(())
     }
@@ -33,6 +38,7 @@ impl MongoDb {
         severity: Severity,
     ) -> Result<(), AppError> {
         let event = SecurityEvent {
+            id: None,
             timestamp: Utc::now(),
             event_type: SecurityEventType::IpBlocked,
             ip: Some(ip.to_string()),
@@ -44,13 +50,137 @@ impl MongoDb {
         self.log_security_event(&event).await
     }
 
-    /// Log a rate limit exceeded event
-    pub async fn log_rate_limit_exceeded(&self, ip: &str, requests: i32) -> Result<(), AppError> {
+    /// Log a client running out of a rate limit rule's requests (`blocked`:
+    /// the request was refused with 429)
+    pub async fn log_rate_limit_exceeded(
+        &self,
+        ip: &str,
+        rule: &RateLimitRule,
+        path: &str,
+        blocked: bool,
+    ) -> Result<(), AppError> {
         let event = SecurityEvent {
+            id: None,
             timestamp: Utc::now(),
             event_type: SecurityEventType::RateLimitExceeded,
             ip: Some(ip.to_string()),
-            details: serde_json::json!({ "requests": requests }),
+            details: serde_json::json!({
+                "rule_id": rule.id,
+                "path_prefix": rule.path_prefix,
+                "path": path,
+                "requests": rule.requests_per_window,
+                "window_sec": rule.window_sec,
+                "action": rule.action,
+                "blocked": blocked,
+            }),
+            severity: if blocked {
+                Severity::High
+            } else {
+                Severity::Medium
+            },
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
+    /// Log a request path refused by proxy path normalization
+    pub async fn log_path_rejected(
+        &self,
+        ip: &str,
+        path: &str,
+        reason: &str,
+    ) -> Result<(), AppError> {
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::SuspiciousActivity,
+            ip: Some(ip.to_string()),
+            details: serde_json::json!({
+                "kind": "path_normalization",
+                "reason": reason,
+                "path": path,
+            }),
+            severity: Severity::Medium,
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
+    /// Log a request to an admin_network_only route from a non-admin network
+    pub async fn log_admin_network_denied(
+        &self,
+        ip: &str,
+        route_id: i32,
+        path: &str,
+    ) -> Result<(), AppError> {
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::SuspiciousActivity,
+            ip: Some(ip.to_string()),
+            details: serde_json::json!({
+                "kind": "admin_network_only",
+                "route_id": route_id,
+                "path": path,
+            }),
+            severity: Severity::Low,
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
+    /// Log a request body refused for exceeding the route's limit
+    /// (`declared`: its Content-Length, None when counted while streaming)
+    pub async fn log_body_too_large(
+        &self,
+        ip: &str,
+        route_id: i32,
+        path: &str,
+        limit: usize,
+        declared: Option<u64>,
+    ) -> Result<(), AppError> {
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::SuspiciousActivity,
+            ip: Some(ip.to_string()),
+            details: serde_json::json!({
+                "kind": "request_body_limit",
+                "route_id": route_id,
+                "path": path,
+                "limit_bytes": limit,
+                "declared_bytes": declared,
+            }),
+            severity: Severity::Medium,
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
+    /// Log a request refused by the country rules (`country_code` None:
+    /// unresolved client)
+    pub async fn log_geo_denied(
+        &self,
+        ip: &str,
+        country_code: Option<&str>,
+        path: &str,
+        mode: GeoMode,
+    ) -> Result<(), AppError> {
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::SuspiciousActivity,
+            ip: Some(ip.to_string()),
+            details: serde_json::json!({
+                "kind": "geo_rule",
+                "country_code": country_code,
+                "mode": mode,
+                "path": path,
+            }),
             severity: Severity::Medium,
             notified: false,
         };
@@ -58,6 +188,57 @@ impl MongoDb {
         self.log_security_event(&event).await
     }
 
+    /// Log a device seen on the network for the first time
+    pub async fn log_new_device(&self, alert: &NewDeviceAlert) -> Result<(), AppError> {
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::NewDevice,
+            ip: alert.ip.clone(),
+            details: serde_json::to_value(alert).map_err(|e| AppError::database(e.to_string()))?,
+            severity: Severity::Low,
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
+    /// Log a fired alert rule
+    pub async fn log_alert_rule(
+        &self,
+        rule: &AlertRule,
+        evaluation: &AlertEvaluation,
+    ) -> Result<(), AppError> {
+        // Attribute the event to an IP only when a single source caused it
+        let ip = match evaluation.stats.top_ips.as_slice() {
+            [only] if evaluation.stats.unique_ips == 1 => Some(only.ip.clone()),
+            _ => None,
+        };
+        let event = SecurityEvent {
+            id: None,
+            timestamp: Utc::now(),
+            event_type: SecurityEventType::AlertRule,
+            ip,
+            details: serde_json::json!({
+                "rule_id": rule.rule_id,
+                "rule_name": rule.name,
+                "filter": rule.filter,
+                "metric": rule.metric,
+                "window_minutes": rule.window_minutes,
+                "threshold": rule.threshold,
+                "value": evaluation.value,
+                "matched": evaluation.stats.matched,
+                "errors": evaluation.stats.errors,
+                "unique_ips": evaluation.stats.unique_ips,
+                "top_ips": evaluation.stats.top_ips,
+            }),
+            severity: rule.severity,
+            notified: false,
+        };
+
+        self.log_security_event(&event).await
+    }
+
     /// Log a DDNS failure event
     pub async fn log_ddns_failure(
         &self,
@@ -66,6 +247,7 @@ impl MongoDb {
         error: &str,
     ) -> Result<(), AppError> {
         let event = SecurityEvent {
+            id: None,
             timestamp: Utc::now(),
             event_type: SecurityEventType::DdnsFailure,
             ip: None,
@@ -97,6 +279,7 @@ impl MongoDb {
         };
 
         let event = SecurityEvent {
+            id: None,
             timestamp: Utc::now(),
             event_type: SecurityEventType::HealthCheckFailure,
             ip: None,
@@ -129,13 +312,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! {}, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut events = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(event) = bson::from_document(doc) {
                 events.push(event);
@@ -159,6 +342,11 @@ impl MongoDb {
             SecurityEventType::SuspiciousActivity => "suspicious_activity",
             SecurityEventType::DdnsFailure => "ddns_failure",
             SecurityEventType::HealthCheckFailure => "health_check_failure",
+            SecurityEventType::NewDevice => "new_device",
+            SecurityEventType::AlertRule => "alert_rule",
+            SecurityEventType::InsecureConfiguration => "insecure_configuration",
+            SecurityEventType::DnsMismatch => "dns_mismatch",
+            SecurityEventType::TlsCertificateError => "tls_certificate_error",
         };
 
         let options = FindOptions::builder()
@@ -169,13 +357,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! { "event_type": type_str }, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut events = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(event) = bson::from_document(doc) {
                 events.push(event);
@@ -185,6 +373,21 @@ impl MongoDb {
         Ok(events)
     }
 
+    /// Get one security event by its document id (None for unknown or
+    /// malformed ids)
+    pub async fn get_security_event(&self, id: &str) -> Result<Option<SecurityEvent>, AppError> {
+        let Ok(oid) = bson::oid::ObjectId::parse_str(id) else {
+            return Ok(None);
+        };
+        let doc = self
+            .db
+            .collection::<bson::Document>("security_events")
+            .find_one(doc! { "_id": oid }, None)
+            .await
+            .map_err(|e| AppError::database(e.to_string()))?;
+        Ok(doc.and_then(|d| bson::from_document(d).ok()))
+    }
+
     /// Get security events for a specific IP
     pub async fn get_security_events_by_ip(
         &self,
@@ -199,15 +402,18 @@ impl MongoDb {
             .build();
 
         let mut cursor = collection
-            .find(doc! { "ip": ip }, options)
+            .find(
+                doc! { "ip": { "$in": crate::ip::ip_variants(ip) } },
+                options,
+            )
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut events = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(event) = bson::from_document(doc) {
                 events.push(event);
@@ -229,13 +435,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(doc! { "notified": false }, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut events = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(event) = bson::from_document(doc) {
                 events.push(event);
@@ -258,6 +464,11 @@ impl MongoDb {
             SecurityEventType::SuspiciousActivity => "suspicious_activity",
             SecurityEventType::DdnsFailure => "ddns_failure",
             SecurityEventType::HealthCheckFailure => "health_check_failure",
+            SecurityEventType::NewDevice => "new_device",
+            SecurityEventType::AlertRule => "alert_rule",
+            SecurityEventType::InsecureConfiguration => "insecure_configuration",
+            SecurityEventType::DnsMismatch => "dns_mismatch",
+            SecurityEventType::TlsCertificateError => "tls_certificate_error",
         };
 
         collection
@@ -267,7 +478,7 @@ impl MongoDb {
                 None,
             )
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         Ok(())
     }
@@ -310,7 +521,7 @@ impl MongoDb {
         // IP
         if let Some(ref ip) = query.ip {
             if !ip.is_empty() {
-                filter.insert("ip", ip.as_str());
+                filter.insert("ip", doc! { "$in": crate::ip::ip_variants(ip) });
             }
         }
 
@@ -323,13 +534,13 @@ impl MongoDb {
         let mut cursor = collection
             .find(filter, options)
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?;
+            .map_err(|e| AppError::database(e.to_string()))?;
 
         let mut events = Vec::new();
         while let Some(doc) = cursor
             .try_next()
             .await
-            .map_err(|e| AppError::InternalError(e.to_string()))?
+            .map_err(|e| AppError::database(e.to_string()))?
         {
             if let Ok(event) = bson::from_document(doc) {
                 events.push(event);
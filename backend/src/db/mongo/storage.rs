@@ -0,0 +1,463 @@
+//! Collection storage figures, the declared-index registry, compaction and
+//! daily storage samples (collection `storage_stats_daily`)
+//!
+//! `declared_indexes` lists every index the code relies on, including the
+//! ones earlier startup migrations created. `check_declared_indexes` runs at
+//! startup: it creates declared indexes that are missing and reports the
+//! ones whose options differ, and indexes nobody declared, without changing
+//! them. New indexes go into the registry rather than a migration.
+
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::options::{IndexOptions, ReplaceOptions};
+use mongodb::IndexModel;
+use serde::Serialize;
+
+use super::MongoDb;
+use crate::storage_stats::{CollectionSample, StorageSample};
+
+const SAMPLES: &str = "storage_stats_daily";
+
+/// One index the code expects
+#[derive(Debug, Clone)]
+pub struct DeclaredIndex {
+    pub collection: &'static str,
+    pub keys: Document,
+    /// Explicit name (otherwise the server derives one from the keys)
+    pub name: Option<&'static str>,
+    pub unique: bool,
+    pub ttl_seconds: Option<u64>,
+}
+
+impl DeclaredIndex {
+    fn new(collection: &'static str, keys: Document) -> Self {
+        Self {
+            collection,
+            keys,
+            name: None,
+            unique: false,
+            ttl_seconds: None,
+        }
+    }
+
+    fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    fn named(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    fn model(&self) -> IndexModel {
+        let options = IndexOptions::builder()
+            .name(self.name.map(str::to_string))
+            .unique(self.unique.then_some(true))
+            .expire_after(self.ttl_seconds.map(Duration::from_secs))
+            .build();
+        IndexModel::builder()
+            .keys(self.keys.clone())
+            .options(options)
+            .build()
+    }
+}
+
+/// Every index the code relies on, by collection
+pub fn declared_indexes() -> Vec<DeclaredIndex> {
+    let mut indexes = Vec::new();
+    for (collection, fields) in [
+        ("omada_clients", &["mac", "ip", "host_name", "name"][..]),
+        ("openwrt_clients", &["mac", "ip", "hostname"][..]),
+        ("external_clients", &["mac", "ip", "hostname"][..]),
+        (
+            "user_object_detail",
+            &["mac", "ip", "hostname", "label"][..],
+        ),
+    ] {
+        for field in fields {
+            let name: &'static str = Box::leak(format!("search_{}", field).into_boxed_str());
+            indexes.push(DeclaredIndex::new(collection, doc! { *field: 1 }).named(name));
+        }
+    }
+    indexes.extend([
+        DeclaredIndex::new("access_logs", doc! { "route_id": 1, "timestamp": -1 }),
+        // Equality / sort / range order of the recent route errors lookup
+        DeclaredIndex::new(
+            "access_logs",
+            doc! { "route_id": 1, "timestamp": -1, "status": 1 },
+        ),
+        DeclaredIndex::new("health_checks", doc! { "route_id": 1, "timestamp": -1 }),
+        // Age-based pruning (crate::log_retention)
+        DeclaredIndex::new("access_logs", doc! { "timestamp": 1 }),
+        DeclaredIndex::new("security_events", doc! { "timestamp": 1 }),
+        DeclaredIndex::new("health_checks", doc! { "timestamp": 1 }),
+        DeclaredIndex::new("forward_queue", doc! { "queue_id": 1 }).unique(),
+        DeclaredIndex::new("forward_queue", doc! { "status": 1, "next_attempt_at": 1 }),
+        DeclaredIndex::new("forward_queue", doc! { "route_id": 1, "created_at": -1 }),
+        DeclaredIndex::new("ip_daily_stats", doc! { "ip": 1, "day": 1 }).unique(),
+        DeclaredIndex::new("ip_daily_stats", doc! { "day": 1 }),
+        DeclaredIndex::new(
+            "dependencies_health",
+            doc! { "dependency": 1, "target": 1, "timestamp": -1 },
+        ),
+        DeclaredIndex::new("dependencies_health", doc! { "timestamp": 1 }),
+        DeclaredIndex::new("facility_reports", doc! { "report_id": 1 }),
+        DeclaredIndex::new("facility_reports", doc! { "fid": 1, "generated_at": -1 }),
+        DeclaredIndex::new("facility_reports", doc! { "generated_at": -1 }),
+        DeclaredIndex::new("route_uptime_daily", doc! { "route_id": 1, "day": 1 }).unique(),
+        DeclaredIndex::new("route_uptime_daily", doc! { "day": 1 }),
+        DeclaredIndex::new("hostname_usage_monthly", doc! { "month": 1, "hostname": 1 }).unique(),
+        DeclaredIndex::new("ddns_dns_checks", doc! { "ddns_config_id": 1 }).unique(),
+        DeclaredIndex::new("route_target_health", doc! { "route_id": 1, "target": 1 }).unique(),
+        DeclaredIndex::new("api_trace", doc! { "trace_id": 1 }).unique(),
+        DeclaredIndex::new("api_trace", doc! { "at": -1 }),
+        DeclaredIndex::new("api_trace", doc! { "actor": 1, "at": -1 }),
+        DeclaredIndex::new(SAMPLES, doc! { "day": 1 }).unique(),
+    ]);
+    indexes
+}
+
+/// Key patterns are equal when fields, order and directions match
+/// (the server may report 1 as int32, int64 or double)
+fn same_keys(a: &Document, b: &Document) -> bool {
+    fn direction(value: &Bson) -> Bson {
+        match value {
+            Bson::Int32(v) => Bson::Double(*v as f64),
+            Bson::Int64(v) => Bson::Double(*v as f64),
+            other => other.clone(),
+        }
+    }
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .all(|((ka, va), (kb, vb))| ka == kb && direction(va) == direction(vb))
+}
+
+/// An index as it exists on the server
+#[derive(Debug, Clone, Serialize)]
+pub struct ExistingIndex {
+    pub name: String,
+    pub keys: Document,
+    pub unique: bool,
+    pub ttl_seconds: Option<u64>,
+    pub size_bytes: Option<u64>,
+    pub declared: bool,
+}
+
+/// Declared index whose options differ from the existing one
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexMismatch {
+    pub name: String,
+    pub keys: Document,
+    pub expected_unique: bool,
+    pub actual_unique: bool,
+    pub expected_ttl_seconds: Option<u64>,
+    pub actual_ttl_seconds: Option<u64>,
+}
+
+/// Registry check of one collection
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CollectionIndexReport {
+    pub collection: String,
+    pub indexes: Vec<ExistingIndex>,
+    /// Declared but absent (after any creation attempt)
+    pub missing: Vec<Document>,
+    /// Created by this check
+    pub created: Vec<Document>,
+    pub mismatched: Vec<IndexMismatch>,
+    /// Existing indexes (other than `_id_`) nobody declared
+    pub undeclared: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Compare existing indexes of one collection with its declarations
+pub fn compare_indexes(
+    collection: &str,
+    existing: Vec<IndexModel>,
+    declared: &[&DeclaredIndex],
+) -> CollectionIndexReport {
+    let mut report = CollectionIndexReport {
+        collection: collection.to_string(),
+        ..Default::default()
+    };
+    for model in existing {
+        let options = model.options.unwrap_or_default();
+        let name = options.name.unwrap_or_default();
+        let unique = options.unique.unwrap_or(false);
+        let ttl_seconds = options.expire_after.map(|d| d.as_secs());
+        let matching = declared.iter().find(|d| same_keys(&d.keys, &model.keys));
+        if let Some(d) = matching {
+            if d.unique != unique || d.ttl_seconds != ttl_seconds {
+                report.mismatched.push(IndexMismatch {
+                    name: name.clone(),
+                    keys: model.keys.clone(),
+                    expected_unique: d.unique,
+                    actual_unique: unique,
+                    expected_ttl_seconds: d.ttl_seconds,
+                    actual_ttl_seconds: ttl_seconds,
+                });
+            }
+        } else if name != "_id_" {
+            report.undeclared.push(name.clone());
+        }
+        report.indexes.push(ExistingIndex {
+            name,
+            keys: model.keys,
+            unique,
+            ttl_seconds,
+            size_bytes: None,
+            declared: matching.is_some(),
+        });
+    }
+    report.missing = declared
+        .iter()
+        .filter(|d| !report.indexes.iter().any(|i| same_keys(&d.keys, &i.keys)))
+        .map(|d| d.keys.clone())
+        .collect();
+    report
+}
+
+fn stat(stats: &Document, key: &str) -> u64 {
+    match stats.get(key) {
+        Some(Bson::Int32(v)) => *v as u64,
+        Some(Bson::Int64(v)) => *v as u64,
+        Some(Bson::Double(v)) => *v as u64,
+        _ => 0,
+    }
+}
+
+impl MongoDb {
+    /// Collection names, excluding system collections
+    pub async fn list_collections(&self) -> Result<Vec<String>, String> {
+        let mut names = self
+            .db
+            .list_collection_names(None)
+            .await
+            .map_err(|e| format!("List collections: {}", e))?;
+        names.retain(|n| !n.starts_with("system."));
+        names.sort();
+        Ok(names)
+    }
+
+    /// Storage figures of one collection plus per-index sizes
+    pub async fn collection_storage(
+        &self,
+        name: &str,
+    ) -> Result<(CollectionSample, Document), String> {
+        let mut cursor = self
+            .db
+            .collection::<Document>(name)
+            .aggregate(vec![doc! { "$collStats": { "storageStats": {} } }], None)
+            .await
+            .map_err(|e| format!("collStats {}: {}", name, e))?;
+        let stats = cursor
+            .try_next()
+            .await
+            .map_err(|e| format!("collStats {}: {}", name, e))?
+            .and_then(|d| d.get_document("storageStats").ok().cloned())
+            .unwrap_or_default();
+        let index_sizes = stats
+            .get_document("indexSizes")
+            .cloned()
+            .unwrap_or_default();
+        Ok((
+            CollectionSample {
+                name: name.to_string(),
+                count: stat(&stats, "count"),
+                size: stat(&stats, "size"),
+                storage_size: stat(&stats, "storageSize"),
+                index_size: stat(&stats, "totalIndexSize"),
+            },
+            index_sizes,
+        ))
+    }
+
+    /// Check every collection with declared indexes (and, for the report,
+    /// every other collection); `create_missing` creates absent ones
+    pub async fn check_declared_indexes(
+        &self,
+        create_missing: bool,
+    ) -> Result<Vec<CollectionIndexReport>, String> {
+        let declared = declared_indexes();
+        let mut collections = self.list_collections().await?;
+        for d in &declared {
+            if !collections.iter().any(|c| c == d.collection) {
+                collections.push(d.collection.to_string());
+            }
+        }
+        collections.sort();
+
+        let mut reports = Vec::new();
+        for collection in collections {
+            let wanted: Vec<&DeclaredIndex> = declared
+                .iter()
+                .filter(|d| d.collection == collection)
+                .collect();
+            let coll = self.db.collection::<Document>(&collection);
+            let existing: Vec<IndexModel> = match coll.list_indexes(None).await {
+                Ok(cursor) => cursor.try_collect().await.unwrap_or_default(),
+                // A collection that does not exist yet has no indexes
+                Err(_) => Vec::new(),
+            };
+            let mut report = compare_indexes(&collection, existing, &wanted);
+
+            if create_missing && !report.missing.is_empty() {
+                let models: Vec<IndexModel> = wanted
+                    .iter()
+                    .filter(|d| report.missing.iter().any(|k| same_keys(k, &d.keys)))
+                    .map(|d| d.model())
+                    .collect();
+                match coll.create_indexes(models, None).await {
+                    Ok(_) => report.created = std::mem::take(&mut report.missing),
+                    Err(e) => report.error = Some(format!("Create indexes: {}", e)),
+                }
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Run `compact` on one collection (blocks the collection while it runs)
+    pub async fn compact_collection(&self, name: &str) -> Result<Document, String> {
+        self.db
+            .run_command(doc! { "compact": name }, None)
+            .await
+            .map_err(|e| format!("compact {}: {}", name, e))
+    }
+
+    /// Store the day's sample (a later sample of the same day replaces it);
+    /// returns true when it is the first sample of the day
+    pub async fn save_storage_sample(&self, sample: &StorageSample) -> Result<bool, String> {
+        let doc = bson::to_document(sample).map_err(|e| format!("Encode: {}", e))?;
+        let result = self
+            .db
+            .collection::<Document>(SAMPLES)
+            .replace_one(
+                doc! { "day": &sample.day },
+                doc,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| format!("Save storage sample: {}", e))?;
+        Ok(result.upserted_id.is_some())
+    }
+
+    /// Samples from `since_day` on, oldest first
+    pub async fn list_storage_samples(
+        &self,
+        since_day: &str,
+    ) -> Result<Vec<StorageSample>, String> {
+        let docs: Vec<Document> = self
+            .db
+            .collection::<Document>(SAMPLES)
+            .find(
+                doc! { "day": { "$gte": since_day } },
+                mongodb::options::FindOptions::builder()
+                    .sort(doc! { "day": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Query storage samples: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Read storage samples: {}", e))?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|d| bson::from_document(d).ok())
+            .collect())
+    }
+
+    pub async fn purge_storage_samples(&self, before_day: &str) -> Result<u64, String> {
+        let result = self
+            .db
+            .collection::<Document>(SAMPLES)
+            .delete_many(doc! { "day": { "$lt": before_day } }, None)
+            .await
+            .map_err(|e| format!("Purge storage samples: {}", e))?;
+        Ok(result.deleted_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(name: &str, keys: Document, unique: bool) -> IndexModel {
+        IndexModel::builder()
+            .keys(keys)
+            .options(
+                IndexOptions::builder()
+                    .name(name.to_string())
+                    .unique(unique.then_some(true))
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn registry_check_reports_missing_mismatched_and_undeclared() {
+        let declared = [
+            DeclaredIndex::new("forward_queue", doc! { "queue_id": 1 }).unique(),
+            DeclaredIndex::new("forward_queue", doc! { "status": 1, "next_attempt_at": 1 }),
+            DeclaredIndex::new("forward_queue", doc! { "route_id": 1, "created_at": -1 }),
+        ];
+        let wanted: Vec<&DeclaredIndex> = declared.iter().collect();
+        let report = compare_indexes(
+            "forward_queue",
+            vec![
+                existing("_id_", doc! { "_id": 1 }, false),
+                // Same key as declared, reported with a 64-bit direction
+                existing("queue_id_1", doc! { "queue_id": 1_i64 }, false),
+                existing(
+                    "status_1_next_attempt_at_1",
+                    doc! { "status": 1.0, "next_attempt_at": 1 },
+                    false,
+                ),
+                existing("legacy_1", doc! { "legacy": 1 }, false),
+            ],
+            &wanted,
+        );
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].name, "queue_id_1");
+        assert!(report.mismatched[0].expected_unique);
+        assert_eq!(report.undeclared, vec!["legacy_1"]);
+        assert_eq!(
+            report.missing,
+            vec![doc! { "route_id": 1, "created_at": -1 }]
+        );
+        assert_eq!(report.indexes.iter().filter(|i| i.declared).count(), 2);
+    }
+
+    #[test]
+    fn key_order_and_direction_matter() {
+        assert!(same_keys(
+            &doc! { "a": 1, "b": -1 },
+            &doc! { "a": 1_i64, "b": -1.0 }
+        ));
+        assert!(!same_keys(
+            &doc! { "a": 1, "b": -1 },
+            &doc! { "b": -1, "a": 1 }
+        ));
+        assert!(!same_keys(&doc! { "a": 1 }, &doc! { "a": -1 }));
+        assert!(!same_keys(&doc! { "a": 1 }, &doc! { "a": 1, "b": 1 }));
+    }
+
+    #[test]
+    fn declared_indexes_are_unique_per_collection() {
+        let declared = declared_indexes();
+        for (i, a) in declared.iter().enumerate() {
+            for b in &declared[i + 1..] {
+                assert!(
+                    a.collection != b.collection || !same_keys(&a.keys, &b.keys),
+                    "{} declares {:?} twice",
+                    a.collection,
+                    a.keys
+                );
+            }
+        }
+    }
+}
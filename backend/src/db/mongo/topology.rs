@@ -2,10 +2,20 @@
 //!
 //! Collection: `celestial_globe_topology`
 //! Stores: node positions, collapsed state, logic devices
+//!
+//! Stored positions are user overrides of the computed layout. Writes to them
+//! are guarded by `layout_generation` on the state document: a writer claims
+//! the next generation from the one it read, so edits based on a stale view
+//! are refused instead of interleaving.
+
+use std::collections::HashMap;
 
+use futures::TryStreamExt;
 use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
 use serde::{Deserialize, Serialize};
 
+use super::ingest_writes::{update_statement, IngestCycleWrites};
 use super::MongoDb;
 
 const COLLECTION_POSITIONS: &str = "cg_node_positions";
@@ -24,6 +34,9 @@ pub struct NodePosition {
     pub x: f64,
     pub y: f64,
     pub pinned: bool,
+    /// Layout generation the position was written under
+    #[serde(default)]
+    pub layout_generation: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,7 +57,20 @@ pub struct LogicDeviceDoc {
 pub struct TopologyStateDoc {
     pub key: String, // always "global"
     pub collapsed_node_ids: Vec<String>,
+    #[serde(default)]
     pub last_layout_at: String,
+    /// Bumped by every position batch and layout recalc
+    #[serde(default)]
+    pub layout_generation: i64,
+}
+
+/// Result of `claim_layout_generation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutClaim {
+    /// The base was current; carries the new generation
+    Claimed(i64),
+    /// The base was stale; carries the current generation
+    Stale(i64),
 }
 
 /// nodeOrder SSoT entry — the single source of truth for CelestialGlobe topology.
@@ -126,6 +152,7 @@ impl MongoDb {
                 "x": pos.x,
                 "y": pos.y,
                 "pinned": pos.pinned,
+                "layout_generation": pos.layout_generation,
             }
         };
         let opts = mongodb::options::UpdateOptions::builder()
@@ -149,6 +176,24 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Delete unpinned positions, of `node_ids` only when given; returns the
+    /// number deleted
+    pub async fn clear_unpinned_node_positions(
+        &self,
+        node_ids: Option<&[String]>,
+    ) -> Result<u64, String> {
+        let collection = self.db.collection::<Document>(COLLECTION_POSITIONS);
+        let mut filter = doc! { "pinned": { "$ne": true } };
+        if let Some(ids) = node_ids {
+            filter.insert("node_id", doc! { "$in": ids });
+        }
+        let result = collection
+            .delete_many(filter, None)
+            .await
+            .map_err(|e| format!("Failed to clear node positions: {}", e))?;
+        Ok(result.deleted_count)
+    }
+
     // ========================================================================
     // Topology State (collapsed nodes, layout timestamp)
     // ========================================================================
@@ -164,9 +209,67 @@ impl MongoDb {
             key: "global".to_string(),
             collapsed_node_ids: Vec::new(),
             last_layout_at: String::new(),
+            layout_generation: 0,
         }))
     }
 
+    /// Move the layout generation from `base` to `base + 1` if `base` is
+    /// still current. The compare and the increment are one update, so of
+    /// two writers based on the same generation only one succeeds.
+    pub async fn claim_layout_generation(&self, base: i64) -> Result<LayoutClaim, String> {
+        let collection = self.db.collection::<Document>(COLLECTION_STATE);
+        let opts = mongodb::options::UpdateOptions::builder()
+            .upsert(true)
+            .build();
+        collection
+            .update_one(
+                doc! { "key": "global" },
+                doc! {
+                    "$setOnInsert": {
+                        "collapsed_node_ids": mongodb::bson::Bson::Array(vec![]),
+                        "last_layout_at": "",
+                        "layout_generation": 0_i64,
+                    }
+                },
+                Some(opts),
+            )
+            .await
+            .map_err(|e| format!("Failed to init topology state: {}", e))?;
+
+        // State documents from before the counter count as generation 0
+        let filter = if base == 0 {
+            doc! {
+                "key": "global",
+                "$or": [
+                    { "layout_generation": { "$exists": false } },
+                    { "layout_generation": 0_i64 },
+                ],
+            }
+        } else {
+            doc! { "key": "global", "layout_generation": base }
+        };
+        let opts = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let claimed = collection
+            .find_one_and_update(
+                filter,
+                doc! { "$inc": { "layout_generation": 1_i64 } },
+                Some(opts),
+            )
+            .await
+            .map_err(|e| format!("Failed to claim layout generation: {}", e))?;
+
+        match claimed {
+            Some(state) => Ok(LayoutClaim::Claimed(
+                state.get_i64("layout_generation").unwrap_or(base + 1),
+            )),
+            None => Ok(LayoutClaim::Stale(
+                self.get_topology_state().await?.layout_generation,
+            )),
+        }
+    }
+
     /// Update collapsed state for a node
     pub async fn set_node_collapsed(&self, node_id: &str, collapsed: bool) -> Result<(), String> {
         let collection = self.db.collection::<Document>(COLLECTION_STATE);
@@ -383,94 +486,15 @@ impl MongoDb {
 
         if let Some(existing) = existing {
             // Update volatile fields only — preserve topology structure
-            let mut set_doc = doc! {
-                "status": &entry.status,
-                "ip": entry.ip.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-                "hostname": entry.hostname.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-                "updated_at": &entry.updated_at,
-                "ssid": entry.ssid.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-            };
-
-            // Update metadata (merge, not replace)
-            if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
-                set_doc.insert("metadata", bson_val);
-            }
-
-            // Update label only if not customized
-            if !existing.label_customized {
-                set_doc.insert("label", &entry.label);
-            }
-
-            // Update lacis_id / candidate_lacis_id if provided (don't overwrite existing with None)
-            if entry.lacis_id.is_some() {
-                set_doc.insert("lacis_id", entry.lacis_id.as_deref().unwrap());
-            }
-            if entry.candidate_lacis_id.is_some() {
-                set_doc.insert(
-                    "candidate_lacis_id",
-                    entry.candidate_lacis_id.as_deref().unwrap(),
-                );
-            }
-
+            let set_doc = node_order_volatile_fields(entry, &existing);
             collection
                 .update_one(filter, doc! { "$set": set_doc }, None)
                 .await
                 .map_err(|e| format!("Failed to update node order: {}", e))?;
         } else {
             // New entry: insert all fields
-            let mut insert_doc = doc! {
-                "mac": &entry.mac,
-                "parent_mac": &entry.parent_mac,
-                "depth": entry.depth,
-                "order": entry.order,
-                "label": &entry.label,
-                "node_type": &entry.node_type,
-                "source": &entry.source,
-                "status": &entry.status,
-                "state_type": &entry.state_type,
-                "connection_type": &entry.connection_type,
-                "label_customized": entry.label_customized,
-                "created_at": &entry.created_at,
-                "updated_at": &entry.updated_at,
-            };
-
-            // Optional fields
-            if let Some(ref v) = entry.ip {
-                insert_doc.insert("ip", v);
-            }
-            if let Some(ref v) = entry.hostname {
-                insert_doc.insert("hostname", v);
-            }
-            if let Some(ref v) = entry.source_ref_id {
-                insert_doc.insert("source_ref_id", v);
-            }
-            if let Some(ref v) = entry.lacis_id {
-                insert_doc.insert("lacis_id", v);
-            }
-            if let Some(ref v) = entry.candidate_lacis_id {
-                insert_doc.insert("candidate_lacis_id", v);
-            }
-            if let Some(ref v) = entry.product_type {
-                insert_doc.insert("product_type", v);
-            }
-            if let Some(ref v) = entry.network_device_type {
-                insert_doc.insert("network_device_type", v);
-            }
-            if let Some(ref v) = entry.fid {
-                insert_doc.insert("fid", v);
-            }
-            if let Some(ref v) = entry.facility_name {
-                insert_doc.insert("facility_name", v);
-            }
-            if let Some(ref v) = entry.ssid {
-                insert_doc.insert("ssid", v);
-            }
-            if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
-                insert_doc.insert("metadata", bson_val);
-            }
-
             collection
-                .insert_one(insert_doc, None)
+                .insert_one(node_order_insert_doc(entry), None)
                 .await
                 .map_err(|e| format!("Failed to insert node order: {}", e))?;
         }
@@ -478,6 +502,58 @@ impl MongoDb {
         Ok(())
     }
 
+    /// Upsert one ingest cycle's node order entries with the same rules as
+    /// `upsert_node_order`, as paced bulk writes on the ingestion pool
+    pub async fn bulk_upsert_node_orders(
+        &self,
+        entries: &[NodeOrderEntry],
+        cycle: &mut IngestCycleWrites,
+    ) -> Result<(), String> {
+        let pacing = self.ingest_pacing();
+        let collection = self
+            .ingest_db
+            .collection::<NodeOrderEntry>(COLLECTION_NODE_ORDER);
+
+        for (index, chunk) in entries.chunks(pacing.batch_size).enumerate() {
+            self.pace_ingest_batch(index, pacing).await;
+            let macs: Vec<&str> = chunk.iter().map(|e| e.mac.as_str()).collect();
+            let existing: HashMap<String, NodeOrderEntry> = collection
+                .find(doc! { "mac": { "$in": macs } }, None)
+                .await
+                .map_err(|e| format!("Failed to query node orders: {}", e))?
+                .try_collect::<Vec<_>>()
+                .await
+                .map_err(|e| format!("Failed to read node orders: {}", e))?
+                .into_iter()
+                .map(|e| (e.mac.clone(), e))
+                .collect();
+
+            let statements = chunk
+                .iter()
+                .map(|entry| match existing.get(&entry.mac) {
+                    Some(ex) => update_statement(
+                        doc! { "mac": &entry.mac },
+                        doc! { "$set": node_order_volatile_fields(entry, ex) },
+                        false,
+                    ),
+                    None => {
+                        // The filter's mac seeds the inserted document
+                        let mut insert_doc = node_order_insert_doc(entry);
+                        insert_doc.remove("mac");
+                        update_statement(
+                            doc! { "mac": &entry.mac },
+                            doc! { "$setOnInsert": insert_doc },
+                            true,
+                        )
+                    }
+                })
+                .collect();
+            self.ingest_bulk_update(COLLECTION_NODE_ORDER, statements, cycle)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Update only the parent_mac and depth of a node (for reparent operations)
     pub async fn update_node_order_parent(
         &self,
@@ -501,6 +577,23 @@ impl MongoDb {
         Ok(result.modified_count > 0)
     }
 
+    /// Update only the status of a node (webhook fast path)
+    pub async fn update_node_order_status(&self, mac: &str, status: &str) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>(COLLECTION_NODE_ORDER);
+        let result = collection
+            .update_one(
+                doc! { "mac": mac },
+                doc! { "$set": {
+                    "status": status,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to update node order status: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
     /// Update the label of a node and set label_customized flag
     pub async fn update_node_order_label(
         &self,
@@ -583,3 +676,91 @@ impl MongoDb {
         Ok(())
     }
 }
+
+/// `$set` of an ingest over an existing node order entry (volatile fields only)
+fn node_order_volatile_fields(entry: &NodeOrderEntry, existing: &NodeOrderEntry) -> Document {
+    let mut set_doc = doc! {
+        "status": &entry.status,
+        "ip": entry.ip.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+        "hostname": entry.hostname.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+        "updated_at": &entry.updated_at,
+        "ssid": entry.ssid.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+    };
+
+    // Update metadata (merge, not replace)
+    if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
+        set_doc.insert("metadata", bson_val);
+    }
+
+    // Update label only if not customized
+    if !existing.label_customized {
+        set_doc.insert("label", &entry.label);
+    }
+
+    // Update lacis_id / candidate_lacis_id if provided (don't overwrite existing with None)
+    if entry.lacis_id.is_some() {
+        set_doc.insert("lacis_id", entry.lacis_id.as_deref().unwrap());
+    }
+    if entry.candidate_lacis_id.is_some() {
+        set_doc.insert(
+            "candidate_lacis_id",
+            entry.candidate_lacis_id.as_deref().unwrap(),
+        );
+    }
+    set_doc
+}
+
+/// Document of a first-seen node order entry
+fn node_order_insert_doc(entry: &NodeOrderEntry) -> Document {
+    let mut insert_doc = doc! {
+        "mac": &entry.mac,
+        "parent_mac": &entry.parent_mac,
+        "depth": entry.depth,
+        "order": entry.order,
+        "label": &entry.label,
+        "node_type": &entry.node_type,
+        "source": &entry.source,
+        "status": &entry.status,
+        "state_type": &entry.state_type,
+        "connection_type": &entry.connection_type,
+        "label_customized": entry.label_customized,
+        "created_at": &entry.created_at,
+        "updated_at": &entry.updated_at,
+    };
+
+    // Optional fields
+    if let Some(ref v) = entry.ip {
+        insert_doc.insert("ip", v);
+    }
+    if let Some(ref v) = entry.hostname {
+        insert_doc.insert("hostname", v);
+    }
+    if let Some(ref v) = entry.source_ref_id {
+        insert_doc.insert("source_ref_id", v);
+    }
+    if let Some(ref v) = entry.lacis_id {
+        insert_doc.insert("lacis_id", v);
+    }
+    if let Some(ref v) = entry.candidate_lacis_id {
+        insert_doc.insert("candidate_lacis_id", v);
+    }
+    if let Some(ref v) = entry.product_type {
+        insert_doc.insert("product_type", v);
+    }
+    if let Some(ref v) = entry.network_device_type {
+        insert_doc.insert("network_device_type", v);
+    }
+    if let Some(ref v) = entry.fid {
+        insert_doc.insert("fid", v);
+    }
+    if let Some(ref v) = entry.facility_name {
+        insert_doc.insert("facility_name", v);
+    }
+    if let Some(ref v) = entry.ssid {
+        insert_doc.insert("ssid", v);
+    }
+    if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
+        insert_doc.insert("metadata", bson_val);
+    }
+    insert_doc
+}
@@ -0,0 +1,294 @@
+//! Topology revision counter for watch clients
+//!
+//! Every user_object_detail write bumps a single counter document
+//! (`topology_revision`) and appends one `topology_changes` row per node, so
+//! GET /api/topology/watch can return what changed since a client's revision.
+//! Waiting watchers sit on an in-process watch channel (plus a short poll for
+//! writes made by other instances), never on a Mongo cursor.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use super::MongoDb;
+
+const REVISION_COLLECTION: &str = "topology_revision";
+const CHANGES_COLLECTION: &str = "topology_changes";
+const REVISION_DOC_ID: &str = "user_object_detail";
+
+/// Change rows kept; older revisions answer with `truncated`
+const CHANGES_KEPT: i64 = 10_000;
+/// Prune the change log every N revisions
+const PRUNE_EVERY: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TopologyChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// One changed node (latest revision that touched it)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyChange {
+    pub node_id: String,
+    pub change: TopologyChangeKind,
+    pub revision: i64,
+}
+
+/// Changes after a revision
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologyChangesSince {
+    pub revision: i64,
+    pub changes: Vec<TopologyChange>,
+    /// The requested revision is older than the retained log: refetch the
+    /// full topology instead of applying `changes`
+    pub truncated: bool,
+}
+
+/// Collapse per-node rows (in revision order) into one change per node
+pub fn merge_changes(rows: Vec<TopologyChange>) -> Vec<TopologyChange> {
+    let mut merged: HashMap<String, TopologyChange> = HashMap::new();
+    for row in rows {
+        match merged.get(&row.node_id).map(|c| c.change) {
+            // Appeared and disappeared within the window: nothing to report
+            Some(TopologyChangeKind::Added) if row.change == TopologyChangeKind::Removed => {
+                merged.remove(&row.node_id);
+            }
+            Some(TopologyChangeKind::Added) => {
+                merged.insert(
+                    row.node_id.clone(),
+                    TopologyChange {
+                        change: TopologyChangeKind::Added,
+                        ..row
+                    },
+                );
+            }
+            Some(TopologyChangeKind::Removed) if row.change == TopologyChangeKind::Added => {
+                merged.insert(
+                    row.node_id.clone(),
+                    TopologyChange {
+                        change: TopologyChangeKind::Updated,
+                        ..row
+                    },
+                );
+            }
+            _ => {
+                merged.insert(row.node_id.clone(), row);
+            }
+        }
+    }
+    let mut changes: Vec<TopologyChange> = merged.into_values().collect();
+    changes.sort_by(|a, b| a.revision.cmp(&b.revision).then(a.node_id.cmp(&b.node_id)));
+    changes
+}
+
+/// In-process revision broadcast shared by all clones of `MongoDb`
+pub(super) fn revision_channel() -> watch::Sender<i64> {
+    watch::channel(0).0
+}
+
+impl MongoDb {
+    /// Bump the topology revision for `node_ids` and wake watchers.
+    /// Returns the new revision.
+    pub async fn bump_topology_revision(
+        &self,
+        node_ids: &[&str],
+        change: TopologyChangeKind,
+    ) -> Result<i64, String> {
+        if node_ids.is_empty() {
+            return self.topology_revision().await;
+        }
+
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let counter = self
+            .db
+            .collection::<Document>(REVISION_COLLECTION)
+            .find_one_and_update(
+                doc! { "_id": REVISION_DOC_ID },
+                doc! { "$inc": { "revision": 1_i64 } },
+                Some(options),
+            )
+            .await
+            .map_err(|e| format!("Failed to bump topology revision: {}", e))?;
+        let revision = counter
+            .and_then(|d| d.get_i64("revision").ok())
+            .ok_or("Topology revision document missing")?;
+
+        let now = Utc::now().to_rfc3339();
+        let kind = mongodb::bson::to_bson(&change).map_err(|e| e.to_string())?;
+        let rows: Vec<Document> = node_ids
+            .iter()
+            .map(|id| {
+                doc! {
+                    "revision": revision,
+                    "node_id": *id,
+                    "change": kind.clone(),
+                    "at": &now,
+                }
+            })
+            .collect();
+        let changes = self.db.collection::<Document>(CHANGES_COLLECTION);
+        changes
+            .insert_many(rows, None)
+            .await
+            .map_err(|e| format!("Failed to record topology change: {}", e))?;
+
+        if revision % PRUNE_EVERY == 0 {
+            if let Err(e) = changes
+                .delete_many(
+                    doc! { "revision": { "$lte": revision - CHANGES_KEPT } },
+                    None,
+                )
+                .await
+            {
+                tracing::warn!("Failed to prune topology changes: {}", e);
+            }
+        }
+
+        self.topology_rev.send_if_modified(|current| {
+            let advanced = revision > *current;
+            if advanced {
+                *current = revision;
+            }
+            advanced
+        });
+        Ok(revision)
+    }
+
+    /// Bump for a write that already succeeded; failures are only logged
+    pub(crate) async fn note_topology_change(&self, node_ids: &[&str], change: TopologyChangeKind) {
+        if let Err(e) = self.bump_topology_revision(node_ids, change).await {
+            tracing::warn!("Topology revision not bumped for {:?}: {}", node_ids, e);
+        }
+    }
+
+    /// Current topology revision (0 before the first change)
+    pub async fn topology_revision(&self) -> Result<i64, String> {
+        let counter = self
+            .db
+            .collection::<Document>(REVISION_COLLECTION)
+            .find_one(doc! { "_id": REVISION_DOC_ID }, None)
+            .await
+            .map_err(|e| format!("Failed to read topology revision: {}", e))?;
+        let revision = counter
+            .and_then(|d| d.get_i64("revision").ok())
+            .unwrap_or(0);
+        self.topology_rev.send_if_modified(|current| {
+            let advanced = revision > *current;
+            if advanced {
+                *current = revision;
+            }
+            advanced
+        });
+        Ok(revision)
+    }
+
+    /// Receiver that changes whenever this instance sees a newer revision
+    pub fn subscribe_topology_revision(&self) -> watch::Receiver<i64> {
+        self.topology_rev.subscribe()
+    }
+
+    /// Changes after `since`, merged per node (at most `limit` raw rows)
+    pub async fn topology_changes_since(
+        &self,
+        since: i64,
+        limit: i64,
+    ) -> Result<TopologyChangesSince, String> {
+        let revision = self.topology_revision().await?;
+        if revision <= since {
+            return Ok(TopologyChangesSince {
+                revision,
+                changes: Vec::new(),
+                truncated: false,
+            });
+        }
+
+        let collection = self.db.collection::<Document>(CHANGES_COLLECTION);
+        let oldest = collection
+            .find_one(
+                None,
+                mongodb::options::FindOneOptions::builder()
+                    .sort(doc! { "revision": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Failed to read topology changes: {}", e))?
+            .and_then(|d| d.get_i64("revision").ok());
+        if oldest.is_none_or(|oldest| since + 1 < oldest) {
+            return Ok(TopologyChangesSince {
+                revision,
+                changes: Vec::new(),
+                truncated: true,
+            });
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "revision": 1 })
+            .limit(limit)
+            .build();
+        let rows: Vec<Document> = collection
+            .find(doc! { "revision": { "$gt": since } }, Some(options))
+            .await
+            .map_err(|e| format!("Failed to read topology changes: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to read topology changes: {}", e))?;
+        let truncated = rows.len() as i64 >= limit;
+        let rows = rows
+            .into_iter()
+            .filter_map(|d| mongodb::bson::from_document::<TopologyChange>(d).ok())
+            .collect();
+
+        Ok(TopologyChangesSince {
+            revision,
+            changes: merge_changes(rows),
+            truncated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(node_id: &str, change: TopologyChangeKind, revision: i64) -> TopologyChange {
+        TopologyChange {
+            node_id: node_id.to_string(),
+            change,
+            revision,
+        }
+    }
+
+    #[test]
+    fn merge_keeps_one_change_per_node() {
+        use TopologyChangeKind::*;
+        let merged = merge_changes(vec![
+            row("A", Added, 1),
+            row("B", Updated, 2),
+            row("A", Updated, 3),
+            row("C", Added, 4),
+            row("C", Removed, 5),
+            row("D", Removed, 6),
+            row("D", Added, 7),
+            row("B", Removed, 8),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                row("A", Added, 3),
+                row("D", Updated, 7),
+                row("B", Removed, 8)
+            ]
+        );
+    }
+}
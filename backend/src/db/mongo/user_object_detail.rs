@@ -7,10 +7,16 @@
 //!
 //! Parent eligibility: `_id.len() == 20` (LacisID) or `_id.starts_with("F2")` (Logic Device)
 
+use std::collections::HashMap;
+
+use futures::TryStreamExt;
 use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
 
+use super::ingest_writes::{update_statement, IngestCycleWrites};
+use super::topology_revision::TopologyChangeKind;
 use super::MongoDb;
+use crate::omada::health::{STATE_BEFORE_OUTAGE, UNKNOWN_CONTROLLER_OFFLINE};
 
 const COLLECTION: &str = "user_object_detail";
 
@@ -20,21 +26,22 @@ const COLLECTION: &str = "user_object_detail";
 /// `id` maps to `_id` in the document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserObjectDetail {
-    pub id: String,                        // _id: LacisID (20) or MAC (12)
-    pub mac: String,                       // 12-digit uppercase HEX
-    pub lacis_id: Option<String>,          // Confirmed LacisID (infra only)
-    pub device_type: String,               // "NetworkDevice" or "araneaDevice"
-    pub parent_id: String,                 // Parent _id (LacisID) or "INTERNET"
-    pub sort_order: u32,                   // Sibling order (the only layout parameter)
-    pub node_type: String,                 // gateway, switch, ap, client, wg_peer, etc.
-    pub state_type: String,                // online, offline, StaticOnline, StaticOffline
+    pub id: String,               // _id: LacisID (20) or MAC (12)
+    pub mac: String,              // 12-digit uppercase HEX
+    pub lacis_id: Option<String>, // Confirmed LacisID (infra only)
+    pub device_type: String,      // "NetworkDevice" or "araneaDevice"
+    pub parent_id: String,        // Parent _id (LacisID) or "INTERNET"
+    pub sort_order: u32,          // Sibling order (the only layout parameter)
+    pub node_type: String,        // gateway, switch, ap, client, wg_peer, etc.
+    pub state_type: String,       // online, offline, StaticOnline, StaticOffline
     pub label: String,
     pub label_customized: bool,
+    pub claimed_by: Option<NodeClaim>, // Owner claim (kept across ingestion)
     pub ip: Option<String>,
     pub hostname: Option<String>,
-    pub source: String,                    // omada, openwrt, external, manual
+    pub source: String, // omada, openwrt, external, manual
     pub source_ref_id: Option<String>,
-    pub connection_type: String,           // wired, wireless, vpn
+    pub connection_type: String, // wired, wireless, vpn
     pub product_type: Option<String>,
     pub product_code: Option<String>,
     pub network_device_type: Option<String>,
@@ -42,17 +49,58 @@ pub struct UserObjectDetail {
     pub fid: Option<String>,
     pub facility_name: Option<String>,
     pub ssid: Option<String>,
+    pub device_class: Option<String>, // Clients only: phone, laptop, printer, camera, iot, server, unknown
     pub metadata: serde_json::Value,
-    pub aranea_lacis_id: Option<String>,   // araneaDevice match: prefix-3 LacisID
+    pub aranea_lacis_id: Option<String>, // araneaDevice match: prefix-3 LacisID
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// A user's claim on a node ("this MAC is my laptop").
+///
+/// Like `label_customized`, ingestion never writes this field; only the claim
+/// endpoints do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeClaim {
+    pub lacis_id: String,
+    pub display_name: String,
+    pub claimed_at: String,
+    /// Admin who assigned the claim on the owner's behalf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assigned_by: Option<String>,
+}
+
+/// Result of a self-service claim
+#[derive(Debug)]
+pub enum ClaimOutcome {
+    Claimed,
+    /// Held by another user
+    Conflict(NodeClaim),
+    NotFound,
+}
+
 impl UserObjectDetail {
     /// Whether this node can be a parent (LacisID format or Logic Device pseudo-MAC)
     pub fn can_be_parent(id: &str) -> bool {
         id.len() == 20 || id.starts_with("F2")
     }
+
+    /// Whether upserting `incoming` over `self` changes anything a topology
+    /// watcher would see (metadata counters alone do not)
+    fn upsert_changes_view(&self, incoming: &UserObjectDetail) -> bool {
+        self.state_type != incoming.state_type
+            || self.ip != incoming.ip
+            || self.hostname != incoming.hostname
+            || self.ssid != incoming.ssid
+            || (incoming.device_class.is_some() && self.device_class != incoming.device_class)
+            || self.device_type != incoming.device_type
+            || (!self.label_customized && self.label != incoming.label)
+            || (incoming.lacis_id.is_some() && self.lacis_id != incoming.lacis_id)
+            || (incoming.aranea_lacis_id.is_some()
+                && self.aranea_lacis_id != incoming.aranea_lacis_id)
+            || (incoming.source == "omada"
+                && (self.fid != incoming.fid || self.facility_name != incoming.facility_name))
+    }
 }
 
 impl MongoDb {
@@ -109,8 +157,15 @@ impl MongoDb {
     /// If the _id already exists, respects immutable fields:
     /// - parent_id, sort_order are NOT overwritten for existing entries
     /// - label is NOT overwritten if label_customized=true
+    /// - claimed_by is never touched
     /// - state_type, ip, hostname, metadata, updated_at ARE always updated
-    pub async fn upsert_user_object_detail(&self, entry: &UserObjectDetail) -> Result<(), String> {
+    /// - device_class is updated when the entry carries one (clients)
+    ///
+    /// Returns true when the entry was inserted (a device seen for the first time).
+    pub async fn upsert_user_object_detail(
+        &self,
+        entry: &UserObjectDetail,
+    ) -> Result<bool, String> {
         let collection = self.db.collection::<Document>(COLLECTION);
         let filter = doc! { "_id": &entry.id };
 
@@ -119,48 +174,15 @@ impl MongoDb {
 
         if let Some(existing) = existing {
             // Update volatile fields only — preserve topology structure
-            let mut set_doc = doc! {
-                "state_type": &entry.state_type,
-                "ip": entry.ip.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-                "hostname": entry.hostname.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-                "ssid": entry.ssid.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
-                "updated_at": &entry.updated_at,
-            };
-
-            // Update metadata
-            if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
-                set_doc.insert("metadata", bson_val);
-            }
-
-            // Update label only if not customized
-            if !existing.label_customized {
-                set_doc.insert("label", &entry.label);
-            }
-
-            // Update lacis_id if provided (don't overwrite existing with None)
-            if entry.lacis_id.is_some() {
-                set_doc.insert("lacis_id", entry.lacis_id.as_deref().unwrap());
-            }
-            if entry.candidate_lacis_id.is_some() {
-                set_doc.insert(
-                    "candidate_lacis_id",
-                    entry.candidate_lacis_id.as_deref().unwrap(),
-                );
-            }
-
-            // Update device_type if changed (araneaDevice detection)
-            set_doc.insert("device_type", &entry.device_type);
-            if entry.aranea_lacis_id.is_some() {
-                set_doc.insert(
-                    "aranea_lacis_id",
-                    entry.aranea_lacis_id.as_deref().unwrap(),
-                );
-            }
-
+            let set_doc = volatile_fields(entry, &existing);
             collection
                 .update_one(filter, doc! { "$set": set_doc }, None)
                 .await
                 .map_err(|e| format!("Failed to update user_object_detail: {}", e))?;
+            if existing.upsert_changes_view(entry) {
+                self.note_topology_change(&[&entry.id], TopologyChangeKind::Updated)
+                    .await;
+            }
         } else {
             // New entry: insert all fields
             let insert_doc = user_object_detail_to_doc(entry);
@@ -168,9 +190,98 @@ impl MongoDb {
                 .insert_one(insert_doc, None)
                 .await
                 .map_err(|e| format!("Failed to insert user_object_detail: {}", e))?;
+            self.note_topology_change(&[&entry.id], TopologyChangeKind::Added)
+                .await;
+            return Ok(true);
         }
 
-        Ok(())
+        Ok(false)
+    }
+
+    /// Existing entries among `ids`, keyed by _id (read on the ingestion pool)
+    pub async fn get_user_object_details_by_ids(
+        &self,
+        ids: &[String],
+    ) -> Result<HashMap<String, UserObjectDetail>, String> {
+        let mut found = HashMap::new();
+        for chunk in ids.chunks(1000) {
+            let docs: Vec<Document> = self
+                .ingest_db
+                .collection::<Document>(COLLECTION)
+                .find(doc! { "_id": { "$in": chunk } }, None)
+                .await
+                .map_err(|e| format!("Failed to query user_object_detail: {}", e))?
+                .try_collect()
+                .await
+                .map_err(|e| format!("Failed to read user_object_detail: {}", e))?;
+            for d in &docs {
+                if let Ok(entry) = doc_to_user_object_detail(d) {
+                    found.insert(entry.id.clone(), entry);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Upsert one ingest cycle's entries with the same rules as
+    /// `upsert_user_object_detail`, as paced bulk writes on the ingestion
+    /// pool. `existing` is what `get_user_object_details_by_ids` returned
+    /// for these entries. Returns the ids that were inserted.
+    pub async fn bulk_upsert_user_object_details(
+        &self,
+        entries: &[UserObjectDetail],
+        existing: &HashMap<String, UserObjectDetail>,
+        cycle: &mut IngestCycleWrites,
+    ) -> Result<Vec<String>, String> {
+        let pacing = self.ingest_pacing();
+        let mut updated: Vec<&str> = Vec::new();
+        let mut inserted: Vec<String> = Vec::new();
+
+        for (index, chunk) in entries.chunks(pacing.batch_size).enumerate() {
+            self.pace_ingest_batch(index, pacing).await;
+            let statements = chunk
+                .iter()
+                .map(|entry| match existing.get(&entry.id) {
+                    Some(ex) => update_statement(
+                        doc! { "_id": &entry.id },
+                        doc! { "$set": volatile_fields(entry, ex) },
+                        false,
+                    ),
+                    None => {
+                        let mut insert_doc = user_object_detail_to_doc(entry);
+                        insert_doc.remove("_id");
+                        update_statement(
+                            doc! { "_id": &entry.id },
+                            doc! { "$setOnInsert": insert_doc },
+                            true,
+                        )
+                    }
+                })
+                .collect();
+            let upserted = self
+                .ingest_bulk_update(COLLECTION, statements, cycle)
+                .await?;
+            inserted.extend(
+                upserted
+                    .into_iter()
+                    .filter_map(|i| chunk.get(i).map(|entry| entry.id.clone())),
+            );
+            updated.extend(chunk.iter().filter_map(|entry| {
+                let ex = existing.get(&entry.id)?;
+                ex.upsert_changes_view(entry).then_some(entry.id.as_str())
+            }));
+        }
+
+        if !updated.is_empty() {
+            self.note_topology_change(&updated, TopologyChangeKind::Updated)
+                .await;
+        }
+        if !inserted.is_empty() {
+            let ids: Vec<&str> = inserted.iter().map(String::as_str).collect();
+            self.note_topology_change(&ids, TopologyChangeKind::Added)
+                .await;
+        }
+        Ok(inserted)
     }
 
     /// Update only the parent_id of a node (for reparent operations)
@@ -191,6 +302,10 @@ impl MongoDb {
             )
             .await
             .map_err(|e| format!("Failed to update user_object_detail parent: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
         Ok(result.modified_count > 0)
     }
 
@@ -212,6 +327,10 @@ impl MongoDb {
             )
             .await
             .map_err(|e| format!("Failed to update sort_order: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
         Ok(result.modified_count > 0)
     }
 
@@ -235,6 +354,10 @@ impl MongoDb {
             )
             .await
             .map_err(|e| format!("Failed to update label: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
         Ok(result.modified_count > 0)
     }
 
@@ -256,9 +379,303 @@ impl MongoDb {
             )
             .await
             .map_err(|e| format!("Failed to update state_type: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
+        Ok(result.modified_count > 0)
+    }
+
+    /// Record the LacisID confirmed by araneaDeviceGate registration
+    pub async fn set_user_object_detail_lacis_id(
+        &self,
+        id: &str,
+        lacis_id: &str,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let result = collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": {
+                    "lacis_id": lacis_id,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to set lacis_id: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
+        Ok(result.matched_count > 0)
+    }
+
+    /// Claim a node for `claim.lacis_id` unless another user already holds it.
+    /// Re-claiming one's own node refreshes the display name and timestamp.
+    pub async fn claim_user_object_detail(
+        &self,
+        id: &str,
+        claim: &NodeClaim,
+    ) -> Result<ClaimOutcome, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let claim_bson = mongodb::bson::to_bson(claim).map_err(|e| e.to_string())?;
+        let result = collection
+            .update_one(
+                doc! {
+                    "_id": id,
+                    "$or": [
+                        { "claimed_by": null },
+                        { "claimed_by.lacis_id": &claim.lacis_id },
+                    ],
+                },
+                doc! { "$set": {
+                    "claimed_by": claim_bson,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to claim user_object_detail: {}", e))?;
+        if result.matched_count > 0 {
+            if result.modified_count > 0 {
+                self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                    .await;
+            }
+            return Ok(ClaimOutcome::Claimed);
+        }
+
+        // Not matched: either missing or held by someone else
+        match self.get_user_object_detail_by_id(id).await? {
+            None => Ok(ClaimOutcome::NotFound),
+            Some(node) => node
+                .claimed_by
+                .map(ClaimOutcome::Conflict)
+                .ok_or_else(|| format!("Claim on {} changed concurrently, retry", id)),
+        }
+    }
+
+    /// Clear a node's claim if `lacis_id` still holds it; false when the
+    /// claim is gone or belongs to someone else by now
+    pub async fn release_user_object_detail_claim(
+        &self,
+        id: &str,
+        lacis_id: &str,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let result = collection
+            .update_one(
+                doc! { "_id": id, "claimed_by.lacis_id": lacis_id },
+                doc! {
+                    "$unset": { "claimed_by": "" },
+                    "$set": { "updated_at": chrono::Utc::now().to_rfc3339() },
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to release claim: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
         Ok(result.modified_count > 0)
     }
 
+    /// Set or clear a node's claim unconditionally (admin override)
+    pub async fn set_user_object_detail_claim(
+        &self,
+        id: &str,
+        claim: Option<&NodeClaim>,
+    ) -> Result<bool, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let now = chrono::Utc::now().to_rfc3339();
+        let update = match claim {
+            Some(claim) => {
+                let claim_bson = mongodb::bson::to_bson(claim).map_err(|e| e.to_string())?;
+                doc! { "$set": { "claimed_by": claim_bson, "updated_at": now } }
+            }
+            None => doc! {
+                "$unset": { "claimed_by": "" },
+                "$set": { "updated_at": now },
+            },
+        };
+        let result = collection
+            .update_one(doc! { "_id": id }, update, None)
+            .await
+            .map_err(|e| format!("Failed to set claim: {}", e))?;
+        if result.modified_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Updated)
+                .await;
+        }
+        Ok(result.matched_count > 0)
+    }
+
+    /// Apply a site's facility mapping to every Omada entry ingested from it.
+    /// Returns the number of entries changed.
+    pub async fn set_user_object_detail_facility_for_site(
+        &self,
+        controller_id: &str,
+        site_id: &str,
+        fid: Option<&str>,
+        facility_name: Option<&str>,
+    ) -> Result<u64, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let filter = doc! {
+            "source": "omada",
+            "metadata.controller_id": controller_id,
+            "metadata.site_id": site_id,
+            "$or": [
+                { "fid": { "$ne": fid } },
+                { "facility_name": { "$ne": facility_name } },
+            ],
+        };
+        let ids: Vec<String> = collection
+            .find(
+                filter.clone(),
+                mongodb::options::FindOptions::builder()
+                    .projection(doc! { "_id": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Failed to query facility entries: {}", e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Failed to query facility entries: {}", e))?
+            .iter()
+            .filter_map(|d| d.get_str("_id").ok().map(str::to_string))
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = collection
+            .update_many(
+                doc! { "_id": { "$in": &ids } },
+                doc! { "$set": {
+                    "fid": fid,
+                    "facility_name": facility_name,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                }},
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to set facility: {}", e))?;
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        self.note_topology_change(&ids, TopologyChangeKind::Updated)
+            .await;
+        Ok(result.modified_count)
+    }
+
+    /// Ids of the Omada entries ingested from `controller_id` matching `filter`
+    async fn controller_entry_ids(
+        &self,
+        controller_id: &str,
+        filter: Document,
+    ) -> Result<Vec<String>, String> {
+        let mut query = doc! {
+            "source": "omada",
+            "metadata.controller_id": controller_id,
+        };
+        query.extend(filter);
+        let ids = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .find(
+                query,
+                mongodb::options::FindOptions::builder()
+                    .projection(doc! { "_id": 1 })
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Failed to query controller entries: {}", e))?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| format!("Failed to query controller entries: {}", e))?
+            .iter()
+            .filter_map(|d| d.get_str("_id").ok().map(str::to_string))
+            .collect();
+        Ok(ids)
+    }
+
+    /// Put every entry of an offline controller into the unknown state,
+    /// keeping its state in `metadata.state_before_outage` (admin overrides
+    /// are left alone). Returns the number of entries changed.
+    pub async fn mark_controller_entries_unknown(
+        &self,
+        controller_id: &str,
+    ) -> Result<u64, String> {
+        let ids = self
+            .controller_entry_ids(
+                controller_id,
+                doc! {
+                    "state_type": {
+                        "$ne": UNKNOWN_CONTROLLER_OFFLINE,
+                        "$not": { "$regex": "^Static" },
+                    },
+                },
+            )
+            .await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .update_many(
+                doc! { "_id": { "$in": &ids } },
+                vec![doc! { "$set": {
+                    format!("metadata.{}", STATE_BEFORE_OUTAGE): "$state_type",
+                    "state_type": UNKNOWN_CONTROLLER_OFFLINE,
+                    "updated_at": chrono::Utc::now().to_rfc3339(),
+                }}],
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to mark controller entries unknown: {}", e))?;
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        self.note_topology_change(&ids, TopologyChangeKind::Updated)
+            .await;
+        Ok(result.modified_count)
+    }
+
+    /// Return entries still in the unknown state after a controller's
+    /// reconciliation (no longer reported by it) to their state before the
+    /// outage. Returns the number of entries changed.
+    pub async fn restore_controller_entries(&self, controller_id: &str) -> Result<u64, String> {
+        let ids = self
+            .controller_entry_ids(
+                controller_id,
+                doc! { "state_type": UNKNOWN_CONTROLLER_OFFLINE },
+            )
+            .await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let previous = format!("$metadata.{}", STATE_BEFORE_OUTAGE);
+        let result = self
+            .db
+            .collection::<Document>(COLLECTION)
+            .update_many(
+                doc! { "_id": { "$in": &ids } },
+                vec![
+                    doc! { "$set": {
+                        "state_type": { "$ifNull": [previous, "offline"] },
+                        "updated_at": chrono::Utc::now().to_rfc3339(),
+                    }},
+                    doc! { "$unset": format!("metadata.{}", STATE_BEFORE_OUTAGE) },
+                ],
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to restore controller entries: {}", e))?;
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        self.note_topology_change(&ids, TopologyChangeKind::Updated)
+            .await;
+        Ok(result.modified_count)
+    }
+
     /// Delete a user object detail entry by _id
     pub async fn delete_user_object_detail(&self, id: &str) -> Result<bool, String> {
         let collection = self.db.collection::<Document>(COLLECTION);
@@ -266,9 +683,33 @@ impl MongoDb {
             .delete_one(doc! { "_id": id }, None)
             .await
             .map_err(|e| format!("Failed to delete user_object_detail: {}", e))?;
+        if result.deleted_count > 0 {
+            self.note_topology_change(&[id], TopologyChangeKind::Removed)
+                .await;
+        }
         Ok(result.deleted_count > 0)
     }
 
+    /// Write all fields of an entry, inserting it if missing (node merges only;
+    /// ingestion goes through `upsert_user_object_detail`)
+    pub async fn replace_user_object_detail(&self, entry: &UserObjectDetail) -> Result<(), String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let opts = mongodb::options::ReplaceOptions::builder()
+            .upsert(true)
+            .build();
+        collection
+            .replace_one(
+                doc! { "_id": &entry.id },
+                user_object_detail_to_doc(entry),
+                Some(opts),
+            )
+            .await
+            .map_err(|e| format!("Failed to replace user_object_detail: {}", e))?;
+        self.note_topology_change(&[&entry.id], TopologyChangeKind::Updated)
+            .await;
+        Ok(())
+    }
+
     /// Count user object detail entries (used for migration check)
     pub async fn count_user_object_details(&self) -> Result<u64, String> {
         let collection = self.db.collection::<Document>(COLLECTION);
@@ -278,6 +719,74 @@ impl MongoDb {
             .map_err(|e| format!("Failed to count user_object_detail: {}", e))
     }
 
+    /// Entries currently reporting `ip`
+    pub async fn get_user_object_details_by_ip(
+        &self,
+        ip: &str,
+    ) -> Result<Vec<UserObjectDetail>, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let docs: Vec<Document> = collection
+            .find(doc! { "ip": ip }, None)
+            .await
+            .map_err(|e| format!("Failed to query user_object_detail by ip: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to query user_object_detail by ip: {}", e))?;
+        Ok(docs
+            .iter()
+            .filter_map(|d| doc_to_user_object_detail(d).ok())
+            .collect())
+    }
+
+    /// Direct children of a node in sibling order (at most `limit`)
+    pub async fn get_user_object_detail_children(
+        &self,
+        parent_id: &str,
+        limit: i64,
+    ) -> Result<Vec<UserObjectDetail>, String> {
+        let collection = self.db.collection::<Document>(COLLECTION);
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "sort_order": 1, "_id": 1 })
+            .limit(limit)
+            .build();
+        let docs: Vec<Document> = collection
+            .find(doc! { "parent_id": parent_id }, options)
+            .await
+            .map_err(|e| format!("Failed to query node children: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Failed to query node children: {}", e))?;
+        Ok(docs
+            .iter()
+            .filter_map(|d| doc_to_user_object_detail(d).ok())
+            .collect())
+    }
+
+    /// The raw source document a node was ingested from (omada/openwrt/
+    /// external device or client), with credentials masked. None when the
+    /// reference is absent, unknown, or the source record is gone.
+    pub async fn get_node_source_record(
+        &self,
+        source_ref_id: &str,
+    ) -> Result<Option<serde_json::Value>, String> {
+        let Some((collection_name, filter)) = source_record_filter(source_ref_id) else {
+            return Ok(None);
+        };
+        let doc = self
+            .db
+            .collection::<Document>(collection_name)
+            .find_one(filter, None)
+            .await
+            .map_err(|e| format!("Failed to get source record: {}", e))?;
+
+        Ok(doc.map(|mut d| {
+            d.remove("_id");
+            let mut value = mongodb::bson::Bson::Document(d).into_relaxed_extjson();
+            crate::masking::mask_credentials(&mut value);
+            value
+        }))
+    }
+
     /// Find user object detail by MAC field (not _id)
     pub async fn get_user_object_detail_by_mac(
         &self,
@@ -303,6 +812,54 @@ impl MongoDb {
 // Document conversion helpers
 // ============================================================================
 
+/// `$set` of an ingest over an existing entry: volatile fields only, so
+/// parent_id, sort_order, claims and a customized label are kept
+fn volatile_fields(entry: &UserObjectDetail, existing: &UserObjectDetail) -> Document {
+    let mut set_doc = doc! {
+        "state_type": &entry.state_type,
+        "ip": entry.ip.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+        "hostname": entry.hostname.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+        "ssid": entry.ssid.as_ref().map(|s| mongodb::bson::Bson::String(s.clone())).unwrap_or(mongodb::bson::Bson::Null),
+        "updated_at": &entry.updated_at,
+    };
+
+    // Update metadata
+    if let Ok(bson_val) = mongodb::bson::to_bson(&entry.metadata) {
+        set_doc.insert("metadata", bson_val);
+    }
+
+    // Omada entries follow their site's facility mapping
+    if entry.source == "omada" {
+        set_doc.insert("fid", entry.fid.as_deref());
+        set_doc.insert("facility_name", entry.facility_name.as_deref());
+    }
+
+    // Update label only if not customized
+    if !existing.label_customized {
+        set_doc.insert("label", &entry.label);
+    }
+
+    // Update lacis_id if provided (don't overwrite existing with None)
+    if let Some(ref lacis_id) = entry.lacis_id {
+        set_doc.insert("lacis_id", lacis_id);
+    }
+    if let Some(ref candidate) = entry.candidate_lacis_id {
+        set_doc.insert("candidate_lacis_id", candidate);
+    }
+
+    // Reclassified on every ingest (rules may have changed)
+    if let Some(ref class) = entry.device_class {
+        set_doc.insert("device_class", class);
+    }
+
+    // Update device_type if changed (araneaDevice detection)
+    set_doc.insert("device_type", &entry.device_type);
+    if let Some(ref aranea) = entry.aranea_lacis_id {
+        set_doc.insert("aranea_lacis_id", aranea);
+    }
+    set_doc
+}
+
 fn user_object_detail_to_doc(entry: &UserObjectDetail) -> Document {
     let mut doc = doc! {
         "_id": &entry.id,
@@ -324,6 +881,11 @@ fn user_object_detail_to_doc(entry: &UserObjectDetail) -> Document {
     if let Some(ref v) = entry.lacis_id {
         doc.insert("lacis_id", v);
     }
+    if let Some(ref v) = entry.claimed_by {
+        if let Ok(claim) = mongodb::bson::to_bson(v) {
+            doc.insert("claimed_by", claim);
+        }
+    }
     if let Some(ref v) = entry.ip {
         doc.insert("ip", v);
     }
@@ -354,6 +916,9 @@ fn user_object_detail_to_doc(entry: &UserObjectDetail) -> Document {
     if let Some(ref v) = entry.ssid {
         doc.insert("ssid", v);
     }
+    if let Some(ref v) = entry.device_class {
+        doc.insert("device_class", v);
+    }
     if let Some(ref v) = entry.aranea_lacis_id {
         doc.insert("aranea_lacis_id", v);
     }
@@ -364,7 +929,7 @@ fn user_object_detail_to_doc(entry: &UserObjectDetail) -> Document {
     doc
 }
 
-fn doc_to_user_object_detail(doc: &Document) -> Result<UserObjectDetail, String> {
+pub(super) fn doc_to_user_object_detail(doc: &Document) -> Result<UserObjectDetail, String> {
     let get_str = |key: &str| -> String {
         doc.get_str(key).unwrap_or_default().to_string()
     };
@@ -391,6 +956,10 @@ fn doc_to_user_object_detail(doc: &Document) -> Result<UserObjectDetail, String>
         state_type: get_str("state_type"),
         label: get_str("label"),
         label_customized: doc.get_bool("label_customized").unwrap_or(false),
+        claimed_by: doc
+            .get_document("claimed_by")
+            .ok()
+            .and_then(|d| mongodb::bson::from_document(d.clone()).ok()),
         ip: get_opt_str("ip"),
         hostname: get_opt_str("hostname"),
         source: get_str("source"),
@@ -403,6 +972,7 @@ fn doc_to_user_object_detail(doc: &Document) -> Result<UserObjectDetail, String>
         fid: get_opt_str("fid"),
         facility_name: get_opt_str("facility_name"),
         ssid: get_opt_str("ssid"),
+        device_class: get_opt_str("device_class"),
         metadata: doc
             .get("metadata")
             .and_then(|v| mongodb::bson::from_bson(v.clone()).ok())
@@ -412,3 +982,66 @@ fn doc_to_user_object_detail(doc: &Document) -> Result<UserObjectDetail, String>
         updated_at: get_str("updated_at"),
     })
 }
+
+/// Collection and filter for a `source_ref_id` written by the ingester:
+/// `{source}:{reference}:{kind}:{key}` with kind dev, cli or wg
+fn source_record_filter(source_ref_id: &str) -> Option<(&'static str, Document)> {
+    let (source, rest) = source_ref_id.split_once(':')?;
+    let mut parts = rest.rsplitn(3, ':');
+    let key = parts.next().filter(|k| !k.is_empty())?;
+    let kind = parts.next()?;
+    let reference = parts.next().filter(|r| !r.is_empty())?;
+
+    Some(match (source, kind) {
+        ("omada", "dev") => (
+            "omada_devices",
+            doc! { "controller_id": reference, "mac": key },
+        ),
+        ("omada", "cli") => (
+            "omada_clients",
+            doc! { "controller_id": reference, "mac": key },
+        ),
+        ("omada", "wg") => ("omada_wg_peers", doc! { "peer_id": key }),
+        ("openwrt", "dev") => ("openwrt_routers", doc! { "router_id": reference }),
+        ("openwrt", "cli") => (
+            "openwrt_clients",
+            doc! { "router_id": reference, "mac": key },
+        ),
+        ("external", "dev") => ("external_devices", doc! { "device_id": reference }),
+        ("external", "cli") => (
+            "external_clients",
+            doc! { "device_id": reference, "mac": key },
+        ),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_record_filter() {
+        let (collection, filter) = source_record_filter("omada:ctrl1:cli:AABBCCDDEEFF").unwrap();
+        assert_eq!(collection, "omada_clients");
+        assert_eq!(
+            filter,
+            doc! { "controller_id": "ctrl1", "mac": "AABBCCDDEEFF" }
+        );
+
+        let (collection, filter) = source_record_filter("openwrt:r-1:dev:001122334455").unwrap();
+        assert_eq!(
+            (collection, filter),
+            ("openwrt_routers", doc! { "router_id": "r-1" })
+        );
+        assert_eq!(
+            source_record_filter("omada:c:wg:peer9").unwrap().1,
+            doc! { "peer_id": "peer9" }
+        );
+
+        // Logic devices and pre-migration ids carry no source reference
+        assert!(source_record_filter("F2A1B2C3D4E5").is_none());
+        assert!(source_record_filter("manual:x:dev:AA").is_none());
+        assert!(source_record_filter("omada::dev:AA").is_none());
+    }
+}
@@ -0,0 +1,160 @@
+//! ACME account keys and the certificates issued for DDNS hostnames
+
+use chrono::{DateTime, Utc};
+
+use crate::error::AppError;
+use crate::models::AcmeCertificate;
+
+use super::MySqlDb;
+
+const CERTIFICATE_COLUMNS: &str = "id, ddns_config_id, hostname, status, cert_pem, key_pem, \
+     not_before, not_after, issued_at, last_attempt_at, last_error, created_at, updated_at";
+
+impl MySqlDb {
+    /// Tables of the account keys and certificates (run by startup
+    /// migration 036_acme_certificates)
+    pub async fn ensure_acme_tables(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS acme_accounts (
+                directory_url VARCHAR(255) PRIMARY KEY,
+                key_pem TEXT NOT NULL COMMENT 'PKCS#8 P-256 account key',
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS acme_certificates (
+                id INT AUTO_INCREMENT PRIMARY KEY,
+                ddns_config_id INT NOT NULL UNIQUE,
+                hostname VARCHAR(253) NOT NULL,
+                status VARCHAR(16) NOT NULL DEFAULT 'pending' COMMENT 'pending | valid | failed',
+                cert_pem MEDIUMTEXT NULL COMMENT 'Full chain, leaf first',
+                key_pem TEXT NULL,
+                not_before TIMESTAMP NULL,
+                not_after TIMESTAMP NULL,
+                issued_at TIMESTAMP NULL,
+                last_attempt_at TIMESTAMP NULL,
+                last_error TEXT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+                FOREIGN KEY (ddns_config_id) REFERENCES ddns_configs(id) ON DELETE CASCADE
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Account key used with `directory_url`
+    pub async fn get_acme_account_key(
+        &self,
+        directory_url: &str,
+    ) -> Result<Option<String>, AppError> {
+        let key: Option<(String,)> =
+            sqlx::query_as("SELECT key_pem FROM acme_accounts WHERE directory_url = ?")
+                .bind(directory_url)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(key.map(|(k,)| k))
+    }
+
+    /// Store a new account key unless another instance stored one first
+    pub async fn insert_acme_account_key(
+        &self,
+        directory_url: &str,
+        key_pem: &str,
+    ) -> Result<(), AppError> {
+        sqlx::query("INSERT IGNORE INTO acme_accounts (directory_url, key_pem) VALUES (?, ?)")
+            .bind(directory_url)
+            .bind(key_pem)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// All certificates, by hostname
+    pub async fn list_acme_certificates(&self) -> Result<Vec<AcmeCertificate>, AppError> {
+        let rows = sqlx::query_as::<_, AcmeCertificate>(&format!(
+            "SELECT {} FROM acme_certificates ORDER BY hostname",
+            CERTIFICATE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Record an order started for a DDNS config's hostname
+    pub async fn begin_acme_order(
+        &self,
+        ddns_config_id: i32,
+        hostname: &str,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO acme_certificates (ddns_config_id, hostname, status, last_attempt_at)
+            VALUES (?, ?, 'pending', NOW())
+            ON DUPLICATE KEY UPDATE
+                hostname = VALUES(hostname), status = 'pending', last_attempt_at = NOW()
+            "#,
+        )
+        .bind(ddns_config_id)
+        .bind(hostname)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store an issued certificate
+    pub async fn store_acme_certificate(
+        &self,
+        ddns_config_id: i32,
+        cert_pem: &str,
+        key_pem: &str,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+        issued_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE acme_certificates
+            SET status = 'valid', cert_pem = ?, key_pem = ?, not_before = ?, not_after = ?,
+                issued_at = ?, last_error = NULL
+            WHERE ddns_config_id = ?
+            "#,
+        )
+        .bind(cert_pem)
+        .bind(key_pem)
+        .bind(not_before)
+        .bind(not_after)
+        .bind(issued_at)
+        .bind(ddns_config_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed order (a stored certificate stays in service)
+    pub async fn fail_acme_order(&self, ddns_config_id: i32, error: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE acme_certificates SET status = 'failed', last_error = ? WHERE ddns_config_id = ?",
+        )
+        .bind(error)
+        .bind(ddns_config_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! Device registration tokens for POST /api/aranea/register
+
+use chrono::{DateTime, Utc};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::AraneaRegistrationToken;
+
+use super::MySqlDb;
+
+const TOKEN_COLUMNS: &str = "id, name, fid, product_types, token_prefix, expires_at, max_uses, \
+     use_count, last_used_at, last_used_ip, revoked_at, created_by, created_at";
+
+fn token_from_row(row: &MySqlRow) -> AraneaRegistrationToken {
+    AraneaRegistrationToken {
+        id: row.get("id"),
+        name: row.get("name"),
+        fid: row.get("fid"),
+        product_types: serde_json::from_str(row.get::<&str, _>("product_types"))
+            .unwrap_or_default(),
+        token_prefix: row.get("token_prefix"),
+        expires_at: row.get("expires_at"),
+        max_uses: row.get("max_uses"),
+        use_count: row.get("use_count"),
+        last_used_at: row.get("last_used_at"),
+        last_used_ip: row.get("last_used_ip"),
+        revoked_at: row.get("revoked_at"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+    }
+}
+
+impl MySqlDb {
+    /// Table for the tokens (run by startup migration 018_aranea_registration_tokens)
+    pub async fn ensure_aranea_registration_tokens_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS aranea_registration_tokens (
+                id INT AUTO_INCREMENT PRIMARY KEY,
+                name VARCHAR(100) NOT NULL UNIQUE,
+                fid VARCHAR(50) NOT NULL,
+                product_types TEXT NOT NULL COMMENT 'JSON list of allowed productType values',
+                token_hash CHAR(64) NOT NULL UNIQUE COMMENT 'SHA-256 hex of the token',
+                token_prefix VARCHAR(16) NOT NULL,
+                expires_at TIMESTAMP NULL,
+                max_uses INT NULL COMMENT 'NULL = unlimited',
+                use_count INT NOT NULL DEFAULT 0,
+                last_used_at TIMESTAMP NULL,
+                last_used_ip VARCHAR(45) NULL,
+                revoked_at TIMESTAMP NULL,
+                created_by VARCHAR(255) NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// All tokens, revoked ones included, newest first
+    pub async fn list_registration_tokens(&self) -> Result<Vec<AraneaRegistrationToken>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM aranea_registration_tokens ORDER BY created_at DESC, id DESC",
+            TOKEN_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(token_from_row).collect())
+    }
+
+    pub async fn get_registration_token(
+        &self,
+        id: i32,
+    ) -> Result<Option<AraneaRegistrationToken>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM aranea_registration_tokens WHERE id = ?",
+            TOKEN_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(token_from_row))
+    }
+
+    /// Token whose hash is `token_hash`, whatever its state
+    pub async fn find_registration_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<AraneaRegistrationToken>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM aranea_registration_tokens WHERE token_hash = ?",
+            TOKEN_COLUMNS
+        ))
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(token_from_row))
+    }
+
+    /// Insert a token; returns the new id
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_registration_token(
+        &self,
+        name: &str,
+        fid: &str,
+        product_types: &[String],
+        token_hash: &str,
+        token_prefix: &str,
+        expires_at: Option<DateTime<Utc>>,
+        max_uses: Option<i32>,
+        created_by: &str,
+    ) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO aranea_registration_tokens
+                (name, fid, product_types, token_hash, token_prefix, expires_at, max_uses, created_by)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(name)
+        .bind(fid)
+        .bind(serde_json::to_string(product_types).unwrap_or_default())
+        .bind(token_hash)
+        .bind(token_prefix)
+        .bind(expires_at)
+        .bind(max_uses)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Replace the secret of a live token; false if missing or revoked
+    pub async fn rotate_registration_token(
+        &self,
+        id: i32,
+        token_hash: &str,
+        token_prefix: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE aranea_registration_tokens
+            SET token_hash = ?, token_prefix = ?
+            WHERE id = ? AND revoked_at IS NULL
+            "#,
+        )
+        .bind(token_hash)
+        .bind(token_prefix)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revoke a token; false if missing or already revoked
+    pub async fn revoke_registration_token(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE aranea_registration_tokens SET revoked_at = NOW() WHERE id = ? AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count one use if the token is still live (not revoked, expired or used
+    /// up); false otherwise. The check and the increment are one statement,
+    /// so concurrent registrations cannot overrun `max_uses`.
+    pub async fn consume_registration_token(&self, id: i32, ip: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE aranea_registration_tokens
+            SET use_count = use_count + 1, last_used_at = NOW(), last_used_ip = ?
+            WHERE id = ?
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (max_uses IS NULL OR use_count < max_uses)
+            "#,
+        )
+        .bind(ip)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,19 +1,31 @@
 //! Blocked IPs CRUD operations
 
 use chrono::{DateTime, Utc};
-use sqlx::Row;
+use sqlx::{MySql, QueryBuilder, Row};
 
+use crate::blocklist::BlockEntryInput;
 use crate::error::AppError;
 use crate::models::{BlockIpRequest, BlockedIp};
 
 use super::MySqlDb;
 
+/// `ip IN (...)` over the spellings a stored entry may have (rows written
+/// before addresses were canonicalized keep theirs, see `crate::ip`)
+fn push_ip_match(builder: &mut QueryBuilder<'_, MySql>, ip: &str) {
+    builder.push("ip IN (");
+    let mut separated = builder.separated(", ");
+    for variant in crate::ip::ip_variants(ip) {
+        separated.push_bind(variant);
+    }
+    separated.push_unseparated(")");
+}
+
 impl MySqlDb {
     /// Get all blocked IPs (including expired for history)
     pub async fn list_blocked_ips(&self) -> Result<Vec<BlockedIp>, AppError> {
         let ips = sqlx::query_as::<_, BlockedIp>(
             r#"
-            SELECT id, ip, reason, blocked_by, expires_at, created_at
+            SELECT id, ip, reason, blocked_by, expires_at, created_at, context_ref
             FROM blocked_ips
             ORDER BY created_at DESC
             "#,
@@ -28,7 +40,7 @@ impl MySqlDb {
     pub async fn list_active_blocked_ips(&self) -> Result<Vec<BlockedIp>, AppError> {
         let ips = sqlx::query_as::<_, BlockedIp>(
             r#"
-            SELECT id, ip, reason, blocked_by, expires_at, created_at
+            SELECT id, ip, reason, blocked_by, expires_at, created_at, context_ref
             FROM blocked_ips
             WHERE expires_at IS NULL OR expires_at > NOW()
             ORDER BY created_at DESC
@@ -40,18 +52,24 @@ impl MySqlDb {
         Ok(ips)
     }
 
-    /// Check if an IP is blocked
+    /// (row count, highest id): changes on every insert and delete, so the
+    /// network policy watcher can skip rebuilds while nothing changed
+    pub async fn blocked_ips_fingerprint(&self) -> Result<(i64, Option<i32>), AppError> {
+        let row = sqlx::query("SELECT COUNT(*) AS count, MAX(id) AS max_id FROM blocked_ips")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((row.get("count"), row.get("max_id")))
+    }
+
+    /// Check if an IP is blocked (by an entry of exactly this address or
+    /// network, in any spelling)
     pub async fn is_ip_blocked(&self, ip: &str) -> Result<bool, AppError> {
-        let row = sqlx::query(
-            r#"
-            SELECT COUNT(*) as count
-            FROM blocked_ips
-            WHERE ip = ? AND (expires_at IS NULL OR expires_at > NOW())
-            "#,
-        )
-        .bind(ip)
-        .fetch_one(&self.pool)
-        .await?;
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("SELECT COUNT(*) as count FROM blocked_ips WHERE ");
+        push_ip_match(&mut builder, ip);
+        builder.push(" AND (expires_at IS NULL OR expires_at > NOW())");
+        let row = builder.build().fetch_one(&self.pool).await?;
 
         Ok(row.get::<i64, _>("count") > 0)
     }
@@ -60,7 +78,7 @@ impl MySqlDb {
     pub async fn get_blocked_ip(&self, id: i32) -> Result<Option<BlockedIp>, AppError> {
         let ip = sqlx::query_as::<_, BlockedIp>(
             r#"
-            SELECT id, ip, reason, blocked_by, expires_at, created_at
+            SELECT id, ip, reason, blocked_by, expires_at, created_at, context_ref
             FROM blocked_ips
             WHERE id = ?
             "#,
@@ -74,23 +92,50 @@ impl MySqlDb {
 
     /// Block an IP address
     pub async fn block_ip(&self, req: &BlockIpRequest, blocked_by: &str) -> Result<i32, AppError> {
+        self.block_ip_with_context(req, blocked_by, None).await
+    }
+
+    /// Block an IP address, recording what the block was made from
+    pub async fn block_ip_with_context(
+        &self,
+        req: &BlockIpRequest,
+        blocked_by: &str,
+        context_ref: Option<&str>,
+    ) -> Result<i32, AppError> {
         let result = sqlx::query(
             r#"
-            INSERT INTO blocked_ips (ip, reason, blocked_by, expires_at)
-            VALUES (?, ?, ?, ?)
-            ON DUPLICATE KEY UPDATE reason = VALUES(reason), blocked_by = VALUES(blocked_by), expires_at = VALUES(expires_at)
+            INSERT INTO blocked_ips (ip, reason, blocked_by, expires_at, context_ref)
+            VALUES (?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE reason = VALUES(reason), blocked_by = VALUES(blocked_by), expires_at = VALUES(expires_at), context_ref = VALUES(context_ref)
             "#,
         )
         .bind(&req.ip)
         .bind(&req.reason)
         .bind(blocked_by)
         .bind(&req.expires_at)
+        .bind(context_ref)
         .execute(&self.pool)
         .await?;
 
         Ok(result.last_insert_id() as i32)
     }
 
+    /// Active block of exactly this address/network, if any
+    pub async fn get_active_block_by_ip(&self, ip: &str) -> Result<Option<BlockedIp>, AppError> {
+        let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+            "SELECT id, ip, reason, blocked_by, expires_at, created_at, context_ref \
+             FROM blocked_ips WHERE ",
+        );
+        push_ip_match(&mut builder, ip);
+        builder.push(" AND (expires_at IS NULL OR expires_at > NOW()) LIMIT 1");
+        let blocked = builder
+            .build_query_as::<BlockedIp>()
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(blocked)
+    }
+
     /// Block an IP with auto-detection source
     pub async fn auto_block_ip(
         &self,
@@ -126,14 +171,92 @@ impl MySqlDb {
 
     /// Unblock an IP by address
     pub async fn unblock_ip_by_address(&self, ip: &str) -> Result<bool, AppError> {
-        let result = sqlx::query("DELETE FROM blocked_ips WHERE ip = ?")
-            .bind(ip)
-            .execute(&self.pool)
-            .await?;
+        let mut builder: QueryBuilder<MySql> = QueryBuilder::new("DELETE FROM blocked_ips WHERE ");
+        push_ip_match(&mut builder, ip);
+        let result = builder.build().execute(&self.pool).await?;
 
         Ok(result.rows_affected() > 0)
     }
 
+    /// List IP strings blocked by a given source (e.g. "feed:spamhaus")
+    pub async fn list_blocked_ips_by_source(
+        &self,
+        blocked_by: &str,
+    ) -> Result<Vec<String>, AppError> {
+        let rows = sqlx::query("SELECT ip FROM blocked_ips WHERE blocked_by = ?")
+            .bind(blocked_by)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("ip")).collect())
+    }
+
+    /// Insert entries that are not yet blocked, leaving existing rows untouched.
+    /// Returns the number of rows inserted.
+    pub async fn insert_blocked_ips_if_absent(
+        &self,
+        entries: &[BlockEntryInput],
+        blocked_by: &str,
+    ) -> Result<u64, AppError> {
+        let mut inserted = 0;
+
+        for chunk in entries.chunks(500) {
+            let mut builder: QueryBuilder<MySql> = QueryBuilder::new(
+                "INSERT IGNORE INTO blocked_ips (ip, reason, blocked_by, expires_at) ",
+            );
+            builder.push_values(chunk, |mut b, entry| {
+                b.push_bind(&entry.ip)
+                    .push_bind(&entry.reason)
+                    .push_bind(blocked_by)
+                    .push_bind(entry.expires_at);
+            });
+            let result = builder.build().execute(&self.pool).await?;
+            inserted += result.rows_affected();
+        }
+
+        Ok(inserted)
+    }
+
+    /// Delete the given IPs, but only rows owned by `blocked_by`
+    pub async fn delete_blocked_ips_by_source(
+        &self,
+        blocked_by: &str,
+        ips: &[String],
+    ) -> Result<u64, AppError> {
+        let mut deleted = 0;
+
+        for chunk in ips.chunks(500) {
+            let mut builder: QueryBuilder<MySql> =
+                QueryBuilder::new("DELETE FROM blocked_ips WHERE blocked_by = ");
+            builder.push_bind(blocked_by).push(" AND ip IN (");
+            let mut separated = builder.separated(", ");
+            for ip in chunk {
+                separated.push_bind(ip);
+            }
+            separated.push_unseparated(")");
+            let result = builder.build().execute(&self.pool).await?;
+            deleted += result.rows_affected();
+        }
+
+        Ok(deleted)
+    }
+
+    /// blocked_ips.context_ref (run by startup migration 019_blocked_ip_context)
+    pub async fn ensure_blocked_ip_context_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE blocked_ips
+                ADD COLUMN IF NOT EXISTS context_ref VARCHAR(100) NULL
+                    COMMENT 'Quick-block source: access_log:<id> or security_event:<id>'
+                    AFTER created_at
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// Get count of blocked IPs (active only)
     pub async fn count_blocked_ips(&self) -> Result<u32, AppError> {
         let row = sqlx::query(
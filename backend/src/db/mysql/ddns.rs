@@ -3,20 +3,64 @@
 use chrono::Utc;
 use sqlx::Row;
 
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode};
 use crate::models::{CreateDdnsRequest, DdnsConfig, DdnsConfigRow, DdnsStatus, UpdateDdnsRequest};
 
 use super::MySqlDb;
 
 impl MySqlDb {
+    /// ddns_configs.update_ipv6 / last_ipv6 (run by startup migration
+    /// 032_ddns_ipv6)
+    pub async fn ensure_ddns_ipv6_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE ddns_configs
+                ADD COLUMN IF NOT EXISTS update_ipv6 BOOLEAN NOT NULL DEFAULT FALSE
+                    COMMENT 'Also push the public IPv6 address (AAAA)'
+                    AFTER last_ip,
+                ADD COLUMN IF NOT EXISTS last_ipv6 VARCHAR(45) NULL
+                    COMMENT 'Last pushed IPv6 address'
+                    AFTER update_ipv6
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// ddns_configs.record_id / record_id_v6 (run by startup migration
+    /// 033_ddns_record_ids)
+    pub async fn ensure_ddns_record_id_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE ddns_configs
+                ADD COLUMN IF NOT EXISTS record_id VARCHAR(64) NULL
+                    COMMENT 'cloudflare: cached A record id'
+                    AFTER ttl,
+                ADD COLUMN IF NOT EXISTS record_id_v6 VARCHAR(64) NULL
+                    COMMENT 'cloudflare: cached AAAA record id'
+                    AFTER record_id
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// Get all DDNS configurations
     pub async fn list_ddns(&self) -> Result<Vec<DdnsConfig>, AppError> {
         let rows = sqlx::query_as::<_, DdnsConfigRow>(
             r#"
             SELECT id, provider, hostname, username, password, api_token, zone_id,
-                   update_interval_sec, last_ip, last_update, last_error, status,
+                   update_interval_sec, last_ip, update_ipv6, last_ipv6,
+                   last_update, last_error, status,
                    omada_controller_id, omada_site_id,
-                   created_at, updated_at
+                   ip_source, openwrt_router_id, report_token, reported_ip, reported_at,
+                   proxied, ttl, record_id, record_id_v6,
+                   secondary_config_id, failover_threshold,
+                   consecutive_failures, failover_active, created_at, updated_at
             FROM ddns_configs
             ORDER BY id ASC
             "#,
@@ -34,9 +78,13 @@ impl MySqlDb {
         let rows = sqlx::query_as::<_, DdnsConfigRow>(
             r#"
             SELECT id, provider, hostname, username, password, api_token, zone_id,
-                   update_interval_sec, last_ip, last_update, last_error, status,
+                   update_interval_sec, last_ip, update_ipv6, last_ipv6,
+                   last_update, last_error, status,
                    omada_controller_id, omada_site_id,
-                   created_at, updated_at
+                   ip_source, openwrt_router_id, report_token, reported_ip, reported_at,
+                   proxied, ttl, record_id, record_id_v6,
+                   secondary_config_id, failover_threshold,
+                   consecutive_failures, failover_active, created_at, updated_at
             FROM ddns_configs
             WHERE status = 'active'
             ORDER BY id ASC
@@ -55,9 +103,13 @@ impl MySqlDb {
         let row = sqlx::query_as::<_, DdnsConfigRow>(
             r#"
             SELECT id, provider, hostname, username, password, api_token, zone_id,
-                   update_interval_sec, last_ip, last_update, last_error, status,
+                   update_interval_sec, last_ip, update_ipv6, last_ipv6,
+                   last_update, last_error, status,
                    omada_controller_id, omada_site_id,
-                   created_at, updated_at
+                   ip_source, openwrt_router_id, report_token, reported_ip, reported_at,
+                   proxied, ttl, record_id, record_id_v6,
+                   secondary_config_id, failover_threshold,
+                   consecutive_failures, failover_active, created_at, updated_at
             FROM ddns_configs
             WHERE id = ?
             "#,
@@ -75,11 +127,17 @@ impl MySqlDb {
     }
 
     /// Create a new DDNS configuration
-    pub async fn create_ddns(&self, req: &CreateDdnsRequest) -> Result<i32, AppError> {
+    pub async fn create_ddns(
+        &self,
+        req: &CreateDdnsRequest,
+        report_token: Option<&str>,
+    ) -> Result<i32, AppError> {
         let result = sqlx::query(
             r#"
-            INSERT INTO ddns_configs (provider, hostname, username, password, api_token, zone_id, update_interval_sec)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO ddns_configs (provider, hostname, username, password, api_token, zone_id, update_interval_sec,
+                                      update_ipv6, ip_source, openwrt_router_id, report_token, proxied, ttl,
+                                      secondary_config_id, failover_threshold)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(req.provider.to_string())
@@ -89,6 +147,14 @@ impl MySqlDb {
         .bind(&req.api_token)
         .bind(&req.zone_id)
         .bind(req.update_interval_sec)
+        .bind(req.update_ipv6)
+        .bind(req.ip_source.to_string())
+        .bind(&req.openwrt_router_id)
+        .bind(report_token)
+        .bind(req.proxied)
+        .bind(req.ttl)
+        .bind(req.secondary_config_id)
+        .bind(req.failover_threshold)
         .execute(&self.pool)
         .await?;
 
@@ -97,10 +163,12 @@ impl MySqlDb {
 
     /// Update an existing DDNS configuration
     pub async fn update_ddns(&self, id: i32, req: &UpdateDdnsRequest) -> Result<bool, AppError> {
-        let existing = self
-            .get_ddns(id)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("DDNS config {} not found", id)))?;
+        let existing = self.get_ddns(id).await?.ok_or_else(|| {
+            AppError::coded(
+                ErrorCode::DdnsConfigNotFound,
+                format!("DDNS config {} not found", id),
+            )
+        })?;
 
         let hostname = req.hostname.as_ref().unwrap_or(&existing.hostname);
         let username = req.username.as_ref().or(existing.username.as_ref());
@@ -110,13 +178,36 @@ impl MySqlDb {
         let update_interval_sec = req
             .update_interval_sec
             .unwrap_or(existing.update_interval_sec);
+        let update_ipv6 = req.update_ipv6.unwrap_or(existing.update_ipv6);
         let status = req.status.unwrap_or(existing.status);
+        let ip_source = req.ip_source.unwrap_or(existing.ip_source);
+        let openwrt_router_id = req
+            .openwrt_router_id
+            .as_ref()
+            .or(existing.openwrt_router_id.as_ref())
+            .filter(|r| !r.is_empty());
+        let proxied = req.proxied.unwrap_or(existing.proxied);
+        let ttl = req.ttl.unwrap_or(existing.ttl);
+        let secondary_config_id = req
+            .secondary_config_id
+            .unwrap_or(existing.secondary_config_id);
+        let failover_threshold = req
+            .failover_threshold
+            .unwrap_or(existing.failover_threshold);
+        // Unlinking the secondary ends any failover in progress
+        let failover_active = existing.failover_active && secondary_config_id.is_some();
+        // Cached record ids belong to the old record
+        let records_moved = hostname != &existing.hostname || zone_id != existing.zone_id.as_ref();
 
         let result = sqlx::query(
             r#"
             UPDATE ddns_configs
             SET hostname = ?, username = ?, password = ?, api_token = ?,
-                zone_id = ?, update_interval_sec = ?, status = ?
+                zone_id = ?, update_interval_sec = ?, update_ipv6 = ?,
+                last_ipv6 = IF(?, last_ipv6, NULL), status = ?,
+                ip_source = ?, openwrt_router_id = ?, proxied = ?, ttl = ?,
+                secondary_config_id = ?, failover_threshold = ?, failover_active = ?,
+                record_id = IF(?, NULL, record_id), record_id_v6 = IF(?, NULL, record_id_v6)
             WHERE id = ?
             "#,
         )
@@ -126,7 +217,18 @@ impl MySqlDb {
         .bind(api_token)
         .bind(zone_id)
         .bind(update_interval_sec)
+        .bind(update_ipv6)
+        .bind(update_ipv6)
         .bind(status.to_string())
+        .bind(ip_source.to_string())
+        .bind(openwrt_router_id)
+        .bind(proxied)
+        .bind(ttl)
+        .bind(secondary_config_id)
+        .bind(failover_threshold)
+        .bind(failover_active)
+        .bind(records_moved)
+        .bind(records_moved)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -134,6 +236,29 @@ impl MySqlDb {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Set (or replace) the report-ip token
+    pub async fn set_ddns_report_token(&self, id: i32, token: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("UPDATE ddns_configs SET report_token = ? WHERE id = ?")
+            .bind(token)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record an address pushed by a webhook reporter
+    pub async fn set_ddns_reported_ip(&self, id: i32, ip: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE ddns_configs SET reported_ip = ?, reported_at = ? WHERE id = ?")
+            .bind(ip)
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// Delete a DDNS configuration
     pub async fn delete_ddns(&self, id: i32) -> Result<bool, AppError> {
         let result = sqlx::query("DELETE FROM ddns_configs WHERE id = ?")
@@ -144,22 +269,25 @@ impl MySqlDb {
         Ok(result.rows_affected() > 0)
     }
 
-    /// Update DDNS last IP and status
+    /// Update DDNS last IP(s) and status (no `ipv6` keeps last_ipv6)
     pub async fn update_ddns_ip(
         &self,
         id: i32,
         ip: &str,
+        ipv6: Option<&str>,
         status: DdnsStatus,
         error: Option<&str>,
     ) -> Result<(), AppError> {
         sqlx::query(
             r#"
             UPDATE ddns_configs
-            SET last_ip = ?, last_update = ?, status = ?, last_error = ?
+            SET last_ip = ?, last_ipv6 = COALESCE(?, last_ipv6), last_update = ?,
+                status = ?, last_error = ?, consecutive_failures = 0
             WHERE id = ?
             "#,
         )
         .bind(ip)
+        .bind(ipv6)
         .bind(Utc::now())
         .bind(status.to_string())
         .bind(error)
@@ -170,12 +298,40 @@ impl MySqlDb {
         Ok(())
     }
 
-    /// Set DDNS error status
+    /// Keep provider-side ids learned during an update (None leaves a
+    /// column as it is)
+    pub async fn set_ddns_provider_ids(
+        &self,
+        id: i32,
+        zone_id: Option<&str>,
+        record_id: Option<&str>,
+        record_id_v6: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE ddns_configs
+            SET zone_id = COALESCE(?, zone_id), record_id = COALESCE(?, record_id),
+                record_id_v6 = COALESCE(?, record_id_v6)
+            WHERE id = ?
+            "#,
+        )
+        .bind(zone_id)
+        .bind(record_id)
+        .bind(record_id_v6)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set DDNS error status (counts towards the failover threshold)
     pub async fn set_ddns_error(&self, id: i32, error: &str) -> Result<(), AppError> {
         sqlx::query(
             r#"
             UPDATE ddns_configs
-            SET status = 'error', last_error = ?
+            SET status = 'error', last_error = ?,
+                consecutive_failures = consecutive_failures + 1
             WHERE id = ?
             "#,
         )
@@ -187,6 +343,29 @@ impl MySqlDb {
         Ok(())
     }
 
+    /// Record a failed ACME order in `last_error` (leaves the update status
+    /// and the failover count alone)
+    pub async fn set_ddns_acme_error(&self, id: i32, error: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE ddns_configs SET last_error = ? WHERE id = ?")
+            .bind(format!("ACME: {}", error))
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Switch updates to (true) or back from (false) the secondary config
+    pub async fn set_ddns_failover_active(&self, id: i32, active: bool) -> Result<(), AppError> {
+        sqlx::query("UPDATE ddns_configs SET failover_active = ? WHERE id = ?")
+            .bind(active)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// Get count of active DDNS configs
     pub async fn count_active_ddns(&self) -> Result<u32, AppError> {
         let row = sqlx::query("SELECT COUNT(*) as count FROM ddns_configs WHERE status = 'active'")
@@ -4,8 +4,21 @@
 //! for devices in user_object_detail. Writes happen only when the ingester
 //! detects a state_type change.
 
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
 use super::MySqlDb;
 
+/// One recorded state transition
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct DeviceStateChange {
+    pub device_id: String,
+    pub state_type: String,
+    pub previous_state: Option<String>,
+    /// UTC
+    pub changed_at: NaiveDateTime,
+}
+
 impl MySqlDb {
     /// Ensure device_state_history table exists (auto-migration on startup)
     pub async fn ensure_device_state_history_table(&self) -> Result<(), String> {
@@ -53,4 +66,47 @@ impl MySqlDb {
 
         Ok(())
     }
+
+    /// State changes in [from, to), oldest first
+    pub async fn list_device_state_changes(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Result<Vec<DeviceStateChange>, String> {
+        sqlx::query_as::<_, DeviceStateChange>(
+            r#"
+            SELECT device_id, state_type, previous_state, changed_at
+            FROM device_state_history
+            WHERE changed_at >= ? AND changed_at < ?
+            ORDER BY changed_at, id
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|e| format!("Failed to list device state changes: {}", e))
+    }
+
+    /// Most recent state changes of one device, newest first
+    pub async fn list_device_state_history(
+        &self,
+        device_id: &str,
+        limit: i64,
+    ) -> Result<Vec<DeviceStateChange>, String> {
+        sqlx::query_as::<_, DeviceStateChange>(
+            r#"
+            SELECT device_id, state_type, previous_state, changed_at
+            FROM device_state_history
+            WHERE device_id = ?
+            ORDER BY changed_at DESC, id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(device_id)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await
+        .map_err(|e| format!("Failed to list device state history: {}", e))
+    }
 }
@@ -0,0 +1,112 @@
+//! Local DNS overrides served by the built-in LAN responder
+
+use crate::error::AppError;
+use crate::models::DnsOverride;
+
+use super::MySqlDb;
+
+const OVERRIDE_COLUMNS: &str =
+    "id, hostname, target, enabled, source, comment, created_at, updated_at";
+
+impl MySqlDb {
+    /// Table for the override records (run by startup migration 017_dns_overrides)
+    pub async fn ensure_dns_overrides_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS dns_overrides (
+                id INT AUTO_INCREMENT PRIMARY KEY,
+                hostname VARCHAR(253) NOT NULL UNIQUE,
+                target VARCHAR(45) NULL COMMENT 'NULL = LPG LAN IP',
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                source VARCHAR(20) NOT NULL DEFAULT 'manual' COMMENT 'manual | ddns',
+                comment VARCHAR(500) NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// All overrides, by hostname
+    pub async fn list_dns_overrides(&self) -> Result<Vec<DnsOverride>, AppError> {
+        let rows = sqlx::query_as::<_, DnsOverride>(&format!(
+            "SELECT {} FROM dns_overrides ORDER BY hostname",
+            OVERRIDE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn get_dns_override(&self, id: i32) -> Result<Option<DnsOverride>, AppError> {
+        let row = sqlx::query_as::<_, DnsOverride>(&format!(
+            "SELECT {} FROM dns_overrides WHERE id = ?",
+            OVERRIDE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Insert a validated override; returns the new id
+    pub async fn create_dns_override(
+        &self,
+        hostname: &str,
+        target: Option<&str>,
+        enabled: bool,
+        source: &str,
+        comment: Option<&str>,
+    ) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO dns_overrides (hostname, target, enabled, source, comment)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(hostname)
+        .bind(target)
+        .bind(enabled)
+        .bind(source)
+        .bind(comment)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Overwrite an override's editable fields with `record`
+    pub async fn update_dns_override(&self, record: &DnsOverride) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE dns_overrides
+            SET hostname = ?, target = ?, enabled = ?, comment = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&record.hostname)
+        .bind(&record.target)
+        .bind(record.enabled)
+        .bind(&record.comment)
+        .bind(record.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_dns_override(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM dns_overrides WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,140 @@
+//! LacisOath identity providers (one row per mobes 2.0 environment)
+
+use crate::error::AppError;
+use crate::models::{CreateLacisOathProviderRequest, LacisOathProvider};
+
+use super::MySqlDb;
+
+const PROVIDER_COLUMNS: &str = "provider_id, display_name, client_id, client_secret, auth_url, \
+     token_url, redirect_uri, expected_tid, required_permission, required_fid, enabled, \
+     created_at, updated_at";
+
+impl MySqlDb {
+    /// Table for the stored providers (run by startup migration 010_lacisoath_providers)
+    pub async fn ensure_lacisoath_providers_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS lacisoath_providers (
+                provider_id VARCHAR(50) PRIMARY KEY,
+                display_name VARCHAR(100) NOT NULL,
+                client_id VARCHAR(255) NOT NULL,
+                client_secret VARCHAR(500) NOT NULL,
+                auth_url VARCHAR(500) NOT NULL,
+                token_url VARCHAR(500) NOT NULL,
+                redirect_uri VARCHAR(500) NOT NULL,
+                expected_tid VARCHAR(100) NULL COMMENT 'NULL = tenant not checked',
+                required_permission INT NULL COMMENT 'NULL = login floor only',
+                required_fid VARCHAR(20) NOT NULL,
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stored providers, by id
+    pub async fn list_lacisoath_providers(&self) -> Result<Vec<LacisOathProvider>, AppError> {
+        let rows = sqlx::query_as::<_, LacisOathProvider>(&format!(
+            "SELECT {} FROM lacisoath_providers ORDER BY provider_id",
+            PROVIDER_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn get_lacisoath_provider(
+        &self,
+        provider_id: &str,
+    ) -> Result<Option<LacisOathProvider>, AppError> {
+        let row = sqlx::query_as::<_, LacisOathProvider>(&format!(
+            "SELECT {} FROM lacisoath_providers WHERE provider_id = ?",
+            PROVIDER_COLUMNS
+        ))
+        .bind(provider_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    pub async fn create_lacisoath_provider(
+        &self,
+        req: &CreateLacisOathProviderRequest,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO lacisoath_providers
+            (provider_id, display_name, client_id, client_secret, auth_url, token_url,
+             redirect_uri, expected_tid, required_permission, required_fid, enabled)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(req.provider_id.trim())
+        .bind(req.display_name.trim())
+        .bind(req.client_id.trim())
+        .bind(&req.client_secret)
+        .bind(req.auth_url.trim())
+        .bind(req.token_url.trim())
+        .bind(req.redirect_uri.trim())
+        .bind(
+            req.expected_tid
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty()),
+        )
+        .bind(req.required_permission)
+        .bind(req.required_fid.trim())
+        .bind(req.enabled)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Overwrite a provider's editable fields with `provider`
+    pub async fn update_lacisoath_provider(
+        &self,
+        provider: &LacisOathProvider,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE lacisoath_providers
+            SET display_name = ?, client_id = ?, client_secret = ?, auth_url = ?, token_url = ?,
+                redirect_uri = ?, expected_tid = ?, required_permission = ?, required_fid = ?,
+                enabled = ?
+            WHERE provider_id = ?
+            "#,
+        )
+        .bind(&provider.display_name)
+        .bind(&provider.client_id)
+        .bind(&provider.client_secret)
+        .bind(&provider.auth_url)
+        .bind(&provider.token_url)
+        .bind(&provider.redirect_uri)
+        .bind(&provider.expected_tid)
+        .bind(provider.required_permission)
+        .bind(&provider.required_fid)
+        .bind(provider.enabled)
+        .bind(&provider.provider_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_lacisoath_provider(&self, provider_id: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM lacisoath_providers WHERE provider_id = ?")
+            .bind(provider_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
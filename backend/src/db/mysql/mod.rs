@@ -1,11 +1,20 @@
 //! MySQL database module
 
+mod acme;
+mod aranea_tokens;
 mod audit;
 mod blocked_ips;
 mod ddns;
-mod device_state;
+pub mod device_state;
+mod dns_overrides;
+mod lacisoath_providers;
+mod rate_limit_rules;
+mod route_pending;
+mod route_versions;
 mod routes;
 mod settings;
+mod topology_shares;
+mod wg_profiles;
 
 use sqlx::mysql::MySqlPoolOptions;
 use sqlx::MySqlPool;
@@ -50,4 +59,13 @@ impl MySqlDb {
     pub fn pool(&self) -> &MySqlPool {
         &self.pool
     }
+
+    /// Check the server answers
+    pub async fn ping(&self) -> Result<(), String> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("MySQL ping failed: {}", e))
+    }
 }
@@ -0,0 +1,139 @@
+//! Per-client-IP rate limit rules enforced by the proxy
+
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::RateLimitRule;
+use crate::proxy::rate_limit::RateLimitAction;
+
+use super::MySqlDb;
+
+const RULE_COLUMNS: &str = "id, path_prefix, requests_per_window, window_sec, action, \
+     block_duration_sec, exclude_lan, enabled, comment, created_at, updated_at";
+
+fn rule_from_row(row: &MySqlRow) -> RateLimitRule {
+    RateLimitRule {
+        id: row.get("id"),
+        path_prefix: row.get("path_prefix"),
+        requests_per_window: row.get("requests_per_window"),
+        window_sec: row.get("window_sec"),
+        action: RateLimitAction::from_column(row.get("action")),
+        block_duration_sec: row.get("block_duration_sec"),
+        exclude_lan: row.get("exclude_lan"),
+        enabled: row.get("enabled"),
+        comment: row.get("comment"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+impl MySqlDb {
+    /// Table for the rules (run by startup migration 031_rate_limit_rules)
+    pub async fn ensure_rate_limit_rules_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rate_limit_rules (
+                id INT AUTO_INCREMENT PRIMARY KEY,
+                path_prefix VARCHAR(255) NOT NULL,
+                requests_per_window INT NOT NULL,
+                window_sec INT NOT NULL,
+                action VARCHAR(16) NOT NULL DEFAULT 'log' COMMENT 'log | block',
+                block_duration_sec INT NULL COMMENT 'block: seconds on the block list, NULL = 429 only',
+                exclude_lan BOOLEAN NOT NULL DEFAULT TRUE,
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                comment VARCHAR(500) NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// All rules, by path prefix
+    pub async fn list_rate_limit_rules(&self) -> Result<Vec<RateLimitRule>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM rate_limit_rules ORDER BY path_prefix, id",
+            RULE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(rule_from_row).collect())
+    }
+
+    pub async fn get_rate_limit_rule(&self, id: i32) -> Result<Option<RateLimitRule>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM rate_limit_rules WHERE id = ?",
+            RULE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(rule_from_row))
+    }
+
+    /// Insert a validated rule (id and timestamps of `rule` are ignored);
+    /// returns the new id
+    pub async fn create_rate_limit_rule(&self, rule: &RateLimitRule) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO rate_limit_rules
+                (path_prefix, requests_per_window, window_sec, action,
+                 block_duration_sec, exclude_lan, enabled, comment)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&rule.path_prefix)
+        .bind(rule.requests_per_window)
+        .bind(rule.window_sec)
+        .bind(rule.action.as_str())
+        .bind(rule.block_duration_sec)
+        .bind(rule.exclude_lan)
+        .bind(rule.enabled)
+        .bind(&rule.comment)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Overwrite a rule's editable fields with `rule`
+    pub async fn update_rate_limit_rule(&self, rule: &RateLimitRule) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE rate_limit_rules
+            SET path_prefix = ?, requests_per_window = ?, window_sec = ?, action = ?,
+                block_duration_sec = ?, exclude_lan = ?, enabled = ?, comment = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&rule.path_prefix)
+        .bind(rule.requests_per_window)
+        .bind(rule.window_sec)
+        .bind(rule.action.as_str())
+        .bind(rule.block_duration_sec)
+        .bind(rule.exclude_lan)
+        .bind(rule.enabled)
+        .bind(&rule.comment)
+        .bind(rule.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_rate_limit_rule(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM rate_limit_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
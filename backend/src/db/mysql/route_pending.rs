@@ -0,0 +1,159 @@
+//! Pending route changes (approval workflow)
+
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::RoutePendingChange;
+
+use super::MySqlDb;
+
+const PENDING_COLUMNS: &str = "id, route_id, action, payload, status, proposed_by, \
+     proposed_by_permission, reviewed_by, review_comment, created_at, reviewed_at";
+
+fn pending_from_row(row: &MySqlRow) -> RoutePendingChange {
+    let payload: String = row.get("payload");
+    RoutePendingChange {
+        id: row.get("id"),
+        route_id: row.get("route_id"),
+        action: row.get("action"),
+        payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::Null),
+        status: row.get("status"),
+        proposed_by: row.get("proposed_by"),
+        proposed_by_permission: row.get("proposed_by_permission"),
+        reviewed_by: row.get("reviewed_by"),
+        review_comment: row.get("review_comment"),
+        created_at: row.get("created_at"),
+        reviewed_at: row.get("reviewed_at"),
+    }
+}
+
+impl MySqlDb {
+    /// Queue a proposed route change
+    pub async fn create_route_pending_change(
+        &self,
+        route_id: Option<i32>,
+        action: &str,
+        payload: &serde_json::Value,
+        proposed_by: &str,
+        proposed_by_permission: i32,
+    ) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO route_pending_changes
+            (route_id, action, payload, status, proposed_by, proposed_by_permission)
+            VALUES (?, ?, ?, 'pending', ?, ?)
+            "#,
+        )
+        .bind(route_id)
+        .bind(action)
+        .bind(payload.to_string())
+        .bind(proposed_by)
+        .bind(proposed_by_permission)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// List pending changes, newest first (`status` None = all)
+    pub async fn list_route_pending_changes(
+        &self,
+        status: Option<&str>,
+    ) -> Result<Vec<RoutePendingChange>, AppError> {
+        let rows = match status {
+            Some(status) => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM route_pending_changes WHERE status = ? ORDER BY id DESC",
+                    PENDING_COLUMNS
+                ))
+                .bind(status)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query(&format!(
+                    "SELECT {} FROM route_pending_changes ORDER BY id DESC LIMIT 500",
+                    PENDING_COLUMNS
+                ))
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+
+        Ok(rows.iter().map(pending_from_row).collect())
+    }
+
+    /// Get a single pending change
+    pub async fn get_route_pending_change(
+        &self,
+        id: i32,
+    ) -> Result<Option<RoutePendingChange>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM route_pending_changes WHERE id = ?",
+            PENDING_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(pending_from_row))
+    }
+
+    /// Claim a pending change as approved/rejected.
+    /// Returns false if it was already reviewed (concurrent reviewer).
+    pub async fn review_route_pending_change(
+        &self,
+        id: i32,
+        status: &str,
+        reviewed_by: &str,
+        comment: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE route_pending_changes
+            SET status = ?, reviewed_by = ?, review_comment = ?, reviewed_at = NOW()
+            WHERE id = ? AND status = 'pending'
+            "#,
+        )
+        .bind(status)
+        .bind(reviewed_by)
+        .bind(comment)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Put a claimed change back in the queue (approval could not be applied)
+    pub async fn reopen_route_pending_change(&self, id: i32) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE route_pending_changes
+            SET status = 'pending', reviewed_by = NULL, review_comment = NULL, reviewed_at = NULL
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record the route created by an approved "create" change
+    pub async fn set_route_pending_change_route(
+        &self,
+        id: i32,
+        route_id: i32,
+    ) -> Result<(), AppError> {
+        sqlx::query("UPDATE route_pending_changes SET route_id = ? WHERE id = ?")
+            .bind(route_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! Route configuration history (route_versions)
+
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::RouteVersion;
+
+use super::MySqlDb;
+
+const VERSION_COLUMNS: &str =
+    "id, route_id, version, config, actor, reason, restored_version, created_at";
+/// Inserts retried when another writer took the same version number
+const INSERT_ATTEMPTS: usize = 3;
+
+fn version_from_row(row: &MySqlRow) -> RouteVersion {
+    let config: String = row.get("config");
+    RouteVersion {
+        id: row.get("id"),
+        route_id: row.get("route_id"),
+        version: row.get("version"),
+        config: serde_json::from_str(&config).unwrap_or(serde_json::Value::Null),
+        actor: row.get("actor"),
+        reason: row.get("reason"),
+        restored_version: row.get("restored_version"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn is_duplicate_key(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(db) if db.code().as_deref() == Some("23000"))
+}
+
+impl MySqlDb {
+    /// Table for route history (run by startup migration 022_route_versions)
+    pub async fn ensure_route_versions_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS route_versions (
+                id BIGINT AUTO_INCREMENT PRIMARY KEY,
+                route_id INT NOT NULL,
+                version INT NOT NULL COMMENT '1-based, per route',
+                config MEDIUMTEXT NOT NULL COMMENT 'Whole ProxyRoute JSON as written',
+                actor VARCHAR(255) NOT NULL COMMENT 'User, or system',
+                reason VARCHAR(32) NOT NULL COMMENT 'create, update, rollback, ...',
+                restored_version INT NULL COMMENT 'Version a rollback restored',
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE KEY uniq_route_version (route_id, version)
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store `config` as the route's next version and drop versions beyond
+    /// the newest `keep`; returns the new version number
+    pub async fn record_route_version(
+        &self,
+        route_id: i32,
+        config: &serde_json::Value,
+        actor: &str,
+        reason: &str,
+        restored_version: Option<i32>,
+        keep: i32,
+    ) -> Result<i32, AppError> {
+        let config = config.to_string();
+        let mut attempt = 0;
+        let id = loop {
+            attempt += 1;
+            let result = sqlx::query(
+                r#"
+                INSERT INTO route_versions
+                    (route_id, version, config, actor, reason, restored_version)
+                SELECT ?, COALESCE(MAX(version), 0) + 1, ?, ?, ?, ?
+                FROM route_versions WHERE route_id = ?
+                "#,
+            )
+            .bind(route_id)
+            .bind(&config)
+            .bind(actor)
+            .bind(reason)
+            .bind(restored_version)
+            .bind(route_id)
+            .execute(&self.pool)
+            .await;
+            match result {
+                Ok(result) => break result.last_insert_id(),
+                Err(e) if is_duplicate_key(&e) && attempt < INSERT_ATTEMPTS => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let row = sqlx::query("SELECT version FROM route_versions WHERE id = ?")
+            .bind(id as i64)
+            .fetch_one(&self.pool)
+            .await?;
+        let version: i32 = row.get("version");
+
+        sqlx::query("DELETE FROM route_versions WHERE route_id = ? AND version <= ?")
+            .bind(route_id)
+            .bind(version - keep.max(1))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(version)
+    }
+
+    /// Store `config` as version 1 unless the route already has history;
+    /// true when stored
+    pub async fn record_route_baseline(
+        &self,
+        route_id: i32,
+        config: &serde_json::Value,
+        actor: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO route_versions (route_id, version, config, actor, reason)
+            SELECT ?, 1, ?, ?, 'baseline' FROM DUAL
+            WHERE NOT EXISTS (SELECT 1 FROM route_versions WHERE route_id = ?)
+            "#,
+        )
+        .bind(route_id)
+        .bind(config.to_string())
+        .bind(actor)
+        .bind(route_id)
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Versions of a route, newest first
+    pub async fn list_route_versions(&self, route_id: i32) -> Result<Vec<RouteVersion>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM route_versions WHERE route_id = ? ORDER BY version DESC",
+            VERSION_COLUMNS
+        ))
+        .bind(route_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(version_from_row).collect())
+    }
+
+    pub async fn get_route_version(
+        &self,
+        route_id: i32,
+        version: i32,
+    ) -> Result<Option<RouteVersion>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM route_versions WHERE route_id = ? AND version = ?",
+            VERSION_COLUMNS
+        ))
+        .bind(route_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(version_from_row))
+    }
+}
@@ -2,22 +2,88 @@
 
 use sqlx::Row;
 
-use crate::error::AppError;
+use crate::error::{AppError, ErrorCode};
 use crate::models::{CreateRouteRequest, ProxyRoute, ProxyRouteWithDdns, UpdateRouteRequest};
+use crate::proxy::expect::ExpectContinue;
+use crate::proxy::failover::{fallback_targets_column, MAX_RETRY_COUNT};
+use crate::proxy::methods::allowed_methods_column;
 
 use super::MySqlDb;
 
+/// Column list shared by proxy_routes SELECTs (must match `ProxyRoute`)
+const ROUTE_COLUMNS: &str = "id, path, target, ddns_config_id, priority, active, strip_prefix, \
+     preserve_host, timeout_ms, websocket_support, admin_network_only, security_headers, \
+     allowed_methods, store_forward, expect_continue, transform, log_fields, owner_name, \
+     owner_contact, team, show_on_status_page, status_page_name, canary_target, canary_percent, \
+     canary_sticky, grpc, grpc_health_service, health_check_path, health_check_interval_sec, \
+     upstream_auth_user, upstream_auth_password, cache_ttl_sec, header_rewrite, \
+     connect_timeout_ms, max_idle_per_host, fallback_targets, retry_count, retry_on, \
+     maintenance_mode, maintenance_message, max_body_bytes, deleted_at, created_at, updated_at";
+
+/// Canary percentage column; 0 is stored as NULL (canary off)
+fn canary_percent_column(value: Option<i32>) -> Option<i32> {
+    value.filter(|p| *p > 0)
+}
+
+/// grpc.health.v1 service column; trimmed, "" (whole server) is kept
+fn grpc_health_column(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim)
+}
+
+/// Health check interval column; 0 is stored as NULL (global interval)
+fn health_check_interval_column(value: Option<i32>) -> Option<i32> {
+    value.filter(|s| *s > 0)
+}
+
+/// Connect timeout column; 0 is stored as NULL (shared client)
+fn connect_timeout_column(value: Option<i32>) -> Option<i32> {
+    value.filter(|ms| *ms > 0)
+}
+
+/// Maintenance page text column; trimmed, empty is stored as NULL
+fn maintenance_message_column(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|m| !m.is_empty())
+}
+
+/// Request body limit column; 0 and below are stored as NULL (global limit)
+fn max_body_bytes_column(value: Option<i64>) -> Option<i64> {
+    value.filter(|bytes| *bytes > 0)
+}
+
+/// Retry count column, clamped to 0..=MAX_RETRY_COUNT
+fn retry_count_column(value: i32) -> i32 {
+    value.clamp(0, MAX_RETRY_COUNT)
+}
+
+/// Upstream auth password column; empty is stored as NULL
+fn upstream_password_column(value: Option<&str>) -> Option<&str> {
+    value.filter(|p| !p.is_empty())
+}
+
+/// Trimmed owner field; empty means "not set"
+fn owner_field(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
 impl MySqlDb {
-    /// Get all proxy routes ordered by priority
+    /// Get all live (not soft-deleted) proxy routes ordered by priority
     pub async fn list_routes(&self) -> Result<Vec<ProxyRoute>, AppError> {
-        let routes = sqlx::query_as::<_, ProxyRoute>(
-            r#"
-            SELECT id, path, target, ddns_config_id, priority, active, strip_prefix, preserve_host,
-                   timeout_ms, websocket_support, created_at, updated_at
-            FROM proxy_routes
-            ORDER BY priority ASC, id ASC
-            "#,
-        )
+        let routes = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes WHERE deleted_at IS NULL ORDER BY priority ASC, id ASC",
+            ROUTE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(routes)
+    }
+
+    /// Get all proxy routes including soft-deleted ones
+    pub async fn list_routes_including_deleted(&self) -> Result<Vec<ProxyRoute>, AppError> {
+        let routes = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes ORDER BY priority ASC, id ASC",
+            ROUTE_COLUMNS
+        ))
         .fetch_all(&self.pool)
         .await?;
 
@@ -26,15 +92,11 @@ impl MySqlDb {
 
     /// Get active proxy routes ordered by priority
     pub async fn list_active_routes(&self) -> Result<Vec<ProxyRoute>, AppError> {
-        let routes = sqlx::query_as::<_, ProxyRoute>(
-            r#"
-            SELECT id, path, target, ddns_config_id, priority, active, strip_prefix, preserve_host,
-                   timeout_ms, websocket_support, created_at, updated_at
-            FROM proxy_routes
-            WHERE active = TRUE
-            ORDER BY priority ASC, id ASC
-            "#,
-        )
+        let routes = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes WHERE active = TRUE AND deleted_at IS NULL \
+             ORDER BY priority ASC, id ASC",
+            ROUTE_COLUMNS
+        ))
         .fetch_all(&self.pool)
         .await?;
 
@@ -43,57 +105,43 @@ impl MySqlDb {
 
     /// Get active proxy routes with DDNS hostname for routing decisions
     pub async fn list_active_routes_with_ddns(&self) -> Result<Vec<ProxyRouteWithDdns>, AppError> {
-        let rows = sqlx::query(
+        let routes = sqlx::query_as::<_, ProxyRouteWithDdns>(
             r#"
-            SELECT r.id, r.path, r.target, r.ddns_config_id, r.priority, r.active,
-                   r.strip_prefix, r.preserve_host, r.timeout_ms, r.websocket_support,
-                   r.created_at, r.updated_at, d.hostname as ddns_hostname
+            SELECT r.*, d.hostname as ddns_hostname
             FROM proxy_routes r
             LEFT JOIN ddns_configs d ON r.ddns_config_id = d.id
-            WHERE r.active = TRUE
+            WHERE r.active = TRUE AND r.deleted_at IS NULL
             ORDER BY r.priority ASC, r.id ASC
             "#,
         )
         .fetch_all(&self.pool)
         .await?;
 
-        let routes = rows
-            .into_iter()
-            .map(|row| {
-                let route = ProxyRoute {
-                    id: row.get("id"),
-                    path: row.get("path"),
-                    target: row.get("target"),
-                    ddns_config_id: row.get("ddns_config_id"),
-                    priority: row.get("priority"),
-                    active: row.get("active"),
-                    strip_prefix: row.get("strip_prefix"),
-                    preserve_host: row.get("preserve_host"),
-                    timeout_ms: row.get("timeout_ms"),
-                    websocket_support: row.get("websocket_support"),
-                    created_at: row.get("created_at"),
-                    updated_at: row.get("updated_at"),
-                };
-                ProxyRouteWithDdns {
-                    route,
-                    ddns_hostname: row.get("ddns_hostname"),
-                }
-            })
-            .collect();
-
         Ok(routes)
     }
 
-    /// Get a single route by ID
-    pub async fn get_route(&self, id: i32) -> Result<Option<ProxyRoute>, AppError> {
-        let route = sqlx::query_as::<_, ProxyRoute>(
+    /// DDNS hostname of every route, soft-deleted ones included (None = no
+    /// DDNS config)
+    pub async fn list_route_hostnames(&self) -> Result<Vec<(i32, Option<String>)>, AppError> {
+        let rows = sqlx::query_as::<_, (i32, Option<String>)>(
             r#"
-            SELECT id, path, target, ddns_config_id, priority, active, strip_prefix, preserve_host,
-                   timeout_ms, websocket_support, created_at, updated_at
-            FROM proxy_routes
-            WHERE id = ?
+            SELECT r.id, d.hostname
+            FROM proxy_routes r
+            LEFT JOIN ddns_configs d ON r.ddns_config_id = d.id
             "#,
         )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Get a single route by ID (soft-deleted routes included; check `deleted_at`)
+    pub async fn get_route(&self, id: i32) -> Result<Option<ProxyRoute>, AppError> {
+        let route = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes WHERE id = ?",
+            ROUTE_COLUMNS
+        ))
         .bind(id)
         .fetch_optional(&self.pool)
         .await?;
@@ -101,12 +149,54 @@ impl MySqlDb {
         Ok(route)
     }
 
+    /// Find a live route occupying the same path / DDNS hostname slot
+    pub async fn find_live_route_conflict(
+        &self,
+        path: &str,
+        ddns_config_id: Option<i32>,
+        exclude_id: i32,
+    ) -> Result<Option<ProxyRoute>, AppError> {
+        let route = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes \
+             WHERE path = ? AND ddns_config_id <=> ? AND id <> ? AND deleted_at IS NULL \
+             LIMIT 1",
+            ROUTE_COLUMNS
+        ))
+        .bind(path)
+        .bind(ddns_config_id)
+        .bind(exclude_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(route)
+    }
+
+    /// Find a soft-deleted route occupying the same path / DDNS hostname slot
+    pub async fn find_deleted_route_conflict(
+        &self,
+        path: &str,
+        ddns_config_id: Option<i32>,
+    ) -> Result<Option<ProxyRoute>, AppError> {
+        let route = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes \
+             WHERE path = ? AND ddns_config_id <=> ? AND deleted_at IS NOT NULL \
+             LIMIT 1",
+            ROUTE_COLUMNS
+        ))
+        .bind(path)
+        .bind(ddns_config_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(route)
+    }
+
     /// Create a new proxy route
     pub async fn create_route(&self, req: &CreateRouteRequest) -> Result<i32, AppError> {
         let result = sqlx::query(
             r#"
-            INSERT INTO proxy_routes (path, target, ddns_config_id, priority, active, strip_prefix, preserve_host, timeout_ms, websocket_support)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO proxy_routes (path, target, ddns_config_id, priority, active, strip_prefix, preserve_host, timeout_ms, websocket_support, admin_network_only, security_headers, allowed_methods, expect_continue, owner_name, owner_contact, team, canary_target, canary_percent, canary_sticky, grpc, grpc_health_service, health_check_path, health_check_interval_sec, upstream_auth_user, upstream_auth_password, cache_ttl_sec, header_rewrite, connect_timeout_ms, max_idle_per_host, fallback_targets, retry_count, retry_on, maintenance_mode, maintenance_message, max_body_bytes)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&req.path)
@@ -118,6 +208,35 @@ impl MySqlDb {
         .bind(req.preserve_host)
         .bind(req.timeout_ms)
         .bind(req.websocket_support)
+        .bind(req.admin_network_only)
+        .bind(req.security_headers.as_ref().and_then(|v| v.to_column()))
+        .bind(allowed_methods_column(req.allowed_methods.as_deref()))
+        .bind(req.expect_continue.and_then(ExpectContinue::to_column))
+        .bind(owner_field(req.owner_name.as_deref()))
+        .bind(owner_field(req.owner_contact.as_deref()))
+        .bind(owner_field(req.team.as_deref()))
+        .bind(owner_field(req.canary_target.as_deref()))
+        .bind(canary_percent_column(req.canary_percent))
+        .bind(req.canary_sticky)
+        .bind(req.grpc)
+        .bind(grpc_health_column(req.grpc_health_service.as_deref()))
+        .bind(owner_field(req.health_check_path.as_deref()))
+        .bind(health_check_interval_column(req.health_check_interval_sec))
+        .bind(owner_field(req.upstream_auth_user.as_deref()))
+        .bind(
+            upstream_password_column(req.upstream_auth_password.as_deref())
+                .filter(|_| owner_field(req.upstream_auth_user.as_deref()).is_some()),
+        )
+        .bind(req.cache_ttl_sec.max(0))
+        .bind(req.header_rewrite.as_ref().and_then(|v| v.to_column()))
+        .bind(connect_timeout_column(req.connect_timeout_ms))
+        .bind(req.max_idle_per_host)
+        .bind(fallback_targets_column(req.fallback_targets.as_deref()))
+        .bind(retry_count_column(req.retry_count))
+        .bind(req.retry_on.and_then(|r| r.to_column()))
+        .bind(req.maintenance_mode)
+        .bind(maintenance_message_column(req.maintenance_message.as_deref()))
+        .bind(max_body_bytes_column(req.max_body_bytes))
         .execute(&self.pool)
         .await?;
 
@@ -126,10 +245,9 @@ impl MySqlDb {
 
     /// Update an existing route
     pub async fn update_route(&self, id: i32, req: &UpdateRouteRequest) -> Result<bool, AppError> {
-        let existing = self
-            .get_route(id)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("Route {} not found", id)))?;
+        let existing = self.get_route(id).await?.ok_or_else(|| {
+            AppError::coded(ErrorCode::RouteNotFound, format!("Route {} not found", id))
+        })?;
 
         let path = req.path.as_ref().unwrap_or(&existing.path);
         let target = req.target.as_ref().unwrap_or(&existing.target);
@@ -143,12 +261,110 @@ impl MySqlDb {
         let preserve_host = req.preserve_host.unwrap_or(existing.preserve_host);
         let timeout_ms = req.timeout_ms.unwrap_or(existing.timeout_ms);
         let websocket_support = req.websocket_support.unwrap_or(existing.websocket_support);
+        let admin_network_only = req
+            .admin_network_only
+            .unwrap_or(existing.admin_network_only);
+        let security_headers = match &req.security_headers {
+            Some(v) => v.to_column(),
+            None => existing.security_headers.clone(),
+        };
+        let allowed_methods = match &req.allowed_methods {
+            Some(v) => allowed_methods_column(v.as_deref()),
+            None => existing.allowed_methods.clone(),
+        };
+        let expect_continue = match req.expect_continue {
+            Some(mode) => mode.to_column(),
+            None => existing.expect_continue.clone(),
+        };
+        let owner_name = match &req.owner_name {
+            Some(v) => owner_field(Some(v)),
+            None => existing.owner_name.as_deref(),
+        };
+        let owner_contact = match &req.owner_contact {
+            Some(v) => owner_field(Some(v)),
+            None => existing.owner_contact.as_deref(),
+        };
+        let team = match &req.team {
+            Some(v) => owner_field(Some(v)),
+            None => existing.team.as_deref(),
+        };
+        let canary_target = match &req.canary_target {
+            Some(v) => owner_field(v.as_deref()),
+            None => existing.canary_target.as_deref(),
+        };
+        let canary_percent = match req.canary_percent {
+            Some(v) => canary_percent_column(v),
+            None => existing.canary_percent,
+        };
+        let canary_sticky = req.canary_sticky.unwrap_or(existing.canary_sticky);
+        let grpc = req.grpc.unwrap_or(existing.grpc);
+        let grpc_health_service = match &req.grpc_health_service {
+            Some(v) => grpc_health_column(v.as_deref()),
+            None => existing.grpc_health_service.as_deref(),
+        };
+        let health_check_path = match &req.health_check_path {
+            Some(v) => owner_field(v.as_deref()),
+            None => existing.health_check_path.as_deref(),
+        };
+        let health_check_interval_sec = match req.health_check_interval_sec {
+            Some(v) => health_check_interval_column(v),
+            None => existing.health_check_interval_sec,
+        };
+        // Removing the user removes the password with it
+        let upstream_auth_user = match &req.upstream_auth_user {
+            Some(v) => owner_field(v.as_deref()),
+            None => existing.upstream_auth_user.as_deref(),
+        };
+        let upstream_auth_password = match &req.upstream_auth_password {
+            Some(v) => upstream_password_column(Some(v)),
+            None => existing.upstream_auth_password.as_deref(),
+        }
+        .filter(|_| upstream_auth_user.is_some());
+        let cache_ttl_sec = req.cache_ttl_sec.unwrap_or(existing.cache_ttl_sec).max(0);
+        let header_rewrite = match &req.header_rewrite {
+            Some(v) => v.to_column(),
+            None => existing.header_rewrite.clone(),
+        };
+        let connect_timeout_ms = match req.connect_timeout_ms {
+            Some(v) => connect_timeout_column(v),
+            None => existing.connect_timeout_ms,
+        };
+        let max_idle_per_host = match req.max_idle_per_host {
+            Some(v) => v,
+            None => existing.max_idle_per_host,
+        };
+        let fallback_targets = match &req.fallback_targets {
+            Some(v) => fallback_targets_column(v.as_deref()),
+            None => existing.fallback_targets.clone(),
+        };
+        let retry_count = retry_count_column(req.retry_count.unwrap_or(existing.retry_count));
+        let retry_on = match req.retry_on {
+            Some(v) => v.to_column(),
+            None => existing.retry_on.clone(),
+        };
+        let maintenance_mode = req.maintenance_mode.unwrap_or(existing.maintenance_mode);
+        let maintenance_message = match &req.maintenance_message {
+            Some(v) => maintenance_message_column(v.as_deref()),
+            None => existing.maintenance_message.as_deref(),
+        };
+        let max_body_bytes = match req.max_body_bytes {
+            Some(v) => max_body_bytes_column(v),
+            None => existing.max_body_bytes,
+        };
 
         let result = sqlx::query(
             r#"
             UPDATE proxy_routes
             SET path = ?, target = ?, ddns_config_id = ?, priority = ?, active = ?,
-                strip_prefix = ?, preserve_host = ?, timeout_ms = ?, websocket_support = ?
+                strip_prefix = ?, preserve_host = ?, timeout_ms = ?, websocket_support = ?,
+                admin_network_only = ?, security_headers = ?, allowed_methods = ?,
+                expect_continue = ?, owner_name = ?, owner_contact = ?, team = ?,
+                canary_target = ?, canary_percent = ?, canary_sticky = ?, grpc = ?,
+                grpc_health_service = ?, health_check_path = ?, health_check_interval_sec = ?,
+                upstream_auth_user = ?, upstream_auth_password = ?, cache_ttl_sec = ?,
+                header_rewrite = ?, connect_timeout_ms = ?, max_idle_per_host = ?,
+                fallback_targets = ?, retry_count = ?, retry_on = ?, maintenance_mode = ?,
+                maintenance_message = ?, max_body_bytes = ?
             WHERE id = ?
             "#,
         )
@@ -161,6 +377,32 @@ impl MySqlDb {
         .bind(preserve_host)
         .bind(timeout_ms)
         .bind(websocket_support)
+        .bind(admin_network_only)
+        .bind(security_headers)
+        .bind(allowed_methods)
+        .bind(expect_continue)
+        .bind(owner_name)
+        .bind(owner_contact)
+        .bind(team)
+        .bind(canary_target)
+        .bind(canary_percent)
+        .bind(canary_sticky)
+        .bind(grpc)
+        .bind(grpc_health_service)
+        .bind(health_check_path)
+        .bind(health_check_interval_sec)
+        .bind(upstream_auth_user)
+        .bind(upstream_auth_password)
+        .bind(cache_ttl_sec)
+        .bind(header_rewrite)
+        .bind(connect_timeout_ms)
+        .bind(max_idle_per_host)
+        .bind(fallback_targets)
+        .bind(retry_count)
+        .bind(retry_on)
+        .bind(maintenance_mode)
+        .bind(maintenance_message)
+        .bind(max_body_bytes)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -168,7 +410,124 @@ impl MySqlDb {
         Ok(result.rows_affected() > 0)
     }
 
-    /// Delete a route
+    /// Write every configuration column of `route` back to its row (rollback);
+    /// the id, timestamps and upstream auth credentials (not versioned) are
+    /// not touched
+    pub async fn restore_route_config(&self, route: &ProxyRoute) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE proxy_routes
+            SET path = ?, target = ?, ddns_config_id = ?, priority = ?, active = ?,
+                strip_prefix = ?, preserve_host = ?, timeout_ms = ?, websocket_support = ?,
+                admin_network_only = ?, security_headers = ?, allowed_methods = ?,
+                store_forward = ?, expect_continue = ?, transform = ?, log_fields = ?,
+                owner_name = ?, owner_contact = ?, team = ?, show_on_status_page = ?,
+                status_page_name = ?, canary_target = ?, canary_percent = ?, canary_sticky = ?,
+                grpc = ?, grpc_health_service = ?, health_check_path = ?,
+                health_check_interval_sec = ?, cache_ttl_sec = ?, header_rewrite = ?,
+                connect_timeout_ms = ?, max_idle_per_host = ?, fallback_targets = ?,
+                retry_count = ?, retry_on = ?, maintenance_mode = ?, maintenance_message = ?,
+                max_body_bytes = ?
+            WHERE id = ? AND deleted_at IS NULL
+            "#,
+        )
+        .bind(&route.path)
+        .bind(&route.target)
+        .bind(route.ddns_config_id)
+        .bind(route.priority)
+        .bind(route.active)
+        .bind(route.strip_prefix)
+        .bind(route.preserve_host)
+        .bind(route.timeout_ms)
+        .bind(route.websocket_support)
+        .bind(route.admin_network_only)
+        .bind(&route.security_headers)
+        .bind(&route.allowed_methods)
+        .bind(&route.store_forward)
+        .bind(&route.expect_continue)
+        .bind(&route.transform)
+        .bind(&route.log_fields)
+        .bind(&route.owner_name)
+        .bind(&route.owner_contact)
+        .bind(&route.team)
+        .bind(route.show_on_status_page)
+        .bind(&route.status_page_name)
+        .bind(&route.canary_target)
+        .bind(canary_percent_column(route.canary_percent))
+        .bind(route.canary_sticky)
+        .bind(route.grpc)
+        .bind(&route.grpc_health_service)
+        .bind(&route.health_check_path)
+        .bind(health_check_interval_column(
+            route.health_check_interval_sec,
+        ))
+        .bind(route.cache_ttl_sec.max(0))
+        .bind(&route.header_rewrite)
+        .bind(connect_timeout_column(route.connect_timeout_ms))
+        .bind(route.max_idle_per_host)
+        .bind(&route.fallback_targets)
+        .bind(retry_count_column(route.retry_count))
+        .bind(&route.retry_on)
+        .bind(route.maintenance_mode)
+        .bind(&route.maintenance_message)
+        .bind(max_body_bytes_column(route.max_body_bytes))
+        .bind(route.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Soft-delete a route (hidden from listing, matching, and reload)
+    pub async fn soft_delete_route(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET deleted_at = NOW() WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Restore a soft-deleted route
+    pub async fn restore_route(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Hard-delete routes soft-deleted more than `older_than_days` ago.
+    /// Returns the purged routes so callers can audit them.
+    pub async fn purge_deleted_routes(
+        &self,
+        older_than_days: i32,
+    ) -> Result<Vec<ProxyRoute>, AppError> {
+        let routes = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes \
+             WHERE deleted_at IS NOT NULL AND deleted_at <= NOW() - INTERVAL ? DAY",
+            ROUTE_COLUMNS
+        ))
+        .bind(older_than_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for route in &routes {
+            sqlx::query("DELETE FROM proxy_routes WHERE id = ? AND deleted_at IS NOT NULL")
+                .bind(route.id)
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(routes)
+    }
+
+    /// Permanently delete a route
     pub async fn delete_route(&self, id: i32) -> Result<bool, AppError> {
         let result = sqlx::query("DELETE FROM proxy_routes WHERE id = ?")
             .bind(id)
@@ -178,11 +537,409 @@ impl MySqlDb {
         Ok(result.rows_affected() > 0)
     }
 
+    /// proxy_routes.store_forward (run by startup migration 009_route_store_forward)
+    pub async fn ensure_route_store_forward_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS store_forward TEXT NULL
+                    COMMENT 'Store-and-forward queue policy JSON (NULL = off)'
+                    AFTER allowed_methods
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.expect_continue (run by startup migration 011_route_expect_continue)
+    pub async fn ensure_route_expect_continue_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS expect_continue VARCHAR(16) NULL
+                    COMMENT 'Expect: 100-continue handling: immediate|passthrough|strip (NULL = immediate)'
+                    AFTER store_forward
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.transform (run by startup migration 016_route_transform)
+    pub async fn ensure_route_transform_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS transform MEDIUMTEXT NULL
+                    COMMENT 'Transformation script policy JSON (NULL = none)'
+                    AFTER expect_continue
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.canary_* (run by startup migration 020_route_canary)
+    pub async fn ensure_route_canary_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS canary_target VARCHAR(500) NULL
+                    COMMENT 'Canary target URL'
+                    AFTER status_page_name,
+                ADD COLUMN IF NOT EXISTS canary_percent INT NULL
+                    COMMENT 'Share of requests sent to canary_target, 0-100 (NULL/0 = off)'
+                    AFTER canary_target,
+                ADD COLUMN IF NOT EXISTS canary_sticky BOOLEAN NOT NULL DEFAULT FALSE
+                    COMMENT 'Keep a client IP on the same side'
+                    AFTER canary_percent
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Kill switch: send every request of a route back to its primary target
+    pub async fn disable_route_canary(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET canary_percent = NULL WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Turn a route's maintenance mode on or off and set its page text
+    /// (None = the default text)
+    pub async fn set_route_maintenance(
+        &self,
+        id: i32,
+        enabled: bool,
+        message: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET maintenance_mode = ?, maintenance_message = ? \
+             WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(enabled)
+        .bind(maintenance_message_column(message))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (None) a route's transformation script policy column
+    pub async fn set_route_transform(
+        &self,
+        id: i32,
+        policy: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET transform = ? WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(policy)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// proxy_routes.log_fields (run by startup migration 023_route_log_fields)
+    pub async fn ensure_route_log_fields_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS log_fields TEXT NULL
+                    COMMENT 'Custom access log field rules JSON (NULL = none)'
+                    AFTER transform
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.grpc* (run by startup migration 025_route_grpc)
+    pub async fn ensure_route_grpc_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS grpc BOOLEAN NOT NULL DEFAULT FALSE
+                    COMMENT 'gRPC passthrough (HTTP/2 upstream, trailers, streaming)'
+                    AFTER canary_sticky,
+                ADD COLUMN IF NOT EXISTS grpc_health_service VARCHAR(255) NULL
+                    COMMENT 'grpc.health.v1 service checked (empty = whole server, NULL = HTTP probe)'
+                    AFTER grpc
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.health_check_* (run by startup migration
+    /// 028_route_health_check)
+    pub async fn ensure_route_health_check_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS health_check_path VARCHAR(255) NULL
+                    COMMENT 'Path probed by HTTP health checks (NULL = /)'
+                    AFTER grpc_health_service,
+                ADD COLUMN IF NOT EXISTS health_check_interval_sec INT NULL
+                    COMMENT 'Seconds between health checks (NULL = global interval)'
+                    AFTER health_check_path
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.upstream_auth_* (run by startup migration
+    /// 029_route_upstream_auth)
+    pub async fn ensure_route_upstream_auth_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS upstream_auth_user VARCHAR(255) NULL
+                    COMMENT 'Basic auth user sent to the target (NULL = pass the client header)'
+                    AFTER health_check_interval_sec,
+                ADD COLUMN IF NOT EXISTS upstream_auth_password VARCHAR(255) NULL
+                    COMMENT 'Basic auth password sent to the target'
+                    AFTER upstream_auth_user
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.cache_ttl_sec (run by startup migration 030_route_cache)
+    pub async fn ensure_route_cache_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS cache_ttl_sec INT NOT NULL DEFAULT 0
+                    COMMENT 'Seconds GET responses stay cached (0 = no caching)'
+                    AFTER upstream_auth_password
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.header_rewrite (run by startup migration
+    /// 035_route_header_rewrite)
+    pub async fn ensure_route_header_rewrite_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS header_rewrite TEXT NULL
+                    COMMENT 'Request/response header rewrite rules JSON (NULL = none)'
+                    AFTER cache_ttl_sec
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.connect_timeout_ms and max_idle_per_host (run by startup
+    /// migration 037_route_upstream_tuning)
+    pub async fn ensure_route_upstream_tuning_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS connect_timeout_ms INT NULL
+                    COMMENT 'Upstream connect timeout (NULL = shared client)'
+                    AFTER header_rewrite,
+                ADD COLUMN IF NOT EXISTS max_idle_per_host INT NULL
+                    COMMENT 'Idle upstream connections per host (NULL = shared client)'
+                    AFTER connect_timeout_ms
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.fallback_targets, retry_count and retry_on (run by startup
+    /// migration 038_route_failover)
+    pub async fn ensure_route_failover_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS fallback_targets TEXT NULL
+                    COMMENT 'Fallback target URLs JSON list, tried in order (NULL = none)'
+                    AFTER max_idle_per_host,
+                ADD COLUMN IF NOT EXISTS retry_count INT NOT NULL DEFAULT 0
+                    COMMENT 'Further attempts per target'
+                    AFTER fallback_targets,
+                ADD COLUMN IF NOT EXISTS retry_on VARCHAR(16) NULL
+                    COMMENT 'connect_error or gateway_error (NULL = connect_error)'
+                    AFTER retry_count
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.maintenance_mode and maintenance_message (run by startup
+    /// migration 039_route_maintenance_mode)
+    pub async fn ensure_route_maintenance_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS maintenance_mode BOOLEAN NOT NULL DEFAULT FALSE
+                    COMMENT 'Answer 503 with the maintenance page instead of proxying'
+                    AFTER retry_on,
+                ADD COLUMN IF NOT EXISTS maintenance_message TEXT NULL
+                    COMMENT 'Maintenance page text (NULL = default text)'
+                    AFTER maintenance_mode
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// proxy_routes.max_body_bytes (run by startup migration
+    /// 040_route_body_limit)
+    pub async fn ensure_route_body_limit_column(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS max_body_bytes BIGINT NULL
+                    COMMENT 'Request body limit in bytes (NULL = global limit)'
+                    AFTER maintenance_message
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set or clear (None) a route's custom log field rules column
+    pub async fn set_route_log_fields(
+        &self,
+        id: i32,
+        rules: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET log_fields = ? WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(rules)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// proxy_routes.show_on_status_page / status_page_name (run by startup
+    /// migration 015_status_page)
+    pub async fn ensure_route_status_page_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE proxy_routes
+                ADD COLUMN IF NOT EXISTS show_on_status_page BOOLEAN NOT NULL DEFAULT FALSE
+                    COMMENT 'Listed on the public status page'
+                    AFTER team,
+                ADD COLUMN IF NOT EXISTS status_page_name VARCHAR(100) NULL
+                    COMMENT 'Public display name on the status page'
+                    AFTER show_on_status_page
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set a route's status page opt-in and public display name
+    pub async fn set_route_status_page(
+        &self,
+        id: i32,
+        show: bool,
+        name: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET show_on_status_page = ?, status_page_name = ? \
+             WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(show)
+        .bind(name)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Live routes listed on the status page, in route priority order
+    pub async fn list_status_page_routes(&self) -> Result<Vec<ProxyRoute>, AppError> {
+        let routes = sqlx::query_as::<_, ProxyRoute>(&format!(
+            "SELECT {} FROM proxy_routes \
+             WHERE show_on_status_page = TRUE AND deleted_at IS NULL \
+             ORDER BY priority ASC, id ASC",
+            ROUTE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(routes)
+    }
+
+    /// Set or clear (None) a route's store-and-forward policy column
+    pub async fn set_route_store_forward(
+        &self,
+        id: i32,
+        policy: Option<&str>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE proxy_routes SET store_forward = ? WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(policy)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Get count of active routes
     pub async fn count_active_routes(&self) -> Result<u32, AppError> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM proxy_routes WHERE active = TRUE")
-            .fetch_one(&self.pool)
-            .await?;
+        let row = sqlx::query(
+            "SELECT COUNT(*) as count FROM proxy_routes WHERE active = TRUE AND deleted_at IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(row.get::<i64, _>("count") as u32)
     }
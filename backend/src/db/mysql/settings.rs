@@ -1,7 +1,7 @@
 //! Settings CRUD operations
 
 use crate::error::AppError;
-use crate::models::Setting;
+use crate::models::{PermissionFloors, Setting};
 
 use super::MySqlDb;
 
@@ -89,6 +89,29 @@ impl MySqlDb {
         Ok(())
     }
 
+    /// Add a setting unless the key exists (keeps a configured value);
+    /// returns whether it was added
+    pub async fn insert_setting_if_missing(
+        &self,
+        key: &str,
+        value: &str,
+        description: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT IGNORE INTO settings (setting_key, setting_value, description)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(key)
+        .bind(value)
+        .bind(description)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Get Discord webhook URL
     pub async fn get_discord_webhook_url(&self) -> Result<Option<String>, AppError> {
         self.get_setting("discord_webhook_url").await
@@ -122,4 +145,48 @@ impl MySqlDb {
             .await?;
         Ok((interval, timeout, threshold))
     }
+
+    /// Load permission floors. Missing keys are seeded from `defaults`
+    /// (the login floor comes from `auth.lacisoath_required_permission`).
+    pub async fn load_permission_floors(
+        &self,
+        defaults: PermissionFloors,
+    ) -> Result<PermissionFloors, AppError> {
+        let mut floors = defaults;
+        for (key, slot) in [
+            (PermissionFloors::SETTING_LOGIN, &mut floors.login),
+            (PermissionFloors::SETTING_READ, &mut floors.read),
+            (PermissionFloors::SETTING_OPERATE, &mut floors.operate),
+            (PermissionFloors::SETTING_ADMIN, &mut floors.admin),
+            (PermissionFloors::SETTING_DANGEROUS, &mut floors.dangerous),
+        ] {
+            match self.get_setting(key).await?.and_then(|v| v.parse().ok()) {
+                Some(value) => *slot = value,
+                None => {
+                    self.upsert_setting(
+                        key,
+                        Some(&slot.to_string()),
+                        Some("Permission floor (runtime editable)"),
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(floors)
+    }
+
+    /// Persist all permission floors
+    pub async fn save_permission_floors(&self, floors: &PermissionFloors) -> Result<(), AppError> {
+        for (key, value) in [
+            (PermissionFloors::SETTING_LOGIN, floors.login),
+            (PermissionFloors::SETTING_READ, floors.read),
+            (PermissionFloors::SETTING_OPERATE, floors.operate),
+            (PermissionFloors::SETTING_ADMIN, floors.admin),
+            (PermissionFloors::SETTING_DANGEROUS, floors.dangerous),
+        ] {
+            self.upsert_setting(key, Some(&value.to_string()), None)
+                .await?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,159 @@
+//! Read-only topology share tokens for GET /api/public/topology
+
+use chrono::{DateTime, Utc};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::TopologyShare;
+use crate::topology_share::{ShareDetail, ShareView};
+
+use super::MySqlDb;
+
+const SHARE_COLUMNS: &str = "id, name, view, fid, detail, token_prefix, expires_at, \
+     request_count, last_used_at, revoked_at, created_by, created_at";
+
+fn share_from_row(row: &MySqlRow) -> TopologyShare {
+    TopologyShare {
+        id: row.get("id"),
+        name: row.get("name"),
+        view: ShareView::from_column(row.get("view")),
+        fid: row.get("fid"),
+        detail: ShareDetail::from_column(row.get("detail")),
+        token_prefix: row.get("token_prefix"),
+        expires_at: row.get("expires_at"),
+        request_count: row.get("request_count"),
+        last_used_at: row.get("last_used_at"),
+        revoked_at: row.get("revoked_at"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+    }
+}
+
+impl MySqlDb {
+    /// Table for the shares (run by startup migration 021_topology_shares)
+    pub async fn ensure_topology_shares_table(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS topology_shares (
+                id INT AUTO_INCREMENT PRIMARY KEY,
+                name VARCHAR(100) NOT NULL,
+                view VARCHAR(16) NOT NULL COMMENT 'full | site',
+                fid VARCHAR(50) NULL COMMENT 'Facility of a site share',
+                detail VARCHAR(16) NOT NULL COMMENT 'labels | labels_ips',
+                token_hash CHAR(64) NOT NULL UNIQUE COMMENT 'SHA-256 hex of the token',
+                token_prefix VARCHAR(16) NOT NULL,
+                expires_at TIMESTAMP NOT NULL,
+                request_count BIGINT NOT NULL DEFAULT 0,
+                last_used_at TIMESTAMP NULL,
+                revoked_at TIMESTAMP NULL,
+                created_by VARCHAR(255) NOT NULL,
+                created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            ) ENGINE=InnoDB
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// All shares, revoked ones included, newest first
+    pub async fn list_topology_shares(&self) -> Result<Vec<TopologyShare>, AppError> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM topology_shares ORDER BY created_at DESC, id DESC",
+            SHARE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(share_from_row).collect())
+    }
+
+    pub async fn get_topology_share(&self, id: i32) -> Result<Option<TopologyShare>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM topology_shares WHERE id = ?",
+            SHARE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(share_from_row))
+    }
+
+    /// Share whose hash is `token_hash`, whatever its state
+    pub async fn find_topology_share(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<TopologyShare>, AppError> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM topology_shares WHERE token_hash = ?",
+            SHARE_COLUMNS
+        ))
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(share_from_row))
+    }
+
+    /// Insert a share; returns the new id
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_topology_share(
+        &self,
+        name: &str,
+        view: ShareView,
+        fid: Option<&str>,
+        detail: ShareDetail,
+        token_hash: &str,
+        token_prefix: &str,
+        expires_at: DateTime<Utc>,
+        created_by: &str,
+    ) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO topology_shares
+                (name, view, fid, detail, token_hash, token_prefix, expires_at, created_by)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(name)
+        .bind(view.as_str())
+        .bind(fid)
+        .bind(detail.as_str())
+        .bind(token_hash)
+        .bind(token_prefix)
+        .bind(expires_at)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Revoke a share; false if missing or already revoked
+    pub async fn revoke_topology_share(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE topology_shares SET revoked_at = NOW() WHERE id = ? AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count one served request
+    pub async fn record_topology_share_use(&self, id: i32) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE topology_shares SET request_count = request_count + 1, last_used_at = NOW() \
+             WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! WireGuard client-config profiles and LPG peer records (peer → profile,
+//! allowed addresses, enabled)
+
+use std::collections::HashMap;
+
+use sqlx::Row;
+
+use crate::error::AppError;
+use crate::models::{CreateWgProfileRequest, WgConfigProfile, WgPeerRecord};
+
+use super::MySqlDb;
+
+const PROFILE_COLUMNS: &str = "id, name, description, dns, allowed_ips, persistent_keepalive, \
+     mtu, scripts_allowed, is_default, created_at, updated_at";
+
+impl MySqlDb {
+    /// List all WireGuard config profiles (default first)
+    pub async fn list_wg_profiles(&self) -> Result<Vec<WgConfigProfile>, AppError> {
+        let rows = sqlx::query_as::<_, WgConfigProfile>(&format!(
+            "SELECT {} FROM wg_config_profiles ORDER BY is_default DESC, name",
+            PROFILE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Get a profile by ID
+    pub async fn get_wg_profile(&self, id: i32) -> Result<Option<WgConfigProfile>, AppError> {
+        let row = sqlx::query_as::<_, WgConfigProfile>(&format!(
+            "SELECT {} FROM wg_config_profiles WHERE id = ?",
+            PROFILE_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Get the profile marked as default, if any
+    pub async fn get_default_wg_profile(&self) -> Result<Option<WgConfigProfile>, AppError> {
+        let row = sqlx::query_as::<_, WgConfigProfile>(&format!(
+            "SELECT {} FROM wg_config_profiles WHERE is_default = TRUE ORDER BY id LIMIT 1",
+            PROFILE_COLUMNS
+        ))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Create a profile (never created as default)
+    pub async fn create_wg_profile(&self, req: &CreateWgProfileRequest) -> Result<i32, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO wg_config_profiles
+            (name, description, dns, allowed_ips, persistent_keepalive, mtu, scripts_allowed, is_default)
+            VALUES (?, ?, ?, ?, ?, ?, ?, FALSE)
+            "#,
+        )
+        .bind(req.name.trim())
+        .bind(&req.description)
+        .bind(req.dns.as_deref().filter(|d| !d.is_empty()))
+        .bind(&req.allowed_ips)
+        .bind(req.persistent_keepalive)
+        .bind(req.mtu.filter(|m| *m > 0))
+        .bind(req.scripts_allowed)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    /// Overwrite a profile's editable fields with `profile`
+    pub async fn update_wg_profile(&self, profile: &WgConfigProfile) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE wg_config_profiles
+            SET name = ?, description = ?, dns = ?, allowed_ips = ?,
+                persistent_keepalive = ?, mtu = ?, scripts_allowed = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&profile.name)
+        .bind(&profile.description)
+        .bind(&profile.dns)
+        .bind(&profile.allowed_ips)
+        .bind(profile.persistent_keepalive)
+        .bind(profile.mtu)
+        .bind(profile.scripts_allowed)
+        .bind(profile.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a non-default profile
+    pub async fn delete_wg_profile(&self, id: i32) -> Result<bool, AppError> {
+        let result =
+            sqlx::query("DELETE FROM wg_config_profiles WHERE id = ? AND is_default = FALSE")
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Number of peers recorded against a profile
+    pub async fn count_wg_profile_peers(&self, profile_id: i32) -> Result<i64, AppError> {
+        let row = sqlx::query("SELECT COUNT(*) AS cnt FROM wg_peer_profiles WHERE profile_id = ?")
+            .bind(profile_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.get("cnt"))
+    }
+
+    /// Columns added for the unified peer view (run by startup migration
+    /// 008_wg_peer_records): nullable profile, allowed addresses, enabled
+    pub async fn ensure_wg_peer_record_columns(&self) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            ALTER TABLE wg_peer_profiles
+                MODIFY profile_id INT NULL,
+                ADD COLUMN IF NOT EXISTS allow_address TEXT NULL
+                    COMMENT 'JSON list as provisioned by LPG',
+                ADD COLUMN IF NOT EXISTS enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP
+                    DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a peer LPG created (keyed by public key, enabled)
+    pub async fn record_wg_peer(
+        &self,
+        public_key: &str,
+        controller_id: &str,
+        site_id: &str,
+        peer_name: &str,
+        profile_id: Option<i32>,
+        allow_address: &[String],
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO wg_peer_profiles
+            (public_key, controller_id, site_id, peer_name, profile_id, allow_address, enabled)
+            VALUES (?, ?, ?, ?, ?, ?, TRUE)
+            ON DUPLICATE KEY UPDATE
+                controller_id = VALUES(controller_id), site_id = VALUES(site_id),
+                peer_name = VALUES(peer_name), profile_id = VALUES(profile_id),
+                allow_address = VALUES(allow_address), enabled = TRUE
+            "#,
+        )
+        .bind(public_key)
+        .bind(controller_id)
+        .bind(site_id)
+        .bind(peer_name)
+        .bind(profile_id)
+        .bind(serde_json::to_string(allow_address).unwrap_or_default())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Update the fields LPG tracks for a recorded peer (None = unchanged)
+    pub async fn update_wg_peer_record(
+        &self,
+        public_key: &str,
+        peer_name: Option<&str>,
+        allow_address: Option<&[String]>,
+        enabled: Option<bool>,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE wg_peer_profiles
+            SET peer_name = COALESCE(?, peer_name),
+                allow_address = COALESCE(?, allow_address),
+                enabled = COALESCE(?, enabled)
+            WHERE public_key = ?
+            "#,
+        )
+        .bind(peer_name)
+        .bind(allow_address.map(|a| serde_json::to_string(a).unwrap_or_default()))
+        .bind(enabled)
+        .bind(public_key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_wg_peer_record(&self, public_key: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM wg_peer_profiles WHERE public_key = ?")
+            .bind(public_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All peers LPG has recorded, with their profile names
+    pub async fn list_wg_peer_records(&self) -> Result<Vec<WgPeerRecord>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT pp.public_key, pp.controller_id, pp.site_id, pp.peer_name, pp.profile_id,
+                   p.name AS profile_name, pp.allow_address, pp.enabled,
+                   pp.created_at, pp.updated_at
+            FROM wg_peer_profiles pp
+            LEFT JOIN wg_config_profiles p ON p.id = pp.profile_id
+            ORDER BY pp.created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| WgPeerRecord {
+                public_key: row.get("public_key"),
+                controller_id: row.get("controller_id"),
+                site_id: row.get("site_id"),
+                peer_name: row.get("peer_name"),
+                profile_id: row.get("profile_id"),
+                profile_name: row.get("profile_name"),
+                allow_address: row
+                    .get::<Option<String>, _>("allow_address")
+                    .and_then(|a| serde_json::from_str(&a).ok()),
+                enabled: row.get("enabled"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            })
+            .collect())
+    }
+
+    /// Map of peer public key → (profile_id, profile_name)
+    pub async fn list_wg_peer_profiles(&self) -> Result<HashMap<String, (i32, String)>, AppError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT pp.public_key, pp.profile_id, p.name
+            FROM wg_peer_profiles pp
+            JOIN wg_config_profiles p ON p.id = pp.profile_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("public_key"),
+                    (row.get("profile_id"), row.get("name")),
+                )
+            })
+            .collect())
+    }
+}
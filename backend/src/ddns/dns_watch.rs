@@ -0,0 +1,415 @@
+//! DNS dependency monitor for DDNS-linked routes
+//!
+//! Routes with a `ddns_config_id` assume the hostname points at this gateway.
+//! Every `CHECK_INTERVAL` each DDNS config referenced by at least one active
+//! route is resolved with the host resolver and compared against the
+//! config's `last_ip` and the server's public IP (latest `server` entry in
+//! ip_history). Any resolved address matching either counts as a match.
+//!
+//! A divergence (no matching address, or the name no longer resolves) is
+//! only reported once it has lasted `GRACE_PERIOD`, measured from the later
+//! of the first diverging check and the config's last provider update, so
+//! propagation after a legitimate IP change stays quiet. Reported
+//! divergences are written as a security event and notified once; the
+//! recovery is notified when the name matches again.
+//!
+//! The latest result per config is stored in `ddns_dns_checks` so the route
+//! list and the integrated DDNS view read it instead of resolving per load.
+//! The host resolver must answer with public DNS: a local split-horizon
+//! override for the hostname would read as a mismatch.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use crate::db::AppState;
+use crate::models::{DdnsConfig, SecurityEvent, SecurityEventType, Severity};
+use crate::notify::DiscordNotifier;
+
+/// How often linked hostnames are resolved
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// How long a divergence must last before it is reported
+const GRACE_PERIOD: chrono::Duration = chrono::Duration::minutes(15);
+
+/// Outcome of comparing the resolved addresses with the expected ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsMatchState {
+    Match,
+    Mismatch,
+    /// The hostname did not resolve
+    Unresolved,
+    /// Neither last_ip nor the server IP is known yet
+    Unknown,
+}
+
+/// Latest check of one DDNS config (collection `ddns_dns_checks`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DdnsDnsCheck {
+    pub ddns_config_id: i32,
+    pub hostname: String,
+    pub checked_at: DateTime<Utc>,
+    /// Addresses the hostname resolved to
+    pub resolved: Vec<String>,
+    /// The config's last_ip and the server's public IP
+    pub expected: Vec<String>,
+    pub state: DnsMatchState,
+    /// First check of the current divergence
+    pub diverged_since: Option<DateTime<Utc>>,
+    /// Divergence outlasted the grace period (routes show dns_mismatch)
+    pub mismatch: bool,
+    pub error: Option<String>,
+    /// Active routes linked to the config at check time
+    pub route_ids: Vec<i32>,
+}
+
+impl DdnsDnsCheck {
+    /// The `dns_mismatch` indicator for routes and the integrated DDNS view
+    pub fn indicator(&self) -> Option<serde_json::Value> {
+        self.mismatch.then(|| {
+            serde_json::json!({
+                "ddns_config_id": self.ddns_config_id,
+                "hostname": self.hostname,
+                "state": self.state,
+                "observed": self.resolved,
+                "expected": self.expected,
+                "since": self.diverged_since,
+                "checked_at": self.checked_at,
+            })
+        })
+    }
+}
+
+/// Expected addresses for a config, without duplicates
+fn expected_addresses(config: &DdnsConfig, server_ip: Option<&str>) -> Vec<String> {
+    let mut expected: Vec<String> = config.last_ip.iter().cloned().collect();
+    if let Some(ip) = server_ip {
+        if !expected.iter().any(|e| e == ip) {
+            expected.push(ip.to_string());
+        }
+    }
+    expected
+}
+
+/// Build the new check from a resolution result and the previous check
+pub fn evaluate(
+    previous: Option<&DdnsDnsCheck>,
+    config: &DdnsConfig,
+    server_ip: Option<&str>,
+    resolved: Result<Vec<String>, String>,
+    route_ids: Vec<i32>,
+    now: DateTime<Utc>,
+) -> DdnsDnsCheck {
+    let expected = expected_addresses(config, server_ip);
+    let (resolved, error) = match resolved {
+        Ok(addrs) => (addrs, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    let state = if resolved.is_empty() {
+        DnsMatchState::Unresolved
+    } else if expected.is_empty() {
+        DnsMatchState::Unknown
+    } else if resolved.iter().any(|ip| expected.contains(ip)) {
+        DnsMatchState::Match
+    } else {
+        DnsMatchState::Mismatch
+    };
+
+    let diverged = matches!(state, DnsMatchState::Mismatch | DnsMatchState::Unresolved);
+    let diverged_since = diverged.then(|| {
+        previous
+            .filter(|p| p.hostname == config.hostname)
+            .and_then(|p| p.diverged_since)
+            .unwrap_or(now)
+    });
+    let mismatch = diverged_since.is_some_and(|since| {
+        let start = config
+            .last_update
+            .map_or(since, |updated| since.max(updated));
+        now - start >= GRACE_PERIOD
+    });
+
+    DdnsDnsCheck {
+        ddns_config_id: config.id,
+        hostname: config.hostname.clone(),
+        checked_at: now,
+        resolved,
+        expected,
+        state,
+        diverged_since,
+        mismatch,
+        error,
+        route_ids,
+    }
+}
+
+/// All addresses the hostname resolves to, in resolver order
+async fn resolve(hostname: &str) -> Result<Vec<String>, String> {
+    let addrs = tokio::net::lookup_host(format!("{}:0", hostname))
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut ips: Vec<String> = Vec::new();
+    for addr in addrs {
+        let ip = addr.ip().to_string();
+        if !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+    Ok(ips)
+}
+
+/// Background monitor of DDNS hostnames that routes depend on
+pub struct DnsDependencyMonitor {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+}
+
+impl DnsDependencyMonitor {
+    pub fn new(app_state: AppState, notifier: Arc<DiscordNotifier>) -> Self {
+        Self {
+            app_state,
+            notifier,
+        }
+    }
+
+    /// Start the check loop
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting DDNS DNS dependency monitor...");
+        let mut tick = interval(CHECK_INTERVAL);
+        loop {
+            tick.tick().await;
+            if let Err(e) = self.check_all().await {
+                tracing::error!("DDNS DNS dependency check failed: {}", e);
+            }
+        }
+    }
+
+    async fn check_all(&self) -> anyhow::Result<()> {
+        let mut linked: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for route in self.app_state.mysql.list_routes().await? {
+            if let (true, Some(ddns_id)) = (route.active, route.ddns_config_id) {
+                linked.entry(ddns_id).or_default().push(route.id);
+            }
+        }
+
+        let ids: Vec<i32> = linked.keys().copied().collect();
+        self.app_state
+            .mongo
+            .retain_ddns_dns_checks(&ids)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        if linked.is_empty() {
+            return Ok(());
+        }
+
+        let configs = self.app_state.mysql.list_ddns().await?;
+        let previous = self
+            .app_state
+            .mongo
+            .list_ddns_dns_checks()
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let server_ip = self
+            .app_state
+            .mongo
+            .get_ip_history("server")
+            .await
+            .ok()
+            .and_then(|ips| ips.into_iter().next());
+
+        for (ddns_id, route_ids) in linked {
+            let Some(config) = configs.iter().find(|c| c.id == ddns_id) else {
+                continue;
+            };
+            let prev = previous.iter().find(|c| c.ddns_config_id == ddns_id);
+            let resolved = resolve(&config.hostname).await;
+            let check = evaluate(
+                prev,
+                config,
+                server_ip.as_deref(),
+                resolved,
+                route_ids,
+                Utc::now(),
+            );
+
+            let was_mismatch = prev.is_some_and(|p| p.mismatch);
+            if check.mismatch && !was_mismatch {
+                self.report_mismatch(&check).await;
+            } else if was_mismatch && check.state == DnsMatchState::Match {
+                tracing::info!(
+                    "{} resolves to this gateway again ({})",
+                    check.hostname,
+                    check.resolved.join(", ")
+                );
+                self.notifier
+                    .notify_dns_mismatch_recovery(&check.hostname, &check.resolved)
+                    .await;
+            }
+
+            if let Err(e) = self.app_state.mongo.save_ddns_dns_check(&check).await {
+                tracing::warn!("Failed to save DNS check for {}: {}", check.hostname, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn report_mismatch(&self, check: &DdnsDnsCheck) {
+        tracing::warn!(
+            "{} no longer resolves to this gateway: {:?} resolved {:?}, expected {:?} (routes {:?})",
+            check.hostname,
+            check.state,
+            check.resolved,
+            check.expected,
+            check.route_ids
+        );
+
+        let event = SecurityEvent {
+            id: None,
+            timestamp: check.checked_at,
+            event_type: SecurityEventType::DnsMismatch,
+            ip: check.resolved.first().cloned(),
+            details: serde_json::json!({
+                "ddns_config_id": check.ddns_config_id,
+                "hostname": check.hostname,
+                "state": check.state,
+                "observed": check.resolved,
+                "expected": check.expected,
+                "since": check.diverged_since,
+                "route_ids": check.route_ids,
+                "error": check.error,
+            }),
+            severity: Severity::High,
+            notified: true,
+        };
+        if let Err(e) = self.app_state.mongo.log_security_event(&event).await {
+            tracing::error!("Failed to record DNS mismatch security event: {}", e);
+        }
+
+        let mut paths = Vec::new();
+        for id in &check.route_ids {
+            if let Ok(Some(route)) = self.app_state.mysql.get_route(*id).await {
+                paths.push(route.path);
+            }
+        }
+        self.notifier
+            .notify_dns_mismatch(&check.hostname, &check.resolved, &check.expected, &paths)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(last_ip: Option<&str>, last_update: Option<DateTime<Utc>>) -> DdnsConfig {
+        let mut config: DdnsConfig = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "provider": "cloudflare",
+            "hostname": "home.example.com",
+            "update_interval_sec": 300,
+            "status": "active",
+            "ip_source": "poll",
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        config.last_ip = last_ip.map(str::to_string);
+        config.last_update = last_update;
+        config
+    }
+
+    fn ips(list: &[&str]) -> Result<Vec<String>, String> {
+        Ok(list.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn any_expected_address_matches() {
+        let now = Utc::now();
+        let c = config(Some("203.0.113.5"), None);
+        let check = evaluate(
+            None,
+            &c,
+            Some("198.51.100.9"),
+            ips(&["198.51.100.9"]),
+            vec![1],
+            now,
+        );
+        assert_eq!(check.state, DnsMatchState::Match);
+        assert_eq!(check.expected, vec!["203.0.113.5", "198.51.100.9"]);
+        assert!(check.diverged_since.is_none());
+        assert!(check.indicator().is_none());
+
+        let unknown = evaluate(
+            None,
+            &config(None, None),
+            None,
+            ips(&["192.0.2.1"]),
+            vec![],
+            now,
+        );
+        assert_eq!(unknown.state, DnsMatchState::Unknown);
+        assert!(!unknown.mismatch);
+    }
+
+    #[test]
+    fn divergence_is_reported_after_the_grace_period() {
+        let start = Utc::now();
+        let c = config(Some("203.0.113.5"), None);
+        let first = evaluate(None, &c, None, ips(&["192.0.2.44"]), vec![1], start);
+        assert_eq!(first.state, DnsMatchState::Mismatch);
+        assert_eq!(first.diverged_since, Some(start));
+        assert!(!first.mismatch);
+
+        let later = start + GRACE_PERIOD;
+        let second = evaluate(Some(&first), &c, None, ips(&["192.0.2.44"]), vec![1], later);
+        assert_eq!(second.diverged_since, Some(start));
+        assert!(second.mismatch);
+        assert_eq!(second.indicator().unwrap()["observed"][0], "192.0.2.44");
+
+        // A failed lookup keeps the divergence running
+        let gone = evaluate(
+            Some(&second),
+            &c,
+            None,
+            Err("NXDOMAIN".into()),
+            vec![1],
+            later,
+        );
+        assert_eq!(gone.state, DnsMatchState::Unresolved);
+        assert!(gone.mismatch);
+
+        let back = evaluate(Some(&gone), &c, None, ips(&["203.0.113.5"]), vec![1], later);
+        assert!(!back.mismatch);
+        assert!(back.diverged_since.is_none());
+    }
+
+    #[test]
+    fn recent_provider_update_extends_the_grace_period() {
+        let start = Utc::now();
+        let updated = start + chrono::Duration::minutes(10);
+        let c = config(Some("203.0.113.5"), Some(updated));
+        let first = evaluate(None, &c, None, ips(&["192.0.2.44"]), vec![1], start);
+        let check = evaluate(
+            Some(&first),
+            &c,
+            None,
+            ips(&["192.0.2.44"]),
+            vec![1],
+            start + GRACE_PERIOD,
+        );
+        assert!(!check.mismatch);
+        let check = evaluate(
+            Some(&first),
+            &c,
+            None,
+            ips(&["192.0.2.44"]),
+            vec![1],
+            updated + GRACE_PERIOD,
+        );
+        assert!(check.mismatch);
+    }
+}
@@ -0,0 +1,142 @@
+//! DDNS failover to a secondary provider
+//!
+//! A config may name another config as its secondary: a different provider
+//! for the same logical hostname (the record is CNAMEd or delegated there).
+//! Secondaries are standby and are not updated on their own. After
+//! `failover_threshold` consecutive primary failures the updater pushes the
+//! address through the secondary instead, keeps retrying the primary every
+//! cycle, and switches back on the first primary success. Links are one
+//! level deep: a secondary cannot have a secondary, a primary cannot be a
+//! secondary, and a secondary serves a single primary.
+
+use std::collections::HashSet;
+
+use crate::models::{DdnsConfig, DdnsStatus};
+
+/// Upper bound for failover_threshold
+pub const MAX_FAILOVER_THRESHOLD: i32 = 20;
+
+/// Validate linking `secondary` to config `id` (None while creating).
+/// `links` holds `(id, secondary_config_id)` for every existing config.
+pub fn validate_failover_link(
+    id: Option<i32>,
+    secondary: i32,
+    links: &[(i32, Option<i32>)],
+) -> Result<(), String> {
+    if id == Some(secondary) {
+        return Err("A DDNS config cannot be its own secondary".to_string());
+    }
+    let target = links
+        .iter()
+        .find(|(other, _)| *other == secondary)
+        .ok_or_else(|| format!("DDNS config {} not found", secondary))?;
+    if target.1.is_some() {
+        return Err(format!(
+            "DDNS config {} has a secondary of its own; failover chains are not allowed",
+            secondary
+        ));
+    }
+    if let Some(id) = id {
+        if let Some((primary, _)) = links.iter().find(|(_, s)| *s == Some(id)) {
+            return Err(format!(
+                "DDNS config {} is the secondary of config {} and cannot have a secondary",
+                id, primary
+            ));
+        }
+    }
+    if let Some((primary, _)) = links
+        .iter()
+        .find(|(other, s)| *s == Some(secondary) && Some(*other) != id)
+    {
+        return Err(format!(
+            "DDNS config {} is already the secondary of config {}",
+            secondary, primary
+        ));
+    }
+    Ok(())
+}
+
+/// Configs the scheduler updates: secondaries are standby, errored configs
+/// are only retried when they can fail over
+pub fn scheduled_configs(configs: Vec<DdnsConfig>) -> Vec<DdnsConfig> {
+    let standby: HashSet<i32> = configs
+        .iter()
+        .filter_map(|c| c.secondary_config_id)
+        .collect();
+    configs
+        .into_iter()
+        .filter(|c| !standby.contains(&c.id))
+        .filter(|c| match c.status {
+            DdnsStatus::Active => true,
+            DdnsStatus::Error => c.secondary_config_id.is_some(),
+            DdnsStatus::Disabled => false,
+        })
+        .collect()
+}
+
+/// Whether a failure brings the primary to its threshold
+pub fn should_fail_over(config: &DdnsConfig) -> bool {
+    !config.failover_active && config.consecutive_failures + 1 >= config.failover_threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(id: i32, status: &str, secondary: Option<i32>) -> DdnsConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "provider": "cloudflare",
+            "hostname": "home.example.com",
+            "update_interval_sec": 300,
+            "status": status,
+            "ip_source": "poll",
+            "secondary_config_id": secondary,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn failover_links_reject_loops_and_chains() {
+        // 1 -> 2, 3 and 4 unlinked
+        let links = [(1, Some(2)), (2, None), (3, None), (4, None)];
+        assert!(validate_failover_link(Some(3), 4, &links).is_ok());
+        assert!(validate_failover_link(None, 3, &links).is_ok());
+        // Re-saving the existing link
+        assert!(validate_failover_link(Some(1), 2, &links).is_ok());
+
+        assert!(validate_failover_link(Some(3), 3, &links).is_err());
+        assert!(validate_failover_link(Some(3), 9, &links).is_err());
+        // 3 -> 1 -> 2 would chain
+        assert!(validate_failover_link(Some(3), 1, &links).is_err());
+        // 2 is a secondary, so it cannot get one (2 -> 1 would loop)
+        assert!(validate_failover_link(Some(2), 1, &links).is_err());
+        assert!(validate_failover_link(Some(2), 3, &links).is_err());
+        // 2 already serves 1
+        assert!(validate_failover_link(Some(3), 2, &links).is_err());
+    }
+
+    #[test]
+    fn secondaries_are_standby() {
+        let scheduled = scheduled_configs(vec![
+            config(1, "error", Some(2)),
+            config(2, "active", None),
+            config(3, "error", None),
+            config(4, "active", None),
+            config(5, "disabled", None),
+        ]);
+        let ids: Vec<i32> = scheduled.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![1, 4]);
+
+        let mut primary = config(1, "error", Some(2));
+        assert_eq!(primary.failover_threshold, 3);
+        primary.consecutive_failures = 1;
+        assert!(!should_fail_over(&primary));
+        primary.consecutive_failures = 2;
+        assert!(should_fail_over(&primary));
+        primary.failover_active = true;
+        assert!(!should_fail_over(&primary));
+    }
+}
@@ -1,7 +1,12 @@
 //! DDNS module - Dynamic DNS update functionality
 
+pub mod dns_watch;
+pub mod failover;
 mod providers;
 mod updater;
 
-pub use self::providers::DdnsProviderTrait;
+pub use self::dns_watch::DnsDependencyMonitor;
+pub use self::providers::{
+    is_auth_failure, DdnsProviderTrait, DdnsTestResult, DdnsUpdate, DdnsUpdateOutcome,
+};
 pub use self::updater::DdnsUpdater;
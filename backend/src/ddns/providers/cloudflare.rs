@@ -1,11 +1,42 @@
 //! Cloudflare provider implementation
+//!
+//! The zone is `zone_id`, or is looked up by name from the hostname when that
+//! is empty (home.example.com tries home.example.com, then example.com).
+//! Record ids are kept on the config after the first update, so later updates
+//! PATCH the record directly; without a cached id, or when the cached record
+//! is gone, the record is looked up by name and type and created when
+//! missing. The updater stores the zone and record ids the provider used
+//! (`DdnsUpdate`).
+//!
+//! A PATCH sends the address plus `proxied`/`ttl` only when the config sets
+//! them, so an update never flips the orange cloud. New records take the
+//! config values, else not proxied and automatic TTL. With an IPv6 address as
+//! well, the AAAA record is upserted the same way after the A record.
+//!
+//! The credential test verifies the token, resolves the zone, then looks the
+//! record up (A, then AAAA) without writing anything.
 
 use async_trait::async_trait;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
-use super::DdnsProviderTrait;
+use super::{DdnsProviderTrait, DdnsTestResult, DdnsUpdate, DdnsUpdateOutcome, AUTH_FAILED};
 use crate::models::DdnsConfig;
 
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+/// API error codes for missing, invalid or insufficient credentials
+const AUTH_ERROR_CODES: [i64; 4] = [9103, 9106, 9109, 10000];
+
+/// Of those, the ones a valid token gets for a zone it may not edit
+const PERMISSION_ERROR_CODES: [i64; 2] = [9109, 10000];
+
+/// "Record does not exist." (PATCH of a deleted record)
+const RECORD_NOT_FOUND: i64 = 81044;
+
+/// Cloudflare's "automatic" TTL
+const TTL_AUTO: u32 = 1;
+
 pub struct CloudflareProvider {
     client: reqwest::Client,
 }
@@ -24,14 +55,16 @@ impl Default for CloudflareProvider {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 struct CloudflareDnsRecord {
     #[serde(rename = "type")]
     record_type: String,
     name: String,
     content: String,
-    ttl: u32,
-    proxied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxied: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,98 +74,397 @@ struct CloudflareResponse<T> {
     result: Option<T>,
 }
 
+impl<T> CloudflareResponse<T> {
+    /// Result on success, joined error messages otherwise
+    fn into_result(self) -> Result<Option<T>, String> {
+        self.into_result_with(&AUTH_ERROR_CODES, |errors| {
+            format!("{} (Cloudflare): {}", AUTH_FAILED, errors)
+        })
+    }
+
+    /// Like `into_result`, naming the missing permission when the token may
+    /// not touch the zone's DNS records
+    fn into_dns_result(self, zone_id: &str) -> Result<Option<T>, String> {
+        if self
+            .errors
+            .iter()
+            .any(|e| PERMISSION_ERROR_CODES.contains(&e.code))
+        {
+            return self.into_result_with(&PERMISSION_ERROR_CODES, |errors| {
+                format!(
+                    "{} (Cloudflare): token lacks the Zone.DNS Edit permission for zone {}: {}",
+                    AUTH_FAILED, zone_id, errors
+                )
+            });
+        }
+        self.into_result()
+    }
+
+    fn into_result_with(
+        self,
+        auth_codes: &[i64],
+        auth_error: impl FnOnce(&str) -> String,
+    ) -> Result<Option<T>, String> {
+        if self.success {
+            return Ok(self.result);
+        }
+        let auth = self.errors.iter().any(|e| auth_codes.contains(&e.code));
+        let errors: Vec<String> = self.errors.into_iter().map(|e| e.message).collect();
+        if auth {
+            return Err(auth_error(&errors.join(", ")));
+        }
+        Err(format!("Cloudflare error: {}", errors.join(", ")))
+    }
+
+    fn has_error(&self, code: i64) -> bool {
+        self.errors.iter().any(|e| e.code == code)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CloudflareError {
+    #[serde(default)]
+    code: i64,
     message: String,
 }
 
+/// Record as returned by the list, create and update endpoints
 #[derive(Debug, Deserialize)]
 struct CloudflareDnsResult {
     id: String,
+    #[serde(default)]
+    proxied: Option<bool>,
+    #[serde(default)]
+    ttl: Option<u32>,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// GET /zones result entry
+#[derive(Debug, Deserialize)]
+struct CloudflareZone {
+    id: String,
+    name: String,
+}
+
+/// GET /user/tokens/verify result
+#[derive(Debug, Deserialize)]
+struct TokenVerifyResult {
+    status: String,
+}
+
+/// Verify response to Ok, or an auth failure unless the token is active
+fn token_status(response: CloudflareResponse<TokenVerifyResult>) -> Result<(), String> {
+    match response.into_result()? {
+        Some(token) if token.status == "active" => Ok(()),
+        Some(token) => Err(format!(
+            "{} (Cloudflare): token is {}",
+            AUTH_FAILED, token.status
+        )),
+        None => Err("Cloudflare error: empty token verify response".to_string()),
+    }
+}
+
+/// Record body. Unset options are left out of updates (the record keeps its
+/// own) and get Cloudflare defaults (not proxied, auto TTL) on create.
+fn record_payload(config: &DdnsConfig, ip: &str, create: bool) -> CloudflareDnsRecord {
+    let ttl = config.ttl.and_then(|t| u32::try_from(t).ok());
+    CloudflareDnsRecord {
+        record_type: record_type(ip).to_string(),
+        name: config.hostname.clone(),
+        content: ip.to_string(),
+        ttl: if create {
+            Some(ttl.unwrap_or(TTL_AUTO))
+        } else {
+            ttl
+        },
+        proxied: if create {
+            Some(config.proxied.unwrap_or(false))
+        } else {
+            config.proxied
+        },
+    }
+}
+
+/// Zone names the hostname can belong to, longest first (no bare TLD)
+fn zone_candidates(hostname: &str) -> Vec<&str> {
+    let mut candidates = Vec::new();
+    let mut rest = hostname.trim_end_matches('.');
+    while let Some((_, parent)) = rest.split_once('.') {
+        candidates.push(rest);
+        rest = parent;
+    }
+    candidates
+}
+
+/// Outcome of an A and an AAAA upsert: Created when either record was new
+fn combine(a: DdnsUpdateOutcome, b: DdnsUpdateOutcome) -> DdnsUpdateOutcome {
+    if a == DdnsUpdateOutcome::Created || b == DdnsUpdateOutcome::Created {
+        DdnsUpdateOutcome::Created
+    } else {
+        DdnsUpdateOutcome::Updated
+    }
+}
+
+/// Record type based on IP format
+fn record_type(ip: &str) -> &'static str {
+    if ip.contains(':') {
+        "AAAA"
+    } else {
+        "A"
+    }
+}
+
+/// Cached id of the record holding addresses of `ip`'s family
+fn cached_record_id<'a>(config: &'a DdnsConfig, ip: &str) -> Option<&'a str> {
+    if record_type(ip) == "AAAA" {
+        config.record_id_v6.as_deref()
+    } else {
+        config.record_id.as_deref()
+    }
 }
 
 #[async_trait]
 impl DdnsProviderTrait for CloudflareProvider {
-    async fn update(&self, config: &DdnsConfig, ip: &str) -> Result<(), String> {
+    async fn update(
+        &self,
+        config: &DdnsConfig,
+        ip: &str,
+        ipv6: Option<&str>,
+    ) -> Result<DdnsUpdate, String> {
         let api_token = config
             .api_token
             .as_ref()
             .ok_or("API token required for Cloudflare")?;
-        let zone_id = config
-            .zone_id
-            .as_ref()
-            .ok_or("Zone ID required for Cloudflare")?;
+        let zone_id = self.zone_for(api_token, config).await?;
 
-        // First, get the existing DNS record ID
-        let record_id = self
-            .get_record_id(api_token, zone_id, &config.hostname)
-            .await?;
+        let mut update = DdnsUpdate {
+            zone_id: Some(zone_id.clone()),
+            ..DdnsUpdateOutcome::Updated.into()
+        };
+        for address in std::iter::once(ip).chain(ipv6.filter(|v6| *v6 != ip)) {
+            let (outcome, record_id) = self
+                .upsert_record(config, api_token, &zone_id, address)
+                .await?;
+            update.outcome = combine(update.outcome, outcome);
+            if record_type(address) == "AAAA" {
+                update.record_id_v6 = Some(record_id);
+            } else {
+                update.record_id = Some(record_id);
+            }
+        }
+        Ok(update)
+    }
+
+    async fn test(&self, config: &DdnsConfig) -> DdnsTestResult {
+        let result = DdnsTestResult::new(self, config, "token verify and record lookup");
+        let Some(api_token) = &config.api_token else {
+            return result.failed("API token required for Cloudflare".to_string());
+        };
+        if let Err(e) = self.verify_token(api_token).await {
+            return result.failed(e);
+        }
+        let zone_id = match self.zone_for(api_token, config).await {
+            Ok(zone_id) => zone_id,
+            Err(e) => return result.failed(e),
+        };
+        for record_type in ["A", "AAAA"] {
+            match self
+                .find_record(api_token, &zone_id, &config.hostname, record_type)
+                .await
+            {
+                Ok(Some(found)) => return result.passed(found.content),
+                Ok(None) => {}
+                Err(e) => return result.failed(e),
+            }
+        }
+        result.passed(None)
+    }
 
-        // Determine record type based on IP format
-        let record_type = if ip.contains(':') { "AAAA" } else { "A" };
+    fn name(&self) -> &'static str {
+        "Cloudflare"
+    }
+}
+
+impl CloudflareProvider {
+    /// The config's zone, else the one found for its hostname
+    async fn zone_for(&self, api_token: &str, config: &DdnsConfig) -> Result<String, String> {
+        match config.zone_id.as_deref().filter(|z| !z.is_empty()) {
+            Some(zone_id) => Ok(zone_id.to_string()),
+            None => self.discover_zone(api_token, &config.hostname).await,
+        }
+    }
+
+    async fn discover_zone(&self, api_token: &str, hostname: &str) -> Result<String, String> {
+        for name in zone_candidates(hostname) {
+            let response = self
+                .client
+                .get(format!("{}/zones?name={}", API_BASE, name))
+                .header("Authorization", format!("Bearer {}", api_token))
+                .timeout(std::time::Duration::from_secs(30))
+                .send()
+                .await
+                .map_err(|e| format!("Failed to list zones: {}", e))?;
+
+            let zones: CloudflareResponse<Vec<CloudflareZone>> = response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse zone list response: {}", e))?;
+
+            if let Some(zone) = zones.into_result()?.and_then(|z| z.into_iter().next()) {
+                tracing::info!(
+                    "Cloudflare zone for {}: {} ({})",
+                    hostname,
+                    zone.name,
+                    zone.id
+                );
+                return Ok(zone.id);
+            }
+        }
+        Err(format!(
+            "No Cloudflare zone found for {}: set zone_id or give the token Zone.Zone Read permission",
+            hostname
+        ))
+    }
+
+    /// Update the record of `ip`'s family (cached id first), or create it;
+    /// returns what happened and the record id
+    async fn upsert_record(
+        &self,
+        config: &DdnsConfig,
+        api_token: &str,
+        zone_id: &str,
+        ip: &str,
+    ) -> Result<(DdnsUpdateOutcome, String), String> {
+        let payload = record_payload(config, ip, false);
+        if let Some(record_id) = cached_record_id(config, ip) {
+            match self
+                .patch_record(api_token, zone_id, record_id, &payload)
+                .await?
+            {
+                Some(record) => {
+                    log_record(config, &payload, &record, DdnsUpdateOutcome::Updated);
+                    return Ok((DdnsUpdateOutcome::Updated, record.id));
+                }
+                None => tracing::info!(
+                    "Cached Cloudflare record {} for {} is gone, looking it up",
+                    record_id,
+                    config.hostname
+                ),
+            }
+        }
 
-        let record = CloudflareDnsRecord {
-            record_type: record_type.to_string(),
-            name: config.hostname.clone(),
-            content: ip.to_string(),
-            ttl: 1, // Auto TTL
-            proxied: false,
+        let existing = self
+            .find_record(api_token, zone_id, &config.hostname, &payload.record_type)
+            .await?;
+        let (record, outcome) = match existing {
+            Some(found) => (
+                self.patch_record(api_token, zone_id, &found.id, &payload)
+                    .await?
+                    .ok_or_else(|| {
+                        format!("Cloudflare record {} vanished during the update", found.id)
+                    })?,
+                DdnsUpdateOutcome::Updated,
+            ),
+            None => (
+                self.create_record(api_token, zone_id, &record_payload(config, ip, true))
+                    .await?,
+                DdnsUpdateOutcome::Created,
+            ),
         };
+        log_record(config, &payload, &record, outcome);
+        Ok((outcome, record.id))
+    }
 
-        // Update the DNS record
-        let url = format!(
-            "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}",
-            zone_id, record_id
-        );
+    /// PATCH a record; None when it no longer exists
+    async fn patch_record(
+        &self,
+        api_token: &str,
+        zone_id: &str,
+        record_id: &str,
+        record: &CloudflareDnsRecord,
+    ) -> Result<Option<CloudflareDnsResult>, String> {
+        let request = self.client.patch(format!(
+            "{}/zones/{}/dns_records/{}",
+            API_BASE, zone_id, record_id
+        ));
+        let (status, response) = self.send_record(request, api_token, record).await?;
+        if status == StatusCode::NOT_FOUND || response.has_error(RECORD_NOT_FOUND) {
+            return Ok(None);
+        }
+        response
+            .into_dns_result(zone_id)?
+            .map(Some)
+            .ok_or_else(|| "Cloudflare error: empty record response".to_string())
+    }
 
-        let response = self
+    async fn create_record(
+        &self,
+        api_token: &str,
+        zone_id: &str,
+        record: &CloudflareDnsRecord,
+    ) -> Result<CloudflareDnsResult, String> {
+        let request = self
             .client
-            .put(&url)
+            .post(format!("{}/zones/{}/dns_records", API_BASE, zone_id));
+        let (_, response) = self.send_record(request, api_token, record).await?;
+        response
+            .into_dns_result(zone_id)?
+            .ok_or_else(|| "Cloudflare error: empty record response".to_string())
+    }
+
+    async fn send_record(
+        &self,
+        request: reqwest::RequestBuilder,
+        api_token: &str,
+        record: &CloudflareDnsRecord,
+    ) -> Result<(StatusCode, CloudflareResponse<CloudflareDnsResult>), String> {
+        let response = request
             .header("Authorization", format!("Bearer {}", api_token))
             .header("Content-Type", "application/json")
-            .json(&record)
+            .json(record)
             .timeout(std::time::Duration::from_secs(30))
             .send()
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
-        let cf_response: CloudflareResponse<CloudflareDnsResult> = response
+        let status = response.status();
+        let cf_response = response
             .json()
             .await
             .map_err(|e| format!("Failed to parse response: {}", e))?;
-
-        if cf_response.success {
-            tracing::info!(
-                "Cloudflare update successful for {}: {}",
-                config.hostname,
-                ip
-            );
-            Ok(())
-        } else {
-            let errors: Vec<String> = cf_response
-                .errors
-                .iter()
-                .map(|e| e.message.clone())
-                .collect();
-            Err(format!("Cloudflare error: {}", errors.join(", ")))
-        }
+        Ok((status, cf_response))
     }
 
-    fn name(&self) -> &'static str {
-        "Cloudflare"
+    async fn verify_token(&self, api_token: &str) -> Result<(), String> {
+        let response = self
+            .client
+            .get(format!("{}/user/tokens/verify", API_BASE))
+            .header("Authorization", format!("Bearer {}", api_token))
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to verify token: {}", e))?;
+
+        token_status(
+            response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse token verify response: {}", e))?,
+        )
     }
-}
 
-impl CloudflareProvider {
-    async fn get_record_id(
+    /// Existing record for `hostname`/`record_type`, None when there is none
+    async fn find_record(
         &self,
         api_token: &str,
         zone_id: &str,
         hostname: &str,
-    ) -> Result<String, String> {
+        record_type: &str,
+    ) -> Result<Option<CloudflareDnsResult>, String> {
         let url = format!(
-            "https://api.cloudflare.com/client/v4/zones/{}/dns_records?name={}",
-            zone_id, hostname
+            "{}/zones/{}/dns_records?type={}&name={}",
+            API_BASE, zone_id, record_type, hostname
         );
 
         let response = self
@@ -144,29 +476,262 @@ impl CloudflareProvider {
             .await
             .map_err(|e| format!("Failed to get DNS records: {}", e))?;
 
-        #[derive(Debug, Deserialize)]
-        struct ListResult {
-            id: String,
-        }
-
-        let cf_response: CloudflareResponse<Vec<ListResult>> = response
+        let cf_response: CloudflareResponse<Vec<CloudflareDnsResult>> = response
             .json()
             .await
             .map_err(|e| format!("Failed to parse DNS list response: {}", e))?;
 
-        if !cf_response.success {
-            let errors: Vec<String> = cf_response
-                .errors
-                .iter()
-                .map(|e| e.message.clone())
-                .collect();
-            return Err(format!("Cloudflare error: {}", errors.join(", ")));
-        }
+        Ok(cf_response
+            .into_dns_result(zone_id)?
+            .and_then(|records| records.into_iter().next()))
+    }
+}
+
+fn log_record(
+    config: &DdnsConfig,
+    payload: &CloudflareDnsRecord,
+    record: &CloudflareDnsResult,
+    outcome: DdnsUpdateOutcome,
+) {
+    tracing::info!(
+        "Cloudflare {} {} record {} for {}: {} (proxied={}, ttl={})",
+        if outcome == DdnsUpdateOutcome::Created {
+            "created"
+        } else {
+            "updated"
+        },
+        payload.record_type,
+        record.id,
+        config.hostname,
+        payload.content,
+        record.proxied.unwrap_or(false),
+        record.ttl.unwrap_or(TTL_AUTO)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed responses recorded from the Cloudflare v4 API
+    const LIST_PROXIED: &str = r#"{
+        "result": [{
+            "id": "372e67954025e0ba6aaa6d586b9e0b59",
+            "zone_id": "023e105f4ecef8ad9ca31a8372d0c353",
+            "zone_name": "example.com",
+            "name": "home.example.com",
+            "type": "A",
+            "content": "198.51.100.4",
+            "proxiable": true,
+            "proxied": true,
+            "ttl": 1,
+            "locked": false
+        }],
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result_info": {"page": 1, "per_page": 100, "count": 1, "total_count": 1}
+    }"#;
+    const LIST_EMPTY: &str = r#"{
+        "result": [],
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result_info": {"page": 1, "per_page": 100, "count": 0, "total_count": 0}
+    }"#;
+    const CREATED: &str = r#"{
+        "result": {
+            "id": "9a7806061c88ada191ed06f989cc3dac",
+            "name": "home.example.com",
+            "type": "A",
+            "content": "203.0.113.7",
+            "proxied": false,
+            "ttl": 300
+        },
+        "success": true,
+        "errors": [],
+        "messages": []
+    }"#;
+    const AUTH_ERROR: &str = r#"{
+        "result": null,
+        "success": false,
+        "errors": [{"code": 10000, "message": "Authentication error"}],
+        "messages": []
+    }"#;
+
+    fn config(proxied: Option<bool>, ttl: Option<i32>) -> DdnsConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "provider": "cloudflare",
+            "hostname": "home.example.com",
+            "update_interval_sec": 300,
+            "status": "active",
+            "ip_source": "poll",
+            "proxied": proxied,
+            "ttl": ttl,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn first(raw: &str) -> Option<CloudflareDnsResult> {
+        serde_json::from_str::<CloudflareResponse<Vec<CloudflareDnsResult>>>(raw)
+            .unwrap()
+            .into_result()
+            .unwrap()
+            .and_then(|r| r.into_iter().next())
+    }
+
+    #[test]
+    fn update_preserves_proxied_when_unset() {
+        let existing = first(LIST_PROXIED).unwrap();
+        assert_eq!(existing.id, "372e67954025e0ba6aaa6d586b9e0b59");
+
+        // Unset options are not sent, so the record keeps its own
+        let payload = record_payload(&config(None, None), "203.0.113.7", false);
+        assert_eq!(payload.proxied, None);
+        assert_eq!(payload.ttl, None);
+        assert_eq!(payload.record_type, "A");
+        assert_eq!(payload.content, "203.0.113.7");
+        let body = serde_json::to_value(&payload).unwrap();
+        assert!(body.get("proxied").is_none() && body.get("ttl").is_none());
 
-        cf_response
-            .result
-            .and_then(|records| records.into_iter().next())
-            .map(|r| r.id)
-            .ok_or_else(|| format!("DNS record not found for {}", hostname))
+        // Explicit config values are sent
+        let payload = record_payload(&config(Some(false), Some(300)), "203.0.113.7", false);
+        assert_eq!(payload.proxied, Some(false));
+        assert_eq!(payload.ttl, Some(300));
+    }
+
+    #[test]
+    fn missing_record_is_created_with_config_options() {
+        assert!(first(LIST_EMPTY).is_none());
+
+        let payload = record_payload(&config(None, Some(300)), "2001:db8::7", true);
+        assert_eq!(payload.record_type, "AAAA");
+        assert_eq!(payload.proxied, Some(false));
+        assert_eq!(payload.ttl, Some(300));
+        let payload = record_payload(&config(None, None), "203.0.113.7", true);
+        assert_eq!(payload.ttl, Some(TTL_AUTO));
+
+        let created = serde_json::from_str::<CloudflareResponse<CloudflareDnsResult>>(CREATED)
+            .unwrap()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        assert_eq!(created.ttl, Some(300));
+        assert_eq!(DdnsUpdateOutcome::Created.note(), Some("record created"));
+    }
+
+    #[test]
+    fn zone_is_searched_from_the_full_hostname_up() {
+        assert_eq!(
+            zone_candidates("a.home.example.com."),
+            vec!["a.home.example.com", "home.example.com", "example.com"]
+        );
+        assert!(zone_candidates("localhost").is_empty());
+
+        let zones: CloudflareResponse<Vec<CloudflareZone>> = serde_json::from_str(
+            r#"{"result": [{"id": "023e105f4ecef8ad9ca31a8372d0c353", "name": "example.com",
+                "status": "active"}], "success": true, "errors": [], "messages": []}"#,
+        )
+        .unwrap();
+        let zone = zones.into_result().unwrap().unwrap().remove(0);
+        assert_eq!(zone.id, "023e105f4ecef8ad9ca31a8372d0c353");
+    }
+
+    #[test]
+    fn record_ids_are_cached_per_family() {
+        let mut config = config(None, None);
+        config.zone_id = Some("zone".to_string());
+        config.record_id = Some("a-record".to_string());
+        assert_eq!(cached_record_id(&config, "203.0.113.7"), Some("a-record"));
+        assert_eq!(cached_record_id(&config, "2001:db8::7"), None);
+
+        let update = DdnsUpdate {
+            zone_id: Some("zone".to_string()),
+            record_id: Some("a-record".to_string()),
+            ..DdnsUpdateOutcome::Updated.into()
+        };
+        assert!(!update.changes_ids(&config));
+        let update = DdnsUpdate {
+            record_id_v6: Some("aaaa-record".to_string()),
+            ..update
+        };
+        assert!(update.changes_ids(&config));
+
+        let gone: CloudflareResponse<CloudflareDnsResult> = serde_json::from_str(
+            r#"{"result": null, "success": false,
+                "errors": [{"code": 81044, "message": "Record does not exist."}]}"#,
+        )
+        .unwrap();
+        assert!(gone.has_error(RECORD_NOT_FOUND));
+    }
+
+    #[test]
+    fn dual_stack_outcome_reports_any_created_record() {
+        use DdnsUpdateOutcome::{Created, Updated};
+        assert_eq!(combine(Updated, Updated), Updated);
+        assert_eq!(combine(Updated, Created), Created);
+        assert_eq!(combine(Created, Updated), Created);
+    }
+
+    #[test]
+    fn api_errors_are_reported() {
+        let err = serde_json::from_str::<CloudflareResponse<CloudflareDnsResult>>(AUTH_ERROR)
+            .unwrap()
+            .into_result()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "Authentication failed (Cloudflare): Authentication error"
+        );
+        assert!(crate::ddns::is_auth_failure(&err));
+
+        let other = r#"{"result": null, "success": false,
+            "errors": [{"code": 81057, "message": "Record already exists."}]}"#;
+        let err = serde_json::from_str::<CloudflareResponse<CloudflareDnsResult>>(other)
+            .unwrap()
+            .into_result()
+            .unwrap_err();
+        assert_eq!(err, "Cloudflare error: Record already exists.");
+        assert!(!crate::ddns::is_auth_failure(&err));
+
+        // DNS calls name the permission the token is missing
+        let err = serde_json::from_str::<CloudflareResponse<CloudflareDnsResult>>(AUTH_ERROR)
+            .unwrap()
+            .into_dns_result("zone")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "Authentication failed (Cloudflare): token lacks the Zone.DNS Edit permission \
+             for zone zone: Authentication error"
+        );
+        assert!(crate::ddns::is_auth_failure(&err));
+    }
+
+    #[test]
+    fn token_verify_requires_an_active_token() {
+        let parse = |raw: &str| token_status(serde_json::from_str(raw).unwrap());
+        assert!(parse(
+            r#"{"result": {"id": "ed17574386854bf78a67040be0a770b0", "status": "active"},
+                "success": true, "errors": [], "messages": []}"#
+        )
+        .is_ok());
+
+        let err = parse(
+            r#"{"result": {"id": "ed17574386854bf78a67040be0a770b0", "status": "expired"},
+                "success": true, "errors": [], "messages": []}"#,
+        )
+        .unwrap_err();
+        assert!(crate::ddns::is_auth_failure(&err));
+        assert!(crate::ddns::is_auth_failure(
+            &parse(AUTH_ERROR).unwrap_err()
+        ));
+
+        assert_eq!(
+            first(LIST_PROXIED).unwrap().content.as_deref(),
+            Some("198.51.100.4")
+        );
     }
 }
@@ -2,7 +2,10 @@
 
 use async_trait::async_trait;
 
-use super::DdnsProviderTrait;
+use super::{
+    test_with_unchanged_update, DdnsProviderTrait, DdnsTestResult, DdnsUpdate, DdnsUpdateOutcome,
+    AUTH_FAILED, NO_HOST,
+};
 use crate::models::DdnsConfig;
 
 pub struct DynDnsProvider {
@@ -25,7 +28,12 @@ impl Default for DynDnsProvider {
 
 #[async_trait]
 impl DdnsProviderTrait for DynDnsProvider {
-    async fn update(&self, config: &DdnsConfig, ip: &str) -> Result<(), String> {
+    async fn update(
+        &self,
+        config: &DdnsConfig,
+        ip: &str,
+        ipv6: Option<&str>,
+    ) -> Result<DdnsUpdate, String> {
         let username = config
             .username
             .as_ref()
@@ -36,10 +44,14 @@ impl DdnsProviderTrait for DynDnsProvider {
             .ok_or("Password required for DynDNS")?;
 
         // DynDNS update URL format
-        // https://members.dyndns.org/nic/update?hostname=<hostname>&myip=<ip>
+        // https://members.dyndns.org/nic/update?hostname=<hostname>&myip=<ip>[,<ipv6>]
+        let myip = match ipv6.filter(|v6| *v6 != ip) {
+            Some(v6) => format!("{},{}", ip, v6),
+            None => ip.to_string(),
+        };
         let url = format!(
             "https://members.dyndns.org/nic/update?hostname={}&myip={}",
-            config.hostname, ip
+            config.hostname, myip
         );
 
         let response = self
@@ -52,6 +64,9 @@ impl DdnsProviderTrait for DynDnsProvider {
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
+        if matches!(response.status().as_u16(), 401 | 403) {
+            return Err(format!("{}: HTTP {}", AUTH_FAILED, response.status()));
+        }
         if !response.status().is_success() {
             return Err(format!("HTTP error: {}", response.status()));
         }
@@ -77,11 +92,11 @@ impl DdnsProviderTrait for DynDnsProvider {
         match response_code {
             "good" | "nochg" => {
                 tracing::info!("DynDNS update successful for {}: {}", config.hostname, body);
-                Ok(())
+                Ok(DdnsUpdateOutcome::Applied.into())
             }
-            "badauth" => Err("Bad authentication credentials".to_string()),
+            "badauth" => Err(format!("{}: bad credentials", AUTH_FAILED)),
             "notfqdn" => Err("Hostname is not a fully qualified domain name".to_string()),
-            "nohost" => Err("Hostname does not exist in your account".to_string()),
+            "nohost" => Err(format!("{} in your account", NO_HOST)),
             "numhost" => Err("Too many hosts or aliases".to_string()),
             "abuse" => Err("Hostname has been blocked due to abuse".to_string()),
             "dnserr" => Err("DNS error on server side".to_string()),
@@ -90,6 +105,10 @@ impl DdnsProviderTrait for DynDnsProvider {
         }
     }
 
+    async fn test(&self, config: &DdnsConfig) -> DdnsTestResult {
+        test_with_unchanged_update(self, config).await
+    }
+
     fn name(&self) -> &'static str {
         "DynDNS"
     }
@@ -8,32 +8,211 @@ pub use self::cloudflare::CloudflareProvider;
 pub use self::dyndns::DynDnsProvider;
 pub use self::noip::NoIpProvider;
 
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
 use async_trait::async_trait;
+use serde::Serialize;
 
 use crate::models::DdnsConfig;
 
+/// Prefix of update errors caused by rejected credentials (mapped to
+/// DDNS_PROVIDER_AUTH_FAILED; keep it stable)
+pub const AUTH_FAILED: &str = "Authentication failed";
+
+pub fn is_auth_failure(error: &str) -> bool {
+    error.starts_with(AUTH_FAILED)
+}
+
+/// Prefix of dyndns2 `nohost` errors (the account has no such hostname)
+pub const NO_HOST: &str = "Hostname does not exist";
+
+/// What a successful provider update did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DdnsUpdateOutcome {
+    /// Provider accepted the address (no record-level detail)
+    Applied,
+    /// Existing record was updated
+    Updated,
+    /// Record did not exist and was created
+    Created,
+}
+
+impl DdnsUpdateOutcome {
+    /// Note kept in last_error after a successful update (None clears it)
+    pub fn note(self) -> Option<&'static str> {
+        match self {
+            DdnsUpdateOutcome::Applied => None,
+            DdnsUpdateOutcome::Updated => Some("record updated"),
+            DdnsUpdateOutcome::Created => Some("record created"),
+        }
+    }
+}
+
+/// Successful provider update: what it did, plus provider-side ids it used
+/// (Cloudflare zone and record ids) for the updater to keep on the config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdnsUpdate {
+    pub outcome: DdnsUpdateOutcome,
+    pub zone_id: Option<String>,
+    pub record_id: Option<String>,
+    pub record_id_v6: Option<String>,
+}
+
+impl From<DdnsUpdateOutcome> for DdnsUpdate {
+    fn from(outcome: DdnsUpdateOutcome) -> Self {
+        Self {
+            outcome,
+            zone_id: None,
+            record_id: None,
+            record_id_v6: None,
+        }
+    }
+}
+
+impl DdnsUpdate {
+    /// Whether any id differs from what the config holds
+    pub fn changes_ids(&self, config: &DdnsConfig) -> bool {
+        let differs = |new: &Option<String>, old: &Option<String>| new.is_some() && new != old;
+        differs(&self.zone_id, &config.zone_id)
+            || differs(&self.record_id, &config.record_id)
+            || differs(&self.record_id_v6, &config.record_id_v6)
+    }
+}
+
+/// Outcome of a non-destructive credential check (POST /api/ddns/test)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DdnsTestResult {
+    pub provider: String,
+    pub hostname: String,
+    /// What the check did at the provider
+    pub method: String,
+    /// Credentials accepted and nothing else went wrong
+    pub ok: bool,
+    /// None when the check stopped before the credentials were exercised
+    pub auth_ok: Option<bool>,
+    pub record_found: Option<bool>,
+    /// Address the record holds now
+    pub remote_value: Option<String>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+impl DdnsTestResult {
+    pub fn new(provider: &dyn DdnsProviderTrait, config: &DdnsConfig, method: &str) -> Self {
+        Self {
+            provider: provider.name().to_string(),
+            hostname: config.hostname.clone(),
+            method: method.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Credentials worked; `remote_value` None means no record yet
+    pub fn passed(mut self, remote_value: Option<String>) -> Self {
+        self.ok = true;
+        self.auth_ok = Some(true);
+        self.record_found = Some(remote_value.is_some());
+        self.remote_value = remote_value;
+        self
+    }
+
+    pub fn failed(mut self, error: String) -> Self {
+        self.ok = false;
+        if is_auth_failure(&error) {
+            self.auth_ok = Some(false);
+        } else if error.starts_with(NO_HOST) {
+            // dyndns2 checks the hostname after the credentials
+            self.auth_ok = Some(true);
+            self.record_found = Some(false);
+        }
+        self.error = Some(error);
+        self
+    }
+}
+
 /// DDNS provider trait
 #[async_trait]
 pub trait DdnsProviderTrait: Send + Sync {
-    /// Update the DNS record with the current IP
-    async fn update(&self, config: &DdnsConfig, ip: &str) -> Result<(), String>;
+    /// Update the DNS record with the current IP, and the IPv6 record (or
+    /// field) with `ipv6` when given
+    async fn update(
+        &self,
+        config: &DdnsConfig,
+        ip: &str,
+        ipv6: Option<&str>,
+    ) -> Result<DdnsUpdate, String>;
+
+    /// Check credentials and the record without changing the record
+    async fn test(&self, config: &DdnsConfig) -> DdnsTestResult;
 
     /// Get the provider name
     fn name(&self) -> &'static str;
 }
 
-/// Get the current public IP address
+/// dyndns2 check: re-send the address the hostname resolves to now, which
+/// the provider answers with `nochg` and leaves the record as it is
+pub async fn test_with_unchanged_update(
+    provider: &dyn DdnsProviderTrait,
+    config: &DdnsConfig,
+) -> DdnsTestResult {
+    let result = DdnsTestResult::new(provider, config, "update with the current address");
+    let current = match tokio::net::lookup_host(format!("{}:0", config.hostname)).await {
+        Ok(mut addrs) => addrs.next().map(|addr| addr.ip().to_string()),
+        Err(_) => None,
+    };
+    let Some(current) = current else {
+        // Any address sent now would change the record
+        return DdnsTestResult {
+            record_found: Some(false),
+            ..result.failed(format!(
+                "{} does not resolve; credentials not checked to leave the record untouched",
+                config.hostname
+            ))
+        };
+    };
+    match provider.update(config, &current, None).await {
+        Ok(_) => result.passed(Some(current)),
+        Err(e) => result.failed(e),
+    }
+}
+
+/// Get the current public IPv4 address
 pub async fn get_public_ip() -> Result<String, String> {
-    let client = reqwest::Client::new();
+    // Binding to the v4 wildcard keeps dual-stack hosts off their v6 route
+    lookup_public_ip(
+        &[
+            "https://api.ipify.org",
+            "https://ifconfig.me/ip",
+            "https://icanhazip.com",
+        ],
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    )
+    .await
+    .ok_or_else(|| "Failed to get public IP from all services".to_string())
+}
+
+/// Get the current public IPv6 address (fails without IPv6 connectivity)
+pub async fn get_public_ipv6() -> Result<String, String> {
+    lookup_public_ip(
+        &[
+            "https://api6.ipify.org",
+            "https://api64.ipify.org",
+            "https://ipv6.icanhazip.com",
+        ],
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    )
+    .await
+    .ok_or_else(|| "Failed to get public IPv6 from all services".to_string())
+}
 
-    // Try multiple IP services
-    let services = [
-        "https://api.ipify.org",
-        "https://ifconfig.me/ip",
-        "https://icanhazip.com",
-    ];
+/// First address of the family of `local` any of the services reports
+async fn lookup_public_ip(services: &[&str], local: IpAddr) -> Option<String> {
+    let client = reqwest::Client::builder()
+        .local_address(local)
+        .build()
+        .ok()?;
 
-    for service in &services {
+    for service in services {
         match client
             .get(*service)
             .timeout(std::time::Duration::from_secs(10))
@@ -44,9 +223,8 @@ pub async fn get_public_ip() -> Result<String, String> {
                 if response.status().is_success() {
                     if let Ok(ip) = response.text().await {
                         let ip = ip.trim().to_string();
-                        // Basic IP validation
-                        if is_valid_ip(&ip) {
-                            return Ok(ip);
+                        if same_family(&ip, local) {
+                            return Some(ip);
                         }
                     }
                 }
@@ -55,16 +233,10 @@ pub async fn get_public_ip() -> Result<String, String> {
         }
     }
 
-    Err("Failed to get public IP from all services".to_string())
+    None
 }
 
-fn is_valid_ip(ip: &str) -> bool {
-    // Basic IPv4 validation
-    let parts: Vec<&str> = ip.split('.').collect();
-    if parts.len() == 4 {
-        return parts.iter().all(|p| p.parse::<u8>().is_ok());
-    }
-
-    // Basic IPv6 validation (contains colons)
-    ip.contains(':')
+fn same_family(ip: &str, local: IpAddr) -> bool {
+    ip.parse::<IpAddr>()
+        .is_ok_and(|ip| ip.is_ipv4() == local.is_ipv4())
 }
@@ -2,7 +2,10 @@
 
 use async_trait::async_trait;
 
-use super::DdnsProviderTrait;
+use super::{
+    test_with_unchanged_update, DdnsProviderTrait, DdnsTestResult, DdnsUpdate, DdnsUpdateOutcome,
+    AUTH_FAILED, NO_HOST,
+};
 use crate::models::DdnsConfig;
 
 pub struct NoIpProvider {
@@ -25,7 +28,12 @@ impl Default for NoIpProvider {
 
 #[async_trait]
 impl DdnsProviderTrait for NoIpProvider {
-    async fn update(&self, config: &DdnsConfig, ip: &str) -> Result<(), String> {
+    async fn update(
+        &self,
+        config: &DdnsConfig,
+        ip: &str,
+        ipv6: Option<&str>,
+    ) -> Result<DdnsUpdate, String> {
         let username = config
             .username
             .as_ref()
@@ -36,11 +44,14 @@ impl DdnsProviderTrait for NoIpProvider {
             .ok_or("Password required for No-IP")?;
 
         // No-IP update URL format
-        // https://dynupdate.no-ip.com/nic/update?hostname=<hostname>&myip=<ip>
-        let url = format!(
+        // https://dynupdate.no-ip.com/nic/update?hostname=<hostname>&myip=<ip>[&myipv6=<ipv6>]
+        let mut url = format!(
             "https://dynupdate.no-ip.com/nic/update?hostname={}&myip={}",
             config.hostname, ip
         );
+        if let Some(v6) = ipv6.filter(|v6| *v6 != ip) {
+            url.push_str(&format!("&myipv6={}", v6));
+        }
 
         let response = self
             .client
@@ -52,6 +63,9 @@ impl DdnsProviderTrait for NoIpProvider {
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
+        if matches!(response.status().as_u16(), 401 | 403) {
+            return Err(format!("{}: HTTP {}", AUTH_FAILED, response.status()));
+        }
         if !response.status().is_success() {
             return Err(format!("HTTP error: {}", response.status()));
         }
@@ -67,10 +81,10 @@ impl DdnsProviderTrait for NoIpProvider {
         match response_code {
             "good" | "nochg" => {
                 tracing::info!("No-IP update successful for {}: {}", config.hostname, body);
-                Ok(())
+                Ok(DdnsUpdateOutcome::Applied.into())
             }
-            "badauth" => Err("Bad authentication credentials".to_string()),
-            "nohost" => Err("Hostname does not exist".to_string()),
+            "badauth" => Err(format!("{}: bad credentials", AUTH_FAILED)),
+            "nohost" => Err(NO_HOST.to_string()),
             "badagent" => Err("Bad user agent - update client".to_string()),
             "abuse" => Err("Hostname has been blocked due to abuse".to_string()),
             "!donator" => Err("Feature not available for free accounts".to_string()),
@@ -79,6 +93,10 @@ impl DdnsProviderTrait for NoIpProvider {
         }
     }
 
+    async fn test(&self, config: &DdnsConfig) -> DdnsTestResult {
+        test_with_unchanged_update(self, config).await
+    }
+
     fn name(&self) -> &'static str {
         "No-IP"
     }
@@ -5,12 +5,23 @@ use std::time::Duration;
 
 use tokio::time::interval;
 
+use super::failover::{scheduled_configs, should_fail_over};
 use super::providers::{
-    get_public_ip, CloudflareProvider, DdnsProviderTrait, DynDnsProvider, NoIpProvider,
+    get_public_ip, get_public_ipv6, CloudflareProvider, DdnsProviderTrait, DdnsTestResult,
+    DdnsUpdate, DdnsUpdateOutcome, DynDnsProvider, NoIpProvider,
 };
+use crate::db::mongo::operation_logs::OperationLogDoc;
 use crate::db::AppState;
-use crate::models::{DdnsProvider, DdnsStatus};
+use crate::models::{DdnsConfig, DdnsIpSource, DdnsProvider, DdnsStatus};
 use crate::notify::DiscordNotifier;
+use crate::omada::OmadaManager;
+
+/// Result of pushing an address through one config's provider
+enum PushResult {
+    Unchanged,
+    Updated,
+    Failed(String),
+}
 
 /// DDNS updater that runs in the background
 pub struct DdnsUpdater {
@@ -19,19 +30,37 @@ pub struct DdnsUpdater {
     noip: NoIpProvider,
     cloudflare: CloudflareProvider,
     notifier: Arc<DiscordNotifier>,
+    omada_manager: Arc<OmadaManager>,
+    /// Replaces every provider in integration tests (see crate::testing)
+    #[cfg(test)]
+    stub_provider: Option<Arc<dyn DdnsProviderTrait>>,
 }
 
 impl DdnsUpdater {
-    pub fn new(app_state: AppState, notifier: Arc<DiscordNotifier>) -> Self {
+    pub fn new(
+        app_state: AppState,
+        notifier: Arc<DiscordNotifier>,
+        omada_manager: Arc<OmadaManager>,
+    ) -> Self {
         Self {
             app_state,
             dyndns: DynDnsProvider::new(),
             noip: NoIpProvider::new(),
             cloudflare: CloudflareProvider::new(),
             notifier,
+            omada_manager,
+            #[cfg(test)]
+            stub_provider: None,
         }
     }
 
+    /// Route all provider updates to `provider` instead of the real APIs
+    #[cfg(test)]
+    pub fn with_stub_provider(mut self, provider: Arc<dyn DdnsProviderTrait>) -> Self {
+        self.stub_provider = Some(provider);
+        self
+    }
+
     /// Start the DDNS update loop
     pub async fn start(self: Arc<Self>) {
         tracing::info!("Starting DDNS updater...");
@@ -48,98 +77,409 @@ impl DdnsUpdater {
         }
     }
 
-    /// Update all active DDNS configurations
+    /// Update all active DDNS configurations (secondaries only via failover)
     async fn update_all(&self) -> anyhow::Result<()> {
-        let configs = self.app_state.mysql.list_active_ddns().await?;
+        let configs = scheduled_configs(self.app_state.mysql.list_ddns().await?);
 
         if configs.is_empty() {
             return Ok(());
         }
 
-        // Get current public IP
-        let current_ip = match get_public_ip().await {
-            Ok(ip) => ip,
-            Err(e) => {
-                tracing::error!("Failed to get public IP: {}", e);
-                return Ok(());
+        // Public IP lookup only when some config still polls for it
+        let public_ip = if configs.iter().any(|c| c.ip_source == DdnsIpSource::Poll) {
+            match get_public_ip().await {
+                Ok(ip) => {
+                    // Record current public IP to ip_history (always, so history is complete)
+                    if let Err(e) = self.app_state.mongo.upsert_ip_history(&ip, "server").await {
+                        tracing::warn!("Failed to record server IP to history: {}", e);
+                    }
+                    Some(ip)
+                }
+                Err(e) => {
+                    tracing::error!("Failed to get public IP: {}", e);
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        // Record current public IP to ip_history (always, so history is complete)
-        if let Err(e) = self
-            .app_state
-            .mongo
-            .upsert_ip_history(&current_ip, "server")
+        // IPv6 comes from the lookup whatever the IPv4 source; a failed
+        // lookup leaves the IPv6 records as they are
+        let public_ipv6 = if configs.iter().any(|c| c.update_ipv6) {
+            match get_public_ipv6().await {
+                Ok(ip) => {
+                    if let Err(e) = self.app_state.mongo.upsert_ip_history(&ip, "server").await {
+                        tracing::warn!("Failed to record server IPv6 to history: {}", e);
+                    }
+                    Some(ip)
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to get public IPv6: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        for config in configs {
+            let current_ip = match config.ip_source {
+                DdnsIpSource::Poll => match &public_ip {
+                    Some(ip) => ip.clone(),
+                    None => continue,
+                },
+                DdnsIpSource::Router => match self.router_ip(&config).await {
+                    Ok(ip) => ip,
+                    Err(e) => {
+                        tracing::warn!("Router WAN IP unavailable for {}: {}", config.hostname, e);
+                        continue;
+                    }
+                },
+                // Pushed addresses are applied on report; retry here only
+                // when the last push did not reach the provider
+                DdnsIpSource::Webhook => match &config.reported_ip {
+                    Some(ip) => ip.clone(),
+                    None => continue,
+                },
+            };
+
+            self.apply_ip(&config, &current_ip, public_ipv6.as_deref())
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Push `current_ip` (and `ipv6` to configs that update it) through the
+    /// config, or through its secondary while failed over. Returns true when
+    /// a provider was updated.
+    async fn apply_ip(&self, config: &DdnsConfig, current_ip: &str, ipv6: Option<&str>) -> bool {
+        let secondary = self.secondary_for(config).await;
+        let Some(secondary) = secondary else {
+            if config.failover_active {
+                // Secondary was deleted or disabled mid-failover
+                let _ = self
+                    .app_state
+                    .mysql
+                    .set_ddns_failover_active(config.id, false)
+                    .await;
+            }
+            return matches!(
+                self.push_ip(config, current_ip, ipv6, false, false).await,
+                PushResult::Updated
+            );
+        };
+
+        // While failed over the primary is retried every cycle to detect recovery
+        let failed_over = config.failover_active;
+        match self
+            .push_ip(config, current_ip, ipv6, failed_over, failed_over)
             .await
         {
-            tracing::warn!("Failed to record server IP to history: {}", e);
+            PushResult::Updated => {
+                if failed_over {
+                    self.fail_back(config, &secondary).await;
+                }
+                true
+            }
+            PushResult::Unchanged => false,
+            PushResult::Failed(e) => {
+                if should_fail_over(config) {
+                    self.fail_over(config, &secondary, &e).await;
+                } else if !failed_over {
+                    return false;
+                }
+                matches!(
+                    self.push_ip(&secondary, current_ip, ipv6, false, false)
+                        .await,
+                    PushResult::Updated
+                )
+            }
         }
+    }
 
-        for config in configs {
-            // Check if IP has changed
-            if config.last_ip.as_ref() == Some(&current_ip) {
-                tracing::debug!("IP unchanged for {}, skipping", config.hostname);
-                continue;
-            }
+    /// Push `current_ip` (plus `ipv6` when the config updates it) to the
+    /// config's provider if either differs from the last update (`force`
+    /// retries anyway). `quiet` skips the failure notification.
+    async fn push_ip(
+        &self,
+        config: &DdnsConfig,
+        current_ip: &str,
+        ipv6: Option<&str>,
+        force: bool,
+        quiet: bool,
+    ) -> PushResult {
+        let ipv6 = ipv6.filter(|_| config.update_ipv6);
 
-            // Get appropriate provider
-            let provider: &dyn DdnsProviderTrait = match config.provider {
-                DdnsProvider::DynDns => &self.dyndns,
-                DdnsProvider::NoIp => &self.noip,
-                DdnsProvider::Cloudflare => &self.cloudflare,
-            };
+        // Check if either IP has changed
+        let ipv6_changed = ipv6.is_some_and(|v6| config.last_ipv6.as_deref() != Some(v6));
+        if !force && config.last_ip.as_deref() == Some(current_ip) && !ipv6_changed {
+            tracing::debug!("IP unchanged for {}, skipping", config.hostname);
+            return PushResult::Unchanged;
+        }
+
+        // Get appropriate provider
+        let provider = self.provider_for(config);
 
+        tracing::info!(
+            "Updating DDNS for {} via {} ({}): {} -> {}",
+            config.hostname,
+            provider.name(),
+            config.ip_source,
+            config.last_ip.as_deref().unwrap_or("unknown"),
+            current_ip
+        );
+        if let Some(v6) = ipv6 {
             tracing::info!(
-                "Updating DDNS for {} via {}: {} -> {}",
+                "Updating DDNS IPv6 for {}: {} -> {}",
                 config.hostname,
-                provider.name(),
-                config.last_ip.as_deref().unwrap_or("unknown"),
-                current_ip
+                config.last_ipv6.as_deref().unwrap_or("unknown"),
+                v6
             );
+        }
 
-            match provider.update(&config, &current_ip).await {
-                Ok(()) => {
-                    // Update database with new IP
-                    if let Err(e) = self
-                        .app_state
-                        .mysql
-                        .update_ddns_ip(config.id, &current_ip, DdnsStatus::Active, None)
-                        .await
-                    {
-                        tracing::error!("Failed to update DDNS status in DB: {}", e);
-                    }
+        match provider.update(config, current_ip, ipv6).await {
+            Ok(update) => {
+                // Update database with new IP (plus what the provider did)
+                if let Err(e) = self
+                    .app_state
+                    .mysql
+                    .update_ddns_ip(
+                        config.id,
+                        current_ip,
+                        ipv6,
+                        DdnsStatus::Active,
+                        update.outcome.note(),
+                    )
+                    .await
+                {
+                    tracing::error!("Failed to update DDNS status in DB: {}", e);
                 }
-                Err(e) => {
-                    tracing::error!("DDNS update failed for {}: {}", config.hostname, e);
+                self.remember_ids(config, &update).await;
+                PushResult::Updated
+            }
+            Err(e) => {
+                tracing::error!("DDNS update failed for {}: {}", config.hostname, e);
 
-                    // Update database with error
-                    if let Err(db_err) = self.app_state.mysql.set_ddns_error(config.id, &e).await {
-                        tracing::error!("Failed to update DDNS error in DB: {}", db_err);
-                    }
+                // Update database with error
+                if let Err(db_err) = self.app_state.mysql.set_ddns_error(config.id, &e).await {
+                    tracing::error!("Failed to update DDNS error in DB: {}", db_err);
+                }
 
-                    // Log security event
-                    if let Err(log_err) = self
-                        .app_state
-                        .mongo
-                        .log_ddns_failure(&config.hostname, &config.provider.to_string(), &e)
-                        .await
-                    {
-                        tracing::error!("Failed to log DDNS failure: {}", log_err);
-                    }
+                // Log security event
+                if let Err(log_err) = self
+                    .app_state
+                    .mongo
+                    .log_ddns_failure(&config.hostname, &config.provider.to_string(), &e)
+                    .await
+                {
+                    tracing::error!("Failed to log DDNS failure: {}", log_err);
+                }
 
-                    // Send Discord notification
+                // Send Discord notification
+                if !quiet {
                     self.notifier
                         .notify_ddns_failure(&config.hostname, &config.provider.to_string(), &e)
                         .await;
                 }
+                PushResult::Failed(e)
+            }
+        }
+    }
+
+    /// Linked secondary, unless missing or disabled
+    async fn secondary_for(&self, config: &DdnsConfig) -> Option<DdnsConfig> {
+        let id = config.secondary_config_id?;
+        match self.app_state.mysql.get_ddns(id).await {
+            Ok(Some(secondary)) if secondary.status != DdnsStatus::Disabled => Some(secondary),
+            Ok(_) => {
+                tracing::warn!(
+                    "DDNS secondary {} for {} is missing or disabled",
+                    id,
+                    config.hostname
+                );
+                None
+            }
+            Err(e) => {
+                tracing::error!("Failed to load DDNS secondary {}: {}", id, e);
+                None
             }
         }
+    }
 
-        Ok(())
+    async fn fail_over(&self, config: &DdnsConfig, secondary: &DdnsConfig, error: &str) {
+        tracing::warn!(
+            "[DDNS] {} failed {} time(s) via {}, failing over to {} ({})",
+            config.hostname,
+            config.consecutive_failures + 1,
+            config.provider,
+            secondary.provider,
+            secondary.hostname
+        );
+        self.record_transition(config, secondary, true, error).await;
+    }
+
+    async fn fail_back(&self, config: &DdnsConfig, secondary: &DdnsConfig) {
+        tracing::info!(
+            "[DDNS] {} recovered via {}, switching back from {}",
+            config.hostname,
+            config.provider,
+            secondary.provider
+        );
+        self.record_transition(config, secondary, false, "primary update succeeded")
+            .await;
+    }
+
+    /// Persist the failover flag, add a `ddns_failover` operation log entry
+    /// and notify
+    async fn record_transition(
+        &self,
+        config: &DdnsConfig,
+        secondary: &DdnsConfig,
+        failed_over: bool,
+        detail: &str,
+    ) {
+        if let Err(e) = self
+            .app_state
+            .mysql
+            .set_ddns_failover_active(config.id, failed_over)
+            .await
+        {
+            tracing::error!("Failed to update DDNS failover state in DB: {}", e);
+        }
+
+        let primary = format!("{} ({})", config.provider, config.hostname);
+        let standby = format!("{} ({})", secondary.provider, secondary.hostname);
+        let (from, to) = if failed_over {
+            (primary, standby)
+        } else {
+            (standby, primary)
+        };
+
+        let _ = self
+            .app_state
+            .mongo
+            .insert_operation_log(&OperationLogDoc {
+                operation_id: uuid::Uuid::new_v4().to_string(),
+                operation_type: "ddns_failover".to_string(),
+                initiated_by: "scheduler".to_string(),
+                target: Some(config.hostname.clone()),
+                status: "success".to_string(),
+                result: Some(serde_json::json!({
+                    "config_id": config.id,
+                    "secondary_config_id": secondary.id,
+                    "transition": if failed_over { "failover" } else { "failback" },
+                    "from": &from,
+                    "to": &to,
+                    "consecutive_failures": config.consecutive_failures + i32::from(failed_over),
+                    "detail": detail,
+                })),
+                error: None,
+                duration_ms: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                operator: None,
+            })
+            .await;
+
+        self.notifier
+            .notify_ddns_failover(&config.hostname, &from, &to, failed_over, detail)
+            .await;
+    }
+
+    fn provider_for(&self, config: &DdnsConfig) -> &dyn DdnsProviderTrait {
+        #[cfg(test)]
+        if let Some(stub) = &self.stub_provider {
+            return stub.as_ref();
+        }
+        match config.provider {
+            DdnsProvider::DynDns => &self.dyndns,
+            DdnsProvider::NoIp => &self.noip,
+            DdnsProvider::Cloudflare => &self.cloudflare,
+        }
+    }
+
+    /// WAN IP from the linked OpenWrt router (last sync) or Omada gateway
+    async fn router_ip(&self, config: &DdnsConfig) -> Result<String, String> {
+        if let Some(router_id) = &config.openwrt_router_id {
+            let router = self
+                .app_state
+                .mongo
+                .get_openwrt_router(router_id)
+                .await?
+                .ok_or_else(|| format!("OpenWrt router {} not found", router_id))?;
+            return router
+                .wan_ip
+                .filter(|ip| !ip.is_empty())
+                .ok_or_else(|| format!("OpenWrt router {} has no WAN IP yet", router_id));
+        }
+
+        let ctrl_id = config
+            .omada_controller_id
+            .as_deref()
+            .ok_or("No OpenWrt router or Omada controller linked")?;
+        let client = self
+            .omada_manager
+            .get_client(ctrl_id)
+            .await
+            .ok_or_else(|| format!("Omada controller {} not loaded", ctrl_id))?;
+        client
+            .get_gateway_wan_status()
+            .await?
+            .ok_or_else(|| format!("Omada controller {} reported no WAN IP", ctrl_id))
+    }
+
+    /// Current IP for a config according to its source
+    async fn current_ip(&self, config: &DdnsConfig) -> Result<String, String> {
+        match config.ip_source {
+            DdnsIpSource::Poll => get_public_ip().await,
+            DdnsIpSource::Router => self.router_ip(config).await,
+            DdnsIpSource::Webhook => config
+                .reported_ip
+                .clone()
+                .ok_or_else(|| "No IP has been reported yet".to_string()),
+        }
+    }
+
+    /// Webhook source: store the pushed address and update the provider
+    /// immediately when it changed. Returns true when the provider was updated.
+    pub async fn report_ip(&self, config: &DdnsConfig, ip: &str) -> Result<bool, String> {
+        self.app_state
+            .mysql
+            .set_ddns_reported_ip(config.id, ip)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if config.status == DdnsStatus::Disabled {
+            return Ok(false);
+        }
+        // IPv6 records follow the scheduler's lookup
+        Ok(self.apply_ip(config, ip, None).await)
+    }
+
+    /// Check a config's credentials against its provider without changing
+    /// the record or anything stored (POST /api/ddns/test)
+    pub async fn test_config(&self, config: &DdnsConfig) -> DdnsTestResult {
+        let started = std::time::Instant::now();
+        let mut result = self.provider_for(config).test(config).await;
+        result.latency_ms = started.elapsed().as_millis() as u64;
+        tracing::info!(
+            "DDNS credential test for {} via {}: ok={} ({}ms){}",
+            config.hostname,
+            result.provider,
+            result.ok,
+            result.latency_ms,
+            result
+                .error
+                .as_deref()
+                .map(|e| format!(": {}", e))
+                .unwrap_or_default()
+        );
+        result
     }
 
     /// Manually trigger update for a specific DDNS config
-    pub async fn update_single(&self, config_id: i32) -> Result<(), String> {
+    pub async fn update_single(&self, config_id: i32) -> Result<DdnsUpdateOutcome, String> {
         let config = self
             .app_state
             .mysql
@@ -148,22 +488,60 @@ impl DdnsUpdater {
             .map_err(|e| e.to_string())?
             .ok_or_else(|| format!("DDNS config {} not found", config_id))?;
 
-        let current_ip = get_public_ip().await?;
-
-        let provider: &dyn DdnsProviderTrait = match config.provider {
-            DdnsProvider::DynDns => &self.dyndns,
-            DdnsProvider::NoIp => &self.noip,
-            DdnsProvider::Cloudflare => &self.cloudflare,
+        let current_ip = self.current_ip(&config).await?;
+        let current_ipv6 = if config.update_ipv6 {
+            get_public_ipv6()
+                .await
+                .map_err(|e| tracing::warn!("IPv6 skipped for {}: {}", config.hostname, e))
+                .ok()
+        } else {
+            None
         };
 
-        provider.update(&config, &current_ip).await?;
+        let update = self
+            .provider_for(&config)
+            .update(&config, &current_ip, current_ipv6.as_deref())
+            .await?;
 
         self.app_state
             .mysql
-            .update_ddns_ip(config.id, &current_ip, DdnsStatus::Active, None)
+            .update_ddns_ip(
+                config.id,
+                &current_ip,
+                current_ipv6.as_deref(),
+                DdnsStatus::Active,
+                update.outcome.note(),
+            )
             .await
             .map_err(|e| e.to_string())?;
+        self.remember_ids(&config, &update).await;
 
-        Ok(())
+        if config.failover_active {
+            if let Some(secondary) = self.secondary_for(&config).await {
+                self.fail_back(&config, &secondary).await;
+            }
+        }
+
+        Ok(update.outcome)
+    }
+
+    /// Store zone and record ids the provider discovered or recreated
+    async fn remember_ids(&self, config: &DdnsConfig, update: &DdnsUpdate) {
+        if !update.changes_ids(config) {
+            return;
+        }
+        if let Err(e) = self
+            .app_state
+            .mysql
+            .set_ddns_provider_ids(
+                config.id,
+                update.zone_id.as_deref(),
+                update.record_id.as_deref(),
+                update.record_id_v6.as_deref(),
+            )
+            .await
+        {
+            tracing::error!("Failed to store DDNS provider ids in DB: {}", e);
+        }
     }
 }
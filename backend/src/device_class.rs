@@ -0,0 +1,442 @@
+//! Device classification for topology clients
+//!
+//! Ingestion gives every client in user_object_detail a `device_class`
+//! (phone, laptop, printer, camera, iot, server or unknown) so the topology
+//! can pick icons and inventories can be filtered by kind of device.
+//!
+//! Classes come from a rules table tried in order; the first match wins:
+//!
+//! - `field`: what the pattern is matched against — the Omada fingerprint
+//!   (`device_category`, `device_type`, `os_name`), the OUI `vendor` name,
+//!   the MAC prefix (`oui`) or the `hostname` (client name included)
+//! - `pattern`: case-insensitive glob (`*` any run, `?` any character),
+//!   anchored at both ends; `oui` patterns are MAC prefixes in any format
+//!
+//! The built-in table applies unless the `device_class_rules` setting holds
+//! a replacement (`{"rules": [...]}`). Clients are reclassified on every
+//! ingest cycle, so edited rules take effect with the next sync.
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::mysql::MySqlDb;
+use crate::omada::client::normalize_mac;
+
+pub const SETTING_DEVICE_CLASS_RULES: &str = "device_class_rules";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceClass {
+    Phone,
+    Laptop,
+    Printer,
+    Camera,
+    Iot,
+    Server,
+    Unknown,
+}
+
+impl DeviceClass {
+    pub const ALL: [DeviceClass; 7] = [
+        DeviceClass::Phone,
+        DeviceClass::Laptop,
+        DeviceClass::Printer,
+        DeviceClass::Camera,
+        DeviceClass::Iot,
+        DeviceClass::Server,
+        DeviceClass::Unknown,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceClass::Phone => "phone",
+            DeviceClass::Laptop => "laptop",
+            DeviceClass::Printer => "printer",
+            DeviceClass::Camera => "camera",
+            DeviceClass::Iot => "iot",
+            DeviceClass::Server => "server",
+            DeviceClass::Unknown => "unknown",
+        }
+    }
+}
+
+/// Input a rule can look at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    DeviceCategory,
+    DeviceType,
+    OsName,
+    Vendor,
+    Oui,
+    Hostname,
+}
+
+/// What is known about a client; absent fields never match
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fingerprint<'a> {
+    pub mac: &'a str,
+    pub device_category: Option<&'a str>,
+    pub device_type: Option<&'a str>,
+    pub os_name: Option<&'a str>,
+    pub vendor: Option<&'a str>,
+    pub hostname: Option<&'a str>,
+    /// Client name as reported by the source (matched as a hostname)
+    pub name: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceClassRule {
+    pub field: RuleField,
+    pub pattern: String,
+    pub class: DeviceClass,
+}
+
+impl DeviceClassRule {
+    fn new(field: RuleField, pattern: &str, class: DeviceClass) -> Self {
+        Self {
+            field,
+            pattern: pattern.to_string(),
+            class,
+        }
+    }
+
+    fn matches(&self, input: &Fingerprint) -> bool {
+        let text = |value: Option<&str>| value.is_some_and(|v| glob_match(&self.pattern, v));
+        match self.field {
+            RuleField::DeviceCategory => text(input.device_category),
+            RuleField::DeviceType => text(input.device_type),
+            RuleField::OsName => text(input.os_name),
+            RuleField::Vendor => text(input.vendor),
+            RuleField::Oui => normalize_mac(input.mac).starts_with(&normalize_mac(&self.pattern)),
+            RuleField::Hostname => text(input.hostname) || text(input.name),
+        }
+    }
+}
+
+/// The `device_class_rules` setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceClassRules {
+    pub rules: Vec<DeviceClassRule>,
+}
+
+impl Default for DeviceClassRules {
+    fn default() -> Self {
+        use DeviceClass::*;
+        use RuleField::*;
+        let rule = DeviceClassRule::new;
+        Self {
+            rules: vec![
+                // Omada fingerprint (most specific first)
+                rule(DeviceCategory, "*printer*", Printer),
+                rule(DeviceCategory, "*camera*", Camera),
+                rule(DeviceCategory, "*surveillance*", Camera),
+                rule(DeviceCategory, "*phone*", Phone),
+                rule(DeviceCategory, "*tablet*", Phone),
+                rule(DeviceCategory, "*server*", Server),
+                rule(DeviceCategory, "*nas*", Server),
+                rule(DeviceCategory, "*storage*", Server),
+                rule(DeviceCategory, "*computer*", Laptop),
+                rule(DeviceCategory, "*laptop*", Laptop),
+                rule(DeviceCategory, "*smart*", Iot),
+                rule(DeviceCategory, "*iot*", Iot),
+                rule(DeviceCategory, "*home*", Iot),
+                rule(DeviceCategory, "*appliance*", Iot),
+                rule(DeviceCategory, "*tv*", Iot),
+                rule(DeviceType, "iphone*", Phone),
+                rule(DeviceType, "ipad*", Phone),
+                rule(DeviceType, "*android*", Phone),
+                rule(DeviceType, "*phone*", Phone),
+                rule(DeviceType, "macbook*", Laptop),
+                rule(DeviceType, "*laptop*", Laptop),
+                rule(DeviceType, "*printer*", Printer),
+                rule(DeviceType, "*camera*", Camera),
+                rule(OsName, "ios*", Phone),
+                rule(OsName, "ipados*", Phone),
+                rule(OsName, "android*", Phone),
+                rule(OsName, "windows*", Laptop),
+                rule(OsName, "mac*os*", Laptop),
+                rule(OsName, "chrome*os*", Laptop),
+                // Virtual NICs and single-board computers by OUI
+                rule(Oui, "000C29", Server),
+                rule(Oui, "005056", Server),
+                rule(Oui, "00155D", Server),
+                rule(Oui, "525400", Server),
+                rule(Oui, "B827EB", Iot),
+                rule(Oui, "DCA632", Iot),
+                rule(Oui, "E45F01", Iot),
+                // OUI vendor names
+                rule(Vendor, "*hikvision*", Camera),
+                rule(Vendor, "*dahua*", Camera),
+                rule(Vendor, "*axis comm*", Camera),
+                rule(Vendor, "*brother*", Printer),
+                rule(Vendor, "*seiko epson*", Printer),
+                rule(Vendor, "*canon*", Printer),
+                rule(Vendor, "*kyocera*", Printer),
+                rule(Vendor, "*ricoh*", Printer),
+                rule(Vendor, "*xerox*", Printer),
+                rule(Vendor, "*synology*", Server),
+                rule(Vendor, "*qnap*", Server),
+                rule(Vendor, "*super micro*", Server),
+                rule(Vendor, "*espressif*", Iot),
+                rule(Vendor, "*tuya*", Iot),
+                rule(Vendor, "*shelly*", Iot),
+                rule(Vendor, "*sonos*", Iot),
+                rule(Vendor, "*nest labs*", Iot),
+                // Hostname conventions
+                rule(Hostname, "*iphone*", Phone),
+                rule(Hostname, "*ipad*", Phone),
+                rule(Hostname, "android-*", Phone),
+                rule(Hostname, "galaxy*", Phone),
+                rule(Hostname, "pixel-*", Phone),
+                rule(Hostname, "*macbook*", Laptop),
+                rule(Hostname, "*laptop*", Laptop),
+                rule(Hostname, "desktop-*", Laptop),
+                rule(Hostname, "*printer*", Printer),
+                rule(Hostname, "npi*", Printer),
+                rule(Hostname, "brw*", Printer),
+                rule(Hostname, "epson*", Printer),
+                rule(Hostname, "ipc*", Camera),
+                rule(Hostname, "*camera*", Camera),
+                rule(Hostname, "*nas*", Server),
+                rule(Hostname, "*server*", Server),
+                rule(Hostname, "srv*", Server),
+                rule(Hostname, "esp_*", Iot),
+                rule(Hostname, "esp-*", Iot),
+                rule(Hostname, "shelly*", Iot),
+                rule(Hostname, "tasmota*", Iot),
+            ],
+        }
+    }
+}
+
+impl DeviceClassRules {
+    /// Load the rules; a missing, invalid or unreadable setting falls back to
+    /// the built-in table so ingestion never stops over classification
+    pub async fn load(mysql: &MySqlDb) -> Self {
+        let raw = match mysql.get_setting(SETTING_DEVICE_CLASS_RULES).await {
+            Ok(raw) => raw,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to read {} setting: {}",
+                    SETTING_DEVICE_CLASS_RULES,
+                    e
+                );
+                None
+            }
+        };
+        match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(raw) => Self::parse(raw).unwrap_or_else(|e| {
+                tracing::warn!(
+                    "Invalid {} setting, using built-in rules: {}",
+                    SETTING_DEVICE_CLASS_RULES,
+                    e
+                );
+                Self::default()
+            }),
+            None => Self::default(),
+        }
+    }
+
+    /// Parse and validate a rules JSON document (OUI prefixes are normalized)
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut rules: Self = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+        for (i, rule) in rules.rules.iter_mut().enumerate() {
+            rule.pattern = rule.pattern.trim().to_string();
+            if rule.pattern.is_empty() {
+                return Err(format!("rule {}: empty pattern", i + 1));
+            }
+            if rule.field == RuleField::Oui {
+                let prefix = normalize_mac(&rule.pattern);
+                if prefix.len() < 2
+                    || prefix.len() > 12
+                    || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+                {
+                    return Err(format!(
+                        "rule {}: invalid OUI prefix {:?}",
+                        i + 1,
+                        rule.pattern
+                    ));
+                }
+                rule.pattern = prefix;
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn classify(&self, input: &Fingerprint) -> DeviceClass {
+        self.rules
+            .iter()
+            .find(|r| r.matches(input))
+            .map(|r| r.class)
+            .unwrap_or(DeviceClass::Unknown)
+    }
+}
+
+/// Case-insensitive glob match (`*` any run, `?` any character)
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.trim().to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Last `*` seen and the text position it is currently absorbing up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(input: Fingerprint) -> DeviceClass {
+        DeviceClassRules::default().classify(&input)
+    }
+
+    #[test]
+    fn glob_is_anchored_and_case_insensitive() {
+        assert!(glob_match("iphone*", "iPhone 15 Pro"));
+        assert!(glob_match("*nas*", "office-NAS-01"));
+        assert!(glob_match("npi??????", "NPI1A2B3C"));
+        assert!(!glob_match("iphone*", "my-iphone"));
+        assert!(!glob_match("npi??????", "NPI1A2B3"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn omada_fingerprints() {
+        fn omada<'a>(category: &'a str, device_type: &'a str, os: &'a str) -> Fingerprint<'a> {
+            Fingerprint {
+                mac: "AABBCCDDEEFF",
+                device_category: Some(category),
+                device_type: Some(device_type),
+                os_name: Some(os),
+                ..Default::default()
+            }
+        }
+        assert_eq!(
+            classify(omada("Mobile Phone", "iPhone", "iOS 17")),
+            DeviceClass::Phone
+        );
+        assert_eq!(
+            classify(omada("Computer", "MacBook Pro", "macOS")),
+            DeviceClass::Laptop
+        );
+        assert_eq!(
+            classify(omada("Printer", "Printer", "")),
+            DeviceClass::Printer
+        );
+        assert_eq!(
+            classify(omada("Surveillance", "IP Camera", "Linux")),
+            DeviceClass::Camera
+        );
+        assert_eq!(
+            classify(omada("Smart Home", "Smart Plug", "")),
+            DeviceClass::Iot
+        );
+        // Category unknown to the table: the OS decides
+        assert_eq!(
+            classify(omada("Other", "Unknown", "Android 14")),
+            DeviceClass::Phone
+        );
+    }
+
+    #[test]
+    fn other_sources_fall_back_to_oui_vendor_and_hostname() {
+        // Raspberry Pi by OUI, any MAC format
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "b8:27:eb:12:34:56",
+                ..Default::default()
+            }),
+            DeviceClass::Iot
+        );
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "001122334455",
+                vendor: Some("Hangzhou Hikvision Digital Technology"),
+                ..Default::default()
+            }),
+            DeviceClass::Camera
+        );
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "001122334455",
+                hostname: Some("DESKTOP-7K2QF9L"),
+                ..Default::default()
+            }),
+            DeviceClass::Laptop
+        );
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "001122334455",
+                hostname: Some("BRW0080927AFBEA"),
+                ..Default::default()
+            }),
+            DeviceClass::Printer
+        );
+        // A user-set client name counts as a hostname
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "001122334455",
+                name: Some("Lobby Camera"),
+                ..Default::default()
+            }),
+            DeviceClass::Camera
+        );
+        assert_eq!(
+            classify(Fingerprint {
+                mac: "001122334455",
+                hostname: Some("workstation"),
+                ..Default::default()
+            }),
+            DeviceClass::Unknown
+        );
+    }
+
+    #[test]
+    fn custom_rules_replace_the_table_in_order() {
+        let rules = DeviceClassRules::parse(
+            r#"{"rules": [
+                {"field": "oui", "pattern": "aa-bb-cc", "class": "camera"},
+                {"field": "hostname", "pattern": "*", "class": "laptop"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(rules.rules[0].pattern, "AABBCC");
+        let mac = |mac| Fingerprint {
+            mac,
+            hostname: Some("iphone"),
+            ..Default::default()
+        };
+        assert_eq!(rules.classify(&mac("AABBCC000001")), DeviceClass::Camera);
+        assert_eq!(rules.classify(&mac("001122334455")), DeviceClass::Laptop);
+
+        assert!(DeviceClassRules::parse(
+            r#"{"rules": [{"field": "oui", "pattern": "XYZ", "class": "iot"}]}"#
+        )
+        .is_err());
+        assert!(DeviceClassRules::parse(
+            r#"{"rules": [{"field": "hostname", "pattern": "a", "class": "tablet"}]}"#
+        )
+        .is_err());
+        assert!(DeviceClassRules::parse(
+            r#"{"rules": [{"field": "ssid", "pattern": "a", "class": "iot"}]}"#
+        )
+        .is_err());
+    }
+}
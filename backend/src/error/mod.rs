@@ -1,12 +1,257 @@
 //! Error handling module
+//!
+//! Every error response carries a stable machine-readable code next to the
+//! human-readable message (which may be reworded at any time):
+//!
+//! ```json
+//! {
+//!   "error": "Route 12 not found",
+//!   "code": "ROUTE_NOT_FOUND",
+//!   "status": 404,
+//!   "details": { "fields": [{ "field": "path", "message": "must start with /" }] }
+//! }
+//! ```
+//!
+//! - `error` (string): message for people
+//! - `code` (string): one of [`ErrorCode::ALL`]; clients branch on this
+//! - `status` (number): the HTTP status, repeated
+//! - `details` (object, optional): code-specific data; `VALIDATION_FAILED`
+//!   carries `fields`, a list of `{field, message}`
+//!
+//! Codes are only ever added, never renamed or reused. The list is also
+//! served to agents in `GET /api/agent/context` (`system.error_codes`).
 
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use thiserror::Error;
 
+/// Stable machine-readable error codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // Generic codes of the plain variants
+    NotFound,
+    BadRequest,
+    ValidationFailed,
+    Unauthorized,
+    Forbidden,
+    InternalError,
+    DatabaseError,
+    ConfigError,
+    UpstreamError,
+    // Specific codes
+    RouteNotFound,
+    RouteDeleted,
+    DdnsConfigNotFound,
+    DdnsProviderAuthFailed,
+    DdnsUpdateFailed,
+    NodeNotFound,
+    ControllerNotFound,
+    OmadaTokenExpired,
+    OmadaUnavailable,
+    WireguardProfileNotFound,
+    WireguardPeerNotFound,
+    AlertRuleNotFound,
+    SettingNotFound,
+    DnsOverrideNotFound,
+    RateLimitRuleNotFound,
+    RegistrationTokenNotFound,
+    RegistrationTokenInvalid,
+    RegistrationTokenScope,
+    RateLimited,
+    MaintenanceWindowNotFound,
+    LayoutGenerationConflict,
+    TopologyShareNotFound,
+    RouteVersionNotFound,
+    NginxConfigStale,
+    AcmeOrderInProgress,
+}
+
+impl ErrorCode {
+    /// Every code, in documentation order
+    pub const ALL: &'static [ErrorCode] = &[
+        Self::NotFound,
+        Self::BadRequest,
+        Self::ValidationFailed,
+        Self::Unauthorized,
+        Self::Forbidden,
+        Self::InternalError,
+        Self::DatabaseError,
+        Self::ConfigError,
+        Self::UpstreamError,
+        Self::RouteNotFound,
+        Self::RouteDeleted,
+        Self::DdnsConfigNotFound,
+        Self::DdnsProviderAuthFailed,
+        Self::DdnsUpdateFailed,
+        Self::NodeNotFound,
+        Self::ControllerNotFound,
+        Self::OmadaTokenExpired,
+        Self::OmadaUnavailable,
+        Self::WireguardProfileNotFound,
+        Self::WireguardPeerNotFound,
+        Self::AlertRuleNotFound,
+        Self::SettingNotFound,
+        Self::DnsOverrideNotFound,
+        Self::RateLimitRuleNotFound,
+        Self::RegistrationTokenNotFound,
+        Self::RegistrationTokenInvalid,
+        Self::RegistrationTokenScope,
+        Self::RateLimited,
+        Self::MaintenanceWindowNotFound,
+        Self::LayoutGenerationConflict,
+        Self::TopologyShareNotFound,
+        Self::RouteVersionNotFound,
+        Self::NginxConfigStale,
+        Self::AcmeOrderInProgress,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::BadRequest => "BAD_REQUEST",
+            Self::ValidationFailed => "VALIDATION_FAILED",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::Forbidden => "FORBIDDEN",
+            Self::InternalError => "INTERNAL_ERROR",
+            Self::DatabaseError => "DATABASE_ERROR",
+            Self::ConfigError => "CONFIG_ERROR",
+            Self::UpstreamError => "UPSTREAM_ERROR",
+            Self::RouteNotFound => "ROUTE_NOT_FOUND",
+            Self::RouteDeleted => "ROUTE_DELETED",
+            Self::DdnsConfigNotFound => "DDNS_CONFIG_NOT_FOUND",
+            Self::DdnsProviderAuthFailed => "DDNS_PROVIDER_AUTH_FAILED",
+            Self::DdnsUpdateFailed => "DDNS_UPDATE_FAILED",
+            Self::NodeNotFound => "NODE_NOT_FOUND",
+            Self::ControllerNotFound => "CONTROLLER_NOT_FOUND",
+            Self::OmadaTokenExpired => "OMADA_TOKEN_EXPIRED",
+            Self::OmadaUnavailable => "OMADA_UNAVAILABLE",
+            Self::WireguardProfileNotFound => "WIREGUARD_PROFILE_NOT_FOUND",
+            Self::WireguardPeerNotFound => "WIREGUARD_PEER_NOT_FOUND",
+            Self::AlertRuleNotFound => "ALERT_RULE_NOT_FOUND",
+            Self::SettingNotFound => "SETTING_NOT_FOUND",
+            Self::DnsOverrideNotFound => "DNS_OVERRIDE_NOT_FOUND",
+            Self::RateLimitRuleNotFound => "RATE_LIMIT_RULE_NOT_FOUND",
+            Self::RegistrationTokenNotFound => "REGISTRATION_TOKEN_NOT_FOUND",
+            Self::RegistrationTokenInvalid => "REGISTRATION_TOKEN_INVALID",
+            Self::RegistrationTokenScope => "REGISTRATION_TOKEN_SCOPE",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::MaintenanceWindowNotFound => "MAINTENANCE_WINDOW_NOT_FOUND",
+            Self::LayoutGenerationConflict => "LAYOUT_GENERATION_CONFLICT",
+            Self::TopologyShareNotFound => "TOPOLOGY_SHARE_NOT_FOUND",
+            Self::RouteVersionNotFound => "ROUTE_VERSION_NOT_FOUND",
+            Self::NginxConfigStale => "NGINX_CONFIG_STALE",
+            Self::AcmeOrderInProgress => "ACME_ORDER_IN_PROGRESS",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound
+            | Self::RouteNotFound
+            | Self::DdnsConfigNotFound
+            | Self::NodeNotFound
+            | Self::ControllerNotFound
+            | Self::WireguardProfileNotFound
+            | Self::WireguardPeerNotFound
+            | Self::AlertRuleNotFound
+            | Self::SettingNotFound
+            | Self::DnsOverrideNotFound
+            | Self::RateLimitRuleNotFound
+            | Self::RegistrationTokenNotFound
+            | Self::MaintenanceWindowNotFound
+            | Self::TopologyShareNotFound
+            | Self::RouteVersionNotFound => StatusCode::NOT_FOUND,
+            Self::BadRequest | Self::ValidationFailed | Self::RouteDeleted => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::Unauthorized | Self::RegistrationTokenInvalid => StatusCode::UNAUTHORIZED,
+            Self::Forbidden | Self::RegistrationTokenScope => StatusCode::FORBIDDEN,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::LayoutGenerationConflict | Self::NginxConfigStale | Self::AcmeOrderInProgress => {
+                StatusCode::CONFLICT
+            }
+            Self::InternalError | Self::DatabaseError | Self::ConfigError => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Self::UpstreamError
+            | Self::DdnsProviderAuthFailed
+            | Self::DdnsUpdateFailed
+            | Self::OmadaTokenExpired
+            | Self::OmadaUnavailable => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::NotFound => "Resource not found",
+            Self::BadRequest => "Request cannot be processed as sent",
+            Self::ValidationFailed => "One or more fields are invalid (details.fields)",
+            Self::Unauthorized => "Missing or invalid session",
+            Self::Forbidden => "Permission too low or network not allowed",
+            Self::InternalError => "Unexpected server failure",
+            Self::DatabaseError => "MySQL or MongoDB operation failed",
+            Self::ConfigError => "Server configuration problem",
+            Self::UpstreamError => "Upstream or external service failed",
+            Self::RouteNotFound => "No live route with this id",
+            Self::RouteDeleted => "Route is soft-deleted; restore or purge it first",
+            Self::DdnsConfigNotFound => "No DDNS config with this id",
+            Self::DdnsProviderAuthFailed => "DDNS provider rejected the credentials",
+            Self::DdnsUpdateFailed => "DDNS provider update failed for another reason",
+            Self::NodeNotFound => "No topology node with this id",
+            Self::ControllerNotFound => "No Omada controller with this id",
+            Self::OmadaTokenExpired => "Omada controller access token could not be obtained",
+            Self::OmadaUnavailable => "Omada controller request failed",
+            Self::WireguardProfileNotFound => "No WireGuard profile with this id",
+            Self::WireguardPeerNotFound => "No WireGuard peer with this id",
+            Self::AlertRuleNotFound => "No alert rule with this id",
+            Self::SettingNotFound => "No setting with this key",
+            Self::DnsOverrideNotFound => "No local DNS override with this id",
+            Self::RateLimitRuleNotFound => "No rate limit rule with this id",
+            Self::RegistrationTokenNotFound => "No device registration token with this id",
+            Self::RegistrationTokenInvalid => {
+                "Registration token unknown, revoked, expired or used up"
+            }
+            Self::RegistrationTokenScope => {
+                "Registration token may not register this fid or productType"
+            }
+            Self::RateLimited => "Too many requests; retry after details.retry_after_secs",
+            Self::MaintenanceWindowNotFound => "No maintenance window with this id",
+            Self::LayoutGenerationConflict => {
+                "Topology layout changed since it was read; details.current_generation"
+            }
+            Self::TopologyShareNotFound => "No topology share with this id",
+            Self::RouteVersionNotFound => "The route has no such version (or it was pruned)",
+            Self::NginxConfigStale => {
+                "Nginx inputs changed while the config was generated; details.changes"
+            }
+            Self::AcmeOrderInProgress => {
+                "A certificate order for this DDNS config is already running"
+            }
+        }
+    }
+}
+
+/// One invalid field of a `VALIDATION_FAILED` error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Not found: {0}")]
@@ -32,26 +277,195 @@ pub enum AppError {
 
     #[error("Proxy error: {0}")]
     ProxyError(String),
+
+    #[error("Validation failed: {}", describe_fields(.0))]
+    Validation(Vec<FieldError>),
+
+    /// A failure with a specific code (status follows the code)
+    #[error("{message}")]
+    Coded {
+        code: ErrorCode,
+        message: String,
+        details: Option<serde_json::Value>,
+    },
+}
+
+fn describe_fields(fields: &[FieldError]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("{}: {}", f.field, f.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl AppError {
+    pub fn coded(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError::Coded {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// Single-field validation failure
+    pub fn validation(field: impl Into<String>, message: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError::new(field, message)])
+    }
+
+    /// MongoDB failure (the mongo layer reports errors as strings)
+    pub fn database(message: String) -> Self {
+        Self::coded(ErrorCode::DatabaseError, message)
+    }
+
+    /// Attach a details object (codes other than VALIDATION_FAILED)
+    pub fn with_details(self, details: serde_json::Value) -> Self {
+        let code = self.code();
+        let message = self.message();
+        AppError::Coded {
+            code,
+            message,
+            details: Some(details),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Unauthorized => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::InternalError(_) => ErrorCode::InternalError,
+            AppError::DatabaseError(_) => ErrorCode::DatabaseError,
+            AppError::ConfigError(_) => ErrorCode::ConfigError,
+            AppError::ProxyError(_) => ErrorCode::UpstreamError,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::Coded { code, .. } => *code,
+        }
+    }
+
+    /// Message for the `error` field
+    fn message(&self) -> String {
+        match self {
+            AppError::NotFound(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Forbidden(msg)
+            | AppError::InternalError(msg)
+            | AppError::ConfigError(msg)
+            | AppError::ProxyError(msg) => msg.clone(),
+            AppError::Unauthorized => "Unauthorized".to_string(),
+            AppError::DatabaseError(e) => e.to_string(),
+            AppError::Validation(_) | AppError::Coded { .. } => self.to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::Validation(fields) => Some(serde_json::json!({ "fields": fields })),
+            AppError::Coded { details, .. } => details.clone(),
+            _ => None,
+        }
+    }
+
+    /// JSON error body (see module docs)
+    pub fn body(&self) -> serde_json::Value {
+        let code = self.code();
+        let mut body = serde_json::json!({
+            "error": self.message(),
+            "code": code.as_str(),
+            "status": code.status().as_u16(),
+        });
+        if let Some(details) = self.details() {
+            body["details"] = details;
+        }
+        body
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match &self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
-            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
-            AppError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
-            AppError::DatabaseError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-            AppError::ConfigError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
-            AppError::ProxyError(msg) => (StatusCode::BAD_GATEWAY, msg.clone()),
-        };
-
-        let body = Json(serde_json::json!({
-            "error": message,
-            "status": status.as_u16()
-        }));
-
-        (status, body).into_response()
+        (self.code().status(), Json(self.body())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique_and_registered() {
+        let mut seen = HashSet::new();
+        for code in ErrorCode::ALL {
+            let s = code.as_str();
+            assert!(seen.insert(s), "duplicate code {}", s);
+            assert!(
+                s.chars().all(|c| c.is_ascii_uppercase() || c == '_'),
+                "{} is not SCREAMING_SNAKE_CASE",
+                s
+            );
+            // Serialized form matches as_str
+            assert_eq!(serde_json::to_value(code).unwrap(), s);
+            assert!(code.status().is_client_error() || code.status().is_server_error());
+            assert!(!code.description().is_empty());
+        }
+
+        // Every plain variant maps to a registered code with its old status
+        let plain = [
+            (AppError::NotFound(String::new()), StatusCode::NOT_FOUND),
+            (AppError::BadRequest(String::new()), StatusCode::BAD_REQUEST),
+            (AppError::Unauthorized, StatusCode::UNAUTHORIZED),
+            (AppError::Forbidden(String::new()), StatusCode::FORBIDDEN),
+            (
+                AppError::InternalError(String::new()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                AppError::DatabaseError(sqlx::Error::RowNotFound),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                AppError::ConfigError(String::new()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (AppError::ProxyError(String::new()), StatusCode::BAD_GATEWAY),
+            (AppError::Validation(Vec::new()), StatusCode::BAD_REQUEST),
+        ];
+        for (error, status) in plain {
+            assert!(ErrorCode::ALL.contains(&error.code()));
+            assert_eq!(error.code().status(), status);
+        }
+    }
+
+    #[test]
+    fn body_follows_the_documented_schema() {
+        let body = AppError::coded(ErrorCode::RouteNotFound, "Route 12 not found").body();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": "Route 12 not found",
+                "code": "ROUTE_NOT_FOUND",
+                "status": 404,
+            })
+        );
+
+        let body = AppError::Validation(vec![
+            FieldError::new("path", "must start with /"),
+            FieldError::new("target", "must be an http(s) URL"),
+        ])
+        .body();
+        assert_eq!(body["code"], "VALIDATION_FAILED");
+        assert_eq!(body["status"], 400);
+        assert_eq!(body["details"]["fields"][1]["field"], "target");
+        assert_eq!(
+            body["error"],
+            "Validation failed: path: must start with /; target: must be an http(s) URL"
+        );
+
+        let body = AppError::BadRequest("nope".to_string())
+            .with_details(serde_json::json!({ "hint": "x" }))
+            .body();
+        assert_eq!(body["code"], "BAD_REQUEST");
+        assert_eq!(body["details"]["hint"], "x");
+        assert_eq!(body["error"], "nope");
     }
 }
@@ -118,6 +118,8 @@ impl ExternalDeviceManager {
             product_type: proto.product_type().to_string(),
             network_device_type: proto.network_device_type().to_string(),
             last_polled_at: None,
+            poll_interval_sec: None,
+            paused: false,
             created_at: now.clone(),
             updated_at: now,
         };
@@ -25,6 +25,16 @@ pub struct MercuryClientInfo {
     pub mac: String,
     pub ip: Option<String>,
     pub hostname: Option<String>,
+    /// Associated SSID (wired hosts report none)
+    pub ssid: Option<String>,
+    /// "2.4GHz" / "5GHz" when the AC reports the radio
+    pub band: Option<String>,
+}
+
+impl MercuryClientInfo {
+    pub fn wireless(&self) -> bool {
+        self.ssid.is_some()
+    }
 }
 
 // ============================================================================
@@ -43,6 +53,45 @@ fn xor_encode_password(password: &str) -> String {
     encoded
 }
 
+// ============================================================================
+// host_info parsing
+// ============================================================================
+
+/// Parse one `host_info` entry; None when it carries no MAC
+fn parse_host(host: &Value) -> Option<MercuryClientInfo> {
+    let text = |key: &str| {
+        host.get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    let mac = text("mac")?;
+
+    // Wired hosts come back with an empty ssid (or type "wired")
+    let wired = text("type").is_some_and(|t| t.eq_ignore_ascii_case("wired") || t == "0");
+    let ssid = if wired { None } else { text("ssid") };
+    let band = ssid
+        .as_ref()
+        .and(
+            text("band")
+                .or_else(|| text("wifi_mode"))
+                .and_then(|b| match b.as_str() {
+                    "0" | "2g" | "2.4g" | "2.4GHz" => Some("2.4GHz".to_string()),
+                    "1" | "5g" | "5GHz" => Some("5GHz".to_string()),
+                    _ => None,
+                }),
+        );
+
+    Some(MercuryClientInfo {
+        mac: crate::omada::client::normalize_mac(&mac),
+        ip: text("ip"),
+        hostname: text("hostname"),
+        ssid,
+        band,
+    })
+}
+
 // ============================================================================
 // Mercury Client
 // ============================================================================
@@ -175,49 +224,39 @@ impl MercuryClient {
 
         if let Some(hosts) = result.get("hosts_info").and_then(|h| h.get("host_info")) {
             if let Some(arr) = hosts.as_array() {
-                for host in arr {
-                    let mac = host
-                        .get("mac")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    if mac.is_empty() {
-                        continue;
-                    }
-
-                    clients.push(MercuryClientInfo {
-                        mac: crate::omada::client::normalize_mac(&mac),
-                        ip: host.get("ip").and_then(|v| v.as_str()).map(String::from),
-                        hostname: host
-                            .get("hostname")
-                            .and_then(|v| v.as_str())
-                            .map(String::from),
-                    });
-                }
+                clients.extend(arr.iter().filter_map(parse_host));
             } else if let Some(obj) = hosts.as_object() {
                 // Some Mercury models return an object with index keys
-                for (_key, host) in obj {
-                    let mac = host
-                        .get("mac")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    if mac.is_empty() {
-                        continue;
-                    }
-
-                    clients.push(MercuryClientInfo {
-                        mac: crate::omada::client::normalize_mac(&mac),
-                        ip: host.get("ip").and_then(|v| v.as_str()).map(String::from),
-                        hostname: host
-                            .get("hostname")
-                            .and_then(|v| v.as_str())
-                            .map(String::from),
-                    });
-                }
+                clients.extend(obj.values().filter_map(parse_host));
             }
         }
 
         Ok(clients)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_info_keeps_ssid_for_wireless_hosts() {
+        let wireless = parse_host(&serde_json::json!({
+            "mac": "aa-bb-cc-dd-ee-ff", "ip": "192.168.1.20", "hostname": "phone",
+            "ssid": "Office", "band": "1", "type": "wireless"
+        }))
+        .unwrap();
+        assert!(wireless.wireless());
+        assert_eq!(wireless.ssid.as_deref(), Some("Office"));
+        assert_eq!(wireless.band.as_deref(), Some("5GHz"));
+
+        let wired = parse_host(&serde_json::json!({
+            "mac": "11-22-33-44-55-66", "ssid": "", "type": "wired"
+        }))
+        .unwrap();
+        assert!(!wired.wireless());
+        assert!(wired.band.is_none());
+
+        assert!(parse_host(&serde_json::json!({ "ip": "192.168.1.30" })).is_none());
+    }
+}
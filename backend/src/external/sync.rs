@@ -1,62 +1,149 @@
 //! ExternalSyncer: Periodic polling for all registered external devices
 //!
-//! Runs in a background tokio task. Every 60 seconds, polls devices
-//! that support auto-polling (Mercury AC) and upserts to MongoDB.
+//! Runs in a background tokio task. Every 15 seconds, polls the devices
+//! that support auto-polling (Mercury AC) and whose own interval (default
+//! 60s) has elapsed, then upserts to MongoDB. Paused devices are skipped.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
 use tokio::time::{self, Duration};
 
+use crate::db::mongo::ingest_writes::IngestPacing;
 use crate::db::mongo::MongoDb;
 use crate::db::mysql::MySqlDb;
 use crate::external::manager::{DeviceProtocol, ExternalDeviceManager};
 use crate::external::mercury::MercuryClient;
+use crate::new_device::NewDeviceWatch;
 use crate::node_order::NodeOrderIngester;
+use crate::poll_schedule::{effective_interval, PollOutcome, PollSchedule};
+use crate::restart::DrainGate;
 use crate::user_object_ingester::UserObjectIngester;
 
+/// Scheduler tick (finest effective poll granularity)
+const TICK_SECS: u64 = 15;
+/// Poll interval for devices without an override
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
 /// Background synchronization service for external devices
 pub struct ExternalSyncer {
     manager: Arc<ExternalDeviceManager>,
     mongo: Arc<MongoDb>,
+    mysql: Arc<MySqlDb>,
     ingester: UserObjectIngester,
     node_order_ingester: NodeOrderIngester,
+    schedule: Mutex<PollSchedule>,
+    /// Cycles are skipped and tracked for pre-restart drains
+    drain: Arc<DrainGate>,
 }
 
 impl ExternalSyncer {
     pub fn new(manager: Arc<ExternalDeviceManager>, mongo: Arc<MongoDb>, mysql: Arc<MySqlDb>) -> Self {
-        let ingester = UserObjectIngester::new(mongo.clone(), mysql);
+        let ingester = UserObjectIngester::new(mongo.clone(), mysql.clone());
         let node_order_ingester = NodeOrderIngester::new(mongo.clone());
         Self {
             manager,
             mongo,
+            mysql,
             ingester,
             node_order_ingester,
+            schedule: Mutex::new(PollSchedule::new()),
+            drain: Arc::new(DrainGate::default()),
         }
     }
 
+    /// Share the restart drain gate (background instance)
+    pub fn with_drain(mut self, drain: Arc<DrainGate>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Report first-seen devices from this syncer's ingestion
+    pub fn with_new_device_watch(mut self, watch: Arc<NewDeviceWatch>) -> Self {
+        self.ingester = self.ingester.with_new_device_watch(watch);
+        self
+    }
+
     /// Start the background sync loop (runs forever)
     pub async fn start(self: Arc<Self>) {
-        tracing::info!("[ExternalSync] Starting background sync (interval: 60s)");
+        tracing::info!(
+            "[ExternalSync] Starting background sync (default interval: {}s, tick: {}s)",
+            DEFAULT_POLL_INTERVAL_SECS,
+            TICK_SECS
+        );
 
         // Initial sync after 15 seconds
         time::sleep(Duration::from_secs(15)).await;
 
         loop {
-            self.sync_all_devices().await;
-            time::sleep(Duration::from_secs(60)).await;
+            if self.drain.is_draining() {
+                tracing::debug!("[ExternalSync] Restart drain in progress, skipping cycle");
+            } else {
+                let _cycle = self.drain.sync_cycle("external");
+                self.mongo
+                    .set_ingest_pacing(IngestPacing::load(&self.mysql).await);
+                self.sync_due_devices().await;
+            }
+            time::sleep(Duration::from_secs(TICK_SECS)).await;
         }
     }
 
-    /// Sync all registered devices
-    async fn sync_all_devices(&self) {
+    /// Poll every registered device whose interval has elapsed
+    async fn sync_due_devices(&self) {
         let device_ids = self.manager.list_device_ids().await;
 
         if device_ids.is_empty() {
             return;
         }
 
-        tracing::debug!("[ExternalSync] Syncing {} devices", device_ids.len());
+        let docs: HashMap<String, (bool, u64)> = match self.mongo.list_external_devices().await {
+            Ok(docs) => docs
+                .into_iter()
+                .map(|d| {
+                    let interval =
+                        effective_interval(d.poll_interval_sec, DEFAULT_POLL_INTERVAL_SECS);
+                    (d.device_id, (d.paused, interval))
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("[ExternalSync] Failed to list devices: {}", e);
+                return;
+            }
+        };
+
+        let now = Utc::now();
+        let due: Vec<String> = {
+            let mut schedule = self.schedule.lock().unwrap();
+            schedule.retain(|id| device_ids.iter().any(|d| d == id));
+
+            let mut due = Vec::new();
+            for id in &device_ids {
+                let (paused, interval) = docs
+                    .get(id)
+                    .copied()
+                    .unwrap_or((false, DEFAULT_POLL_INTERVAL_SECS));
+                if paused {
+                    schedule.forget(id);
+                } else if schedule.is_due(id, interval, now) {
+                    schedule.mark_attempt(id, now);
+                    due.push(id.clone());
+                }
+            }
+            due
+        };
+
+        if due.is_empty() {
+            return;
+        }
+
+        tracing::debug!(
+            "[ExternalSync] Syncing {} of {} devices",
+            due.len(),
+            device_ids.len()
+        );
 
-        for id in device_ids {
+        for id in due {
             if let Err(e) = self.poll_device(&id).await {
                 tracing::warn!("[ExternalSync] Device {} sync failed: {}", id, e);
                 let _ = self
@@ -150,8 +237,18 @@ impl ExternalSyncer {
         Ok(())
     }
 
-    /// Manual poll trigger for a specific device
-    pub async fn poll_one(&self, device_id: &str) -> Result<(), String> {
-        self.poll_device(device_id).await
+    /// Manual poll trigger for a specific device (paused devices are left alone)
+    pub async fn poll_one(&self, device_id: &str) -> Result<PollOutcome, String> {
+        let paused = self
+            .mongo
+            .get_external_device(device_id)
+            .await?
+            .map(|d| d.paused)
+            .unwrap_or(false);
+        if paused {
+            return Ok(PollOutcome::Paused);
+        }
+        self.poll_device(device_id).await?;
+        Ok(PollOutcome::Polled)
     }
 }
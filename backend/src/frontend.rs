@@ -0,0 +1,692 @@
+//! Admin frontend served by LPG itself ([frontend] mode)
+//!
+//! "disabled" (the default) leaves the frontend to its own Next.js server as
+//! before. "external" serves a static export of it (`frontend/out`, built
+//! with `LPG_STATIC_EXPORT=1`) from `dist_dir` on disk; "embedded" serves the
+//! copy compiled into the binary with the `embed-frontend` feature, for
+//! single-binary deployments.
+//!
+//! Precedence of a request when enabled: the /api routes (also reachable as
+//! `{base_path}/api`, where the frontend calls them), then the frontend for
+//! the admin hosts and paths under `base_path`, then the reverse proxy. A
+//! proxy route more specific than `base_path` (e.g.
+//! /LacisProxyGateway2/grafana) keeps its requests; a route at `base_path`
+//! or above it (e.g. "/") does not shadow the frontend. Paths the frontend
+//! has no file for fall through to the proxy, except client-side routes
+//! (no file extension), which get `index.html`.
+//!
+//! Hashed build assets are sent as immutable; everything else must be
+//! revalidated, using the ETag (a content hash for embedded files, size and
+//! mtime for files on disk).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+
+use crate::config::FrontendConfig;
+use crate::proxy::{normalize_path, ProxyRouter};
+
+#[cfg(feature = "embed-frontend")]
+static EMBEDDED: include_dir::Dir<'static> =
+    include_dir::include_dir!("$CARGO_MANIFEST_DIR/../frontend/out");
+
+/// Cache-Control of hashed build assets
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Cache-Control of everything else (pages, unhashed files)
+const REVALIDATE: &str = "no-cache";
+/// Session cookie set by the auth API (see `auth_middleware`)
+const SESSION_COOKIE: &str = "lpg_session";
+
+enum AssetSource {
+    Disabled,
+    /// Static export on disk (mode "external")
+    Directory(PathBuf),
+    /// Static export compiled in (mode "embedded")
+    #[cfg(feature = "embed-frontend")]
+    Embedded,
+}
+
+/// One file of the export
+struct Asset {
+    body: Bytes,
+    etag: String,
+}
+
+/// Frontend files and where they are served
+pub struct FrontendAssets {
+    source: AssetSource,
+    /// Without the trailing slash ("" = served at /)
+    base_path: String,
+    /// Lower-case admin host names; empty = any host
+    hosts: Vec<String>,
+    /// Content hashes of embedded files by path
+    etags: Mutex<HashMap<String, String>>,
+}
+
+impl FrontendAssets {
+    pub fn from_config(config: &FrontendConfig) -> anyhow::Result<Self> {
+        let source = match config.mode.as_str() {
+            "disabled" => AssetSource::Disabled,
+            "external" => {
+                let dir = PathBuf::from(&config.dist_dir);
+                if !dir.join("index.html").is_file() {
+                    tracing::warn!(
+                        "Frontend dist_dir {} has no index.html (not built yet?)",
+                        dir.display()
+                    );
+                }
+                AssetSource::Directory(dir)
+            }
+            #[cfg(feature = "embed-frontend")]
+            "embedded" => AssetSource::Embedded,
+            #[cfg(not(feature = "embed-frontend"))]
+            "embedded" => anyhow::bail!(
+                "[frontend] mode = \"embedded\" needs a build with the embed-frontend feature"
+            ),
+            other => anyhow::bail!(
+                "[frontend] mode must be disabled, external or embedded (got {:?})",
+                other
+            ),
+        };
+
+        let base_path = config.base_path.trim().trim_end_matches('/');
+        if !base_path.is_empty() && !base_path.starts_with('/') {
+            anyhow::bail!("[frontend] base_path must start with '/'");
+        }
+
+        Ok(Self {
+            source,
+            base_path: base_path.to_string(),
+            hosts: config
+                .hosts
+                .iter()
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            etags: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self.source, AssetSource::Disabled)
+    }
+
+    pub fn mode(&self) -> &'static str {
+        match self.source {
+            AssetSource::Disabled => "disabled",
+            AssetSource::Directory(_) => "external",
+            #[cfg(feature = "embed-frontend")]
+            AssetSource::Embedded => "embedded",
+        }
+    }
+
+    /// Path the API router is also nested under, so the frontend reaches
+    /// /api as `{base_path}/api`; None when disabled or served at /
+    pub fn api_nest_path(&self) -> Option<&str> {
+        (self.is_enabled() && !self.base_path.is_empty()).then_some(self.base_path.as_str())
+    }
+
+    /// Whether the frontend owns a request the API routes did not take:
+    /// GET or HEAD from an admin host, under `base_path` and not shadowed by
+    /// a proxy route. Needs no file, so the caller can release the router
+    /// lock before `respond`
+    pub fn claims(
+        &self,
+        router: &ProxyRouter,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> bool {
+        if !self.is_enabled() || (method != Method::GET && method != Method::HEAD) {
+            return false;
+        }
+        let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+        self.is_admin_host(host)
+            && self.relative_path(path).is_some()
+            && !self.shadowed(router, path, host)
+    }
+
+    /// Response for a request `claims` accepted; None when the export has
+    /// nothing for it and it belongs to the reverse proxy
+    pub async fn respond(
+        &self,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Option<Response> {
+        let rel = self.relative_path(path)?;
+
+        let (file, asset) = match self.find(rel).await {
+            Some(found) => found,
+            None if is_client_route(rel) => {
+                ("index.html".to_string(), self.load("index.html").await?)
+            }
+            None => return None,
+        };
+
+        if file.ends_with(".html") {
+            if let Some(redirect) = self.session_redirect(rel, headers) {
+                return Some(redirect);
+            }
+        }
+
+        Some(self.build_response(method, &file, asset, headers))
+    }
+
+    fn is_admin_host(&self, host: Option<&str>) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+        host.map(|h| h.split(':').next().unwrap_or(h).to_ascii_lowercase())
+            .is_some_and(|h| self.hosts.contains(&h))
+    }
+
+    /// Request path relative to `base_path` ("" for the root page); None
+    /// outside it or for anything that is not a plain relative path
+    fn relative_path<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(&self.base_path)?;
+        let rel = match rest {
+            "" => "",
+            _ => rest.strip_prefix('/')?,
+        };
+        let plain = rel.split('/').all(|segment| {
+            segment != "." && segment != ".." && !segment.contains(['\\', '%', '\0'])
+        });
+        (plain && !rel.contains("//")).then_some(rel)
+    }
+
+    /// A proxy route more specific than `base_path` takes the request.
+    /// Matched on the normalized path like the proxy does, so both agree on
+    /// who owns `/x/../y` or `//y`; a path the proxy rejects is left to it
+    fn shadowed(&self, router: &ProxyRouter, path: &str, host: Option<&str>) -> bool {
+        let Ok(normalized) = normalize_path(path) else {
+            return true;
+        };
+        router
+            .match_route(&normalized.matching, host)
+            .is_some_and(|route| route.path.trim_end_matches('/').len() > self.base_path.len())
+    }
+
+    /// File for `rel` (Next.js exports "logs" as logs.html or logs/index.html)
+    async fn find(&self, rel: &str) -> Option<(String, Asset)> {
+        let trimmed = rel.trim_end_matches('/');
+        let candidates = if trimmed.is_empty() {
+            vec!["index.html".to_string()]
+        } else {
+            vec![
+                trimmed.to_string(),
+                format!("{}.html", trimmed),
+                format!("{}/index.html", trimmed),
+            ]
+        };
+        for file in candidates {
+            if let Some(asset) = self.load(&file).await {
+                return Some((file, asset));
+            }
+        }
+        None
+    }
+
+    async fn load(&self, file: &str) -> Option<Asset> {
+        match &self.source {
+            AssetSource::Disabled => None,
+            AssetSource::Directory(dir) => {
+                let full = dir.join(file);
+                let meta = tokio::fs::metadata(&full).await.ok()?;
+                if !meta.is_file() {
+                    return None;
+                }
+                let body = tokio::fs::read(&full).await.ok()?;
+                let mtime = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                Some(Asset {
+                    etag: format!("\"{:x}-{:x}\"", meta.len(), mtime),
+                    body: Bytes::from(body),
+                })
+            }
+            #[cfg(feature = "embed-frontend")]
+            AssetSource::Embedded => {
+                let body = EMBEDDED.get_file(file)?.contents();
+                Some(Asset {
+                    etag: self.content_etag(file, body),
+                    body: Bytes::from_static(body),
+                })
+            }
+        }
+    }
+
+    /// Content hash ETag, computed once per file
+    #[cfg_attr(not(feature = "embed-frontend"), allow(dead_code))]
+    fn content_etag(&self, file: &str, body: &[u8]) -> String {
+        let mut etags = self.etags.lock().unwrap_or_else(|p| p.into_inner());
+        etags
+            .entry(file.to_string())
+            .or_insert_with(|| {
+                let digest = Sha256::digest(body);
+                format!("\"{}\"", hex::encode(&digest[..12]))
+            })
+            .clone()
+    }
+
+    /// Pages need a session like under the Next.js server (middleware.ts):
+    /// without one they go to the login page, and with one the login page
+    /// goes to the dashboard
+    fn session_redirect(&self, rel: &str, headers: &HeaderMap) -> Option<Response> {
+        let has_session = headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|s| s.split(';'))
+            .any(|c| {
+                c.trim()
+                    .strip_prefix(SESSION_COOKIE)
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .is_some_and(|value| !value.is_empty())
+            });
+        let is_login = matches!(rel.trim_end_matches('/'), "login" | "login.html");
+        let location = match (has_session, is_login) {
+            (false, false) => format!("{}/login", self.base_path),
+            (true, true) if self.base_path.is_empty() => "/".to_string(),
+            (true, true) => self.base_path.clone(),
+            _ => return None,
+        };
+        Some(
+            (
+                StatusCode::TEMPORARY_REDIRECT,
+                [(header::LOCATION, location)],
+            )
+                .into_response(),
+        )
+    }
+
+    fn build_response(
+        &self,
+        method: &Method,
+        file: &str,
+        asset: Asset,
+        headers: &HeaderMap,
+    ) -> Response {
+        let cache_control = if is_hashed_asset(file) {
+            IMMUTABLE
+        } else {
+            REVALIDATE
+        };
+        let etag = HeaderValue::from_str(&asset.etag).unwrap_or(HeaderValue::from_static("\"\""));
+
+        let not_modified = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|tags| etag_matches(tags, &asset.etag));
+        if not_modified {
+            return (
+                StatusCode::NOT_MODIFIED,
+                [
+                    (header::ETAG, etag),
+                    (
+                        header::CACHE_CONTROL,
+                        HeaderValue::from_static(cache_control),
+                    ),
+                ],
+            )
+                .into_response();
+        }
+
+        let length = asset.body.len();
+        let body = if method == Method::HEAD {
+            Body::empty()
+        } else {
+            Body::from(asset.body)
+        };
+        let mut response = (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(content_type(file)),
+                ),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(cache_control),
+                ),
+                (header::ETAG, etag),
+            ],
+            body,
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+        response
+    }
+}
+
+/// Client-side route that gets `index.html`: no file extension, and not
+/// under /api or the build asset directory
+fn is_client_route(rel: &str) -> bool {
+    let first = rel.split('/').next().unwrap_or("");
+    let last = rel.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    first != "api" && first != "_next" && !last.contains('.')
+}
+
+/// Build output whose name changes with its content (Next.js puts those
+/// under _next/static; elsewhere a hex hash segment like `app.3f9a1c2b.js`)
+fn is_hashed_asset(file: &str) -> bool {
+    if file.starts_with("_next/static/") {
+        return true;
+    }
+    let name = file.rsplit('/').next().unwrap_or(file);
+    name.split(['.', '-', '_']).skip(1).any(|part| {
+        part.len() >= 8
+            && part.chars().all(|c| c.is_ascii_hexdigit())
+            && part.chars().any(|c| c.is_ascii_digit())
+    })
+}
+
+/// If-None-Match against our ETag (weak comparison, "*" matches)
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn content_type(file: &str) -> &'static str {
+    let ext = file.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    match ext.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyRoute;
+
+    const BASE: &str = "/LacisProxyGateway2";
+
+    /// Static export in a fresh temp directory
+    fn export_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lpg-frontend-{}", uuid::Uuid::new_v4()));
+        for (file, contents) in [
+            ("index.html", "<html>dashboard</html>"),
+            ("login.html", "<html>login</html>"),
+            ("logs.html", "<html>logs</html>"),
+            ("_next/static/chunks/app-3f9a1c2b.js", "console.log(1)"),
+            ("favicon.ico", "ico"),
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn assets(hosts: &[&str]) -> FrontendAssets {
+        FrontendAssets::from_config(&FrontendConfig {
+            mode: "external".to_string(),
+            base_path: BASE.to_string(),
+            dist_dir: export_dir().to_string_lossy().into_owned(),
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    fn route(path: &str) -> ProxyRoute {
+        ProxyRoute {
+            path: path.to_string(),
+            strip_prefix: false,
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    fn session_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("lpg_session=abc"));
+        headers
+    }
+
+    async fn get(
+        assets: &FrontendAssets,
+        router: &ProxyRouter,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Option<Response> {
+        if !assets.claims(router, &Method::GET, path, headers) {
+            return None;
+        }
+        assets.respond(&Method::GET, path, headers).await
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn pages_assets_and_client_routes() {
+        let assets = assets(&[]);
+        let router = ProxyRouter::from_routes(vec![]);
+        let headers = session_headers();
+
+        let page = get(&assets, &router, "/LacisProxyGateway2/logs", &headers)
+            .await
+            .unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+        assert_eq!(page.headers()[header::CACHE_CONTROL], REVALIDATE);
+        assert!(page.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        assert_eq!(body(page).await, "<html>logs</html>");
+
+        let chunk = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/_next/static/chunks/app-3f9a1c2b.js",
+            &headers,
+        )
+        .await
+        .unwrap();
+        assert_eq!(chunk.headers()[header::CACHE_CONTROL], IMMUTABLE);
+        assert!(chunk.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/javascript"));
+
+        // Client-side route -> index.html; missing files and /api do not
+        let client = get(&assets, &router, "/LacisProxyGateway2/routes/12", &headers)
+            .await
+            .unwrap();
+        assert_eq!(body(client).await, "<html>dashboard</html>");
+        assert!(
+            get(&assets, &router, "/LacisProxyGateway2/missing.js", &headers)
+                .await
+                .is_none()
+        );
+        assert!(
+            get(&assets, &router, "/LacisProxyGateway2/api/nope", &headers)
+                .await
+                .is_none()
+        );
+        assert!(get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/../etc/passwd",
+            &headers
+        )
+        .await
+        .is_none());
+
+        // Outside base_path everything belongs to the proxy
+        assert!(get(&assets, &router, "/other", &headers).await.is_none());
+        assert!(get(&assets, &router, "/LacisProxyGateway2x", &headers)
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn etag_revalidation() {
+        let assets = assets(&[]);
+        let router = ProxyRouter::from_routes(vec![]);
+        let first = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/favicon.ico",
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        let etag = first.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let second = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/favicon.ico",
+            &headers,
+        )
+        .await
+        .unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(second.headers()[header::ETAG], etag);
+
+        assert!(etag_matches("\"a\", W/\"b\"", "\"b\""));
+        assert!(!etag_matches("\"a\"", "\"b\""));
+    }
+
+    #[tokio::test]
+    async fn proxied_route_shadows_frontend_path() {
+        let assets = assets(&[]);
+        let headers = session_headers();
+
+        // More specific than base_path: the proxy keeps the path, even
+        // where the export has a page for it
+        let router = ProxyRouter::from_routes(vec![route("/LacisProxyGateway2/logs")]);
+        assert!(get(&assets, &router, "/LacisProxyGateway2/logs", &headers)
+            .await
+            .is_none());
+        assert!(
+            get(&assets, &router, "/LacisProxyGateway2/logs/live", &headers)
+                .await
+                .is_none()
+        );
+        assert!(
+            get(&assets, &router, "/LacisProxyGateway2/settings", &headers)
+                .await
+                .is_some()
+        );
+        // Spellings the proxy normalizes onto that route stay with it
+        for path in [
+            "/LacisProxyGateway2//logs",
+            "/LacisProxyGateway2/settings/../logs",
+            "/LacisProxyGateway2/%6Cogs",
+        ] {
+            assert!(
+                get(&assets, &router, path, &headers).await.is_none(),
+                "{}",
+                path
+            );
+        }
+
+        // A catch-all or the old frontend route at base_path does not
+        for path in ["/", BASE] {
+            let router = ProxyRouter::from_routes(vec![route(path)]);
+            assert!(
+                get(&assets, &router, "/LacisProxyGateway2/logs", &headers)
+                    .await
+                    .is_some(),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_hosts_and_session_redirect() {
+        let assets = assets(&["admin.example.com"]);
+        let router = ProxyRouter::from_routes(vec![]);
+
+        let mut headers = session_headers();
+        headers.insert(
+            header::HOST,
+            HeaderValue::from_static("Admin.example.com:443"),
+        );
+        assert!(get(&assets, &router, BASE, &headers).await.is_some());
+        headers.insert(header::HOST, HeaderValue::from_static("shop.example.com"));
+        assert!(get(&assets, &router, BASE, &headers).await.is_none());
+
+        let assets = self::assets(&[]);
+        let anonymous = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/logs",
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(anonymous.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            anonymous.headers()[header::LOCATION],
+            "/LacisProxyGateway2/login"
+        );
+        let login = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/login",
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(login.status(), StatusCode::OK);
+        // Assets never redirect
+        let icon = get(
+            &assets,
+            &router,
+            "/LacisProxyGateway2/favicon.ico",
+            &HeaderMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(icon.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn hashed_asset_detection() {
+        assert!(is_hashed_asset("_next/static/css/app.css"));
+        assert!(is_hashed_asset("assets/main.3f9a1c2b.js"));
+        assert!(!is_hashed_asset("favicon.ico"));
+        assert!(!is_hashed_asset("index.html"));
+        assert!(!is_hashed_asset("deadbeefcafe.txt"));
+    }
+}
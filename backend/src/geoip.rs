@@ -5,7 +5,6 @@
 
 use maxminddb::{geoip2, Reader};
 use serde::Serialize;
-use std::net::IpAddr;
 
 /// Geographic information for an IP address
 #[derive(Debug, Serialize, Clone, Default)]
@@ -33,20 +32,11 @@ impl GeoIpReader {
     /// Look up geographic info for an IP address string.
     /// Returns None if the IP is unparseable, private, or not found in the database.
     pub fn lookup(&self, ip_str: &str) -> Option<GeoInfo> {
-        let ip: IpAddr = ip_str.parse().ok()?;
+        let ip = crate::ip::parse_ip(ip_str)?;
 
         // Skip private/loopback/link-local addresses
-        match &ip {
-            IpAddr::V4(v4) => {
-                if v4.is_private() || v4.is_loopback() || v4.is_link_local() {
-                    return None;
-                }
-            }
-            IpAddr::V6(v6) => {
-                if v6.is_loopback() {
-                    return None;
-                }
-            }
+        if crate::ip::is_lan(ip) {
+            return None;
         }
 
         // maxminddb 0.27 API: lookup() -> LookupResult, then decode()
@@ -74,7 +64,7 @@ impl GeoIpReader {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::net::IpAddr;
 
     #[test]
     fn test_private_ip_returns_none() {
@@ -1,26 +1,167 @@
 //! Health check scheduler
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use chrono::Utc;
+use serde::Serialize;
 use tokio::sync::RwLock;
-use tokio::time::interval;
+use tokio::time::{interval, MissedTickBehavior};
 
+use crate::db::mongo::MongoDb;
 use crate::db::AppState;
-use crate::models::HealthCheck;
+use crate::maintenance::{self, ActiveMaintenance, MaintenanceWindow, MaintenanceWindowStatus};
+use crate::models::{HealthCheck, HealthFailureContext, ProxyRoute};
 use crate::notify::DiscordNotifier;
+use crate::proxy::GrpcClient;
+
+use super::RouteWarmup;
 
 /// Track consecutive failures per route
 type FailureTracker = HashMap<i32, u32>;
 
+/// Number of recent error access logs attached to a failure notification
+const FAILURE_CONTEXT_ERROR_LIMIT: i64 = 5;
+
+/// Upper bound on context gathering so a slow MongoDB cannot stall the check loop
+const FAILURE_CONTEXT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the scheduler looks for due routes; also the shortest
+/// per-route interval
+pub const MIN_HEALTH_CHECK_INTERVAL_SEC: i32 = 5;
+
+/// Longest per-route interval
+pub const MAX_HEALTH_CHECK_INTERVAL_SEC: i32 = 86_400;
+
 /// Health checker that runs in the background
 pub struct HealthChecker {
     app_state: AppState,
     client: reqwest::Client,
+    /// grpc.health.v1 checks of gRPC routes
+    grpc_client: GrpcClient,
     notifier: Arc<DiscordNotifier>,
     failures: Arc<RwLock<FailureTracker>>,
+    /// Warming routes to release, and the nudge for early cycles
+    warmup: Arc<RouteWarmup>,
+    /// Routes whose failure notification a maintenance window held back
+    held_back: Arc<RwLock<HashSet<i32>>>,
+}
+
+/// Result of a single target probe, as returned by the activation pre-check
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetProbe {
+    pub target: String,
+    pub healthy: bool,
+    pub response_time_ms: Option<i32>,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+impl TargetProbe {
+    fn from_result(target: &str, result: &Result<i32, String>) -> Self {
+        Self {
+            target: target.to_string(),
+            healthy: result.is_ok(),
+            response_time_ms: result.as_ref().ok().copied(),
+            status_code: result.as_ref().err().and_then(|e| e.parse::<i32>().ok()),
+            error: result.as_ref().err().cloned(),
+        }
+    }
+}
+
+/// Maintenance window statuses for one check cycle, saved at its end
+struct MaintenanceTally {
+    windows: HashMap<String, MaintenanceWindow>,
+    changed: HashSet<String>,
+    /// Windows whose previous occurrence ended as a new one started
+    restarted: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceTally {
+    /// Start tallying the active occurrences that are not tallied yet
+    fn open(windows: &[MaintenanceWindow], active: &[ActiveMaintenance]) -> Self {
+        let mut tally = Self {
+            windows: windows
+                .iter()
+                .map(|w| (w.window_id.clone(), w.clone()))
+                .collect(),
+            changed: HashSet::new(),
+            restarted: Vec::new(),
+        };
+        for occurrence in active {
+            let Some(window) = tally.windows.get_mut(&occurrence.window_id) else {
+                continue;
+            };
+            if window.status.occurrence_start == Some(occurrence.starts_at) {
+                continue;
+            }
+            if window.status.occurrence_start.is_some() {
+                tally.restarted.push(window.clone());
+            }
+            window.status.occurrence_start = Some(occurrence.starts_at);
+            window.status.occurrence_end = Some(occurrence.ends_at);
+            window.status.suppressed_failures = 0;
+            window.status.suppressed_alerts = 0;
+            window.status.failed_routes.clear();
+            tally.changed.insert(occurrence.window_id.clone());
+        }
+        tally
+    }
+
+    /// Previous occurrences of windows that started a new one, to close
+    fn restarted(&mut self) -> Vec<MaintenanceWindow> {
+        std::mem::take(&mut self.restarted)
+    }
+
+    /// Tallied windows that are no longer active (ended, disabled or
+    /// rescheduled), to close
+    fn ended(&self, active: &[ActiveMaintenance]) -> Vec<MaintenanceWindow> {
+        self.windows
+            .values()
+            .filter(|w| w.status.occurrence_start.is_some())
+            .filter(|w| !active.iter().any(|a| a.window_id == w.window_id))
+            .cloned()
+            .collect()
+    }
+
+    fn status_mut(&mut self, window_id: &str) -> &mut MaintenanceWindowStatus {
+        self.changed.insert(window_id.to_string());
+        &mut self
+            .windows
+            .get_mut(window_id)
+            .expect("tallied window")
+            .status
+    }
+
+    fn record_failure(&mut self, window_id: &str, route_id: i32, alert: bool) {
+        let status = self.status_mut(window_id);
+        status.suppressed_failures += 1;
+        if alert {
+            status.suppressed_alerts += 1;
+        }
+        if !status.failed_routes.contains(&route_id) {
+            status.failed_routes.push(route_id);
+        }
+    }
+
+    async fn save(&self, mongo: &MongoDb) {
+        for window_id in &self.changed {
+            let Some(window) = self.windows.get(window_id) else {
+                continue;
+            };
+            if let Err(e) = mongo
+                .set_maintenance_window_status(window_id, &window.status)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to store maintenance window '{}' status: {}",
+                    window.name,
+                    e
+                );
+            }
+        }
+    }
 }
 
 impl HealthChecker {
@@ -32,64 +173,137 @@ impl HealthChecker {
                 .connect_timeout(Duration::from_secs(3))
                 .build()
                 .unwrap(),
+            grpc_client: GrpcClient::default(),
             notifier,
             failures: Arc::new(RwLock::new(HashMap::new())),
+            warmup: Arc::new(RouteWarmup::default()),
+            held_back: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
-    /// Start the health check loop
+    /// Share the route warm-up state with the proxy
+    pub fn with_warmup(mut self, warmup: Arc<RouteWarmup>) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Start the health check loop.
+    ///
+    /// Routes and intervals are re-read on every tick, so a route's own
+    /// `health_check_interval_sec` (or the global setting) applies without a
+    /// restart.
     pub async fn start(self: Arc<Self>) {
         tracing::info!("Starting health checker...");
 
-        // Get check interval from settings (default 60 seconds)
-        let check_interval = self
-            .app_state
-            .mysql
-            .get_health_check_settings()
-            .await
-            .map(|(interval, _, _)| interval as u64)
-            .unwrap_or(60);
-
-        let mut interval_timer = interval(Duration::from_secs(check_interval));
+        let mut ticker = interval(Duration::from_secs(MIN_HEALTH_CHECK_INTERVAL_SEC as u64));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_checked = HashMap::new();
 
         loop {
-            interval_timer.tick().await;
+            let all = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = self.warmup.nudged() => {
+                    tracing::debug!("Health check cycle requested by a route activation");
+                    true
+                }
+            };
 
-            if let Err(e) = self.check_all().await {
+            if let Err(e) = self.check_due(&mut last_checked, all).await {
                 tracing::error!("Health check cycle failed: {}", e);
             }
         }
     }
 
-    /// Check all active routes
-    async fn check_all(&self) -> anyhow::Result<()> {
+    /// Check the active routes whose interval has passed since their last
+    /// check (every active route when `all`)
+    async fn check_due(
+        &self,
+        last_checked: &mut HashMap<i32, Instant>,
+        all: bool,
+    ) -> anyhow::Result<()> {
         let routes = self.app_state.mysql.list_active_routes().await?;
 
+        let (global_interval, timeout_ms, failure_threshold) = self
+            .app_state
+            .mysql
+            .get_health_check_settings()
+            .await
+            .unwrap_or((60, 5000, 3));
+
+        // Deactivated or deleted routes are checked at once when they return
+        last_checked.retain(|id, _| routes.iter().any(|r| r.id == *id));
+        let now = Instant::now();
+        let routes: Vec<ProxyRoute> = routes
+            .into_iter()
+            .filter(|route| {
+                all || last_checked.get(&route.id).is_none_or(|at| {
+                    is_due(
+                        now.duration_since(*at),
+                        route.health_check_interval(global_interval),
+                    )
+                })
+            })
+            .collect();
         if routes.is_empty() {
             return Ok(());
         }
+        for route in &routes {
+            last_checked.insert(route.id, now);
+        }
 
-        let (_, timeout_ms, failure_threshold) = self
+        // A failing window lookup must not stop the checks: run as if none
+        let windows = self
             .app_state
-            .mysql
-            .get_health_check_settings()
+            .mongo
+            .list_maintenance_windows()
             .await
-            .unwrap_or((60, 5000, 3));
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load maintenance windows: {}", e);
+                Vec::new()
+            });
+        let active = maintenance::active_windows(&windows, Utc::now());
+        let mut tally = MaintenanceTally::open(&windows, &active);
+        for window in tally.restarted() {
+            self.close_maintenance(window, &mut tally).await;
+        }
 
         for route in routes {
-            let healthy = self.check_route(&route.target, timeout_ms as u64).await;
+            // Planned downtime: the target is expected to be down
+            if route.maintenance_mode {
+                tracing::debug!("Health check of {} paused by maintenance mode", route.path);
+                continue;
+            }
+            let in_maintenance = maintenance::resolve(&route, &windows, &active);
+            if in_maintenance.paused {
+                tracing::debug!("Health check of {} paused by maintenance", route.path);
+                continue;
+            }
+
+            let healthy = self
+                .check_route(&route, &route.target, timeout_ms as u64)
+                .await;
+            self.check_fallbacks(&route, timeout_ms as u64).await;
 
             // Record health check
+            let probe = TargetProbe::from_result(&route.target, &healthy);
             let check = HealthCheck {
                 timestamp: Utc::now(),
                 route_id: route.id,
-                target: route.target.clone(),
-                healthy: healthy.is_ok(),
-                response_time_ms: healthy.as_ref().ok().copied(),
-                status_code: healthy.as_ref().err().and_then(|e| e.parse::<i32>().ok()),
-                error: healthy.as_ref().err().map(|s| s.to_string()),
+                target: probe.target,
+                healthy: probe.healthy,
+                response_time_ms: probe.response_time_ms,
+                status_code: probe.status_code,
+                error: probe.error,
             };
 
+            if healthy.is_ok() && self.warmup.finish(route.id) {
+                tracing::info!(
+                    "Route {} ({}) is healthy, warm-up finished",
+                    route.path,
+                    route.target
+                );
+            }
+
             if let Err(e) = self.app_state.mongo.save_health_check(&check).await {
                 tracing::warn!("Failed to save health check: {}", e);
             }
@@ -107,8 +321,13 @@ impl HealthChecker {
                     *count
                 );
 
+                let alert = *count == failure_threshold as u32;
+                if let Some(window_id) = &in_maintenance.charged_to {
+                    tally.record_failure(window_id, route.id, alert);
+                }
+
                 // Notify if threshold reached
-                if *count == failure_threshold as u32 {
+                if alert {
                     // Log security event
                     if let Err(e) = self
                         .app_state
@@ -119,18 +338,41 @@ impl HealthChecker {
                         tracing::warn!("Failed to log health check failure: {}", e);
                     }
 
-                    // Send Discord notification
+                    if in_maintenance.charged_to.is_some() {
+                        // Announced by the window's closing summary instead
+                        self.held_back.write().await.insert(route.id);
+                        tracing::info!(
+                            "Failure notification for {} held back by maintenance",
+                            route.path
+                        );
+                        continue;
+                    }
+
+                    // Send Discord notification (with access log context if available)
+                    let context = self.gather_failure_context(route.id).await;
                     self.notifier
-                        .notify_health_failure(&route.path, &route.target, *count)
+                        .notify_health_failure(
+                            &route.path,
+                            &route.target,
+                            *count,
+                            route.owner_label().as_deref(),
+                            context.as_ref(),
+                        )
                         .await;
                 }
             } else {
                 // Check if we're recovering from failure
                 if let Some(prev_count) = failures.get(&route.id) {
-                    if *prev_count >= failure_threshold as u32 {
+                    // Whose failure was never announced recovers silently
+                    let announced = !self.held_back.write().await.remove(&route.id);
+                    if *prev_count >= failure_threshold as u32 && announced {
                         // Send recovery notification
                         self.notifier
-                            .notify_health_recovery(&route.path, &route.target)
+                            .notify_health_recovery(
+                                &route.path,
+                                &route.target,
+                                route.owner_label().as_deref(),
+                            )
                             .await;
 
                         tracing::info!(
@@ -146,37 +388,142 @@ impl HealthChecker {
             }
         }
 
+        for window in tally.ended(&active) {
+            self.close_maintenance(window, &mut tally).await;
+        }
+        tally.save(&self.app_state.mongo).await;
+
         Ok(())
     }
 
-    /// Check a single route's health
-    async fn check_route(&self, target: &str, timeout_ms: u64) -> Result<i32, String> {
-        let start = Instant::now();
+    /// Summarize the occurrence tallied in `window.status` and record it as
+    /// closed. Routes still failing are named in the summary and then notify
+    /// their recovery.
+    async fn close_maintenance(&self, window: MaintenanceWindow, tally: &mut MaintenanceTally) {
+        let failures = self.failures.read().await;
+        let still_failing: Vec<i32> = window
+            .status
+            .failed_routes
+            .iter()
+            .copied()
+            .filter(|id| failures.get(id).is_some_and(|count| *count > 0))
+            .collect();
+        drop(failures);
 
-        // Use HEAD request for efficiency
-        let response = self
-            .client
-            .head(target)
-            .timeout(Duration::from_millis(timeout_ms))
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    "timeout".to_string()
-                } else if e.is_connect() {
-                    "connection_failed".to_string()
-                } else {
-                    e.to_string()
-                }
-            })?;
+        let mut paths = Vec::with_capacity(still_failing.len());
+        for id in &still_failing {
+            let path = match self.app_state.mysql.get_route(*id).await {
+                Ok(Some(route)) => route.path,
+                _ => format!("route {}", id),
+            };
+            paths.push(path);
+        }
+        let mut held_back = self.held_back.write().await;
+        for id in &still_failing {
+            held_back.remove(id);
+        }
+        drop(held_back);
 
-        let elapsed_ms = start.elapsed().as_millis() as i32;
+        let summary = maintenance::summarize(&window, &window.status, &paths);
+        if let Some(summary) = &summary {
+            tracing::info!("Maintenance window '{}' closed: {}", window.name, summary);
+            self.notifier
+                .notify_maintenance_summary(&window.name, summary, still_failing.is_empty())
+                .await;
+        }
 
-        // Consider 2xx and 3xx as healthy
-        if response.status().is_success() || response.status().is_redirection() {
-            Ok(elapsed_ms)
-        } else {
-            Err(response.status().as_u16().to_string())
+        let status = tally.status_mut(&window.window_id);
+        // A restarted window already tallies its next occurrence
+        if status.occurrence_start == window.status.occurrence_start {
+            *status = MaintenanceWindowStatus {
+                last_summary: status.last_summary.take(),
+                ..MaintenanceWindowStatus::default()
+            };
+        }
+        status.last_closed_at = Some(Utc::now());
+        if summary.is_some() {
+            status.last_summary = summary;
+        }
+    }
+
+    /// Gather recent error context for a failing route.
+    /// Returns None if MongoDB does not answer within FAILURE_CONTEXT_TIMEOUT.
+    async fn gather_failure_context(&self, route_id: i32) -> Option<HealthFailureContext> {
+        let mongo = &self.app_state.mongo;
+        let gather = async {
+            let (errors, last_success) = tokio::join!(
+                mongo.get_recent_route_errors(route_id, FAILURE_CONTEXT_ERROR_LIMIT),
+                mongo.get_last_healthy_check_time(route_id),
+            );
+            HealthFailureContext {
+                recent_errors: errors.unwrap_or_default(),
+                last_success: last_success.ok().flatten(),
+            }
+        };
+
+        match tokio::time::timeout(FAILURE_CONTEXT_TIMEOUT, gather).await {
+            Ok(context) => Some(context),
+            Err(_) => {
+                tracing::warn!(
+                    "Health failure context for route {} timed out, sending plain notification",
+                    route_id
+                );
+                None
+            }
+        }
+    }
+
+    /// Check one target of a route the way the route is checked
+    /// (grpc.health.v1 when configured)
+    async fn check_route(
+        &self,
+        route: &ProxyRoute,
+        target: &str,
+        timeout_ms: u64,
+    ) -> Result<i32, String> {
+        match route.grpc_health_check() {
+            Some(service) => {
+                check_grpc_target(&self.grpc_client, target, service, timeout_ms).await
+            }
+            None => {
+                let url = probe_url(target, route.health_check_path());
+                let authorization = route.upstream_authorization();
+                check_target(&self.client, &url, authorization.as_deref(), timeout_ms).await
+            }
+        }
+    }
+
+    /// Probe the route's fallback targets and keep the latest result of
+    /// each; failures are logged but never notified (the primary's are)
+    async fn check_fallbacks(&self, route: &ProxyRoute, timeout_ms: u64) {
+        let fallbacks = route.fallback_targets();
+        for target in &fallbacks {
+            let result = self.check_route(route, target, timeout_ms).await;
+            if let Err(e) = &result {
+                tracing::warn!(
+                    "Health check failed for fallback {} of {}: {}",
+                    target,
+                    route.path,
+                    e
+                );
+            }
+            let probe = TargetProbe::from_result(target, &result);
+            if let Err(e) = self
+                .app_state
+                .mongo
+                .save_fallback_check(route.id, &probe)
+                .await
+            {
+                tracing::warn!("Failed to save fallback health check: {}", e);
+            }
+        }
+        if let Err(e) = self
+            .app_state
+            .mongo
+            .retain_fallback_checks(route.id, &fallbacks)
+            .await
+        {
+            tracing::warn!("Failed to prune fallback health checks: {}", e);
         }
     }
 
@@ -185,3 +532,132 @@ impl HealthChecker {
         self.failures.read().await.clone()
     }
 }
+
+/// Whether a route last checked `elapsed` ago is due; half a scheduler tick
+/// of slack keeps an interval from slipping to the following tick
+fn is_due(elapsed: Duration, interval_sec: i32) -> bool {
+    let slack = Duration::from_millis(MIN_HEALTH_CHECK_INTERVAL_SEC as u64 * 500);
+    elapsed + slack >= Duration::from_secs(interval_sec.max(MIN_HEALTH_CHECK_INTERVAL_SEC) as u64)
+}
+
+/// URL an HTTP probe requests: `path` appended to the target ("/" or an
+/// empty path probes the target as configured)
+pub fn probe_url(target: &str, path: &str) -> String {
+    let path = path.trim().trim_start_matches('/');
+    if path.is_empty() {
+        return target.to_string();
+    }
+    format!("{}/{}", target.trim_end_matches('/'), path)
+}
+
+/// Probe a target once with the health check timeout from settings
+/// (`authorization` = the route's upstream Basic auth)
+pub async fn probe_target(
+    app_state: &AppState,
+    client: &reqwest::Client,
+    target: &str,
+    authorization: Option<&str>,
+) -> TargetProbe {
+    let (_, timeout_ms, _) = app_state
+        .mysql
+        .get_health_check_settings()
+        .await
+        .unwrap_or((60, 5000, 3));
+    let result = check_target(client, target, authorization, timeout_ms as u64).await;
+    TargetProbe::from_result(target, &result)
+}
+
+/// Probe a gRPC target once over grpc.health.v1 with the health check timeout
+pub async fn probe_grpc_target(
+    app_state: &AppState,
+    client: &GrpcClient,
+    target: &str,
+    service: &str,
+) -> TargetProbe {
+    let (_, timeout_ms, _) = app_state
+        .mysql
+        .get_health_check_settings()
+        .await
+        .unwrap_or((60, 5000, 3));
+    let result = check_grpc_target(client, target, service, timeout_ms as u64).await;
+    TargetProbe::from_result(target, &result)
+}
+
+/// grpc.health.v1.Health/Check; Ok(response time in ms) when SERVING
+async fn check_grpc_target(
+    client: &GrpcClient,
+    target: &str,
+    service: &str,
+    timeout_ms: u64,
+) -> Result<i32, String> {
+    let start = Instant::now();
+    client
+        .health_check(target, service, Duration::from_millis(timeout_ms))
+        .await?;
+    Ok(start.elapsed().as_millis() as i32)
+}
+
+/// HEAD the target; Ok(response time in ms) on 2xx/3xx, Err(status or error kind)
+async fn check_target(
+    client: &reqwest::Client,
+    target: &str,
+    authorization: Option<&str>,
+    timeout_ms: u64,
+) -> Result<i32, String> {
+    let start = Instant::now();
+
+    // Use HEAD request for efficiency
+    let mut request = client
+        .head(target)
+        .timeout(Duration::from_millis(timeout_ms));
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+    let response = request.send().await.map_err(|e| {
+        if e.is_timeout() {
+            "timeout".to_string()
+        } else if e.is_connect() {
+            "connection_failed".to_string()
+        } else {
+            e.to_string()
+        }
+    })?;
+
+    let elapsed_ms = start.elapsed().as_millis() as i32;
+
+    // Consider 2xx and 3xx as healthy
+    if response.status().is_success() || response.status().is_redirection() {
+        Ok(elapsed_ms)
+    } else {
+        Err(response.status().as_u16().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_url_appends_the_path() {
+        assert_eq!(probe_url("http://api:8080", "/"), "http://api:8080");
+        assert_eq!(probe_url("http://api:8080", ""), "http://api:8080");
+        assert_eq!(
+            probe_url("http://api:8080/", "/api/v1/status"),
+            "http://api:8080/api/v1/status"
+        );
+        assert_eq!(
+            probe_url("http://api:8080/base", "health"),
+            "http://api:8080/base/health"
+        );
+    }
+
+    #[test]
+    fn routes_are_due_after_their_interval() {
+        assert!(!is_due(Duration::from_secs(5), 60));
+        // A tick that lands just short of the interval still counts
+        assert!(is_due(Duration::from_millis(59_990), 60));
+        assert!(is_due(Duration::from_secs(61), 60));
+        // Intervals below the scheduler tick run every tick
+        assert!(is_due(Duration::from_secs(5), 1));
+    }
+}
@@ -0,0 +1,666 @@
+//! Dependency probes
+//!
+//! Besides the proxy routes, the gateway depends on its own databases and on
+//! the services it integrates with. A leader-only loop (interval
+//! `dependency_check_interval_sec`, default 300) probes each of them:
+//!
+//! - `mysql`: `SELECT 1`, plus pool size / idle connections
+//! - `mongodb`: ping, plus `dbStats`; unhealthy when the filesystem holding
+//!   the data is above `mongo_disk_usage_max_percent` (default 90) or the
+//!   storage size above `mongo_storage_max_mb` (0 = no limit)
+//! - `omada`: a fresh token request per registered controller
+//! - `openwrt`: the SSH banner of each registered router (no login)
+//! - `aranea`: an authenticated device state query for the tenant (the
+//!   client's recent call counts go into the details)
+//! - `discord`: GET of the configured webhook, which returns the webhook
+//!   without posting a message
+//!
+//! Every result is stored in `dependencies_health` with the consecutive
+//! failure count of its dependency/target, so the latest record per target
+//! is the current state: `GET /api/dashboard/dependencies` (and anything
+//! else that needs the state, such as a readiness check) reads it instead
+//! of probing again. Reaching `dependency_failure_threshold` consecutive
+//! failures notifies once, like route health; so does the recovery.
+//! Probes listed in `dependency_probes_disabled` (comma-separated) are
+//! skipped.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::sync::RwLock;
+use tokio::time::{interval, timeout};
+
+use crate::aranea::AraneaClient;
+use crate::db::mongo::dependencies_health::MongoStorage;
+use crate::db::AppState;
+use crate::notify::DiscordNotifier;
+use crate::omada::OmadaManager;
+
+/// Records older than this are purged after each cycle
+const RETENTION: chrono::Duration = chrono::Duration::days(7);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dependency {
+    Mysql,
+    Mongodb,
+    Omada,
+    Openwrt,
+    Aranea,
+    Discord,
+}
+
+impl Dependency {
+    pub const ALL: [Dependency; 6] = [
+        Self::Mysql,
+        Self::Mongodb,
+        Self::Omada,
+        Self::Openwrt,
+        Self::Aranea,
+        Self::Discord,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mysql => "mysql",
+            Self::Mongodb => "mongodb",
+            Self::Omada => "omada",
+            Self::Openwrt => "openwrt",
+            Self::Aranea => "aranea",
+            Self::Discord => "discord",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.as_str().eq_ignore_ascii_case(value.trim()))
+    }
+}
+
+/// One probe result (a `dependencies_health` document)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyHealth {
+    pub timestamp: DateTime<Utc>,
+    pub dependency: Dependency,
+    /// Controller / router id; the dependency name for single instances
+    pub target: String,
+    /// Display name, when the target has one
+    #[serde(default)]
+    pub label: Option<String>,
+    pub healthy: bool,
+    #[serde(default)]
+    pub response_time_ms: Option<i32>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Failures in a row up to and including this one
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Probe-specific measurements (pool stats, storage, SSH banner, ...)
+    #[serde(default)]
+    pub details: serde_json::Value,
+}
+
+/// Probe settings, read at the start of every cycle
+#[derive(Debug, Clone)]
+pub struct DependencySettings {
+    pub interval_sec: u64,
+    pub timeout_ms: u64,
+    pub failure_threshold: u32,
+    pub mongo_disk_max_percent: f64,
+    pub mongo_storage_max_mb: u64,
+    pub disabled: Vec<Dependency>,
+}
+
+impl Default for DependencySettings {
+    fn default() -> Self {
+        Self {
+            interval_sec: 300,
+            timeout_ms: 5000,
+            failure_threshold: 2,
+            mongo_disk_max_percent: 90.0,
+            mongo_storage_max_mb: 0,
+            disabled: Vec::new(),
+        }
+    }
+}
+
+impl DependencySettings {
+    pub async fn load(app_state: &AppState) -> Self {
+        let defaults = Self::default();
+        let mysql = &app_state.mysql;
+        let int = |key: &'static str, default: i32| async move {
+            mysql.get_setting_i32(key, default).await.unwrap_or(default)
+        };
+        Self {
+            interval_sec: int("dependency_check_interval_sec", 300).await.max(30) as u64,
+            timeout_ms: int("dependency_check_timeout_ms", 5000).await.max(100) as u64,
+            failure_threshold: int("dependency_failure_threshold", 2).await.max(1) as u32,
+            mongo_disk_max_percent: int("mongo_disk_usage_max_percent", 90).await.clamp(1, 100)
+                as f64,
+            mongo_storage_max_mb: int("mongo_storage_max_mb", 0).await.max(0) as u64,
+            disabled: mysql
+                .get_setting("dependency_probes_disabled")
+                .await
+                .ok()
+                .flatten()
+                .map(|v| parse_disabled(&v))
+                .unwrap_or(defaults.disabled),
+        }
+    }
+
+    pub fn enabled(&self, dependency: Dependency) -> bool {
+        !self.disabled.contains(&dependency)
+    }
+}
+
+/// Comma-separated probe names; unknown names are ignored
+pub fn parse_disabled(value: &str) -> Vec<Dependency> {
+    value.split(',').filter_map(Dependency::parse).collect()
+}
+
+/// Storage verdict for the MongoDB probe
+pub fn check_mongo_storage(
+    storage: &MongoStorage,
+    max_disk_percent: f64,
+    max_storage_mb: u64,
+) -> Result<(), String> {
+    if let Some(percent) = storage.fs_used_percent() {
+        if percent > max_disk_percent {
+            return Err(format!(
+                "Filesystem {:.1}% full (limit {:.0}%)",
+                percent, max_disk_percent
+            ));
+        }
+    }
+    let storage_mb = storage.storage_size / (1024 * 1024);
+    if max_storage_mb > 0 && storage_mb > max_storage_mb {
+        return Err(format!(
+            "Storage size {} MB exceeds {} MB",
+            storage_mb, max_storage_mb
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the first bytes a server sent are an SSH identification string
+pub fn is_ssh_banner(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"SSH-")
+}
+
+/// What a probe result changes for notifications
+#[derive(Debug, PartialEq, Eq)]
+pub enum Transition {
+    None,
+    /// The failure threshold was just reached
+    Failed,
+    /// Healthy again after having reached the threshold
+    Recovered,
+}
+
+/// Consecutive failures per dependency/target
+#[derive(Debug, Default)]
+pub struct FailureTracker {
+    counts: HashMap<(Dependency, String), u32>,
+}
+
+impl FailureTracker {
+    /// Record a result; returns the failure count and the transition
+    pub fn record(
+        &mut self,
+        dependency: Dependency,
+        target: &str,
+        healthy: bool,
+        threshold: u32,
+    ) -> (u32, Transition) {
+        let key = (dependency, target.to_string());
+        if healthy {
+            let previous = self.counts.remove(&key).unwrap_or(0);
+            let transition = if previous >= threshold {
+                Transition::Recovered
+            } else {
+                Transition::None
+            };
+            return (0, transition);
+        }
+        let count = self.counts.entry(key).or_insert(0);
+        *count += 1;
+        let transition = if *count == threshold {
+            Transition::Failed
+        } else {
+            Transition::None
+        };
+        (*count, transition)
+    }
+}
+
+/// Outcome of one probe before it is recorded
+struct ProbeOutcome {
+    target: String,
+    label: Option<String>,
+    result: Result<(), String>,
+    elapsed_ms: i32,
+    details: serde_json::Value,
+}
+
+impl ProbeOutcome {
+    fn single(dependency: Dependency, start: Instant, result: Result<(), String>) -> Self {
+        Self {
+            target: dependency.as_str().to_string(),
+            label: None,
+            result,
+            elapsed_ms: start.elapsed().as_millis() as i32,
+            details: serde_json::Value::Null,
+        }
+    }
+
+    fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Dependency checker that runs in the background
+pub struct DependencyChecker {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    omada_manager: Arc<OmadaManager>,
+    aranea_client: Arc<AraneaClient>,
+    client: reqwest::Client,
+    failures: RwLock<FailureTracker>,
+}
+
+impl DependencyChecker {
+    pub fn new(
+        app_state: AppState,
+        notifier: Arc<DiscordNotifier>,
+        omada_manager: Arc<OmadaManager>,
+        aranea_client: Arc<AraneaClient>,
+    ) -> Self {
+        Self {
+            app_state,
+            notifier,
+            omada_manager,
+            aranea_client,
+            client: reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(3))
+                .build()
+                .unwrap(),
+            failures: RwLock::new(FailureTracker::default()),
+        }
+    }
+
+    /// Start the probe loop
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting dependency checker...");
+
+        let settings = DependencySettings::load(&self.app_state).await;
+        let mut interval_timer = interval(Duration::from_secs(settings.interval_sec));
+
+        loop {
+            interval_timer.tick().await;
+            let settings = DependencySettings::load(&self.app_state).await;
+            self.check_all(&settings).await;
+        }
+    }
+
+    /// Run every enabled probe once and record the results
+    pub async fn check_all(&self, settings: &DependencySettings) {
+        let probe_timeout = Duration::from_millis(settings.timeout_ms);
+
+        for dependency in Dependency::ALL {
+            if !settings.enabled(dependency) {
+                continue;
+            }
+            let outcomes = match dependency {
+                Dependency::Mysql => vec![self.probe_mysql(probe_timeout).await],
+                Dependency::Mongodb => vec![self.probe_mongodb(settings, probe_timeout).await],
+                Dependency::Omada => self.probe_omada(probe_timeout).await,
+                Dependency::Openwrt => self.probe_openwrt(probe_timeout).await,
+                Dependency::Aranea => self.probe_aranea(probe_timeout).await.into_iter().collect(),
+                Dependency::Discord => self
+                    .probe_discord(probe_timeout)
+                    .await
+                    .into_iter()
+                    .collect(),
+            };
+            for outcome in outcomes {
+                self.record(dependency, outcome, settings.failure_threshold)
+                    .await;
+            }
+        }
+
+        if let Err(e) = self
+            .app_state
+            .mongo
+            .purge_dependencies_health(Utc::now() - RETENTION)
+            .await
+        {
+            tracing::warn!("Failed to purge dependency health records: {}", e);
+        }
+    }
+
+    async fn record(&self, dependency: Dependency, outcome: ProbeOutcome, threshold: u32) {
+        let healthy = outcome.result.is_ok();
+        let (count, transition) =
+            self.failures
+                .write()
+                .await
+                .record(dependency, &outcome.target, healthy, threshold);
+
+        let record = DependencyHealth {
+            timestamp: Utc::now(),
+            dependency,
+            target: outcome.target,
+            label: outcome.label,
+            healthy,
+            response_time_ms: healthy.then_some(outcome.elapsed_ms),
+            error: outcome.result.err(),
+            consecutive_failures: count,
+            details: outcome.details,
+        };
+        let name = record
+            .label
+            .as_deref()
+            .unwrap_or(&record.target)
+            .to_string();
+
+        if let Some(error) = &record.error {
+            tracing::warn!(
+                "Dependency {} ({}) unhealthy: {} (consecutive failures = {})",
+                dependency.as_str(),
+                name,
+                error,
+                count
+            );
+        }
+        match transition {
+            Transition::Failed => {
+                self.notifier
+                    .notify_dependency_failure(
+                        dependency.as_str(),
+                        &name,
+                        count,
+                        record.error.as_deref().unwrap_or_default(),
+                    )
+                    .await
+            }
+            Transition::Recovered => {
+                tracing::info!("Dependency {} ({}) recovered", dependency.as_str(), name);
+                self.notifier
+                    .notify_dependency_recovery(dependency.as_str(), &name)
+                    .await
+            }
+            Transition::None => {}
+        }
+
+        if let Err(e) = self.app_state.mongo.save_dependency_health(&record).await {
+            tracing::warn!("Failed to save dependency health: {}", e);
+        }
+    }
+
+    async fn probe_mysql(&self, probe_timeout: Duration) -> ProbeOutcome {
+        let mysql = &self.app_state.mysql;
+        let start = Instant::now();
+        let result = timeout(probe_timeout, mysql.ping())
+            .await
+            .unwrap_or_else(|_| Err("timeout".to_string()));
+        let pool = mysql.pool();
+        ProbeOutcome::single(Dependency::Mysql, start, result).with_details(serde_json::json!({
+            "pool_size": pool.size(),
+            "pool_idle": pool.num_idle(),
+            "pool_max": pool.options().get_max_connections(),
+        }))
+    }
+
+    async fn probe_mongodb(
+        &self,
+        settings: &DependencySettings,
+        probe_timeout: Duration,
+    ) -> ProbeOutcome {
+        let mongo = &self.app_state.mongo;
+        let start = Instant::now();
+        let probe = async {
+            mongo.ping().await?;
+            mongo.storage_stats().await
+        };
+        match timeout(probe_timeout, probe).await {
+            Ok(Ok(storage)) => {
+                let verdict = check_mongo_storage(
+                    &storage,
+                    settings.mongo_disk_max_percent,
+                    settings.mongo_storage_max_mb,
+                );
+                ProbeOutcome::single(Dependency::Mongodb, start, verdict).with_details(
+                    serde_json::json!({
+                        "storage_size": storage.storage_size,
+                        "data_size": storage.data_size,
+                        "fs_used_size": storage.fs_used_size,
+                        "fs_total_size": storage.fs_total_size,
+                        "fs_used_percent": storage.fs_used_percent(),
+                    }),
+                )
+            }
+            Ok(Err(e)) => ProbeOutcome::single(Dependency::Mongodb, start, Err(e)),
+            Err(_) => ProbeOutcome::single(Dependency::Mongodb, start, Err("timeout".into())),
+        }
+    }
+
+    async fn probe_omada(&self, probe_timeout: Duration) -> Vec<ProbeOutcome> {
+        let controllers = match self.app_state.mongo.list_omada_controllers().await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!("Dependency probe: cannot list Omada controllers: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut outcomes = Vec::new();
+        for ctrl in controllers {
+            let start = Instant::now();
+            let result = match self.omada_manager.get_client(&ctrl.controller_id).await {
+                Some(client) => timeout(probe_timeout, client.refresh_token())
+                    .await
+                    .unwrap_or_else(|_| Err("timeout".to_string())),
+                None => Err("Controller not loaded".to_string()),
+            };
+            outcomes.push(ProbeOutcome {
+                target: ctrl.controller_id,
+                label: Some(ctrl.display_name),
+                result,
+                elapsed_ms: start.elapsed().as_millis() as i32,
+                details: serde_json::json!({ "base_url": ctrl.base_url }),
+            });
+        }
+        outcomes
+    }
+
+    async fn probe_openwrt(&self, probe_timeout: Duration) -> Vec<ProbeOutcome> {
+        let routers = match self.app_state.mongo.list_openwrt_routers().await {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::warn!("Dependency probe: cannot list OpenWrt routers: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut outcomes = Vec::new();
+        for router in routers {
+            let start = Instant::now();
+            let banner = timeout(probe_timeout, read_banner(&router.ip, router.port))
+                .await
+                .unwrap_or_else(|_| Err("timeout".to_string()));
+            let (result, details) = match banner {
+                Ok(bytes) if is_ssh_banner(&bytes) => {
+                    let banner = String::from_utf8_lossy(&bytes);
+                    let line = banner.lines().next().unwrap_or_default().to_string();
+                    (Ok(()), serde_json::json!({ "banner": line }))
+                }
+                Ok(_) => (
+                    Err("Port does not answer with an SSH banner".to_string()),
+                    serde_json::Value::Null,
+                ),
+                Err(e) => (Err(e), serde_json::Value::Null),
+            };
+            outcomes.push(ProbeOutcome {
+                target: router.router_id,
+                label: Some(router.display_name),
+                result,
+                elapsed_ms: start.elapsed().as_millis() as i32,
+                details,
+            });
+        }
+        outcomes
+    }
+
+    async fn probe_aranea(&self, probe_timeout: Duration) -> Option<ProbeOutcome> {
+        if !self.aranea_client.is_configured() {
+            return None;
+        }
+        let start = Instant::now();
+        let outcome = match timeout(probe_timeout, self.aranea_client.check_health()).await {
+            Ok(health) => {
+                let result = match (health.reachable, health.error) {
+                    (Some(true), _) => Ok(()),
+                    (_, error) => Err(error.unwrap_or_else(|| "Unreachable".to_string())),
+                };
+                ProbeOutcome::single(Dependency::Aranea, start, result).with_details(
+                    serde_json::json!({
+                        "endpoint": health.endpoint,
+                        "recent": health.recent,
+                    }),
+                )
+            }
+            Err(_) => ProbeOutcome::single(Dependency::Aranea, start, Err("timeout".to_string()))
+                .with_details(serde_json::json!({ "recent": self.aranea_client.call_stats() })),
+        };
+        Some(outcome)
+    }
+
+    async fn probe_discord(&self, probe_timeout: Duration) -> Option<ProbeOutcome> {
+        let url = self
+            .app_state
+            .mysql
+            .get_discord_webhook_url()
+            .await
+            .ok()
+            .flatten()
+            .filter(|u| !u.is_empty())?;
+        let start = Instant::now();
+        let result = self
+            .client
+            .get(&url)
+            .timeout(probe_timeout)
+            .send()
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| match r.status() {
+                s if s.is_success() => Ok(()),
+                s => Err(format!("HTTP {}", s.as_u16())),
+            });
+        Some(ProbeOutcome::single(Dependency::Discord, start, result))
+    }
+}
+
+/// Connect and read what the server sends first (SSH servers identify
+/// themselves before the client says anything)
+async fn read_banner(ip: &str, port: u16) -> Result<Vec<u8>, String> {
+    let mut stream = TcpStream::connect((ip, port))
+        .await
+        .map_err(|e| format!("Connect failed: {}", e))?;
+    let mut buf = vec![0u8; 255];
+    let n = stream
+        .read(&mut buf)
+        .await
+        .map_err(|e| format!("Read failed: {}", e))?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_probes_are_parsed_leniently() {
+        assert_eq!(
+            parse_disabled(" Discord ,openwrt,,bogus"),
+            vec![Dependency::Discord, Dependency::Openwrt]
+        );
+        let settings = DependencySettings {
+            disabled: parse_disabled("aranea"),
+            ..Default::default()
+        };
+        assert!(!settings.enabled(Dependency::Aranea));
+        assert!(settings.enabled(Dependency::Mongodb));
+    }
+
+    #[test]
+    fn mongo_storage_thresholds() {
+        let storage = MongoStorage {
+            storage_size: 300 * 1024 * 1024,
+            data_size: 0,
+            fs_used_size: Some(95),
+            fs_total_size: Some(100),
+        };
+        assert!(check_mongo_storage(&storage, 90.0, 0)
+            .unwrap_err()
+            .contains("95.0% full"));
+        assert!(check_mongo_storage(&storage, 96.0, 0).is_ok());
+        assert!(check_mongo_storage(&storage, 96.0, 200).is_err());
+        assert!(check_mongo_storage(&storage, 96.0, 400).is_ok());
+
+        // Older servers do not report filesystem sizes
+        let unknown_fs = MongoStorage {
+            fs_used_size: None,
+            fs_total_size: None,
+            ..storage
+        };
+        assert!(check_mongo_storage(&unknown_fs, 1.0, 0).is_ok());
+    }
+
+    #[test]
+    fn notifications_fire_on_transitions_only() {
+        let mut tracker = FailureTracker::default();
+        let db = Dependency::Mongodb;
+        assert_eq!(
+            tracker.record(db, "mongodb", false, 2),
+            (1, Transition::None)
+        );
+        assert_eq!(
+            tracker.record(db, "mongodb", false, 2),
+            (2, Transition::Failed)
+        );
+        assert_eq!(
+            tracker.record(db, "mongodb", false, 2),
+            (3, Transition::None)
+        );
+        // Other targets are tracked separately
+        assert_eq!(
+            tracker.record(Dependency::Omada, "c1", true, 2),
+            (0, Transition::None)
+        );
+        assert_eq!(
+            tracker.record(db, "mongodb", true, 2),
+            (0, Transition::Recovered)
+        );
+        // A blip below the threshold recovers silently
+        tracker.record(db, "mongodb", false, 2);
+        assert_eq!(
+            tracker.record(db, "mongodb", true, 2),
+            (0, Transition::None)
+        );
+    }
+
+    #[test]
+    fn ssh_banner_detection() {
+        assert!(is_ssh_banner(b"SSH-2.0-dropbear_2022.83\r\n"));
+        assert!(!is_ssh_banner(b"HTTP/1.1 400 Bad Request"));
+        assert!(!is_ssh_banner(b""));
+    }
+}
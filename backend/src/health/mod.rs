@@ -1,5 +1,14 @@
 //! Health check module
 
 mod checker;
+pub mod dependencies;
+pub mod reachability;
+mod warmup;
 
-pub use self::checker::HealthChecker;
+pub use self::checker::{
+    probe_grpc_target, probe_target, probe_url, HealthChecker, TargetProbe,
+    MAX_HEALTH_CHECK_INTERVAL_SEC, MIN_HEALTH_CHECK_INTERVAL_SEC,
+};
+pub use self::dependencies::DependencyChecker;
+pub use self::reachability::TargetProbeCache;
+pub use self::warmup::RouteWarmup;
@@ -0,0 +1,282 @@
+//! Route target reachability and subnet claims (GET /api/server-routes/analysis)
+//!
+//! Reachability is a plain TCP connect from the gateway to each distinct
+//! target host:port, run in parallel with a short timeout. Results are
+//! cached for a minute so the analysis endpoint can be polled cheaply.
+//!
+//! Subnet claims answer "which known network would this target IP land in".
+//! Known networks are the /24 around each Omada site gateway, the /24 around
+//! each OpenWrt LAN IP and the allowed addresses of Omada WireGuard peers.
+//! A target claimed by networks of more than one owner (e.g. two sites that
+//! both use 192.168.3.0/24 behind different VPNs) is ambiguous: where the
+//! traffic goes depends on the gateway's routing table, not on the route.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use ipnetwork::IpNetwork;
+use serde::Serialize;
+use tokio::net::TcpStream;
+
+/// How long a probe result is reused
+const PROBE_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Connect (and DNS) timeout per target
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Host and port a route target connects to (scheme default when omitted)
+pub fn target_host_port(target: &str) -> Option<(String, u16)> {
+    let url = url::Url::parse(target).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    Some((host, url.port_or_known_default()?))
+}
+
+/// Result of one TCP reachability probe
+#[derive(Debug, Clone, Serialize)]
+pub struct TcpProbe {
+    pub host: String,
+    pub port: u16,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    /// Address actually connected to (hostnames are resolved first)
+    pub resolved_ip: Option<String>,
+    pub error: Option<String>,
+    pub probed_at: String,
+    /// Served from the one-minute cache
+    pub cached: bool,
+}
+
+async fn probe_tcp(host: String, port: u16) -> TcpProbe {
+    let start = Instant::now();
+    let result: Result<SocketAddr, String> = async {
+        let addr = match host.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => tokio::time::timeout(
+                PROBE_TIMEOUT,
+                tokio::net::lookup_host((host.as_str(), port)),
+            )
+            .await
+            .map_err(|_| "DNS lookup timed out".to_string())?
+            .map_err(|e| format!("DNS lookup failed: {}", e))?
+            .next()
+            .ok_or_else(|| "DNS lookup returned no addresses".to_string())?,
+        };
+        let remaining = PROBE_TIMEOUT.saturating_sub(start.elapsed());
+        match tokio::time::timeout(remaining, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => Ok(addr),
+            Ok(Err(e)) => Err(format!("connect to {} failed: {}", addr, e)),
+            Err(_) => Err(format!(
+                "connect to {} timed out after {}ms",
+                addr,
+                PROBE_TIMEOUT.as_millis()
+            )),
+        }
+    }
+    .await;
+
+    let (reachable, resolved_ip, error) = match result {
+        Ok(addr) => (true, Some(addr.ip().to_string()), None),
+        Err(e) => (
+            false,
+            host.parse::<IpAddr>().ok().map(|ip| ip.to_string()),
+            Some(e),
+        ),
+    };
+    TcpProbe {
+        host,
+        port,
+        reachable,
+        latency_ms: reachable.then(|| start.elapsed().as_millis() as u64),
+        resolved_ip,
+        error,
+        probed_at: Utc::now().to_rfc3339(),
+        cached: false,
+    }
+}
+
+/// Probe results by host:port, shared by all API requests
+#[derive(Default)]
+pub struct TargetProbeCache {
+    entries: Mutex<HashMap<(String, u16), (Instant, TcpProbe)>>,
+}
+
+impl TargetProbeCache {
+    /// Probe every target in parallel, reusing results younger than a minute
+    /// unless `refresh` is set
+    pub async fn probe_all(&self, targets: &[(String, u16)], refresh: bool) -> Vec<TcpProbe> {
+        let mut results = Vec::new();
+        let mut pending = Vec::new();
+        {
+            let entries = self.lock();
+            for key in targets {
+                match entries.get(key) {
+                    Some((at, probe)) if !refresh && at.elapsed() < PROBE_CACHE_TTL => {
+                        results.push(TcpProbe {
+                            cached: true,
+                            ..probe.clone()
+                        });
+                    }
+                    _ => pending.push(key.clone()),
+                }
+            }
+        }
+
+        let fresh = futures::future::join_all(
+            pending
+                .into_iter()
+                .map(|(host, port)| probe_tcp(host, port)),
+        )
+        .await;
+
+        let mut entries = self.lock();
+        entries.retain(|_, (at, _)| at.elapsed() < PROBE_CACHE_TTL);
+        for probe in fresh {
+            entries.insert(
+                (probe.host.clone(), probe.port),
+                (Instant::now(), probe.clone()),
+            );
+            results.push(probe);
+        }
+        results
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, u16), (Instant, TcpProbe)>> {
+        self.entries.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// A network some managed device routes to
+#[derive(Debug, Clone, Serialize)]
+pub struct KnownNetwork {
+    pub network: String,
+    /// "omada_site" | "openwrt_lan" | "omada_wg_peer"
+    pub source: &'static str,
+    /// Who owns the network (site, router or peer); claims by one owner never conflict
+    pub owner: String,
+    pub label: String,
+    #[serde(skip)]
+    net: IpNetwork,
+}
+
+impl KnownNetwork {
+    /// `network` is a CIDR or a bare address (widened to `default_prefix`)
+    pub fn new(
+        network: &str,
+        default_prefix: u8,
+        source: &'static str,
+        owner: String,
+        label: String,
+    ) -> Option<Self> {
+        let parsed: IpNetwork = match network.trim().parse::<IpAddr>() {
+            Ok(ip) => IpNetwork::new(ip, default_prefix.min(max_prefix(ip))).ok()?,
+            Err(_) => network.trim().parse().ok()?,
+        };
+        // Canonical network address; a default route claims nothing in particular
+        let net = IpNetwork::new(parsed.network(), parsed.prefix()).ok()?;
+        (net.prefix() > 0).then(|| Self {
+            network: net.to_string(),
+            source,
+            owner,
+            label,
+            net,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.net.contains(ip)
+    }
+}
+
+fn max_prefix(ip: IpAddr) -> u8 {
+    if ip.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+/// Networks containing `ip`, most specific first, one per owner and network
+pub fn subnet_claims(ip: IpAddr, networks: &[KnownNetwork]) -> Vec<KnownNetwork> {
+    let mut claims: Vec<KnownNetwork> = Vec::new();
+    for network in networks.iter().filter(|n| n.contains(ip)) {
+        if !claims
+            .iter()
+            .any(|c| c.owner == network.owner && c.network == network.network)
+        {
+            claims.push(network.clone());
+        }
+    }
+    claims.sort_by_key(|c| std::cmp::Reverse(c.net.prefix()));
+    claims
+}
+
+/// Claimed by more than one owner
+pub fn is_ambiguous(claims: &[KnownNetwork]) -> bool {
+    claims.iter().any(|c| c.owner != claims[0].owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_host_ports() {
+        assert_eq!(
+            target_host_port("http://192.168.3.10:8080/app"),
+            Some(("192.168.3.10".to_string(), 8080))
+        );
+        assert_eq!(
+            target_host_port("https://backend.local"),
+            Some(("backend.local".to_string(), 443))
+        );
+        assert_eq!(
+            target_host_port("http://[fd00::5]/"),
+            Some(("fd00::5".to_string(), 80))
+        );
+        assert_eq!(target_host_port("not a url"), None);
+    }
+
+    #[test]
+    fn overlapping_sites_are_ambiguous() {
+        let net = |cidr: &str, source, owner: &str| {
+            KnownNetwork::new(cidr, 24, source, owner.to_string(), owner.to_string()).unwrap()
+        };
+        let networks = vec![
+            net("192.168.3.1", "omada_site", "omada:c1/tokyo"),
+            // Same site seen twice (two gateways) is one claim
+            net("192.168.3.254", "omada_site", "omada:c1/tokyo"),
+            net("192.168.3.0/24", "omada_wg_peer", "wg:osaka"),
+            net("10.0.0.0/8", "omada_wg_peer", "wg:dc"),
+            net("10.20.0.1", "openwrt_lan", "openwrt:r1"),
+        ];
+        assert!(KnownNetwork::new(
+            "0.0.0.0/0",
+            24,
+            "omada_wg_peer",
+            String::new(),
+            String::new()
+        )
+        .is_none());
+
+        let claims = subnet_claims("192.168.3.10".parse().unwrap(), &networks);
+        assert_eq!(claims.len(), 2);
+        assert!(is_ambiguous(&claims));
+
+        let claims = subnet_claims("10.20.0.5".parse().unwrap(), &networks);
+        assert_eq!(claims[0].network, "10.20.0.0/24");
+        assert_eq!(claims[1].network, "10.0.0.0/8");
+        assert!(is_ambiguous(&claims));
+
+        let claims = subnet_claims("10.9.0.5".parse().unwrap(), &networks);
+        assert_eq!(claims.len(), 1);
+        assert!(!is_ambiguous(&claims));
+
+        assert!(subnet_claims("172.16.0.1".parse().unwrap(), &networks).is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+//! Route activation warm-up
+//!
+//! A route activated with `?warm=true` after a failed pre-check is "warming":
+//! the proxy answers 503 for it until the health checker sees the target
+//! healthy. Activations also nudge the checker so the route is probed right
+//! away instead of after a full interval.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+/// Warming routes plus the health checker nudge, shared with the proxy
+#[derive(Default)]
+pub struct RouteWarmup {
+    warming: Mutex<HashSet<i32>>,
+    nudge: Notify,
+}
+
+impl RouteWarmup {
+    pub fn start(&self, route_id: i32) {
+        self.lock().insert(route_id);
+    }
+
+    pub fn is_warming(&self, route_id: i32) -> bool {
+        self.lock().contains(&route_id)
+    }
+
+    /// Clear the warming state; true when the route was warming
+    pub fn finish(&self, route_id: i32) -> bool {
+        self.lock().remove(&route_id)
+    }
+
+    /// Ask the health checker to run its next cycle now
+    pub fn nudge(&self) {
+        self.nudge.notify_one();
+    }
+
+    /// Resolves when a nudge is pending (a nudge sent while the checker is busy is kept)
+    pub async fn nudged(&self) {
+        self.nudge.notified().await;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<i32>> {
+        self.warming.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warming_routes_clear_once() {
+        let warmup = RouteWarmup::default();
+        warmup.start(7);
+        warmup.start(3);
+        assert!(warmup.is_warming(7));
+
+        assert!(warmup.finish(7));
+        assert!(!warmup.finish(7));
+        assert!(!warmup.is_warming(7));
+        assert!(warmup.is_warming(3));
+    }
+}
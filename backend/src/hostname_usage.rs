@@ -0,0 +1,420 @@
+//! Monthly usage per DDNS hostname (collection `hostname_usage_monthly`)
+//!
+//! Billing view of the access logs: per UTC calendar month and DDNS hostname
+//! (the hostname of the route's DDNS config, `UNASSIGNED` for routes without
+//! one) the requests, bytes in (request) and out (response), 4xx/5xx
+//! responses and the busiest hour. Requests no route matched are not billed.
+//!
+//! A leader-only job refreshes the current month every hour, and the
+//! previous one until `CLOSE_GRACE_HOURS` after it ended; it then records the
+//! month as complete in `rollup_state` (as its last day) and never touches it
+//! again, so a closed month reads instantly and stays stable. Hostnames are
+//! resolved when a month is rolled up, soft-deleted routes included.
+//!
+//! Every rollup replaces all rows of its month, so re-running one is safe.
+//! After late log ingestion an admin re-runs a past month through
+//! POST /api/dashboard/hostname-usage/rollup, which is audit-logged with the
+//! month's totals before and after.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use crate::db::AppState;
+
+/// Rollup job id in `rollup_state`
+pub const ROLLUP_JOB: &str = "hostname_usage_monthly";
+/// Bucket of routes without a DDNS config
+pub const UNASSIGNED: &str = "unassigned";
+/// Rollup interval (the current month is at most this stale)
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(3600);
+/// A month is closed this long after it ended (late log ingestion)
+const CLOSE_GRACE_HOURS: i64 = 6;
+/// Months rolled up on the first run (current one included)
+const INITIAL_MONTHS: u32 = 3;
+
+/// One hostname's usage for one month
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostnameUsage {
+    /// "YYYY-MM"
+    pub month: String,
+    pub hostname: String,
+    #[serde(default)]
+    pub requests: u64,
+    /// Request bytes
+    #[serde(default)]
+    pub bytes_in: u64,
+    /// Response bytes
+    #[serde(default)]
+    pub bytes_out: u64,
+    #[serde(default)]
+    pub errors_4xx: u64,
+    #[serde(default)]
+    pub errors_5xx: u64,
+    /// Requests in the busiest hour of the month
+    #[serde(default)]
+    pub peak_hourly_requests: u64,
+    /// That hour, "YYYY-MM-DDTHH" (UTC)
+    #[serde(default)]
+    pub peak_hour: Option<String>,
+    /// Routes counted under the hostname
+    #[serde(default)]
+    pub route_ids: Vec<i32>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+/// One route's access log totals for one hour
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RouteHour {
+    pub route_id: i32,
+    /// "YYYY-MM-DDTHH"
+    pub hour: String,
+    pub requests: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub errors_4xx: u64,
+    pub errors_5xx: u64,
+}
+
+/// All hostnames of a month added up
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub errors_4xx: u64,
+    pub errors_5xx: u64,
+}
+
+impl UsageTotals {
+    pub fn of(rows: &[HostnameUsage]) -> Self {
+        rows.iter().fold(Self::default(), |mut t, row| {
+            t.requests += row.requests;
+            t.bytes_in += row.bytes_in;
+            t.bytes_out += row.bytes_out;
+            t.errors_4xx += row.errors_4xx;
+            t.errors_5xx += row.errors_5xx;
+            t
+        })
+    }
+}
+
+/// Month key as stored ("YYYY-MM"); also a prefix of stored timestamps
+pub fn month_key(month: NaiveDate) -> String {
+    month.format("%Y-%m").to_string()
+}
+
+/// First day of a "YYYY-MM" month
+pub fn parse_month(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", s.trim()), "%Y-%m-%d").ok()
+}
+
+pub fn first_of_month(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).unwrap_or(day)
+}
+
+pub fn next_month(month: NaiveDate) -> NaiveDate {
+    first_of_month(month)
+        .checked_add_months(chrono::Months::new(1))
+        .unwrap_or(month)
+}
+
+/// Whether a month is past its close grace at `now`
+pub fn is_closed(month: NaiveDate, now: DateTime<Utc>) -> bool {
+    let end = next_month(month)
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    now >= end + chrono::Duration::hours(CLOSE_GRACE_HOURS)
+}
+
+/// Months to roll up: every month after the last completed one (or the
+/// initial backfill) through the current month, first days, oldest first
+pub fn months_to_roll_up(completed: Option<NaiveDate>, now: DateTime<Utc>) -> Vec<NaiveDate> {
+    let current = first_of_month(now.date_naive());
+    let first = match completed {
+        Some(day) => next_month(day),
+        None => current
+            .checked_sub_months(chrono::Months::new(INITIAL_MONTHS - 1))
+            .unwrap_or(current),
+    };
+    let mut months = Vec::new();
+    let mut month = first;
+    while month <= current {
+        months.push(month);
+        month = next_month(month);
+    }
+    months
+}
+
+/// First days of the months overlapping `first..=last`
+pub fn months_in_span(first: NaiveDate, last: NaiveDate) -> Vec<NaiveDate> {
+    let mut months = Vec::new();
+    let mut month = first_of_month(first);
+    while month <= last {
+        months.push(month);
+        month = next_month(month);
+    }
+    months
+}
+
+/// Rows of one month from per-route hourly totals; routes missing from
+/// `hostnames` (no DDNS config, or purged) go to `UNASSIGNED`. Busiest
+/// hostname first.
+pub fn combine(
+    month: &str,
+    hours: &[RouteHour],
+    hostnames: &HashMap<i32, String>,
+) -> Vec<HostnameUsage> {
+    let mut rows: BTreeMap<&str, HostnameUsage> = BTreeMap::new();
+    let mut hourly: HashMap<&str, BTreeMap<&str, u64>> = HashMap::new();
+    let mut routes: HashMap<&str, BTreeSet<i32>> = HashMap::new();
+
+    for h in hours {
+        let hostname = hostnames
+            .get(&h.route_id)
+            .map(String::as_str)
+            .unwrap_or(UNASSIGNED);
+        let row = rows.entry(hostname).or_insert_with(|| HostnameUsage {
+            month: month.to_string(),
+            hostname: hostname.to_string(),
+            ..Default::default()
+        });
+        row.requests += h.requests;
+        row.bytes_in += h.bytes_in;
+        row.bytes_out += h.bytes_out;
+        row.errors_4xx += h.errors_4xx;
+        row.errors_5xx += h.errors_5xx;
+        *hourly
+            .entry(hostname)
+            .or_default()
+            .entry(h.hour.as_str())
+            .or_default() += h.requests;
+        routes.entry(hostname).or_default().insert(h.route_id);
+    }
+
+    let mut rows: Vec<HostnameUsage> = rows
+        .into_iter()
+        .map(|(hostname, mut row)| {
+            // Earliest hour wins a tie
+            if let Some((hour, requests)) = hourly.get(hostname).and_then(|hours| {
+                hours
+                    .iter()
+                    .fold(None, |best: Option<(&str, u64)>, (hour, n)| match best {
+                        Some((_, top)) if top >= *n => best,
+                        _ => Some((*hour, *n)),
+                    })
+            }) {
+                row.peak_hourly_requests = requests;
+                row.peak_hour = Some(hour.to_string());
+            }
+            row.route_ids = routes
+                .get(hostname)
+                .map(|ids| ids.iter().copied().collect())
+                .unwrap_or_default();
+            row
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.requests
+            .cmp(&a.requests)
+            .then(a.hostname.cmp(&b.hostname))
+    });
+    rows
+}
+
+/// Recompute one month's rows (replacing them) and return them
+pub async fn roll_up_month(
+    app_state: &AppState,
+    month: NaiveDate,
+) -> Result<Vec<HostnameUsage>, String> {
+    let hostnames: HashMap<i32, String> = app_state
+        .mysql
+        .list_route_hostnames()
+        .await
+        .map_err(|e| format!("Read route hostnames: {}", e))?
+        .into_iter()
+        .filter_map(|(id, hostname)| Some((id, hostname?)))
+        .collect();
+    let key = month_key(month);
+    let hours = app_state.mongo.aggregate_route_hours(month).await?;
+    let rows = combine(&key, &hours, &hostnames);
+    app_state.mongo.replace_hostname_usage(&key, &rows).await?;
+    Ok(rows)
+}
+
+/// Recompute the already rolled-up months overlapping `first..=last` (access
+/// logs of that span were deleted); returns the number of months
+pub async fn recompute_months(
+    app_state: &AppState,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Result<usize, String> {
+    let mut recomputed = 0;
+    for month in months_in_span(first, last) {
+        if app_state
+            .mongo
+            .get_hostname_usage(&month_key(month))
+            .await?
+            .is_empty()
+        {
+            continue;
+        }
+        roll_up_month(app_state, month).await?;
+        recomputed += 1;
+    }
+    Ok(recomputed)
+}
+
+/// Background rollup job
+pub struct HostnameUsageRollup {
+    app_state: AppState,
+}
+
+impl HostnameUsageRollup {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    /// Start the rollup loop (hourly)
+    pub async fn start(&self) {
+        tracing::info!("Starting hostname usage rollup...");
+
+        let mut interval_timer = interval(ROLLUP_INTERVAL);
+        loop {
+            interval_timer.tick().await;
+            if let Err(e) = self.run_once(Utc::now()).await {
+                tracing::warn!("Hostname usage rollup failed: {}", e);
+            }
+        }
+    }
+
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<(), String> {
+        let mongo = &self.app_state.mongo;
+        let completed = mongo.rollup_completed_day(ROLLUP_JOB).await?;
+        let mut closing = true;
+        for month in months_to_roll_up(completed, now) {
+            let rows = roll_up_month(&self.app_state, month).await?;
+            // Only consecutive closed months advance the marker
+            closing &= is_closed(month, now);
+            if closing {
+                let last_day = next_month(month).pred_opt().unwrap_or(month);
+                mongo.set_rollup_completed_day(ROLLUP_JOB, last_day).await?;
+                tracing::info!(
+                    "Hostname usage: closed {} ({} hostnames)",
+                    month_key(month),
+                    rows.len()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn hour(route_id: i32, hour: &str, requests: u64, errors_5xx: u64) -> RouteHour {
+        RouteHour {
+            route_id,
+            hour: hour.to_string(),
+            requests,
+            bytes_in: requests * 10,
+            bytes_out: requests * 100,
+            errors_5xx,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn months_resume_after_last_closed_month() {
+        let now = at("2026-03-02T10:00:00Z");
+        assert_eq!(
+            months_to_roll_up(Some(date("2026-01-31")), now),
+            vec![date("2026-02-01"), date("2026-03-01")]
+        );
+        assert_eq!(
+            months_to_roll_up(None, now),
+            vec![date("2026-01-01"), date("2026-02-01"), date("2026-03-01")]
+        );
+        assert_eq!(
+            months_to_roll_up(Some(date("2026-02-28")), now),
+            vec![date("2026-03-01")]
+        );
+
+        // February closes CLOSE_GRACE_HOURS into March
+        assert!(!is_closed(date("2026-02-01"), at("2026-03-01T05:59:00Z")));
+        assert!(is_closed(date("2026-02-01"), at("2026-03-01T06:00:00Z")));
+        assert!(!is_closed(date("2026-03-01"), now));
+    }
+
+    #[test]
+    fn deleted_span_covers_every_touched_month() {
+        assert_eq!(
+            months_in_span(date("2026-01-31"), date("2026-03-01")),
+            vec![date("2026-01-01"), date("2026-02-01"), date("2026-03-01")]
+        );
+        assert_eq!(
+            months_in_span(date("2026-02-10"), date("2026-02-11")),
+            vec![date("2026-02-01")]
+        );
+    }
+
+    #[test]
+    fn month_keys() {
+        assert_eq!(parse_month("2026-09"), Some(date("2026-09-01")));
+        assert_eq!(parse_month("2026-13"), None);
+        assert_eq!(parse_month("2026-09-05"), None);
+        assert_eq!(next_month(date("2026-12-01")), date("2027-01-01"));
+        assert_eq!(month_key(date("2026-09-01")), "2026-09");
+    }
+
+    #[test]
+    fn routes_are_grouped_by_hostname_with_peak_hour() {
+        let hostnames = HashMap::from([
+            (1, "a.example.com".to_string()),
+            (2, "a.example.com".to_string()),
+            (3, "b.example.com".to_string()),
+        ]);
+        let hours = vec![
+            hour(1, "2026-09-01T10", 30, 1),
+            hour(2, "2026-09-01T10", 20, 0),
+            hour(1, "2026-09-02T08", 40, 2),
+            hour(3, "2026-09-01T10", 5, 0),
+            // No DDNS config (or purged route)
+            hour(9, "2026-09-03T00", 7, 0),
+        ];
+        let rows = combine("2026-09", &hours, &hostnames);
+
+        let names: Vec<_> = rows.iter().map(|r| r.hostname.as_str()).collect();
+        assert_eq!(names, ["a.example.com", UNASSIGNED, "b.example.com"]);
+
+        let a = &rows[0];
+        assert_eq!(a.requests, 90);
+        assert_eq!(a.bytes_in, 900);
+        assert_eq!(a.bytes_out, 9000);
+        assert_eq!(a.errors_5xx, 3);
+        // Both routes share 10:00 on the 1st (50) which beats 40 on the 2nd
+        assert_eq!(a.peak_hourly_requests, 50);
+        assert_eq!(a.peak_hour.as_deref(), Some("2026-09-01T10"));
+        assert_eq!(a.route_ids, [1, 2]);
+        assert_eq!(rows[1].route_ids, [9]);
+
+        let totals = UsageTotals::of(&rows);
+        assert_eq!(totals.requests, 102);
+        assert_eq!(totals.errors_5xx, 3);
+    }
+}
@@ -0,0 +1,194 @@
+//! IP address parsing and normalization
+//!
+//! The same address has many spellings: `2001:DB8::1` and
+//! `2001:db8:0:0::1`, `[::1]`, `fe80::1%eth0`, or `::ffff:192.0.2.1` for an
+//! IPv4 client seen through a dual-stack socket. Ingestion (access logs,
+//! security events, blocked IPs) stores [`canonical_ip`]: IPv4-mapped
+//! addresses unwrapped to IPv4, IPv6 in RFC 5952 form. Rows stored before
+//! this keep their raw spelling, so lookups by address match every form in
+//! [`ip_variants`].
+
+use std::net::{IpAddr, SocketAddr};
+
+/// Parse an address as it appears in headers, logs and API inputs:
+/// surrounding brackets, an IPv6 zone (`%eth0`) or a port are ignored and
+/// IPv4-mapped IPv6 becomes IPv4
+pub fn parse_ip(input: &str) -> Option<IpAddr> {
+    let trimmed = input.trim();
+    let addr = match trimmed.parse::<IpAddr>() {
+        Ok(addr) => addr,
+        Err(_) => match trimmed.parse::<SocketAddr>() {
+            Ok(socket) => socket.ip(),
+            Err(_) => {
+                let bare = trimmed.trim_start_matches('[').trim_end_matches(']');
+                let bare = bare.split('%').next().unwrap_or(bare);
+                bare.parse::<IpAddr>().ok()?
+            }
+        },
+    };
+    Some(addr.to_canonical())
+}
+
+/// Canonical spelling of an address; input that is not an address is
+/// returned trimmed (client IPs from headers are stored whatever they are)
+pub fn canonical_ip(input: &str) -> String {
+    match parse_ip(input) {
+        Some(addr) => addr.to_string(),
+        None => input.trim().to_string(),
+    }
+}
+
+/// Spellings a stored address may have: the input as given, the canonical
+/// form and, for IPv4, its IPv4-mapped IPv6 form
+pub fn ip_variants(input: &str) -> Vec<String> {
+    let raw = input.trim().to_string();
+    let mut variants = vec![raw.clone()];
+    if let Some(addr) = parse_ip(&raw) {
+        let canonical = addr.to_string();
+        if canonical != raw {
+            variants.push(canonical);
+        }
+        if let IpAddr::V4(v4) = addr {
+            variants.push(format!("::ffff:{}", v4));
+        }
+    }
+    variants
+}
+
+/// Private, loopback, link-local or unspecified: RFC 1918, 127/8, 169.254/16
+/// and 0.0.0.0 for IPv4; ::1, fc00::/7 (ULA), fe80::/10 and :: for IPv6.
+/// IPv4-mapped addresses are judged as IPv4.
+pub fn is_lan(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xFE00) == 0xFC00
+                || (first & 0xFFC0) == 0xFE80
+        }
+    }
+}
+
+/// MongoDB regex (case-insensitive) matching stored LAN addresses in any
+/// spelling `is_lan` accepts, for filters over stored strings
+pub const LAN_IP_REGEX: &str = concat!(
+    r"^(::ffff:)?(10\.|172\.(1[6-9]|2[0-9]|3[01])\.|192\.168\.|127\.|169\.254\.|0\.0\.0\.0$)",
+    r"|^\[?(f[cd][0-9a-f]{2}|fe[89ab][0-9a-f]):",
+    r"|^\[?(0{0,4}:){2,7}0{0,3}1\]?$",
+    r"|^\[?::\]?$",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_notations_parse_to_one_address() {
+        for input in [
+            "2001:db8::1",
+            "2001:DB8::1",
+            "2001:0db8:0000:0000:0000:0000:0000:0001",
+            "[2001:db8::1]",
+            "[2001:db8::1]:443",
+            " 2001:db8:0:0::1 ",
+        ] {
+            assert_eq!(canonical_ip(input), "2001:db8::1", "{}", input);
+        }
+        for input in [
+            "192.0.2.1",
+            "::ffff:192.0.2.1",
+            "::FFFF:c000:201",
+            "192.0.2.1:8080",
+        ] {
+            assert_eq!(canonical_ip(input), "192.0.2.1", "{}", input);
+        }
+        assert_eq!(canonical_ip("fe80::1%eth0"), "fe80::1");
+        assert_eq!(canonical_ip(" unknown "), "unknown");
+        assert!(parse_ip("300.1.1.1").is_none());
+    }
+
+    #[test]
+    fn variants_cover_raw_canonical_and_mapped_forms() {
+        assert_eq!(
+            ip_variants("::ffff:192.0.2.1"),
+            vec!["::ffff:192.0.2.1", "192.0.2.1", "::ffff:192.0.2.1"]
+        );
+        assert_eq!(
+            ip_variants("192.0.2.1"),
+            vec!["192.0.2.1", "::ffff:192.0.2.1"]
+        );
+        assert_eq!(
+            ip_variants("2001:DB8::1"),
+            vec!["2001:DB8::1", "2001:db8::1"]
+        );
+        assert_eq!(ip_variants("n/a"), vec!["n/a"]);
+    }
+
+    #[test]
+    fn lan_detection_covers_v6_ranges() {
+        for lan in [
+            "10.1.2.3",
+            "172.31.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.10.1",
+            "::ffff:192.168.1.1",
+            "::1",
+            "fe80::1",
+            "febf::1",
+            "fc00::1",
+            "fd12:3456::1",
+        ] {
+            assert!(is_lan(parse_ip(lan).unwrap()), "{}", lan);
+        }
+        for public in [
+            "8.8.8.8",
+            "172.32.0.1",
+            "::ffff:8.8.8.8",
+            "2001:db8::1",
+            "fec0::1",
+            "fb00::1",
+        ] {
+            assert!(!is_lan(parse_ip(public).unwrap()), "{}", public);
+        }
+    }
+
+    #[test]
+    fn lan_regex_agrees_with_is_lan() {
+        let regex = regex::RegexBuilder::new(LAN_IP_REGEX)
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        for input in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.0.1",
+            "127.0.0.1",
+            "169.254.1.1",
+            "::ffff:10.0.0.1",
+            "::1",
+            "0:0:0:0:0:0:0:1",
+            "FE80::1",
+            "fd00::1",
+            "::",
+            "8.8.8.8",
+            "172.32.0.1",
+            "2001:db8::1",
+            "fec0::1",
+            "::ffff:8.8.8.8",
+            "::11",
+            "100.64.0.1",
+        ] {
+            assert_eq!(
+                regex.is_match(input),
+                is_lan(parse_ip(input).unwrap()),
+                "{}",
+                input
+            );
+        }
+    }
+}
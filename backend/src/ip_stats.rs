@@ -0,0 +1,257 @@
+//! Per-client-IP daily rollups (collection `ip_daily_stats`)
+//!
+//! A leader-only job summarizes the access logs and security events of each
+//! UTC day per source IP: requests, errors (status >= 400), distinct paths,
+//! bytes (request + response), countries and security events. Client IPs are
+//! also recorded in `ip_history` (source "client") with their first and last
+//! request.
+//!
+//! Every write sets absolute values (`$set`, `$min`, `$max`), so recomputing
+//! a day never double-counts. Completed days are tracked in `rollup_state`;
+//! a run rolls up each day after the last completed one up to yesterday,
+//! advancing the marker after every day, then refreshes today's partial row.
+//! A restart mid-run resumes at the first unfinished day. Rows older than
+//! `ip_stats_retention_days` are purged.
+
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use crate::db::mongo::ip_daily_stats::ROLLUP_JOB;
+use crate::db::AppState;
+
+/// Retention when the setting is missing
+pub const DEFAULT_RETENTION_DAYS: i32 = 90;
+/// Longest retention (and longest profile series)
+pub const MAX_RETENTION_DAYS: i32 = 365;
+/// Rollup interval (today's row is at most this stale)
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// One IP's totals for one UTC day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IpDailyStat {
+    pub ip: String,
+    /// UTC day, "YYYY-MM-DD"
+    pub day: String,
+    #[serde(default)]
+    pub requests: u64,
+    /// Requests answered with status >= 400
+    #[serde(default)]
+    pub errors: u64,
+    #[serde(default)]
+    pub distinct_paths: u64,
+    /// Request plus response bytes
+    #[serde(default)]
+    pub bytes: u64,
+    /// Country codes seen
+    #[serde(default)]
+    pub countries: Vec<String>,
+    #[serde(default)]
+    pub security_events: u64,
+}
+
+/// Day key as stored ("YYYY-MM-DD"); also a prefix of stored RFC3339 timestamps
+pub fn day_key(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+/// Days to roll up: every day after `completed` (or the retention start)
+/// through `today`; all but the last are complete
+pub fn days_to_roll_up(
+    completed: Option<NaiveDate>,
+    today: NaiveDate,
+    retention_days: i32,
+) -> Vec<NaiveDate> {
+    let oldest = today - chrono::Duration::days(retention_days.max(1) as i64 - 1);
+    let first = completed
+        .and_then(|d| d.succ_opt())
+        .map_or(oldest, |d| d.max(oldest));
+    first.iter_days().take_while(|d| *d <= today).collect()
+}
+
+/// Retained days of `first..=last` (through `today`): the rows to recompute
+/// after access logs of that span were deleted
+pub fn days_in_span(
+    first: NaiveDate,
+    last: NaiveDate,
+    today: NaiveDate,
+    retention_days: i32,
+) -> Vec<NaiveDate> {
+    let oldest = today - chrono::Duration::days(retention_days.max(1) as i64 - 1);
+    let last = last.min(today);
+    first
+        .max(oldest)
+        .iter_days()
+        .take_while(|d| *d <= last)
+        .collect()
+}
+
+/// Series of the last `days` days ending today, oldest first, zero-filled.
+/// Rows for the same day under different spellings of the address are merged.
+pub fn daily_series(
+    ip: &str,
+    stats: Vec<IpDailyStat>,
+    today: NaiveDate,
+    days: i32,
+) -> Vec<IpDailyStat> {
+    let first = today - chrono::Duration::days(days.max(1) as i64 - 1);
+    first
+        .iter_days()
+        .take_while(|d| *d <= today)
+        .map(|d| {
+            let key = day_key(d);
+            let mut merged = IpDailyStat {
+                ip: ip.to_string(),
+                day: key,
+                ..Default::default()
+            };
+            for s in stats.iter().filter(|s| s.day == merged.day) {
+                merged.requests += s.requests;
+                merged.errors += s.errors;
+                merged.distinct_paths = merged.distinct_paths.max(s.distinct_paths);
+                merged.bytes += s.bytes;
+                merged.security_events += s.security_events;
+                for country in &s.countries {
+                    if !merged.countries.contains(country) {
+                        merged.countries.push(country.clone());
+                    }
+                }
+            }
+            merged
+        })
+        .collect()
+}
+
+/// Configured retention, clamped to 1..=MAX_RETENTION_DAYS
+pub async fn retention_days(app_state: &AppState) -> i32 {
+    app_state
+        .mysql
+        .get_setting_i32("ip_stats_retention_days", DEFAULT_RETENTION_DAYS)
+        .await
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+        .clamp(1, MAX_RETENTION_DAYS)
+}
+
+/// Recompute the rows of `first..=last` (access logs of those days were
+/// deleted); returns the number of days
+pub async fn recompute_days(
+    app_state: &AppState,
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Result<usize, String> {
+    let retention = retention_days(app_state).await;
+    let days = days_in_span(first, last, Utc::now().date_naive(), retention);
+    for day in &days {
+        app_state.mongo.roll_up_ip_day(*day).await?;
+    }
+    Ok(days.len())
+}
+
+/// Background rollup job
+pub struct IpStatsRollup {
+    app_state: AppState,
+}
+
+impl IpStatsRollup {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    /// Start the rollup loop (every 10 min)
+    pub async fn start(&self) {
+        tracing::info!("Starting IP daily stats rollup...");
+
+        let mut interval_timer = interval(ROLLUP_INTERVAL);
+        loop {
+            interval_timer.tick().await;
+            if let Err(e) = self.run_once(Utc::now()).await {
+                tracing::warn!("IP daily stats rollup failed: {}", e);
+            }
+        }
+    }
+
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<(), String> {
+        let mongo = &self.app_state.mongo;
+        let retention = retention_days(&self.app_state).await;
+        let today = now.date_naive();
+
+        let completed = mongo.rollup_completed_day(ROLLUP_JOB).await?;
+        let days = days_to_roll_up(completed, today, retention);
+        for day in &days {
+            let ips = mongo.roll_up_ip_day(*day).await?;
+            if *day < today {
+                mongo.set_rollup_completed_day(ROLLUP_JOB, *day).await?;
+                tracing::info!("IP daily stats: rolled up {} ({} IPs)", day, ips);
+            }
+        }
+
+        let cutoff = today - chrono::Duration::days(retention as i64 - 1);
+        let purged = mongo.purge_ip_daily_stats(cutoff).await?;
+        if purged > 0 {
+            tracing::info!("IP daily stats: purged {} rows before {}", purged, cutoff);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn resumes_after_last_completed_day() {
+        let today = date("2026-03-02");
+        assert_eq!(
+            days_to_roll_up(Some(date("2026-02-27")), today, 90),
+            vec![date("2026-02-28"), date("2026-03-01"), date("2026-03-02")]
+        );
+        // Up to date: only today's partial row
+        assert_eq!(
+            days_to_roll_up(Some(date("2026-03-01")), today, 90),
+            vec![today]
+        );
+        // First run and long gaps are bounded by retention
+        assert_eq!(days_to_roll_up(None, today, 3).len(), 3);
+        assert_eq!(
+            days_to_roll_up(Some(date("2025-01-01")), today, 2),
+            vec![date("2026-03-01"), today]
+        );
+    }
+
+    #[test]
+    fn deleted_span_is_clipped_to_retained_days() {
+        let today = date("2026-03-02");
+        assert_eq!(
+            days_in_span(date("2026-02-28"), date("2026-03-01"), today, 90),
+            vec![date("2026-02-28"), date("2026-03-01")]
+        );
+        assert_eq!(
+            days_in_span(date("2025-01-01"), date("2026-03-05"), today, 2),
+            vec![date("2026-03-01"), today]
+        );
+        assert!(days_in_span(date("2025-01-01"), date("2025-01-02"), today, 90).is_empty());
+    }
+
+    #[test]
+    fn series_is_zero_filled_oldest_first() {
+        let today = date("2026-03-02");
+        let stat = IpDailyStat {
+            ip: "203.0.113.7".to_string(),
+            day: "2026-03-01".to_string(),
+            requests: 12,
+            ..Default::default()
+        };
+        let series = daily_series("203.0.113.7", vec![stat], today, 3);
+        let days: Vec<_> = series.iter().map(|s| s.day.as_str()).collect();
+        assert_eq!(days, ["2026-02-28", "2026-03-01", "2026-03-02"]);
+        let requests: Vec<_> = series.iter().map(|s| s.requests).collect();
+        assert_eq!(requests, [0, 12, 0]);
+        assert!(series.iter().all(|s| s.ip == "203.0.113.7"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Session signing secret
+//!
+//! Sessions and API keys are HS256 JWTs signed with `[auth] jwt_secret`
+//! until the secret is rotated through POST /api/admin/rotate-jwt-secret;
+//! from then on the generated secret lives in the settings table (it is
+//! masked in GET /api/settings and cannot be set through PUT) and takes
+//! precedence over the config file on every instance.
+//!
+//! A rotation keeps the replaced secret as a verification-only key until
+//! the longest session issued with it has expired (`session_duration_hours`
+//! after the rotation), so signed-in admins are not all logged out at once.
+//! API keys signed with it stop working then and have to be re-issued.
+//! Instances that did not perform the rotation pick it up within
+//! `RELOAD_INTERVAL`.
+//!
+//! Running with the shipped default secret lets anyone forge a session. It
+//! is logged at startup and recorded as a Critical security event, reported
+//! by GET /api/dashboard/stats and GET /readyz, and with
+//! `[auth] refuse_default_jwt_secret` refuses startup while internet access
+//! is enabled.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use tokio::sync::RwLock;
+use tokio::time::interval;
+
+use crate::db::mysql::MySqlDb;
+use crate::error::AppError;
+use crate::models::{SecurityEvent, SecurityEventType, Severity};
+use crate::proxy::ProxyState;
+
+/// The `[auth] jwt_secret` shipped in config/default.toml
+pub const DEFAULT_JWT_SECRET: &str = "CHANGE_ME_IN_PRODUCTION";
+
+/// Rotated signing secret (overrides `[auth] jwt_secret`)
+pub const SETTING_SECRET: &str = "jwt_secret";
+/// Replaced secret, accepted for verification until `SETTING_PREVIOUS_UNTIL`
+pub const SETTING_PREVIOUS: &str = "jwt_secret_previous";
+/// RFC 3339
+pub const SETTING_PREVIOUS_UNTIL: &str = "jwt_secret_previous_until";
+
+/// Random bytes in a generated secret (base64url encoded)
+const GENERATED_SECRET_BYTES: usize = 48;
+/// How often every instance re-reads the secrets from settings
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether settings keys belong to the signing secrets
+pub fn is_secret_setting(key: &str) -> bool {
+    key.starts_with(SETTING_SECRET)
+}
+
+/// The shipped default, or no secret at all
+pub fn is_default_secret(secret: &str) -> bool {
+    let secret = secret.trim();
+    secret.is_empty() || secret == DEFAULT_JWT_SECRET
+}
+
+/// A new random signing secret
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; GENERATED_SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Secrets in effect (no Debug: never log them)
+#[derive(Clone)]
+pub struct JwtKeys {
+    current: String,
+    previous: Option<(String, DateTime<Utc>)>,
+    /// The signing secret comes from a rotation, not the config file
+    rotated: bool,
+}
+
+impl JwtKeys {
+    /// The configured secret only
+    pub fn new(configured: &str) -> Self {
+        Self {
+            current: configured.to_string(),
+            previous: None,
+            rotated: false,
+        }
+    }
+
+    /// The rotated secrets from settings, else the configured one
+    pub async fn load(mysql: &MySqlDb, configured: &str) -> Result<Self, AppError> {
+        let Some(current) = mysql
+            .get_setting(SETTING_SECRET)
+            .await?
+            .filter(|s| !s.is_empty())
+        else {
+            return Ok(Self::new(configured));
+        };
+        let previous = match (
+            mysql.get_setting(SETTING_PREVIOUS).await?,
+            mysql.get_setting(SETTING_PREVIOUS_UNTIL).await?,
+        ) {
+            (Some(secret), Some(until)) if !secret.is_empty() => {
+                DateTime::parse_from_rfc3339(&until)
+                    .ok()
+                    .map(|until| (secret, until.with_timezone(&Utc)))
+            }
+            _ => None,
+        };
+        Ok(Self {
+            current,
+            previous,
+            rotated: true,
+        })
+    }
+
+    /// Secret new sessions and API keys are signed with
+    pub fn signing_secret(&self) -> &str {
+        &self.current
+    }
+
+    /// Secrets a token may be signed with at `now`, signing secret first
+    pub fn verification_secrets(&self, now: DateTime<Utc>) -> Vec<&str> {
+        let mut secrets = vec![self.current.as_str()];
+        if let Some((secret, until)) = &self.previous {
+            if now < *until {
+                secrets.push(secret.as_str());
+            }
+        }
+        secrets
+    }
+
+    pub fn is_default(&self) -> bool {
+        is_default_secret(&self.current)
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.rotated
+    }
+
+    /// Until when the replaced secret is still accepted (None once expired)
+    pub fn previous_valid_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.previous
+            .as_ref()
+            .map(|(_, until)| *until)
+            .filter(|until| now < *until)
+    }
+
+    /// Sign with `secret` from now on; the current secret stays valid for
+    /// verification until `previous_until` (a still-valid older one is dropped)
+    pub fn rotate(&self, secret: String, previous_until: DateTime<Utc>) -> Self {
+        Self {
+            current: secret,
+            previous: Some((self.current.clone(), previous_until)),
+            rotated: true,
+        }
+    }
+
+    /// Persist to settings (shared by every instance)
+    pub async fn save(&self, mysql: &MySqlDb) -> Result<(), AppError> {
+        let (previous, until) = match &self.previous {
+            Some((secret, until)) => (Some(secret.as_str()), Some(until.to_rfc3339())),
+            None => (None, None),
+        };
+        // The previous secret first: an instance reloading in between still
+        // accepts every session
+        mysql
+            .upsert_setting(
+                SETTING_PREVIOUS,
+                previous,
+                Some("Replaced session signing secret (verification only)"),
+            )
+            .await?;
+        mysql
+            .upsert_setting(
+                SETTING_PREVIOUS_UNTIL,
+                until.as_deref(),
+                Some("Replaced session signing secret is accepted until (RFC 3339)"),
+            )
+            .await?;
+        mysql
+            .upsert_setting(
+                SETTING_SECRET,
+                Some(&self.current),
+                Some("Session signing secret (POST /api/admin/rotate-jwt-secret)"),
+            )
+            .await
+    }
+}
+
+/// Startup handling of the default secret: warn, record a Critical security
+/// event, and refuse to start when configured to while internet access is on
+pub async fn check_startup(state: &ProxyState) -> anyhow::Result<()> {
+    if !state.jwt_keys.read().await.is_default() {
+        return Ok(());
+    }
+
+    let internet_access = state
+        .app_state
+        .mysql
+        .get_setting_bool("internet_access_enabled")
+        .await
+        .unwrap_or(state.auth_config.internet_access_enabled);
+
+    tracing::warn!("==================================================================");
+    tracing::warn!("jwt_secret is the shipped default: anyone can forge admin sessions");
+    tracing::warn!("Rotate it with POST /api/admin/rotate-jwt-secret (permission 100)");
+    tracing::warn!("==================================================================");
+
+    let event = SecurityEvent {
+        id: None,
+        timestamp: Utc::now(),
+        event_type: SecurityEventType::InsecureConfiguration,
+        ip: None,
+        details: serde_json::json!({
+            "kind": "default_jwt_secret",
+            "internet_access_enabled": internet_access,
+            "remedy": "POST /api/admin/rotate-jwt-secret",
+        }),
+        severity: Severity::Critical,
+        notified: false,
+    };
+    if let Err(e) = state.app_state.mongo.log_security_event(&event).await {
+        tracing::error!("Failed to record default jwt_secret security event: {}", e);
+    }
+
+    if internet_access && state.auth_config.refuse_default_jwt_secret {
+        anyhow::bail!(
+            "Refusing to start: jwt_secret is the default while internet access is enabled \
+             (set [auth] jwt_secret, or disable refuse_default_jwt_secret)"
+        );
+    }
+    Ok(())
+}
+
+/// Re-read the secrets every `RELOAD_INTERVAL` so a rotation on another
+/// instance takes effect here (per instance)
+pub async fn reload_loop(keys: Arc<RwLock<JwtKeys>>, mysql: Arc<MySqlDb>, configured: String) {
+    let mut tick = interval(RELOAD_INTERVAL);
+    tick.tick().await;
+    loop {
+        tick.tick().await;
+        match JwtKeys::load(&mysql, &configured).await {
+            Ok(loaded) => *keys.write().await = loaded,
+            Err(e) => tracing::warn!("Failed to reload session signing secret: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_and_empty_secrets_are_flagged() {
+        assert!(is_default_secret(DEFAULT_JWT_SECRET));
+        assert!(is_default_secret("  "));
+        assert!(!is_default_secret(&generate_secret()));
+        assert_ne!(generate_secret(), generate_secret());
+        assert_eq!(generate_secret().len(), 64);
+    }
+
+    #[test]
+    fn rotation_keeps_the_old_secret_until_its_deadline() {
+        let now = Utc::now();
+        let keys = JwtKeys::new(DEFAULT_JWT_SECRET);
+        assert!(keys.is_default());
+        assert_eq!(keys.verification_secrets(now), vec![DEFAULT_JWT_SECRET]);
+
+        let until = now + chrono::Duration::hours(24);
+        let rotated = keys.rotate("fresh".to_string(), until);
+        assert!(!rotated.is_default());
+        assert!(rotated.is_rotated());
+        assert_eq!(rotated.signing_secret(), "fresh");
+        assert_eq!(
+            rotated.verification_secrets(now),
+            vec!["fresh", DEFAULT_JWT_SECRET]
+        );
+        assert_eq!(rotated.previous_valid_until(now), Some(until));
+        assert_eq!(rotated.verification_secrets(until), vec!["fresh"]);
+        assert_eq!(rotated.previous_valid_until(until), None);
+
+        // Rotating again drops the oldest secret
+        let again = rotated.rotate("fresher".to_string(), until);
+        assert_eq!(again.verification_secrets(now), vec!["fresher", "fresh"]);
+    }
+
+    #[test]
+    fn secret_settings_are_recognized() {
+        assert!(is_secret_setting(SETTING_SECRET));
+        assert!(is_secret_setting(SETTING_PREVIOUS));
+        assert!(is_secret_setting(SETTING_PREVIOUS_UNTIL));
+        assert!(!is_secret_setting("internet_access_enabled"));
+    }
+}
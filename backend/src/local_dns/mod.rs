@@ -0,0 +1,753 @@
+//! Local DNS overrides: a small UDP/TCP responder for LAN clients
+//!
+//! Names in `dns_overrides` (typically the DDNS hostnames) are answered with
+//! the LPG's LAN IP or the record's own target, so LAN clients reach the
+//! gateway directly instead of hairpinning through the WAN. Everything else
+//! is relayed unchanged to the upstream resolver. Only clients in the allowed
+//! networks are served; others get REFUSED. Off unless `[dns] enabled`.
+//!
+//! Every instance runs its own listener; the override table is reloaded
+//! after API changes and once a minute.
+
+pub mod wire;
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use ipnetwork::IpNetwork;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::Semaphore;
+
+use self::wire::{Query, Rcode, WireError};
+use crate::config::DnsConfig;
+use crate::db::mysql::MySqlDb;
+use crate::models::DnsOverride;
+
+/// Largest message accepted over UDP (EDNS clients may send more than 512)
+const UDP_BUFFER: usize = 4096;
+/// Queries handled at once per transport; excess UDP queries are dropped
+const MAX_IN_FLIGHT: usize = 256;
+const MAX_TCP_CONNECTIONS: usize = 64;
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+/// Recent queries kept for GET /api/dns/status
+const QUERY_LOG_CAPACITY: usize = 200;
+/// Window of the rolling per-record counter
+const ROLLING_MINUTES: usize = 60;
+
+/// Normalize a hostname for the override table: lowercase, no trailing dot,
+/// 1-63 character labels of letters, digits, '-' and '_'
+pub fn normalize_hostname(input: &str) -> Result<String, String> {
+    let name = input.trim().trim_end_matches('.').to_ascii_lowercase();
+    if name.is_empty() || name.len() > 253 {
+        return Err("must be 1-253 characters".to_string());
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err("labels must be 1-63 characters".to_string());
+        }
+        if !label
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return Err(format!("invalid label '{}'", label));
+        }
+    }
+    Ok(name)
+}
+
+/// Whether a client may use the responder when no networks are configured
+fn is_lan_address(ip: IpAddr) -> bool {
+    crate::ip::is_lan(ip)
+}
+
+/// Hits per minute over the last hour
+#[derive(Debug, Clone)]
+struct RollingCounter {
+    /// (minute since epoch, hits) per slot
+    slots: [(i64, u64); ROLLING_MINUTES],
+}
+
+impl Default for RollingCounter {
+    fn default() -> Self {
+        Self {
+            slots: [(i64::MIN, 0); ROLLING_MINUTES],
+        }
+    }
+}
+
+impl RollingCounter {
+    fn add(&mut self, minute: i64) {
+        let slot = &mut self.slots[minute.rem_euclid(ROLLING_MINUTES as i64) as usize];
+        if slot.0 != minute {
+            *slot = (minute, 0);
+        }
+        slot.1 += 1;
+    }
+
+    fn total(&self, minute: i64) -> u64 {
+        self.slots
+            .iter()
+            .filter(|(m, _)| *m > minute - ROLLING_MINUTES as i64 && *m <= minute)
+            .map(|(_, hits)| hits)
+            .sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecordStats {
+    total: u64,
+    rolling: RollingCounter,
+    last_query_at: Option<DateTime<Utc>>,
+    last_client: Option<String>,
+}
+
+/// Query counters of one override record
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordHits {
+    pub hostname: String,
+    pub total: u64,
+    pub last_hour: u64,
+    pub last_query_at: Option<DateTime<Utc>>,
+    pub last_client: Option<String>,
+}
+
+/// How a query was handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOutcome {
+    Override,
+    Forwarded,
+    UpstreamFailed,
+    Refused,
+    Malformed,
+}
+
+/// One entry of the recent query log
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryLogEntry {
+    pub at: DateTime<Utc>,
+    pub client: String,
+    /// "udp" | "tcp"
+    pub transport: &'static str,
+    pub name: Option<String>,
+    pub qtype: Option<u16>,
+    pub outcome: QueryOutcome,
+    pub duration_us: u64,
+}
+
+/// Totals since start
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DnsCounters {
+    pub queries: u64,
+    pub answered_local: u64,
+    pub forwarded: u64,
+    pub upstream_failures: u64,
+    pub refused: u64,
+    pub malformed: u64,
+}
+
+/// Listener state for the dashboard and GET /api/dns/status
+#[derive(Debug, Clone, Serialize)]
+pub struct DnsHealth {
+    pub enabled: bool,
+    /// "disabled" | "ok" | "degraded" | "down"
+    pub status: &'static str,
+    pub listen: String,
+    pub upstream: String,
+    pub lan_ip: Option<String>,
+    pub udp_listening: bool,
+    pub tcp_listening: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_upstream_error_at: Option<DateTime<Utc>>,
+    pub overrides: usize,
+    pub counters: DnsCounters,
+}
+
+#[derive(Debug, Default)]
+struct ListenerState {
+    udp_listening: bool,
+    tcp_listening: bool,
+    started_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_upstream_error_at: Option<DateTime<Utc>>,
+    last_upstream_ok_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Udp,
+    Tcp,
+}
+
+impl Transport {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Udp => "udp",
+            Self::Tcp => "tcp",
+        }
+    }
+}
+
+/// Responder state shared by the listeners and the API
+pub struct LocalDns {
+    config: DnsConfig,
+    allowed: Vec<IpNetwork>,
+    upstream: Option<SocketAddr>,
+    lan_ip: RwLock<Option<IpAddr>>,
+    /// hostname -> explicit target (None = LAN IP)
+    table: RwLock<HashMap<String, Option<IpAddr>>>,
+    records: Mutex<HashMap<String, RecordStats>>,
+    counters: Mutex<DnsCounters>,
+    state: Mutex<ListenerState>,
+    log: Mutex<VecDeque<QueryLogEntry>>,
+}
+
+impl LocalDns {
+    pub fn new(config: DnsConfig) -> Self {
+        let allowed = config
+            .allowed_networks
+            .iter()
+            .filter_map(|n| {
+                let parsed = crate::blocklist::parse_network(n);
+                if parsed.is_none() {
+                    tracing::warn!("[DNS] Ignoring invalid allowed network '{}'", n);
+                }
+                parsed
+            })
+            .collect();
+        let upstream = parse_upstream(&config.upstream);
+        let lan_ip = config.lan_ip.as_deref().and_then(|ip| ip.parse().ok());
+        Self {
+            config,
+            allowed,
+            upstream,
+            lan_ip: RwLock::new(lan_ip),
+            table: RwLock::new(HashMap::new()),
+            records: Mutex::new(HashMap::new()),
+            counters: Mutex::new(DnsCounters::default()),
+            state: Mutex::new(ListenerState::default()),
+            log: Mutex::new(VecDeque::with_capacity(QUERY_LOG_CAPACITY)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Address answered for overrides without a target
+    pub fn lan_ip(&self) -> Option<IpAddr> {
+        *self.lan_ip.read().unwrap()
+    }
+
+    /// Replace the served table with the enabled records
+    pub fn load(&self, records: &[DnsOverride]) {
+        let table: HashMap<String, Option<IpAddr>> = records
+            .iter()
+            .filter(|r| r.enabled)
+            .map(|r| {
+                let target = r.target.as_deref().and_then(|t| t.parse().ok());
+                (r.hostname.clone(), target)
+            })
+            .collect();
+        self.records
+            .lock()
+            .unwrap()
+            .retain(|hostname, _| table.contains_key(hostname));
+        *self.table.write().unwrap() = table;
+    }
+
+    /// Reload the table from MySQL
+    pub async fn reload(&self, mysql: &MySqlDb) -> Result<(), String> {
+        let records = mysql
+            .list_dns_overrides()
+            .await
+            .map_err(|e| e.to_string())?;
+        self.load(&records);
+        Ok(())
+    }
+
+    fn is_allowed(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        if self.allowed.is_empty() {
+            is_lan_address(ip)
+        } else {
+            self.allowed.iter().any(|n| n.contains(ip))
+        }
+    }
+
+    /// Addresses for an overridden name (None = not overridden)
+    fn lookup(&self, name: &str) -> Option<Vec<IpAddr>> {
+        let target = *self.table.read().unwrap().get(name)?;
+        Some(target.or_else(|| self.lan_ip()).into_iter().collect())
+    }
+
+    /// Answer one message; None = send nothing
+    async fn handle(
+        &self,
+        raw: &[u8],
+        client: SocketAddr,
+        transport: Transport,
+    ) -> Option<Vec<u8>> {
+        let started = Instant::now();
+        let parsed = wire::parse_query(raw);
+        let query = parsed.as_ref().ok();
+
+        let (outcome, reply) = if !self.is_allowed(client.ip()) {
+            (
+                QueryOutcome::Refused,
+                wire::error_reply(raw, query, Rcode::Refused),
+            )
+        } else {
+            match &parsed {
+                Err(WireError::TooShort) | Err(WireError::NotAQuery) => {
+                    (QueryOutcome::Malformed, None)
+                }
+                Err(WireError::Reject(rcode)) => (
+                    QueryOutcome::Malformed,
+                    wire::error_reply(raw, None, *rcode),
+                ),
+                Ok(query) => self.resolve(raw, query, transport).await,
+            }
+        };
+
+        self.record(client, transport, query, outcome, started.elapsed());
+        reply
+    }
+
+    async fn resolve(
+        &self,
+        raw: &[u8],
+        query: &Query,
+        transport: Transport,
+    ) -> (QueryOutcome, Option<Vec<u8>>) {
+        if let Some(addrs) = self.lookup(&query.name) {
+            let reply = wire::answer(query, raw, &addrs, self.config.ttl_secs);
+            return (QueryOutcome::Override, Some(reply));
+        }
+        match self.forward(raw, transport).await {
+            Ok(reply) => {
+                self.state.lock().unwrap().last_upstream_ok_at = Some(Utc::now());
+                (QueryOutcome::Forwarded, Some(reply))
+            }
+            Err(e) => {
+                tracing::debug!("[DNS] Upstream failed for {}: {}", query.name, e);
+                self.state.lock().unwrap().last_upstream_error_at = Some(Utc::now());
+                (
+                    QueryOutcome::UpstreamFailed,
+                    wire::error_reply(raw, Some(query), Rcode::ServFail),
+                )
+            }
+        }
+    }
+
+    /// Relay a query to the upstream resolver over the client's transport
+    async fn forward(&self, raw: &[u8], transport: Transport) -> Result<Vec<u8>, String> {
+        let upstream = self.upstream.ok_or("invalid upstream address")?;
+        let timeout = Duration::from_millis(self.config.upstream_timeout_ms);
+        let exchange = async {
+            match transport {
+                Transport::Udp => forward_udp(upstream, raw).await,
+                Transport::Tcp => forward_tcp(upstream, raw).await,
+            }
+        };
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| "timed out".to_string())?
+    }
+
+    fn record(
+        &self,
+        client: SocketAddr,
+        transport: Transport,
+        query: Option<&Query>,
+        outcome: QueryOutcome,
+        elapsed: Duration,
+    ) {
+        {
+            let mut counters = self.counters.lock().unwrap();
+            counters.queries += 1;
+            match outcome {
+                QueryOutcome::Override => counters.answered_local += 1,
+                QueryOutcome::Forwarded => counters.forwarded += 1,
+                QueryOutcome::UpstreamFailed => counters.upstream_failures += 1,
+                QueryOutcome::Refused => counters.refused += 1,
+                QueryOutcome::Malformed => counters.malformed += 1,
+            }
+        }
+
+        let now = Utc::now();
+        let client_ip = client.ip().to_canonical().to_string();
+        if let (QueryOutcome::Override, Some(q)) = (outcome, query) {
+            let mut records = self.records.lock().unwrap();
+            let stats = records.entry(q.name.clone()).or_default();
+            stats.total += 1;
+            stats.rolling.add(now.timestamp() / 60);
+            stats.last_query_at = Some(now);
+            stats.last_client = Some(client_ip.clone());
+        }
+
+        tracing::debug!(
+            "[DNS] {} {} {:?} type {:?} -> {:?}",
+            transport.as_str(),
+            client_ip,
+            query.map(|q| q.name.as_str()),
+            query.map(|q| q.qtype),
+            outcome
+        );
+        let mut log = self.log.lock().unwrap();
+        if log.len() >= QUERY_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(QueryLogEntry {
+            at: now,
+            client: client_ip,
+            transport: transport.as_str(),
+            name: query.map(|q| q.name.clone()),
+            qtype: query.map(|q| q.qtype),
+            outcome,
+            duration_us: elapsed.as_micros() as u64,
+        });
+    }
+
+    /// Per-record counters of the served overrides (most queried first)
+    pub fn record_hits(&self) -> Vec<RecordHits> {
+        let minute = Utc::now().timestamp() / 60;
+        let records = self.records.lock().unwrap();
+        let mut hits: Vec<RecordHits> = self
+            .table
+            .read()
+            .unwrap()
+            .keys()
+            .map(|hostname| {
+                let stats = records.get(hostname);
+                RecordHits {
+                    hostname: hostname.clone(),
+                    total: stats.map_or(0, |s| s.total),
+                    last_hour: stats.map_or(0, |s| s.rolling.total(minute)),
+                    last_query_at: stats.and_then(|s| s.last_query_at),
+                    last_client: stats.and_then(|s| s.last_client.clone()),
+                }
+            })
+            .collect();
+        hits.sort_by(|a, b| b.total.cmp(&a.total).then(a.hostname.cmp(&b.hostname)));
+        hits
+    }
+
+    /// Most recent queries, newest first
+    pub fn recent_queries(&self, limit: usize) -> Vec<QueryLogEntry> {
+        self.log
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn health(&self) -> DnsHealth {
+        let state = self.state.lock().unwrap();
+        let upstream_failing = match (state.last_upstream_error_at, state.last_upstream_ok_at) {
+            (Some(failed), Some(ok)) => failed > ok,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        let status = if !self.config.enabled {
+            "disabled"
+        } else if !state.udp_listening && !state.tcp_listening {
+            "down"
+        } else if !state.udp_listening || !state.tcp_listening || upstream_failing {
+            "degraded"
+        } else {
+            "ok"
+        };
+        DnsHealth {
+            enabled: self.config.enabled,
+            status,
+            listen: self.config.listen.clone(),
+            upstream: self.config.upstream.clone(),
+            lan_ip: self.lan_ip().map(|ip| ip.to_string()),
+            udp_listening: state.udp_listening,
+            tcp_listening: state.tcp_listening,
+            started_at: state.started_at,
+            last_error: state.last_error.clone(),
+            last_upstream_error_at: state.last_upstream_error_at,
+            overrides: self.table.read().unwrap().len(),
+            counters: self.counters.lock().unwrap().clone(),
+        }
+    }
+
+    fn set_error(&self, error: String) {
+        tracing::error!("[DNS] {}", error);
+        self.state.lock().unwrap().last_error = Some(error);
+    }
+
+    /// Bind the listeners and serve until the process exits (no-op when disabled)
+    pub async fn start(self: Arc<Self>, mysql: Arc<MySqlDb>) {
+        if !self.config.enabled {
+            return;
+        }
+        let Some(upstream) = self.upstream else {
+            self.set_error(format!("Invalid upstream '{}'", self.config.upstream));
+            return;
+        };
+        if self.lan_ip().is_none() {
+            match detect_lan_ip(upstream).await {
+                Some(ip) => *self.lan_ip.write().unwrap() = Some(ip),
+                None => self.set_error(
+                    "LAN IP unknown (set [dns] lan_ip); overrides without a target get no answer"
+                        .to_string(),
+                ),
+            }
+        }
+        if let Err(e) = self.reload(&mysql).await {
+            self.set_error(format!("Loading overrides failed: {}", e));
+        }
+
+        match UdpSocket::bind(&self.config.listen).await {
+            Ok(socket) => {
+                self.state.lock().unwrap().udp_listening = true;
+                tokio::spawn(self.clone().serve_udp(Arc::new(socket)));
+            }
+            Err(e) => self.set_error(format!("UDP bind {} failed: {}", self.config.listen, e)),
+        }
+        match TcpListener::bind(&self.config.listen).await {
+            Ok(listener) => {
+                self.state.lock().unwrap().tcp_listening = true;
+                tokio::spawn(self.clone().serve_tcp(listener));
+            }
+            Err(e) => self.set_error(format!("TCP bind {} failed: {}", self.config.listen, e)),
+        }
+        self.state.lock().unwrap().started_at = Some(Utc::now());
+        tracing::info!(
+            "[DNS] Local responder on {} (upstream {}, LAN IP {:?}, {} overrides)",
+            self.config.listen,
+            upstream,
+            self.lan_ip(),
+            self.table.read().unwrap().len()
+        );
+
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            if let Err(e) = self.reload(&mysql).await {
+                tracing::warn!("[DNS] Override reload failed: {}", e);
+            }
+        }
+    }
+
+    async fn serve_udp(self: Arc<Self>, socket: Arc<UdpSocket>) {
+        let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+        let mut buf = vec![0u8; UDP_BUFFER];
+        loop {
+            let (len, client) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    // ICMP errors from earlier replies surface here on some platforms
+                    tracing::debug!("[DNS] UDP receive error: {}", e);
+                    continue;
+                }
+            };
+            let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+                continue;
+            };
+            let raw = buf[..len].to_vec();
+            let dns = self.clone();
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                if let Some(reply) = dns.handle(&raw, client, Transport::Udp).await {
+                    let _ = socket.send_to(&reply, client).await;
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    async fn serve_tcp(self: Arc<Self>, listener: TcpListener) {
+        let connections = Arc::new(Semaphore::new(MAX_TCP_CONNECTIONS));
+        loop {
+            let (stream, client) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::debug!("[DNS] TCP accept error: {}", e);
+                    continue;
+                }
+            };
+            let Ok(permit) = connections.clone().try_acquire_owned() else {
+                continue;
+            };
+            let dns = self.clone();
+            tokio::spawn(async move {
+                dns.serve_connection(stream, client).await;
+                drop(permit);
+            });
+        }
+    }
+
+    /// Length-prefixed messages until the client closes or idles
+    async fn serve_connection(&self, mut stream: TcpStream, client: SocketAddr) {
+        loop {
+            let Ok(Ok(len)) = tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_u16()).await
+            else {
+                return;
+            };
+            let mut raw = vec![0u8; len as usize];
+            if len == 0
+                || !matches!(
+                    tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_exact(&mut raw)).await,
+                    Ok(Ok(_))
+                )
+            {
+                return;
+            }
+            let Some(reply) = self.handle(&raw, client, Transport::Tcp).await else {
+                return;
+            };
+            let mut framed = Vec::with_capacity(reply.len() + 2);
+            framed.extend_from_slice(&(reply.len() as u16).to_be_bytes());
+            framed.extend_from_slice(&reply);
+            if stream.write_all(&framed).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// "ip" or "ip:port" (port 53 by default)
+fn parse_upstream(upstream: &str) -> Option<SocketAddr> {
+    let upstream = upstream.trim();
+    upstream.parse().ok().or_else(|| {
+        upstream
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+fn unspecified_for(addr: SocketAddr) -> &'static str {
+    if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    }
+}
+
+/// Local address the host uses toward `upstream` (no packet is sent)
+async fn detect_lan_ip(upstream: SocketAddr) -> Option<IpAddr> {
+    let socket = UdpSocket::bind(unspecified_for(upstream)).await.ok()?;
+    socket.connect(upstream).await.ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+async fn forward_udp(upstream: SocketAddr, raw: &[u8]) -> Result<Vec<u8>, String> {
+    let socket = UdpSocket::bind(unspecified_for(upstream))
+        .await
+        .map_err(|e| e.to_string())?;
+    socket.connect(upstream).await.map_err(|e| e.to_string())?;
+    socket.send(raw).await.map_err(|e| e.to_string())?;
+    let id = wire::message_id(raw);
+    let mut buf = vec![0u8; UDP_BUFFER];
+    loop {
+        let len = socket.recv(&mut buf).await.map_err(|e| e.to_string())?;
+        // Ignore stray datagrams that are not the reply to this query
+        if wire::message_id(&buf[..len]) == id {
+            return Ok(buf[..len].to_vec());
+        }
+    }
+}
+
+async fn forward_tcp(upstream: SocketAddr, raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut stream = TcpStream::connect(upstream)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut framed = Vec::with_capacity(raw.len() + 2);
+    framed.extend_from_slice(&(raw.len() as u16).to_be_bytes());
+    framed.extend_from_slice(raw);
+    stream.write_all(&framed).await.map_err(|e| e.to_string())?;
+    let len = stream.read_u16().await.map_err(|e| e.to_string())?;
+    let mut reply = vec![0u8; len as usize];
+    stream
+        .read_exact(&mut reply)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(hostname: &str, target: Option<&str>, enabled: bool) -> DnsOverride {
+        DnsOverride {
+            id: 1,
+            hostname: hostname.to_string(),
+            target: target.map(str::to_string),
+            enabled,
+            source: "manual".to_string(),
+            comment: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_hostname() {
+        assert_eq!(
+            normalize_hostname(" Home.Example.COM. ").unwrap(),
+            "home.example.com"
+        );
+        assert!(normalize_hostname("").is_err());
+        assert!(normalize_hostname("a..b").is_err());
+        assert!(normalize_hostname("bad host.example").is_err());
+        assert!(normalize_hostname(&"a".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_lookup_and_access() {
+        let dns = LocalDns::new(DnsConfig {
+            lan_ip: Some("192.168.3.10".to_string()),
+            ..DnsConfig::default()
+        });
+        dns.load(&[
+            record("home.example.com", None, true),
+            record("nas.example.com", Some("192.168.3.20"), true),
+            record("off.example.com", None, false),
+        ]);
+        let lan: IpAddr = "192.168.3.10".parse().unwrap();
+        assert_eq!(dns.lookup("home.example.com"), Some(vec![lan]));
+        assert_eq!(
+            dns.lookup("nas.example.com"),
+            Some(vec!["192.168.3.20".parse().unwrap()])
+        );
+        assert_eq!(dns.lookup("off.example.com"), None);
+
+        assert!(dns.is_allowed("10.1.2.3".parse().unwrap()));
+        assert!(dns.is_allowed("::ffff:192.168.1.5".parse().unwrap()));
+        assert!(dns.is_allowed("fd12::1".parse().unwrap()));
+        assert!(!dns.is_allowed("8.8.8.8".parse().unwrap()));
+        assert_eq!(
+            parse_upstream("9.9.9.9"),
+            Some("9.9.9.9:53".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_rolling_counter() {
+        let mut counter = RollingCounter::default();
+        counter.add(1000);
+        counter.add(1000);
+        counter.add(1030);
+        assert_eq!(counter.total(1030), 3);
+        assert_eq!(counter.total(1061), 1);
+        // Slot reused by a later minute
+        counter.add(1060);
+        assert_eq!(counter.total(1060), 2);
+        assert_eq!(counter.total(1200), 0);
+    }
+}
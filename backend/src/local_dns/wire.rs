@@ -0,0 +1,298 @@
+//! Minimal DNS wire format: parse single-question queries and build
+//! authoritative answers and error replies (RFC 1035)
+//!
+//! Only what the override responder needs is decoded; everything else is
+//! relayed to the upstream resolver as raw bytes. Parsing never panics on
+//! arbitrary input.
+
+use std::net::IpAddr;
+
+pub const HEADER_LEN: usize = 12;
+pub const TYPE_A: u16 = 1;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_ANY: u16 = 255;
+pub const CLASS_IN: u16 = 1;
+
+const MAX_NAME_LEN: usize = 255;
+const MAX_LABEL_LEN: usize = 63;
+/// Pointer to the question name right after the header
+const QUESTION_NAME_POINTER: u16 = 0xC000 | HEADER_LEN as u16;
+
+/// Response codes used in replies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rcode {
+    NoError = 0,
+    FormErr = 1,
+    ServFail = 2,
+    NotImp = 4,
+    Refused = 5,
+}
+
+/// Why a message was not answered as a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// Shorter than a header: nothing to reply to
+    TooShort,
+    /// Not something to answer (reply bit set): dropped
+    NotAQuery,
+    /// Reply with this code
+    Reject(Rcode),
+}
+
+/// A standard query with exactly one question
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub id: u16,
+    /// Recursion desired
+    pub rd: bool,
+    /// Lowercased, without the trailing dot ("" for the root)
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    /// End offset of the question section in the message
+    question_end: usize,
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(at)?, *buf.get(at + 1)?]))
+}
+
+/// Parse a query; compressed or over-long names are rejected as FORMERR
+pub fn parse_query(buf: &[u8]) -> Result<Query, WireError> {
+    if buf.len() < HEADER_LEN {
+        return Err(WireError::TooShort);
+    }
+    let id = read_u16(buf, 0).ok_or(WireError::TooShort)?;
+    let flags = read_u16(buf, 2).ok_or(WireError::TooShort)?;
+    if flags & 0x8000 != 0 {
+        return Err(WireError::NotAQuery);
+    }
+    if (flags >> 11) & 0x0F != 0 {
+        return Err(WireError::Reject(Rcode::NotImp));
+    }
+    if read_u16(buf, 4) != Some(1) {
+        return Err(WireError::Reject(Rcode::FormErr));
+    }
+
+    let form_err = WireError::Reject(Rcode::FormErr);
+    let mut pos = HEADER_LEN;
+    let mut labels: Vec<String> = Vec::new();
+    let mut wire_len = 1;
+    loop {
+        let len = *buf.get(pos).ok_or(form_err.clone())? as usize;
+        pos += 1;
+        if len == 0 {
+            break;
+        }
+        if len > MAX_LABEL_LEN {
+            // Compression pointers and extended label types
+            return Err(form_err);
+        }
+        wire_len += len + 1;
+        if wire_len > MAX_NAME_LEN {
+            return Err(form_err);
+        }
+        let label = buf.get(pos..pos + len).ok_or(form_err.clone())?;
+        if label.iter().any(|b| !b.is_ascii_graphic() || *b == b'.') {
+            return Err(form_err);
+        }
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += len;
+    }
+    let qtype = read_u16(buf, pos).ok_or(form_err.clone())?;
+    let qclass = read_u16(buf, pos + 2).ok_or(form_err)?;
+
+    Ok(Query {
+        id,
+        rd: flags & 0x0100 != 0,
+        name: labels.join("."),
+        qtype,
+        qclass,
+        question_end: pos + 4,
+    })
+}
+
+fn header(out: &mut Vec<u8>, id: u16, rd: bool, aa: bool, rcode: Rcode, qd: u16, an: u16) {
+    let mut flags: u16 = 0x8000 | 0x0080 | rcode as u16;
+    if aa {
+        flags |= 0x0400;
+    }
+    if rd {
+        flags |= 0x0100;
+    }
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&flags.to_be_bytes());
+    out.extend_from_slice(&qd.to_be_bytes());
+    out.extend_from_slice(&an.to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+}
+
+/// Whether an address answers a question of `qtype`
+pub fn answers_type(addr: &IpAddr, qtype: u16) -> bool {
+    match addr {
+        IpAddr::V4(_) => qtype == TYPE_A || qtype == TYPE_ANY,
+        IpAddr::V6(_) => qtype == TYPE_AAAA || qtype == TYPE_ANY,
+    }
+}
+
+/// Authoritative answer with the addresses matching the question type
+/// (none matching = NODATA, so the name never leaks upstream)
+pub fn answer(query: &Query, raw: &[u8], addrs: &[IpAddr], ttl: u32) -> Vec<u8> {
+    let matching: Vec<&IpAddr> = addrs
+        .iter()
+        .filter(|a| query.qclass == CLASS_IN && answers_type(a, query.qtype))
+        .collect();
+    let mut out = Vec::with_capacity(query.question_end + matching.len() * 28);
+    header(
+        &mut out,
+        query.id,
+        query.rd,
+        true,
+        Rcode::NoError,
+        1,
+        matching.len() as u16,
+    );
+    out.extend_from_slice(&raw[HEADER_LEN..query.question_end]);
+    for addr in matching {
+        let (rtype, rdata): (u16, Vec<u8>) = match addr {
+            IpAddr::V4(v4) => (TYPE_A, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (TYPE_AAAA, v6.octets().to_vec()),
+        };
+        out.extend_from_slice(&QUESTION_NAME_POINTER.to_be_bytes());
+        out.extend_from_slice(&rtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&ttl.to_be_bytes());
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(&rdata);
+    }
+    out
+}
+
+/// Reply carrying only a response code; echoes the question when the
+/// message parsed, else just the header (None when there is no header)
+pub fn error_reply(raw: &[u8], query: Option<&Query>, rcode: Rcode) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(HEADER_LEN + 64);
+    match query {
+        Some(q) => {
+            header(&mut out, q.id, q.rd, false, rcode, 1, 0);
+            out.extend_from_slice(&raw[HEADER_LEN..q.question_end]);
+        }
+        None => {
+            let id = read_u16(raw, 0)?;
+            let rd = read_u16(raw, 2)? & 0x0100 != 0;
+            header(&mut out, id, rd, false, rcode, 0, 0);
+        }
+    }
+    Some(out)
+}
+
+/// Message id of a raw message (to match upstream replies)
+pub fn message_id(raw: &[u8]) -> Option<u16> {
+    read_u16(raw, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_bytes(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.').filter(|l| !l.is_empty()) {
+            out.push(label.len() as u8);
+            out.extend_from_slice(label.as_bytes());
+        }
+        out.push(0);
+        out.extend_from_slice(&qtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn test_parse_and_answer() {
+        let raw = query_bytes(0xBEEF, "Home.Example.com", TYPE_A);
+        let q = parse_query(&raw).unwrap();
+        assert_eq!(
+            (q.id, q.rd, q.name.as_str(), q.qtype),
+            (0xBEEF, true, "home.example.com", TYPE_A)
+        );
+
+        let addrs: Vec<IpAddr> = vec!["192.168.1.10".parse().unwrap(), "fd00::1".parse().unwrap()];
+        let reply = answer(&q, &raw, &addrs, 60);
+        assert_eq!(&reply[..2], &[0xBE, 0xEF]);
+        assert_eq!(reply[2] & 0x84, 0x84, "QR and AA set");
+        assert_eq!(read_u16(&reply, 6), Some(1), "only the A record");
+        assert_eq!(&reply[reply.len() - 4..], &[192, 168, 1, 10]);
+
+        // AAAA question on an IPv4-only override: NODATA
+        let raw6 = query_bytes(1, "home.example.com", TYPE_AAAA);
+        let q6 = parse_query(&raw6).unwrap();
+        let reply = answer(&q6, &raw6, &addrs[..1], 60);
+        assert_eq!((read_u16(&reply, 6), reply[3] & 0x0F), (Some(0), 0));
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        assert_eq!(parse_query(&[0; 5]), Err(WireError::TooShort));
+
+        let mut reply = query_bytes(7, "a.b", TYPE_A);
+        reply[2] |= 0x80;
+        assert_eq!(parse_query(&reply), Err(WireError::NotAQuery));
+
+        // Compression pointer in the question
+        let mut raw = query_bytes(7, "", TYPE_A);
+        raw[HEADER_LEN] = 0xC0;
+        raw.insert(HEADER_LEN + 1, 0x0C);
+        assert_eq!(parse_query(&raw), Err(WireError::Reject(Rcode::FormErr)));
+
+        // Truncated question
+        let raw = query_bytes(7, "example.com", TYPE_A);
+        assert_eq!(
+            parse_query(&raw[..raw.len() - 3]),
+            Err(WireError::Reject(Rcode::FormErr))
+        );
+
+        let err = error_reply(&raw[..HEADER_LEN], None, Rcode::FormErr).unwrap();
+        assert_eq!((err.len(), err[3] & 0x0F), (HEADER_LEN, 1));
+        assert_eq!(error_reply(&raw[..3], None, Rcode::FormErr), None);
+    }
+
+    /// Random and mutated messages must never panic the parser or builders
+    #[test]
+    fn test_fuzz_parser() {
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        let valid = query_bytes(42, "nas.home.example.org", TYPE_AAAA);
+        let addrs: Vec<IpAddr> = vec!["10.0.0.1".parse().unwrap(), "::1".parse().unwrap()];
+        for i in 0..20_000 {
+            let buf: Vec<u8> = if i % 2 == 0 {
+                let len = (next() % 300) as usize;
+                (0..len).map(|_| next() as u8).collect()
+            } else {
+                let mut buf = valid.clone();
+                for _ in 0..=(next() % 4) {
+                    let at = (next() as usize) % buf.len();
+                    buf[at] = next() as u8;
+                }
+                buf.truncate((next() as usize) % (buf.len() + 1));
+                buf
+            };
+            match parse_query(&buf) {
+                Ok(q) => {
+                    let reply = answer(&q, &buf, &addrs, 30);
+                    assert!(reply.len() >= HEADER_LEN);
+                    error_reply(&buf, Some(&q), Rcode::Refused).unwrap();
+                }
+                Err(_) => {
+                    let _ = error_reply(&buf, None, Rcode::FormErr);
+                }
+            }
+        }
+    }
+}
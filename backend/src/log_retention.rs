@@ -0,0 +1,225 @@
+//! Age-based pruning of the log collections
+//!
+//! A leader-only job deletes access logs, security events and health check
+//! results older than their retention setting once a day, in batches of
+//! `DELETE_BATCH` documents with a short pause in between so the proxy's own
+//! log writes are not starved. Timestamps are stored as RFC3339 strings, so a
+//! MongoDB TTL index cannot expire them; the `{timestamp: 1}` indexes
+//! (storage::declared_indexes) keep the range deletes off a collection scan.
+//!
+//! A retention of 0 keeps a collection forever. Every run is recorded as a
+//! `log_prune` operation log; POST /api/tools/logs/prune starts one on demand.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::interval;
+
+use crate::db::mongo::OperatorInfo;
+use crate::db::AppState;
+
+/// Retention when a setting is missing
+pub const DEFAULT_RETENTION_DAYS: i32 = 90;
+/// Longest retention accepted (longer values are clamped)
+pub const MAX_RETENTION_DAYS: i32 = 3650;
+/// Prune interval
+const PRUNE_INTERVAL: Duration = Duration::from_secs(86_400);
+/// Documents deleted per round trip
+const DELETE_BATCH: i64 = 5000;
+/// Pause between batches
+const DELETE_PAUSE: Duration = Duration::from_millis(250);
+
+/// A pruned collection and the setting holding its retention in days
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionTarget {
+    pub collection: &'static str,
+    pub setting: &'static str,
+}
+
+pub const TARGETS: [RetentionTarget; 3] = [
+    RetentionTarget {
+        collection: "access_logs",
+        setting: "access_log_retention_days",
+    },
+    RetentionTarget {
+        collection: "security_events",
+        setting: "security_event_retention_days",
+    },
+    RetentionTarget {
+        collection: "health_checks",
+        setting: "health_check_retention_days",
+    },
+];
+
+/// Set while a run is in progress (scheduled or on demand)
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Outcome for one collection
+#[derive(Debug, Clone, Serialize)]
+pub struct PruneResult {
+    pub collection: &'static str,
+    pub retention_days: i32,
+    /// Documents before this were deleted; None when kept forever
+    pub cutoff: Option<DateTime<Utc>>,
+    pub deleted: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Oldest timestamp kept with `days` of retention (None for 0 = forever)
+pub fn cutoff(now: DateTime<Utc>, days: i32) -> Option<DateTime<Utc>> {
+    (days > 0).then(|| now - chrono::Duration::days(days.min(MAX_RETENTION_DAYS) as i64))
+}
+
+/// Configured retention of a target, clamped to 0..=MAX_RETENTION_DAYS
+pub async fn retention_days(app_state: &AppState, target: &RetentionTarget) -> i32 {
+    app_state
+        .mysql
+        .get_setting_i32(target.setting, DEFAULT_RETENTION_DAYS)
+        .await
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+        .clamp(0, MAX_RETENTION_DAYS)
+}
+
+/// Background prune job
+pub struct LogPruner {
+    app_state: AppState,
+}
+
+impl LogPruner {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    /// Start the prune loop (daily, first run at startup)
+    pub async fn start(&self) {
+        tracing::info!("Starting log retention pruner...");
+
+        let mut interval_timer = interval(PRUNE_INTERVAL);
+        loop {
+            interval_timer.tick().await;
+            self.run("scheduler", None).await;
+        }
+    }
+
+    /// Prune every target once; None when a run is already in progress
+    pub async fn run(
+        &self,
+        initiated_by: &str,
+        operator: Option<OperatorInfo>,
+    ) -> Option<Vec<PruneResult>> {
+        if RUNNING.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+
+        let mongo = &self.app_state.mongo;
+        let op_id = mongo
+            .start_operation_log_with_operator("log_prune", initiated_by, None, operator)
+            .await
+            .unwrap_or_default();
+        let start = Instant::now();
+
+        let now = Utc::now();
+        let mut results = Vec::with_capacity(TARGETS.len());
+        for target in &TARGETS {
+            let retention_days = retention_days(&self.app_state, target).await;
+            let mut result = PruneResult {
+                collection: target.collection,
+                retention_days,
+                cutoff: cutoff(now, retention_days),
+                deleted: 0,
+                error: None,
+            };
+            if let Some(before) = result.cutoff {
+                match self.prune(target.collection, before).await {
+                    Ok(deleted) => result.deleted = deleted,
+                    Err((deleted, e)) => {
+                        tracing::warn!("Pruning {} failed: {}", target.collection, e);
+                        result.deleted = deleted;
+                        result.error = Some(e);
+                    }
+                }
+                tracing::info!(
+                    "Pruned {} {} document(s) older than {} days",
+                    result.deleted,
+                    target.collection,
+                    retention_days
+                );
+            }
+            results.push(result);
+        }
+
+        let duration = start.elapsed().as_millis() as u64;
+        if !op_id.is_empty() {
+            let errors: Vec<String> = results
+                .iter()
+                .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.collection, e)))
+                .collect();
+            let _ = if errors.is_empty() {
+                mongo
+                    .complete_operation_log(
+                        &op_id,
+                        Some(&serde_json::json!({ "results": results })),
+                        duration,
+                    )
+                    .await
+            } else {
+                mongo
+                    .fail_operation_log(&op_id, &errors.join("; "), duration)
+                    .await
+            };
+        }
+
+        RUNNING.store(false, Ordering::SeqCst);
+        Some(results)
+    }
+
+    /// Delete a collection's documents before `before` batch by batch;
+    /// on error, the count deleted so far comes with it
+    async fn prune(&self, collection: &str, before: DateTime<Utc>) -> Result<u64, (u64, String)> {
+        let mut deleted = 0u64;
+        loop {
+            let removed = self
+                .app_state
+                .mongo
+                .delete_logs_before_batch(collection, before, DELETE_BATCH)
+                .await
+                .map_err(|e| (deleted, e))?;
+            deleted += removed;
+            if removed < DELETE_BATCH as u64 {
+                return Ok(deleted);
+            }
+            tokio::time::sleep(DELETE_PAUSE).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn zero_retention_keeps_everything() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 12, 0, 0).unwrap();
+        assert_eq!(cutoff(now, 0), None);
+        assert_eq!(
+            cutoff(now, 30),
+            Some(Utc.with_ymd_and_hms(2024, 5, 31, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            cutoff(now, MAX_RETENTION_DAYS + 1),
+            cutoff(now, MAX_RETENTION_DAYS)
+        );
+    }
+
+    #[test]
+    fn every_target_has_its_own_setting() {
+        let mut settings: Vec<&str> = TARGETS.iter().map(|t| t.setting).collect();
+        settings.sort_unstable();
+        settings.dedup();
+        assert_eq!(settings.len(), TARGETS.len());
+    }
+}
@@ -0,0 +1,315 @@
+//! Logging setup
+//!
+//! Pretty or JSON output to stdout and/or a rotating file, with a level
+//! filter that can be swapped at runtime (POST /api/admin/log-level).
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Layered, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+use crate::config::LoggingConfig;
+
+type Base = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+type BoxedLayer = Box<dyn Layer<Base> + Send + Sync>;
+
+static LEVEL_CONTROL: OnceLock<LevelControl> = OnceLock::new();
+
+/// Reload handle plus the directives it was built from
+struct LevelControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    configured: String,
+    current: Mutex<String>,
+}
+
+/// Current and startup filter directives
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLevelState {
+    pub current: String,
+    pub configured: String,
+}
+
+/// Keeps the background file writer alive; drop flushes it
+pub struct LogGuard {
+    _guards: Vec<WorkerGuard>,
+}
+
+/// Install the global subscriber
+pub fn init(config: &LoggingConfig) -> anyhow::Result<LogGuard> {
+    let base = std::env::var("RUST_LOG")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| config.level.clone());
+    let directives = compose_directives(&base, &config.modules);
+    let filter = EnvFilter::try_new(&directives)
+        .map_err(|e| anyhow::anyhow!("Invalid log level '{}': {}", directives, e))?;
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    let json = match config.format.as_str() {
+        "json" => true,
+        "pretty" => false,
+        other => anyhow::bail!("Unknown logging.format '{}' (pretty|json)", other),
+    };
+
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+    let mut guards = Vec::new();
+
+    if config.stdout {
+        layers.push(output_layer(json, io::stdout, true));
+    }
+
+    if let Some(file_path) = config.file_path.as_deref().filter(|p| !p.is_empty()) {
+        let (writer, guard) = match file_writer(file_path, config)? {
+            FileWriter::Rolling(appender) => tracing_appender::non_blocking(appender),
+            FileWriter::Size(file) => tracing_appender::non_blocking(file),
+        };
+        guards.push(guard);
+        layers.push(output_layer(json, writer, false));
+    }
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(layers)
+        .try_init()?;
+
+    let _ = LEVEL_CONTROL.set(LevelControl {
+        handle,
+        configured: directives.clone(),
+        current: Mutex::new(directives),
+    });
+
+    Ok(LogGuard { _guards: guards })
+}
+
+/// Base directives followed by per-module overrides
+pub fn compose_directives(base: &str, modules: &BTreeMap<String, String>) -> String {
+    let mut parts: Vec<String> = base
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    for (module, level) in modules {
+        parts.push(format!("{}={}", module.trim(), level.trim()));
+    }
+    parts.join(",")
+}
+
+/// Active filter (None before init, e.g. in tests)
+pub fn level_state() -> Option<LogLevelState> {
+    let control = LEVEL_CONTROL.get()?;
+    let current = control.current.lock().unwrap().clone();
+    Some(LogLevelState {
+        current,
+        configured: control.configured.clone(),
+    })
+}
+
+/// Replace the active filter; returns the previous directives
+pub fn set_level(directives: &str) -> Result<String, String> {
+    let control = LEVEL_CONTROL
+        .get()
+        .ok_or_else(|| "Logging is not initialized".to_string())?;
+    let directives = directives.trim();
+    if directives.is_empty() {
+        return Err("Log level must not be empty".to_string());
+    }
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| format!("Invalid log level '{}': {}", directives, e))?;
+    control
+        .handle
+        .reload(filter)
+        .map_err(|e| format!("Reload log filter: {}", e))?;
+
+    let mut current = control.current.lock().unwrap();
+    Ok(std::mem::replace(&mut *current, directives.to_string()))
+}
+
+/// Restore the directives the process started with
+pub fn reset_level() -> Result<String, String> {
+    let configured = LEVEL_CONTROL
+        .get()
+        .map(|c| c.configured.clone())
+        .ok_or_else(|| "Logging is not initialized".to_string())?;
+    set_level(&configured)
+}
+
+fn output_layer<W>(json: bool, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    if json {
+        // Span fields (request_id, route_id, client_ip, target) ride along on
+        // every event; CLOSE adds time.busy/time.idle for proxied requests
+        fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(writer)
+            .boxed()
+    } else {
+        fmt::layer().with_ansi(ansi).with_writer(writer).boxed()
+    }
+}
+
+enum FileWriter {
+    Rolling(RollingFileAppender),
+    Size(SizeRollingFile),
+}
+
+fn file_writer(file_path: &str, config: &LoggingConfig) -> anyhow::Result<FileWriter> {
+    let path = Path::new(file_path);
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow::anyhow!("logging.file_path has no file name"))?;
+    fs::create_dir_all(dir)?;
+
+    let rotation = match config.rotation.as_str() {
+        "size" => {
+            let max_bytes = config.max_size_mb.max(1) * 1024 * 1024;
+            let file = SizeRollingFile::open(path.to_path_buf(), max_bytes, config.max_files)?;
+            return Ok(FileWriter::Size(file));
+        }
+        "daily" => Rotation::DAILY,
+        "hourly" => Rotation::HOURLY,
+        "never" => Rotation::NEVER,
+        other => anyhow::bail!(
+            "Unknown logging.rotation '{}' (daily|hourly|never|size)",
+            other
+        ),
+    };
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(file_name);
+    if config.max_files > 0 {
+        builder = builder.max_log_files(config.max_files);
+    }
+    Ok(FileWriter::Rolling(builder.build(dir)?))
+}
+
+/// File that is renamed to `<path>.1` (shifting older ones up) once it
+/// reaches `max_bytes`; keeps at most `max_files` rotated files
+struct SizeRollingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRollingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files: max_files.max(1),
+            file,
+            written,
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_overrides_follow_base() {
+        let mut modules = BTreeMap::new();
+        modules.insert(
+            "lacis_proxy_gateway::omada".to_string(),
+            "debug".to_string(),
+        );
+        modules.insert("hyper".to_string(), " warn ".to_string());
+
+        assert_eq!(
+            compose_directives("lacis_proxy_gateway=info, tower_http=debug,", &modules),
+            "lacis_proxy_gateway=info,tower_http=debug,hyper=warn,lacis_proxy_gateway::omada=debug"
+        );
+        assert_eq!(compose_directives("info", &BTreeMap::new()), "info");
+    }
+
+    #[test]
+    fn size_rotation_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("lpg-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gw.log");
+
+        let mut file = SizeRollingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("gw.log.1")).unwrap(),
+            "cccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("gw.log.2")).unwrap(),
+            "bbbbbbbb\n"
+        );
+        assert!(!dir.join("gw.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
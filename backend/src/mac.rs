@@ -0,0 +1,115 @@
+//! MAC address parsing and normalization for API inputs
+//!
+//! Ingestion keys devices by the canonical MAC: 12 uppercase hex digits
+//! without separators. Handlers that receive a MAC parse it with
+//! [`MacAddr::parse`] so hand-entered values match the ingested keys instead
+//! of creating a second entry for the same device.
+
+use std::fmt;
+
+/// Accepted input formats, for error messages
+pub const MAC_FORMATS: &str =
+    "AA:BB:CC:DD:EE:FF, AA-BB-CC-DD-EE-FF, AABB.CCDD.EEFF or AABBCCDDEEFF";
+
+/// A validated MAC in canonical form
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddr(String);
+
+impl MacAddr {
+    /// Parse colon, dash, dot (Cisco) or bare notation, case-insensitive
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let trimmed = input.trim();
+        let (separator, groups, width) = if trimmed.contains(':') {
+            (Some(':'), 6, 2)
+        } else if trimmed.contains('-') {
+            (Some('-'), 6, 2)
+        } else if trimmed.contains('.') {
+            (Some('.'), 3, 4)
+        } else {
+            (None, 1, 12)
+        };
+        let parts: Vec<&str> = match separator {
+            Some(sep) => trimmed.split(sep).collect(),
+            None => vec![trimmed],
+        };
+
+        let valid = parts.len() == groups
+            && parts
+                .iter()
+                .all(|p| p.len() == width && p.chars().all(|c| c.is_ascii_hexdigit()));
+        if !valid {
+            return Err(format!(
+                "Invalid MAC address '{}': expected {}",
+                input, MAC_FORMATS
+            ));
+        }
+        Ok(Self(parts.concat().to_ascii_uppercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Topology node ids are LacisIDs, MACs or pseudo-MACs: MAC-shaped ids are
+/// canonicalized, anything else is returned as given
+pub fn canonical_node_id(id: &str) -> String {
+    MacAddr::parse(id)
+        .map(MacAddr::into_string)
+        .unwrap_or_else(|_| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_notations() {
+        for input in [
+            "aa:bb:cc:dd:ee:0f",
+            "AA-BB-CC-DD-EE-0F",
+            "aabb.ccdd.ee0f",
+            " aabbccddee0f ",
+        ] {
+            assert_eq!(MacAddr::parse(input).unwrap().as_str(), "AABBCCDDEE0F");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for input in [
+            "",
+            "aa:bb:cc:dd:ee",
+            "aa:bb:cc:dd:ee:ff:00",
+            "aa:bb-cc:dd:ee:ff",
+            "aabbccddeeff00",
+            "gg:bb:cc:dd:ee:ff",
+            "a:bb:cc:dd:ee:fff",
+            "aabb.ccdd",
+        ] {
+            let err = MacAddr::parse(input).unwrap_err();
+            assert!(err.contains(MAC_FORMATS), "{}", input);
+        }
+    }
+
+    #[test]
+    fn node_ids_keep_non_mac_forms() {
+        assert_eq!(canonical_node_id("aa:bb:cc:dd:ee:ff"), "AABBCCDDEEFF");
+        assert_eq!(canonical_node_id("f2abcdef0000"), "F2ABCDEF0000");
+        assert_eq!(
+            canonical_node_id("40010011223344550000"),
+            "40010011223344550000"
+        );
+        assert_eq!(canonical_node_id("INTERNET"), "INTERNET");
+    }
+}
@@ -3,75 +3,155 @@
 //! A reverse proxy gateway with DDNS integration, traffic routing,
 //! security monitoring, and Discord notifications.
 
+mod acme;
+mod alert_rules;
 mod api;
 mod aranea;
+mod blocklist;
+mod cluster;
 mod config;
 mod db;
 mod ddns;
+mod device_class;
 mod error;
 mod external;
+mod frontend;
 mod geoip;
 mod health;
+mod hostname_usage;
+mod ip;
+mod ip_stats;
+mod jwt_keys;
 mod lacis_id;
+mod local_dns;
+mod log_retention;
+mod logging;
+mod mac;
+mod maintenance;
+mod masking;
+mod migrations;
 mod models;
+mod network_policy;
+mod new_device;
+mod nginx_drift;
+mod nginx_log;
+mod nginx_stats;
+mod node_dedup;
 mod node_order;
 mod notify;
 mod user_object_ingester;
 mod omada;
 mod openwrt;
+mod poll_schedule;
 mod proxy;
 mod restart;
+mod route_sync;
+mod route_versions;
+mod status_page;
+mod storage_stats;
+mod sysmetrics;
+#[cfg(test)]
+mod testing;
+mod tls;
+mod topology_share;
 mod wireguard;
 
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use axum::Router;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use crate::alert_rules::AlertRuleEngine;
+use crate::aranea::reports::FacilityReporter;
+use crate::blocklist::ThreatFeedSyncer;
 use crate::db::AppState;
-use crate::ddns::DdnsUpdater;
+use crate::ddns::DnsDependencyMonitor;
 use crate::external::{ExternalDeviceManager, ExternalSyncer};
-use crate::health::HealthChecker;
-use crate::notify::DiscordNotifier;
+use crate::health::{DependencyChecker, HealthChecker};
+use crate::hostname_usage::HostnameUsageRollup;
+use crate::ip_stats::IpStatsRollup;
+use crate::log_retention::LogPruner;
+use crate::migrations::{MigrationContext, MigrationRunner};
+use crate::new_device::NewDeviceWatch;
+use crate::nginx_drift::NginxDriftWatch;
+use crate::notify::{DiscordNotifier, NotificationQueue};
 use crate::omada::{OmadaManager, OmadaSyncer};
 use crate::openwrt::{OpenWrtManager, OpenWrtSyncer};
-use crate::proxy::ProxyState;
+use crate::proxy::store_forward::ForwardReplayer;
+use crate::proxy::{ProxyState, ProxyStateDeps};
 use crate::restart::RestartScheduler;
+use crate::status_page::RouteUptimeRollup;
+use crate::storage_stats::StorageSampler;
+use crate::wireguard::expiry::WgExpiryWatch;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "lacis_proxy_gateway=info,tower_http=debug".into()),
-        )
-        .init();
+    // Load configuration (before logging, which it configures)
+    let config = config::Config::load()?;
 
-    tracing::info!("Starting LacisProxyGateway2...");
+    // Initialize tracing (guard flushes the file writer on exit)
+    let _log_guard = logging::init(&config.logging)?;
 
-    // Load configuration
-    let config = config::Config::load()?;
+    tracing::info!("Starting LacisProxyGateway2...");
     tracing::info!("Configuration loaded");
 
     // Initialize database connections
     let app_state = AppState::new(&config).await?;
     tracing::info!("Database connections established");
 
-    // Initialize notifier
-    let notifier = Arc::new(DiscordNotifier::new(app_state.clone()));
+    // Initialize notifier; its queue worker runs on every instance
+    let notification_queue = Arc::new(NotificationQueue::new(config.notify.clone()));
+    let queue_worker = notification_queue.clone();
+    tokio::spawn(async move {
+        queue_worker.run().await;
+    });
+    let notifier = Arc::new(DiscordNotifier::new(app_state.clone(), notification_queue));
 
     // Initialize OmadaManager (multi-controller management)
     let omada_manager = Arc::new(OmadaManager::new(app_state.mongo.clone()));
 
-    // MySQL → MongoDB migration (one-time, startup)
-    match omada_manager.migrate_from_mysql(&app_state.mysql).await {
-        Ok(true) => tracing::info!("Migrated omada_config from MySQL to MongoDB"),
-        Ok(false) => tracing::debug!("Omada MySQL migration skipped (already migrated or empty)"),
-        Err(e) => tracing::warn!("Omada MySQL migration failed (non-fatal): {}", e),
+    // Startup migrations (recorded in schema_migrations; a failure halts startup)
+    let migrations = Arc::new(MigrationRunner::new(MigrationContext {
+        mongo: app_state.mongo.clone(),
+        mysql: app_state.mysql.clone(),
+        omada_manager: omada_manager.clone(),
+    }));
+    let reports = migrations
+        .run_startup(config.migrations.continue_on_error)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    tracing::info!("Startup migrations complete ({} run)", reports.len());
+
+    // Declared MongoDB indexes: create missing ones, report drift
+    match app_state.mongo.check_declared_indexes(true).await {
+        Ok(reports) => {
+            for report in reports {
+                if !report.created.is_empty() {
+                    tracing::info!(
+                        "Created {} declared index(es) on {}",
+                        report.created.len(),
+                        report.collection
+                    );
+                }
+                for mismatch in &report.mismatched {
+                    tracing::warn!(
+                        "Index {} on {} differs from its declaration (unique {} vs {}, ttl {:?} vs {:?})",
+                        mismatch.name,
+                        report.collection,
+                        mismatch.actual_unique,
+                        mismatch.expected_unique,
+                        mismatch.actual_ttl_seconds,
+                        mismatch.expected_ttl_seconds
+                    );
+                }
+                if let Some(error) = &report.error {
+                    tracing::warn!("Index check of {} failed: {}", report.collection, error);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Declared index check failed (non-fatal): {}", e),
     }
 
     // Load existing controllers from MongoDB
@@ -96,86 +176,67 @@ async fn main() -> anyhow::Result<()> {
 
     // Initialize AraneaClient for mobes2.0 Cloud Functions proxy
     let aranea_client = Arc::new(aranea::AraneaClient::new(config.aranea));
+    if let Err(e) = aranea::config::reload(&aranea_client, &app_state.mysql).await {
+        tracing::warn!("Aranea config override not loaded (non-fatal): {}", e);
+    }
     if aranea_client.is_configured() {
         tracing::info!(
             "AraneaClient configured (tid: {})",
-            aranea_client.config.tid
+            aranea_client.config().tid
         );
     } else {
         tracing::info!("AraneaClient not configured (no aranea section in config)");
     }
 
-    // Initialize proxy state (includes DdnsUpdater, optional GeoIP, auth config, managers)
-    let proxy_state = ProxyState::new(
-        app_state.clone(),
+    // Leader election for singleton background tasks (standalone unless enabled)
+    let cluster = Arc::new(cluster::ClusterCoordinator::new(
+        config.cluster,
+        app_state.mongo.clone(),
         notifier.clone(),
-        config.server.geoip_db_path.as_deref(),
-        config.auth,
-        omada_manager.clone(),
-        openwrt_manager.clone(),
-        external_manager.clone(),
+    ));
+
+    // Initialize proxy state (includes DdnsUpdater, optional GeoIP, auth config, managers)
+    let proxy_state = ProxyState::new(ProxyStateDeps {
+        app_state: app_state.clone(),
+        notifier: notifier.clone(),
+        omada_manager: omada_manager.clone(),
+        openwrt_manager: openwrt_manager.clone(),
+        external_manager: external_manager.clone(),
         aranea_client,
-    )
+        cluster,
+        migrations,
+        geoip_db_path: config.server.geoip_db_path.clone(),
+        response_cache_max_mb: config.server.response_cache_max_mb,
+        max_body_mb: config.server.max_body_mb,
+        tls_config: config.server.tls.clone(),
+        acme_config: config.acme.clone(),
+        auth_config: config.auth,
+        dns_config: config.dns,
+        frontend_config: config.frontend,
+    })
     .await?;
+    if proxy_state.frontend.is_enabled() {
+        tracing::info!(
+            "Admin frontend served by LPG ({})",
+            proxy_state.frontend.mode()
+        );
+    }
+    jwt_keys::check_startup(&proxy_state).await?;
+    match proxy_state.api_trace.reload(&app_state.mysql).await {
+        Ok(()) => {
+            let recorder = proxy_state.api_trace.state(chrono::Utc::now());
+            if let (true, Some(until)) = (recorder.enabled, recorder.until) {
+                tracing::warn!("API request recorder is on until {}", until);
+            }
+        }
+        Err(e) => tracing::warn!("API request recorder state not loaded (non-fatal): {}", e),
+    }
     let route_count = proxy_state.router.read().await.len();
     tracing::info!(
         "Proxy router initialized with {} active routes",
         route_count
     );
 
-    // Migrate to nodeOrder SSoT (one-time, if cg_node_order is empty)
-    match node_order::migrate_to_node_order(&app_state.mongo).await {
-        Ok(()) => tracing::debug!("NodeOrder migration check complete"),
-        Err(e) => tracing::warn!("NodeOrder migration failed (non-fatal): {}", e),
-    }
-
-    // Repair Omada device parent relationships (AP→Switch heuristic)
-    {
-        let ingester = node_order::NodeOrderIngester::new(app_state.mongo.clone());
-        match ingester.repair_omada_device_parents().await {
-            Ok(count) => {
-                if count > 0 {
-                    tracing::info!("NodeOrder: repaired {} AP device parents", count);
-                }
-            }
-            Err(e) => tracing::warn!("NodeOrder repair failed (non-fatal): {}", e),
-        }
-    }
-
-    // Migrate cg_node_order → user_object_detail (one-time, if user_object_detail is empty)
-    match user_object_ingester::migrate_to_user_object_detail(&app_state.mongo).await {
-        Ok(()) => tracing::debug!("UserObjectDetail migration check complete"),
-        Err(e) => tracing::warn!("UserObjectDetail migration failed (non-fatal): {}", e),
-    }
-
-    // Repair user_object_detail AP parent relationships (AP→Switch heuristic)
-    {
-        let uod_ingester = user_object_ingester::UserObjectIngester::new(
-            app_state.mongo.clone(),
-            app_state.mysql.clone(),
-        );
-        match uod_ingester.repair_omada_device_parents_uod().await {
-            Ok(count) => {
-                if count > 0 {
-                    tracing::info!(
-                        "UserObjectDetail: repaired {} AP device parents",
-                        count
-                    );
-                }
-            }
-            Err(e) => tracing::warn!(
-                "UserObjectDetail repair failed (non-fatal): {}",
-                e
-            ),
-        }
-    }
-
-    // Ensure device_state_history table exists
-    match app_state.mysql.ensure_device_state_history_table().await {
-        Ok(()) => tracing::debug!("device_state_history table ready"),
-        Err(e) => tracing::warn!("device_state_history table creation failed (non-fatal): {}", e),
-    }
-
     // Refresh araneaDevice cache (non-blocking, non-fatal)
     if proxy_state.aranea_client.is_configured() {
         match proxy_state.aranea_client.refresh_device_cache().await {
@@ -188,26 +249,37 @@ async fn main() -> anyhow::Result<()> {
     start_background_tasks(
         app_state.clone(),
         notifier.clone(),
-        proxy_state.ddns_updater.clone(),
+        &proxy_state,
         omada_manager,
         openwrt_manager,
         external_manager,
     );
 
     // Build application router
-    let cors = CorsLayer::permissive();
-
-    let api_routes = api::routes(proxy_state.clone());
-
-    // Build router with proxy fallback
-    let app = api_routes
-        .fallback(proxy::proxy_handler)
-        .with_state(proxy_state)
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(cors),
+    let tls_store = proxy_state.tls.clone();
+    let app = build_app(proxy_state);
+
+    // Built-in HTTPS listener ([server.tls])
+    if tls_store.is_enabled() {
+        let tls_addr = SocketAddr::from(([0, 0, 0, 0], tls_store.port()));
+        let tls_listener = tokio::net::TcpListener::bind(tls_addr).await?;
+        if tls_store.loaded() == 0 {
+            tracing::error!(
+                "HTTPS listener has no usable certificate yet; handshakes fail until one loads"
+            );
+        }
+        tracing::info!(
+            "Listening on {} (HTTPS, {} certificates)",
+            tls_addr,
+            tls_store.loaded()
         );
+        let tls_app = app.clone();
+        tokio::spawn(async move {
+            if let Err(e) = tls::serve(tls_listener, tls_app, tls_store).await {
+                tracing::error!("HTTPS listener stopped: {}", e);
+            }
+        });
+    }
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
@@ -223,53 +295,378 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-/// Start background tasks (DDNS updater, health checker, restart scheduler, syncers)
+/// API routes with the frontend/proxy fallback (shared with the integration
+/// test harness)
+fn build_app(proxy_state: ProxyState) -> Router {
+    let cors = CorsLayer::permissive();
+
+    // The served frontend calls the API under its own base path
+    let routes = api::routes(proxy_state.clone());
+    let routes = match proxy_state.frontend.api_nest_path() {
+        Some(base_path) => routes.clone().nest(base_path, routes),
+        None => routes,
+    };
+
+    routes
+        .fallback(api::handlers::serve_frontend_or_proxy)
+        .with_state(proxy_state)
+        .layer(
+            ServiceBuilder::new()
+                .layer(TraceLayer::new_for_http())
+                .layer(cors),
+        )
+}
+
+/// Start background tasks (DDNS updater, health and dependency checkers, metrics sampler, restart scheduler, syncers).
+///
+/// The metrics sampler runs on every instance; everything else is a singleton
+/// task started by the cluster coordinator only while this instance leads.
 fn start_background_tasks(
     app_state: AppState,
     notifier: Arc<DiscordNotifier>,
-    ddns_updater: Arc<DdnsUpdater>,
+    proxy_state: &ProxyState,
     omada_manager: Arc<OmadaManager>,
     openwrt_manager: Arc<OpenWrtManager>,
     external_manager: Arc<ExternalDeviceManager>,
 ) {
-    // DDNS updater (use shared instance)
+    let cluster = proxy_state.cluster.clone();
+    let ddns_updater = proxy_state.ddns_updater.clone();
+    let network_policy = proxy_state.network_policy.clone();
+    let system_metrics = proxy_state.system_metrics.clone();
+
+    // System metrics sampler (30s, 1h ring buffer) - per instance
+    let metrics_sampler = system_metrics.clone();
+    let metrics_mysql = app_state.mysql.clone();
     tokio::spawn(async move {
-        ddns_updater.start().await;
+        metrics_sampler.start(metrics_mysql).await;
     });
 
-    // Health checker
-    let health_checker = Arc::new(HealthChecker::new(app_state.clone(), notifier));
+    // Local DNS responder for LAN clients - per instance, off unless [dns] enabled
+    let local_dns = proxy_state.local_dns.clone();
+    if local_dns.is_enabled() {
+        let dns_mysql = app_state.mysql.clone();
+        tokio::spawn(async move {
+            local_dns.start(dns_mysql).await;
+        });
+    }
+
+    // Network policy watcher (rebuilds on changed inputs) - per instance
+    let policy_watcher = network_policy.clone();
+    let policy_mysql = app_state.mysql.clone();
     tokio::spawn(async move {
-        health_checker.start().await;
+        policy_watcher.watch(policy_mysql).await;
     });
 
-    // Restart scheduler
-    let restart_scheduler = Arc::new(RestartScheduler::new(app_state.mysql.clone()));
+    // Session signing secret reload (rotations on other instances) - per instance
+    let signing_keys = proxy_state.jwt_keys.clone();
+    let jwt_mysql = app_state.mysql.clone();
+    let configured_secret = proxy_state.auth_config.jwt_secret.clone();
     tokio::spawn(async move {
-        restart_scheduler.start_monitoring().await;
+        jwt_keys::reload_loop(signing_keys, jwt_mysql, configured_secret).await;
     });
 
-    // Omada syncer (60s interval, all controllers)
-    let omada_syncer = Arc::new(OmadaSyncer::new(omada_manager, app_state.mongo.clone(), app_state.mysql.clone()));
+    // API request recorder on/off (changes on other instances) - per instance
+    let api_trace = proxy_state.api_trace.clone();
+    let api_trace_mysql = app_state.mysql.clone();
     tokio::spawn(async move {
-        omada_syncer.start().await;
+        api::api_trace::reload_loop(api_trace, api_trace_mysql).await;
     });
 
-    // OpenWrt syncer (30s interval, all routers)
-    let openwrt_syncer = Arc::new(OpenWrtSyncer::new(openwrt_manager, app_state.mongo.clone(), app_state.mysql.clone()));
+    // Aranea config override reload (changes on other instances) - per instance
+    let aranea_client = proxy_state.aranea_client.clone();
+    let aranea_mysql = app_state.mysql.clone();
     tokio::spawn(async move {
-        openwrt_syncer.start().await;
+        aranea::config::reload_loop(aranea_client, aranea_mysql).await;
     });
 
-    // External device syncer (60s interval, Mercury AC etc.)
-    let external_syncer = Arc::new(ExternalSyncer::new(
-        external_manager,
-        app_state.mongo.clone(),
+    // HTTPS certificate file reload - per instance, idle unless [server.tls] enabled
+    let tls_store = proxy_state.tls.clone();
+    let tls_mongo = app_state.mongo.clone();
+    tokio::spawn(async move {
+        tls::reload_loop(tls_store, tls_mongo).await;
+    });
+
+    // ACME certificates issued on other instances - per instance
+    let acme_sync = proxy_state.acme.clone();
+    tokio::spawn(async move {
+        acme_sync.sync_loop().await;
+    });
+
+    // Dead WebSocket tunnel sweep - per instance
+    let tunnel_tracker = proxy_state.in_flight.clone();
+    let tunnel_limits = proxy_state.proxy_limits.clone();
+    tokio::spawn(async move {
+        proxy::inflight::reap_loop(tunnel_tracker, tunnel_limits).await;
+    });
+
+    // nginx access log ingestion - per instance, idle until the file exists
+    let nginx_log = proxy_state.nginx_log.clone();
+    let nginx_log_state = proxy_state.clone();
+    tokio::spawn(async move {
+        nginx_log.start(nginx_log_state).await;
+    });
+
+    // nginx stub_status sampling - per instance
+    let nginx_stats = proxy_state.nginx_stats.clone();
+    let nginx_stats_log = proxy_state.nginx_log.clone();
+    tokio::spawn(async move {
+        nginx_stats.start(nginx_stats_log).await;
+    });
+
+    // nginx config drift vs. routes (auto-regeneration) - per instance
+    let nginx_drift = Arc::new(NginxDriftWatch::new(proxy_state.clone()));
+    tokio::spawn(async move {
+        nginx_drift.start().await;
+    });
+
+    // DDNS updater (use shared instance)
+    cluster.register_task("ddns_updater", move || {
+        let ddns_updater = ddns_updater.clone();
+        tokio::spawn(async move {
+            ddns_updater.start().await;
+        })
+    });
+
+    // ACME certificate renewal (hourly check, renew_before_days before expiry)
+    let acme = proxy_state.acme.clone();
+    cluster.register_task("acme_renewal", move || {
+        let acme = acme.clone();
+        tokio::spawn(async move {
+            acme.start().await;
+        })
+    });
+
+    // Route-linked DDNS hostnames vs. this gateway's address (every 5 min)
+    let dns_watch = Arc::new(DnsDependencyMonitor::new(
+        app_state.clone(),
+        notifier.clone(),
+    ));
+    cluster.register_task("ddns_dns_watch", move || {
+        let dns_watch = dns_watch.clone();
+        tokio::spawn(async move {
+            dns_watch.start().await;
+        })
+    });
+
+    // MongoDB collection sizes and growth projection (hourly)
+    let storage_sampler = Arc::new(StorageSampler::new(app_state.clone(), notifier.clone()));
+    cluster.register_task("storage_sampler", move || {
+        let storage_sampler = storage_sampler.clone();
+        tokio::spawn(async move {
+            storage_sampler.start().await;
+        })
+    });
+
+    // Health checker
+    let health_checker = Arc::new(
+        HealthChecker::new(app_state.clone(), notifier.clone())
+            .with_warmup(proxy_state.route_warmup.clone()),
+    );
+    cluster.register_task("health_checker", move || {
+        let health_checker = health_checker.clone();
+        tokio::spawn(async move {
+            health_checker.start().await;
+        })
+    });
+
+    // Dependency probes (MySQL, MongoDB, Omada, OpenWrt, Aranea, Discord)
+    let dependency_checker = Arc::new(DependencyChecker::new(
+        app_state.clone(),
+        notifier.clone(),
+        omada_manager.clone(),
+        proxy_state.aranea_client.clone(),
+    ));
+    cluster.register_task("dependency_checker", move || {
+        let dependency_checker = dependency_checker.clone();
+        tokio::spawn(async move {
+            dependency_checker.start().await;
+        })
+    });
+
+    // Facility reports to mobes2.0 (every facility_report_interval_hours)
+    let facility_reporter = Arc::new(FacilityReporter::new(
+        app_state.clone(),
+        proxy_state.aranea_client.clone(),
+    ));
+    cluster.register_task("facility_reports", move || {
+        let facility_reporter = facility_reporter.clone();
+        tokio::spawn(async move {
+            facility_reporter.start().await;
+        })
+    });
+
+    // New device detection, shared by the syncers' ingesters
+    let new_devices = Arc::new(NewDeviceWatch::new(app_state.clone(), notifier.clone()));
+
+    // Access-log alert rules (every 60s, rules from the alert_rules collection)
+    let alert_engine = Arc::new(AlertRuleEngine::new(app_state.clone(), notifier.clone()));
+    cluster.register_task("alert_rules", move || {
+        let alert_engine = alert_engine.clone();
+        tokio::spawn(async move {
+            alert_engine.start().await;
+        })
+    });
+
+    // Per-IP daily rollups (every 10 min, resumes at the first unfinished day)
+    let ip_stats_rollup = Arc::new(IpStatsRollup::new(app_state.clone()));
+    cluster.register_task("ip_stats_rollup", move || {
+        let ip_stats_rollup = ip_stats_rollup.clone();
+        tokio::spawn(async move {
+            ip_stats_rollup.start().await;
+        })
+    });
+
+    // Age-based pruning of access logs, security events and health checks (daily)
+    let log_pruner = Arc::new(LogPruner::new(app_state.clone()));
+    cluster.register_task("log_retention", move || {
+        let log_pruner = log_pruner.clone();
+        tokio::spawn(async move {
+            log_pruner.start().await;
+        })
+    });
+
+    // Per-route daily uptime for the status page (every 10 min)
+    let uptime_rollup = Arc::new(RouteUptimeRollup::new(app_state.clone()));
+    cluster.register_task("route_uptime_rollup", move || {
+        let uptime_rollup = uptime_rollup.clone();
+        tokio::spawn(async move {
+            uptime_rollup.start().await;
+        })
+    });
+
+    // Monthly usage per DDNS hostname for billing (hourly)
+    let hostname_usage_rollup = Arc::new(HostnameUsageRollup::new(app_state.clone()));
+    cluster.register_task("hostname_usage_rollup", move || {
+        let hostname_usage_rollup = hostname_usage_rollup.clone();
+        tokio::spawn(async move {
+            hostname_usage_rollup.start().await;
+        })
+    });
+
+    // WireGuard peer expiry (every 5 min, disables expired peers via Omada)
+    let wg_expiry = Arc::new(WgExpiryWatch::new(
+        app_state.clone(),
+        notifier.clone(),
+        proxy_state.omada_manager.clone(),
+    ));
+    cluster.register_task("wg_peer_expiry", move || {
+        let wg_expiry = wg_expiry.clone();
+        tokio::spawn(async move {
+            wg_expiry.start().await;
+        })
+    });
+
+    // Store-and-forward replayer (every 5s, routes with a store_forward policy)
+    let forward_replayer = Arc::new(ForwardReplayer::new(
+        app_state.clone(),
+        proxy_state.http_client.clone(),
+    ));
+    cluster.register_task("store_forward_replay", move || {
+        let forward_replayer = forward_replayer.clone();
+        tokio::spawn(async move {
+            forward_replayer.start().await;
+        })
+    });
+
+    // Threat feed syncer (feeds from the threat_feeds setting)
+    let feed_syncer = Arc::new(ThreatFeedSyncer::new(
+        app_state.clone(),
+        notifier.clone(),
+        network_policy.clone(),
+    ));
+    cluster.register_task("threat_feed_syncer", move || {
+        let feed_syncer = feed_syncer.clone();
+        tokio::spawn(async move {
+            feed_syncer.start().await;
+        })
+    });
+
+    // Soft-deleted route purge (hourly, retention from settings)
+    let purge_mysql = app_state.mysql.clone();
+    cluster.register_task("route_purge", move || {
+        let purge_mysql = purge_mysql.clone();
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                timer.tick().await;
+                let days = api::handlers::route_deleted_retention_days(&purge_mysql).await;
+                if let Err(e) =
+                    api::handlers::purge_and_audit(&purge_mysql, days, "retention").await
+                {
+                    tracing::warn!("Soft-deleted route purge failed: {}", e);
+                }
+            }
+        })
+    });
+
+    // Restart scheduler (reads the shared metrics samples, drains before rebooting)
+    let restart_scheduler = Arc::new(RestartScheduler::new(
         app_state.mysql.clone(),
+        system_metrics,
+        proxy_state.drain.clone(),
+        proxy_state.in_flight.clone(),
     ));
-    tokio::spawn(async move {
-        external_syncer.start().await;
+    cluster.register_task("restart_scheduler", move || {
+        let restart_scheduler = restart_scheduler.clone();
+        tokio::spawn(async move {
+            restart_scheduler.start_monitoring().await;
+        })
     });
 
+    // Omada syncer (60s interval, all controllers)
+    let omada_syncer = Arc::new(
+        OmadaSyncer::new(
+            omada_manager,
+            app_state.mongo.clone(),
+            app_state.mysql.clone(),
+        )
+        .with_drain(proxy_state.drain.clone())
+        .with_new_device_watch(new_devices.clone())
+        .with_notifier(notifier),
+    );
+    cluster.register_task("omada_syncer", move || {
+        let omada_syncer = omada_syncer.clone();
+        tokio::spawn(async move {
+            omada_syncer.start().await;
+        })
+    });
+
+    // OpenWrt syncer (30s interval, all routers)
+    let openwrt_syncer = Arc::new(
+        OpenWrtSyncer::new(
+            openwrt_manager,
+            app_state.mongo.clone(),
+            app_state.mysql.clone(),
+        )
+        .with_drain(proxy_state.drain.clone())
+        .with_new_device_watch(new_devices.clone()),
+    );
+    cluster.register_task("openwrt_syncer", move || {
+        let openwrt_syncer = openwrt_syncer.clone();
+        tokio::spawn(async move {
+            openwrt_syncer.start().await;
+        })
+    });
+
+    // External device syncer (60s interval, Mercury AC etc.)
+    let external_syncer = Arc::new(
+        ExternalSyncer::new(
+            external_manager,
+            app_state.mongo.clone(),
+            app_state.mysql.clone(),
+        )
+        .with_drain(proxy_state.drain.clone())
+        .with_new_device_watch(new_devices),
+    );
+    cluster.register_task("external_syncer", move || {
+        let external_syncer = external_syncer.clone();
+        tokio::spawn(async move {
+            external_syncer.start().await;
+        })
+    });
+
+    // Leader election starts/stops the singleton tasks above
+    tokio::spawn(cluster.run());
+
     tracing::info!("Background tasks started");
 }
@@ -0,0 +1,611 @@
+//! Maintenance windows for health check alert suppression
+//!
+//! Windows live in the `maintenance_windows` collection and are evaluated by
+//! the health checker on every cycle:
+//!
+//! - `schedule`: a one-shot `once` range, or `weekly` occurrences on the
+//!   listed days (empty = every day) starting at `start` (HH:MM, server local
+//!   time, like the scheduled restart) for `duration_minutes`
+//! - scope: `route_ids` and/or `teams` (route team tags); both empty = all
+//!   routes
+//! - `mode`: `suppress` runs checks but sends no failure/recovery
+//!   notifications; `pause` skips the checks of covered routes
+//!
+//! When windows overlap, `pause` wins over `suppress`, and a suppressed
+//! failure is counted once, against the covering window that ends last. When
+//! an occurrence closes, the checker sends one summary per window that
+//! suppressed anything ("12 failures suppressed during 'weekly patching', all
+//! recovered"). Routes still failing at that point are listed in the summary
+//! and notify their recovery normally.
+
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProxyRoute;
+
+/// Longest one-shot window (30 days)
+pub const MAX_ONCE_MINUTES: i64 = 43_200;
+/// Longest recurring occurrence (one week)
+pub const MAX_WEEKLY_MINUTES: u32 = 10_080;
+/// Routes named in a summary before it is cut short
+const SUMMARY_ROUTES: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceMode {
+    /// Checks run, notifications are held back
+    #[default]
+    Suppress,
+    /// Checks of covered routes do not run
+    Pause,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MaintenanceSchedule {
+    Once {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    Weekly {
+        /// "mon".."sun"; empty = every day
+        #[serde(default)]
+        days: Vec<String>,
+        /// HH:MM, server local time
+        start: String,
+        duration_minutes: u32,
+    },
+}
+
+/// Tally of the current occurrence and the last summary, written by the
+/// health checker only
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindowStatus {
+    /// Start of the occurrence being tallied (None = not active)
+    #[serde(default)]
+    pub occurrence_start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub occurrence_end: Option<DateTime<Utc>>,
+    /// Failed checks of covered routes during the occurrence
+    #[serde(default)]
+    pub suppressed_failures: u64,
+    /// Failure notifications held back during the occurrence
+    #[serde(default)]
+    pub suppressed_alerts: u64,
+    /// Routes with suppressed failures during the occurrence
+    #[serde(default)]
+    pub failed_routes: Vec<i32>,
+    #[serde(default)]
+    pub last_closed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_summary: Option<String>,
+}
+
+/// A maintenance window document (collection `maintenance_windows`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub window_id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub schedule: MaintenanceSchedule,
+    #[serde(default)]
+    pub route_ids: Vec<i32>,
+    #[serde(default)]
+    pub teams: Vec<String>,
+    #[serde(default)]
+    pub mode: MaintenanceMode,
+    #[serde(default)]
+    pub status: MaintenanceWindowStatus,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// POST /api/maintenance/windows body
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateMaintenanceWindowRequest {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub schedule: MaintenanceSchedule,
+    #[serde(default)]
+    pub route_ids: Vec<i32>,
+    #[serde(default)]
+    pub teams: Vec<String>,
+    #[serde(default)]
+    pub mode: MaintenanceMode,
+}
+
+/// PUT /api/maintenance/windows/:id body (None = unchanged)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UpdateMaintenanceWindowRequest {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub schedule: Option<MaintenanceSchedule>,
+    pub route_ids: Option<Vec<i32>>,
+    pub teams: Option<Vec<String>>,
+    pub mode: Option<MaintenanceMode>,
+}
+
+fn parse_start(start: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(start.trim(), "%H:%M")
+        .map_err(|_| format!("start must be HH:MM, got {:?}", start))
+}
+
+fn parse_day(day: &str) -> Result<Weekday, String> {
+    day.trim()
+        .parse::<Weekday>()
+        .map_err(|_| format!("unknown day {:?} (use mon..sun)", day))
+}
+
+/// "mon".."sun" for a weekday
+fn day_key(day: Weekday) -> String {
+    day.to_string().to_lowercase()
+}
+
+fn normalize_teams(teams: Vec<String>) -> Vec<String> {
+    let mut teams: Vec<String> = teams
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    teams.sort();
+    teams.dedup();
+    teams
+}
+
+impl MaintenanceWindow {
+    pub fn new(req: CreateMaintenanceWindowRequest) -> Result<Self, String> {
+        let now = Utc::now().to_rfc3339();
+        let mut window = Self {
+            window_id: uuid::Uuid::new_v4().to_string(),
+            name: req.name.trim().to_string(),
+            enabled: req.enabled,
+            schedule: req.schedule,
+            route_ids: req.route_ids,
+            teams: normalize_teams(req.teams),
+            mode: req.mode,
+            status: MaintenanceWindowStatus::default(),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        window.validate()?;
+        window.normalize_schedule();
+        Ok(window)
+    }
+
+    /// Apply an update and re-validate the result
+    pub fn apply(&mut self, req: UpdateMaintenanceWindowRequest) -> Result<(), String> {
+        if let Some(name) = req.name {
+            self.name = name.trim().to_string();
+        }
+        if let Some(enabled) = req.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(schedule) = req.schedule {
+            self.schedule = schedule;
+        }
+        if let Some(route_ids) = req.route_ids {
+            self.route_ids = route_ids;
+        }
+        if let Some(teams) = req.teams {
+            self.teams = normalize_teams(teams);
+        }
+        if let Some(mode) = req.mode {
+            self.mode = mode;
+        }
+        self.updated_at = Utc::now().to_rfc3339();
+        self.validate()?;
+        self.normalize_schedule();
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name is required".to_string());
+        }
+        match &self.schedule {
+            MaintenanceSchedule::Once { start, end } => {
+                if end <= start {
+                    return Err("end must be after start".to_string());
+                }
+                if (*end - *start).num_minutes() > MAX_ONCE_MINUTES {
+                    return Err(format!(
+                        "a one-shot window may last at most {} minutes",
+                        MAX_ONCE_MINUTES
+                    ));
+                }
+            }
+            MaintenanceSchedule::Weekly {
+                days,
+                start,
+                duration_minutes,
+            } => {
+                parse_start(start)?;
+                for day in days {
+                    parse_day(day)?;
+                }
+                if !(1..=MAX_WEEKLY_MINUTES).contains(duration_minutes) {
+                    return Err(format!(
+                        "duration_minutes must be between 1 and {}",
+                        MAX_WEEKLY_MINUTES
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Canonical day names and start time (after `validate`)
+    fn normalize_schedule(&mut self) {
+        if let MaintenanceSchedule::Weekly { days, start, .. } = &mut self.schedule {
+            let mut parsed: Vec<Weekday> = days.iter().filter_map(|d| parse_day(d).ok()).collect();
+            parsed.sort_by_key(|d| d.num_days_from_monday());
+            parsed.dedup();
+            *days = parsed.into_iter().map(day_key).collect();
+            if let Ok(time) = parse_start(start) {
+                *start = time.format("%H:%M").to_string();
+            }
+        }
+    }
+
+    /// The occurrence containing `now`, as (start, end), with recurring
+    /// schedules read in `tz`. An occurrence runs from start inclusive to
+    /// end exclusive; when recurring occurrences overlap the latest wins.
+    pub fn occurrence_at<Tz: TimeZone>(
+        &self,
+        now: DateTime<Utc>,
+        tz: &Tz,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        match &self.schedule {
+            MaintenanceSchedule::Once { start, end } => {
+                (*start <= now && now < *end).then_some((*start, *end))
+            }
+            MaintenanceSchedule::Weekly {
+                days,
+                start,
+                duration_minutes,
+            } => {
+                let time = parse_start(start).ok()?;
+                let days: Vec<Weekday> = days.iter().filter_map(|d| parse_day(d).ok()).collect();
+                let duration = Duration::minutes(*duration_minutes as i64);
+                let today = now.with_timezone(tz).date_naive();
+                // Occurrences last at most a week: today and the 7 days before
+                (0..=7).find_map(|back| {
+                    let day = today - Duration::days(back);
+                    if !days.is_empty() && !days.contains(&chrono::Datelike::weekday(&day)) {
+                        return None;
+                    }
+                    let begin = tz
+                        .from_local_datetime(&day.and_time(time))
+                        .earliest()?
+                        .with_timezone(&Utc);
+                    let end = begin + duration;
+                    (begin <= now && now < end).then_some((begin, end))
+                })
+            }
+        }
+    }
+
+    /// Whether the window applies to `route`
+    pub fn covers(&self, route: &ProxyRoute) -> bool {
+        if self.route_ids.is_empty() && self.teams.is_empty() {
+            return true;
+        }
+        self.route_ids.contains(&route.id)
+            || route.team.as_deref().is_some_and(|team| {
+                self.teams
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(team.trim()))
+            })
+    }
+}
+
+/// A window whose occurrence contains the evaluation time
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveMaintenance {
+    pub window_id: String,
+    pub name: String,
+    pub mode: MaintenanceMode,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Enabled windows active at `now` (recurring schedules in server local time)
+pub fn active_windows(windows: &[MaintenanceWindow], now: DateTime<Utc>) -> Vec<ActiveMaintenance> {
+    active_windows_in(windows, now, &chrono::Local)
+}
+
+fn active_windows_in<Tz: TimeZone>(
+    windows: &[MaintenanceWindow],
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> Vec<ActiveMaintenance> {
+    windows
+        .iter()
+        .filter(|w| w.enabled)
+        .filter_map(|w| {
+            let (starts_at, ends_at) = w.occurrence_at(now, tz)?;
+            Some(ActiveMaintenance {
+                window_id: w.window_id.clone(),
+                name: w.name.clone(),
+                mode: w.mode,
+                starts_at,
+                ends_at,
+            })
+        })
+        .collect()
+}
+
+/// How the active windows apply to one route
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteMaintenance {
+    /// Some covering window pauses the checks
+    pub paused: bool,
+    /// Window a suppressed failure is counted against (the covering window
+    /// that ends last); None = not in maintenance
+    pub charged_to: Option<String>,
+}
+
+/// Active windows that cover `route`
+pub fn covering<'a>(
+    route: &ProxyRoute,
+    windows: &[MaintenanceWindow],
+    active: &'a [ActiveMaintenance],
+) -> Vec<&'a ActiveMaintenance> {
+    active
+        .iter()
+        .filter(|a| {
+            windows
+                .iter()
+                .any(|w| w.window_id == a.window_id && w.covers(route))
+        })
+        .collect()
+}
+
+/// Resolve overlapping windows for `route`
+pub fn resolve(
+    route: &ProxyRoute,
+    windows: &[MaintenanceWindow],
+    active: &[ActiveMaintenance],
+) -> RouteMaintenance {
+    let covering = covering(route, windows, active);
+    RouteMaintenance {
+        paused: covering.iter().any(|a| a.mode == MaintenanceMode::Pause),
+        charged_to: covering
+            .iter()
+            .max_by(|a, b| a.ends_at.cmp(&b.ends_at).then(b.name.cmp(&a.name)))
+            .map(|a| a.window_id.clone()),
+    }
+}
+
+/// Closing summary of an occurrence; `still_failing` are the paths of
+/// tallied routes that have not recovered. None when nothing was suppressed.
+pub fn summarize(
+    window: &MaintenanceWindow,
+    status: &MaintenanceWindowStatus,
+    still_failing: &[String],
+) -> Option<String> {
+    if status.suppressed_failures == 0 {
+        return None;
+    }
+    let plural = if status.suppressed_failures == 1 {
+        "failure"
+    } else {
+        "failures"
+    };
+    let mut text = format!(
+        "{} {} suppressed during '{}' ({} route(s)",
+        status.suppressed_failures,
+        plural,
+        window.name,
+        status.failed_routes.len()
+    );
+    if status.suppressed_alerts > 0 {
+        text.push_str(&format!(
+            ", {} alert(s) held back",
+            status.suppressed_alerts
+        ));
+    }
+    text.push_str("), ");
+    if still_failing.is_empty() {
+        text.push_str("all recovered");
+    } else {
+        let mut listed = still_failing
+            .iter()
+            .take(SUMMARY_ROUTES)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if still_failing.len() > SUMMARY_ROUTES {
+            listed.push_str(&format!(
+                " and {} more",
+                still_failing.len() - SUMMARY_ROUTES
+            ));
+        }
+        text.push_str(&format!("still failing: {}", listed));
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn weekly(days: &[&str], start: &str, minutes: u32) -> MaintenanceWindow {
+        MaintenanceWindow::new(CreateMaintenanceWindowRequest {
+            name: "weekly patching".to_string(),
+            enabled: true,
+            schedule: MaintenanceSchedule::Weekly {
+                days: days.iter().map(|d| d.to_string()).collect(),
+                start: start.to_string(),
+                duration_minutes: minutes,
+            },
+            route_ids: vec![],
+            teams: vec![],
+            mode: MaintenanceMode::Suppress,
+        })
+        .unwrap()
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn route(id: i32, team: Option<&str>) -> ProxyRoute {
+        ProxyRoute {
+            id,
+            path: format!("/r{}", id),
+            target: "http://127.0.0.1:9".to_string(),
+            priority: 0,
+            team: team.map(str::to_string),
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    #[test]
+    fn weekly_occurrences_follow_day_and_time() {
+        // 2026-10-18 is a Sunday
+        let w = weekly(&["Sunday"], "2:00", 60);
+        assert_eq!(
+            w.schedule,
+            MaintenanceSchedule::Weekly {
+                days: vec!["sun".to_string()],
+                start: "02:00".to_string(),
+                duration_minutes: 60
+            }
+        );
+        assert_eq!(
+            w.occurrence_at(at("2026-10-18T02:30:00Z"), &Utc),
+            Some((at("2026-10-18T02:00:00Z"), at("2026-10-18T03:00:00Z")))
+        );
+        assert_eq!(w.occurrence_at(at("2026-10-18T03:00:00Z"), &Utc), None);
+        assert_eq!(w.occurrence_at(at("2026-10-17T02:30:00Z"), &Utc), None);
+
+        // Read in the server's zone: 02:00 at +09:00 is 17:00 UTC the day before
+        let jst = FixedOffset::east_opt(9 * 3600).unwrap();
+        assert!(w.occurrence_at(at("2026-10-17T17:10:00Z"), &jst).is_some());
+    }
+
+    #[test]
+    fn weekly_occurrences_span_midnight_and_every_day() {
+        let w = weekly(&["sat"], "23:30", 120);
+        assert_eq!(
+            w.occurrence_at(at("2026-10-18T01:00:00Z"), &Utc),
+            Some((at("2026-10-17T23:30:00Z"), at("2026-10-18T01:30:00Z")))
+        );
+        let daily = weekly(&[], "12:00", 10);
+        assert!(daily
+            .occurrence_at(at("2026-10-14T12:05:00Z"), &Utc)
+            .is_some());
+        assert!(daily
+            .occurrence_at(at("2026-10-14T12:15:00Z"), &Utc)
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        let mut w = weekly(&["sun"], "02:00", 60);
+        let bad = |schedule| UpdateMaintenanceWindowRequest {
+            schedule: Some(schedule),
+            ..Default::default()
+        };
+        assert!(w
+            .apply(bad(MaintenanceSchedule::Weekly {
+                days: vec!["funday".to_string()],
+                start: "02:00".to_string(),
+                duration_minutes: 60,
+            }))
+            .is_err());
+        assert!(w
+            .apply(bad(MaintenanceSchedule::Weekly {
+                days: vec![],
+                start: "25:00".to_string(),
+                duration_minutes: 60,
+            }))
+            .is_err());
+        assert!(w
+            .apply(bad(MaintenanceSchedule::Once {
+                start: at("2026-10-18T03:00:00Z"),
+                end: at("2026-10-18T02:00:00Z"),
+            }))
+            .is_err());
+        assert!(w
+            .apply(bad(MaintenanceSchedule::Once {
+                start: at("2026-10-18T02:00:00Z"),
+                end: at("2026-10-18T03:00:00Z"),
+            }))
+            .is_ok());
+        assert!(w.occurrence_at(at("2026-10-18T02:59:59Z"), &Utc).is_some());
+    }
+
+    #[test]
+    fn scope_matches_route_ids_and_teams() {
+        let mut w = weekly(&[], "00:00", 60);
+        assert!(w.covers(&route(1, None)));
+        w.route_ids = vec![2];
+        w.teams = vec!["Infra".to_string()];
+        assert!(!w.covers(&route(1, None)));
+        assert!(w.covers(&route(2, None)));
+        assert!(w.covers(&route(3, Some("infra"))));
+        assert!(!w.covers(&route(4, Some("web"))));
+    }
+
+    #[test]
+    fn overlapping_windows_pause_wins_and_last_ending_is_charged() {
+        let now = at("2026-10-18T02:30:00Z");
+        let mut short = weekly(&[], "02:00", 60);
+        short.name = "short".to_string();
+        let mut long = weekly(&[], "02:15", 120);
+        long.name = "long".to_string();
+        long.teams = vec!["infra".to_string()];
+        let windows = vec![short.clone(), long.clone()];
+        let active = active_windows_in(&windows, now, &Utc);
+        assert_eq!(active.len(), 2);
+
+        let infra = resolve(&route(1, Some("infra")), &windows, &active);
+        assert_eq!(
+            infra,
+            RouteMaintenance {
+                paused: false,
+                charged_to: Some(long.window_id.clone())
+            }
+        );
+        let web = resolve(&route(2, Some("web")), &windows, &active);
+        assert_eq!(web.charged_to, Some(short.window_id.clone()));
+
+        let mut paused = windows.clone();
+        paused[0].mode = MaintenanceMode::Pause;
+        let active = active_windows_in(&paused, now, &Utc);
+        assert!(resolve(&route(1, Some("infra")), &paused, &active).paused);
+
+        paused[0].enabled = false;
+        let active = active_windows_in(&paused, now, &Utc);
+        assert_eq!(
+            resolve(&route(2, None), &paused, &active),
+            RouteMaintenance::default()
+        );
+    }
+
+    #[test]
+    fn summarizes_suppressed_failures() {
+        let w = weekly(&["sun"], "02:00", 60);
+        let mut status = MaintenanceWindowStatus::default();
+        assert_eq!(summarize(&w, &status, &[]), None);
+
+        status.suppressed_failures = 12;
+        status.suppressed_alerts = 2;
+        status.failed_routes = vec![1, 2];
+        assert_eq!(
+            summarize(&w, &status, &[]).unwrap(),
+            "12 failures suppressed during 'weekly patching' (2 route(s), 2 alert(s) held back), all recovered"
+        );
+        assert!(summarize(&w, &status, &["/api".to_string()])
+            .unwrap()
+            .ends_with("still failing: /api"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Credential masking for values that leave the process or get stored
+//!
+//! Source records returned by the topology API and admin API requests kept
+//! by the request recorder (`api_trace`) go through the same rules: any
+//! field or query parameter whose name looks like a credential is replaced
+//! by [`MASK`], at any depth.
+
+/// Shown instead of a credential
+pub const MASK: &str = "********";
+
+/// Name fragments marking a credential (compared lowercase)
+const CREDENTIAL_NAMES: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "private_key",
+    "api_key",
+    "signing_key",
+    "webhook_url",
+    "cic",
+    "authorization",
+    "cookie",
+];
+
+/// Whether a field or parameter name holds a credential
+pub fn is_credential_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    CREDENTIAL_NAMES.iter().any(|s| {
+        // "cic" only as a whole word (tenant_cic, not "specific")
+        if *s == "cic" {
+            name.split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|part| part == "cic")
+        } else {
+            name.contains(s)
+        }
+    })
+}
+
+/// Replace password/secret/token values (any depth) with a mask
+pub fn mask_credentials(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if is_credential_name(key) {
+                    if !v.is_null() {
+                        *v = serde_json::Value::String(MASK.to_string());
+                    }
+                } else {
+                    mask_credentials(v);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_credentials),
+        _ => {}
+    }
+}
+
+/// Query string with credential parameters masked (order and encoding of
+/// the other parameters kept)
+pub fn mask_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_credential_name(name) => format!("{}={}", name, MASK),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_credentials() {
+        let mut value = serde_json::json!({
+            "router_id": "r1",
+            "password": "hunter2",
+            "ssh": { "private_key": "KEY", "user": "root" },
+            "api_token": null,
+            "tenant_cic": "123456",
+            "specific": "kept",
+            "items": [{ "client_secret": "s" }],
+        });
+        mask_credentials(&mut value);
+        assert_eq!(value["password"], MASK);
+        assert_eq!(value["ssh"]["private_key"], MASK);
+        assert_eq!(value["ssh"]["user"], "root");
+        assert!(value["api_token"].is_null());
+        assert_eq!(value["tenant_cic"], MASK);
+        assert_eq!(value["specific"], "kept");
+        assert_eq!(value["items"][0]["client_secret"], MASK);
+    }
+
+    #[test]
+    fn query_credentials_are_masked() {
+        assert_eq!(
+            mask_query("limit=10&token=abc&path=%2Fapi"),
+            format!("limit=10&token={}&path=%2Fapi", MASK)
+        );
+        assert_eq!(
+            mask_query("flag&Api_Key=1"),
+            format!("flag&Api_Key={}", MASK)
+        );
+        assert_eq!(mask_query(""), "");
+    }
+}
@@ -0,0 +1,1392 @@
+//! Startup migrations
+//!
+//! Every one-time data step (and the idempotent repairs that used to run
+//! beside them) is a named [`Migration`]. The runner executes them in
+//! registry order at startup and records each run in the `schema_migrations`
+//! collection, so "already done" is a recorded fact instead of a guess from
+//! collection counts. A migration runs at startup when it has no record or
+//! its last run failed; `every_start` migrations run on every start.
+//!
+//! A failed migration halts startup. With `migrations.continue_on_error`
+//! set, failures of non-critical migrations are only logged.
+//!
+//! Ids are never renamed or reused: a new step gets a new id appended to
+//! [`registry`].
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::db::mongo::schema_migrations::SchemaMigrationDoc;
+use crate::db::{MongoDb, MySqlDb};
+use crate::node_order::{self, NodeOrderIngester};
+use crate::omada::OmadaManager;
+use crate::route_versions::{self, SYSTEM_ACTOR};
+use crate::user_object_ingester::{self, UserObjectIngester};
+
+/// What a successful run did
+#[derive(Debug, Clone)]
+pub enum MigrationRun {
+    Applied(String),
+    /// Nothing to do (already in place, or no source data)
+    Skipped(String),
+}
+
+/// Databases and managers a migration may touch; tests point these at
+/// fixture databases
+#[derive(Clone)]
+pub struct MigrationContext {
+    pub mongo: Arc<MongoDb>,
+    pub mysql: Arc<MySqlDb>,
+    pub omada_manager: Arc<OmadaManager>,
+}
+
+/// One named, idempotent startup step
+#[async_trait]
+pub trait Migration: Send + Sync {
+    /// Stable id, recorded in schema_migrations
+    fn id(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    /// A failure halts startup even with `continue_on_error`
+    fn critical(&self) -> bool {
+        true
+    }
+
+    /// Run on every start, not only until it succeeds once
+    fn every_start(&self) -> bool {
+        false
+    }
+
+    /// `force` skips the step's own "already populated" guard
+    async fn run(&self, ctx: &MigrationContext, force: bool) -> Result<MigrationRun, String>;
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// All migrations, in execution order
+pub fn registry() -> Vec<Box<dyn Migration>> {
+    vec![
+        Box::new(OmadaConfigToMongo),
+        Box::new(NodeOrderInit),
+        Box::new(NodeOrderApParents),
+        Box::new(UserObjectDetailInit),
+        Box::new(UserObjectDetailApParents),
+        Box::new(DeviceStateHistoryTable),
+        Box::new(DeviceSearchIndexes),
+        Box::new(WgPeerRecords),
+        Box::new(RouteStoreForward),
+        Box::new(LacisOathProviders),
+        Box::new(RouteExpectContinue),
+        Box::new(IpDailyStatsIndexes),
+        Box::new(DependenciesHealthIndexes),
+        Box::new(FacilityReportIndexes),
+        Box::new(StatusPage),
+        Box::new(RouteTransform),
+        Box::new(DnsOverrides),
+        Box::new(AraneaRegistrationTokens),
+        Box::new(BlockedIpContext),
+        Box::new(RouteCanary),
+        Box::new(TopologyShares),
+        Box::new(RouteVersions),
+        Box::new(RouteLogFields),
+        Box::new(HostnameUsageIndexes),
+        Box::new(RouteGrpc),
+        Box::new(DdnsDnsChecks),
+        Box::new(ApiTrace),
+        Box::new(RouteHealthCheck),
+        Box::new(RouteUpstreamAuth),
+        Box::new(RouteCache),
+        Box::new(RateLimitRules),
+        Box::new(DdnsIpv6),
+        Box::new(DdnsRecordIds),
+        Box::new(LogRetentionSettings),
+        Box::new(RouteHeaderRewrite),
+        Box::new(AcmeCertificates),
+        Box::new(RouteUpstreamTuning),
+        Box::new(RouteFailover),
+        Box::new(RouteMaintenanceMode),
+        Box::new(RouteBodyLimit),
+        Box::new(AccessLogRouteErrors),
+        Box::new(NetworkPolicySettings),
+        Box::new(AccessLogRouteErrorOrder),
+    ]
+}
+
+struct OmadaConfigToMongo;
+
+#[async_trait]
+impl Migration for OmadaConfigToMongo {
+    fn id(&self) -> &'static str {
+        "001_omada_config_to_mongo"
+    }
+
+    fn description(&self) -> &'static str {
+        "Copy the MySQL omada_config controller into MongoDB omada_controllers"
+    }
+
+    /// Needs the controller reachable when omadac_id was never stored
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, ctx: &MigrationContext, force: bool) -> Result<MigrationRun, String> {
+        match ctx
+            .omada_manager
+            .migrate_from_mysql(&ctx.mysql, force)
+            .await?
+        {
+            true => Ok(MigrationRun::Applied("controller migrated".to_string())),
+            false => Ok(MigrationRun::Skipped(
+                "controllers already in MongoDB, or omada_config empty/incomplete".to_string(),
+            )),
+        }
+    }
+}
+
+struct NodeOrderInit;
+
+#[async_trait]
+impl Migration for NodeOrderInit {
+    fn id(&self) -> &'static str {
+        "002_cg_node_order_init"
+    }
+
+    fn description(&self) -> &'static str {
+        "Populate cg_node_order from Omada/OpenWrt/external data and migrate old node ids"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, force: bool) -> Result<MigrationRun, String> {
+        match node_order::migrate_to_node_order(&ctx.mongo, force).await? {
+            true => Ok(MigrationRun::Applied(format!(
+                "{} cg_node_order entries",
+                ctx.mongo.count_node_order().await?
+            ))),
+            false => Ok(MigrationRun::Skipped(
+                "cg_node_order already populated".to_string(),
+            )),
+        }
+    }
+}
+
+struct NodeOrderApParents;
+
+#[async_trait]
+impl Migration for NodeOrderApParents {
+    fn id(&self) -> &'static str {
+        "003_cg_node_order_ap_parents"
+    }
+
+    fn description(&self) -> &'static str {
+        "Repair AP parents in cg_node_order (AP→Switch heuristic)"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    fn every_start(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        let ingester = NodeOrderIngester::new(ctx.mongo.clone());
+        repaired(ingester.repair_omada_device_parents().await?)
+    }
+}
+
+struct UserObjectDetailInit;
+
+#[async_trait]
+impl Migration for UserObjectDetailInit {
+    fn id(&self) -> &'static str {
+        "004_user_object_detail_init"
+    }
+
+    fn description(&self) -> &'static str {
+        "Populate user_object_detail from cg_node_order (MAC → LacisID ids)"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, force: bool) -> Result<MigrationRun, String> {
+        match user_object_ingester::migrate_to_user_object_detail(&ctx.mongo, force).await? {
+            true => Ok(MigrationRun::Applied(format!(
+                "{} user_object_detail entries",
+                ctx.mongo.count_user_object_details().await?
+            ))),
+            false => Ok(MigrationRun::Skipped(
+                "user_object_detail already populated, or cg_node_order empty".to_string(),
+            )),
+        }
+    }
+}
+
+struct UserObjectDetailApParents;
+
+#[async_trait]
+impl Migration for UserObjectDetailApParents {
+    fn id(&self) -> &'static str {
+        "005_user_object_detail_ap_parents"
+    }
+
+    fn description(&self) -> &'static str {
+        "Repair AP parents in user_object_detail (AP→Switch heuristic)"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    fn every_start(&self) -> bool {
+        true
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        let ingester = UserObjectIngester::new(ctx.mongo.clone(), ctx.mysql.clone());
+        repaired(ingester.repair_omada_device_parents_uod().await?)
+    }
+}
+
+struct DeviceStateHistoryTable;
+
+#[async_trait]
+impl Migration for DeviceStateHistoryTable {
+    fn id(&self) -> &'static str {
+        "006_device_state_history_table"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the MySQL device_state_history table"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql.ensure_device_state_history_table().await?;
+        Ok(MigrationRun::Applied("table ready".to_string()))
+    }
+}
+
+struct DeviceSearchIndexes;
+
+#[async_trait]
+impl Migration for DeviceSearchIndexes {
+    fn id(&self) -> &'static str {
+        "007_device_search_indexes"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the MongoDB indexes used by GET /api/devices/search"
+    }
+
+    fn critical(&self) -> bool {
+        false
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_device_search_indexes().await?;
+        Ok(MigrationRun::Applied("indexes ready".to_string()))
+    }
+}
+
+struct WgPeerRecords;
+
+#[async_trait]
+impl Migration for WgPeerRecords {
+    fn id(&self) -> &'static str {
+        "008_wg_peer_records"
+    }
+
+    fn description(&self) -> &'static str {
+        "Track allowed addresses and enabled state of LPG-provisioned WireGuard peers"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_wg_peer_record_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "wg_peer_profiles columns ready".to_string(),
+        ))
+    }
+}
+
+struct RouteStoreForward;
+
+#[async_trait]
+impl Migration for RouteStoreForward {
+    fn id(&self) -> &'static str {
+        "009_route_store_forward"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route store-and-forward policy column and queue indexes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_store_forward_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        ctx.mongo.ensure_forward_queue_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "store_forward column and forward_queue indexes ready".to_string(),
+        ))
+    }
+}
+
+struct LacisOathProviders;
+
+#[async_trait]
+impl Migration for LacisOathProviders {
+    fn id(&self) -> &'static str {
+        "010_lacisoath_providers"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the table of additional LacisOath identity providers"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_lacisoath_providers_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "lacisoath_providers table ready".to_string(),
+        ))
+    }
+}
+
+struct RouteExpectContinue;
+
+#[async_trait]
+impl Migration for RouteExpectContinue {
+    fn id(&self) -> &'static str {
+        "011_route_expect_continue"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route Expect: 100-continue handling column"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_expect_continue_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "expect_continue column ready".to_string(),
+        ))
+    }
+}
+
+struct IpDailyStatsIndexes;
+
+#[async_trait]
+impl Migration for IpDailyStatsIndexes {
+    fn id(&self) -> &'static str {
+        "012_ip_daily_stats"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the per-IP daily rollup indexes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_ip_daily_stats_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "ip_daily_stats indexes ready".to_string(),
+        ))
+    }
+}
+
+struct DependenciesHealthIndexes;
+
+#[async_trait]
+impl Migration for DependenciesHealthIndexes {
+    fn id(&self) -> &'static str {
+        "013_dependencies_health"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the dependency probe result indexes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_dependencies_health_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "dependencies_health indexes ready".to_string(),
+        ))
+    }
+}
+
+struct FacilityReportIndexes;
+
+#[async_trait]
+impl Migration for FacilityReportIndexes {
+    fn id(&self) -> &'static str {
+        "014_facility_reports"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the facility report indexes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_facility_report_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "facility_reports indexes ready".to_string(),
+        ))
+    }
+}
+
+struct StatusPage;
+
+#[async_trait]
+impl Migration for StatusPage {
+    fn id(&self) -> &'static str {
+        "015_status_page"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route status page columns and route uptime indexes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_status_page_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        ctx.mongo.ensure_route_uptime_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "status page columns and route_uptime_daily indexes ready".to_string(),
+        ))
+    }
+}
+
+struct RouteTransform;
+
+#[async_trait]
+impl Migration for RouteTransform {
+    fn id(&self) -> &'static str {
+        "016_route_transform"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route transformation script column"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_transform_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied("transform column ready".to_string()))
+    }
+}
+
+struct DnsOverrides;
+
+#[async_trait]
+impl Migration for DnsOverrides {
+    fn id(&self) -> &'static str {
+        "017_dns_overrides"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the table of local DNS overrides"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_dns_overrides_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "dns_overrides table ready".to_string(),
+        ))
+    }
+}
+
+struct AraneaRegistrationTokens;
+
+#[async_trait]
+impl Migration for AraneaRegistrationTokens {
+    fn id(&self) -> &'static str {
+        "018_aranea_registration_tokens"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the table of device registration tokens"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_aranea_registration_tokens_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "aranea_registration_tokens table ready".to_string(),
+        ))
+    }
+}
+
+struct BlockedIpContext;
+
+#[async_trait]
+impl Migration for BlockedIpContext {
+    fn id(&self) -> &'static str {
+        "019_blocked_ip_context"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the quick-block context reference to blocked IPs"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_blocked_ip_context_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "context_ref column ready".to_string(),
+        ))
+    }
+}
+
+struct RouteCanary;
+
+#[async_trait]
+impl Migration for RouteCanary {
+    fn id(&self) -> &'static str {
+        "020_route_canary"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add canary target, percentage and stickiness to proxy routes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_canary_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied("canary columns ready".to_string()))
+    }
+}
+
+struct TopologyShares;
+
+#[async_trait]
+impl Migration for TopologyShares {
+    fn id(&self) -> &'static str {
+        "021_topology_shares"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the topology_shares table for read-only topology share tokens"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_topology_shares_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "topology_shares table ready".to_string(),
+        ))
+    }
+}
+
+struct RouteVersions;
+
+#[async_trait]
+impl Migration for RouteVersions {
+    fn id(&self) -> &'static str {
+        "022_route_versions"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create route_versions and record each route's current configuration as its baseline"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_versions_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        let routes = ctx
+            .mysql
+            .list_routes_including_deleted()
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut recorded = 0;
+        for route in &routes {
+            if ctx
+                .mysql
+                .record_route_baseline(route.id, &route_versions::snapshot(route), SYSTEM_ACTOR)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                recorded += 1;
+            }
+        }
+        Ok(MigrationRun::Applied(format!(
+            "route_versions ready; baseline recorded for {} of {} routes",
+            recorded,
+            routes.len()
+        )))
+    }
+}
+
+struct RouteLogFields;
+
+#[async_trait]
+impl Migration for RouteLogFields {
+    fn id(&self) -> &'static str {
+        "023_route_log_fields"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route custom log field column and the access_logs route index"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_log_fields_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        ctx.mongo.ensure_access_log_route_index().await?;
+        Ok(MigrationRun::Applied(
+            "log_fields column and access_logs route index ready".to_string(),
+        ))
+    }
+}
+
+struct HostnameUsageIndexes;
+
+#[async_trait]
+impl Migration for HostnameUsageIndexes {
+    fn id(&self) -> &'static str {
+        "024_hostname_usage"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the monthly per-hostname usage rollup index"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_hostname_usage_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "hostname_usage_monthly index ready".to_string(),
+        ))
+    }
+}
+
+struct RouteGrpc;
+
+#[async_trait]
+impl Migration for RouteGrpc {
+    fn id(&self) -> &'static str {
+        "025_route_grpc"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the gRPC passthrough flag and health check service to proxy routes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_grpc_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied("grpc columns ready".to_string()))
+    }
+}
+
+struct DdnsDnsChecks;
+
+#[async_trait]
+impl Migration for DdnsDnsChecks {
+    fn id(&self) -> &'static str {
+        "026_ddns_dns_checks"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the DDNS DNS dependency check index"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_ddns_dns_check_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "ddns_dns_checks index ready".to_string(),
+        ))
+    }
+}
+
+struct ApiTrace;
+
+#[async_trait]
+impl Migration for ApiTrace {
+    fn id(&self) -> &'static str {
+        "027_api_trace"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the capped api_trace collection for the API request recorder"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_api_trace_collection().await?;
+        Ok(MigrationRun::Applied(
+            "api_trace collection ready".to_string(),
+        ))
+    }
+}
+
+struct RouteHealthCheck;
+
+#[async_trait]
+impl Migration for RouteHealthCheck {
+    fn id(&self) -> &'static str {
+        "028_route_health_check"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route health check path and interval to proxy routes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_health_check_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "health check columns ready".to_string(),
+        ))
+    }
+}
+
+struct RouteUpstreamAuth;
+
+#[async_trait]
+impl Migration for RouteUpstreamAuth {
+    fn id(&self) -> &'static str {
+        "029_route_upstream_auth"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the upstream Basic auth credentials to proxy routes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_upstream_auth_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "upstream auth columns ready".to_string(),
+        ))
+    }
+}
+
+struct RouteCache;
+
+#[async_trait]
+impl Migration for RouteCache {
+    fn id(&self) -> &'static str {
+        "030_route_cache"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the response cache TTL to proxy routes"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_cache_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "cache_ttl_sec column ready".to_string(),
+        ))
+    }
+}
+
+struct RateLimitRules;
+
+#[async_trait]
+impl Migration for RateLimitRules {
+    fn id(&self) -> &'static str {
+        "031_rate_limit_rules"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the table of per-client-IP rate limit rules"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_rate_limit_rules_table()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "rate_limit_rules table ready".to_string(),
+        ))
+    }
+}
+
+struct DdnsIpv6;
+
+#[async_trait]
+impl Migration for DdnsIpv6 {
+    fn id(&self) -> &'static str {
+        "032_ddns_ipv6"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the IPv6 (AAAA) update flag and last pushed IPv6 to DDNS configs"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_ddns_ipv6_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied("ddns ipv6 columns ready".to_string()))
+    }
+}
+
+struct DdnsRecordIds;
+
+#[async_trait]
+impl Migration for DdnsRecordIds {
+    fn id(&self) -> &'static str {
+        "033_ddns_record_ids"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the cached Cloudflare record ids to DDNS configs"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_ddns_record_id_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "ddns record id columns ready".to_string(),
+        ))
+    }
+}
+
+/// Retention settings of the log pruner, editable through /api/settings
+struct LogRetentionSettings;
+
+#[async_trait]
+impl Migration for LogRetentionSettings {
+    fn id(&self) -> &'static str {
+        "034_log_retention_settings"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the access log, security event and health check retention settings"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        let days = crate::log_retention::DEFAULT_RETENTION_DAYS.to_string();
+        let settings = [
+            (
+                "access_log_retention_days",
+                "Days to retain access logs (0 = forever)",
+            ),
+            (
+                "security_event_retention_days",
+                "Days to retain security events (0 = forever)",
+            ),
+            (
+                "health_check_retention_days",
+                "Days to retain health check results (0 = forever)",
+            ),
+        ];
+        let mut added = Vec::new();
+        for (key, description) in settings {
+            if ctx
+                .mysql
+                .insert_setting_if_missing(key, &days, description)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                added.push(key);
+            }
+        }
+        match added.is_empty() {
+            true => Ok(MigrationRun::Skipped(
+                "retention settings present".to_string(),
+            )),
+            false => Ok(MigrationRun::Applied(format!("added {}", added.join(", ")))),
+        }
+    }
+}
+
+fn repaired(count: u32) -> Result<MigrationRun, String> {
+    Ok(match count {
+        0 => MigrationRun::Skipped("no parents to repair".to_string()),
+        n => MigrationRun::Applied(format!("repaired {} AP parents", n)),
+    })
+}
+
+// ============================================================================
+// Runner
+// ============================================================================
+
+/// Result of one executed migration
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationReport {
+    pub migration_id: String,
+    /// "applied" | "skipped" | "failed"
+    pub outcome: String,
+    pub detail: String,
+    pub forced: bool,
+    pub duration_ms: i64,
+}
+
+/// Registry entry with its last recorded run
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub migration_id: String,
+    pub description: String,
+    pub critical: bool,
+    pub every_start: bool,
+    /// Never recorded, or the last run failed
+    pub pending: bool,
+    pub last_run: Option<SchemaMigrationDoc>,
+}
+
+/// Never recorded, or the last run failed
+fn is_pending(record: Option<&SchemaMigrationDoc>) -> bool {
+    record.is_none_or(|r| r.outcome == "failed")
+}
+
+/// Runs at startup
+fn is_due(migration: &dyn Migration, record: Option<&SchemaMigrationDoc>) -> bool {
+    migration.every_start() || is_pending(record)
+}
+
+/// Executes and records migrations; shared by startup and the admin API
+pub struct MigrationRunner {
+    ctx: MigrationContext,
+    migrations: Vec<Box<dyn Migration>>,
+    /// One run at a time
+    running: Mutex<()>,
+}
+
+impl MigrationRunner {
+    pub fn new(ctx: MigrationContext) -> Self {
+        Self::with_migrations(ctx, registry())
+    }
+
+    pub fn with_migrations(ctx: MigrationContext, migrations: Vec<Box<dyn Migration>>) -> Self {
+        Self {
+            ctx,
+            migrations,
+            running: Mutex::new(()),
+        }
+    }
+
+    /// Run every due migration in order. Err means startup must stop.
+    pub async fn run_startup(
+        &self,
+        continue_on_error: bool,
+    ) -> Result<Vec<MigrationReport>, String> {
+        let _guard = self.running.lock().await;
+        let records = self.ctx.mongo.list_schema_migrations().await?;
+
+        let mut reports = Vec::new();
+        for migration in &self.migrations {
+            let record = records.iter().find(|r| r.migration_id == migration.id());
+            if !is_due(migration.as_ref(), record) {
+                continue;
+            }
+            let report = self.execute(migration.as_ref(), false).await;
+            if report.outcome == "failed" {
+                if migration.critical() || !continue_on_error {
+                    return Err(format!(
+                        "Migration {} failed: {}",
+                        report.migration_id, report.detail
+                    ));
+                }
+                tracing::warn!(
+                    "Migration {} failed (non-critical, continuing): {}",
+                    report.migration_id,
+                    report.detail
+                );
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    /// Run one migration now; None if the id is unknown
+    pub async fn run_one(&self, migration_id: &str, force: bool) -> Option<MigrationReport> {
+        let migration = self.migrations.iter().find(|m| m.id() == migration_id)?;
+        let _guard = self.running.lock().await;
+        Some(self.execute(migration.as_ref(), force).await)
+    }
+
+    /// Every registered migration with its last recorded run
+    pub async fn status(&self) -> Result<Vec<MigrationStatus>, String> {
+        let records = self.ctx.mongo.list_schema_migrations().await?;
+        Ok(self
+            .migrations
+            .iter()
+            .map(|m| {
+                let record = records.iter().find(|r| r.migration_id == m.id());
+                MigrationStatus {
+                    migration_id: m.id().to_string(),
+                    description: m.description().to_string(),
+                    critical: m.critical(),
+                    every_start: m.every_start(),
+                    pending: is_pending(record),
+                    last_run: record.cloned(),
+                }
+            })
+            .collect())
+    }
+
+    async fn execute(&self, migration: &dyn Migration, force: bool) -> MigrationReport {
+        let start = Instant::now();
+        let (outcome, detail) = match migration.run(&self.ctx, force).await {
+            Ok(MigrationRun::Applied(detail)) => {
+                tracing::info!("Migration {} applied: {}", migration.id(), detail);
+                ("applied", detail)
+            }
+            Ok(MigrationRun::Skipped(detail)) => {
+                tracing::debug!("Migration {} skipped: {}", migration.id(), detail);
+                ("skipped", detail)
+            }
+            Err(e) => ("failed", e),
+        };
+        let report = MigrationReport {
+            migration_id: migration.id().to_string(),
+            outcome: outcome.to_string(),
+            detail,
+            forced: force,
+            duration_ms: start.elapsed().as_millis() as i64,
+        };
+
+        let now = Utc::now().to_rfc3339();
+        let record = SchemaMigrationDoc {
+            migration_id: report.migration_id.clone(),
+            description: migration.description().to_string(),
+            outcome: report.outcome.clone(),
+            detail: report.detail.clone(),
+            forced: force,
+            run_count: 1,
+            duration_ms: report.duration_ms,
+            first_run_at: now.clone(),
+            last_run_at: now,
+        };
+        // Unrecorded runs repeat on the next start, which idempotent steps allow
+        if let Err(e) = self.ctx.mongo.record_schema_migration(&record).await {
+            tracing::warn!("Failed to record migration {}: {}", migration.id(), e);
+        }
+        report
+    }
+}
+
+struct RouteHeaderRewrite;
+
+#[async_trait]
+impl Migration for RouteHeaderRewrite {
+    fn id(&self) -> &'static str {
+        "035_route_header_rewrite"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route header rewrite rules column"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_header_rewrite_column()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "header_rewrite column ready".to_string(),
+        ))
+    }
+}
+
+struct AcmeCertificates;
+
+#[async_trait]
+impl Migration for AcmeCertificates {
+    fn id(&self) -> &'static str {
+        "036_acme_certificates"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the ACME account key and certificate tables"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_acme_tables()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "acme_accounts and acme_certificates tables ready".to_string(),
+        ))
+    }
+}
+
+struct RouteUpstreamTuning;
+
+#[async_trait]
+impl Migration for RouteUpstreamTuning {
+    fn id(&self) -> &'static str {
+        "037_route_upstream_tuning"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route upstream connect timeout and idle pool columns"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_upstream_tuning_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "connect_timeout_ms and max_idle_per_host columns ready".to_string(),
+        ))
+    }
+}
+
+struct RouteFailover;
+
+#[async_trait]
+impl Migration for RouteFailover {
+    fn id(&self) -> &'static str {
+        "038_route_failover"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route fallback target and retry columns, and the fallback health index"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_failover_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        ctx.mongo.ensure_route_target_health_indexes().await?;
+        Ok(MigrationRun::Applied(
+            "failover columns and route_target_health index ready".to_string(),
+        ))
+    }
+}
+
+struct RouteMaintenanceMode;
+
+#[async_trait]
+impl Migration for RouteMaintenanceMode {
+    fn id(&self) -> &'static str {
+        "039_route_maintenance_mode"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route maintenance mode and message columns"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_maintenance_columns()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(MigrationRun::Applied(
+            "maintenance_mode and maintenance_message columns ready".to_string(),
+        ))
+    }
+}
+
+struct RouteBodyLimit;
+
+#[async_trait]
+impl Migration for RouteBodyLimit {
+    fn id(&self) -> &'static str {
+        "040_route_body_limit"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the per-route request body limit column; an untouched global limit \
+         setting defers to [server] max_body_mb"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mysql
+            .ensure_route_body_limit_column()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // The seeded value equals the config default; clearing it lets
+        // [server] max_body_mb take effect
+        let seeded = ctx
+            .mysql
+            .get_setting("proxy_max_request_body_mb")
+            .await
+            .map_err(|e| e.to_string())?;
+        if seeded.as_deref() == Some("100") {
+            ctx.mysql
+                .set_setting("proxy_max_request_body_mb", None)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(MigrationRun::Applied(
+            "max_body_bytes column ready".to_string(),
+        ))
+    }
+}
+
+struct AccessLogRouteErrors;
+
+#[async_trait]
+impl Migration for AccessLogRouteErrors {
+    fn id(&self) -> &'static str {
+        "041_access_log_route_errors"
+    }
+
+    fn description(&self) -> &'static str {
+        "Create the access_logs route / status index of health failure context"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        ctx.mongo.ensure_access_log_route_error_index().await?;
+        Ok(MigrationRun::Applied(
+            "access_logs route error index ready".to_string(),
+        ))
+    }
+}
+
+/// Network policy inputs (crate::network_policy), editable through /api/settings
+struct NetworkPolicySettings;
+
+#[async_trait]
+impl Migration for NetworkPolicySettings {
+    fn id(&self) -> &'static str {
+        "042_network_policy_settings"
+    }
+
+    fn description(&self) -> &'static str {
+        "Add the extra LAN, trusted proxy and admin allowlist network settings"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        use crate::network_policy::{
+            SETTING_ADMIN_ALLOWLIST, SETTING_LAN_EXTRA, SETTING_TRUSTED_PROXIES,
+        };
+        let settings = [
+            (
+                SETTING_LAN_EXTRA,
+                "Networks treated as LAN besides the private ranges (IPs/CIDRs, comma separated)",
+            ),
+            (
+                SETTING_TRUSTED_PROXIES,
+                "Peers whose X-Forwarded-For/X-Real-IP is trusted (IPs/CIDRs; loopback is always trusted)",
+            ),
+            (
+                SETTING_ADMIN_ALLOWLIST,
+                "Public networks allowed to reach the management UI without internet access (IPs/CIDRs)",
+            ),
+        ];
+        let mut added = Vec::new();
+        for (key, description) in settings {
+            if ctx
+                .mysql
+                .insert_setting_if_missing(key, "", description)
+                .await
+                .map_err(|e| e.to_string())?
+            {
+                added.push(key);
+            }
+        }
+        match added.is_empty() {
+            true => Ok(MigrationRun::Skipped(
+                "network policy settings present".to_string(),
+            )),
+            false => Ok(MigrationRun::Applied(format!("added {}", added.join(", ")))),
+        }
+    }
+}
+
+/// The recent-errors lookup sorts on timestamp after matching the route, so
+/// its index goes route / time / status (equality / sort / range)
+struct AccessLogRouteErrorOrder;
+
+#[async_trait]
+impl Migration for AccessLogRouteErrorOrder {
+    fn id(&self) -> &'static str {
+        "043_access_log_route_error_order"
+    }
+
+    fn description(&self) -> &'static str {
+        "Reorder the access_logs route error index as route / time / status"
+    }
+
+    async fn run(&self, ctx: &MigrationContext, _force: bool) -> Result<MigrationRun, String> {
+        let dropped = ctx.mongo.reorder_access_log_route_error_index().await?;
+        Ok(MigrationRun::Applied(match dropped {
+            true => "access_logs route error index reordered".to_string(),
+            false => "access_logs route error index ready".to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(outcome: &str) -> SchemaMigrationDoc {
+        SchemaMigrationDoc {
+            migration_id: "x".to_string(),
+            description: String::new(),
+            outcome: outcome.to_string(),
+            detail: String::new(),
+            forced: false,
+            run_count: 1,
+            duration_ms: 0,
+            first_run_at: String::new(),
+            last_run_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn due_at_startup_until_recorded_without_failure() {
+        let once = UserObjectDetailInit;
+        assert!(is_due(&once, None));
+        assert!(is_due(&once, Some(&record("failed"))));
+        assert!(!is_due(&once, Some(&record("applied"))));
+        // A skip is recorded too; only a forced re-run repeats it
+        assert!(!is_due(&once, Some(&record("skipped"))));
+
+        let repair = UserObjectDetailApParents;
+        assert!(is_due(&repair, Some(&record("applied"))));
+        assert!(!is_pending(Some(&record("applied"))));
+    }
+
+    #[test]
+    fn registry_ids_are_unique_and_ordered() {
+        let ids: Vec<&str> = registry().iter().map(|m| m.id()).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(ids, sorted);
+    }
+}
@@ -1,8 +1,16 @@
 //! Data models for LacisProxyGateway2
 
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::proxy::expect::ExpectContinue;
+use crate::proxy::failover::RetryOn;
+use crate::proxy::header_rewrite::RouteHeaderRewrite;
+use crate::proxy::rate_limit::RateLimitAction;
+use crate::topology_share::{ShareDetail, ShareView};
+
 // ============================================================================
 // Proxy Route Models
 // ============================================================================
@@ -19,19 +27,216 @@ pub struct ProxyRoute {
     pub preserve_host: bool,
     pub timeout_ms: i32,
     pub websocket_support: bool,
+    /// Only reachable from networks allowed to use the admin UI (LAN, or
+    /// anywhere when internet access is enabled); others get 404
+    pub admin_network_only: bool,
+    /// Security header override as JSON (`RouteSecurityHeaders`), NULL = global policy
+    pub security_headers: Option<String>,
+    /// Allowed HTTP methods as a JSON list, NULL = all methods
+    pub allowed_methods: Option<String>,
+    /// Store-and-forward policy as JSON (`RouteStoreForward`), NULL = off
+    pub store_forward: Option<String>,
+    /// `Expect: 100-continue` handling (`ExpectContinue`), NULL = immediate
+    pub expect_continue: Option<String>,
+    /// Transformation script policy as JSON (`RouteTransform`), NULL = none
+    #[serde(default)]
+    pub transform: Option<String>,
+    /// Custom access log field rules as JSON (`RouteLogFields`), NULL = none
+    #[serde(default)]
+    pub log_fields: Option<String>,
+    /// Responsible person for this route
+    pub owner_name: Option<String>,
+    /// Owner contact: Discord webhook URL (notified directly) or free-form handle
+    pub owner_contact: Option<String>,
+    /// Team tag
+    pub team: Option<String>,
+    /// Listed on the public status page (GET /status)
+    #[serde(default)]
+    pub show_on_status_page: bool,
+    /// Public display name on the status page (the path and target never are)
+    pub status_page_name: Option<String>,
+    /// Canary target URL (`canary_percent` of requests go here instead of `target`)
+    pub canary_target: Option<String>,
+    /// Share of requests sent to the canary, 0-100; NULL or 0 = off
+    pub canary_percent: Option<i32>,
+    /// Keep a client IP on the same side for the whole rollout
+    #[serde(default)]
+    pub canary_sticky: bool,
+    /// gRPC passthrough: HTTP/2 to the target (prior knowledge for http://),
+    /// streamed bodies and trailers, no transformations
+    #[serde(default)]
+    pub grpc: bool,
+    /// Service health-checked over grpc.health.v1 ("" = the whole server);
+    /// NULL = HTTP probe
+    #[serde(default)]
+    pub grpc_health_service: Option<String>,
+    /// Path probed by HTTP health checks, appended to the target (NULL = "/")
+    #[serde(default)]
+    pub health_check_path: Option<String>,
+    /// Seconds between health checks of this route (NULL = global
+    /// `health_check_interval_sec`)
+    #[serde(default)]
+    pub health_check_interval_sec: Option<i32>,
+    /// Basic auth user sent to the target instead of the client's
+    /// Authorization (never serialized; see `upstream_auth_configured`)
+    #[serde(skip)]
+    pub upstream_auth_user: Option<String>,
+    #[serde(skip)]
+    pub upstream_auth_password: Option<String>,
+    /// Seconds successful GET responses stay cached (0 = no caching)
+    #[serde(default)]
+    pub cache_ttl_sec: i32,
+    /// Request/response header rewrite rules as JSON (`RouteHeaderRewrite`),
+    /// NULL = none
+    #[serde(default)]
+    pub header_rewrite: Option<String>,
+    /// Upstream connect timeout in ms (NULL = the shared client's 10s);
+    /// `timeout_ms` still bounds the whole request
+    #[serde(default)]
+    pub connect_timeout_ms: Option<i32>,
+    /// Idle upstream connections kept per host (NULL = the shared client's
+    /// 10); either column gives the route a pool of its own
+    #[serde(default)]
+    pub max_idle_per_host: Option<i32>,
+    /// Alternative base URLs as a JSON list, tried in order when the target
+    /// fails (`proxy::failover`), NULL = none
+    #[serde(default)]
+    pub fallback_targets: Option<String>,
+    /// Further attempts per target before moving to the next one
+    #[serde(default)]
+    pub retry_count: i32,
+    /// What triggers a retry (`RetryOn`), NULL = connection errors only
+    #[serde(default)]
+    pub retry_on: Option<String>,
+    /// Answer 503 with the maintenance page instead of proxying; health
+    /// checks of the route pause meanwhile
+    #[serde(default)]
+    pub maintenance_mode: bool,
+    /// Text of the maintenance page (NULL = the default text)
+    #[serde(default)]
+    pub maintenance_message: Option<String>,
+    /// Largest accepted request body in bytes, 413 above it (NULL = the
+    /// global `proxy_max_request_body_mb`)
+    #[serde(default)]
+    pub max_body_bytes: Option<i64>,
+    /// Soft-delete timestamp (None = live route)
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[cfg(test)]
+impl ProxyRoute {
+    /// Active route /app -> http://127.0.0.1:9000 with every optional
+    /// setting off; tests override fields with struct-update syntax
+    pub fn test_default() -> Self {
+        let created_at = "2026-01-01T00:00:00Z".parse().unwrap();
+        Self {
+            id: 1,
+            path: "/app".to_string(),
+            target: "http://127.0.0.1:9000".to_string(),
+            ddns_config_id: None,
+            priority: 100,
+            active: true,
+            strip_prefix: true,
+            preserve_host: false,
+            timeout_ms: 30000,
+            websocket_support: false,
+            admin_network_only: false,
+            security_headers: None,
+            allowed_methods: None,
+            store_forward: None,
+            expect_continue: None,
+            transform: None,
+            log_fields: None,
+            owner_name: None,
+            owner_contact: None,
+            team: None,
+            show_on_status_page: false,
+            status_page_name: None,
+            canary_target: None,
+            canary_percent: None,
+            canary_sticky: false,
+            grpc: false,
+            grpc_health_service: None,
+            health_check_path: None,
+            health_check_interval_sec: None,
+            upstream_auth_user: None,
+            upstream_auth_password: None,
+            cache_ttl_sec: 0,
+            header_rewrite: None,
+            connect_timeout_ms: None,
+            max_idle_per_host: None,
+            fallback_targets: None,
+            retry_count: 0,
+            retry_on: None,
+            maintenance_mode: false,
+            maintenance_message: None,
+            max_body_bytes: None,
+            deleted_at: None,
+            created_at,
+            updated_at: created_at,
+        }
+    }
+}
+
+impl ProxyRoute {
+    /// "name (contact) [team]" for notifications; None when no owner is recorded
+    pub fn owner_label(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(name) = self.owner_name.as_deref().filter(|s| !s.is_empty()) {
+            parts.push(name.to_string());
+        }
+        if let Some(contact) = self.owner_contact.as_deref().filter(|s| !s.is_empty()) {
+            parts.push(format!("({})", contact));
+        }
+        if let Some(team) = self.team.as_deref().filter(|s| !s.is_empty()) {
+            parts.push(format!("[{}]", team));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+
+    /// Path probed by HTTP health checks ("/" when unset or empty)
+    pub fn health_check_path(&self) -> &str {
+        self.health_check_path
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .unwrap_or("/")
+    }
+
+    /// Seconds between health checks, `global` when the route sets none
+    pub fn health_check_interval(&self, global: i32) -> i32 {
+        self.health_check_interval_sec
+            .filter(|s| *s > 0)
+            .unwrap_or(global)
+    }
+
+    /// Whether `user` is this route's recorded owner (by name or contact)
+    pub fn is_owned_by(&self, user: &AuthUser) -> bool {
+        let ids = [Some(user.sub.as_str()), user.lacis_id.as_deref()];
+        [self.owner_name.as_deref(), self.owner_contact.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .any(|owner| ids.contains(&Some(owner)))
+    }
+}
+
 /// Extended route with DDNS hostname for routing decisions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ProxyRouteWithDdns {
     #[serde(flatten)]
+    #[sqlx(flatten)]
     pub route: ProxyRoute,
     pub ddns_hostname: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateRouteRequest {
     pub path: String,
     pub target: String,
@@ -48,9 +253,77 @@ pub struct CreateRouteRequest {
     pub timeout_ms: i32,
     #[serde(default)]
     pub websocket_support: bool,
+    #[serde(default)]
+    pub admin_network_only: bool,
+    #[serde(default)]
+    pub security_headers: Option<RouteSecurityHeaders>,
+    /// None = all methods
+    #[serde(default)]
+    pub allowed_methods: Option<Vec<String>>,
+    /// None = immediate
+    #[serde(default)]
+    pub expect_continue: Option<ExpectContinue>,
+    #[serde(default)]
+    pub owner_name: Option<String>,
+    #[serde(default)]
+    pub owner_contact: Option<String>,
+    #[serde(default)]
+    pub team: Option<String>,
+    #[serde(default)]
+    pub canary_target: Option<String>,
+    /// None or 0 = no canary
+    #[serde(default)]
+    pub canary_percent: Option<i32>,
+    #[serde(default)]
+    pub canary_sticky: bool,
+    #[serde(default)]
+    pub grpc: bool,
+    /// None = HTTP health probe, "" = grpc.health.v1 for the whole server
+    #[serde(default)]
+    pub grpc_health_service: Option<String>,
+    /// None or "" = "/"
+    #[serde(default)]
+    pub health_check_path: Option<String>,
+    /// None = global health check interval
+    #[serde(default)]
+    pub health_check_interval_sec: Option<i32>,
+    /// Basic auth sent to the target (replaces the client's Authorization);
+    /// the password may be omitted. In a route sync an omitted user keeps the
+    /// stored credentials and an empty one clears them
+    #[serde(default)]
+    pub upstream_auth_user: Option<String>,
+    #[serde(default)]
+    pub upstream_auth_password: Option<String>,
+    /// 0 = no response caching
+    #[serde(default)]
+    pub cache_ttl_sec: i32,
+    #[serde(default)]
+    pub header_rewrite: Option<RouteHeaderRewrite>,
+    /// None or 0 = the shared client's connect timeout
+    #[serde(default)]
+    pub connect_timeout_ms: Option<i32>,
+    /// None = the shared client's pool; 0 keeps no idle connections
+    #[serde(default)]
+    pub max_idle_per_host: Option<i32>,
+    /// None = no fallbacks
+    #[serde(default)]
+    pub fallback_targets: Option<Vec<String>>,
+    #[serde(default)]
+    pub retry_count: i32,
+    /// None = connection errors only
+    #[serde(default)]
+    pub retry_on: Option<RetryOn>,
+    #[serde(default)]
+    pub maintenance_mode: bool,
+    /// None or empty = the default text
+    #[serde(default)]
+    pub maintenance_message: Option<String>,
+    /// None = the global request body limit
+    #[serde(default)]
+    pub max_body_bytes: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpdateRouteRequest {
     pub path: Option<String>,
     pub target: Option<String>,
@@ -61,6 +334,193 @@ pub struct UpdateRouteRequest {
     pub preserve_host: Option<bool>,
     pub timeout_ms: Option<i32>,
     pub websocket_support: Option<bool>,
+    pub admin_network_only: Option<bool>,
+    /// Security header override; an empty object clears it
+    pub security_headers: Option<RouteSecurityHeaders>,
+    /// Allowed methods; `null` lifts the restriction
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allowed_methods: Option<Option<Vec<String>>>,
+    pub expect_continue: Option<ExpectContinue>,
+    /// Empty string clears the owner field
+    pub owner_name: Option<String>,
+    pub owner_contact: Option<String>,
+    pub team: Option<String>,
+    /// Canary target; `null` or an empty string clears it
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub canary_target: Option<Option<String>>,
+    /// Canary share 0-100; `null` or 0 disables the canary
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub canary_percent: Option<Option<i32>>,
+    pub canary_sticky: Option<bool>,
+    pub grpc: Option<bool>,
+    /// grpc.health.v1 service ("" = whole server); `null` returns to HTTP probes
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub grpc_health_service: Option<Option<String>>,
+    /// HTTP health probe path; `null` or an empty string probes "/"
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health_check_path: Option<Option<String>>,
+    /// Health check interval in seconds; `null` returns to the global interval
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub health_check_interval_sec: Option<Option<i32>>,
+    /// Upstream Basic auth user; `null` or an empty string removes the
+    /// credentials
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub upstream_auth_user: Option<Option<String>>,
+    /// Upstream Basic auth password; omitted keeps the stored one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_auth_password: Option<String>,
+    /// 0 disables response caching
+    pub cache_ttl_sec: Option<i32>,
+    /// Header rewrite rules; an empty object clears them
+    pub header_rewrite: Option<RouteHeaderRewrite>,
+    /// Upstream connect timeout in ms; `null` or 0 returns to the shared
+    /// client's
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub connect_timeout_ms: Option<Option<i32>>,
+    /// Idle upstream connections per host; `null` returns to the shared pool
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_idle_per_host: Option<Option<i32>>,
+    /// Fallback targets; `null` or an empty list removes them
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fallback_targets: Option<Option<Vec<String>>>,
+    pub retry_count: Option<i32>,
+    pub retry_on: Option<RetryOn>,
+    pub maintenance_mode: Option<bool>,
+    /// Maintenance page text; `null` or an empty string returns to the
+    /// default text
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub maintenance_message: Option<Option<String>>,
+    /// Request body limit in bytes; `null` returns to the global limit
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_body_bytes: Option<Option<i64>>,
+}
+
+/// Route mutation waiting for approval (`route_approval_required`)
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutePendingChange {
+    pub id: i32,
+    /// None for proposed creations
+    pub route_id: Option<i32>,
+    /// "create" or "update"
+    pub action: String,
+    /// Proposed CreateRouteRequest / UpdateRouteRequest
+    pub payload: serde_json::Value,
+    /// "pending", "approved" or "rejected"
+    pub status: String,
+    pub proposed_by: String,
+    pub proposed_by_permission: i32,
+    pub reviewed_by: Option<String>,
+    pub review_comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// Stored route configuration (route_versions)
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteVersion {
+    pub id: i64,
+    pub route_id: i32,
+    /// 1-based, per route
+    pub version: i32,
+    /// Whole `ProxyRoute` as written
+    pub config: serde_json::Value,
+    /// User, or "system" for writes LPG made on its own
+    pub actor: String,
+    /// "create", "update", "rollback", ...
+    pub reason: String,
+    /// Version a rollback restored
+    pub restored_version: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReviewPendingChangeRequest {
+    pub comment: Option<String>,
+}
+
+// Security headers (merged and applied in proxy::security_headers)
+
+/// One header in a policy or override
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeaderRule {
+    /// Header value (an override may omit it to keep the global value)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Replace the header even when the upstream already set it
+    #[serde(default)]
+    pub force: bool,
+    /// Never inject this header
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Global policy (setting `security_headers_default`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityHeadersPolicy {
+    /// Applies to routes without an explicit `enabled` override
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub headers: BTreeMap<String, HeaderRule>,
+}
+
+/// Per-route override (`proxy_routes.security_headers`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteSecurityHeaders {
+    /// None = follow the global `enabled` flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Per-header overrides merged over the global rules
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, HeaderRule>,
 }
 
 fn default_priority() -> i32 {
@@ -133,6 +593,43 @@ impl std::str::FromStr for DdnsStatus {
     }
 }
 
+/// Where a DDNS config gets its current IP from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DdnsIpSource {
+    /// Public-IP lookup services (ifconfig.me etc.)
+    #[serde(rename = "poll")]
+    #[default]
+    Poll,
+    /// WAN IP of the linked Omada gateway or OpenWrt router
+    #[serde(rename = "router")]
+    Router,
+    /// Pushed via POST /api/ddns/:id/report-ip
+    #[serde(rename = "webhook")]
+    Webhook,
+}
+
+impl std::fmt::Display for DdnsIpSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DdnsIpSource::Poll => write!(f, "poll"),
+            DdnsIpSource::Router => write!(f, "router"),
+            DdnsIpSource::Webhook => write!(f, "webhook"),
+        }
+    }
+}
+
+impl std::str::FromStr for DdnsIpSource {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "poll" => Ok(DdnsIpSource::Poll),
+            "router" => Ok(DdnsIpSource::Router),
+            "webhook" => Ok(DdnsIpSource::Webhook),
+            _ => Err(format!("Unknown IP source: {}", s)),
+        }
+    }
+}
+
 /// Raw DDNS config from database (uses String for enum fields)
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct DdnsConfigRow {
@@ -145,11 +642,26 @@ pub struct DdnsConfigRow {
     pub zone_id: Option<String>,
     pub update_interval_sec: i32,
     pub last_ip: Option<String>,
+    pub update_ipv6: bool,
+    pub last_ipv6: Option<String>,
     pub last_update: Option<DateTime<Utc>>,
     pub last_error: Option<String>,
     pub status: String,
     pub omada_controller_id: Option<String>,
     pub omada_site_id: Option<String>,
+    pub ip_source: String,
+    pub openwrt_router_id: Option<String>,
+    pub report_token: Option<String>,
+    pub reported_ip: Option<String>,
+    pub reported_at: Option<DateTime<Utc>>,
+    pub proxied: Option<bool>,
+    pub ttl: Option<i32>,
+    pub record_id: Option<String>,
+    pub record_id_v6: Option<String>,
+    pub secondary_config_id: Option<i32>,
+    pub failover_threshold: i32,
+    pub consecutive_failures: i32,
+    pub failover_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -165,15 +677,67 @@ pub struct DdnsConfig {
     pub zone_id: Option<String>,
     pub update_interval_sec: i32,
     pub last_ip: Option<String>,
+    /// Keep the AAAA record (or the provider's IPv6 field) current as well
+    #[serde(default)]
+    pub update_ipv6: bool,
+    /// IPv6 address last pushed to the provider
+    #[serde(default)]
+    pub last_ipv6: Option<String>,
     pub last_update: Option<DateTime<Utc>>,
     pub last_error: Option<String>,
     pub status: DdnsStatus,
     pub omada_controller_id: Option<String>,
     pub omada_site_id: Option<String>,
+    pub ip_source: DdnsIpSource,
+    /// Router source: OpenWrt router to read wan_ip from (else the Omada link)
+    pub openwrt_router_id: Option<String>,
+    /// Webhook source: bearer token for report-ip
+    pub report_token: Option<String>,
+    /// Webhook source: last address pushed by the reporter
+    pub reported_ip: Option<String>,
+    pub reported_at: Option<DateTime<Utc>>,
+    /// Cloudflare: orange-cloud flag (None keeps the record's current state)
+    pub proxied: Option<bool>,
+    /// Cloudflare: record TTL in seconds, 1 = auto (None keeps the current TTL)
+    pub ttl: Option<i32>,
+    /// Cloudflare: id of the A record, cached after the first update
+    #[serde(default)]
+    pub record_id: Option<String>,
+    /// Cloudflare: id of the AAAA record
+    #[serde(default)]
+    pub record_id_v6: Option<String>,
+    /// Standby config (another provider) for the same logical hostname
+    #[serde(default)]
+    pub secondary_config_id: Option<i32>,
+    /// Consecutive primary failures before switching to the secondary
+    #[serde(default = "default_failover_threshold")]
+    pub failover_threshold: i32,
+    #[serde(default)]
+    pub consecutive_failures: i32,
+    /// Updates currently go through the secondary
+    #[serde(default)]
+    pub failover_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl DdnsConfig {
+    /// Copy safe to return from the API: provider credentials and the
+    /// report-ip bearer token are masked
+    pub fn masked(mut self) -> Self {
+        for secret in [
+            &mut self.password,
+            &mut self.api_token,
+            &mut self.report_token,
+        ] {
+            if secret.is_some() {
+                *secret = Some("********".to_string());
+            }
+        }
+        self
+    }
+}
+
 impl TryFrom<DdnsConfigRow> for DdnsConfig {
     type Error = String;
 
@@ -188,11 +752,26 @@ impl TryFrom<DdnsConfigRow> for DdnsConfig {
             zone_id: row.zone_id,
             update_interval_sec: row.update_interval_sec,
             last_ip: row.last_ip,
+            update_ipv6: row.update_ipv6,
+            last_ipv6: row.last_ipv6,
             last_update: row.last_update,
             last_error: row.last_error,
             status: row.status.parse()?,
             omada_controller_id: row.omada_controller_id,
             omada_site_id: row.omada_site_id,
+            ip_source: row.ip_source.parse()?,
+            openwrt_router_id: row.openwrt_router_id,
+            report_token: row.report_token,
+            reported_ip: row.reported_ip,
+            reported_at: row.reported_at,
+            proxied: row.proxied,
+            ttl: row.ttl,
+            record_id: row.record_id,
+            record_id_v6: row.record_id_v6,
+            secondary_config_id: row.secondary_config_id,
+            failover_threshold: row.failover_threshold,
+            consecutive_failures: row.consecutive_failures,
+            failover_active: row.failover_active,
             created_at: row.created_at,
             updated_at: row.updated_at,
         })
@@ -206,9 +785,79 @@ pub struct CreateDdnsRequest {
     pub username: Option<String>,
     pub password: Option<String>,
     pub api_token: Option<String>,
+    /// Cloudflare: looked up from the hostname when omitted
     pub zone_id: Option<String>,
     #[serde(default = "default_update_interval")]
     pub update_interval_sec: i32,
+    /// Also push the public IPv6 address (AAAA record)
+    #[serde(default)]
+    pub update_ipv6: bool,
+    #[serde(default)]
+    pub ip_source: DdnsIpSource,
+    pub openwrt_router_id: Option<String>,
+    /// Cloudflare only
+    pub proxied: Option<bool>,
+    /// Cloudflare only: 1 (auto) or 60-86400
+    pub ttl: Option<i32>,
+    /// Failover config for the same hostname (see ddns::failover)
+    pub secondary_config_id: Option<i32>,
+    #[serde(default = "default_failover_threshold")]
+    pub failover_threshold: i32,
+}
+
+impl CreateDdnsRequest {
+    /// The config as it would be stored, for testing before it is saved
+    pub fn unsaved_config(&self) -> DdnsConfig {
+        let now = Utc::now();
+        DdnsConfig {
+            id: 0,
+            provider: self.provider,
+            hostname: self.hostname.clone(),
+            username: self.username.clone(),
+            password: self.password.clone(),
+            api_token: self.api_token.clone(),
+            zone_id: self.zone_id.clone(),
+            update_interval_sec: self.update_interval_sec,
+            last_ip: None,
+            update_ipv6: self.update_ipv6,
+            last_ipv6: None,
+            last_update: None,
+            last_error: None,
+            status: DdnsStatus::Active,
+            omada_controller_id: None,
+            omada_site_id: None,
+            ip_source: self.ip_source,
+            openwrt_router_id: self.openwrt_router_id.clone(),
+            report_token: None,
+            reported_ip: None,
+            reported_at: None,
+            proxied: self.proxied,
+            ttl: self.ttl,
+            record_id: None,
+            record_id_v6: None,
+            secondary_config_id: self.secondary_config_id,
+            failover_threshold: self.failover_threshold,
+            consecutive_failures: 0,
+            failover_active: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// POST /api/ddns/test body: a stored config, or a create body to try first
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DdnsTestRequest {
+    Stored { config_id: i32 },
+    New(CreateDdnsRequest),
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateDdnsQuery {
+    /// Run the credential test first and reject the config if it fails
+    #[serde(default)]
+    pub validate: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -219,7 +868,36 @@ pub struct UpdateDdnsRequest {
     pub api_token: Option<String>,
     pub zone_id: Option<String>,
     pub update_interval_sec: Option<i32>,
+    /// false also forgets last_ipv6
+    pub update_ipv6: Option<bool>,
     pub status: Option<DdnsStatus>,
+    pub ip_source: Option<DdnsIpSource>,
+    pub openwrt_router_id: Option<String>,
+    /// Cloudflare only; null clears it (keep the record's current state)
+    #[serde(default, deserialize_with = "nullable")]
+    pub proxied: Option<Option<bool>>,
+    /// Cloudflare only; null clears it
+    #[serde(default, deserialize_with = "nullable")]
+    pub ttl: Option<Option<i32>>,
+    /// null removes the failover link
+    #[serde(default, deserialize_with = "nullable")]
+    pub secondary_config_id: Option<Option<i32>>,
+    pub failover_threshold: Option<i32>,
+}
+
+/// Distinguish an explicit `null` (Some(None)) from an absent field (None)
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Body for POST /api/ddns/:id/report-ip (omit ip to use the caller's address)
+#[derive(Debug, Deserialize, Default)]
+pub struct ReportIpRequest {
+    pub ip: Option<String>,
 }
 
 /// Request to link a DDNS config to an Omada controller/site
@@ -233,6 +911,36 @@ fn default_update_interval() -> i32 {
     300
 }
 
+fn default_failover_threshold() -> i32 {
+    3
+}
+
+// ============================================================================
+// ACME Certificate Models
+// ============================================================================
+
+/// Certificate of a DDNS hostname issued through ACME (acme_certificates)
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AcmeCertificate {
+    pub id: i32,
+    pub ddns_config_id: i32,
+    pub hostname: String,
+    /// "pending" (order running), "valid" or "failed" (last order failed;
+    /// an earlier certificate is still served)
+    pub status: String,
+    #[serde(skip)]
+    pub cert_pem: Option<String>,
+    #[serde(skip)]
+    pub key_pem: Option<String>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // ============================================================================
 // Blocked IP Models
 // ============================================================================
@@ -245,6 +953,8 @@ pub struct BlockedIp {
     pub blocked_by: String,
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    /// What the block was made from (quick-block), e.g. `access_log:<id>`
+    pub context_ref: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -254,6 +964,58 @@ pub struct BlockIpRequest {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+// ============================================================================
+// Rate Limit Models
+// ============================================================================
+
+/// Per-client-IP request limit below a path (rate_limit_rules), enforced by
+/// the proxy (`crate::proxy::rate_limit`)
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitRule {
+    pub id: i32,
+    /// Request paths equal to or below this prefix count ("/" = all)
+    pub path_prefix: String,
+    pub requests_per_window: i32,
+    pub window_sec: i32,
+    pub action: RateLimitAction,
+    /// `block` rules: seconds the client is put on the block list for;
+    /// None = answer 429 only
+    pub block_duration_sec: Option<i32>,
+    /// LAN clients (network policy) are not counted
+    pub exclude_lan: bool,
+    pub enabled: bool,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRateLimitRuleRequest {
+    pub path_prefix: String,
+    pub requests_per_window: i32,
+    pub window_sec: i32,
+    #[serde(default)]
+    pub action: RateLimitAction,
+    pub block_duration_sec: Option<i32>,
+    pub exclude_lan: Option<bool>,
+    pub enabled: Option<bool>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRateLimitRuleRequest {
+    pub path_prefix: Option<String>,
+    pub requests_per_window: Option<i32>,
+    pub window_sec: Option<i32>,
+    pub action: Option<RateLimitAction>,
+    /// null stops blocking (429 only)
+    #[serde(default, deserialize_with = "nullable")]
+    pub block_duration_sec: Option<Option<i32>>,
+    pub exclude_lan: Option<bool>,
+    pub enabled: Option<bool>,
+    pub comment: Option<String>,
+}
+
 // ============================================================================
 // Settings Models
 // ============================================================================
@@ -278,6 +1040,14 @@ pub struct UpdateSettingRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessLog {
+    /// Stored document id (None until written)
+    #[serde(
+        rename = "_id",
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "document_id"
+    )]
+    pub id: Option<String>,
     pub timestamp: DateTime<Utc>,
     pub ip: String,
     pub method: String,
@@ -301,6 +1071,55 @@ pub struct AccessLog {
     pub latitude: Option<f64>,
     #[serde(default)]
     pub longitude: Option<f64>,
+    /// Upstream failure detail when no response was received (timeout, connect error)
+    #[serde(default)]
+    pub upstream_error: Option<String>,
+    // Protocol fields (optional; TLS fields stay None until TLS terminates in-process)
+    /// Client request HTTP version ("HTTP/1.0", "HTTP/1.1", "HTTP/2.0")
+    #[serde(default)]
+    pub http_version: Option<String>,
+    #[serde(default)]
+    pub tls_version: Option<String>,
+    #[serde(default)]
+    pub tls_cipher: Option<String>,
+    /// Which proxy served the request: `lpg` or `nginx` (None on entries
+    /// written before the field existed, all of which came from LPG)
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Values extracted by the route's log field rules (`RouteLogFields`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_fields: Option<BTreeMap<String, String>>,
+    /// "package.Service/Method" of a request on a gRPC route
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_method: Option<String>,
+    /// grpc-status from the trailers (HTTP status is 200 for nearly every call)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_status: Option<i32>,
+    /// How long a WebSocket session stayed open (`response_time_ms` is the
+    /// upstream handshake; request/response sizes are the bytes relayed
+    /// each way)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_duration_ms: Option<u64>,
+}
+
+impl AccessLog {
+    pub const SOURCE_LPG: &'static str = "lpg";
+    pub const SOURCE_NGINX: &'static str = "nginx";
+}
+
+/// Mongo `_id` as a string (ObjectIds as hex), so API clients can refer back
+/// to a stored log entry or event
+fn document_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(
+        match Option::<mongodb::bson::Bson>::deserialize(deserializer)? {
+            Some(mongodb::bson::Bson::ObjectId(id)) => Some(id.to_hex()),
+            Some(mongodb::bson::Bson::String(id)) => Some(id),
+            _ => None,
+        },
+    )
 }
 
 // ============================================================================
@@ -315,9 +1134,18 @@ pub enum SecurityEventType {
     SuspiciousActivity,
     DdnsFailure,
     HealthCheckFailure,
+    NewDevice,
+    AlertRule,
+    /// Unsafe settings found at startup (e.g. the default jwt_secret)
+    InsecureConfiguration,
+    /// A route's DDNS hostname no longer resolves to this gateway
+    DnsMismatch,
+    /// A `[server.tls]` certificate or key could not be loaded
+    TlsCertificateError,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Ordered Low < Medium < High < Critical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     Low,
@@ -328,6 +1156,14 @@ pub enum Severity {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityEvent {
+    /// Stored document id (None until written)
+    #[serde(
+        rename = "_id",
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "document_id"
+    )]
+    pub id: Option<String>,
     pub timestamp: DateTime<Utc>,
     pub event_type: SecurityEventType,
     pub ip: Option<String>,
@@ -351,18 +1187,85 @@ pub struct HealthCheck {
     pub error: Option<String>,
 }
 
+/// Extra context attached to a health failure notification.
+/// Gathered on a best-effort basis; empty when MongoDB is slow or unavailable.
+#[derive(Debug, Clone, Default)]
+pub struct HealthFailureContext {
+    /// Most recent 5xx access log entries for the route (newest first)
+    pub recent_errors: Vec<AccessLog>,
+    /// Timestamp of the last successful health check
+    pub last_success: Option<DateTime<Utc>>,
+}
+
 // ============================================================================
 // Dashboard Models
 // ============================================================================
 
+/// Dashboard stats. A count that could not be read is reported as 0 (for
+/// older clients) and listed in `field_errors`; `server_health` is
+/// "unknown" when its inputs could not be read.
 #[derive(Debug, Serialize)]
 pub struct DashboardStats {
     pub total_requests_today: u64,
     pub active_routes: u32,
     pub active_ddns: u32,
     pub blocked_ips: u32,
+    /// "healthy" | "degraded" | "unhealthy" | "unknown"
     pub server_health: String,
     pub uptime_seconds: u64,
+    /// Present when requested with `compare=previous`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<StatsComparison>,
+    /// Field name → why its value is unknown (empty when everything was read)
+    pub field_errors: std::collections::BTreeMap<String, String>,
+    pub data_sources: DashboardDataSources,
+    /// LAN DNS responder listener ("disabled" unless configured)
+    pub local_dns: crate::local_dns::DnsHealth,
+    /// Sessions are signed with the shipped default secret (rotate it with
+    /// POST /api/admin/rotate-jwt-secret)
+    pub default_jwt_secret: bool,
+}
+
+/// Reachability of the databases behind a dashboard response
+#[derive(Debug, Serialize)]
+pub struct DashboardDataSources {
+    pub mysql: DataSourceStatus,
+    pub mongo: DataSourceStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataSourceStatus {
+    pub reachable: bool,
+    /// Query errors encountered while assembling the response
+    pub errors: Vec<String>,
+}
+
+/// Request totals for one time window
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PeriodTotals {
+    pub total_requests: u64,
+    pub error_count: u64,
+    pub avg_response_time_ms: f64,
+}
+
+/// Percentage change vs the previous window (null when the previous value is zero)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PeriodDeltas {
+    pub total_requests_pct: Option<f64>,
+    pub error_count_pct: Option<f64>,
+    pub avg_response_time_pct: Option<f64>,
+}
+
+/// Current window vs the immediately preceding equivalent window
+#[derive(Debug, Serialize)]
+pub struct StatsComparison {
+    pub current_from: DateTime<Utc>,
+    pub current_to: DateTime<Utc>,
+    pub previous_from: DateTime<Utc>,
+    pub previous_to: DateTime<Utc>,
+    pub current: PeriodTotals,
+    pub previous: PeriodTotals,
+    pub delta: PeriodDeltas,
 }
 
 #[derive(Debug, Serialize)]
@@ -370,9 +1273,24 @@ pub struct RouteHealth {
     pub route_id: i32,
     pub path: String,
     pub target: String,
+    pub owner_name: Option<String>,
+    pub owner_contact: Option<String>,
+    pub team: Option<String>,
+    /// Kept for older clients; true when no check exists yet (see `status`)
     pub healthy: bool,
+    /// "healthy" | "unhealthy" | "unknown" (never checked, or checks unreadable)
+    pub status: String,
     pub last_check: Option<DateTime<Utc>>,
     pub consecutive_failures: u32,
+    /// Path the HTTP probe checks; None for grpc.health.v1 checks
+    pub probe_path: Option<String>,
+    /// Effective seconds between checks
+    pub check_interval_sec: i32,
+    /// Why check data could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Active maintenance windows covering the route
+    pub maintenance: Vec<crate::maintenance::ActiveMaintenance>,
 }
 
 /// Route statistics for detailed status
@@ -393,7 +1311,8 @@ pub struct RouteStats {
 pub struct IpExclusionParams {
     /// カンマ区切りの除外IPリスト (例: "1.2.3.4,5.6.7.8")
     pub exclude_ips: Option<String>,
-    /// true の場合、プライベートネットワークIP (10.x, 172.16.x, 192.168.x, 127.x) を除外
+    /// true の場合、プライベートネットワークIP (10.x, 172.16.x, 192.168.x, 127.x, 169.254.x,
+    /// IPv6 の ULA fc00::/7・リンクローカル fe80::/10・ループバック ::1) を除外
     pub exclude_lan: Option<bool>,
 }
 
@@ -418,6 +1337,11 @@ pub struct AccessLogSearchQuery {
     pub exclude_ips: Option<String>,
     /// true の場合、LAN IPを除外
     pub exclude_lan: Option<bool>,
+    /// HTTP version ("HTTP/1.1"; "unknown" matches logs without the field)
+    pub http_version: Option<String>,
+    /// Custom log field name; matched exactly against `custom_value`
+    pub custom_field: Option<String>,
+    pub custom_value: Option<String>,
 }
 
 fn default_search_limit() -> i64 {
@@ -430,6 +1354,82 @@ pub struct AccessLogSearchResult {
     pub total: u64,
 }
 
+/// One value of a custom log field (GET /api/dashboard/custom-field-summary)
+#[derive(Debug, Serialize)]
+pub struct CustomFieldValueStat {
+    pub value: String,
+    pub requests: u64,
+    /// Responses with status >= 400
+    pub errors: u64,
+    pub error_rate_percent: f64,
+    pub avg_response_time_ms: f64,
+}
+
+/// Requests of a route grouped by one custom log field
+#[derive(Debug, Serialize)]
+pub struct CustomFieldSummary {
+    pub route_id: i32,
+    pub field: String,
+    pub from: String,
+    pub to: String,
+    /// Requests carrying the field
+    pub total_requests: u64,
+    pub distinct_values: u64,
+    /// More values exist than were returned
+    pub truncated: bool,
+    /// Requests of the values not returned
+    pub other_requests: u64,
+    /// Busiest first
+    pub values: Vec<CustomFieldValueStat>,
+}
+
+/// Filter for POST /api/dashboard/access-log/delete (search shape, path is a prefix)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessLogDeleteFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub method: Option<String>,
+    pub status_min: Option<i32>,
+    pub status_max: Option<i32>,
+    pub ip: Option<String>,
+    /// Path prefix (matched literally, not as a regex)
+    pub path: Option<String>,
+}
+
+impl AccessLogDeleteFilter {
+    /// True when the delete is actually bounded: an ip, a path prefix below
+    /// "/", a status range narrower than 100-599, or a window ending before
+    /// now. Method alone, `from` alone and catch-all values do not count.
+    pub fn is_narrowed(&self) -> bool {
+        let ip = self.ip.as_deref().is_some_and(|s| !s.trim().is_empty());
+        let path = self
+            .path
+            .as_deref()
+            .is_some_and(|s| !s.trim().trim_end_matches('/').is_empty());
+        let status =
+            self.status_min.is_some_and(|m| m > 100) || self.status_max.is_some_and(|m| m < 599);
+        let window = self.to.is_some_and(|to| to < Utc::now());
+        ip || path || status || window
+    }
+}
+
+/// Progress of a bulk access log delete (GET /api/dashboard/access-log/delete/:job_id)
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogDeleteJob {
+    pub job_id: String,
+    /// running / completed / failed
+    pub status: String,
+    pub filter: AccessLogDeleteFilter,
+    /// Documents matching the filter when the job started
+    pub matched: u64,
+    pub deleted: u64,
+    pub batches: u32,
+    pub started_by: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct HourlyStat {
     pub hour: String,
@@ -438,6 +1438,24 @@ pub struct HourlyStat {
     pub avg_response_time_ms: f64,
 }
 
+/// One aligned hourly bucket with its counterpart from the previous window
+#[derive(Debug, Serialize)]
+pub struct HourlyComparisonBucket {
+    pub hour: String,
+    pub previous_hour: String,
+    pub current: PeriodTotals,
+    pub previous: PeriodTotals,
+    pub delta: PeriodDeltas,
+}
+
+/// Hourly stats with the previous window overlaid bucket-for-bucket
+#[derive(Debug, Serialize)]
+pub struct HourlyStatsComparison {
+    pub buckets: Vec<HourlyComparisonBucket>,
+    /// Whole-window totals, including both window bounds
+    pub totals: StatsComparison,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TopEntry {
     pub key: String,
@@ -454,6 +1472,19 @@ pub struct TopEntry {
     pub latitude: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub longitude: Option<f64>,
+    /// Country rule verdict ("allow" | "deny") for the IP's current GeoIP
+    /// country (top IPs with `resolve_country`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_action: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProtocolSummary {
+    /// HTTP version, or "unknown" for logs recorded before the field existed
+    pub http_version: String,
+    pub count: u64,
+    pub error_count: u64,
+    pub avg_response_time_ms: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -494,6 +1525,107 @@ pub struct LocalLoginRequest {
 pub struct LacisOathLoginRequest {
     pub code: String,
     pub redirect_uri: String,
+    /// Identity provider id (default: the first enabled provider)
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+/// Provider id of the LacisOath client configured in `[auth]`
+pub const CONFIG_LACISOATH_PROVIDER: &str = "default";
+
+/// A LacisOath (mobes 2.0) environment users can log in with. The client
+/// configured in `[auth]` is provider `default`; others live in the
+/// `lacisoath_providers` table.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LacisOathProvider {
+    pub provider_id: String,
+    pub display_name: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub redirect_uri: String,
+    /// Tenant the user must belong to (None = not checked)
+    pub expected_tid: Option<String>,
+    /// Minimum permission on top of the login floor (None = login floor only)
+    pub required_permission: Option<i32>,
+    /// Facility the user must have (or 0000)
+    pub required_fid: String,
+    pub enabled: bool,
+    /// Defined in the config file (read-only through the API)
+    #[sqlx(default)]
+    #[serde(default)]
+    pub from_config: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl LacisOathProvider {
+    /// The `[auth]` client, when one is configured
+    pub fn from_config(auth: &crate::config::AuthConfig) -> Option<Self> {
+        if auth.lacisoath_client_id.is_empty() || auth.lacisoath_client_secret.is_empty() {
+            return None;
+        }
+        Some(Self {
+            provider_id: CONFIG_LACISOATH_PROVIDER.to_string(),
+            display_name: "mobes 2.0".to_string(),
+            client_id: auth.lacisoath_client_id.clone(),
+            client_secret: auth.lacisoath_client_secret.clone(),
+            auth_url: auth.lacisoath_auth_url.clone(),
+            token_url: auth.lacisoath_token_url.clone(),
+            redirect_uri: auth.lacisoath_redirect_uri.clone(),
+            expected_tid: None,
+            required_permission: None,
+            required_fid: auth.lacisoath_required_fid.clone(),
+            enabled: true,
+            from_config: true,
+            created_at: None,
+            updated_at: None,
+        })
+    }
+
+    /// Copy safe to return from the API
+    pub fn masked(mut self) -> Self {
+        if !self.client_secret.is_empty() {
+            self.client_secret = "********".to_string();
+        }
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateLacisOathProviderRequest {
+    pub provider_id: String,
+    pub display_name: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub redirect_uri: String,
+    pub expected_tid: Option<String>,
+    pub required_permission: Option<i32>,
+    pub required_fid: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Omitted fields are unchanged; a masked or empty client_secret keeps the stored one
+#[derive(Debug, Deserialize)]
+pub struct UpdateLacisOathProviderRequest {
+    pub display_name: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub redirect_uri: Option<String>,
+    /// null clears
+    #[serde(default, deserialize_with = "nullable")]
+    pub expected_tid: Option<Option<String>>,
+    /// null clears
+    #[serde(default, deserialize_with = "nullable")]
+    pub required_permission: Option<Option<i32>>,
+    pub required_fid: Option<String>,
+    pub enabled: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -502,7 +1634,13 @@ pub struct SessionClaims {
     pub lacis_id: Option<String>,
     pub permission: i32,
     pub auth_method: String,
+    /// LacisOath provider that authenticated the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_provider: Option<String>,
     pub exp: usize,
+    /// Permission floors in effect when the session was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floors: Option<PermissionFloors>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -511,6 +1649,12 @@ pub struct AuthUser {
     pub lacis_id: Option<String>,
     pub permission: i32,
     pub auth_method: String,
+    /// LacisOath provider that authenticated the session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_provider: Option<String>,
+    /// Effective floors for this request (resolved by the auth middleware)
+    #[serde(skip)]
+    pub floors: PermissionFloors,
 }
 
 impl From<SessionClaims> for AuthUser {
@@ -520,10 +1664,94 @@ impl From<SessionClaims> for AuthUser {
             lacis_id: claims.lacis_id,
             permission: claims.permission,
             auth_method: claims.auth_method,
+            auth_provider: claims.auth_provider,
+            floors: claims.floors.unwrap_or_default(),
+        }
+    }
+}
+
+/// Permission floors per endpoint group, stored in the settings table
+/// (`permission_floor_*`) and editable at runtime.
+///
+/// Handlers name the group they need by its default value (0/50/80/100);
+/// `for_level` maps that to the live floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionFloors {
+    /// Minimum LacisOath permission to log in at all
+    pub login: i32,
+    pub read: i32,
+    pub operate: i32,
+    pub admin: i32,
+    pub dangerous: i32,
+}
+
+impl Default for PermissionFloors {
+    fn default() -> Self {
+        Self {
+            login: 80,
+            read: 0,
+            operate: 50,
+            admin: 80,
+            dangerous: 100,
         }
     }
 }
 
+impl PermissionFloors {
+    pub const SETTING_LOGIN: &'static str = "permission_floor_login";
+    pub const SETTING_READ: &'static str = "permission_floor_read";
+    pub const SETTING_OPERATE: &'static str = "permission_floor_operate";
+    pub const SETTING_ADMIN: &'static str = "permission_floor_admin";
+    pub const SETTING_DANGEROUS: &'static str = "permission_floor_dangerous";
+
+    /// Map a handler's group level (0/50/80/100) to the configured floor.
+    /// Any other value is treated as a literal requirement.
+    pub fn for_level(&self, level: i32) -> i32 {
+        match level {
+            0 => self.read,
+            50 => self.operate,
+            80 => self.admin,
+            100 => self.dangerous,
+            other => other,
+        }
+    }
+
+    /// Field-wise maximum. Used to combine the floors captured in a session
+    /// with the live ones, so raising a floor applies immediately while
+    /// lowering one only benefits sessions issued after the change.
+    pub fn stricter(&self, other: &PermissionFloors) -> PermissionFloors {
+        PermissionFloors {
+            login: self.login.max(other.login),
+            read: self.read.max(other.read),
+            operate: self.operate.max(other.operate),
+            admin: self.admin.max(other.admin),
+            dangerous: self.dangerous.max(other.dangerous),
+        }
+    }
+
+    /// Values must be within 0..=100 and non-decreasing read → dangerous
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("login", self.login),
+            ("read", self.read),
+            ("operate", self.operate),
+            ("admin", self.admin),
+            ("dangerous", self.dangerous),
+        ] {
+            if !(0..=100).contains(&value) {
+                return Err(format!("{} floor must be between 0 and 100", name));
+            }
+        }
+        if !(self.read <= self.operate
+            && self.operate <= self.admin
+            && self.admin <= self.dangerous)
+        {
+            return Err("Floors must satisfy read <= operate <= admin <= dangerous".to_string());
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub ok: bool,
@@ -586,3 +1814,176 @@ pub struct AuditLog {
     pub ip_address: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
 }
+
+// ============================================================================
+// WireGuard Config Profile Models
+// ============================================================================
+
+/// Named client-config profile (wg_config_profiles).
+///
+/// `dns` and `allowed_ips` are templates; see `wireguard::config` for the
+/// placeholders. A NULL/empty `dns` omits the DNS line.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WgConfigProfile {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub dns: Option<String>,
+    pub allowed_ips: String,
+    /// None = use the request's persistent_keepalive; 0 = never emit
+    pub persistent_keepalive: Option<i32>,
+    pub mtu: Option<i32>,
+    /// Whether PreUp/PostUp/PreDown/PostDown may be included
+    pub scripts_allowed: bool,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWgProfileRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub dns: Option<String>,
+    pub allowed_ips: String,
+    pub persistent_keepalive: Option<i32>,
+    pub mtu: Option<i32>,
+    #[serde(default)]
+    pub scripts_allowed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWgProfileRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Empty string removes the DNS line
+    pub dns: Option<String>,
+    pub allowed_ips: Option<String>,
+    /// Negative value clears the override
+    pub persistent_keepalive: Option<i32>,
+    /// Zero or negative clears the MTU line
+    pub mtu: Option<i32>,
+    pub scripts_allowed: Option<bool>,
+}
+
+/// LPG's record of a peer it provisioned (wg_peer_profiles), keyed by
+/// public key. Compared against the controller's copy in the unified view.
+#[derive(Debug, Clone, Serialize)]
+pub struct WgPeerRecord {
+    pub public_key: String,
+    pub controller_id: String,
+    pub site_id: String,
+    pub peer_name: String,
+    pub profile_id: Option<i32>,
+    pub profile_name: Option<String>,
+    /// Allowed addresses as last written by LPG (None on rows recorded
+    /// before they were tracked)
+    pub allow_address: Option<Vec<String>>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Local DNS override (dns_overrides): the built-in responder answers
+/// `hostname` itself instead of forwarding it upstream
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DnsOverride {
+    pub id: i32,
+    /// Lowercase, without trailing dot
+    pub hostname: String,
+    /// IPv4/IPv6 address; None = the LPG's LAN IP
+    pub target: Option<String>,
+    pub enabled: bool,
+    /// "manual" | "ddns" (created from a DDNS suggestion)
+    pub source: String,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDnsOverrideRequest {
+    pub hostname: String,
+    /// Omit for the LPG's LAN IP
+    pub target: Option<String>,
+    pub enabled: Option<bool>,
+    pub source: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDnsOverrideRequest {
+    pub hostname: Option<String>,
+    /// null switches back to the LPG's LAN IP
+    #[serde(default, deserialize_with = "nullable")]
+    pub target: Option<Option<String>>,
+    pub enabled: Option<bool>,
+    pub comment: Option<String>,
+}
+
+/// Read-only topology share (topology_shares): GET /api/public/topology?token=...
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologyShare {
+    pub id: i32,
+    pub name: String,
+    pub view: ShareView,
+    /// Facility of a `site` share
+    pub fid: Option<String>,
+    pub detail: ShareDetail,
+    /// First characters of the plaintext, to tell tokens apart
+    pub token_prefix: String,
+    pub expires_at: DateTime<Utc>,
+    /// Payloads served
+    pub request_count: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTopologyShareRequest {
+    pub name: String,
+    #[serde(default)]
+    pub view: ShareView,
+    /// Required for the `site` view
+    pub fid: Option<String>,
+    #[serde(default)]
+    pub detail: ShareDetail,
+    /// Hours until the share expires (default 720, max 8760)
+    pub expires_in_hours: Option<i64>,
+}
+
+/// Device registration token (aranea_registration_tokens): lets devices of a
+/// facility call POST /api/aranea/register without an admin session
+#[derive(Debug, Clone, Serialize)]
+pub struct AraneaRegistrationToken {
+    pub id: i32,
+    /// Audit actor, as `token:<name>`
+    pub name: String,
+    /// Facility the token may register devices for
+    pub fid: String,
+    /// productType values the token may register
+    pub product_types: Vec<String>,
+    /// First characters of the plaintext, to tell tokens apart
+    pub token_prefix: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// None = unlimited
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRegistrationTokenRequest {
+    pub name: String,
+    pub fid: String,
+    pub product_types: Vec<String>,
+    /// Hours until the token expires; omit for no expiry
+    pub expires_in_hours: Option<i64>,
+    pub max_uses: Option<i32>,
+}
@@ -0,0 +1,576 @@
+//! Compiled network policy
+//!
+//! Every source-address decision - the block list on proxied requests, the
+//! internet access guard on the API and `admin_network_only` routes, and
+//! which peers may set the client address through X-Forwarded-For - is
+//! answered by one `NetworkPolicy` snapshot compiled from:
+//!
+//! - `blocked_ips` (single IPs and CIDR ranges, see `crate::blocklist`)
+//! - the LAN: RFC 1918, loopback, IPv6 loopback, plus `network_lan_extra`
+//! - `network_trusted_proxies`: peers whose X-Forwarded-For is honoured,
+//!   in addition to loopback (nginx on the same host)
+//! - `network_admin_allowlist`: public networks admitted to admin surfaces
+//!   while internet access is disabled
+//! - `internet_access_enabled` (settings, falling back to `[auth]`)
+//!
+//! The snapshot sits in an `ArcSwap`, so a request reads it without a lock
+//! and `NetworkPolicy::evaluate` is a pure function of it. Admin endpoints
+//! that change an input rebuild it before answering; every instance also
+//! checks the inputs every `WATCH_INTERVAL` (changes made by other
+//! instances, direct SQL) and rebuilds unconditionally every
+//! `FULL_REBUILD_INTERVAL`.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::{ArcSwap, Guard};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use ipnetwork::IpNetwork;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use crate::blocklist::{self, BlockList};
+use crate::db::MySqlDb;
+use crate::error::AppError;
+use crate::ip::{canonical_ip, parse_ip};
+use crate::models::BlockedIp;
+
+pub const SETTING_INTERNET_ACCESS: &str = "internet_access_enabled";
+/// Networks treated as LAN in addition to the private ranges
+pub const SETTING_LAN_EXTRA: &str = "network_lan_extra";
+/// Peers whose X-Forwarded-For / X-Real-IP is trusted (loopback always is)
+pub const SETTING_TRUSTED_PROXIES: &str = "network_trusted_proxies";
+/// Public networks admitted to admin surfaces without internet access
+pub const SETTING_ADMIN_ALLOWLIST: &str = "network_admin_allowlist";
+
+/// How often the inputs are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(15);
+/// Rebuild even without a detected change (edits the fingerprint misses)
+const FULL_REBUILD_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Whether a settings key is an input of the policy
+pub fn is_policy_setting(key: &str) -> bool {
+    [
+        SETTING_INTERNET_ACCESS,
+        SETTING_LAN_EXTRA,
+        SETTING_TRUSTED_PROXIES,
+        SETTING_ADMIN_ALLOWLIST,
+    ]
+    .contains(&key)
+}
+
+/// Parse a list of IPs / CIDRs separated by commas or whitespace
+pub fn parse_networks(raw: &str) -> Result<Vec<IpNetwork>, String> {
+    raw.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| blocklist::parse_network(s).ok_or_else(|| format!("Invalid IP or CIDR: {}", s)))
+        .collect()
+}
+
+/// Private, loopback, link-local or unspecified (IPv4 and IPv6, see
+/// `crate::ip::is_lan`)
+pub fn is_private(ip: IpAddr) -> bool {
+    crate::ip::is_lan(ip)
+}
+
+/// Where a request is headed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    /// A proxied route
+    Proxy,
+    /// The API, or a route marked `admin_network_only`
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Allow,
+    Deny,
+    /// Allowed, but worth noting (admin surface reached from the internet)
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// Covered by an active `blocked_ips` entry
+    Blocked,
+    Lan,
+    /// In `network_admin_allowlist`
+    Allowlisted,
+    /// Public source, admitted by `internet_access_enabled`
+    InternetAccess,
+    /// Public source on an admin surface without internet access
+    InternetAccessDisabled,
+    /// Public source on a proxied route
+    Public,
+}
+
+impl Reason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Blocked => "blocked",
+            Self::Lan => "lan",
+            Self::Allowlisted => "allowlisted",
+            Self::InternetAccess => "internet_access",
+            Self::InternetAccessDisabled => "internet_access_disabled",
+            Self::Public => "public",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Decision {
+    pub verdict: Verdict,
+    pub reason: Reason,
+}
+
+impl Decision {
+    fn new(verdict: Verdict, reason: Reason) -> Self {
+        Self { verdict, reason }
+    }
+
+    pub fn is_denied(&self) -> bool {
+        self.verdict == Verdict::Deny
+    }
+}
+
+/// Policy inputs kept in settings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolicySettings {
+    /// None = not set (the `[auth]` value applies)
+    pub internet_access: Option<bool>,
+    pub lan_extra: String,
+    pub trusted_proxies: String,
+    pub admin_allowlist: String,
+}
+
+impl PolicySettings {
+    pub async fn load(mysql: &MySqlDb) -> Result<Self, AppError> {
+        let text = |v: Option<String>| v.unwrap_or_default();
+        Ok(Self {
+            internet_access: mysql
+                .get_setting(SETTING_INTERNET_ACCESS)
+                .await?
+                .map(|v| v == "true" || v == "1"),
+            lan_extra: text(mysql.get_setting(SETTING_LAN_EXTRA).await?),
+            trusted_proxies: text(mysql.get_setting(SETTING_TRUSTED_PROXIES).await?),
+            admin_allowlist: text(mysql.get_setting(SETTING_ADMIN_ALLOWLIST).await?),
+        })
+    }
+}
+
+/// One compiled snapshot
+pub struct NetworkPolicy {
+    blocklist: BlockList,
+    lan_extra: Vec<IpNetwork>,
+    trusted_proxies: Vec<IpNetwork>,
+    admin_allowlist: Vec<IpNetwork>,
+    internet_access: bool,
+}
+
+impl NetworkPolicy {
+    /// Compile; unparseable list entries are skipped (they are validated
+    /// when written through the API)
+    pub fn compile(
+        blocked: &[BlockedIp],
+        settings: &PolicySettings,
+        internet_access_default: bool,
+    ) -> Self {
+        let networks = |key: &str, raw: &str| {
+            raw.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .filter_map(|s| {
+                    let network = blocklist::parse_network(s);
+                    if network.is_none() {
+                        tracing::warn!("Skipping invalid {} entry: {}", key, s);
+                    }
+                    network
+                })
+                .collect::<Vec<_>>()
+        };
+        Self {
+            blocklist: BlockList::new(blocked),
+            lan_extra: networks(SETTING_LAN_EXTRA, &settings.lan_extra),
+            trusted_proxies: networks(SETTING_TRUSTED_PROXIES, &settings.trusted_proxies),
+            admin_allowlist: networks(SETTING_ADMIN_ALLOWLIST, &settings.admin_allowlist),
+            internet_access: settings.internet_access.unwrap_or(internet_access_default),
+        }
+    }
+
+    /// The decision for a source address at `now`
+    pub fn evaluate(&self, ip: IpAddr, surface: Surface, now: DateTime<Utc>) -> Decision {
+        // IPv4-mapped addresses are judged as the IPv4 address
+        let ip = ip.to_canonical();
+        if self.blocklist.is_blocked_at(ip, now) {
+            return Decision::new(Verdict::Deny, Reason::Blocked);
+        }
+        if self.is_lan(ip) {
+            return Decision::new(Verdict::Allow, Reason::Lan);
+        }
+        match surface {
+            Surface::Proxy => Decision::new(Verdict::Allow, Reason::Public),
+            Surface::Admin if contains(&self.admin_allowlist, ip) => {
+                Decision::new(Verdict::Allow, Reason::Allowlisted)
+            }
+            Surface::Admin if self.internet_access => {
+                Decision::new(Verdict::Flag, Reason::InternetAccess)
+            }
+            Surface::Admin => Decision::new(Verdict::Deny, Reason::InternetAccessDisabled),
+        }
+    }
+
+    /// `evaluate` for an address as extracted from a request; one that does
+    /// not parse is treated as a public, unblocked source
+    pub fn evaluate_str(&self, ip: &str, surface: Surface, now: DateTime<Utc>) -> Decision {
+        match parse_ip(ip) {
+            Some(addr) => self.evaluate(addr, surface, now),
+            None => match surface {
+                Surface::Proxy => Decision::new(Verdict::Allow, Reason::Public),
+                Surface::Admin if self.internet_access => {
+                    Decision::new(Verdict::Flag, Reason::InternetAccess)
+                }
+                Surface::Admin => Decision::new(Verdict::Deny, Reason::InternetAccessDisabled),
+            },
+        }
+    }
+
+    /// Client address of a request: the socket peer, unless the peer is a
+    /// trusted proxy. Then X-Forwarded-For is walked right to left past the
+    /// trusted hops (entries further left are client-supplied), falling back
+    /// to X-Real-IP and then the peer.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: SocketAddr) -> String {
+        let peer = peer.ip().to_canonical();
+        if !self.is_trusted_proxy(peer) {
+            return peer.to_string();
+        }
+
+        let forwarded = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|hop| !hop.is_empty())
+            .collect::<Vec<_>>();
+        for hop in forwarded.iter().rev() {
+            match parse_ip(hop) {
+                Some(addr) if self.is_trusted_proxy(addr) => continue,
+                // An unparseable hop is kept as text and judged as public
+                _ => return canonical_ip(hop),
+            }
+        }
+        if let Some(first) = forwarded.first() {
+            // Every hop is a trusted proxy
+            return canonical_ip(first);
+        }
+
+        headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .map(canonical_ip)
+            .unwrap_or_else(|| peer.to_string())
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_loopback() || contains(&self.trusted_proxies, ip)
+    }
+
+    pub fn is_lan(&self, ip: IpAddr) -> bool {
+        is_private(ip) || contains(&self.lan_extra, ip)
+    }
+
+    pub fn blocked_entries(&self) -> usize {
+        self.blocklist.len()
+    }
+}
+
+fn contains(networks: &[IpNetwork], ip: IpAddr) -> bool {
+    networks.iter().any(|n| n.contains(ip))
+}
+
+/// What the watcher compares to detect changed inputs
+#[derive(Debug, Clone, PartialEq)]
+struct Fingerprint {
+    settings: PolicySettings,
+    /// (row count, highest id) of `blocked_ips`
+    blocked: (i64, Option<i32>),
+}
+
+/// The live policy and its rebuilds
+pub struct NetworkPolicyStore {
+    current: ArcSwap<NetworkPolicy>,
+    internet_access_default: bool,
+    /// Serializes rebuilds so an older snapshot never replaces a newer one
+    last_built: Mutex<Option<Fingerprint>>,
+}
+
+impl NetworkPolicyStore {
+    /// Compile the initial snapshot from MySQL
+    pub async fn load(mysql: &MySqlDb, internet_access_default: bool) -> anyhow::Result<Self> {
+        let store = Self {
+            current: ArcSwap::from_pointee(NetworkPolicy::compile(
+                &[],
+                &PolicySettings::default(),
+                internet_access_default,
+            )),
+            internet_access_default,
+            last_built: Mutex::new(None),
+        };
+        store.rebuild(mysql).await?;
+        Ok(store)
+    }
+
+    /// The current snapshot (lock-free)
+    pub fn load_policy(&self) -> Guard<Arc<NetworkPolicy>> {
+        self.current.load()
+    }
+
+    /// Recompile from MySQL and swap it in; the old snapshot stays on failure.
+    /// Returns the number of compiled block entries.
+    pub async fn rebuild(&self, mysql: &MySqlDb) -> anyhow::Result<usize> {
+        let mut last_built = self.last_built.lock().await;
+        let fingerprint = fingerprint(mysql).await?;
+        let blocked = mysql.list_active_blocked_ips().await?;
+        let policy = NetworkPolicy::compile(
+            &blocked,
+            &fingerprint.settings,
+            self.internet_access_default,
+        );
+        let count = policy.blocked_entries();
+        self.current.store(Arc::new(policy));
+        *last_built = Some(fingerprint);
+        tracing::debug!("Network policy rebuilt: {} blocked entries", count);
+        Ok(count)
+    }
+
+    /// Rebuild when the inputs changed, or when the last rebuild is older
+    /// than `FULL_REBUILD_INTERVAL` (per instance)
+    pub async fn watch(self: Arc<Self>, mysql: Arc<MySqlDb>) {
+        let mut tick = interval(WATCH_INTERVAL);
+        let mut last_full = Instant::now();
+        loop {
+            tick.tick().await;
+            let changed = match fingerprint(&mysql).await {
+                Ok(current) => self.last_built.lock().await.as_ref() != Some(&current),
+                Err(e) => {
+                    tracing::warn!("Network policy check failed: {}", e);
+                    continue;
+                }
+            };
+            if !changed && last_full.elapsed() < FULL_REBUILD_INTERVAL {
+                continue;
+            }
+            match self.rebuild(&mysql).await {
+                Ok(count) => {
+                    last_full = Instant::now();
+                    if changed {
+                        tracing::info!(
+                            "Network policy inputs changed; rebuilt ({} blocked entries)",
+                            count
+                        );
+                    }
+                }
+                Err(e) => tracing::warn!("Network policy rebuild failed: {}", e),
+            }
+        }
+    }
+}
+
+async fn fingerprint(mysql: &MySqlDb) -> anyhow::Result<Fingerprint> {
+    Ok(Fingerprint {
+        settings: PolicySettings::load(mysql).await?,
+        blocked: mysql.blocked_ips_fingerprint().await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ip: &str) -> BlockedIp {
+        BlockedIp {
+            id: 0,
+            ip: ip.to_string(),
+            reason: None,
+            blocked_by: "manual".to_string(),
+            expires_at: None,
+            created_at: Utc::now(),
+            context_ref: None,
+        }
+    }
+
+    fn decide(policy: &NetworkPolicy, ip: &str, surface: Surface) -> (Verdict, Reason) {
+        let d = policy.evaluate_str(ip, surface, Utc::now());
+        (d.verdict, d.reason)
+    }
+
+    #[test]
+    fn admin_surface_follows_lan_allowlist_and_internet_access() {
+        let settings = PolicySettings {
+            lan_extra: "100.64.0.0/10".to_string(),
+            admin_allowlist: "198.51.100.7".to_string(),
+            ..Default::default()
+        };
+        let policy = NetworkPolicy::compile(&[entry("192.168.1.66")], &settings, false);
+
+        use Reason::*;
+        use Verdict::*;
+        assert_eq!(decide(&policy, "192.168.1.5", Surface::Admin), (Allow, Lan));
+        assert_eq!(decide(&policy, "100.64.3.4", Surface::Admin), (Allow, Lan));
+        assert_eq!(
+            decide(&policy, "198.51.100.7", Surface::Admin),
+            (Allow, Allowlisted)
+        );
+        assert_eq!(
+            decide(&policy, "8.8.8.8", Surface::Admin),
+            (Deny, InternetAccessDisabled)
+        );
+        assert_eq!(decide(&policy, "8.8.8.8", Surface::Proxy), (Allow, Public));
+        // A block wins over LAN membership, on every surface
+        assert_eq!(
+            decide(&policy, "192.168.1.66", Surface::Admin),
+            (Deny, Blocked)
+        );
+        assert_eq!(
+            decide(&policy, "::ffff:192.168.1.66", Surface::Proxy),
+            (Deny, Blocked)
+        );
+
+        let open = NetworkPolicy::compile(
+            &[],
+            &PolicySettings {
+                internet_access: Some(true),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(
+            decide(&open, "8.8.8.8", Surface::Admin),
+            (Flag, InternetAccess)
+        );
+        assert_eq!(
+            decide(&open, "garbage", Surface::Admin),
+            (Flag, InternetAccess)
+        );
+        assert_eq!(decide(&open, "garbage", Surface::Proxy), (Allow, Public));
+    }
+
+    #[test]
+    fn forwarding_headers_only_from_trusted_proxies() {
+        let xff = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", value.parse().unwrap());
+            headers
+        };
+        let proxy: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        let local: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+        let stranger: SocketAddr = "198.51.100.1:443".parse().unwrap();
+
+        // Nothing configured: only loopback (local nginx) may forward
+        let default = NetworkPolicy::compile(&[], &PolicySettings::default(), false);
+        assert_eq!(
+            default.client_ip(&xff("203.0.113.9"), stranger),
+            "198.51.100.1"
+        );
+        assert_eq!(default.client_ip(&xff("203.0.113.9"), local), "203.0.113.9");
+        assert_eq!(default.client_ip(&HeaderMap::new(), local), "127.0.0.1");
+
+        let trusted = NetworkPolicy::compile(
+            &[],
+            &PolicySettings {
+                trusted_proxies: "10.0.0.0/24".to_string(),
+                ..Default::default()
+            },
+            false,
+        );
+        assert_eq!(
+            trusted.client_ip(&xff("203.0.113.9, 10.0.0.3"), proxy),
+            "203.0.113.9"
+        );
+        assert_eq!(
+            trusted.client_ip(&xff("203.0.113.9"), stranger),
+            "198.51.100.1"
+        );
+        // The leftmost entry is whatever the client sent; the proxy appended
+        // the address it saw
+        assert_eq!(
+            trusted.client_ip(&xff("192.168.1.1, 203.0.113.9"), proxy),
+            "203.0.113.9"
+        );
+        let mut real_ip = HeaderMap::new();
+        real_ip.insert("x-real-ip", "203.0.113.10".parse().unwrap());
+        assert_eq!(trusted.client_ip(&real_ip, proxy), "203.0.113.10");
+        assert_eq!(trusted.client_ip(&real_ip, stranger), "198.51.100.1");
+    }
+
+    #[test]
+    fn network_lists_are_validated() {
+        assert_eq!(parse_networks("").unwrap(), vec![]);
+        assert_eq!(
+            parse_networks("10.8.0.0/16, 2001:db8::/32\n192.0.2.1")
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(parse_networks("10.8.0.0/16, nope").is_err());
+        assert!(is_policy_setting(SETTING_TRUSTED_PROXIES));
+        assert!(!is_policy_setting("proxy_idle_timeout_sec"));
+    }
+
+    /// Evaluation cost with 10k block entries. Run in release:
+    /// `cargo test --release -- --ignored evaluation_benchmark --nocapture`
+    #[test]
+    #[ignore]
+    fn evaluation_benchmark() {
+        let mut blocked = Vec::with_capacity(10_000);
+        for i in 0..10_000u32 {
+            // Hosts and /24, /20, /16 ranges spread over public space
+            let prefix = [32, 24, 20, 16][(i % 4) as usize];
+            let addr =
+                std::net::Ipv4Addr::from(0x2000_0000u32.wrapping_add(i.wrapping_mul(0x0001_3579)));
+            blocked.push(entry(&format!("{}/{}", addr, prefix)));
+        }
+        let settings = PolicySettings {
+            lan_extra: "100.64.0.0/10".to_string(),
+            admin_allowlist: "198.51.100.0/24, 203.0.113.7".to_string(),
+            ..Default::default()
+        };
+        let policy = NetworkPolicy::compile(&blocked, &settings, false);
+        let probes: Vec<IpAddr> = (0..1024u32)
+            .map(|i| {
+                IpAddr::V4(std::net::Ipv4Addr::from(
+                    0x0800_0000u32.wrapping_add(i.wrapping_mul(0x00ab_cdef)),
+                ))
+            })
+            .collect();
+
+        let now = Utc::now();
+        let rounds = 1_000;
+        let started = Instant::now();
+        let mut denied = 0usize;
+        for _ in 0..rounds {
+            for ip in &probes {
+                denied += std::hint::black_box(policy.evaluate(*ip, Surface::Admin, now))
+                    .is_denied() as usize;
+            }
+        }
+        let per_call = started.elapsed() / (rounds * probes.len() as u32);
+        println!(
+            "{} entries: {:?} per evaluation ({} denied)",
+            policy.blocked_entries(),
+            per_call,
+            denied
+        );
+        assert!(
+            per_call < Duration::from_micros(1),
+            "{:?} per evaluation",
+            per_call
+        );
+    }
+}
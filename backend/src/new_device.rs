@@ -0,0 +1,369 @@
+//! New device detection
+//!
+//! When ingestion inserts a user_object_detail entry (as opposed to updating
+//! one), the device is checked against the `new_device_alerts` setting:
+//!
+//! - `quiet_macs`: MAC prefixes or full MACs that are ignored entirely
+//! - `rules`: filters on node_type, source, parent_id, ssid, fid (all set
+//!   fields must match; any rule may match; no rules = every device)
+//! - `group_randomized`: locally administered client MACs (randomized phone
+//!   MACs) are reported in one grouped notification per ingest cycle
+//!
+//! Matching devices are written as low-severity security events and queued;
+//! the ingester flushes the queue to Discord at the end of each cycle.
+
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::mongo::user_object_detail::UserObjectDetail;
+use crate::db::mysql::MySqlDb;
+use crate::db::AppState;
+use crate::error::AppError;
+use crate::notify::DiscordNotifier;
+use crate::omada::client::normalize_mac;
+
+pub const SETTING_NEW_DEVICE_ALERTS: &str = "new_device_alerts";
+
+/// More individual alerts than this in one cycle are sent as a single summary
+const MAX_INDIVIDUAL_ALERTS: usize = 10;
+
+/// One notification rule; unset fields match anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewDeviceRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Parent node _id (e.g. a specific switch or AP)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fid: Option<String>,
+}
+
+impl NewDeviceRule {
+    fn matches(&self, entry: &UserObjectDetail) -> bool {
+        fn field(filter: &Option<String>, value: Option<&str>) -> bool {
+            filter.as_deref().is_none_or(|f| value == Some(f))
+        }
+        field(&self.node_type, Some(&entry.node_type))
+            && field(&self.source, Some(&entry.source))
+            && field(&self.parent_id, Some(&entry.parent_id))
+            && field(&self.ssid, entry.ssid.as_deref())
+            && field(&self.fid, entry.fid.as_deref())
+    }
+}
+
+/// The `new_device_alerts` setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewDevicePolicy {
+    #[serde(default)]
+    pub rules: Vec<NewDeviceRule>,
+    /// MAC prefixes or full MACs (any separator, any case)
+    #[serde(default)]
+    pub quiet_macs: Vec<String>,
+    #[serde(default = "default_group_randomized")]
+    pub group_randomized: bool,
+}
+
+fn default_group_randomized() -> bool {
+    true
+}
+
+impl Default for NewDevicePolicy {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            quiet_macs: Vec::new(),
+            group_randomized: default_group_randomized(),
+        }
+    }
+}
+
+impl NewDevicePolicy {
+    /// Load the policy; a missing or invalid setting falls back to the default
+    pub async fn load(mysql: &MySqlDb) -> Result<Self, AppError> {
+        let raw = mysql.get_setting(SETTING_NEW_DEVICE_ALERTS).await?;
+        Ok(
+            match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+                Some(raw) => Self::parse(raw).unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "Invalid {} setting, using default: {}",
+                        SETTING_NEW_DEVICE_ALERTS,
+                        e
+                    );
+                    Self::default()
+                }),
+                None => Self::default(),
+            },
+        )
+    }
+
+    /// Parse and validate a policy JSON document (quiet MACs are normalized)
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut policy: Self = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+        for quiet in policy.quiet_macs.iter_mut() {
+            let normalized = normalize_mac(quiet.trim());
+            if normalized.len() < 2
+                || normalized.len() > 12
+                || !normalized.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return Err(format!("invalid quiet MAC or prefix: {:?}", quiet));
+            }
+            *quiet = normalized;
+        }
+        Ok(policy)
+    }
+
+    pub fn is_quiet(&self, mac: &str) -> bool {
+        let mac = normalize_mac(mac);
+        self.quiet_macs.iter().any(|q| mac.starts_with(q.as_str()))
+    }
+
+    pub fn matches(&self, entry: &UserObjectDetail) -> bool {
+        self.rules.is_empty() || self.rules.iter().any(|r| r.matches(entry))
+    }
+}
+
+/// Whether the locally administered bit is set (randomized/private MACs)
+pub fn is_locally_administered(mac: &str) -> bool {
+    let mac = normalize_mac(mac);
+    mac.get(0..2)
+        .and_then(|b| u8::from_str_radix(b, 16).ok())
+        .is_some_and(|b| b & 0x02 != 0)
+}
+
+/// A newly seen device, as notified and stored in the security event
+#[derive(Debug, Clone, Serialize)]
+pub struct NewDeviceAlert {
+    pub id: String,
+    pub mac: String,
+    pub label: String,
+    pub node_type: String,
+    pub source: String,
+    pub ip: Option<String>,
+    pub vendor: Option<String>,
+    pub parent_id: String,
+    pub parent_label: Option<String>,
+    pub ssid: Option<String>,
+    /// Switch port for wired clients ("SW-1 port 7 (Office)")
+    pub port: Option<String>,
+    pub fid: Option<String>,
+    pub facility_name: Option<String>,
+    /// Locally administered MAC
+    pub randomized: bool,
+}
+
+impl NewDeviceAlert {
+    fn new(entry: &UserObjectDetail, parent_label: Option<String>) -> Self {
+        let port = entry.metadata.get("switch_port").and_then(|p| {
+            let no = p.get("port")?.as_i64()?;
+            let switch = p
+                .get("switch_name")
+                .and_then(|s| s.as_str())
+                .unwrap_or("switch");
+            Some(match p.get("port_name").and_then(|n| n.as_str()) {
+                Some(name) => format!("{} port {} ({})", switch, no, name),
+                None => format!("{} port {}", switch, no),
+            })
+        });
+        Self {
+            id: entry.id.clone(),
+            mac: entry.mac.clone(),
+            label: entry.label.clone(),
+            node_type: entry.node_type.clone(),
+            source: entry.source.clone(),
+            ip: entry.ip.clone(),
+            vendor: entry
+                .metadata
+                .get("vendor")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string),
+            parent_id: entry.parent_id.clone(),
+            parent_label,
+            ssid: entry.ssid.clone(),
+            port,
+            fid: entry.fid.clone(),
+            facility_name: entry.facility_name.clone(),
+            randomized: entry.node_type == "client" && is_locally_administered(&entry.mac),
+        }
+    }
+
+    /// Where the device appeared: SSID, switch port or parent node
+    pub fn location(&self) -> String {
+        let parent = self.parent_label.as_deref().unwrap_or(&self.parent_id);
+        match (&self.ssid, &self.port) {
+            (Some(ssid), _) => format!("SSID {} via {}", ssid, parent),
+            (None, Some(port)) => port.clone(),
+            (None, None) => parent.to_string(),
+        }
+    }
+}
+
+/// Shared by the ingesters of all syncers
+pub struct NewDeviceWatch {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    /// Alerts waiting for the end of the ingest cycle, with their grouping flag
+    pending: Mutex<Vec<(NewDeviceAlert, bool)>>,
+}
+
+impl NewDeviceWatch {
+    pub fn new(app_state: AppState, notifier: Arc<DiscordNotifier>) -> Self {
+        Self {
+            app_state,
+            notifier,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Called for an entry the ingester just inserted
+    pub async fn detected(&self, entry: &UserObjectDetail) {
+        let policy = match NewDevicePolicy::load(&self.app_state.mysql).await {
+            Ok(policy) => policy,
+            Err(e) => {
+                tracing::warn!("[NewDevice] Failed to load policy: {}", e);
+                return;
+            }
+        };
+        if policy.is_quiet(&entry.mac) {
+            tracing::debug!("[NewDevice] {} is on the quiet list", entry.mac);
+            return;
+        }
+        if !policy.matches(entry) {
+            return;
+        }
+
+        let parent_label = self
+            .app_state
+            .mongo
+            .get_user_object_detail_by_id(&entry.parent_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|p| p.label);
+        let alert = NewDeviceAlert::new(entry, parent_label);
+        tracing::info!(
+            "[NewDevice] {} ({}) appeared at {}",
+            alert.label,
+            alert.mac,
+            alert.location()
+        );
+
+        if let Err(e) = self.app_state.mongo.log_new_device(&alert).await {
+            tracing::warn!("[NewDevice] Failed to log security event: {}", e);
+        }
+        let grouped = policy.group_randomized && alert.randomized;
+        self.lock().push((alert, grouped));
+    }
+
+    /// Send queued alerts (end of an ingest cycle)
+    pub async fn flush(&self) {
+        let pending = std::mem::take(&mut *self.lock());
+        if pending.is_empty() {
+            return;
+        }
+
+        let (randomized, individual): (Vec<_>, Vec<_>) =
+            pending.into_iter().partition(|(_, grouped)| *grouped);
+        let randomized: Vec<NewDeviceAlert> = randomized.into_iter().map(|(a, _)| a).collect();
+        let individual: Vec<NewDeviceAlert> = individual.into_iter().map(|(a, _)| a).collect();
+
+        if individual.len() > MAX_INDIVIDUAL_ALERTS {
+            self.notifier
+                .notify_new_devices(&individual, "New devices")
+                .await;
+        } else {
+            for alert in &individual {
+                self.notifier.notify_new_device(alert).await;
+            }
+        }
+        if !randomized.is_empty() {
+            self.notifier
+                .notify_new_devices(&randomized, "Randomized-MAC devices")
+                .await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(NewDeviceAlert, bool)>> {
+        self.pending.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(mac: &str, ssid: Option<&str>) -> UserObjectDetail {
+        serde_json::from_value(serde_json::json!({
+            "id": mac,
+            "mac": mac,
+            "lacis_id": null,
+            "device_type": "NetworkDevice",
+            "parent_id": "40000AABBCCDDEEFF0000",
+            "sort_order": 0,
+            "node_type": "client",
+            "state_type": "online",
+            "label": "phone",
+            "label_customized": false,
+            "claimed_by": null,
+            "ip": "192.168.1.20",
+            "hostname": null,
+            "source": "omada",
+            "source_ref_id": null,
+            "connection_type": "wireless",
+            "product_type": null,
+            "product_code": null,
+            "network_device_type": null,
+            "candidate_lacis_id": null,
+            "fid": "0150",
+            "facility_name": null,
+            "ssid": ssid,
+            "metadata": { "vendor": "Apple" },
+            "aranea_lacis_id": null,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn policy_rules_and_quiet_list() {
+        let policy = NewDevicePolicy::parse(
+            r#"{"rules":[{"ssid":"Guest"},{"node_type":"client","fid":"0999"}],
+                "quiet_macs":["aa:bb:cc","112233445566"]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.quiet_macs, vec!["AABBCC", "112233445566"]);
+        assert!(policy.group_randomized);
+
+        assert!(policy.is_quiet("AA-BB-CC-00-00-01"));
+        assert!(policy.is_quiet("112233445566"));
+        assert!(!policy.is_quiet("112233445567"));
+
+        assert!(policy.matches(&client("001122334455", Some("Guest"))));
+        assert!(!policy.matches(&client("001122334455", Some("Staff"))));
+        assert!(NewDevicePolicy::default().matches(&client("001122334455", None)));
+
+        assert!(NewDevicePolicy::parse(r#"{"quiet_macs":["zz"]}"#).is_err());
+        assert!(NewDevicePolicy::parse(r#"{"rules":[{"ssid_name":"x"}]}"#).is_err());
+    }
+
+    #[test]
+    fn randomized_macs_are_flagged() {
+        assert!(is_locally_administered("DA:A1:19:00:00:01"));
+        assert!(is_locally_administered("f2a1b2c3d4e5"));
+        assert!(!is_locally_administered("00:11:22:33:44:55"));
+
+        let alert = NewDeviceAlert::new(&client("DAA119000001", Some("Guest")), None);
+        assert!(alert.randomized);
+        assert_eq!(alert.vendor.as_deref(), Some("Apple"));
+        assert_eq!(alert.location(), "SSID Guest via 40000AABBCCDDEEFF0000");
+    }
+}
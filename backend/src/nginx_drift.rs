@@ -0,0 +1,410 @@
+//! Drift between LPG's route table and the generated nginx config
+//!
+//! The full proxy config is generated at one point in time from the
+//! template settings. Nginx's server names and timeouts sit in front of every
+//! route, so routes, DDNS hostnames and settings changed afterwards can leave
+//! nginx serving something LPG no longer describes. Generation records the
+//! inputs it used next to the config (`<config>.lpg-inputs.json`); the nginx
+//! status compares them with the current inputs.
+//!
+//! With `nginx_auto_regenerate` enabled, each instance regenerates and
+//! reloads its local nginx once its inputs drift (checked every minute).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use crate::db::mysql::MySqlDb;
+use crate::error::AppError;
+use crate::nginx_stats::config_hash;
+use crate::proxy::ProxyState;
+
+pub const SETTING_AUTO_REGENERATE: &str = "nginx_auto_regenerate";
+const INPUTS_SUFFIX: &str = ".lpg-inputs.json";
+const WATCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Route fields nginx depends on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteInput {
+    pub id: i32,
+    pub path: String,
+    /// DDNS hostname
+    pub host: Option<String>,
+    pub timeout_ms: i32,
+    pub websocket_support: bool,
+}
+
+/// Everything the full proxy config is generated from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NginxInputs {
+    /// Hash of the config the template settings produce
+    pub template_hash: String,
+    /// Active routes by id
+    pub routes: Vec<RouteInput>,
+    /// Lowercased, sorted
+    pub ddns_hostnames: Vec<String>,
+}
+
+impl NginxInputs {
+    pub fn hash(&self) -> String {
+        config_hash(&serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// Inputs recorded when the config was generated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedInputs {
+    pub generated_at: String,
+    pub inputs_hash: String,
+    pub inputs: NginxInputs,
+}
+
+/// Route whose nginx-relevant fields changed
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteInputChange {
+    pub id: i32,
+    pub path: String,
+    pub host: Option<String>,
+    pub changes: Vec<&'static str>,
+}
+
+/// Differences since generation
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NginxInputChanges {
+    pub template_changed: bool,
+    pub routes_added: Vec<RouteInput>,
+    pub routes_changed: Vec<RouteInputChange>,
+    pub routes_removed: Vec<RouteInput>,
+    pub ddns_hostnames_added: Vec<String>,
+    pub ddns_hostnames_removed: Vec<String>,
+}
+
+impl NginxInputChanges {
+    pub fn is_empty(&self) -> bool {
+        !self.template_changed
+            && self.routes_added.is_empty()
+            && self.routes_changed.is_empty()
+            && self.routes_removed.is_empty()
+            && self.ddns_hostnames_added.is_empty()
+            && self.ddns_hostnames_removed.is_empty()
+    }
+
+    /// One line per kind of change, for notifications
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        if self.template_changed {
+            lines.push("template settings changed".to_string());
+        }
+        for (label, count) in [
+            ("route(s) added", self.routes_added.len()),
+            ("route(s) changed", self.routes_changed.len()),
+            ("route(s) removed", self.routes_removed.len()),
+            ("DDNS hostname(s) added", self.ddns_hostnames_added.len()),
+            (
+                "DDNS hostname(s) removed",
+                self.ddns_hostnames_removed.len(),
+            ),
+        ] {
+            if count > 0 {
+                lines.push(format!("{} {}", count, label));
+            }
+        }
+        lines.join(", ")
+    }
+}
+
+/// What changed between the generated and the current inputs
+pub fn compare(generated: &NginxInputs, current: &NginxInputs) -> NginxInputChanges {
+    let mut changes = NginxInputChanges {
+        template_changed: generated.template_hash != current.template_hash,
+        ..Default::default()
+    };
+    for route in &current.routes {
+        let Some(old) = generated.routes.iter().find(|r| r.id == route.id) else {
+            changes.routes_added.push(route.clone());
+            continue;
+        };
+        let mut fields = Vec::new();
+        if old.path != route.path {
+            fields.push("path");
+        }
+        if old.host != route.host {
+            fields.push("host");
+        }
+        if old.timeout_ms != route.timeout_ms {
+            fields.push("timeout_ms");
+        }
+        if old.websocket_support != route.websocket_support {
+            fields.push("websocket_support");
+        }
+        if !fields.is_empty() {
+            changes.routes_changed.push(RouteInputChange {
+                id: route.id,
+                path: route.path.clone(),
+                host: route.host.clone(),
+                changes: fields,
+            });
+        }
+    }
+    changes.routes_removed = generated
+        .routes
+        .iter()
+        .filter(|r| !current.routes.iter().any(|c| c.id == r.id))
+        .cloned()
+        .collect();
+    changes.ddns_hostnames_added = current
+        .ddns_hostnames
+        .iter()
+        .filter(|h| !generated.ddns_hostnames.contains(h))
+        .cloned()
+        .collect();
+    changes.ddns_hostnames_removed = generated
+        .ddns_hostnames
+        .iter()
+        .filter(|h| !current.ddns_hostnames.contains(h))
+        .cloned()
+        .collect();
+    changes
+}
+
+/// Current inputs vs. the recorded ones
+#[derive(Debug, Clone, Serialize)]
+pub struct NginxInputDrift {
+    pub inputs_hash: String,
+    pub generated_inputs_hash: Option<String>,
+    pub generated_at: Option<String>,
+    /// None: not in full proxy mode, or the config was not generated by LPG
+    /// (or before inputs were recorded)
+    pub in_sync: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<NginxInputChanges>,
+}
+
+pub fn drift(generated: Option<&GeneratedInputs>, current: &NginxInputs) -> NginxInputDrift {
+    let inputs_hash = current.hash();
+    let changes = generated.map(|g| compare(&g.inputs, current));
+    NginxInputDrift {
+        in_sync: changes.as_ref().map(NginxInputChanges::is_empty),
+        changes: changes.filter(|c| !c.is_empty()),
+        generated_inputs_hash: generated.map(|g| g.inputs_hash.clone()),
+        generated_at: generated.map(|g| g.generated_at.clone()),
+        inputs_hash,
+    }
+}
+
+/// Inputs as they are now; `template_hash` is the hash of the config the
+/// current template settings generate
+pub async fn current_inputs(db: &MySqlDb, template_hash: String) -> Result<NginxInputs, AppError> {
+    let mut routes: Vec<RouteInput> = db
+        .list_active_routes_with_ddns()
+        .await?
+        .into_iter()
+        .map(|r| RouteInput {
+            id: r.route.id,
+            path: r.route.path,
+            host: r.ddns_hostname.map(|h| h.to_ascii_lowercase()),
+            timeout_ms: r.route.timeout_ms,
+            websocket_support: r.route.websocket_support,
+        })
+        .collect();
+    routes.sort_by_key(|r| r.id);
+    let mut ddns_hostnames: Vec<String> = db
+        .list_ddns()
+        .await?
+        .into_iter()
+        .map(|d| d.hostname.trim().to_ascii_lowercase())
+        .collect();
+    ddns_hostnames.sort();
+    ddns_hostnames.dedup();
+    Ok(NginxInputs {
+        template_hash,
+        routes,
+        ddns_hostnames,
+    })
+}
+
+fn inputs_path(config_path: &str) -> String {
+    format!("{}{}", config_path, INPUTS_SUFFIX)
+}
+
+/// Inputs recorded for the config at `config_path`
+pub async fn load_generated(config_path: &str) -> Option<GeneratedInputs> {
+    let content = tokio::fs::read_to_string(inputs_path(config_path))
+        .await
+        .ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Record the inputs the config at `config_path` was generated from
+pub async fn save_generated(
+    config_path: &str,
+    inputs: NginxInputs,
+) -> Result<GeneratedInputs, String> {
+    let generated = GeneratedInputs {
+        generated_at: Utc::now().to_rfc3339(),
+        inputs_hash: inputs.hash(),
+        inputs,
+    };
+    let content = serde_json::to_string_pretty(&generated).map_err(|e| e.to_string())?;
+    tokio::fs::write(inputs_path(config_path), content)
+        .await
+        .map_err(|e| format!("Failed to record nginx inputs: {}", e))?;
+    Ok(generated)
+}
+
+/// Regenerates the local nginx config when its inputs drift and
+/// `nginx_auto_regenerate` is on (one per instance)
+pub struct NginxDriftWatch {
+    state: ProxyState,
+    /// Inputs whose regeneration failed; not retried until they change
+    failed_hash: Mutex<Option<String>>,
+}
+
+impl NginxDriftWatch {
+    pub fn new(state: ProxyState) -> Self {
+        Self {
+            state,
+            failed_hash: Mutex::new(None),
+        }
+    }
+
+    /// Start the check loop
+    pub async fn start(self: Arc<Self>) {
+        let mut tick = interval(WATCH_INTERVAL);
+        loop {
+            tick.tick().await;
+            if !self
+                .state
+                .app_state
+                .mysql
+                .get_setting_bool(SETTING_AUTO_REGENERATE)
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if let Err(e) = self.check().await {
+                tracing::warn!("Nginx drift check failed: {}", e);
+            }
+        }
+    }
+
+    async fn check(&self) -> Result<(), AppError> {
+        let drift = crate::api::handlers::nginx_input_drift(&self.state).await?;
+        let (Some(false), Some(changes)) = (drift.in_sync, drift.changes) else {
+            return Ok(());
+        };
+        let mut failed_hash = self.failed_hash.lock().await;
+        if failed_hash.as_deref() == Some(drift.inputs_hash.as_str()) {
+            return Ok(());
+        }
+
+        let summary = changes.summary();
+        tracing::info!("Nginx inputs drifted ({}), regenerating config", summary);
+        match crate::api::handlers::apply_full_proxy_config(&self.state).await {
+            Ok(_) => {
+                *failed_hash = None;
+                self.state
+                    .notifier
+                    .notify_config_change(
+                        "Nginx Config Regenerated",
+                        &format!(
+                            "Nginx configuration regenerated automatically after {}.",
+                            summary
+                        ),
+                    )
+                    .await;
+                Ok(())
+            }
+            Err(e) => {
+                *failed_hash = Some(drift.inputs_hash);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(id: i32, path: &str, host: Option<&str>) -> RouteInput {
+        RouteInput {
+            id,
+            path: path.to_string(),
+            host: host.map(str::to_string),
+            timeout_ms: 30000,
+            websocket_support: false,
+        }
+    }
+
+    fn inputs(routes: Vec<RouteInput>, hostnames: &[&str]) -> NginxInputs {
+        NginxInputs {
+            template_hash: "t1".to_string(),
+            routes,
+            ddns_hostnames: hostnames.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn unchanged_inputs_are_in_sync() {
+        let current = inputs(
+            vec![route(1, "/app", Some("a.example.com"))],
+            &["a.example.com"],
+        );
+        let generated = GeneratedInputs {
+            generated_at: "2026-10-01T00:00:00+00:00".to_string(),
+            inputs_hash: current.hash(),
+            inputs: current.clone(),
+        };
+        let drift = drift(Some(&generated), &current);
+        assert_eq!(drift.in_sync, Some(true));
+        assert!(drift.changes.is_none());
+        assert_eq!(
+            drift.generated_inputs_hash.as_deref(),
+            Some(drift.inputs_hash.as_str())
+        );
+    }
+
+    #[test]
+    fn reports_routes_added_changed_and_removed() {
+        let generated = inputs(
+            vec![
+                route(1, "/app", Some("a.example.com")),
+                route(2, "/old", None),
+            ],
+            &["a.example.com"],
+        );
+        let mut changed = route(1, "/app", Some("b.example.com"));
+        changed.timeout_ms = 120000;
+        let mut current = inputs(vec![changed, route(3, "/new", None)], &["b.example.com"]);
+        current.template_hash = "t2".to_string();
+
+        let changes = compare(&generated, &current);
+        assert!(changes.template_changed);
+        assert_eq!(changes.routes_added, vec![route(3, "/new", None)]);
+        assert_eq!(changes.routes_changed.len(), 1);
+        assert_eq!(
+            changes.routes_changed[0].changes,
+            vec!["host", "timeout_ms"]
+        );
+        assert_eq!(changes.routes_removed, vec![route(2, "/old", None)]);
+        assert_eq!(changes.ddns_hostnames_added, vec!["b.example.com"]);
+        assert_eq!(changes.ddns_hostnames_removed, vec!["a.example.com"]);
+        assert_eq!(
+            changes.summary(),
+            "template settings changed, 1 route(s) added, 1 route(s) changed, 1 route(s) removed, 1 DDNS hostname(s) added, 1 DDNS hostname(s) removed"
+        );
+    }
+
+    #[test]
+    fn unrecorded_config_has_unknown_sync() {
+        let drift = drift(None, &inputs(vec![], &[]));
+        assert_eq!(drift.in_sync, None);
+        assert!(drift.generated_at.is_none());
+    }
+}
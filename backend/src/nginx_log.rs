@@ -0,0 +1,529 @@
+//! Access log ingestion from nginx (full proxy mode)
+//!
+//! The full-proxy template makes nginx write one JSON object per request to
+//! `ACCESS_LOG_PATH`. This tailer follows the file and stores the requests
+//! LPG never answered itself — port 80 redirects, ACME challenges, requests
+//! nginx rejected, 502/504s while the backend was down — as access logs with
+//! `source: "nginx"`, so dashboards, alert rules and security detection see
+//! them. Requests nginx passed to LPG on loopback and got a response for are
+//! skipped: the proxy handler has already logged them. Every line is still
+//! counted per `server_name` for the per-site rates of GET /api/nginx/status.
+//!
+//! At startup the file is followed from its end. On rotation (the path
+//! points at a new inode) the old file is drained before the new one is read
+//! from the start; a file truncated in place is also re-read from the start.
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::geoip::GeoInfo;
+use crate::models::AccessLog;
+use crate::proxy::ProxyState;
+
+/// File the full-proxy template points nginx's `access_log` at (`.log` so
+/// the distribution's nginx logrotate rule covers it)
+pub const ACCESS_LOG_PATH: &str = "/var/log/nginx/lpg_access.log";
+
+/// `log_format` of `ACCESS_LOG_PATH` (every value is a string)
+pub const LOG_FORMAT: &str = r#"log_format lpg_json escape=json '{"msec":"$msec","remote_addr":"$remote_addr","method":"$request_method","uri":"$request_uri","host":"$host","server_name":"$server_name","status":"$status","request_time":"$request_time","request_length":"$request_length","bytes_sent":"$bytes_sent","user_agent":"$http_user_agent","referer":"$http_referer","protocol":"$server_protocol","ssl_protocol":"$ssl_protocol","ssl_cipher":"$ssl_cipher","upstream_addr":"$upstream_addr","upstream_status":"$upstream_status","upstream_header_time":"$upstream_header_time"}';"#;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Bytes read per poll; a backlog is worked off over several polls
+const READ_LIMIT: usize = 4 * 1024 * 1024;
+const READ_CHUNK: usize = 64 * 1024;
+/// Access logs per insert
+const BATCH_SIZE: usize = 500;
+
+/// One line of `ACCESS_LOG_PATH`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NginxLogLine {
+    pub msec: String,
+    pub remote_addr: String,
+    pub method: String,
+    pub uri: String,
+    pub host: String,
+    /// `server_name` of the server block that handled the request
+    pub server_name: String,
+    pub status: String,
+    pub request_time: String,
+    pub request_length: String,
+    pub bytes_sent: String,
+    pub user_agent: String,
+    pub referer: String,
+    pub protocol: String,
+    pub ssl_protocol: String,
+    pub ssl_cipher: String,
+    /// Comma-separated when nginx tried several upstreams
+    pub upstream_addr: String,
+    pub upstream_status: String,
+    pub upstream_header_time: String,
+}
+
+/// nginx writes "" or "-" for unset variables
+fn value(s: &str) -> Option<&str> {
+    let s = s.trim();
+    (!s.is_empty() && s != "-").then_some(s)
+}
+
+fn is_loopback(addr: &str) -> bool {
+    let host = addr.trim().rsplit_once(':').map_or(addr, |(host, _)| host);
+    host.trim_matches(|c| c == '[' || c == ']')
+        .parse::<std::net::IpAddr>()
+        .is_ok_and(|ip| ip.is_loopback())
+}
+
+impl NginxLogLine {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        serde_json::from_str(line).map_err(|e| format!("Invalid nginx log line: {}", e))
+    }
+
+    /// Passed to LPG on loopback and LPG sent response headers, i.e. the
+    /// proxy handler logged it
+    pub fn answered_by_lpg(&self) -> bool {
+        let Some(addrs) = value(&self.upstream_addr) else {
+            return false;
+        };
+        addrs.split(',').all(is_loopback)
+            && self
+                .upstream_header_time
+                .split(',')
+                .filter_map(value)
+                .any(|t| t.parse::<f64>().is_ok())
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        let secs: f64 = value(&self.msec)?.parse().ok()?;
+        Utc.timestamp_millis_opt((secs * 1000.0) as i64).single()
+    }
+
+    /// Site the request is counted under: the server block's `server_name`
+    /// (older lines without it fall back to the Host header)
+    pub fn site(&self) -> &str {
+        value(&self.server_name)
+            .or_else(|| value(&self.host))
+            .unwrap_or("_")
+    }
+
+    /// Path without the query string
+    pub fn path(&self) -> &str {
+        self.uri.split('?').next().unwrap_or(&self.uri)
+    }
+
+    /// Map to the access log model; `route` is the LPG route the path
+    /// matches, if any
+    pub fn to_access_log(&self, route: Option<(i32, &str)>, geo: Option<GeoInfo>) -> AccessLog {
+        let upstream = value(&self.upstream_addr);
+        let upstream_error = upstream.filter(|_| !self.answered_by_lpg()).map(|addr| {
+            format!(
+                "nginx: no response from {} (upstream status {})",
+                addr,
+                value(&self.upstream_status).unwrap_or("-")
+            )
+        });
+        let geo = geo.unwrap_or_default();
+
+        AccessLog {
+            id: None,
+            timestamp: self.timestamp().unwrap_or_else(Utc::now),
+            ip: crate::ip::canonical_ip(&self.remote_addr),
+            method: self.method.clone(),
+            path: self.uri.clone(),
+            route_id: route.map(|(id, _)| id),
+            target: route
+                .map(|(_, target)| target.to_string())
+                .or_else(|| upstream.map(str::to_string)),
+            status: self.status.parse().unwrap_or(0),
+            response_time_ms: self
+                .request_time
+                .parse::<f64>()
+                .map(|s| (s * 1000.0).round() as i32)
+                .unwrap_or(0),
+            request_size: self.request_length.parse().ok(),
+            response_size: self.bytes_sent.parse().ok(),
+            user_agent: value(&self.user_agent).map(str::to_string),
+            referer: value(&self.referer).map(str::to_string),
+            country_code: geo.country_code,
+            country: geo.country,
+            city: geo.city,
+            latitude: geo.latitude,
+            longitude: geo.longitude,
+            upstream_error,
+            http_version: value(&self.protocol).map(str::to_string),
+            tls_version: value(&self.ssl_protocol).map(str::to_string),
+            tls_cipher: value(&self.ssl_cipher).map(str::to_string),
+            source: Some(AccessLog::SOURCE_NGINX.to_string()),
+            custom_fields: None,
+            grpc_method: None,
+            grpc_status: None,
+            session_duration_ms: None,
+        }
+    }
+}
+
+/// Split complete lines off `buf`, leaving a trailing partial line in it
+fn take_lines(buf: &mut Vec<u8>) -> Vec<String> {
+    let Some(end) = buf.iter().rposition(|b| *b == b'\n') else {
+        return Vec::new();
+    };
+    let rest = buf.split_off(end + 1);
+    let complete = std::mem::replace(buf, rest);
+    String::from_utf8_lossy(&complete)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Tailer health (GET /api/nginx/status)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NginxLogIngestStatus {
+    pub path: String,
+    /// The file exists and is being followed
+    pub following: bool,
+    /// Bytes read from the current file
+    pub offset: u64,
+    /// Bytes nginx has written that are not read yet
+    pub lag_bytes: u64,
+    /// Delay between nginx logging the newest ingested entry and its insert
+    pub ingest_delay_ms: Option<i64>,
+    pub last_entry_at: Option<DateTime<Utc>>,
+    pub last_poll_at: Option<DateTime<Utc>>,
+    pub lines_read: u64,
+    pub ingested: u64,
+    /// Entries LPG answered (and logged) itself
+    pub skipped_lpg: u64,
+    pub parse_errors: u64,
+    pub rotations: u64,
+    pub last_error: Option<String>,
+}
+
+/// Position in the followed file
+#[derive(Default)]
+struct Cursor {
+    file: Option<(File, u64)>,
+    offset: u64,
+    partial: Vec<u8>,
+    /// Set after the first poll; files found later are read from the start
+    primed: bool,
+}
+
+/// Follows the nginx JSON access log (one per instance: each reads its
+/// local nginx)
+pub struct NginxLogTailer {
+    path: String,
+    status: Mutex<NginxLogIngestStatus>,
+    /// Lines read per site since startup, including those LPG answered
+    sites: Mutex<HashMap<String, u64>>,
+}
+
+impl Default for NginxLogTailer {
+    fn default() -> Self {
+        Self::new(ACCESS_LOG_PATH)
+    }
+}
+
+impl NginxLogTailer {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            status: Mutex::new(NginxLogIngestStatus {
+                path: path.to_string(),
+                ..Default::default()
+            }),
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn status(&self) -> NginxLogIngestStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Requests per site (`NginxLogLine::site`) since startup
+    pub fn site_requests(&self) -> HashMap<String, u64> {
+        self.sites.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut NginxLogIngestStatus)) {
+        f(&mut self.status.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    pub async fn start(&self, state: ProxyState) {
+        tracing::info!("nginx access log tailer following {}", self.path);
+        let mut cursor = Cursor::default();
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = self.poll(&state, &mut cursor).await;
+            let previous = self.status().last_error;
+            if let Err(e) = &result {
+                if previous.as_ref() != Some(e) {
+                    tracing::warn!("nginx access log ingestion: {}", e);
+                }
+            }
+            self.update(|s| {
+                s.last_poll_at = Some(Utc::now());
+                s.last_error = result.err();
+            });
+        }
+    }
+
+    async fn poll(&self, state: &ProxyState, cursor: &mut Cursor) -> Result<(), String> {
+        let meta = match tokio::fs::metadata(&self.path).await {
+            Ok(meta) => Some(meta),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("stat {}: {}", self.path, e)),
+        };
+
+        // Rotated: drain what nginx wrote to the old file before switching
+        let rotated = match (&cursor.file, &meta) {
+            (Some((_, inode)), Some(meta)) => meta.ino() != *inode,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if rotated {
+            self.read_available(state, cursor).await?;
+            cursor.file = None;
+            cursor.partial.clear();
+            self.update(|s| s.rotations += 1);
+        }
+
+        let Some(meta) = meta else {
+            cursor.primed = true;
+            self.update(|s| {
+                s.following = false;
+                s.offset = 0;
+                s.lag_bytes = 0;
+            });
+            return Ok(());
+        };
+
+        match &mut cursor.file {
+            None => {
+                let mut file = File::open(&self.path)
+                    .await
+                    .map_err(|e| format!("open {}: {}", self.path, e))?;
+                cursor.offset = if cursor.primed {
+                    0
+                } else {
+                    file.seek(SeekFrom::End(0))
+                        .await
+                        .map_err(|e| format!("seek {}: {}", self.path, e))?
+                };
+                cursor.file = Some((file, meta.ino()));
+            }
+            // Truncated in place (copytruncate)
+            Some((file, _)) if meta.len() < cursor.offset => {
+                file.seek(SeekFrom::Start(0))
+                    .await
+                    .map_err(|e| format!("seek {}: {}", self.path, e))?;
+                cursor.offset = 0;
+                cursor.partial.clear();
+                self.update(|s| s.rotations += 1);
+            }
+            Some(_) => {}
+        }
+        cursor.primed = true;
+
+        let result = self.read_available(state, cursor).await;
+        let offset = cursor.offset;
+        self.update(|s| {
+            s.following = true;
+            s.offset = offset;
+            s.lag_bytes = meta.len().saturating_sub(offset);
+        });
+        result
+    }
+
+    /// Read up to `READ_LIMIT` bytes of the current file and ingest the
+    /// complete lines
+    async fn read_available(&self, state: &ProxyState, cursor: &mut Cursor) -> Result<(), String> {
+        let Some((file, _)) = cursor.file.as_mut() else {
+            return Ok(());
+        };
+        let mut chunk = vec![0u8; READ_CHUNK];
+        let mut read_total = 0;
+        while read_total < READ_LIMIT {
+            let n = file
+                .read(&mut chunk)
+                .await
+                .map_err(|e| format!("read {}: {}", self.path, e))?;
+            if n == 0 {
+                break;
+            }
+            read_total += n;
+            cursor.offset += n as u64;
+            cursor.partial.extend_from_slice(&chunk[..n]);
+        }
+
+        let lines = take_lines(&mut cursor.partial);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        self.ingest(state, &lines).await
+    }
+
+    async fn ingest(&self, state: &ProxyState, lines: &[String]) -> Result<(), String> {
+        let mut logs = Vec::new();
+        let mut skipped = 0;
+        let mut parse_errors = 0;
+        let mut sites: HashMap<String, u64> = HashMap::new();
+        {
+            let router = state.router.read().await;
+            for line in lines {
+                let entry = match NginxLogLine::parse(line) {
+                    Ok(entry) => entry,
+                    Err(_) => {
+                        parse_errors += 1;
+                        continue;
+                    }
+                };
+                *sites.entry(entry.site().to_string()).or_default() += 1;
+                if entry.answered_by_lpg() {
+                    skipped += 1;
+                    continue;
+                }
+                let route = router
+                    .match_route(entry.path(), value(&entry.host))
+                    .map(|r| (r.id, r.target.as_str()));
+                let geo = state
+                    .geoip
+                    .as_ref()
+                    .and_then(|reader| reader.lookup(&entry.remote_addr));
+                logs.push(entry.to_access_log(route, geo));
+            }
+        }
+
+        let newest = logs.iter().map(|l| l.timestamp).max();
+        self.update(|s| {
+            s.lines_read += lines.len() as u64;
+            s.skipped_lpg += skipped;
+            s.parse_errors += parse_errors;
+        });
+        {
+            let mut totals = self.sites.lock().unwrap_or_else(|e| e.into_inner());
+            for (site, n) in sites {
+                *totals.entry(site).or_default() += n;
+            }
+        }
+
+        for batch in logs.chunks(BATCH_SIZE) {
+            state
+                .app_state
+                .mongo
+                .log_access_batch(batch)
+                .await
+                .map_err(|e| format!("insert access logs: {}", e))?;
+            self.update(|s| s.ingested += batch.len() as u64);
+        }
+        if let Some(newest) = newest {
+            self.update(|s| {
+                s.last_entry_at = Some(newest);
+                s.ingest_delay_ms = Some((Utc::now() - newest).num_milliseconds());
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(upstream_addr: &str, upstream_header_time: &str) -> NginxLogLine {
+        NginxLogLine {
+            msec: "1712345678.250".to_string(),
+            remote_addr: "203.0.113.7".to_string(),
+            method: "GET".to_string(),
+            uri: "/app/index.html?x=1".to_string(),
+            host: "gw.example.com".to_string(),
+            server_name: "gw.example.com".to_string(),
+            status: "502".to_string(),
+            request_time: "0.012".to_string(),
+            request_length: "420".to_string(),
+            bytes_sent: "157".to_string(),
+            user_agent: "curl/8.0".to_string(),
+            referer: "-".to_string(),
+            protocol: "HTTP/2.0".to_string(),
+            ssl_protocol: "TLSv1.3".to_string(),
+            ssl_cipher: "TLS_AES_128_GCM_SHA256".to_string(),
+            upstream_addr: upstream_addr.to_string(),
+            upstream_status: "502".to_string(),
+            upstream_header_time: upstream_header_time.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_the_template_log_format() {
+        let raw = r#"{"msec":"1712345678.250","remote_addr":"203.0.113.7","method":"GET","uri":"/","host":"gw","status":"301","request_time":"0.000","request_length":"75","bytes_sent":"162","user_agent":"","referer":"","protocol":"HTTP/1.1","ssl_protocol":"","ssl_cipher":"","upstream_addr":"","upstream_status":"","upstream_header_time":""}"#;
+        let entry = NginxLogLine::parse(raw).unwrap();
+        assert_eq!(entry.status, "301");
+        assert_eq!(
+            entry.timestamp().unwrap().timestamp_millis(),
+            1_712_345_678_250
+        );
+        assert!(!entry.answered_by_lpg());
+        // Written before server_name was part of the format
+        assert_eq!(entry.site(), "gw");
+        assert!(NginxLogLine::parse("not json").is_err());
+        assert_eq!(line("", "").site(), "gw.example.com");
+    }
+
+    #[test]
+    fn only_loopback_responses_count_as_answered_by_lpg() {
+        assert!(line("127.0.0.1:8081", "0.004").answered_by_lpg());
+        assert!(line("[::1]:8081", "0.004").answered_by_lpg());
+        // LPG down: nginx got no response headers
+        assert!(!line("127.0.0.1:8081", "-").answered_by_lpg());
+        // A retry that finally got headers
+        assert!(line("127.0.0.1:8081, 127.0.0.1:8081", "-, 0.010").answered_by_lpg());
+        // Served by nginx itself, or by another upstream
+        assert!(!line("", "").answered_by_lpg());
+        assert!(!line("192.0.2.10:80", "0.004").answered_by_lpg());
+    }
+
+    #[test]
+    fn maps_to_an_nginx_access_log() {
+        let log = line("127.0.0.1:8081", "-").to_access_log(Some((4, "http://app:3000")), None);
+        assert_eq!(log.source.as_deref(), Some(AccessLog::SOURCE_NGINX));
+        assert_eq!(log.route_id, Some(4));
+        assert_eq!(log.target.as_deref(), Some("http://app:3000"));
+        assert_eq!(log.path, "/app/index.html?x=1");
+        assert_eq!(log.status, 502);
+        assert_eq!(log.response_time_ms, 12);
+        assert_eq!(log.request_size, Some(420));
+        assert_eq!(log.response_size, Some(157));
+        assert_eq!(log.referer, None);
+        assert_eq!(log.tls_version.as_deref(), Some("TLSv1.3"));
+        assert!(log.upstream_error.unwrap().contains("127.0.0.1:8081"));
+
+        let log = line("", "").to_access_log(None, None);
+        assert_eq!(log.route_id, None);
+        assert_eq!(log.target, None);
+        assert_eq!(log.upstream_error, None);
+        assert_eq!(line("", "").path(), "/app/index.html");
+    }
+
+    #[test]
+    fn keeps_partial_lines_for_the_next_read() {
+        let mut buf = b"{\"a\":1}\n{\"b\":2}\n{\"c\"".to_vec();
+        assert_eq!(take_lines(&mut buf), vec!["{\"a\":1}", "{\"b\":2}"]);
+        assert_eq!(buf, b"{\"c\"");
+        assert!(take_lines(&mut buf).is_empty());
+        buf.extend_from_slice(b":3}\n");
+        assert_eq!(take_lines(&mut buf), vec!["{\"c\":3}"]);
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,446 @@
+//! nginx runtime metrics (GET /api/nginx/status)
+//!
+//! The full-proxy template adds a `stub_status` server bound to
+//! `STUB_STATUS_LISTEN`. Every `SAMPLE_INTERVAL` this instance scrapes it and
+//! snapshots the per-site request counters of the access log tailer into a
+//! ring buffer covering `WINDOW`, from which requests per second over the
+//! last minute are derived.
+//!
+//! It also remembers the last reload LPG performed and the hash of the
+//! config file at that moment, so the status endpoint can tell whether the
+//! file on disk has changed since (nginx is running something else). Both
+//! are per instance and in memory: after a restart the running config is
+//! unknown until the next reload.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::nginx_log::NginxLogTailer;
+
+/// Address of the `stub_status` server in the generated config (loopback only)
+pub const STUB_STATUS_LISTEN: &str = "127.0.0.1:8089";
+pub const STUB_STATUS_PATH: &str = "/nginx_status";
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Span of the ring buffer the rates are computed over
+const WINDOW: Duration = Duration::from_secs(60);
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `stub_status` server block of the generated config
+pub fn stub_status_server_block() -> String {
+    format!(
+        r#"# nginx metrics for LacisProxyGateway2 (loopback only)
+server {{
+    listen {STUB_STATUS_LISTEN};
+    server_name localhost;
+    access_log off;
+
+    location = {STUB_STATUS_PATH} {{
+        stub_status;
+        allow 127.0.0.1;
+        deny all;
+    }}
+}}"#
+    )
+}
+
+/// SHA-256 of a config file (hex)
+pub fn config_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+/// One `stub_status` page
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StubStatus {
+    pub active_connections: u64,
+    pub accepts: u64,
+    pub handled: u64,
+    pub requests: u64,
+    pub reading: u64,
+    pub writing: u64,
+    pub waiting: u64,
+}
+
+impl StubStatus {
+    /// Parse the plain-text page:
+    ///
+    /// ```text
+    /// Active connections: 2
+    /// server accepts handled requests
+    ///  16 16 31
+    /// Reading: 0 Writing: 1 Waiting: 1
+    /// ```
+    pub fn parse(body: &str) -> Result<Self, String> {
+        let mut status = StubStatus::default();
+        let mut lines = body.lines().map(str::trim).filter(|l| !l.is_empty());
+
+        let active = lines
+            .next()
+            .and_then(|l| l.strip_prefix("Active connections:"))
+            .ok_or("missing \"Active connections\"")?;
+        status.active_connections = number(active)?;
+
+        if lines.next() != Some("server accepts handled requests") {
+            return Err("missing \"server accepts handled requests\"".to_string());
+        }
+        let counters = lines
+            .next()
+            .ok_or("missing accepts/handled/requests")?
+            .split_whitespace()
+            .map(number)
+            .collect::<Result<Vec<_>, _>>()?;
+        let [accepts, handled, requests] = counters[..] else {
+            return Err("expected three counters after \"server accepts handled requests\"".into());
+        };
+        status.accepts = accepts;
+        status.handled = handled;
+        status.requests = requests;
+
+        let states = lines.next().ok_or("missing Reading/Writing/Waiting")?;
+        let mut tokens = states.split_whitespace();
+        while let Some(label) = tokens.next() {
+            let value = number(
+                tokens
+                    .next()
+                    .ok_or("missing value after connection state")?,
+            )?;
+            match label {
+                "Reading:" => status.reading = value,
+                "Writing:" => status.writing = value,
+                "Waiting:" => status.waiting = value,
+                _ => return Err(format!("unexpected connection state {}", label)),
+            }
+        }
+        Ok(status)
+    }
+}
+
+fn number(s: &str) -> Result<u64, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("invalid number {:?}", s.trim()))
+}
+
+/// One entry of the ring buffer
+#[derive(Debug, Clone)]
+struct Sample {
+    at: DateTime<Utc>,
+    /// `stub_status` request counter (None when the scrape failed)
+    requests: Option<u64>,
+    /// Access log lines per site since startup
+    sites: HashMap<String, u64>,
+}
+
+/// Growth of a counter over consecutive readings; a reading below the
+/// previous one is a reset (nginx restarted, LPG restarted) and counts from 0
+fn counter_growth(values: impl Iterator<Item = u64>) -> u64 {
+    let mut total = 0;
+    let mut previous: Option<u64> = None;
+    for value in values {
+        if let Some(previous) = previous {
+            total += if value >= previous {
+                value - previous
+            } else {
+                value
+            };
+        }
+        previous = Some(value);
+    }
+    total
+}
+
+/// Requests per second between the first and last of `samples` with a value
+fn rate(samples: &[(DateTime<Utc>, u64)]) -> Option<f64> {
+    let (first, last) = (samples.first()?, samples.last()?);
+    let secs = (last.0 - first.0).num_milliseconds() as f64 / 1000.0;
+    if secs <= 0.0 {
+        return None;
+    }
+    Some(counter_growth(samples.iter().map(|(_, v)| *v)) as f64 / secs)
+}
+
+/// Requests of one site (`server_name`) from the access log
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteRequests {
+    pub server_name: String,
+    /// Since this instance started following the log
+    pub requests: u64,
+    /// Over the last minute
+    pub requests_per_second: Option<f64>,
+}
+
+/// Runtime metrics part of GET /api/nginx/status
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NginxMetrics {
+    pub stub_status_url: String,
+    /// Latest successful scrape
+    pub stub_status: Option<StubStatus>,
+    pub scraped_at: Option<DateTime<Utc>>,
+    /// Error of the latest scrape (e.g. the config has no stub_status server)
+    pub scrape_error: Option<String>,
+    /// Over `window_secs` (None until two scrapes succeeded)
+    pub requests_per_second: Option<f64>,
+    pub window_secs: u64,
+    /// Busiest first; empty unless nginx writes the JSON access log
+    pub sites: Vec<SiteRequests>,
+}
+
+/// Last reload performed by LPG
+#[derive(Debug, Clone, Serialize)]
+pub struct NginxReload {
+    pub at: DateTime<Utc>,
+    pub config_path: Option<String>,
+    /// Hash of the config file when nginx was reloaded
+    pub config_hash: Option<String>,
+}
+
+/// Samples `stub_status` and the per-site counters (one per instance: each
+/// watches its local nginx)
+pub struct NginxStats {
+    client: reqwest::Client,
+    url: String,
+    latest: Mutex<NginxMetrics>,
+    samples: Mutex<VecDeque<Sample>>,
+    last_reload: Mutex<Option<NginxReload>>,
+}
+
+impl Default for NginxStats {
+    fn default() -> Self {
+        Self::new(&format!(
+            "http://{}{}",
+            STUB_STATUS_LISTEN, STUB_STATUS_PATH
+        ))
+    }
+}
+
+impl NginxStats {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(SCRAPE_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            url: url.to_string(),
+            latest: Mutex::new(NginxMetrics {
+                stub_status_url: url.to_string(),
+                window_secs: WINDOW.as_secs(),
+                ..Default::default()
+            }),
+            samples: Mutex::new(VecDeque::new()),
+            last_reload: Mutex::new(None),
+        }
+    }
+
+    /// Latest scrape plus the rates over the ring buffer
+    pub fn metrics(&self) -> NginxMetrics {
+        let mut metrics = self
+            .latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let samples: Vec<Sample> = self
+            .samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect();
+
+        let requests: Vec<_> = samples
+            .iter()
+            .filter_map(|s| s.requests.map(|r| (s.at, r)))
+            .collect();
+        metrics.requests_per_second = rate(&requests);
+
+        if let Some(newest) = samples.last() {
+            let mut sites: Vec<SiteRequests> = newest
+                .sites
+                .iter()
+                .map(|(site, total)| {
+                    let series: Vec<_> = samples
+                        .iter()
+                        .map(|s| (s.at, s.sites.get(site).copied().unwrap_or(0)))
+                        .collect();
+                    SiteRequests {
+                        server_name: site.clone(),
+                        requests: *total,
+                        requests_per_second: rate(&series),
+                    }
+                })
+                .collect();
+            sites.sort_by(|a, b| {
+                b.requests
+                    .cmp(&a.requests)
+                    .then_with(|| a.server_name.cmp(&b.server_name))
+            });
+            metrics.sites = sites;
+        }
+        metrics
+    }
+
+    pub fn last_reload(&self) -> Option<NginxReload> {
+        self.last_reload
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Record a successful reload of the config at `config_path`
+    pub async fn record_reload(&self, config_path: Option<String>) {
+        let config_hash = match &config_path {
+            Some(path) => tokio::fs::read_to_string(path)
+                .await
+                .ok()
+                .map(|content| config_hash(&content)),
+            None => None,
+        };
+        *self.last_reload.lock().unwrap_or_else(|e| e.into_inner()) = Some(NginxReload {
+            at: Utc::now(),
+            config_path,
+            config_hash,
+        });
+    }
+
+    async fn scrape(&self) -> Result<StubStatus, String> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .map_err(|e| format!("GET {}: {}", self.url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("GET {}: HTTP {}", self.url, response.status()));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("read {}: {}", self.url, e))?;
+        StubStatus::parse(&body)
+    }
+
+    fn push(&self, sample: Sample) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let cutoff = sample.at - chrono::Duration::from_std(WINDOW).unwrap_or_default();
+        samples.push_back(sample);
+        while samples.front().is_some_and(|s| s.at < cutoff) {
+            samples.pop_front();
+        }
+    }
+
+    pub async fn start(self: Arc<Self>, nginx_log: Arc<NginxLogTailer>) {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = self.scrape().await;
+            let now = Utc::now();
+
+            let previous_error = {
+                let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+                let previous = latest.scrape_error.take();
+                match &result {
+                    Ok(status) => {
+                        latest.stub_status = Some(status.clone());
+                        latest.scraped_at = Some(now);
+                    }
+                    Err(e) => latest.scrape_error = Some(e.clone()),
+                }
+                previous
+            };
+            if let Err(e) = &result {
+                if previous_error.as_ref() != Some(e) {
+                    tracing::debug!("nginx stub_status scrape failed: {}", e);
+                }
+            }
+
+            self.push(Sample {
+                at: now,
+                requests: result.ok().map(|s| s.requests),
+                sites: nginx_log.site_requests(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "Active connections: 291 \nserver accepts handled requests\n 16630948 16630948 31070465 \nReading: 6 Writing: 179 Waiting: 106 \n";
+
+    #[test]
+    fn parses_stub_status() {
+        assert_eq!(
+            StubStatus::parse(PAGE).unwrap(),
+            StubStatus {
+                active_connections: 291,
+                accepts: 16_630_948,
+                handled: 16_630_948,
+                requests: 31_070_465,
+                reading: 6,
+                writing: 179,
+                waiting: 106,
+            }
+        );
+        assert!(StubStatus::parse("").is_err());
+        assert!(StubStatus::parse("<html>404 Not Found</html>").is_err());
+        assert!(StubStatus::parse(&PAGE.replace("31070465", "x")).is_err());
+    }
+
+    #[test]
+    fn rates_survive_counter_resets() {
+        let t0 = Utc::now();
+        let at = |secs: i64| t0 + chrono::Duration::seconds(secs);
+        assert_eq!(rate(&[]), None);
+        assert_eq!(rate(&[(t0, 10)]), None);
+        assert_eq!(
+            rate(&[(at(0), 100), (at(10), 150), (at(20), 300)]),
+            Some(10.0)
+        );
+        // nginx restarted between the 2nd and 3rd reading
+        assert_eq!(
+            rate(&[(at(0), 100), (at(10), 150), (at(20), 50)]),
+            Some(5.0)
+        );
+    }
+
+    #[test]
+    fn ring_buffer_keeps_one_window_and_rates_sites() {
+        let stats = NginxStats::default();
+        let t0 = Utc::now() - chrono::Duration::seconds(120);
+        for i in 0..25i64 {
+            stats.push(Sample {
+                at: t0 + chrono::Duration::seconds(i * 5),
+                requests: Some(i as u64 * 50),
+                sites: HashMap::from([
+                    ("a.example.com".to_string(), i as u64 * 20),
+                    ("b.example.com".to_string(), 7),
+                ]),
+            });
+        }
+        assert_eq!(stats.samples.lock().unwrap().len(), 13);
+
+        let metrics = stats.metrics();
+        assert_eq!(metrics.requests_per_second, Some(10.0));
+        assert_eq!(metrics.window_secs, 60);
+        assert_eq!(metrics.sites.len(), 2);
+        assert_eq!(metrics.sites[0].server_name, "a.example.com");
+        assert_eq!(metrics.sites[0].requests, 480);
+        assert_eq!(metrics.sites[0].requests_per_second, Some(4.0));
+        assert_eq!(metrics.sites[1].requests_per_second, Some(0.0));
+    }
+
+    #[test]
+    fn stub_status_server_is_loopback_only() {
+        let block = stub_status_server_block();
+        assert!(block.contains("listen 127.0.0.1:8089;"));
+        assert!(block.contains("location = /nginx_status {"));
+        assert!(block.contains("deny all;"));
+        assert_ne!(config_hash("a"), config_hash("b"));
+        assert_eq!(config_hash("a").len(), 64);
+    }
+}
@@ -0,0 +1,258 @@
+//! One-time cleanup of topology nodes whose _id differs only by MAC formatting
+//!
+//! Before MAC inputs were validated, nodes could be created under ids like
+//! "aa:bb:cc:dd:ee:ff" that never match the canonical "AABBCCDDEEFF" used by
+//! ingestion, leaving two nodes for one device. The planner groups nodes by
+//! canonical id and merges each group into a single node under that id,
+//! keeping the custom label, claim and lacisIDs from whichever duplicate has
+//! them. Children of removed ids are moved to the canonical id.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::db::mongo::user_object_detail::UserObjectDetail;
+use crate::db::mongo::MongoDb;
+use crate::mac::canonical_node_id;
+
+/// One group of duplicates and the node that replaces it
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMerge {
+    pub canonical_id: String,
+    /// Ids deleted by the merge (the canonical id is rewritten in place)
+    pub removed_ids: Vec<String>,
+    /// Node whose volatile fields are kept
+    pub base_id: String,
+    /// Where the preserved user data came from
+    pub label_from: Option<String>,
+    pub claim_from: Option<String>,
+    pub lacis_id_from: Option<String>,
+    /// Children whose parent_id moves to the canonical id
+    pub reparented: Vec<String>,
+    /// Data that could not be kept (e.g. a second, different claim)
+    pub dropped: Vec<String>,
+    #[serde(skip)]
+    pub merged: UserObjectDetail,
+}
+
+/// Plan merges for every canonical id that has a non-canonical duplicate
+pub fn plan_mac_merges(nodes: &[UserObjectDetail]) -> Vec<NodeMerge> {
+    let mut groups: BTreeMap<String, Vec<&UserObjectDetail>> = BTreeMap::new();
+    for node in nodes {
+        groups
+            .entry(canonical_node_id(&node.id))
+            .or_default()
+            .push(node);
+    }
+
+    groups
+        .into_iter()
+        .filter(|(canonical, members)| members.iter().any(|n| n.id != *canonical))
+        .map(|(canonical, members)| merge_group(canonical, members, nodes))
+        .collect()
+}
+
+fn merge_group(
+    canonical: String,
+    mut members: Vec<&UserObjectDetail>,
+    nodes: &[UserObjectDetail],
+) -> NodeMerge {
+    // The ingested (canonical) node first, then the most recently updated
+    members.sort_by(|a, b| {
+        (b.id == canonical)
+            .cmp(&(a.id == canonical))
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
+    let base = members[0];
+    let mut merged = base.clone();
+    merged.id = canonical.clone();
+    merged.mac = canonical_node_id(&base.mac);
+    // A non-canonical parent is re-keyed by its own merge
+    merged.parent_id = canonical_node_id(&base.parent_id);
+
+    let mut label_from = base.label_customized.then(|| base.id.clone());
+    let mut claim_from = base.claimed_by.as_ref().map(|_| base.id.clone());
+    let mut lacis_id_from = base.lacis_id.as_ref().map(|_| base.id.clone());
+    let mut dropped = Vec::new();
+
+    for other in &members[1..] {
+        if other.label_customized && !merged.label_customized {
+            merged.label = other.label.clone();
+            merged.label_customized = true;
+            label_from = Some(other.id.clone());
+        } else if other.label_customized && other.label != merged.label {
+            dropped.push(format!("label '{}' from {}", other.label, other.id));
+        }
+
+        match (&merged.claimed_by, &other.claimed_by) {
+            (None, Some(claim)) => {
+                merged.claimed_by = Some(claim.clone());
+                claim_from = Some(other.id.clone());
+            }
+            (Some(kept), Some(claim)) if kept.lacis_id != claim.lacis_id => {
+                dropped.push(format!(
+                    "claim by {} ({}) from {}",
+                    claim.display_name, claim.lacis_id, other.id
+                ));
+            }
+            _ => {}
+        }
+
+        if merged.lacis_id.is_none() && other.lacis_id.is_some() {
+            merged.lacis_id = other.lacis_id.clone();
+            lacis_id_from = Some(other.id.clone());
+        }
+        if merged.aranea_lacis_id.is_none() {
+            merged.aranea_lacis_id = other.aranea_lacis_id.clone();
+        }
+        if merged.candidate_lacis_id.is_none() {
+            merged.candidate_lacis_id = other.candidate_lacis_id.clone();
+        }
+        if other.created_at < merged.created_at {
+            merged.created_at = other.created_at.clone();
+        }
+    }
+
+    let removed_ids: Vec<String> = members
+        .iter()
+        .map(|n| n.id.clone())
+        .filter(|id| *id != canonical)
+        .collect();
+    // Duplicates among the children get their parent fixed by their own merge
+    let reparented = nodes
+        .iter()
+        .filter(|n| removed_ids.contains(&n.parent_id) && n.id == canonical_node_id(&n.id))
+        .map(|n| n.id.clone())
+        .collect();
+
+    NodeMerge {
+        canonical_id: canonical,
+        removed_ids,
+        base_id: base.id.clone(),
+        label_from,
+        claim_from,
+        lacis_id_from,
+        reparented,
+        dropped,
+        merged,
+    }
+}
+
+/// Write one planned merge: the merged node replaces the canonical id, the
+/// duplicates are deleted and references move over
+pub async fn apply_merge(mongo: &MongoDb, merge: &NodeMerge) -> Result<(), String> {
+    mongo.replace_user_object_detail(&merge.merged).await?;
+    for old_id in &merge.removed_ids {
+        mongo.delete_user_object_detail(old_id).await?;
+        let _ = mongo
+            .migrate_node_position_id(old_id, &merge.canonical_id)
+            .await;
+        let _ = mongo
+            .migrate_collapsed_node_id(old_id, &merge.canonical_id)
+            .await;
+    }
+    for child in &merge.reparented {
+        mongo
+            .update_user_object_detail_parent(child, &merge.canonical_id)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, parent: &str, updated_at: &str) -> UserObjectDetail {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "mac": id,
+            "lacis_id": null,
+            "device_type": "NetworkDevice",
+            "parent_id": parent,
+            "sort_order": 0,
+            "node_type": "client",
+            "state_type": "online",
+            "label": "auto",
+            "label_customized": false,
+            "ip": null,
+            "hostname": null,
+            "source": "omada",
+            "source_ref_id": null,
+            "connection_type": "wireless",
+            "product_type": null,
+            "product_code": null,
+            "network_device_type": null,
+            "candidate_lacis_id": null,
+            "fid": null,
+            "facility_name": null,
+            "ssid": null,
+            "metadata": {},
+            "aranea_lacis_id": null,
+            "created_at": "2026-01-01T00:00:00+00:00",
+            "updated_at": updated_at,
+        }))
+        .unwrap()
+    }
+
+    fn claim(lacis_id: &str) -> crate::db::mongo::user_object_detail::NodeClaim {
+        serde_json::from_value(serde_json::json!({
+            "lacis_id": lacis_id,
+            "display_name": lacis_id,
+            "claimed_at": "2026-01-01T00:00:00+00:00",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merges_user_data_into_canonical_node() {
+        let canonical = node("AABBCCDDEEFF", "GW", "2026-03-01T00:00:00+00:00");
+        let mut colon = node("aa:bb:cc:dd:ee:ff", "GW", "2026-02-01T00:00:00+00:00");
+        colon.label = "Alice laptop".to_string();
+        colon.label_customized = true;
+        colon.claimed_by = Some(claim("alice"));
+        let mut dash = node("aa-bb-cc-dd-ee-ff", "GW", "2026-01-15T00:00:00+00:00");
+        dash.lacis_id = Some("4001AABBCCDDEEFF0000".to_string());
+        dash.claimed_by = Some(claim("bob"));
+        let child = node(
+            "F2000000000A",
+            "aa:bb:cc:dd:ee:ff",
+            "2026-01-01T00:00:00+00:00",
+        );
+        let other = node("112233445566", "GW", "2026-01-01T00:00:00+00:00");
+
+        let merges = plan_mac_merges(&[canonical, colon, dash, child, other]);
+        assert_eq!(merges.len(), 1);
+        let merge = &merges[0];
+        assert_eq!(merge.canonical_id, "AABBCCDDEEFF");
+        assert_eq!(merge.base_id, "AABBCCDDEEFF");
+        assert_eq!(
+            merge.removed_ids,
+            vec!["aa:bb:cc:dd:ee:ff", "aa-bb-cc-dd-ee-ff"]
+        );
+        assert_eq!(merge.merged.label, "Alice laptop");
+        assert!(merge.merged.label_customized);
+        assert_eq!(
+            merge
+                .merged
+                .claimed_by
+                .as_ref()
+                .map(|c| c.lacis_id.as_str()),
+            Some("alice")
+        );
+        assert_eq!(merge.lacis_id_from.as_deref(), Some("aa-bb-cc-dd-ee-ff"));
+        assert_eq!(merge.reparented, vec!["F2000000000A"]);
+        assert_eq!(merge.dropped.len(), 1);
+    }
+
+    #[test]
+    fn lone_non_canonical_node_is_rekeyed() {
+        let merges = plan_mac_merges(&[node("aa:bb:cc:dd:ee:ff", "GW", "2026-01-01")]);
+        assert_eq!(merges.len(), 1);
+        assert_eq!(merges[0].merged.id, "AABBCCDDEEFF");
+        assert_eq!(merges[0].merged.mac, "AABBCCDDEEFF");
+        assert_eq!(merges[0].removed_ids, vec!["aa:bb:cc:dd:ee:ff"]);
+
+        assert!(plan_mac_merges(&[node("AABBCCDDEEFF", "GW", "2026-01-01")]).is_empty());
+    }
+}
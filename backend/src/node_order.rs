@@ -15,6 +15,7 @@
 
 use std::sync::Arc;
 
+use crate::db::mongo::ingest_writes::IngestCycleWrites;
 use crate::db::mongo::topology::NodeOrderEntry;
 use crate::db::mongo::MongoDb;
 use crate::lacis_id::{compute_network_device_lacis_id, default_product_code};
@@ -106,6 +107,24 @@ fn looks_like_mac(s: &str) -> bool {
             .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '-' || c == '.')
 }
 
+/// Whether a client label is `client_label`'s MAC fallback (the MAC, or
+/// vendor with the short MAC) rather than a name of the client's own
+pub fn is_mac_label(label: &str, mac: &str) -> bool {
+    if looks_like_mac(label) {
+        return true;
+    }
+    let hex = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .collect::<String>()
+            .to_ascii_uppercase()
+    };
+    let mac_hex = hex(mac);
+    mac_hex.len() >= 6
+        && label.ends_with(')')
+        && hex(label).ends_with(&mac_hex[mac_hex.len() - 6..])
+}
+
 /// NodeOrderIngester: reads source collections and upserts into cg_node_order
 pub struct NodeOrderIngester {
     mongo: Arc<MongoDb>,
@@ -116,9 +135,24 @@ impl NodeOrderIngester {
         Self { mongo }
     }
 
+    /// Write one ingest cycle's entries as paced bulk upserts
+    async fn write(&self, source: &str, entries: &[NodeOrderEntry]) -> Result<(), String> {
+        let mut cycle = IngestCycleWrites::start(source, "cg_node_order");
+        let result = self
+            .mongo
+            .bulk_upsert_node_orders(entries, &mut cycle)
+            .await;
+        if let Err(ref e) = result {
+            cycle.last_error = Some(e.clone());
+        }
+        self.mongo.finish_ingest_cycle(cycle);
+        result
+    }
+
     /// Ingest all Omada data for a specific controller into nodeOrder.
     /// Called after OmadaSyncer.sync_controller() completes.
     pub async fn ingest_omada(&self, controller_id: &str) -> Result<(), String> {
+        let mut entries: Vec<NodeOrderEntry> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         // Load controller info for fid/facility_name resolution
@@ -247,7 +281,7 @@ impl NodeOrderIngester {
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_node_order(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
@@ -293,7 +327,11 @@ impl NodeOrderIngester {
                 2 // Switch depth
             } else {
                 // AP depth: 3 if under switch, 2 otherwise
-                if switch_mac.is_some() { 3 } else { 2 }
+                if switch_mac.is_some() {
+                    3
+                } else {
+                    2
+                }
             };
 
             let conn_type = if cli.wireless { "wireless" } else { "wired" };
@@ -337,7 +375,7 @@ impl NodeOrderIngester {
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_node_order(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
@@ -395,10 +433,13 @@ impl NodeOrderIngester {
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_node_order(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
+        self.write(&format!("omada:{}", controller_id), &entries)
+            .await?;
+
         tracing::debug!(
             "[NodeOrder] Omada controller {} ingested: {} entries",
             controller_id,
@@ -509,6 +550,7 @@ impl NodeOrderIngester {
     /// Ingest OpenWrt router and its clients into nodeOrder.
     /// Called after OpenWrtSyncer.poll_router() completes.
     pub async fn ingest_openwrt(&self, router_id: &str) -> Result<(), String> {
+        let mut entries: Vec<NodeOrderEntry> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         let routers = self.mongo.list_openwrt_routers().await.unwrap_or_default();
@@ -594,7 +636,7 @@ impl NodeOrderIngester {
             updated_at: now.clone(),
         };
 
-        self.mongo.upsert_node_order(&entry).await?;
+        entries.push(entry);
 
         // --- Ingest OpenWrt clients ---
         let all_clients = self
@@ -627,23 +669,30 @@ impl NodeOrderIngester {
                     "inactive".to_string()
                 },
                 state_type: "trackingOnline".to_string(),
-                connection_type: "wired".to_string(),
+                connection_type: if cli.wireless { "wireless" } else { "wired" }.to_string(),
                 lacis_id: cli.lacis_id.clone(),
                 candidate_lacis_id: None,
                 product_type: None,
                 network_device_type: None,
                 fid: None,
                 facility_name: None,
-                metadata: serde_json::json!({ "router_id": router_id }),
+                metadata: serde_json::json!({
+                    "router_id": router_id,
+                    "ssid": &cli.ssid,
+                    "band": &cli.band,
+                }),
                 label_customized: false,
-                ssid: None,
+                ssid: cli.ssid.clone(),
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_node_order(&entry).await?;
+            entries.push(entry);
         }
 
+        self.write(&format!("openwrt:{}", router_id), &entries)
+            .await?;
+
         tracing::debug!(
             "[NodeOrder] OpenWrt router {} ingested: 1 router + {} clients",
             router_id,
@@ -655,6 +704,7 @@ impl NodeOrderIngester {
     /// Ingest external device and its clients into nodeOrder.
     /// Called after ExternalSyncer.poll_device() completes.
     pub async fn ingest_external(&self, device_id: &str) -> Result<(), String> {
+        let mut entries: Vec<NodeOrderEntry> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         let devices = self.mongo.list_external_devices().await.unwrap_or_default();
@@ -737,7 +787,7 @@ impl NodeOrderIngester {
             updated_at: now.clone(),
         };
 
-        self.mongo.upsert_node_order(&entry).await?;
+        entries.push(entry);
 
         // --- Ingest external clients ---
         let all_clients = self
@@ -770,23 +820,30 @@ impl NodeOrderIngester {
                     "inactive".to_string()
                 },
                 state_type: "trackingOnline".to_string(),
-                connection_type: "wired".to_string(),
+                connection_type: if cli.wireless { "wireless" } else { "wired" }.to_string(),
                 lacis_id: cli.lacis_id.clone(),
                 candidate_lacis_id: None,
                 product_type: None,
                 network_device_type: None,
                 fid: None,
                 facility_name: None,
-                metadata: serde_json::json!({ "device_id": device_id }),
+                metadata: serde_json::json!({
+                    "device_id": device_id,
+                    "ssid": &cli.ssid,
+                    "band": &cli.band,
+                }),
                 label_customized: false,
-                ssid: None,
+                ssid: cli.ssid.clone(),
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_node_order(&entry).await?;
+            entries.push(entry);
         }
 
+        self.write(&format!("external:{}", device_id), &entries)
+            .await?;
+
         tracing::debug!(
             "[NodeOrder] External device {} ingested: 1 device + {} clients",
             device_id,
@@ -800,16 +857,17 @@ impl NodeOrderIngester {
 // Migration: Initial population of cg_node_order from existing data
 // ============================================================================
 
-/// Migrate existing data to cg_node_order (one-time, runs on startup if collection is empty).
+/// Migrate existing data to cg_node_order (skipped if the collection already has entries, unless forced).
 /// Also migrates cg_node_positions and cg_state IDs from old format to MAC format.
-pub async fn migrate_to_node_order(mongo: &Arc<MongoDb>) -> Result<(), String> {
+/// Returns whether anything was migrated.
+pub async fn migrate_to_node_order(mongo: &Arc<MongoDb>, force: bool) -> Result<bool, String> {
     let count = mongo.count_node_order().await?;
-    if count > 0 {
+    if count > 0 && !force {
         tracing::debug!(
             "[NodeOrder] Migration skipped: cg_node_order already has {} entries",
             count
         );
-        return Ok(());
+        return Ok(false);
     }
 
     tracing::info!("[NodeOrder] Starting migration to cg_node_order...");
@@ -1413,5 +1471,5 @@ pub async fn migrate_to_node_order(mongo: &Arc<MongoDb>) -> Result<(), String> {
         id_migration.len()
     );
 
-    Ok(())
+    Ok(true)
 }
@@ -1,14 +1,23 @@
 //! Discord webhook notifications
 
-use chrono::Utc;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 
+use crate::alert_rules::{AlertEvaluation, AlertRule};
 use crate::db::AppState;
-use crate::models::Severity;
+use crate::models::{HealthFailureContext, ProxyRoute, RateLimitRule, Severity};
+use crate::new_device::NewDeviceAlert;
+
+use super::queue::{Notification, NotificationQueue};
+
+/// Discord limits embed field values to 1024 characters
+const DISCORD_FIELD_VALUE_MAX: usize = 1024;
 
-/// Discord notifier
+/// Discord notifier (messages go out through the shared `NotificationQueue`)
 pub struct DiscordNotifier {
-    client: reqwest::Client,
+    queue: Arc<NotificationQueue>,
     app_state: AppState,
 }
 
@@ -35,11 +44,12 @@ struct DiscordWebhookPayload {
 }
 
 impl DiscordNotifier {
-    pub fn new(app_state: AppState) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            app_state,
-        }
+    pub fn new(app_state: AppState, queue: Arc<NotificationQueue>) -> Self {
+        Self { queue, app_state }
+    }
+
+    pub fn queue(&self) -> &Arc<NotificationQueue> {
+        &self.queue
     }
 
     /// Get webhook URL from settings
@@ -61,8 +71,8 @@ impl DiscordNotifier {
             .unwrap_or(false)
     }
 
-    /// Send a Discord notification
-    async fn send(&self, embed: DiscordEmbed) {
+    /// Queue a Discord notification for the configured webhook
+    async fn send(&self, embed: DiscordEmbed, severity: Severity) {
         let webhook_url = match self.get_webhook_url().await {
             Some(url) => url,
             None => {
@@ -71,27 +81,29 @@ impl DiscordNotifier {
             }
         };
 
+        self.send_to(&webhook_url, embed, severity);
+    }
+
+    /// Queue an embed for a specific webhook URL
+    fn send_to(&self, webhook_url: &str, embed: DiscordEmbed, severity: Severity) {
+        let label = embed.title.clone();
         let payload = DiscordWebhookPayload {
             embeds: vec![embed],
         };
-
-        match self
-            .client
-            .post(&webhook_url)
-            .json(&payload)
-            .timeout(std::time::Duration::from_secs(10))
-            .send()
-            .await
-        {
-            Ok(response) => {
-                if !response.status().is_success() {
-                    tracing::warn!("Discord webhook returned status: {}", response.status());
-                }
-            }
+        let payload = match serde_json::to_value(&payload) {
+            Ok(payload) => payload,
             Err(e) => {
-                tracing::error!("Failed to send Discord notification: {}", e);
+                tracing::error!("Failed to encode Discord notification: {}", e);
+                return;
             }
-        }
+        };
+
+        self.queue.enqueue(Notification {
+            destination: webhook_url.to_string(),
+            payload,
+            severity,
+            label,
+        });
     }
 
     /// Convert severity to Discord embed color
@@ -134,7 +146,7 @@ impl DiscordNotifier {
             ],
         };
 
-        self.send(embed).await;
+        self.send(embed, severity).await;
     }
 
     /// Notify DDNS failure
@@ -167,11 +179,184 @@ impl DiscordNotifier {
             ],
         };
 
-        self.send(embed).await;
+        self.send(embed, Severity::High).await;
+    }
+
+    /// Notify a DDNS failover transition (`failed_over` false = switched back)
+    pub async fn notify_ddns_failover(
+        &self,
+        hostname: &str,
+        from: &str,
+        to: &str,
+        failed_over: bool,
+        detail: &str,
+    ) {
+        if !self.is_notify_enabled("ddns").await {
+            return;
+        }
+
+        let (title, description, severity) = if failed_over {
+            (
+                "DDNS Failover",
+                format!("{} is now updated via its secondary provider", hostname),
+                Severity::High,
+            )
+        } else {
+            (
+                "DDNS Failover Recovered",
+                format!("{} is updated via its primary provider again", hostname),
+                Severity::Low,
+            )
+        };
+        let embed = DiscordEmbed {
+            title: title.to_string(),
+            description,
+            color: Self::severity_to_color(severity),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "From".to_string(),
+                    value: from.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "To".to_string(),
+                    value: to.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Detail".to_string(),
+                    value: detail.to_string(),
+                    inline: false,
+                },
+            ],
+        };
+
+        self.send(embed, severity).await;
+    }
+
+    /// Notify a route-linked DDNS hostname that stopped resolving to the gateway
+    pub async fn notify_dns_mismatch(
+        &self,
+        hostname: &str,
+        observed: &[String],
+        expected: &[String],
+        routes: &[String],
+    ) {
+        if !self.is_notify_enabled("ddns").await {
+            return;
+        }
+
+        let list = |items: &[String]| {
+            if items.is_empty() {
+                "-".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        let embed = DiscordEmbed {
+            title: "DDNS Hostname Diverged".to_string(),
+            description: format!(
+                "{} no longer resolves to this gateway; linked routes may not receive traffic",
+                hostname
+            ),
+            color: Self::severity_to_color(Severity::High),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Resolved".to_string(),
+                    value: if observed.is_empty() {
+                        "(does not resolve)".to_string()
+                    } else {
+                        observed.join(", ")
+                    },
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Expected".to_string(),
+                    value: list(expected),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Routes".to_string(),
+                    value: list(routes),
+                    inline: false,
+                },
+            ],
+        };
+
+        self.send(embed, Severity::High).await;
+    }
+
+    /// Notify a diverged DDNS hostname resolving to the gateway again
+    pub async fn notify_dns_mismatch_recovery(&self, hostname: &str, resolved: &[String]) {
+        if !self.is_notify_enabled("ddns").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "DDNS Hostname Recovered".to_string(),
+            description: format!(
+                "{} resolves to this gateway again ({})",
+                hostname,
+                resolved.join(", ")
+            ),
+            color: 0x2ecc71, // Green
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![],
+        };
+
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Notify a failed ACME certificate order of a DDNS hostname
+    pub async fn notify_acme_failure(
+        &self,
+        hostname: &str,
+        error: &str,
+        expires: Option<DateTime<Utc>>,
+    ) {
+        if !self.is_notify_enabled("ddns").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Certificate Order Failed".to_string(),
+            description: format!("The ACME order for {} failed", hostname),
+            color: Self::severity_to_color(Severity::High),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Error".to_string(),
+                    value: error.chars().take(DISCORD_FIELD_VALUE_MAX).collect(),
+                    inline: false,
+                },
+                DiscordField {
+                    name: "Current certificate".to_string(),
+                    value: match expires {
+                        Some(at) => format!("expires {}", at.format("%Y-%m-%d %H:%M UTC")),
+                        None => "none".to_string(),
+                    },
+                    inline: true,
+                },
+            ],
+        };
+
+        self.send(embed, Severity::High).await;
     }
 
     /// Notify health check failure
-    pub async fn notify_health_failure(&self, path: &str, target: &str, consecutive_failures: u32) {
+    ///
+    /// `context` adds recent 5xx access logs and the last successful check time
+    /// when available; without it the plain failure embed is sent.
+    pub async fn notify_health_failure(
+        &self,
+        path: &str,
+        target: &str,
+        consecutive_failures: u32,
+        owner: Option<&str>,
+        context: Option<&HealthFailureContext>,
+    ) {
         if !self.is_notify_enabled("health").await {
             return;
         }
@@ -184,11 +369,86 @@ impl DiscordNotifier {
             Severity::Medium
         };
 
+        let mut fields = vec![
+            DiscordField {
+                name: "Path".to_string(),
+                value: path.to_string(),
+                inline: true,
+            },
+            DiscordField {
+                name: "Target".to_string(),
+                value: target.to_string(),
+                inline: true,
+            },
+            DiscordField {
+                name: "Consecutive Failures".to_string(),
+                value: consecutive_failures.to_string(),
+                inline: true,
+            },
+        ];
+
+        if let Some(owner) = owner {
+            fields.push(DiscordField {
+                name: "Owner".to_string(),
+                value: owner.to_string(),
+                inline: true,
+            });
+        }
+
+        if let Some(ctx) = context {
+            fields.push(DiscordField {
+                name: "Last Success".to_string(),
+                value: ctx
+                    .last_success
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                inline: true,
+            });
+
+            if !ctx.recent_errors.is_empty() {
+                let lines: Vec<String> = ctx
+                    .recent_errors
+                    .iter()
+                    .map(|log| {
+                        format!(
+                            "{} {} {} {}",
+                            log.timestamp.format("%H:%M:%S"),
+                            log.status,
+                            log.country_code.as_deref().unwrap_or("--"),
+                            log.upstream_error.as_deref().unwrap_or("-"),
+                        )
+                    })
+                    .collect();
+                fields.push(DiscordField {
+                    name: "Recent Errors".to_string(),
+                    value: code_block(&lines.join("\n")),
+                    inline: false,
+                });
+            }
+        }
+
         let embed = DiscordEmbed {
             title: "Health Check Failed".to_string(),
             description: format!("Route {} is experiencing issues", path),
             color: Self::severity_to_color(severity),
             timestamp: Utc::now().to_rfc3339(),
+            fields,
+        };
+
+        self.send(embed, severity).await;
+    }
+
+    /// Notify health recovery
+    pub async fn notify_health_recovery(&self, path: &str, target: &str, owner: Option<&str>) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let mut embed = DiscordEmbed {
+            title: "Health Check Recovered".to_string(),
+            description: format!("Route {} is now healthy", path),
+            color: 0x2ecc71, // Green
+            timestamp: Utc::now().to_rfc3339(),
             fields: vec![
                 DiscordField {
                     name: "Path".to_string(),
@@ -200,55 +460,220 @@ impl DiscordNotifier {
                     value: target.to_string(),
                     inline: true,
                 },
+            ],
+        };
+        if let Some(owner) = owner {
+            embed.fields.push(DiscordField {
+                name: "Owner".to_string(),
+                value: owner.to_string(),
+                inline: true,
+            });
+        }
+
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Summarize the health failures held back during a maintenance window
+    pub async fn notify_maintenance_summary(&self, window: &str, summary: &str, recovered: bool) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Maintenance Window Closed".to_string(),
+            description: summary.to_string(),
+            color: if recovered { 0x2ecc71 } else { 0xe67e22 }, // Green / orange
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![DiscordField {
+                name: "Window".to_string(),
+                value: window.to_string(),
+                inline: true,
+            }],
+        };
+
+        let severity = if recovered {
+            Severity::Low
+        } else {
+            Severity::Medium
+        };
+        self.send(embed, severity).await;
+    }
+
+    /// Notify a dependency (database, controller, router, ...) failing its probe
+    pub async fn notify_dependency_failure(
+        &self,
+        dependency: &str,
+        name: &str,
+        consecutive_failures: u32,
+        error: &str,
+    ) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Dependency Check Failed".to_string(),
+            description: format!("{} ({}) is not healthy", dependency, name),
+            color: Self::severity_to_color(Severity::High),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Dependency".to_string(),
+                    value: dependency.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Target".to_string(),
+                    value: name.to_string(),
+                    inline: true,
+                },
                 DiscordField {
                     name: "Consecutive Failures".to_string(),
                     value: consecutive_failures.to_string(),
                     inline: true,
                 },
+                DiscordField {
+                    name: "Error".to_string(),
+                    value: code_block(error),
+                    inline: false,
+                },
             ],
         };
 
-        self.send(embed).await;
+        self.send(embed, Severity::High).await;
     }
 
-    /// Notify health recovery
-    pub async fn notify_health_recovery(&self, path: &str, target: &str) {
+    /// Notify a dependency passing its probe again
+    pub async fn notify_dependency_recovery(&self, dependency: &str, name: &str) {
         if !self.is_notify_enabled("health").await {
             return;
         }
 
         let embed = DiscordEmbed {
-            title: "Health Check Recovered".to_string(),
-            description: format!("Route {} is now healthy", path),
+            title: "Dependency Recovered".to_string(),
+            description: format!("{} ({}) is healthy again", dependency, name),
             color: 0x2ecc71, // Green
             timestamp: Utc::now().to_rfc3339(),
+            fields: vec![],
+        };
+
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Notify MongoDB storage projected to run out within the warning horizon
+    pub async fn notify_storage_projection(&self, warning: &str, largest: &[String]) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "MongoDB Storage Running Out".to_string(),
+            description: warning.to_string(),
+            color: Self::severity_to_color(Severity::High),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![DiscordField {
+                name: "Largest Collections".to_string(),
+                value: if largest.is_empty() {
+                    "-".to_string()
+                } else {
+                    largest.join("\n")
+                },
+                inline: false,
+            }],
+        };
+
+        self.send(embed, Severity::High).await;
+    }
+
+    /// Notify an Omada controller going offline; sent once per outage in
+    /// place of alerts for each of its devices
+    pub async fn notify_omada_controller_offline(
+        &self,
+        name: &str,
+        consecutive_failures: u32,
+        nodes: u64,
+        error: &str,
+    ) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Omada Controller Offline".to_string(),
+            description: format!(
+                "Controller {} is unreachable; the state of its devices is unknown until it recovers",
+                name
+            ),
+            color: Self::severity_to_color(Severity::High),
+            timestamp: Utc::now().to_rfc3339(),
             fields: vec![
                 DiscordField {
-                    name: "Path".to_string(),
-                    value: path.to_string(),
+                    name: "Consecutive Failures".to_string(),
+                    value: consecutive_failures.to_string(),
                     inline: true,
                 },
                 DiscordField {
-                    name: "Target".to_string(),
-                    value: target.to_string(),
+                    name: "Nodes Affected".to_string(),
+                    value: nodes.to_string(),
                     inline: true,
                 },
+                DiscordField {
+                    name: "Error".to_string(),
+                    value: code_block(error),
+                    inline: false,
+                },
             ],
         };
 
-        self.send(embed).await;
+        self.send(embed, Severity::High).await;
+    }
+
+    /// Notify an Omada controller syncing again after an outage
+    pub async fn notify_omada_controller_recovery(&self, name: &str, offline_since: Option<&str>) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Omada Controller Recovered".to_string(),
+            description: format!(
+                "Controller {} is syncing again; device states were reconciled",
+                name
+            ),
+            color: 0x2ecc71, // Green
+            timestamp: Utc::now().to_rfc3339(),
+            fields: offline_since
+                .map(|since| {
+                    vec![DiscordField {
+                        name: "Offline Since".to_string(),
+                        value: since.to_string(),
+                        inline: true,
+                    }]
+                })
+                .unwrap_or_default(),
+        };
+
+        self.send(embed, Severity::Low).await;
     }
 
-    /// Notify rate limit exceeded
-    pub async fn notify_rate_limit(&self, ip: &str, requests: i32) {
+    /// Notify a client auto-blocked by a rate limit rule
+    pub async fn notify_rate_limit_block(
+        &self,
+        ip: &str,
+        rule: &RateLimitRule,
+        expires_at: DateTime<Utc>,
+    ) {
         if !self.is_notify_enabled("security").await {
             return;
         }
 
         let embed = DiscordEmbed {
-            title: "Rate Limit Exceeded".to_string(),
-            description: format!("IP {} exceeded rate limit", ip),
-            color: Self::severity_to_color(Severity::Medium),
+            title: "IP Auto-Blocked (Rate Limit)".to_string(),
+            description: format!(
+                "IP {} exceeded {} requests per {}s below {} and was blocked",
+                ip, rule.requests_per_window, rule.window_sec, rule.path_prefix
+            ),
+            color: Self::severity_to_color(Severity::High),
             timestamp: Utc::now().to_rfc3339(),
             fields: vec![
                 DiscordField {
@@ -257,14 +682,197 @@ impl DiscordNotifier {
                     inline: true,
                 },
                 DiscordField {
-                    name: "Requests".to_string(),
-                    value: requests.to_string(),
+                    name: "Rule".to_string(),
+                    value: format!("#{} {}", rule.id, rule.path_prefix),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Blocked Until".to_string(),
+                    value: expires_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+                    inline: true,
+                },
+            ],
+        };
+
+        self.send(embed, Severity::High).await;
+    }
+
+    /// Notify repeated threat feed fetch failures
+    pub async fn notify_threat_feed_failure(
+        &self,
+        name: &str,
+        url: &str,
+        consecutive_failures: u32,
+        error: &str,
+    ) {
+        if !self.is_notify_enabled("security").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Threat Feed Fetch Failed".to_string(),
+            description: format!("Threat feed {} could not be fetched", name),
+            color: Self::severity_to_color(Severity::Medium),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Feed".to_string(),
+                    value: name.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Consecutive Failures".to_string(),
+                    value: consecutive_failures.to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "URL".to_string(),
+                    value: url.to_string(),
+                    inline: false,
+                },
+                DiscordField {
+                    name: "Error".to_string(),
+                    value: error.to_string(),
+                    inline: false,
+                },
+            ],
+        };
+
+        self.send(embed, Severity::Medium).await;
+    }
+
+    /// Notify a device seen on the network for the first time
+    pub async fn notify_new_device(&self, alert: &NewDeviceAlert) {
+        if !self.is_notify_enabled("new_device").await {
+            return;
+        }
+
+        let mut fields = vec![
+            DiscordField {
+                name: "MAC".to_string(),
+                value: alert.mac.clone(),
+                inline: true,
+            },
+            DiscordField {
+                name: "IP".to_string(),
+                value: alert.ip.clone().unwrap_or_else(|| "-".to_string()),
+                inline: true,
+            },
+            DiscordField {
+                name: "Vendor".to_string(),
+                value: alert.vendor.clone().unwrap_or_else(|| "-".to_string()),
+                inline: true,
+            },
+            DiscordField {
+                name: "Parent".to_string(),
+                value: alert
+                    .parent_label
+                    .clone()
+                    .unwrap_or_else(|| alert.parent_id.clone()),
+                inline: true,
+            },
+        ];
+        if let Some(ssid) = &alert.ssid {
+            fields.push(DiscordField {
+                name: "SSID".to_string(),
+                value: ssid.clone(),
+                inline: true,
+            });
+        }
+        if let Some(port) = &alert.port {
+            fields.push(DiscordField {
+                name: "Port".to_string(),
+                value: port.clone(),
+                inline: true,
+            });
+        }
+        if let Some(facility) = alert.facility_name.as_ref().or(alert.fid.as_ref()) {
+            fields.push(DiscordField {
+                name: "Facility".to_string(),
+                value: facility.clone(),
+                inline: true,
+            });
+        }
+
+        let embed = DiscordEmbed {
+            title: "New Device".to_string(),
+            description: format!(
+                "{} ({}, {}) joined the network",
+                alert.label, alert.node_type, alert.source
+            ),
+            color: Self::severity_to_color(Severity::Low),
+            timestamp: Utc::now().to_rfc3339(),
+            fields,
+        };
+
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Notify several new devices in one message (randomized MACs, bursts)
+    pub async fn notify_new_devices(&self, alerts: &[NewDeviceAlert], title: &str) {
+        if alerts.is_empty() || !self.is_notify_enabled("new_device").await {
+            return;
+        }
+
+        let lines: Vec<String> = alerts
+            .iter()
+            .map(|a| {
+                format!(
+                    "{} {} {} — {}",
+                    a.mac,
+                    a.label,
+                    a.ip.as_deref().unwrap_or("-"),
+                    a.location()
+                )
+            })
+            .collect();
+
+        let embed = DiscordEmbed {
+            title: format!("{} ({})", title, alerts.len()),
+            description: format!("{} devices joined the network", alerts.len()),
+            color: Self::severity_to_color(Severity::Low),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![DiscordField {
+                name: "Devices".to_string(),
+                value: code_block(&lines.join("\n")),
+                inline: false,
+            }],
+        };
+
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Notify that this instance became the cluster leader
+    pub async fn notify_leadership_change(
+        &self,
+        instance_id: &str,
+        previous: Option<&str>,
+        term: i64,
+    ) {
+        if !self.is_notify_enabled("health").await {
+            return;
+        }
+
+        let embed = DiscordEmbed {
+            title: "Cluster Leader Changed".to_string(),
+            description: format!("{} is now running the background tasks", instance_id),
+            color: Self::severity_to_color(Severity::Medium),
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Previous Leader".to_string(),
+                    value: previous.unwrap_or("(none)").to_string(),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Term".to_string(),
+                    value: term.to_string(),
                     inline: true,
                 },
             ],
         };
 
-        self.send(embed).await;
+        self.send(embed, Severity::Medium).await;
     }
 
     /// Notify configuration change (routes, settings, etc.)
@@ -278,6 +886,183 @@ impl DiscordNotifier {
             fields: vec![],
         };
 
-        self.send(embed).await;
+        self.send(embed, Severity::Low).await;
+    }
+
+    /// Notify that an expired WireGuard peer was disabled (or could not be)
+    pub async fn notify_wg_peer_expired(
+        &self,
+        name: &str,
+        interface_name: &str,
+        expires_at: &str,
+        error: Option<&str>,
+    ) {
+        if !self.is_notify_enabled("security").await {
+            return;
+        }
+
+        let mut fields = vec![
+            DiscordField {
+                name: "Peer".to_string(),
+                value: name.to_string(),
+                inline: true,
+            },
+            DiscordField {
+                name: "Interface".to_string(),
+                value: interface_name.to_string(),
+                inline: true,
+            },
+            DiscordField {
+                name: "Expired".to_string(),
+                value: expires_at.to_string(),
+                inline: true,
+            },
+        ];
+        if let Some(error) = error {
+            fields.push(DiscordField {
+                name: "Error".to_string(),
+                value: code_block(error),
+                inline: false,
+            });
+        }
+
+        let embed = match error {
+            None => DiscordEmbed {
+                title: "WireGuard Peer Expired".to_string(),
+                description: format!("Peer {} passed its expiry and was disabled", name),
+                color: Self::severity_to_color(Severity::Medium),
+                timestamp: Utc::now().to_rfc3339(),
+                fields,
+            },
+            Some(_) => DiscordEmbed {
+                title: "WireGuard Peer Expiry Failed".to_string(),
+                description: format!(
+                    "Peer {} passed its expiry but could not be disabled; retrying",
+                    name
+                ),
+                color: Self::severity_to_color(Severity::High),
+                timestamp: Utc::now().to_rfc3339(),
+                fields,
+            },
+        };
+
+        let severity = if error.is_some() {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        self.send(embed, severity).await;
     }
+
+    /// Notify a fired alert rule on the rule's own target ("none", "discord"
+    /// for the configured webhook, or an https webhook URL)
+    pub async fn notify_alert_rule(&self, rule: &AlertRule, evaluation: &AlertEvaluation) {
+        if rule.notify == "none" {
+            return;
+        }
+
+        let mut fields = vec![
+            DiscordField {
+                name: "Value".to_string(),
+                value: format!("{:.1}", evaluation.value),
+                inline: true,
+            },
+            DiscordField {
+                name: "Matched".to_string(),
+                value: evaluation.stats.matched.to_string(),
+                inline: true,
+            },
+            DiscordField {
+                name: "Unique IPs".to_string(),
+                value: evaluation.stats.unique_ips.to_string(),
+                inline: true,
+            },
+        ];
+        if !rule.filter.is_empty() {
+            fields.push(DiscordField {
+                name: "Filter".to_string(),
+                value: code_block(&rule.filter),
+                inline: false,
+            });
+        }
+        if !evaluation.stats.top_ips.is_empty() {
+            let top: Vec<String> = evaluation
+                .stats
+                .top_ips
+                .iter()
+                .map(|t| format!("{} ({})", t.ip, t.count))
+                .collect();
+            fields.push(DiscordField {
+                name: "Top IPs".to_string(),
+                value: top.join("\n"),
+                inline: false,
+            });
+        }
+
+        let embed = DiscordEmbed {
+            title: format!("🚨 Alert: {}", rule.name),
+            description: rule.describe_condition(),
+            color: Self::severity_to_color(rule.severity),
+            timestamp: Utc::now().to_rfc3339(),
+            fields,
+        };
+
+        match rule.notify.as_str() {
+            "discord" => self.send(embed, rule.severity).await,
+            url => self.send_to(url, embed, rule.severity),
+        }
+    }
+
+    /// Notify a route's owner that someone else changed or proposed a change.
+    ///
+    /// An https owner contact is treated as the owner's own webhook; any other
+    /// contact is mentioned in a message on the main webhook.
+    pub async fn notify_route_owner(&self, route: &ProxyRoute, title: &str, description: &str) {
+        let Some(owner) = route.owner_label() else {
+            return;
+        };
+
+        let embed = DiscordEmbed {
+            title: format!("👤 {}", title),
+            description: description.to_string(),
+            color: 0x9b59b6,
+            timestamp: Utc::now().to_rfc3339(),
+            fields: vec![
+                DiscordField {
+                    name: "Route".to_string(),
+                    value: format!("#{} {}", route.id, route.path),
+                    inline: true,
+                },
+                DiscordField {
+                    name: "Owner".to_string(),
+                    value: owner,
+                    inline: true,
+                },
+            ],
+        };
+
+        match route
+            .owner_contact
+            .as_deref()
+            .filter(|c| c.starts_with("https://"))
+        {
+            Some(url) => self.send_to(url, embed, Severity::Low),
+            None => self.send(embed, Severity::Low).await,
+        }
+    }
+}
+
+/// Wrap text in a Discord code block, truncating to fit the field value limit
+fn code_block(text: &str) -> String {
+    // 8 = two ``` fences plus newlines
+    let max = DISCORD_FIELD_VALUE_MAX - 8;
+    let body: String = if text.chars().count() > max {
+        text.chars()
+            .take(max - 1)
+            .chain(std::iter::once('…'))
+            .collect()
+    } else {
+        text.to_string()
+    };
+    format!("```\n{}\n```", body)
 }
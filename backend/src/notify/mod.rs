@@ -1,5 +1,7 @@
 //! Notification module
 
 mod discord;
+pub mod queue;
 
 pub use self::discord::DiscordNotifier;
+pub use self::queue::NotificationQueue;
@@ -0,0 +1,667 @@
+//! Outbound notification queue (Discord and other webhooks)
+//!
+//! Notifications are queued instead of posted inline, and one worker drains
+//! the queue. Each destination URL has a token bucket: Discord webhooks use
+//! Discord's per-webhook limits (a burst of 5, 30 per minute), other webhooks
+//! the `[notify]` webhook limits. A 429 pauses the destination for its
+//! `Retry-After`. Network errors and 5xx responses retry with exponential
+//! backoff up to `max_attempts`.
+//!
+//! Critical notifications jump ahead of everything else. When the queue is
+//! full, the oldest notification of the lowest severity is dropped. If the
+//! new one is lower than everything queued, the new one is dropped instead.
+//! GET /api/dashboard/notification-queue shows the depth and drop counts.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::config::NotifyConfig;
+use crate::models::Severity;
+
+/// Pause after a 429 without a usable Retry-After
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+/// Backoff cap for transient failures
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests a destination may burst, refilled at `per_minute`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// Per-destination token bucket
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+    /// Set by a 429 (Retry-After)
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.burst.max(1)),
+            updated: now,
+            paused_until: None,
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        f64::from(self.limit.per_minute.max(1)) / 60.0
+    }
+
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * self.refill_per_sec()).min(f64::from(self.limit.burst.max(1)))
+    }
+
+    fn refill(&mut self, now: Instant) {
+        self.tokens = self.tokens_at(now);
+        self.updated = now;
+    }
+
+    /// Take a token, or return when the next one is available
+    fn try_take(&mut self, now: Instant) -> Result<(), Instant> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Err(until);
+            }
+            self.paused_until = None;
+        }
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - self.tokens) / self.refill_per_sec();
+            Err(now + Duration::from_secs_f64(wait))
+        }
+    }
+
+    fn pause(&mut self, now: Instant, duration: Duration) {
+        self.paused_until = Some(now + duration);
+        self.tokens = 0.0;
+        self.updated = now;
+    }
+}
+
+/// One message for one webhook
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub destination: String,
+    pub payload: serde_json::Value,
+    pub severity: Severity,
+    /// Short description for logs (e.g. the embed title)
+    pub label: String,
+}
+
+#[derive(Debug)]
+struct Queued {
+    seq: u64,
+    notification: Notification,
+    attempts: u32,
+    /// Backoff after a transient failure
+    not_before: Option<Instant>,
+}
+
+/// Running totals since startup
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueueCounters {
+    pub enqueued: u64,
+    pub sent: u64,
+    /// Transient failures that were retried
+    pub retried: u64,
+    /// 429 responses
+    pub rate_limited: u64,
+    /// Evicted (or refused) because the queue was full
+    pub dropped_full: u64,
+    /// Given up: rejected by the destination or out of attempts
+    pub failed: u64,
+}
+
+/// Queue state of one destination
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationStats {
+    /// Host (and Discord webhook id); the webhook token is never shown
+    pub destination: String,
+    pub queued: usize,
+    pub limit: RateLimit,
+    pub tokens: f64,
+    /// Remaining 429 pause
+    pub paused_for_ms: Option<u64>,
+}
+
+/// GET /api/dashboard/notification-queue
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationQueueStats {
+    pub depth: usize,
+    pub capacity: usize,
+    pub critical_queued: usize,
+    #[serde(flatten)]
+    pub counters: QueueCounters,
+    pub destinations: Vec<DestinationStats>,
+}
+
+/// Result of one delivery attempt
+#[derive(Debug)]
+enum Delivery {
+    Sent,
+    RateLimited(Duration),
+    /// Network error or 5xx
+    Transient(String),
+    /// Other 4xx: retrying will not help
+    Rejected(String),
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    items: Vec<Queued>,
+    buckets: HashMap<String, TokenBucket>,
+    next_seq: u64,
+    counters: QueueCounters,
+}
+
+/// Shared outbound queue; `run` is the worker
+pub struct NotificationQueue {
+    client: reqwest::Client,
+    config: NotifyConfig,
+    state: Mutex<QueueState>,
+    wake: tokio::sync::Notify,
+}
+
+impl NotificationQueue {
+    pub fn new(config: NotifyConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+            state: Mutex::new(QueueState::default()),
+            wake: tokio::sync::Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Rate limit of a destination URL
+    fn limit_for(&self, destination: &str) -> RateLimit {
+        if is_discord_webhook(destination) {
+            RateLimit {
+                burst: self.config.discord_burst,
+                per_minute: self.config.discord_per_minute,
+            }
+        } else {
+            RateLimit {
+                burst: self.config.webhook_burst,
+                per_minute: self.config.webhook_per_minute,
+            }
+        }
+    }
+
+    /// Queue a notification (never blocks on the network)
+    pub fn enqueue(&self, notification: Notification) {
+        {
+            let mut state = self.lock();
+            if !admit(&mut state, &notification, self.config.queue_capacity) {
+                tracing::warn!(
+                    "[Notify] Queue full, dropped {:?} notification \"{}\"",
+                    notification.severity,
+                    notification.label
+                );
+                return;
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.items.push(Queued {
+                seq,
+                notification,
+                attempts: 0,
+                not_before: None,
+            });
+        }
+        self.wake.notify_one();
+    }
+
+    /// Worker: post queued notifications as their destinations allow
+    pub async fn run(&self) {
+        loop {
+            let next = {
+                let mut state = self.lock();
+                take_ready(&mut state, Instant::now(), |d| self.limit_for(d))
+            };
+            match next {
+                Ok(item) => {
+                    let delivery = self.deliver(&item.notification).await;
+                    let mut state = self.lock();
+                    settle(
+                        &mut state,
+                        item,
+                        delivery,
+                        Instant::now(),
+                        self.config.max_attempts,
+                    );
+                }
+                Err(Some(at)) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(at.into()) => {}
+                        _ = self.wake.notified() => {}
+                    }
+                }
+                Err(None) => self.wake.notified().await,
+            }
+        }
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Delivery {
+        let response = match self
+            .client
+            .post(&notification.destination)
+            .json(&notification.payload)
+            .timeout(SEND_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Delivery::Transient(e.to_string()),
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            Delivery::Sent
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            let header = retry_after(response.headers());
+            let body = response.json::<serde_json::Value>().await.ok();
+            let from_body = body
+                .as_ref()
+                .and_then(|b| b.get("retry_after"))
+                .and_then(|v| v.as_f64())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+            Delivery::RateLimited(header.or(from_body).unwrap_or(DEFAULT_RETRY_AFTER))
+        } else if status.is_server_error() {
+            Delivery::Transient(format!("status {}", status))
+        } else {
+            Delivery::Rejected(format!("status {}", status))
+        }
+    }
+
+    pub fn stats(&self) -> NotificationQueueStats {
+        let state = self.lock();
+        let now = Instant::now();
+        let mut destinations: Vec<DestinationStats> = state
+            .buckets
+            .iter()
+            .map(|(destination, bucket)| DestinationStats {
+                destination: redact_destination(destination),
+                queued: state
+                    .items
+                    .iter()
+                    .filter(|q| &q.notification.destination == destination)
+                    .count(),
+                limit: bucket.limit,
+                tokens: (bucket.tokens_at(now) * 10.0).floor() / 10.0,
+                paused_for_ms: bucket
+                    .paused_until
+                    .filter(|until| *until > now)
+                    .map(|until| (until - now).as_millis() as u64),
+            })
+            .collect();
+        destinations.sort_by(|a, b| a.destination.cmp(&b.destination));
+
+        NotificationQueueStats {
+            depth: state.items.len(),
+            capacity: self.config.queue_capacity,
+            critical_queued: state
+                .items
+                .iter()
+                .filter(|q| q.notification.severity == Severity::Critical)
+                .count(),
+            counters: state.counters.clone(),
+            destinations,
+        }
+    }
+}
+
+/// Make room for a new notification; false = the new one is dropped
+fn admit(state: &mut QueueState, notification: &Notification, capacity: usize) -> bool {
+    state.counters.enqueued += 1;
+    if state.items.len() < capacity {
+        return true;
+    }
+    let victim = state
+        .items
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, q)| (q.notification.severity, q.seq))
+        .map(|(i, q)| (i, q.notification.severity));
+    state.counters.dropped_full += 1;
+    match victim {
+        Some((index, severity)) if severity <= notification.severity => {
+            let evicted = state.items.remove(index);
+            tracing::warn!(
+                "[Notify] Queue full, evicted {:?} notification \"{}\"",
+                evicted.notification.severity,
+                evicted.notification.label
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Next notification whose destination has a token: Critical first, then in
+/// queue order. Err carries the earliest time something becomes ready.
+fn take_ready(
+    state: &mut QueueState,
+    now: Instant,
+    limit_for: impl Fn(&str) -> RateLimit,
+) -> Result<Queued, Option<Instant>> {
+    let mut order: Vec<usize> = (0..state.items.len()).collect();
+    order.sort_by_key(|&i| {
+        let q = &state.items[i];
+        (q.notification.severity != Severity::Critical, q.seq)
+    });
+
+    let mut earliest: Option<Instant> = None;
+    let mut wait_until = |at: Instant| {
+        earliest = Some(earliest.map_or(at, |e| e.min(at)));
+    };
+    for index in order {
+        let item = &state.items[index];
+        if let Some(at) = item.not_before.filter(|at| *at > now) {
+            wait_until(at);
+            continue;
+        }
+        let destination = item.notification.destination.clone();
+        let bucket = state
+            .buckets
+            .entry(destination.clone())
+            .or_insert_with(|| TokenBucket::new(limit_for(&destination), now));
+        match bucket.try_take(now) {
+            Ok(()) => return Ok(state.items.remove(index)),
+            Err(at) => wait_until(at),
+        }
+    }
+    Err(earliest)
+}
+
+/// Record a delivery attempt; retried items go back into the queue
+fn settle(
+    state: &mut QueueState,
+    mut item: Queued,
+    delivery: Delivery,
+    now: Instant,
+    max_attempts: u32,
+) {
+    let label = item.notification.label.clone();
+    match delivery {
+        Delivery::Sent => state.counters.sent += 1,
+        Delivery::RateLimited(pause) => {
+            state.counters.rate_limited += 1;
+            tracing::warn!(
+                "[Notify] {} rate limited, pausing for {:?}",
+                redact_destination(&item.notification.destination),
+                pause
+            );
+            if let Some(bucket) = state.buckets.get_mut(&item.notification.destination) {
+                bucket.pause(now, pause);
+            }
+            // Not the notification's fault: keep its place and attempts
+            state.items.push(item);
+        }
+        Delivery::Transient(error) => {
+            item.attempts += 1;
+            if item.attempts >= max_attempts.max(1) {
+                state.counters.failed += 1;
+                tracing::error!(
+                    "[Notify] Giving up on \"{}\" after {} attempts: {}",
+                    label,
+                    item.attempts,
+                    error
+                );
+            } else {
+                state.counters.retried += 1;
+                let delay = backoff(item.attempts);
+                tracing::warn!(
+                    "[Notify] \"{}\" failed ({}), retrying in {:?}",
+                    label,
+                    error,
+                    delay
+                );
+                item.not_before = Some(now + delay);
+                state.items.push(item);
+            }
+        }
+        Delivery::Rejected(error) => {
+            state.counters.failed += 1;
+            tracing::error!("[Notify] \"{}\" rejected: {}", label, error);
+        }
+    }
+}
+
+/// 1s, 2s, 4s, ... capped at MAX_BACKOFF
+fn backoff(attempts: u32) -> Duration {
+    Duration::from_secs(1u64 << attempts.saturating_sub(1).min(6)).min(MAX_BACKOFF)
+}
+
+/// `Retry-After` in (possibly fractional) seconds
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let secs: f64 = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+fn is_discord_webhook(destination: &str) -> bool {
+    reqwest::Url::parse(destination).is_ok_and(|url| {
+        url.host_str().is_some_and(|host| {
+            host == "discord.com" || host == "discordapp.com" || host.ends_with(".discord.com")
+        }) && url.path().starts_with("/api/webhooks/")
+    })
+}
+
+/// Host (and Discord webhook id) of a destination, without credentials
+fn redact_destination(destination: &str) -> String {
+    let Ok(url) = reqwest::Url::parse(destination) else {
+        return "(invalid url)".to_string();
+    };
+    let host = url.host_str().unwrap_or("");
+    if is_discord_webhook(destination) {
+        let id = url.path_segments().and_then(|mut s| s.nth(2)).unwrap_or("");
+        format!("{}/api/webhooks/{}", host, id)
+    } else {
+        match url.port() {
+            Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+            None => format!("{}://{}", url.scheme(), host),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISCORD: &str = "https://discord.com/api/webhooks/123/secret-token";
+    const OTHER: &str = "https://hooks.example.com/x";
+
+    fn note(destination: &str, severity: Severity, label: &str) -> Notification {
+        Notification {
+            destination: destination.to_string(),
+            payload: serde_json::json!({}),
+            severity,
+            label: label.to_string(),
+        }
+    }
+
+    fn push(state: &mut QueueState, notification: Notification, capacity: usize) -> bool {
+        if !admit(state, &notification, capacity) {
+            return false;
+        }
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.items.push(Queued {
+            seq,
+            notification,
+            attempts: 0,
+            not_before: None,
+        });
+        true
+    }
+
+    fn limit(burst: u32, per_minute: u32) -> impl Fn(&str) -> RateLimit {
+        move |_| RateLimit { burst, per_minute }
+    }
+
+    fn labels(state: &QueueState) -> Vec<&str> {
+        let mut items: Vec<&Queued> = state.items.iter().collect();
+        items.sort_by_key(|q| q.seq);
+        items
+            .iter()
+            .map(|q| q.notification.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn bucket_allows_burst_then_paces() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                burst: 2,
+                per_minute: 60,
+            },
+            now,
+        );
+        assert!(bucket.try_take(now).is_ok());
+        assert!(bucket.try_take(now).is_ok());
+        let next = bucket.try_take(now).unwrap_err();
+        assert_eq!(next - now, Duration::from_secs(1));
+        assert!(bucket.try_take(now + Duration::from_secs(1)).is_ok());
+
+        bucket.pause(now, Duration::from_secs(30));
+        assert_eq!(
+            bucket.try_take(now + Duration::from_secs(5)).unwrap_err(),
+            now + Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn full_queue_evicts_oldest_lowest_severity() {
+        let mut state = QueueState::default();
+        assert!(push(&mut state, note(OTHER, Severity::Medium, "m1"), 3));
+        assert!(push(&mut state, note(OTHER, Severity::Low, "l1"), 3));
+        assert!(push(&mut state, note(OTHER, Severity::Low, "l2"), 3));
+
+        assert!(push(&mut state, note(OTHER, Severity::High, "h1"), 3));
+        assert_eq!(labels(&state), vec!["m1", "l2", "h1"]);
+
+        // Same severity: the older one goes
+        assert!(push(&mut state, note(OTHER, Severity::Low, "l3"), 3));
+        assert_eq!(labels(&state), vec!["m1", "h1", "l3"]);
+        assert!(push(&mut state, note(OTHER, Severity::High, "h2"), 3));
+        // Lower than everything queued: the newcomer is dropped
+        assert!(!push(&mut state, note(OTHER, Severity::Low, "l4"), 3));
+        assert_eq!(labels(&state), vec!["m1", "h1", "h2"]);
+        assert_eq!(state.counters.dropped_full, 4);
+        assert_eq!(state.counters.enqueued, 7);
+    }
+
+    #[test]
+    fn critical_jumps_the_queue_and_buckets_are_per_destination() {
+        let now = Instant::now();
+        let mut state = QueueState::default();
+        push(&mut state, note(DISCORD, Severity::High, "a"), 10);
+        push(&mut state, note(DISCORD, Severity::Low, "b"), 10);
+        push(&mut state, note(OTHER, Severity::Low, "c"), 10);
+        push(&mut state, note(DISCORD, Severity::Critical, "crit"), 10);
+
+        let take = |state: &mut QueueState| {
+            take_ready(state, now, limit(1, 60)).map(|q| q.notification.label)
+        };
+        assert_eq!(take(&mut state).unwrap(), "crit");
+        // Discord bucket is empty now; the other webhook still has a token
+        assert_eq!(take(&mut state).unwrap(), "c");
+        assert_eq!(
+            take(&mut state).unwrap_err(),
+            Some(now + Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn failures_retry_with_backoff_and_429_pauses_the_destination() {
+        let now = Instant::now();
+        let mut state = QueueState::default();
+        push(&mut state, note(OTHER, Severity::High, "x"), 10);
+
+        let item = take_ready(&mut state, now, limit(5, 60)).unwrap();
+        settle(
+            &mut state,
+            item,
+            Delivery::Transient("timeout".into()),
+            now,
+            2,
+        );
+        assert_eq!(state.counters.retried, 1);
+        assert_eq!(
+            take_ready(&mut state, now, limit(5, 60)).unwrap_err(),
+            Some(now + Duration::from_secs(1))
+        );
+
+        let later = now + Duration::from_secs(1);
+        let item = take_ready(&mut state, later, limit(5, 60)).unwrap();
+        settle(
+            &mut state,
+            item,
+            Delivery::Transient("timeout".into()),
+            later,
+            2,
+        );
+        assert!(state.items.is_empty());
+        assert_eq!(state.counters.failed, 1);
+
+        push(&mut state, note(OTHER, Severity::High, "y"), 10);
+        let item = take_ready(&mut state, later, limit(5, 60)).unwrap();
+        settle(
+            &mut state,
+            item,
+            Delivery::RateLimited(Duration::from_secs(7)),
+            later,
+            2,
+        );
+        assert_eq!(state.items[0].attempts, 0);
+        assert_eq!(
+            take_ready(&mut state, later, limit(5, 60)).unwrap_err(),
+            Some(later + Duration::from_secs(7))
+        );
+    }
+
+    #[test]
+    fn discord_destinations_are_detected_and_redacted() {
+        assert!(is_discord_webhook(DISCORD));
+        assert!(!is_discord_webhook(OTHER));
+        assert_eq!(redact_destination(DISCORD), "discord.com/api/webhooks/123");
+        assert_eq!(
+            redact_destination("http://10.0.0.5:8080/hook?token=abc"),
+            "http://10.0.0.5:8080"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "1.5".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(1500)));
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(4));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+}
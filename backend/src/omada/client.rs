@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc as StdArc;
 use tokio::sync::RwLock;
 
+use super::paging::{self, Page, PageOptions, Paged};
 use crate::db::MySqlDb;
 
 // ============================================================================
@@ -62,6 +63,8 @@ struct TokenResult {
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct ListResult<T> {
+    #[serde(rename = "totalRows")]
+    pub total_rows: Option<u64>,
     pub data: Option<Vec<T>>,
 }
 
@@ -165,6 +168,46 @@ pub struct OmadaClientDevice {
     pub guest: Option<bool>,
 }
 
+// ============================================================================
+// Switch port (per-port status of a switch)
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OmadaSwitchPort {
+    pub port: i32,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub disable: bool,
+    /// 0=down, 1=up
+    #[serde(rename = "linkStatus")]
+    pub link_status: Option<i32>,
+    /// Omada speed code (see `link_speed_mbps`)
+    #[serde(rename = "linkSpeed")]
+    pub link_speed: Option<i32>,
+    /// 0=half, 1=full
+    pub duplex: Option<i32>,
+    pub poe: Option<bool>,
+    /// Current PoE draw in watts
+    #[serde(rename = "poePower")]
+    pub poe_power: Option<f64>,
+    #[serde(rename = "profileName")]
+    pub profile_name: Option<String>,
+}
+
+/// Map Omada linkSpeed code to Mbps (None for auto/unknown)
+pub fn link_speed_mbps(code: i32) -> Option<u32> {
+    match code {
+        1 => Some(10),
+        2 => Some(100),
+        3 => Some(1_000),
+        4 => Some(2_500),
+        5 => Some(10_000),
+        6 => Some(5_000),
+        7 => Some(25_000),
+        _ => None,
+    }
+}
+
 // ============================================================================
 // WireGuard Peer
 // ============================================================================
@@ -251,6 +294,14 @@ pub fn device_type_to_network_device_type(device_type: &str) -> &'static str {
 // OmadaClient
 // ============================================================================
 
+/// Prefix of errors where the controller refused the client credentials
+pub const TOKEN_REJECTED: &str = "Token error";
+
+/// Whether an Omada error (possibly wrapped) is a refused token request
+pub fn is_token_rejection(error: &str) -> bool {
+    error.contains(TOKEN_REJECTED)
+}
+
 pub struct OmadaClient {
     config: RwLock<Option<OmadaConfig>>,
     token: RwLock<Option<TokenInfo>>,
@@ -301,6 +352,14 @@ impl OmadaClient {
     // Token management
     // ========================================================================
 
+    /// Request a new access token even if the cached one is still valid
+    pub async fn refresh_token(&self) -> Result<(), String> {
+        *self.token.write().await = None;
+        self.ensure_token().await.map(|_| ())
+    }
+
+    /// Obtain (or reuse) an access token; a controller refusing the
+    /// credentials yields an error starting with [`TOKEN_REJECTED`]
     pub async fn ensure_token(&self) -> Result<String, String> {
         // Check if we have a valid token
         {
@@ -341,7 +400,7 @@ impl OmadaClient {
             .map_err(|e| format!("Token parse failed: {}", e))?;
 
         if result.error_code != 0 {
-            return Err(format!("Token error: {:?}", result.msg));
+            return Err(format!("{}: {:?}", TOKEN_REJECTED, result.msg));
         }
 
         let token_result = result.result.ok_or("No token in response")?;
@@ -489,53 +548,98 @@ impl OmadaClient {
     // Clients (per site, paginated)
     // ========================================================================
 
-    /// Get connected clients for a specific site (paginated)
+    /// Get connected clients for a specific site (paginated, see [`paging`]);
+    /// pages that keep failing are reported in the result
     pub async fn get_clients_for_site(
         &self,
         site_id: &str,
-    ) -> Result<Vec<OmadaClientDevice>, String> {
+        options: &PageOptions,
+    ) -> Result<Paged<OmadaClientDevice>, String> {
         let token = self.ensure_token().await?;
         let config = self.config.read().await;
         let cfg = config.as_ref().ok_or("Omada not configured")?;
 
-        let mut all_clients = Vec::new();
-        let mut page = 1;
-        let page_size = 100;
+        paging::paginate(options, |page| {
+            let request = self
+                .http_client
+                .get(format!(
+                    "{}/openapi/v1/{}/sites/{}/clients?page={}&pageSize={}",
+                    cfg.base_url, cfg.omadac_id, site_id, page, options.page_size
+                ))
+                .header("Authorization", format!("AccessToken={}", token));
+            async move {
+                let resp = request
+                    .send()
+                    .await
+                    .map_err(|e| format!("Clients page {} request failed: {}", page, e))?;
+
+                let result: OmadaResponse<ListResult<OmadaClientDevice>> = resp
+                    .json()
+                    .await
+                    .map_err(|e| format!("Clients page {} parse failed: {}", page, e))?;
+
+                if result.error_code != 0 {
+                    return Err(format!("Clients page {} error: {:?}", page, result.msg));
+                }
 
-        loop {
-            let url = format!(
-                "{}/openapi/v1/{}/sites/{}/clients?page={}&pageSize={}",
-                cfg.base_url, cfg.omadac_id, site_id, page, page_size
-            );
+                let list = result.result;
+                Ok(Page {
+                    total_rows: list.as_ref().and_then(|r| r.total_rows),
+                    items: list.and_then(|r| r.data).unwrap_or_default(),
+                })
+            }
+        })
+        .await
+    }
 
-            let resp = self
-                .http_client
-                .get(&url)
-                .header("Authorization", format!("AccessToken={}", token))
-                .send()
-                .await
-                .map_err(|e| format!("Clients request failed: {}", e))?;
+    // ========================================================================
+    // Switch ports (per switch)
+    // ========================================================================
 
-            let result: OmadaResponse<ListResult<OmadaClientDevice>> = resp
-                .json()
-                .await
-                .map_err(|e| format!("Clients parse failed: {}", e))?;
+    /// Get per-port status for a switch (`switch_mac` as reported by the controller)
+    pub async fn get_switch_ports(
+        &self,
+        site_id: &str,
+        switch_mac: &str,
+    ) -> Result<Vec<OmadaSwitchPort>, String> {
+        let token = self.ensure_token().await?;
+        let config = self.config.read().await;
+        let cfg = config.as_ref().ok_or("Omada not configured")?;
 
-            if result.error_code != 0 {
-                return Err(format!("Clients error: {:?}", result.msg));
-            }
+        let url = format!(
+            "{}/openapi/v1/{}/sites/{}/switches/{}/ports",
+            cfg.base_url, cfg.omadac_id, site_id, switch_mac
+        );
 
-            let clients = result.result.and_then(|r| r.data).unwrap_or_default();
-            let count = clients.len();
-            all_clients.extend(clients);
+        let resp = self
+            .http_client
+            .get(&url)
+            .header("Authorization", format!("AccessToken={}", token))
+            .send()
+            .await
+            .map_err(|e| format!("Switch ports request failed: {}", e))?;
 
-            if count < page_size {
-                break;
-            }
-            page += 1;
+        // Controllers return either a bare list or a paged { data: [...] } result
+        let result: OmadaResponse<serde_json::Value> = resp
+            .json()
+            .await
+            .map_err(|e| format!("Switch ports parse failed: {}", e))?;
+
+        if result.error_code != 0 {
+            return Err(format!("Switch ports error: {:?}", result.msg));
         }
 
-        Ok(all_clients)
+        let list = match result.result {
+            Some(serde_json::Value::Object(mut obj)) => {
+                obj.remove("data").unwrap_or(serde_json::Value::Null)
+            }
+            Some(other) => other,
+            None => serde_json::Value::Null,
+        };
+        if list.is_null() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_value(list).map_err(|e| format!("Switch ports parse failed: {}", e))
     }
 
     // ========================================================================
@@ -979,6 +1083,9 @@ pub struct CreateWgPeerRequest {
 pub struct UpdateWgPeerRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Enabled state (false disables the peer without deleting it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<bool>,
     #[serde(rename = "allowAddress")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_address: Option<Vec<String>>,
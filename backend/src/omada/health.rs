@@ -0,0 +1,91 @@
+//! Controller-level health for the Omada syncer
+//!
+//! A controller that fails `omada_controller_offline_after_failures`
+//! consecutive syncs is marked offline: its status becomes "offline" and the
+//! topology nodes ingested from it get the `UNKNOWN_CONTROLLER_OFFLINE`
+//! state instead of keeping their last (possibly green) state. The state
+//! they had is kept in `metadata.state_before_outage`. No per-device state
+//! changes are recorded for this; one controller-down notification is sent
+//! instead.
+//!
+//! The first successful sync clears the offline state. Its ingestion
+//! reconciles the nodes, recording a state change only where a device ends
+//! up in a different state than before the outage.
+
+/// state_type of nodes whose controller is offline
+pub const UNKNOWN_CONTROLLER_OFFLINE: &str = "unknown_controller_offline";
+/// Metadata key holding a node's state when its controller went offline
+pub const STATE_BEFORE_OUTAGE: &str = "state_before_outage";
+/// Failed syncs before a controller counts as offline
+pub const DEFAULT_OFFLINE_AFTER_FAILURES: i32 = 3;
+
+/// What a sync result changes about a controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Unchanged,
+    WentOffline,
+    Recovered,
+}
+
+/// Transition after a failed sync, `failures` counting this one
+pub fn after_failure(failures: u32, threshold: u32, offline: bool) -> Transition {
+    if !offline && failures >= threshold.max(1) {
+        Transition::WentOffline
+    } else {
+        Transition::Unchanged
+    }
+}
+
+/// Transition after a successful sync
+pub fn after_success(offline: bool) -> Transition {
+    if offline {
+        Transition::Recovered
+    } else {
+        Transition::Unchanged
+    }
+}
+
+/// Previous state to record for an ingested node, or None when nothing
+/// changed. Nodes coming out of the unknown state compare against their
+/// state before the outage; the unknown state itself is never recorded.
+pub fn state_change_from<'a>(
+    existing: &'a str,
+    before_outage: Option<&'a str>,
+    new_state: &str,
+) -> Option<&'a str> {
+    let previous = if existing == UNKNOWN_CONTROLLER_OFFLINE {
+        before_outage?
+    } else {
+        existing
+    };
+    (previous != new_state && !previous.starts_with("Static")).then_some(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goes_offline_once_at_the_threshold() {
+        assert_eq!(after_failure(1, 3, false), Transition::Unchanged);
+        assert_eq!(after_failure(3, 3, false), Transition::WentOffline);
+        assert_eq!(after_failure(4, 3, true), Transition::Unchanged);
+        assert_eq!(after_failure(1, 0, false), Transition::WentOffline);
+        assert_eq!(after_success(true), Transition::Recovered);
+        assert_eq!(after_success(false), Transition::Unchanged);
+    }
+
+    #[test]
+    fn reconciliation_compares_against_the_state_before_the_outage() {
+        let unknown = UNKNOWN_CONTROLLER_OFFLINE;
+        assert_eq!(state_change_from(unknown, Some("online"), "online"), None);
+        assert_eq!(
+            state_change_from(unknown, Some("online"), "offline"),
+            Some("online")
+        );
+        // Before-outage state lost: nothing worth recording
+        assert_eq!(state_change_from(unknown, None, "offline"), None);
+        assert_eq!(state_change_from("online", None, "offline"), Some("online"));
+        assert_eq!(state_change_from("StaticOnline", None, "offline"), None);
+    }
+}
@@ -14,6 +14,7 @@ use crate::db::mongo::omada::{OmadaControllerDoc, OmadaSiteMapping};
 use crate::db::mongo::MongoDb;
 use crate::db::MySqlDb;
 use crate::omada::client::{OmadaClient, OmadaConfig};
+use crate::omada::webhook::WebhookDedup;
 
 /// Result of a connection test
 #[derive(Debug, serde::Serialize)]
@@ -38,6 +39,8 @@ pub struct OmadaManager {
     /// controller_id → OmadaClient instance
     clients: RwLock<HashMap<String, Arc<OmadaClient>>>,
     mongo: Arc<MongoDb>,
+    /// Replay protection for inbound webhook events
+    webhook_dedup: WebhookDedup,
 }
 
 impl OmadaManager {
@@ -45,6 +48,7 @@ impl OmadaManager {
         Self {
             clients: RwLock::new(HashMap::new()),
             mongo,
+            webhook_dedup: WebhookDedup::default(),
         }
     }
 
@@ -132,6 +136,8 @@ impl OmadaManager {
             last_error: None,
             sites: site_mappings,
             last_synced_at: None,
+            consecutive_failures: 0,
+            offline_since: None,
             created_at: now.clone(),
             updated_at: now,
         };
@@ -286,16 +292,22 @@ impl OmadaManager {
         map.keys().cloned().collect()
     }
 
+    /// Webhook replay protection state
+    pub fn webhook_dedup(&self) -> &WebhookDedup {
+        &self.webhook_dedup
+    }
+
     /// Get MongoDB reference (for syncer)
     pub fn mongo(&self) -> &Arc<MongoDb> {
         &self.mongo
     }
 
-    /// Migrate from MySQL omada_config to MongoDB omada_controllers (one-time, startup)
-    pub async fn migrate_from_mysql(&self, mysql: &MySqlDb) -> Result<bool, String> {
+    /// Migrate from MySQL omada_config to MongoDB omada_controllers (skipped if
+    /// MongoDB already has controllers, unless forced)
+    pub async fn migrate_from_mysql(&self, mysql: &MySqlDb, force: bool) -> Result<bool, String> {
         // Check if MongoDB already has controllers
         let existing = self.mongo.list_omada_controllers().await?;
-        if !existing.is_empty() {
+        if !existing.is_empty() && !force {
             tracing::info!(
                 "[OmadaManager] MongoDB already has {} controllers, skipping MySQL migration",
                 existing.len()
@@ -392,6 +404,8 @@ impl OmadaManager {
             last_error: None,
             sites,
             last_synced_at: None,
+            consecutive_failures: 0,
+            offline_since: None,
             created_at: now.clone(),
             updated_at: now,
         };
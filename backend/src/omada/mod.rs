@@ -1,12 +1,20 @@
 //! Omada OpenAPI integration module
 //!
 //! - `client`: Low-level API client (token management, HTTP requests)
+//! - `health`: Controller-level health (offline controllers, unknown node state)
 //! - `manager`: Multi-controller lifecycle management
+//! - `paging`: Retried, bounded-parallel pagination of list endpoints
+//! - `ports`: Switch port tables and summaries
 //! - `sync`: Background data synchronization
+//! - `webhook`: Inbound controller event notifications
 
 pub mod client;
+pub mod health;
 pub mod manager;
+pub mod paging;
+pub mod ports;
 pub mod sync;
+pub mod webhook;
 
 pub use client::OmadaClient;
 pub use manager::OmadaManager;
@@ -0,0 +1,265 @@
+//! Resilient pagination for Omada list endpoints
+//!
+//! Each page is retried with exponential backoff. A page that keeps failing
+//! is skipped and reported instead of failing the whole list, so callers can
+//! merge what was fetched. When the first page reports the total row count,
+//! the remaining pages are fetched in parallel (bounded); otherwise pages are
+//! walked one by one until a short page.
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures::stream::{self, StreamExt};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+pub const MAX_PAGE_SIZE: u32 = 1000;
+pub const MAX_CONCURRENCY: u32 = 8;
+pub const DEFAULT_RETRIES: u32 = 2;
+const MAX_RETRIES: u32 = 5;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Safety stop for controllers that never return a short page
+const MAX_PAGES: u32 = 1000;
+/// Without a total row count, stop after this many failed pages in a row
+const MAX_CONSECUTIVE_FAILURES: u32 = 2;
+
+/// How a list endpoint is paged
+#[derive(Debug, Clone)]
+pub struct PageOptions {
+    pub page_size: u32,
+    /// Pages fetched at once (1 = serial)
+    pub concurrency: u32,
+    /// Extra attempts per page
+    pub retries: u32,
+    /// Delay before the first retry; doubled for each further one
+    pub retry_delay: Duration,
+}
+
+impl Default for PageOptions {
+    fn default() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            concurrency: 1,
+            retries: DEFAULT_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        }
+    }
+}
+
+impl PageOptions {
+    /// Options with configured values clamped to supported ranges
+    pub fn new(page_size: i32, concurrency: i32, retries: i32) -> Self {
+        Self {
+            page_size: page_size.clamp(1, MAX_PAGE_SIZE as i32) as u32,
+            concurrency: concurrency.clamp(1, MAX_CONCURRENCY as i32) as u32,
+            retries: retries.clamp(0, MAX_RETRIES as i32) as u32,
+            ..Self::default()
+        }
+    }
+}
+
+/// One page as returned by the controller
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `totalRows` of the list, when the controller reports it
+    pub total_rows: Option<u64>,
+}
+
+/// Items of all pages that could be fetched
+#[derive(Debug)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    /// Pages that still failed after retries (ascending)
+    pub failed_pages: Vec<u32>,
+    /// Error of the last failed page
+    pub last_error: Option<String>,
+}
+
+impl<T> Paged<T> {
+    /// Whether every page was fetched
+    pub fn is_complete(&self) -> bool {
+        self.failed_pages.is_empty()
+    }
+}
+
+/// Number of pages holding `total_rows`
+fn page_count(total_rows: u64, page_size: u32) -> u32 {
+    total_rows
+        .div_ceil(page_size as u64)
+        .clamp(1, MAX_PAGES as u64) as u32
+}
+
+async fn fetch_with_retry<T, F, Fut>(
+    fetch: &F,
+    page: u32,
+    options: &PageOptions,
+) -> Result<Page<T>, String>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Page<T>, String>>,
+{
+    let mut attempt = 0;
+    loop {
+        match fetch(page).await {
+            Ok(result) => return Ok(result),
+            Err(e) if attempt >= options.retries => return Err(e),
+            Err(e) => {
+                let delay = options.retry_delay * 2u32.pow(attempt);
+                tracing::debug!(
+                    "[Omada] Page {} failed (attempt {}), retrying in {:?}: {}",
+                    page,
+                    attempt + 1,
+                    delay,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Fetch all pages of a list; only a failing first page fails the call
+pub async fn paginate<T, F, Fut>(options: &PageOptions, fetch: F) -> Result<Paged<T>, String>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Page<T>, String>>,
+{
+    let fetch = &fetch;
+    let first = fetch_with_retry(fetch, 1, options).await?;
+    let full = first.items.len() >= options.page_size as usize;
+    let mut paged = Paged {
+        items: first.items,
+        failed_pages: Vec::new(),
+        last_error: None,
+    };
+    if !full {
+        return Ok(paged);
+    }
+
+    match first.total_rows {
+        Some(total_rows) => {
+            let pages = page_count(total_rows, options.page_size);
+            // `buffered` keeps page order while running pages concurrently
+            let results: Vec<_> = stream::iter(2..=pages)
+                .map(|page| async move { (page, fetch_with_retry(fetch, page, options).await) })
+                .buffered(options.concurrency.max(1) as usize)
+                .collect()
+                .await;
+            for (page, result) in results {
+                match result {
+                    Ok(result) => paged.items.extend(result.items),
+                    Err(e) => {
+                        paged.failed_pages.push(page);
+                        paged.last_error = Some(e);
+                    }
+                }
+            }
+        }
+        None => {
+            let mut consecutive_failures = 0;
+            for page in 2..=MAX_PAGES {
+                match fetch_with_retry(fetch, page, options).await {
+                    Ok(result) => {
+                        consecutive_failures = 0;
+                        let count = result.items.len();
+                        paged.items.extend(result.items);
+                        if count < options.page_size as usize {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        paged.failed_pages.push(page);
+                        paged.last_error = Some(e);
+                        consecutive_failures += 1;
+                        if consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(paged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const ROWS: u32 = 250;
+
+    fn options(concurrency: u32) -> PageOptions {
+        PageOptions {
+            page_size: 10,
+            concurrency,
+            retries: 2,
+            retry_delay: Duration::ZERO,
+        }
+    }
+
+    /// Page `n` of rows 0..ROWS
+    fn page(n: u32, size: u32, with_total: bool) -> Page<u32> {
+        let start = (n - 1) * size;
+        Page {
+            items: (start..(start + size).min(ROWS)).collect(),
+            total_rows: with_total.then_some(ROWS as u64),
+        }
+    }
+
+    #[test]
+    fn test_page_options_clamped() {
+        let o = PageOptions::new(0, 50, -1);
+        assert_eq!(
+            (o.page_size, o.concurrency, o.retries),
+            (1, MAX_CONCURRENCY, 0)
+        );
+        assert_eq!(page_count(250, 100), 3);
+        assert_eq!(page_count(0, 100), 1);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_pages_in_order_with_retry() {
+        let calls = AtomicU32::new(0);
+        let result = paginate(&options(4), |n| {
+            // Page 7 fails once, then succeeds
+            let first_try = n == 7 && calls.fetch_add(1, Ordering::SeqCst) == 0;
+            async move {
+                if first_try {
+                    Err("transient".to_string())
+                } else {
+                    Ok(page(n, 10, true))
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert!(result.is_complete());
+        assert_eq!(result.items, (0..ROWS).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_persistent_failure_returns_partial() {
+        for with_total in [true, false] {
+            let result = paginate(&options(3), |n| async move {
+                if n == 17 {
+                    Err(format!("page {} down", n))
+                } else {
+                    Ok(page(n, 10, with_total))
+                }
+            })
+            .await
+            .unwrap();
+            assert_eq!(result.failed_pages, vec![17]);
+            assert_eq!(result.last_error.as_deref(), Some("page 17 down"));
+            assert_eq!(result.items.len() as u32, ROWS - 10);
+            assert!(!result.items.contains(&165));
+        }
+
+        // Nothing fetched at all is still an error
+        let result: Result<Paged<u32>, String> =
+            paginate(&options(1), |_| async { Err("down".to_string()) }).await;
+        assert!(result.is_err());
+    }
+}
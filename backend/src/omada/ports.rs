@@ -0,0 +1,146 @@
+//! Switch port tables: controller port status joined with wired clients
+//!
+//! Stored on the switch's omada_devices document during sync and used to
+//! enrich topology nodes (client port context, switch port summary).
+
+use serde::Serialize;
+
+use crate::db::mongo::omada::{OmadaClientDoc, OmadaSwitchPortDoc};
+use crate::omada::client::{link_speed_mbps, normalize_mac, OmadaClientDevice, OmadaSwitchPort};
+
+/// Up/down counts and PoE draw for a switch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PortsSummary {
+    pub total: usize,
+    pub up: usize,
+    pub down: usize,
+    pub disabled: usize,
+    pub poe_ports: usize,
+    pub poe_power_w: f64,
+}
+
+/// Build the stored port table, attaching wired clients seen on each port
+pub fn build_port_docs(
+    switch_mac: &str,
+    ports: &[OmadaSwitchPort],
+    clients: &[OmadaClientDevice],
+) -> Vec<OmadaSwitchPortDoc> {
+    let switch_mac = normalize_mac(switch_mac);
+    ports
+        .iter()
+        .map(|p| {
+            let client_macs = clients
+                .iter()
+                .filter(|c| !c.wireless.unwrap_or(false) && c.port == Some(p.port))
+                .filter(|c| {
+                    c.switch_mac.as_deref().map(normalize_mac).as_deref() == Some(&switch_mac)
+                })
+                .map(|c| normalize_mac(&c.mac))
+                .collect();
+            OmadaSwitchPortDoc {
+                port: p.port,
+                name: p.name.clone(),
+                disabled: p.disable,
+                link_up: p.link_status == Some(1),
+                link_speed_mbps: p.link_speed.and_then(link_speed_mbps),
+                full_duplex: p.duplex.map(|d| d == 1),
+                poe_enabled: p.poe.unwrap_or(false),
+                poe_power_w: p.poe_power.unwrap_or(0.0),
+                profile_name: p.profile_name.clone(),
+                client_macs,
+            }
+        })
+        .collect()
+}
+
+pub fn summarize(ports: &[OmadaSwitchPortDoc]) -> PortsSummary {
+    let up = ports.iter().filter(|p| p.link_up).count();
+    let poe_power_w: f64 = ports.iter().map(|p| p.poe_power_w).sum();
+    PortsSummary {
+        total: ports.len(),
+        up,
+        down: ports.len() - up,
+        disabled: ports.iter().filter(|p| p.disabled).count(),
+        poe_ports: ports.iter().filter(|p| p.poe_enabled).count(),
+        poe_power_w: (poe_power_w * 10.0).round() / 10.0,
+    }
+}
+
+/// Port context for a wired client (switch + port row), if known
+pub fn client_port_context(
+    client: &OmadaClientDoc,
+    switch_name: Option<&str>,
+    ports: &[OmadaSwitchPortDoc],
+) -> Option<serde_json::Value> {
+    if client.wireless {
+        return None;
+    }
+    let switch_mac = client.switch_mac.as_deref()?;
+    let port_no = client.port?;
+    let port = ports.iter().find(|p| p.port == port_no);
+    Some(serde_json::json!({
+        "switch_mac": normalize_mac(switch_mac),
+        "switch_name": switch_name.or(client.switch_name.as_deref()),
+        "port": port_no,
+        "port_name": port.and_then(|p| p.name.as_deref()),
+        "link_speed_mbps": port.and_then(|p| p.link_speed_mbps),
+        "poe_enabled": port.map(|p| p.poe_enabled),
+        "poe_power_w": port.map(|p| p.poe_power_w),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(no: i32, up: bool, poe: Option<f64>) -> OmadaSwitchPort {
+        OmadaSwitchPort {
+            port: no,
+            name: Some(format!("Port{}", no)),
+            disable: false,
+            link_status: Some(up as i32),
+            link_speed: Some(if up { 3 } else { 0 }),
+            duplex: Some(1),
+            poe: Some(poe.is_some()),
+            poe_power: poe,
+            profile_name: None,
+        }
+    }
+
+    fn wired(mac: &str, switch_mac: &str, port: i32) -> OmadaClientDevice {
+        serde_json::from_value(serde_json::json!({
+            "mac": mac,
+            "wireless": false,
+            "switchMac": switch_mac,
+            "port": port,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ports_join_wired_clients_and_summarize() {
+        let ports = [
+            port(1, true, Some(4.25)),
+            port(2, true, None),
+            port(3, false, None),
+        ];
+        let clients = [
+            wired("aa-aa-aa-aa-aa-01", "11-22-33-44-55-66", 1),
+            wired("aa-aa-aa-aa-aa-02", "11-22-33-44-55-66", 1),
+            wired("aa-aa-aa-aa-aa-03", "99-99-99-99-99-99", 2),
+        ];
+
+        let docs = build_port_docs("11:22:33:44:55:66", &ports, &clients);
+        assert_eq!(docs[0].client_macs, ["AAAAAAAAAA01", "AAAAAAAAAA02"]);
+        assert!(docs[1].client_macs.is_empty());
+        assert_eq!(docs[0].link_speed_mbps, Some(1000));
+        assert_eq!(docs[2].link_speed_mbps, None);
+
+        let summary = summarize(&docs);
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.up, 2);
+        assert_eq!(summary.down, 1);
+        assert_eq!(summary.poe_ports, 1);
+        assert_eq!(summary.poe_power_w, 4.3);
+    }
+}
@@ -1,37 +1,79 @@
 //! OmadaSyncer: Periodic data synchronization for all controllers
 //!
 //! Runs in a background tokio task. Every 60 seconds, iterates all registered
-//! controllers, fetches sites/devices/clients/wireguard data, and upserts to MongoDB.
+//! controllers, fetches sites/devices/clients/switch ports/wireguard data, and
+//! upserts to MongoDB.
+//!
+//! Client lists are paged per `omada_page_size` / `omada_page_concurrency` /
+//! `omada_page_retries`. When some pages still fail, the clients that were
+//! fetched are merged (nothing is marked offline) and the controller status
+//! becomes "partial" instead of failing the whole cycle.
+//!
+//! Repeated failures of a whole controller mark it offline (see `health`).
 
 use std::sync::Arc;
 use tokio::time::{self, Duration};
 
+use crate::db::mongo::ingest_writes::IngestPacing;
 use crate::db::mongo::MongoDb;
 use crate::db::mysql::MySqlDb;
+use crate::new_device::NewDeviceWatch;
 use crate::node_order::NodeOrderIngester;
+use crate::notify::DiscordNotifier;
+use crate::omada::client::normalize_mac;
+use crate::omada::health::{self, Transition, DEFAULT_OFFLINE_AFTER_FAILURES};
 use crate::omada::manager::OmadaManager;
+use crate::omada::paging::{PageOptions, DEFAULT_PAGE_SIZE, DEFAULT_RETRIES};
+use crate::omada::ports;
+use crate::restart::DrainGate;
 use crate::user_object_ingester::UserObjectIngester;
 
 /// Background synchronization service
 pub struct OmadaSyncer {
     manager: Arc<OmadaManager>,
     mongo: Arc<MongoDb>,
+    mysql: Arc<MySqlDb>,
     ingester: UserObjectIngester,
     node_order_ingester: NodeOrderIngester,
+    /// Cycles are skipped and tracked for pre-restart drains
+    drain: Arc<DrainGate>,
+    /// Controller-down / recovery notifications (background instance)
+    notifier: Option<Arc<DiscordNotifier>>,
 }
 
 impl OmadaSyncer {
     pub fn new(manager: Arc<OmadaManager>, mongo: Arc<MongoDb>, mysql: Arc<MySqlDb>) -> Self {
-        let ingester = UserObjectIngester::new(mongo.clone(), mysql);
+        let ingester = UserObjectIngester::new(mongo.clone(), mysql.clone());
         let node_order_ingester = NodeOrderIngester::new(mongo.clone());
         Self {
             manager,
             mongo,
+            mysql,
             ingester,
             node_order_ingester,
+            drain: Arc::new(DrainGate::default()),
+            notifier: None,
         }
     }
 
+    /// Share the restart drain gate (background instance)
+    pub fn with_drain(mut self, drain: Arc<DrainGate>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Report first-seen devices from this syncer's ingestion
+    pub fn with_new_device_watch(mut self, watch: Arc<NewDeviceWatch>) -> Self {
+        self.ingester = self.ingester.with_new_device_watch(watch);
+        self
+    }
+
+    /// Notify when a controller goes offline or recovers
+    pub fn with_notifier(mut self, notifier: Arc<DiscordNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
     /// Start the background sync loop (runs forever)
     pub async fn start(self: Arc<Self>) {
         tracing::info!("[OmadaSync] Starting background sync (interval: 60s)");
@@ -40,7 +82,14 @@ impl OmadaSyncer {
         time::sleep(Duration::from_secs(5)).await;
 
         loop {
-            self.sync_all_controllers().await;
+            if self.drain.is_draining() {
+                tracing::debug!("[OmadaSync] Restart drain in progress, skipping cycle");
+            } else {
+                let _cycle = self.drain.sync_cycle("omada");
+                self.mongo
+                    .set_ingest_pacing(IngestPacing::load(&self.mysql).await);
+                self.sync_all_controllers().await;
+            }
             time::sleep(Duration::from_secs(60)).await;
         }
     }
@@ -56,14 +105,120 @@ impl OmadaSyncer {
         tracing::debug!("[OmadaSync] Syncing {} controllers", controller_ids.len());
 
         for id in controller_ids {
-            if let Err(e) = self.sync_controller(&id).await {
+            if let Err(e) = self.sync_tracked(&id).await {
                 tracing::warn!("[OmadaSync] Controller {} sync failed: {}", id, e);
-                let _ = self
-                    .mongo
-                    .update_omada_controller_status(&id, "error", Some(&e))
+            }
+        }
+    }
+
+    /// Sync a controller and update its health from the outcome
+    async fn sync_tracked(&self, id: &str) -> Result<(), String> {
+        let before = self.mongo.get_omada_controller(id).await.ok().flatten();
+        let offline_since = before.as_ref().and_then(|c| c.offline_since.clone());
+        let name = before
+            .map(|c| c.display_name)
+            .unwrap_or_else(|| id.to_string());
+
+        let result = self.sync_controller(id).await;
+        match &result {
+            Ok(()) => {
+                if health::after_success(offline_since.is_some()) == Transition::Recovered {
+                    self.controller_recovered(id, &name, offline_since.as_deref())
+                        .await;
+                }
+            }
+            Err(e) => {
+                self.controller_failed(id, &name, e, offline_since.is_some())
                     .await;
             }
         }
+        result
+    }
+
+    /// Count a failed sync; at the threshold, mark the controller and its
+    /// nodes offline and send the one controller-down notification
+    async fn controller_failed(&self, id: &str, name: &str, error: &str, offline: bool) {
+        let failures = match self.mongo.record_omada_controller_failure(id, error).await {
+            Ok(failures) => failures,
+            Err(e) => {
+                tracing::warn!("[OmadaSync] Controller {} failure not recorded: {}", id, e);
+                return;
+            }
+        };
+        let threshold = self
+            .mysql
+            .get_setting_i32(
+                "omada_controller_offline_after_failures",
+                DEFAULT_OFFLINE_AFTER_FAILURES,
+            )
+            .await
+            .unwrap_or(DEFAULT_OFFLINE_AFTER_FAILURES)
+            .max(1) as u32;
+        if health::after_failure(failures, threshold, offline) != Transition::WentOffline {
+            return;
+        }
+
+        if let Err(e) = self.mongo.set_omada_controller_offline(id).await {
+            tracing::warn!("[OmadaSync] Controller {} not marked offline: {}", id, e);
+        }
+        let nodes = match self.mongo.mark_controller_entries_unknown(id).await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                tracing::warn!("[OmadaSync] Nodes of controller {} not marked: {}", id, e);
+                0
+            }
+        };
+        tracing::error!(
+            "[OmadaSync] Controller {} offline after {} failed syncs; {} nodes now {}",
+            id,
+            failures,
+            nodes,
+            health::UNKNOWN_CONTROLLER_OFFLINE
+        );
+        if let Some(notifier) = &self.notifier {
+            notifier
+                .notify_omada_controller_offline(name, failures, nodes, error)
+                .await;
+        }
+    }
+
+    /// First successful sync after an outage: the sync's ingestion has
+    /// reconciled the nodes; entries it no longer covers get their
+    /// pre-outage state back
+    async fn controller_recovered(&self, id: &str, name: &str, offline_since: Option<&str>) {
+        let leftover = self
+            .mongo
+            .restore_controller_entries(id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("[OmadaSync] Nodes of controller {} not restored: {}", id, e);
+                0
+            });
+        tracing::info!(
+            "[OmadaSync] Controller {} back online ({} nodes not reported restored)",
+            id,
+            leftover
+        );
+        if let Some(notifier) = &self.notifier {
+            notifier
+                .notify_omada_controller_recovery(name, offline_since)
+                .await;
+        }
+    }
+
+    /// Client list paging from settings
+    async fn page_options(&self) -> PageOptions {
+        let setting = |key: &'static str, default: i32| async move {
+            self.mysql
+                .get_setting_i32(key, default)
+                .await
+                .unwrap_or(default)
+        };
+        PageOptions::new(
+            setting("omada_page_size", DEFAULT_PAGE_SIZE as i32).await,
+            setting("omada_page_concurrency", 1).await,
+            setting("omada_page_retries", DEFAULT_RETRIES as i32).await,
+        )
     }
 
     /// Sync a single controller: fetch all data and upsert to MongoDB
@@ -103,18 +258,21 @@ impl OmadaSyncer {
         }
 
         // 3. For each site, fetch devices, clients, WG peers
+        let page_options = self.page_options().await;
+        let mut incomplete_sites = Vec::new();
         let mut total_devices = 0usize;
         let mut total_clients = 0usize;
         let mut total_wg_peers = 0usize;
 
         for site in &sites {
             // Devices
-            match client.get_devices_for_site(&site.site_id).await {
+            let site_devices = match client.get_devices_for_site(&site.site_id).await {
                 Ok(devices) => {
                     total_devices += devices.len();
                     self.mongo
                         .upsert_omada_devices(controller_id, &site.site_id, &devices)
                         .await?;
+                    devices
                 }
                 Err(e) => {
                     tracing::warn!(
@@ -122,16 +280,42 @@ impl OmadaSyncer {
                         site.site_id,
                         e
                     );
+                    Vec::new()
                 }
-            }
+            };
 
-            // Clients
-            match client.get_clients_for_site(&site.site_id).await {
-                Ok(clients) => {
-                    total_clients += clients.len();
+            // Clients (a partial list only updates what was fetched)
+            let site_clients = match client
+                .get_clients_for_site(&site.site_id, &page_options)
+                .await
+            {
+                Ok(paged) => {
+                    total_clients += paged.items.len();
                     self.mongo
-                        .upsert_omada_clients(controller_id, &site.site_id, &clients)
+                        .upsert_omada_clients(controller_id, &site.site_id, &paged.items)
                         .await?;
+                    if paged.is_complete() {
+                        let macs: Vec<String> =
+                            paged.items.iter().map(|c| normalize_mac(&c.mac)).collect();
+                        self.mongo
+                            .mark_omada_clients_inactive_except(controller_id, &site.site_id, &macs)
+                            .await?;
+                    } else {
+                        tracing::warn!(
+                            "[OmadaSync] Clients for site {} incomplete: {} fetched, pages {:?} failed: {}",
+                            site.site_id,
+                            paged.items.len(),
+                            paged.failed_pages,
+                            paged.last_error.as_deref().unwrap_or("unknown")
+                        );
+                        incomplete_sites.push(format!(
+                            "site {}: client pages {:?} failed ({})",
+                            site.name,
+                            paged.failed_pages,
+                            paged.last_error.as_deref().unwrap_or("unknown")
+                        ));
+                    }
+                    paged.items
                 }
                 Err(e) => {
                     tracing::warn!(
@@ -139,6 +323,26 @@ impl OmadaSyncer {
                         site.site_id,
                         e
                     );
+                    Vec::new()
+                }
+            };
+
+            // Switch ports (joined with the wired clients just fetched)
+            for switch in site_devices.iter().filter(|d| d.device_type == "switch") {
+                match client.get_switch_ports(&site.site_id, &switch.mac).await {
+                    Ok(ports) => {
+                        let docs = ports::build_port_docs(&switch.mac, &ports, &site_clients);
+                        self.mongo
+                            .set_omada_device_ports(controller_id, &switch.mac, &docs)
+                            .await?;
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "[OmadaSync] Ports fetch failed for switch {}: {}",
+                            switch.mac,
+                            e
+                        );
+                    }
                 }
             }
 
@@ -160,10 +364,20 @@ impl OmadaSyncer {
             }
         }
 
-        // 4. Update status to connected
-        self.mongo
-            .update_omada_controller_status(controller_id, "connected", None)
-            .await?;
+        // 4. Update status: connected, or partial when client lists were cut short
+        if incomplete_sites.is_empty() {
+            self.mongo
+                .update_omada_controller_status(controller_id, "connected", None)
+                .await?;
+        } else {
+            self.mongo
+                .update_omada_controller_status(
+                    controller_id,
+                    "partial",
+                    Some(&incomplete_sites.join("; ")),
+                )
+                .await?;
+        }
 
         // 5. Ingest into user_object_detail SSoT
         if let Err(e) = self.ingester.ingest_omada(controller_id).await {
@@ -196,6 +410,6 @@ impl OmadaSyncer {
 
     /// Manual sync trigger for a specific controller
     pub async fn sync_one(&self, controller_id: &str) -> Result<(), String> {
-        self.sync_controller(controller_id).await
+        self.sync_tracked(controller_id).await
     }
 }
@@ -0,0 +1,372 @@
+//! Omada controller webhook receiver
+//!
+//! The controller pushes event notifications to
+//! `POST /api/omada/webhook/:controller_id`. Device online/offline and client
+//! connect/disconnect events update the cached collections and the affected
+//! topology node immediately; the 60s syncer remains the consistency backstop.
+//!
+//! Authentication (any one, against the per-controller secret):
+//! - `X-Webhook-Secret: <secret>`
+//! - `X-Webhook-Signature: sha256=<hex HMAC-SHA256 of the raw body>`
+//! - `shardSecret` field in the payload (Omada's native webhook secret)
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::db::mongo::MongoDb;
+use crate::db::mysql::MySqlDb;
+use crate::omada::client::normalize_mac;
+
+/// How long an event id is remembered for replay protection
+const DEDUP_WINDOW: Duration = Duration::from_secs(600);
+
+/// Events with a timestamp older than this are rejected as stale
+pub const MAX_EVENT_AGE_MS: i64 = 10 * 60 * 1000;
+
+/// Classified webhook event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEventKind {
+    DeviceOnline,
+    DeviceOffline,
+    ClientConnected,
+    ClientDisconnected,
+    Other,
+}
+
+impl WebhookEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeviceOnline => "device_online",
+            Self::DeviceOffline => "device_offline",
+            Self::ClientConnected => "client_connected",
+            Self::ClientDisconnected => "client_disconnected",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Parsed webhook payload
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    /// Event id from the payload, or a SHA-256 of the raw body
+    pub event_id: String,
+    pub kind: WebhookEventKind,
+    /// Normalized MAC of the affected device/client
+    pub mac: Option<String>,
+    pub timestamp_ms: Option<i64>,
+    pub shard_secret: Option<String>,
+    pub raw: serde_json::Value,
+}
+
+/// Parse a webhook body. Accepts structured payloads
+/// (`{"eventId", "event", "mac", "timestamp"}` and common aliases) as well as
+/// Omada's native text form (`{"text": ["..."], "timestamp", "shardSecret"}`).
+pub fn parse_event(body: &[u8]) -> Result<WebhookEvent, String> {
+    let raw: serde_json::Value =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON payload: {}", e))?;
+    let obj = raw
+        .as_object()
+        .ok_or_else(|| "Payload must be a JSON object".to_string())?;
+
+    let str_field = |keys: &[&str]| -> Option<String> {
+        keys.iter().find_map(|k| match obj.get(*k) {
+            Some(serde_json::Value::String(s)) if !s.is_empty() => Some(s.clone()),
+            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        })
+    };
+
+    let event_id = str_field(&["eventId", "event_id", "id"])
+        .unwrap_or_else(|| hex::encode(Sha256::digest(body)));
+
+    let text = obj
+        .get("text")
+        .and_then(|t| t.as_array())
+        .map(|lines| {
+            lines
+                .iter()
+                .filter_map(|l| l.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    let descriptor = str_field(&["event", "eventType", "event_type", "type", "key"])
+        .unwrap_or_else(|| text.clone());
+
+    let mac = str_field(&["mac", "deviceMac", "device_mac", "clientMac", "client_mac"])
+        .or_else(|| find_mac(&text))
+        .map(|m| normalize_mac(&m));
+
+    let timestamp_ms = obj.get("timestamp").and_then(|t| t.as_i64());
+    let shard_secret = str_field(&["shardSecret"]);
+
+    Ok(WebhookEvent {
+        event_id,
+        kind: classify(&descriptor),
+        mac,
+        timestamp_ms,
+        shard_secret,
+        raw,
+    })
+}
+
+/// Map an event type or message text to a kind
+pub fn classify(descriptor: &str) -> WebhookEventKind {
+    let d = descriptor.to_lowercase();
+    let is_client = d.contains("client") || d.contains("station");
+
+    if d.contains("disconnect") {
+        if is_client || !d.contains("device") {
+            WebhookEventKind::ClientDisconnected
+        } else {
+            WebhookEventKind::DeviceOffline
+        }
+    } else if d.contains("connect") && !d.contains("reconnect") {
+        if is_client || !d.contains("device") {
+            WebhookEventKind::ClientConnected
+        } else {
+            WebhookEventKind::DeviceOnline
+        }
+    } else if d.contains("offline") || d.contains("lost") {
+        if is_client {
+            WebhookEventKind::ClientDisconnected
+        } else {
+            WebhookEventKind::DeviceOffline
+        }
+    } else if d.contains("online") || d.contains("adopted") {
+        if is_client {
+            WebhookEventKind::ClientConnected
+        } else {
+            WebhookEventKind::DeviceOnline
+        }
+    } else {
+        WebhookEventKind::Other
+    }
+}
+
+/// Find the first MAC address (any common separator) in free text
+fn find_mac(text: &str) -> Option<String> {
+    let re = regex::Regex::new(r"(?i)\b[0-9a-f]{2}(?:[:-][0-9a-f]{2}){5}\b").ok()?;
+    re.find(text).map(|m| m.as_str().to_string())
+}
+
+/// Verify the request against the controller's secret
+pub fn verify(
+    secret: &str,
+    body: &[u8],
+    header_secret: Option<&str>,
+    header_signature: Option<&str>,
+    shard_secret: Option<&str>,
+) -> bool {
+    if let Some(sig) = header_signature {
+        let hex_sig = sig.strip_prefix("sha256=").unwrap_or(sig);
+        let Ok(expected) = hex::decode(hex_sig.trim()) else {
+            return false;
+        };
+        let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+        return mac.verify_slice(&expected).is_ok();
+    }
+
+    header_secret
+        .or(shard_secret)
+        .map(|provided| constant_time_eq(provided.as_bytes(), secret.as_bytes()))
+        .unwrap_or(false)
+}
+
+/// Byte comparison that does not short-circuit on the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generate a new random webhook secret (64 hex chars)
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Replay protection: remembers recently seen event ids per controller
+#[derive(Default)]
+pub struct WebhookDedup {
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl WebhookDedup {
+    /// Returns true if the event is new (and records it), false for a replay
+    pub fn check_and_mark(&self, controller_id: &str, event_id: &str) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        seen.retain(|_, at| now.duration_since(*at) < DEDUP_WINDOW);
+
+        let key = format!("{}:{}", controller_id, event_id);
+        if seen.contains_key(&key) {
+            return false;
+        }
+        seen.insert(key, now);
+        true
+    }
+
+    /// Forget an event so a retry of it is applied again (after a failed apply)
+    pub fn unmark(&self, controller_id: &str, event_id: &str) {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.remove(&format!("{}:{}", controller_id, event_id));
+    }
+}
+
+/// Apply an event to the cached collections and the affected topology node.
+/// Returns a list of what was updated (recorded in the operation log).
+pub async fn apply_event(
+    mongo: &MongoDb,
+    mysql: &MySqlDb,
+    controller_id: &str,
+    event: &WebhookEvent,
+) -> Result<Vec<String>, String> {
+    let mut applied = Vec::new();
+    let Some(mac) = event.mac.as_deref() else {
+        return Ok(applied);
+    };
+
+    let (online, node_status) = match event.kind {
+        WebhookEventKind::DeviceOnline => {
+            if mongo.set_omada_device_status(controller_id, mac, 1).await? {
+                applied.push("omada_devices".to_string());
+            }
+            (true, "online")
+        }
+        WebhookEventKind::DeviceOffline => {
+            if mongo.set_omada_device_status(controller_id, mac, 0).await? {
+                applied.push("omada_devices".to_string());
+            }
+            (false, "offline")
+        }
+        WebhookEventKind::ClientConnected => {
+            if mongo
+                .set_omada_client_active(controller_id, mac, true)
+                .await?
+            {
+                applied.push("omada_clients".to_string());
+            }
+            (true, "active")
+        }
+        WebhookEventKind::ClientDisconnected => {
+            if mongo
+                .set_omada_client_active(controller_id, mac, false)
+                .await?
+            {
+                applied.push("omada_clients".to_string());
+            }
+            (false, "inactive")
+        }
+        WebhookEventKind::Other => return Ok(applied),
+    };
+
+    if mongo.update_node_order_status(mac, node_status).await? {
+        applied.push("cg_node_order".to_string());
+    }
+
+    // user_object_detail: same rules as the ingester (admin overrides win)
+    if let Some(node) = mongo.get_user_object_detail_by_mac(mac).await? {
+        let state_type = if online { "online" } else { "offline" };
+        if node.source == "omada"
+            && node.state_type != state_type
+            && !node.state_type.starts_with("Static")
+        {
+            mongo
+                .update_user_object_detail_state_type(&node.id, state_type)
+                .await?;
+            let _ = mysql
+                .insert_device_state_change(&node.id, state_type, Some(&node.state_type), "webhook")
+                .await;
+            applied.push("user_object_detail".to_string());
+        }
+    }
+
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("device_online"), WebhookEventKind::DeviceOnline);
+        assert_eq!(
+            classify("EVENT_AP_OFFLINE"),
+            WebhookEventKind::DeviceOffline
+        );
+        assert_eq!(
+            classify("client_disconnected"),
+            WebhookEventKind::ClientDisconnected
+        );
+        assert_eq!(
+            classify("[client:wireless]phone is connected to AP"),
+            WebhookEventKind::ClientConnected
+        );
+        assert_eq!(classify("firmware upgraded"), WebhookEventKind::Other);
+    }
+
+    #[test]
+    fn test_parse_structured_event() {
+        let body = br#"{"eventId":"e1","event":"device_offline","mac":"aa-bb-cc-dd-ee-ff","timestamp":1700000000000}"#;
+        let event = parse_event(body).unwrap();
+        assert_eq!(event.event_id, "e1");
+        assert_eq!(event.kind, WebhookEventKind::DeviceOffline);
+        assert_eq!(event.mac.as_deref(), Some("AABBCCDDEEFF"));
+        assert_eq!(event.timestamp_ms, Some(1700000000000));
+    }
+
+    #[test]
+    fn test_parse_native_text_event() {
+        let body = br#"{"Site":"Default","shardSecret":"s3","text":["[client]AA:BB:CC:00:11:22 was disconnected from SSID office"]}"#;
+        let event = parse_event(body).unwrap();
+        assert_eq!(event.kind, WebhookEventKind::ClientDisconnected);
+        assert_eq!(event.mac.as_deref(), Some("AABBCC001122"));
+        assert_eq!(event.shard_secret.as_deref(), Some("s3"));
+        // No id in payload: falls back to a body hash
+        assert_eq!(event.event_id.len(), 64);
+    }
+
+    #[test]
+    fn test_verify() {
+        let body = b"{}";
+        assert!(verify("secret", body, Some("secret"), None, None));
+        assert!(!verify("secret", body, Some("wrong"), None, None));
+        assert!(verify("secret", body, None, None, Some("secret")));
+        assert!(!verify("secret", body, None, None, None));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let sig = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert!(verify("secret", body, None, Some(&sig), None));
+        assert!(!verify("other", body, None, Some(&sig), None));
+    }
+
+    #[test]
+    fn test_dedup() {
+        let dedup = WebhookDedup::default();
+        assert!(dedup.check_and_mark("c1", "e1"));
+        assert!(!dedup.check_and_mark("c1", "e1"));
+        assert!(dedup.check_and_mark("c2", "e1"));
+    }
+
+    #[test]
+    fn test_dedup_unmark_allows_retry() {
+        let dedup = WebhookDedup::default();
+        assert!(dedup.check_and_mark("c1", "e1"));
+        dedup.unmark("c1", "e1");
+        assert!(dedup.check_and_mark("c1", "e1"));
+        assert!(!dedup.check_and_mark("c1", "e1"));
+    }
+}
@@ -3,6 +3,7 @@
 //! Uses `tokio::process::Command` with `sshpass` for password-based SSH.
 //! Command mapping based on is10 (aranea_ISMS) patterns.
 
+use std::collections::HashMap;
 use std::time::Duration;
 use tokio::process::Command;
 
@@ -52,6 +53,94 @@ pub struct RouterClientEntry {
     pub mac: String,
     pub ip: String,
     pub hostname: Option<String>,
+    /// Associated to one of the router's wireless interfaces
+    pub wireless: bool,
+    pub ssid: Option<String>,
+    /// "2.4GHz" / "5GHz" / "6GHz"
+    pub band: Option<String>,
+}
+
+/// Wireless association of one station (from iwinfo / hostapd_cli)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WirelessStation {
+    pub interface: String,
+    pub ssid: Option<String>,
+    pub band: Option<String>,
+}
+
+/// Per-interface dump: ESSID/Channel lines followed by station lines
+/// (`iwinfo assoclist`, falling back to `hostapd_cli all_sta`)
+const WIRELESS_STATIONS_CMD: &str = concat!(
+    "for i in $(iwinfo 2>/dev/null | awk '/ESSID/ {print $1}'); do ",
+    "echo \"---WIFI $i---\"; ",
+    "iwinfo $i info 2>/dev/null | grep -E 'ESSID|Channel'; ",
+    "iwinfo $i assoclist 2>/dev/null | grep -qiE '^([0-9a-f]{2}:){5}' ",
+    "&& iwinfo $i assoclist 2>/dev/null ",
+    "|| hostapd_cli -i $i all_sta 2>/dev/null; ",
+    "done"
+);
+
+/// Parse the output of `WIRELESS_STATIONS_CMD` into station MAC -> association
+pub fn parse_wireless_stations(output: &str) -> HashMap<String, WirelessStation> {
+    let mut stations = HashMap::new();
+    let mut current: Option<WirelessStation> = None;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(iface) = line
+            .strip_prefix("---WIFI ")
+            .and_then(|r| r.strip_suffix("---"))
+        {
+            current = Some(WirelessStation {
+                interface: iface.to_string(),
+                ssid: None,
+                band: None,
+            });
+            continue;
+        }
+        let Some(cur) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(rest) = line.split_once("ESSID:").map(|(_, r)| r.trim()) {
+            let ssid = rest.trim_matches('"');
+            if !ssid.is_empty() && ssid != "unknown" {
+                cur.ssid = Some(ssid.to_string());
+            }
+        } else if line.starts_with("Channel:") || line.contains(" Channel:") {
+            // "Channel: 36 (5.180 GHz)"
+            cur.band = line
+                .split_once('(')
+                .and_then(|(_, r)| r.split_whitespace().next())
+                .and_then(|f| f.parse::<f64>().ok())
+                .map(band_for_ghz);
+        } else if let Some(first) = line.split_whitespace().next() {
+            if is_mac(first) {
+                stations.insert(crate::omada::client::normalize_mac(first), cur.clone());
+            }
+        }
+    }
+
+    stations
+}
+
+fn band_for_ghz(freq: f64) -> String {
+    if freq < 3.0 {
+        "2.4GHz"
+    } else if freq < 5.9 {
+        "5GHz"
+    } else {
+        "6GHz"
+    }
+    .to_string()
+}
+
+fn is_mac(s: &str) -> bool {
+    let parts: Vec<&str> = s.split(':').collect();
+    parts.len() == 6
+        && parts
+            .iter()
+            .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
 }
 
 // ============================================================================
@@ -201,6 +290,14 @@ impl SshRouterClient {
         let output = self
             .ssh_exec("cat /tmp/dhcp.leases 2>/dev/null || echo ''")
             .await?;
+        // Wireless associations are best-effort (wired-only routers have no iwinfo)
+        let stations = match self.ssh_exec(WIRELESS_STATIONS_CMD).await {
+            Ok(out) => parse_wireless_stations(&out),
+            Err(e) => {
+                tracing::debug!("[OpenWrt] {} wireless station dump failed: {}", self.ip, e);
+                HashMap::new()
+            }
+        };
         let mut clients = Vec::new();
 
         for line in output.lines() {
@@ -214,7 +311,15 @@ impl SshRouterClient {
                     None
                 };
 
-                clients.push(RouterClientEntry { mac, ip, hostname });
+                let station = stations.get(&mac);
+                clients.push(RouterClientEntry {
+                    wireless: station.is_some(),
+                    ssid: station.and_then(|s| s.ssid.clone()),
+                    band: station.and_then(|s| s.band.clone()),
+                    mac,
+                    ip,
+                    hostname,
+                });
             }
         }
 
@@ -291,6 +396,9 @@ impl SshRouterClient {
                     mac,
                     ip,
                     hostname: None,
+                    wireless: false,
+                    ssid: None,
+                    band: None,
                 });
             }
         }
@@ -325,3 +433,35 @@ impl SshRouterClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iwinfo_and_hostapd_stations() {
+        let output = r#"---WIFI wlan0---
+wlan0     ESSID: "Home-2G"
+          Mode: Master  Channel: 6 (2.437 GHz)  HT Mode: HT20
+AA:BB:CC:DD:EE:01  -52 dBm / -95 dBm (SNR 43)  10 ms ago
+	RX: 72.2 MBit/s, MCS 7, 20MHz                     1234 Pkts.
+---WIFI wlan1---
+wlan1     ESSID: "Home-5G"
+          Mode: Master  Channel: 36 (5.180 GHz)  HT Mode: VHT80
+aa:bb:cc:dd:ee:02
+flags=[AUTH][ASSOC][AUTHORIZED]
+rx_packets=10
+"#;
+        let stations = parse_wireless_stations(output);
+        assert_eq!(stations.len(), 2);
+
+        let first = &stations[&crate::omada::client::normalize_mac("AA:BB:CC:DD:EE:01")];
+        assert_eq!(first.interface, "wlan0");
+        assert_eq!(first.ssid.as_deref(), Some("Home-2G"));
+        assert_eq!(first.band.as_deref(), Some("2.4GHz"));
+
+        let second = &stations[&crate::omada::client::normalize_mac("aa:bb:cc:dd:ee:02")];
+        assert_eq!(second.ssid.as_deref(), Some("Home-5G"));
+        assert_eq!(second.band.as_deref(), Some("5GHz"));
+    }
+}
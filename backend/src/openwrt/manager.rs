@@ -87,6 +87,8 @@ impl OpenWrtManager {
             product_type: "101".to_string(), // Router
             network_device_type: "Router".to_string(),
             last_polled_at: None,
+            poll_interval_sec: None,
+            paused: false,
             created_at: now.clone(),
             updated_at: now,
         };
@@ -1,61 +1,148 @@
 //! OpenWrtSyncer: Periodic SSH polling for all registered routers
 //!
-//! Runs in a background tokio task. Every 30 seconds, polls all routers
-//! via SSH and upserts status/clients to MongoDB.
+//! Runs in a background tokio task. Every 10 seconds, polls the routers
+//! whose own interval (default 30s) has elapsed via SSH and upserts
+//! status/clients to MongoDB. Paused routers are skipped.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
 use tokio::time::{self, Duration};
 
+use crate::db::mongo::ingest_writes::IngestPacing;
 use crate::db::mongo::MongoDb;
 use crate::db::mysql::MySqlDb;
+use crate::new_device::NewDeviceWatch;
 use crate::node_order::NodeOrderIngester;
 use crate::openwrt::manager::OpenWrtManager;
+use crate::poll_schedule::{effective_interval, PollOutcome, PollSchedule};
+use crate::restart::DrainGate;
 use crate::user_object_ingester::UserObjectIngester;
 
+/// Scheduler tick (finest effective poll granularity)
+const TICK_SECS: u64 = 10;
+/// Poll interval for routers without an override
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 30;
+
 /// Background synchronization service for OpenWrt/AsusWrt routers
 pub struct OpenWrtSyncer {
     manager: Arc<OpenWrtManager>,
     mongo: Arc<MongoDb>,
+    mysql: Arc<MySqlDb>,
     ingester: UserObjectIngester,
     node_order_ingester: NodeOrderIngester,
+    schedule: Mutex<PollSchedule>,
+    /// Cycles are skipped and tracked for pre-restart drains
+    drain: Arc<DrainGate>,
 }
 
 impl OpenWrtSyncer {
     pub fn new(manager: Arc<OpenWrtManager>, mongo: Arc<MongoDb>, mysql: Arc<MySqlDb>) -> Self {
-        let ingester = UserObjectIngester::new(mongo.clone(), mysql);
+        let ingester = UserObjectIngester::new(mongo.clone(), mysql.clone());
         let node_order_ingester = NodeOrderIngester::new(mongo.clone());
         Self {
             manager,
             mongo,
+            mysql,
             ingester,
             node_order_ingester,
+            schedule: Mutex::new(PollSchedule::new()),
+            drain: Arc::new(DrainGate::default()),
         }
     }
 
+    /// Share the restart drain gate (background instance)
+    pub fn with_drain(mut self, drain: Arc<DrainGate>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Report first-seen devices from this syncer's ingestion
+    pub fn with_new_device_watch(mut self, watch: Arc<NewDeviceWatch>) -> Self {
+        self.ingester = self.ingester.with_new_device_watch(watch);
+        self
+    }
+
     /// Start the background sync loop (runs forever)
     pub async fn start(self: Arc<Self>) {
-        tracing::info!("[OpenWrtSync] Starting background sync (interval: 30s)");
+        tracing::info!(
+            "[OpenWrtSync] Starting background sync (default interval: {}s, tick: {}s)",
+            DEFAULT_POLL_INTERVAL_SECS,
+            TICK_SECS
+        );
 
         // Initial sync after 10 seconds
         time::sleep(Duration::from_secs(10)).await;
 
         loop {
-            self.sync_all_routers().await;
-            time::sleep(Duration::from_secs(30)).await;
+            if self.drain.is_draining() {
+                tracing::debug!("[OpenWrtSync] Restart drain in progress, skipping cycle");
+            } else {
+                let _cycle = self.drain.sync_cycle("openwrt");
+                self.mongo
+                    .set_ingest_pacing(IngestPacing::load(&self.mysql).await);
+                self.sync_due_routers().await;
+            }
+            time::sleep(Duration::from_secs(TICK_SECS)).await;
         }
     }
 
-    /// Sync all registered routers
-    async fn sync_all_routers(&self) {
+    /// Poll every registered router whose interval has elapsed
+    async fn sync_due_routers(&self) {
         let router_ids = self.manager.list_router_ids().await;
 
         if router_ids.is_empty() {
             return;
         }
 
-        tracing::debug!("[OpenWrtSync] Syncing {} routers", router_ids.len());
+        let docs: HashMap<String, (bool, u64)> = match self.mongo.list_openwrt_routers().await {
+            Ok(docs) => docs
+                .into_iter()
+                .map(|d| {
+                    let interval =
+                        effective_interval(d.poll_interval_sec, DEFAULT_POLL_INTERVAL_SECS);
+                    (d.router_id, (d.paused, interval))
+                })
+                .collect(),
+            Err(e) => {
+                tracing::warn!("[OpenWrtSync] Failed to list routers: {}", e);
+                return;
+            }
+        };
+
+        let now = Utc::now();
+        let due: Vec<String> = {
+            let mut schedule = self.schedule.lock().unwrap();
+            schedule.retain(|id| router_ids.iter().any(|r| r == id));
+
+            let mut due = Vec::new();
+            for id in &router_ids {
+                let (paused, interval) = docs
+                    .get(id)
+                    .copied()
+                    .unwrap_or((false, DEFAULT_POLL_INTERVAL_SECS));
+                if paused {
+                    schedule.forget(id);
+                } else if schedule.is_due(id, interval, now) {
+                    schedule.mark_attempt(id, now);
+                    due.push(id.clone());
+                }
+            }
+            due
+        };
+
+        if due.is_empty() {
+            return;
+        }
 
-        for id in router_ids {
+        tracing::debug!(
+            "[OpenWrtSync] Syncing {} of {} routers",
+            due.len(),
+            router_ids.len()
+        );
+
+        for id in due {
             if let Err(e) = self.poll_router(&id).await {
                 tracing::warn!("[OpenWrtSync] Router {} sync failed: {}", id, e);
                 let _ = self
@@ -139,8 +226,18 @@ impl OpenWrtSyncer {
         Ok(())
     }
 
-    /// Manual poll trigger for a specific router
-    pub async fn poll_one(&self, router_id: &str) -> Result<(), String> {
-        self.poll_router(router_id).await
+    /// Manual poll trigger for a specific router (paused routers are left alone)
+    pub async fn poll_one(&self, router_id: &str) -> Result<PollOutcome, String> {
+        let paused = self
+            .mongo
+            .get_openwrt_router(router_id)
+            .await?
+            .map(|r| r.paused)
+            .unwrap_or(false);
+        if paused {
+            return Ok(PollOutcome::Paused);
+        }
+        self.poll_router(router_id).await?;
+        Ok(PollOutcome::Polled)
     }
 }
@@ -0,0 +1,101 @@
+//! Per-device poll scheduling shared by the OpenWrt and external syncers
+//!
+//! Syncers tick at a short fixed interval and poll only the devices whose
+//! own interval has elapsed since their last attempt. The next due time is
+//! derived from the device's *current* interval, so edits apply immediately.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Result of a single poll request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollOutcome {
+    Polled,
+    /// Device is paused; cached data left untouched
+    Paused,
+}
+
+/// Device override, or the syncer default when unset/zero
+pub fn effective_interval(override_secs: Option<u32>, default_secs: u64) -> u64 {
+    override_secs
+        .filter(|s| *s > 0)
+        .map(u64::from)
+        .unwrap_or(default_secs)
+}
+
+/// Last poll attempt per device id
+#[derive(Debug, Default)]
+pub struct PollSchedule {
+    last_attempt: HashMap<String, DateTime<Utc>>,
+}
+
+impl PollSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never-polled devices are due immediately
+    pub fn is_due(&self, id: &str, interval_secs: u64, now: DateTime<Utc>) -> bool {
+        self.next_due(id, interval_secs)
+            .is_none_or(|due| now >= due)
+    }
+
+    pub fn next_due(&self, id: &str, interval_secs: u64) -> Option<DateTime<Utc>> {
+        self.last_attempt
+            .get(id)
+            .map(|last| *last + Duration::seconds(interval_secs as i64))
+    }
+
+    /// Record an attempt (successful or not) so failures also wait a full interval
+    pub fn mark_attempt(&mut self, id: &str, now: DateTime<Utc>) {
+        self.last_attempt.insert(id.to_string(), now);
+    }
+
+    /// Drop a device (paused or removed) so it is due as soon as it returns
+    pub fn forget(&mut self, id: &str) {
+        self.last_attempt.remove(id);
+    }
+
+    /// Keep only entries for devices that still exist
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.last_attempt.retain(|id, _| keep(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn due_follows_current_interval() {
+        let t0 = Utc::now();
+        let mut schedule = PollSchedule::new();
+        assert!(schedule.is_due("a", 60, t0));
+
+        schedule.mark_attempt("a", t0);
+        assert!(!schedule.is_due("a", 60, t0 + Duration::seconds(59)));
+        assert!(schedule.is_due("a", 60, t0 + Duration::seconds(60)));
+        // Interval raised to 15 min after the last poll
+        assert!(!schedule.is_due("a", 900, t0 + Duration::seconds(60)));
+        assert_eq!(
+            schedule.next_due("a", 900),
+            Some(t0 + Duration::seconds(900))
+        );
+
+        schedule.forget("a");
+        assert!(schedule.is_due("a", 900, t0 + Duration::seconds(61)));
+    }
+
+    #[test]
+    fn retain_prunes_removed_devices() {
+        let now = Utc::now();
+        let mut schedule = PollSchedule::new();
+        schedule.mark_attempt("keep", now);
+        schedule.mark_attempt("gone", now);
+        schedule.retain(|id| id == "keep");
+
+        assert!(!schedule.is_due("keep", 60, now));
+        assert!(schedule.is_due("gone", 60, now));
+    }
+}
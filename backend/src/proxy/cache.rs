@@ -0,0 +1,432 @@
+//! Per-route response cache for GET requests
+//!
+//! Routes with `cache_ttl_sec > 0` keep successful GET responses in memory,
+//! keyed on (route, path, query, Accept-Encoding), for that many seconds. The cache is an LRU
+//! bounded by `[server] response_cache_max_mb`; served responses carry
+//! `X-LPG-Cache: HIT` or `MISS`.
+//!
+//! Only shared content is cached: requests carrying client credentials
+//! (Authorization, Cookie) and responses that set cookies or are marked
+//! `private` / `no-store` pass through. The upstream gets the client's
+//! Accept-Encoding and its body is stored as sent, so the key keeps encoded
+//! variants apart; responses that `Vary` on any other request header are not
+//! stored. A client's `Cache-Control: no-cache`
+//! skips the lookup (the fresh response replaces the entry). Non-GET methods
+//! on a route drop the entries of their path and everything below it, and a
+//! route's entries go whenever its configuration changes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use serde::Serialize;
+
+/// Response header naming the cache outcome
+pub const CACHE_HEADER: &str = "x-lpg-cache";
+
+/// Default `[server] response_cache_max_mb`
+pub const DEFAULT_MAX_MB: usize = 64;
+
+/// Longest accepted `cache_ttl_sec`
+pub const MAX_TTL_SECS: i32 = 86_400;
+
+/// Share of the cache one response may take at most
+const MAX_ENTRY_SHARE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub route_id: i32,
+    pub path: String,
+    pub query: Option<String>,
+    /// Normalized Accept-Encoding of the request (see `encoding_variant`)
+    pub accept_encoding: Option<String>,
+}
+
+/// Stored response (headers as sent, before security headers)
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl CachedResponse {
+    fn size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(k, v)| k.as_str().len() + v.len())
+                .sum::<usize>()
+    }
+}
+
+struct Entry {
+    response: CachedResponse,
+    expires_at: Instant,
+    /// Position in `Inner::order`
+    used: u64,
+    size: usize,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<CacheKey, Entry>,
+    /// Use counter -> key, least recently used first
+    order: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl Inner {
+    fn remove(&mut self, key: &CacheKey) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.order.remove(&entry.used);
+                self.bytes -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_where(&mut self, matches: impl Fn(&CacheKey) -> bool) -> usize {
+        let keys: Vec<CacheKey> = self
+            .entries
+            .keys()
+            .filter(|k| matches(k))
+            .cloned()
+            .collect();
+        keys.iter().filter(|k| self.remove(k)).count()
+    }
+
+    fn touch(&mut self, key: &CacheKey) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.order.remove(&entry.used);
+            entry.used = clock;
+            self.order.insert(clock, key.clone());
+        }
+    }
+}
+
+/// Cache totals (POST /api/routes/:id/cache/purge answers with these)
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub max_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// In-memory LRU of proxied GET responses
+pub struct ResponseCache {
+    inner: Mutex<Inner>,
+    max_bytes: usize,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MB * 1024 * 1024)
+    }
+}
+
+impl ResponseCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            max_bytes,
+        }
+    }
+
+    /// Fresh entry for `key`, counting the hit or miss
+    pub fn get(&self, key: &CacheKey) -> Option<CachedResponse> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &CacheKey, now: Instant) -> Option<CachedResponse> {
+        let mut inner = self.inner.lock().unwrap();
+        let fresh = inner.entries.get(key).map(|e| e.expires_at > now);
+        match fresh {
+            Some(true) => {
+                inner.hits += 1;
+                inner.touch(key);
+                inner.entries.get(key).map(|e| e.response.clone())
+            }
+            Some(false) => {
+                inner.remove(key);
+                inner.misses += 1;
+                None
+            }
+            None => {
+                inner.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store a response for `ttl`, evicting the least recently used entries
+    /// to stay within the size bound; returns false when it is too large
+    pub fn insert(&self, key: CacheKey, response: CachedResponse, ttl: Duration) -> bool {
+        self.insert_at(key, response, ttl, Instant::now())
+    }
+
+    fn insert_at(
+        &self,
+        key: CacheKey,
+        response: CachedResponse,
+        ttl: Duration,
+        now: Instant,
+    ) -> bool {
+        let size = response.size();
+        if self.max_bytes == 0 || size > self.max_bytes / MAX_ENTRY_SHARE {
+            return false;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(&key);
+        while inner.bytes + size > self.max_bytes {
+            let Some((_, oldest)) = inner.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = inner.entries.remove(&oldest) {
+                inner.bytes -= entry.size;
+            }
+        }
+        inner.clock += 1;
+        let used = inner.clock;
+        inner.order.insert(used, key.clone());
+        inner.bytes += size;
+        inner.entries.insert(
+            key,
+            Entry {
+                response,
+                expires_at: now + ttl,
+                used,
+                size,
+            },
+        );
+        true
+    }
+
+    /// Drop a route's entries for `path` and the paths below it (after a
+    /// mutating request); returns how many went
+    pub fn invalidate_path(&self, route_id: i32, path: &str) -> usize {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.inner.lock().unwrap().remove_where(|k| {
+            k.route_id == route_id && (k.path == path || k.path.starts_with(&prefix))
+        })
+    }
+
+    /// Drop every entry of a route; returns how many went
+    pub fn purge_route(&self, route_id: i32) -> usize {
+        self.inner
+            .lock()
+            .unwrap()
+            .remove_where(|k| k.route_id == route_id)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            entries: inner.entries.len(),
+            bytes: inner.bytes,
+            max_bytes: self.max_bytes,
+            hits: inner.hits,
+            misses: inner.misses,
+        }
+    }
+}
+
+fn has_directive(headers: &HeaderMap, name: header::HeaderName, directives: &[&str]) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|d| d.trim().to_ascii_lowercase())
+        .any(|d| directives.iter().any(|want| d == *want))
+}
+
+/// Whether a request may be answered from or stored into the cache
+pub fn request_cacheable(headers: &HeaderMap) -> bool {
+    !headers.contains_key(header::AUTHORIZATION) && !headers.contains_key(header::COOKIE)
+}
+
+/// Accept-Encoding as part of a cache key: lowercased, without whitespace,
+/// None when absent or empty
+pub fn encoding_variant(headers: &HeaderMap) -> Option<String> {
+    let variant: Vec<String> = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .map(|v| v.replace(char::is_whitespace, "").to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect();
+    (!variant.is_empty()).then(|| variant.join(","))
+}
+
+/// Whether the client asked to skip stored responses
+pub fn bypass_requested(headers: &HeaderMap) -> bool {
+    has_directive(headers, header::CACHE_CONTROL, &["no-cache", "no-store"])
+        || has_directive(headers, header::PRAGMA, &["no-cache"])
+}
+
+/// Whether an upstream response may be shared with other clients
+pub fn response_cacheable(status: StatusCode, headers: &HeaderMap) -> bool {
+    status == StatusCode::OK
+        && !headers.contains_key(header::SET_COOKIE)
+        && !has_directive(headers, header::CACHE_CONTROL, &["private", "no-store"])
+        && varies_on_encoding_only(headers)
+}
+
+/// Whether `Vary` names no request header but Accept-Encoding (the only one
+/// in the key)
+fn varies_on_encoding_only(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::VARY)
+        .iter()
+        .map(|v| v.to_str().unwrap_or("*"))
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .all(|name| name.eq_ignore_ascii_case("accept-encoding"))
+}
+
+/// `X-LPG-Cache` value
+pub fn outcome_header(hit: bool) -> HeaderValue {
+    HeaderValue::from_static(if hit { "HIT" } else { "MISS" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str, query: Option<&str>) -> CacheKey {
+        CacheKey {
+            route_id: 1,
+            path: path.to_string(),
+            query: query.map(str::to_string),
+            accept_encoding: None,
+        }
+    }
+
+    fn response(body: &'static [u8]) -> CachedResponse {
+        CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(body),
+        }
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let cache = ResponseCache::new(1024);
+        let now = Instant::now();
+        let ttl = Duration::from_secs(10);
+        assert!(cache.insert_at(key("/a", None), response(b"one"), ttl, now));
+        assert!(cache.get_at(&key("/a", None), now).is_some());
+        assert!(cache.get_at(&key("/a", Some("x=1")), now).is_none());
+        assert!(cache
+            .get_at(&key("/a", None), now + Duration::from_secs(11))
+            .is_none());
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (0, 1, 2));
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        // 8 entries of 10 bytes fill it; one more must go
+        let cache = ResponseCache::new(80);
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        for i in 0..8 {
+            cache.insert_at(
+                key(&format!("/{}", i), None),
+                response(b"0123456789"),
+                ttl,
+                now,
+            );
+        }
+        assert!(cache.get_at(&key("/0", None), now).is_some());
+        cache.insert_at(key("/8", None), response(b"0123456789"), ttl, now);
+        assert!(cache.get_at(&key("/0", None), now).is_some());
+        assert!(cache.get_at(&key("/1", None), now).is_none());
+        assert!(cache.stats().bytes <= 80);
+        // Larger than an eighth of the cache
+        assert!(!cache.insert_at(key("/big", None), response(b"0123456789ab"), ttl, now));
+    }
+
+    #[test]
+    fn mutations_invalidate_the_path_and_below() {
+        let cache = ResponseCache::new(1024);
+        let ttl = Duration::from_secs(60);
+        cache.insert(key("/devices", None), response(b"a"), ttl);
+        cache.insert(key("/devices", Some("page=2")), response(b"b"), ttl);
+        cache.insert(key("/devices/7", None), response(b"c"), ttl);
+        cache.insert(key("/devices-old", None), response(b"d"), ttl);
+        assert_eq!(cache.invalidate_path(1, "/devices"), 3);
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.purge_route(1), 1);
+    }
+
+    #[test]
+    fn shared_content_only() {
+        let mut request = HeaderMap::new();
+        assert!(request_cacheable(&request));
+        request.insert(header::CACHE_CONTROL, HeaderValue::from_static("No-Cache"));
+        assert!(bypass_requested(&request));
+        request.insert(header::COOKIE, HeaderValue::from_static("s=1"));
+        assert!(!request_cacheable(&request));
+
+        let mut response = HeaderMap::new();
+        assert!(response_cacheable(StatusCode::OK, &response));
+        assert!(!response_cacheable(StatusCode::NOT_FOUND, &response));
+        response.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("max-age=60, private"),
+        );
+        assert!(!response_cacheable(StatusCode::OK, &response));
+
+        let mut response = HeaderMap::new();
+        response.insert(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+        assert!(response_cacheable(StatusCode::OK, &response));
+        response.append(header::VARY, HeaderValue::from_static("Accept-Language"));
+        assert!(!response_cacheable(StatusCode::OK, &response));
+        response.insert(header::VARY, HeaderValue::from_static("*"));
+        assert!(!response_cacheable(StatusCode::OK, &response));
+    }
+
+    #[test]
+    fn encoded_responses_stay_with_their_encoding() {
+        let client = |accept_encoding: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(value) = accept_encoding {
+                headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(value));
+            }
+            CacheKey {
+                accept_encoding: encoding_variant(&headers),
+                ..key("/app.js", None)
+            }
+        };
+        let gzip = client(Some("gzip, br"));
+        assert_eq!(gzip.accept_encoding.as_deref(), Some("gzip,br"));
+        assert_eq!(client(Some("GZIP,br")), gzip);
+
+        let cache = ResponseCache::new(1024);
+        let mut compressed = response(b"\x1f\x8b");
+        compressed
+            .headers
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        cache.insert(gzip.clone(), compressed, Duration::from_secs(60));
+
+        assert!(cache.get(&gzip).is_some());
+        assert!(cache.get(&client(Some("identity"))).is_none());
+        assert!(cache.get(&client(None)).is_none());
+    }
+}
@@ -0,0 +1,151 @@
+//! Route-level canary deployments
+//!
+//! A route with `canary_target` and a `canary_percent` above 0 sends that
+//! share of its requests to the canary instead of `target`. With
+//! `canary_sticky` the side is a hash of route and client IP, so a client
+//! stays on one side until the percentage changes; otherwise each request is
+//! drawn at random. The access log records the target that served the
+//! request, which is what GET /api/routes/:id/canary-stats compares.
+
+use rand::Rng;
+use serde::Serialize;
+
+use crate::models::ProxyRoute;
+use crate::proxy::trace::percentile;
+
+impl ProxyRoute {
+    /// Canary target and share when the canary is on
+    pub fn canary(&self) -> Option<(&str, u32)> {
+        let target = self.canary_target.as_deref().filter(|t| !t.is_empty())?;
+        let percent = self.canary_percent.filter(|p| *p > 0)?;
+        Some((target, percent.min(100) as u32))
+    }
+
+    /// Target this request goes to: the canary for its share, else `target`
+    pub fn choose_target(&self, client_ip: &str) -> &str {
+        let Some((canary, percent)) = self.canary() else {
+            return &self.target;
+        };
+        let bucket = if self.canary_sticky {
+            sticky_bucket(self.id, client_ip)
+        } else {
+            rand::thread_rng().gen_range(0..100)
+        };
+        if bucket < percent {
+            canary
+        } else {
+            &self.target
+        }
+    }
+}
+
+/// Stable 0..100 bucket of a client on a route (FNV-1a)
+fn sticky_bucket(route_id: i32, client_ip: &str) -> u32 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in route_id.to_be_bytes().iter().chain(client_ip.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % 100) as u32
+}
+
+/// Requests, errors and latency of one side of a canary
+#[derive(Debug, Clone, Serialize)]
+pub struct CanarySideStats {
+    pub target: String,
+    pub requests: u64,
+    /// Responses with status >= 500
+    pub errors: u64,
+    pub error_rate: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    /// Latest requests the percentiles were computed from
+    pub latency_samples: usize,
+}
+
+impl CanarySideStats {
+    pub fn new(target: &str, requests: u64, errors: u64, mut latencies: Vec<u64>) -> Self {
+        latencies.sort_unstable();
+        Self {
+            target: target.to_string(),
+            requests,
+            errors,
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                errors as f64 / requests as f64
+            },
+            p50_ms: percentile(&latencies, 50.0),
+            p95_ms: percentile(&latencies, 95.0),
+            p99_ms: percentile(&latencies, 99.0),
+            latency_samples: latencies.len(),
+        }
+    }
+}
+
+/// GET /api/routes/:id/canary-stats
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryStats {
+    pub route_id: i32,
+    pub minutes: i64,
+    /// Current share (0 = canary off)
+    pub canary_percent: i32,
+    pub canary_sticky: bool,
+    pub primary: CanarySideStats,
+    /// None when the route has no canary target
+    pub canary: Option<CanarySideStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(canary_percent: Option<i32>, sticky: bool) -> ProxyRoute {
+        ProxyRoute {
+            id: 3,
+            target: "http://10.0.0.1:8080".to_string(),
+            canary_target: Some("http://10.0.0.2:8080".to_string()),
+            canary_percent,
+            canary_sticky: sticky,
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    #[test]
+    fn zero_or_null_percent_keeps_the_primary() {
+        for percent in [None, Some(0)] {
+            let r = route(percent, false);
+            assert!(r.canary().is_none());
+            assert!((0..50).all(|_| r.choose_target("1.2.3.4") == "http://10.0.0.1:8080"));
+        }
+        let all = route(Some(100), false);
+        assert!((0..50).all(|_| all.choose_target("1.2.3.4") == "http://10.0.0.2:8080"));
+    }
+
+    #[test]
+    fn sticky_clients_stay_on_one_side() {
+        let r = route(Some(30), true);
+        let canary = (0..1000)
+            .filter(|i| {
+                let ip = format!("10.1.{}.{}", i / 256, i % 256);
+                let first = r.choose_target(&ip);
+                assert_eq!(first, r.choose_target(&ip));
+                first == "http://10.0.0.2:8080"
+            })
+            .count();
+        // Roughly the configured share of clients
+        assert!((200..400).contains(&canary), "{}", canary);
+    }
+
+    #[test]
+    fn side_stats_compute_rate_and_percentiles() {
+        let side = CanarySideStats::new("t", 200, 10, (1..=100).rev().collect());
+        assert_eq!(side.error_rate, 0.05);
+        assert_eq!((side.p50_ms, side.p95_ms, side.p99_ms), (50, 95, 99));
+        assert_eq!(side.latency_samples, 100);
+
+        let empty = CanarySideStats::new("t", 0, 0, Vec::new());
+        assert_eq!((empty.error_rate, empty.p99_ms), (0.0, 0));
+    }
+}
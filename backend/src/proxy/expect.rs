@@ -0,0 +1,160 @@
+//! Per-route `Expect: 100-continue` handling
+//!
+//! `proxy_routes.expect_continue` selects the mode (NULL = immediate):
+//! - `immediate`: the gateway answers the expectation itself. A body declared
+//!   larger than the request body limit is refused with 413 before the client
+//!   sends it; otherwise `100 Continue` goes out when the body is first read,
+//!   i.e. after routing, blocklist, admin-network, method and warm-up checks.
+//!   The header is not forwarded upstream.
+//! - `passthrough`: as immediate towards the client (the body is buffered, so
+//!   the gateway has to take it first), but the header is forwarded so the
+//!   upstream sees the expectation. The upstream client does not surface 1xx
+//!   responses: an upstream `100 Continue` is consumed and only the final
+//!   response (e.g. an upstream 417) is relayed.
+//! - `strip`: the header is dropped and no early checks are made; hyper still
+//!   acknowledges it when the body is read. (A `Content-Length` over the
+//!   route's body limit is refused up front in every mode.)
+//!
+//! Expectations other than `100-continue` are answered 417 (RFC 9110 §10.1.1)
+//! in immediate and passthrough modes.
+
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+
+use super::limits::declared_body_len;
+use crate::models::ProxyRoute;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectContinue {
+    #[default]
+    Immediate,
+    Passthrough,
+    Strip,
+}
+
+impl ExpectContinue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Immediate => "immediate",
+            Self::Passthrough => "passthrough",
+            Self::Strip => "strip",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "immediate" => Some(Self::Immediate),
+            "passthrough" => Some(Self::Passthrough),
+            "strip" => Some(Self::Strip),
+            _ => None,
+        }
+    }
+
+    /// Stored column value; immediate is the default and stored as NULL
+    pub fn to_column(self) -> Option<String> {
+        match self {
+            Self::Immediate => None,
+            other => Some(other.as_str().to_string()),
+        }
+    }
+
+    /// Whether the client's `Expect` header is sent upstream
+    pub fn forwards_header(self) -> bool {
+        self == Self::Passthrough
+    }
+}
+
+impl ProxyRoute {
+    /// Expect handling mode; unknown column values fall back to immediate
+    pub fn expect_continue(&self) -> ExpectContinue {
+        self.expect_continue
+            .as_deref()
+            .and_then(ExpectContinue::parse)
+            .unwrap_or_default()
+    }
+}
+
+/// Why a request is answered before its body is read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectRejection {
+    /// 417: an expectation other than 100-continue
+    Unsupported(String),
+    /// 413: the declared Content-Length exceeds the limit
+    TooLarge(u64),
+}
+
+/// Check a request's `Expect` header before its body is read (and so before
+/// hyper sends `100 Continue`)
+pub fn check_expectation(
+    mode: ExpectContinue,
+    headers: &HeaderMap,
+    max_body_bytes: usize,
+) -> Result<(), ExpectRejection> {
+    if mode == ExpectContinue::Strip {
+        return Ok(());
+    }
+    let Some(expect) = headers.get(header::EXPECT) else {
+        return Ok(());
+    };
+    let value = expect.to_str().unwrap_or("").trim();
+    if !value.eq_ignore_ascii_case("100-continue") {
+        return Err(ExpectRejection::Unsupported(value.to_string()));
+    }
+
+    match declared_body_len(headers) {
+        Some(len) if len > max_body_bytes as u64 => Err(ExpectRejection::TooLarge(len)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: usize = 1024;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.insert(*k, v.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn mode_parsing_and_column() {
+        assert_eq!(
+            ExpectContinue::parse(" Passthrough "),
+            Some(ExpectContinue::Passthrough)
+        );
+        assert_eq!(ExpectContinue::parse("bogus"), None);
+        assert_eq!(ExpectContinue::Immediate.to_column(), None);
+        assert_eq!(ExpectContinue::Strip.to_column().as_deref(), Some("strip"));
+        assert!(ExpectContinue::Passthrough.forwards_header());
+        assert!(!ExpectContinue::Immediate.forwards_header());
+    }
+
+    #[test]
+    fn expectation_checks() {
+        let mode = ExpectContinue::Immediate;
+        assert_eq!(check_expectation(mode, &HeaderMap::new(), LIMIT), Ok(()));
+        let ok = headers(&[("expect", "100-Continue"), ("content-length", "10")]);
+        assert_eq!(check_expectation(mode, &ok, LIMIT), Ok(()));
+
+        let big = headers(&[("expect", "100-continue"), ("content-length", "4096")]);
+        assert_eq!(
+            check_expectation(mode, &big, LIMIT),
+            Err(ExpectRejection::TooLarge(4096))
+        );
+        let odd = headers(&[("expect", "200-ok")]);
+        assert!(matches!(
+            check_expectation(ExpectContinue::Passthrough, &odd, LIMIT),
+            Err(ExpectRejection::Unsupported(_))
+        ));
+        assert_eq!(
+            check_expectation(ExpectContinue::Strip, &big, LIMIT),
+            Ok(())
+        );
+    }
+}
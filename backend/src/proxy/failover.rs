@@ -0,0 +1,387 @@
+//! Per-route retries and fallback targets
+//!
+//! `proxy_routes.fallback_targets` holds a JSON list of alternative base
+//! URLs, tried in order after the target a request was sent to (`target`, or
+//! the canary side that drew it). Each target gets `retry_count` further
+//! attempts before the next one is tried. `retry_on` decides what counts as a
+//! failure: connection errors only (the default), or also 502/503/504
+//! answers. Anything else, a request that timed out after it was sent
+//! included, is answered as is since the upstream may have acted on it.
+//!
+//! Only idempotent methods are retried; other methods only when the client
+//! sends an `Idempotency-Key` header, which every attempt carries so the
+//! upstream can drop duplicates. No attempt starts once the route's
+//! `timeout_ms` has passed since the first. The access log records the
+//! target that answered. WebSocket and gRPC requests use the primary only.
+//!
+//! The health checker probes the fallbacks along with the primary and keeps
+//! the latest result per fallback (collection `route_target_health`), which
+//! the route status lists per target.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use axum::http::{HeaderMap, Method};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{HealthCheck, ProxyRoute};
+use crate::proxy::upstream;
+
+/// Upper bound of `retry_count`
+pub const MAX_RETRY_COUNT: i32 = 5;
+
+/// Fallback targets a route may list
+pub const MAX_FALLBACK_TARGETS: usize = 8;
+
+/// Request header that lets non-idempotent methods be retried
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Failures that move a request to its next attempt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryOn {
+    /// No connection could be made to the target
+    #[default]
+    ConnectError,
+    /// Connection errors and 502/503/504 answers
+    GatewayError,
+}
+
+impl RetryOn {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ConnectError => "connect_error",
+            Self::GatewayError => "gateway_error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "connect_error" => Some(Self::ConnectError),
+            "gateway_error" => Some(Self::GatewayError),
+            _ => None,
+        }
+    }
+
+    /// Stored column value; connect_error is the default and stored as NULL
+    pub fn to_column(self) -> Option<String> {
+        match self {
+            Self::ConnectError => None,
+            other => Some(other.as_str().to_string()),
+        }
+    }
+}
+
+/// Validate and normalize a fallback list: trimmed HTTP(S) URLs without a
+/// trailing slash, duplicates dropped
+pub fn normalize_fallback_targets(targets: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(targets.len());
+    for target in targets {
+        let target = target.trim().trim_end_matches('/');
+        if !target.starts_with("http://") && !target.starts_with("https://") {
+            return Err(format!("not an HTTP(S) URL: {:?}", target));
+        }
+        if !normalized.iter().any(|t| t == target) {
+            normalized.push(target.to_string());
+        }
+    }
+    if normalized.len() > MAX_FALLBACK_TARGETS {
+        return Err(format!(
+            "at most {} fallback targets are allowed",
+            MAX_FALLBACK_TARGETS
+        ));
+    }
+    Ok(normalized)
+}
+
+/// Stored column value; None (NULL) = no fallbacks
+pub fn fallback_targets_column(targets: Option<&[String]>) -> Option<String> {
+    targets
+        .and_then(|t| normalize_fallback_targets(t).ok())
+        .filter(|t| !t.is_empty())
+        .and_then(|t| serde_json::to_string(&t).ok())
+}
+
+impl ProxyRoute {
+    /// Fallback targets in order; empty when none (or the column is invalid)
+    pub fn fallback_targets(&self) -> Vec<String> {
+        let Some(raw) = self
+            .fallback_targets
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        else {
+            return Vec::new();
+        };
+        serde_json::from_str(raw).unwrap_or_else(|e| {
+            tracing::warn!(
+                "Ignoring invalid fallback_targets on route {}: {}",
+                self.id,
+                e
+            );
+            Vec::new()
+        })
+    }
+
+    /// Retry trigger; unknown column values fall back to connect_error
+    pub fn retry_on(&self) -> RetryOn {
+        self.retry_on
+            .as_deref()
+            .and_then(RetryOn::parse)
+            .unwrap_or_default()
+    }
+}
+
+/// Whether a request may be sent more than once
+pub fn retries_allowed(method: &Method, headers: &HeaderMap) -> bool {
+    method.is_idempotent() || headers.contains_key(IDEMPOTENCY_KEY)
+}
+
+/// `url`, built on the base URL `from`, moved onto the base URL `to`
+pub fn rebase(url: &str, from: &str, to: &str) -> String {
+    let rest = url
+        .strip_prefix(from.trim_end_matches('/'))
+        .unwrap_or_default();
+    format!("{}{}", to.trim_end_matches('/'), rest)
+}
+
+/// Why an attempt failed, as far as failover is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptFailure {
+    /// Refused, unreachable or not connected within the connect timeout
+    Connect,
+    /// The target answered 502, 503 or 504
+    Gateway(u16),
+}
+
+impl AttemptFailure {
+    /// None when the outcome stands whatever the policy
+    pub fn of(sent: &Result<reqwest::Response, reqwest::Error>) -> Option<Self> {
+        match sent {
+            Err(e) if e.is_connect() => Some(Self::Connect),
+            Ok(response) if matches!(response.status().as_u16(), 502..=504) => {
+                Some(Self::Gateway(response.status().as_u16()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AttemptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect => f.write_str("connection failed"),
+            Self::Gateway(status) => write!(f, "answered {}", status),
+        }
+    }
+}
+
+/// Attempts of one request: the target it was sent to, then the fallbacks
+#[derive(Debug)]
+pub struct Attempts {
+    targets: Vec<String>,
+    /// Attempts per target (1 + retry_count)
+    per_target: u32,
+    retry_on: RetryOn,
+    target: usize,
+    attempt: u32,
+    started: Instant,
+    budget: Duration,
+}
+
+impl Attempts {
+    /// Plan for a request on `route`, whose `target` the first attempt uses
+    pub fn new(route: &ProxyRoute, method: &Method, headers: &HeaderMap) -> Self {
+        let mut targets = vec![route.target.trim_end_matches('/').to_string()];
+        let mut per_target = 1;
+        if retries_allowed(method, headers) {
+            for fallback in route.fallback_targets() {
+                if !targets.contains(&fallback) {
+                    targets.push(fallback);
+                }
+            }
+            per_target += route.retry_count.clamp(0, MAX_RETRY_COUNT) as u32;
+        }
+        Self {
+            targets,
+            per_target,
+            retry_on: route.retry_on(),
+            target: 0,
+            attempt: 0,
+            started: Instant::now(),
+            budget: upstream::route_timeout(route),
+        }
+    }
+
+    /// Target of the next attempt after `failure`; None when the last
+    /// outcome is the answer
+    pub fn retry(&mut self, failure: Option<AttemptFailure>) -> Option<&str> {
+        match failure? {
+            AttemptFailure::Connect => {}
+            AttemptFailure::Gateway(_) if self.retry_on == RetryOn::GatewayError => {}
+            AttemptFailure::Gateway(_) => return None,
+        }
+        if self.started.elapsed() >= self.budget {
+            return None;
+        }
+        self.attempt += 1;
+        if self.attempt >= self.per_target {
+            self.target += 1;
+            self.attempt = 0;
+        }
+        self.targets.get(self.target).map(String::as_str)
+    }
+}
+
+/// Latest probe of one fallback target (collection `route_target_health`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackCheck {
+    pub route_id: i32,
+    pub target: String,
+    pub checked_at: DateTime<Utc>,
+    pub healthy: bool,
+    pub response_time_ms: Option<i32>,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+}
+
+/// Health of one target of a route, as the route status shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetHealth {
+    pub target: String,
+    /// Whether this is the route's `target` (else a fallback)
+    pub primary: bool,
+    /// True until the target has been checked
+    pub healthy: bool,
+    pub last_check: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+    pub response_time_ms: Option<i32>,
+    pub last_status_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+/// The primary from its latest health check, then the fallbacks in order
+pub fn target_health(
+    route: &ProxyRoute,
+    check: Option<&HealthCheck>,
+    consecutive_failures: u32,
+    fallback_checks: &[FallbackCheck],
+) -> Vec<TargetHealth> {
+    let mut targets = vec![TargetHealth {
+        target: route.target.clone(),
+        primary: true,
+        healthy: check.map(|c| c.healthy).unwrap_or(true),
+        last_check: check.map(|c| c.timestamp),
+        consecutive_failures,
+        response_time_ms: check.and_then(|c| c.response_time_ms),
+        last_status_code: check.and_then(|c| c.status_code),
+        error: check.and_then(|c| c.error.clone()),
+    }];
+    for target in route.fallback_targets() {
+        let check = fallback_checks
+            .iter()
+            .find(|c| c.route_id == route.id && c.target == target);
+        targets.push(TargetHealth {
+            target,
+            primary: false,
+            healthy: check.map(|c| c.healthy).unwrap_or(true),
+            last_check: check.map(|c| c.checked_at),
+            consecutive_failures: check.map(|c| c.consecutive_failures).unwrap_or(0),
+            response_time_ms: check.and_then(|c| c.response_time_ms),
+            last_status_code: check.and_then(|c| c.status_code),
+            error: check.and_then(|c| c.error.clone()),
+        });
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(fallbacks: &[&str], retry_count: i32, retry_on: Option<&str>) -> ProxyRoute {
+        ProxyRoute {
+            id: 6,
+            path: "/svc".to_string(),
+            target: "http://10.0.0.1:8080/".to_string(),
+            fallback_targets: fallback_targets_column(Some(
+                &fallbacks.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+            )),
+            retry_count,
+            retry_on: retry_on.map(str::to_string),
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    #[test]
+    fn fallback_lists_are_normalized() {
+        let targets = vec![
+            " http://10.0.0.2:8080/ ".to_string(),
+            "http://10.0.0.2:8080".to_string(),
+            "https://replica".to_string(),
+        ];
+        assert_eq!(
+            normalize_fallback_targets(&targets).unwrap(),
+            vec!["http://10.0.0.2:8080", "https://replica"]
+        );
+        assert!(normalize_fallback_targets(&["ftp://x".to_string()]).is_err());
+        assert_eq!(fallback_targets_column(Some(&[])), None);
+    }
+
+    #[test]
+    fn connect_errors_walk_the_fallbacks() {
+        let route = route(&["http://10.0.0.2:8080", "http://10.0.0.3:8080"], 1, None);
+        let mut attempts = Attempts::new(&route, &Method::GET, &HeaderMap::new());
+        let failed = Some(AttemptFailure::Connect);
+        assert_eq!(attempts.retry(failed), Some("http://10.0.0.1:8080"));
+        assert_eq!(attempts.retry(failed), Some("http://10.0.0.2:8080"));
+        assert_eq!(attempts.retry(failed), Some("http://10.0.0.2:8080"));
+        assert_eq!(attempts.retry(failed), Some("http://10.0.0.3:8080"));
+        assert_eq!(attempts.retry(failed), Some("http://10.0.0.3:8080"));
+        assert_eq!(attempts.retry(failed), None);
+    }
+
+    #[test]
+    fn gateway_answers_are_retried_only_when_asked() {
+        let bad_gateway = Some(AttemptFailure::Gateway(502));
+        let connect_only = route(&["http://10.0.0.2:8080"], 0, None);
+        let mut attempts = Attempts::new(&connect_only, &Method::GET, &HeaderMap::new());
+        assert_eq!(attempts.retry(bad_gateway), None);
+        assert_eq!(attempts.retry(None), None);
+
+        let gateway = route(&["http://10.0.0.2:8080"], 0, Some("gateway_error"));
+        let mut attempts = Attempts::new(&gateway, &Method::GET, &HeaderMap::new());
+        assert_eq!(attempts.retry(bad_gateway), Some("http://10.0.0.2:8080"));
+        assert_eq!(attempts.retry(bad_gateway), None);
+    }
+
+    #[test]
+    fn non_idempotent_methods_need_an_idempotency_key() {
+        let route = route(&["http://10.0.0.2:8080"], 2, None);
+        let mut attempts = Attempts::new(&route, &Method::POST, &HeaderMap::new());
+        assert_eq!(attempts.retry(Some(AttemptFailure::Connect)), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY, "order-41".parse().unwrap());
+        let mut attempts = Attempts::new(&route, &Method::POST, &headers);
+        assert_eq!(
+            attempts.retry(Some(AttemptFailure::Connect)),
+            Some("http://10.0.0.1:8080")
+        );
+    }
+
+    #[test]
+    fn urls_move_to_the_fallback_base() {
+        assert_eq!(
+            rebase(
+                "http://10.0.0.1:8080/api/items?page=2",
+                "http://10.0.0.1:8080/",
+                "http://10.0.0.2:9090/"
+            ),
+            "http://10.0.0.2:9090/api/items?page=2"
+        );
+    }
+}
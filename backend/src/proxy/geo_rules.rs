@@ -0,0 +1,263 @@
+//! Country allow/deny rules for proxied requests
+//!
+//! The rules are stored as JSON in the `geo_rules` setting (GET/PUT
+//! /api/security/geo-rules) and cached here. In `deny` mode the listed
+//! countries are refused; in `allow` mode every other country is. Clients
+//! without a known country (LAN and other private addresses, GeoIP misses,
+//! no GeoIP database loaded) get `unresolved_action`, allow by default.
+//!
+//! A refused request is answered 403 and recorded as a suspicious-activity
+//! security event, once per client and `REPORT_INTERVAL`.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::mysql::MySqlDb;
+use crate::error::{AppError, FieldError};
+
+/// Settings key holding the rules
+pub const SETTING_GEO_RULES: &str = "geo_rules";
+
+/// Most countries a rule set may list
+pub const MAX_COUNTRIES: usize = 300;
+
+/// A client's denials are reported at most this often
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Report entries at which stale ones are swept
+const SWEEP_AT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoMode {
+    /// Refuse the listed countries
+    #[default]
+    Deny,
+    /// Refuse every country but the listed ones
+    Allow,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoAction {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl GeoAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoRules {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode: GeoMode,
+    /// ISO 3166-1 alpha-2 codes (upper case, sorted)
+    #[serde(default)]
+    pub countries: Vec<String>,
+    /// Clients whose country is unknown
+    #[serde(default)]
+    pub unresolved_action: GeoAction,
+}
+
+impl GeoRules {
+    /// Upper-case, sort and dedupe the country list; every invalid field at once
+    pub fn normalized(mut self) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let mut countries = Vec::with_capacity(self.countries.len());
+        for code in &self.countries {
+            let code = code.trim().to_ascii_uppercase();
+            if code.len() != 2 || !code.bytes().all(|b| b.is_ascii_uppercase()) {
+                errors.push(FieldError::new(
+                    "countries",
+                    format!("'{}' is not a two-letter ISO 3166-1 country code", code),
+                ));
+                continue;
+            }
+            countries.push(code);
+        }
+        countries.sort_unstable();
+        countries.dedup();
+        if countries.len() > MAX_COUNTRIES {
+            errors.push(FieldError::new(
+                "countries",
+                format!("must list at most {} countries", MAX_COUNTRIES),
+            ));
+        }
+        if self.enabled && self.mode == GeoMode::Allow && countries.is_empty() {
+            errors.push(FieldError::new(
+                "countries",
+                "must list at least one country in allow mode",
+            ));
+        }
+        self.countries = countries;
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// What happens to a client of `country_code` (None = unresolved)
+    pub fn action(&self, country_code: Option<&str>) -> GeoAction {
+        if !self.enabled {
+            return GeoAction::Allow;
+        }
+        let Some(code) = country_code else {
+            return self.unresolved_action;
+        };
+        let listed = self.countries.iter().any(|c| c.eq_ignore_ascii_case(code));
+        match (self.mode, listed) {
+            (GeoMode::Deny, true) | (GeoMode::Allow, false) => GeoAction::Deny,
+            _ => GeoAction::Allow,
+        }
+    }
+}
+
+/// Stored rules; an unreadable value is ignored (and logged) so the proxy
+/// keeps serving
+pub async fn load(mysql: &MySqlDb) -> Result<GeoRules, AppError> {
+    let raw = mysql.get_setting(SETTING_GEO_RULES).await?;
+    Ok(
+        match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            None => GeoRules::default(),
+            Some(raw) => serde_json::from_str(raw).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable {}: {}", SETTING_GEO_RULES, e);
+                GeoRules::default()
+            }),
+        },
+    )
+}
+
+/// Persist validated rules
+pub async fn save(mysql: &MySqlDb, rules: &GeoRules) -> Result<(), AppError> {
+    let raw = serde_json::to_string(rules)
+        .map_err(|e| AppError::InternalError(format!("Encode geo rules: {}", e)))?;
+    mysql
+        .upsert_setting(
+            SETTING_GEO_RULES,
+            Some(&raw),
+            Some("Country allow/deny rules for proxied requests (JSON)"),
+        )
+        .await
+}
+
+/// Loaded rules and when each refused client was last reported
+#[derive(Default)]
+pub struct GeoFilter {
+    rules: RwLock<GeoRules>,
+    reported: Mutex<HashMap<IpAddr, Instant>>,
+}
+
+impl GeoFilter {
+    /// Re-read the rules from settings
+    pub async fn reload(&self, mysql: &MySqlDb) -> Result<(), AppError> {
+        self.set_rules(load(mysql).await?);
+        Ok(())
+    }
+
+    pub fn set_rules(&self, rules: GeoRules) {
+        *self.rules.write().unwrap() = rules;
+        self.reported.lock().unwrap().clear();
+    }
+
+    pub fn rules(&self) -> GeoRules {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.rules.read().unwrap().enabled
+    }
+
+    pub fn action(&self, country_code: Option<&str>) -> GeoAction {
+        self.rules.read().unwrap().action(country_code)
+    }
+
+    /// Whether a denial of `ip` should be reported (first in REPORT_INTERVAL)
+    pub fn should_report(&self, ip: IpAddr) -> bool {
+        self.should_report_at(ip, Instant::now())
+    }
+
+    fn should_report_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut reported = self.reported.lock().unwrap();
+        if reported.len() >= SWEEP_AT {
+            reported.retain(|_, at| now.saturating_duration_since(*at) < REPORT_INTERVAL);
+        }
+        match reported.get(&ip) {
+            Some(at) if now.saturating_duration_since(*at) < REPORT_INTERVAL => false,
+            _ => {
+                reported.insert(ip, now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(mode: GeoMode, countries: &[&str]) -> GeoRules {
+        GeoRules {
+            enabled: true,
+            mode,
+            countries: countries.iter().map(|c| c.to_string()).collect(),
+            unresolved_action: GeoAction::Allow,
+        }
+    }
+
+    #[test]
+    fn deny_and_allow_modes() {
+        let deny = rules(GeoMode::Deny, &["CN", "RU"]);
+        assert_eq!(deny.action(Some("ru")), GeoAction::Deny);
+        assert_eq!(deny.action(Some("JP")), GeoAction::Allow);
+        assert_eq!(deny.action(None), GeoAction::Allow);
+
+        let mut allow = rules(GeoMode::Allow, &["JP"]);
+        allow.unresolved_action = GeoAction::Deny;
+        assert_eq!(allow.action(Some("JP")), GeoAction::Allow);
+        assert_eq!(allow.action(Some("US")), GeoAction::Deny);
+        assert_eq!(allow.action(None), GeoAction::Deny);
+
+        allow.enabled = false;
+        assert_eq!(allow.action(Some("US")), GeoAction::Allow);
+    }
+
+    #[test]
+    fn countries_are_normalized_and_validated() {
+        let normalized = rules(GeoMode::Deny, &[" ru", "CN", "ru"])
+            .normalized()
+            .unwrap();
+        assert_eq!(normalized.countries, vec!["CN", "RU"]);
+
+        let errors = rules(GeoMode::Deny, &["USA", "1A"])
+            .normalized()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(rules(GeoMode::Allow, &[]).normalized().is_err());
+    }
+
+    #[test]
+    fn denials_are_reported_once_per_interval() {
+        let filter = GeoFilter::default();
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let now = Instant::now();
+        assert!(filter.should_report_at(ip, now));
+        assert!(!filter.should_report_at(ip, now + Duration::from_secs(30)));
+        assert!(filter.should_report_at(ip, now + REPORT_INTERVAL));
+    }
+}
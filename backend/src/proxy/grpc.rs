@@ -0,0 +1,622 @@
+//! gRPC passthrough for routes with `grpc` set
+//!
+//! The reqwest upstream path buffers bodies and drops trailers, so these
+//! routes take their own path: the call goes out over HTTP/2 (prior knowledge
+//! for `http://` targets, ALPN for `https://`), request and response bodies
+//! are streamed frame by frame together with their trailers, and nothing is
+//! buffered, transformed or queued — transformation scripts,
+//! store-and-forward and security headers do not apply. The route's request
+//! body limit does: a larger declared length is refused with 413, and a
+//! stream that grows past it is cut off. Clients have to reach
+//! LPG over HTTP/2 (h2c on the listener); the `:authority` sent upstream is
+//! always the target's, so `preserve_host` has no effect.
+//!
+//! The access log is written when the response stream ends and records the
+//! gRPC method from the path and the grpc-status trailer; the HTTP status is
+//! 200 for nearly every call.
+//!
+//! Routes with `grpc_health_service` are health-checked with
+//! grpc.health.v1.Health/Check instead of HEAD.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{
+    header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, Uri, Version,
+};
+use axum::response::{IntoResponse, Response};
+use http_body_util::{BodyExt, Limited};
+use hyper::body::{Bytes, Frame, Incoming, SizeHint};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+
+use super::request_log::RequestLogContext;
+use super::ProxyState;
+use crate::models::{AccessLog, ProxyRoute};
+
+const GRPC_OK: i32 = 0;
+const GRPC_DEADLINE_EXCEEDED: i32 = 4;
+const GRPC_UNAVAILABLE: i32 = 14;
+
+const HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+/// HealthCheckResponse.ServingStatus
+const SERVING_STATUSES: [&str; 4] = ["UNKNOWN", "SERVING", "NOT_SERVING", "SERVICE_UNKNOWN"];
+const SERVING: u64 = 1;
+
+/// access_logs.upstream_error when the client went away mid-stream
+const STREAM_CANCELLED: &str = "grpc stream closed before the trailers";
+
+/// HTTP/2-only upstream client (shared by the proxy; the health checker has
+/// its own)
+#[derive(Clone)]
+pub struct GrpcClient {
+    client: Client<HttpsConnector<HttpConnector>, Body>,
+}
+
+impl Default for GrpcClient {
+    fn default() -> Self {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http2()
+            .build();
+        Self {
+            client: Client::builder(TokioExecutor::new())
+                .http2_only(true)
+                .build(connector),
+        }
+    }
+}
+
+impl ProxyRoute {
+    /// grpc.health.v1 service to health-check ("" = whole server); None =
+    /// HTTP probe
+    pub fn grpc_health_check(&self) -> Option<&str> {
+        self.grpc_health_service.as_deref().filter(|_| self.grpc)
+    }
+}
+
+/// "package.Service/Method" from a path ending in `/{service}/{method}`
+pub fn method_from_path(path: &str) -> Option<String> {
+    let mut segments = path.trim_end_matches('/').rsplit('/');
+    let method = segments.next().filter(|s| !s.is_empty())?;
+    let service = segments.next().filter(|s| !s.is_empty())?;
+    Some(format!("{}/{}", service, method))
+}
+
+/// grpc-status of a header or trailer block
+pub fn grpc_status(headers: &HeaderMap) -> Option<i32> {
+    headers
+        .get("grpc-status")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// Connection-specific headers HTTP/2 forbids; `te` survives as "trailers"
+fn is_connection_header(name: &HeaderName) -> bool {
+    matches!(
+        name.as_str(),
+        "connection"
+            | "keep-alive"
+            | "proxy-connection"
+            | "proxy-authenticate"
+            | "proxy-authorization"
+            | "transfer-encoding"
+            | "upgrade"
+            | "trailers"
+            | "host"
+    )
+}
+
+/// Drop connection-specific headers from an upstream response
+fn strip_connection_headers(headers: &mut HeaderMap) {
+    let names: Vec<HeaderName> = headers
+        .keys()
+        .filter(|name| is_connection_header(name) || *name == header::TE)
+        .cloned()
+        .collect();
+    for name in names {
+        headers.remove(name);
+    }
+}
+
+/// Client headers to send upstream, plus the forwarding headers
+fn upstream_headers(headers: &HeaderMap, client_ip: &str) -> HeaderMap {
+    let mut out = HeaderMap::with_capacity(headers.len() + 3);
+    for (name, value) in headers {
+        if is_connection_header(name) {
+            continue;
+        }
+        if name == header::TE && value.as_bytes() != b"trailers" {
+            continue;
+        }
+        out.append(name.clone(), value.clone());
+    }
+
+    let xff = match headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+        Some(existing) => format!("{}, {}", existing, client_ip),
+        None => client_ip.to_string(),
+    };
+    let proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("http")
+        .to_string();
+    for (name, value) in [
+        ("x-forwarded-for", xff),
+        ("x-real-ip", client_ip.to_string()),
+        ("x-forwarded-proto", proto),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            out.insert(name, value);
+        }
+    }
+    out
+}
+
+/// Access log entry of a call, before its status is known
+fn access_log(
+    state: &ProxyState,
+    route: &ProxyRoute,
+    request_log: &RequestLogContext,
+) -> AccessLog {
+    let mut log = request_log.entry(
+        state,
+        Some(&route.target),
+        StatusCode::OK.as_u16() as i32,
+        0,
+        None,
+    );
+    log.grpc_method = method_from_path(&request_log.path);
+    log
+}
+
+fn insert_log(state: &ProxyState, log: AccessLog) {
+    let mongo = state.app_state.mongo.clone();
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    runtime.spawn(async move {
+        if let Err(e) = mongo.log_access(&log).await {
+            tracing::warn!("Failed to log access: {}", e);
+        }
+    });
+}
+
+/// Trailers-only error response (the client sees a gRPC status, not just 502)
+fn error_response(status: StatusCode, grpc_status: i32, message: &str) -> Response {
+    let mut response = status.into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc"),
+    );
+    headers.insert("grpc-status", HeaderValue::from(grpc_status));
+    if let Ok(message) = HeaderValue::from_str(message) {
+        headers.insert("grpc-message", message);
+    }
+    response
+}
+
+/// Forward a call to `url` over HTTP/2 and stream the response back
+pub async fn forward(
+    state: &ProxyState,
+    route: &ProxyRoute,
+    url: &str,
+    req: Request<Body>,
+    body_limit: usize,
+    request_log: &RequestLogContext,
+) -> Response {
+    let (parts, body) = req.into_parts();
+    // Counted as the frames stream; past the limit the upstream stream is reset
+    let body = Body::new(Limited::new(body, body_limit));
+    let mut log = access_log(state, route, request_log);
+
+    let uri = match url.parse::<Uri>() {
+        Ok(uri) => uri,
+        Err(e) => {
+            tracing::error!("Invalid gRPC upstream URL {}: {}", url, e);
+            log.status = StatusCode::BAD_GATEWAY.as_u16() as i32;
+            log.grpc_status = Some(GRPC_UNAVAILABLE);
+            log.upstream_error = Some(format!("invalid upstream URL: {}", e));
+            insert_log(state, log);
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                GRPC_UNAVAILABLE,
+                "invalid upstream URL",
+            );
+        }
+    };
+
+    let mut upstream = Request::new(body);
+    *upstream.method_mut() = parts.method;
+    *upstream.uri_mut() = uri;
+    *upstream.version_mut() = Version::HTTP_2;
+    *upstream.headers_mut() = upstream_headers(&parts.headers, &request_log.client_ip);
+
+    // The route timeout bounds the wait for response headers; streams run
+    // as long as both sides keep them open
+    let timeout = Duration::from_millis(route.timeout_ms.max(0) as u64);
+    let sent = tokio::time::timeout(timeout, state.grpc_client.client.request(upstream)).await;
+
+    let response = match sent {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            tracing::error!(
+                "gRPC proxy request failed: {} -> {}: {}",
+                request_log.path,
+                url,
+                e
+            );
+            log.status = StatusCode::BAD_GATEWAY.as_u16() as i32;
+            log.response_time_ms = request_log.start_time.elapsed().as_millis() as i32;
+            log.grpc_status = Some(GRPC_UNAVAILABLE);
+            log.upstream_error = Some(e.to_string());
+            insert_log(state, log);
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                GRPC_UNAVAILABLE,
+                "upstream unavailable",
+            );
+        }
+        Err(_) => {
+            tracing::error!(
+                "gRPC proxy request timed out: {} -> {}",
+                request_log.path,
+                url
+            );
+            log.status = StatusCode::GATEWAY_TIMEOUT.as_u16() as i32;
+            log.response_time_ms = request_log.start_time.elapsed().as_millis() as i32;
+            log.grpc_status = Some(GRPC_DEADLINE_EXCEEDED);
+            log.upstream_error = Some("upstream response headers timed out".to_string());
+            insert_log(state, log);
+            return error_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                GRPC_DEADLINE_EXCEEDED,
+                "upstream timed out",
+            );
+        }
+    };
+
+    let (mut parts, incoming) = response.into_parts();
+    log.status = parts.status.as_u16() as i32;
+    strip_connection_headers(&mut parts.headers);
+    parts.version = Version::default();
+
+    let body = GrpcResponseBody {
+        inner: incoming,
+        bytes: 0,
+        // Trailers-only responses carry the status in the headers
+        grpc_status: grpc_status(&parts.headers),
+        pending: Some(PendingLog {
+            state: state.clone(),
+            log,
+            start_time: request_log.start_time,
+        }),
+    };
+    Response::from_parts(parts, Body::new(body))
+}
+
+struct PendingLog {
+    state: ProxyState,
+    log: AccessLog,
+    start_time: Instant,
+}
+
+/// Upstream response body passed through frame by frame; records the size
+/// and the grpc-status trailer and writes the access log when the stream
+/// ends (or the client drops it)
+struct GrpcResponseBody {
+    inner: Incoming,
+    bytes: u64,
+    grpc_status: Option<i32>,
+    pending: Option<PendingLog>,
+}
+
+impl GrpcResponseBody {
+    fn finish(&mut self, error: Option<String>) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        let mut log = pending.log;
+        log.response_time_ms = pending.start_time.elapsed().as_millis() as i32;
+        log.response_size = Some(self.bytes.min(i32::MAX as u64) as i32);
+        log.grpc_status = self.grpc_status;
+        log.upstream_error = error;
+        insert_log(&pending.state, log);
+    }
+}
+
+impl hyper::body::Body for GrpcResponseBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, hyper::Error>>> {
+        let this = &mut *self;
+        let polled = Pin::new(&mut this.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    this.bytes += data.len() as u64;
+                }
+                if let Some(trailers) = frame.trailers_ref() {
+                    this.grpc_status = grpc_status(trailers).or(this.grpc_status);
+                    this.finish(None);
+                }
+            }
+            Poll::Ready(Some(Err(e))) => this.finish(Some(format!("grpc stream error: {}", e))),
+            Poll::Ready(None) => this.finish(None),
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for GrpcResponseBody {
+    fn drop(&mut self) {
+        self.finish(Some(STREAM_CANCELLED.to_string()));
+    }
+}
+
+// ============================================================================
+// grpc.health.v1 (hand-rolled: one string field out, one enum field back)
+// ============================================================================
+
+fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Length-prefixed, uncompressed gRPC message
+fn frame(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len() + 5);
+    out.push(0);
+    out.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    out.extend_from_slice(message);
+    out
+}
+
+/// The first message of a response body
+fn unframe(body: &[u8]) -> Result<&[u8], String> {
+    let (&compressed, rest) = body.split_first().ok_or("empty response")?;
+    if compressed != 0 {
+        return Err("compressed response".to_string());
+    }
+    let len = rest
+        .get(..4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or("truncated message header")?;
+    rest.get(4..4 + len)
+        .ok_or_else(|| "truncated message".to_string())
+}
+
+/// HealthCheckRequest { string service = 1; }
+fn encode_health_request(service: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    if !service.is_empty() {
+        out.push(0x0a);
+        encode_varint(service.len() as u64, &mut out);
+        out.extend_from_slice(service.as_bytes());
+    }
+    out
+}
+
+/// HealthCheckResponse { ServingStatus status = 1; } (absent = UNKNOWN)
+fn decode_health_status(message: &[u8]) -> Result<u64, String> {
+    let mut status = 0;
+    let mut pos = 0;
+    while pos < message.len() {
+        let key = read_varint(message, &mut pos).ok_or("malformed field key")?;
+        match (key >> 3, key & 7) {
+            (1, 0) => status = read_varint(message, &mut pos).ok_or("malformed status")?,
+            (_, 0) => {
+                read_varint(message, &mut pos).ok_or("malformed varint")?;
+            }
+            (_, 1) => pos += 8,
+            (_, 2) => {
+                let len = read_varint(message, &mut pos).ok_or("malformed length")?;
+                pos += len as usize;
+            }
+            (_, 5) => pos += 4,
+            (_, wire) => return Err(format!("unsupported wire type {}", wire)),
+        }
+    }
+    if pos > message.len() {
+        return Err("truncated response".to_string());
+    }
+    Ok(status)
+}
+
+fn serving_status_name(status: u64) -> String {
+    SERVING_STATUSES
+        .get(status as usize)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("status {}", status))
+}
+
+impl GrpcClient {
+    /// grpc.health.v1.Health/Check on the target's origin; Ok when SERVING
+    pub async fn health_check(
+        &self,
+        target: &str,
+        service: &str,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let origin = url::Url::parse(target)
+            .map_err(|e| format!("invalid target: {}", e))?
+            .origin()
+            .ascii_serialization();
+        let uri: Uri = format!("{}{}", origin, HEALTH_CHECK_PATH)
+            .parse()
+            .map_err(|e| format!("invalid target: {}", e))?;
+
+        let mut req = Request::new(Body::from(frame(&encode_health_request(service))));
+        *req.method_mut() = Method::POST;
+        *req.uri_mut() = uri;
+        *req.version_mut() = Version::HTTP_2;
+        let headers = req.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+
+        let call = async {
+            let response = self.client.request(req).await.map_err(|e| {
+                if e.is_connect() {
+                    "connection_failed".to_string()
+                } else {
+                    e.to_string()
+                }
+            })?;
+            if response.status() != StatusCode::OK {
+                return Err(response.status().as_u16().to_string());
+            }
+            let header_status = grpc_status(response.headers());
+            let collected = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| e.to_string())?;
+            let code = collected
+                .trailers()
+                .and_then(grpc_status)
+                .or(header_status)
+                .ok_or("no grpc-status in the response")?;
+            if code != GRPC_OK {
+                return Err(format!("grpc_status {}", code));
+            }
+            let body = collected.to_bytes();
+            let status = decode_health_status(unframe(&body)?)?;
+            if status == SERVING {
+                Ok(())
+            } else {
+                Err(serving_status_name(status))
+            }
+        };
+        tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| "timeout".to_string())?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_comes_from_the_last_two_segments() {
+        assert_eq!(
+            method_from_path("/grpc.health.v1.Health/Check").as_deref(),
+            Some("grpc.health.v1.Health/Check")
+        );
+        // Route prefix kept (strip_prefix off)
+        assert_eq!(
+            method_from_path("/billing/acme.billing.v1.Invoices/List").as_deref(),
+            Some("acme.billing.v1.Invoices/List")
+        );
+        assert_eq!(method_from_path("/Check"), None);
+        assert_eq!(method_from_path("/"), None);
+    }
+
+    #[test]
+    fn forwards_te_trailers_and_drops_connection_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::TE, HeaderValue::from_static("trailers"));
+        headers.insert(header::HOST, HeaderValue::from_static("gw.example.com"));
+        headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive"));
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        headers.insert("grpc-timeout", HeaderValue::from_static("5S"));
+        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.1"));
+
+        let out = upstream_headers(&headers, "203.0.113.9");
+        assert_eq!(out.get(header::TE).unwrap(), "trailers");
+        assert_eq!(out.get("grpc-timeout").unwrap(), "5S");
+        assert_eq!(out.get(header::CONTENT_TYPE).unwrap(), "application/grpc");
+        assert!(out.get(header::HOST).is_none());
+        assert!(out.get(header::CONNECTION).is_none());
+        assert_eq!(
+            out.get("x-forwarded-for").unwrap(),
+            "198.51.100.1, 203.0.113.9"
+        );
+        assert_eq!(out.get("x-real-ip").unwrap(), "203.0.113.9");
+
+        headers.insert(header::TE, HeaderValue::from_static("gzip"));
+        assert!(upstream_headers(&headers, "203.0.113.9")
+            .get(header::TE)
+            .is_none());
+    }
+
+    #[test]
+    fn grpc_status_is_read_from_headers_or_trailers() {
+        let mut trailers = HeaderMap::new();
+        assert_eq!(grpc_status(&trailers), None);
+        trailers.insert("grpc-status", HeaderValue::from_static("5"));
+        assert_eq!(grpc_status(&trailers), Some(5));
+    }
+
+    #[test]
+    fn health_messages_round_trip() {
+        assert!(encode_health_request("").is_empty());
+        assert_eq!(
+            encode_health_request("svc"),
+            vec![0x0a, 3, b's', b'v', b'c']
+        );
+        assert_eq!(frame(&[0x08, 0x01]), vec![0, 0, 0, 0, 2, 0x08, 0x01]);
+
+        assert_eq!(unframe(&frame(&[0x08, 0x01])).unwrap(), &[0x08, 0x01]);
+        assert!(unframe(&[]).is_err());
+        assert!(unframe(&[1, 0, 0, 0, 0]).is_err());
+        assert!(unframe(&[0, 0, 0, 0, 9, 1]).is_err());
+
+        assert_eq!(decode_health_status(&[0x08, 0x01]).unwrap(), SERVING);
+        assert_eq!(decode_health_status(&[0x08, 0x02]).unwrap(), 2);
+        // Empty message: default UNKNOWN; unknown fields are skipped
+        assert_eq!(decode_health_status(&[]).unwrap(), 0);
+        assert_eq!(
+            decode_health_status(&[0x12, 0x02, b'o', b'k', 0x08, 0x01]).unwrap(),
+            SERVING
+        );
+        assert!(decode_health_status(&[0x12, 0x09, b'o']).is_err());
+        assert_eq!(serving_status_name(2), "NOT_SERVING");
+
+        let mut varint = Vec::new();
+        encode_varint(300, &mut varint);
+        assert_eq!(varint, vec![0xac, 0x02]);
+        assert_eq!(read_varint(&varint, &mut 0), Some(300));
+    }
+}
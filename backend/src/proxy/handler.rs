@@ -2,37 +2,215 @@
 
 use axum::{
     body::Body,
+    body::Bytes,
     extract::ws::WebSocketUpgrade,
     extract::{ConnectInfo, FromRequest, Request, State},
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, Method, StatusCode},
     response::{IntoResponse, Response},
 };
 use chrono::Utc;
+use futures::StreamExt;
 use std::net::SocketAddr;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tracing::{field, Instrument};
 
+use super::cache::{self, CacheKey, CachedResponse, CACHE_HEADER};
+use super::expect::{self, ExpectRejection};
+use super::failover;
+use super::geo_rules::GeoAction;
+use super::grpc;
+use super::inflight::{InFlightGuard, IN_FLIGHT_ABORTED, IN_FLIGHT_ABORTED_STATUS};
+use super::limits::{self, Protection, ProxyLimits, TransferGuard, TransferViolation};
+use super::maintenance_page;
+use super::path::normalize_path;
+use super::rate_limit::RateLimitAction;
+use super::request_log::RequestLogContext;
+use super::security_headers::EffectiveSecurityHeaders;
+use super::store_forward::{self, ForwardQueueItem};
+use super::trace::{self, Phase};
+use super::transform::{FailMode, Hook, HookInput, RouteTransform, TransformError};
+use super::upstream;
 use super::ProxyState;
-use crate::models::AccessLog;
+use crate::api::admin_guard::is_admin_network_allowed;
+use crate::models::{ProxyRoute, RateLimitRule, RouteSecurityHeaders, Severity};
+use crate::network_policy::Surface;
+
+/// access_logs.upstream_error marker for admin_network_only rejections
+pub(crate) const ADMIN_NETWORK_DENIED: &str = "admin_network_only";
+
+/// access_logs.upstream_error marker for requests refused by a rate limit rule
+const RATE_LIMITED: &str = "rate_limited";
+
+/// access_logs.upstream_error marker for requests refused by the country rules
+const GEO_DENIED: &str = "geo_denied";
+
+/// Retry-After sent while a restart drains
+const DRAIN_RETRY_AFTER_SECS: &str = "60";
+
+/// access_logs.upstream_error marker for requests to a warming route
+const ROUTE_WARMING: &str = "route_warming";
+
+/// Retry-After sent while a route warms up
+const WARMING_RETRY_AFTER_SECS: &str = "30";
+
+/// access_logs.upstream_error marker for requests to a route in maintenance
+pub(crate) const ROUTE_MAINTENANCE: &str = "route_maintenance";
+
+/// access_logs.upstream_error marker for requests queued for replay
+const STORE_FORWARD_QUEUED: &str = "store_forward_queued";
 
 /// Main proxy handler
+///
+/// Runs inside a `proxy` span so every log line of the request carries
+/// client_ip, request_id (when known), route_id and target.
 pub async fn proxy_handler(
     State(state): State<ProxyState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     req: Request,
 ) -> Response {
-    let start_time = Instant::now();
+    let client_ip = state
+        .network_policy
+        .load_policy()
+        .client_ip(req.headers(), addr);
+    let span = tracing::info_span!(
+        "proxy",
+        method = %req.method(),
+        path = %req.uri().path(),
+        client_ip = %client_ip,
+        request_id = field::Empty,
+        route_id = field::Empty,
+        target = field::Empty,
+    );
+    if let Some(request_id) = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+    {
+        span.record("request_id", request_id);
+    }
+
+    async move {
+        // A restart is draining: refuse new proxied traffic (including
+        // WebSocket upgrades); the /api admin surface is routed separately
+        if state.drain.is_draining() {
+            tracing::info!("Refused during restart drain");
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, DRAIN_RETRY_AFTER_SECS)],
+                "Service restarting, please retry shortly",
+            )
+                .into_response();
+        }
+
+        // Tracked for the dashboard; aborting drops the request future,
+        // which cancels the upstream call mid-flight
+        let in_flight =
+            state
+                .in_flight
+                .register("http", &client_ip, req.method().as_str(), req.uri().path());
+        let request_log = RequestLogContext::new(&client_ip, &req);
+
+        tokio::select! {
+            response = proxy_request(state.clone(), request_log.clone(), req, &in_flight) => response,
+            _ = in_flight.aborted() => {
+                let (route_id, target) = in_flight.route();
+                tracing::warn!(
+                    "In-flight request {} aborted by admin: {} {} ({} bytes transferred)",
+                    in_flight.id(),
+                    request_log.method,
+                    request_log.path,
+                    in_flight.bytes()
+                );
+                let request_log = RequestLogContext { route_id, ..request_log };
+                log_access(
+                    &state,
+                    &request_log,
+                    target.as_deref(),
+                    IN_FLIGHT_ABORTED_STATUS as i32,
+                    Some(IN_FLIGHT_ABORTED),
+                )
+                .await;
+                let status = StatusCode::from_u16(IN_FLIGHT_ABORTED_STATUS)
+                    .unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+                (status, "Request aborted by administrator").into_response()
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+async fn proxy_request(
+    state: ProxyState,
+    mut request_log: RequestLogContext,
+    req: Request,
+    in_flight: &InFlightGuard,
+) -> Response {
+    let start_time = request_log.start_time;
+    let client_ip = request_log.client_ip.clone();
     let method = req.method().clone();
     let uri = req.uri().clone();
     let headers = req.headers().clone();
-    let path = uri.path();
-    let client_ip = extract_client_ip(&headers, addr);
+    let tracing_active = state.route_tracer.is_active();
+
+    // Check if IP is blocked (compiled network policy, covers CIDR ranges)
+    if state
+        .network_policy
+        .load_policy()
+        .evaluate_str(&client_ip, Surface::Proxy, chrono::Utc::now())
+        .is_denied()
+    {
+        tracing::warn!("Blocked IP attempted access: {}", client_ip);
+        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    }
+    let blocklist_done = tracing_active.then(Instant::now);
 
-    // Check if IP is blocked
-    if let Ok(blocked) = state.app_state.mysql.is_ip_blocked(&client_ip).await {
-        if blocked {
-            tracing::warn!("Blocked IP attempted access: {}", client_ip);
-            return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    // Normalize before matching so encoded traversal or duplicate slashes
+    // cannot select a different route than the prefix suggests
+    let normalized = match normalize_path(uri.path()) {
+        Ok(normalized) => normalized,
+        Err(rejection) => {
+            tracing::warn!(
+                "Rejected request path from {} ({}): {}",
+                client_ip,
+                rejection,
+                uri.path()
+            );
+            let _ = state
+                .app_state
+                .mongo
+                .log_path_rejected(&client_ip, uri.path(), rejection.as_str())
+                .await;
+            log_access(
+                &state,
+                &request_log,
+                None,
+                400,
+                Some(&format!("path rejected: {}", rejection)),
+            )
+            .await;
+            return (StatusCode::BAD_REQUEST, "Invalid request path").into_response();
         }
+    };
+    let path = normalized.matching.as_str();
+    request_log.path = path.to_string();
+
+    // Country rules, before rate limits so refused clients use no tokens
+    if enforce_geo_rules(&state, &client_ip, path) == GeoAction::Deny {
+        log_access(&state, &request_log, None, 403, Some(GEO_DENIED)).await;
+        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    }
+
+    // Per-client-IP rate limits, before route matching so unmatched paths
+    // count as well
+    if let Some(retry_after) = enforce_rate_limits(&state, &client_ip, path) {
+        log_access(&state, &request_log, None, 429, Some(RATE_LIMITED)).await;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            "Too many requests",
+        )
+            .into_response();
     }
 
     // Get host header for DDNS-based routing
@@ -40,36 +218,138 @@ pub async fn proxy_handler(
 
     // Find matching route (considering host for DDNS routing)
     let router = state.router.read().await;
-    let matched_route = match router.match_route(path, host) {
+    let mut matched_route = match router.match_route(path, host) {
         Some(route) => route.clone(),
         None => {
             drop(router);
             // Log 404 for unmatched routes
-            log_access(
-                &state,
-                &client_ip,
-                method.as_str(),
-                path,
-                None,
-                None,
-                404,
-                start_time.elapsed().as_millis() as i32,
-                headers
-                    .get(header::USER_AGENT)
-                    .and_then(|v| v.to_str().ok()),
-                headers.get(header::REFERER).and_then(|v| v.to_str().ok()),
-            )
-            .await;
+            log_access(&state, &request_log, None, 404, None).await;
             return (StatusCode::NOT_FOUND, "No route found").into_response();
         }
     };
 
+    // Canary share: the chosen side becomes the target for the URL, the
+    // access log and the trace
+    let served_by = matched_route.choose_target(&client_ip).to_string();
+    matched_route.target = served_by;
+
+    // Custom access log fields of the route (tenant ids and the like)
+    request_log.route_id = Some(matched_route.id);
+    request_log.custom_fields =
+        state
+            .route_log_fields
+            .extract(&matched_route, &headers, path, uri.query());
+
     // Build target URL
-    let target_url = router.build_target_url(&matched_route, path);
+    let target_url = router.build_target_url(&matched_route, &normalized);
+    // Query string is forwarded exactly as received
     let query_string = uri.query().map(|q| format!("?{}", q)).unwrap_or_default();
     let full_url = format!("{}{}", target_url, query_string);
     drop(router);
 
+    // admin_network_only routes answer 404 to sources outside the admin
+    // networks so their existence is not advertised
+    if matched_route.admin_network_only && !is_admin_network_allowed(&state, &client_ip) {
+        tracing::warn!(
+            "admin_network_only route {} denied for {} ({} {})",
+            matched_route.id,
+            client_ip,
+            method,
+            path
+        );
+        let _ = state
+            .app_state
+            .mongo
+            .log_admin_network_denied(&client_ip, matched_route.id, path)
+            .await;
+        log_access(&state, &request_log, None, 404, Some(ADMIN_NETWORK_DENIED)).await;
+        return (StatusCode::NOT_FOUND, "No route found").into_response();
+    }
+
+    // Maintenance page instead of the target (never reaches upstream)
+    if matched_route.maintenance_mode {
+        log_access(
+            &state,
+            &request_log,
+            Some(&matched_route.target),
+            StatusCode::SERVICE_UNAVAILABLE.as_u16() as i32,
+            Some(ROUTE_MAINTENANCE),
+        )
+        .await;
+        return maintenance_page::response(&matched_route, &headers);
+    }
+
+    // Per-route method restriction, enforced before anything reaches upstream
+    if let Err(allow) = matched_route.check_method(method.as_str()) {
+        tracing::info!(
+            "Method {} not allowed on route {} for {} ({}; allowed: {})",
+            method,
+            matched_route.id,
+            client_ip,
+            path,
+            allow
+        );
+        state
+            .proxy_violations
+            .record(matched_route.id, Protection::MethodNotAllowed);
+        log_access(
+            &state,
+            &request_log,
+            Some(&matched_route.target),
+            StatusCode::METHOD_NOT_ALLOWED.as_u16() as i32,
+            Some(Protection::MethodNotAllowed.as_str()),
+        )
+        .await;
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(header::ALLOW, allow)],
+            "Method not allowed",
+        )
+            .into_response();
+    }
+
+    // Activated before the target was healthy: hold traffic until the
+    // health checker sees it up
+    if state.route_warmup.is_warming(matched_route.id) {
+        tracing::debug!(
+            "Route {} is warming up, refusing {}",
+            matched_route.id,
+            path
+        );
+        log_access(
+            &state,
+            &request_log,
+            Some(&matched_route.target),
+            StatusCode::SERVICE_UNAVAILABLE.as_u16() as i32,
+            Some(ROUTE_WARMING),
+        )
+        .await;
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, WARMING_RETRY_AFTER_SECS)],
+            "Service starting, please retry shortly",
+        )
+            .into_response();
+    }
+
+    let mut trace = blocklist_done.and_then(|at| {
+        state.route_tracer.attach(
+            start_time,
+            at,
+            matched_route.id,
+            headers.get("x-request-id").and_then(|v| v.to_str().ok()),
+        )
+    });
+
+    in_flight.set_route(matched_route.id, &matched_route.target);
+
+    let span = tracing::Span::current();
+    span.record("route_id", matched_route.id);
+    span.record("target", matched_route.target.as_str());
+    if let Some(t) = &trace {
+        span.record("request_id", t.request_id.as_str());
+    }
+
     tracing::debug!("Proxying {} {} -> {}", method, path, full_url);
 
     // WebSocket upgrade detection
@@ -81,15 +361,6 @@ pub async fn proxy_handler(
 
     if is_websocket && matched_route.websocket_support {
         // Extract WebSocketUpgrade from the request
-        let user_agent = headers
-            .get(header::USER_AGENT)
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
-        let referer = headers
-            .get(header::REFERER)
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
-
         match WebSocketUpgrade::from_request(req, &()).await {
             Ok(ws) => {
                 return super::ws_handler::handle_websocket_upgrade(
@@ -97,10 +368,7 @@ pub async fn proxy_handler(
                     state,
                     matched_route,
                     full_url,
-                    client_ip,
-                    path.to_string(),
-                    user_agent,
-                    referer,
+                    request_log,
                 )
                 .await;
             }
@@ -119,30 +387,110 @@ pub async fn proxy_handler(
             .into_response();
     }
 
-    // Build upstream request
-    let mut request_builder = state
-        .http_client
-        .request(convert_method(&method), &full_url);
+    // gRPC: streamed over HTTP/2 with trailers, bypassing the buffered path
+    // (transformations, store-and-forward, security headers); the request
+    // body limit still applies
+    if matched_route.grpc {
+        let body_limit = state
+            .proxy_limits
+            .read()
+            .await
+            .request_body_limit(&matched_route);
+        if let Some(len) =
+            limits::declared_body_len(&headers).filter(|len| *len > body_limit as u64)
+        {
+            let violation = ViolationContext {
+                state: &state,
+                request_log: &request_log,
+                route: &matched_route,
+            };
+            return violation.reject_body_too_large(body_limit, Some(len)).await;
+        }
+        let response = grpc::forward(
+            &state,
+            &matched_route,
+            &full_url,
+            req,
+            body_limit,
+            &request_log,
+        )
+        .await;
+        if let Some(mut t) = trace.take() {
+            t.mark(Phase::Ttfb);
+            t.finish(method.as_str(), path, response.status().as_u16(), None);
+        }
+        return response;
+    }
+
+    // Per-route response cache: GETs of shared content only
+    let cache_key = (method == Method::GET
+        && matched_route.cache_ttl_sec > 0
+        && cache::request_cacheable(&headers))
+    .then(|| CacheKey {
+        route_id: matched_route.id,
+        path: path.to_string(),
+        query: uri.query().map(str::to_string),
+        accept_encoding: cache::encoding_variant(&headers),
+    });
+    if let Some(key) = cache_key
+        .as_ref()
+        .filter(|_| !cache::bypass_requested(&headers))
+    {
+        if let Some(cached) = state.response_cache.get(key) {
+            if let Some(mut t) = trace.take() {
+                t.mark(Phase::Ttfb);
+                t.finish(method.as_str(), path, cached.status.as_u16(), None);
+            }
+            log_access(
+                &state,
+                &request_log,
+                Some(&matched_route.target),
+                cached.status.as_u16() as i32,
+                None,
+            )
+            .await;
+            let https = headers
+                .get("x-forwarded-proto")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|p| p.eq_ignore_ascii_case("https"));
+            let mut response = Response::new(Body::from(cached.body));
+            *response.status_mut() = cached.status;
+            *response.headers_mut() = cached.headers;
+            response
+                .headers_mut()
+                .insert(CACHE_HEADER, cache::outcome_header(true));
+            apply_security_headers(&state, &matched_route, response.headers_mut(), https).await;
+            return response;
+        }
+    }
 
-    // Forward headers
+    // Forward headers (kept as a list so a queued request replays with the same set)
+    let expect_mode = matched_route.expect_continue();
+    let upstream_authorization = matched_route.upstream_authorization();
+    let mut forwarded: Vec<(String, String)> = Vec::with_capacity(headers.len() + 4);
     for (key, value) in headers.iter() {
         // Skip hop-by-hop headers
         if is_hop_by_hop_header(key.as_str()) {
             continue;
         }
 
+        // Route credentials replace the client's
+        if key == header::AUTHORIZATION && upstream_authorization.is_some() {
+            continue;
+        }
+
+        // Expect is answered here unless the route passes it through
+        if key == header::EXPECT && !expect_mode.forwards_header() {
+            continue;
+        }
+
         // Handle Host header
-        if key == header::HOST {
-            if matched_route.preserve_host {
-                if let Ok(s) = value.to_str() {
-                    request_builder = request_builder.header(key.as_str(), s);
-                }
-            }
+        if key == header::HOST && !matched_route.preserve_host {
             continue;
         }
 
         if let Ok(s) = value.to_str() {
-            request_builder = request_builder.header(key.as_str(), s);
+            forwarded.push((key.as_str().to_string(), s.to_string()));
         }
     }
 
@@ -152,37 +500,265 @@ pub async fn proxy_handler(
     } else {
         client_ip.clone()
     };
-    request_builder = request_builder.header("X-Forwarded-For", &xff);
-    request_builder = request_builder.header("X-Real-IP", &client_ip);
+    forwarded.push(("X-Forwarded-For".to_string(), xff));
+    forwarded.push(("X-Real-IP".to_string(), client_ip.clone()));
 
     // Add X-Forwarded-Proto
     let proto = headers
         .get("x-forwarded-proto")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("http");
-    request_builder = request_builder.header("X-Forwarded-Proto", proto);
+    forwarded.push(("X-Forwarded-Proto".to_string(), proto.to_string()));
+    if let Some(authorization) = upstream_authorization {
+        forwarded.push(("Authorization".to_string(), authorization));
+    }
+
+    // Route header rules override anything set above
+    let header_rewrite = matched_route.header_rewrite();
+    if let Some(rules) = &header_rewrite {
+        rules.apply_to_request(&mut forwarded);
+    }
+
+    let limits = *state.proxy_limits.read().await;
+    let violation = ViolationContext {
+        state: &state,
+        request_log: &request_log,
+        route: &matched_route,
+    };
+
+    // Refuse a declared oversized body before any of it is read
+    let body_limit = limits.request_body_limit(&matched_route);
+    if let Some(len) = limits::declared_body_len(&headers).filter(|len| *len > body_limit as u64) {
+        return violation.reject_body_too_large(body_limit, Some(len)).await;
+    }
 
-    // Set timeout
-    let timeout = std::time::Duration::from_millis(matched_route.timeout_ms as u64);
-    request_builder = request_builder.timeout(timeout);
+    // Answer Expect before the body is polled (hyper sends 100 Continue then)
+    match expect::check_expectation(expect_mode, &headers, body_limit) {
+        Ok(()) => {}
+        Err(ExpectRejection::TooLarge(len)) => {
+            return violation.reject_body_too_large(body_limit, Some(len)).await;
+        }
+        Err(ExpectRejection::Unsupported(value)) => {
+            let detail = format!("unsupported expectation {:?}", value);
+            return violation
+                .reject(
+                    Protection::ExpectationFailed,
+                    StatusCode::EXPECTATION_FAILED,
+                    detail,
+                )
+                .await;
+        }
+    }
 
-    // Send request body (100MB limit)
-    let body = req.into_body();
-    let body_bytes = match axum::body::to_bytes(body, 100 * 1024 * 1024).await {
+    // Read request body (size counted as it streams, idle and slow-client limits)
+    let body = read_request_body(req.into_body(), &limits, body_limit, in_flight).await;
+    let mut body_bytes = match body {
         Ok(bytes) => bytes,
-        Err(e) => {
+        Err(BodyReadError::Protection(Protection::RequestBodyLimit, _)) => {
+            return violation.reject_body_too_large(body_limit, None).await;
+        }
+        Err(BodyReadError::Protection(protection, detail)) => {
+            return violation
+                .reject(protection, StatusCode::REQUEST_TIMEOUT, detail)
+                .await;
+        }
+        Err(BodyReadError::Io(e)) => {
             tracing::error!("Failed to read request body: {}", e);
             return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response();
         }
+        // Only upstream reads run on the route deadline
+        Err(BodyReadError::UpstreamTimeout) => {
+            return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response();
+        }
+    };
+
+    // Opt-in transformation script, request side
+    let transform = matched_route.transform().filter(|policy| policy.enabled);
+    let mut full_url = full_url;
+    if let Some(policy) = &transform {
+        if state
+            .route_transforms
+            .has_hook(&matched_route, policy, Hook::Request)
+        {
+            let input = HookInput {
+                hook: Hook::Request,
+                method: method.as_str(),
+                path,
+                query: uri.query(),
+                status: None,
+                client_ip: &client_ip,
+                headers: forwarded
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect(),
+                body: &body_bytes,
+            };
+            match state
+                .route_transforms
+                .run(&matched_route, policy, &input)
+                .await
+            {
+                Ok(outcome) => {
+                    outcome.apply_to_pairs(&mut forwarded);
+                    if let Some(body) = outcome.body {
+                        body_bytes = body;
+                    }
+                    if let Some(query) = outcome.query {
+                        full_url = if query.is_empty() {
+                            target_url.clone()
+                        } else {
+                            format!("{}?{}", target_url, query)
+                        };
+                    }
+                }
+                Err(e) => {
+                    if let Some(response) = violation.transform_failed(policy, e).await {
+                        return response;
+                    }
+                }
+            }
+        }
+    }
+
+    // Kept whole so a retry or fallback can send it again
+    let body_bytes = Bytes::from(body_bytes);
+
+    // Opt-in store-and-forward: keep a copy of matching requests in case
+    // the upstream cannot take them
+    let store_forward = matched_route
+        .store_forward()
+        .filter(|policy| policy.matches(method.as_str(), path, body_bytes.len()))
+        .map(|policy| (policy, body_bytes.clone()));
+
+    if let Some(t) = trace.as_mut() {
+        t.mark(Phase::RequestBody);
+    }
+
+    // Execute request: the target first, then retries and fallbacks where
+    // the route allows them (timeout_ms covers connect through the body)
+    let mut attempts = failover::Attempts::new(&matched_route, &method, &headers);
+    let mut attempt_target = matched_route.target.clone();
+    let mut attempt_url = full_url.clone();
+    let sent = loop {
+        let mut request_builder = state
+            .upstream
+            .checkout(&matched_route)
+            .request(convert_method(&method), &attempt_url);
+        for (key, value) in &forwarded {
+            request_builder = request_builder.header(key.as_str(), value.as_str());
+        }
+        request_builder = request_builder.timeout(upstream::route_timeout(&matched_route));
+        if !body_bytes.is_empty() {
+            request_builder = request_builder.body(body_bytes.clone());
+        }
+
+        let sent_at = Instant::now();
+        let sent = trace::send(trace.as_ref(), request_builder.send()).await;
+        state
+            .upstream
+            .record_sent(matched_route.id, &sent, sent_at.elapsed());
+
+        let failure = failover::AttemptFailure::of(&sent);
+        let Some(next) = attempts.retry(failure) else {
+            break sent;
+        };
+        tracing::warn!(
+            "{} {} on route {}: {} {}, retrying at {}",
+            method,
+            path,
+            matched_route.id,
+            attempt_target,
+            failure.map(|f| f.to_string()).unwrap_or_default(),
+            next
+        );
+        attempt_url = failover::rebase(&full_url, &matched_route.target, next);
+        attempt_target = next.to_string();
     };
+    // The target that answered, for the log and Location rewrites
+    let served_target = attempt_target;
+    full_url = attempt_url;
 
-    if !body_bytes.is_empty() {
-        request_builder = request_builder.body(body_bytes.to_vec());
+    if let Some((policy, body)) = &store_forward {
+        if let Some(reason) = store_forward::queue_reason(&sent) {
+            let item = ForwardQueueItem::new(
+                &matched_route,
+                policy,
+                method.as_str(),
+                &full_url,
+                path,
+                forwarded,
+                body,
+                &client_ip,
+                reason,
+                Utc::now(),
+            );
+            match state.app_state.mongo.insert_forward_item(&item).await {
+                Ok(()) => {
+                    tracing::warn!(
+                        "Queued {} {} on route {} for replay ({}): {}",
+                        method,
+                        path,
+                        matched_route.id,
+                        item.queue_id,
+                        item.reason
+                    );
+                    if let Some(mut t) = trace.take() {
+                        t.mark(Phase::Ttfb);
+                        t.finish(
+                            method.as_str(),
+                            path,
+                            StatusCode::ACCEPTED.as_u16(),
+                            Some(item.reason.clone()),
+                        );
+                    }
+                    log_access(
+                        &state,
+                        &request_log,
+                        Some(&served_target),
+                        StatusCode::ACCEPTED.as_u16() as i32,
+                        Some(STORE_FORWARD_QUEUED),
+                    )
+                    .await;
+                    return (
+                        StatusCode::ACCEPTED,
+                        axum::Json(serde_json::json!({
+                            "queued": true,
+                            "queue_id": item.queue_id,
+                        })),
+                    )
+                        .into_response();
+                }
+                // Fall through to the normal upstream error
+                Err(e) => tracing::error!(
+                    "Failed to queue request on route {}: {}",
+                    matched_route.id,
+                    e
+                ),
+            }
+        }
     }
 
-    // Execute request
-    let response = match request_builder.send().await {
-        Ok(resp) => resp,
+    let response = match sent {
+        Ok(resp) => {
+            if let Some(t) = trace.as_mut() {
+                t.mark(Phase::Ttfb);
+            }
+            // Mutations drop the cached responses of their path
+            if !method.is_safe() {
+                state.response_cache.invalidate_path(matched_route.id, path);
+            }
+            resp
+        }
+        // hyper refuses heads beyond its own buffer before we can measure them
+        Err(e) if limits::is_head_too_large(&e) => {
+            return violation
+                .reject(
+                    Protection::ResponseHeaderSize,
+                    StatusCode::BAD_GATEWAY,
+                    "upstream response head exceeds the client buffer".to_string(),
+                )
+                .await;
+        }
         Err(e) => {
             tracing::error!("Proxy request failed: {} -> {}: {}", path, full_url, e);
 
@@ -191,20 +767,23 @@ pub async fn proxy_handler(
             } else {
                 StatusCode::BAD_GATEWAY
             };
+            let upstream_error = e.to_string();
+            if let Some(mut t) = trace.take() {
+                t.mark(Phase::Ttfb);
+                t.finish(
+                    method.as_str(),
+                    path,
+                    status.as_u16(),
+                    Some(upstream_error.clone()),
+                );
+            }
 
             log_access(
                 &state,
-                &client_ip,
-                method.as_str(),
-                path,
-                Some(matched_route.id),
-                Some(&matched_route.target),
+                &request_log,
+                Some(&served_target),
                 status.as_u16() as i32,
-                start_time.elapsed().as_millis() as i32,
-                headers
-                    .get(header::USER_AGENT)
-                    .and_then(|v| v.to_str().ok()),
-                headers.get(header::REFERER).and_then(|v| v.to_str().ok()),
+                Some(&upstream_error),
             )
             .await;
 
@@ -213,33 +792,126 @@ pub async fn proxy_handler(
     };
 
     let upstream_status = response.status();
-    let response_headers = response.headers().clone();
+    let mut response_headers = response.headers().clone();
+    if let Some(rules) = &header_rewrite {
+        rules.apply_to_response(&mut response_headers);
+    }
+
+    let header_size = limits::header_bytes(&response_headers);
+    if header_size > limits.max_response_header_bytes {
+        return violation
+            .reject(
+                Protection::ResponseHeaderSize,
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "upstream response headers are {} bytes (limit {})",
+                    header_size, limits.max_response_header_bytes
+                ),
+            )
+            .await;
+    }
 
-    // Read response body
-    let response_body = match response.bytes().await {
+    // Read response body (buffer cap, idle and slow-upstream limits)
+    let mut response_body = match read_response_body(response, &limits, in_flight).await {
         Ok(bytes) => bytes,
-        Err(e) => {
+        Err(BodyReadError::Protection(protection, detail)) => {
+            return violation
+                .reject(protection, StatusCode::BAD_GATEWAY, detail)
+                .await;
+        }
+        Err(BodyReadError::UpstreamTimeout) => {
+            state.upstream.record_timeout(matched_route.id);
+            let upstream_error = format!(
+                "upstream response not complete within {} ms",
+                matched_route.timeout_ms
+            );
+            tracing::error!(
+                "Proxy request failed: {} -> {}: {}",
+                path,
+                full_url,
+                upstream_error
+            );
+            if let Some(t) = trace.take() {
+                t.finish(
+                    method.as_str(),
+                    path,
+                    StatusCode::GATEWAY_TIMEOUT.as_u16(),
+                    Some(upstream_error.clone()),
+                );
+            }
+            log_access(
+                &state,
+                &request_log,
+                Some(&served_target),
+                StatusCode::GATEWAY_TIMEOUT.as_u16() as i32,
+                Some(&upstream_error),
+            )
+            .await;
+            return (
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Upstream error: {}", upstream_error),
+            )
+                .into_response();
+        }
+        Err(BodyReadError::Io(e)) => {
             tracing::error!("Failed to read upstream response: {}", e);
             return (StatusCode::BAD_GATEWAY, "Failed to read upstream response").into_response();
         }
     };
 
-    let elapsed_ms = start_time.elapsed().as_millis() as i32;
+    // Transformation script, response side
+    if let Some(policy) = &transform {
+        if state
+            .route_transforms
+            .has_hook(&matched_route, policy, Hook::Response)
+        {
+            let input = HookInput {
+                hook: Hook::Response,
+                method: method.as_str(),
+                path,
+                query: None,
+                status: Some(upstream_status.as_u16()),
+                client_ip: &client_ip,
+                headers: response_headers
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.as_str(), v.to_str().ok()?)))
+                    .collect(),
+                body: &response_body,
+            };
+            match state
+                .route_transforms
+                .run(&matched_route, policy, &input)
+                .await
+            {
+                Ok(outcome) => {
+                    outcome.apply_to_header_map(&mut response_headers);
+                    if let Some(body) = outcome.body {
+                        response_body = body;
+                    }
+                }
+                Err(e) => {
+                    if let Some(response) = violation.transform_failed(policy, e).await {
+                        return response;
+                    }
+                }
+            }
+        }
+    }
+
+    let trace_request_id = trace.take().map(|mut t| {
+        t.mark(Phase::ResponseBody);
+        let request_id = t.request_id.clone();
+        t.finish(method.as_str(), path, upstream_status.as_u16(), None);
+        request_id
+    });
 
     // Log access
     log_access(
         &state,
-        &client_ip,
-        method.as_str(),
-        path,
-        Some(matched_route.id),
-        Some(&matched_route.target),
+        &request_log,
+        Some(&served_target),
         upstream_status.as_u16() as i32,
-        elapsed_ms,
-        headers
-            .get(header::USER_AGENT)
-            .and_then(|v| v.to_str().ok()),
-        headers.get(header::REFERER).and_then(|v| v.to_str().ok()),
+        None,
     )
     .await;
 
@@ -247,6 +919,11 @@ pub async fn proxy_handler(
     let axum_status =
         StatusCode::from_u16(upstream_status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
     let mut builder = Response::builder().status(axum_status);
+    if let Some(request_id) = &trace_request_id {
+        if !response_headers.contains_key("x-request-id") {
+            builder = builder.header("X-Request-Id", request_id.as_str());
+        }
+    }
 
     // Determine original path prefix for Location header rewriting
     let original_prefix = if matched_route.strip_prefix {
@@ -269,7 +946,7 @@ pub async fn proxy_handler(
             if let Ok(location_str) = value.to_str() {
                 let rewritten = rewrite_location_header(
                     location_str,
-                    &matched_route.target,
+                    &served_target,
                     original_prefix.as_deref(),
                     request_scheme,
                     request_host,
@@ -286,31 +963,371 @@ pub async fn proxy_handler(
         }
     }
 
+    // Stored as sent so far; security headers are applied per response
+    let response_body = Bytes::from(response_body);
+    if let Some(key) = cache_key {
+        if let Some(out_headers) = builder
+            .headers_ref()
+            .filter(|h| cache::response_cacheable(axum_status, h))
+        {
+            let cached = CachedResponse {
+                status: axum_status,
+                headers: out_headers.clone(),
+                body: response_body.clone(),
+            };
+            let ttl = Duration::from_secs(matched_route.cache_ttl_sec as u64);
+            state.response_cache.insert(key, cached, ttl);
+        }
+        builder = builder.header(CACHE_HEADER, cache::outcome_header(false));
+    }
+
+    // HSTS only for HTTPS origins (X-Forwarded-Proto from the TLS-terminating front)
+    if let Some(out_headers) = builder.headers_mut() {
+        apply_security_headers(
+            &state,
+            &matched_route,
+            out_headers,
+            request_scheme.eq_ignore_ascii_case("https"),
+        )
+        .await;
+    }
+
     builder.body(Body::from(response_body)).unwrap_or_else(|_| {
         (StatusCode::INTERNAL_SERVER_ERROR, "Response build failed").into_response()
     })
 }
 
-/// Extract client IP from headers or connection
-fn extract_client_ip(headers: &HeaderMap, addr: SocketAddr) -> String {
-    // Check X-Forwarded-For first
-    if let Some(xff) = headers.get("x-forwarded-for") {
-        if let Ok(s) = xff.to_str() {
-            if let Some(ip) = s.split(',').next() {
-                return ip.trim().to_string();
-            }
+/// Evaluate the country rules for a client; denials are reported in the
+/// background (once per client and interval)
+fn enforce_geo_rules(state: &ProxyState, client_ip: &str, path: &str) -> GeoAction {
+    if !state.geo_filter.is_enabled() {
+        return GeoAction::Allow;
+    }
+    let country_code = state
+        .geoip
+        .as_ref()
+        .and_then(|reader| reader.lookup(client_ip))
+        .and_then(|geo| geo.country_code);
+    let action = state.geo_filter.action(country_code.as_deref());
+    if action == GeoAction::Deny {
+        let report =
+            crate::ip::parse_ip(client_ip).is_none_or(|ip| state.geo_filter.should_report(ip));
+        if report {
+            tracing::warn!(
+                "Country rules refused {} (country {})",
+                client_ip,
+                country_code.as_deref().unwrap_or("unknown")
+            );
+            let state = state.clone();
+            let ip = client_ip.to_string();
+            let path = path.to_string();
+            let mode = state.geo_filter.rules().mode;
+            tokio::spawn(async move {
+                let _ = state
+                    .app_state
+                    .mongo
+                    .log_geo_denied(&ip, country_code.as_deref(), &path, mode)
+                    .await;
+            });
+        }
+    }
+    action
+}
+
+/// Count a request against the rate limit rules; Some(Retry-After seconds)
+/// when a `block` rule refuses it. First violations are reported in the
+/// background (security event, auto-block, Discord).
+fn enforce_rate_limits(state: &ProxyState, client_ip: &str, path: &str) -> Option<u64> {
+    let ip = crate::ip::parse_ip(client_ip)?;
+    let lan = state.network_policy.load_policy().is_lan(ip);
+    let mut retry_after: Option<u64> = None;
+    for hit in state.rate_limiter.check(ip, path, lan) {
+        let refused = hit.rule.action == RateLimitAction::Block;
+        if refused {
+            let secs = hit.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            retry_after = Some(retry_after.map_or(secs, |r| r.max(secs)));
+        }
+        if hit.first {
+            tracing::warn!(
+                "Rate limit rule {} ({} requests per {}s below {}) exceeded by {}",
+                hit.rule.id,
+                hit.rule.requests_per_window,
+                hit.rule.window_sec,
+                hit.rule.path_prefix,
+                ip
+            );
+            let state = state.clone();
+            let path = path.to_string();
+            tokio::spawn(async move {
+                report_rate_limit(&state, &ip.to_string(), &hit.rule, &path, refused).await;
+            });
+        }
+    }
+    retry_after
+}
+
+async fn report_rate_limit(
+    state: &ProxyState,
+    ip: &str,
+    rule: &RateLimitRule,
+    path: &str,
+    refused: bool,
+) {
+    let _ = state
+        .app_state
+        .mongo
+        .log_rate_limit_exceeded(ip, rule, path, refused)
+        .await;
+    let Some(secs) = rule.block_duration_sec.filter(|_| refused) else {
+        return;
+    };
+
+    // Another instance (or an earlier window) may have blocked it already
+    match state.app_state.mysql.is_ip_blocked(ip).await {
+        Ok(false) => {}
+        Ok(true) => return,
+        Err(e) => {
+            tracing::error!("Rate limit auto-block of {} skipped: {}", ip, e);
+            return;
         }
     }
+    let expires_at = Utc::now() + chrono::Duration::seconds(secs as i64);
+    let reason = format!(
+        "rate limit: over {} requests per {}s below {} (rule {})",
+        rule.requests_per_window, rule.window_sec, rule.path_prefix, rule.id
+    );
+    if let Err(e) = state
+        .app_state
+        .mysql
+        .auto_block_ip(ip, &reason, Some(expires_at))
+        .await
+    {
+        tracing::error!("Rate limit auto-block of {} failed: {}", ip, e);
+        return;
+    }
+    tracing::warn!("Auto-blocked {} until {}: {}", ip, expires_at, reason);
+    let _ = state
+        .app_state
+        .mongo
+        .log_ip_blocked(ip, &reason, Severity::High)
+        .await;
+    if let Err(e) = state.rebuild_network_policy().await {
+        tracing::error!("Failed to rebuild network policy after auto-block: {}", e);
+    }
+    state
+        .notifier
+        .notify_rate_limit_block(ip, rule, expires_at)
+        .await;
+}
+
+/// Security headers (global policy + route override) of an outgoing response
+async fn apply_security_headers(
+    state: &ProxyState,
+    route: &ProxyRoute,
+    headers: &mut HeaderMap,
+    https: bool,
+) {
+    let security_headers = EffectiveSecurityHeaders::merge(
+        &*state.security_headers.read().await,
+        RouteSecurityHeaders::from_column(route.security_headers.as_deref()).as_ref(),
+    );
+    security_headers.apply(headers, https);
+    state.security_header_samples.record(route.id, headers);
+}
+
+/// Failure while reading a request or response body
+enum BodyReadError {
+    /// A hardening limit fired (with a human-readable detail)
+    Protection(Protection, String),
+    /// The route's `timeout_ms` ran out while the upstream body was read
+    UpstreamTimeout,
+    Io(String),
+}
 
-    // Check X-Real-IP
-    if let Some(xri) = headers.get("x-real-ip") {
-        if let Ok(s) = xri.to_str() {
-            return s.to_string();
+fn transfer_violation(
+    violation: TransferViolation,
+    too_large: Protection,
+    too_slow: Protection,
+    guard_limit: usize,
+    min_rate: u64,
+) -> BodyReadError {
+    match violation {
+        TransferViolation::TooLarge => {
+            BodyReadError::Protection(too_large, format!("body exceeds {} bytes", guard_limit))
         }
+        TransferViolation::TooSlow => BodyReadError::Protection(
+            too_slow,
+            format!("transfer rate below {} bytes/sec", min_rate),
+        ),
+    }
+}
+
+/// Read the client request body under the configured limits (`max_bytes`:
+/// the route's body limit)
+async fn read_request_body(
+    body: Body,
+    limits: &ProxyLimits,
+    max_bytes: usize,
+    in_flight: &InFlightGuard,
+) -> Result<Vec<u8>, BodyReadError> {
+    let mut stream = body.into_data_stream();
+    let mut guard = TransferGuard::new(max_bytes, limits);
+    let mut buf = Vec::new();
+
+    loop {
+        let chunk = match tokio::time::timeout(limits.idle_timeout, stream.next()).await {
+            Ok(Some(Ok(chunk))) => chunk,
+            Ok(Some(Err(e))) => return Err(BodyReadError::Io(e.to_string())),
+            Ok(None) => return Ok(buf),
+            Err(_) => {
+                return Err(BodyReadError::Protection(
+                    Protection::RequestIdle,
+                    format!("no request data for {}s", limits.idle_timeout.as_secs()),
+                ))
+            }
+        };
+        guard.on_chunk(chunk.len()).map_err(|v| {
+            transfer_violation(
+                v,
+                Protection::RequestBodyLimit,
+                Protection::RequestSlowTransfer,
+                max_bytes,
+                limits.min_transfer_rate_bps,
+            )
+        })?;
+        in_flight.add_bytes(chunk.len());
+        buf.extend_from_slice(&chunk);
+    }
+}
+
+/// Read the upstream response body under the configured limits
+async fn read_response_body(
+    mut response: reqwest::Response,
+    limits: &ProxyLimits,
+    in_flight: &InFlightGuard,
+) -> Result<Vec<u8>, BodyReadError> {
+    let mut guard = TransferGuard::new(limits.max_response_buffer_bytes, limits);
+    let mut buf = Vec::new();
+
+    loop {
+        let chunk = match tokio::time::timeout(limits.idle_timeout, response.chunk()).await {
+            Ok(Ok(Some(chunk))) => chunk,
+            Ok(Ok(None)) => return Ok(buf),
+            Ok(Err(e)) if e.is_timeout() => return Err(BodyReadError::UpstreamTimeout),
+            Ok(Err(e)) => return Err(BodyReadError::Io(e.to_string())),
+            Err(_) => {
+                return Err(BodyReadError::Protection(
+                    Protection::ResponseIdle,
+                    format!("no upstream data for {}s", limits.idle_timeout.as_secs()),
+                ))
+            }
+        };
+        guard.on_chunk(chunk.len()).map_err(|v| {
+            transfer_violation(
+                v,
+                Protection::ResponseBufferLimit,
+                Protection::ResponseSlowTransfer,
+                limits.max_response_buffer_bytes,
+                limits.min_transfer_rate_bps,
+            )
+        })?;
+        in_flight.add_bytes(chunk.len());
+        buf.extend_from_slice(&chunk);
+    }
+}
+
+/// Request details needed to log and count a protection violation
+struct ViolationContext<'a> {
+    state: &'a ProxyState,
+    request_log: &'a RequestLogContext,
+    route: &'a ProxyRoute,
+}
+
+impl ViolationContext<'_> {
+    /// Count, log and answer a fired protection
+    async fn reject(&self, protection: Protection, status: StatusCode, detail: String) -> Response {
+        tracing::warn!(
+            "Proxy protection {} fired on route {} ({} {}): {}",
+            protection.as_str(),
+            self.route.id,
+            self.request_log.method,
+            self.request_log.path,
+            detail
+        );
+        self.state
+            .proxy_violations
+            .record(self.route.id, protection);
+
+        let error = format!("{}: {}", protection.as_str(), detail);
+        log_access(
+            self.state,
+            self.request_log,
+            Some(&self.route.target),
+            status.as_u16() as i32,
+            Some(&error),
+        )
+        .await;
+
+        (status, format!("Proxy protection triggered: {}", error)).into_response()
+    }
+
+    /// Record an oversized request body as a security event, then answer 413
+    /// (`declared`: its Content-Length, None when counted while streaming)
+    async fn reject_body_too_large(&self, limit: usize, declared: Option<u64>) -> Response {
+        let _ = self
+            .state
+            .app_state
+            .mongo
+            .log_body_too_large(
+                &self.request_log.client_ip,
+                self.route.id,
+                &self.request_log.path,
+                limit,
+                declared,
+            )
+            .await;
+        let detail = match declared {
+            Some(len) => format!("declared body of {} bytes exceeds {} bytes", len, limit),
+            None => format!("body exceeds {} bytes", limit),
+        };
+        self.reject(
+            Protection::RequestBodyLimit,
+            StatusCode::PAYLOAD_TOO_LARGE,
+            detail,
+        )
+        .await
     }
 
-    // Fall back to connection address
-    addr.ip().to_string()
+    /// Log a failed transformation script; the 502 to send when the route
+    /// fails closed, None to carry on untransformed
+    async fn transform_failed(
+        &self,
+        policy: &RouteTransform,
+        error: TransformError,
+    ) -> Option<Response> {
+        tracing::warn!(
+            "Route {} {} ({} {}, failing {:?})",
+            self.route.id,
+            error,
+            self.request_log.method,
+            self.request_log.path,
+            policy.on_error
+        );
+        if policy.on_error == FailMode::Open {
+            return None;
+        }
+
+        let error = error.to_string();
+        log_access(
+            self.state,
+            self.request_log,
+            Some(&self.route.target),
+            StatusCode::BAD_GATEWAY.as_u16() as i32,
+            Some(&error),
+        )
+        .await;
+        Some((StatusCode::BAD_GATEWAY, "Transformation failed").into_response())
+    }
 }
 
 /// Convert axum Method to reqwest Method
@@ -409,39 +1426,18 @@ fn rewrite_location_header(
 /// Log access to MongoDB
 async fn log_access(
     state: &ProxyState,
-    ip: &str,
-    method: &str,
-    path: &str,
-    route_id: Option<i32>,
+    request_log: &RequestLogContext,
     target: Option<&str>,
     status: i32,
-    response_time_ms: i32,
-    user_agent: Option<&str>,
-    referer: Option<&str>,
+    upstream_error: Option<&str>,
 ) {
-    // GeoIP lookup (non-blocking, memory-mapped read)
-    let geo = state.geoip.as_ref().and_then(|reader| reader.lookup(ip));
-
-    let log = AccessLog {
-        timestamp: Utc::now(),
-        ip: ip.to_string(),
-        method: method.to_string(),
-        path: path.to_string(),
-        route_id,
-        target: target.map(|s| s.to_string()),
+    let log = request_log.entry(
+        state,
+        target,
         status,
-        response_time_ms,
-        request_size: None,
-        response_size: None,
-        user_agent: user_agent.map(|s| s.to_string()),
-        referer: referer.map(|s| s.to_string()),
-        country_code: geo.as_ref().and_then(|g| g.country_code.clone()),
-        country: geo.as_ref().and_then(|g| g.country.clone()),
-        city: geo.as_ref().and_then(|g| g.city.clone()),
-        latitude: geo.as_ref().and_then(|g| g.latitude),
-        longitude: geo.as_ref().and_then(|g| g.longitude),
-    };
-
+        request_log.elapsed_ms(),
+        upstream_error,
+    );
     if let Err(e) = state.app_state.mongo.log_access(&log).await {
         tracing::warn!("Failed to log access: {}", e);
     }
@@ -0,0 +1,281 @@
+//! Per-route request/response header rewrite rules
+//!
+//! `proxy_routes.header_rewrite` holds the rules as JSON (NULL = none):
+//! headers to set (replacing any value already present) and headers to
+//! remove, separately for the request sent upstream and the response sent
+//! back. Names are matched case-insensitively and stored lower-case.
+//!
+//! Request rules run after the gateway's own header handling (Host per
+//! `preserve_host`, X-Forwarded-For/-Proto, X-Real-IP, upstream auth), so
+//! a route can override or drop any of those; a route that sets `host`
+//! sends it whatever `preserve_host` says, and one that removes it gets the
+//! target's host. Response rules run on the upstream response before the
+//! transformation script, Location rewriting and security headers.
+//!
+//! Hop-by-hop headers and Content-Length are managed by the gateway and
+//! cannot be rewritten. Rules apply to plain HTTP requests, not WebSocket
+//! tunnels or gRPC passthrough.
+
+use std::collections::BTreeMap;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProxyRoute;
+
+/// Most headers one rule list may name
+pub const MAX_RULES: usize = 50;
+
+/// Headers the gateway manages itself
+const RESERVED: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+/// Header rewrite rules of a route
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteHeaderRewrite {
+    /// Set on the upstream request (replaces client and gateway values)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub request_headers_set: BTreeMap<String, String>,
+    /// Dropped from the upstream request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request_headers_remove: Vec<String>,
+    /// Set on the response (replaces upstream values)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub response_headers_set: BTreeMap<String, String>,
+    /// Dropped from the response
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub response_headers_remove: Vec<String>,
+}
+
+impl RouteHeaderRewrite {
+    /// Lower-case and validate every name and value; None when there are no
+    /// rules (stored as NULL)
+    pub fn normalized(self) -> Result<Option<Self>, String> {
+        let rules = Self {
+            request_headers_set: normalize_set("request_headers_set", self.request_headers_set)?,
+            request_headers_remove: normalize_remove(
+                "request_headers_remove",
+                self.request_headers_remove,
+            )?,
+            response_headers_set: normalize_set("response_headers_set", self.response_headers_set)?,
+            response_headers_remove: normalize_remove(
+                "response_headers_remove",
+                self.response_headers_remove,
+            )?,
+        };
+        if rules == Self::default() {
+            return Ok(None);
+        }
+        Ok(Some(rules))
+    }
+
+    /// Stored column value (normalized JSON); no rules is NULL
+    pub fn to_column(&self) -> Option<String> {
+        self.clone()
+            .normalized()
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::to_string(&v).ok())
+    }
+
+    /// Parse the stored column; invalid JSON is ignored with a warning
+    pub fn from_column(raw: Option<&str>) -> Option<Self> {
+        let raw = raw.map(str::trim).filter(|s| !s.is_empty())?;
+        match serde_json::from_str(raw) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid route header_rewrite: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Rewrite the headers sent upstream
+    pub fn apply_to_request(&self, pairs: &mut Vec<(String, String)>) {
+        pairs.retain(|(name, _)| {
+            !self
+                .request_headers_remove
+                .iter()
+                .chain(self.request_headers_set.keys())
+                .any(|n| n.eq_ignore_ascii_case(name))
+        });
+        for (name, value) in &self.request_headers_set {
+            pairs.push((name.clone(), value.clone()));
+        }
+    }
+
+    /// Rewrite the upstream response headers
+    pub fn apply_to_response(&self, headers: &mut HeaderMap) {
+        for name in &self.response_headers_remove {
+            headers.remove(name.as_str());
+        }
+        for (name, value) in &self.response_headers_set {
+            let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) else {
+                continue;
+            };
+            headers.insert(name, value);
+        }
+    }
+}
+
+impl ProxyRoute {
+    /// Header rewrite rules; None when unset (or the column is invalid)
+    pub fn header_rewrite(&self) -> Option<RouteHeaderRewrite> {
+        RouteHeaderRewrite::from_column(self.header_rewrite.as_deref())
+    }
+}
+
+/// Lower-cased, checked header name
+fn header_name(field: &str, name: &str) -> Result<String, String> {
+    let name = name.trim().to_ascii_lowercase();
+    HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| format!("{}: invalid header name '{}'", field, name))?;
+    if RESERVED.contains(&name.as_str()) {
+        return Err(format!(
+            "{}: {} is managed by the gateway and cannot be rewritten",
+            field, name
+        ));
+    }
+    Ok(name)
+}
+
+fn normalize_set(
+    field: &str,
+    rules: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    if rules.len() > MAX_RULES {
+        return Err(format!("{}: at most {} headers", field, MAX_RULES));
+    }
+    let mut normalized = BTreeMap::new();
+    for (name, value) in rules {
+        let name = header_name(field, &name)?;
+        HeaderValue::from_str(&value)
+            .map_err(|_| format!("{}: invalid value for {}", field, name))?;
+        if normalized.insert(name.clone(), value).is_some() {
+            return Err(format!("{}: {} is listed twice", field, name));
+        }
+    }
+    Ok(normalized)
+}
+
+fn normalize_remove(field: &str, names: Vec<String>) -> Result<Vec<String>, String> {
+    if names.len() > MAX_RULES {
+        return Err(format!("{}: at most {} headers", field, MAX_RULES));
+    }
+    let mut normalized = names
+        .iter()
+        .map(|name| header_name(field, name))
+        .collect::<Result<Vec<_>, _>>()?;
+    normalized.sort_unstable();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> RouteHeaderRewrite {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// Pairs as the proxy handler builds them before the rules run
+    fn forwarded(preserve_host: bool) -> Vec<(String, String)> {
+        let mut pairs = vec![
+            ("x-internal-token".to_string(), "secret".to_string()),
+            ("accept".to_string(), "*/*".to_string()),
+        ];
+        if preserve_host {
+            pairs.push(("host".to_string(), "app.example.com".to_string()));
+        }
+        pairs.push(("X-Forwarded-For".to_string(), "203.0.113.7".to_string()));
+        pairs.push(("X-Forwarded-Proto".to_string(), "https".to_string()));
+        pairs
+    }
+
+    fn get<'a>(pairs: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+        pairs
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        let rewrite = rules(
+            r#"{"request_headers_set": {"X-Forwarded-Prefix": "/app", "x-forwarded-proto": "http"},
+                "request_headers_remove": ["X-INTERNAL-TOKEN"],
+                "response_headers_remove": ["Server"]}"#,
+        )
+        .normalized()
+        .unwrap()
+        .unwrap();
+        assert_eq!(rewrite.request_headers_remove, vec!["x-internal-token"]);
+        assert!(rewrite
+            .request_headers_set
+            .contains_key("x-forwarded-prefix"));
+
+        let mut pairs = forwarded(false);
+        rewrite.apply_to_request(&mut pairs);
+        assert!(get(&pairs, "x-internal-token").is_empty());
+        assert_eq!(get(&pairs, "X-Forwarded-Prefix"), vec!["/app"]);
+        // The automatic header is overridden, not duplicated
+        assert_eq!(get(&pairs, "X-Forwarded-Proto"), vec!["http"]);
+        assert_eq!(get(&pairs, "X-Forwarded-For"), vec!["203.0.113.7"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("SERVER", HeaderValue::from_static("nginx"));
+        rewrite.apply_to_response(&mut headers);
+        assert!(headers.get("server").is_none());
+
+        let duplicate = rules(r#"{"response_headers_set": {"X-A": "1", "x-a": "2"}}"#);
+        assert!(duplicate.normalized().is_err());
+    }
+
+    #[test]
+    fn host_rules_override_preserve_host() {
+        let set = rules(r#"{"request_headers_set": {"Host": "internal.lan"}}"#)
+            .normalized()
+            .unwrap()
+            .unwrap();
+        for preserve_host in [false, true] {
+            let mut pairs = forwarded(preserve_host);
+            set.apply_to_request(&mut pairs);
+            assert_eq!(get(&pairs, "host"), vec!["internal.lan"]);
+        }
+
+        let remove = rules(r#"{"request_headers_remove": ["HOST"]}"#);
+        let mut pairs = forwarded(true);
+        remove.apply_to_request(&mut pairs);
+        assert!(get(&pairs, "host").is_empty());
+    }
+
+    #[test]
+    fn managed_headers_are_rejected() {
+        for json in [
+            r#"{"request_headers_remove": ["Connection"]}"#,
+            r#"{"response_headers_set": {"Transfer-Encoding": "chunked"}}"#,
+            r#"{"request_headers_set": {"content-length": "0"}}"#,
+            r#"{"request_headers_set": {"bad name": "x"}}"#,
+            r#"{"request_headers_set": {"x-a": "line\nbreak"}}"#,
+        ] {
+            assert!(rules(json).normalized().is_err(), "{}", json);
+        }
+        assert_eq!(rules("{}").normalized(), Ok(None));
+        assert_eq!(RouteHeaderRewrite::default().to_column(), None);
+    }
+}
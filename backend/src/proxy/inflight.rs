@@ -0,0 +1,348 @@
+//! In-flight request tracking
+//!
+//! Every proxied request (and every WebSocket tunnel) holds an entry in
+//! `ProxyState.in_flight` for its lifetime, so the dashboard can list what
+//! is running right now and abort a stuck transfer. WebSocket tunnels whose
+//! peer went away without closing are reaped by `reap_loop`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{Notify, RwLock};
+
+use super::tunnel::{TunnelMetrics, TunnelSnapshot, STALE_KEEPALIVES};
+use super::ProxyLimits;
+
+/// access_logs.upstream_error marker for requests aborted from the dashboard
+pub(crate) const IN_FLIGHT_ABORTED: &str = "aborted_by_admin";
+
+/// Status logged for aborted requests (client closed request, nginx style)
+pub(crate) const IN_FLIGHT_ABORTED_STATUS: u16 = 499;
+
+/// How often `reap_loop` looks for dead tunnels
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One tracked request or tunnel
+struct Entry {
+    kind: &'static str,
+    client_ip: String,
+    method: String,
+    path: String,
+    /// 0 until a route matched
+    route_id: AtomicI32,
+    target: Mutex<Option<String>>,
+    started: Instant,
+    started_at: DateTime<Utc>,
+    bytes: AtomicU64,
+    aborted: AtomicBool,
+    /// Aborted by the stale-tunnel sweep rather than an admin
+    reaped: AtomicBool,
+    abort: Notify,
+    /// Relay counters, for WebSocket tunnels
+    tunnel: Mutex<Option<Arc<TunnelMetrics>>>,
+}
+
+/// Snapshot returned by GET /api/dashboard/in-flight
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightRequest {
+    pub id: u64,
+    /// "http" or "websocket"
+    pub kind: String,
+    pub route_id: Option<i32>,
+    pub target: Option<String>,
+    pub client_ip: String,
+    pub method: String,
+    pub path: String,
+    pub started_at: String,
+    /// Running time (connected duration for tunnels)
+    pub age_ms: u64,
+    /// Request + response body bytes (or relayed frame bytes) so far
+    pub bytes_transferred: u64,
+    pub aborting: bool,
+    /// Per-direction frame/byte counters and queue depth (tunnels only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tunnel: Option<TunnelSnapshot>,
+}
+
+/// Registry of in-flight requests, shared through `ProxyState`
+#[derive(Default)]
+pub struct InFlightTracker {
+    next_id: AtomicU64,
+    entries: Mutex<HashMap<u64, Arc<Entry>>>,
+}
+
+impl InFlightTracker {
+    /// Track a new request; the entry is removed when the guard drops
+    pub fn register(
+        self: &Arc<Self>,
+        kind: &'static str,
+        client_ip: &str,
+        method: &str,
+        path: &str,
+    ) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let entry = Arc::new(Entry {
+            kind,
+            client_ip: client_ip.to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            route_id: AtomicI32::new(0),
+            target: Mutex::new(None),
+            started: Instant::now(),
+            started_at: Utc::now(),
+            bytes: AtomicU64::new(0),
+            aborted: AtomicBool::new(false),
+            reaped: AtomicBool::new(false),
+            abort: Notify::new(),
+            tunnel: Mutex::new(None),
+        });
+        self.lock().insert(id, entry.clone());
+        InFlightGuard {
+            id,
+            entry,
+            tracker: self.clone(),
+        }
+    }
+
+    /// Current entries, oldest first, capped at `limit`
+    pub fn list(&self, limit: usize) -> Vec<InFlightRequest> {
+        let mut list: Vec<InFlightRequest> = self
+            .lock()
+            .iter()
+            .map(|(id, e)| {
+                let route_id = e.route_id.load(Ordering::Relaxed);
+                InFlightRequest {
+                    id: *id,
+                    kind: e.kind.to_string(),
+                    route_id: (route_id != 0).then_some(route_id),
+                    target: e.target.lock().unwrap_or_else(|p| p.into_inner()).clone(),
+                    client_ip: e.client_ip.clone(),
+                    method: e.method.clone(),
+                    path: e.path.clone(),
+                    started_at: e.started_at.to_rfc3339(),
+                    age_ms: e.started.elapsed().as_millis() as u64,
+                    bytes_transferred: e.bytes.load(Ordering::Relaxed),
+                    aborting: e.aborted.load(Ordering::Relaxed),
+                    tunnel: e
+                        .tunnel
+                        .lock()
+                        .unwrap_or_else(|p| p.into_inner())
+                        .as_ref()
+                        .map(|t| t.snapshot()),
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| b.age_ms.cmp(&a.age_ms).then(a.id.cmp(&b.id)));
+        list.truncate(limit);
+        list
+    }
+
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Entries of one kind ("http" or "websocket")
+    pub fn count_kind(&self, kind: &str) -> usize {
+        self.lock().values().filter(|e| e.kind == kind).count()
+    }
+
+    /// Entries of one kind matched to `route_id`
+    pub fn count_route_kind(&self, route_id: i32, kind: &str) -> usize {
+        self.lock()
+            .values()
+            .filter(|e| e.kind == kind && e.route_id.load(Ordering::Relaxed) == route_id)
+            .count()
+    }
+
+    /// Signal an entry to abort; false when it is no longer in flight
+    pub fn abort(&self, id: u64) -> bool {
+        match self.lock().get(&id) {
+            Some(entry) => {
+                entry.signal_abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Abort tunnels that have looked dead for `stale_after` (see
+    /// `TunnelMetrics::stale_for`); returns their ids
+    pub fn reap_stale_tunnels(&self, stale_after: Duration) -> Vec<u64> {
+        let mut reaped = Vec::new();
+        for (id, entry) in self.lock().iter() {
+            let stale = entry
+                .tunnel
+                .lock()
+                .unwrap_or_else(|p| p.into_inner())
+                .as_ref()
+                .is_some_and(|t| t.stale_for() >= stale_after);
+            if stale && !entry.aborted.load(Ordering::Relaxed) {
+                entry.reaped.store(true, Ordering::Relaxed);
+                entry.signal_abort();
+                reaped.push(*id);
+            }
+        }
+        reaped
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<Entry>>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Entry {
+    fn signal_abort(&self) {
+        self.aborted.store(true, Ordering::Relaxed);
+        // notify_one keeps a permit if the request is not waiting yet
+        self.abort.notify_one();
+    }
+}
+
+/// Periodically reap dead WebSocket tunnels. Only runs while keepalive is on:
+/// without pings a quiet live peer cannot be told from a dead one.
+pub async fn reap_loop(tracker: Arc<InFlightTracker>, limits: Arc<RwLock<ProxyLimits>>) {
+    let mut tick = tokio::time::interval(REAP_INTERVAL);
+    loop {
+        tick.tick().await;
+        let Some(keepalive) = limits.read().await.ws_keepalive_interval else {
+            continue;
+        };
+        let reaped = tracker.reap_stale_tunnels(keepalive * STALE_KEEPALIVES);
+        if !reaped.is_empty() {
+            tracing::info!(
+                "Reaped {} WebSocket tunnel(s) with an unresponsive peer: {:?}",
+                reaped.len(),
+                reaped
+            );
+        }
+    }
+}
+
+/// Handle held by the request while it runs
+pub struct InFlightGuard {
+    id: u64,
+    entry: Arc<Entry>,
+    tracker: Arc<InFlightTracker>,
+}
+
+impl InFlightGuard {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Record the matched route once known
+    pub fn set_route(&self, route_id: i32, target: &str) {
+        self.entry.route_id.store(route_id, Ordering::Relaxed);
+        *self.entry.target.lock().unwrap_or_else(|p| p.into_inner()) = Some(target.to_string());
+    }
+
+    /// Matched route id and target, if any
+    pub fn route(&self) -> (Option<i32>, Option<String>) {
+        let route_id = self.entry.route_id.load(Ordering::Relaxed);
+        let target = self
+            .entry
+            .target
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .clone();
+        ((route_id != 0).then_some(route_id), target)
+    }
+
+    /// Expose a tunnel's relay counters in the listing
+    pub fn attach_tunnel(&self, metrics: Arc<TunnelMetrics>) {
+        *self.entry.tunnel.lock().unwrap_or_else(|p| p.into_inner()) = Some(metrics);
+    }
+
+    pub fn add_bytes(&self, n: usize) {
+        self.entry.bytes.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.entry.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Whether the abort came from the stale-tunnel sweep
+    pub fn reaped(&self) -> bool {
+        self.entry.reaped.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once the entry is aborted from the dashboard
+    pub async fn aborted(&self) {
+        if self.entry.aborted.load(Ordering::Relaxed) {
+            return;
+        }
+        self.entry.abort.notified().await;
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.tracker.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guard_lifetime_and_abort() {
+        let tracker = Arc::new(InFlightTracker::default());
+        let first = tracker.register("http", "10.0.0.1", "GET", "/a");
+        let second = tracker.register("websocket", "10.0.0.2", "WS", "/ws");
+        second.set_route(7, "http://backend:8080");
+        second.add_bytes(512);
+
+        let list = tracker.list(10);
+        assert_eq!(list.len(), 2);
+        // Oldest first
+        assert_eq!(list[0].id, first.id());
+        assert_eq!(list[1].route_id, Some(7));
+        assert_eq!(list[1].bytes_transferred, 512);
+        assert!(list[1].tunnel.is_none());
+        assert_eq!(tracker.count_route_kind(7, "websocket"), 1);
+        assert_eq!(tracker.count_route_kind(7, "http"), 0);
+
+        let metrics = Arc::new(TunnelMetrics::default());
+        metrics.relayed(crate::proxy::tunnel::Direction::UpstreamToClient, 64);
+        second.attach_tunnel(metrics);
+        let tunnel = tracker.list(10)[1].tunnel.clone().unwrap();
+        assert_eq!(tunnel.bytes_upstream_to_client, 64);
+        assert_eq!(tracker.list(1).len(), 1);
+
+        assert!(tracker.abort(second.id()));
+        assert!(tracker.list(10)[1].aborting);
+
+        let gone = first.id();
+        drop(first);
+        assert_eq!(tracker.count(), 1);
+        assert!(!tracker.abort(gone));
+    }
+
+    #[test]
+    fn stale_tunnels_are_reaped_once() {
+        let tracker = Arc::new(InFlightTracker::default());
+        let http = tracker.register("http", "10.0.0.1", "GET", "/a");
+        let tunnel = tracker.register("websocket", "10.0.0.2", "WS", "/ws");
+        tunnel.attach_tunnel(Arc::new(TunnelMetrics::default()));
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(tracker
+            .reap_stale_tunnels(Duration::from_secs(60))
+            .is_empty());
+        assert_eq!(
+            tracker.reap_stale_tunnels(Duration::from_millis(10)),
+            vec![tunnel.id()]
+        );
+        assert!(tunnel.reaped());
+        assert!(!http.reaped());
+        // Already aborting: not reported again
+        assert!(tracker
+            .reap_stale_tunnels(Duration::from_millis(10))
+            .is_empty());
+    }
+}
@@ -0,0 +1,364 @@
+//! Proxy hardening limits
+//!
+//! Protects workers from misbehaving peers on both sides of the proxy:
+//! oversized upstream response headers, slow (trickling) or stalled bodies,
+//! and unbounded buffering. Limits live in the settings table (`proxy_*`)
+//! and are cached in `ProxyState`; violations are counted per route.
+//!
+//! The request body limit defaults to `[server] max_body_mb` while the
+//! setting is empty, and a route's `max_body_bytes` overrides it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, HeaderMap};
+use serde::Serialize;
+
+use crate::db::MySqlDb;
+use crate::error::AppError;
+use crate::models::ProxyRoute;
+
+/// Default `[server] max_body_mb`
+pub const DEFAULT_MAX_REQUEST_BODY_MB: usize = 100;
+
+/// Largest per-route request body limit (bodies are buffered in memory)
+pub const MAX_ROUTE_BODY_BYTES: i64 = 128 * 1024 * 1024;
+
+/// Configured limits (defaults are deliberately generous)
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ProxyLimits {
+    /// Total size of upstream response header names + values
+    pub max_response_header_bytes: usize,
+    /// Max gap between two body chunks
+    pub idle_timeout: Duration,
+    /// Minimum average body transfer rate (bytes/sec), 0 = disabled
+    pub min_transfer_rate_bps: u64,
+    /// Time before the minimum rate starts being enforced
+    pub slow_transfer_grace: Duration,
+    /// Max upstream response body held in memory
+    pub max_response_buffer_bytes: usize,
+    /// Max client request body
+    pub max_request_body_bytes: usize,
+    /// Frames queued per WebSocket direction before reading pauses
+    pub ws_queue_frames: usize,
+    /// Max bytes queued in a WebSocket tunnel before it is closed with 1011
+    pub ws_max_buffered_bytes: usize,
+    /// Idle time after which the gateway pings both peers (None = disabled)
+    pub ws_keepalive_interval: Option<Duration>,
+}
+
+impl Default for ProxyLimits {
+    fn default() -> Self {
+        Self {
+            max_response_header_bytes: 64 * 1024,
+            idle_timeout: Duration::from_secs(30),
+            min_transfer_rate_bps: 128,
+            slow_transfer_grace: Duration::from_secs(10),
+            max_response_buffer_bytes: 100 * 1024 * 1024,
+            max_request_body_bytes: DEFAULT_MAX_REQUEST_BODY_MB * 1024 * 1024,
+            ws_queue_frames: 256,
+            ws_max_buffered_bytes: 16 * 1024 * 1024,
+            ws_keepalive_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl ProxyLimits {
+    /// Load limits from settings, falling back to defaults per key
+    /// (`max_body_mb`: the request body limit while its setting is empty)
+    pub async fn load(mysql: &MySqlDb, max_body_mb: usize) -> Result<Self, AppError> {
+        let d = Self {
+            max_request_body_bytes: max_body_mb.max(1) * 1024 * 1024,
+            ..Self::default()
+        };
+        let kb = |v: i32| (v.max(1) as usize) * 1024;
+        let mb = |v: i32| (v.max(1) as usize) * 1024 * 1024;
+        let secs = |v: i32| Duration::from_secs(v.max(1) as u64);
+
+        Ok(Self {
+            max_response_header_bytes: kb(mysql
+                .get_setting_i32(
+                    "proxy_max_response_header_kb",
+                    (d.max_response_header_bytes / 1024) as i32,
+                )
+                .await?),
+            idle_timeout: secs(
+                mysql
+                    .get_setting_i32("proxy_idle_timeout_sec", d.idle_timeout.as_secs() as i32)
+                    .await?,
+            ),
+            min_transfer_rate_bps: mysql
+                .get_setting_i32(
+                    "proxy_min_transfer_rate_bps",
+                    d.min_transfer_rate_bps as i32,
+                )
+                .await?
+                .max(0) as u64,
+            slow_transfer_grace: secs(
+                mysql
+                    .get_setting_i32(
+                        "proxy_slow_transfer_grace_sec",
+                        d.slow_transfer_grace.as_secs() as i32,
+                    )
+                    .await?,
+            ),
+            max_response_buffer_bytes: mb(mysql
+                .get_setting_i32(
+                    "proxy_max_response_buffer_mb",
+                    (d.max_response_buffer_bytes / 1024 / 1024) as i32,
+                )
+                .await?),
+            max_request_body_bytes: mb(mysql
+                .get_setting_i32(
+                    "proxy_max_request_body_mb",
+                    (d.max_request_body_bytes / 1024 / 1024) as i32,
+                )
+                .await?),
+            ws_queue_frames: mysql
+                .get_setting_i32("proxy_ws_queue_frames", d.ws_queue_frames as i32)
+                .await?
+                .max(1) as usize,
+            ws_max_buffered_bytes: kb(mysql
+                .get_setting_i32(
+                    "proxy_ws_max_buffered_kb",
+                    (d.ws_max_buffered_bytes / 1024) as i32,
+                )
+                .await?),
+            ws_keepalive_interval: match mysql.get_setting_i32("proxy_ws_keepalive_sec", 30).await?
+            {
+                n if n > 0 => Some(Duration::from_secs(n as u64)),
+                _ => None,
+            },
+        })
+    }
+
+    /// Request body limit of a route: its own `max_body_bytes` (capped at
+    /// `MAX_ROUTE_BODY_BYTES`, for rows stored before the cap) or the global one
+    pub fn request_body_limit(&self, route: &ProxyRoute) -> usize {
+        route
+            .max_body_bytes
+            .filter(|bytes| *bytes > 0)
+            .map_or(self.max_request_body_bytes, |bytes| {
+                bytes.min(MAX_ROUTE_BODY_BYTES) as usize
+            })
+    }
+}
+
+/// Body length a request declares in `Content-Length`
+pub fn declared_body_len(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// Which protection fired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protection {
+    ResponseHeaderSize,
+    ResponseIdle,
+    ResponseSlowTransfer,
+    ResponseBufferLimit,
+    RequestIdle,
+    RequestSlowTransfer,
+    RequestBodyLimit,
+    MethodNotAllowed,
+    ExpectationFailed,
+}
+
+impl Protection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ResponseHeaderSize => "response_header_size",
+            Self::ResponseIdle => "response_idle_timeout",
+            Self::ResponseSlowTransfer => "response_slow_transfer",
+            Self::ResponseBufferLimit => "response_buffer_limit",
+            Self::RequestIdle => "request_idle_timeout",
+            Self::RequestSlowTransfer => "request_slow_transfer",
+            Self::RequestBodyLimit => "request_body_limit",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::ExpectationFailed => "expectation_failed",
+        }
+    }
+}
+
+/// Per-route violation counters (in-memory, reset on restart)
+#[derive(Default)]
+pub struct ViolationCounters {
+    counts: Mutex<HashMap<i32, HashMap<Protection, u64>>>,
+}
+
+impl ViolationCounters {
+    pub fn record(&self, route_id: i32, protection: Protection) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        *counts
+            .entry(route_id)
+            .or_default()
+            .entry(protection)
+            .or_insert(0) += 1;
+    }
+
+    /// Counts for one route keyed by protection name
+    pub fn for_route(&self, route_id: i32) -> HashMap<String, u64> {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts
+            .get(&route_id)
+            .map(|m| {
+                m.iter()
+                    .map(|(p, n)| (p.as_str().to_string(), *n))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Tracks a body transfer and decides when it violates the size/rate limits
+pub struct TransferGuard {
+    started: Instant,
+    bytes: usize,
+    max_bytes: usize,
+    min_rate_bps: u64,
+    grace: Duration,
+}
+
+/// Why a transfer was stopped
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransferViolation {
+    TooLarge,
+    TooSlow,
+}
+
+impl TransferGuard {
+    pub fn new(max_bytes: usize, limits: &ProxyLimits) -> Self {
+        Self {
+            started: Instant::now(),
+            bytes: 0,
+            max_bytes,
+            min_rate_bps: limits.min_transfer_rate_bps,
+            grace: limits.slow_transfer_grace,
+        }
+    }
+
+    /// Account for a received chunk
+    pub fn on_chunk(&mut self, len: usize) -> Result<(), TransferViolation> {
+        self.bytes += len;
+        if self.bytes > self.max_bytes {
+            return Err(TransferViolation::TooLarge);
+        }
+        self.check_rate(self.started.elapsed())
+    }
+
+    fn check_rate(&self, elapsed: Duration) -> Result<(), TransferViolation> {
+        if self.min_rate_bps == 0 || elapsed <= self.grace {
+            return Ok(());
+        }
+        let rate = self.bytes as f64 / elapsed.as_secs_f64();
+        if rate < self.min_rate_bps as f64 {
+            Err(TransferViolation::TooSlow)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Total size of header names and values
+pub fn header_bytes(headers: &reqwest::header::HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(k, v)| k.as_str().len() + v.as_bytes().len())
+        .sum()
+}
+
+/// Whether a send error was caused by an oversized response head
+pub fn is_head_too_large(err: &reqwest::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if e.to_string().contains("message head is too large") {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(min_rate: u64, grace_secs: u64) -> ProxyLimits {
+        ProxyLimits {
+            min_transfer_rate_bps: min_rate,
+            slow_transfer_grace: Duration::from_secs(grace_secs),
+            ..ProxyLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_transfer_guard_size_limit() {
+        let mut guard = TransferGuard::new(10, &limits(0, 0));
+        assert!(guard.on_chunk(6).is_ok());
+        assert_eq!(guard.on_chunk(6), Err(TransferViolation::TooLarge));
+    }
+
+    #[test]
+    fn test_transfer_guard_rate_after_grace() {
+        let mut guard = TransferGuard::new(1000, &limits(100, 10));
+        guard.bytes = 50;
+        // Within grace: never too slow
+        assert!(guard.check_rate(Duration::from_secs(5)).is_ok());
+        // 50 bytes over 20s = 2.5 B/s
+        assert_eq!(
+            guard.check_rate(Duration::from_secs(20)),
+            Err(TransferViolation::TooSlow)
+        );
+        guard.bytes = 5000;
+        assert!(guard.check_rate(Duration::from_secs(20)).is_ok());
+    }
+
+    #[test]
+    fn test_transfer_guard_rate_disabled() {
+        let guard = TransferGuard::new(1000, &limits(0, 0));
+        assert!(guard.check_rate(Duration::from_secs(600)).is_ok());
+    }
+
+    #[test]
+    fn test_request_body_limit() {
+        let route = |max_body_bytes: Option<i64>| ProxyRoute {
+            path: "/upload".to_string(),
+            max_body_bytes,
+            ..ProxyRoute::test_default()
+        };
+        let limits = ProxyLimits::default();
+        assert_eq!(
+            limits.request_body_limit(&route(None)),
+            limits.max_request_body_bytes
+        );
+        assert_eq!(
+            limits.request_body_limit(&route(Some(0))),
+            limits.max_request_body_bytes
+        );
+        assert_eq!(limits.request_body_limit(&route(Some(2048))), 2048);
+        assert_eq!(
+            limits.request_body_limit(&route(Some(1024 * 1024 * 1024))),
+            MAX_ROUTE_BODY_BYTES as usize
+        );
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(declared_body_len(&headers), None);
+        headers.insert(header::CONTENT_LENGTH, "4096".parse().unwrap());
+        assert_eq!(declared_body_len(&headers), Some(4096));
+    }
+
+    #[test]
+    fn test_violation_counters() {
+        let counters = ViolationCounters::default();
+        counters.record(1, Protection::ResponseHeaderSize);
+        counters.record(1, Protection::ResponseHeaderSize);
+        counters.record(1, Protection::RequestIdle);
+        let counts = counters.for_route(1);
+        assert_eq!(counts.get("response_header_size"), Some(&2));
+        assert_eq!(counts.get("request_idle_timeout"), Some(&1));
+        assert!(counters.for_route(2).is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+//! Per-route custom access log fields
+//!
+//! `proxy_routes.log_fields` maps up to `MAX_FIELDS` field names to where
+//! each value comes from: a request header, a capture group of a regex
+//! matched against the request path, or a query parameter. The proxy
+//! handler extracts them for every request on the route and stores them in
+//! the access log entry's `custom_fields`, which the access log search can
+//! filter on and GET /api/dashboard/custom-field-summary groups by.
+//!
+//! Values are cut to `MAX_VALUE_CHARS`. Credential headers (`DENIED_HEADERS`)
+//! are never read: a rule naming one is rejected on save and skipped if it
+//! reaches the handler anyway.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use axum::http::{HeaderMap, HeaderName};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::models::ProxyRoute;
+
+/// Most fields a route may extract
+pub const MAX_FIELDS: usize = 8;
+/// Longest stored value; longer ones are cut
+pub const MAX_VALUE_CHARS: usize = 128;
+/// Longest field name
+pub const MAX_NAME_CHARS: usize = 32;
+/// Longest path pattern
+const MAX_PATTERN_BYTES: usize = 512;
+
+/// Headers values are never extracted from
+pub const DENIED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Extracted values by field name (`access_logs.custom_fields`)
+pub type CustomFields = BTreeMap<String, String>;
+
+fn default_group() -> usize {
+    1
+}
+
+/// Where one field's value comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum LogFieldSource {
+    /// Request header (case-insensitive)
+    Header { name: String },
+    /// Capture group of a regex matched against the request path
+    Path {
+        pattern: String,
+        #[serde(default = "default_group")]
+        group: usize,
+    },
+    /// Query parameter (first occurrence)
+    Query { name: String },
+}
+
+/// Extraction rules of a route, by field name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RouteLogFields(pub BTreeMap<String, LogFieldSource>);
+
+/// Field names are stored as Mongo keys: lower-case letters, digits, `_`
+/// and `-`, starting with a letter
+pub fn is_valid_field_name(name: &str) -> bool {
+    name.len() <= MAX_NAME_CHARS
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+impl RouteLogFields {
+    /// Validate the rules; header names are lower-cased. Errors start with
+    /// the field name they are about.
+    pub fn normalize(&self) -> Result<Self, String> {
+        if self.0.len() > MAX_FIELDS {
+            return Err(format!("at most {} fields per route", MAX_FIELDS));
+        }
+        self.0
+            .iter()
+            .map(|(field, source)| {
+                if !is_valid_field_name(field) {
+                    return Err(format!(
+                        "{}: names are up to {} lower-case letters, digits, '_' or '-', starting with a letter",
+                        field, MAX_NAME_CHARS
+                    ));
+                }
+                let source = match source {
+                    LogFieldSource::Header { name } => {
+                        let name = name.trim().to_ascii_lowercase();
+                        HeaderName::from_bytes(name.as_bytes())
+                            .map_err(|_| format!("{}: invalid header name", field))?;
+                        if DENIED_HEADERS.contains(&name.as_str()) {
+                            return Err(format!("{}: {} is never logged", field, name));
+                        }
+                        LogFieldSource::Header { name }
+                    }
+                    LogFieldSource::Path { pattern, group } => {
+                        if pattern.len() > MAX_PATTERN_BYTES {
+                            return Err(format!(
+                                "{}: pattern exceeds {} bytes",
+                                field, MAX_PATTERN_BYTES
+                            ));
+                        }
+                        let regex =
+                            Regex::new(pattern).map_err(|e| format!("{}: {}", field, e))?;
+                        if *group == 0 || *group >= regex.captures_len() {
+                            return Err(format!("{}: pattern has no group {}", field, group));
+                        }
+                        source.clone()
+                    }
+                    LogFieldSource::Query { name } => {
+                        if name.trim().is_empty() {
+                            return Err(format!("{}: query parameter name is empty", field));
+                        }
+                        LogFieldSource::Query {
+                            name: name.trim().to_string(),
+                        }
+                    }
+                };
+                Ok((field.clone(), source))
+            })
+            .collect::<Result<_, String>>()
+            .map(Self)
+    }
+
+    /// Stored column value; None when there are no rules or they are invalid
+    pub fn to_column(&self) -> Option<String> {
+        self.normalize()
+            .ok()
+            .filter(|rules| !rules.0.is_empty())
+            .and_then(|rules| serde_json::to_string(&rules).ok())
+    }
+}
+
+impl ProxyRoute {
+    /// Custom log field rules; None when unset (or the column is invalid)
+    pub fn log_fields(&self) -> Option<RouteLogFields> {
+        let raw = self
+            .log_fields
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        match serde_json::from_str::<RouteLogFields>(raw) {
+            Ok(rules) => Some(rules),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid log_fields on route {}: {}", self.id, e);
+                None
+            }
+        }
+    }
+}
+
+/// Value cut to `MAX_VALUE_CHARS`; None when empty
+fn limit_value(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value.chars().take(MAX_VALUE_CHARS).collect())
+}
+
+enum Extractor {
+    Header(HeaderName),
+    Path(Regex, usize),
+    Query(String),
+}
+
+/// Rules of one route, ready to run
+struct CompiledLogFields {
+    fields: Vec<(String, Extractor)>,
+}
+
+impl CompiledLogFields {
+    fn compile(rules: &RouteLogFields) -> Result<Self, String> {
+        let rules = rules.normalize()?;
+        let fields = rules
+            .0
+            .into_iter()
+            .filter_map(|(field, source)| {
+                let extractor = match source {
+                    LogFieldSource::Header { name } => {
+                        Extractor::Header(HeaderName::from_bytes(name.as_bytes()).ok()?)
+                    }
+                    LogFieldSource::Path { pattern, group } => {
+                        Extractor::Path(Regex::new(&pattern).ok()?, group)
+                    }
+                    LogFieldSource::Query { name } => Extractor::Query(name),
+                };
+                Some((field, extractor))
+            })
+            .collect();
+        Ok(Self { fields })
+    }
+
+    fn extract(&self, headers: &HeaderMap, path: &str, query: Option<&str>) -> CustomFields {
+        self.fields
+            .iter()
+            .filter_map(|(field, extractor)| {
+                let value = match extractor {
+                    Extractor::Header(name) => {
+                        if DENIED_HEADERS.contains(&name.as_str()) {
+                            return None;
+                        }
+                        limit_value(headers.get(name)?.to_str().ok()?)
+                    }
+                    Extractor::Path(regex, group) => {
+                        limit_value(regex.captures(path)?.get(*group)?.as_str())
+                    }
+                    Extractor::Query(name) => url::form_urlencoded::parse(query?.as_bytes())
+                        .find(|(key, _)| key == name)
+                        .and_then(|(_, value)| limit_value(&value)),
+                }?;
+                Some((field.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Column hash and the rules compiled from it (None = unusable column)
+type CompiledEntry = (u64, Option<Arc<CompiledLogFields>>);
+
+/// Compiled rules per route, rebuilt when the route's column changes
+#[derive(Default)]
+pub struct LogFieldExtractors {
+    compiled: Mutex<HashMap<i32, CompiledEntry>>,
+}
+
+impl LogFieldExtractors {
+    fn compiled(&self, route: &ProxyRoute, raw: &str) -> Option<Arc<CompiledLogFields>> {
+        let mut hasher = DefaultHasher::new();
+        raw.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut compiled = self.compiled.lock().unwrap_or_else(|p| p.into_inner());
+        if let Some((h, rules)) = compiled.get(&route.id) {
+            if *h == hash {
+                return rules.clone();
+            }
+        }
+        let rules = route.log_fields().and_then(|rules| {
+            CompiledLogFields::compile(&rules)
+                .map_err(|e| {
+                    tracing::warn!("Ignoring log_fields on route {}: {}", route.id, e);
+                })
+                .ok()
+                .map(Arc::new)
+        });
+        compiled.insert(route.id, (hash, rules.clone()));
+        rules
+    }
+
+    /// Custom fields of a request on `route`; None when the route has no
+    /// rules or none of them matched
+    pub fn extract(
+        &self,
+        route: &ProxyRoute,
+        headers: &HeaderMap,
+        path: &str,
+        query: Option<&str>,
+    ) -> Option<CustomFields> {
+        let raw = route
+            .log_fields
+            .as_deref()
+            .filter(|s| !s.trim().is_empty())?;
+        let fields = self.compiled(route, raw)?.extract(headers, path, query);
+        (!fields.is_empty()).then_some(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    fn rules(value: serde_json::Value) -> RouteLogFields {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn credential_headers_and_bad_rules_are_rejected() {
+        for header in ["Authorization", "cookie", "Proxy-Authorization"] {
+            let err = rules(json!({ "who": { "source": "header", "name": header } }))
+                .normalize()
+                .unwrap_err();
+            assert!(err.contains("never logged"), "{}", err);
+        }
+        let no_group = rules(json!({ "tenant": { "source": "path", "pattern": "^/t/[^/]+" } }));
+        assert!(no_group.normalize().is_err());
+        let bad_name = rules(json!({ "Tenant.Id": { "source": "query", "name": "t" } }));
+        assert!(bad_name.normalize().is_err());
+
+        let too_many: BTreeMap<String, LogFieldSource> = (0..=MAX_FIELDS)
+            .map(|i| {
+                (
+                    format!("f{}", i),
+                    LogFieldSource::Query {
+                        name: "q".to_string(),
+                    },
+                )
+            })
+            .collect();
+        assert!(RouteLogFields(too_many).normalize().is_err());
+    }
+
+    #[test]
+    fn extracts_header_path_and_query_values() {
+        let compiled = CompiledLogFields::compile(&rules(json!({
+            "tenant": { "source": "header", "name": "X-Tenant-Id" },
+            "tenant_path": { "source": "path", "pattern": "^/api/t/([^/]+)/" },
+            "plan": { "source": "query", "name": "plan" },
+            "missing": { "source": "query", "name": "nope" },
+        })))
+        .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", HeaderValue::from_static("acme"));
+        let fields = compiled.extract(
+            &headers,
+            "/api/t/globex/orders",
+            Some("plan=pro%20plus&x=1"),
+        );
+
+        assert_eq!(fields.get("tenant").map(String::as_str), Some("acme"));
+        assert_eq!(
+            fields.get("tenant_path").map(String::as_str),
+            Some("globex")
+        );
+        assert_eq!(fields.get("plan").map(String::as_str), Some("pro plus"));
+        assert!(!fields.contains_key("missing"));
+    }
+
+    #[test]
+    fn long_values_are_cut() {
+        let compiled = CompiledLogFields::compile(&rules(json!({
+            "tenant": { "source": "query", "name": "t" },
+        })))
+        .unwrap();
+        let long = "x".repeat(MAX_VALUE_CHARS * 2);
+        let fields = compiled.extract(&HeaderMap::new(), "/", Some(&format!("t={}", long)));
+        assert_eq!(fields["tenant"].chars().count(), MAX_VALUE_CHARS);
+    }
+}
@@ -0,0 +1,142 @@
+//! Per-route maintenance mode
+//!
+//! A route with `maintenance_mode` on answers every request with 503, a
+//! `Retry-After` and its `maintenance_message` (or a default text) instead of
+//! reaching the target: an HTML page, or JSON when the client's `Accept`
+//! ranks JSON above HTML. The health checker skips such routes, so planned
+//! downtime raises no failures or alerts. Set through PUT /api/routes/:id
+//! or POST /api/routes/:id/maintenance.
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+
+use crate::models::ProxyRoute;
+use crate::status_page::escape_html;
+
+/// Retry-After sent while a route is in maintenance
+pub const RETRY_AFTER_SECS: u32 = 300;
+
+/// Longest maintenance message
+pub const MAX_MESSAGE_LEN: usize = 1000;
+
+/// Text shown when the route sets none
+pub const DEFAULT_MESSAGE: &str = "This service is down for maintenance. Please try again later.";
+
+impl ProxyRoute {
+    /// Text of the maintenance page
+    pub fn maintenance_text(&self) -> &str {
+        self.maintenance_message
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .unwrap_or(DEFAULT_MESSAGE)
+    }
+}
+
+/// Highest q an `Accept` header gives to any of `types`
+fn accept_quality(accept: &str, types: &[&str]) -> f32 {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let media = parts.next()?.trim().to_ascii_lowercase();
+            if !types.iter().any(|t| media == *t || media.ends_with(t)) {
+                return None;
+            }
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some(q)
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Whether the client ranks JSON above HTML (ties and no preference: HTML)
+pub fn prefers_json(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    accept_quality(accept, &["application/json", "+json"])
+        > accept_quality(accept, &["text/html", "application/xhtml+xml"])
+}
+
+/// The 503 a route in maintenance answers with
+pub fn response(route: &ProxyRoute, headers: &HeaderMap) -> Response {
+    let message = route.maintenance_text();
+    let retry_after = [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())];
+    if prefers_json(headers) {
+        let body = serde_json::json!({
+            "error": "maintenance",
+            "message": message,
+            "retry_after": RETRY_AFTER_SECS,
+        });
+        return (StatusCode::SERVICE_UNAVAILABLE, retry_after, Json(body)).into_response();
+    }
+    let page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Under maintenance</title>\n</head>\n<body style=\"font-family: sans-serif; \
+         max-width: 40em; margin: 4em auto; padding: 0 1em;\">\n<h1>Under maintenance</h1>\n\
+         <p>{}</p>\n</body>\n</html>\n",
+        escape_html(message)
+    );
+    (StatusCode::SERVICE_UNAVAILABLE, retry_after, Html(page)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn accept_decides_between_html_and_json() {
+        assert!(!prefers_json(&HeaderMap::new()));
+        assert!(!prefers_json(&accept("*/*")));
+        assert!(!prefers_json(&accept(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+        )));
+        assert!(prefers_json(&accept("application/json")));
+        assert!(prefers_json(&accept("application/problem+json")));
+        assert!(prefers_json(&accept("text/html;q=0.5, application/json")));
+        assert!(!prefers_json(&accept("application/json, text/html")));
+    }
+
+    #[tokio::test]
+    async fn maintenance_pages_escape_the_message() {
+        let route = ProxyRoute {
+            id: 9,
+            path: "/shop".to_string(),
+            target: "http://10.0.0.9:8080".to_string(),
+            maintenance_mode: true,
+            maintenance_message: Some("Back at <b>10:00</b>".to_string()),
+            ..ProxyRoute::test_default()
+        };
+        let response = response(&route, &HeaderMap::new());
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "300");
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(page.contains("Back at &lt;b&gt;10:00&lt;/b&gt;"));
+
+        let route = ProxyRoute {
+            maintenance_message: Some("  ".to_string()),
+            ..route
+        };
+        assert_eq!(route.maintenance_text(), DEFAULT_MESSAGE);
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-route allowed HTTP methods
+//!
+//! `proxy_routes.allowed_methods` holds a JSON list of methods; NULL allows
+//! every method. Standard methods are stored upper-case, extension methods
+//! exactly as given (methods are case-sensitive). Requests with any other
+//! method are answered 405 with an `Allow` header before reaching upstream.
+
+use crate::models::ProxyRoute;
+
+/// Methods defined by RFC 9110 plus PATCH
+pub const STANDARD_METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// RFC 9110 token characters
+fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Validate and normalize a method list: standard methods are upper-cased,
+/// extension methods must be valid tokens, duplicates are dropped.
+pub fn normalize_allowed_methods(methods: &[String]) -> Result<Vec<String>, String> {
+    if methods.is_empty() {
+        return Err("list must not be empty (use null to allow all methods)".to_string());
+    }
+    let mut normalized: Vec<String> = Vec::with_capacity(methods.len());
+    for method in methods {
+        let method = method.trim();
+        if method.is_empty() || !method.chars().all(is_tchar) {
+            return Err(format!("invalid method: {:?}", method));
+        }
+        let method = STANDARD_METHODS
+            .iter()
+            .find(|m| m.eq_ignore_ascii_case(method))
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| method.to_string());
+        if !normalized.contains(&method) {
+            normalized.push(method);
+        }
+    }
+    Ok(normalized)
+}
+
+/// Stored column value; None (NULL) allows all methods
+pub fn allowed_methods_column(methods: Option<&[String]>) -> Option<String> {
+    methods
+        .and_then(|m| normalize_allowed_methods(m).ok())
+        .and_then(|m| serde_json::to_string(&m).ok())
+}
+
+impl ProxyRoute {
+    /// Allowed methods; None when unrestricted (or the column is invalid)
+    pub fn allowed_methods(&self) -> Option<Vec<String>> {
+        let raw = self
+            .allowed_methods
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        match serde_json::from_str::<Vec<String>>(raw) {
+            Ok(methods) if !methods.is_empty() => Some(methods),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!(
+                    "Ignoring invalid allowed_methods on route {}: {}",
+                    self.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// `Err(allow_header)` when `method` is not allowed on this route
+    pub fn check_method(&self, method: &str) -> Result<(), String> {
+        match self.allowed_methods() {
+            Some(allowed) if !allowed.iter().any(|m| m == method) => Err(allowed.join(", ")),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn normalizes_standard_and_keeps_extension_methods() {
+        assert_eq!(
+            normalize_allowed_methods(&strings(&["get", " HEAD ", "options", "GET", "PROPFIND"]))
+                .unwrap(),
+            strings(&["GET", "HEAD", "OPTIONS", "PROPFIND"])
+        );
+        assert!(normalize_allowed_methods(&[]).is_err());
+        assert!(normalize_allowed_methods(&strings(&["GET", "BAD METHOD"])).is_err());
+        assert!(normalize_allowed_methods(&strings(&[""])).is_err());
+    }
+
+    #[test]
+    fn check_method_reports_allow_header() {
+        let mut route = ProxyRoute {
+            path: "/api".to_string(),
+            ..ProxyRoute::test_default()
+        };
+        assert!(route.check_method("DELETE").is_ok());
+
+        route.allowed_methods = allowed_methods_column(Some(&strings(&["get", "head", "options"])));
+        assert_eq!(
+            route.allowed_methods.as_deref(),
+            Some(r#"["GET","HEAD","OPTIONS"]"#)
+        );
+        assert!(route.check_method("GET").is_ok());
+        assert_eq!(
+            route.check_method("DELETE").unwrap_err(),
+            "GET, HEAD, OPTIONS"
+        );
+    }
+}
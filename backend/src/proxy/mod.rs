@@ -1,97 +1,440 @@
 //! Proxy module - Reverse proxy functionality
 
+pub mod cache;
+pub mod canary;
+pub mod expect;
+pub mod failover;
+pub mod geo_rules;
+pub mod grpc;
 mod handler;
+pub mod header_rewrite;
+pub mod inflight;
+pub mod limits;
+pub mod log_fields;
+pub mod maintenance_page;
+pub mod methods;
+mod path;
+pub mod rate_limit;
+pub mod request_log;
 mod router;
+pub mod security_headers;
+pub mod store_forward;
+pub mod trace;
+pub mod transform;
+pub mod tunnel;
+pub mod upstream;
+pub mod upstream_auth;
 pub(crate) mod ws_handler;
 
+pub use self::cache::ResponseCache;
+pub use self::geo_rules::GeoFilter;
+pub use self::grpc::GrpcClient;
 pub use self::handler::proxy_handler;
+pub(crate) use self::handler::ADMIN_NETWORK_DENIED;
+pub use self::inflight::InFlightTracker;
+pub use self::limits::{ProxyLimits, ViolationCounters};
+pub use self::log_fields::LogFieldExtractors;
+pub use self::path::normalize_path;
+pub use self::rate_limit::RateLimiter;
 pub use self::router::ProxyRouter;
+pub use self::security_headers::HeaderSamples;
+pub use self::trace::RouteTracer;
+pub use self::transform::RouteTransforms;
+pub use self::tunnel::TunnelStats;
+pub use self::upstream::UpstreamClients;
 
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 
+use crate::acme::AcmeManager;
+use crate::api::api_trace::ApiTraceRecorder;
+use crate::aranea::tokens::RegistrationLimiter;
 use crate::aranea::AraneaClient;
-use crate::config::AuthConfig;
+use crate::cluster::ClusterCoordinator;
+use crate::config::{AcmeConfig, AuthConfig, DnsConfig, FrontendConfig, TlsConfig};
 use crate::db::AppState;
 use crate::ddns::DdnsUpdater;
 use crate::external::ExternalDeviceManager;
+use crate::frontend::FrontendAssets;
 use crate::geoip::GeoIpReader;
+use crate::health::{RouteWarmup, TargetProbeCache};
+use crate::jwt_keys::JwtKeys;
+use crate::local_dns::LocalDns;
+use crate::migrations::MigrationRunner;
+use crate::models::{AccessLogDeleteJob, PermissionFloors, SecurityHeadersPolicy};
+use crate::network_policy::NetworkPolicyStore;
+use crate::nginx_log::NginxLogTailer;
+use crate::nginx_stats::NginxStats;
 use crate::notify::DiscordNotifier;
 use crate::omada::OmadaManager;
 use crate::openwrt::OpenWrtManager;
+use crate::restart::DrainGate;
+use crate::status_page::StatusPageCache;
+use crate::sysmetrics::SystemMetrics;
+use crate::tls::TlsCertStore;
+use crate::topology_share::TopologyShareGuard;
+
+/// Concurrent GET /api/topology/watch requests; more are rejected with 429
+pub const MAX_TOPOLOGY_WATCHERS: usize = 64;
 
 /// Shared proxy router state
 #[derive(Clone)]
 pub struct ProxyState {
     pub router: Arc<RwLock<ProxyRouter>>,
+    /// Compiled block list, LAN, trusted proxies and admin allowlist
+    pub network_policy: Arc<NetworkPolicyStore>,
     pub app_state: AppState,
     pub http_client: reqwest::Client,
+    /// Clients of routes with upstream tuning, and per-route upstream counters
+    pub upstream: Arc<UpstreamClients>,
+    /// HTTP/2 client of gRPC routes (streams and trailers)
+    pub grpc_client: GrpcClient,
     pub ddns_updater: Arc<DdnsUpdater>,
     pub notifier: Arc<DiscordNotifier>,
     pub geoip: Option<Arc<GeoIpReader>>,
     pub auth_config: AuthConfig,
+    /// Session signing secrets (`[auth] jwt_secret` until rotated)
+    pub jwt_keys: Arc<RwLock<JwtKeys>>,
+    /// Live permission floors (settings `permission_floor_*`)
+    pub permission_floors: Arc<RwLock<PermissionFloors>>,
+    /// Proxy hardening limits (settings `proxy_*`)
+    pub proxy_limits: Arc<RwLock<ProxyLimits>>,
+    /// `[server] max_body_mb`, the request body limit while its setting is empty
+    pub max_body_mb: usize,
+    /// Global security response header policy (setting `security_headers_default`)
+    pub security_headers: Arc<RwLock<SecurityHeadersPolicy>>,
+    /// Tracked security headers seen on recent responses, per route
+    pub security_header_samples: Arc<HeaderSamples>,
+    /// Per-route counts of fired proxy protections
+    pub proxy_violations: Arc<ViolationCounters>,
+    /// Requests and WebSocket tunnels currently being proxied
+    pub in_flight: Arc<InFlightTracker>,
+    /// Per-route totals of closed WebSocket tunnels
+    pub tunnel_stats: Arc<TunnelStats>,
+    /// Per-route request tracing (PUT /api/routes/:id/trace)
+    pub route_tracer: Arc<RouteTracer>,
+    /// Compiled transformation scripts and their metrics
+    pub route_transforms: Arc<RouteTransforms>,
+    /// Compiled custom access log field rules
+    pub route_log_fields: Arc<LogFieldExtractors>,
+    /// Host metrics collector (sampled in the background, 1h history)
+    pub system_metrics: Arc<SystemMetrics>,
+    /// Bulk access log delete jobs by job id (POST /api/dashboard/access-log/delete)
+    pub access_log_delete_jobs: Arc<RwLock<HashMap<String, AccessLogDeleteJob>>>,
+    /// Slots for held GET /api/topology/watch requests
+    pub topology_watchers: Arc<Semaphore>,
+    /// Set while a restart drains; the proxy refuses new requests
+    pub drain: Arc<DrainGate>,
+    /// Routes activated before their target was healthy (503 until it is)
+    pub route_warmup: Arc<RouteWarmup>,
+    /// Cached TCP probes of route targets (GET /api/server-routes/analysis)
+    pub target_probes: Arc<TargetProbeCache>,
+    /// Public status page snapshot cache and per-IP limiter (GET /status)
+    pub status_page: Arc<StatusPageCache>,
+    /// Limiter and payload cache of GET /api/public/topology
+    pub topology_shares: Arc<TopologyShareGuard>,
+    /// LAN DNS responder (overrides, counters, listener health)
+    pub local_dns: Arc<LocalDns>,
+    /// Admin frontend files served before the proxy fallback ([frontend] mode)
+    pub frontend: Arc<FrontendAssets>,
+    /// Follows nginx's JSON access log in full proxy mode
+    pub nginx_log: Arc<NginxLogTailer>,
+    /// nginx stub_status samples and the last reload LPG performed
+    pub nginx_stats: Arc<NginxStats>,
+    pub omada_manager: Arc<OmadaManager>,
+    pub openwrt_manager: Arc<OpenWrtManager>,
+    pub external_manager: Arc<ExternalDeviceManager>,
+    pub aranea_client: Arc<AraneaClient>,
+    /// Per-IP and per-token limits for token-authenticated device registration
+    pub registration_limiter: Arc<RegistrationLimiter>,
+    /// Leader election / singleton task supervision
+    pub cluster: Arc<ClusterCoordinator>,
+    /// Startup migrations (re-runs via the admin API)
+    pub migrations: Arc<MigrationRunner>,
+    /// Admin API request recorder state (see api::api_trace)
+    pub api_trace: Arc<ApiTraceRecorder>,
+    /// Cached GET responses of routes with `cache_ttl_sec`
+    pub response_cache: Arc<ResponseCache>,
+    /// Per-client-IP rate limit rules and their token buckets
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Country allow/deny rules (setting `geo_rules`)
+    pub geo_filter: Arc<GeoFilter>,
+    /// Certificates of the built-in HTTPS listener (`[server.tls]`)
+    pub tls: Arc<TlsCertStore>,
+    /// ACME orders, challenge tokens and renewal of DDNS hostnames
+    pub acme: Arc<AcmeManager>,
+}
+
+/// Inputs of `ProxyState::new`: services built before it and the config
+/// sections it reads
+pub struct ProxyStateDeps {
+    pub app_state: AppState,
+    pub notifier: Arc<DiscordNotifier>,
     pub omada_manager: Arc<OmadaManager>,
     pub openwrt_manager: Arc<OpenWrtManager>,
     pub external_manager: Arc<ExternalDeviceManager>,
     pub aranea_client: Arc<AraneaClient>,
+    pub cluster: Arc<ClusterCoordinator>,
+    pub migrations: Arc<MigrationRunner>,
+    /// `[server] geoip_db_path` (None = no GeoIP)
+    pub geoip_db_path: Option<String>,
+    /// `[server] response_cache_max_mb`
+    pub response_cache_max_mb: usize,
+    /// `[server] max_body_mb`
+    pub max_body_mb: usize,
+    pub tls_config: TlsConfig,
+    pub acme_config: AcmeConfig,
+    pub auth_config: AuthConfig,
+    pub dns_config: DnsConfig,
+    pub frontend_config: FrontendConfig,
 }
 
 impl ProxyState {
-    pub async fn new(
-        app_state: AppState,
-        notifier: Arc<DiscordNotifier>,
-        geoip_db_path: Option<&str>,
-        auth_config: AuthConfig,
-        omada_manager: Arc<OmadaManager>,
-        openwrt_manager: Arc<OpenWrtManager>,
-        external_manager: Arc<ExternalDeviceManager>,
-        aranea_client: Arc<AraneaClient>,
-    ) -> anyhow::Result<Self> {
+    pub async fn new(deps: ProxyStateDeps) -> anyhow::Result<Self> {
+        let ProxyStateDeps {
+            app_state,
+            notifier,
+            omada_manager,
+            openwrt_manager,
+            external_manager,
+            aranea_client,
+            cluster,
+            migrations,
+            geoip_db_path,
+            response_cache_max_mb,
+            max_body_mb,
+            tls_config,
+            acme_config,
+            auth_config,
+            dns_config,
+            frontend_config,
+        } = deps;
+
         // Load initial routes from database (with DDNS hostname info)
         let routes = app_state.mysql.list_active_routes_with_ddns().await?;
         let router = ProxyRouter::new(routes);
 
-        // Create HTTP client with sensible defaults
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .pool_max_idle_per_host(10)
-            .build()?;
+        // Compile blocked IPs and network settings into the policy snapshot
+        let network_policy =
+            NetworkPolicyStore::load(&app_state.mysql, auth_config.internet_access_enabled).await?;
+
+        // Permission floors; the login floor defaults to the configured value
+        let permission_floors = app_state
+            .mysql
+            .load_permission_floors(PermissionFloors {
+                login: auth_config.lacisoath_required_permission,
+                ..PermissionFloors::default()
+            })
+            .await?;
+
+        let jwt_keys = JwtKeys::load(&app_state.mysql, &auth_config.jwt_secret).await?;
+        let proxy_limits = ProxyLimits::load(&app_state.mysql, max_body_mb).await?;
+        let security_headers = SecurityHeadersPolicy::load(&app_state.mysql).await?;
+
+        // Rate limit rules (non-fatal: the proxy runs unlimited without them)
+        let rate_limiter = RateLimiter::default();
+        if let Err(e) = rate_limiter.reload(&app_state.mysql).await {
+            tracing::warn!("Rate limit rules not loaded: {}", e);
+        }
+
+        // Country rules (non-fatal: no country filtering without them)
+        let geo_filter = GeoFilter::default();
+        if let Err(e) = geo_filter.reload(&app_state.mysql).await {
+            tracing::warn!("Geo rules not loaded: {}", e);
+        }
+
+        let frontend = FrontendAssets::from_config(&frontend_config)?;
+
+        // HTTPS certificates (failures are reported by tls::reload_loop)
+        let tls = Arc::new(TlsCertStore::from_config(&tls_config));
+        let acme = Arc::new(AcmeManager::new(
+            app_state.clone(),
+            notifier.clone(),
+            acme_config,
+            tls.clone(),
+        ));
+
+        // Shared upstream client; tuned routes get their own (see upstream)
+        let http_client = upstream::build_client(
+            upstream::DEFAULT_CONNECT_TIMEOUT_MS,
+            upstream::DEFAULT_MAX_IDLE_PER_HOST,
+        )?;
 
         // Create DDNS updater
-        let ddns_updater = Arc::new(DdnsUpdater::new(app_state.clone(), notifier.clone()));
+        let ddns_updater = Arc::new(DdnsUpdater::new(
+            app_state.clone(),
+            notifier.clone(),
+            omada_manager.clone(),
+        ));
 
         // Initialize GeoIP reader (optional, non-fatal on failure)
-        let geoip = geoip_db_path.and_then(|path| match GeoIpReader::open(path) {
-            Ok(reader) => Some(Arc::new(reader)),
-            Err(e) => {
-                tracing::warn!("GeoIP database not available: {} (path: {})", e, path);
-                None
-            }
-        });
+        let geoip = geoip_db_path
+            .as_deref()
+            .and_then(|path| match GeoIpReader::open(path) {
+                Ok(reader) => Some(Arc::new(reader)),
+                Err(e) => {
+                    tracing::warn!("GeoIP database not available: {} (path: {})", e, path);
+                    None
+                }
+            });
 
         Ok(Self {
             router: Arc::new(RwLock::new(router)),
+            network_policy: Arc::new(network_policy),
             app_state,
+            upstream: Arc::new(UpstreamClients::new(http_client.clone())),
             http_client,
+            grpc_client: GrpcClient::default(),
             ddns_updater,
             notifier,
             geoip,
             auth_config,
+            jwt_keys: Arc::new(RwLock::new(jwt_keys)),
+            permission_floors: Arc::new(RwLock::new(permission_floors)),
+            proxy_limits: Arc::new(RwLock::new(proxy_limits)),
+            max_body_mb,
+            security_headers: Arc::new(RwLock::new(security_headers)),
+            security_header_samples: Arc::new(HeaderSamples::default()),
+            proxy_violations: Arc::new(ViolationCounters::default()),
+            in_flight: Arc::new(InFlightTracker::default()),
+            tunnel_stats: Arc::new(TunnelStats::default()),
+            route_tracer: Arc::new(RouteTracer::default()),
+            route_transforms: Arc::new(RouteTransforms::default()),
+            route_log_fields: Arc::new(LogFieldExtractors::default()),
+            system_metrics: Arc::new(SystemMetrics::new()),
+            access_log_delete_jobs: Arc::new(RwLock::new(HashMap::new())),
+            topology_watchers: Arc::new(Semaphore::new(MAX_TOPOLOGY_WATCHERS)),
+            drain: Arc::new(DrainGate::default()),
+            route_warmup: Arc::new(RouteWarmup::default()),
+            target_probes: Arc::new(TargetProbeCache::default()),
+            status_page: Arc::new(StatusPageCache::default()),
+            topology_shares: Arc::new(TopologyShareGuard::default()),
+            local_dns: Arc::new(LocalDns::new(dns_config)),
+            frontend: Arc::new(frontend),
+            nginx_log: Arc::new(NginxLogTailer::default()),
+            nginx_stats: Arc::new(NginxStats::default()),
             omada_manager,
             openwrt_manager,
             external_manager,
             aranea_client,
+            registration_limiter: Arc::new(RegistrationLimiter::default()),
+            cluster,
+            migrations,
+            api_trace: Arc::new(ApiTraceRecorder::default()),
+            response_cache: Arc::new(ResponseCache::new(response_cache_max_mb * 1024 * 1024)),
+            rate_limiter: Arc::new(rate_limiter),
+            geo_filter: Arc::new(geo_filter),
+            tls,
+            acme,
         })
     }
 
-    /// Reload routes from database
+    /// Reload routes from database; cached responses of routes that changed
+    /// or went away are dropped
     pub async fn reload_routes(&self) -> anyhow::Result<()> {
         let routes = self.app_state.mysql.list_active_routes_with_ddns().await?;
         let count = routes.len();
         let mut router = self.router.write().await;
+        let previous: HashMap<i32, _> = router.routes().map(|r| (r.id, r.updated_at)).collect();
         *router = ProxyRouter::new(routes);
+        self.upstream.retain(router.routes());
+        for (id, updated_at) in previous {
+            if !router
+                .routes()
+                .any(|r| r.id == id && r.updated_at == updated_at)
+            {
+                self.response_cache.purge_route(id);
+            }
+        }
         tracing::info!("Proxy routes reloaded: {} active routes", count);
         Ok(())
     }
+
+    /// Reload proxy hardening limits from settings
+    pub async fn reload_proxy_limits(&self) -> anyhow::Result<()> {
+        let limits = ProxyLimits::load(&self.app_state.mysql, self.max_body_mb).await?;
+        *self.proxy_limits.write().await = limits;
+        tracing::info!("Proxy limits reloaded: {:?}", limits);
+        Ok(())
+    }
+
+    /// Reload the global security header policy from settings
+    pub async fn reload_security_headers(&self) -> anyhow::Result<()> {
+        let policy = SecurityHeadersPolicy::load(&self.app_state.mysql).await?;
+        *self.security_headers.write().await = policy;
+        tracing::info!("Security header policy reloaded");
+        Ok(())
+    }
+
+    /// Reload rate limit rules and the `rate_limit_enabled` switch
+    pub async fn reload_rate_limits(&self) -> anyhow::Result<()> {
+        self.rate_limiter.reload(&self.app_state.mysql).await?;
+        tracing::info!("Rate limit rules reloaded");
+        Ok(())
+    }
+
+    /// Recompile the network policy from database (blocked IPs, network settings)
+    pub async fn rebuild_network_policy(&self) -> anyhow::Result<()> {
+        self.network_policy.rebuild(&self.app_state.mysql).await?;
+        Ok(())
+    }
+
+    /// Resolve a request the way the proxy handler would, without forwarding
+    pub async fn dry_run_route(&self, path: &str, host: Option<&str>, method: &str) -> RouteDryRun {
+        let mut result = RouteDryRun {
+            outcome: "match",
+            status: 200,
+            route_id: None,
+            route_path: None,
+            target_url: None,
+            admin_network_only: false,
+            allow: None,
+            detail: None,
+        };
+        let normalized = match path::normalize_path(path) {
+            Ok(normalized) => normalized,
+            Err(rejection) => {
+                result.outcome = "path_rejected";
+                result.status = 400;
+                result.detail = Some(rejection.as_str().to_string());
+                return result;
+            }
+        };
+
+        let router = self.router.read().await;
+        let Some(route) = router.match_route(&normalized.matching, host) else {
+            result.outcome = "no_route";
+            result.status = 404;
+            return result;
+        };
+        result.route_id = Some(route.id);
+        result.route_path = Some(route.path.clone());
+        result.target_url = Some(router.build_target_url(route, &normalized));
+        result.admin_network_only = route.admin_network_only;
+        if let Err(allow) = route.check_method(method) {
+            result.outcome = "method_not_allowed";
+            result.status = 405;
+            result.allow = Some(allow);
+        }
+        result
+    }
+}
+
+/// Result of GET /api/routes/test
+#[derive(Debug, Serialize)]
+pub struct RouteDryRun {
+    /// "match", "no_route", "method_not_allowed" or "path_rejected"
+    pub outcome: &'static str,
+    /// Status the proxy would answer with (200 = forwarded upstream)
+    pub status: u16,
+    pub route_id: Option<i32>,
+    pub route_path: Option<String>,
+    pub target_url: Option<String>,
+    /// Callers outside the admin networks would get 404 instead
+    pub admin_network_only: bool,
+    /// `Allow` header sent with a 405
+    pub allow: Option<String>,
+    pub detail: Option<String>,
 }
@@ -0,0 +1,356 @@
+//! Request path normalization before route matching
+//!
+//! The raw request path is percent-decoded exactly once per segment,
+//! duplicate slashes are collapsed and dot segments resolved. The decoded
+//! form is used for route matching; the upstream form re-encodes each
+//! decoded segment so the backend receives the same canonical path.
+//!
+//! Anything that could still be interpreted as traversal by a backend that
+//! decodes again, or that treats `\` as a separator, is rejected. So is an
+//! encoded `/` or a `\` inside a segment: matching would otherwise see a
+//! segment boundary the upstream never does. Both forms therefore always
+//! have the same segments, which is what prefix stripping relies on.
+
+use std::fmt;
+
+/// Normalized request path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedPath {
+    /// Decoded path used for route matching and logging
+    pub matching: String,
+    /// Re-encoded path used to build the upstream URL
+    pub upstream: String,
+}
+
+/// Why a path was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathRejection {
+    /// `%` not followed by two hex digits
+    InvalidEncoding,
+    /// Decoded bytes are not valid UTF-8 (includes overlong encodings)
+    InvalidUtf8,
+    /// Decoded NUL or other control character
+    ControlCharacter,
+    /// Dot segments escaping the root, or hidden behind encoded separators,
+    /// backslashes or a second encoding layer
+    Traversal,
+    /// `%2F`, `%5C` or `\` inside a segment
+    EncodedSeparator,
+}
+
+impl PathRejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidEncoding => "invalid_encoding",
+            Self::InvalidUtf8 => "invalid_utf8",
+            Self::ControlCharacter => "control_character",
+            Self::Traversal => "traversal",
+            Self::EncodedSeparator => "encoded_separator",
+        }
+    }
+}
+
+impl fmt::Display for PathRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Normalize a raw request path (without query string)
+pub fn normalize_path(raw: &str) -> Result<NormalizedPath, PathRejection> {
+    let mut segments: Vec<String> = Vec::new();
+    let raw_segments: Vec<&str> = raw.split('/').filter(|s| !s.is_empty()).collect();
+    let mut trailing_slash = raw.len() > 1 && raw.ends_with('/');
+
+    for (i, raw_segment) in raw_segments.iter().enumerate() {
+        let decoded = percent_decode(raw_segment)?;
+        if decoded.chars().any(char::is_control) {
+            return Err(PathRejection::ControlCharacter);
+        }
+        if hides_traversal(&decoded) {
+            return Err(PathRejection::Traversal);
+        }
+        if decoded.contains(['/', '\\']) {
+            return Err(PathRejection::EncodedSeparator);
+        }
+
+        let is_last = i + 1 == raw_segments.len();
+        match decoded.as_str() {
+            "." => {
+                if is_last {
+                    trailing_slash = true;
+                }
+            }
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(PathRejection::Traversal);
+                }
+                if is_last {
+                    trailing_slash = true;
+                }
+            }
+            _ => segments.push(decoded),
+        }
+    }
+
+    let mut matching = String::with_capacity(raw.len());
+    let mut upstream = String::with_capacity(raw.len());
+    for segment in &segments {
+        matching.push('/');
+        matching.push_str(segment);
+        upstream.push('/');
+        encode_segment(segment, &mut upstream);
+    }
+    if segments.is_empty() || trailing_slash {
+        matching.push('/');
+        upstream.push('/');
+    }
+
+    Ok(NormalizedPath { matching, upstream })
+}
+
+impl NormalizedPath {
+    /// Upstream form without its first `count` segments
+    pub fn upstream_after_segments(&self, count: usize) -> &str {
+        let mut rest = self.upstream.as_str();
+        for _ in 0..count {
+            if rest.is_empty() {
+                break;
+            }
+            rest = match rest[1..].find('/') {
+                Some(i) => &rest[i + 1..],
+                None => "",
+            };
+        }
+        rest
+    }
+}
+
+/// Whether a once-decoded segment still carries a dot segment behind an
+/// encoded `/`, a backslash, or a second layer of percent-encoding
+fn hides_traversal(decoded: &str) -> bool {
+    let is_dot = |piece: &str| piece == "." || piece == "..";
+
+    if (decoded.contains('/') || decoded.contains('\\')) && decoded.split(['/', '\\']).any(is_dot) {
+        return true;
+    }
+
+    if decoded.contains('%') {
+        // Double-encoded (%252e%252e): only refuse if the second layer is
+        // traversal, so literal "%" in file names keeps working
+        if let Ok(twice) = percent_decode(decoded) {
+            return twice.split(['/', '\\']).any(is_dot);
+        }
+    }
+
+    false
+}
+
+fn percent_decode(input: &str) -> Result<String, PathRejection> {
+    if !input.contains('%') {
+        return Ok(input.to_string());
+    }
+
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hi = bytes.get(i + 1).and_then(|b| hex_value(*b));
+            let lo = bytes.get(i + 2).and_then(|b| hex_value(*b));
+            match (hi, lo) {
+                (Some(hi), Some(lo)) => out.push(hi << 4 | lo),
+                _ => return Err(PathRejection::InvalidEncoding),
+            }
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).map_err(|_| PathRejection::InvalidUtf8)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Percent-encode everything outside RFC 3986 `pchar`
+fn encode_segment(segment: &str, out: &mut String) {
+    for b in segment.bytes() {
+        let keep = b.is_ascii_alphanumeric()
+            || matches!(
+                b,
+                b'-' | b'.'
+                    | b'_'
+                    | b'~'
+                    | b'!'
+                    | b'$'
+                    | b'&'
+                    | b'\''
+                    | b'('
+                    | b')'
+                    | b'*'
+                    | b'+'
+                    | b','
+                    | b';'
+                    | b'='
+                    | b':'
+                    | b'@'
+            );
+        if keep {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(raw: &str) -> (String, String) {
+        let n = normalize_path(raw).unwrap_or_else(|e| panic!("{} rejected: {}", raw, e));
+        (n.matching, n.upstream)
+    }
+
+    fn rejected(raw: &str) -> PathRejection {
+        match normalize_path(raw) {
+            Ok(n) => panic!("{} accepted as {:?}", raw, n),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn plain_paths_are_unchanged() {
+        for p in [
+            "/",
+            "/api/routes",
+            "/api/routes/",
+            "/a-b_c~d/e.f",
+            "/u/@me:1",
+        ] {
+            assert_eq!(ok(p), (p.to_string(), p.to_string()));
+        }
+        assert_eq!(ok(""), ("/".to_string(), "/".to_string()));
+    }
+
+    #[test]
+    fn duplicate_slashes_collapse() {
+        assert_eq!(ok("//api//routes").0, "/api/routes");
+        assert_eq!(ok("///").0, "/");
+        assert_eq!(ok("/app//").0, "/app/");
+    }
+
+    #[test]
+    fn dot_segments_resolve_within_root() {
+        assert_eq!(ok("/api/../admin").0, "/admin");
+        assert_eq!(ok("/api/./routes").0, "/api/routes");
+        assert_eq!(ok("/api/v1/..").0, "/api/");
+        assert_eq!(ok("/api/%2e%2e/admin").0, "/admin");
+        assert_eq!(ok("/api/%2E/routes").0, "/api/routes");
+        assert_eq!(ok("/a/.%2e/b").0, "/b");
+    }
+
+    #[test]
+    fn traversal_above_root_is_rejected() {
+        for p in [
+            "/..",
+            "/../admin",
+            "/%2e%2e/admin",
+            "/%2E%2E/admin",
+            "/a/../../etc/passwd",
+        ] {
+            assert_eq!(rejected(p), PathRejection::Traversal, "{}", p);
+        }
+    }
+
+    #[test]
+    fn encoded_separators_and_backslashes_are_rejected() {
+        for p in [
+            "/app/%2e%2e%2fadmin",
+            "/app/..%2fadmin",
+            "/app/%2e%2e%5cadmin",
+            "/app/..\\admin",
+            "/app\\..\\admin",
+            "/app/%5c..%5cadmin",
+            "/app/foo%2f..%2f..%2fetc",
+        ] {
+            assert_eq!(rejected(p), PathRejection::Traversal, "{}", p);
+        }
+    }
+
+    #[test]
+    fn encoded_separators_inside_a_segment_are_rejected() {
+        for p in [
+            "/files/a%2Fb",
+            "/files/a%2fb",
+            "/files/a%5Cb",
+            "/files/a\\b",
+        ] {
+            assert_eq!(rejected(p), PathRejection::EncodedSeparator, "{}", p);
+        }
+    }
+
+    #[test]
+    fn segments_are_stripped_from_the_upstream_form() {
+        let n = normalize_path("/caf%C3%A9/menu%20du%20jour/").unwrap();
+        assert_eq!(n.upstream_after_segments(0), "/caf%C3%A9/menu%20du%20jour/");
+        assert_eq!(n.upstream_after_segments(1), "/menu%20du%20jour/");
+        assert_eq!(n.upstream_after_segments(2), "/");
+        assert_eq!(n.upstream_after_segments(3), "");
+        assert_eq!(normalize_path("/a").unwrap().upstream_after_segments(1), "");
+    }
+
+    #[test]
+    fn double_encoding_is_decoded_once() {
+        for p in [
+            "/app/%252e%252e/admin",
+            "/%252e%252e%252fetc",
+            "/app/%25%32%65%25%32%65/x",
+        ] {
+            assert_eq!(rejected(p), PathRejection::Traversal, "{}", p);
+        }
+        // A literal percent that is not traversal survives round-trip
+        assert_eq!(
+            ok("/files/100%2525.txt"),
+            (
+                "/files/100%25.txt".to_string(),
+                "/files/100%2525.txt".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn overlong_and_invalid_encodings_are_rejected() {
+        // Overlong UTF-8 for '.' and '/'
+        assert_eq!(rejected("/%c0%ae%c0%ae/admin"), PathRejection::InvalidUtf8);
+        assert_eq!(rejected("/app/%c0%af"), PathRejection::InvalidUtf8);
+        assert_eq!(rejected("/app/%e0%80%ae"), PathRejection::InvalidUtf8);
+        assert_eq!(rejected("/app/%zz"), PathRejection::InvalidEncoding);
+        assert_eq!(rejected("/app/%2"), PathRejection::InvalidEncoding);
+        assert_eq!(rejected("/app/%00.txt"), PathRejection::ControlCharacter);
+    }
+
+    #[test]
+    fn upstream_form_is_canonically_encoded() {
+        assert_eq!(
+            ok("/docs/my%20file%7e.pdf"),
+            (
+                "/docs/my file~.pdf".to_string(),
+                "/docs/my%20file~.pdf".to_string()
+            )
+        );
+        assert_eq!(
+            ok("/caf%C3%A9//menu"),
+            ("/café/menu".to_string(), "/caf%C3%A9/menu".to_string())
+        );
+    }
+}
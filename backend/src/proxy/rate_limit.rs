@@ -0,0 +1,282 @@
+//! Per-client-IP rate limiting of proxied requests
+//!
+//! Each enabled rule (rate_limit_rules) gives every client IP a token bucket
+//! of `requests_per_window` tokens refilled over `window_sec`; a request
+//! below the rule's path prefix takes one token. Every matching rule counts
+//! on its own. An empty bucket is a violation: `log` rules only record it,
+//! `block` rules answer 429 and, with `block_duration_sec`, put the address
+//! on the block list for that long. A violation is reported (security event,
+//! block) once per window and client, however many requests follow.
+//!
+//! Rules with `exclude_lan` skip LAN clients as the network policy sees
+//! them. Setting `rate_limit_enabled = false` turns all rules off.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::mysql::MySqlDb;
+use crate::error::AppError;
+use crate::models::RateLimitRule;
+
+/// Master switch (settings); missing counts as on
+pub const SETTING_ENABLED: &str = "rate_limit_enabled";
+
+/// Longest accepted `window_sec` and `block_duration_sec`
+pub const MAX_WINDOW_SECS: i32 = 86_400;
+pub const MAX_BLOCK_SECS: i32 = 30 * 86_400;
+
+/// Bucket count at which idle ones are swept
+const SWEEP_AT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitAction {
+    /// Record a security event, let the request through
+    #[default]
+    Log,
+    /// Answer 429 (and block the IP when the rule has a block duration)
+    Block,
+}
+
+impl RateLimitAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Log => "log",
+            Self::Block => "block",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "block" => Self::Block,
+            _ => Self::Log,
+        }
+    }
+}
+
+/// Whether `path` is `prefix` or below it ("/" covers everything)
+pub fn prefix_matches(prefix: &str, path: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() || path == prefix {
+        return true;
+    }
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// A rule a request ran out of tokens on
+#[derive(Debug, Clone)]
+pub struct RateLimitHit {
+    pub rule: RateLimitRule,
+    /// Until the bucket holds a token again
+    pub retry_after: Duration,
+    /// First violation of this client in the window (report it)
+    pub first: bool,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    reported: Option<Instant>,
+}
+
+/// Loaded rules and the token buckets of the clients they saw
+#[derive(Default)]
+pub struct RateLimiter {
+    rules: RwLock<Vec<RateLimitRule>>,
+    enabled: RwLock<bool>,
+    buckets: Mutex<HashMap<(i32, IpAddr), Bucket>>,
+}
+
+impl RateLimiter {
+    /// Re-read rules and the master switch; buckets of rules that changed
+    /// or went away start over
+    pub async fn reload(&self, mysql: &MySqlDb) -> Result<(), AppError> {
+        let rules = mysql.list_rate_limit_rules().await?;
+        let enabled = mysql
+            .get_setting(SETTING_ENABLED)
+            .await?
+            .is_none_or(|v| v == "true" || v == "1");
+        self.set_rules(rules, enabled);
+        Ok(())
+    }
+
+    fn set_rules(&self, rules: Vec<RateLimitRule>, enabled: bool) {
+        let rules: Vec<RateLimitRule> = rules.into_iter().filter(|r| r.enabled).collect();
+        {
+            let previous = self.rules.read().unwrap();
+            let kept: Vec<i32> = previous
+                .iter()
+                .filter(|old| {
+                    rules
+                        .iter()
+                        .any(|r| r.id == old.id && r.updated_at == old.updated_at)
+                })
+                .map(|r| r.id)
+                .collect();
+            self.buckets
+                .lock()
+                .unwrap()
+                .retain(|(rule_id, _), _| kept.contains(rule_id));
+        }
+        *self.rules.write().unwrap() = rules;
+        *self.enabled.write().unwrap() = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self.enabled.read().unwrap()
+    }
+
+    /// Clients currently tracked per rule id
+    pub fn tracked_clients(&self) -> HashMap<i32, usize> {
+        let mut counts = HashMap::new();
+        for (rule_id, _) in self.buckets.lock().unwrap().keys() {
+            *counts.entry(*rule_id).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Take a token from every rule matching `path`; returns the rules
+    /// that had none left
+    pub fn check(&self, ip: IpAddr, path: &str, lan: bool) -> Vec<RateLimitHit> {
+        self.check_at(ip, path, lan, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, path: &str, lan: bool, now: Instant) -> Vec<RateLimitHit> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        let rules = self.rules.read().unwrap();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= SWEEP_AT {
+            sweep(&mut buckets, &rules, now);
+        }
+
+        let mut hits = Vec::new();
+        for rule in rules.iter() {
+            if (lan && rule.exclude_lan) || !prefix_matches(&rule.path_prefix, path) {
+                continue;
+            }
+            let capacity = rule.requests_per_window.max(1) as f64;
+            let window = Duration::from_secs(rule.window_sec.max(1) as u64);
+            let per_sec = capacity / window.as_secs_f64();
+            let bucket = buckets.entry((rule.id, ip)).or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+                reported: None,
+            });
+            let elapsed = now.saturating_duration_since(bucket.updated);
+            bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * per_sec).min(capacity);
+            bucket.updated = now;
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                continue;
+            }
+
+            let first = bucket
+                .reported
+                .is_none_or(|at| now.saturating_duration_since(at) >= window);
+            if first {
+                bucket.reported = Some(now);
+            }
+            hits.push(RateLimitHit {
+                rule: rule.clone(),
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec),
+                first,
+            });
+        }
+        hits
+    }
+}
+
+/// Drop full buckets (an untouched client is indistinguishable from a new one)
+fn sweep(buckets: &mut HashMap<(i32, IpAddr), Bucket>, rules: &[RateLimitRule], now: Instant) {
+    let windows: HashMap<i32, Duration> = rules
+        .iter()
+        .map(|r| (r.id, Duration::from_secs(r.window_sec.max(1) as u64)))
+        .collect();
+    buckets.retain(|(rule_id, _), bucket| {
+        windows
+            .get(rule_id)
+            .is_some_and(|window| now.saturating_duration_since(bucket.updated) < *window)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn rule(id: i32, prefix: &str, requests: i32, window_sec: i32) -> RateLimitRule {
+        RateLimitRule {
+            id,
+            path_prefix: prefix.to_string(),
+            requests_per_window: requests,
+            window_sec,
+            action: RateLimitAction::Block,
+            block_duration_sec: None,
+            exclude_lan: true,
+            enabled: true,
+            comment: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn limiter(rules: Vec<RateLimitRule>) -> RateLimiter {
+        let limiter = RateLimiter::default();
+        limiter.set_rules(rules, true);
+        limiter
+    }
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        assert!(prefix_matches("/", "/anything"));
+        assert!(prefix_matches("/api", "/api"));
+        assert!(prefix_matches("/api/", "/api/v1/users"));
+        assert!(!prefix_matches("/api", "/apis"));
+    }
+
+    #[test]
+    fn bucket_refills_over_the_window() {
+        let limiter = limiter(vec![rule(1, "/api", 2, 10)]);
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let now = Instant::now();
+        assert!(limiter.check_at(ip, "/api/a", false, now).is_empty());
+        assert!(limiter.check_at(ip, "/api/b", false, now).is_empty());
+        assert!(limiter.check_at(ip, "/other", false, now).is_empty());
+
+        let hits = limiter.check_at(ip, "/api/c", false, now);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].first);
+        assert_eq!(hits[0].retry_after, Duration::from_secs(5));
+        // Reported once per window
+        assert!(!limiter.check_at(ip, "/api/c", false, now)[0].first);
+
+        // Another client has its own bucket; one token back after 5s
+        let other: IpAddr = "2001:db8::7".parse().unwrap();
+        assert!(limiter.check_at(other, "/api", false, now).is_empty());
+        let later = now + Duration::from_secs(5);
+        assert!(limiter.check_at(ip, "/api/d", false, later).is_empty());
+    }
+
+    #[test]
+    fn lan_clients_and_the_master_switch() {
+        let mut counted = rule(2, "/", 1, 60);
+        counted.exclude_lan = false;
+        let limiter = limiter(vec![rule(1, "/", 1, 60), counted]);
+        let ip: IpAddr = "192.168.1.20".parse().unwrap();
+        let now = Instant::now();
+        limiter.check_at(ip, "/", true, now);
+        let hits = limiter.check_at(ip, "/", true, now);
+        assert_eq!(hits.iter().map(|h| h.rule.id).collect::<Vec<_>>(), vec![2]);
+
+        limiter.set_rules(vec![rule(1, "/", 1, 60)], false);
+        assert!(limiter.check_at(ip, "/", false, now).is_empty());
+        assert!(limiter.check_at(ip, "/", false, now).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Access log fields shared by every entry of one proxied request
+//!
+//! A `RequestLogContext` is built once when a request arrives (client IP,
+//! method, User-Agent, Referer, HTTP version, start time). The handler fills
+//! in the normalized path and the matched route as it learns them, and every
+//! access log entry of the request (rejections, upstream errors, the final
+//! response, a WebSocket session or a gRPC call) is built from it.
+
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::http::header;
+use chrono::Utc;
+
+use super::log_fields::CustomFields;
+use super::ProxyState;
+use crate::models::AccessLog;
+
+/// Access log fields of one request
+#[derive(Debug, Clone)]
+pub struct RequestLogContext {
+    pub client_ip: String,
+    pub method: String,
+    /// Request path; the normalized matching path once it is known
+    pub path: String,
+    pub user_agent: Option<String>,
+    pub referer: Option<String>,
+    pub http_version: String,
+    pub start_time: Instant,
+    /// Set when a route matches
+    pub route_id: Option<i32>,
+    /// Custom access log fields of the matched route
+    pub custom_fields: Option<CustomFields>,
+}
+
+impl RequestLogContext {
+    pub fn new(client_ip: &str, req: &Request) -> Self {
+        let header = |name: header::HeaderName| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            client_ip: client_ip.to_string(),
+            method: req.method().to_string(),
+            path: req.uri().path().to_string(),
+            user_agent: header(header::USER_AGENT),
+            referer: header(header::REFERER),
+            http_version: format!("{:?}", req.version()),
+            start_time: Instant::now(),
+            route_id: None,
+            custom_fields: None,
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> i32 {
+        self.start_time.elapsed().as_millis() as i32
+    }
+
+    /// Access log entry of this request (GeoIP looked up from the client IP)
+    pub fn entry(
+        &self,
+        state: &ProxyState,
+        target: Option<&str>,
+        status: i32,
+        response_time_ms: i32,
+        upstream_error: Option<&str>,
+    ) -> AccessLog {
+        // GeoIP lookup (non-blocking, memory-mapped read)
+        let geo = state
+            .geoip
+            .as_ref()
+            .and_then(|reader| reader.lookup(&self.client_ip))
+            .unwrap_or_default();
+
+        AccessLog {
+            id: None,
+            timestamp: Utc::now(),
+            ip: self.client_ip.clone(),
+            method: self.method.clone(),
+            path: self.path.clone(),
+            route_id: self.route_id,
+            target: target.map(str::to_string),
+            status,
+            response_time_ms,
+            request_size: None,
+            response_size: None,
+            user_agent: self.user_agent.clone(),
+            referer: self.referer.clone(),
+            country_code: geo.country_code,
+            country: geo.country,
+            city: geo.city,
+            latitude: geo.latitude,
+            longitude: geo.longitude,
+            upstream_error: upstream_error.map(str::to_string),
+            http_version: Some(self.http_version.clone()),
+            tls_version: None,
+            tls_cipher: None,
+            source: Some(AccessLog::SOURCE_LPG.to_string()),
+            custom_fields: self.custom_fields.clone(),
+            grpc_method: None,
+            grpc_status: None,
+            session_duration_ms: None,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Proxy router - Path matching and route selection
 
+use super::path::NormalizedPath;
 use crate::models::{ProxyRoute, ProxyRouteWithDdns};
 
 /// Proxy router with route matching
@@ -88,17 +89,16 @@ impl ProxyRouter {
     }
 
     /// Build the target URL for a matched route
-    pub fn build_target_url(&self, route: &ProxyRoute, request_path: &str) -> String {
+    ///
+    /// The prefix is stripped by whole segments of the upstream form, so an
+    /// encoded segment is never cut in the middle.
+    pub fn build_target_url(&self, route: &ProxyRoute, path: &NormalizedPath) -> String {
         let target = route.target.trim_end_matches('/');
 
-        if route.strip_prefix {
+        if route.strip_prefix && self.path_matches(&route.path, &path.matching) {
             // Remove the route path prefix from the request path
-            let route_path = route.path.trim_end_matches('/');
-            let stripped = if request_path.starts_with(route_path) {
-                &request_path[route_path.len()..]
-            } else {
-                request_path
-            };
+            let route_segments = route.path.split('/').filter(|s| !s.is_empty()).count();
+            let stripped = path.upstream_after_segments(route_segments);
 
             // Ensure there's a leading slash
             if stripped.is_empty() || !stripped.starts_with('/') {
@@ -108,10 +108,15 @@ impl ProxyRouter {
             }
         } else {
             // Forward the full path
-            format!("{}{}", target, request_path)
+            format!("{}{}", target, path.upstream)
         }
     }
 
+    /// Loaded routes in matching order
+    pub fn routes(&self) -> impl Iterator<Item = &ProxyRoute> {
+        self.routes.iter().map(|r| &r.route)
+    }
+
     /// Get route count
     pub fn len(&self) -> usize {
         self.routes.len()
@@ -126,22 +131,19 @@ impl ProxyRouter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use crate::proxy::path::{normalize_path, PathRejection};
+
+    fn normalized(raw: &str) -> NormalizedPath {
+        normalize_path(raw).unwrap()
+    }
 
     fn make_route(path: &str, target: &str, priority: i32, strip_prefix: bool) -> ProxyRoute {
         ProxyRoute {
-            id: 1,
             path: path.to_string(),
             target: target.to_string(),
-            ddns_config_id: None,
             priority,
-            active: true,
             strip_prefix,
-            preserve_host: false,
-            timeout_ms: 30000,
-            websocket_support: false,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            ..ProxyRoute::test_default()
         }
     }
 
@@ -153,18 +155,8 @@ mod tests {
     ) -> ProxyRouteWithDdns {
         ProxyRouteWithDdns {
             route: ProxyRoute {
-                id: 1,
-                path: path.to_string(),
-                target: target.to_string(),
                 ddns_config_id: ddns_hostname.map(|_| 1),
-                priority,
-                active: true,
-                strip_prefix: true,
-                preserve_host: false,
-                timeout_ms: 30000,
-                websocket_support: false,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
+                ..make_route(path, target, priority, true)
             },
             ddns_hostname: ddns_hostname.map(|s| s.to_string()),
         }
@@ -248,11 +240,11 @@ mod tests {
         let router = ProxyRouter::from_routes(vec![route.clone()]);
 
         assert_eq!(
-            router.build_target_url(&route, "/eatyui/api/test"),
+            router.build_target_url(&route, &normalized("/eatyui/api/test")),
             "http://localhost:3000/api/test"
         );
         assert_eq!(
-            router.build_target_url(&route, "/eatyui"),
+            router.build_target_url(&route, &normalized("/eatyui")),
             "http://localhost:3000/"
         );
     }
@@ -263,8 +255,32 @@ mod tests {
         let router = ProxyRouter::from_routes(vec![route.clone()]);
 
         assert_eq!(
-            router.build_target_url(&route, "/eatyui/api/test"),
+            router.build_target_url(&route, &normalized("/eatyui/api/test")),
             "http://localhost:3000/eatyui/api/test"
         );
     }
+
+    #[test]
+    fn test_build_target_url_strips_encoded_segments_whole() {
+        let route = make_route("/café", "http://localhost:3000", 10, true);
+        let router = ProxyRouter::from_routes(vec![route.clone()]);
+        let path = normalized("/caf%C3%A9/menu%20du%20jour");
+        assert_eq!(
+            router
+                .match_route(&path.matching, None)
+                .map(|r| r.path.as_str()),
+            Some("/café")
+        );
+        assert_eq!(
+            router.build_target_url(&route, &path),
+            "http://localhost:3000/menu%20du%20jour"
+        );
+
+        // An encoded slash cannot fake a boundary after a route prefix
+        // such as /files/a
+        assert_eq!(
+            normalize_path("/files/a%2Fb"),
+            Err(PathRejection::EncodedSeparator)
+        );
+    }
 }
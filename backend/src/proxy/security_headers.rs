@@ -0,0 +1,327 @@
+//! Security response headers
+//!
+//! A global policy (setting `security_headers_default`, JSON) merged with an
+//! optional per-route override (`proxy_routes.security_headers`, JSON) decides
+//! which headers are added to proxied responses. Headers the upstream already
+//! sent are kept unless the rule is forced; HSTS is only added on HTTPS-origin
+//! requests. Final response headers are sampled per route for the gap report.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+
+use crate::db::MySqlDb;
+use crate::error::AppError;
+use crate::models::{HeaderRule, RouteSecurityHeaders, SecurityHeadersPolicy};
+
+/// Settings key holding the global policy
+pub const SETTING_SECURITY_HEADERS: &str = "security_headers_default";
+
+const HSTS: &str = "strict-transport-security";
+
+/// Headers checked by the gap report
+pub const TRACKED_HEADERS: [&str; 5] = [
+    HSTS,
+    "x-content-type-options",
+    "x-frame-options",
+    "referrer-policy",
+    "content-security-policy",
+];
+
+/// Responses kept per route for the gap report
+const SAMPLES_PER_ROUTE: usize = 100;
+
+impl Default for SecurityHeadersPolicy {
+    fn default() -> Self {
+        let rule = |v: &str| HeaderRule {
+            value: Some(v.to_string()),
+            ..Default::default()
+        };
+        Self {
+            enabled: false,
+            headers: BTreeMap::from([
+                (
+                    HSTS.to_string(),
+                    rule("max-age=31536000; includeSubDomains"),
+                ),
+                ("x-content-type-options".to_string(), rule("nosniff")),
+                ("x-frame-options".to_string(), rule("SAMEORIGIN")),
+                (
+                    "referrer-policy".to_string(),
+                    rule("strict-origin-when-cross-origin"),
+                ),
+                (
+                    "content-security-policy".to_string(),
+                    rule("frame-ancestors 'self'"),
+                ),
+            ]),
+        }
+    }
+}
+
+impl SecurityHeadersPolicy {
+    /// Load the global policy; a missing or invalid setting falls back to the default
+    pub async fn load(mysql: &MySqlDb) -> Result<Self, AppError> {
+        let raw = mysql.get_setting(SETTING_SECURITY_HEADERS).await?;
+        Ok(
+            match raw.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+                Some(raw) => Self::parse(raw).unwrap_or_else(|e| {
+                    tracing::warn!(
+                        "Invalid {} setting, using default: {}",
+                        SETTING_SECURITY_HEADERS,
+                        e
+                    );
+                    Self::default()
+                }),
+                None => Self::default(),
+            },
+        )
+    }
+
+    /// Parse and validate a policy JSON document
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut policy: Self = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+        policy.headers = validate_rules(policy.headers)?;
+        Ok(policy)
+    }
+}
+
+impl RouteSecurityHeaders {
+    /// Validate an override; None when it carries nothing (stored as NULL)
+    pub fn normalized(self) -> Result<Option<Self>, String> {
+        let headers = validate_rules(self.headers)?;
+        if self.enabled.is_none() && headers.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            enabled: self.enabled,
+            headers,
+        }))
+    }
+
+    /// Stored column value (normalized JSON); an empty override is NULL
+    pub fn to_column(&self) -> Option<String> {
+        self.clone()
+            .normalized()
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::to_string(&v).ok())
+    }
+
+    /// Parse the stored column; invalid JSON is ignored with a warning
+    pub fn from_column(raw: Option<&str>) -> Option<Self> {
+        let raw = raw.map(str::trim).filter(|s| !s.is_empty())?;
+        match serde_json::from_str(raw) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid route security_headers: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Lower-case names and check names/values are valid HTTP headers
+fn validate_rules(
+    rules: BTreeMap<String, HeaderRule>,
+) -> Result<BTreeMap<String, HeaderRule>, String> {
+    rules
+        .into_iter()
+        .map(|(name, rule)| {
+            let name = name.trim().to_ascii_lowercase();
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("invalid header name: {}", name))?;
+            if let Some(value) = &rule.value {
+                HeaderValue::from_str(value).map_err(|_| format!("invalid value for {}", name))?;
+            }
+            Ok((name, rule))
+        })
+        .collect()
+}
+
+/// One header that will be injected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveHeader {
+    pub name: String,
+    pub value: String,
+    pub force: bool,
+}
+
+/// Global policy merged with a route override
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveSecurityHeaders {
+    pub enabled: bool,
+    /// "global" or "route" (where `enabled` came from)
+    pub source: &'static str,
+    pub headers: Vec<EffectiveHeader>,
+}
+
+impl EffectiveSecurityHeaders {
+    pub fn merge(global: &SecurityHeadersPolicy, route: Option<&RouteSecurityHeaders>) -> Self {
+        let mut rules = global.headers.clone();
+        for (name, over) in route.map(|r| &r.headers).into_iter().flatten() {
+            let rule = rules.entry(name.clone()).or_default();
+            if over.value.is_some() {
+                rule.value = over.value.clone();
+            }
+            rule.force = over.force;
+            rule.disabled = over.disabled;
+        }
+
+        let route_enabled = route.and_then(|r| r.enabled);
+        Self {
+            enabled: route_enabled.unwrap_or(global.enabled),
+            source: if route_enabled.is_some() {
+                "route"
+            } else {
+                "global"
+            },
+            headers: rules
+                .into_iter()
+                .filter(|(_, r)| !r.disabled)
+                .filter_map(|(name, r)| {
+                    r.value.map(|value| EffectiveHeader {
+                        name,
+                        value,
+                        force: r.force,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Add missing (or forced) headers to a response; HSTS only when `https`
+    pub fn apply(&self, headers: &mut HeaderMap, https: bool) {
+        if !self.enabled {
+            return;
+        }
+        for h in &self.headers {
+            if h.name == HSTS && !https {
+                continue;
+            }
+            let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(h.name.as_bytes()),
+                HeaderValue::from_str(&h.value),
+            ) else {
+                continue;
+            };
+            if h.force || !headers.contains_key(&name) {
+                headers.insert(name, value);
+            }
+        }
+    }
+}
+
+/// Presence of `TRACKED_HEADERS` on recent responses, per route (in-memory)
+#[derive(Default)]
+pub struct HeaderSamples {
+    routes: Mutex<HashMap<i32, VecDeque<u8>>>,
+}
+
+/// Gap report row for one route
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteHeaderSample {
+    pub samples: usize,
+    /// Header -> responses without it (only headers missing at least once)
+    pub missing: BTreeMap<String, usize>,
+}
+
+impl HeaderSamples {
+    /// Record which tracked headers a response carried
+    pub fn record(&self, route_id: i32, headers: &HeaderMap) {
+        let mask = TRACKED_HEADERS
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| headers.contains_key(**name))
+            .fold(0u8, |m, (i, _)| m | (1 << i));
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let ring = routes.entry(route_id).or_default();
+        if ring.len() == SAMPLES_PER_ROUTE {
+            ring.pop_front();
+        }
+        ring.push_back(mask);
+    }
+
+    pub fn for_route(&self, route_id: i32) -> RouteHeaderSample {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let ring = routes.get(&route_id);
+        let mut missing = BTreeMap::new();
+        for (i, name) in TRACKED_HEADERS.iter().enumerate() {
+            let count = ring
+                .map(|r| r.iter().filter(|m| *m & (1 << i) == 0).count())
+                .unwrap_or(0);
+            if count > 0 {
+                missing.insert(name.to_string(), count);
+            }
+        }
+        RouteHeaderSample {
+            samples: ring.map(|r| r.len()).unwrap_or(0),
+            missing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_override_merges_and_apply_respects_upstream() {
+        let global = SecurityHeadersPolicy::default();
+        let route: RouteSecurityHeaders = serde_json::from_str(
+            r#"{"enabled": true, "headers": {
+                "X-Frame-Options": {"value": "DENY", "force": true},
+                "content-security-policy": {"disabled": true}
+            }}"#,
+        )
+        .unwrap();
+        let route = route.normalized().unwrap().unwrap();
+        let effective = EffectiveSecurityHeaders::merge(&global, Some(&route));
+        assert!(effective.enabled);
+        assert_eq!(effective.source, "route");
+        assert!(!effective
+            .headers
+            .iter()
+            .any(|h| h.name == "content-security-policy"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-frame-options", HeaderValue::from_static("SAMEORIGIN"));
+        headers.insert("referrer-policy", HeaderValue::from_static("no-referrer"));
+        effective.apply(&mut headers, false);
+
+        // Forced rule replaces, unforced keeps the upstream value
+        assert_eq!(headers["x-frame-options"], "DENY");
+        assert_eq!(headers["referrer-policy"], "no-referrer");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        // No HSTS on plain HTTP
+        assert!(!headers.contains_key(HSTS));
+
+        effective.apply(&mut headers, true);
+        assert!(headers.contains_key(HSTS));
+
+        // Disabled globally and not overridden: nothing injected
+        let mut untouched = HeaderMap::new();
+        EffectiveSecurityHeaders::merge(&global, None).apply(&mut untouched, true);
+        assert!(untouched.is_empty());
+    }
+
+    #[test]
+    fn samples_count_missing_headers() {
+        let samples = HeaderSamples::default();
+        let mut headers = HeaderMap::new();
+        samples.record(1, &headers);
+        headers.insert(
+            "x-content-type-options",
+            HeaderValue::from_static("nosniff"),
+        );
+        samples.record(1, &headers);
+
+        let report = samples.for_route(1);
+        assert_eq!(report.samples, 2);
+        assert_eq!(report.missing["x-content-type-options"], 1);
+        assert_eq!(report.missing[HSTS], 2);
+        assert_eq!(samples.for_route(2).samples, 0);
+    }
+}
@@ -0,0 +1,516 @@
+//! Per-route store-and-forward queue
+//!
+//! `proxy_routes.store_forward` holds an opt-in policy as JSON; NULL keeps
+//! the route's normal behaviour. When a request matching the policy (method,
+//! optional path prefix, body size) finds the upstream unreachable or gets
+//! 502/503 back, its forwarded headers and body are written to the Mongo
+//! `forward_queue` collection and the client is answered 202 with the queue
+//! id. A leader-only replayer re-sends due items with exponential backoff
+//! until one is accepted (2xx/3xx), the upstream rejects it (4xx), or the
+//! policy's TTL passes; every attempt is recorded on the item.
+//!
+//! Timeouts are never queued: the upstream may already have processed the
+//! request, and a replay would deliver it twice. Consumers must still be
+//! idempotent, since a 502 from an intermediate hop does not prove the
+//! request went unprocessed.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::interval;
+
+use super::methods::normalize_allowed_methods;
+use crate::db::AppState;
+use crate::models::ProxyRoute;
+
+/// Hard cap on a queued body, whatever the policy says
+pub const MAX_BODY_BYTES_CAP: usize = 1024 * 1024;
+/// Longest a request may wait in the queue
+pub const MAX_TTL_SECS: u64 = 7 * 86400;
+const MIN_TTL_SECS: u64 = 60;
+/// Attempts kept on an item (older ones are dropped)
+pub const ATTEMPT_HISTORY: i32 = 20;
+/// Delivered items are kept this long for inspection
+const DELIVERED_RETENTION_DAYS: i64 = 7;
+
+const BACKOFF_BASE_SECS: i64 = 10;
+const BACKOFF_MAX_SECS: i64 = 3600;
+/// How often the replayer looks for due items
+const REPLAY_INTERVAL: Duration = Duration::from_secs(5);
+/// Items replayed per tick
+const REPLAY_BATCH: usize = 20;
+/// Claim held on an item while it is being replayed
+const LEASE_SECS: i64 = 120;
+
+/// Headers never shown in the queue listing
+const MASKED_HEADERS: [&str; 4] = ["authorization", "cookie", "x-api-key", "x-auth-token"];
+
+fn default_methods() -> Vec<String> {
+    vec!["POST".to_string()]
+}
+
+fn default_max_body_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_ttl_secs() -> u64 {
+    86400
+}
+
+/// Store-and-forward policy of a route
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteStoreForward {
+    /// Methods that may be queued (default POST)
+    #[serde(default = "default_methods")]
+    pub methods: Vec<String>,
+    /// Only requests under this path prefix (matched against the request path)
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    /// Larger bodies are never queued (default 64 KiB, at most 1 MiB)
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Give up on an item this long after it was queued (default 1 day)
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl RouteStoreForward {
+    /// Validate and normalize (methods upper-cased, prefix trimmed)
+    pub fn normalize(&self) -> Result<Self, String> {
+        let methods =
+            normalize_allowed_methods(&self.methods).map_err(|e| format!("methods: {}", e))?;
+        let path_prefix = match self.path_prefix.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(p) if p.starts_with('/') => Some(p.to_string()),
+            Some(p) => return Err(format!("path_prefix must start with '/': {:?}", p)),
+        };
+        if self.max_body_bytes == 0 || self.max_body_bytes > MAX_BODY_BYTES_CAP {
+            return Err(format!(
+                "max_body_bytes must be between 1 and {}",
+                MAX_BODY_BYTES_CAP
+            ));
+        }
+        if !(MIN_TTL_SECS..=MAX_TTL_SECS).contains(&self.ttl_secs) {
+            return Err(format!(
+                "ttl_secs must be between {} and {}",
+                MIN_TTL_SECS, MAX_TTL_SECS
+            ));
+        }
+        Ok(Self {
+            methods,
+            path_prefix,
+            max_body_bytes: self.max_body_bytes,
+            ttl_secs: self.ttl_secs,
+        })
+    }
+
+    /// Stored column value (None when the policy is invalid)
+    pub fn to_column(&self) -> Option<String> {
+        self.normalize()
+            .ok()
+            .and_then(|p| serde_json::to_string(&p).ok())
+    }
+
+    /// Whether a request may be queued under this policy
+    pub fn matches(&self, method: &str, path: &str, body_len: usize) -> bool {
+        self.methods.iter().any(|m| m == method)
+            && body_len <= self.max_body_bytes.min(MAX_BODY_BYTES_CAP)
+            && self
+                .path_prefix
+                .as_deref()
+                .is_none_or(|prefix| path.starts_with(prefix))
+    }
+}
+
+impl ProxyRoute {
+    /// Store-and-forward policy; None when off (or the column is invalid)
+    pub fn store_forward(&self) -> Option<RouteStoreForward> {
+        let raw = self
+            .store_forward
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        match serde_json::from_str::<RouteStoreForward>(raw)
+            .and_then(|p| p.normalize().map_err(serde::de::Error::custom))
+        {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid store_forward on route {}: {}", self.id, e);
+                None
+            }
+        }
+    }
+}
+
+/// Why a send result should be queued; None when it must not be
+pub fn queue_reason(result: &Result<reqwest::Response, reqwest::Error>) -> Option<String> {
+    match result {
+        Err(e) if e.is_connect() => Some(format!("upstream unreachable: {}", e)),
+        Ok(resp) if matches!(resp.status().as_u16(), 502 | 503) => {
+            Some(format!("upstream answered {}", resp.status().as_u16()))
+        }
+        _ => None,
+    }
+}
+
+/// Delay before the next attempt after `attempts` failed ones
+pub fn backoff(attempts: u32) -> chrono::Duration {
+    let secs = BACKOFF_BASE_SECS
+        .saturating_mul(1_i64 << attempts.min(20))
+        .min(BACKOFF_MAX_SECS);
+    chrono::Duration::seconds(secs)
+}
+
+/// Stored time format (UTC, milliseconds) so stored values compare as strings
+pub fn stamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// One delivery attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardAttempt {
+    pub at: String,
+    /// Upstream status, None when no response was received
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl ForwardAttempt {
+    fn delivered(&self) -> bool {
+        self.status.is_some_and(|s| (200..400).contains(&s))
+    }
+
+    /// The upstream answered and refused the request; retrying will not help
+    fn rejected(&self) -> bool {
+        self.status.is_some_and(|s| (400..500).contains(&s))
+    }
+}
+
+/// Queued request (collection `forward_queue`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardQueueItem {
+    pub queue_id: String,
+    pub route_id: i32,
+    pub method: String,
+    /// Upstream URL resolved when the request was queued (query included)
+    pub url: String,
+    /// Request path as received (for display)
+    pub path: String,
+    /// Headers as forwarded upstream
+    pub headers: Vec<(String, String)>,
+    /// Request body, base64; emptied once delivered
+    #[serde(default)]
+    pub body_b64: String,
+    pub body_size: usize,
+    pub timeout_ms: u64,
+    pub client_ip: String,
+    /// Why the request was queued
+    pub reason: String,
+    /// "pending" | "delivered" | "failed"
+    pub status: String,
+    pub attempt_count: u32,
+    #[serde(default)]
+    pub attempts: Vec<ForwardAttempt>,
+    pub next_attempt_at: String,
+    pub expires_at: String,
+    #[serde(default)]
+    pub lease_until: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl ForwardQueueItem {
+    /// New pending item, due immediately after the first backoff step
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        route: &ProxyRoute,
+        policy: &RouteStoreForward,
+        method: &str,
+        url: &str,
+        path: &str,
+        headers: Vec<(String, String)>,
+        body: &[u8],
+        client_ip: &str,
+        reason: String,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            queue_id: uuid::Uuid::new_v4().to_string(),
+            route_id: route.id,
+            method: method.to_string(),
+            url: url.to_string(),
+            path: path.to_string(),
+            headers,
+            body_b64: base64::engine::general_purpose::STANDARD.encode(body),
+            body_size: body.len(),
+            timeout_ms: route.timeout_ms.max(1) as u64,
+            client_ip: client_ip.to_string(),
+            reason,
+            status: "pending".to_string(),
+            attempt_count: 0,
+            attempts: Vec::new(),
+            next_attempt_at: stamp(now + backoff(0)),
+            expires_at: stamp(now + chrono::Duration::seconds(policy.ttl_secs as i64)),
+            lease_until: None,
+            created_at: stamp(now),
+            updated_at: stamp(now),
+        }
+    }
+
+    /// Listing copy: no body, credentials masked
+    pub fn redacted(mut self) -> Self {
+        self.body_b64 = String::new();
+        for (name, value) in self.headers.iter_mut() {
+            if MASKED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                *value = "***".to_string();
+            }
+        }
+        self
+    }
+
+    /// Status and next attempt time after `attempt`
+    pub fn outcome(&self, attempt: &ForwardAttempt, now: DateTime<Utc>) -> (&'static str, String) {
+        if attempt.delivered() {
+            return ("delivered", self.next_attempt_at.clone());
+        }
+        let next = now + backoff(self.attempt_count + 1);
+        if attempt.rejected() || self.status == "failed" || stamp(next) >= self.expires_at {
+            ("failed", self.next_attempt_at.clone())
+        } else {
+            ("pending", stamp(next))
+        }
+    }
+}
+
+/// Send a queued request once
+pub async fn deliver(client: &reqwest::Client, item: &ForwardQueueItem) -> ForwardAttempt {
+    let started = std::time::Instant::now();
+    let at = stamp(Utc::now());
+
+    let body = match base64::engine::general_purpose::STANDARD.decode(&item.body_b64) {
+        Ok(body) => body,
+        Err(e) => {
+            return ForwardAttempt {
+                at,
+                status: None,
+                error: Some(format!("stored body is unreadable: {}", e)),
+                duration_ms: 0,
+            }
+        }
+    };
+    let method =
+        reqwest::Method::from_bytes(item.method.as_bytes()).unwrap_or(reqwest::Method::POST);
+    let mut request = client
+        .request(method, &item.url)
+        .timeout(Duration::from_millis(item.timeout_ms));
+    for (name, value) in &item.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    if !body.is_empty() {
+        request = request.body(body);
+    }
+
+    let (status, error) = match request.send().await {
+        Ok(resp) => (Some(resp.status().as_u16()), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    ForwardAttempt {
+        at,
+        status,
+        error,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Deliver a claimed item and record the attempt; returns the new status
+pub async fn replay(
+    app_state: &AppState,
+    client: &reqwest::Client,
+    item: &ForwardQueueItem,
+) -> Result<&'static str, String> {
+    let attempt = deliver(client, item).await;
+    let (status, next_attempt_at) = item.outcome(&attempt, Utc::now());
+    if status != "pending" {
+        tracing::info!(
+            "Queued request {} on route {} is {} after {} attempts",
+            item.queue_id,
+            item.route_id,
+            status,
+            item.attempt_count + 1
+        );
+    }
+    app_state
+        .mongo
+        .record_forward_attempt(&item.queue_id, &attempt, status, &next_attempt_at)
+        .await?;
+    Ok(status)
+}
+
+/// Leader-only replayer for the forward queue
+pub struct ForwardReplayer {
+    app_state: AppState,
+    http_client: reqwest::Client,
+}
+
+impl ForwardReplayer {
+    pub fn new(app_state: AppState, http_client: reqwest::Client) -> Self {
+        Self {
+            app_state,
+            http_client,
+        }
+    }
+
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Store-and-forward replayer started");
+        let mut timer = interval(REPLAY_INTERVAL);
+        let mut ticks: u64 = 0;
+        loop {
+            timer.tick().await;
+            if let Err(e) = self.run_once().await {
+                tracing::warn!("Store-and-forward replay failed: {}", e);
+            }
+            // Hourly housekeeping
+            ticks += 1;
+            if ticks.is_multiple_of(720) {
+                let before = stamp(Utc::now() - chrono::Duration::days(DELIVERED_RETENTION_DAYS));
+                if let Err(e) = self
+                    .app_state
+                    .mongo
+                    .purge_delivered_forward_items(&before)
+                    .await
+                {
+                    tracing::warn!("Forward queue purge failed: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<(), String> {
+        let mongo = &self.app_state.mongo;
+        let now = Utc::now();
+        let expired = mongo.expire_forward_items(&stamp(now)).await?;
+        if expired > 0 {
+            tracing::warn!("{} queued requests expired undelivered", expired);
+        }
+
+        let lease_until = stamp(now + chrono::Duration::seconds(LEASE_SECS));
+        for _ in 0..REPLAY_BATCH {
+            let Some(item) = mongo
+                .claim_due_forward_item(&stamp(Utc::now()), &lease_until)
+                .await?
+            else {
+                break;
+            };
+            replay(&self.app_state, &self.http_client, &item).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lease to hold while replaying by hand
+pub fn manual_lease(now: DateTime<Utc>) -> String {
+    stamp(now + chrono::Duration::seconds(LEASE_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(json: serde_json::Value) -> Result<RouteStoreForward, String> {
+        serde_json::from_value::<RouteStoreForward>(json)
+            .map_err(|e| e.to_string())?
+            .normalize()
+    }
+
+    #[test]
+    fn policy_defaults_and_limits() {
+        let p = policy(serde_json::json!({})).unwrap();
+        assert_eq!(p.methods, vec!["POST"]);
+        assert_eq!(p.max_body_bytes, 64 * 1024);
+        assert_eq!(p.ttl_secs, 86400);
+
+        let p =
+            policy(serde_json::json!({ "methods": ["post", "put"], "path_prefix": " /hooks " }))
+                .unwrap();
+        assert_eq!(p.methods, vec!["POST", "PUT"]);
+        assert_eq!(p.path_prefix.as_deref(), Some("/hooks"));
+
+        assert!(policy(serde_json::json!({ "max_body_bytes": MAX_BODY_BYTES_CAP + 1 })).is_err());
+        assert!(policy(serde_json::json!({ "max_body_bytes": 0 })).is_err());
+        assert!(policy(serde_json::json!({ "ttl_secs": MAX_TTL_SECS + 1 })).is_err());
+        assert!(policy(serde_json::json!({ "path_prefix": "hooks" })).is_err());
+        assert!(policy(serde_json::json!({ "methods": [] })).is_err());
+    }
+
+    #[test]
+    fn matches_method_prefix_and_size() {
+        let p =
+            policy(serde_json::json!({ "path_prefix": "/hooks", "max_body_bytes": 100 })).unwrap();
+        assert!(p.matches("POST", "/hooks/github", 100));
+        assert!(!p.matches("GET", "/hooks/github", 0));
+        assert!(!p.matches("POST", "/api/hooks", 0));
+        assert!(!p.matches("POST", "/hooks/github", 101));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(backoff(0).num_seconds(), 10);
+        assert_eq!(backoff(1).num_seconds(), 20);
+        assert_eq!(backoff(3).num_seconds(), 80);
+        assert_eq!(backoff(9).num_seconds(), 3600);
+        assert_eq!(backoff(u32::MAX).num_seconds(), 3600);
+    }
+
+    #[test]
+    fn outcome_follows_attempt_result_and_ttl() {
+        let now = DateTime::parse_from_rfc3339("2026-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let route = ProxyRoute {
+            path: "/hooks".to_string(),
+            target: "http://127.0.0.1:8080".to_string(),
+            ..ProxyRoute::test_default()
+        };
+        let p = policy(serde_json::json!({ "ttl_secs": 300 })).unwrap();
+        let item = ForwardQueueItem::new(
+            &route,
+            &p,
+            "POST",
+            "http://127.0.0.1:8080/github",
+            "/hooks/github",
+            vec![("Authorization".to_string(), "Bearer x".to_string())],
+            b"{}",
+            "192.0.2.1",
+            "upstream answered 503".to_string(),
+            now,
+        );
+        let attempt = |status: Option<u16>| ForwardAttempt {
+            at: stamp(now),
+            status,
+            error: None,
+            duration_ms: 1,
+        };
+
+        assert_eq!(item.outcome(&attempt(Some(204)), now).0, "delivered");
+        assert_eq!(item.outcome(&attempt(Some(422)), now).0, "failed");
+        let (status, next) = item.outcome(&attempt(Some(503)), now);
+        assert_eq!(
+            (status, next.as_str()),
+            ("pending", "2026-06-01T12:00:20.000Z")
+        );
+        // The next attempt would land past the TTL
+        assert_eq!(
+            item.outcome(&attempt(None), now + chrono::Duration::seconds(290))
+                .0,
+            "failed"
+        );
+
+        let listed = item.redacted();
+        assert!(listed.body_b64.is_empty());
+        assert_eq!(listed.headers[0].1, "***");
+    }
+}
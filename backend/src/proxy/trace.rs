@@ -0,0 +1,586 @@
+//! Per-route request tracing
+//!
+//! While tracing is enabled for a route (with an automatic expiry), every
+//! proxied request to it records a per-phase timing breakdown into a bounded
+//! in-memory ring. When no route is traced the proxy pays one atomic load
+//! per request.
+//!
+//! Phases: blocked-IP check, route matching, request body read, upstream DNS,
+//! connect, time to first byte and response body. DNS and connect are taken
+//! from the proxy clients' own connection setup (`TracingResolver`,
+//! `TraceConnectLayer`) and only occur on new pooled connections. reqwest
+//! runs the TCP connect and the TLS handshake as one connector step, so
+//! `connect` covers both.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::Serialize;
+use tower::{Layer, Service};
+
+/// Traces kept per route
+pub const TRACE_CAPACITY: usize = 1000;
+
+/// Default and maximum trace window
+pub const DEFAULT_TRACE_SECS: u64 = 600;
+pub const MAX_TRACE_SECS: u64 = 24 * 3600;
+
+/// Timing breakdown of one request (microseconds)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PhaseTimings {
+    pub blocklist_us: u64,
+    pub route_match_us: u64,
+    pub request_body_us: u64,
+    /// None unless a new connection looked up the upstream host
+    pub dns_us: Option<u64>,
+    /// TCP connect and TLS handshake; None on a pooled connection
+    pub connect_us: Option<u64>,
+    /// Send → response headers, less DNS and connect
+    pub ttfb_us: u64,
+    pub response_body_us: u64,
+    pub total_us: u64,
+}
+
+impl PhaseTimings {
+    fn phases(&self) -> [(&'static str, Option<u64>); 8] {
+        [
+            ("blocklist", Some(self.blocklist_us)),
+            ("route_match", Some(self.route_match_us)),
+            ("request_body", Some(self.request_body_us)),
+            ("dns", self.dns_us),
+            ("connect", self.connect_us),
+            ("ttfb", Some(self.ttfb_us)),
+            ("response_body", Some(self.response_body_us)),
+            ("total", Some(self.total_us)),
+        ]
+    }
+}
+
+/// One traced request
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceRecord {
+    pub request_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub error: Option<String>,
+    pub phases: PhaseTimings,
+}
+
+/// Percentiles for one phase
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PhaseStats {
+    pub count: usize,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+/// GET /api/routes/:id/traces payload
+#[derive(Debug, Serialize)]
+pub struct TraceSummary {
+    pub route_id: i32,
+    pub enabled: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub sample_count: usize,
+    pub capacity: usize,
+    pub phases: BTreeMap<&'static str, PhaseStats>,
+    pub slowest: Vec<TraceRecord>,
+}
+
+/// In-flight timing for a traced request
+pub struct TraceTimer {
+    trace: Arc<RouteTrace>,
+    pub request_id: String,
+    start: Instant,
+    last: Instant,
+    phases: PhaseTimings,
+    connection: Arc<ConnectionTimings>,
+}
+
+/// Phases measured by the proxy handler
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Blocklist,
+    RouteMatch,
+    RequestBody,
+    Ttfb,
+    ResponseBody,
+}
+
+impl TraceTimer {
+    /// Attribute the time since the previous mark to `phase`
+    pub fn mark(&mut self, phase: Phase) {
+        self.mark_at(phase, Instant::now());
+    }
+
+    fn mark_at(&mut self, phase: Phase, now: Instant) {
+        let us = now.saturating_duration_since(self.last).as_micros() as u64;
+        self.last = now;
+        match phase {
+            Phase::Blocklist => self.phases.blocklist_us = us,
+            Phase::RouteMatch => self.phases.route_match_us = us,
+            Phase::RequestBody => self.phases.request_body_us = us,
+            Phase::Ttfb => {
+                let timings = *self.connection.timings.lock().unwrap();
+                self.phases.dns_us = timings.dns_us;
+                self.phases.connect_us = timings.connect_us;
+                self.phases.ttfb_us = us
+                    .saturating_sub(timings.dns_us.unwrap_or(0))
+                    .saturating_sub(timings.connect_us.unwrap_or(0));
+            }
+            Phase::ResponseBody => self.phases.response_body_us = us,
+        }
+    }
+
+    /// Store the finished trace
+    pub fn finish(mut self, method: &str, path: &str, status: u16, error: Option<String>) {
+        self.phases.total_us = self.start.elapsed().as_micros() as u64;
+        self.trace.push(TraceRecord {
+            request_id: self.request_id,
+            timestamp: Utc::now(),
+            method: method.to_string(),
+            path: path.to_string(),
+            status,
+            error,
+            phases: self.phases,
+        });
+    }
+}
+
+/// Send an upstream request; a traced one collects the DNS and connect
+/// time of any connection it opens
+pub async fn send<F: Future>(timer: Option<&TraceTimer>, request: F) -> F::Output {
+    match timer {
+        Some(timer) => CONNECTION.scope(timer.connection.clone(), request).await,
+        None => request.await,
+    }
+}
+
+tokio::task_local! {
+    /// Connection setup of the traced request being sent
+    static CONNECTION: Arc<ConnectionTimings>;
+}
+
+/// DNS and connect time of a traced request, summed over its attempts
+#[derive(Default)]
+struct ConnectionTimings {
+    timings: Mutex<ConnectionPhases>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ConnectionPhases {
+    dns_us: Option<u64>,
+    connect_us: Option<u64>,
+}
+
+impl ConnectionTimings {
+    fn dns_us(&self) -> u64 {
+        self.timings.lock().unwrap().dns_us.unwrap_or(0)
+    }
+
+    fn add_dns(&self, took: Duration) {
+        let mut timings = self.timings.lock().unwrap();
+        timings.dns_us = Some(timings.dns_us.unwrap_or(0) + took.as_micros() as u64);
+    }
+
+    /// `took`: the whole connector call, `dns_before`: DNS time recorded
+    /// before it started (its own lookup is not connect time)
+    fn add_connect(&self, took: Duration, dns_before: u64) {
+        let mut timings = self.timings.lock().unwrap();
+        let lookup = timings.dns_us.unwrap_or(0).saturating_sub(dns_before);
+        let connect = (took.as_micros() as u64).saturating_sub(lookup);
+        timings.connect_us = Some(timings.connect_us.unwrap_or(0) + connect);
+    }
+}
+
+/// Resolver of the proxy clients: the system lookup, timed for traced
+/// requests
+#[derive(Debug, Default)]
+pub struct TracingResolver;
+
+impl Resolve for TracingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let timings = CONNECTION.try_with(Arc::clone).ok();
+            let started = Instant::now();
+            let resolved = tokio::net::lookup_host((name.as_str(), 0))
+                .await
+                .map(|addrs| addrs.collect::<Vec<_>>());
+            if let Some(timings) = timings {
+                timings.add_dns(started.elapsed());
+            }
+            let addrs: Addrs = Box::new(resolved?.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Connector layer of the proxy clients timing connection setup for traced
+/// requests
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceConnectLayer;
+
+impl<S> Layer<S> for TraceConnectLayer {
+    type Service = TraceConnect<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceConnect { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceConnect<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for TraceConnect<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: R) -> Self::Future {
+        let connecting = self.inner.call(dst);
+        Box::pin(async move {
+            // Connections finished in the background after the request
+            // found a pooled one are not on its path
+            let Ok(timings) = CONNECTION.try_with(Arc::clone) else {
+                return connecting.await;
+            };
+            let dns_before = timings.dns_us();
+            let started = Instant::now();
+            let connected = connecting.await;
+            timings.add_connect(started.elapsed(), dns_before);
+            connected
+        })
+    }
+}
+
+/// Trace state of one route
+#[derive(Default)]
+struct RouteTrace {
+    /// Unix millis until which tracing is on (0 = off)
+    enabled_until: AtomicI64,
+    records: Mutex<VecDeque<TraceRecord>>,
+}
+
+impl RouteTrace {
+    fn is_enabled(&self, now_ms: i64) -> bool {
+        self.enabled_until.load(Ordering::Relaxed) > now_ms
+    }
+
+    fn push(&self, record: TraceRecord) {
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= TRACE_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+/// Registry of traced routes, shared through `ProxyState`
+#[derive(Default)]
+pub struct RouteTracer {
+    /// Fast-path gate: true while any route may be traced
+    any_active: AtomicBool,
+    /// Latest expiry across routes (unix millis)
+    latest_until: AtomicI64,
+    routes: RwLock<HashMap<i32, Arc<RouteTrace>>>,
+}
+
+impl RouteTracer {
+    /// Enable tracing for a route for `duration`; clears earlier traces
+    pub fn enable(&self, route_id: i32, duration: Duration) -> DateTime<Utc> {
+        let until = Utc::now().timestamp_millis() + duration.as_millis() as i64;
+        let trace = self
+            .routes
+            .write()
+            .unwrap()
+            .entry(route_id)
+            .or_default()
+            .clone();
+        trace.records.lock().unwrap().clear();
+        trace.enabled_until.store(until, Ordering::Relaxed);
+        self.latest_until.fetch_max(until, Ordering::Relaxed);
+        self.any_active.store(true, Ordering::Relaxed);
+        Utc.timestamp_millis_opt(until)
+            .single()
+            .unwrap_or_else(Utc::now)
+    }
+
+    /// Disable tracing for a route (collected traces are kept)
+    pub fn disable(&self, route_id: i32) {
+        if let Some(trace) = self.routes.read().unwrap().get(&route_id) {
+            trace.enabled_until.store(0, Ordering::Relaxed);
+        }
+        self.refresh();
+    }
+
+    /// Recompute the fast-path gate from per-route expiries
+    fn refresh(&self) {
+        let now = Utc::now().timestamp_millis();
+        let latest = self
+            .routes
+            .read()
+            .unwrap()
+            .values()
+            .map(|t| t.enabled_until.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or(0);
+        self.latest_until.store(latest, Ordering::Relaxed);
+        self.any_active.store(latest > now, Ordering::Relaxed);
+    }
+
+    /// Checked once per request; a single atomic load unless some route is
+    /// being traced.
+    pub fn is_active(&self) -> bool {
+        if !self.any_active.load(Ordering::Relaxed) {
+            return false;
+        }
+        if self.latest_until.load(Ordering::Relaxed) <= Utc::now().timestamp_millis() {
+            self.refresh();
+            return false;
+        }
+        true
+    }
+
+    /// Start a timer if the matched route is traced. `blocklist_done` is when
+    /// the blocked-IP check finished; route matching is measured up to now.
+    pub fn attach(
+        &self,
+        start: Instant,
+        blocklist_done: Instant,
+        route_id: i32,
+        request_id: Option<&str>,
+    ) -> Option<TraceTimer> {
+        let trace = self.routes.read().unwrap().get(&route_id)?.clone();
+        if !trace.is_enabled(Utc::now().timestamp_millis()) {
+            return None;
+        }
+        let mut timer = TraceTimer {
+            trace,
+            request_id: request_id
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            start,
+            last: start,
+            phases: PhaseTimings::default(),
+            connection: Arc::default(),
+        };
+        timer.mark_at(Phase::Blocklist, blocklist_done);
+        timer.mark(Phase::RouteMatch);
+        Some(timer)
+    }
+
+    /// Percentiles per phase plus the slowest traces
+    pub fn summary(&self, route_id: i32, slowest: usize) -> TraceSummary {
+        let trace = self.routes.read().unwrap().get(&route_id).cloned();
+        let Some(trace) = trace else {
+            return TraceSummary {
+                route_id,
+                enabled: false,
+                expires_at: None,
+                sample_count: 0,
+                capacity: TRACE_CAPACITY,
+                phases: BTreeMap::new(),
+                slowest: Vec::new(),
+            };
+        };
+
+        let until = trace.enabled_until.load(Ordering::Relaxed);
+        let enabled = trace.is_enabled(Utc::now().timestamp_millis());
+        let records: Vec<TraceRecord> = trace.records.lock().unwrap().iter().cloned().collect();
+
+        let mut slow = records.clone();
+        slow.sort_by_key(|r| std::cmp::Reverse(r.phases.total_us));
+        slow.truncate(slowest);
+
+        TraceSummary {
+            route_id,
+            enabled,
+            expires_at: if enabled {
+                Utc.timestamp_millis_opt(until).single()
+            } else {
+                None
+            },
+            sample_count: records.len(),
+            capacity: TRACE_CAPACITY,
+            phases: phase_stats(&records),
+            slowest: slow,
+        }
+    }
+}
+
+fn phase_stats(records: &[TraceRecord]) -> BTreeMap<&'static str, PhaseStats> {
+    let mut values: BTreeMap<&'static str, Vec<u64>> = BTreeMap::new();
+    for record in records {
+        for (name, value) in record.phases.phases() {
+            if let Some(v) = value {
+                values.entry(name).or_default().push(v);
+            }
+        }
+    }
+    values
+        .into_iter()
+        .map(|(name, mut v)| {
+            v.sort_unstable();
+            let stats = PhaseStats {
+                count: v.len(),
+                p50_us: percentile(&v, 50.0),
+                p95_us: percentile(&v, 95.0),
+                p99_us: percentile(&v, 99.0),
+                max_us: *v.last().unwrap_or(&0),
+            };
+            (name, stats)
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of a sorted slice
+pub(crate) fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(total_us: u64, dns_us: Option<u64>) -> TraceRecord {
+        TraceRecord {
+            request_id: format!("req-{}", total_us),
+            timestamp: Utc::now(),
+            method: "GET".to_string(),
+            path: "/app".to_string(),
+            status: 200,
+            error: None,
+            phases: PhaseTimings {
+                dns_us,
+                ttfb_us: total_us / 2,
+                total_us,
+                ..PhaseTimings::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let v: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&v, 50.0), 50);
+        assert_eq!(percentile(&v, 95.0), 95);
+        assert_eq!(percentile(&v, 99.0), 99);
+        assert_eq!(percentile(&[7], 99.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_gate_closed_when_nothing_traced() {
+        let tracer = RouteTracer::default();
+        assert!(!tracer.is_active());
+
+        tracer.enable(3, Duration::from_secs(60));
+        assert!(tracer.is_active());
+        let start = Instant::now();
+        assert!(tracer.attach(start, start, 4, None).is_none());
+        assert!(tracer.attach(start, start, 3, Some("abc")).is_some());
+
+        tracer.disable(3);
+        assert!(!tracer.is_active());
+    }
+
+    #[test]
+    fn test_expired_trace_closes_gate() {
+        let tracer = RouteTracer::default();
+        tracer.enable(1, Duration::ZERO);
+        assert!(!tracer.is_active());
+        assert!(!tracer.any_active.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_summary_and_bounded_ring() {
+        let tracer = RouteTracer::default();
+        tracer.enable(1, Duration::from_secs(60));
+        let trace = tracer.routes.read().unwrap().get(&1).unwrap().clone();
+        for i in 1..=(TRACE_CAPACITY as u64 + 10) {
+            trace.push(record(i, (i % 2 == 0).then_some(5)));
+        }
+
+        let summary = tracer.summary(1, 3);
+        assert!(summary.enabled);
+        assert_eq!(summary.sample_count, TRACE_CAPACITY);
+        assert_eq!(summary.slowest.len(), 3);
+        assert_eq!(
+            summary.slowest[0].phases.total_us,
+            TRACE_CAPACITY as u64 + 10
+        );
+        assert_eq!(summary.phases["total"].max_us, TRACE_CAPACITY as u64 + 10);
+        assert_eq!(summary.phases["dns"].count, TRACE_CAPACITY / 2);
+    }
+
+    #[test]
+    fn test_timer_finish_records_trace() {
+        let tracer = RouteTracer::default();
+        tracer.enable(9, Duration::from_secs(60));
+        let start = Instant::now();
+        let mut timer = tracer.attach(start, start, 9, Some("req-1")).unwrap();
+        timer.mark(Phase::Ttfb);
+        timer.finish("POST", "/x", 502, Some("boom".to_string()));
+
+        let summary = tracer.summary(9, 10);
+        assert_eq!(summary.sample_count, 1);
+        assert_eq!(summary.slowest[0].request_id, "req-1");
+        assert_eq!(summary.slowest[0].status, 502);
+    }
+
+    #[tokio::test]
+    async fn test_connection_setup_timed_from_the_client() {
+        let upstream = crate::testing::MockUpstream::start().await;
+        let url = upstream.url().replace("127.0.0.1", "localhost");
+        let client = super::super::upstream::build_client(1000, 1).unwrap();
+        let tracer = RouteTracer::default();
+        tracer.enable(5, Duration::from_secs(60));
+
+        let traced = |request_id: &'static str| {
+            let start = Instant::now();
+            let mut timer = tracer.attach(start, start, 5, Some(request_id)).unwrap();
+            let request = client.get(&url).send();
+            async move {
+                let res = send(Some(&timer), request).await.unwrap();
+                assert_eq!(res.status(), 200);
+                timer.mark(Phase::Ttfb);
+                timer.phases
+            }
+        };
+
+        // A new connection looks up the host and connects
+        let first = traced("req-1").await;
+        assert!(first.dns_us.is_some());
+        assert!(first.connect_us.is_some());
+        // The pooled connection is reused
+        let second = traced("req-2").await;
+        assert_eq!((second.dns_us, second.connect_us), (None, None));
+        // Untraced requests use the same client without timings
+        let res = send(None, client.get(&url).send()).await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+}
@@ -0,0 +1,811 @@
+//! Per-route request/response transformation scripts
+//!
+//! `proxy_routes.transform` holds an opt-in policy as JSON: a rhai script,
+//! whether it is live (`enabled`; a disabled script is validated and stored
+//! but never run), what to do when it fails (`on_error`: `open` forwards the
+//! message untransformed, `closed` answers 502), a per-invocation time limit
+//! and how much of the body the script sees.
+//!
+//! The script defines `fn on_request()` and/or `fn on_response()`; only those
+//! functions run, never top-level statements. Each gets the message as `this`:
+//!
+//! - `this.headers`: map of lower-case name to value (set `()` to remove)
+//! - `this.body`: the first `body_kb` KiB as a string; assigning it replaces
+//!   that part and keeps the rest (`body_truncated` tells whether there is a
+//!   rest). Binary bodies (`body_binary`) are read-only and empty here.
+//! - `this.query` (request, writable), `this.status` (response), `method`,
+//!   `path`, `client_ip`, and `route` (`id`, `path`, `team`)
+//!
+//! Helpers: `sha256_hex(s)`, `hmac_sha256_hex(key, s)`, plus rhai's own
+//! `parse_json` and `to_json`. Scripts have no filesystem, network, module
+//! or `eval` access; `print`/`debug` are discarded and `sleep` fails. A run
+//! past its time limit is aborted. Scripts run on the blocking thread pool,
+//! at most `MAX_CONCURRENT_SCRIPTS` at a time; a run that cannot start
+//! within its time limit counts as timed out. Scripts apply to plain HTTP
+//! requests, not WebSocket tunnels.
+
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, INT};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+
+use crate::models::ProxyRoute;
+
+/// Longest stored script
+pub const MAX_SCRIPT_BYTES: usize = 16 * 1024;
+/// Longest allowed per-invocation time limit
+pub const MAX_TIMEOUT_MS: u64 = 1000;
+/// Most body a script may see
+pub const MAX_BODY_KB: usize = 1024;
+/// Operations between deadline checks
+const DEADLINE_CHECK_EVERY: u64 = 256;
+/// Scripts running at once across all routes
+const MAX_CONCURRENT_SCRIPTS: usize = 8;
+
+fn default_timeout_ms() -> u64 {
+    50
+}
+
+fn default_body_kb() -> usize {
+    64
+}
+
+/// What happens to a message whose script fails or times out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailMode {
+    /// Forward the message untransformed
+    #[default]
+    Open,
+    /// Answer 502
+    Closed,
+}
+
+/// Transformation policy of a route
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteTransform {
+    /// Run the script; false stages it
+    #[serde(default)]
+    pub enabled: bool,
+    pub script: String,
+    #[serde(default)]
+    pub on_error: FailMode,
+    /// Per invocation (default 50 ms, at most 1 s)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Body prefix visible to the script (default 64 KiB, at most 1 MiB)
+    #[serde(default = "default_body_kb")]
+    pub body_kb: usize,
+}
+
+impl RouteTransform {
+    /// Validate (limits, compilation, hooks present)
+    pub fn normalize(&self) -> Result<Self, String> {
+        if self.script.trim().is_empty() {
+            return Err("script is empty".to_string());
+        }
+        if self.script.len() > MAX_SCRIPT_BYTES {
+            return Err(format!("script exceeds {} bytes", MAX_SCRIPT_BYTES));
+        }
+        if !(1..=MAX_TIMEOUT_MS).contains(&self.timeout_ms) {
+            return Err(format!(
+                "timeout_ms must be between 1 and {}",
+                MAX_TIMEOUT_MS
+            ));
+        }
+        if !(1..=MAX_BODY_KB).contains(&self.body_kb) {
+            return Err(format!("body_kb must be between 1 and {}", MAX_BODY_KB));
+        }
+        compile(&self.script)?;
+        Ok(self.clone())
+    }
+
+    /// Stored column value (None when the policy is invalid)
+    pub fn to_column(&self) -> Option<String> {
+        self.normalize()
+            .ok()
+            .and_then(|p| serde_json::to_string(&p).ok())
+    }
+
+    fn body_limit(&self) -> usize {
+        self.body_kb.min(MAX_BODY_KB) * 1024
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.clamp(1, MAX_TIMEOUT_MS))
+    }
+}
+
+impl ProxyRoute {
+    /// Transformation policy; None when unset (or the column is invalid).
+    /// The script is compiled on first use, not here.
+    pub fn transform(&self) -> Option<RouteTransform> {
+        let raw = self
+            .transform
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())?;
+        match serde_json::from_str::<RouteTransform>(raw) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid transform on route {}: {}", self.id, e);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    Request,
+    Response,
+}
+
+impl Hook {
+    fn function(&self) -> &'static str {
+        match self {
+            Self::Request => "on_request",
+            Self::Response => "on_response",
+        }
+    }
+}
+
+/// Compiled script and the hooks it defines
+pub struct CompiledScript {
+    ast: AST,
+    on_request: bool,
+    on_response: bool,
+}
+
+impl CompiledScript {
+    fn defines(&self, hook: Hook) -> bool {
+        match hook {
+            Hook::Request => self.on_request,
+            Hook::Response => self.on_response,
+        }
+    }
+}
+
+/// Compile a script; errors carry rhai's message and position
+pub fn compile(script: &str) -> Result<CompiledScript, String> {
+    let ast = engine().compile(script).map_err(|e| e.to_string())?;
+    let mut compiled = CompiledScript {
+        on_request: false,
+        on_response: false,
+        ast: AST::empty(),
+    };
+    for f in ast.iter_functions() {
+        let hook = match f.name {
+            "on_request" => &mut compiled.on_request,
+            "on_response" => &mut compiled.on_response,
+            _ => continue,
+        };
+        if !f.params.is_empty() {
+            return Err(format!("{}() takes no parameters (use `this`)", f.name));
+        }
+        *hook = true;
+    }
+    if !compiled.on_request && !compiled.on_response {
+        return Err("script defines neither on_request() nor on_response()".to_string());
+    }
+    compiled.ast = ast;
+    Ok(compiled)
+}
+
+thread_local! {
+    /// Deadline of the script running on this thread
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+fn sha256_hex(data: &str) -> String {
+    hex::encode(Sha256::digest(data.as_bytes()))
+}
+
+fn hmac_sha256_hex(key: &str, data: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn no_sleep(_: Dynamic) -> Result<(), Box<EvalAltResult>> {
+    Err("sleep is not available in transformation scripts".into())
+}
+
+/// Shared sandboxed engine
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .disable_symbol("eval")
+            .disable_symbol("import")
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(4 * MAX_BODY_KB * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .on_print(|_| {})
+            .on_debug(|_, _, _| {})
+            .on_progress(|ops| {
+                if ops % DEADLINE_CHECK_EVERY != 0 {
+                    return None;
+                }
+                DEADLINE.with(|d| match d.get() {
+                    Some(at) if Instant::now() >= at => Some(Dynamic::from("timeout")),
+                    _ => None,
+                })
+            })
+            .register_fn("sha256_hex", sha256_hex)
+            .register_fn("hmac_sha256_hex", hmac_sha256_hex)
+            .register_fn("sleep", |s: INT| no_sleep(Dynamic::from(s)))
+            .register_fn("sleep", |s: rhai::FLOAT| no_sleep(Dynamic::from(s)));
+        engine
+    })
+}
+
+/// The message a hook sees
+pub struct HookInput<'a> {
+    pub hook: Hook,
+    pub method: &'a str,
+    pub path: &'a str,
+    /// Request query string without '?'
+    pub query: Option<&'a str>,
+    /// Response status
+    pub status: Option<u16>,
+    pub client_ip: &'a str,
+    pub headers: Vec<(&'a str, &'a str)>,
+    pub body: &'a [u8],
+}
+
+/// Changes a hook made
+#[derive(Debug, Default, PartialEq)]
+pub struct HookOutcome {
+    /// Header set (Some) or removed (None), lower-case names
+    pub headers: Vec<(String, Option<String>)>,
+    /// Replacement body (changed prefix plus the unseen rest)
+    pub body: Option<Vec<u8>>,
+    /// Replacement query string (empty = none)
+    pub query: Option<String>,
+}
+
+impl HookOutcome {
+    /// Apply header changes to a forwarded header list; a replaced body
+    /// drops Content-Length (recomputed on send)
+    pub fn apply_to_pairs(&self, pairs: &mut Vec<(String, String)>) {
+        for (name, value) in &self.headers {
+            pairs.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+            if let Some(value) = value {
+                pairs.push((name.clone(), value.clone()));
+            }
+        }
+        if self.body.is_some() {
+            pairs.retain(|(k, _)| !k.eq_ignore_ascii_case("content-length"));
+        }
+    }
+
+    /// Apply header changes to an upstream response header map (names and
+    /// values were validated by `run`)
+    pub fn apply_to_header_map(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.headers {
+            let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+                continue;
+            };
+            match value.as_deref().map(HeaderValue::from_str) {
+                Some(Ok(value)) => {
+                    headers.insert(name, value);
+                }
+                Some(Err(_)) => {}
+                None => {
+                    headers.remove(name);
+                }
+            }
+        }
+        if self.body.is_some() {
+            headers.remove(CONTENT_LENGTH);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TransformError {
+    Failed(String),
+    TimedOut,
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(e) => write!(f, "transform script failed: {}", e),
+            Self::TimedOut => f.write_str("transform script timed out"),
+        }
+    }
+}
+
+/// Visible part of a body: (text, truncated, binary, visible byte length)
+fn body_view(body: &[u8], limit: usize) -> (String, bool, bool, usize) {
+    let prefix = &body[..body.len().min(limit)];
+    match std::str::from_utf8(prefix) {
+        Ok(text) => (
+            text.to_string(),
+            prefix.len() < body.len(),
+            false,
+            prefix.len(),
+        ),
+        // Cut inside a multi-byte character: show up to it
+        Err(e) if e.error_len().is_none() && prefix.len() < body.len() => {
+            let valid = e.valid_up_to();
+            let text = String::from_utf8_lossy(&prefix[..valid]).into_owned();
+            (text, true, false, valid)
+        }
+        Err(_) => (String::new(), prefix.len() < body.len(), true, 0),
+    }
+}
+
+/// Header map as the script sees it (repeated headers joined with ", ")
+fn header_map(headers: &[(&str, &str)]) -> Map {
+    let mut map = Map::new();
+    for (name, value) in headers {
+        let name = name.to_ascii_lowercase();
+        let joined = match map.get(name.as_str()) {
+            Some(existing) => format!("{}, {}", existing, value),
+            None => value.to_string(),
+        };
+        map.insert(name.into(), joined.into());
+    }
+    map
+}
+
+/// Header differences between what the script got and what it left
+fn header_changes(before: &Map, after: &Map) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut changes = Vec::new();
+    for (name, value) in after {
+        let name = name.to_ascii_lowercase();
+        let value = (!value.is_unit()).then(|| value.to_string());
+        let original = before.get(name.as_str()).map(|v| v.to_string());
+        if value == original {
+            continue;
+        }
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name {:?}", name))?;
+        if let Some(v) = &value {
+            HeaderValue::from_str(v).map_err(|_| format!("invalid value for header {:?}", name))?;
+        }
+        changes.push((name, value));
+    }
+    for name in before.keys() {
+        if !after.contains_key(name.as_str()) {
+            changes.push((name.to_string(), None));
+        }
+    }
+    Ok(changes)
+}
+
+/// Per-hook execution counters
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HookMetrics {
+    pub runs: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub avg_us: u64,
+    pub max_us: u64,
+    #[serde(skip)]
+    total_us: u64,
+    pub last_error: Option<String>,
+}
+
+impl HookMetrics {
+    fn record(&mut self, elapsed: Duration, result: &Result<HookOutcome, TransformError>) {
+        let us = elapsed.as_micros() as u64;
+        self.runs += 1;
+        self.total_us += us;
+        self.avg_us = self.total_us / self.runs;
+        self.max_us = self.max_us.max(us);
+        match result {
+            Ok(_) => {}
+            Err(TransformError::TimedOut) => {
+                self.timeouts += 1;
+                self.last_error = Some(TransformError::TimedOut.to_string());
+            }
+            Err(e) => {
+                self.failures += 1;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Script metrics of one route since startup
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransformMetrics {
+    pub on_request: HookMetrics,
+    pub on_response: HookMetrics,
+}
+
+/// Compiled scripts (by route and script hash) and their metrics
+pub struct RouteTransforms {
+    scripts: Mutex<HashMap<i32, (u64, Arc<CompiledScript>)>>,
+    metrics: Mutex<HashMap<i32, TransformMetrics>>,
+    /// Bounds scripts occupying blocking threads
+    running: Arc<Semaphore>,
+}
+
+impl Default for RouteTransforms {
+    fn default() -> Self {
+        Self {
+            scripts: Mutex::default(),
+            metrics: Mutex::default(),
+            running: Arc::new(Semaphore::new(MAX_CONCURRENT_SCRIPTS)),
+        }
+    }
+}
+
+impl RouteTransforms {
+    fn compiled(&self, route_id: i32, script: &str) -> Result<Arc<CompiledScript>, String> {
+        let mut hasher = DefaultHasher::new();
+        script.hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some((h, compiled)) = self.lock_scripts().get(&route_id) {
+            if *h == hash {
+                return Ok(compiled.clone());
+            }
+        }
+        let compiled = Arc::new(compile(script)?);
+        self.lock_scripts()
+            .insert(route_id, (hash, compiled.clone()));
+        Ok(compiled)
+    }
+
+    /// Whether the route's live script defines `hook`
+    pub fn has_hook(&self, route: &ProxyRoute, policy: &RouteTransform, hook: Hook) -> bool {
+        policy.enabled
+            && self
+                .compiled(route.id, &policy.script)
+                .is_ok_and(|c| c.defines(hook))
+    }
+
+    /// Run one hook of a live script on the blocking pool (bounded by the
+    /// policy's time limit)
+    pub async fn run(
+        &self,
+        route: &ProxyRoute,
+        policy: &RouteTransform,
+        input: &HookInput<'_>,
+    ) -> Result<HookOutcome, TransformError> {
+        let started = Instant::now();
+        let result = self.execute(route, policy, input).await;
+        let mut metrics = self.metrics.lock().unwrap_or_else(|p| p.into_inner());
+        let entry = metrics.entry(route.id).or_default();
+        match input.hook {
+            Hook::Request => entry.on_request.record(started.elapsed(), &result),
+            Hook::Response => entry.on_response.record(started.elapsed(), &result),
+        }
+        result
+    }
+
+    async fn execute(
+        &self,
+        route: &ProxyRoute,
+        policy: &RouteTransform,
+        input: &HookInput<'_>,
+    ) -> Result<HookOutcome, TransformError> {
+        let compiled = self
+            .compiled(route.id, &policy.script)
+            .map_err(TransformError::Failed)?;
+
+        let (body, truncated, binary, visible) = body_view(input.body, policy.body_limit());
+        let headers = header_map(&input.headers);
+        let mut route_meta = Map::new();
+        route_meta.insert("id".into(), (route.id as INT).into());
+        route_meta.insert("path".into(), route.path.clone().into());
+        route_meta.insert(
+            "team".into(),
+            route.team.clone().map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        let mut this = Map::new();
+        this.insert("method".into(), input.method.into());
+        this.insert("path".into(), input.path.into());
+        this.insert("client_ip".into(), input.client_ip.into());
+        this.insert("headers".into(), headers.clone().into());
+        this.insert("body".into(), body.clone().into());
+        this.insert("body_truncated".into(), truncated.into());
+        this.insert("body_binary".into(), binary.into());
+        this.insert("route".into(), route_meta.into());
+        match input.hook {
+            Hook::Request => {
+                this.insert("query".into(), input.query.unwrap_or_default().into());
+            }
+            Hook::Response => {
+                this.insert("status".into(), (input.status.unwrap_or(0) as INT).into());
+            }
+        }
+        let this: Dynamic = this.into();
+
+        let slot = tokio::time::timeout(policy.timeout(), self.running.clone().acquire_owned())
+            .await
+            .map_err(|_| TransformError::TimedOut)?
+            .map_err(|_| TransformError::Failed("script runner closed".to_string()))?;
+        let timeout = policy.timeout();
+        let hook = input.hook;
+        // The slot is held by the blocking task, so it is released only when
+        // the script stops, even if this request goes away
+        let this = tokio::task::spawn_blocking(move || {
+            let _slot = slot;
+            call_hook(&compiled, hook, this, timeout)
+        })
+        .await
+        .map_err(|e| TransformError::Failed(format!("script task failed: {}", e)))??;
+
+        let this = this
+            .try_cast::<Map>()
+            .ok_or_else(|| TransformError::Failed("`this` must stay a map".to_string()))?;
+        let mut outcome = HookOutcome::default();
+
+        let after = this
+            .get("headers")
+            .and_then(|h| h.clone().try_cast::<Map>())
+            .ok_or_else(|| TransformError::Failed("`this.headers` must stay a map".to_string()))?;
+        outcome.headers = header_changes(&headers, &after).map_err(TransformError::Failed)?;
+
+        match this.get("body") {
+            Some(b) if b.is_string() => {
+                let new_body = b.to_string();
+                if new_body != body {
+                    if binary {
+                        return Err(TransformError::Failed(
+                            "a binary body cannot be modified".to_string(),
+                        ));
+                    }
+                    let mut replaced = new_body.into_bytes();
+                    replaced.extend_from_slice(&input.body[visible..]);
+                    outcome.body = Some(replaced);
+                }
+            }
+            _ => {
+                return Err(TransformError::Failed(
+                    "`this.body` must stay a string".to_string(),
+                ))
+            }
+        }
+
+        if input.hook == Hook::Request {
+            let query = this.get("query").map(|q| q.to_string()).unwrap_or_default();
+            if query != input.query.unwrap_or_default() {
+                outcome.query = Some(query.trim_start_matches('?').to_string());
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Metrics of a route (zeros when its script never ran)
+    pub fn metrics(&self, route_id: i32) -> TransformMetrics {
+        self.metrics
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .get(&route_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Drop a route's compiled script and metrics (policy changed)
+    pub fn forget(&self, route_id: i32) {
+        self.lock_scripts().remove(&route_id);
+        self.metrics
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .remove(&route_id);
+    }
+
+    fn lock_scripts(&self) -> std::sync::MutexGuard<'_, HashMap<i32, (u64, Arc<CompiledScript>)>> {
+        self.scripts.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// Call a hook with `this` bound, under the deadline (blocking; the
+/// deadline is thread-local, so it is set on the thread that runs it)
+fn call_hook(
+    compiled: &CompiledScript,
+    hook: Hook,
+    mut this: Dynamic,
+    timeout: Duration,
+) -> Result<Dynamic, TransformError> {
+    DEADLINE.with(|d| d.set(Some(Instant::now() + timeout)));
+    let result = engine().call_fn_with_options::<Dynamic>(
+        CallFnOptions::new()
+            .eval_ast(false)
+            .rewind_scope(false)
+            .bind_this_ptr(&mut this),
+        &mut Scope::new(),
+        &compiled.ast,
+        hook.function(),
+        (),
+    );
+    DEADLINE.with(|d| d.set(None));
+    match result {
+        Ok(_) => Ok(this),
+        Err(e) => Err(match *e {
+            EvalAltResult::ErrorTerminated(..) => TransformError::TimedOut,
+            e => TransformError::Failed(e.to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route() -> ProxyRoute {
+        ProxyRoute {
+            id: 7,
+            path: "/api".to_string(),
+            target: "http://127.0.0.1:8080".to_string(),
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    fn policy(script: &str) -> RouteTransform {
+        RouteTransform {
+            enabled: true,
+            script: script.to_string(),
+            on_error: FailMode::Open,
+            timeout_ms: 50,
+            body_kb: 1,
+        }
+    }
+
+    fn request<'a>(headers: Vec<(&'a str, &'a str)>, body: &'a [u8]) -> HookInput<'a> {
+        HookInput {
+            hook: Hook::Request,
+            method: "POST",
+            path: "/api/items",
+            query: Some("id=1&utm_source=x"),
+            status: None,
+            client_ip: "203.0.113.7",
+            headers,
+            body,
+        }
+    }
+
+    #[test]
+    fn validation_reports_compile_errors_and_missing_hooks() {
+        let err = policy("fn on_request() { let x = ; }")
+            .normalize()
+            .unwrap_err();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(policy("let x = 1;")
+            .normalize()
+            .unwrap_err()
+            .contains("neither"));
+        assert!(policy("fn on_response(res) {}").normalize().is_err());
+        let mut big = policy("fn on_request() {}");
+        big.timeout_ms = MAX_TIMEOUT_MS + 1;
+        assert!(big.normalize().is_err());
+        assert!(policy("fn on_request() {}").normalize().is_ok());
+    }
+
+    #[tokio::test]
+    async fn request_hook_edits_headers_query_and_body() {
+        let transforms = RouteTransforms::default();
+        let script = r#"
+            fn on_request() {
+                this.headers["x-signature"] = hmac_sha256_hex("k", this.body);
+                this.headers["cookie"] = ();
+                this.query = "id=1";
+                let doc = parse_json(this.body);
+                doc.name = "renamed";
+                this.body = doc.to_json();
+            }
+        "#;
+        let input = request(
+            vec![("Cookie", "a=1"), ("Accept", "*/*")],
+            br#"{"name":"original"}"#,
+        );
+        let outcome = transforms
+            .run(&route(), &policy(script), &input)
+            .await
+            .unwrap();
+        assert_eq!(outcome.query.as_deref(), Some("id=1"));
+        assert_eq!(
+            outcome.body.as_deref(),
+            Some(br#"{"name":"renamed"}"#.as_slice())
+        );
+        assert!(outcome.headers.contains(&("cookie".to_string(), None)));
+        assert!(outcome
+            .headers
+            .iter()
+            .any(|(n, v)| n == "x-signature" && v.as_ref().is_some_and(|v| v.len() == 64)));
+        // Untouched headers are not rewritten
+        assert!(!outcome.headers.iter().any(|(n, _)| n == "accept"));
+
+        let mut pairs = vec![
+            ("Cookie".to_string(), "a=1".to_string()),
+            ("Content-Length".to_string(), "19".to_string()),
+        ];
+        outcome.apply_to_pairs(&mut pairs);
+        assert!(pairs
+            .iter()
+            .all(|(k, _)| k != "Cookie" && k != "Content-Length"));
+        assert_eq!(transforms.metrics(7).on_request.runs, 1);
+    }
+
+    #[tokio::test]
+    async fn body_edits_keep_the_unseen_rest() {
+        let transforms = RouteTransforms::default();
+        let mut body = vec![b'a'; 1024];
+        body.extend_from_slice(b"TAIL");
+        let script = r#"fn on_request() { if this.body_truncated { this.body = "x"; } }"#;
+        let outcome = transforms
+            .run(&route(), &policy(script), &request(vec![], &body))
+            .await
+            .unwrap();
+        assert_eq!(outcome.body.as_deref(), Some(b"xTAIL".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn runaway_and_sandboxed_calls_fail() {
+        let transforms = RouteTransforms::default();
+        let input = request(vec![], b"");
+        assert_eq!(
+            transforms
+                .run(&route(), &policy("fn on_request() { loop {} }"), &input)
+                .await,
+            Err(TransformError::TimedOut)
+        );
+        for script in [
+            "fn on_request() { sleep(5); }",
+            r#"fn on_request() { eval("1"); }"#,
+            r#"fn on_request() { this.headers["bad header"] = "1"; }"#,
+        ] {
+            let result = match policy(script).normalize() {
+                Ok(p) => transforms.run(&route(), &p, &input).await,
+                Err(e) => Err(TransformError::Failed(e)),
+            };
+            assert!(
+                matches!(result, Err(TransformError::Failed(_))),
+                "{}: {:?}",
+                script,
+                result
+            );
+        }
+        let metrics = transforms.metrics(7).on_request;
+        assert_eq!(metrics.timeouts, 1);
+        assert!(metrics.failures >= 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn slow_scripts_leave_the_runtime_responsive() {
+        let transforms = Arc::new(RouteTransforms::default());
+        let mut slow = policy("fn on_request() { loop {} }");
+        slow.timeout_ms = 300;
+        let runs = (0..4)
+            .map(|_| {
+                let transforms = transforms.clone();
+                let slow = slow.clone();
+                tokio::spawn(async move {
+                    let input = request(vec![], b"");
+                    transforms.run(&route(), &slow, &input).await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Both workers stay free while the scripts spin
+        let started = Instant::now();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(started.elapsed() < Duration::from_millis(200));
+
+        for run in runs {
+            assert_eq!(run.await.unwrap(), Err(TransformError::TimedOut));
+        }
+    }
+}
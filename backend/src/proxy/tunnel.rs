@@ -0,0 +1,372 @@
+//! WebSocket tunnel flow control and metrics
+//!
+//! Each direction of a tunnel relays through a bounded queue
+//! (`proxy_ws_queue_frames`). When the receiving peer is slow its queue
+//! fills and the bridge stops reading from the sending peer, so the fast side
+//! is slowed down by TCP back-pressure instead of frames piling up in memory.
+//! Bytes queued in both directions are capped per connection
+//! (`proxy_ws_max_buffered_kb`); a tunnel over the cap is closed with 1011.
+//!
+//! Live counters are exposed through the in-flight listing and GET
+//! /api/dashboard/websockets; closed tunnels are folded into per-route totals
+//! (in-memory, reset on restart) and logged as one access log entry each.
+//!
+//! A peer that vanishes without a close frame (half-open TCP) never ends its
+//! read loop. While keepalive is on, quiet tunnels are pinged, so a tunnel
+//! with no data frames where one peer has not answered for
+//! `STALE_KEEPALIVES` keepalive intervals is dead and gets reaped by the
+//! periodic sweep (`inflight::reap_loop`).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Payload of the gateway's keepalive pings; the matching pongs are not
+/// relayed to the other peer
+pub const KEEPALIVE_PAYLOAD: &[u8] = b"lpg-keepalive";
+
+/// Close code sent to both peers when the buffer cap is exceeded
+pub const CLOSE_BUFFER_LIMIT: u16 = 1011;
+
+/// Keepalive intervals a peer may leave unanswered before its tunnel is reaped
+pub const STALE_KEEPALIVES: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ClientToUpstream,
+    UpstreamToClient,
+}
+
+impl Direction {
+    /// The peer whose queue filled up
+    pub fn slow_side(&self) -> &'static str {
+        match self {
+            Self::ClientToUpstream => "upstream",
+            Self::UpstreamToClient => "client",
+        }
+    }
+}
+
+/// Counters of one live tunnel, shared by the bridge and the in-flight entry
+pub struct TunnelMetrics {
+    started: Instant,
+    frames_client_to_upstream: AtomicU64,
+    bytes_client_to_upstream: AtomicU64,
+    frames_upstream_to_client: AtomicU64,
+    bytes_upstream_to_client: AtomicU64,
+    queued_bytes: AtomicU64,
+    high_water_bytes: AtomicU64,
+    keepalive_pings: AtomicU64,
+    /// Milliseconds since `started` of the last frame read from either peer
+    last_activity_ms: AtomicU64,
+    /// Milliseconds since `started` of the last relayed frame (keepalive
+    /// pings and pongs excluded)
+    last_frame_ms: AtomicU64,
+    /// Milliseconds since `started` of the last frame (pongs included) read
+    /// from the client / the upstream
+    heard_client_ms: AtomicU64,
+    heard_upstream_ms: AtomicU64,
+}
+
+/// Snapshot of a tunnel's counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TunnelSnapshot {
+    pub frames_client_to_upstream: u64,
+    pub bytes_client_to_upstream: u64,
+    pub frames_upstream_to_client: u64,
+    pub bytes_upstream_to_client: u64,
+    /// Bytes read from one peer and not yet written to the other
+    pub queued_bytes: u64,
+    /// Highest `queued_bytes` seen
+    pub queue_high_water_bytes: u64,
+    pub keepalive_pings: u64,
+    pub duration_ms: u64,
+}
+
+impl Default for TunnelMetrics {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            frames_client_to_upstream: AtomicU64::new(0),
+            bytes_client_to_upstream: AtomicU64::new(0),
+            frames_upstream_to_client: AtomicU64::new(0),
+            bytes_upstream_to_client: AtomicU64::new(0),
+            queued_bytes: AtomicU64::new(0),
+            high_water_bytes: AtomicU64::new(0),
+            keepalive_pings: AtomicU64::new(0),
+            last_activity_ms: AtomicU64::new(0),
+            last_frame_ms: AtomicU64::new(0),
+            heard_client_ms: AtomicU64::new(0),
+            heard_upstream_ms: AtomicU64::new(0),
+        }
+    }
+}
+
+impl TunnelMetrics {
+    /// Count a frame read from one peer; also marks the tunnel active
+    pub fn relayed(&self, direction: Direction, len: usize) {
+        let (frames, bytes) = match direction {
+            Direction::ClientToUpstream => (
+                &self.frames_client_to_upstream,
+                &self.bytes_client_to_upstream,
+            ),
+            Direction::UpstreamToClient => (
+                &self.frames_upstream_to_client,
+                &self.bytes_upstream_to_client,
+            ),
+        };
+        frames.fetch_add(1, Ordering::Relaxed);
+        bytes.fetch_add(len as u64, Ordering::Relaxed);
+        self.touch();
+        self.last_frame_ms.store(self.now_ms(), Ordering::Relaxed);
+    }
+
+    /// Note that the peer sending `direction` is alive (any frame, including
+    /// keepalive pongs)
+    pub fn heard(&self, direction: Direction) {
+        let heard = match direction {
+            Direction::ClientToUpstream => &self.heard_client_ms,
+            Direction::UpstreamToClient => &self.heard_upstream_ms,
+        };
+        heard.store(self.now_ms(), Ordering::Relaxed);
+    }
+
+    /// Account for bytes entering a queue; returns the new total
+    pub fn queued(&self, len: usize) -> u64 {
+        let total = self.queued_bytes.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        self.high_water_bytes.fetch_max(total, Ordering::Relaxed);
+        total
+    }
+
+    /// Account for bytes written to a peer (or dropped from a queue)
+    pub fn dequeued(&self, len: usize) {
+        let _ = self
+            .queued_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |q| {
+                Some(q.saturating_sub(len as u64))
+            });
+    }
+
+    pub fn keepalive_sent(&self) {
+        self.keepalive_pings.fetch_add(1, Ordering::Relaxed);
+        self.touch();
+    }
+
+    fn touch(&self) {
+        self.last_activity_ms
+            .store(self.now_ms(), Ordering::Relaxed);
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn since(&self, field: &AtomicU64) -> Duration {
+        let last = Duration::from_millis(field.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last)
+    }
+
+    /// Time since the last frame from either peer (or keepalive)
+    pub fn idle(&self) -> Duration {
+        self.since(&self.last_activity_ms)
+    }
+
+    /// How long the tunnel has looked dead: no data frames and one peer not
+    /// heard from (zero while data flows)
+    pub fn stale_for(&self) -> Duration {
+        let silent = self
+            .since(&self.heard_client_ms)
+            .max(self.since(&self.heard_upstream_ms));
+        self.since(&self.last_frame_ms).min(silent)
+    }
+
+    pub fn snapshot(&self) -> TunnelSnapshot {
+        TunnelSnapshot {
+            frames_client_to_upstream: self.frames_client_to_upstream.load(Ordering::Relaxed),
+            bytes_client_to_upstream: self.bytes_client_to_upstream.load(Ordering::Relaxed),
+            frames_upstream_to_client: self.frames_upstream_to_client.load(Ordering::Relaxed),
+            bytes_upstream_to_client: self.bytes_upstream_to_client.load(Ordering::Relaxed),
+            queued_bytes: self.queued_bytes.load(Ordering::Relaxed),
+            queue_high_water_bytes: self.high_water_bytes.load(Ordering::Relaxed),
+            keepalive_pings: self.keepalive_pings.load(Ordering::Relaxed),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Closed-tunnel totals of one route
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RouteTunnelSummary {
+    /// Tunnels open right now (filled in from the in-flight tracker)
+    pub active_tunnels: usize,
+    pub closed_tunnels: u64,
+    /// Closed with 1011 after exceeding the buffer cap
+    pub buffer_limit_closes: u64,
+    pub frames_client_to_upstream: u64,
+    pub bytes_client_to_upstream: u64,
+    pub frames_upstream_to_client: u64,
+    pub bytes_upstream_to_client: u64,
+    /// Highest queue high-water mark of any closed tunnel
+    pub max_queue_high_water_bytes: u64,
+    pub keepalive_pings: u64,
+    pub total_duration_ms: u64,
+}
+
+/// Per-route totals of closed tunnels
+#[derive(Default)]
+pub struct TunnelStats {
+    routes: Mutex<HashMap<i32, RouteTunnelSummary>>,
+}
+
+impl TunnelStats {
+    pub fn record(&self, route_id: i32, tunnel: &TunnelSnapshot, buffer_limit: bool) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let s = routes.entry(route_id).or_default();
+        s.closed_tunnels += 1;
+        s.buffer_limit_closes += u64::from(buffer_limit);
+        s.frames_client_to_upstream += tunnel.frames_client_to_upstream;
+        s.bytes_client_to_upstream += tunnel.bytes_client_to_upstream;
+        s.frames_upstream_to_client += tunnel.frames_upstream_to_client;
+        s.bytes_upstream_to_client += tunnel.bytes_upstream_to_client;
+        s.max_queue_high_water_bytes = s
+            .max_queue_high_water_bytes
+            .max(tunnel.queue_high_water_bytes);
+        s.keepalive_pings += tunnel.keepalive_pings;
+        s.total_duration_ms += tunnel.duration_ms;
+    }
+
+    /// Summary over all routes (`max_queue_high_water_bytes` is the highest)
+    pub fn totals(&self, active_tunnels: usize) -> RouteTunnelSummary {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.values().fold(
+            RouteTunnelSummary {
+                active_tunnels,
+                ..RouteTunnelSummary::default()
+            },
+            |mut t, s| {
+                t.closed_tunnels += s.closed_tunnels;
+                t.buffer_limit_closes += s.buffer_limit_closes;
+                t.frames_client_to_upstream += s.frames_client_to_upstream;
+                t.bytes_client_to_upstream += s.bytes_client_to_upstream;
+                t.frames_upstream_to_client += s.frames_upstream_to_client;
+                t.bytes_upstream_to_client += s.bytes_upstream_to_client;
+                t.max_queue_high_water_bytes = t
+                    .max_queue_high_water_bytes
+                    .max(s.max_queue_high_water_bytes);
+                t.keepalive_pings += s.keepalive_pings;
+                t.total_duration_ms += s.total_duration_ms;
+                t
+            },
+        )
+    }
+
+    /// Summary for one route; None when it never carried a tunnel
+    pub fn for_route(&self, route_id: i32, active_tunnels: usize) -> Option<RouteTunnelSummary> {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        match routes.get(&route_id) {
+            Some(s) => Some(RouteTunnelSummary {
+                active_tunnels,
+                ..s.clone()
+            }),
+            None if active_tunnels > 0 => Some(RouteTunnelSummary {
+                active_tunnels,
+                ..RouteTunnelSummary::default()
+            }),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_accounting_tracks_high_water() {
+        let metrics = TunnelMetrics::default();
+        metrics.relayed(Direction::ClientToUpstream, 100);
+        assert_eq!(metrics.queued(100), 100);
+        metrics.relayed(Direction::UpstreamToClient, 300);
+        assert_eq!(metrics.queued(300), 400);
+        metrics.dequeued(100);
+        metrics.dequeued(300);
+        // Never goes below zero
+        metrics.dequeued(50);
+
+        let s = metrics.snapshot();
+        assert_eq!(s.frames_client_to_upstream, 1);
+        assert_eq!(s.bytes_client_to_upstream, 100);
+        assert_eq!(s.frames_upstream_to_client, 1);
+        assert_eq!(s.bytes_upstream_to_client, 300);
+        assert_eq!(s.queued_bytes, 0);
+        assert_eq!(s.queue_high_water_bytes, 400);
+    }
+
+    #[test]
+    fn activity_resets_idle_time() {
+        let metrics = TunnelMetrics::default();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(metrics.idle() >= Duration::from_millis(20));
+        metrics.keepalive_sent();
+        assert!(metrics.idle() < Duration::from_millis(20));
+        assert_eq!(metrics.snapshot().keepalive_pings, 1);
+    }
+
+    #[test]
+    fn route_summary_folds_closed_tunnels() {
+        let stats = TunnelStats::default();
+        assert_eq!(stats.for_route(1, 0), None);
+        assert_eq!(stats.for_route(1, 2).map(|s| s.active_tunnels), Some(2));
+
+        let tunnel = |bytes: u64, high_water: u64| TunnelSnapshot {
+            frames_upstream_to_client: 1,
+            bytes_upstream_to_client: bytes,
+            queue_high_water_bytes: high_water,
+            duration_ms: 1000,
+            ..TunnelSnapshot::default()
+        };
+        stats.record(1, &tunnel(10, 500), false);
+        stats.record(1, &tunnel(20, 900), true);
+
+        let s = stats.for_route(1, 1).unwrap();
+        assert_eq!(s.active_tunnels, 1);
+        assert_eq!(s.closed_tunnels, 2);
+        assert_eq!(s.buffer_limit_closes, 1);
+        assert_eq!(s.frames_upstream_to_client, 2);
+        assert_eq!(s.bytes_upstream_to_client, 30);
+        assert_eq!(s.max_queue_high_water_bytes, 900);
+        assert_eq!(s.total_duration_ms, 2000);
+        assert_eq!(stats.for_route(2, 0), None);
+
+        stats.record(2, &tunnel(5, 100), false);
+        let totals = stats.totals(3);
+        assert_eq!(totals.active_tunnels, 3);
+        assert_eq!(totals.closed_tunnels, 3);
+        assert_eq!(totals.bytes_upstream_to_client, 35);
+        assert_eq!(totals.max_queue_high_water_bytes, 900);
+    }
+
+    #[test]
+    fn silent_peer_makes_a_quiet_tunnel_stale() {
+        let metrics = TunnelMetrics::default();
+        std::thread::sleep(Duration::from_millis(20));
+        // Data flowing: never stale, whoever is silent
+        metrics.relayed(Direction::UpstreamToClient, 10);
+        assert!(metrics.stale_for() < Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(20));
+        // Both peers answered the keepalive: not stale
+        metrics.heard(Direction::ClientToUpstream);
+        metrics.heard(Direction::UpstreamToClient);
+        assert!(metrics.stale_for() < Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(20));
+        // Only the upstream answers
+        metrics.heard(Direction::UpstreamToClient);
+        assert!(metrics.stale_for() >= Duration::from_millis(20));
+    }
+}
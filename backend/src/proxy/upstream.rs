@@ -0,0 +1,355 @@
+//! Upstream HTTP clients per tuning, and per-route upstream counters
+//!
+//! Routes without tuning share `ProxyState::http_client`. A route with
+//! `connect_timeout_ms` or `max_idle_per_host` set gets a client (and so a
+//! connection pool) of its own, shared only with routes tuned the same way,
+//! so a slow backend cannot hold the idle connections everyone else needs.
+//! On every client the route's `timeout_ms` bounds the whole request, from
+//! connecting to the last byte of the response body.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::trace;
+use crate::models::ProxyRoute;
+
+/// Connect timeout of the shared client
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u32 = 10_000;
+
+/// Idle connections per host kept by the shared client
+pub const DEFAULT_MAX_IDLE_PER_HOST: u32 = 10;
+
+/// Upper bound of `connect_timeout_ms`
+pub const MAX_CONNECT_TIMEOUT_MS: i32 = 300_000;
+
+/// Upper bound of `max_idle_per_host`
+pub const MAX_IDLE_PER_HOST: i32 = 1000;
+
+/// Tuned clients kept at most; routes tuned beyond this use the shared client
+const MAX_TUNED_CLIENTS: usize = 32;
+
+/// Timeout of the shared client for callers that set none per request
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client settings a route asks for (None = the shared client's)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UpstreamTuning {
+    pub connect_timeout_ms: Option<u32>,
+    pub max_idle_per_host: Option<u32>,
+}
+
+impl UpstreamTuning {
+    pub fn of(route: &ProxyRoute) -> Self {
+        Self {
+            connect_timeout_ms: route
+                .connect_timeout_ms
+                .filter(|ms| *ms > 0)
+                .map(|ms| ms as u32),
+            max_idle_per_host: route
+                .max_idle_per_host
+                .filter(|n| *n >= 0)
+                .map(|n| n as u32),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.connect_timeout_ms.is_none() && self.max_idle_per_host.is_none()
+    }
+
+    fn build(&self) -> reqwest::Result<reqwest::Client> {
+        build_client(
+            self.connect_timeout_ms
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS),
+            self.max_idle_per_host.unwrap_or(DEFAULT_MAX_IDLE_PER_HOST),
+        )
+    }
+}
+
+/// Proxy client with the given connect timeout and idle pool size (DNS and
+/// connect are timed for traced routes)
+pub fn build_client(
+    connect_timeout_ms: u32,
+    max_idle_per_host: u32,
+) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(CLIENT_TIMEOUT)
+        .connect_timeout(Duration::from_millis(connect_timeout_ms as u64))
+        .pool_max_idle_per_host(max_idle_per_host as usize)
+        .dns_resolver(std::sync::Arc::new(trace::TracingResolver))
+        .connector_layer(trace::TraceConnectLayer)
+        .build()
+}
+
+/// Whole-request timeout of a route (connect through the response body)
+pub fn route_timeout(route: &ProxyRoute) -> Duration {
+    Duration::from_millis(route.timeout_ms.max(1) as u64)
+}
+
+/// Which client served a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamPool {
+    /// `ProxyState::http_client`
+    Shared,
+    /// A client of the route's tuning
+    Tuned,
+    /// Tuned, but `MAX_TUNED_CLIENTS` are in use; the shared client served
+    Overflow,
+}
+
+#[derive(Debug, Default)]
+struct RouteCounters {
+    requests: u64,
+    pool_hits: u64,
+    pool_misses: u64,
+    timeouts: u64,
+    connect_errors: u64,
+    last_timeout_at: Option<DateTime<Utc>>,
+    responses: u64,
+    ttfb_ms_total: u64,
+    ttfb_ms_max: u64,
+}
+
+/// `GET /api/routes/:id/status` upstream section (counters since startup)
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteUpstreamStatus {
+    pub pool: UpstreamPool,
+    /// Effective settings
+    pub timeout_ms: i32,
+    pub connect_timeout_ms: u32,
+    pub max_idle_per_host: u32,
+    pub requests: u64,
+    /// Requests that found their client's pool already built
+    pub pool_hits: u64,
+    /// Requests that had to build their tuned client first (requests
+    /// served by an overflow client are neither)
+    pub pool_misses: u64,
+    /// Requests that ran out of `timeout_ms` (or the connect timeout)
+    pub timeouts: u64,
+    /// Requests that could not connect to the target
+    pub connect_errors: u64,
+    pub last_timeout_at: Option<DateTime<Utc>>,
+    /// Time to the upstream response head
+    pub avg_ttfb_ms: Option<u64>,
+    pub max_ttfb_ms: Option<u64>,
+}
+
+/// Tuned clients and the per-route counters (in-memory, reset on restart)
+pub struct UpstreamClients {
+    shared: reqwest::Client,
+    tuned: RwLock<HashMap<UpstreamTuning, reqwest::Client>>,
+    counters: Mutex<HashMap<i32, RouteCounters>>,
+}
+
+impl UpstreamClients {
+    pub fn new(shared: reqwest::Client) -> Self {
+        Self {
+            shared,
+            tuned: RwLock::new(HashMap::new()),
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Client for one request of `route` (counted as a request)
+    pub fn checkout(&self, route: &ProxyRoute) -> reqwest::Client {
+        let (client, pool, hit) = self.client_for(UpstreamTuning::of(route));
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let c = counters.entry(route.id).or_default();
+        c.requests += 1;
+        if hit {
+            c.pool_hits += 1;
+        } else if pool == UpstreamPool::Tuned {
+            c.pool_misses += 1;
+        }
+        client
+    }
+
+    fn client_for(&self, tuning: UpstreamTuning) -> (reqwest::Client, UpstreamPool, bool) {
+        if tuning.is_default() {
+            return (self.shared.clone(), UpstreamPool::Shared, true);
+        }
+        if let Some(client) = self.tuned.read().unwrap().get(&tuning) {
+            return (client.clone(), UpstreamPool::Tuned, true);
+        }
+        let mut tuned = self.tuned.write().unwrap();
+        if let Some(client) = tuned.get(&tuning) {
+            return (client.clone(), UpstreamPool::Tuned, true);
+        }
+        if tuned.len() >= MAX_TUNED_CLIENTS {
+            return (self.shared.clone(), UpstreamPool::Overflow, false);
+        }
+        match tuning.build() {
+            Ok(client) => {
+                tuned.insert(tuning, client.clone());
+                (client, UpstreamPool::Tuned, false)
+            }
+            Err(e) => {
+                tracing::error!("Upstream client for {:?} not built: {}", tuning, e);
+                (self.shared.clone(), UpstreamPool::Overflow, false)
+            }
+        }
+    }
+
+    /// Drop the clients no live route is tuned for any more
+    pub fn retain<'a>(&self, routes: impl Iterator<Item = &'a ProxyRoute>) {
+        let used: Vec<UpstreamTuning> = routes.map(UpstreamTuning::of).collect();
+        self.tuned
+            .write()
+            .unwrap()
+            .retain(|tuning, _| used.contains(tuning));
+    }
+
+    /// Outcome of sending a request (the response head, not the body)
+    pub fn record_sent(
+        &self,
+        route_id: i32,
+        sent: &Result<reqwest::Response, reqwest::Error>,
+        elapsed: Duration,
+    ) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let c = counters.entry(route_id).or_default();
+        match sent {
+            Ok(_) => {
+                let ms = elapsed.as_millis() as u64;
+                c.responses += 1;
+                c.ttfb_ms_total += ms;
+                c.ttfb_ms_max = c.ttfb_ms_max.max(ms);
+            }
+            Err(e) => {
+                if e.is_connect() {
+                    c.connect_errors += 1;
+                }
+                if e.is_timeout() {
+                    c.timeouts += 1;
+                    c.last_timeout_at = Some(Utc::now());
+                }
+            }
+        }
+    }
+
+    /// `timeout_ms` ran out while the response body was read
+    pub fn record_timeout(&self, route_id: i32) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let c = counters.entry(route_id).or_default();
+        c.timeouts += 1;
+        c.last_timeout_at = Some(Utc::now());
+    }
+
+    pub fn for_route(&self, route: &ProxyRoute) -> RouteUpstreamStatus {
+        let tuning = UpstreamTuning::of(route);
+        let pool = if tuning.is_default() {
+            UpstreamPool::Shared
+        } else {
+            let tuned = self.tuned.read().unwrap();
+            if tuned.contains_key(&tuning) || tuned.len() < MAX_TUNED_CLIENTS {
+                UpstreamPool::Tuned
+            } else {
+                UpstreamPool::Overflow
+            }
+        };
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let c = counters.get(&route.id);
+        let count = |f: fn(&RouteCounters) -> u64| c.map(f).unwrap_or(0);
+        let responses = count(|c| c.responses);
+        RouteUpstreamStatus {
+            pool,
+            timeout_ms: route.timeout_ms,
+            connect_timeout_ms: tuning
+                .connect_timeout_ms
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS),
+            max_idle_per_host: tuning
+                .max_idle_per_host
+                .unwrap_or(DEFAULT_MAX_IDLE_PER_HOST),
+            requests: count(|c| c.requests),
+            pool_hits: count(|c| c.pool_hits),
+            pool_misses: count(|c| c.pool_misses),
+            timeouts: count(|c| c.timeouts),
+            connect_errors: count(|c| c.connect_errors),
+            last_timeout_at: c.and_then(|c| c.last_timeout_at),
+            avg_ttfb_ms: (responses > 0).then(|| count(|c| c.ttfb_ms_total) / responses),
+            max_ttfb_ms: (responses > 0).then(|| count(|c| c.ttfb_ms_max)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(id: i32, connect_timeout_ms: Option<i32>, max_idle: Option<i32>) -> ProxyRoute {
+        ProxyRoute {
+            id,
+            path: format!("/r{}", id),
+            target: "http://127.0.0.1:9".to_string(),
+            timeout_ms: 5000,
+            connect_timeout_ms,
+            max_idle_per_host: max_idle,
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    fn clients() -> UpstreamClients {
+        UpstreamClients::new(build_client(1000, 1).unwrap())
+    }
+
+    #[test]
+    fn tuned_routes_get_their_own_pool() {
+        let clients = clients();
+        let plain = route(1, None, None);
+        let slow = route(2, Some(2000), Some(2));
+        let same = route(3, Some(2000), Some(2));
+        for r in [&plain, &plain, &slow, &slow, &same] {
+            clients.checkout(r);
+        }
+
+        let status = clients.for_route(&plain);
+        assert_eq!(status.pool, UpstreamPool::Shared);
+        assert_eq!((status.requests, status.pool_hits), (2, 2));
+        assert_eq!(status.connect_timeout_ms, DEFAULT_CONNECT_TIMEOUT_MS);
+        let status = clients.for_route(&slow);
+        assert_eq!(status.pool, UpstreamPool::Tuned);
+        assert_eq!((status.pool_hits, status.pool_misses), (1, 1));
+        assert_eq!(
+            (status.connect_timeout_ms, status.max_idle_per_host),
+            (2000, 2)
+        );
+        // Routes tuned the same way share one client
+        assert_eq!(clients.for_route(&same).pool_hits, 1);
+        assert_eq!(clients.tuned.read().unwrap().len(), 1);
+
+        clients.retain([&plain].into_iter());
+        assert!(clients.tuned.read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tuned_clients_are_capped() {
+        let clients = clients();
+        for id in 0..MAX_TUNED_CLIENTS as i32 {
+            clients.checkout(&route(id, Some(1000 + id), None));
+        }
+        let extra = route(99, Some(99_000), None);
+        clients.checkout(&extra);
+        let status = clients.for_route(&extra);
+        assert_eq!(status.pool, UpstreamPool::Overflow);
+        assert_eq!(
+            (status.requests, status.pool_hits, status.pool_misses),
+            (1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn timeouts_are_counted_per_route() {
+        let clients = clients();
+        let r = route(1, None, None);
+        clients.record_timeout(1);
+        let status = clients.for_route(&r);
+        assert_eq!(status.timeouts, 1);
+        assert!(status.last_timeout_at.is_some());
+        assert_eq!(status.avg_ttfb_ms, None);
+        assert_eq!(route_timeout(&r), Duration::from_millis(5000));
+    }
+}
@@ -0,0 +1,147 @@
+//! Per-route upstream HTTP Basic auth
+//!
+//! A route with `upstream_auth_user` / `upstream_auth_password` sends its own
+//! `Authorization: Basic` header to the target (HTTP, WebSocket upgrades and
+//! health probes), replacing whatever the client sent. The credentials stay
+//! in `proxy_routes`: route JSON only carries `upstream_auth_configured`, and
+//! route versions do not record them.
+
+use base64::Engine;
+
+use crate::models::{ProxyRoute, UpdateRouteRequest};
+
+impl ProxyRoute {
+    /// Whether upstream Basic auth credentials are set
+    pub fn upstream_auth_configured(&self) -> bool {
+        self.upstream_authorization().is_some()
+    }
+
+    /// `Authorization` value sent to the target; None when the route passes
+    /// the client's header through
+    pub fn upstream_authorization(&self) -> Option<String> {
+        let user = self
+            .upstream_auth_user
+            .as_deref()
+            .filter(|u| !u.is_empty())?;
+        let password = self.upstream_auth_password.as_deref().unwrap_or("");
+        Some(basic_authorization(user, password))
+    }
+
+    /// `Authorization` value once `update` is stored (fields it omits keep
+    /// the stored credentials; removing the user drops the password)
+    pub fn updated_upstream_authorization(&self, update: &UpdateRouteRequest) -> Option<String> {
+        let user = match &update.upstream_auth_user {
+            Some(v) => v.as_deref().map(str::trim),
+            None => self.upstream_auth_user.as_deref(),
+        }
+        .filter(|u| !u.is_empty())?;
+        let password = match &update.upstream_auth_password {
+            Some(v) => v.as_str(),
+            None => self.upstream_auth_password.as_deref().unwrap_or(""),
+        };
+        Some(basic_authorization(user, password))
+    }
+}
+
+/// `Basic base64(user:password)`
+pub fn basic_authorization(user: &str, password: &str) -> String {
+    let credentials = format!("{}:{}", user, password);
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    )
+}
+
+/// A Basic auth user may not contain ':' (it separates the password)
+pub fn validate_user(user: &str) -> Result<(), String> {
+    if user.contains(':') || user.chars().any(char::is_control) || user.len() > 255 {
+        return Err(
+            "Upstream auth user must be at most 255 characters without ':' or control characters"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Passwords are sent as-is, so only control characters are refused
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().any(char::is_control) || password.len() > 255 {
+        return Err(
+            "Upstream auth password must be at most 255 characters without control characters"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(user: Option<&str>, password: Option<&str>) -> ProxyRoute {
+        ProxyRoute {
+            path: "/grafana".to_string(),
+            target: "http://grafana:3000".to_string(),
+            websocket_support: true,
+            upstream_auth_user: user.map(str::to_string),
+            upstream_auth_password: password.map(str::to_string),
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    #[test]
+    fn authorization_is_basic_with_user_and_password() {
+        assert_eq!(
+            route(Some("Aladdin"), Some("open sesame")).upstream_authorization(),
+            Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_string())
+        );
+        assert_eq!(route(None, Some("orphan")).upstream_authorization(), None);
+        assert!(!route(Some(""), None).upstream_auth_configured());
+    }
+
+    #[test]
+    fn updated_authorization_uses_the_new_credentials() {
+        let update = |json: serde_json::Value| -> UpdateRouteRequest {
+            serde_json::from_value(json).unwrap()
+        };
+        let stored = route(Some("admin"), Some("old"));
+        assert_eq!(
+            stored.updated_upstream_authorization(&update(serde_json::json!({
+                "upstream_auth_password": "new",
+            }))),
+            Some(basic_authorization("admin", "new"))
+        );
+        assert_eq!(
+            stored.updated_upstream_authorization(&update(serde_json::json!({
+                "upstream_auth_user": " ops ",
+            }))),
+            Some(basic_authorization("ops", "old"))
+        );
+        assert_eq!(
+            stored.updated_upstream_authorization(&update(serde_json::json!({
+                "upstream_auth_user": null,
+            }))),
+            None
+        );
+        assert_eq!(
+            stored.updated_upstream_authorization(&update(serde_json::json!({}))),
+            stored.upstream_authorization()
+        );
+    }
+
+    #[test]
+    fn credentials_are_never_serialized() {
+        let value = serde_json::to_value(route(Some("admin"), Some("hunter2"))).unwrap();
+        let json = value.to_string();
+        assert!(!json.contains("hunter2"));
+        assert!(value.get("upstream_auth_user").is_none());
+    }
+
+    #[test]
+    fn user_may_not_contain_a_colon() {
+        assert!(validate_user("admin").is_ok());
+        assert!(validate_user("ad:min").is_err());
+        assert!(validate_password("p:ss word").is_ok());
+        assert!(validate_password("bad\n").is_err());
+    }
+}
@@ -1,4 +1,8 @@
 //! WebSocket proxy handler - bidirectional WebSocket relay
+//!
+//! Flow control, keepalive and tunnel metrics are described in `tunnel`.
+//! Failed upgrades are logged straight away; a session is logged once, when
+//! it ends, with its duration and the bytes relayed each way.
 
 use axum::{
     extract::ws::{Message as AxumMessage, WebSocket, WebSocketUpgrade},
@@ -6,12 +10,43 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use chrono::Utc;
+use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
-use std::time::Instant;
-use tokio_tungstenite::{connect_async, tungstenite::Message as TungsteniteMessage};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request as UpstreamRequest;
+use tokio_tungstenite::tungstenite::http::{header, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::{
+    connect_async, tungstenite::Message as TungsteniteMessage, MaybeTlsStream, WebSocketStream,
+};
+
+use super::inflight::{InFlightGuard, IN_FLIGHT_ABORTED, IN_FLIGHT_ABORTED_STATUS};
+use super::request_log::RequestLogContext;
+use super::tunnel::{
+    Direction, TunnelMetrics, TunnelSnapshot, CLOSE_BUFFER_LIMIT, KEEPALIVE_PAYLOAD,
+};
+use super::{ProxyLimits, ProxyState};
+use crate::db::mongo::operation_logs::OperationLogDoc;
+use crate::models::ProxyRoute;
 
-use super::ProxyState;
-use crate::models::{AccessLog, ProxyRoute};
+type UpstreamSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Reason sent with close code 1011
+const BUFFER_LIMIT_REASON: &str = "buffer limit exceeded";
+/// Reason sent with close code 1001 when a reaped tunnel is closed
+const STALE_REASON: &str = "peer unresponsive";
+/// access_logs.upstream_error markers for sessions that did not end with a
+/// peer closing
+const BUFFER_LIMIT_MARKER: &str = "ws_buffer_limit";
+const STALE_MARKER: &str = "ws_peer_unresponsive";
+/// Time allowed for delivering a close frame to a peer
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Handle WebSocket upgrade request
 ///
@@ -22,10 +57,7 @@ pub async fn handle_websocket_upgrade(
     state: ProxyState,
     route: ProxyRoute,
     target_url: String,
-    client_ip: String,
-    path: String,
-    user_agent: Option<String>,
-    referer: Option<String>,
+    request_log: RequestLogContext,
 ) -> Response {
     let ws_url = match http_to_ws_url(&target_url) {
         Some(url) => url,
@@ -38,44 +70,56 @@ pub async fn handle_websocket_upgrade(
         }
     };
 
-    let timeout_ms = route.timeout_ms as u64;
-    let route_id = route.id;
-    let route_target = route.target.clone();
+    let upstream_request =
+        match upstream_request(&ws_url, route.upstream_authorization().as_deref()) {
+            Some(request) => request,
+            None => {
+                tracing::error!("Invalid upstream WebSocket URL: {}", ws_url);
+                return (StatusCode::BAD_GATEWAY, "Invalid upstream WebSocket URL").into_response();
+            }
+        };
 
     ws.on_upgrade(move |socket| {
-        websocket_bridge(
-            socket,
-            ws_url,
-            state,
-            route_id,
-            route_target,
-            timeout_ms,
-            client_ip,
-            path,
-            user_agent,
-            referer,
-        )
+        websocket_bridge(socket, upstream_request, state, route, request_log)
     })
 }
 
+/// Upstream handshake request, carrying the route's Basic auth when set
+/// (client headers are not forwarded to the upstream socket)
+fn upstream_request(ws_url: &str, authorization: Option<&str>) -> Option<UpstreamRequest> {
+    let mut request = ws_url.into_client_request().ok()?;
+    if let Some(authorization) = authorization {
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(authorization).ok()?,
+        );
+    }
+    Some(request)
+}
+
 /// Bidirectional WebSocket bridge between client and upstream
 async fn websocket_bridge(
     client_socket: WebSocket,
-    ws_url: String,
+    upstream_request: UpstreamRequest,
     state: ProxyState,
-    route_id: i32,
-    route_target: String,
-    timeout_ms: u64,
-    client_ip: String,
-    path: String,
-    user_agent: Option<String>,
-    referer: Option<String>,
+    route: ProxyRoute,
+    mut request_log: RequestLogContext,
 ) {
     let start_time = Instant::now();
+    // Upgrades are only accepted over HTTP/1.1
+    request_log.method = "WS".to_string();
+    request_log.http_version = "HTTP/1.1".to_string();
+    let path = request_log.path.clone();
+    let in_flight = state
+        .in_flight
+        .register("websocket", &request_log.client_ip, "WS", &path);
+    in_flight.set_route(route.id, &route.target);
 
     // Connect to upstream WebSocket with timeout
-    let connect_timeout = std::time::Duration::from_millis(timeout_ms);
-    let upstream_result = tokio::time::timeout(connect_timeout, connect_async(&ws_url)).await;
+    let connect_timeout = std::time::Duration::from_millis(route.timeout_ms as u64);
+    let ws_url = upstream_request.uri().to_string();
+    let upstream_result =
+        tokio::time::timeout(connect_timeout, connect_async(upstream_request)).await;
 
     let upstream_socket = match upstream_result {
         Ok(Ok((stream, _response))) => {
@@ -91,14 +135,12 @@ async fn websocket_bridge(
             );
             log_ws_access(
                 &state,
-                &client_ip,
-                &path,
-                Some(route_id),
-                Some(&route_target),
+                &request_log,
+                &route.target,
                 502,
                 start_time.elapsed().as_millis() as i32,
-                user_agent.as_deref(),
-                referer.as_deref(),
+                None,
+                None,
             )
             .await;
             return;
@@ -111,101 +153,394 @@ async fn websocket_bridge(
             );
             log_ws_access(
                 &state,
-                &client_ip,
-                &path,
-                Some(route_id),
-                Some(&route_target),
+                &request_log,
+                &route.target,
                 504,
                 start_time.elapsed().as_millis() as i32,
-                user_agent.as_deref(),
-                referer.as_deref(),
+                None,
+                None,
             )
             .await;
             return;
         }
     };
 
-    // Log successful WebSocket upgrade (101 Switching Protocols)
+    let connect_ms = start_time.elapsed().as_millis() as i32;
+    let limits = *state.proxy_limits.read().await;
+    let metrics = Arc::new(TunnelMetrics::default());
+    in_flight.attach_tunnel(metrics.clone());
+
+    let end = relay(
+        client_socket,
+        upstream_socket,
+        &in_flight,
+        &metrics,
+        &limits,
+    )
+    .await;
+
+    let tunnel = metrics.snapshot();
+    state
+        .tunnel_stats
+        .record(route.id, &tunnel, matches!(end, TunnelEnd::BufferLimit(_)));
+    let session_duration_ms = start_time.elapsed().as_millis() as i32;
+    tracing::info!(
+        "WebSocket session ended: {} -> {} (duration: {}ms, frames in/out: {}/{}, queue high-water: {} bytes)",
+        path,
+        ws_url,
+        session_duration_ms,
+        tunnel.frames_client_to_upstream,
+        tunnel.frames_upstream_to_client,
+        tunnel.queue_high_water_bytes
+    );
+
+    // One entry per session (101 Switching Protocols unless aborted)
+    let (status, marker) = match end {
+        TunnelEnd::Closed => (101, None),
+        TunnelEnd::Aborted => (IN_FLIGHT_ABORTED_STATUS as i32, Some(IN_FLIGHT_ABORTED)),
+        TunnelEnd::Reaped => (101, Some(STALE_MARKER)),
+        TunnelEnd::BufferLimit(_) => (101, Some(BUFFER_LIMIT_MARKER)),
+    };
     log_ws_access(
         &state,
-        &client_ip,
-        &path,
-        Some(route_id),
-        Some(&route_target),
-        101,
-        start_time.elapsed().as_millis() as i32,
-        user_agent.as_deref(),
-        referer.as_deref(),
+        &request_log,
+        &route.target,
+        status,
+        connect_ms,
+        marker,
+        Some(&tunnel),
     )
     .await;
 
-    // Split both sockets for bidirectional relay
+    match end {
+        TunnelEnd::Closed => {}
+        TunnelEnd::Aborted => {
+            tracing::warn!(
+                "WebSocket tunnel {} aborted by admin after {}ms ({} bytes relayed)",
+                in_flight.id(),
+                session_duration_ms,
+                in_flight.bytes()
+            );
+        }
+        TunnelEnd::Reaped => {
+            tracing::warn!(
+                "WebSocket tunnel {} reaped after {}ms: a peer stopped answering keepalives",
+                in_flight.id(),
+                session_duration_ms
+            );
+        }
+        TunnelEnd::BufferLimit(direction) => {
+            tracing::warn!(
+                "WebSocket tunnel {} closed with {}: more than {} bytes queued, {} too slow",
+                in_flight.id(),
+                CLOSE_BUFFER_LIMIT,
+                limits.ws_max_buffered_bytes,
+                direction.slow_side()
+            );
+            let _ = state
+                .app_state
+                .mongo
+                .insert_operation_log(&OperationLogDoc {
+                    operation_id: uuid::Uuid::new_v4().to_string(),
+                    operation_type: "ws_buffer_limit".to_string(),
+                    initiated_by: "proxy".to_string(),
+                    target: Some(path.clone()),
+                    status: "error".to_string(),
+                    result: Some(serde_json::json!({
+                        "route_id": route.id,
+                        "target": &route.target,
+                        "client_ip": &request_log.client_ip,
+                        "direction": direction,
+                        "slow_side": direction.slow_side(),
+                        "close_code": CLOSE_BUFFER_LIMIT,
+                        "max_buffered_bytes": limits.ws_max_buffered_bytes,
+                        "tunnel": &tunnel,
+                    })),
+                    error: Some(format!(
+                        "WebSocket buffer limit exceeded ({} too slow)",
+                        direction.slow_side()
+                    )),
+                    duration_ms: Some(tunnel.duration_ms),
+                    created_at: Utc::now().to_rfc3339(),
+                    operator: None,
+                })
+                .await;
+        }
+    }
+}
+
+/// Why a tunnel ended
+enum TunnelEnd {
+    /// A peer closed or disconnected; queued frames were delivered first
+    Closed,
+    /// Dashboard abort (DELETE /api/dashboard/in-flight/:id or
+    /// /api/dashboard/websockets/:id)
+    Aborted,
+    /// Stale-tunnel sweep: a peer went away without closing
+    Reaped,
+    /// Queued bytes exceeded `proxy_ws_max_buffered_kb`
+    BufferLimit(Direction),
+}
+
+/// Relay frames both ways through bounded queues until a peer leaves, the
+/// tunnel is aborted or the buffer cap is hit; sends the closing frames
+async fn relay(
+    client_socket: WebSocket,
+    upstream_socket: UpstreamSocket,
+    in_flight: &InFlightGuard,
+    metrics: &TunnelMetrics,
+    limits: &ProxyLimits,
+) -> TunnelEnd {
     let (mut client_sink, mut client_stream) = client_socket.split();
     let (mut upstream_sink, mut upstream_stream) = upstream_socket.split();
+    let (to_upstream, mut upstream_queue) = mpsc::channel(limits.ws_queue_frames);
+    let (to_client, mut client_queue) = mpsc::channel(limits.ws_queue_frames);
+    let pings = (to_upstream.downgrade(), to_client.downgrade());
 
-    // Bidirectional message relay using tokio::select!
-    loop {
-        tokio::select! {
-            // Client -> Upstream
-            client_msg = client_stream.next() => {
-                match client_msg {
-                    Some(Ok(msg)) => {
-                        if let Some(tung_msg) = axum_to_tungstenite(msg) {
-                            if tung_msg.is_close() {
-                                let _ = upstream_sink.send(tung_msg).await;
-                                break;
-                            }
-                            if let Err(e) = upstream_sink.send(tung_msg).await {
-                                tracing::debug!("WebSocket upstream send error: {}", e);
-                                break;
-                            }
-                        }
-                    }
-                    Some(Err(e)) => {
-                        tracing::debug!("WebSocket client read error: {}", e);
-                        break;
-                    }
-                    None => {
-                        // Client disconnected
-                        break;
-                    }
-                }
+    let end = tokio::select! {
+        _ = in_flight.aborted() => match in_flight.reaped() {
+            true => TunnelEnd::Reaped,
+            false => TunnelEnd::Aborted,
+        },
+        end = direction(
+            read_client(&mut client_stream, to_upstream, in_flight, metrics, limits),
+            write_upstream(&mut upstream_sink, &mut upstream_queue, metrics),
+        ) => end,
+        end = direction(
+            read_upstream(&mut upstream_stream, to_client, in_flight, metrics, limits),
+            write_client(&mut client_sink, &mut client_queue, metrics),
+        ) => end,
+        _ = keepalive(limits.ws_keepalive_interval, pings, metrics) => TunnelEnd::Closed,
+    };
+
+    let (upstream_close, client_close) = match end {
+        TunnelEnd::Closed => return end,
+        TunnelEnd::Aborted => (None, None),
+        TunnelEnd::Reaped => (
+            Some(CloseFrame {
+                code: CloseCode::Away,
+                reason: STALE_REASON.into(),
+            }),
+            Some(axum::extract::ws::CloseFrame {
+                code: 1001,
+                reason: STALE_REASON.into(),
+            }),
+        ),
+        TunnelEnd::BufferLimit(_) => (
+            Some(CloseFrame {
+                code: CloseCode::Error,
+                reason: BUFFER_LIMIT_REASON.into(),
+            }),
+            Some(axum::extract::ws::CloseFrame {
+                code: CLOSE_BUFFER_LIMIT,
+                reason: BUFFER_LIMIT_REASON.into(),
+            }),
+        ),
+    };
+    // A peer that stopped reading must not hold the bridge open
+    let _ = tokio::time::timeout(
+        CLOSE_TIMEOUT,
+        upstream_sink.send(TungsteniteMessage::Close(upstream_close)),
+    )
+    .await;
+    let _ = tokio::time::timeout(
+        CLOSE_TIMEOUT,
+        client_sink.send(AxumMessage::Close(client_close)),
+    )
+    .await;
+    end
+}
+
+/// One relay direction: ends when the reader hits the buffer cap, or once
+/// the writer has drained the queue after the reader stopped
+async fn direction(
+    reader: impl Future<Output = Option<TunnelEnd>>,
+    writer: impl Future<Output = TunnelEnd>,
+) -> TunnelEnd {
+    tokio::pin!(reader, writer);
+    tokio::select! {
+        Some(end) = &mut reader => end,
+        end = &mut writer => end,
+    }
+}
+
+/// Queue a frame read from one peer. Waits while the queue is full, which
+/// stops reading from that peer. Ok(false) once reading should stop.
+async fn enqueue(
+    queue: &mpsc::Sender<TungsteniteMessage>,
+    msg: TungsteniteMessage,
+    direction: Direction,
+    in_flight: &InFlightGuard,
+    metrics: &TunnelMetrics,
+    limits: &ProxyLimits,
+) -> Result<bool, TunnelEnd> {
+    let len = msg.len();
+    in_flight.add_bytes(len);
+    metrics.relayed(direction, len);
+    if metrics.queued(len) > limits.ws_max_buffered_bytes as u64 {
+        return Err(TunnelEnd::BufferLimit(direction));
+    }
+    let close = msg.is_close();
+    if queue.send(msg).await.is_err() {
+        return Ok(false);
+    }
+    Ok(!close)
+}
+
+/// Client -> upstream queue; dropping `queue` on return lets the writer drain
+async fn read_client(
+    stream: &mut SplitStream<WebSocket>,
+    queue: mpsc::Sender<TungsteniteMessage>,
+    in_flight: &InFlightGuard,
+    metrics: &TunnelMetrics,
+    limits: &ProxyLimits,
+) -> Option<TunnelEnd> {
+    while let Some(msg) = stream.next().await {
+        if msg.is_ok() {
+            metrics.heard(Direction::ClientToUpstream);
+        }
+        let msg = match msg {
+            Ok(AxumMessage::Pong(data)) if data == KEEPALIVE_PAYLOAD => continue,
+            Ok(msg) => msg,
+            Err(e) => {
+                tracing::debug!("WebSocket client read error: {}", e);
+                break;
             }
-            // Upstream -> Client
-            upstream_msg = upstream_stream.next() => {
-                match upstream_msg {
-                    Some(Ok(msg)) => {
-                        if let Some(axum_msg) = tungstenite_to_axum(msg) {
-                            if matches!(&axum_msg, AxumMessage::Close(_)) {
-                                let _ = client_sink.send(axum_msg).await;
-                                break;
-                            }
-                            if let Err(e) = client_sink.send(axum_msg).await {
-                                tracing::debug!("WebSocket client send error: {}", e);
-                                break;
-                            }
-                        }
-                    }
-                    Some(Err(e)) => {
-                        tracing::debug!("WebSocket upstream read error: {}", e);
-                        break;
-                    }
-                    None => {
-                        // Upstream disconnected
-                        break;
-                    }
-                }
+        };
+        let Some(msg) = axum_to_tungstenite(msg) else {
+            continue;
+        };
+        match enqueue(
+            &queue,
+            msg,
+            Direction::ClientToUpstream,
+            in_flight,
+            metrics,
+            limits,
+        )
+        .await
+        {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(end) => return Some(end),
+        }
+    }
+    None
+}
+
+/// Upstream -> client queue
+async fn read_upstream(
+    stream: &mut SplitStream<UpstreamSocket>,
+    queue: mpsc::Sender<TungsteniteMessage>,
+    in_flight: &InFlightGuard,
+    metrics: &TunnelMetrics,
+    limits: &ProxyLimits,
+) -> Option<TunnelEnd> {
+    while let Some(msg) = stream.next().await {
+        if msg.is_ok() {
+            metrics.heard(Direction::UpstreamToClient);
+        }
+        let msg = match msg {
+            Ok(TungsteniteMessage::Pong(data)) if data == KEEPALIVE_PAYLOAD => continue,
+            Ok(msg) => msg,
+            Err(e) => {
+                tracing::debug!("WebSocket upstream read error: {}", e);
+                break;
             }
+        };
+        match enqueue(
+            &queue,
+            msg,
+            Direction::UpstreamToClient,
+            in_flight,
+            metrics,
+            limits,
+        )
+        .await
+        {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(end) => return Some(end),
         }
     }
+    None
+}
 
-    let session_duration_ms = start_time.elapsed().as_millis() as i32;
-    tracing::info!(
-        "WebSocket session ended: {} -> {} (duration: {}ms)",
-        path,
-        ws_url,
-        session_duration_ms
-    );
+async fn write_upstream(
+    sink: &mut SplitSink<UpstreamSocket, TungsteniteMessage>,
+    queue: &mut mpsc::Receiver<TungsteniteMessage>,
+    metrics: &TunnelMetrics,
+) -> TunnelEnd {
+    while let Some(msg) = queue.recv().await {
+        metrics.dequeued(msg.len());
+        let close = msg.is_close();
+        if let Err(e) = sink.send(msg).await {
+            tracing::debug!("WebSocket upstream send error: {}", e);
+            break;
+        }
+        if close {
+            break;
+        }
+    }
+    TunnelEnd::Closed
+}
+
+async fn write_client(
+    sink: &mut SplitSink<WebSocket, AxumMessage>,
+    queue: &mut mpsc::Receiver<TungsteniteMessage>,
+    metrics: &TunnelMetrics,
+) -> TunnelEnd {
+    while let Some(msg) = queue.recv().await {
+        metrics.dequeued(msg.len());
+        let Some(msg) = tungstenite_to_axum(msg) else {
+            continue;
+        };
+        let close = matches!(msg, AxumMessage::Close(_));
+        if let Err(e) = sink.send(msg).await {
+            tracing::debug!("WebSocket client send error: {}", e);
+            break;
+        }
+        if close {
+            break;
+        }
+    }
+    TunnelEnd::Closed
+}
+
+/// Ping both peers whenever neither has sent anything for `interval`, so
+/// NAT and load balancer idle timeouts do not drop quiet tunnels. The pongs
+/// are swallowed by the readers.
+async fn keepalive(
+    interval: Option<Duration>,
+    (to_upstream, to_client): (
+        mpsc::WeakSender<TungsteniteMessage>,
+        mpsc::WeakSender<TungsteniteMessage>,
+    ),
+    metrics: &TunnelMetrics,
+) {
+    let Some(interval) = interval else {
+        return futures::future::pending().await;
+    };
+    loop {
+        tokio::time::sleep(interval.saturating_sub(metrics.idle())).await;
+        if metrics.idle() < interval {
+            continue;
+        }
+        for queue in [&to_upstream, &to_client] {
+            let Some(queue) = queue.upgrade() else {
+                continue;
+            };
+            // A full queue means traffic is flowing; skip the ping
+            metrics.queued(KEEPALIVE_PAYLOAD.len());
+            if queue
+                .try_send(TungsteniteMessage::Ping(KEEPALIVE_PAYLOAD.to_vec()))
+                .is_err()
+            {
+                metrics.dequeued(KEEPALIVE_PAYLOAD.len());
+            }
+        }
+        metrics.keepalive_sent();
+    }
 }
 
 /// Convert HTTP/HTTPS URL to WS/WSS URL
@@ -258,46 +593,37 @@ fn tungstenite_to_axum(msg: TungsteniteMessage) -> Option<AxumMessage> {
     }
 }
 
-/// Log WebSocket access to MongoDB (reuses existing AccessLog model)
+/// Log WebSocket access to MongoDB (reuses existing AccessLog model); a
+/// finished session carries its tunnel counters
 async fn log_ws_access(
     state: &ProxyState,
-    ip: &str,
-    path: &str,
-    route_id: Option<i32>,
-    target: Option<&str>,
+    request_log: &RequestLogContext,
+    target: &str,
     status: i32,
     response_time_ms: i32,
-    user_agent: Option<&str>,
-    referer: Option<&str>,
+    upstream_error: Option<&str>,
+    session: Option<&TunnelSnapshot>,
 ) {
-    // GeoIP lookup (non-blocking, memory-mapped read)
-    let geo = state.geoip.as_ref().and_then(|reader| reader.lookup(ip));
-
-    let log = AccessLog {
-        timestamp: Utc::now(),
-        ip: ip.to_string(),
-        method: "WS".to_string(),
-        path: path.to_string(),
-        route_id,
-        target: target.map(|s| s.to_string()),
+    let mut log = request_log.entry(
+        state,
+        Some(target),
         status,
         response_time_ms,
-        request_size: None,
-        response_size: None,
-        user_agent: user_agent.map(|s| s.to_string()),
-        referer: referer.map(|s| s.to_string()),
-        country_code: geo.as_ref().and_then(|g| g.country_code.clone()),
-        country: geo.as_ref().and_then(|g| g.country.clone()),
-        city: geo.as_ref().and_then(|g| g.city.clone()),
-        latitude: geo.as_ref().and_then(|g| g.latitude),
-        longitude: geo.as_ref().and_then(|g| g.longitude),
-    };
+        upstream_error,
+    );
+    log.request_size = session.map(|t| clamp_i32(t.bytes_client_to_upstream));
+    log.response_size = session.map(|t| clamp_i32(t.bytes_upstream_to_client));
+    log.session_duration_ms = session.map(|t| t.duration_ms);
 
     if let Err(e) = state.app_state.mongo.log_access(&log).await {
         tracing::warn!("Failed to log WebSocket access: {}", e);
     }
 }
 
+fn clamp_i32(n: u64) -> i32 {
+    n.min(i32::MAX as u64) as i32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
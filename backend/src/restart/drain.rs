@@ -0,0 +1,180 @@
+//! Pre-restart drain
+//!
+//! While a drain is active the proxy refuses new requests (the /api admin
+//! surface stays up) and the background syncers skip their next cycle. The
+//! restarter then waits for in-flight HTTP requests and running sync cycles
+//! to finish before rebooting. This is the only shutdown coordination in the
+//! backend; anything else that needs to quiesce should go through it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::proxy::InFlightTracker;
+
+/// How often the drain re-checks for remaining activity
+const DRAIN_POLL: Duration = Duration::from_secs(1);
+
+/// Shared drain flag plus running sync cycles by syncer name
+#[derive(Default)]
+pub struct DrainGate {
+    draining: AtomicBool,
+    active_syncs: Mutex<HashMap<&'static str, usize>>,
+}
+
+/// Activity still running when the grace period ran out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrainReport {
+    pub in_flight: usize,
+    pub active_syncs: Vec<&'static str>,
+}
+
+impl DrainReport {
+    pub fn is_idle(&self) -> bool {
+        self.in_flight == 0 && self.active_syncs.is_empty()
+    }
+
+    /// Human-readable reason for notifications
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.in_flight > 0 {
+            parts.push(format!("{} proxied request(s) in flight", self.in_flight));
+        }
+        if !self.active_syncs.is_empty() {
+            parts.push(format!("sync running: {}", self.active_syncs.join(", ")));
+        }
+        if parts.is_empty() {
+            "idle".to_string()
+        } else {
+            parts.join("; ")
+        }
+    }
+}
+
+impl DrainGate {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Mark one sync cycle of `name` as running until the guard drops
+    pub fn sync_cycle(self: &Arc<Self>, name: &'static str) -> SyncCycleGuard {
+        *self.lock().entry(name).or_insert(0) += 1;
+        SyncCycleGuard {
+            gate: self.clone(),
+            name,
+        }
+    }
+
+    /// Names of syncers with a cycle in progress, sorted
+    pub fn active_syncs(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self
+            .lock()
+            .iter()
+            .filter(|(_, n)| **n > 0)
+            .map(|(name, _)| *name)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn report(&self, in_flight: &InFlightTracker) -> DrainReport {
+        DrainReport {
+            in_flight: in_flight.count_kind("http"),
+            active_syncs: self.active_syncs(),
+        }
+    }
+
+    /// Start refusing new work and wait up to `grace` for running work to
+    /// finish. On success the gate stays closed (the caller restarts); on
+    /// timeout it is reopened and the remaining activity is returned.
+    pub async fn drain(
+        &self,
+        in_flight: &InFlightTracker,
+        grace: Duration,
+    ) -> Result<(), DrainReport> {
+        self.draining.store(true, Ordering::Relaxed);
+        tracing::warn!(
+            "[Drain] Draining for up to {}s before restart",
+            grace.as_secs()
+        );
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let report = self.report(in_flight);
+            if report.is_idle() {
+                tracing::info!("[Drain] Drained");
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                self.draining.store(false, Ordering::Relaxed);
+                tracing::warn!("[Drain] Grace period expired: {}", report.describe());
+                return Err(report);
+            }
+            tokio::time::sleep(DRAIN_POLL).await;
+        }
+    }
+
+    /// Reopen the gate (restart command failed)
+    pub fn cancel(&self) {
+        self.draining.store(false, Ordering::Relaxed);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<&'static str, usize>> {
+        self.active_syncs.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// Running sync cycle; see [`DrainGate::sync_cycle`]
+pub struct SyncCycleGuard {
+    gate: Arc<DrainGate>,
+    name: &'static str,
+}
+
+impl Drop for SyncCycleGuard {
+    fn drop(&mut self) {
+        let mut syncs = self.gate.lock();
+        if let Some(n) = syncs.get_mut(self.name) {
+            *n = n.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_cycles_are_counted_until_dropped() {
+        let gate = Arc::new(DrainGate::default());
+        let omada = gate.sync_cycle("omada");
+        let openwrt = gate.sync_cycle("openwrt");
+        let omada_again = gate.sync_cycle("omada");
+        assert_eq!(gate.active_syncs(), vec!["omada", "openwrt"]);
+
+        drop(omada);
+        drop(openwrt);
+        assert_eq!(gate.active_syncs(), vec!["omada"]);
+        drop(omada_again);
+        assert!(gate.active_syncs().is_empty());
+    }
+
+    #[test]
+    fn report_describes_remaining_activity() {
+        let report = DrainReport {
+            in_flight: 2,
+            active_syncs: vec!["omada"],
+        };
+        assert!(!report.is_idle());
+        assert_eq!(
+            report.describe(),
+            "2 proxied request(s) in flight; sync running: omada"
+        );
+        let idle = DrainReport {
+            in_flight: 0,
+            active_syncs: Vec::new(),
+        };
+        assert!(idle.is_idle());
+        assert_eq!(idle.describe(), "idle");
+    }
+}
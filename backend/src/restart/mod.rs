@@ -1,10 +1,55 @@
 //! Restart scheduler module
 //! Handles scheduled restarts and resource-based auto-restart
+//!
+//! Every restart first drains (see [`drain`]); when the grace period runs out
+//! with work still running, the restart is postponed by [`RETRY_DELAY_MIN`]
+//! minutes and a notification explains what was still busy.
+
+pub mod drain;
+
+pub use self::drain::DrainGate;
 
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 use crate::db::MySqlDb;
+use crate::proxy::InFlightTracker;
+use crate::sysmetrics::SystemMetrics;
+
+/// Default grace period for the pre-restart drain
+pub const DEFAULT_DRAIN_GRACE_SEC: u64 = 300;
+/// Delay before retrying a restart whose drain did not complete
+pub const RETRY_DELAY_MIN: i64 = 30;
+
+/// Pre-restart drain grace period (setting `restart_drain_grace_sec`)
+pub async fn drain_grace(db: &MySqlDb) -> Duration {
+    let secs = db
+        .get_setting_i32("restart_drain_grace_sec", DEFAULT_DRAIN_GRACE_SEC as i32)
+        .await
+        .unwrap_or(DEFAULT_DRAIN_GRACE_SEC as i32);
+    Duration::from_secs(secs.max(0) as u64)
+}
+
+/// Post a restart embed to the Discord webhook, if configured
+pub async fn notify_restart(db: &MySqlDb, title: &str, description: &str, color: u32) {
+    if let Ok(Some(webhook_url)) = db.get_discord_webhook_url().await {
+        let client = reqwest::Client::new();
+        let _ = client
+            .post(&webhook_url)
+            .json(&serde_json::json!({
+                "embeds": [{
+                    "title": title,
+                    "description": description,
+                    "color": color,
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "footer": {"text": "LacisProxyGateway2"}
+                }]
+            }))
+            .send()
+            .await;
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct RestartConfig {
@@ -18,6 +63,8 @@ pub struct RestartConfig {
     pub cpu_threshold: u32,
     /// RAM threshold percentage (default 90)
     pub ram_threshold: u32,
+    /// Max wait for in-flight requests and syncs before restarting
+    pub drain_grace_sec: u64,
     /// Last restart timestamp
     pub last_restart: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -30,6 +77,7 @@ impl Default for RestartConfig {
             auto_restart_enabled: false,
             cpu_threshold: 90,
             ram_threshold: 90,
+            drain_grace_sec: DEFAULT_DRAIN_GRACE_SEC,
             last_restart: None,
         }
     }
@@ -38,15 +86,29 @@ impl Default for RestartConfig {
 pub struct RestartScheduler {
     config: RwLock<RestartConfig>,
     db: Arc<MySqlDb>,
+    metrics: Arc<SystemMetrics>,
+    drain: Arc<DrainGate>,
+    in_flight: Arc<InFlightTracker>,
     last_scheduled_check: RwLock<Option<chrono::NaiveDate>>,
+    /// Postponed restart: when to retry and the original reason
+    pending_retry: RwLock<Option<(chrono::DateTime<chrono::Utc>, String)>>,
 }
 
 impl RestartScheduler {
-    pub fn new(db: Arc<MySqlDb>) -> Self {
+    pub fn new(
+        db: Arc<MySqlDb>,
+        metrics: Arc<SystemMetrics>,
+        drain: Arc<DrainGate>,
+        in_flight: Arc<InFlightTracker>,
+    ) -> Self {
         Self {
             config: RwLock::new(RestartConfig::default()),
             db,
+            metrics,
+            drain,
+            in_flight,
             last_scheduled_check: RwLock::new(None),
+            pending_retry: RwLock::new(None),
         }
     }
 
@@ -87,17 +149,23 @@ impl RestartScheduler {
                         config.ram_threshold = v.parse().unwrap_or(90);
                     }
                 }
+                "restart_drain_grace_sec" => {
+                    if let Some(v) = setting.setting_value {
+                        config.drain_grace_sec = v.parse().unwrap_or(DEFAULT_DRAIN_GRACE_SEC);
+                    }
+                }
                 _ => {}
             }
         }
 
         tracing::info!(
-            "[RestartScheduler] Config loaded: scheduled={}, time={}, auto={}, cpu_thresh={}%, ram_thresh={}%",
+            "[RestartScheduler] Config loaded: scheduled={}, time={}, auto={}, cpu_thresh={}%, ram_thresh={}%, drain_grace={}s",
             config.scheduled_enabled,
             config.scheduled_time,
             config.auto_restart_enabled,
             config.cpu_threshold,
-            config.ram_threshold
+            config.ram_threshold,
+            config.drain_grace_sec
         );
 
         Ok(())
@@ -108,64 +176,6 @@ impl RestartScheduler {
         self.config.read().await.clone()
     }
 
-    /// Get current CPU usage percentage
-    fn get_cpu_usage() -> f64 {
-        let read_stat = || -> (u64, u64) {
-            let content = std::fs::read_to_string("/proc/stat").unwrap_or_default();
-            if let Some(line) = content.lines().next() {
-                let parts: Vec<u64> = line
-                    .split_whitespace()
-                    .skip(1)
-                    .filter_map(|s| s.parse().ok())
-                    .collect();
-                if parts.len() >= 4 {
-                    let idle = parts[3];
-                    let total: u64 = parts.iter().sum();
-                    return (idle, total);
-                }
-            }
-            (0, 0)
-        };
-
-        let (idle1, total1) = read_stat();
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        let (idle2, total2) = read_stat();
-
-        let idle_delta = idle2.saturating_sub(idle1);
-        let total_delta = total2.saturating_sub(total1);
-
-        if total_delta > 0 {
-            ((total_delta - idle_delta) as f64 / total_delta as f64) * 100.0
-        } else {
-            0.0
-        }
-    }
-
-    /// Get current RAM usage percentage
-    fn get_ram_usage() -> f64 {
-        let content = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
-        let mut mem_total = 0u64;
-        let mut mem_available = 0u64;
-
-        for line in content.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let value: u64 = parts[1].parse().unwrap_or(0);
-                match parts[0] {
-                    "MemTotal:" => mem_total = value,
-                    "MemAvailable:" => mem_available = value,
-                    _ => {}
-                }
-            }
-        }
-
-        if mem_total > 0 {
-            ((mem_total - mem_available) as f64 / mem_total as f64) * 100.0
-        } else {
-            0.0
-        }
-    }
-
     /// Check if scheduled restart time has been reached
     fn should_scheduled_restart(&self, config: &RestartConfig) -> bool {
         if !config.scheduled_enabled {
@@ -179,27 +189,42 @@ impl RestartScheduler {
         current_time == config.scheduled_time
     }
 
-    /// Trigger system restart
+    /// Drain, then restart; postpone by RETRY_DELAY_MIN when the drain
+    /// does not complete within the grace period
     async fn trigger_restart(&self, reason: &str) {
+        let grace = Duration::from_secs(self.get_config().await.drain_grace_sec);
+        if let Err(report) = self.drain.drain(&self.in_flight, grace).await {
+            let retry_at = chrono::Utc::now() + chrono::Duration::minutes(RETRY_DELAY_MIN);
+            tracing::warn!(
+                "[RestartScheduler] Restart postponed to {}: {}",
+                retry_at.to_rfc3339(),
+                report.describe()
+            );
+            notify_restart(
+                &self.db,
+                "System Restart Postponed",
+                &format!(
+                    "{}\nDrain did not complete within {}s ({}). Retrying at {}.",
+                    reason,
+                    grace.as_secs(),
+                    report.describe(),
+                    retry_at.format("%Y-%m-%d %H:%M UTC")
+                ),
+                15105570,
+            )
+            .await;
+            *self.pending_retry.write().await = Some((retry_at, reason.to_string()));
+            return;
+        }
+
+        self.reboot(reason).await;
+    }
+
+    /// Reboot the host (drain already completed)
+    async fn reboot(&self, reason: &str) {
         tracing::warn!("[RestartScheduler] Triggering system restart: {}", reason);
 
-        // Send Discord notification if configured
-        if let Ok(Some(webhook_url)) = self.db.get_discord_webhook_url().await {
-            let client = reqwest::Client::new();
-            let _ = client
-                .post(&webhook_url)
-                .json(&serde_json::json!({
-                    "embeds": [{
-                        "title": "System Restart Triggered",
-                        "description": reason,
-                        "color": 15158332,
-                        "timestamp": chrono::Utc::now().to_rfc3339(),
-                        "footer": {"text": "LacisProxyGateway2"}
-                    }]
-                }))
-                .send()
-                .await;
-        }
+        notify_restart(&self.db, "System Restart Triggered", reason, 15158332).await;
 
         // Wait a moment for notification to send
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
@@ -218,9 +243,11 @@ impl RestartScheduler {
                     "[RestartScheduler] Restart command failed: {}",
                     String::from_utf8_lossy(&o.stderr)
                 );
+                self.drain.cancel();
             }
             Err(e) => {
                 tracing::error!("[RestartScheduler] Failed to execute restart: {}", e);
+                self.drain.cancel();
             }
         }
     }
@@ -246,6 +273,17 @@ impl RestartScheduler {
 
             let config = self.get_config().await;
 
+            // A postponed restart takes precedence; other triggers wait for it
+            let pending = self.pending_retry.read().await.clone();
+            if let Some((retry_at, reason)) = pending {
+                if chrono::Utc::now() >= retry_at {
+                    *self.pending_retry.write().await = None;
+                    self.trigger_restart(&format!("{} (retry after postponed drain)", reason))
+                        .await;
+                }
+                continue;
+            }
+
             // Check scheduled restart
             if self.should_scheduled_restart(&config) {
                 let today = chrono::Local::now().date_naive();
@@ -265,9 +303,15 @@ impl RestartScheduler {
             }
 
             // Check resource-based restart
-            if config.auto_restart_enabled {
-                let cpu_usage = Self::get_cpu_usage();
-                let ram_usage = Self::get_ram_usage();
+            // Uses the latest 30s sample from the shared metrics collector
+            let latest = if config.auto_restart_enabled {
+                self.metrics.latest()
+            } else {
+                None
+            };
+            if let Some(sample) = latest {
+                let cpu_usage = sample.cpu_percent;
+                let ram_usage = sample.memory_percent;
 
                 tracing::debug!(
                     "[RestartScheduler] CPU: {:.1}%, RAM: {:.1}%",
@@ -0,0 +1,420 @@
+//! Declarative route sync (PUT /api/routes/sync)
+//!
+//! The caller sends the whole desired route set. Each spec is keyed by its
+//! path and host (the DDNS hostname, or none for host-less routes) and
+//! compared against the live routes: unknown keys are created, differing
+//! routes get an update carrying only the changed fields, and live routes
+//! missing from the set are soft-deleted when pruning. Fields a spec cannot
+//! express (store-and-forward, transforms, log fields, status page) are left
+//! as they are, and so are upstream credentials when a spec carries none
+//! (route exports never include them); `upstream_auth_user: ""` clears them.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{CreateRouteRequest, ProxyRoute, RouteSecurityHeaders, UpdateRouteRequest};
+use crate::proxy::failover::{fallback_targets_column, MAX_RETRY_COUNT};
+use crate::proxy::header_rewrite::RouteHeaderRewrite;
+use crate::proxy::methods::allowed_methods_column;
+
+/// One desired route: a creation request plus the host it is keyed by
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteSpec {
+    /// DDNS hostname; omitted for routes without a DDNS config
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(flatten)]
+    pub route: CreateRouteRequest,
+}
+
+/// Body of PUT /api/routes/sync
+#[derive(Debug, Deserialize)]
+pub struct RouteSyncRequest {
+    pub routes: Vec<RouteSpec>,
+}
+
+/// Route identity within a sync
+pub type RouteKey = (String, Option<i32>);
+
+pub fn route_key(route: &ProxyRoute) -> RouteKey {
+    (route.path.clone(), route.ddns_config_id)
+}
+
+/// DDNS id for a spec's host (hostnames compare case-insensitively). An
+/// explicit `ddns_config_id` must agree with the host when both are given.
+pub fn resolve_host(
+    spec: &RouteSpec,
+    hostnames: &HashMap<String, i32>,
+) -> Result<Option<i32>, String> {
+    let by_host = match spec
+        .host
+        .as_deref()
+        .map(str::trim)
+        .filter(|h| !h.is_empty())
+    {
+        Some(host) => Some(
+            *hostnames
+                .get(&host.to_ascii_lowercase())
+                .ok_or_else(|| format!("No DDNS config for host {}", host))?,
+        ),
+        None => None,
+    };
+    match (by_host, spec.route.ddns_config_id) {
+        (Some(id), Some(explicit)) if id != explicit => Err(format!(
+            "host resolves to DDNS config {} but ddns_config_id is {}",
+            id, explicit
+        )),
+        (Some(id), _) => Ok(Some(id)),
+        (None, explicit) => Ok(explicit),
+    }
+}
+
+/// Update that turns `current` into `desired`, with the names of the
+/// changed fields (empty when the route already matches)
+pub fn diff_route(
+    current: &ProxyRoute,
+    desired: &CreateRouteRequest,
+) -> (UpdateRouteRequest, Vec<&'static str>) {
+    fn trimmed(value: Option<&str>) -> Option<&str> {
+        value.map(str::trim).filter(|v| !v.is_empty())
+    }
+
+    let mut update = UpdateRouteRequest::default();
+    let mut changed = Vec::new();
+
+    if current.target != desired.target {
+        update.target = Some(desired.target.clone());
+        changed.push("target");
+    }
+    if current.priority != desired.priority {
+        update.priority = Some(desired.priority);
+        changed.push("priority");
+    }
+    if current.active != desired.active {
+        update.active = Some(desired.active);
+        changed.push("active");
+    }
+    if current.strip_prefix != desired.strip_prefix {
+        update.strip_prefix = Some(desired.strip_prefix);
+        changed.push("strip_prefix");
+    }
+    if current.preserve_host != desired.preserve_host {
+        update.preserve_host = Some(desired.preserve_host);
+        changed.push("preserve_host");
+    }
+    if current.timeout_ms != desired.timeout_ms {
+        update.timeout_ms = Some(desired.timeout_ms);
+        changed.push("timeout_ms");
+    }
+    if current.websocket_support != desired.websocket_support {
+        update.websocket_support = Some(desired.websocket_support);
+        changed.push("websocket_support");
+    }
+    if current.admin_network_only != desired.admin_network_only {
+        update.admin_network_only = Some(desired.admin_network_only);
+        changed.push("admin_network_only");
+    }
+    if current.security_headers
+        != desired
+            .security_headers
+            .as_ref()
+            .and_then(RouteSecurityHeaders::to_column)
+    {
+        // An empty override clears the column
+        update.security_headers = Some(desired.security_headers.clone().unwrap_or_default());
+        changed.push("security_headers");
+    }
+    if current.allowed_methods != allowed_methods_column(desired.allowed_methods.as_deref()) {
+        update.allowed_methods = Some(desired.allowed_methods.clone());
+        changed.push("allowed_methods");
+    }
+    let expect = desired.expect_continue.unwrap_or_default();
+    if current.expect_continue() != expect {
+        update.expect_continue = Some(expect);
+        changed.push("expect_continue");
+    }
+    for (field, current_value, desired_value, slot) in [
+        (
+            "owner_name",
+            &current.owner_name,
+            &desired.owner_name,
+            &mut update.owner_name,
+        ),
+        (
+            "owner_contact",
+            &current.owner_contact,
+            &desired.owner_contact,
+            &mut update.owner_contact,
+        ),
+        ("team", &current.team, &desired.team, &mut update.team),
+    ] {
+        let desired_value = trimmed(desired_value.as_deref());
+        if trimmed(current_value.as_deref()) != desired_value {
+            // Empty string clears the field
+            *slot = Some(desired_value.unwrap_or_default().to_string());
+            changed.push(field);
+        }
+    }
+    let canary_target = trimmed(desired.canary_target.as_deref());
+    if trimmed(current.canary_target.as_deref()) != canary_target {
+        update.canary_target = Some(canary_target.map(str::to_string));
+        changed.push("canary_target");
+    }
+    let canary_percent = desired.canary_percent.filter(|p| *p > 0);
+    if current.canary_percent.filter(|p| *p > 0) != canary_percent {
+        update.canary_percent = Some(canary_percent);
+        changed.push("canary_percent");
+    }
+    if current.canary_sticky != desired.canary_sticky {
+        update.canary_sticky = Some(desired.canary_sticky);
+        changed.push("canary_sticky");
+    }
+    if current.grpc != desired.grpc {
+        update.grpc = Some(desired.grpc);
+        changed.push("grpc");
+    }
+    let grpc_health_service = desired.grpc_health_service.as_deref().map(str::trim);
+    if current.grpc_health_service.as_deref() != grpc_health_service {
+        update.grpc_health_service = Some(grpc_health_service.map(str::to_string));
+        changed.push("grpc_health_service");
+    }
+    let health_check_path = trimmed(desired.health_check_path.as_deref());
+    if trimmed(current.health_check_path.as_deref()) != health_check_path {
+        update.health_check_path = Some(health_check_path.map(str::to_string));
+        changed.push("health_check_path");
+    }
+    let health_check_interval = desired.health_check_interval_sec.filter(|s| *s > 0);
+    if current.health_check_interval_sec.filter(|s| *s > 0) != health_check_interval {
+        update.health_check_interval_sec = Some(health_check_interval);
+        changed.push("health_check_interval_sec");
+    }
+    if current.cache_ttl_sec != desired.cache_ttl_sec.max(0) {
+        update.cache_ttl_sec = Some(desired.cache_ttl_sec.max(0));
+        changed.push("cache_ttl_sec");
+    }
+    if current.header_rewrite
+        != desired
+            .header_rewrite
+            .as_ref()
+            .and_then(RouteHeaderRewrite::to_column)
+    {
+        // Empty rules clear the column
+        update.header_rewrite = Some(desired.header_rewrite.clone().unwrap_or_default());
+        changed.push("header_rewrite");
+    }
+    let connect_timeout = desired.connect_timeout_ms.filter(|ms| *ms > 0);
+    if current.connect_timeout_ms.filter(|ms| *ms > 0) != connect_timeout {
+        update.connect_timeout_ms = Some(connect_timeout);
+        changed.push("connect_timeout_ms");
+    }
+    if current.max_idle_per_host != desired.max_idle_per_host {
+        update.max_idle_per_host = Some(desired.max_idle_per_host);
+        changed.push("max_idle_per_host");
+    }
+    if current.fallback_targets != fallback_targets_column(desired.fallback_targets.as_deref()) {
+        update.fallback_targets = Some(desired.fallback_targets.clone());
+        changed.push("fallback_targets");
+    }
+    let retry_count = desired.retry_count.clamp(0, MAX_RETRY_COUNT);
+    if current.retry_count != retry_count {
+        update.retry_count = Some(retry_count);
+        changed.push("retry_count");
+    }
+    let retry_on = desired.retry_on.unwrap_or_default();
+    if current.retry_on() != retry_on {
+        update.retry_on = Some(retry_on);
+        changed.push("retry_on");
+    }
+    let max_body_bytes = desired.max_body_bytes.filter(|bytes| *bytes > 0);
+    if current.max_body_bytes != max_body_bytes {
+        update.max_body_bytes = Some(max_body_bytes);
+        changed.push("max_body_bytes");
+    }
+    // Compared, never echoed: the change list names the field only. No user
+    // keeps the stored credentials, an empty one clears them, and an omitted
+    // password keeps the stored password.
+    match desired.upstream_auth_user.as_deref().map(str::trim) {
+        None => {}
+        Some("") if current.upstream_auth_user.is_some() => {
+            update.upstream_auth_user = Some(None);
+            update.upstream_auth_password = Some(String::new());
+            changed.push("upstream_auth");
+        }
+        Some("") => {}
+        Some(user) => {
+            let password = desired
+                .upstream_auth_password
+                .as_deref()
+                .filter(|p| !p.is_empty());
+            let password_changed =
+                password.is_some_and(|p| current.upstream_auth_password.as_deref() != Some(p));
+            if trimmed(current.upstream_auth_user.as_deref()) != Some(user) || password_changed {
+                update.upstream_auth_user = Some(Some(user.to_string()));
+                update.upstream_auth_password = password.map(str::to_string);
+                changed.push("upstream_auth");
+            }
+        }
+    }
+
+    (update, changed)
+}
+
+/// What happened (or would happen) to one planned action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// Dry run, or a strict sync aborted before applying
+    Planned,
+    Applied,
+    Failed,
+    /// An earlier action failed and the pass stopped
+    NotApplied,
+}
+
+/// Planned creation, update or deletion
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncAction {
+    /// "create", "update" or "delete"
+    pub action: &'static str,
+    /// Index in the request (None for deletions)
+    pub index: Option<usize>,
+    pub path: String,
+    pub host: Option<String>,
+    /// None for creations until applied
+    pub route_id: Option<i32>,
+    /// Changed fields of an update
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<&'static str>,
+    pub status: SyncStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+}
+
+/// Entry or live route left alone
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncSkip {
+    pub index: Option<usize>,
+    pub path: String,
+    pub host: Option<String>,
+    pub route_id: Option<i32>,
+    pub reason: String,
+}
+
+/// Entry rejected by validation
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncError {
+    pub index: usize,
+    pub path: String,
+    pub host: Option<String>,
+    /// Error body as the single-route endpoints return it
+    pub error: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route() -> ProxyRoute {
+        ProxyRoute {
+            id: 4,
+            target: "http://10.0.0.5:8080".to_string(),
+            ddns_config_id: Some(2),
+            owner_name: Some("ops".to_string()),
+            ..ProxyRoute::test_default()
+        }
+    }
+
+    fn spec(value: serde_json::Value) -> RouteSpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn matching_spec_has_no_changes() {
+        let desired = spec(serde_json::json!({
+            "path": "/app",
+            "host": "home.example.com",
+            "target": "http://10.0.0.5:8080",
+            "owner_name": " ops ",
+            "canary_percent": 0,
+        }));
+        let (_, changed) = diff_route(&route(), &desired.route);
+        assert!(changed.is_empty(), "{:?}", changed);
+    }
+
+    #[test]
+    fn only_differing_fields_are_updated() {
+        let desired = spec(serde_json::json!({
+            "path": "/app",
+            "target": "http://10.0.0.6:8080",
+            "active": false,
+            "allowed_methods": ["get"],
+        }));
+        let (update, changed) = diff_route(&route(), &desired.route);
+        assert_eq!(
+            changed,
+            vec!["target", "active", "allowed_methods", "owner_name"]
+        );
+        assert_eq!(update.target.as_deref(), Some("http://10.0.0.6:8080"));
+        assert_eq!(update.active, Some(false));
+        assert_eq!(update.owner_name.as_deref(), Some(""));
+        assert!(update.priority.is_none());
+        assert!(update.canary_target.is_none());
+    }
+
+    #[test]
+    fn exported_routes_keep_upstream_credentials() {
+        let mut current = route();
+        current.upstream_auth_user = Some("svc".to_string());
+        current.upstream_auth_password = Some("secret".to_string());
+
+        // GET /api/routes never returns the credentials
+        let exported = serde_json::to_value(&current).unwrap();
+        assert!(exported.get("upstream_auth_user").is_none());
+        let desired = spec(exported);
+        let (update, changed) = diff_route(&current, &desired.route);
+        assert!(!changed.contains(&"upstream_auth"), "{:?}", changed);
+        assert!(update.upstream_auth_user.is_none());
+        assert!(update.upstream_auth_password.is_none());
+
+        let desired = spec(serde_json::json!({
+            "path": "/app",
+            "target": "http://10.0.0.5:8080",
+            "upstream_auth_user": "svc",
+        }));
+        let (_, changed) = diff_route(&current, &desired.route);
+        assert!(!changed.contains(&"upstream_auth"), "{:?}", changed);
+
+        let desired = spec(serde_json::json!({
+            "path": "/app",
+            "target": "http://10.0.0.5:8080",
+            "upstream_auth_user": "",
+        }));
+        let (update, changed) = diff_route(&current, &desired.route);
+        assert!(changed.contains(&"upstream_auth"));
+        assert_eq!(update.upstream_auth_user, Some(None));
+    }
+
+    #[test]
+    fn hosts_resolve_case_insensitively() {
+        let hostnames = HashMap::from([("home.example.com".to_string(), 2)]);
+        let with_host = |host: &str, id: Option<i32>| {
+            spec(serde_json::json!({
+                "path": "/",
+                "target": "http://a",
+                "host": host,
+                "ddns_config_id": id,
+            }))
+        };
+        assert_eq!(
+            resolve_host(&with_host("Home.Example.com", None), &hostnames),
+            Ok(Some(2))
+        );
+        assert_eq!(
+            resolve_host(&with_host("", Some(7)), &hostnames),
+            Ok(Some(7))
+        );
+        assert!(resolve_host(&with_host("other.example.com", None), &hostnames).is_err());
+        assert!(resolve_host(&with_host("home.example.com", Some(3)), &hostnames).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Route configuration history (route_versions)
+//!
+//! Every write to a route's configuration stores the whole route as JSON
+//! under the next version number, with the actor and what caused it.
+//! Rollbacks restore an earlier snapshot as a new version, so history is
+//! never rewritten. Writes LPG makes on its own (such as the baseline taken
+//! when the table is created) are recorded under `SYSTEM_ACTOR` so human
+//! edits stand out. Only the newest
+//! `route_version_retention` versions of a route are kept.
+
+use serde::Serialize;
+
+use crate::models::{ProxyRoute, RouteVersion};
+
+/// Actor of versions not caused by a user
+pub const SYSTEM_ACTOR: &str = "system";
+/// Versions kept per route when the setting is missing
+pub const DEFAULT_RETENTION: i32 = 50;
+/// Lowest accepted `route_version_retention`
+pub const MIN_RETENTION: i32 = 2;
+
+/// Snapshot fields that change without a configuration change
+const VOLATILE_FIELDS: &[&str] = &["id", "created_at", "updated_at", "deleted_at"];
+
+/// One field that differs between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    /// Null when the field did not exist in the older snapshot
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+/// A version with its changes against the one before it
+#[derive(Debug, Serialize)]
+pub struct RouteVersionEntry {
+    #[serde(flatten)]
+    pub version: RouteVersion,
+    /// Empty for the oldest version kept
+    pub changes: Vec<FieldChange>,
+}
+
+/// Stored form of a route
+pub fn snapshot(route: &ProxyRoute) -> serde_json::Value {
+    serde_json::to_value(route).unwrap_or(serde_json::Value::Null)
+}
+
+/// Changed configuration fields from `old` to `new`, ignoring volatile ones
+pub fn diff(old: &serde_json::Value, new: &serde_json::Value) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|field| !VOLATILE_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let from = old.get(field).cloned().unwrap_or_default();
+            let to = new.get(field).cloned().unwrap_or_default();
+            (from != to).then(|| FieldChange {
+                field: field.clone(),
+                from,
+                to,
+            })
+        })
+        .collect()
+}
+
+/// Attach diffs to versions listed newest first
+pub fn with_diffs(versions: Vec<RouteVersion>) -> Vec<RouteVersionEntry> {
+    let previous: Vec<Option<serde_json::Value>> = versions
+        .iter()
+        .skip(1)
+        .map(|v| Some(v.config.clone()))
+        .chain(std::iter::once(None))
+        .collect();
+
+    versions
+        .into_iter()
+        .zip(previous)
+        .map(|(version, previous)| {
+            let changes = previous
+                .map(|previous| diff(&previous, &version.config))
+                .unwrap_or_default();
+            RouteVersionEntry { version, changes }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn version(number: i32, config: serde_json::Value) -> RouteVersion {
+        RouteVersion {
+            id: number as i64,
+            route_id: 7,
+            version: number,
+            config,
+            actor: "admin".to_string(),
+            reason: "update".to_string(),
+            restored_version: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn diff_ignores_volatile_fields() {
+        let old = json!({ "id": 7, "target": "http://a", "timeout_ms": 30000, "updated_at": "x" });
+        let new = json!({ "id": 7, "target": "http://b", "timeout_ms": 30000, "updated_at": "y" });
+        assert_eq!(
+            diff(&old, &new),
+            vec![FieldChange {
+                field: "target".to_string(),
+                from: json!("http://a"),
+                to: json!("http://b"),
+            }]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn fields_missing_on_one_side_diff_against_null() {
+        let changes = diff(
+            &json!({ "path": "/a" }),
+            &json!({ "path": "/a", "team": "net" }),
+        );
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "team");
+        assert!(changes[0].from.is_null());
+    }
+
+    #[test]
+    fn versions_diff_against_the_next_older_one() {
+        let entries = with_diffs(vec![
+            version(3, json!({ "priority": 3 })),
+            version(2, json!({ "priority": 2 })),
+            version(1, json!({ "priority": 2 })),
+        ]);
+        assert_eq!(entries[0].changes[0].from, json!(2));
+        assert_eq!(entries[0].changes[0].to, json!(3));
+        assert!(entries[1].changes.is_empty());
+        assert!(entries[2].changes.is_empty());
+    }
+}
@@ -0,0 +1,607 @@
+//! Public status page (GET /status, GET /status.json)
+//!
+//! Routes opt in with `show_on_status_page` and a public display name; the
+//! page shows only that name, never the path, target or any address. Each
+//! service is operational, degraded or down according to its most recent
+//! health checks; failure streaks of at least `status_page_incident_minutes`
+//! in the last 24 hours are listed as incidents. The 90-day uptime bar reads
+//! `route_uptime_daily`, rolled up from `health_checks` by a leader-only job
+//! the same way `ip_stats` rolls up access logs.
+//!
+//! Both endpoints are unauthenticated. Snapshots are cached for 30 seconds
+//! (and sent with a matching `Cache-Control`), clients are limited per IP,
+//! and with `status_page_enabled` off the paths fall through to the proxy.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use crate::db::AppState;
+use crate::error::AppError;
+use crate::ip_stats::{day_key, days_to_roll_up};
+
+/// Rollup job id in `rollup_state`
+pub const ROLLUP_JOB: &str = "route_uptime_daily";
+/// Days on the uptime bar (and rollup retention)
+pub const UPTIME_DAYS: i32 = 90;
+/// Snapshot lifetime; also the `Cache-Control` max-age
+pub const CACHE_TTL: Duration = Duration::from_secs(30);
+/// Checks considered for a service's current state
+const RECENT_CHECKS: usize = 10;
+/// How far back incidents are listed
+const INCIDENT_WINDOW_HOURS: i64 = 24;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Rate limiter entries kept before expired ones are swept
+const RATE_SWEEP_AT: usize = 1024;
+/// Rollup interval (today's bar segment is at most this stale)
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(600);
+
+pub const DEFAULT_TITLE: &str = "Service Status";
+pub const DEFAULT_INCIDENT_MINUTES: i32 = 5;
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: i32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceState {
+    /// No health checks recorded yet
+    Unknown,
+    Operational,
+    /// Recent failures below the failure threshold
+    Degraded,
+    /// Failing for at least the failure threshold
+    Down,
+}
+
+impl ServiceState {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Unknown => "No data",
+            Self::Operational => "Operational",
+            Self::Degraded => "Degraded",
+            Self::Down => "Down",
+        }
+    }
+}
+
+/// One health check outcome (projection of `health_checks`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckPoint {
+    pub timestamp: DateTime<Utc>,
+    pub healthy: bool,
+}
+
+/// One route's health check totals for one UTC day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteUptimeDay {
+    pub route_id: i32,
+    /// UTC day, "YYYY-MM-DD"
+    pub day: String,
+    pub checks: u64,
+    pub healthy: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UptimeDay {
+    pub day: String,
+    /// None when the day has no checks
+    pub uptime_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub status: ServiceState,
+    /// Over the whole bar
+    pub uptime_percent: Option<f64>,
+    /// Oldest first
+    pub days: Vec<UptimeDay>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    pub service: String,
+    pub started_at: DateTime<Utc>,
+    /// None while ongoing
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub duration_secs: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    pub title: String,
+    /// Worst service state
+    pub status: ServiceState,
+    pub generated_at: DateTime<Utc>,
+    pub services: Vec<ServiceStatus>,
+    /// Newest first
+    pub incidents: Vec<Incident>,
+}
+
+/// A failure streak; `ended_at` is the first healthy check after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureSpan {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl FailureSpan {
+    pub fn duration(&self, now: DateTime<Utc>) -> chrono::Duration {
+        self.ended_at.unwrap_or(now) - self.started_at
+    }
+}
+
+/// Current state from the newest checks (newest first)
+pub fn service_state(recent: &[CheckPoint], failure_threshold: u32) -> ServiceState {
+    if recent.is_empty() {
+        return ServiceState::Unknown;
+    }
+    let failing = recent.iter().take_while(|c| !c.healthy).count();
+    if failing >= failure_threshold.max(1) as usize {
+        ServiceState::Down
+    } else if recent.iter().any(|c| !c.healthy) {
+        ServiceState::Degraded
+    } else {
+        ServiceState::Operational
+    }
+}
+
+/// Failure streaks in checks ordered oldest first
+pub fn failure_spans(checks: &[CheckPoint]) -> Vec<FailureSpan> {
+    let mut spans = Vec::new();
+    let mut open: Option<DateTime<Utc>> = None;
+    for check in checks {
+        match (check.healthy, open) {
+            (false, None) => open = Some(check.timestamp),
+            (true, Some(started_at)) => {
+                spans.push(FailureSpan {
+                    started_at,
+                    ended_at: Some(check.timestamp),
+                });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(started_at) = open {
+        spans.push(FailureSpan {
+            started_at,
+            ended_at: None,
+        });
+    }
+    spans
+}
+
+/// Bar segments of the last `days` days ending `today`, oldest first
+pub fn uptime_days(rows: &[&RouteUptimeDay], today: NaiveDate, days: i32) -> Vec<UptimeDay> {
+    let first = today - chrono::Duration::days(days.max(1) as i64 - 1);
+    first
+        .iter_days()
+        .take_while(|d| *d <= today)
+        .map(|d| {
+            let day = day_key(d);
+            let uptime_percent = rows
+                .iter()
+                .find(|r| r.day == day)
+                .and_then(|r| uptime_percent(r.checks, r.healthy));
+            UptimeDay {
+                day,
+                uptime_percent,
+            }
+        })
+        .collect()
+}
+
+fn uptime_percent(checks: u64, healthy: u64) -> Option<f64> {
+    (checks > 0).then(|| (healthy as f64 * 10000.0 / checks as f64).round() / 100.0)
+}
+
+/// Build the public snapshot from settings, opted-in routes and health data
+pub async fn build_snapshot(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<StatusSnapshot, AppError> {
+    let mysql = &app_state.mysql;
+    let mongo = &app_state.mongo;
+    let title = mysql
+        .get_setting("status_page_title")
+        .await?
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| DEFAULT_TITLE.to_string());
+    let incident_minutes = mysql
+        .get_setting_i32("status_page_incident_minutes", DEFAULT_INCIDENT_MINUTES)
+        .await
+        .unwrap_or(DEFAULT_INCIDENT_MINUTES)
+        .max(0);
+    let (_, _, failure_threshold) = mysql
+        .get_health_check_settings()
+        .await
+        .unwrap_or((60, 5000, 3));
+
+    let routes = mysql.list_status_page_routes().await?;
+    let today = now.date_naive();
+    let ids: Vec<i32> = routes.iter().map(|r| r.id).collect();
+    let rows = mongo
+        .route_uptime_days(&ids, today - chrono::Duration::days(UPTIME_DAYS as i64 - 1))
+        .await
+        .map_err(AppError::database)?;
+
+    let window_start = now - chrono::Duration::hours(INCIDENT_WINDOW_HOURS);
+    let min_duration = chrono::Duration::minutes(incident_minutes as i64);
+    let mut services = Vec::new();
+    let mut incidents = Vec::new();
+    for (index, route) in routes.iter().enumerate() {
+        let name = route
+            .status_page_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Service {}", index + 1));
+        let checks = mongo
+            .route_checks_since(route.id, window_start)
+            .await
+            .map_err(AppError::database)?;
+        let recent: Vec<CheckPoint> = checks.iter().rev().take(RECENT_CHECKS).cloned().collect();
+
+        let mut spans = failure_spans(&checks);
+        // A streak open at the window start began before it
+        if let (Some(first), Some(span)) = (checks.first(), spans.first_mut()) {
+            if !first.healthy {
+                if let Some(started_at) = mongo
+                    .failure_streak_start(route.id, window_start)
+                    .await
+                    .map_err(AppError::database)?
+                {
+                    span.started_at = span.started_at.min(started_at);
+                }
+            }
+        }
+        incidents.extend(
+            spans
+                .into_iter()
+                .filter(|s| s.duration(now) >= min_duration)
+                .map(|s| Incident {
+                    service: name.clone(),
+                    started_at: s.started_at,
+                    resolved_at: s.ended_at,
+                    duration_secs: s.duration(now).num_seconds(),
+                }),
+        );
+
+        let route_rows: Vec<&RouteUptimeDay> =
+            rows.iter().filter(|r| r.route_id == route.id).collect();
+        services.push(ServiceStatus {
+            name,
+            status: service_state(&recent, failure_threshold as u32),
+            uptime_percent: uptime_percent(
+                route_rows.iter().map(|r| r.checks).sum(),
+                route_rows.iter().map(|r| r.healthy).sum(),
+            ),
+            days: uptime_days(&route_rows, today, UPTIME_DAYS),
+        });
+    }
+    incidents.sort_by_key(|i| std::cmp::Reverse(i.started_at));
+
+    Ok(StatusSnapshot {
+        title,
+        status: services
+            .iter()
+            .map(|s| s.status)
+            .max()
+            .unwrap_or(ServiceState::Unknown),
+        generated_at: now,
+        services,
+        incidents,
+    })
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_duration(secs: i64) -> String {
+    let minutes = secs.max(0) / 60;
+    match (minutes / 1440, minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, _) => format!("{}d {}h", d, h),
+    }
+}
+
+fn bar_color(uptime: Option<f64>) -> &'static str {
+    match uptime {
+        None => "#d0d4d9",
+        Some(p) if p >= 99.5 => "#2fb463",
+        Some(p) if p >= 95.0 => "#e3b341",
+        Some(_) => "#d9534f",
+    }
+}
+
+fn state_color(state: ServiceState) -> &'static str {
+    match state {
+        ServiceState::Operational => "#2fb463",
+        ServiceState::Degraded => "#e3b341",
+        ServiceState::Down => "#d9534f",
+        ServiceState::Unknown => "#8a939c",
+    }
+}
+
+/// Self-contained HTML page (no scripts, no external assets)
+pub fn render_html(snapshot: &StatusSnapshot) -> String {
+    let title = escape_html(&snapshot.title);
+    let overall = match snapshot.status {
+        ServiceState::Operational => "All systems operational",
+        ServiceState::Degraded => "Some systems degraded",
+        ServiceState::Down => "Service outage",
+        ServiceState::Unknown => "Status unknown",
+    };
+
+    let mut services = String::new();
+    for service in &snapshot.services {
+        let bars: String = service
+            .days
+            .iter()
+            .map(|d| {
+                let tip = match d.uptime_percent {
+                    Some(p) => format!("{}: {:.2}%", d.day, p),
+                    None => format!("{}: no data", d.day),
+                };
+                format!(
+                    "<span class=\"bar\" style=\"background:{}\" title=\"{}\"></span>",
+                    bar_color(d.uptime_percent),
+                    tip
+                )
+            })
+            .collect();
+        let uptime = service
+            .uptime_percent
+            .map_or("no data".to_string(), |p| format!("{:.2}% uptime", p));
+        services.push_str(&format!(
+            "<section class=\"service\"><div class=\"row\"><strong>{}</strong>\
+             <span style=\"color:{}\">{}</span></div><div class=\"bars\">{}</div>\
+             <div class=\"row muted\"><span>{} days ago</span><span>{}</span><span>Today</span></div></section>",
+            escape_html(&service.name),
+            state_color(service.status),
+            service.status.label(),
+            bars,
+            UPTIME_DAYS,
+            uptime
+        ));
+    }
+    if snapshot.services.is_empty() {
+        services.push_str("<p class=\"muted\">No services are listed.</p>");
+    }
+
+    let mut incidents = String::new();
+    for incident in &snapshot.incidents {
+        let state = match incident.resolved_at {
+            Some(at) => format!("Resolved {}", at.format("%Y-%m-%d %H:%M UTC")),
+            None => "Ongoing".to_string(),
+        };
+        incidents.push_str(&format!(
+            "<li><strong>{}</strong> unavailable since {} ({}) &mdash; {}</li>",
+            escape_html(&incident.service),
+            incident.started_at.format("%Y-%m-%d %H:%M UTC"),
+            format_duration(incident.duration_secs),
+            state
+        ));
+    }
+    if incidents.is_empty() {
+        incidents.push_str("<li class=\"muted\">No incidents in the last 24 hours.</li>");
+    }
+
+    format!(
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{title}</title><style>\
+         body{{font-family:system-ui,sans-serif;max-width:820px;margin:2rem auto;padding:0 1rem;color:#1f2328}}\
+         .banner{{padding:1rem;border-radius:6px;color:#fff;background:{banner}}}\
+         .service{{border:1px solid #d0d7de;border-radius:6px;padding:.75rem;margin:.75rem 0}}\
+         .row{{display:flex;justify-content:space-between}}\
+         .bars{{display:flex;gap:1px;margin:.5rem 0}}\
+         .bar{{flex:1;height:28px;border-radius:1px}}\
+         .muted{{color:#656d76;font-size:.85rem}}\
+         </style></head><body><h1>{title}</h1><div class=\"banner\">{overall}</div>\
+         {services}<h2>Incidents</h2><ul>{incidents}</ul>\
+         <p class=\"muted\">Updated {updated}</p></body></html>",
+        title = title,
+        banner = state_color(snapshot.status),
+        overall = overall,
+        services = services,
+        incidents = incidents,
+        updated = snapshot.generated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    )
+}
+
+/// Cached snapshot and per-IP request counts, shared by both endpoints
+#[derive(Default)]
+pub struct StatusPageCache {
+    snapshot: Mutex<Option<(Instant, Arc<StatusSnapshot>)>>,
+    requests: std::sync::Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl StatusPageCache {
+    /// Cached snapshot, rebuilt by one request at a time once stale
+    pub async fn snapshot(&self, app_state: &AppState) -> Result<Arc<StatusSnapshot>, AppError> {
+        let mut cached = self.snapshot.lock().await;
+        if let Some((at, snapshot)) = cached.as_ref() {
+            if at.elapsed() < CACHE_TTL {
+                return Ok(snapshot.clone());
+            }
+        }
+        let snapshot = Arc::new(build_snapshot(app_state, Utc::now()).await?);
+        *cached = Some((Instant::now(), snapshot.clone()));
+        Ok(snapshot)
+    }
+
+    /// Drop the cached snapshot (after a route's listing changed)
+    pub async fn invalidate(&self) {
+        *self.snapshot.lock().await = None;
+    }
+
+    /// Count a request from `ip`; false once it exceeds `per_minute` in the
+    /// current window
+    pub fn allow(&self, ip: &str, per_minute: u32) -> bool {
+        let mut requests = self.requests.lock().unwrap_or_else(|p| p.into_inner());
+        if requests.len() >= RATE_SWEEP_AT {
+            requests.retain(|_, (start, _)| start.elapsed() < RATE_WINDOW);
+        }
+        let entry = requests
+            .entry(ip.to_string())
+            .or_insert((Instant::now(), 0));
+        if entry.0.elapsed() >= RATE_WINDOW {
+            *entry = (Instant::now(), 0);
+        }
+        entry.1 += 1;
+        entry.1 <= per_minute
+    }
+}
+
+/// Background rollup of health checks into `route_uptime_daily`
+pub struct RouteUptimeRollup {
+    app_state: AppState,
+}
+
+impl RouteUptimeRollup {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    /// Start the rollup loop (every 10 min)
+    pub async fn start(&self) {
+        tracing::info!("Starting route uptime rollup...");
+
+        let mut interval_timer = interval(ROLLUP_INTERVAL);
+        loop {
+            interval_timer.tick().await;
+            if let Err(e) = self.run_once(Utc::now()).await {
+                tracing::warn!("Route uptime rollup failed: {}", e);
+            }
+        }
+    }
+
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<(), String> {
+        let mongo = &self.app_state.mongo;
+        let today = now.date_naive();
+
+        let completed = mongo.rollup_completed_day(ROLLUP_JOB).await?;
+        for day in days_to_roll_up(completed, today, UPTIME_DAYS) {
+            let routes = mongo.roll_up_route_uptime_day(day).await?;
+            if day < today {
+                mongo.set_rollup_completed_day(ROLLUP_JOB, day).await?;
+                tracing::info!("Route uptime: rolled up {} ({} routes)", day, routes);
+            }
+        }
+
+        let cutoff = today - chrono::Duration::days(UPTIME_DAYS as i64 - 1);
+        let purged = mongo.purge_route_uptime(cutoff).await?;
+        if purged > 0 {
+            tracing::info!("Route uptime: purged {} rows before {}", purged, cutoff);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(pattern: &str) -> Vec<CheckPoint> {
+        let start = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        pattern
+            .chars()
+            .enumerate()
+            .map(|(i, c)| CheckPoint {
+                timestamp: start + chrono::Duration::minutes(i as i64),
+                healthy: c == '+',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn state_follows_recent_failures() {
+        // Newest first
+        assert_eq!(service_state(&[], 3), ServiceState::Unknown);
+        assert_eq!(service_state(&checks("+++"), 3), ServiceState::Operational);
+        assert_eq!(service_state(&checks("--+"), 3), ServiceState::Degraded);
+        assert_eq!(service_state(&checks("+-+"), 3), ServiceState::Degraded);
+        assert_eq!(service_state(&checks("---+"), 3), ServiceState::Down);
+    }
+
+    #[test]
+    fn failure_spans_end_at_first_healthy_check() {
+        let points = checks("+--+---");
+        let spans = failure_spans(&points);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].started_at, points[1].timestamp);
+        assert_eq!(spans[0].ended_at, Some(points[3].timestamp));
+        assert_eq!(spans[0].duration(Utc::now()), chrono::Duration::minutes(2));
+        assert_eq!(spans[1].started_at, points[4].timestamp);
+        assert_eq!(spans[1].ended_at, None);
+        assert!(failure_spans(&checks("+++")).is_empty());
+    }
+
+    #[test]
+    fn uptime_bar_is_gap_filled_oldest_first() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let row = RouteUptimeDay {
+            route_id: 1,
+            day: "2026-03-01".to_string(),
+            checks: 3,
+            healthy: 2,
+        };
+        let days = uptime_days(&[&row], today, 3);
+        let keys: Vec<_> = days.iter().map(|d| d.day.as_str()).collect();
+        assert_eq!(keys, ["2026-02-28", "2026-03-01", "2026-03-02"]);
+        let uptime: Vec<_> = days.iter().map(|d| d.uptime_percent).collect();
+        assert_eq!(uptime, [None, Some(66.67), None]);
+    }
+
+    #[test]
+    fn rate_limit_counts_per_ip() {
+        let cache = StatusPageCache::default();
+        assert!(cache.allow("203.0.113.7", 2));
+        assert!(cache.allow("203.0.113.7", 2));
+        assert!(!cache.allow("203.0.113.7", 2));
+        assert!(cache.allow("198.51.100.1", 2));
+    }
+
+    #[test]
+    fn html_escapes_public_names() {
+        let snapshot = StatusSnapshot {
+            title: "Status <b>".to_string(),
+            status: ServiceState::Operational,
+            generated_at: Utc::now(),
+            services: vec![ServiceStatus {
+                name: "A&B <script>".to_string(),
+                status: ServiceState::Operational,
+                uptime_percent: None,
+                days: Vec::new(),
+            }],
+            incidents: Vec::new(),
+        };
+        let html = render_html(&snapshot);
+        assert!(html.contains("Status &lt;b&gt;"));
+        assert!(html.contains("A&amp;B &lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+}
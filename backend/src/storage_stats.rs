@@ -0,0 +1,387 @@
+//! MongoDB storage sampling, growth rates and disk projection
+//!
+//! A leader-only sampler records per-collection sizes once an hour into the
+//! day's row of `storage_stats_daily` (the last sample of a day wins). Growth
+//! is the change in storage plus index size between the oldest and newest
+//! sample of the last `GROWTH_WINDOW_DAYS` days, per day. The projection
+//! divides the remaining room (filesystem free space, or the room left under
+//! `mongo_storage_max_mb`, the dependency probe's limit, when tighter) by the
+//! total growth; a projection
+//! inside `storage_growth_warning_days` is reported by GET /api/admin/storage
+//! and notified once per day.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+use crate::db::mongo::dependencies_health::MongoStorage;
+use crate::db::AppState;
+use crate::ip_stats::day_key;
+use crate::notify::DiscordNotifier;
+
+/// Warning horizon when the setting is missing
+pub const DEFAULT_WARNING_DAYS: i32 = 30;
+pub const SETTING_WARNING_DAYS: &str = "storage_growth_warning_days";
+/// Storage size limit of the dependency probe in MB (0 = filesystem only)
+pub const SETTING_MAX_MB: &str = "mongo_storage_max_mb";
+/// Samples used for growth rates
+pub const GROWTH_WINDOW_DAYS: i64 = 7;
+/// Samples kept
+const RETENTION_DAYS: i64 = 90;
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Sizes of one collection (bytes)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionSample {
+    pub name: String,
+    #[serde(default)]
+    pub count: u64,
+    /// Uncompressed document size
+    #[serde(default)]
+    pub size: u64,
+    /// Allocated on disk
+    #[serde(default)]
+    pub storage_size: u64,
+    #[serde(default)]
+    pub index_size: u64,
+}
+
+impl CollectionSample {
+    /// Bytes on disk, data plus indexes
+    pub fn disk_bytes(&self) -> u64 {
+        self.storage_size + self.index_size
+    }
+}
+
+/// One day's sample
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageSample {
+    /// UTC day, "YYYY-MM-DD"
+    pub day: String,
+    pub sampled_at: String,
+    #[serde(default)]
+    pub fs_used_size: Option<u64>,
+    #[serde(default)]
+    pub fs_total_size: Option<u64>,
+    #[serde(default)]
+    pub collections: Vec<CollectionSample>,
+}
+
+impl StorageSample {
+    pub fn disk_bytes(&self) -> u64 {
+        self.collections
+            .iter()
+            .map(CollectionSample::disk_bytes)
+            .sum()
+    }
+}
+
+/// Growth in bytes per day between the oldest and newest sample (oldest
+/// first); None with fewer than two days of samples. `bytes` picks the
+/// figure, returning None when the sample lacks it.
+pub fn growth_per_day<F>(samples: &[StorageSample], bytes: F) -> Option<f64>
+where
+    F: Fn(&StorageSample) -> Option<u64>,
+{
+    let parse = |s: &StorageSample| NaiveDate::parse_from_str(&s.day, "%Y-%m-%d").ok();
+    let mut with_bytes = samples.iter().filter_map(|s| Some((parse(s)?, bytes(s)?)));
+    let (first_day, first) = with_bytes.next()?;
+    let (last_day, last) = with_bytes.last()?;
+    let days = (last_day - first_day).num_days();
+    if days <= 0 {
+        return None;
+    }
+    Some((last as f64 - first as f64) / days as f64)
+}
+
+/// Growth of one collection; a collection missing from the oldest sample
+/// counts from zero there
+pub fn collection_growth(samples: &[StorageSample], name: &str) -> Option<f64> {
+    growth_per_day(samples, |s| {
+        Some(
+            s.collections
+                .iter()
+                .find(|c| c.name == name)
+                .map_or(0, CollectionSample::disk_bytes),
+        )
+    })
+}
+
+/// When the database runs out of room at its current growth
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageProjection {
+    /// Total growth of all collections, bytes per day
+    pub growth_bytes_per_day: Option<f64>,
+    /// Bytes left before the limit
+    pub remaining_bytes: Option<u64>,
+    /// "filesystem" or "mongo_storage_max_mb", whichever is tighter
+    pub limit: Option<&'static str>,
+    /// None when not growing or no limit is known
+    pub days_until_full: Option<f64>,
+    pub warning_days: i32,
+    pub warning: Option<String>,
+}
+
+/// Project the remaining room at the current growth
+pub fn project(
+    samples: &[StorageSample],
+    current_bytes: u64,
+    storage: Option<&MongoStorage>,
+    max_bytes: Option<u64>,
+    warning_days: i32,
+) -> StorageProjection {
+    let growth = growth_per_day(samples, |s| Some(s.disk_bytes()));
+    let fs_free = storage.and_then(|s| match (s.fs_used_size, s.fs_total_size) {
+        (Some(used), Some(total)) => Some(total.saturating_sub(used)),
+        _ => None,
+    });
+    // The probe compares dbStats storageSize with the limit
+    let used = storage.map_or(current_bytes, |s| s.storage_size);
+    let budget_free = max_bytes.map(|max| max.saturating_sub(used));
+    let (remaining, limit) = match (fs_free, budget_free) {
+        (Some(fs), Some(budget)) if budget < fs => (Some(budget), Some(SETTING_MAX_MB)),
+        (Some(fs), _) => (Some(fs), Some("filesystem")),
+        (None, Some(budget)) => (Some(budget), Some(SETTING_MAX_MB)),
+        (None, None) => (None, None),
+    };
+    let days_until_full = match (remaining, growth) {
+        (Some(remaining), Some(growth)) if growth > 0.0 => Some(remaining as f64 / growth),
+        _ => None,
+    };
+    let warning = days_until_full
+        .filter(|days| *days < warning_days as f64)
+        .map(|days| {
+            format!(
+                "MongoDB grows {} per day; the {} limit is reached in about {:.0} day(s)",
+                format_bytes(growth.unwrap_or_default() as u64),
+                limit.unwrap_or("storage"),
+                days.floor()
+            )
+        });
+    StorageProjection {
+        growth_bytes_per_day: growth,
+        remaining_bytes: remaining,
+        limit,
+        days_until_full,
+        warning_days,
+        warning,
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Warning horizon and storage budget from settings
+pub async fn projection_settings(app_state: &AppState) -> (i32, Option<u64>) {
+    let warning_days = app_state
+        .mysql
+        .get_setting_i32(SETTING_WARNING_DAYS, DEFAULT_WARNING_DAYS)
+        .await
+        .unwrap_or(DEFAULT_WARNING_DAYS)
+        .max(1);
+    let max_mb = app_state
+        .mysql
+        .get_setting_i32(SETTING_MAX_MB, 0)
+        .await
+        .unwrap_or(0);
+    let max_bytes = (max_mb > 0).then(|| max_mb as u64 * 1024 * 1024);
+    (warning_days, max_bytes)
+}
+
+/// Samples of the growth window, oldest first
+pub async fn recent_samples(app_state: &AppState) -> Result<Vec<StorageSample>, String> {
+    let since = Utc::now().date_naive() - chrono::Duration::days(GROWTH_WINDOW_DAYS);
+    app_state.mongo.list_storage_samples(&day_key(since)).await
+}
+
+/// Current sizes of every collection, largest first
+pub async fn take_sample(app_state: &AppState) -> Result<StorageSample, String> {
+    let mut collections = Vec::new();
+    for name in app_state.mongo.list_collections().await? {
+        match app_state.mongo.collection_storage(&name).await {
+            Ok((sample, _)) => collections.push(sample),
+            Err(e) => tracing::warn!("Storage sample skipped {}: {}", name, e),
+        }
+    }
+    collections.sort_by_key(|c| std::cmp::Reverse(c.disk_bytes()));
+    let storage = app_state.mongo.storage_stats().await.ok();
+    let now = Utc::now();
+    Ok(StorageSample {
+        day: day_key(now.date_naive()),
+        sampled_at: now.to_rfc3339(),
+        fs_used_size: storage.and_then(|s| s.fs_used_size),
+        fs_total_size: storage.and_then(|s| s.fs_total_size),
+        collections,
+    })
+}
+
+/// Hourly storage sampler
+pub struct StorageSampler {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    /// Day the projection warning was last sent
+    warned_day: Mutex<Option<String>>,
+}
+
+impl StorageSampler {
+    pub fn new(app_state: AppState, notifier: Arc<DiscordNotifier>) -> Self {
+        Self {
+            app_state,
+            notifier,
+            warned_day: Mutex::new(None),
+        }
+    }
+
+    /// Start the sampling loop
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting MongoDB storage sampler...");
+        let mut tick = interval(SAMPLE_INTERVAL);
+        loop {
+            tick.tick().await;
+            if let Err(e) = self.sample().await {
+                tracing::error!("Storage sampling failed: {}", e);
+            }
+        }
+    }
+
+    async fn sample(&self) -> Result<(), String> {
+        let sample = take_sample(&self.app_state).await?;
+        self.app_state.mongo.save_storage_sample(&sample).await?;
+
+        let before = Utc::now().date_naive() - chrono::Duration::days(RETENTION_DAYS);
+        self.app_state
+            .mongo
+            .purge_storage_samples(&day_key(before))
+            .await?;
+
+        let samples = recent_samples(&self.app_state).await?;
+        let storage = self.app_state.mongo.storage_stats().await.ok();
+        let (warning_days, max_bytes) = projection_settings(&self.app_state).await;
+        let projection = project(
+            &samples,
+            sample.disk_bytes(),
+            storage.as_ref(),
+            max_bytes,
+            warning_days,
+        );
+        let Some(warning) = projection.warning else {
+            return Ok(());
+        };
+
+        let mut warned_day = self.warned_day.lock().await;
+        if warned_day.as_deref() == Some(sample.day.as_str()) {
+            return Ok(());
+        }
+        *warned_day = Some(sample.day.clone());
+        tracing::warn!("{}", warning);
+        let largest: Vec<String> = sample
+            .collections
+            .iter()
+            .take(3)
+            .map(|c| {
+                let growth = collection_growth(&samples, &c.name)
+                    .map(|g| format!(", {}/day", format_bytes(g.max(0.0) as u64)))
+                    .unwrap_or_default();
+                format!("{} ({}{})", c.name, format_bytes(c.disk_bytes()), growth)
+            })
+            .collect();
+        self.notifier
+            .notify_storage_projection(&warning, &largest)
+            .await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(day: &str, sizes: &[(&str, u64)], fs: Option<(u64, u64)>) -> StorageSample {
+        StorageSample {
+            day: day.to_string(),
+            sampled_at: format!("{}T12:00:00+00:00", day),
+            fs_used_size: fs.map(|(used, _)| used),
+            fs_total_size: fs.map(|(_, total)| total),
+            collections: sizes
+                .iter()
+                .map(|(name, bytes)| CollectionSample {
+                    name: name.to_string(),
+                    storage_size: *bytes,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn growth_uses_oldest_and_newest_day() {
+        let samples = vec![
+            sample("2026-10-01", &[("health_checks", 1000)], None),
+            sample("2026-10-02", &[("health_checks", 9000)], None),
+            sample(
+                "2026-10-05",
+                &[("health_checks", 5000), ("access_logs", 400)],
+                None,
+            ),
+        ];
+        assert_eq!(collection_growth(&samples, "health_checks"), Some(1000.0));
+        assert_eq!(collection_growth(&samples, "access_logs"), Some(100.0));
+        assert_eq!(collection_growth(&samples[..1], "health_checks"), None);
+    }
+
+    #[test]
+    fn projection_warns_inside_horizon() {
+        let gb = 1024 * 1024 * 1024;
+        let samples = vec![
+            sample("2026-10-01", &[("health_checks", gb)], None),
+            sample("2026-10-03", &[("health_checks", 3 * gb)], None),
+        ];
+        let storage = MongoStorage {
+            storage_size: 3 * gb,
+            data_size: 3 * gb,
+            fs_used_size: Some(80 * gb),
+            fs_total_size: Some(100 * gb),
+        };
+
+        let projection = project(&samples, 3 * gb, Some(&storage), None, 30);
+        assert_eq!(projection.limit, Some("filesystem"));
+        assert_eq!(projection.days_until_full, Some(20.0));
+        assert!(projection.warning.unwrap().contains("about 20 day(s)"));
+
+        // A tighter storage budget takes over
+        let projection = project(&samples, 3 * gb, Some(&storage), Some(5 * gb), 30);
+        assert_eq!(projection.limit, Some(SETTING_MAX_MB));
+        assert_eq!(projection.days_until_full, Some(2.0));
+
+        let projection = project(&samples, 3 * gb, Some(&storage), None, 10);
+        assert!(projection.warning.is_none());
+    }
+
+    #[test]
+    fn shrinking_or_unknown_storage_has_no_projection() {
+        let samples = vec![
+            sample("2026-10-01", &[("a", 5000)], None),
+            sample("2026-10-02", &[("a", 4000)], None),
+        ];
+        let projection = project(&samples, 4000, None, Some(10_000), 30);
+        assert_eq!(projection.remaining_bytes, Some(6000));
+        assert!(projection.days_until_full.is_none());
+        assert!(project(&samples[..1], 4000, None, None, 30).limit.is_none());
+    }
+}
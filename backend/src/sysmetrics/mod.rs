@@ -0,0 +1,682 @@
+//! System metrics collector
+//!
+//! Samples /proc every 30 seconds and keeps the last hour in an in-memory
+//! ring buffer. Shared by the server-health endpoint and the RestartScheduler
+//! so /proc is parsed in one place.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::db::MySqlDb;
+
+/// Sampling interval of the background collector
+pub const SAMPLE_INTERVAL_SECS: u64 = 30;
+/// Ring buffer length (1 hour at 30s resolution)
+pub const HISTORY_LEN: usize = 120;
+/// Setting key: disk usage percentage that flags a mount as over threshold
+pub const SETTING_DISK_WARN_PERCENT: &str = "server_health_disk_warn_percent";
+pub const DEFAULT_DISK_WARN_PERCENT: i32 = 85;
+
+/// Minimum window for CPU/network deltas when no recent baseline exists
+const MIN_WINDOW: Duration = Duration::from_millis(500);
+
+/// Filesystem types that never hold data worth watching
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "tmpfs", "devtmpfs", "squashfs", "overlay", "proc", "sysfs", "cgroup", "cgroup2", "efivarfs",
+    "ramfs",
+];
+
+/// Cumulative jiffies of one `cpu` line in /proc/stat
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuTimes {
+    /// idle + iowait
+    pub idle: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Busy percentage between an earlier sample and this one
+    pub fn usage_since(&self, prev: &CpuTimes) -> f64 {
+        let total = self.total.saturating_sub(prev.total);
+        let idle = self.idle.saturating_sub(prev.idle);
+        if total == 0 {
+            return 0.0;
+        }
+        (total.saturating_sub(idle) as f64 / total as f64) * 100.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuStats {
+    pub usage_percent: f64,
+    pub per_core_percent: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadAverages {
+    pub one_min: f64,
+    pub five_min: f64,
+    pub fifteen_min: f64,
+    pub running_tasks: u32,
+    pub total_tasks: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryStats {
+    pub total_mb: u64,
+    pub used_mb: u64,
+    pub free_mb: u64,
+    pub available_mb: u64,
+    pub buffers_mb: u64,
+    pub cached_mb: u64,
+    pub usage_percent: f64,
+    pub swap_total_mb: u64,
+    pub swap_used_mb: u64,
+    pub swap_free_mb: u64,
+    pub swap_usage_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub device: String,
+    pub mount_point: String,
+    pub filesystem: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
+    pub usage_percent: f64,
+    /// usage_percent >= the configured warning threshold
+    pub over_threshold: bool,
+}
+
+/// Cumulative byte counters of one interface in /proc/net/dev
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetCounters {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceThroughput {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
+}
+
+/// Stats of the LPG process itself
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProcessStats {
+    pub pid: u32,
+    pub rss_mb: f64,
+    pub virtual_mb: f64,
+    pub threads: u32,
+    pub open_fds: u32,
+    /// Alive tokio tasks (None outside a runtime)
+    pub tokio_tasks: Option<usize>,
+}
+
+/// Full point-in-time view returned by the server-health endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemSnapshot {
+    pub timestamp: String,
+    /// Window the CPU and throughput figures were averaged over
+    pub window_secs: f64,
+    pub cpu: CpuStats,
+    pub load: LoadAverages,
+    pub memory: MemoryStats,
+    pub disks: Vec<DiskUsage>,
+    pub network: Vec<InterfaceThroughput>,
+    pub process: ProcessStats,
+}
+
+/// Compact ring-buffer entry for charting
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySample {
+    pub timestamp: String,
+    pub cpu_percent: f64,
+    pub per_core_percent: Vec<f64>,
+    pub load_one_min: f64,
+    pub memory_percent: f64,
+    pub swap_percent: f64,
+    pub disk_max_percent: f64,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
+    pub process_rss_mb: f64,
+    pub process_open_fds: u32,
+    pub tokio_tasks: Option<usize>,
+}
+
+impl From<&SystemSnapshot> for HistorySample {
+    fn from(s: &SystemSnapshot) -> Self {
+        Self {
+            timestamp: s.timestamp.clone(),
+            cpu_percent: s.cpu.usage_percent,
+            per_core_percent: s.cpu.per_core_percent.clone(),
+            load_one_min: s.load.one_min,
+            memory_percent: s.memory.usage_percent,
+            swap_percent: s.memory.swap_usage_percent,
+            disk_max_percent: s.disks.iter().map(|d| d.usage_percent).fold(0.0, f64::max),
+            rx_bps: s.network.iter().map(|i| i.rx_bps).sum(),
+            tx_bps: s.network.iter().map(|i| i.tx_bps).sum(),
+            process_rss_mb: s.process.rss_mb,
+            process_open_fds: s.process.open_fds,
+            tokio_tasks: s.process.tokio_tasks,
+        }
+    }
+}
+
+/// Raw cumulative counters used as the delta baseline
+#[derive(Debug, Clone)]
+struct RawCounters {
+    at: Instant,
+    cpu: CpuTimes,
+    cores: Vec<CpuTimes>,
+    net: Vec<NetCounters>,
+}
+
+impl RawCounters {
+    fn read() -> Self {
+        let (cpu, cores) = parse_proc_stat(&read_proc("/proc/stat"));
+        Self {
+            at: Instant::now(),
+            cpu,
+            cores,
+            net: parse_net_dev(&read_proc("/proc/net/dev")),
+        }
+    }
+}
+
+fn read_proc(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_default()
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        round1(part as f64 / whole as f64 * 100.0)
+    }
+}
+
+fn cpu_times(line: &str) -> CpuTimes {
+    let parts: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    if parts.len() < 4 {
+        return CpuTimes::default();
+    }
+    CpuTimes {
+        idle: parts[3] + parts.get(4).copied().unwrap_or(0),
+        total: parts.iter().sum(),
+    }
+}
+
+/// Parse /proc/stat into the aggregate line and per-core lines
+pub fn parse_proc_stat(content: &str) -> (CpuTimes, Vec<CpuTimes>) {
+    let mut aggregate = CpuTimes::default();
+    let mut cores = Vec::new();
+    for line in content.lines() {
+        if line.starts_with("cpu ") {
+            aggregate = cpu_times(line);
+        } else if line.starts_with("cpu") {
+            cores.push(cpu_times(line));
+        }
+    }
+    (aggregate, cores)
+}
+
+/// Parse /proc/meminfo (kB values) into MB figures
+pub fn parse_meminfo(content: &str) -> MemoryStats {
+    let mut total = 0u64;
+    let mut free = 0u64;
+    let mut available: Option<u64> = None;
+    let mut buffers = 0u64;
+    let mut cached = 0u64;
+    let mut swap_total = 0u64;
+    let mut swap_free = 0u64;
+
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        let value: u64 = parts[1].parse().unwrap_or(0);
+        match parts[0] {
+            "MemTotal:" => total = value,
+            "MemFree:" => free = value,
+            "MemAvailable:" => available = Some(value),
+            "Buffers:" => buffers = value,
+            "Cached:" => cached = value,
+            "SwapTotal:" => swap_total = value,
+            "SwapFree:" => swap_free = value,
+            _ => {}
+        }
+    }
+
+    // Old kernels lack MemAvailable; approximate it from free + page cache
+    let available = available.unwrap_or(free + buffers + cached);
+    let used = total.saturating_sub(available);
+    let swap_used = swap_total.saturating_sub(swap_free);
+
+    MemoryStats {
+        total_mb: total / 1024,
+        used_mb: used / 1024,
+        free_mb: free / 1024,
+        available_mb: available / 1024,
+        buffers_mb: buffers / 1024,
+        cached_mb: cached / 1024,
+        usage_percent: percent(used, total),
+        swap_total_mb: swap_total / 1024,
+        swap_used_mb: swap_used / 1024,
+        swap_free_mb: swap_free / 1024,
+        swap_usage_percent: percent(swap_used, swap_total),
+    }
+}
+
+/// Parse /proc/loadavg
+pub fn parse_loadavg(content: &str) -> LoadAverages {
+    let parts: Vec<&str> = content.split_whitespace().collect();
+    let load = |i: usize| parts.get(i).and_then(|s| s.parse().ok()).unwrap_or(0.0);
+    let (running, total) = parts
+        .get(3)
+        .and_then(|s| s.split_once('/'))
+        .map(|(r, t)| (r.parse().unwrap_or(0), t.parse().unwrap_or(0)))
+        .unwrap_or((0, 0));
+    LoadAverages {
+        one_min: load(0),
+        five_min: load(1),
+        fifteen_min: load(2),
+        running_tasks: running,
+        total_tasks: total,
+    }
+}
+
+/// Parse /proc/net/dev, skipping the loopback interface
+pub fn parse_net_dev(content: &str) -> Vec<NetCounters> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            let name = name.trim();
+            if name == "lo" {
+                return None;
+            }
+            let fields: Vec<u64> = rest
+                .split_whitespace()
+                .filter_map(|s| s.parse().ok())
+                .collect();
+            if fields.len() < 9 {
+                return None;
+            }
+            Some(NetCounters {
+                name: name.to_string(),
+                rx_bytes: fields[0],
+                tx_bytes: fields[8],
+            })
+        })
+        .collect()
+}
+
+/// Parse `df -B1 --output=source,fstype,size,used,avail,target`
+pub fn parse_df(output: &str, warn_percent: f64) -> Vec<DiskUsage> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 6 || PSEUDO_FILESYSTEMS.contains(&parts[1]) {
+                return None;
+            }
+            let total: u64 = parts[2].parse().ok()?;
+            if total == 0 {
+                return None;
+            }
+            let used: u64 = parts[3].parse().unwrap_or(0);
+            let free: u64 = parts[4].parse().unwrap_or(0);
+            // Same basis as df's Use%: reserved blocks are not counted as free
+            let usage_percent = percent(used, used + free);
+            Some(DiskUsage {
+                device: parts[0].to_string(),
+                // Mount points may contain spaces
+                mount_point: parts[5..].join(" "),
+                filesystem: parts[1].to_string(),
+                total_bytes: total,
+                used_bytes: used,
+                free_bytes: free,
+                usage_percent,
+                over_threshold: usage_percent >= warn_percent,
+            })
+        })
+        .collect()
+}
+
+/// Per-interface throughput between two counter sets
+pub fn throughput(
+    prev: &[NetCounters],
+    now: &[NetCounters],
+    elapsed_secs: f64,
+) -> Vec<InterfaceThroughput> {
+    now.iter()
+        .map(|cur| {
+            let rate = |prev_bytes: Option<u64>, cur_bytes: u64| match prev_bytes {
+                Some(p) if elapsed_secs > 0.0 => {
+                    round1(cur_bytes.saturating_sub(p) as f64 / elapsed_secs)
+                }
+                _ => 0.0,
+            };
+            let before = prev.iter().find(|p| p.name == cur.name);
+            InterfaceThroughput {
+                name: cur.name.clone(),
+                rx_bytes: cur.rx_bytes,
+                tx_bytes: cur.tx_bytes,
+                rx_bps: rate(before.map(|p| p.rx_bytes), cur.rx_bytes),
+                tx_bps: rate(before.map(|p| p.tx_bytes), cur.tx_bytes),
+            }
+        })
+        .collect()
+}
+
+fn read_disks(warn_percent: f64) -> Vec<DiskUsage> {
+    let output = std::process::Command::new("df")
+        .args(["-B1", "--output=source,fstype,size,used,avail,target"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
+        .unwrap_or_default();
+    parse_df(&output, warn_percent)
+}
+
+fn read_process_stats() -> ProcessStats {
+    let status = read_proc("/proc/self/status");
+    let kb = |key: &str| -> f64 {
+        status
+            .lines()
+            .find(|l| l.starts_with(key))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    let threads = status
+        .lines()
+        .find(|l| l.starts_with("Threads:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let open_fds = std::fs::read_dir("/proc/self/fd")
+        .map(|d| d.count() as u32)
+        .unwrap_or(0);
+
+    ProcessStats {
+        pid: std::process::id(),
+        rss_mb: round1(kb("VmRSS:") / 1024.0),
+        virtual_mb: round1(kb("VmSize:") / 1024.0),
+        threads,
+        open_fds,
+        tokio_tasks: tokio::runtime::Handle::try_current()
+            .ok()
+            .map(|h| h.metrics().num_alive_tasks()),
+    }
+}
+
+/// Shared collector: delta baseline + 1 hour ring buffer
+pub struct SystemMetrics {
+    baseline: Mutex<Option<RawCounters>>,
+    history: Mutex<VecDeque<HistorySample>>,
+}
+
+impl Default for SystemMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemMetrics {
+    pub fn new() -> Self {
+        Self {
+            baseline: Mutex::new(None),
+            history: Mutex::new(VecDeque::with_capacity(HISTORY_LEN)),
+        }
+    }
+
+    /// Take a snapshot. CPU and throughput are averaged since the last
+    /// background sample; with no usable baseline a 500ms window is used.
+    /// `advance` makes this snapshot the new baseline and records it.
+    async fn collect(&self, warn_percent: f64, advance: bool) -> SystemSnapshot {
+        let baseline = self
+            .baseline
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|b| b.at.elapsed() >= MIN_WINDOW);
+        let prev = match baseline {
+            Some(b) => b,
+            None => {
+                let b = RawCounters::read();
+                tokio::time::sleep(MIN_WINDOW).await;
+                b
+            }
+        };
+        let now = RawCounters::read();
+        let elapsed = now.at.duration_since(prev.at).as_secs_f64();
+
+        let per_core_percent = now
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(i, c)| round1(c.usage_since(&prev.cores.get(i).copied().unwrap_or_default())))
+            .collect();
+
+        let snapshot = SystemSnapshot {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            window_secs: round1(elapsed),
+            cpu: CpuStats {
+                usage_percent: round1(now.cpu.usage_since(&prev.cpu)),
+                per_core_percent,
+            },
+            load: parse_loadavg(&read_proc("/proc/loadavg")),
+            memory: parse_meminfo(&read_proc("/proc/meminfo")),
+            disks: read_disks(warn_percent),
+            network: throughput(&prev.net, &now.net, elapsed),
+            process: read_process_stats(),
+        };
+
+        if advance {
+            *self.baseline.lock().unwrap() = Some(now);
+            self.push_history(HistorySample::from(&snapshot));
+        }
+        snapshot
+    }
+
+    fn push_history(&self, sample: HistorySample) {
+        let mut history = self.history.lock().unwrap();
+        if history.len() >= HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(sample);
+    }
+
+    /// Live snapshot for the dashboard (does not disturb the sampling series)
+    pub async fn current(&self, warn_percent: f64) -> SystemSnapshot {
+        self.collect(warn_percent, false).await
+    }
+
+    /// Most recent background sample
+    pub fn latest(&self) -> Option<HistorySample> {
+        self.history.lock().unwrap().back().cloned()
+    }
+
+    /// Ring buffer contents, oldest first
+    pub fn history(&self) -> Vec<HistorySample> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Background sampling loop (every 30 seconds)
+    pub async fn start(self: Arc<Self>, mysql: Arc<MySqlDb>) {
+        tracing::info!(
+            "[SystemMetrics] Sampling every {}s ({} samples retained)",
+            SAMPLE_INTERVAL_SECS,
+            HISTORY_LEN
+        );
+        let mut timer = tokio::time::interval(Duration::from_secs(SAMPLE_INTERVAL_SECS));
+        loop {
+            timer.tick().await;
+            let warn = disk_warn_percent(&mysql).await;
+            self.collect(warn, true).await;
+        }
+    }
+}
+
+/// Disk warning threshold from settings
+pub async fn disk_warn_percent(mysql: &MySqlDb) -> f64 {
+    mysql
+        .get_setting_i32(SETTING_DISK_WARN_PERCENT, DEFAULT_DISK_WARN_PERCENT)
+        .await
+        .unwrap_or(DEFAULT_DISK_WARN_PERCENT) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_STAT: &str = "cpu  100 0 100 700 100 0 0 0 0 0\n\
+cpu0 50 0 50 350 50 0 0 0 0 0\n\
+cpu1 50 0 50 350 50 0 0 0 0 0\n\
+intr 12345\n\
+ctxt 999\n";
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let (cpu, cores) = parse_proc_stat(PROC_STAT);
+        assert_eq!(
+            cpu,
+            CpuTimes {
+                idle: 800,
+                total: 1000
+            }
+        );
+        assert_eq!(cores.len(), 2);
+        assert_eq!(cores[1].total, 500);
+    }
+
+    #[test]
+    fn test_usage_since() {
+        let prev = CpuTimes {
+            idle: 800,
+            total: 1000,
+        };
+        let now = CpuTimes {
+            idle: 850,
+            total: 1200,
+        };
+        assert_eq!(now.usage_since(&prev), 75.0);
+        assert_eq!(prev.usage_since(&prev), 0.0);
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let content = "MemTotal:       8000000 kB\n\
+MemFree:        1000000 kB\n\
+MemAvailable:   6000000 kB\n\
+Buffers:         512000 kB\n\
+Cached:         2048000 kB\n\
+SwapTotal:      2048000 kB\n\
+SwapFree:       1024000 kB\n";
+        let m = parse_meminfo(content);
+        assert_eq!(m.total_mb, 7812);
+        assert_eq!(m.available_mb, 5859);
+        assert_eq!(m.buffers_mb, 500);
+        assert_eq!(m.cached_mb, 2000);
+        assert_eq!(m.usage_percent, 25.0);
+        assert_eq!(m.swap_used_mb, 1000);
+        assert_eq!(m.swap_usage_percent, 50.0);
+    }
+
+    #[test]
+    fn test_parse_loadavg() {
+        let l = parse_loadavg("0.52 0.48 0.40 3/812 12345\n");
+        assert_eq!(l.one_min, 0.52);
+        assert_eq!(l.fifteen_min, 0.40);
+        assert_eq!(l.running_tasks, 3);
+        assert_eq!(l.total_tasks, 812);
+    }
+
+    #[test]
+    fn test_parse_net_dev_and_throughput() {
+        let before = "Inter-|   Receive |  Transmit\n face |bytes packets\n\
+    lo: 5000 10 0 0 0 0 0 0 5000 10 0 0 0 0 0 0\n\
+  eth0: 1000 10 0 0 0 0 0 0 2000 20 0 0 0 0 0 0\n";
+        let after = "Inter-|   Receive |  Transmit\n face |bytes packets\n\
+    lo: 9000 10 0 0 0 0 0 0 9000 10 0 0 0 0 0 0\n\
+  eth0: 4000 10 0 0 0 0 0 0 2600 20 0 0 0 0 0 0\n\
+   wg0: 100 1 0 0 0 0 0 0 100 1 0 0 0 0 0 0\n";
+        let prev = parse_net_dev(before);
+        assert_eq!(prev.len(), 1);
+        assert_eq!(prev[0].tx_bytes, 2000);
+
+        let rates = throughput(&prev, &parse_net_dev(after), 2.0);
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].rx_bps, 1500.0);
+        assert_eq!(rates[0].tx_bps, 300.0);
+        // New interface has no baseline yet
+        assert_eq!(rates[1].rx_bps, 0.0);
+    }
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     Type     1B-blocks        Used      Avail Mounted on\n\
+/dev/sda1      ext4     100000000 90000000 10000000 /\n\
+tmpfs          tmpfs      1000000        0  1000000 /run\n\
+/dev/sdb1      xfs      200000000 20000000 180000000 /mnt/data disk\n";
+        let disks = parse_df(output, 85.0);
+        assert_eq!(disks.len(), 2);
+        assert_eq!(disks[0].mount_point, "/");
+        assert_eq!(disks[0].usage_percent, 90.0);
+        assert!(disks[0].over_threshold);
+        assert_eq!(disks[1].mount_point, "/mnt/data disk");
+        assert!(!disks[1].over_threshold);
+    }
+
+    #[test]
+    fn test_history_ring_buffer_is_bounded() {
+        let metrics = SystemMetrics::new();
+        let sample = HistorySample {
+            timestamp: String::new(),
+            cpu_percent: 0.0,
+            per_core_percent: vec![],
+            load_one_min: 0.0,
+            memory_percent: 0.0,
+            swap_percent: 0.0,
+            disk_max_percent: 0.0,
+            rx_bps: 0.0,
+            tx_bps: 0.0,
+            process_rss_mb: 0.0,
+            process_open_fds: 0,
+            tokio_tasks: None,
+        };
+        for i in 0..HISTORY_LEN + 5 {
+            metrics.push_history(HistorySample {
+                cpu_percent: i as f64,
+                ..sample.clone()
+            });
+        }
+        let history = metrics.history();
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history[0].cpu_percent, 5.0);
+        assert_eq!(
+            metrics.latest().unwrap().cpu_percent,
+            (HISTORY_LEN + 4) as f64
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Throwaway database containers driven through the docker CLI
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A running container with one published port; removed on drop
+pub struct Container {
+    id: String,
+    port: u16,
+}
+
+/// Whether a docker daemon answers
+pub fn docker_available() -> bool {
+    Command::new("docker")
+        .args(["info", "--format", "{{.ServerVersion}}"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn docker(args: &[String]) -> Result<String, String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|e| format!("docker not runnable: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "docker {} failed: {}",
+            args.first().map(String::as_str).unwrap_or(""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl Container {
+    /// `docker run` an image, publishing `container_port` on an ephemeral
+    /// loopback port. Mounts are (host file, container path), read-only.
+    pub fn run(
+        image: &str,
+        container_port: u16,
+        env: &[(&str, &str)],
+        mounts: &[(&Path, &str)],
+    ) -> Result<Self, String> {
+        let mut args: Vec<String> = vec![
+            "run".into(),
+            "-d".into(),
+            "--rm".into(),
+            "-p".into(),
+            format!("127.0.0.1::{}", container_port),
+        ];
+        for (key, value) in env {
+            args.push("-e".into());
+            args.push(format!("{}={}", key, value));
+        }
+        for (host, target) in mounts {
+            let host = host
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", host.display(), e))?;
+            args.push("-v".into());
+            args.push(format!("{}:{}:ro", host.display(), target));
+        }
+        args.push(image.into());
+
+        // Removed again by Drop if the port lookup fails
+        let mut container = Self {
+            id: docker(&args)?,
+            port: 0,
+        };
+        let published = docker(&[
+            "port".into(),
+            container.id.clone(),
+            format!("{}/tcp", container_port),
+        ])?;
+        container.port = published
+            .lines()
+            .find_map(|line| line.rsplit(':').next()?.parse().ok())
+            .ok_or_else(|| format!("no published port in {:?}", published))?;
+
+        Ok(container)
+    }
+
+    /// Host port mapped to the container port
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .args(["rm", "-f", "-v", &self.id])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
@@ -0,0 +1,647 @@
+//! End-to-end flows against `TestApp` (need docker: `cargo test e2e -- --ignored`)
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::{eventually, json, TestApp, TEST_LOGIN_FLOOR};
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_route_crud_reload_and_proxy_match() {
+    let app = TestApp::spawn().await;
+    let id = app.create_route("/e2e").await;
+
+    let routes = json(app.get("/api/routes", 0).send().await.unwrap()).await;
+    assert!(routes
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|r| r["id"] == id && r["path"] == "/e2e"));
+
+    // The router is reloaded on create: the prefix is stripped by default
+    let res = app
+        .proxy("/e2e/hello?x=1", "198.51.100.10")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let seen = app.upstream.requests();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(
+        (seen[0].method.as_str(), seen[0].path.as_str()),
+        ("GET", "/hello?x=1")
+    );
+    assert_eq!(
+        seen[0].headers.get("x-real-ip").map(String::as_str),
+        Some("198.51.100.10")
+    );
+
+    // ... and on update
+    let res = app
+        .put(&format!("/api/routes/{}", id), 80)
+        .json(&serde_json::json!({ "strip_prefix": false }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    app.proxy("/e2e/hello", "198.51.100.10")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(app.upstream.requests()[1].path, "/e2e/hello");
+
+    // ... and on delete
+    let res = app
+        .delete(&format!("/api/routes/{}?confirm=true", id), 100)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app
+        .proxy("/e2e/hello", "198.51.100.10")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 404);
+    assert_eq!(app.upstream.requests().len(), 2);
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_blocked_ip_enforcement() {
+    let app = TestApp::spawn().await;
+    app.create_route("/blocked").await;
+
+    let res = app.proxy("/blocked/a", "203.0.113.7").send().await.unwrap();
+    assert_eq!(res.status(), 200);
+
+    let res = app
+        .post("/api/security/blocked-ips", 80)
+        .json(&serde_json::json!({ "ip": "203.0.113.0/24", "reason": "e2e" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    let block_id = json(res).await["id"].as_i64().unwrap();
+
+    // The whole range is rejected before routing; others still pass
+    assert!(app
+        .state
+        .network_policy
+        .load_policy()
+        .evaluate_str(
+            "203.0.113.99",
+            crate::network_policy::Surface::Proxy,
+            chrono::Utc::now()
+        )
+        .is_denied());
+    let res = app
+        .proxy("/blocked/a", "203.0.113.99")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+    let res = app
+        .proxy("/blocked/a", "198.51.100.20")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(app.upstream.requests().len(), 2);
+
+    let res = app
+        .delete(
+            &format!("/api/security/blocked-ips/{}?confirm=true", block_id),
+            100,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app.proxy("/blocked/a", "203.0.113.7").send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_access_log_write_and_search() {
+    let app = TestApp::spawn().await;
+    app.create_route("/logged").await;
+    let ip = "192.0.2.44";
+
+    let res = app.proxy("/logged/ok", ip).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app.proxy("/unrouted", ip).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+
+    let search = format!("/api/dashboard/access-log/search?ip={}", ip);
+    let logs = eventually("both access logs", || async {
+        let body = json(app.get(&search, 0).send().await.unwrap()).await;
+        (body["total"].as_u64() == Some(2)).then_some(body)
+    })
+    .await;
+    let mut seen: Vec<(String, i64)> = logs["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| {
+            (
+                l["path"].as_str().unwrap().to_string(),
+                l["status"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec![
+            ("/logged/ok".to_string(), 200),
+            ("/unrouted".to_string(), 404)
+        ]
+    );
+
+    let errors = json(
+        app.get(&format!("{}&status_min=400", search), 0)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(errors["total"], 1);
+    assert_eq!(errors["logs"][0]["path"], "/unrouted");
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_ddns_crud_with_stub_provider() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .post("/api/ddns", 80)
+        .json(&serde_json::json!({
+            "provider": "cloudflare",
+            "hostname": "e2e.example.com",
+            "api_token": "token",
+            "zone_id": "zone",
+            "ip_source": "webhook",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    let created = json(res).await;
+    let id = created["id"].as_i64().unwrap();
+    let report_token = created["report_token"].as_str().unwrap().to_string();
+    let path = format!("/api/ddns/{}", id);
+
+    let config = json(app.get(&path, 0).send().await.unwrap()).await;
+    assert_eq!(config["hostname"], "e2e.example.com");
+    assert_eq!(config["api_token"], "********");
+
+    // Webhook report goes straight to the provider
+    let report = format!("/api/ddns/{}/report-ip", id);
+    let res = app
+        .request(reqwest::Method::POST, &report, None)
+        .header("x-ddns-token", "wrong")
+        .json(&serde_json::json!({ "ip": "198.51.100.77" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let res = app
+        .request(reqwest::Method::POST, &report, None)
+        .header("x-ddns-token", &report_token)
+        .json(&serde_json::json!({ "ip": "198.51.100.77" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(json(res).await["updated"], true);
+    assert_eq!(
+        app.ddns_provider.updates(),
+        vec![("e2e.example.com".to_string(), "198.51.100.77".to_string())]
+    );
+    let config = json(app.get(&path, 0).send().await.unwrap()).await;
+    assert_eq!(config["last_ip"], "198.51.100.77");
+
+    let res = app
+        .put(&path, 80)
+        .json(&serde_json::json!({ "update_interval_sec": 600 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let config = json(app.get(&path, 0).send().await.unwrap()).await;
+    assert_eq!(config["update_interval_sec"], 600);
+
+    // Manual update (operate group) re-pushes the reported address
+    let res = app
+        .post(&format!("{}/update", path), 50)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(app.ddns_provider.updates().len(), 2);
+
+    // Credential tests never push an address or store anything
+    let res = app
+        .post("/api/ddns/test", 80)
+        .json(&serde_json::json!({ "config_id": id }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let result = json(res).await;
+    assert_eq!(result["ok"], true);
+    assert_eq!(result["auth_ok"], true);
+    assert_eq!(result["remote_value"], "198.51.100.77");
+    let res = app
+        .post("/api/ddns/test", 80)
+        .json(&serde_json::json!({
+            "provider": "noip",
+            "hostname": "unsaved.example.com",
+            "username": "user",
+            "password": "pass",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(json(res).await["hostname"], "unsaved.example.com");
+    assert_eq!(app.ddns_provider.updates().len(), 2);
+    let configs = json(app.get("/api/ddns", 0).send().await.unwrap()).await;
+    assert_eq!(configs.as_array().unwrap().len(), 1);
+
+    let res = app
+        .post("/api/ddns?validate=true", 80)
+        .json(&serde_json::json!({
+            "provider": "cloudflare",
+            "hostname": "validated.example.com",
+            "api_token": "token",
+            "zone_id": "zone",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    assert_eq!(json(res).await["validation"]["ok"], true);
+
+    let res = app
+        .delete(&format!("{}?confirm=true", path), 100)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app.get(&path, 0).send().await.unwrap();
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_auth_permission_floors() {
+    let app = TestApp::spawn().await;
+
+    let res = app
+        .request(reqwest::Method::GET, "/api/routes", None)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let res = app
+        .request(reqwest::Method::GET, "/api/routes", None)
+        .bearer_auth("not-a-jwt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let res = app
+        .get("/api/routes", TEST_LOGIN_FLOOR - 1)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    // Operate may read but not create; admin may create but not delete
+    let create = serde_json::json!({ "path": "/floors", "target": app.upstream.url() });
+    let res = app
+        .get("/api/routes", TEST_LOGIN_FLOOR)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app
+        .post("/api/routes", 50)
+        .json(&create)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+    let res = app
+        .post("/api/routes", 80)
+        .json(&create)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    let id = json(res).await["id"].as_i64().unwrap();
+    let delete = format!("/api/routes/{}?confirm=true", id);
+    let res = app.delete(&delete, 80).send().await.unwrap();
+    assert_eq!(res.status(), 403);
+
+    // Raising a floor applies to existing sessions on the next request
+    let res = app
+        .put("/api/auth/permission-floors", 100)
+        .json(&serde_json::json!({
+            "login": TEST_LOGIN_FLOOR,
+            "read": 0,
+            "operate": 50,
+            "admin": 90,
+            "dangerous": 100,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = app
+        .put(&format!("/api/routes/{}", id), 80)
+        .json(&serde_json::json!({ "priority": 5 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    let res = app.delete(&delete, 100).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_aranea_registration_tokens() {
+    let app = TestApp::spawn().await;
+    let register = |token: Option<&str>, fid: &str| {
+        let builder = app
+            .request(reqwest::Method::POST, "/api/aranea/register", None)
+            .json(&serde_json::json!({
+                "mac": "aa:bb:cc:dd:ee:ff",
+                "product_type": "ar-is04a",
+                "product_code": "0001",
+                "device_type": "sensor",
+                "fid": fid,
+            }));
+        match token {
+            Some(token) => builder.header("x-registration-token", token),
+            None => builder,
+        }
+    };
+
+    // No session and no token
+    let res = register(None, "0150").send().await.unwrap();
+    assert_eq!(res.status(), 401);
+
+    let res = app
+        .post("/api/aranea/registration-tokens", 80)
+        .json(
+            &serde_json::json!({ "name": "plant-a", "fid": "0150", "product_types": ["ar-is04a"] }),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+    let res = app
+        .post("/api/aranea/registration-tokens", 100)
+        .json(
+            &serde_json::json!({ "name": "plant-a", "fid": "0150", "product_types": ["ar-is04a"] }),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 201);
+    let issued = json(res).await;
+    let id = issued["id"].as_i64().unwrap();
+    let token = issued["registration_token"].as_str().unwrap().to_string();
+
+    let res = register(Some("lpgreg_unknown"), "0150")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 401);
+    let res = register(Some(&token), "0999").send().await.unwrap();
+    assert_eq!(res.status(), 403);
+    assert_eq!(json(res).await["code"], "REGISTRATION_TOKEN_SCOPE");
+
+    // The list never carries the secret
+    let list = json(
+        app.get("/api/aranea/registration-tokens", 80)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(list[0]["name"], "plant-a");
+    assert!(list[0].get("registration_token").is_none());
+
+    let res = app
+        .delete(
+            &format!("/api/aranea/registration-tokens/{}?confirm=true", id),
+            100,
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let res = register(Some(&token), "0150").send().await.unwrap();
+    assert_eq!(res.status(), 401);
+    assert_eq!(json(res).await["code"], "REGISTRATION_TOKEN_INVALID");
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_access_log_delete_recomputes_rollups() {
+    let app = TestApp::spawn().await;
+    app.create_route("/rolled").await;
+    let (kept, deleted) = ("192.0.2.50", "192.0.2.51");
+    for ip in [kept, deleted, deleted] {
+        let res = app.proxy("/rolled/a", ip).send().await.unwrap();
+        assert_eq!(res.status(), 200);
+    }
+    eventually("three access logs", || async {
+        let body = json(
+            app.get("/api/dashboard/access-log/search?path=/rolled", 0)
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        (body["total"].as_u64() == Some(3)).then_some(())
+    })
+    .await;
+
+    let today = chrono::Utc::now().date_naive();
+    let month = crate::hostname_usage::first_of_month(today);
+    let mongo = &app.state.app_state.mongo;
+    mongo.roll_up_ip_day(today).await.unwrap();
+    crate::hostname_usage::roll_up_month(&app.state.app_state, month)
+        .await
+        .unwrap();
+    let requests = || async {
+        let usage = mongo
+            .get_hostname_usage(&crate::hostname_usage::month_key(month))
+            .await
+            .unwrap();
+        usage.iter().map(|u| u.requests).sum::<u64>()
+    };
+    assert_eq!(requests().await, 3);
+
+    let res = app
+        .post("/api/dashboard/access-log/delete?confirm=true", 100)
+        .json(&serde_json::json!({ "ip": deleted }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let job = json(res).await["job_id"].as_str().unwrap().to_string();
+    let status = eventually("the delete job", || async {
+        let body = json(
+            app.get(&format!("/api/dashboard/access-log/delete/{}", job), 100)
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        (body["status"] != "running").then_some(body)
+    })
+    .await;
+    assert_eq!(status["status"], "completed");
+    assert_eq!(status["deleted"], 2);
+
+    // Rollups no longer count the deleted logs
+    assert!(mongo
+        .get_ip_daily_stats(deleted, today)
+        .await
+        .unwrap()
+        .is_empty());
+    let kept_rows = mongo.get_ip_daily_stats(kept, today).await.unwrap();
+    assert_eq!(kept_rows.len(), 1);
+    assert_eq!(kept_rows[0].requests, 1);
+    assert_eq!(requests().await, 1);
+}
+
+/// Read one response head (up to the blank line); interim responses are not
+/// visible through reqwest
+async fn read_head(stream: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut byte))
+            .await
+            .expect("timed out waiting for response")
+            .unwrap();
+        assert_eq!(n, 1, "connection closed mid-response");
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).unwrap()
+}
+
+/// Send a POST head to the proxy and return the connection
+async fn post_head(app: &TestApp, expect: &str, content_length: usize) -> TcpStream {
+    let mut stream = TcpStream::connect(app.addr).await.unwrap();
+    let head = format!(
+        "POST /expect/upload HTTP/1.1\r\nHost: test\r\nX-Forwarded-For: 198.51.100.30\r\n\
+         Content-Length: {}\r\nExpect: {}\r\n\r\n",
+        content_length, expect
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_expect_continue_through_the_proxy() {
+    let app = TestApp::spawn().await;
+    let id = app.create_route("/expect").await;
+
+    // Immediate (the default): the client only sends the body after the
+    // interim response; the upstream gets the body but not the expectation
+    let mut stream = post_head(&app, "100-continue", 5).await;
+    let interim = read_head(&mut stream).await;
+    assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{}", interim);
+    stream.write_all(b"hello").await.unwrap();
+    let fin = read_head(&mut stream).await;
+    assert!(fin.starts_with("HTTP/1.1 200"), "{}", fin);
+    let seen = app.upstream.requests();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(
+        (seen[0].method.as_str(), seen[0].path.as_str()),
+        ("POST", "/upload")
+    );
+    assert_eq!(seen[0].body, b"hello");
+    assert!(!seen[0].headers.contains_key("expect"));
+
+    let res = app
+        .put(&format!("/api/routes/{}", id), 100)
+        .json(&serde_json::json!({ "expect_continue": "passthrough", "max_body_bytes": 16 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    // Passthrough: answered the same way, and the upstream sees the header
+    let mut stream = post_head(&app, "100-continue", 5).await;
+    let interim = read_head(&mut stream).await;
+    assert!(interim.starts_with("HTTP/1.1 100 Continue"), "{}", interim);
+    stream.write_all(b"hello").await.unwrap();
+    let fin = read_head(&mut stream).await;
+    assert!(fin.starts_with("HTTP/1.1 200"), "{}", fin);
+    let seen = app.upstream.requests();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[1].body, b"hello");
+    assert_eq!(
+        seen[1].headers.get("expect").map(String::as_str),
+        Some("100-continue")
+    );
+
+    // A declared body over the route limit is refused without 100 Continue,
+    // and so is an expectation other than 100-continue
+    let mut stream = post_head(&app, "100-continue", 4096).await;
+    let fin = read_head(&mut stream).await;
+    assert!(fin.starts_with("HTTP/1.1 413"), "{}", fin);
+    let mut stream = post_head(&app, "200-ok", 5).await;
+    let fin = read_head(&mut stream).await;
+    assert!(fin.starts_with("HTTP/1.1 417"), "{}", fin);
+    assert_eq!(app.upstream.requests().len(), 2);
+}
+
+#[tokio::test]
+#[ignore = "needs a docker daemon"]
+async fn e2e_update_onto_a_deleted_route_path_is_refused() {
+    let app = TestApp::spawn().await;
+    let live = app.create_route("/live").await;
+    let deleted = app.create_route("/retired").await;
+    let res = app
+        .delete(&format!("/api/routes/{}?confirm=true", deleted), 100)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+
+    // The soft-deleted row still holds the unique path slot
+    let res = app
+        .put(&format!("/api/routes/{}", live), 80)
+        .json(&serde_json::json!({ "path": "/retired" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    let body = json(res).await;
+    assert_eq!(body["code"], "ROUTE_DELETED");
+    assert_eq!(body["details"]["route_id"], deleted);
+
+    // Updates that keep the path are unaffected
+    let res = app
+        .put(&format!("/api/routes/{}", live), 80)
+        .json(&serde_json::json!({ "priority": 50 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,130 @@
+//! Mock upstream server and stub DDNS provider
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::{body::Body, extract::State, http::Request, Json, Router};
+use tokio::task::JoinHandle;
+
+use crate::ddns::{DdnsProviderTrait, DdnsTestResult, DdnsUpdate, DdnsUpdateOutcome};
+use crate::models::DdnsConfig;
+
+/// A request as the upstream received it
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query
+    pub path: String,
+    /// Lowercased header names
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+type RequestLog = Arc<Mutex<Vec<RecordedRequest>>>;
+
+/// HTTP server that answers every request with 200 JSON and records it
+pub struct MockUpstream {
+    addr: SocketAddr,
+    requests: RequestLog,
+    server: JoinHandle<()>,
+}
+
+async fn record(State(log): State<RequestLog>, req: Request<Body>) -> Json<serde_json::Value> {
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+    let headers = req
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+    let method = req.method().to_string();
+    let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .map(|bytes| bytes.to_vec())
+        .unwrap_or_default();
+    log.lock().unwrap().push(RecordedRequest {
+        method,
+        path: path.clone(),
+        headers,
+        body,
+    });
+    Json(serde_json::json!({ "upstream": "mock", "path": path }))
+}
+
+impl MockUpstream {
+    pub async fn start() -> Self {
+        let requests = RequestLog::default();
+        let app = Router::new().fallback(record).with_state(requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock upstream");
+        let addr = listener.local_addr().expect("mock upstream address");
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        Self {
+            addr,
+            requests,
+            server,
+        }
+    }
+
+    /// Base URL to use as a route target
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockUpstream {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// DDNS provider that accepts every update and records (hostname, ip)
+#[derive(Default)]
+pub struct StubDdnsProvider {
+    updates: Mutex<Vec<(String, String)>>,
+}
+
+impl StubDdnsProvider {
+    pub fn updates(&self) -> Vec<(String, String)> {
+        self.updates.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl DdnsProviderTrait for StubDdnsProvider {
+    async fn update(
+        &self,
+        config: &DdnsConfig,
+        ip: &str,
+        _ipv6: Option<&str>,
+    ) -> Result<DdnsUpdate, String> {
+        self.updates
+            .lock()
+            .unwrap()
+            .push((config.hostname.clone(), ip.to_string()));
+        Ok(DdnsUpdateOutcome::Updated.into())
+    }
+
+    async fn test(&self, config: &DdnsConfig) -> DdnsTestResult {
+        DdnsTestResult::new(self, config, "stub").passed(config.last_ip.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        "stub"
+    }
+}
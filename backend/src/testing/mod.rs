@@ -0,0 +1,336 @@
+//! Integration test harness: the full app against real databases
+//!
+//! `TestApp::spawn` starts MariaDB and MongoDB containers through the docker
+//! CLI, loads scripts/init_mysql.sql and scripts/init_mongo.js, runs the
+//! startup migrations, and serves the production router (API routes plus the
+//! proxy fallback) on an ephemeral port. A `MockUpstream` records proxied
+//! requests and a `StubDdnsProvider` stands in for the DDNS provider APIs.
+//!
+//! Each `TestApp` owns its containers, so tests are isolated and can run in
+//! parallel. The end-to-end tests need a docker daemon and are ignored by
+//! default:
+//!
+//! ```text
+//! cargo test e2e -- --ignored
+//! ```
+//!
+//! LPG_TEST_MYSQL_IMAGE / LPG_TEST_MONGO_IMAGE override the images.
+
+mod docker;
+mod e2e;
+mod mock;
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use reqwest::{Method, RequestBuilder};
+use tokio::task::JoinHandle;
+
+use crate::aranea::AraneaClient;
+use crate::cluster::ClusterCoordinator;
+use crate::config::{
+    AcmeConfig, AraneaConfig, AuthConfig, ClusterConfig, Config, DatabaseConfig, DnsConfig,
+    FrontendConfig, LoggingConfig, MigrationsConfig, NotifyConfig, ServerConfig, TlsConfig,
+};
+use crate::db::AppState;
+use crate::ddns::DdnsUpdater;
+use crate::external::ExternalDeviceManager;
+use crate::migrations::{MigrationContext, MigrationRunner};
+use crate::models::SessionClaims;
+use crate::notify::{DiscordNotifier, NotificationQueue};
+use crate::omada::OmadaManager;
+use crate::openwrt::OpenWrtManager;
+use crate::proxy::{ProxyState, ProxyStateDeps};
+
+pub use self::docker::{docker_available, Container};
+pub use self::mock::{MockUpstream, StubDdnsProvider};
+
+const TEST_JWT_SECRET: &str = "lpg-integration-test-secret";
+/// Login floor for test sessions; low enough to exercise the operate group
+pub const TEST_LOGIN_FLOOR: i32 = 50;
+const MYSQL_ROOT_PASSWORD: &str = "lpg-test";
+/// Image pull plus first-start initialisation
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(180);
+/// How long `eventually` waits for asynchronous effects
+const EVENTUALLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The app on an ephemeral port with its own databases and mock upstream
+pub struct TestApp {
+    pub addr: SocketAddr,
+    pub state: ProxyState,
+    pub upstream: MockUpstream,
+    pub ddns_provider: Arc<StubDdnsProvider>,
+    client: reqwest::Client,
+    server: JoinHandle<()>,
+    // Declared last so the containers go after the server stops
+    _mysql: Container,
+    _mongo: Container,
+}
+
+fn script(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../scripts")
+        .join(name)
+}
+
+fn image(var: &str, default: &str) -> String {
+    std::env::var(var).unwrap_or_else(|_| default.to_string())
+}
+
+fn test_config(mysql_port: u16, mongo_port: u16) -> Config {
+    Config {
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            geoip_db_path: None,
+            response_cache_max_mb: 8,
+            max_body_mb: 100,
+            tls: TlsConfig::default(),
+        },
+        database: DatabaseConfig {
+            mysql_url: Some(format!(
+                "mysql://root:{}@127.0.0.1:{}/lacis_proxy",
+                MYSQL_ROOT_PASSWORD, mysql_port
+            )),
+            mongodb_url: Some(format!(
+                "mongodb://127.0.0.1:{}/?directConnection=true&serverSelectionTimeoutMS=2000",
+                mongo_port
+            )),
+            mongodb_ingest_pool_size: 2,
+        },
+        discord: None,
+        auth: AuthConfig {
+            jwt_secret: TEST_JWT_SECRET.to_string(),
+            lacisoath_required_permission: TEST_LOGIN_FLOOR,
+            ..AuthConfig::default()
+        },
+        aranea: AraneaConfig::default(),
+        cluster: ClusterConfig::default(),
+        logging: LoggingConfig::default(),
+        migrations: MigrationsConfig::default(),
+        dns: DnsConfig::default(),
+        notify: NotifyConfig::default(),
+        frontend: FrontendConfig::default(),
+        acme: AcmeConfig::default(),
+    }
+}
+
+/// Connect once both servers finished their init scripts (they only accept
+/// TCP connections afterwards)
+async fn connect(config: &Config) -> AppState {
+    let started = Instant::now();
+    loop {
+        match AppState::new(config).await {
+            Ok(state) => return state,
+            Err(e) if started.elapsed() < STARTUP_TIMEOUT => {
+                tracing::debug!("Test databases not ready yet: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Err(e) => panic!("test databases did not come up: {}", e),
+        }
+    }
+}
+
+impl TestApp {
+    /// Start databases, migrate, and serve the app (panics without docker)
+    pub async fn spawn() -> Self {
+        assert!(
+            docker_available(),
+            "integration tests need a running docker daemon"
+        );
+        let mysql_init = script("init_mysql.sql");
+        let mysql = Container::run(
+            &image("LPG_TEST_MYSQL_IMAGE", "mariadb:10.11"),
+            3306,
+            &[("MARIADB_ROOT_PASSWORD", MYSQL_ROOT_PASSWORD)],
+            &[(&mysql_init, "/docker-entrypoint-initdb.d/init_mysql.sql")],
+        )
+        .expect("start MariaDB container");
+        let mongo_init = script("init_mongo.js");
+        let mongo = Container::run(
+            &image("LPG_TEST_MONGO_IMAGE", "mongo:7"),
+            27017,
+            &[],
+            &[(&mongo_init, "/docker-entrypoint-initdb.d/init_mongo.js")],
+        )
+        .expect("start MongoDB container");
+
+        let config = test_config(mysql.port(), mongo.port());
+        let app_state = connect(&config).await;
+
+        // Same wiring as main(), minus background tasks
+        let notifier = Arc::new(DiscordNotifier::new(
+            app_state.clone(),
+            Arc::new(NotificationQueue::new(config.notify.clone())),
+        ));
+        let omada_manager = Arc::new(OmadaManager::new(app_state.mongo.clone()));
+        let migrations = Arc::new(MigrationRunner::new(MigrationContext {
+            mongo: app_state.mongo.clone(),
+            mysql: app_state.mysql.clone(),
+            omada_manager: omada_manager.clone(),
+        }));
+        migrations
+            .run_startup(false)
+            .await
+            .expect("startup migrations");
+        let cluster = Arc::new(ClusterCoordinator::new(
+            config.cluster,
+            app_state.mongo.clone(),
+            notifier.clone(),
+        ));
+        let mut state = ProxyState::new(ProxyStateDeps {
+            app_state: app_state.clone(),
+            notifier: notifier.clone(),
+            omada_manager: omada_manager.clone(),
+            openwrt_manager: Arc::new(OpenWrtManager::new(app_state.mongo.clone())),
+            external_manager: Arc::new(ExternalDeviceManager::new(app_state.mongo.clone())),
+            aranea_client: Arc::new(AraneaClient::new(config.aranea)),
+            cluster,
+            migrations,
+            geoip_db_path: None,
+            response_cache_max_mb: config.server.response_cache_max_mb,
+            max_body_mb: config.server.max_body_mb,
+            tls_config: config.server.tls.clone(),
+            acme_config: config.acme.clone(),
+            auth_config: config.auth,
+            dns_config: config.dns,
+            frontend_config: config.frontend,
+        })
+        .await
+        .expect("build proxy state");
+
+        let ddns_provider = Arc::new(StubDdnsProvider::default());
+        state.ddns_updater = Arc::new(
+            DdnsUpdater::new(app_state, notifier, omada_manager)
+                .with_stub_provider(ddns_provider.clone()),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind test server");
+        let addr = listener.local_addr().expect("test server address");
+        let app = crate::build_app(state.clone());
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await;
+        });
+
+        Self {
+            addr,
+            state,
+            upstream: MockUpstream::start().await,
+            ddns_provider,
+            client: reqwest::Client::new(),
+            server,
+            _mysql: mysql,
+            _mongo: mongo,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Session JWT for a LacisOath user with `permission`, signed with the
+    /// test secret
+    pub fn token(&self, permission: i32) -> String {
+        let claims = SessionClaims {
+            sub: format!("e2e-{}@example.com", permission),
+            lacis_id: None,
+            permission,
+            auth_method: "lacisoath".to_string(),
+            auth_provider: None,
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            floors: None,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
+        )
+        .expect("encode test session")
+    }
+
+    /// Request to the app; `permission` adds a Bearer session
+    pub fn request(&self, method: Method, path: &str, permission: Option<i32>) -> RequestBuilder {
+        let builder = self.client.request(method, self.url(path));
+        match permission {
+            Some(level) => builder.bearer_auth(self.token(level)),
+            None => builder,
+        }
+    }
+
+    pub fn get(&self, path: &str, permission: i32) -> RequestBuilder {
+        self.request(Method::GET, path, Some(permission))
+    }
+
+    pub fn post(&self, path: &str, permission: i32) -> RequestBuilder {
+        self.request(Method::POST, path, Some(permission))
+    }
+
+    pub fn put(&self, path: &str, permission: i32) -> RequestBuilder {
+        self.request(Method::PUT, path, Some(permission))
+    }
+
+    pub fn delete(&self, path: &str, permission: i32) -> RequestBuilder {
+        self.request(Method::DELETE, path, Some(permission))
+    }
+
+    /// Unauthenticated request through the proxy, as seen from `client_ip`
+    pub fn proxy(&self, path: &str, client_ip: &str) -> RequestBuilder {
+        self.client
+            .get(self.url(path))
+            .header("x-forwarded-for", client_ip)
+    }
+
+    /// Create an active route to the mock upstream; returns its id
+    pub async fn create_route(&self, path: &str) -> i32 {
+        let res = self
+            .post("/api/routes", 100)
+            .json(&serde_json::json!({ "path": path, "target": self.upstream.url() }))
+            .send()
+            .await
+            .expect("create route");
+        assert_eq!(res.status(), 201, "create route {}", path);
+        json(res).await["id"].as_i64().expect("route id") as i32
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Response body as JSON
+pub async fn json(res: reqwest::Response) -> serde_json::Value {
+    res.json().await.expect("JSON response body")
+}
+
+/// Poll `check` until it yields a value (for effects written off the
+/// request path, such as access logs)
+pub async fn eventually<T, F, Fut>(what: &str, mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let started = Instant::now();
+    loop {
+        if let Some(value) = check().await {
+            return value;
+        }
+        assert!(
+            started.elapsed() < EVENTUALLY_TIMEOUT,
+            "timed out waiting for {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
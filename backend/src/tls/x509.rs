@@ -0,0 +1,263 @@
+//! Just enough X.509 (DER) to report what a certificate file holds: subject,
+//! issuer, validity and the DNS names of the subjectAltName extension.
+//! Signatures and the chain are rustls' business, not this reader's.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+/// `[0] EXPLICIT Version`
+const TAG_VERSION: u8 = 0xA0;
+/// `[3] EXPLICIT Extensions`
+const TAG_EXTENSIONS: u8 = 0xA3;
+/// `dNSName [2] IA5String` of a GeneralName
+const TAG_DNS_NAME: u8 = 0x82;
+
+/// id-ce-subjectAltName (2.5.29.17)
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+
+/// Name attributes shown, in RFC 4514 short form (2.5.4.x)
+const NAME_ATTRIBUTES: &[(u8, &str)] = &[
+    (3, "CN"),
+    (6, "C"),
+    (7, "L"),
+    (8, "ST"),
+    (10, "O"),
+    (11, "OU"),
+];
+
+/// What the leaf certificate of a loaded file says about itself
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CertInfo {
+    /// e.g. `CN=example.com, O=Example`
+    pub subject: String,
+    pub issuer: String,
+    #[serde(skip)]
+    pub common_name: Option<String>,
+    #[serde(skip)]
+    pub issuer_common_name: Option<String>,
+    /// subjectAltName DNS entries, lower case
+    pub dns_names: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+}
+
+impl CertInfo {
+    /// Server names the certificate is valid for (SAN, else the CN)
+    pub fn names(&self) -> Vec<String> {
+        if !self.dns_names.is_empty() {
+            return self.dns_names.clone();
+        }
+        self.common_name
+            .iter()
+            .map(|cn| cn.to_ascii_lowercase())
+            .collect()
+    }
+}
+
+/// Read a DER certificate
+pub fn parse(der: &[u8]) -> Result<CertInfo, String> {
+    let mut cert = Der::new(der).expect(TAG_SEQUENCE)?;
+    let mut tbs = cert.expect(TAG_SEQUENCE)?;
+
+    if tbs.peek() == Some(TAG_VERSION) {
+        tbs.next()?;
+    }
+    tbs.expect_raw(TAG_INTEGER)?;
+    tbs.expect(TAG_SEQUENCE)?;
+    let (issuer, issuer_common_name) = name(tbs.expect(TAG_SEQUENCE)?)?;
+    let mut validity = tbs.expect(TAG_SEQUENCE)?;
+    let not_before = time(&mut validity)?;
+    let not_after = time(&mut validity)?;
+    let (subject, common_name) = name(tbs.expect(TAG_SEQUENCE)?)?;
+    tbs.expect(TAG_SEQUENCE)?;
+
+    let mut dns_names = Vec::new();
+    while !tbs.is_empty() {
+        let (tag, content) = tbs.next()?;
+        if tag == TAG_EXTENSIONS {
+            dns_names = subject_alt_names(Der::new(content).expect(TAG_SEQUENCE)?)?;
+        }
+    }
+
+    Ok(CertInfo {
+        subject,
+        issuer,
+        common_name,
+        issuer_common_name,
+        dns_names,
+        not_before,
+        not_after,
+    })
+}
+
+/// RFC 4514-style string of a Name and its CN
+fn name(mut rdns: Der<'_>) -> Result<(String, Option<String>), String> {
+    let mut parts = Vec::new();
+    let mut common_name = None;
+    while !rdns.is_empty() {
+        let mut set = rdns.expect(TAG_SET)?;
+        while !set.is_empty() {
+            let mut attribute = set.expect(TAG_SEQUENCE)?;
+            let oid = attribute.expect_raw(TAG_OID)?;
+            let (tag, value) = attribute.next()?;
+            let Some(label) = oid
+                .strip_prefix(&[0x55, 0x04][..])
+                .filter(|rest| rest.len() == 1)
+                .and_then(|rest| NAME_ATTRIBUTES.iter().find(|(id, _)| *id == rest[0]))
+                .map(|(_, label)| *label)
+            else {
+                continue;
+            };
+            let value = string(tag, value);
+            if label == "CN" && common_name.is_none() {
+                common_name = Some(value.clone());
+            }
+            parts.push(format!("{}={}", label, value));
+        }
+    }
+    Ok((parts.join(", "), common_name))
+}
+
+/// DNS names of the subjectAltName extension, if present
+fn subject_alt_names(mut extensions: Der<'_>) -> Result<Vec<String>, String> {
+    while !extensions.is_empty() {
+        let mut extension = extensions.expect(TAG_SEQUENCE)?;
+        let oid = extension.expect_raw(TAG_OID)?;
+        if extension.peek() == Some(TAG_BOOLEAN) {
+            extension.next()?;
+        }
+        let value = extension.expect_raw(TAG_OCTET_STRING)?;
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        let mut general_names = Der::new(value).expect(TAG_SEQUENCE)?;
+        let mut names = Vec::new();
+        while !general_names.is_empty() {
+            let (tag, content) = general_names.next()?;
+            if tag == TAG_DNS_NAME {
+                names.push(String::from_utf8_lossy(content).to_ascii_lowercase());
+            }
+        }
+        return Ok(names);
+    }
+    Ok(Vec::new())
+}
+
+/// UTCTime (`YYMMDDHHMMSSZ`, 1950-2049) or GeneralizedTime (`YYYYMMDDHHMMSSZ`)
+fn time(der: &mut Der<'_>) -> Result<DateTime<Utc>, String> {
+    let (tag, content) = der.next()?;
+    let text = std::str::from_utf8(content).map_err(|_| "invalid time".to_string())?;
+    let digits = text
+        .strip_suffix('Z')
+        .filter(|d| d.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or_else(|| format!("unsupported time '{}'", text))?;
+    let field = |at: usize, len: usize| -> Result<u32, String> {
+        digits
+            .get(at..at + len)
+            .and_then(|d| d.parse().ok())
+            .ok_or_else(|| format!("unsupported time '{}'", text))
+    };
+    let (year, rest) = match (tag, digits.len()) {
+        (TAG_UTC_TIME, 12) => {
+            let yy = field(0, 2)? as i32;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, 2)
+        }
+        (TAG_GENERALIZED_TIME, 14) => (field(0, 4)? as i32, 4),
+        _ => return Err(format!("unsupported time '{}'", text)),
+    };
+    NaiveDate::from_ymd_opt(year, field(rest, 2)?, field(rest + 2, 2)?)
+        .and_then(|date| {
+            date.and_hms_opt(
+                field(rest + 4, 2).ok()?,
+                field(rest + 6, 2).ok()?,
+                field(rest + 8, 2).ok()?,
+            )
+        })
+        .map(|t| t.and_utc())
+        .ok_or_else(|| format!("invalid time '{}'", text))
+}
+
+/// Directory string of any of the usual types
+fn string(tag: u8, content: &[u8]) -> String {
+    match tag {
+        // BMPString (UTF-16BE)
+        0x1E => char::decode_utf16(
+            content
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+        )
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect(),
+        // TeletexString, read as Latin-1
+        0x14 => content.iter().map(|&b| b as char).collect(),
+        _ => String::from_utf8_lossy(content).into_owned(),
+    }
+}
+
+/// Cursor over consecutive DER TLVs
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Next element's tag and content
+    fn next(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let truncated = || "truncated DER".to_string();
+        let (&tag, rest) = self.data.split_first().ok_or_else(truncated)?;
+        let (&first, rest) = rest.split_first().ok_or_else(truncated)?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return Err("unsupported DER length".to_string());
+            }
+            let len = rest[..count]
+                .iter()
+                .fold(0usize, |len, &b| (len << 8) | b as usize);
+            (len, &rest[count..])
+        };
+        if rest.len() < len {
+            return Err(truncated());
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok((tag, content))
+    }
+
+    /// Content of the next element, which must have `tag`
+    fn expect_raw(&mut self, tag: u8) -> Result<&'a [u8], String> {
+        match self.next()? {
+            (found, content) if found == tag => Ok(content),
+            (found, _) => Err(format!(
+                "unexpected DER tag 0x{:02x} (expected 0x{:02x})",
+                found, tag
+            )),
+        }
+    }
+
+    /// Cursor over the next element, which must have `tag`
+    fn expect(&mut self, tag: u8) -> Result<Der<'a>, String> {
+        self.expect_raw(tag).map(Der::new)
+    }
+}
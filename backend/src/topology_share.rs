@@ -0,0 +1,226 @@
+//! Read-only topology shares (GET /api/public/topology?token=...)
+//!
+//! A share token lets a page without an LPG account fetch the topology v2
+//! payload for one view (full, or one site by fid). Tokens are stored only
+//! as their SHA-256 hash and looked up on every request, so a revocation
+//! or expiry applies to the next fetch. Shared payloads carry opaque node
+//! ids and no lacis_ids, claims or metadata (which holds the source
+//! references); MACs and IPs are kept only at the `labels_ips` level.
+//!
+//! The public endpoint is rate limited per client IP and per token, and
+//! serves each share's payload from a short cache so embedding pages cannot
+//! make LPG rebuild the topology on every view.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::TopologyShare;
+use crate::omada::webhook;
+
+/// Marks share tokens in logs and secret scanners
+const TOKEN_MARKER: &str = "lpgtopo_";
+/// Characters of the plaintext kept for display
+const PREFIX_LEN: usize = 12;
+
+pub const DEFAULT_IP_RATE_PER_MINUTE: i32 = 6;
+pub const DEFAULT_TOKEN_RATE_PER_MINUTE: i32 = 30;
+/// Expiry when the request names none
+pub const DEFAULT_EXPIRES_IN_HOURS: i64 = 24 * 30;
+pub const MAX_EXPIRES_IN_HOURS: i64 = 24 * 365;
+/// How long a share's payload is reused
+pub const PAYLOAD_TTL: Duration = Duration::from_secs(15);
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Map size at which expired windows are swept
+const RATE_SWEEP_AT: usize = 1024;
+
+/// Node fields a share exposes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareDetail {
+    /// Labels, types and structure only
+    #[default]
+    Labels,
+    /// Also IP and MAC addresses
+    LabelsIps,
+}
+
+impl ShareDetail {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Labels => "labels",
+            Self::LabelsIps => "labels_ips",
+        }
+    }
+
+    /// Column value; unknown values fall back to the restrictive level
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "labels_ips" => Self::LabelsIps,
+            _ => Self::Labels,
+        }
+    }
+}
+
+/// Topology view a share is scoped to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareView {
+    #[default]
+    Full,
+    /// One facility (the share's fid) and everything below it
+    Site,
+}
+
+impl ShareView {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Site => "site",
+        }
+    }
+
+    pub fn from_column(value: &str) -> Self {
+        match value {
+            "site" => Self::Site,
+            _ => Self::Full,
+        }
+    }
+}
+
+/// New plaintext token (shown once) with its hash and display prefix
+pub fn generate() -> (String, String, String) {
+    let token = format!("{}{}", TOKEN_MARKER, webhook::generate_secret());
+    let hash = hash(&token);
+    let prefix = token[..PREFIX_LEN].to_string();
+    (token, hash, prefix)
+}
+
+/// Stored form of a token
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Whether a share may still be used (not revoked or expired)
+pub fn is_live(share: &TopologyShare, now: DateTime<Utc>) -> bool {
+    share.revoked_at.is_none() && share.expires_at > now
+}
+
+/// Per-IP and per-token limiter plus the per-share payload cache
+#[derive(Default)]
+pub struct TopologyShareGuard {
+    requests: Mutex<HashMap<String, (Instant, u32)>>,
+    payloads: Mutex<HashMap<i32, (Instant, Arc<serde_json::Value>)>>,
+}
+
+impl TopologyShareGuard {
+    /// Count a request against `key` ("ip:..." / "token:..."); false once
+    /// the key used up `per_minute`
+    pub fn allow(&self, key: &str, per_minute: u32) -> bool {
+        let mut requests = self.requests.lock().unwrap_or_else(|p| p.into_inner());
+        if requests.len() >= RATE_SWEEP_AT {
+            requests.retain(|_, (start, _)| start.elapsed() < RATE_WINDOW);
+        }
+        let entry = requests
+            .entry(key.to_string())
+            .or_insert((Instant::now(), 0));
+        if entry.0.elapsed() >= RATE_WINDOW {
+            *entry = (Instant::now(), 0);
+        }
+        entry.1 += 1;
+        entry.1 <= per_minute
+    }
+
+    /// Cached payload of a share, if still fresh
+    pub fn cached(&self, share_id: i32) -> Option<Arc<serde_json::Value>> {
+        let payloads = self.payloads.lock().unwrap_or_else(|p| p.into_inner());
+        payloads
+            .get(&share_id)
+            .filter(|(at, _)| at.elapsed() < PAYLOAD_TTL)
+            .map(|(_, payload)| payload.clone())
+    }
+
+    pub fn store(&self, share_id: i32, payload: Arc<serde_json::Value>) {
+        let mut payloads = self.payloads.lock().unwrap_or_else(|p| p.into_inner());
+        payloads.retain(|_, (at, _)| at.elapsed() < PAYLOAD_TTL);
+        payloads.insert(share_id, (Instant::now(), payload));
+    }
+
+    /// Drop a share's cached payload (on revocation)
+    pub fn forget(&self, share_id: i32) {
+        let mut payloads = self.payloads.lock().unwrap_or_else(|p| p.into_inner());
+        payloads.remove(&share_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(expires_in: chrono::Duration, revoked: bool) -> TopologyShare {
+        TopologyShare {
+            id: 1,
+            name: "wiki".to_string(),
+            view: ShareView::Site,
+            fid: Some("0150".to_string()),
+            detail: ShareDetail::Labels,
+            token_prefix: "lpgtopo_abcd".to_string(),
+            expires_at: Utc::now() + expires_in,
+            request_count: 0,
+            last_used_at: None,
+            revoked_at: revoked.then(Utc::now),
+            created_by: "admin".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn tokens_are_marked_and_hashed() {
+        let (token, stored, prefix) = generate();
+        assert!(token.starts_with(TOKEN_MARKER));
+        assert_eq!(stored, hash(&format!(" {} ", token)));
+        assert_eq!(stored.len(), 64);
+        assert!(token.starts_with(&prefix));
+        assert_ne!(generate().0, token);
+    }
+
+    #[test]
+    fn expired_and_revoked_shares_are_not_live() {
+        let now = Utc::now();
+        assert!(is_live(&share(chrono::Duration::hours(1), false), now));
+        assert!(!is_live(&share(chrono::Duration::hours(-1), false), now));
+        assert!(!is_live(&share(chrono::Duration::hours(1), true), now));
+    }
+
+    #[test]
+    fn limiter_and_cache_are_per_key() {
+        let guard = TopologyShareGuard::default();
+        assert!(guard.allow("ip:203.0.113.7", 2));
+        assert!(guard.allow("ip:203.0.113.7", 2));
+        assert!(!guard.allow("ip:203.0.113.7", 2));
+        assert!(guard.allow("token:1", 2));
+
+        assert!(guard.cached(1).is_none());
+        guard.store(1, Arc::new(serde_json::json!({ "nodes": [] })));
+        assert!(guard.cached(1).is_some());
+        assert!(guard.cached(2).is_none());
+        guard.forget(1);
+        assert!(guard.cached(1).is_none());
+    }
+
+    #[test]
+    fn unknown_columns_fall_back_to_restrictive_values() {
+        assert_eq!(ShareDetail::from_column("everything"), ShareDetail::Labels);
+        assert_eq!(
+            ShareDetail::from_column("labels_ips"),
+            ShareDetail::LabelsIps
+        );
+        assert_eq!(ShareView::from_column("site"), ShareView::Site);
+        assert_eq!(ShareView::from_column("routes"), ShareView::Full);
+    }
+}
@@ -16,16 +16,22 @@
 //!
 //! upsert rules:
 //! - New _id: insert all fields
-//! - Existing _id: update volatile fields ONLY (state_type, ip, hostname, metadata, updated_at)
+//! - Existing _id: update volatile fields ONLY (state_type, ip, hostname, metadata, updated_at;
+//!   fid/facility_name for Omada entries, from the site mapping; device_class for clients)
 //!   parent_id, sort_order, label(if customized) are NEVER overwritten
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use crate::db::mongo::ingest_writes::IngestCycleWrites;
 use crate::db::mongo::user_object_detail::UserObjectDetail;
 use crate::db::mongo::MongoDb;
 use crate::db::mysql::MySqlDb;
+use crate::device_class::{DeviceClassRules, Fingerprint};
 use crate::lacis_id::{compute_network_device_lacis_id, default_product_code};
+use crate::new_device::NewDeviceWatch;
 use crate::omada::client::normalize_mac;
+use crate::omada::health;
 
 /// Generate a pseudo-MAC for WireGuard peers (no physical MAC).
 /// Uses F0 prefix (IEEE locally administered bit) + first 10 hex chars of peer_id.
@@ -133,11 +139,81 @@ fn map_state_type(status: &str) -> String {
 pub struct UserObjectIngester {
     mongo: Arc<MongoDb>,
     mysql: Arc<MySqlDb>,
+    /// New-device detection for inserted entries (background syncers only)
+    new_devices: Option<Arc<NewDeviceWatch>>,
 }
 
 impl UserObjectIngester {
     pub fn new(mongo: Arc<MongoDb>, mysql: Arc<MySqlDb>) -> Self {
-        Self { mongo, mysql }
+        Self {
+            mongo,
+            mysql,
+            new_devices: None,
+        }
+    }
+
+    /// Report newly inserted devices to the shared watch
+    pub fn with_new_device_watch(mut self, watch: Arc<NewDeviceWatch>) -> Self {
+        self.new_devices = Some(watch);
+        self
+    }
+
+    /// Write one ingest cycle's entries as paced bulk upserts. Existing
+    /// entries are read once for the cycle (state changes, kept sort_order
+    /// and label); first-seen entries go to new-device detection.
+    async fn write(&self, source: &str, entries: Vec<UserObjectDetail>) -> Result<(), String> {
+        // A later entry for the same _id wins, as with one upsert per entry
+        let mut seen = HashSet::new();
+        let mut entries: Vec<UserObjectDetail> = entries
+            .into_iter()
+            .rev()
+            .filter(|e| seen.insert(e.id.clone()))
+            .collect();
+        entries.reverse();
+
+        let mut cycle = IngestCycleWrites::start(source, "user_object_detail");
+        let ids: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
+        let existing = match self.mongo.get_user_object_details_by_ids(&ids).await {
+            Ok(existing) => existing,
+            Err(e) => {
+                cycle.last_error = Some(e.clone());
+                self.mongo.finish_ingest_cycle(cycle);
+                return Err(e);
+            }
+        };
+        for entry in &mut entries {
+            let ex = existing.get(&entry.id).cloned();
+            self.check_and_record_state_change(&entry.id, &entry.state_type, &ex)
+                .await;
+            if let Some(ex) = ex {
+                entry.sort_order = ex.sort_order;
+                entry.label_customized = ex.label_customized;
+            }
+        }
+
+        let result = self
+            .mongo
+            .bulk_upsert_user_object_details(&entries, &existing, &mut cycle)
+            .await;
+        if let Err(ref e) = result {
+            cycle.last_error = Some(e.clone());
+        }
+        self.mongo.finish_ingest_cycle(cycle);
+
+        let inserted: HashSet<String> = result?.into_iter().collect();
+        if let Some(watch) = &self.new_devices {
+            for entry in entries.iter().filter(|e| inserted.contains(&e.id)) {
+                watch.detected(entry).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send new-device alerts queued during this ingest cycle
+    async fn flush_new_devices(&self) {
+        if let Some(watch) = &self.new_devices {
+            watch.flush().await;
+        }
     }
 
     /// Record state change if state_type differs from existing
@@ -148,12 +224,18 @@ impl UserObjectIngester {
         existing: &Option<UserObjectDetail>,
     ) {
         if let Some(ref ex) = existing {
-            if ex.state_type != new_state
-                && !ex.state_type.starts_with("Static") // Don't override admin manual overrides
+            // Admin manual overrides are not changes; nodes of a recovered
+            // controller compare against their state before the outage
+            let before_outage = ex
+                .metadata
+                .get(health::STATE_BEFORE_OUTAGE)
+                .and_then(|v| v.as_str());
+            if let Some(previous) =
+                health::state_change_from(&ex.state_type, before_outage, new_state)
             {
                 let _ = self
                     .mysql
-                    .insert_device_state_change(id, new_state, Some(&ex.state_type), "syncer")
+                    .insert_device_state_change(id, new_state, Some(previous), "syncer")
                     .await;
             }
         }
@@ -188,6 +270,7 @@ impl UserObjectIngester {
     /// Ingest all Omada data for a specific controller into user_object_detail.
     /// Called after OmadaSyncer.sync_controller() completes.
     pub async fn ingest_omada(&self, controller_id: &str) -> Result<(), String> {
+        let mut entries: Vec<UserObjectDetail> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         // Load controller info for fid/facility_name resolution
@@ -255,9 +338,6 @@ impl UserObjectIngester {
 
             let state_type = if dev.status == 1 { "online" } else { "offline" };
 
-            // Check for state change
-            let existing = self.mongo.get_user_object_detail_by_id(&doc_id).await.ok().flatten();
-            self.check_and_record_state_change(&doc_id, state_type, &existing).await;
 
             let entry = UserObjectDetail {
                 id: doc_id.clone(),
@@ -265,11 +345,12 @@ impl UserObjectIngester {
                 lacis_id: dev.lacis_id.clone(),
                 device_type: "NetworkDevice".to_string(),
                 parent_id,
-                sort_order: existing.as_ref().map(|e| e.sort_order).unwrap_or(order_counter),
+                sort_order: order_counter,
                 node_type: dev.device_type.clone(),
                 state_type: state_type.to_string(),
                 label: dev.name.clone(),
-                label_customized: existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+                label_customized: false,
+                claimed_by: None,
                 ip: dev.ip.clone(),
                 hostname: None,
                 source: "omada".to_string(),
@@ -282,22 +363,31 @@ impl UserObjectIngester {
                 fid,
                 facility_name,
                 ssid: None,
-                metadata: serde_json::json!({
-                    "model": &dev.model,
-                    "firmware_version": &dev.firmware_version,
-                    "site_id": &dev.site_id,
-                    "controller_id": controller_id,
-                }),
+                device_class: None,
+                metadata: {
+                    let mut metadata = serde_json::json!({
+                        "model": &dev.model,
+                        "firmware_version": &dev.firmware_version,
+                        "site_id": &dev.site_id,
+                        "controller_id": controller_id,
+                    });
+                    if dev.device_type == "switch" && !dev.ports.is_empty() {
+                        metadata["ports_summary"] =
+                            serde_json::json!(crate::omada::ports::summarize(&dev.ports));
+                    }
+                    metadata
+                },
                 aranea_lacis_id: None,
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_user_object_detail(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
         // --- Ingest clients ---
+        let classes = DeviceClassRules::load(&self.mysql).await;
         let all_clients = self
             .mongo
             .get_omada_clients(None, None, None)
@@ -331,11 +421,20 @@ impl UserObjectIngester {
             });
 
             let conn_type = if cli.wireless { "wireless" } else { "wired" };
+            let switch_port = cli.switch_mac.as_ref().and_then(|m| {
+                let switch = devices
+                    .iter()
+                    .find(|d| normalize_mac(&d.mac) == normalize_mac(m));
+                crate::omada::ports::client_port_context(
+                    cli,
+                    switch.map(|d| d.name.as_str()),
+                    switch.map(|d| d.ports.as_slice()).unwrap_or(&[]),
+                )
+            });
             let state_type = map_state_type(if cli.active { "active" } else { "inactive" });
 
-            // Client _id = MAC
-            let existing = self.mongo.get_user_object_detail_by_id(&mac).await.ok().flatten();
-            self.check_and_record_state_change(&mac, &state_type, &existing).await;
+            let site = ctrl.and_then(|c| c.sites.iter().find(|s| s.site_id == cli.site_id));
+
 
             let entry = UserObjectDetail {
                 id: mac.clone(),
@@ -343,11 +442,12 @@ impl UserObjectIngester {
                 lacis_id: cli.lacis_id.clone(),
                 device_type: "NetworkDevice".to_string(),
                 parent_id,
-                sort_order: existing.as_ref().map(|e| e.sort_order).unwrap_or(order_counter),
+                sort_order: order_counter,
                 node_type: "client".to_string(),
                 state_type,
                 label: client_label(&cli.name, &cli.host_name, &cli.vendor, &cli.mac),
-                label_customized: existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+                label_customized: false,
+                claimed_by: None,
                 ip: cli.ip.clone(),
                 hostname: cli.host_name.clone(),
                 source: "omada".to_string(),
@@ -357,24 +457,43 @@ impl UserObjectIngester {
                 product_code: None,
                 network_device_type: None,
                 candidate_lacis_id: None,
-                fid: None,
-                facility_name: None,
+                fid: site.and_then(|s| s.fid.clone()),
+                facility_name: site.and_then(|s| s.fid_display_name.clone()),
                 ssid: cli.ssid.clone(),
+                device_class: Some(
+                    classes
+                        .classify(&Fingerprint {
+                            mac: &cli.mac,
+                            device_category: cli.device_category.as_deref(),
+                            device_type: cli.device_type.as_deref(),
+                            os_name: cli.os_name.as_deref(),
+                            vendor: cli.vendor.as_deref(),
+                            hostname: cli.host_name.as_deref(),
+                            name: cli.name.as_deref(),
+                        })
+                        .as_str()
+                        .to_string(),
+                ),
                 metadata: serde_json::json!({
+                    "site_id": &cli.site_id,
+                    "controller_id": controller_id,
                     "vendor": &cli.vendor,
                     "os_name": &cli.os_name,
+                    "device_category": &cli.device_category,
+                    "omada_device_type": &cli.device_type,
                     "ssid": &cli.ssid,
                     "signal_level": &cli.signal_level,
                     "traffic_down": cli.traffic_down,
                     "traffic_up": cli.traffic_up,
                     "uptime": cli.uptime,
+                    "switch_port": switch_port,
                 }),
                 aranea_lacis_id: None,
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_user_object_detail(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
@@ -397,8 +516,6 @@ impl UserObjectIngester {
 
             let state_type = map_state_type(if peer.status { "active" } else { "inactive" });
 
-            let existing = self.mongo.get_user_object_detail_by_id(&pseudo_mac).await.ok().flatten();
-            self.check_and_record_state_change(&pseudo_mac, &state_type, &existing).await;
 
             let entry = UserObjectDetail {
                 id: pseudo_mac.clone(),
@@ -406,11 +523,12 @@ impl UserObjectIngester {
                 lacis_id: None,
                 device_type: "NetworkDevice".to_string(),
                 parent_id,
-                sort_order: existing.as_ref().map(|e| e.sort_order).unwrap_or(order_counter),
+                sort_order: order_counter,
                 node_type: "wg_peer".to_string(),
                 state_type,
                 label: peer.name.clone(),
-                label_customized: existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+                label_customized: false,
+                claimed_by: None,
                 ip: peer.allow_address.first().cloned(),
                 hostname: None,
                 source: "omada".to_string(),
@@ -423,6 +541,7 @@ impl UserObjectIngester {
                 fid: None,
                 facility_name: None,
                 ssid: None,
+                device_class: None,
                 metadata: serde_json::json!({
                     "interface_name": &peer.interface_name,
                     "public_key": &peer.public_key,
@@ -434,7 +553,7 @@ impl UserObjectIngester {
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_user_object_detail(&entry).await?;
+            entries.push(entry);
             order_counter += 1;
         }
 
@@ -443,6 +562,9 @@ impl UserObjectIngester {
             controller_id,
             order_counter
         );
+        self.write(&format!("omada:{}", controller_id), entries)
+            .await?;
+        self.flush_new_devices().await;
         Ok(())
     }
 
@@ -516,6 +638,7 @@ impl UserObjectIngester {
     /// Ingest OpenWrt router and its clients into user_object_detail.
     /// Called after OpenWrtSyncer.poll_router() completes.
     pub async fn ingest_openwrt(&self, router_id: &str) -> Result<(), String> {
+        let mut entries: Vec<UserObjectDetail> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         let routers = self.mongo.list_openwrt_routers().await.unwrap_or_default();
@@ -564,7 +687,6 @@ impl UserObjectIngester {
         };
 
         let state_type = map_state_type(&router.status);
-        self.check_and_record_state_change(&doc_id, &state_type, &existing).await;
 
         let entry = UserObjectDetail {
             id: doc_id.clone(),
@@ -576,7 +698,8 @@ impl UserObjectIngester {
             node_type: "router".to_string(),
             state_type,
             label: router.display_name.clone(),
-            label_customized: existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+            label_customized: false,
+            claimed_by: None,
             ip: Some(router.ip.clone()),
             hostname: None,
             source: "openwrt".to_string(),
@@ -589,6 +712,7 @@ impl UserObjectIngester {
             fid: None,
             facility_name: None,
             ssid: None,
+            device_class: None,
             metadata: serde_json::json!({
                 "wan_ip": &router.wan_ip,
                 "lan_ip": &router.lan_ip,
@@ -604,9 +728,10 @@ impl UserObjectIngester {
             updated_at: now.clone(),
         };
 
-        self.mongo.upsert_user_object_detail(&entry).await?;
+        entries.push(entry);
 
         // --- Ingest OpenWrt clients ---
+        let classes = DeviceClassRules::load(&self.mysql).await;
         let all_clients = self
             .mongo
             .get_openwrt_clients(None)
@@ -621,8 +746,6 @@ impl UserObjectIngester {
             let cli_mac = normalize_mac(&cli.mac);
             let state_type = map_state_type(if cli.active { "active" } else { "inactive" });
 
-            let cli_existing = self.mongo.get_user_object_detail_by_id(&cli_mac).await.ok().flatten();
-            self.check_and_record_state_change(&cli_mac, &state_type, &cli_existing).await;
 
             let entry = UserObjectDetail {
                 id: cli_mac.clone(),
@@ -630,30 +753,45 @@ impl UserObjectIngester {
                 lacis_id: cli.lacis_id.clone(),
                 device_type: "NetworkDevice".to_string(),
                 parent_id: doc_id.clone(),
-                sort_order: cli_existing.as_ref().map(|e| e.sort_order).unwrap_or(i as u32),
+                sort_order: i as u32,
                 node_type: "client".to_string(),
                 state_type,
                 label: client_label(&cli.hostname, &None, &None, &cli.mac),
-                label_customized: cli_existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+                label_customized: false,
+                claimed_by: None,
                 ip: Some(cli.ip.clone()),
                 hostname: cli.hostname.clone(),
                 source: "openwrt".to_string(),
                 source_ref_id: Some(format!("openwrt:{}:cli:{}", router_id, cli.mac)),
-                connection_type: "wired".to_string(),
+                connection_type: if cli.wireless { "wireless" } else { "wired" }.to_string(),
                 product_type: None,
                 product_code: None,
                 network_device_type: None,
                 candidate_lacis_id: None,
                 fid: None,
                 facility_name: None,
-                ssid: None,
-                metadata: serde_json::json!({ "router_id": router_id }),
+                ssid: cli.ssid.clone(),
+                device_class: Some(
+                    classes
+                        .classify(&Fingerprint {
+                            mac: &cli.mac,
+                            hostname: cli.hostname.as_deref(),
+                            ..Default::default()
+                        })
+                        .as_str()
+                        .to_string(),
+                ),
+                metadata: serde_json::json!({
+                    "router_id": router_id,
+                    "ssid": &cli.ssid,
+                    "band": &cli.band,
+                }),
                 aranea_lacis_id: None,
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_user_object_detail(&entry).await?;
+            entries.push(entry);
         }
 
         tracing::debug!(
@@ -661,12 +799,16 @@ impl UserObjectIngester {
             router_id,
             clients.len()
         );
+        self.write(&format!("openwrt:{}", router_id), entries)
+            .await?;
+        self.flush_new_devices().await;
         Ok(())
     }
 
     /// Ingest external device and its clients into user_object_detail.
     /// Called after ExternalSyncer.poll_device() completes.
     pub async fn ingest_external(&self, device_id: &str) -> Result<(), String> {
+        let mut entries: Vec<UserObjectDetail> = Vec::new();
         let now = chrono::Utc::now().to_rfc3339();
 
         let devices = self.mongo.list_external_devices().await.unwrap_or_default();
@@ -715,7 +857,6 @@ impl UserObjectIngester {
         };
 
         let state_type = map_state_type(&dev.status);
-        self.check_and_record_state_change(&doc_id, &state_type, &existing).await;
 
         let entry = UserObjectDetail {
             id: doc_id.clone(),
@@ -727,7 +868,8 @@ impl UserObjectIngester {
             node_type: "external".to_string(),
             state_type,
             label: dev.display_name.clone(),
-            label_customized: existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+            label_customized: false,
+            claimed_by: None,
             ip: Some(dev.ip.clone()),
             hostname: None,
             source: "external".to_string(),
@@ -740,6 +882,7 @@ impl UserObjectIngester {
             fid: None,
             facility_name: None,
             ssid: None,
+            device_class: None,
             metadata: serde_json::json!({
                 "protocol": &dev.protocol,
                 "device_model": &dev.device_model,
@@ -751,9 +894,10 @@ impl UserObjectIngester {
             updated_at: now.clone(),
         };
 
-        self.mongo.upsert_user_object_detail(&entry).await?;
+        entries.push(entry);
 
         // --- Ingest external clients ---
+        let classes = DeviceClassRules::load(&self.mysql).await;
         let all_clients = self
             .mongo
             .get_external_clients(None)
@@ -768,8 +912,6 @@ impl UserObjectIngester {
             let cli_mac = normalize_mac(&cli.mac);
             let state_type = map_state_type(if cli.active { "active" } else { "inactive" });
 
-            let cli_existing = self.mongo.get_user_object_detail_by_id(&cli_mac).await.ok().flatten();
-            self.check_and_record_state_change(&cli_mac, &state_type, &cli_existing).await;
 
             let entry = UserObjectDetail {
                 id: cli_mac.clone(),
@@ -777,30 +919,45 @@ impl UserObjectIngester {
                 lacis_id: cli.lacis_id.clone(),
                 device_type: "NetworkDevice".to_string(),
                 parent_id: doc_id.clone(),
-                sort_order: cli_existing.as_ref().map(|e| e.sort_order).unwrap_or(i as u32),
+                sort_order: i as u32,
                 node_type: "client".to_string(),
                 state_type,
                 label: client_label(&cli.hostname, &None, &None, &cli.mac),
-                label_customized: cli_existing.as_ref().map(|e| e.label_customized).unwrap_or(false),
+                label_customized: false,
+                claimed_by: None,
                 ip: cli.ip.clone(),
                 hostname: cli.hostname.clone(),
                 source: "external".to_string(),
                 source_ref_id: Some(format!("external:{}:cli:{}", device_id, cli.mac)),
-                connection_type: "wired".to_string(),
+                connection_type: if cli.wireless { "wireless" } else { "wired" }.to_string(),
                 product_type: None,
                 product_code: None,
                 network_device_type: None,
                 candidate_lacis_id: None,
                 fid: None,
                 facility_name: None,
-                ssid: None,
-                metadata: serde_json::json!({ "device_id": device_id }),
+                ssid: cli.ssid.clone(),
+                device_class: Some(
+                    classes
+                        .classify(&Fingerprint {
+                            mac: &cli.mac,
+                            hostname: cli.hostname.as_deref(),
+                            ..Default::default()
+                        })
+                        .as_str()
+                        .to_string(),
+                ),
+                metadata: serde_json::json!({
+                    "device_id": device_id,
+                    "ssid": &cli.ssid,
+                    "band": &cli.band,
+                }),
                 aranea_lacis_id: None,
                 created_at: now.clone(),
                 updated_at: now.clone(),
             };
 
-            self.mongo.upsert_user_object_detail(&entry).await?;
+            entries.push(entry);
         }
 
         tracing::debug!(
@@ -808,6 +965,9 @@ impl UserObjectIngester {
             device_id,
             clients.len()
         );
+        self.write(&format!("external:{}", device_id), entries)
+            .await?;
+        self.flush_new_devices().await;
         Ok(())
     }
 }
@@ -816,23 +976,27 @@ impl UserObjectIngester {
 // Migration: cg_node_order → user_object_detail (one-time, startup)
 // ============================================================================
 
-/// Migrate cg_node_order to user_object_detail (one-time, runs on startup if user_object_detail is empty).
+/// Migrate cg_node_order to user_object_detail (skipped if user_object_detail already has entries, unless forced).
 /// Also migrates cg_state collapsed_node_ids from MAC to LacisID/MAC format.
-pub async fn migrate_to_user_object_detail(mongo: &Arc<MongoDb>) -> Result<(), String> {
+/// Returns whether anything was migrated.
+pub async fn migrate_to_user_object_detail(
+    mongo: &Arc<MongoDb>,
+    force: bool,
+) -> Result<bool, String> {
     let count = mongo.count_user_object_details().await?;
-    if count > 0 {
+    if count > 0 && !force {
         tracing::debug!(
             "[UserObjectDetail] Migration skipped: user_object_detail already has {} entries",
             count
         );
-        return Ok(());
+        return Ok(false);
     }
 
     // Check if cg_node_order has data to migrate
     let node_order_count = mongo.count_node_order().await?;
     if node_order_count == 0 {
         tracing::debug!("[UserObjectDetail] Migration skipped: cg_node_order is also empty");
-        return Ok(());
+        return Ok(false);
     }
 
     tracing::info!(
@@ -916,6 +1080,7 @@ pub async fn migrate_to_user_object_detail(mongo: &Arc<MongoDb>) -> Result<(), S
             state_type,
             label: entry.label.clone(),
             label_customized: entry.label_customized,
+            claimed_by: None,
             ip: entry.ip.clone(),
             hostname: entry.hostname.clone(),
             source: entry.source.clone(),
@@ -934,6 +1099,7 @@ pub async fn migrate_to_user_object_detail(mongo: &Arc<MongoDb>) -> Result<(), S
             fid: entry.fid.clone(),
             facility_name: entry.facility_name.clone(),
             ssid: entry.ssid.clone(),
+            device_class: None,
             metadata: entry.metadata.clone(),
             aranea_lacis_id: None,
             created_at: entry.created_at.clone(),
@@ -957,5 +1123,5 @@ pub async fn migrate_to_user_object_detail(mongo: &Arc<MongoDb>) -> Result<(), S
         id_migration.len()
     );
 
-    Ok(())
+    Ok(true)
 }
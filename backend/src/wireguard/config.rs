@@ -1,43 +1,280 @@
 //! WireGuard client configuration file generator
+//!
+//! Configs are rendered from a profile (`wg_config_profiles`). Profile `dns`
+//! and `allowed_ips` are templates that may reference request values:
+//! `{dns}`, `{allowed_ips}`, `{address}`, `{endpoint}`.
 
 use serde::Deserialize;
 
+use crate::models::WgConfigProfile;
+
+/// Placeholders accepted in profile templates
+pub const PLACEHOLDERS: &[&str] = &["{dns}", "{allowed_ips}", "{address}", "{endpoint}"];
+
 /// Parameters for generating a WireGuard client config file
 #[derive(Debug, Deserialize)]
 pub struct WgClientConfigParams {
     pub private_key: String,
     pub address: String,
+    #[serde(default)]
     pub dns: String,
     pub server_public_key: String,
     pub endpoint: String,
+    #[serde(default)]
     pub allowed_ips: String,
     pub persistent_keepalive: Option<u32>,
+    /// Profile to render with (None = default profile)
+    pub profile_id: Option<i32>,
+    pub pre_up: Option<String>,
+    pub post_up: Option<String>,
+    pub pre_down: Option<String>,
+    pub post_down: Option<String>,
+}
+
+/// Rendering view of a profile
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileTemplate<'a> {
+    pub dns: Option<&'a str>,
+    pub allowed_ips: &'a str,
+    pub persistent_keepalive: Option<i32>,
+    pub mtu: Option<i32>,
+    pub scripts_allowed: bool,
+}
+
+impl ProfileTemplate<'static> {
+    /// The original single template: request DNS / AllowedIPs / keepalive as given
+    pub const LEGACY: ProfileTemplate<'static> = ProfileTemplate {
+        dns: Some("{dns}"),
+        allowed_ips: "{allowed_ips}",
+        persistent_keepalive: None,
+        mtu: None,
+        scripts_allowed: false,
+    };
+}
+
+impl<'a> From<&'a WgConfigProfile> for ProfileTemplate<'a> {
+    fn from(p: &'a WgConfigProfile) -> Self {
+        Self {
+            dns: p.dns.as_deref(),
+            allowed_ips: &p.allowed_ips,
+            persistent_keepalive: p.persistent_keepalive,
+            mtu: p.mtu,
+            scripts_allowed: p.scripts_allowed,
+        }
+    }
 }
 
-/// Generate a WireGuard client configuration string (.conf format)
-pub fn generate_config(params: &WgClientConfigParams) -> String {
-    let keepalive = params
-        .persistent_keepalive
-        .map(|k| format!("PersistentKeepalive = {}\n", k))
-        .unwrap_or_default();
-
-    format!(
-        "[Interface]\n\
-         PrivateKey = {}\n\
-         Address = {}\n\
-         DNS = {}\n\
-         \n\
-         [Peer]\n\
-         PublicKey = {}\n\
-         Endpoint = {}\n\
-         AllowedIPs = {}\n\
-         {}",
-        params.private_key,
-        params.address,
-        params.dns,
+/// Reject unknown `{...}` placeholders and line breaks in a template
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.contains('\n') || template.contains('\r') {
+        return Err("Template must be a single line".to_string());
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in '{}'", template))?;
+        let token = &rest[start..start + end + 1];
+        if !PLACEHOLDERS.contains(&token) {
+            return Err(format!(
+                "Unknown placeholder {} (allowed: {})",
+                token,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+fn substitute(template: &str, params: &WgClientConfigParams) -> String {
+    template
+        .replace("{dns}", &params.dns)
+        .replace("{allowed_ips}", &params.allowed_ips)
+        .replace("{address}", &params.address)
+        .replace("{endpoint}", &params.endpoint)
+}
+
+/// Render a WireGuard client configuration string (.conf format) from a profile
+pub fn render_config(
+    params: &WgClientConfigParams,
+    profile: &ProfileTemplate<'_>,
+) -> Result<String, String> {
+    let scripts: Vec<(&str, &str)> = [
+        ("PreUp", &params.pre_up),
+        ("PostUp", &params.post_up),
+        ("PreDown", &params.pre_down),
+        ("PostDown", &params.post_down),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| {
+        value
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| (key, v))
+    })
+    .collect();
+
+    if !scripts.is_empty() && !profile.scripts_allowed {
+        return Err(
+            "This profile does not allow PreUp/PostUp/PreDown/PostDown scripts".to_string(),
+        );
+    }
+    if scripts
+        .iter()
+        .any(|(_, v)| v.contains('\n') || v.contains('\r'))
+    {
+        return Err("Scripts must be single-line commands".to_string());
+    }
+
+    let mut out = format!(
+        "[Interface]\nPrivateKey = {}\nAddress = {}\n",
+        params.private_key, params.address
+    );
+    if let Some(dns) = profile.dns.filter(|d| !d.is_empty()) {
+        out.push_str(&format!("DNS = {}\n", substitute(dns, params)));
+    }
+    if let Some(mtu) = profile.mtu.filter(|m| *m > 0) {
+        out.push_str(&format!("MTU = {}\n", mtu));
+    }
+    for (key, value) in scripts {
+        out.push_str(&format!("{} = {}\n", key, value));
+    }
+
+    out.push_str(&format!(
+        "\n[Peer]\nPublicKey = {}\nEndpoint = {}\nAllowedIPs = {}\n",
         params.server_public_key,
         params.endpoint,
-        params.allowed_ips,
-        keepalive,
-    )
+        substitute(profile.allowed_ips, params)
+    ));
+
+    let keepalive = match profile.persistent_keepalive {
+        Some(k) if k > 0 => Some(k as u32),
+        Some(_) => None,
+        None => params.persistent_keepalive,
+    };
+    if let Some(k) = keepalive {
+        out.push_str(&format!("PersistentKeepalive = {}\n", k));
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(keepalive: Option<u32>) -> WgClientConfigParams {
+        WgClientConfigParams {
+            private_key: "cHJpdmF0ZQ==".to_string(),
+            address: "10.8.0.2/32".to_string(),
+            dns: "1.1.1.1".to_string(),
+            server_public_key: "c2VydmVy".to_string(),
+            endpoint: "vpn.example.com:51820".to_string(),
+            allowed_ips: "192.168.3.0/24".to_string(),
+            persistent_keepalive: keepalive,
+            profile_id: None,
+            pre_up: None,
+            post_up: None,
+            pre_down: None,
+            post_down: None,
+        }
+    }
+
+    /// The pre-profile generator, kept verbatim as the compatibility reference
+    fn original_generate_config(params: &WgClientConfigParams) -> String {
+        let keepalive = params
+            .persistent_keepalive
+            .map(|k| format!("PersistentKeepalive = {}\n", k))
+            .unwrap_or_default();
+
+        format!(
+            "[Interface]\n\
+             PrivateKey = {}\n\
+             Address = {}\n\
+             DNS = {}\n\
+             \n\
+             [Peer]\n\
+             PublicKey = {}\n\
+             Endpoint = {}\n\
+             AllowedIPs = {}\n\
+             {}",
+            params.private_key,
+            params.address,
+            params.dns,
+            params.server_public_key,
+            params.endpoint,
+            params.allowed_ips,
+            keepalive,
+        )
+    }
+
+    #[test]
+    fn test_legacy_profile_is_byte_identical() {
+        for keepalive in [None, Some(25)] {
+            let p = params(keepalive);
+            assert_eq!(
+                render_config(&p, &ProfileTemplate::LEGACY).unwrap(),
+                original_generate_config(&p)
+            );
+        }
+        let mut empty_dns = params(None);
+        empty_dns.dns = String::new();
+        assert_eq!(
+            render_config(&empty_dns, &ProfileTemplate::LEGACY).unwrap(),
+            original_generate_config(&empty_dns)
+        );
+    }
+
+    #[test]
+    fn test_site_to_site_profile() {
+        let profile = ProfileTemplate {
+            dns: None,
+            allowed_ips: "{allowed_ips}, 10.8.0.0/24",
+            persistent_keepalive: Some(25),
+            mtu: Some(1380),
+            scripts_allowed: false,
+        };
+        let out = render_config(&params(None), &profile).unwrap();
+        assert!(!out.contains("DNS ="));
+        assert!(out.contains("MTU = 1380\n"));
+        assert!(out.contains("AllowedIPs = 192.168.3.0/24, 10.8.0.0/24\n"));
+        assert!(out.ends_with("PersistentKeepalive = 25\n"));
+    }
+
+    #[test]
+    fn test_profile_keepalive_zero_suppresses_request_value() {
+        let profile = ProfileTemplate {
+            persistent_keepalive: Some(0),
+            ..ProfileTemplate::LEGACY
+        };
+        let out = render_config(&params(Some(25)), &profile).unwrap();
+        assert!(!out.contains("PersistentKeepalive"));
+    }
+
+    #[test]
+    fn test_scripts_require_permission() {
+        let mut p = params(None);
+        p.post_up = Some("iptables -A FORWARD -i wg0 -j ACCEPT".to_string());
+        assert!(render_config(&p, &ProfileTemplate::LEGACY).is_err());
+
+        let allowed = ProfileTemplate {
+            scripts_allowed: true,
+            ..ProfileTemplate::LEGACY
+        };
+        let out = render_config(&p, &allowed).unwrap();
+        assert!(out.contains("PostUp = iptables -A FORWARD -i wg0 -j ACCEPT\n\n[Peer]"));
+
+        p.post_up = Some("a\nb".to_string());
+        assert!(render_config(&p, &allowed).is_err());
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("0.0.0.0/0, ::/0").is_ok());
+        assert!(validate_template("{allowed_ips}, 10.0.0.0/8").is_ok());
+        assert!(validate_template("{private_key}").is_err());
+        assert!(validate_template("{dns").is_err());
+        assert!(validate_template("1.1.1.1\nPostUp = x").is_err());
+    }
 }
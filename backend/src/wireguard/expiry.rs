@@ -0,0 +1,263 @@
+//! WireGuard peer expiry
+//!
+//! Peers may carry an LPG-side `expires_at` (stored on the omada_wg_peers
+//! document; Omada has no expiry of its own). A background check disables
+//! enabled peers whose expiry has passed by setting `status: false` through
+//! the Omada OpenAPI, records `expired_at`, audit-logs and notifies. The
+//! peer is not deleted, so it stays listed as expired. Extending a peer
+//! sets a new expiry and re-enables it if the expiry check had disabled it.
+//!
+//! All peers are managed through Omada; LPG does not run a WireGuard
+//! interface of its own.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use tokio::time::interval;
+
+use crate::db::mongo::omada::OmadaWgPeerDoc;
+use crate::db::AppState;
+use crate::notify::DiscordNotifier;
+use crate::omada::client::UpdateWgPeerRequest;
+use crate::omada::OmadaManager;
+
+/// How often expired peers are looked for
+const CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// Longest single extension
+pub const MAX_EXTEND_DAYS: i64 = 3650;
+
+/// Stored expiry format (UTC, whole seconds) so stored values compare as strings
+pub fn format_expiry(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_expiry(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Expiry has passed
+pub fn is_expired(peer: &OmadaWgPeerDoc, now: DateTime<Utc>) -> bool {
+    peer.expires_at
+        .as_deref()
+        .and_then(parse_expiry)
+        .is_some_and(|at| at <= now)
+}
+
+/// Not expired yet, but will be within `days`
+pub fn expires_within(peer: &OmadaWgPeerDoc, now: DateTime<Utc>, days: i64) -> bool {
+    peer.expires_at
+        .as_deref()
+        .and_then(parse_expiry)
+        .is_some_and(|at| at > now && at <= now + chrono::Duration::days(days))
+}
+
+/// New expiry for an extension: an explicit time (must be in the future),
+/// or `days` added to the current expiry (or to now, if already past or unset)
+pub fn extended_expiry(
+    current: Option<&str>,
+    until: Option<DateTime<Utc>>,
+    days: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, String> {
+    let expiry = match (until, days) {
+        (Some(_), Some(_)) => return Err("Give either expires_at or days, not both".to_string()),
+        (Some(until), None) => until,
+        (None, Some(days)) => {
+            if !(1..=MAX_EXTEND_DAYS).contains(&days) {
+                return Err(format!("days must be between 1 and {}", MAX_EXTEND_DAYS));
+            }
+            let base = current
+                .and_then(parse_expiry)
+                .filter(|at| *at > now)
+                .unwrap_or(now);
+            base + chrono::Duration::days(days)
+        }
+        (None, None) => return Err("expires_at or days is required".to_string()),
+    };
+    if expiry <= now {
+        return Err("expires_at must be in the future".to_string());
+    }
+    Ok(expiry)
+}
+
+/// Enable or disable a peer on its controller
+pub async fn set_peer_enabled(
+    omada_manager: &OmadaManager,
+    peer: &OmadaWgPeerDoc,
+    enabled: bool,
+) -> Result<(), String> {
+    let client = omada_manager
+        .get_client(&peer.controller_id)
+        .await
+        .ok_or_else(|| format!("Controller {} not found", peer.controller_id))?;
+    let req = UpdateWgPeerRequest {
+        name: None,
+        status: Some(enabled),
+        allow_address: None,
+        keep_alive: None,
+        comment: None,
+    };
+    client
+        .update_wireguard_peer(&peer.site_id, &peer.peer_id, &req)
+        .await
+}
+
+/// Background check that disables expired peers
+pub struct WgExpiryWatch {
+    app_state: AppState,
+    notifier: Arc<DiscordNotifier>,
+    omada_manager: Arc<OmadaManager>,
+}
+
+impl WgExpiryWatch {
+    pub fn new(
+        app_state: AppState,
+        notifier: Arc<DiscordNotifier>,
+        omada_manager: Arc<OmadaManager>,
+    ) -> Self {
+        Self {
+            app_state,
+            notifier,
+            omada_manager,
+        }
+    }
+
+    /// Start the check loop (every 5 minutes)
+    pub async fn start(self: Arc<Self>) {
+        tracing::info!("Starting WireGuard peer expiry check...");
+
+        let mut interval_timer = interval(CHECK_INTERVAL);
+
+        loop {
+            interval_timer.tick().await;
+
+            if let Err(e) = self.disable_expired().await {
+                tracing::error!("WireGuard expiry check failed: {}", e);
+            }
+        }
+    }
+
+    async fn disable_expired(&self) -> Result<(), String> {
+        let mongo = &self.app_state.mongo;
+        let now = format_expiry(Utc::now());
+        for peer in mongo.list_expired_wg_peers(&now).await? {
+            let result = set_peer_enabled(&self.omada_manager, &peer, false).await;
+            let error = result.as_ref().err().map(String::as_str);
+            mongo.record_wg_peer_expiry(&peer.peer_id, error).await?;
+
+            match &result {
+                Ok(()) => {
+                    tracing::info!("Disabled expired WireGuard peer {}", peer.name);
+                    let _ = self
+                        .app_state
+                        .mysql
+                        .log_audit(
+                            "wireguard_peer",
+                            None,
+                            "expire_disable",
+                            Some("status"),
+                            Some(&format!("{} ({}): enabled", peer.name, peer.peer_id)),
+                            Some(&format!(
+                                "disabled (expired {})",
+                                peer.expires_at.as_deref().unwrap_or("-")
+                            )),
+                            "system",
+                            None,
+                        )
+                        .await;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to disable expired WireGuard peer {}: {}",
+                        peer.name,
+                        e
+                    );
+                }
+            }
+            // Notify failures once, not on every retry
+            if result.is_ok() || peer.expiry_error.is_none() {
+                self.notifier
+                    .notify_wg_peer_expired(
+                        &peer.name,
+                        &peer.interface_name,
+                        peer.expires_at.as_deref().unwrap_or("-"),
+                        error,
+                    )
+                    .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_start_from_the_later_of_now_and_expiry() {
+        let now = parse_expiry("2026-06-01T00:00:00Z").unwrap();
+        let future = Some("2026-06-10T00:00:00Z");
+        let past = Some("2026-05-01T00:00:00Z");
+
+        assert_eq!(
+            format_expiry(extended_expiry(future, None, Some(30), now).unwrap()),
+            "2026-07-10T00:00:00Z"
+        );
+        assert_eq!(
+            format_expiry(extended_expiry(past, None, Some(30), now).unwrap()),
+            "2026-07-01T00:00:00Z"
+        );
+        assert_eq!(
+            format_expiry(extended_expiry(None, None, Some(7), now).unwrap()),
+            "2026-06-08T00:00:00Z"
+        );
+        let until = parse_expiry("2026-12-31T00:00:00Z");
+        assert_eq!(
+            extended_expiry(future, until, None, now).unwrap(),
+            until.unwrap()
+        );
+
+        assert!(extended_expiry(future, parse_expiry("2026-05-31T00:00:00Z"), None, now).is_err());
+        assert!(extended_expiry(future, until, Some(1), now).is_err());
+        assert!(extended_expiry(future, None, None, now).is_err());
+        assert!(extended_expiry(future, None, Some(0), now).is_err());
+    }
+
+    #[test]
+    fn expiry_windows() {
+        let peer = |expires_at: Option<&str>| -> OmadaWgPeerDoc {
+            serde_json::from_value(serde_json::json!({
+                "peer_id": "p1",
+                "controller_id": "c1",
+                "site_id": "s1",
+                "name": "contractor",
+                "status": true,
+                "interface_id": "i1",
+                "interface_name": "wg0",
+                "public_key": "key",
+                "allow_address": ["10.8.0.5/32"],
+                "keep_alive": 25,
+                "comment": null,
+                "expires_at": expires_at,
+                "synced_at": "2026-06-01T00:00:00Z",
+                "created_at": "2026-06-01T00:00:00Z",
+                "updated_at": "2026-06-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let now = parse_expiry("2026-06-01T12:00:00Z").unwrap();
+
+        assert!(is_expired(&peer(Some("2026-06-01T11:59:59Z")), now));
+        assert!(!is_expired(&peer(Some("2026-06-03T00:00:00Z")), now));
+        assert!(!is_expired(&peer(None), now));
+
+        assert!(expires_within(&peer(Some("2026-06-03T00:00:00Z")), now, 7));
+        assert!(!expires_within(&peer(Some("2026-06-30T00:00:00Z")), now, 7));
+        assert!(!expires_within(&peer(Some("2026-06-01T00:00:00Z")), now, 7));
+        assert!(!expires_within(&peer(None), now, 7));
+    }
+}
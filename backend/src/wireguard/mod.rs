@@ -2,6 +2,10 @@
 //!
 //! - `keygen`: Curve25519 key pair generation
 //! - `config`: Client configuration file generator
+//! - `expiry`: Peer expiry and automatic disable
+//! - `unified`: LPG records and Omada copies merged by public key
 
 pub mod config;
+pub mod expiry;
 pub mod keygen;
+pub mod unified;
@@ -0,0 +1,294 @@
+//! Unified WireGuard peer view (GET /api/wireguard/all-peers)
+//!
+//! Two sources describe peers: LPG's own records of the peers it provisioned
+//! (MySQL wg_peer_profiles) and the controller copies synced from Omada
+//! (Mongo omada_wg_peers). Both are keyed by public key. A peer is managed
+//! by `lpg` (recorded, not on any controller), `omada` (created outside
+//! LPG) or `both`. When both sides carry different allowed addresses the
+//! peer is in conflict, and mutations are refused until one side is chosen.
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::db::mongo::omada::OmadaWgPeerDoc;
+use crate::models::WgPeerRecord;
+use crate::wireguard::expiry;
+
+/// URL-safe id for a public key (base64url without padding)
+pub fn peer_uid(public_key: &str) -> String {
+    public_key
+        .trim_end_matches('=')
+        .replace('+', "-")
+        .replace('/', "_")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManagedBy {
+    Lpg,
+    Omada,
+    Both,
+}
+
+/// Same public key, different allowed addresses
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerConflict {
+    pub lpg_allow_address: Vec<String>,
+    pub omada_allow_address: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnifiedPeer {
+    /// Unified id accepted by /api/wireguard/all-peers/:id
+    pub id: String,
+    pub public_key: String,
+    pub name: String,
+    pub managed_by: ManagedBy,
+    pub controller_id: String,
+    pub site_id: String,
+    /// Omada peer id (None while the peer is not on the controller)
+    pub omada_peer_id: Option<String>,
+    pub interface_id: Option<String>,
+    pub interface_name: Option<String>,
+    /// Enabled state from the more recently updated source
+    pub status: bool,
+    /// "lpg" | "omada"
+    pub status_source: &'static str,
+    /// The controller's addresses when present, otherwise LPG's
+    pub allow_address: Vec<String>,
+    pub conflict: Option<PeerConflict>,
+    pub profile_id: Option<i32>,
+    pub profile_name: Option<String>,
+    pub expires_at: Option<String>,
+    pub expired: bool,
+}
+
+fn same_addresses(a: &[String], b: &[String]) -> bool {
+    let set = |list: &[String]| -> BTreeSet<String> {
+        list.iter().map(|s| s.trim().to_ascii_lowercase()).collect()
+    };
+    set(a) == set(b)
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn merge_one(
+    record: Option<&WgPeerRecord>,
+    omada: Option<&OmadaWgPeerDoc>,
+    now: DateTime<Utc>,
+) -> Option<UnifiedPeer> {
+    let public_key = record
+        .map(|r| r.public_key.clone())
+        .or_else(|| omada.map(|o| o.public_key.clone()))?;
+    let managed_by = match (record, omada) {
+        (Some(_), Some(_)) => ManagedBy::Both,
+        (Some(_), None) => ManagedBy::Lpg,
+        (None, Some(_)) => ManagedBy::Omada,
+        (None, None) => return None,
+    };
+
+    let conflict = match (record.and_then(|r| r.allow_address.as_ref()), omada) {
+        (Some(lpg), Some(o)) if !same_addresses(lpg, &o.allow_address) => Some(PeerConflict {
+            lpg_allow_address: lpg.clone(),
+            omada_allow_address: o.allow_address.clone(),
+        }),
+        _ => None,
+    };
+
+    let (status, status_source) = match (record, omada) {
+        (Some(r), Some(o)) => {
+            let synced = parse_time(&o.synced_at);
+            if synced.is_some_and(|t| t < r.updated_at) {
+                (r.enabled, "lpg")
+            } else {
+                (o.status, "omada")
+            }
+        }
+        (Some(r), None) => (r.enabled, "lpg"),
+        (None, Some(o)) => (o.status, "omada"),
+        (None, None) => return None,
+    };
+
+    Some(UnifiedPeer {
+        id: peer_uid(&public_key),
+        public_key,
+        name: omada
+            .map(|o| o.name.clone())
+            .or_else(|| record.map(|r| r.peer_name.clone()))
+            .unwrap_or_default(),
+        managed_by,
+        controller_id: omada
+            .map(|o| o.controller_id.clone())
+            .or_else(|| record.map(|r| r.controller_id.clone()))
+            .unwrap_or_default(),
+        site_id: omada
+            .map(|o| o.site_id.clone())
+            .or_else(|| record.map(|r| r.site_id.clone()))
+            .unwrap_or_default(),
+        omada_peer_id: omada.map(|o| o.peer_id.clone()),
+        interface_id: omada.map(|o| o.interface_id.clone()),
+        interface_name: omada.map(|o| o.interface_name.clone()),
+        status,
+        status_source,
+        allow_address: omada
+            .map(|o| o.allow_address.clone())
+            .or_else(|| record.and_then(|r| r.allow_address.clone()))
+            .unwrap_or_default(),
+        conflict,
+        profile_id: record.and_then(|r| r.profile_id),
+        profile_name: record.and_then(|r| r.profile_name.clone()),
+        expires_at: omada.and_then(|o| o.expires_at.clone()),
+        expired: omada.is_some_and(|o| expiry::is_expired(o, now)),
+    })
+}
+
+/// Merge both sources by public key. When a key is on several controllers,
+/// the copy on the controller/site LPG recorded is the one compared.
+pub fn merge_peers(
+    records: &[WgPeerRecord],
+    omada: &[OmadaWgPeerDoc],
+    now: DateTime<Utc>,
+) -> Vec<UnifiedPeer> {
+    let mut keys: Vec<&str> = Vec::new();
+    for key in records
+        .iter()
+        .map(|r| r.public_key.as_str())
+        .chain(omada.iter().map(|o| o.public_key.as_str()))
+    {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let record = records.iter().find(|r| r.public_key == key);
+            let mut copies = omada.iter().filter(|o| o.public_key == key);
+            let copy = match record {
+                Some(r) => omada
+                    .iter()
+                    .find(|o| {
+                        o.public_key == key
+                            && o.controller_id == r.controller_id
+                            && o.site_id == r.site_id
+                    })
+                    .or_else(|| copies.next()),
+                None => copies.next(),
+            };
+            merge_one(record, copy, now)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(key: &str, allow: Option<&[&str]>, enabled: bool, updated: &str) -> WgPeerRecord {
+        let at = parse_time(updated).unwrap();
+        WgPeerRecord {
+            public_key: key.to_string(),
+            controller_id: "c1".to_string(),
+            site_id: "s1".to_string(),
+            peer_name: format!("lpg-{}", key),
+            profile_id: Some(1),
+            profile_name: Some("default".to_string()),
+            allow_address: allow.map(|a| a.iter().map(|s| s.to_string()).collect()),
+            enabled,
+            created_at: at,
+            updated_at: at,
+        }
+    }
+
+    fn omada(key: &str, allow: &[&str], status: bool, synced: &str) -> OmadaWgPeerDoc {
+        serde_json::from_value(serde_json::json!({
+            "peer_id": format!("p-{}", key),
+            "controller_id": "c1",
+            "site_id": "s1",
+            "name": format!("omada-{}", key),
+            "status": status,
+            "interface_id": "i1",
+            "interface_name": "wg0",
+            "public_key": key,
+            "allow_address": allow,
+            "keep_alive": 25,
+            "comment": null,
+            "synced_at": synced,
+            "created_at": synced,
+            "updated_at": synced,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn merges_by_public_key_and_flags_conflicts() {
+        let now = parse_time("2026-06-01T12:00:00Z").unwrap();
+        let records = vec![
+            record("A+/=", Some(&["10.8.0.2/32"]), true, "2026-06-01T10:00:00Z"),
+            record("B", Some(&["10.8.0.3/32"]), true, "2026-06-01T10:00:00Z"),
+            // Recorded before addresses were tracked: never a conflict
+            record("C", None, true, "2026-06-01T10:00:00Z"),
+            record("D", Some(&["10.8.0.5/32"]), false, "2026-06-01T11:00:00Z"),
+        ];
+        let synced = "2026-06-01T10:30:00Z";
+        let copies = vec![
+            omada("A+/=", &["10.8.0.2/32"], true, synced),
+            omada("B", &["10.8.0.99/32"], true, synced),
+            omada("C", &["10.8.0.4/32"], true, synced),
+            omada("E", &["10.8.0.6/32"], false, synced),
+        ];
+
+        let peers = merge_peers(&records, &copies, now);
+        let by_key = |k: &str| peers.iter().find(|p| p.public_key == k).unwrap();
+        assert_eq!(peers.len(), 5);
+
+        let a = by_key("A+/=");
+        assert_eq!(a.id, "A-_");
+        assert_eq!(a.managed_by, ManagedBy::Both);
+        assert_eq!(a.name, "omada-A+/=");
+        assert!(a.conflict.is_none());
+
+        assert!(by_key("B").conflict.is_some());
+        assert!(by_key("C").conflict.is_none());
+
+        // LPG disabled it after the last sync
+        let d = by_key("D");
+        assert_eq!(d.managed_by, ManagedBy::Lpg);
+        assert!(!d.status);
+        assert_eq!(d.allow_address, vec!["10.8.0.5/32"]);
+
+        let e = by_key("E");
+        assert_eq!(e.managed_by, ManagedBy::Omada);
+        assert_eq!(e.status_source, "omada");
+    }
+
+    #[test]
+    fn status_comes_from_the_fresher_source() {
+        let now = parse_time("2026-06-01T12:00:00Z").unwrap();
+        let lpg_newer = merge_peers(
+            &[record("A", None, false, "2026-06-01T11:00:00Z")],
+            &[omada("A", &[], true, "2026-06-01T10:00:00Z")],
+            now,
+        );
+        assert_eq!(
+            (lpg_newer[0].status, lpg_newer[0].status_source),
+            (false, "lpg")
+        );
+
+        let omada_newer = merge_peers(
+            &[record("A", None, true, "2026-06-01T09:00:00Z")],
+            &[omada("A", &[], false, "2026-06-01T10:00:00Z")],
+            now,
+        );
+        assert_eq!(
+            (omada_newer[0].status, omada_newer[0].status_source),
+            (false, "omada")
+        );
+    }
+}
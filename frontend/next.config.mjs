@@ -1,16 +1,25 @@
+// LPG_STATIC_EXPORT=1 builds a static export (out/) that the backend serves
+// itself ([frontend] mode = "external" or "embedded"); middleware.ts and the
+// /api rewrite are then handled by the backend
+const staticExport = process.env.LPG_STATIC_EXPORT === '1';
+
 /** @type {import('next').NextConfig} */
 const nextConfig = {
   basePath: '/LacisProxyGateway2',
   assetPrefix: '/LacisProxyGateway2/',
-  output: 'standalone',
-  async rewrites() {
-    return [
-      {
-        source: '/api/:path*',
-        destination: 'http://127.0.0.1:8081/api/:path*',
-      },
-    ];
-  },
+  output: staticExport ? 'export' : 'standalone',
+  ...(staticExport
+    ? {}
+    : {
+        async rewrites() {
+          return [
+            {
+              source: '/api/:path*',
+              destination: 'http://127.0.0.1:8081/api/:path*',
+            },
+          ];
+        },
+      }),
 };
 
 export default nextConfig;
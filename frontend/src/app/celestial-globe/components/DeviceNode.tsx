@@ -9,7 +9,7 @@ import { Handle, Position } from 'reactflow';
 import type { NodeProps } from 'reactflow';
 import type { TopologyNodeV2 } from '../types';
 import { NODE_COLORS } from '../constants';
-import { NetworkDeviceIcon } from './icons';
+import { NetworkDeviceIcon, nodeIconType } from './icons';
 import { Tooltip } from './Tooltip';
 import {
   getStatusBadge,
@@ -202,7 +202,7 @@ function DeviceNodeInner({ id, data, selected }: NodeProps<CgDeviceNodeData>) {
         {/* Icon */}
         <div className="flex-shrink-0 mt-0.5">
           <NetworkDeviceIcon
-            type={node.node_type}
+            type={nodeIconType(node.node_type, node.device_class)}
             className="text-gray-900 dark:text-gray-100"
             size={20}
           />
@@ -21,6 +21,7 @@ import {
   Cpu,
   Camera,
   Printer,
+  Laptop,
 } from 'lucide-react';
 
 export type DeviceIconType =
@@ -28,7 +29,7 @@ export type DeviceIconType =
   | 'server' | 'controller' | 'bridge' | 'mobile'
   | 'unmanaged_switch' | 'iot' | 'camera' | 'printer'
   | 'logic_device' | 'external' | 'lpg_server' | 'wg_peer'
-  | 'gateway';
+  | 'gateway' | 'laptop';
 
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 const ICON_MAP: Record<DeviceIconType, React.ComponentType<any>> = {
@@ -46,12 +47,29 @@ const ICON_MAP: Record<DeviceIconType, React.ComponentType<any>> = {
   iot: CircuitBoard,
   camera: Camera,
   printer: Printer,
+  laptop: Laptop,
   logic_device: Cpu,
   external: ExternalLink,
   lpg_server: Shield,
   wg_peer: Globe,
 };
 
+// Client device_class (backend classifier) → icon type
+const DEVICE_CLASS_ICON: Record<string, DeviceIconType> = {
+  phone: 'mobile',
+  laptop: 'laptop',
+  printer: 'printer',
+  camera: 'camera',
+  iot: 'iot',
+  server: 'server',
+};
+
+/** Icon type for a node: classified clients use their device_class */
+export function nodeIconType(nodeType: string, deviceClass?: string | null): string {
+  if (nodeType !== 'client' || !deviceClass) return nodeType;
+  return DEVICE_CLASS_ICON[deviceClass] ?? nodeType;
+}
+
 interface NetworkDeviceIconProps {
   type: string;
   className?: string;
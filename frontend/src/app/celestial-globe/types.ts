@@ -21,6 +21,8 @@ export interface TopologyNodeV2 {
   device_type?: string;
   product_type?: string;
   network_device_type?: string;
+  /** Clients only: phone | laptop | printer | camera | iot | server | unknown */
+  device_class?: DeviceClass;
   status: string;
   state_type: string;
   metadata: Record<string, unknown>;
@@ -30,6 +32,18 @@ export interface TopologyNodeV2 {
   connection_type: ConnectionType;
   fid?: string;
   facility_name?: string;
+  claimed_by?: NodeClaim;
+}
+
+export type DeviceClass =
+  | 'phone' | 'laptop' | 'printer' | 'camera' | 'iot' | 'server' | 'unknown';
+
+/** Owner claim on a node (self-service, or assigned by an admin) */
+export interface NodeClaim {
+  lacis_id: string;
+  display_name: string;
+  claimed_at: string;
+  assigned_by?: string;
 }
 
 export interface TopologyEdgeV2 {
@@ -45,6 +59,16 @@ export interface TopologyMetadataV2 {
   controllers: number;
   routers: number;
   logic_devices: number;
+  /** Nodes reported offline by their source */
+  offline_nodes: number;
+  /** Nodes whose Omada controller is offline (state unknown) */
+  unknown_nodes: number;
+  /** Classified clients per device_class */
+  device_classes: Partial<Record<DeviceClass, number>>;
+  /** Pass to topologyV2Api.watch as `since` */
+  revision: number;
+  /** Send as `base_generation` with position batches and layout recalcs */
+  layout_generation: number;
   generated_at: string;
 }
 
@@ -52,6 +76,25 @@ export interface ViewConfig {
   collapsed_node_ids: string[];
 }
 
+export interface NodePositionUpdate {
+  node_id: string;
+  x: number;
+  y: number;
+  pinned?: boolean;
+}
+
+export type LayoutScope = { scope: 'all' } | { scope: 'subtree'; root_id: string };
+
+export interface RecalcLayoutResponse {
+  ok: boolean;
+  scope: LayoutScope;
+  /** Null for the whole graph */
+  nodes_in_scope: number | null;
+  cleared: number;
+  layout_generation: number;
+  last_layout_at: string;
+}
+
 export interface TopologyV2Response {
   nodes: TopologyNodeV2[];
   edges: TopologyEdgeV2[];
@@ -205,3 +248,19 @@ export interface TopologyStoreState {
   setViewMode: (mode: ViewMode) => void;
   setViewFilter: (filter: TopologyViewFilter, siteFilter?: string) => void;
 }
+
+export type TopologyChangeKind = 'added' | 'updated' | 'removed';
+
+export interface TopologyChange {
+  node_id: string;
+  change: TopologyChangeKind;
+  revision: number;
+}
+
+export interface TopologyWatchResponse {
+  revision: number;
+  changes: TopologyChange[];
+  /** Revision too old for the change log: refetch the full topology */
+  truncated: boolean;
+  timed_out: boolean;
+}
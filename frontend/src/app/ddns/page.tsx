@@ -10,6 +10,7 @@ import { Badge } from '@/components/ui/Badge';
 import { Card } from '@/components/ui/Card';
 import { ddnsApi, ddnsIntegratedApi, omadaApi, type DdnsIntegrated, type OmadaControllerDoc } from '@/lib/api';
 import type { DdnsConfig, CreateDdnsRequest, DdnsProvider, DdnsStatus } from '@/types';
+import { LocalDnsCard } from '@/components/LocalDnsCard';
 
 type ViewTab = 'standard' | 'integrated';
 
@@ -193,6 +194,22 @@ export default function DdnsPage() {
           : <Badge variant="success">OK</Badge>
         : <span className="text-gray-500">-</span>
     )},
+    { key: 'dns_check', header: 'Routes DNS', render: (d: DdnsIntegrated) => (
+      d.dns_mismatch
+        ? <Badge variant="error">{d.dns_mismatch.state === 'unresolved' ? 'Unresolved' : 'Diverged'}</Badge>
+        : d.dns_check
+          ? d.dns_check.diverged_since
+            ? <Badge variant="warning">Pending</Badge>
+            : <Badge variant="success">OK</Badge>
+          : <span className="text-gray-500">-</span>
+    )},
+    { key: 'failover', header: 'Provider', render: (d: DdnsIntegrated) => (
+      d.failover.role === 'secondary'
+        ? <span className="text-gray-500">Standby for #{d.failover.primary_config_id}</span>
+        : d.failover.active
+          ? <Badge variant="warning">Failover: {d.failover.active_provider}</Badge>
+          : <span className="text-sm">{d.failover.active_provider}</span>
+    )},
     { key: 'linked', header: 'Linked', render: (d: DdnsIntegrated) => (
       d.linked_controller
         ? <Badge variant="info">{d.linked_controller}</Badge>
@@ -255,6 +272,13 @@ export default function DdnsPage() {
         </div>
       )}
 
+      {viewTab === 'integrated' && integrated.some(d => d.dns_mismatch) && (
+        <div className="mb-4 p-3 bg-red-900/30 border border-red-700 rounded text-red-400 text-sm">
+          Route-linked hostnames no longer resolve to this gateway:{' '}
+          {integrated.filter(d => d.dns_mismatch).map(d => `${d.config.hostname} → ${d.dns_mismatch!.observed.join(', ') || 'unresolved'}`).join('; ')}
+        </div>
+      )}
+
       {viewTab === 'standard' && (
         <Card>
           <Table columns={standardColumns} data={configs} keyExtractor={(c) => c.id} emptyMessage="No DDNS configurations" />
@@ -267,6 +291,8 @@ export default function DdnsPage() {
         </Card>
       )}
 
+      <LocalDnsCard />
+
       {/* Create/Edit Modal */}
       <Modal isOpen={isModalOpen} onClose={() => setIsModalOpen(false)} title={editingConfig ? 'Edit DDNS' : 'Add DDNS'}>
         <form onSubmit={handleSubmit} className="space-y-4">
@@ -285,8 +311,8 @@ export default function DdnsPage() {
               <Input label="API Token" type="password" placeholder={editingConfig ? '(unchanged)' : ''}
                 value={formData.api_token} onChange={(e) => setFormData({ ...formData, api_token: e.target.value })}
                 required={!editingConfig} />
-              <Input label="Zone ID" value={formData.zone_id}
-                onChange={(e) => setFormData({ ...formData, zone_id: e.target.value })} required={!editingConfig} />
+              <Input label="Zone ID" placeholder="(auto-detect from hostname)" value={formData.zone_id}
+                onChange={(e) => setFormData({ ...formData, zone_id: e.target.value })} />
             </>
           ) : (
             <>
@@ -103,6 +103,7 @@ function AppShell({ children }: { children: React.ReactNode }) {
                   ? 'bg-blue-600 text-white'
                   : 'bg-yellow-600 text-white'
               }`}
+              title={user.auth_provider ? `Provider: ${user.auth_provider}` : undefined}
             >
               {user.auth_method === 'lacisoath' ? 'LacisOath' : 'Master'}
             </span>
@@ -4,7 +4,7 @@ import { useState, useEffect, useCallback, Suspense } from 'react';
 import { useSearchParams } from 'next/navigation';
 import { useAuth } from '@/contexts/AuthContext';
 import { authApi } from '@/lib/api';
-import type { LacisOathConfig } from '@/types';
+import type { LacisOathConfig, LacisOathProviderConfig } from '@/types';
 
 function LoginContent() {
   const { user, loading, login, loginLacisOath } = useAuth();
@@ -17,6 +17,7 @@ function LoginContent() {
   const [showLocalLogin, setShowLocalLogin] = useState(false);
   const [oauthConfig, setOauthConfig] = useState<LacisOathConfig | null>(null);
   const [oauthLoading, setOauthLoading] = useState(true);
+  const [providerId, setProviderId] = useState<string>('');
 
   // Fetch OAuth 2.0 config on mount
   useEffect(() => {
@@ -24,6 +25,7 @@ function LoginContent() {
       .getLacisOathConfig()
       .then((config) => {
         setOauthConfig(config);
+        setProviderId(config.default_provider ?? '');
       })
       .catch(() => {
         setOauthConfig(null);
@@ -52,12 +54,14 @@ function LoginContent() {
     // Recover redirect_uri used for the authorization request
     const redirectUri = sessionStorage.getItem('lpg_oauth_redirect_uri') || '';
     sessionStorage.removeItem('lpg_oauth_redirect_uri');
+    const provider = sessionStorage.getItem('lpg_oauth_provider') || undefined;
+    sessionStorage.removeItem('lpg_oauth_provider');
 
     setSubmitting(true);
     setError('');
 
     try {
-      await loginLacisOath(code, redirectUri);
+      await loginLacisOath(code, redirectUri, provider);
     } catch (e: unknown) {
       const message = e instanceof Error ? e.message : 'LacisOath login failed';
       setError(message);
@@ -101,22 +105,27 @@ function LoginContent() {
     }
   };
 
+  const providers: LacisOathProviderConfig[] = oauthConfig?.providers ?? [];
+  const selectedProvider =
+    providers.find((p) => p.id === providerId) ?? providers[0] ?? null;
+
   const handleLacisOathLogin = () => {
-    if (!oauthConfig || !oauthConfig.enabled) return;
+    if (!oauthConfig || !oauthConfig.enabled || !selectedProvider) return;
 
     // Generate random state for CSRF protection
     const state = crypto.randomUUID();
     sessionStorage.setItem('lpg_oauth_state', state);
+    sessionStorage.setItem('lpg_oauth_provider', selectedProvider.id);
 
     // Build redirect_uri: current origin + basePath + /login?callback=1
     const redirectUri =
-      oauthConfig.redirect_uri ||
+      selectedProvider.redirect_uri ||
       `${window.location.origin}/LacisProxyGateway2/login?callback=1`;
     sessionStorage.setItem('lpg_oauth_redirect_uri', redirectUri);
 
-    // Redirect to mobes authorization endpoint
-    const authUrl = new URL(oauthConfig.auth_url);
-    authUrl.searchParams.set('client_id', oauthConfig.client_id);
+    // Redirect to the provider's authorization endpoint
+    const authUrl = new URL(selectedProvider.auth_url);
+    authUrl.searchParams.set('client_id', selectedProvider.client_id);
     authUrl.searchParams.set('redirect_uri', redirectUri);
     authUrl.searchParams.set('state', state);
 
@@ -144,6 +153,20 @@ function LoginContent() {
           )}
 
           {/* LacisOath login (primary) - only shown when OAuth client is configured */}
+          {oauthEnabled && providers.length > 1 && (
+            <select
+              value={selectedProvider?.id ?? ''}
+              onChange={(e) => setProviderId(e.target.value)}
+              disabled={submitting}
+              className="w-full mb-3 px-3 py-2 bg-background border border-border rounded-md text-text text-sm focus:outline-none focus:ring-2 focus:ring-blue-500 disabled:opacity-50"
+            >
+              {providers.map((p) => (
+                <option key={p.id} value={p.id}>
+                  {p.display_name}
+                </option>
+              ))}
+            </select>
+          )}
           {oauthEnabled && (
             <button
               type="button"
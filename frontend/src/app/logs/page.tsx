@@ -285,7 +285,7 @@ export default function LogsPage() {
 
   const handleExport = async () => {
     try {
-      await dashboardApi.exportCsv({ ...buildSearchParams(), limit: 10000, offset: 0 });
+      await dashboardApi.exportCsv(buildSearchParams());
     } catch (err) {
       console.error('Failed to export CSV:', err);
     }
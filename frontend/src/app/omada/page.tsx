@@ -242,6 +242,7 @@ function ControllersTab({ onSync }: { onSync: () => void }) {
               <div className="flex items-center gap-2">
                 <span className={`w-2.5 h-2.5 rounded-full ${
                   ctrl.status === 'connected' ? 'bg-green-400' :
+                  ctrl.status === 'partial' ? 'bg-yellow-400' :
                   ctrl.status === 'error' ? 'bg-red-400' : 'bg-gray-400'
                 }`} />
                 <h3 className="font-semibold text-lg">{ctrl.display_name}</h3>
@@ -167,9 +167,13 @@ export default function Dashboard() {
       key: 'healthy',
       header: 'Status',
       render: (h: RouteHealth) => (
-        <Badge variant={h.healthy ? 'success' : 'error'}>
-          {h.healthy ? 'Healthy' : 'Unhealthy'}
-        </Badge>
+        h.status === 'unknown' ? (
+          <Badge variant="default">Unknown</Badge>
+        ) : (
+          <Badge variant={h.healthy ? 'success' : 'error'}>
+            {h.healthy ? 'Healthy' : 'Unhealthy'}
+          </Badge>
+        )
       ),
     },
     {
@@ -560,22 +564,32 @@ export default function Dashboard() {
         </div>
       </Card>
 
+      {/* Partial database failures: affected values show '-' instead of 0 */}
+      {stats && Object.keys(stats.field_errors ?? {}).length > 0 && (
+        <div className="mb-4 p-3 rounded-lg border border-yellow-500/30 bg-yellow-500/10 text-sm text-yellow-400">
+          一部の統計を取得できませんでした:{' '}
+          {Object.entries(stats.field_errors)
+            .map(([field, err]) => `${field} (${err})`)
+            .join(', ')}
+        </div>
+      )}
+
       {/* Stats Grid */}
       <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-5 gap-4 mb-8">
         <Card className="text-center">
-          <div className="text-3xl font-bold">{stats?.total_requests_today.toLocaleString() ?? 0}</div>
+          <div className="text-3xl font-bold">{stats?.field_errors?.total_requests_today ? '-' : stats?.total_requests_today.toLocaleString() ?? 0}</div>
           <div className="text-sm text-gray-400">Requests Today</div>
         </Card>
         <Card className="text-center">
-          <div className="text-3xl font-bold text-blue-400">{stats?.active_routes ?? 0}</div>
+          <div className="text-3xl font-bold text-blue-400">{stats?.field_errors?.active_routes ? '-' : stats?.active_routes ?? 0}</div>
           <div className="text-sm text-gray-400">Active Routes</div>
         </Card>
         <Card className="text-center">
-          <div className="text-3xl font-bold text-purple-400">{stats?.active_ddns ?? 0}</div>
+          <div className="text-3xl font-bold text-purple-400">{stats?.field_errors?.active_ddns ? '-' : stats?.active_ddns ?? 0}</div>
           <div className="text-sm text-gray-400">Active DDNS</div>
         </Card>
         <Card className="text-center">
-          <div className="text-3xl font-bold text-red-400">{stats?.blocked_ips ?? 0}</div>
+          <div className="text-3xl font-bold text-red-400">{stats?.field_errors?.blocked_ips ? '-' : stats?.blocked_ips ?? 0}</div>
           <div className="text-sm text-gray-400">Blocked IPs</div>
         </Card>
         <Card className="text-center">
@@ -722,11 +736,28 @@ export default function Dashboard() {
                   ? 'bg-green-500'
                   : stats?.server_health === 'degraded'
                   ? 'bg-yellow-500'
-                  : 'bg-red-500'
+                  : stats?.server_health === 'unhealthy'
+                  ? 'bg-red-500'
+                  : 'bg-gray-500'
               }`}
             ></span>
             <span className="text-lg font-medium capitalize">{stats?.server_health ?? 'unknown'}</span>
           </div>
+          {stats?.local_dns?.enabled && (
+            <div className="flex items-center gap-2 mb-4 text-sm text-gray-400">
+              <span
+                className={`w-2.5 h-2.5 rounded-full ${
+                  stats.local_dns.status === 'ok'
+                    ? 'bg-green-500'
+                    : stats.local_dns.status === 'degraded'
+                    ? 'bg-yellow-500'
+                    : 'bg-red-500'
+                }`}
+              ></span>
+              Local DNS ({stats.local_dns.listen}): {stats.local_dns.status}
+              {stats.local_dns.last_error && <span className="text-red-400">- {stats.local_dns.last_error}</span>}
+            </div>
+          )}
           <Table
             columns={healthColumns}
             data={health}
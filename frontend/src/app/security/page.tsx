@@ -26,6 +26,11 @@ const EVENT_TYPE_OPTIONS = [
   { value: 'suspicious_activity', label: 'Suspicious' },
   { value: 'ddns_failure', label: 'DDNS Failure' },
   { value: 'health_check_failure', label: 'Health Failure' },
+  { value: 'new_device', label: 'New Device' },
+  { value: 'alert_rule', label: 'Alert Rule' },
+  { value: 'insecure_configuration', label: 'Insecure Config' },
+  { value: 'dns_mismatch', label: 'DNS Mismatch' },
+  { value: 'tls_certificate_error', label: 'TLS Certificate' },
 ];
 
 export default function SecurityPage() {
@@ -140,6 +145,16 @@ export default function SecurityPage() {
         return 'DDNS Failure';
       case 'health_check_failure':
         return 'Health Failure';
+      case 'new_device':
+        return 'New Device';
+      case 'alert_rule':
+        return 'Alert Rule';
+      case 'insecure_configuration':
+        return 'Insecure Config';
+      case 'dns_mismatch':
+        return 'DNS Mismatch';
+      case 'tls_certificate_error':
+        return 'TLS Certificate';
       default:
         return type;
     }
@@ -36,6 +36,7 @@ export default function ServerRoutesPage() {
     preserve_host: false,
     timeout_ms: 30000,
     websocket_support: false,
+    grpc: false,
   });
   const [error, setError] = useState('');
 
@@ -126,6 +127,7 @@ export default function ServerRoutesPage() {
       preserve_host: route.preserve_host,
       timeout_ms: route.timeout_ms ?? 30000,
       websocket_support: route.websocket_support,
+      grpc: route.grpc ?? false,
     });
     setIsModalOpen(true);
   };
@@ -134,7 +136,7 @@ export default function ServerRoutesPage() {
     setFormData({
       path: '', target: '', ddns_config_id: null, priority: 100,
       active: true, strip_prefix: true, preserve_host: false,
-      timeout_ms: 30000, websocket_support: false,
+      timeout_ms: 30000, websocket_support: false, grpc: false,
     });
   };
 
@@ -144,6 +146,8 @@ export default function ServerRoutesPage() {
     { key: 'priority' as const, header: 'Priority' },
     { key: 'active' as const, header: 'Status', render: (r: ProxyRoute) => <Badge variant={r.active ? 'success' : 'error'}>{r.active ? 'Active' : 'Inactive'}</Badge> },
     { key: 'websocket_support' as const, header: 'WS', render: (r: ProxyRoute) => r.websocket_support ? <Badge variant="info">WS</Badge> : null },
+    { key: 'dns_mismatch' as const, header: 'DNS', render: (r: ProxyRoute) => r.dns_mismatch ? <span title={`${r.dns_mismatch.hostname} → ${r.dns_mismatch.observed.join(', ') || 'unresolved'}`}><Badge variant="error">DNS mismatch</Badge></span> : null },
+    { key: 'grpc' as const, header: 'gRPC', render: (r: ProxyRoute) => r.grpc ? <Badge variant="info">gRPC</Badge> : null },
     { key: 'id' as const, header: 'Actions', render: (r: ProxyRoute) => (
       <div className="flex gap-1">
         <Button size="sm" variant="secondary" onClick={() => openEdit(r)}>Edit</Button>
@@ -248,6 +252,10 @@ export default function ServerRoutesPage() {
               <input type="checkbox" checked={formData.websocket_support} onChange={(e) => setFormData(prev => ({ ...prev, websocket_support: e.target.checked }))} className="w-4 h-4 rounded border-gray-600 bg-gray-800 text-blue-500" />
               <span className="text-sm">WebSocket</span>
             </label>
+            <label className="flex items-center gap-2 cursor-pointer">
+              <input type="checkbox" checked={formData.grpc ?? false} onChange={(e) => setFormData(prev => ({ ...prev, grpc: e.target.checked }))} className="w-4 h-4 rounded border-gray-600 bg-gray-800 text-blue-500" />
+              <span className="text-sm">gRPC</span>
+            </label>
           </div>
           <div className="flex justify-end gap-2 pt-4">
             <Button variant="secondary" onClick={() => { setIsModalOpen(false); setEditingRoute(null); }}>Cancel</Button>
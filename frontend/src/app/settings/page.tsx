@@ -22,6 +22,7 @@ const settingGroups: SettingGroup[] = [
       'discord_notify_security',
       'discord_notify_health',
       'discord_notify_ddns',
+      'discord_notify_new_device',
     ],
   },
   {
@@ -50,6 +51,7 @@ const settingLabels: Record<string, string> = {
   discord_notify_security: 'Notify Security Events',
   discord_notify_health: 'Notify Health Check Failures',
   discord_notify_ddns: 'Notify DDNS Updates',
+  discord_notify_new_device: 'Notify New Devices',
   rate_limit_enabled: 'Enable Rate Limiting',
   rate_limit_requests_per_minute: 'Requests per Minute',
   health_check_interval_sec: 'Check Interval (seconds)',
@@ -767,6 +769,12 @@ export default function SettingsPage() {
                     Current: {nginxStatus.client_max_body_size}
                   </p>
                 )}
+                {nginxStatus && (
+                  <p className="text-xs text-gray-500 mt-1">
+                    Built-in proxy: {Math.round(nginxStatus.proxy_max_body_bytes / 1024 / 1024)}M
+                    (routes may set their own limit)
+                  </p>
+                )}
               </div>
 
               {/* Reload Button */}
@@ -0,0 +1,119 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import { Button } from '@/components/ui/Button';
+import { Input } from '@/components/ui/Input';
+import { Table } from '@/components/ui/Table';
+import { Badge } from '@/components/ui/Badge';
+import { Card } from '@/components/ui/Card';
+import {
+  localDnsApi,
+  type DnsHealth,
+  type DnsOverride,
+  type DnsOverrideSuggestion,
+  type DnsRecordHits,
+} from '@/lib/api';
+
+/** Local DNS overrides served to LAN clients, with DDNS suggestions and per-record hits */
+export function LocalDnsCard() {
+  const [overrides, setOverrides] = useState<DnsOverride[]>([]);
+  const [suggestions, setSuggestions] = useState<DnsOverrideSuggestion[]>([]);
+  const [health, setHealth] = useState<DnsHealth | null>(null);
+  const [hits, setHits] = useState<Record<string, DnsRecordHits>>({});
+  const [form, setForm] = useState({ hostname: '', target: '' });
+  const [error, setError] = useState('');
+
+  const load = async () => {
+    try {
+      const [list, suggested, status] = await Promise.all([
+        localDnsApi.list(),
+        localDnsApi.suggestions(),
+        localDnsApi.status(0),
+      ]);
+      setOverrides(list);
+      setSuggestions(suggested);
+      setHealth(status.health);
+      setHits(Object.fromEntries(status.records.map((r) => [r.hostname, r])));
+    } catch (err) {
+      console.error('Failed to load local DNS:', err);
+    }
+  };
+
+  useEffect(() => {
+    load();
+  }, []);
+
+  const run = async (action: () => Promise<unknown>) => {
+    setError('');
+    try {
+      await action();
+      await load();
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Request failed');
+    }
+  };
+
+  const handleAdd = (e: React.FormEvent) => {
+    e.preventDefault();
+    run(async () => {
+      await localDnsApi.create({ hostname: form.hostname, target: form.target || null });
+      setForm({ hostname: '', target: '' });
+    });
+  };
+
+  const columns = [
+    { key: 'hostname', header: 'Hostname', render: (o: DnsOverride) => <code className="text-blue-400">{o.hostname}</code> },
+    { key: 'target', header: 'Answer', render: (o: DnsOverride) => (
+      <span className="text-sm">{o.target ?? <span className="text-gray-400">LAN IP{health?.lan_ip ? ` (${health.lan_ip})` : ''}</span>}</span>
+    )},
+    { key: 'source', header: 'Source', render: (o: DnsOverride) => <Badge variant={o.source === 'ddns' ? 'info' : 'default'}>{o.source}</Badge> },
+    { key: 'hits', header: 'Queries (1h / total)', render: (o: DnsOverride) => {
+      const h = hits[o.hostname];
+      return <span className="text-sm">{h ? `${h.last_hour} / ${h.total}` : '-'}</span>;
+    }},
+    { key: 'enabled', header: 'Enabled', render: (o: DnsOverride) => (
+      <Button size="sm" variant="ghost" onClick={() => run(() => localDnsApi.update(o.id, { enabled: !o.enabled }))}>
+        {o.enabled ? 'On' : 'Off'}
+      </Button>
+    )},
+    { key: 'actions', header: '', render: (o: DnsOverride) => (
+      <Button size="sm" variant="danger" onClick={() => confirm(`Delete override for ${o.hostname}?`) && run(() => localDnsApi.delete(o.id))}>
+        Del
+      </Button>
+    )},
+  ];
+
+  return (
+    <Card title="Local DNS Overrides" className="mt-6">
+      <p className="text-sm text-gray-400 mb-3">
+        {health?.enabled
+          ? `Responder on ${health.listen} (${health.status}), upstream ${health.upstream}.`
+          : 'The LAN DNS responder is disabled ([dns] enabled = false); overrides are stored but not served.'}
+        {health?.last_error && <span className="text-red-400"> {health.last_error}</span>}
+      </p>
+
+      {suggestions.length > 0 && (
+        <div className="mb-3 flex flex-wrap gap-2 items-center text-sm">
+          <span className="text-gray-400">Suggested from DDNS:</span>
+          {suggestions.map((s) => (
+            <Button key={s.hostname} size="sm" variant="ghost"
+              onClick={() => run(() => localDnsApi.create({ hostname: s.hostname, source: 'ddns' }))}>
+              + {s.hostname}
+            </Button>
+          ))}
+        </div>
+      )}
+
+      <Table columns={columns} data={overrides} keyExtractor={(o) => o.id} emptyMessage="No overrides" />
+
+      <form onSubmit={handleAdd} className="mt-4 flex gap-2 items-end">
+        <Input label="Hostname" placeholder="home.example.com" value={form.hostname}
+          onChange={(e) => setForm({ ...form, hostname: e.target.value })} required />
+        <Input label="Target (empty = LAN IP)" placeholder="192.168.3.20" value={form.target}
+          onChange={(e) => setForm({ ...form, target: e.target.value })} />
+        <Button type="submit">Add</Button>
+      </form>
+      {error && <p className="mt-2 text-sm text-red-400">{error}</p>}
+    </Card>
+  );
+}
@@ -9,7 +9,7 @@ interface AuthContextType {
   user: AuthUser | null;
   loading: boolean;
   login: (method: 'local', data: { email: string; password: string }) => Promise<void>;
-  loginLacisOath: (code: string, redirectUri: string) => Promise<void>;
+  loginLacisOath: (code: string, redirectUri: string, provider?: string) => Promise<void>;
   logout: () => Promise<void>;
 }
 
@@ -57,8 +57,8 @@ export function AuthProvider({ children }: { children: ReactNode }) {
   );
 
   const loginLacisOath = useCallback(
-    async (code: string, redirectUri: string) => {
-      const res = await authApi.loginLacisOath(code, redirectUri);
+    async (code: string, redirectUri: string, provider?: string) => {
+      const res = await authApi.loginLacisOath(code, redirectUri, provider);
       setUser(res.user);
       router.push('/');
     },
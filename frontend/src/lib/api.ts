@@ -2,33 +2,88 @@
 
 import type {
   ProxyRoute,
+  ProxyRouteDetail,
+  SecurityHeadersReport,
+  RouteTestResult,
   CreateRouteRequest,
   UpdateRouteRequest,
+  RouteCachePurgeResult,
+  RouteSyncSpec,
+  RouteSyncResult,
+  RoutePendingChange,
   DdnsConfig,
+  DdnsProvider,
   CreateDdnsRequest,
   UpdateDdnsRequest,
+  DdnsTestRequest,
+  DdnsTestResult,
+  DnsMatchState,
+  DnsMismatch,
   BlockedIp,
   BlockIpRequest,
+  QuickBlockRequest,
+  QuickBlockResponse,
   SecurityEvent,
+  AlertRule,
+  CreateAlertRuleRequest,
+  UpdateAlertRuleRequest,
+  AlertRuleTestResult,
+  RateLimitRule,
+  RateLimitRuleList,
+  GeoRules,
+  GeoRulesView,
+  CreateRateLimitRuleRequest,
+  UpdateRateLimitRuleRequest,
+  RouteTunnelSummary,
+  MaintenanceWindow,
+  CreateMaintenanceWindowRequest,
+  UpdateMaintenanceWindowRequest,
+  ActiveMaintenanceResponse,
   Setting,
   DashboardStats,
   RouteHealth,
+  DependenciesStatus,
   AccessLog,
   StatusDistribution,
   SuccessResponse,
   HourlyStat,
+  HourlyStatsComparison,
   TopEntry,
   ErrorSummary,
   AccessLogSearchResult,
   AccessLogSearchParams,
+  AccessLogDeleteFilter,
+  AccessLogDeleteJob,
+  InFlightList,
+  WebSocketSessionList,
+  NotificationQueueStats,
   SecurityEventSearchParams,
   IpExclusionParams,
   AuthResponse,
   LacisOathConfig,
+  LacisOathProvider,
+  LacisOathProviderInput,
+  ErrorCode,
+  ErrorResponse,
 } from '@/types';
 
 const API_BASE = '/LacisProxyGateway2/api';
 
+/** Failed API call; `code` is stable, `message` is for display */
+export class ApiError extends Error {
+  readonly code?: ErrorCode;
+  readonly status: number;
+  readonly details?: ErrorResponse['details'];
+
+  constructor(status: number, body: Partial<ErrorResponse>) {
+    super(body.error || `HTTP ${status}`);
+    this.name = 'ApiError';
+    this.code = body.code;
+    this.status = status;
+    this.details = body.details;
+  }
+}
+
 async function request<T>(path: string, options?: RequestInit): Promise<T> {
   const response = await fetch(`${API_BASE}${path}`, {
     ...options,
@@ -40,8 +95,10 @@ async function request<T>(path: string, options?: RequestInit): Promise<T> {
   });
 
   if (!response.ok) {
-    const error = await response.json().catch(() => ({ error: 'Unknown error' }));
-    throw new Error(error.error || `HTTP ${response.status}`);
+    const error: Partial<ErrorResponse> = await response
+      .json()
+      .catch(() => ({ error: 'Unknown error' }));
+    throw new ApiError(response.status, error);
   }
 
   return response.json();
@@ -51,11 +108,31 @@ async function request<T>(path: string, options?: RequestInit): Promise<T> {
 // Routes API
 // ============================================================================
 
+/** Immediate target probe run when a route is activated */
+export interface RouteActivationCheck {
+  probe: {
+    target: string;
+    healthy: boolean;
+    response_time_ms: number | null;
+    status_code: number | null;
+    error: string | null;
+  };
+  warming: boolean;
+}
+
 export interface RouteDetailedStatus {
   route_id: number;
   path: string;
   target: string;
   active: boolean;
+  /** Activated before the target was healthy; 503 until it is */
+  warming: boolean;
+  /** Answering the maintenance page; health checks paused */
+  maintenance_mode: boolean;
+  maintenance_message: string | null;
+  owner_name?: string | null;
+  owner_contact?: string | null;
+  team?: string | null;
   healthy: boolean;
   last_check: string | null;
   consecutive_failures: number;
@@ -65,12 +142,265 @@ export interface RouteDetailedStatus {
   requests_last_hour: number;
   error_rate_percent: number;
   avg_response_time_ms: number;
+  /** WebSocket tunnel totals; null until the route carried a tunnel */
+  websocket: RouteTunnelSummary | null;
+  upstream: RouteUpstreamStatus;
+  /** The target first, then each fallback target */
+  targets: TargetHealth[];
+}
+
+/** Health of one target of a route */
+export interface TargetHealth {
+  target: string;
+  primary: boolean;
+  /** true until the target has been checked */
+  healthy: boolean;
+  last_check: string | null;
+  consecutive_failures: number;
+  response_time_ms: number | null;
+  last_status_code: number | null;
+  error: string | null;
+}
+
+/** Upstream client of a route and its counters since startup */
+export interface RouteUpstreamStatus {
+  /** shared client, a tuned client of its own, or shared because too many tunings exist */
+  pool: 'shared' | 'tuned' | 'overflow';
+  timeout_ms: number;
+  connect_timeout_ms: number;
+  max_idle_per_host: number;
+  requests: number;
+  pool_hits: number;
+  pool_misses: number;
+  timeouts: number;
+  connect_errors: number;
+  last_timeout_at: string | null;
+  /** Time to the upstream response head */
+  avg_ttfb_ms: number | null;
+  max_ttfb_ms: number | null;
+}
+
+export interface TracePhaseTimings {
+  blocklist_us: number;
+  route_match_us: number;
+  request_body_us: number;
+  dns_us: number | null;
+  connect_us: number | null;
+  ttfb_us: number;
+  response_body_us: number;
+  total_us: number;
+}
+
+export interface RouteTraceSummary {
+  route_id: number;
+  enabled: boolean;
+  expires_at: string | null;
+  sample_count: number;
+  capacity: number;
+  phases: Record<string, { count: number; p50_us: number; p95_us: number; p99_us: number; max_us: number }>;
+  slowest: {
+    request_id: string;
+    timestamp: string;
+    method: string;
+    path: string;
+    status: number;
+    error: string | null;
+    phases: TracePhaseTimings;
+  }[];
+}
+
+// Canary deployments (GET /api/routes/:id/canary-stats)
+export interface CanarySideStats {
+  target: string;
+  requests: number;
+  /** Responses with status >= 500 */
+  errors: number;
+  error_rate: number;
+  p50_ms: number;
+  p95_ms: number;
+  p99_ms: number;
+  latency_samples: number;
+}
+
+export interface CanaryStats {
+  route_id: number;
+  minutes: number;
+  canary_percent: number;
+  canary_sticky: boolean;
+  primary: CanarySideStats;
+  canary: CanarySideStats | null;
+}
+
+// Route configuration history (GET /api/routes/:id/versions)
+export interface RouteVersionChange {
+  field: string;
+  from: unknown;
+  to: unknown;
+}
+
+export interface RouteVersion {
+  id: number;
+  route_id: number;
+  version: number;
+  /** Whole route as written */
+  config: ProxyRoute;
+  /** User, or "system" for writes LPG made on its own */
+  actor: string;
+  reason: string;
+  restored_version: number | null;
+  created_at: string;
+  /** Against the previous version; empty for the oldest kept */
+  changes: RouteVersionChange[];
+}
+
+export interface RouteVersionList {
+  route_id: number;
+  retention: number;
+  versions: RouteVersion[];
+}
+
+// Store-and-forward (per-route queue for unreachable upstreams)
+export interface RouteStoreForward {
+  methods: string[];
+  path_prefix?: string | null;
+  max_body_bytes: number;
+  ttl_secs: number;
+}
+
+// Transformation scripts (Rhai on_request/on_response hooks)
+export interface RouteTransform {
+  enabled: boolean;
+  script: string;
+  on_error: 'open' | 'closed';
+  timeout_ms: number;
+  body_kb: number;
+}
+
+export interface HookMetrics {
+  runs: number;
+  failures: number;
+  timeouts: number;
+  avg_us: number;
+  max_us: number;
+  last_error: string | null;
+}
+
+export interface TransformLimits {
+  max_script_bytes: number;
+  max_timeout_ms: number;
+  max_body_kb: number;
+}
+
+export type LogFieldSource =
+  | { source: 'header'; name: string }
+  | { source: 'path'; pattern: string; group?: number }
+  | { source: 'query'; name: string };
+
+/** Custom access log field rules by field name */
+export type RouteLogFields = Record<string, LogFieldSource>;
+
+export interface LogFieldLimits {
+  max_fields: number;
+  max_value_chars: number;
+  denied_headers: string[];
+}
+
+export interface CustomFieldValueStat {
+  value: string;
+  requests: number;
+  /** Responses with status >= 400 */
+  errors: number;
+  error_rate_percent: number;
+  avg_response_time_ms: number;
+}
+
+export interface CustomFieldSummary {
+  route_id: number;
+  field: string;
+  from: string;
+  to: string;
+  total_requests: number;
+  distinct_values: number;
+  /** More values exist than were returned; their requests are in other_requests */
+  truncated: boolean;
+  other_requests: number;
+  values: CustomFieldValueStat[];
+}
+
+export interface HostnameUsage {
+  /** "YYYY-MM" */
+  month: string;
+  /** DDNS hostname, or "unassigned" for routes without a DDNS config */
+  hostname: string;
+  requests: number;
+  bytes_in: number;
+  bytes_out: number;
+  errors_4xx: number;
+  errors_5xx: number;
+  peak_hourly_requests: number;
+  /** "YYYY-MM-DDTHH" (UTC) */
+  peak_hour: string | null;
+  route_ids: number[];
+  updated_at: string | null;
+}
+
+export interface HostnameUsageTotals {
+  requests: number;
+  bytes_in: number;
+  bytes_out: number;
+  errors_4xx: number;
+  errors_5xx: number;
+}
+
+export interface HostnameUsageReport {
+  month: string;
+  /** Month is closed; rows only change by an explicit re-run */
+  complete: boolean;
+  updated_at: string | null;
+  totals: HostnameUsageTotals;
+  hostnames: HostnameUsage[];
+}
+
+export interface ForwardAttempt {
+  at: string;
+  status: number | null;
+  error: string | null;
+  duration_ms: number;
+}
+
+export interface ForwardQueueItem {
+  queue_id: string;
+  route_id: number;
+  method: string;
+  url: string;
+  path: string;
+  /** Credentials are masked */
+  headers: [string, string][];
+  body_size: number;
+  timeout_ms: number;
+  client_ip: string;
+  reason: string;
+  status: 'pending' | 'delivered' | 'failed';
+  attempt_count: number;
+  attempts: ForwardAttempt[];
+  next_attempt_at: string;
+  expires_at: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface RouteQueueResponse {
+  route_id: number;
+  store_forward: RouteStoreForward | null;
+  pending: number;
+  failed: number;
+  items: ForwardQueueItem[];
 }
 
 export const routesApi = {
   list: () => request<ProxyRoute[]>('/routes'),
 
-  get: (id: number) => request<ProxyRoute>(`/routes/${id}`),
+  get: (id: number) => request<ProxyRouteDetail>(`/routes/${id}`),
 
   create: (data: CreateRouteRequest) =>
     request<SuccessResponse>('/routes', {
@@ -78,24 +408,174 @@ export const routesApi = {
       body: JSON.stringify(data),
     }),
 
-  update: (id: number, data: UpdateRouteRequest) =>
-    request<SuccessResponse>(`/routes/${id}`, {
-      method: 'PUT',
-      body: JSON.stringify(data),
-    }),
+  /** Activation probes the target; `warm` activates even if it is not ready yet */
+  update: (id: number, data: UpdateRouteRequest, warm: boolean = false) =>
+    request<SuccessResponse & { activation?: RouteActivationCheck }>(
+      `/routes/${id}${warm ? '?warm=true' : ''}`,
+      {
+        method: 'PUT',
+        body: JSON.stringify(data),
+      }
+    ),
 
   delete: (id: number) =>
     request<SuccessResponse>(`/routes/${id}`, {
       method: 'DELETE',
     }),
 
+  /** Converge on the declarative set; prune deletes unlisted routes (permission 100) */
+  sync: (
+    routes: RouteSyncSpec[],
+    options: { prune?: boolean; dry_run?: boolean; strict?: boolean } = {}
+  ) => {
+    const params = new URLSearchParams();
+    if (options.prune) params.set('prune', 'true');
+    if (options.dry_run) params.set('dry_run', 'true');
+    if (options.strict) params.set('strict', 'true');
+    const query = params.toString();
+    return request<RouteSyncResult>(`/routes/sync${query ? `?${query}` : ''}`, {
+      method: 'PUT',
+      body: JSON.stringify({ routes }),
+    });
+  },
+
   // Status and health APIs
   getAllStatus: () => request<RouteDetailedStatus[]>('/routes/status'),
 
+  test: (path: string, method: string = 'GET', host?: string) => {
+    const params = new URLSearchParams({ path, method });
+    if (host) params.set('host', host);
+    return request<RouteTestResult>(`/routes/test?${params}`);
+  },
+
+  getSecurityHeadersReport: () =>
+    request<SecurityHeadersReport>('/routes/security-headers/report'),
+
   getStatus: (id: number) => request<RouteDetailedStatus>(`/routes/${id}/status`),
 
   getLogs: (id: number, limit: number = 50) =>
     request<AccessLog[]>(`/routes/${id}/logs?limit=${limit}`),
+
+  // Request tracing (auto-expiring)
+  setTrace: (id: number, enabled: boolean, durationSecs?: number) =>
+    request<{ route_id: number; enabled: boolean; expires_at?: string; capacity?: number }>(
+      `/routes/${id}/trace`,
+      { method: 'PUT', body: JSON.stringify({ enabled, duration_secs: durationSecs }) }
+    ),
+
+  getTraces: (id: number, slowest: number = 20) =>
+    request<RouteTraceSummary>(`/routes/${id}/traces?slowest=${slowest}`),
+
+  // Canary: compare sides over a window; kill sends all traffic to the primary
+  getCanaryStats: (id: number, minutes: number = 60) =>
+    request<CanaryStats>(`/routes/${id}/canary-stats?minutes=${minutes}`),
+
+  killCanary: (id: number) =>
+    request<{ message: string }>(`/routes/${id}/canary/kill`, { method: 'POST' }),
+
+  /** Maintenance page on/off; omit `enabled` to toggle, `message: null` restores the default text */
+  setMaintenance: (id: number, body: { enabled?: boolean; message?: string | null } = {}) =>
+    request<{ route_id: number; maintenance_mode: boolean; maintenance_message: string | null }>(
+      `/routes/${id}/maintenance`,
+      { method: 'POST', body: JSON.stringify(body) }
+    ),
+
+  /** Drops the route's cached GET responses */
+  purgeCache: (id: number) =>
+    request<RouteCachePurgeResult>(`/routes/${id}/cache/purge`, { method: 'POST' }),
+
+  getVersions: (id: number) => request<RouteVersionList>(`/routes/${id}/versions`),
+
+  /** Restores `version` as a new version; history is never rewritten */
+  rollback: (id: number, version: number) =>
+    request<{
+      message: string;
+      version: number | null;
+      restored_version: number;
+      changes: RouteVersionChange[];
+    }>(`/routes/${id}/rollback/${version}`, { method: 'POST' }),
+
+  // Store-and-forward; enabling needs confirm (first call returns the warning)
+  setStoreForward: (id: number, policy: RouteStoreForward | null, confirm: boolean = false) =>
+    request<{
+      route_id?: number;
+      store_forward?: RouteStoreForward | null;
+      confirm_required?: boolean;
+      warning?: string;
+    }>(`/routes/${id}/store-forward`, {
+      method: 'PUT',
+      body: JSON.stringify({ policy, confirm }),
+    }),
+
+  // Transformation script; compile errors come back as validation errors
+  getTransform: (id: number) =>
+    request<{
+      route_id: number;
+      transform: RouteTransform | null;
+      metrics: { on_request: HookMetrics; on_response: HookMetrics };
+      limits: TransformLimits;
+    }>(`/routes/${id}/transform`),
+
+  setTransform: (id: number, policy: RouteTransform | null) =>
+    request<{ route_id: number; transform: RouteTransform | null; limits: TransformLimits }>(
+      `/routes/${id}/transform`,
+      { method: 'PUT', body: JSON.stringify({ policy }) }
+    ),
+
+  getLogFields: (id: number) =>
+    request<{ route_id: number; fields: RouteLogFields; limits: LogFieldLimits }>(
+      `/routes/${id}/log-fields`
+    ),
+
+  // An empty object removes the rules
+  setLogFields: (id: number, fields: RouteLogFields) =>
+    request<{ route_id: number; fields: RouteLogFields; limits: LogFieldLimits }>(
+      `/routes/${id}/log-fields`,
+      { method: 'PUT', body: JSON.stringify({ fields }) }
+    ),
+
+  setStatusPage: (id: number, show: boolean, displayName?: string | null) =>
+    request<{ route_id: number; show_on_status_page: boolean; status_page_name: string | null }>(
+      `/routes/${id}/status-page`,
+      {
+        method: 'PUT',
+        body: JSON.stringify({ show, display_name: displayName ?? null }),
+      }
+    ),
+
+  getQueue: (id: number, status?: ForwardQueueItem['status'], limit: number = 100) => {
+    const params = new URLSearchParams({ limit: String(limit) });
+    if (status) params.set('status', status);
+    return request<RouteQueueResponse>(`/routes/${id}/queue?${params}`);
+  },
+
+  replayQueued: (id: number, queueId: string) =>
+    request<{ queue_id: string; status: string; item: ForwardQueueItem | null }>(
+      `/routes/${id}/queue/${queueId}/replay`,
+      { method: 'POST' }
+    ),
+
+  discardQueued: (id: number, queueId: string, confirm: boolean = false) =>
+    request<{ message?: string; confirm_required?: boolean; warning?: string }>(
+      `/routes/${id}/queue/${queueId}${confirm ? '?confirm=true' : ''}`,
+      { method: 'DELETE' }
+    ),
+
+  // Approval workflow (route_approval_required)
+  listPending: (status: 'pending' | 'approved' | 'rejected' | 'all' = 'pending') =>
+    request<RoutePendingChange[]>(`/routes/pending?status=${status}`),
+
+  approvePending: (id: number, comment?: string, warm: boolean = false) =>
+    request<SuccessResponse>(`/routes/pending/${id}/approve${warm ? '?warm=true' : ''}`, {
+      method: 'POST',
+      body: JSON.stringify({ comment }),
+    }),
+
+  rejectPending: (id: number, comment?: string) =>
+    request<SuccessResponse>(`/routes/pending/${id}/reject`, {
+      method: 'POST',
+      body: JSON.stringify({ comment }),
+    }),
 };
 
 // ============================================================================
@@ -107,14 +587,24 @@ export const ddnsApi = {
 
   get: (id: number) => request<DdnsConfig>(`/ddns/${id}`),
 
-  create: (data: CreateDdnsRequest) =>
-    request<SuccessResponse>('/ddns', {
+  /** validate: test the credentials first; a failing config is rejected */
+  create: (data: CreateDdnsRequest, validate = false) =>
+    request<SuccessResponse & { report_token?: string; validation?: DdnsTestResult | null }>(
+      validate ? '/ddns?validate=true' : '/ddns',
+      {
+        method: 'POST',
+        body: JSON.stringify(data),
+      },
+    ),
+
+  test: (data: DdnsTestRequest) =>
+    request<DdnsTestResult>('/ddns/test', {
       method: 'POST',
       body: JSON.stringify(data),
     }),
 
   update: (id: number, data: UpdateDdnsRequest) =>
-    request<SuccessResponse>(`/ddns/${id}`, {
+    request<SuccessResponse & { report_token?: string }>(`/ddns/${id}`, {
       method: 'PUT',
       body: JSON.stringify(data),
     }),
@@ -128,12 +618,133 @@ export const ddnsApi = {
     request<SuccessResponse>(`/ddns/${id}/update`, {
       method: 'POST',
     }),
+
+  rotateReportToken: (id: number) =>
+    request<{ message: string; report_token: string }>(`/ddns/${id}/report-token`, {
+      method: 'POST',
+    }),
+
+  issueAcme: (id: number) =>
+    request<{ message: string; hostname: string }>(`/ddns/${id}/acme/issue`, {
+      method: 'POST',
+    }),
 };
 
 // ============================================================================
 // Security API
 // ============================================================================
 
+/** Topology device currently holding a profiled IP */
+export interface IpProfileDevice {
+  node_id: string;
+  label: string;
+  mac: string;
+  node_type: string;
+  claimed_by?: NodeClaim;
+}
+
+/** One UTC day of an IP's traffic (ip_daily_stats) */
+export interface IpDailyStat {
+  ip: string;
+  day: string;
+  requests: number;
+  /** Status >= 400 */
+  errors: number;
+  distinct_paths: number;
+  bytes: number;
+  countries: string[];
+  security_events: number;
+}
+
+export interface IpProfile {
+  ip: string;
+  blocked: boolean;
+  devices: IpProfileDevice[];
+  events: SecurityEvent[];
+  /** Oldest first, zero-filled */
+  daily: IpDailyStat[];
+}
+
+// Local DNS overrides (built-in LAN responder, [dns] config)
+export interface DnsOverride {
+  id: number;
+  hostname: string;
+  /** null = the LPG's LAN IP */
+  target: string | null;
+  enabled: boolean;
+  source: 'manual' | 'ddns';
+  comment: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface DnsOverrideSuggestion {
+  hostname: string;
+  ddns_config_id: number;
+  public_ip: string | null;
+  target: string | null;
+}
+
+export interface DnsHealth {
+  enabled: boolean;
+  status: 'disabled' | 'ok' | 'degraded' | 'down';
+  listen: string;
+  upstream: string;
+  lan_ip: string | null;
+  udp_listening: boolean;
+  tcp_listening: boolean;
+  started_at: string | null;
+  last_error: string | null;
+  last_upstream_error_at: string | null;
+  overrides: number;
+  counters: {
+    queries: number;
+    answered_local: number;
+    forwarded: number;
+    upstream_failures: number;
+    refused: number;
+    malformed: number;
+  };
+}
+
+export interface DnsRecordHits {
+  hostname: string;
+  total: number;
+  last_hour: number;
+  last_query_at: string | null;
+  last_client: string | null;
+}
+
+export interface DnsQueryLogEntry {
+  at: string;
+  client: string;
+  transport: 'udp' | 'tcp';
+  name: string | null;
+  qtype: number | null;
+  outcome: 'override' | 'forwarded' | 'upstream_failed' | 'refused' | 'malformed';
+  duration_us: number;
+}
+
+export const localDnsApi = {
+  list: () => request<DnsOverride[]>('/dns/overrides'),
+
+  create: (data: { hostname: string; target?: string | null; enabled?: boolean; source?: 'manual' | 'ddns'; comment?: string }) =>
+    request<DnsOverride>('/dns/overrides', { method: 'POST', body: JSON.stringify(data) }),
+
+  update: (id: number, data: { hostname?: string; target?: string | null; enabled?: boolean; comment?: string }) =>
+    request<DnsOverride>(`/dns/overrides/${id}`, { method: 'PUT', body: JSON.stringify(data) }),
+
+  delete: (id: number) =>
+    request<{ message: string }>(`/dns/overrides/${id}`, { method: 'DELETE' }),
+
+  suggestions: () => request<DnsOverrideSuggestion[]>('/dns/suggestions'),
+
+  status: (recent: number = 50) =>
+    request<{ health: DnsHealth; records: DnsRecordHits[]; recent_queries: DnsQueryLogEntry[] }>(
+      `/dns/status?recent=${recent}`
+    ),
+};
+
 export const securityApi = {
   listBlockedIps: () => request<BlockedIp[]>('/security/blocked-ips'),
 
@@ -148,11 +759,20 @@ export const securityApi = {
       method: 'DELETE',
     }),
 
+  quickBlock: (data: QuickBlockRequest) =>
+    request<QuickBlockResponse & { confirm_required?: boolean; warning?: string }>(
+      '/security/quick-block',
+      { method: 'POST', body: JSON.stringify(data) }
+    ),
+
   listEvents: (limit = 50, offset = 0) =>
     request<SecurityEvent[]>(`/security/events?limit=${limit}&offset=${offset}`),
 
   getEventsByIp: (ip: string) => request<SecurityEvent[]>(`/security/events/ip/${ip}`),
 
+  getIpProfile: (ip: string, days?: number) =>
+    request<IpProfile>(`/security/ip/${ip}${days !== undefined ? `?days=${days}` : ''}`),
+
   searchEvents: (params: SecurityEventSearchParams) => {
     const query = new URLSearchParams();
     if (params.from) query.set('from', params.from);
@@ -164,6 +784,89 @@ export const securityApi = {
     if (params.offset !== undefined) query.set('offset', params.offset.toString());
     return request<SecurityEvent[]>(`/security/events/search?${query}`);
   },
+
+  listAlertRules: () => request<AlertRule[]>('/security/alert-rules'),
+
+  getAlertRule: (id: string) => request<AlertRule>(`/security/alert-rules/${id}`),
+
+  createAlertRule: (data: CreateAlertRuleRequest) =>
+    request<AlertRule>('/security/alert-rules', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  updateAlertRule: (id: string, data: UpdateAlertRuleRequest) =>
+    request<AlertRule>(`/security/alert-rules/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
+  deleteAlertRule: (id: string) =>
+    request<SuccessResponse>(`/security/alert-rules/${id}`, {
+      method: 'DELETE',
+    }),
+
+  testAlertRule: (id: string) =>
+    request<AlertRuleTestResult>(`/security/alert-rules/${id}/test`, {
+      method: 'POST',
+    }),
+
+  listRateLimits: () => request<RateLimitRuleList>('/security/rate-limits'),
+
+  createRateLimit: (data: CreateRateLimitRuleRequest) =>
+    request<RateLimitRule>('/security/rate-limits', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  updateRateLimit: (id: number, data: UpdateRateLimitRuleRequest) =>
+    request<RateLimitRule>(`/security/rate-limits/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
+  deleteRateLimit: (id: number) =>
+    request<SuccessResponse>(`/security/rate-limits/${id}`, {
+      method: 'DELETE',
+    }),
+
+  getGeoRules: () => request<GeoRulesView>('/security/geo-rules'),
+
+  updateGeoRules: (data: GeoRules) =>
+    request<GeoRulesView>('/security/geo-rules', {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+};
+
+// ============================================================================
+// Maintenance Windows API
+// ============================================================================
+
+export const maintenanceApi = {
+  listWindows: () => request<MaintenanceWindow[]>('/maintenance/windows'),
+
+  getWindow: (id: string) => request<MaintenanceWindow>(`/maintenance/windows/${id}`),
+
+  createWindow: (data: CreateMaintenanceWindowRequest) =>
+    request<MaintenanceWindow>('/maintenance/windows', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  updateWindow: (id: string, data: UpdateMaintenanceWindowRequest) =>
+    request<MaintenanceWindow>(`/maintenance/windows/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
+  deleteWindow: (id: string) =>
+    request<SuccessResponse>(`/maintenance/windows/${id}`, {
+      method: 'DELETE',
+    }),
+
+  /** Windows active now; polled for the maintenance banner */
+  getActive: () => request<ActiveMaintenanceResponse>('/maintenance/active'),
 };
 
 // ============================================================================
@@ -176,6 +879,7 @@ export interface RestartSettings {
   auto_restart_enabled: boolean;
   cpu_threshold: number;
   ram_threshold: number;
+  drain_grace_sec: number;
 }
 
 export interface UpdateRestartSettingsRequest {
@@ -184,6 +888,7 @@ export interface UpdateRestartSettingsRequest {
   auto_restart_enabled?: boolean;
   cpu_threshold?: number;
   ram_threshold?: number;
+  drain_grace_sec?: number;
 }
 
 export const settingsApi = {
@@ -208,9 +913,10 @@ export const settingsApi = {
       body: JSON.stringify(data),
     }),
 
-  triggerRestart: () =>
+  triggerRestart: (force = false) =>
     request<SuccessResponse>('/settings/restart/trigger', {
       method: 'POST',
+      body: JSON.stringify({ force }),
     }),
 };
 
@@ -228,6 +934,63 @@ export interface SslStatus {
   auto_renew: boolean;
   last_renewal?: string;
   next_renewal_attempt?: string;
+  /** HTTPS port when [server.tls] is enabled */
+  port?: number;
+  certificates: TlsCertificateStatus[];
+  /** ACME orders of DDNS hostnames */
+  acme: AcmeCertificate[];
+}
+
+export interface TlsCertificateStatus {
+  source: 'file' | 'acme';
+  /** null for ACME certificates */
+  cert_path: string | null;
+  key_path: string | null;
+  /** Names selected by SNI */
+  names: string[];
+  /** Served certificate (null until one loaded) */
+  certificate: {
+    subject: string;
+    issuer: string;
+    dns_names: string[];
+    not_before: string;
+    not_after: string;
+  } | null;
+  days_remaining: number | null;
+  /** Modification time of the served certificate file (ACME issue time) */
+  modified_at: string | null;
+  loaded_at: string | null;
+  /** Why the current files are not served */
+  last_error: string | null;
+}
+
+export interface AcmeCertificate {
+  id: number;
+  ddns_config_id: number;
+  hostname: string;
+  status: 'pending' | 'valid' | 'failed';
+  not_before: string | null;
+  not_after: string | null;
+  issued_at: string | null;
+  last_attempt_at: string | null;
+  last_error: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface ServerHealthSample {
+  timestamp: string;
+  cpu_percent: number;
+  per_core_percent: number[];
+  load_one_min: number;
+  memory_percent: number;
+  swap_percent: number;
+  disk_max_percent: number;
+  rx_bps: number;
+  tx_bps: number;
+  process_rss_mb: number;
+  process_open_fds: number;
+  tokio_tasks?: number;
 }
 
 export interface ServerHealth {
@@ -240,17 +1003,22 @@ export interface ServerHealth {
     one_min: number;
     five_min: number;
     fifteen_min: number;
+    running_tasks: number;
+    total_tasks: number;
   };
   cpu: {
     model: string;
     cores: number;
     usage_percent: number;
+    per_core_percent: number[];
   };
   memory: {
     total_mb: number;
     used_mb: number;
     free_mb: number;
     available_mb: number;
+    buffers_mb: number;
+    cached_mb: number;
     usage_percent: number;
   };
   swap: {
@@ -261,18 +1029,23 @@ export interface ServerHealth {
   };
   disk: Array<{
     mount_point: string;
+    device: string;
     filesystem: string;
     total_gb: number;
     used_gb: number;
     free_gb: number;
     usage_percent: number;
+    over_threshold: boolean;
   }>;
+  disk_warn_percent: number;
   network: {
     interfaces: Array<{
       name: string;
       ip?: string;
       rx_bytes: number;
       tx_bytes: number;
+      rx_bps: number;
+      tx_bps: number;
     }>;
     connections: number;
   };
@@ -281,6 +1054,17 @@ export interface ServerHealth {
     running: number;
     sleeping: number;
   };
+  lpg_process: {
+    pid: number;
+    rss_mb: number;
+    virtual_mb: number;
+    threads: number;
+    open_fds: number;
+    tokio_tasks?: number;
+  };
+  sampled_at: string;
+  sample_window_secs: number;
+  history?: ServerHealthSample[];
 }
 
 /** Append IP exclusion params to a URLSearchParams instance */
@@ -297,9 +1081,10 @@ export const dashboardApi = {
     admin_ip_history: string[];
   }>('/my-ip'),
 
-  getStats: (exclusion?: IpExclusionParams) => {
+  getStats: (exclusion?: IpExclusionParams, comparePrevious = false) => {
     const query = new URLSearchParams();
     appendExclusionParams(query, exclusion);
+    if (comparePrevious) query.set('compare', 'previous');
     const qs = query.toString();
     return request<DashboardStats>(`/dashboard/stats${qs ? `?${qs}` : ''}`);
   },
@@ -322,6 +1107,8 @@ export const dashboardApi = {
 
   getHealth: () => request<RouteHealth[]>('/dashboard/health'),
 
+  getDependencies: () => request<DependenciesStatus>('/dashboard/dependencies'),
+
   getStatusDistribution: (exclusion?: IpExclusionParams) => {
     const query = new URLSearchParams();
     appendExclusionParams(query, exclusion);
@@ -331,7 +1118,8 @@ export const dashboardApi = {
 
   getSslStatus: () => request<SslStatus>('/dashboard/ssl-status'),
 
-  getServerHealth: () => request<ServerHealth>('/dashboard/server-health'),
+  getServerHealth: (history = false) =>
+    request<ServerHealth>(`/dashboard/server-health${history ? '?history=true' : ''}`),
 
   searchAccessLogs: (params: AccessLogSearchParams) => {
     const query = new URLSearchParams();
@@ -346,25 +1134,105 @@ export const dashboardApi = {
     if (params.offset !== undefined) query.set('offset', params.offset.toString());
     if (params.exclude_ips) query.set('exclude_ips', params.exclude_ips);
     if (params.exclude_lan) query.set('exclude_lan', 'true');
+    if (params.custom_field && params.custom_value !== undefined) {
+      query.set('custom_field', params.custom_field);
+      query.set('custom_value', params.custom_value);
+    }
     return request<AccessLogSearchResult>(`/dashboard/access-log/search?${query}`);
   },
 
-  getHourlyStats: (from?: string, to?: string, exclusion?: IpExclusionParams) => {
-    const query = new URLSearchParams();
-    if (from) query.set('from', from);
-    if (to) query.set('to', to);
-    appendExclusionParams(query, exclusion);
-    return request<HourlyStat[]>(`/dashboard/hourly-stats?${query}`);
+  getCustomFieldSummary: (
+    routeId: number,
+    field: string,
+    options: { from?: string; to?: string; limit?: number } = {}
+  ) => {
+    const query = new URLSearchParams({ route_id: routeId.toString(), field });
+    if (options.from) query.set('from', options.from);
+    if (options.to) query.set('to', options.to);
+    if (options.limit !== undefined) query.set('limit', options.limit.toString());
+    return request<CustomFieldSummary>(`/dashboard/custom-field-summary?${query}`);
   },
 
-  getTopIps: (from?: string, to?: string, limit?: number, exclusion?: IpExclusionParams) => {
-    const query = new URLSearchParams();
-    if (from) query.set('from', from);
-    if (to) query.set('to', to);
-    if (limit) query.set('limit', limit.toString());
-    appendExclusionParams(query, exclusion);
-    return request<TopEntry[]>(`/dashboard/top-ips?${query}`);
-  },
+  getHostnameUsage: (month?: string) =>
+    request<HostnameUsageReport>(
+      `/dashboard/hostname-usage${month ? `?month=${encodeURIComponent(month)}` : ''}`
+    ),
+
+  rerunHostnameUsageRollup: (month: string) =>
+    request<HostnameUsageReport>('/dashboard/hostname-usage/rollup', {
+      method: 'POST',
+      body: JSON.stringify({ month }),
+    }),
+
+  exportHostnameUsageCsv: async (month: string) => {
+    const query = new URLSearchParams({ month });
+    const response = await fetch(`${API_BASE}/dashboard/hostname-usage/export?${query}`, {
+      credentials: 'include',
+    });
+    if (!response.ok) throw new Error(`HTTP ${response.status}`);
+    const blob = await response.blob();
+    const url = URL.createObjectURL(blob);
+    const a = document.createElement('a');
+    a.href = url;
+    a.download = `hostname_usage_${month}.csv`;
+    a.click();
+    URL.revokeObjectURL(url);
+  },
+
+  deleteAccessLogs: (filter: AccessLogDeleteFilter, confirm = false) =>
+    request<{ job_id?: string; matched?: number; status?: string; target?: string; confirm_required?: boolean }>(
+      `/dashboard/access-log/delete${confirm ? '?confirm=true' : ''}`,
+      { method: 'POST', body: JSON.stringify(filter) }
+    ),
+
+  getAccessLogDeleteJob: (jobId: string) =>
+    request<AccessLogDeleteJob>(`/dashboard/access-log/delete/${encodeURIComponent(jobId)}`),
+
+  getInFlight: (limit?: number) =>
+    request<InFlightList>(`/dashboard/in-flight${limit !== undefined ? `?limit=${limit}` : ''}`),
+
+  abortInFlight: (id: number) =>
+    request<SuccessResponse>(`/dashboard/in-flight/${id}`, { method: 'DELETE' }),
+
+  getWebSockets: () => request<WebSocketSessionList>('/dashboard/websockets'),
+
+  closeWebSocket: (id: number) =>
+    request<SuccessResponse>(`/dashboard/websockets/${id}`, { method: 'DELETE' }),
+
+  getNotificationQueue: () => request<NotificationQueueStats>('/dashboard/notification-queue'),
+
+  getHourlyStats: (from?: string, to?: string, exclusion?: IpExclusionParams) => {
+    const query = new URLSearchParams();
+    if (from) query.set('from', from);
+    if (to) query.set('to', to);
+    appendExclusionParams(query, exclusion);
+    return request<HourlyStat[]>(`/dashboard/hourly-stats?${query}`);
+  },
+
+  getHourlyStatsComparison: (from?: string, to?: string, exclusion?: IpExclusionParams) => {
+    const query = new URLSearchParams();
+    if (from) query.set('from', from);
+    if (to) query.set('to', to);
+    appendExclusionParams(query, exclusion);
+    query.set('compare', 'previous');
+    return request<HourlyStatsComparison>(`/dashboard/hourly-stats?${query}`);
+  },
+
+  getTopIps: (
+    from?: string,
+    to?: string,
+    limit?: number,
+    exclusion?: IpExclusionParams,
+    resolveCountry?: boolean
+  ) => {
+    const query = new URLSearchParams();
+    if (from) query.set('from', from);
+    if (to) query.set('to', to);
+    if (limit) query.set('limit', limit.toString());
+    appendExclusionParams(query, exclusion);
+    if (resolveCountry) query.set('resolve_country', 'true');
+    return request<TopEntry[]>(`/dashboard/top-ips?${query}`);
+  },
 
   getTopPaths: (from?: string, to?: string, limit?: number, exclusion?: IpExclusionParams) => {
     const query = new URLSearchParams();
@@ -383,6 +1251,7 @@ export const dashboardApi = {
     return request<ErrorSummary[]>(`/dashboard/error-summary?${query}`);
   },
 
+  // Exports every matching log (streamed by the server)
   exportCsv: async (params: AccessLogSearchParams) => {
     const query = new URLSearchParams();
     if (params.from) query.set('from', params.from);
@@ -392,7 +1261,6 @@ export const dashboardApi = {
     if (params.status_max !== undefined) query.set('status_max', params.status_max.toString());
     if (params.ip) query.set('ip', params.ip);
     if (params.path) query.set('path', params.path);
-    if (params.limit !== undefined) query.set('limit', params.limit.toString());
     if (params.exclude_ips) query.set('exclude_ips', params.exclude_ips);
     if (params.exclude_lan) query.set('exclude_lan', 'true');
     const response = await fetch(`${API_BASE}/dashboard/access-log/export?${query}`, {
@@ -403,7 +1271,9 @@ export const dashboardApi = {
     const url = URL.createObjectURL(blob);
     const a = document.createElement('a');
     a.href = url;
-    a.download = 'access_logs.csv';
+    a.download =
+      response.headers.get('Content-Disposition')?.match(/filename="([^"]+)"/)?.[1] ??
+      'access_logs.csv';
     a.click();
     URL.revokeObjectURL(url);
   },
@@ -444,6 +1314,18 @@ export interface OmadaSiteMapping {
   fid_display_name?: string;
 }
 
+export interface OmadaSiteFacility {
+  site_id: string;
+  name: string;
+  region?: string;
+  fid?: string;
+  fid_display_name?: string;
+  device_count: number;
+  online_device_count: number;
+  client_count: number;
+  active_client_count: number;
+}
+
 export interface OmadaControllerDoc {
   controller_id: string;
   display_name: string;
@@ -453,8 +1335,13 @@ export interface OmadaControllerDoc {
   omadac_id: string;
   controller_ver: string;
   api_ver: string;
+  /** connected | partial (some client pages could not be fetched) | error | offline | disconnected */
   status: string;
   last_error?: string;
+  /** Failed syncs in a row; reset by the next successful sync */
+  consecutive_failures?: number;
+  /** Set while the controller is offline */
+  offline_since?: string;
   sites: OmadaSiteMapping[];
   last_synced_at?: string;
   created_at: string;
@@ -474,11 +1361,36 @@ export interface OmadaDeviceDoc {
   lacis_id?: string;
   product_type: string;
   network_device_type: string;
+  /** Switches only; refreshed each sync */
+  ports?: OmadaSwitchPort[];
+  ports_synced_at?: string | null;
   synced_at: string;
   created_at: string;
   updated_at: string;
 }
 
+export interface OmadaSwitchPort {
+  port: number;
+  name?: string | null;
+  disabled: boolean;
+  link_up: boolean;
+  link_speed_mbps?: number | null;
+  full_duplex?: boolean | null;
+  poe_enabled: boolean;
+  poe_power_w: number;
+  profile_name?: string | null;
+  client_macs: string[];
+}
+
+export interface OmadaPortsSummary {
+  total: number;
+  up: number;
+  down: number;
+  disabled: number;
+  poe_ports: number;
+  poe_power_w: number;
+}
+
 export interface OmadaClientDoc {
   mac: string;
   controller_id: string;
@@ -533,6 +1445,51 @@ export interface OmadaWgPeerDoc {
   synced_at: string;
   created_at: string;
   updated_at: string;
+  /** Config profile the peer was provisioned with (WireGuard peer listing only) */
+  profile_id?: number | null;
+  profile_name?: string | null;
+  /** LPG-side expiry; the peer is disabled once it passes */
+  expires_at?: string;
+  /** When the expiry check disabled the peer */
+  expired_at?: string;
+  expiry_error?: string;
+  /** Expiry has passed (WireGuard peer listing only) */
+  expired?: boolean;
+}
+
+/** Peer from GET /api/wireguard/all-peers (LPG records + Omada merged by public key) */
+export interface UnifiedWgPeer {
+  id: string;
+  public_key: string;
+  name: string;
+  managed_by: 'lpg' | 'omada' | 'both';
+  controller_id: string;
+  site_id: string;
+  omada_peer_id: string | null;
+  interface_id: string | null;
+  interface_name: string | null;
+  status: boolean;
+  status_source: 'lpg' | 'omada';
+  allow_address: string[];
+  conflict: { lpg_allow_address: string[]; omada_allow_address: string[] } | null;
+  profile_id: number | null;
+  profile_name: string | null;
+  expires_at: string | null;
+  expired: boolean;
+}
+
+export interface WgConfigProfile {
+  id: number;
+  name: string;
+  description?: string | null;
+  dns?: string | null;
+  allowed_ips: string;
+  persistent_keepalive?: number | null;
+  mtu?: number | null;
+  scripts_allowed: boolean;
+  is_default: boolean;
+  created_at: string;
+  updated_at: string;
 }
 
 export interface OmadaSummary {
@@ -603,6 +1560,24 @@ export const omadaApi = {
       method: 'POST',
     }),
 
+  listControllerSites: (id: string) =>
+    request<{ ok: boolean; controller_id: string; unmapped: number; sites: OmadaSiteFacility[] }>(
+      `/omada/controllers/${id}/sites`
+    ),
+
+  updateControllerSite: (id: string, siteId: string, data: { fid?: string | null; fid_display_name?: string }) =>
+    request<{
+      ok: boolean;
+      changed: boolean;
+      site_id: string;
+      fid?: string;
+      fid_display_name?: string;
+      entries_updated?: number;
+    }>(`/omada/controllers/${id}/sites/${encodeURIComponent(siteId)}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
   // Data viewing
   getDevices: (controllerId?: string, siteId?: string) => {
     const query = new URLSearchParams();
@@ -614,6 +1589,18 @@ export const omadaApi = {
     );
   },
 
+  getDevicePorts: (mac: string) =>
+    request<{
+      ok: boolean;
+      mac?: string;
+      name?: string;
+      controller_id?: string;
+      summary?: OmadaPortsSummary;
+      ports?: OmadaSwitchPort[];
+      ports_synced_at?: string | null;
+      error?: string;
+    }>(`/omada/devices/${encodeURIComponent(mac)}/ports`),
+
   getClients: (controllerId?: string, siteId?: string, active?: boolean) => {
     const query = new URLSearchParams();
     if (controllerId) query.set('controller_id', controllerId);
@@ -664,6 +1651,12 @@ export const openwrtApi = {
       body: JSON.stringify(data),
     }),
 
+  updateRouter: (id: string, data: { display_name?: string; poll_interval_sec?: number; paused?: boolean }) =>
+    request<{ ok: boolean; router?: import('@/types').OpenWrtRouterDoc; error?: string }>(`/openwrt/routers/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
   deleteRouter: (id: string) =>
     request<{ ok: boolean; message?: string; error?: string }>(`/openwrt/routers/${id}`, {
       method: 'DELETE',
@@ -718,16 +1711,21 @@ export const wireguardApi = {
     allow_address: string[];
     keep_alive?: number;
     comment?: string;
+    profile_id?: number;
+    expires_at?: string;
   }) =>
     request<{ ok: boolean; peer?: unknown; error?: string }>('/wireguard/peers', {
       method: 'POST',
       body: JSON.stringify(data),
     }),
 
-  getPeers: (controllerId?: string, siteId?: string) => {
+  getPeers: (controllerId?: string, siteId?: string, expiringWithinDays?: number) => {
     const query = new URLSearchParams();
     if (controllerId) query.set('controller_id', controllerId);
     if (siteId) query.set('site_id', siteId);
+    if (expiringWithinDays !== undefined) {
+      query.set('expiring_within_days', String(expiringWithinDays));
+    }
     const qs = query.toString();
     return request<{ ok: boolean; peers: OmadaWgPeerDoc[]; total: number; error?: string }>(
       `/wireguard/peers${qs ? `?${qs}` : ''}`
@@ -741,12 +1739,64 @@ export const wireguardApi = {
     allow_address?: string[];
     keep_alive?: number;
     comment?: string;
+    /** null removes the expiry */
+    expires_at?: string | null;
   }) =>
     request<{ ok: boolean; message?: string; error?: string }>(`/wireguard/peers/${peerId}`, {
       method: 'PUT',
       body: JSON.stringify(data),
     }),
 
+  getAllPeers: (controllerId?: string, siteId?: string) => {
+    const query = new URLSearchParams();
+    if (controllerId) query.set('controller_id', controllerId);
+    if (siteId) query.set('site_id', siteId);
+    const qs = query.toString();
+    return request<{
+      ok: boolean;
+      peers: UnifiedWgPeer[];
+      total: number;
+      by_source: { lpg: number; omada: number; both: number };
+      conflicts: number;
+    }>(`/wireguard/all-peers${qs ? `?${qs}` : ''}`);
+  },
+
+  updateUnifiedPeer: (id: string, data: {
+    name?: string;
+    allow_address?: string[];
+    enabled?: boolean;
+    keep_alive?: number;
+    comment?: string;
+  }) =>
+    request<{ ok: boolean; id: string; managed_by: UnifiedWgPeer['managed_by'] }>(
+      `/wireguard/all-peers/${id}`,
+      { method: 'PUT', body: JSON.stringify(data) }
+    ),
+
+  deleteUnifiedPeer: (id: string, confirm = false) =>
+    request<{ ok?: boolean; message?: string; confirm_required?: boolean }>(
+      `/wireguard/all-peers/${id}?confirm=${confirm}`,
+      { method: 'DELETE' }
+    ),
+
+  resolveConflict: (id: string, winner: 'lpg' | 'omada') =>
+    request<{ ok: boolean; id: string; winner: string; allow_address: string[] }>(
+      `/wireguard/all-peers/${id}/resolve`,
+      { method: 'POST', body: JSON.stringify({ winner }) }
+    ),
+
+  extendPeer: (peerId: string, data: { expires_at?: string; days?: number }) =>
+    request<{
+      ok: boolean;
+      peer_id: string;
+      expires_at: string;
+      previous_expires_at?: string;
+      reenabled: boolean;
+    }>(`/wireguard/peers/${peerId}/extend`, {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
   deletePeer: (peerId: string, controllerId: string, siteId: string) =>
     request<{ ok: boolean; message?: string; error?: string }>(
       `/wireguard/peers/${peerId}?controller_id=${controllerId}&site_id=${siteId}`,
@@ -761,12 +1811,54 @@ export const wireguardApi = {
     endpoint: string;
     allowed_ips: string;
     persistent_keepalive?: number;
+    profile_id?: number;
+    pre_up?: string;
+    post_up?: string;
+    pre_down?: string;
+    post_down?: string;
   }) =>
-    request<{ ok: boolean; config: string }>('/wireguard/config', {
+    request<{
+      ok: boolean;
+      config?: string;
+      profile_id?: number | null;
+      profile_name?: string | null;
+      error?: string;
+    }>('/wireguard/config', {
       method: 'POST',
       body: JSON.stringify(params),
     }),
 
+  listProfiles: () => request<WgConfigProfile[]>('/wireguard/profiles'),
+
+  getProfile: (id: number) =>
+    request<{ profile: WgConfigProfile; peer_count: number }>(`/wireguard/profiles/${id}`),
+
+  createProfile: (data: {
+    name: string;
+    description?: string;
+    dns?: string;
+    allowed_ips: string;
+    persistent_keepalive?: number;
+    mtu?: number;
+    scripts_allowed?: boolean;
+  }) =>
+    request<{ message: string; id: number }>('/wireguard/profiles', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  updateProfile: (id: number, data: Partial<Omit<WgConfigProfile, 'id' | 'is_default' | 'created_at' | 'updated_at'>>) =>
+    request<{ message: string; id: number; affected_peers: number; note: string }>(
+      `/wireguard/profiles/${id}`,
+      { method: 'PUT', body: JSON.stringify(data) }
+    ),
+
+  deleteProfile: (id: number, confirm = false) =>
+    request<{ message?: string; note?: string; confirm_required?: boolean }>(
+      `/wireguard/profiles/${id}?confirm=${confirm}`,
+      { method: 'DELETE' }
+    ),
+
   getInterfaces: (controllerId?: string, siteId?: string) => {
     const query = new URLSearchParams();
     if (controllerId) query.set('controller_id', controllerId);
@@ -802,6 +1894,19 @@ export const externalApi = {
       body: JSON.stringify(data),
     }),
 
+  updateDevice: (id: string, data: {
+    display_name?: string;
+    ip?: string;
+    username?: string;
+    password?: string;
+    poll_interval_sec?: number;
+    paused?: boolean;
+  }) =>
+    request<{ ok: boolean; device?: import('@/types').ExternalDeviceDoc; error?: string }>(`/external/devices/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
   deleteDevice: (id: string) =>
     request<{ ok: boolean; message?: string; error?: string }>(`/external/devices/${id}`, {
       method: 'DELETE',
@@ -870,6 +1975,94 @@ export interface NginxStatus {
   last_reload: string | null;
   error: string | null;
   client_max_body_size: string | null;
+  /** Request body limit of the built-in proxy (routes may override) */
+  proxy_max_body_bytes: number;
+  /** Ingestion of nginx's JSON access log (full proxy mode) */
+  access_log_ingest: NginxLogIngestStatus;
+  /** stub_status counters, requests per second and per-site requests */
+  metrics: NginxMetrics;
+  config_drift: NginxConfigDrift;
+}
+
+export interface NginxStubStatus {
+  active_connections: number;
+  accepts: number;
+  handled: number;
+  requests: number;
+  reading: number;
+  writing: number;
+  waiting: number;
+}
+
+export interface NginxSiteRequests {
+  server_name: string;
+  /** Since the instance started following the access log */
+  requests: number;
+  requests_per_second: number | null;
+}
+
+export interface NginxMetrics {
+  stub_status_url: string;
+  stub_status: NginxStubStatus | null;
+  scraped_at: string | null;
+  scrape_error: string | null;
+  /** Over window_secs */
+  requests_per_second: number | null;
+  window_secs: number;
+  sites: NginxSiteRequests[];
+}
+
+export interface NginxConfigDrift {
+  disk_hash: string | null;
+  /** Hash of the config file at the last reload (null: not reloaded since LPG started) */
+  running_hash: string | null;
+  running_matches_disk: boolean | null;
+  generated_hash: string;
+  /** Full proxy mode only */
+  disk_matches_generated: boolean | null;
+  /** Hash of the current routes, DDNS hostnames and template settings */
+  inputs_hash: string;
+  generated_inputs_hash: string | null;
+  generated_at: string | null;
+  /** null: not in full proxy mode, or the config was not generated by LPG */
+  in_sync: boolean | null;
+  changes?: NginxInputChanges;
+}
+
+export interface NginxRouteInput {
+  id: number;
+  path: string;
+  host: string | null;
+  timeout_ms: number;
+  websocket_support: boolean;
+}
+
+export interface NginxInputChanges {
+  template_changed: boolean;
+  routes_added: NginxRouteInput[];
+  routes_changed: { id: number; path: string; host: string | null; changes: string[] }[];
+  routes_removed: NginxRouteInput[];
+  ddns_hostnames_added: string[];
+  ddns_hostnames_removed: string[];
+}
+
+export interface NginxLogIngestStatus {
+  path: string;
+  /** The file exists and is being followed */
+  following: boolean;
+  offset: number;
+  /** Bytes nginx has written that are not read yet */
+  lag_bytes: number;
+  ingest_delay_ms: number | null;
+  last_entry_at: string | null;
+  last_poll_at: string | null;
+  lines_read: number;
+  ingested: number;
+  /** Entries LPG answered (and logged) itself */
+  skipped_lpg: number;
+  parse_errors: number;
+  rotations: number;
+  last_error: string | null;
 }
 
 export interface NginxConfig {
@@ -927,12 +2120,34 @@ export const authApi = {
       body: JSON.stringify({ email, password }),
     }),
 
-  loginLacisOath: (code: string, redirectUri: string) =>
+  loginLacisOath: (code: string, redirectUri: string, provider?: string) =>
     request<AuthResponse>('/auth/login/lacisoath', {
       method: 'POST',
-      body: JSON.stringify({ code, redirect_uri: redirectUri }),
+      body: JSON.stringify({ code, redirect_uri: redirectUri, provider }),
     }),
 
+  // LacisOath identity providers (permission 100)
+  listProviders: () => request<LacisOathProvider[]>('/auth/providers'),
+
+  createProvider: (data: LacisOathProviderInput) =>
+    request<LacisOathProvider>('/auth/providers', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  /** A masked or empty client_secret keeps the stored one */
+  updateProvider: (id: string, data: Partial<Omit<LacisOathProviderInput, 'provider_id'>>) =>
+    request<LacisOathProvider>(`/auth/providers/${encodeURIComponent(id)}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
+  deleteProvider: (id: string, confirm: boolean = false) =>
+    request<{ ok?: boolean; confirm_required?: boolean; warning?: string }>(
+      `/auth/providers/${encodeURIComponent(id)}${confirm ? '?confirm=true' : ''}`,
+      { method: 'DELETE' }
+    ),
+
   me: () => request<AuthResponse>('/auth/me'),
 
   logout: () =>
@@ -1047,6 +2262,11 @@ import type {
   CreateLogicDeviceRequest,
   UpdateLogicDeviceRequest,
   TopologyViewFilter,
+  TopologyWatchResponse,
+  NodeClaim,
+  NodePositionUpdate,
+  LayoutScope,
+  RecalcLayoutResponse,
 } from '@/app/celestial-globe/types';
 
 export const topologyV2Api = {
@@ -1058,6 +2278,9 @@ export const topologyV2Api = {
     return request<TopologyV2Response>(`/topology/v2${qs ? `?${qs}` : ''}`);
   },
 
+  watch: (since: number, timeoutSec = 30) =>
+    request<TopologyWatchResponse>(`/topology/watch?since=${since}&timeout=${timeoutSec}`),
+
   updateNodeLabel: (nodeId: string, label: string) =>
     request<{ ok: boolean; node_id: string; label: string }>(
       `/topology/nodes/${encodeURIComponent(nodeId)}/label`,
@@ -1105,8 +2328,178 @@ export const topologyV2Api = {
       `/topology/nodes/${encodeURIComponent(nodeId)}/order`,
       { method: 'PUT', body: JSON.stringify({ new_order: newOrder }) }
     ),
+
+  /** 409 LAYOUT_GENERATION_CONFLICT carries `details.current_generation` when stale */
+  updatePositions: (baseGeneration: number, positions: NodePositionUpdate[]) =>
+    request<{ ok: boolean; updated: number; layout_generation: number }>(
+      '/topology/positions',
+      {
+        method: 'PUT',
+        body: JSON.stringify({ base_generation: baseGeneration, positions }),
+      }
+    ),
+
+  /** Drop unpinned position overrides; 409 when `baseGeneration` is stale */
+  recalcLayout: (baseGeneration: number, scope: LayoutScope) =>
+    request<RecalcLayoutResponse>('/topology/layout/recalc', {
+      method: 'POST',
+      body: JSON.stringify({ base_generation: baseGeneration, ...scope }),
+    }),
+
+  /** Claim as your own device (shown under your login); 409 carries the current owner in `claimed_by` */
+  claimNode: (nodeId: string) =>
+    request<{ ok: boolean; node_id: string; claimed_by: NodeClaim }>(
+      `/topology/nodes/${encodeURIComponent(nodeId)}/claim`,
+      { method: 'POST' }
+    ),
+
+  releaseNodeClaim: (nodeId: string) =>
+    request<{ ok: boolean; node_id: string; released: boolean }>(
+      `/topology/nodes/${encodeURIComponent(nodeId)}/claim`,
+      { method: 'DELETE' }
+    ),
+
+  /** Admin: assign a claim to any user, or clear it with lacisId = null */
+  assignNodeClaim: (nodeId: string, lacisId: string | null, displayName?: string) =>
+    request<{ ok: boolean; node_id: string; claimed_by: NodeClaim | null }>(
+      `/topology/nodes/${encodeURIComponent(nodeId)}/claim`,
+      {
+        method: 'PUT',
+        body: JSON.stringify({ lacis_id: lacisId, display_name: displayName }),
+      }
+    ),
+
+  /** Consolidated node view; parts that failed or timed out are listed in `unavailable` */
+  getNodeDetail: (nodeId: string) =>
+    request<NodeDetail>(`/topology/nodes/${encodeURIComponent(nodeId)}/detail`),
+
+  /** Admin: merge nodes whose ids differ only by MAC formatting */
+  dedupeMacNodes: (dryRun: boolean) =>
+    request<{
+      ok: boolean;
+      dry_run: boolean;
+      merges: NodeMacMerge[];
+      removed: number;
+      errors: { canonical_id: string; error: string }[];
+    }>(`/topology/nodes/dedupe-macs?dry_run=${dryRun}`, { method: 'POST' }),
+
+  listShares: () => request<TopologyShare[]>('/topology/shares'),
+
+  /** The token is returned only here; `public_path` serves the share without a session */
+  createShare: (data: TopologyShareInput) =>
+    request<IssuedTopologyShare>('/topology/shares', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  revokeShare: (id: number, confirm: boolean = false) =>
+    request<{ message?: string; confirm_required?: boolean; warning?: string }>(
+      `/topology/shares/${id}${confirm ? '?confirm=true' : ''}`,
+      { method: 'DELETE' }
+    ),
 };
 
+/** Read-only topology share (token never returned after creation) */
+export interface TopologyShare {
+  id: number;
+  name: string;
+  view: 'full' | 'site';
+  fid: string | null;
+  /** `labels`: no MACs or IPs; `labels_ips`: with them */
+  detail: 'labels' | 'labels_ips';
+  token_prefix: string;
+  expires_at: string;
+  request_count: number;
+  last_used_at: string | null;
+  revoked_at: string | null;
+  created_by: string;
+  created_at: string;
+}
+
+export interface TopologyShareInput {
+  name: string;
+  view?: 'full' | 'site';
+  /** Required for the site view */
+  fid?: string;
+  detail?: 'labels' | 'labels_ips';
+  /** Default 720, max 8760 */
+  expires_in_hours?: number;
+}
+
+export interface IssuedTopologyShare extends TopologyShare {
+  token: string;
+  public_path: string;
+}
+
+/** user_object_detail document as stored */
+export interface UserObjectDetailDoc {
+  id: string;
+  mac: string;
+  parent_id: string;
+  node_type: string;
+  state_type: string;
+  label: string;
+  label_customized: boolean;
+  ip: string | null;
+  hostname: string | null;
+  source: string;
+  source_ref_id: string | null;
+  lacis_id: string | null;
+  candidate_lacis_id: string | null;
+  claimed_by: NodeClaim | null;
+  metadata: Record<string, unknown>;
+  [key: string]: unknown;
+}
+
+export interface NodeDetailChild {
+  id: string;
+  label: string;
+  node_type: string;
+  state_type: string;
+  ip: string | null;
+  mac: string;
+}
+
+export interface NodeStateChange {
+  device_id: string;
+  state_type: string;
+  previous_state: string | null;
+  changed_at: string;
+}
+
+export interface NodeDetail {
+  ok: boolean;
+  node_id: string;
+  node: UserObjectDetailDoc;
+  lacis: {
+    status: 'registered' | 'candidate' | 'none';
+    lacis_id: string | null;
+    candidate_lacis_id: string | null;
+    aranea_lacis_id: string | null;
+  };
+  claim: NodeClaim | null;
+  /** Raw omada/openwrt/external document (credentials masked) */
+  source_record: Record<string, unknown> | null;
+  state_history: NodeStateChange[];
+  security_events: SecurityEvent[];
+  access_logs: AccessLog[];
+  children: NodeDetailChild[];
+  children_truncated: boolean;
+  unavailable: string[];
+  elapsed_ms: number;
+}
+
+export interface NodeMacMerge {
+  canonical_id: string;
+  removed_ids: string[];
+  base_id: string;
+  label_from: string | null;
+  claim_from: string | null;
+  lacis_id_from: string | null;
+  reparented: string[];
+  dropped: string[];
+}
+
 // ============================================================================
 // Operation Logs API
 // ============================================================================
@@ -1196,6 +2589,7 @@ export const toolsApi = {
   syncOpenwrt: () => request<ToolResult>('/tools/sync/openwrt', { method: 'POST' }),
   syncExternal: () => request<ToolResult>('/tools/sync/external', { method: 'POST' }),
   ddnsUpdateAll: () => request<ToolResult>('/tools/ddns/update-all', { method: 'POST' }),
+  pruneLogs: () => request<ToolResult>('/tools/logs/prune', { method: 'POST' }),
   ping: (host: string) => request<ToolResult>('/tools/network/ping', { method: 'POST', body: JSON.stringify({ host }) }),
   dns: (hostname: string) => request<ToolResult>('/tools/network/dns', { method: 'POST', body: JSON.stringify({ hostname }) }),
   curl: (url: string) => request<ToolResult>('/tools/network/curl', { method: 'POST', body: JSON.stringify({ url }) }),
@@ -1260,6 +2654,153 @@ export interface AraneaSummary {
   mqtt_connected: number;
 }
 
+export interface AraneaRegistrationPayload {
+  mac: string;
+  product_type: string;
+  product_code: string;
+  device_type: string;
+  model?: string;
+  fid?: string;
+}
+
+export interface AraneaRegistrationCandidate {
+  id: string;
+  label: string;
+  source: string;
+  node_type: string;
+  candidate_lacis_id: string;
+  facility_name: string | null;
+  payload: AraneaRegistrationPayload;
+  issues: string[];
+}
+
+export interface AraneaRegisterBatchResult {
+  id: string;
+  ok: boolean;
+  lacis_id?: string;
+  source?: string;
+  source_updated?: boolean;
+  payload?: AraneaRegistrationPayload;
+  error?: string;
+}
+
+export interface FacilityReport {
+  schema: string;
+  schema_version: number;
+  report_id: string;
+  fid: string;
+  facility_name: string | null;
+  period_start: string;
+  period_end: string;
+  generated_at: string;
+  devices: {
+    total: number;
+    online: number;
+    by_type: Record<string, number>;
+    with_lacis_id: number;
+    without_lacis_id: number;
+  };
+  clients: { total: number; online: number; by_class: Record<string, number> };
+  state_changes: {
+    node_id: string;
+    label: string;
+    state_type: string;
+    previous_state: string | null;
+    changed_at: string;
+  }[];
+  new_devices: { node_id: string; mac: string; label: string; node_type: string; created_at: string }[];
+}
+
+export interface FacilityReportDelivery {
+  status: 'pending' | 'delivered' | 'failed' | 'skipped';
+  attempts: number;
+  last_error: string | null;
+  delivered_at: string | null;
+}
+
+export interface StoredFacilityReport {
+  report_id: string;
+  fid: string;
+  generated_at: string;
+  trigger: 'schedule' | 'manual';
+  delivery: FacilityReportDelivery;
+  report: FacilityReport;
+}
+
+/** Device registration token (secret never returned after issue/rotate) */
+export interface AraneaRegistrationToken {
+  id: number;
+  name: string;
+  fid: string;
+  product_types: string[];
+  token_prefix: string;
+  expires_at: string | null;
+  max_uses: number | null;
+  use_count: number;
+  last_used_at: string | null;
+  last_used_ip: string | null;
+  revoked_at: string | null;
+  created_by: string;
+  created_at: string;
+}
+
+export interface AraneaRegistrationTokenInput {
+  name: string;
+  fid: string;
+  product_types: string[];
+  expires_in_hours?: number;
+  max_uses?: number;
+}
+
+/** Issued or rotated token; `registration_token` is shown only once */
+export interface IssuedAraneaRegistrationToken extends AraneaRegistrationToken {
+  registration_token: string;
+}
+
+/** Effective aranea config; secrets come back as "********" */
+export interface AraneaConfig {
+  is_configured: boolean;
+  tid: string;
+  tenant_lacis_id: string;
+  tenant_user_id: string;
+  tenant_cic: string;
+  endpoint: string;
+  device_gate_url: string;
+  device_state_url: string;
+  signing_key: string;
+  signing_enabled: boolean;
+  facility_list_url: string;
+  facility_report_url: string;
+  /** Fields set at runtime rather than in the config file */
+  overridden: string[];
+}
+
+/** Fields to override; a masked secret sent back keeps its value */
+export type AraneaConfigUpdate = Partial<
+  Omit<AraneaConfig, 'is_configured' | 'endpoint' | 'overridden'>
+> & { reset?: boolean };
+
+/** Outcomes of the client's calls over the last `window_secs` */
+export interface AraneaCallStats {
+  window_secs: number;
+  calls: number;
+  errors: number;
+  avg_latency_ms: number | null;
+  last_success_at: string | null;
+  last_failure_at: string | null;
+  last_error: string | null;
+}
+
+export interface AraneaHealth {
+  configured: boolean;
+  reachable: boolean | null;
+  endpoint: string;
+  latency_ms: number | null;
+  error: string | null;
+  checked_at: string;
+  recent: AraneaCallStats;
+}
+
 export const araneaApi = {
   listDevices: () =>
     request<{ ok: boolean; devices: AraneaDevice[]; error?: string }>('/aranea/devices'),
@@ -1272,6 +2813,66 @@ export const araneaApi = {
     }),
   getSummary: () =>
     request<{ ok: boolean; summary: AraneaSummary; error?: string }>('/aranea/summary'),
+  getRegistrationCandidates: () =>
+    request<{
+      ok: boolean;
+      configured: boolean;
+      total: number;
+      ready: number;
+      candidates: AraneaRegistrationCandidate[];
+    }>('/aranea/registration-candidates'),
+  registerBatch: (ids: string[], dryRun = false) =>
+    request<{
+      ok: boolean;
+      dry_run: boolean;
+      registered: number;
+      valid: number;
+      failed: number;
+      results: AraneaRegisterBatchResult[];
+    }>('/aranea/register-batch', {
+      method: 'POST',
+      body: JSON.stringify({ ids, dry_run: dryRun }),
+    }),
+  listReports: (params: { fid?: string; limit?: number } = {}) => {
+    const query = new URLSearchParams();
+    if (params.fid) query.set('fid', params.fid);
+    if (params.limit) query.set('limit', String(params.limit));
+    return request<{ ok: boolean; configured: boolean; reports: StoredFacilityReport[] }>(
+      `/aranea/reports?${query}`
+    );
+  },
+  triggerReports: (data: { fid?: string; hours?: number } = {}) =>
+    request<{
+      ok: boolean;
+      period_hours: number;
+      reports: { report_id: string; fid: string; delivery: FacilityReportDelivery }[];
+    }>('/aranea/reports/trigger', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+  listRegistrationTokens: () =>
+    request<AraneaRegistrationToken[]>('/aranea/registration-tokens'),
+  createRegistrationToken: (data: AraneaRegistrationTokenInput) =>
+    request<IssuedAraneaRegistrationToken>('/aranea/registration-tokens', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+  rotateRegistrationToken: (id: number) =>
+    request<IssuedAraneaRegistrationToken>(`/aranea/registration-tokens/${id}/rotate`, {
+      method: 'POST',
+    }),
+  revokeRegistrationToken: (id: number, confirm: boolean = false) =>
+    request<{ message?: string; confirm_required?: boolean; warning?: string }>(
+      `/aranea/registration-tokens/${id}${confirm ? '?confirm=true' : ''}`,
+      { method: 'DELETE' }
+    ),
+  getConfig: () => request<AraneaConfig>('/aranea/config'),
+  updateConfig: (data: AraneaConfigUpdate) =>
+    request<AraneaConfig>('/aranea/config', {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+  getHealth: () => request<AraneaHealth>('/aranea/health'),
 };
 
 // ============================================================================
@@ -1285,6 +2886,32 @@ export interface DdnsIntegrated {
   ip_mismatch: boolean;
   port_forwarding: unknown[];
   linked_controller?: string;
+  failover: DdnsFailoverState;
+  /** Latest dependency check; only for hostnames linked to active routes */
+  dns_check?: DdnsDnsCheck | null;
+  dns_mismatch?: DnsMismatch | null;
+}
+
+export interface DdnsDnsCheck {
+  ddns_config_id: number;
+  hostname: string;
+  checked_at: string;
+  resolved: string[];
+  expected: string[];
+  state: DnsMatchState;
+  diverged_since: string | null;
+  mismatch: boolean;
+  error: string | null;
+  route_ids: number[];
+}
+
+export interface DdnsFailoverState {
+  role: 'primary' | 'secondary' | null;
+  secondary: { id: number; provider: DdnsProvider; hostname: string } | null;
+  primary_config_id: number | null;
+  active: boolean;
+  active_config_id: number;
+  active_provider: DdnsProvider;
 }
 
 export const ddnsIntegratedApi = {
@@ -1324,6 +2951,439 @@ export interface ServerRoute {
   tid?: string;
 }
 
+export interface TargetTcpProbe {
+  host: string;
+  port: number;
+  reachable: boolean;
+  latency_ms?: number;
+  resolved_ip?: string;
+  error?: string;
+  probed_at: string;
+  cached: boolean;
+}
+
+export interface KnownNetwork {
+  network: string;
+  source: 'omada_site' | 'openwrt_lan' | 'omada_wg_peer';
+  owner: string;
+  label: string;
+}
+
+export interface ServerRouteAnalysis {
+  routes: {
+    id: number;
+    path: string;
+    target: string;
+    active: boolean;
+    target_ip?: string;
+    reachability?: TargetTcpProbe;
+    claims: KnownNetwork[];
+    ambiguous: boolean;
+  }[];
+  targets_probed: number;
+  reachable: number;
+  unreachable: number;
+  known_networks: KnownNetwork[];
+  conflicts: { target_ip: string; networks: KnownNetwork[]; route_ids: number[] }[];
+}
+
 export const serverRoutesApi = {
   list: () => request<ServerRoute[]>('/server-routes'),
+  analysis: (refresh = false) =>
+    request<ServerRouteAnalysis>(`/server-routes/analysis${refresh ? '?refresh=true' : ''}`),
+};
+
+// ============================================================================
+// Cluster API (leader election)
+// ============================================================================
+
+export interface ClusterStatus {
+  enabled: boolean;
+  instance_id: string;
+  hostname: string;
+  role: 'standalone' | 'leader' | 'follower';
+  leader: {
+    instance_id: string;
+    hostname: string;
+    term: number;
+    acquired_at: string;
+    heartbeat_at: string;
+    expires_at: string | null;
+  } | null;
+  leader_since: string | null;
+  last_heartbeat: string | null;
+  last_error: string | null;
+  standing_down_for_secs: number | null;
+  lease_ttl_secs: number;
+  heartbeat_interval_secs: number;
+  running_tasks: string[];
+  instances: {
+    instance_id: string;
+    hostname: string;
+    role: string;
+    started_at: string;
+    heartbeat_at: string;
+  }[];
+}
+
+export const clusterApi = {
+  getStatus: () => request<ClusterStatus>('/admin/cluster'),
+
+  failover: (confirm = false, standdownSecs?: number) =>
+    request<{ message?: string; standdown_secs?: number; confirm_required?: boolean }>(
+      '/admin/cluster/failover',
+      { method: 'POST', body: JSON.stringify({ confirm, standdown_secs: standdownSecs }) }
+    ),
+};
+
+// Session signing secret
+export interface JwtSecretRotation {
+  rotated_at: string;
+  /** Sessions signed with the replaced secret are accepted until then */
+  previous_valid_until: string;
+  replaced_default: boolean;
+}
+
+export const jwtSecretApi = {
+  rotate: (confirm = false) =>
+    request<
+      Partial<JwtSecretRotation> & { confirm_required?: boolean; warning?: string }
+    >(`/admin/rotate-jwt-secret${confirm ? '?confirm=true' : ''}`, { method: 'POST' }),
+};
+
+// Logging (runtime level)
+export interface LogLevelState {
+  current: string;
+  configured: string;
+}
+
+export const loggingApi = {
+  getLevel: () => request<LogLevelState>('/admin/log-level'),
+
+  setLevel: (level: string) =>
+    request<{ message: string; previous: string; current: string }>('/admin/log-level', {
+      method: 'POST',
+      body: JSON.stringify({ level }),
+    }),
+
+  resetLevel: () =>
+    request<{ message: string; previous: string; current: string }>('/admin/log-level', {
+      method: 'POST',
+      body: JSON.stringify({ reset: true }),
+    }),
+};
+
+// Syncer ingestion write load
+export interface IngestCycleWrites {
+  source: string;
+  collection: 'user_object_detail' | 'cg_node_order';
+  started_at: string;
+  duration_ms: number;
+  write_ms: number;
+  statements: number;
+  batches: number;
+  inserted: number;
+  write_errors: number;
+  last_error: string | null;
+}
+
+export interface IngestWriteStats {
+  last_cycle: IngestCycleWrites;
+  cycles: number;
+  total_statements: number;
+  total_write_ms: number;
+}
+
+export interface IngestWritesReport {
+  pool_size: number;
+  pacing: { batch_size: number; batch_delay_ms: number };
+  sources: IngestWriteStats[];
+}
+
+export const ingestWritesApi = {
+  get: () => request<IngestWritesReport>('/admin/ingest-writes'),
+};
+
+// Startup migrations
+export interface SchemaMigrationRecord {
+  _id: string;
+  description: string;
+  outcome: 'applied' | 'skipped' | 'failed';
+  detail: string;
+  forced: boolean;
+  run_count: number;
+  duration_ms: number;
+  first_run_at: string;
+  last_run_at: string;
+}
+
+export interface MigrationStatus {
+  migration_id: string;
+  description: string;
+  critical: boolean;
+  every_start: boolean;
+  pending: boolean;
+  last_run: SchemaMigrationRecord | null;
+}
+
+export interface MigrationReport {
+  migration_id: string;
+  outcome: 'applied' | 'skipped' | 'failed';
+  detail: string;
+  forced: boolean;
+  duration_ms: number;
+}
+
+export const migrationsApi = {
+  list: () =>
+    request<{ migrations: MigrationStatus[]; total: number; pending: number }>(
+      '/admin/migrations'
+    ),
+
+  run: (migrationId: string, force = false, confirm = false) =>
+    request<{ ok?: boolean; report?: MigrationReport; confirm_required?: boolean }>(
+      `/admin/migrations/${encodeURIComponent(migrationId)}/run`,
+      { method: 'POST', body: JSON.stringify({ force, confirm }) }
+    ),
+};
+
+// MongoDB storage (collection sizes, indexes, compaction)
+export interface StorageCollection {
+  name: string;
+  count: number;
+  size: number;
+  storage_size: number;
+  index_size: number;
+  disk_bytes: number;
+  disk: string;
+  growth_bytes_per_day: number | null;
+}
+
+export interface StorageIndex {
+  name: string;
+  keys: Record<string, number | string>;
+  unique: boolean;
+  ttl_seconds: number | null;
+  size_bytes: number | null;
+  declared: boolean;
+}
+
+export interface StorageIndexMismatch {
+  name: string;
+  keys: Record<string, number | string>;
+  expected_unique: boolean;
+  actual_unique: boolean;
+  expected_ttl_seconds: number | null;
+  actual_ttl_seconds: number | null;
+}
+
+export interface StorageIndexReport {
+  collection: string;
+  indexes: StorageIndex[];
+  missing: Record<string, number | string>[];
+  created: Record<string, number | string>[];
+  mismatched: StorageIndexMismatch[];
+  undeclared: string[];
+  error?: string;
+}
+
+export interface StorageProjection {
+  growth_bytes_per_day: number | null;
+  remaining_bytes: number | null;
+  limit: 'filesystem' | 'mongo_storage_max_mb' | null;
+  days_until_full: number | null;
+  warning_days: number;
+  warning: string | null;
+}
+
+export interface StorageReport {
+  collections: StorageCollection[];
+  total_bytes: number;
+  indexes: StorageIndexReport[];
+  filesystem: { used_bytes: number | null; total_bytes: number | null; used_percent: number | null } | null;
+  projection: StorageProjection;
+  samples: number;
+  warnings: string[];
+}
+
+export const storageApi = {
+  get: () => request<StorageReport>('/admin/storage'),
+
+  compact: (collection: string, confirm = false) =>
+    request<{
+      ok?: boolean;
+      before_bytes?: number;
+      after_bytes?: number;
+      reclaimed_bytes?: number;
+      confirm_required?: boolean;
+      warning?: string;
+    }>(`/admin/storage/compact/${encodeURIComponent(collection)}`, {
+      method: 'POST',
+      body: JSON.stringify({ confirm }),
+    }),
+};
+
+// Admin API request recorder (permission 100)
+export interface ApiTraceRecorderStatus {
+  enabled: boolean;
+  until: string | null;
+  enabled_by: string | null;
+  entries: number;
+  cap_bytes: number;
+  max_duration_minutes: number;
+}
+
+/** Recorded request; bodies only in the detail view, credentials masked */
+export interface ApiTraceEntry {
+  trace_id: string;
+  at: string;
+  method: string;
+  path: string;
+  query?: string;
+  request_body?: string;
+  status: number;
+  latency_ms: number;
+  actor: string;
+  actor_permission: number;
+  client_ip: string;
+  response_body?: string;
+  notes?: string[];
+}
+
+export interface ApiTraceQuery {
+  actor?: string;
+  path?: string;
+  method?: string;
+  from?: string;
+  to?: string;
+  limit?: number;
+}
+
+export interface ApiTraceReplaySide {
+  at: string;
+  actor: string;
+  status: number;
+  body: unknown | null;
+  notes?: string[];
+}
+
+export interface ApiTraceReplay {
+  trace_id: string;
+  method: string;
+  path: string;
+  query: string | null;
+  original: ApiTraceReplaySide;
+  replay: ApiTraceReplaySide;
+  status_changed: boolean;
+  body_changed: boolean | null;
+}
+
+export const apiTraceApi = {
+  getRecorder: () => request<ApiTraceRecorderStatus>('/admin/api-trace/recorder'),
+
+  setRecorder: (enabled: boolean, durationMinutes?: number) =>
+    request<ApiTraceRecorderStatus>('/admin/api-trace/recorder', {
+      method: 'PUT',
+      body: JSON.stringify({ enabled, duration_minutes: durationMinutes }),
+    }),
+
+  list: (params: ApiTraceQuery = {}) => {
+    const query = new URLSearchParams();
+    Object.entries(params).forEach(([key, value]) => {
+      if (value !== undefined && value !== '') query.set(key, String(value));
+    });
+    return request<{ recorder: ApiTraceRecorderStatus; entries: ApiTraceEntry[] }>(
+      `/admin/api-trace?${query}`
+    );
+  },
+
+  get: (id: string) => request<ApiTraceEntry>(`/admin/api-trace/${encodeURIComponent(id)}`),
+
+  replay: (id: string) =>
+    request<ApiTraceReplay>(`/admin/api-trace/${encodeURIComponent(id)}/replay`, {
+      method: 'POST',
+    }),
+};
+
+// Unified device search (clients of all sources + topology nodes)
+export interface DeviceSearchSource {
+  source: 'omada' | 'openwrt' | 'external' | 'topology';
+  owner_id: string;
+  owner_name: string | null;
+  name: string | null;
+  ip: string | null;
+  state: string;
+  last_seen: string | null;
+  ssid: string | null;
+}
+
+export interface DeviceSearchHit {
+  mac: string;
+  label: string | null;
+  ip: string | null;
+  online: boolean;
+  last_seen: string | null;
+  topology_node_id: string | null;
+  /** Class of the linked topology node (phone, laptop, printer, ...) */
+  device_class: string | null;
+  sources: DeviceSearchSource[];
+}
+
+export interface DeviceSearchResult {
+  query: string;
+  count: number;
+  truncated: boolean;
+  devices: DeviceSearchHit[];
+}
+
+export interface DeviceClassRule {
+  field: 'device_category' | 'device_type' | 'os_name' | 'vendor' | 'oui' | 'hostname';
+  pattern: string;
+  class: string;
+}
+
+export interface DeviceClassRulesResponse {
+  /** Setting key to PUT a replacement table ({"rules": [...]}) to */
+  setting: string;
+  customized: boolean;
+  setting_error: string | null;
+  classes: string[];
+  rules: DeviceClassRule[];
+}
+
+export interface InventoryExportParams {
+  device_class?: string;
+  node_type?: string;
+  source?: string;
+  fid?: string;
+}
+
+export const devicesApi = {
+  search: (q: string, limit?: number, deviceClass?: string) => {
+    const params = new URLSearchParams({ q });
+    if (limit) params.set('limit', String(limit));
+    if (deviceClass) params.set('device_class', deviceClass);
+    return request<DeviceSearchResult>(`/devices/search?${params}`);
+  },
+
+  getClassRules: () => request<DeviceClassRulesResponse>('/devices/class-rules'),
+
+  exportInventory: async (params: InventoryExportParams = {}) => {
+    const query = new URLSearchParams();
+    if (params.device_class) query.set('device_class', params.device_class);
+    if (params.node_type) query.set('node_type', params.node_type);
+    if (params.source) query.set('source', params.source);
+    if (params.fid) query.set('fid', params.fid);
+    const response = await fetch(`${API_BASE}/devices/inventory/export?${query}`, {
+      credentials: 'include',
+    });
+    if (!response.ok) throw new Error(`HTTP ${response.status}`);
+    const blob = await response.blob();
+    const url = URL.createObjectURL(blob);
+    const a = document.createElement('a');
+    a.href = url;
+    a.download = 'device_inventory.csv';
+    a.click();
+    URL.revokeObjectURL(url);
+  },
 };
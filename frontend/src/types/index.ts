@@ -15,10 +15,88 @@ export interface ProxyRoute {
   preserve_host: boolean;
   timeout_ms: number;
   websocket_support: boolean;
+  /** 404 for sources outside the admin networks */
+  admin_network_only: boolean;
+  /** Override JSON (RouteSecurityHeaders); null = global policy */
+  security_headers?: string | null;
+  /** JSON list of allowed methods; null = all methods */
+  allowed_methods?: string | null;
+  /** Store-and-forward policy JSON; null = off */
+  store_forward?: string | null;
+  /** Expect: 100-continue handling; null = immediate */
+  expect_continue?: ExpectContinueMode | null;
+  /** Transformation script policy JSON (RouteTransform); null = none */
+  transform?: string | null;
+  /** Custom access log field rules JSON (RouteLogFields); null = none */
+  log_fields?: string | null;
+  owner_name?: string | null;
+  owner_contact?: string | null;
+  team?: string | null;
+  /** Listed on the public status page (/status) */
+  show_on_status_page?: boolean;
+  /** Public display name on the status page */
+  status_page_name?: string | null;
+  /** Canary target URL */
+  canary_target?: string | null;
+  /** Share of requests sent to the canary (0-100); null/0 = off */
+  canary_percent?: number | null;
+  /** Keep a client IP on the same side */
+  canary_sticky?: boolean;
+  /** Forward as gRPC over HTTP/2 with trailers */
+  grpc?: boolean;
+  /** grpc.health.v1 service probed by health checks; "" = overall server */
+  grpc_health_service?: string | null;
+  /** Path probed by HTTP health checks, appended to the target; null = "/" */
+  health_check_path?: string | null;
+  /** Seconds between health checks; null = global health_check_interval_sec */
+  health_check_interval_sec?: number | null;
+  /** Upstream Basic auth credentials are set (they are never returned) */
+  upstream_auth_configured?: boolean;
+  /** Seconds GET responses stay cached; 0 = no caching */
+  cache_ttl_sec?: number;
+  /** Header rewrite rules JSON (RouteHeaderRewrite); null = none */
+  header_rewrite?: string | null;
+  /** Upstream connect timeout in ms; null = shared client (10s) */
+  connect_timeout_ms?: number | null;
+  /** Idle upstream connections per host; null = shared client (10) */
+  max_idle_per_host?: number | null;
+  /** Fallback target URLs JSON list, tried in order; null = none */
+  fallback_targets?: string | null;
+  /** Further attempts per target */
+  retry_count?: number;
+  /** null = connect_error */
+  retry_on?: RetryOn | null;
+  /** Answering 503 with the maintenance page; health checks paused */
+  maintenance_mode?: boolean;
+  /** Maintenance page text; null = default text */
+  maintenance_message?: string | null;
+  /** Request body limit in bytes (413 above it); null = global limit */
+  max_body_bytes?: number | null;
+  /** Set in route lists when the linked DDNS hostname no longer resolves here */
+  dns_mismatch?: DnsMismatch | null;
   created_at: string;
   updated_at: string;
 }
 
+export type DnsMatchState = 'match' | 'mismatch' | 'unresolved' | 'unknown';
+
+/** A DDNS hostname diverged from this gateway past the grace period */
+export interface DnsMismatch {
+  ddns_config_id: number;
+  hostname: string;
+  state: DnsMatchState;
+  observed: string[];
+  expected: string[];
+  since: string | null;
+  checked_at: string;
+}
+
+/** immediate: gateway answers 100 Continue; passthrough: also forward Expect; strip: drop it */
+export type ExpectContinueMode = 'immediate' | 'passthrough' | 'strip';
+
+/** connect_error: connection failures only; gateway_error: also 502/503/504 answers */
+export type RetryOn = 'connect_error' | 'gateway_error';
+
 export interface CreateRouteRequest {
   path: string;
   target: string;
@@ -29,6 +107,44 @@ export interface CreateRouteRequest {
   preserve_host?: boolean;
   timeout_ms?: number;
   websocket_support?: boolean;
+  admin_network_only?: boolean;
+  security_headers?: RouteSecurityHeaders;
+  /** Omit or null to allow all methods */
+  allowed_methods?: string[] | null;
+  expect_continue?: ExpectContinueMode;
+  owner_name?: string;
+  owner_contact?: string;
+  team?: string;
+  canary_target?: string | null;
+  /** Omit, null or 0 for no canary */
+  canary_percent?: number | null;
+  canary_sticky?: boolean;
+  grpc?: boolean;
+  grpc_health_service?: string | null;
+  /** Empty for "/" */
+  health_check_path?: string | null;
+  /** 5-86400; omit or null for the global interval */
+  health_check_interval_sec?: number | null;
+  /** Basic auth sent to the target instead of the client's Authorization */
+  upstream_auth_user?: string;
+  upstream_auth_password?: string;
+  /** 0-86400; 0 = no caching */
+  cache_ttl_sec?: number;
+  header_rewrite?: RouteHeaderRewrite;
+  /** 0-300000; omitted or 0 = shared client */
+  connect_timeout_ms?: number;
+  /** 0-1000; omitted = shared client */
+  max_idle_per_host?: number;
+  /** HTTP(S) base URLs, at most 8 */
+  fallback_targets?: string[];
+  /** 0-5 */
+  retry_count?: number;
+  retry_on?: RetryOn;
+  maintenance_mode?: boolean;
+  /** Up to 1000 characters; omitted or empty = default text */
+  maintenance_message?: string;
+  /** Up to 128 MiB; omitted or 0 = global limit */
+  max_body_bytes?: number;
 }
 
 export interface UpdateRouteRequest {
@@ -41,6 +157,189 @@ export interface UpdateRouteRequest {
   preserve_host?: boolean;
   timeout_ms?: number;
   websocket_support?: boolean;
+  admin_network_only?: boolean;
+  /** Empty object clears the override */
+  security_headers?: RouteSecurityHeaders;
+  /** null removes the restriction */
+  allowed_methods?: string[] | null;
+  expect_continue?: ExpectContinueMode;
+  /** Empty string clears the field */
+  owner_name?: string;
+  owner_contact?: string;
+  team?: string;
+  /** null or empty string clears the canary target */
+  canary_target?: string | null;
+  /** null or 0 disables the canary */
+  canary_percent?: number | null;
+  canary_sticky?: boolean;
+  grpc?: boolean;
+  grpc_health_service?: string | null;
+  /** null or empty string probes "/" */
+  health_check_path?: string | null;
+  /** null returns to the global interval */
+  health_check_interval_sec?: number | null;
+  /** null or empty string removes the upstream credentials */
+  upstream_auth_user?: string | null;
+  /** Omit to keep the stored password */
+  upstream_auth_password?: string;
+  /** 0 disables response caching */
+  cache_ttl_sec?: number;
+  /** Empty object clears the rules */
+  header_rewrite?: RouteHeaderRewrite;
+  /** null or 0 returns to the shared client */
+  connect_timeout_ms?: number | null;
+  /** null returns to the shared client */
+  max_idle_per_host?: number | null;
+  /** null or an empty list removes the fallbacks */
+  fallback_targets?: string[] | null;
+  retry_count?: number;
+  retry_on?: RetryOn;
+  maintenance_mode?: boolean;
+  /** null or empty returns to the default text */
+  maintenance_message?: string | null;
+  /** null or 0 returns to the global limit */
+  max_body_bytes?: number | null;
+}
+
+/** Per-route header rules; names are case-insensitive (hop-by-hop headers and Content-Length are rejected) */
+export interface RouteHeaderRewrite {
+  /** Set on the upstream request, overriding X-Forwarded-* and Host */
+  request_headers_set?: Record<string, string>;
+  request_headers_remove?: string[];
+  response_headers_set?: Record<string, string>;
+  response_headers_remove?: string[];
+}
+
+/** POST /api/routes/:id/cache/purge */
+export interface RouteCachePurgeResult {
+  route_id: number;
+  cache_ttl_sec: number;
+  purged: number;
+  cache: {
+    entries: number;
+    bytes: number;
+    max_bytes: number;
+    hits: number;
+    misses: number;
+  };
+}
+
+/** GET /api/routes/test */
+export interface RouteTestResult {
+  path: string;
+  host: string | null;
+  method: string;
+  result: {
+    outcome: 'match' | 'no_route' | 'method_not_allowed' | 'path_rejected';
+    status: number;
+    route_id: number | null;
+    route_path: string | null;
+    target_url: string | null;
+    admin_network_only: boolean;
+    allow: string | null;
+    detail: string | null;
+  };
+}
+
+export interface SecurityHeaderRule {
+  value?: string;
+  /** Replace the header even when the upstream set it */
+  force?: boolean;
+  disabled?: boolean;
+}
+
+/** Setting security_headers_default (JSON) */
+export interface SecurityHeadersPolicy {
+  enabled: boolean;
+  headers: Record<string, SecurityHeaderRule>;
+}
+
+export interface RouteSecurityHeaders {
+  /** Omit to follow the global policy */
+  enabled?: boolean;
+  headers?: Record<string, SecurityHeaderRule>;
+}
+
+export interface EffectiveSecurityHeaders {
+  enabled: boolean;
+  source: 'global' | 'route';
+  headers: { name: string; value: string; force: boolean }[];
+}
+
+/** Desired route for PUT /api/routes/sync, keyed by path + host */
+export interface RouteSyncSpec extends CreateRouteRequest {
+  /** DDNS hostname; omit for routes without a DDNS config */
+  host?: string | null;
+}
+
+export type RouteSyncStatus = 'planned' | 'applied' | 'failed' | 'not_applied';
+
+export interface RouteSyncResult {
+  dry_run: boolean;
+  prune: boolean;
+  strict: boolean;
+  /** Strict sync with rejected entries: nothing was applied */
+  aborted: boolean;
+  summary: {
+    create: number;
+    update: number;
+    delete: number;
+    unchanged: number;
+    skipped: number;
+    errors: number;
+    applied: number;
+  };
+  actions: {
+    action: 'create' | 'update' | 'delete';
+    index: number | null;
+    path: string;
+    host: string | null;
+    route_id: number | null;
+    changes?: string[];
+    status: RouteSyncStatus;
+    error?: unknown;
+  }[];
+  skipped: {
+    index: number | null;
+    path: string;
+    host: string | null;
+    route_id: number | null;
+    reason: string;
+  }[];
+  errors: { index: number; path: string; host: string | null; error: unknown }[];
+}
+
+export interface ProxyRouteDetail extends ProxyRoute {
+  security_headers_effective: EffectiveSecurityHeaders;
+}
+
+export interface SecurityHeadersReport {
+  tracked_headers: string[];
+  routes: {
+    route_id: number;
+    path: string;
+    active: boolean;
+    policy_enabled: boolean;
+    policy_source: 'global' | 'route';
+    samples: number;
+    /** Header -> responses without it */
+    missing: Record<string, number>;
+    uncovered_by_policy: string[];
+  }[];
+}
+
+export interface RoutePendingChange {
+  id: number;
+  route_id?: number | null;
+  action: 'create' | 'update';
+  payload: CreateRouteRequest | UpdateRouteRequest;
+  status: 'pending' | 'approved' | 'rejected';
+  proposed_by: string;
+  proposed_by_permission: number;
+  reviewed_by?: string | null;
+  review_comment?: string | null;
+  created_at: string;
+  reviewed_at?: string | null;
 }
 
 // ============================================================================
@@ -60,15 +359,38 @@ export interface DdnsConfig {
   zone_id?: string;
   update_interval_sec: number;
   last_ip?: string;
+  /** Also keep the AAAA record (IPv6) current */
+  update_ipv6: boolean;
+  last_ipv6?: string | null;
   last_update?: string;
   last_error?: string;
   status: DdnsStatus;
   omada_controller_id?: string;
   omada_site_id?: string;
+  ip_source: DdnsIpSource;
+  openwrt_router_id?: string;
+  report_token?: string;
+  reported_ip?: string;
+  reported_at?: string;
+  /** Cloudflare only; unset keeps the record's current state */
+  proxied?: boolean | null;
+  /** Cloudflare only; 1 = auto */
+  ttl?: number | null;
+  /** Cloudflare only: cached A / AAAA record ids */
+  record_id?: string | null;
+  record_id_v6?: string | null;
+  /** Standby config for the same hostname (failover) */
+  secondary_config_id?: number | null;
+  failover_threshold: number;
+  consecutive_failures: number;
+  /** Updates currently go through the secondary */
+  failover_active: boolean;
   created_at: string;
   updated_at: string;
 }
 
+export type DdnsIpSource = 'poll' | 'router' | 'webhook';
+
 export interface CreateDdnsRequest {
   provider: DdnsProvider;
   hostname: string;
@@ -77,6 +399,13 @@ export interface CreateDdnsRequest {
   api_token?: string;
   zone_id?: string;
   update_interval_sec?: number;
+  update_ipv6?: boolean;
+  ip_source?: DdnsIpSource;
+  openwrt_router_id?: string;
+  proxied?: boolean;
+  ttl?: number;
+  secondary_config_id?: number;
+  failover_threshold?: number;
 }
 
 export interface UpdateDdnsRequest {
@@ -86,7 +415,34 @@ export interface UpdateDdnsRequest {
   api_token?: string;
   zone_id?: string;
   update_interval_sec?: number;
+  /** false also forgets last_ipv6 */
+  update_ipv6?: boolean;
   status?: DdnsStatus;
+  ip_source?: DdnsIpSource;
+  openwrt_router_id?: string;
+  /** null clears the override */
+  proxied?: boolean | null;
+  ttl?: number | null;
+  /** null removes the failover link */
+  secondary_config_id?: number | null;
+  failover_threshold?: number;
+}
+
+/** POST /ddns/test: a stored config, or a create body tested before saving */
+export type DdnsTestRequest = { config_id: number } | CreateDdnsRequest;
+
+/** Non-destructive credential check against the provider */
+export interface DdnsTestResult {
+  provider: string;
+  hostname: string;
+  method: string;
+  ok: boolean;
+  /** null when the check stopped before the credentials were used */
+  auth_ok: boolean | null;
+  record_found: boolean | null;
+  remote_value: string | null;
+  latency_ms: number;
+  error: string | null;
 }
 
 // ============================================================================
@@ -100,6 +456,30 @@ export interface BlockedIp {
   blocked_by: string;
   expires_at?: string;
   created_at: string;
+  /** Quick-block source, e.g. `access_log:<id>` */
+  context_ref?: string | null;
+}
+
+export type QuickBlockContext =
+  | { type: 'access_log'; id: string }
+  | { type: 'security_event'; id: string };
+
+export type BlockDurationPreset = '1h' | '24h' | '7d' | '30d' | 'permanent';
+
+export interface QuickBlockRequest {
+  ip: string;
+  context: QuickBlockContext;
+  action?: 'block' | 'unblock';
+  duration?: BlockDurationPreset;
+  confirm?: boolean;
+}
+
+export interface QuickBlockResponse {
+  action: 'block' | 'unblock';
+  block: BlockedIp;
+  context_ref: string;
+  recent_requests: number;
+  recent_window_hours: number;
 }
 
 export interface BlockIpRequest {
@@ -108,16 +488,84 @@ export interface BlockIpRequest {
   expires_at?: string;
 }
 
+export type RateLimitAction = 'log' | 'block';
+
+/** Per-client-IP request limit below a path prefix */
+export interface RateLimitRule {
+  id: number;
+  path_prefix: string;
+  requests_per_window: number;
+  window_sec: number;
+  action: RateLimitAction;
+  /** `block` rules: seconds on the block list; null = 429 only */
+  block_duration_sec: number | null;
+  exclude_lan: boolean;
+  enabled: boolean;
+  comment: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface RateLimitRuleList {
+  /** Setting `rate_limit_enabled` */
+  enabled: boolean;
+  rules: (RateLimitRule & { tracked_clients: number })[];
+}
+
+/** Country allow/deny rules of proxied requests (setting `geo_rules`) */
+export interface GeoRules {
+  enabled: boolean;
+  /** deny: refuse the listed countries; allow: refuse all others */
+  mode: 'deny' | 'allow';
+  /** ISO 3166-1 alpha-2 codes */
+  countries: string[];
+  /** Clients without a known country (LAN, GeoIP miss) */
+  unresolved_action: 'allow' | 'deny';
+}
+
+export interface GeoRulesView extends GeoRules {
+  geoip_loaded: boolean;
+}
+
+export interface CreateRateLimitRuleRequest {
+  path_prefix: string;
+  requests_per_window: number;
+  window_sec: number;
+  action?: RateLimitAction;
+  block_duration_sec?: number;
+  exclude_lan?: boolean;
+  enabled?: boolean;
+  comment?: string;
+}
+
+export interface UpdateRateLimitRuleRequest {
+  path_prefix?: string;
+  requests_per_window?: number;
+  window_sec?: number;
+  action?: RateLimitAction;
+  /** null stops blocking (429 only) */
+  block_duration_sec?: number | null;
+  exclude_lan?: boolean;
+  enabled?: boolean;
+  comment?: string;
+}
+
 export type SecurityEventType =
   | 'ip_blocked'
   | 'rate_limit_exceeded'
   | 'suspicious_activity'
   | 'ddns_failure'
-  | 'health_check_failure';
+  | 'health_check_failure'
+  | 'new_device'
+  | 'alert_rule'
+  | 'insecure_configuration'
+  | 'dns_mismatch'
+  | 'tls_certificate_error';
 
 export type Severity = 'low' | 'medium' | 'high' | 'critical';
 
 export interface SecurityEvent {
+  _id?: string;
   timestamp: string;
   event_type: SecurityEventType;
   ip?: string;
@@ -126,6 +574,152 @@ export interface SecurityEvent {
   notified: boolean;
 }
 
+// Access-log alert rules (filter: `field op value and ...` over access log fields)
+// Daily metrics read per-IP rollups: some IP above threshold on each of the last `days` days
+export type AlertMetric =
+  | 'count'
+  | 'error_rate'
+  | 'unique_ips'
+  | 'ip_daily_requests'
+  | 'ip_daily_errors'
+  | 'ip_daily_security_events';
+
+export type AlertState = 'pending' | 'ok' | 'firing' | 'error';
+
+export interface AlertRuleStatus {
+  state: AlertState;
+  last_evaluated_at?: string;
+  last_value?: number;
+  last_matched?: number;
+  last_fired_at?: string;
+  fire_count: number;
+  last_error?: string;
+}
+
+export interface AlertRule {
+  rule_id: string;
+  name: string;
+  enabled: boolean;
+  filter: string;
+  metric: AlertMetric;
+  window_minutes: number;
+  /** Consecutive completed days (daily metrics) */
+  days: number;
+  threshold: number;
+  min_requests: number;
+  severity: Severity;
+  /** 'none', 'discord' or an https webhook URL */
+  notify: string;
+  cooldown_minutes: number;
+  status: AlertRuleStatus;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreateAlertRuleRequest {
+  name: string;
+  enabled?: boolean;
+  filter?: string;
+  metric?: AlertMetric;
+  window_minutes?: number;
+  days?: number;
+  threshold: number;
+  min_requests?: number;
+  severity?: Severity;
+  notify?: string;
+  cooldown_minutes?: number;
+}
+
+export type UpdateAlertRuleRequest = Partial<CreateAlertRuleRequest>;
+
+export interface AlertEvaluation {
+  rule_id: string;
+  window_from: string;
+  window_to: string;
+  stats: {
+    matched: number;
+    errors: number;
+    unique_ips: number;
+    top_ips: { ip: string; count: number }[];
+  };
+  value: number;
+  threshold: number;
+  breached: boolean;
+  action: 'none' | 'fire' | 'suppressed';
+  cooldown_until?: string;
+}
+
+export interface AlertRuleTestResult {
+  rule: AlertRule;
+  condition: string;
+  query: Record<string, unknown>[];
+  evaluation: AlertEvaluation;
+  would: string;
+}
+
+/** 'suppress' holds back health notifications; 'pause' skips the checks */
+export type MaintenanceMode = 'suppress' | 'pause';
+
+export type MaintenanceSchedule =
+  | { kind: 'once'; start: string; end: string }
+  /** days: 'mon'..'sun' (empty = every day); start: HH:MM server local time */
+  | { kind: 'weekly'; days: string[]; start: string; duration_minutes: number };
+
+export interface MaintenanceWindowStatus {
+  occurrence_start?: string;
+  occurrence_end?: string;
+  suppressed_failures: number;
+  suppressed_alerts: number;
+  failed_routes: number[];
+  last_closed_at?: string;
+  last_summary?: string;
+}
+
+export interface MaintenanceWindow {
+  window_id: string;
+  name: string;
+  enabled: boolean;
+  schedule: MaintenanceSchedule;
+  /** Empty route_ids and teams = all routes */
+  route_ids: number[];
+  teams: string[];
+  mode: MaintenanceMode;
+  status: MaintenanceWindowStatus;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreateMaintenanceWindowRequest {
+  name: string;
+  enabled?: boolean;
+  schedule: MaintenanceSchedule;
+  route_ids?: number[];
+  teams?: string[];
+  mode?: MaintenanceMode;
+}
+
+export type UpdateMaintenanceWindowRequest = Partial<CreateMaintenanceWindowRequest>;
+
+export interface ActiveMaintenance {
+  window_id: string;
+  name: string;
+  mode: MaintenanceMode;
+  starts_at: string;
+  ends_at: string;
+}
+
+export interface ActiveMaintenanceWindow extends ActiveMaintenance {
+  affected_routes: number;
+  suppressed_failures: number;
+  suppressed_alerts: number;
+}
+
+export interface ActiveMaintenanceResponse {
+  active: boolean;
+  windows: ActiveMaintenanceWindow[];
+  checked_at: string;
+}
+
 // ============================================================================
 // Settings
 // ============================================================================
@@ -149,18 +743,125 @@ export interface DashboardStats {
   blocked_ips: number;
   server_health: string;
   uptime_seconds: number;
+  comparison?: StatsComparison;
+  /** Fields that could not be read (their value above is 0), keyed by field name */
+  field_errors: Record<string, string>;
+  data_sources: {
+    mysql: DataSourceStatus;
+    mongo: DataSourceStatus;
+  };
+  /** LAN DNS responder listener (see localDnsApi) */
+  local_dns?: {
+    enabled: boolean;
+    status: 'disabled' | 'ok' | 'degraded' | 'down';
+    listen: string;
+    last_error: string | null;
+  };
+  /** Sessions are signed with the shipped default secret (see jwtSecretApi.rotate) */
+  default_jwt_secret?: boolean;
+}
+
+export interface DataSourceStatus {
+  reachable: boolean;
+  errors: string[];
+}
+
+export interface PeriodTotals {
+  total_requests: number;
+  error_count: number;
+  avg_response_time_ms: number;
+}
+
+/** Percentage change vs previous window; null when the previous value is zero */
+export interface PeriodDeltas {
+  total_requests_pct: number | null;
+  error_count_pct: number | null;
+  avg_response_time_pct: number | null;
+}
+
+export interface StatsComparison {
+  current_from: string;
+  current_to: string;
+  previous_from: string;
+  previous_to: string;
+  current: PeriodTotals;
+  previous: PeriodTotals;
+  delta: PeriodDeltas;
 }
 
 export interface RouteHealth {
   route_id: number;
   path: string;
   target: string;
+  owner_name?: string | null;
+  owner_contact?: string | null;
+  team?: string | null;
   healthy: boolean;
+  /** 'healthy' | 'unhealthy' | 'unknown' */
+  status: string;
   last_check?: string;
   consecutive_failures: number;
+  /** Path the HTTP probe checks; null for grpc.health.v1 checks */
+  probe_path: string | null;
+  /** Effective seconds between checks */
+  check_interval_sec: number;
+  error?: string;
+  /** Maintenance windows covering the route right now */
+  maintenance: ActiveMaintenance[];
+}
+
+export type DependencyName = 'mysql' | 'mongodb' | 'omada' | 'openwrt' | 'aranea' | 'discord';
+
+/** Latest probe result of one dependency target */
+export interface DependencyHealth {
+  timestamp: string;
+  dependency: DependencyName;
+  /** Controller / router id; the dependency name for single instances */
+  target: string;
+  label?: string | null;
+  healthy: boolean;
+  response_time_ms?: number | null;
+  error?: string | null;
+  consecutive_failures: number;
+  /** Probe-specific measurements (pool stats, storage, SSH banner, ...) */
+  details: Record<string, unknown> | null;
+}
+
+export interface DependenciesStatus {
+  /** 'healthy' | 'degraded' | 'unknown' */
+  status: string;
+  dependencies: DependencyHealth[];
+}
+
+// Public status page (GET /status.json)
+export type ServiceState = 'unknown' | 'operational' | 'degraded' | 'down';
+
+export interface StatusPageService {
+  name: string;
+  status: ServiceState;
+  uptime_percent: number | null;
+  /** 90 days, oldest first; null = no checks that day */
+  days: { day: string; uptime_percent: number | null }[];
+}
+
+export interface StatusPageIncident {
+  service: string;
+  started_at: string;
+  /** null while ongoing */
+  resolved_at: string | null;
+  duration_secs: number;
+}
+
+export interface StatusPageSnapshot {
+  title: string;
+  status: ServiceState;
+  generated_at: string;
+  services: StatusPageService[];
+  incidents: StatusPageIncident[];
 }
 
 export interface AccessLog {
+  _id?: string;
   timestamp: string;
   ip: string;
   method: string;
@@ -179,6 +880,16 @@ export interface AccessLog {
   city?: string;
   latitude?: number;
   longitude?: number;
+  /** Which proxy served the request (absent on older entries, all from LPG) */
+  source?: 'lpg' | 'nginx' | null;
+  /** Values extracted by the route's custom log field rules */
+  custom_fields?: Record<string, string>;
+  /** /package.Service/Method on gRPC routes */
+  grpc_method?: string;
+  /** grpc-status from the trailers (or headers on trailers-only replies) */
+  grpc_status?: number;
+  /** WebSocket session length; sizes are the bytes relayed each way */
+  session_duration_ms?: number;
 }
 
 export interface StatusDistribution {
@@ -197,6 +908,19 @@ export interface HourlyStat {
   avg_response_time_ms: number;
 }
 
+export interface HourlyComparisonBucket {
+  hour: string;
+  previous_hour: string;
+  current: PeriodTotals;
+  previous: PeriodTotals;
+  delta: PeriodDeltas;
+}
+
+export interface HourlyStatsComparison {
+  buckets: HourlyComparisonBucket[];
+  totals: StatsComparison;
+}
+
 export interface TopEntry {
   key: string;
   count: number;
@@ -207,6 +931,8 @@ export interface TopEntry {
   city?: string;
   latitude?: number;
   longitude?: number;
+  /** Country rule verdict (top IPs with resolve_country) */
+  geo_action?: 'allow' | 'deny';
 }
 
 export interface ErrorSummary {
@@ -220,6 +946,115 @@ export interface AccessLogSearchResult {
   total: number;
 }
 
+// POST /dashboard/access-log/delete (path is a literal prefix)
+export interface AccessLogDeleteFilter {
+  from?: string;
+  to?: string;
+  method?: string;
+  status_min?: number;
+  status_max?: number;
+  ip?: string;
+  path?: string;
+}
+
+// GET /dashboard/in-flight
+export interface InFlightRequest {
+  id: number;
+  kind: 'http' | 'websocket';
+  route_id?: number;
+  target?: string;
+  client_ip: string;
+  method: string;
+  path: string;
+  started_at: string;
+  age_ms: number;
+  bytes_transferred: number;
+  aborting: boolean;
+  /** Relay counters (WebSocket tunnels only) */
+  tunnel?: TunnelSnapshot;
+}
+
+export interface TunnelSnapshot {
+  frames_client_to_upstream: number;
+  bytes_client_to_upstream: number;
+  frames_upstream_to_client: number;
+  bytes_upstream_to_client: number;
+  /** Bytes read from one peer and not yet written to the other */
+  queued_bytes: number;
+  queue_high_water_bytes: number;
+  keepalive_pings: number;
+  duration_ms: number;
+}
+
+/** Per-route WebSocket totals since startup (closed tunnels + open count) */
+export interface RouteTunnelSummary {
+  active_tunnels: number;
+  closed_tunnels: number;
+  /** Closed with 1011 after exceeding proxy_ws_max_buffered_kb */
+  buffer_limit_closes: number;
+  frames_client_to_upstream: number;
+  bytes_client_to_upstream: number;
+  frames_upstream_to_client: number;
+  bytes_upstream_to_client: number;
+  max_queue_high_water_bytes: number;
+  keepalive_pings: number;
+  total_duration_ms: number;
+}
+
+export interface InFlightList {
+  total: number;
+  requests: InFlightRequest[];
+}
+
+/** GET /api/dashboard/websockets */
+export interface WebSocketSessionList {
+  active: number;
+  /** Relayed so far by the open sessions */
+  bytes_client_to_upstream: number;
+  bytes_upstream_to_client: number;
+  /** All routes since startup (closed sessions + open count) */
+  closed: RouteTunnelSummary;
+  /** Oldest first */
+  sessions: InFlightRequest[];
+}
+
+/** GET /api/dashboard/notification-queue */
+export interface NotificationQueueStats {
+  depth: number;
+  capacity: number;
+  critical_queued: number;
+  enqueued: number;
+  sent: number;
+  retried: number;
+  /** 429 responses */
+  rate_limited: number;
+  /** Evicted or refused because the queue was full */
+  dropped_full: number;
+  /** Rejected by the destination or out of attempts */
+  failed: number;
+  destinations: {
+    /** Host (and Discord webhook id), never the token */
+    destination: string;
+    queued: number;
+    limit: { burst: number; per_minute: number };
+    tokens: number;
+    paused_for_ms: number | null;
+  }[];
+}
+
+export interface AccessLogDeleteJob {
+  job_id: string;
+  status: 'running' | 'completed' | 'failed';
+  filter: AccessLogDeleteFilter;
+  matched: number;
+  deleted: number;
+  batches: number;
+  started_by: string;
+  started_at: string;
+  finished_at?: string;
+  error?: string;
+}
+
 export interface AccessLogSearchParams {
   from?: string;
   to?: string;
@@ -232,6 +1067,9 @@ export interface AccessLogSearchParams {
   offset?: number;
   exclude_ips?: string;
   exclude_lan?: boolean;
+  /** Custom log field name; matched exactly against custom_value */
+  custom_field?: string;
+  custom_value?: string;
 }
 
 export interface IpExclusionParams {
@@ -258,6 +1096,8 @@ export interface AuthUser {
   lacis_id?: string;
   permission: number;
   auth_method: 'local' | 'lacisoath';
+  /** LacisOath provider that authenticated the session */
+  auth_provider?: string;
 }
 
 export interface AuthResponse {
@@ -270,13 +1110,48 @@ export interface LocalLoginRequest {
   password: string;
 }
 
+export interface LacisOathProviderConfig {
+  id: string;
+  display_name: string;
+  client_id: string;
+  auth_url: string;
+  redirect_uri: string;
+}
+
+/** Top-level fields describe the default provider */
 export interface LacisOathConfig {
   enabled: boolean;
   client_id: string;
   auth_url: string;
   redirect_uri: string;
+  default_provider: string | null;
+  providers: LacisOathProviderConfig[];
 }
 
+/** Admin view (client_secret masked) */
+export interface LacisOathProvider {
+  provider_id: string;
+  display_name: string;
+  client_id: string;
+  client_secret: string;
+  auth_url: string;
+  token_url: string;
+  redirect_uri: string;
+  expected_tid: string | null;
+  required_permission: number | null;
+  required_fid: string;
+  enabled: boolean;
+  /** Defined in the config file (read-only) */
+  from_config: boolean;
+  created_at: string | null;
+  updated_at: string | null;
+}
+
+export type LacisOathProviderInput = Omit<
+  LacisOathProvider,
+  'from_config' | 'created_at' | 'updated_at'
+>;
+
 // ============================================================================
 // OpenWrt
 // ============================================================================
@@ -305,6 +1180,8 @@ export interface OpenWrtRouterDoc {
   product_type: string;
   network_device_type: string;
   last_polled_at?: string;
+  poll_interval_sec?: number;
+  paused?: boolean;
   created_at: string;
   updated_at: string;
 }
@@ -315,6 +1192,9 @@ export interface OpenWrtClientDoc {
   ip: string;
   hostname?: string;
   lacis_id?: string;
+  wireless: boolean;
+  ssid?: string;
+  band?: string;
   active: boolean;
   last_seen_at: string;
   synced_at: string;
@@ -351,6 +1231,8 @@ export interface ExternalDeviceDoc {
   product_type: string;
   network_device_type: string;
   last_polled_at?: string;
+  poll_interval_sec?: number;
+  paused?: boolean;
   created_at: string;
   updated_at: string;
 }
@@ -361,6 +1243,9 @@ export interface ExternalClientDoc {
   ip?: string;
   hostname?: string;
   lacis_id?: string;
+  wireless: boolean;
+  ssid?: string;
+  band?: string;
   active: boolean;
   last_seen_at: string;
   synced_at: string;
@@ -399,6 +1284,8 @@ export interface OmadaControllerDoc {
   api_ver: string;
   status: string;
   last_error?: string;
+  consecutive_failures?: number;
+  offline_since?: string;
   sites: OmadaSiteMapping[];
   last_synced_at?: string;
   created_at: string;
@@ -449,7 +1336,52 @@ export interface SuccessResponse {
   id?: number;
 }
 
+/** Stable machine-readable error code (backend `ErrorCode`) */
+export type ErrorCode =
+  | 'NOT_FOUND'
+  | 'BAD_REQUEST'
+  | 'VALIDATION_FAILED'
+  | 'UNAUTHORIZED'
+  | 'FORBIDDEN'
+  | 'INTERNAL_ERROR'
+  | 'DATABASE_ERROR'
+  | 'CONFIG_ERROR'
+  | 'UPSTREAM_ERROR'
+  | 'ROUTE_NOT_FOUND'
+  | 'ROUTE_DELETED'
+  | 'DDNS_CONFIG_NOT_FOUND'
+  | 'DDNS_PROVIDER_AUTH_FAILED'
+  | 'DDNS_UPDATE_FAILED'
+  | 'NODE_NOT_FOUND'
+  | 'CONTROLLER_NOT_FOUND'
+  | 'OMADA_TOKEN_EXPIRED'
+  | 'OMADA_UNAVAILABLE'
+  | 'WIREGUARD_PROFILE_NOT_FOUND'
+  | 'WIREGUARD_PEER_NOT_FOUND'
+  | 'ALERT_RULE_NOT_FOUND'
+  | 'SETTING_NOT_FOUND'
+  | 'DNS_OVERRIDE_NOT_FOUND'
+  | 'RATE_LIMIT_RULE_NOT_FOUND'
+  | 'REGISTRATION_TOKEN_NOT_FOUND'
+  | 'REGISTRATION_TOKEN_INVALID'
+  | 'REGISTRATION_TOKEN_SCOPE'
+  | 'RATE_LIMITED'
+  | 'MAINTENANCE_WINDOW_NOT_FOUND'
+  | 'LAYOUT_GENERATION_CONFLICT'
+  | 'TOPOLOGY_SHARE_NOT_FOUND'
+  | 'ROUTE_VERSION_NOT_FOUND'
+  | 'NGINX_CONFIG_STALE'
+  | 'ACME_ORDER_IN_PROGRESS';
+
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
 export interface ErrorResponse {
   error: string;
+  code: ErrorCode;
   status: number;
+  /** VALIDATION_FAILED carries `fields` */
+  details?: { fields?: FieldError[]; [key: string]: unknown };
 }
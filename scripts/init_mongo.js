@@ -31,6 +31,8 @@ db.access_logs.createIndex({ "timestamp": 1 }, { expireAfterSeconds: 2592000 });
 db.access_logs.createIndex({ "ip": 1, "timestamp": -1 });
 db.access_logs.createIndex({ "path": 1, "timestamp": -1 });
 db.access_logs.createIndex({ "status": 1, "timestamp": -1 });
+db.access_logs.createIndex({ "route_id": 1, "timestamp": -1, "status": 1 }); // health failure context
+db.access_logs.createIndex({ "http_version": 1, "timestamp": -1 }); // protocol summary / filter
 
 // Security Events Collection
 db.createCollection("security_events", {
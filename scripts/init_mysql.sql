@@ -15,12 +15,28 @@ CREATE TABLE IF NOT EXISTS ddns_configs (
     zone_id VARCHAR(100) COMMENT 'Cloudflare zone ID',
     update_interval_sec INT DEFAULT 300 COMMENT 'Update interval in seconds',
     last_ip VARCHAR(45) COMMENT 'Last known IP address',
+    update_ipv6 BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'Also push the public IPv6 address (AAAA)',
+    last_ipv6 VARCHAR(45) NULL COMMENT 'Last pushed IPv6 address',
     last_update TIMESTAMP NULL COMMENT 'Last successful update',
     last_error TEXT COMMENT 'Last error message if any',
     status ENUM('active', 'error', 'disabled') DEFAULT 'active',
+    ip_source VARCHAR(16) NOT NULL DEFAULT 'poll' COMMENT 'poll, router or webhook',
+    openwrt_router_id VARCHAR(64) NULL COMMENT 'router source: OpenWrt router (else Omada link)',
+    report_token VARCHAR(64) NULL COMMENT 'webhook source: report-ip bearer token',
+    reported_ip VARCHAR(45) NULL COMMENT 'webhook source: last pushed address',
+    reported_at TIMESTAMP NULL,
+    proxied BOOLEAN NULL COMMENT 'cloudflare: orange cloud (NULL keeps record state)',
+    ttl INT NULL COMMENT 'cloudflare: record TTL, 1 = auto (NULL keeps record TTL)',
+    record_id VARCHAR(64) NULL COMMENT 'cloudflare: cached A record id',
+    record_id_v6 VARCHAR(64) NULL COMMENT 'cloudflare: cached AAAA record id',
+    secondary_config_id INT NULL COMMENT 'failover: standby config for the same hostname',
+    failover_threshold INT NOT NULL DEFAULT 3 COMMENT 'failover: consecutive failures before switching',
+    consecutive_failures INT NOT NULL DEFAULT 0,
+    failover_active BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'failover: updating via the secondary',
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-    UNIQUE KEY uk_provider_hostname (provider, hostname)
+    UNIQUE KEY uk_provider_hostname (provider, hostname),
+    FOREIGN KEY (secondary_config_id) REFERENCES ddns_configs(id) ON DELETE SET NULL
 ) ENGINE=InnoDB;
 
 -- Proxy Routes Table
@@ -35,23 +51,227 @@ CREATE TABLE IF NOT EXISTS proxy_routes (
     preserve_host BOOLEAN DEFAULT FALSE COMMENT 'Preserve original Host header',
     timeout_ms INT DEFAULT 30000 COMMENT 'Request timeout in milliseconds',
     websocket_support BOOLEAN DEFAULT FALSE COMMENT 'Enable WebSocket proxy support',
+    admin_network_only BOOLEAN DEFAULT FALSE COMMENT 'Only reachable from admin-allowed networks (404 otherwise)',
+    security_headers TEXT NULL COMMENT 'Security header override JSON (NULL = global policy)',
+    allowed_methods TEXT NULL COMMENT 'Allowed HTTP methods JSON list (NULL = all methods)',
+    store_forward TEXT NULL COMMENT 'Store-and-forward queue policy JSON (NULL = off)',
+    expect_continue VARCHAR(16) NULL COMMENT 'Expect: 100-continue handling: immediate|passthrough|strip (NULL = immediate)',
+    transform MEDIUMTEXT NULL COMMENT 'Transformation script policy JSON (NULL = none)',
+    log_fields TEXT NULL COMMENT 'Custom access log field rules JSON (NULL = none)',
+    owner_name VARCHAR(100) NULL COMMENT 'Responsible person',
+    owner_contact VARCHAR(500) NULL COMMENT 'Owner contact (https webhook URL or handle)',
+    team VARCHAR(100) NULL COMMENT 'Team tag',
+    show_on_status_page BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'Listed on the public status page',
+    status_page_name VARCHAR(100) NULL COMMENT 'Public display name on the status page',
+    canary_target VARCHAR(500) NULL COMMENT 'Canary target URL',
+    canary_percent INT NULL COMMENT 'Share of requests sent to canary_target, 0-100 (NULL/0 = off)',
+    canary_sticky BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'Keep a client IP on the same side',
+    grpc BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'gRPC passthrough (HTTP/2 upstream, trailers, streaming)',
+    grpc_health_service VARCHAR(255) NULL COMMENT 'grpc.health.v1 service checked (empty = whole server, NULL = HTTP probe)',
+    health_check_path VARCHAR(255) NULL COMMENT 'Path probed by HTTP health checks (NULL = /)',
+    health_check_interval_sec INT NULL COMMENT 'Seconds between health checks (NULL = global interval)',
+    upstream_auth_user VARCHAR(255) NULL COMMENT 'Basic auth user sent to the target (NULL = pass the client header)',
+    upstream_auth_password VARCHAR(255) NULL COMMENT 'Basic auth password sent to the target',
+    cache_ttl_sec INT NOT NULL DEFAULT 0 COMMENT 'Seconds GET responses stay cached (0 = no caching)',
+    header_rewrite TEXT NULL COMMENT 'Request/response header rewrite rules JSON (NULL = none)',
+    connect_timeout_ms INT NULL COMMENT 'Upstream connect timeout (NULL = shared client)',
+    max_idle_per_host INT NULL COMMENT 'Idle upstream connections per host (NULL = shared client)',
+    fallback_targets TEXT NULL COMMENT 'Fallback target URLs JSON list, tried in order (NULL = none)',
+    retry_count INT NOT NULL DEFAULT 0 COMMENT 'Further attempts per target',
+    retry_on VARCHAR(16) NULL COMMENT 'connect_error or gateway_error (NULL = connect_error)',
+    maintenance_mode BOOLEAN NOT NULL DEFAULT FALSE COMMENT 'Answer 503 with the maintenance page instead of proxying',
+    maintenance_message TEXT NULL COMMENT 'Maintenance page text (NULL = default text)',
+    max_body_bytes BIGINT NULL COMMENT 'Request body limit in bytes (NULL = global limit)',
+    deleted_at TIMESTAMP NULL COMMENT 'Soft-delete timestamp (NULL = live)',
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
     INDEX idx_path (path),
     INDEX idx_active_priority (active, priority),
     INDEX idx_ddns (ddns_config_id),
+    INDEX idx_deleted_at (deleted_at),
+    INDEX idx_team (team),
     UNIQUE KEY uk_path_ddns (path, ddns_config_id),
     FOREIGN KEY (ddns_config_id) REFERENCES ddns_configs(id) ON DELETE SET NULL
 ) ENGINE=InnoDB;
 
+-- Route Pending Changes Table (approval workflow)
+CREATE TABLE IF NOT EXISTS route_pending_changes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    route_id INT NULL COMMENT 'Target route (NULL for proposed creations until approved)',
+    action VARCHAR(20) NOT NULL COMMENT 'create or update',
+    payload TEXT NOT NULL COMMENT 'Proposed CreateRouteRequest / UpdateRouteRequest JSON',
+    status ENUM('pending', 'approved', 'rejected') DEFAULT 'pending',
+    proposed_by VARCHAR(255) NOT NULL,
+    proposed_by_permission INT NOT NULL,
+    reviewed_by VARCHAR(255) NULL,
+    review_comment VARCHAR(1000) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    reviewed_at TIMESTAMP NULL,
+    INDEX idx_status (status),
+    INDEX idx_route (route_id)
+) ENGINE=InnoDB;
+
+-- Route configuration history (GET /api/routes/:id/versions, rollback)
+CREATE TABLE IF NOT EXISTS route_versions (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    route_id INT NOT NULL,
+    version INT NOT NULL COMMENT '1-based, per route',
+    config MEDIUMTEXT NOT NULL COMMENT 'Whole ProxyRoute JSON as written',
+    actor VARCHAR(255) NOT NULL COMMENT 'User, or system',
+    reason VARCHAR(32) NOT NULL COMMENT 'create, update, rollback, ...',
+    restored_version INT NULL COMMENT 'Version a rollback restored',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uniq_route_version (route_id, version)
+) ENGINE=InnoDB;
+
+-- WireGuard client-config profiles
+CREATE TABLE IF NOT EXISTS wg_config_profiles (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description VARCHAR(500) NULL,
+    dns VARCHAR(500) NULL COMMENT 'DNS template (NULL = omit DNS line)',
+    allowed_ips VARCHAR(1000) NOT NULL COMMENT 'AllowedIPs template',
+    persistent_keepalive INT NULL COMMENT 'NULL = request value, 0 = never emit',
+    mtu INT NULL,
+    scripts_allowed BOOLEAN DEFAULT FALSE COMMENT 'Allow PreUp/PostUp/PreDown/PostDown',
+    is_default BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Default profile reproduces the original generator output
+INSERT INTO wg_config_profiles (name, description, dns, allowed_ips, is_default) VALUES
+    ('default', 'Request DNS / AllowedIPs / keepalive as given', '{dns}', '{allowed_ips}', TRUE)
+ON DUPLICATE KEY UPDATE name = name;
+
+-- WireGuard peers provisioned through LPG (profile, allowed addresses, enabled)
+CREATE TABLE IF NOT EXISTS wg_peer_profiles (
+    public_key VARCHAR(64) PRIMARY KEY,
+    controller_id VARCHAR(100) NOT NULL,
+    site_id VARCHAR(100) NOT NULL,
+    peer_name VARCHAR(255) NOT NULL,
+    profile_id INT NULL,
+    allow_address TEXT NULL COMMENT 'JSON list as provisioned by LPG',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX idx_profile (profile_id),
+    FOREIGN KEY (profile_id) REFERENCES wg_config_profiles(id)
+) ENGINE=InnoDB;
+
+-- Local DNS overrides answered by the built-in LAN responder ([dns] config)
+CREATE TABLE IF NOT EXISTS dns_overrides (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    hostname VARCHAR(253) NOT NULL UNIQUE,
+    target VARCHAR(45) NULL COMMENT 'NULL = LPG LAN IP',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual' COMMENT 'manual | ddns',
+    comment VARCHAR(500) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Per-client-IP rate limits of proxied paths (token bucket per rule and IP)
+CREATE TABLE IF NOT EXISTS rate_limit_rules (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    path_prefix VARCHAR(255) NOT NULL,
+    requests_per_window INT NOT NULL,
+    window_sec INT NOT NULL,
+    action VARCHAR(16) NOT NULL DEFAULT 'log' COMMENT 'log | block',
+    block_duration_sec INT NULL COMMENT 'block: seconds on the block list, NULL = 429 only',
+    exclude_lan BOOLEAN NOT NULL DEFAULT TRUE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    comment VARCHAR(500) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Device registration tokens for POST /api/aranea/register (X-Registration-Token)
+CREATE TABLE IF NOT EXISTS aranea_registration_tokens (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    fid VARCHAR(50) NOT NULL,
+    product_types TEXT NOT NULL COMMENT 'JSON list of allowed productType values',
+    token_hash CHAR(64) NOT NULL UNIQUE COMMENT 'SHA-256 hex of the token',
+    token_prefix VARCHAR(16) NOT NULL,
+    expires_at TIMESTAMP NULL,
+    max_uses INT NULL COMMENT 'NULL = unlimited',
+    use_count INT NOT NULL DEFAULT 0,
+    last_used_at TIMESTAMP NULL,
+    last_used_ip VARCHAR(45) NULL,
+    revoked_at TIMESTAMP NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Read-only topology shares (GET /api/public/topology?token=...)
+CREATE TABLE IF NOT EXISTS topology_shares (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    view VARCHAR(16) NOT NULL COMMENT 'full | site',
+    fid VARCHAR(50) NULL COMMENT 'Facility of a site share',
+    detail VARCHAR(16) NOT NULL COMMENT 'labels | labels_ips',
+    token_hash CHAR(64) NOT NULL UNIQUE COMMENT 'SHA-256 hex of the token',
+    token_prefix VARCHAR(16) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    request_count BIGINT NOT NULL DEFAULT 0,
+    last_used_at TIMESTAMP NULL,
+    revoked_at TIMESTAMP NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Additional LacisOath identity providers (the [auth] client is provider 'default')
+CREATE TABLE IF NOT EXISTS lacisoath_providers (
+    provider_id VARCHAR(50) PRIMARY KEY,
+    display_name VARCHAR(100) NOT NULL,
+    client_id VARCHAR(255) NOT NULL,
+    client_secret VARCHAR(500) NOT NULL,
+    auth_url VARCHAR(500) NOT NULL,
+    token_url VARCHAR(500) NOT NULL,
+    redirect_uri VARCHAR(500) NOT NULL,
+    expected_tid VARCHAR(100) NULL COMMENT 'NULL = tenant not checked',
+    required_permission INT NULL COMMENT 'NULL = login floor only',
+    required_fid VARCHAR(20) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- ACME account keys, one per directory (Let's Encrypt production, staging, ...)
+CREATE TABLE IF NOT EXISTS acme_accounts (
+    directory_url VARCHAR(255) PRIMARY KEY,
+    key_pem TEXT NOT NULL COMMENT 'PKCS#8 P-256 account key',
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- ACME certificates of DDNS hostnames (POST /api/ddns/:id/acme/issue, auto-renewed)
+CREATE TABLE IF NOT EXISTS acme_certificates (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    ddns_config_id INT NOT NULL UNIQUE,
+    hostname VARCHAR(253) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' COMMENT 'pending | valid | failed',
+    cert_pem MEDIUMTEXT NULL COMMENT 'Full chain, leaf first',
+    key_pem TEXT NULL,
+    not_before TIMESTAMP NULL,
+    not_after TIMESTAMP NULL,
+    issued_at TIMESTAMP NULL,
+    last_attempt_at TIMESTAMP NULL,
+    last_error TEXT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (ddns_config_id) REFERENCES ddns_configs(id) ON DELETE CASCADE
+) ENGINE=InnoDB;
+
 -- Blocked IPs Table
 CREATE TABLE IF NOT EXISTS blocked_ips (
     id INT AUTO_INCREMENT PRIMARY KEY,
-    ip VARCHAR(45) NOT NULL UNIQUE COMMENT 'IPv4 or IPv6 address',
+    ip VARCHAR(64) NOT NULL UNIQUE COMMENT 'IPv4/IPv6 address or CIDR range',
     reason VARCHAR(500) COMMENT 'Block reason',
     blocked_by VARCHAR(50) DEFAULT 'manual' COMMENT 'manual or auto',
     expires_at TIMESTAMP NULL COMMENT 'NULL = permanent',
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    context_ref VARCHAR(100) NULL COMMENT 'Quick-block source: access_log:<id> or security_event:<id>',
     INDEX idx_ip (ip),
     INDEX idx_expires (expires_at)
 ) ENGINE=InnoDB;
@@ -71,18 +291,75 @@ INSERT INTO settings (setting_key, setting_value, description) VALUES
     ('discord_notify_security', 'true', 'Notify security events to Discord'),
     ('discord_notify_health', 'true', 'Notify health check failures to Discord'),
     ('discord_notify_ddns', 'true', 'Notify DDNS update events to Discord'),
+    ('discord_notify_new_device', 'false', 'Notify devices seen on the network for the first time to Discord'),
     ('rate_limit_enabled', 'true', 'Enable rate limiting'),
     ('rate_limit_requests_per_minute', '60', 'Max requests per minute per IP'),
     ('health_check_interval_sec', '60', 'Health check interval in seconds'),
     ('health_check_timeout_ms', '5000', 'Health check timeout in milliseconds'),
     ('health_check_failure_threshold', '3', 'Consecutive failures before alert'),
-    ('access_log_retention_days', '30', 'Days to retain access logs'),
+    ('dependency_check_interval_sec', '300', 'Dependency probe interval in seconds'),
+    ('dependency_check_timeout_ms', '5000', 'Dependency probe timeout in milliseconds'),
+    ('dependency_failure_threshold', '2', 'Consecutive dependency probe failures before alert'),
+    ('dependency_probes_disabled', '', 'Comma-separated probes to skip (mysql, mongodb, omada, openwrt, aranea, discord)'),
+    ('mongo_disk_usage_max_percent', '90', 'MongoDB filesystem usage (%) above which the dependency probe fails'),
+    ('mongo_storage_max_mb', '0', 'MongoDB storage size (MB) above which the dependency probe fails (0 = no limit)'),
+    ('access_log_retention_days', '90', 'Days to retain access logs (0 = forever)'),
+    ('security_event_retention_days', '90', 'Days to retain security events (0 = forever)'),
+    ('health_check_retention_days', '90', 'Days to retain health check results (0 = forever)'),
     ('restart_scheduled_enabled', 'false', 'Enable scheduled daily restart'),
     ('restart_scheduled_time', '04:00', 'Scheduled restart time (HH:MM, 24h format)'),
     ('restart_auto_enabled', 'false', 'Enable auto-restart on high resource usage'),
     ('restart_cpu_threshold', '90', 'CPU threshold percentage for auto-restart'),
     ('restart_ram_threshold', '90', 'RAM threshold percentage for auto-restart'),
-    ('internet_access_enabled', 'false', 'Allow management UI access from internet (requires authentication)')
+    ('restart_drain_grace_sec', '300', 'Seconds to wait for in-flight requests and syncs before restarting'),
+    ('internet_access_enabled', 'false', 'Allow management UI access from internet (requires authentication)'),
+    ('network_lan_extra', '', 'Networks treated as LAN besides the private ranges (IPs/CIDRs, comma separated)'),
+    ('network_trusted_proxies', '', 'Peers whose X-Forwarded-For/X-Real-IP is trusted (IPs/CIDRs; loopback is always trusted)'),
+    ('network_admin_allowlist', '', 'Public networks allowed to reach the management UI without internet access (IPs/CIDRs)'),
+    ('threat_feeds', '[]', 'Threat feed subscriptions (JSON array of {name, url, reason})'),
+    ('threat_feed_interval_sec', '3600', 'Threat feed fetch interval in seconds'),
+    ('threat_feed_failure_threshold', '3', 'Consecutive feed fetch failures before alert'),
+    ('route_deleted_retention_days', '30', 'Days to keep soft-deleted routes before purging'),
+    ('ip_stats_retention_days', '90', 'Days to keep per-IP daily rollups (ip_daily_stats)'),
+    ('facility_report_interval_hours', '24', 'Hours between facility reports pushed to mobes2.0 (0 = off)'),
+    ('facility_report_retention', '200', 'Number of generated facility reports kept (facility_reports)'),
+    ('omada_page_size', '100', 'Omada client list page size (1-1000)'),
+    ('omada_page_concurrency', '1', 'Omada client list pages fetched in parallel per site (1-8)'),
+    ('omada_page_retries', '2', 'Retries per failed Omada client list page (0-5, with backoff)'),
+    ('status_page_enabled', 'false', 'Serve the public status page at /status and /status.json'),
+    ('status_page_title', 'Service Status', 'Status page title'),
+    ('status_page_incident_minutes', '5', 'Minutes a listed route must be unhealthy before it shows as an incident'),
+    ('status_page_rate_limit_per_minute', '60', 'Status page requests allowed per client IP per minute'),
+    ('aranea_register_token_rate_per_minute', '10', 'Device registrations allowed per registration token per minute'),
+    ('aranea_register_ip_rate_per_minute', '20', 'Token-authenticated registration attempts allowed per client IP per minute'),
+    ('topology_share_ip_rate_per_minute', '6', 'Public topology share requests allowed per client IP per minute'),
+    ('topology_share_token_rate_per_minute', '30', 'Public topology share requests allowed per share token per minute'),
+    ('route_version_retention', '50', 'Configuration versions kept per route (oldest dropped first)'),
+    ('omada_controller_offline_after_failures', '3', 'Consecutive failed Omada syncs before a controller counts as offline and its nodes turn unknown'),
+    -- permission_floor_login is seeded at startup from auth.lacisoath_required_permission
+    ('permission_floor_read', '0', 'Minimum permission for read endpoints'),
+    ('permission_floor_operate', '50', 'Minimum permission for operate endpoints'),
+    ('permission_floor_admin', '80', 'Minimum permission for admin endpoints'),
+    ('permission_floor_dangerous', '100', 'Minimum permission for dangerous endpoints'),
+    ('proxy_max_response_header_kb', '64', 'Max upstream response header size in KB (502 when exceeded)'),
+    ('proxy_idle_timeout_sec', '30', 'Max seconds without body data from client or upstream'),
+    ('proxy_min_transfer_rate_bps', '128', 'Minimum body transfer rate in bytes/sec (0 = disabled)'),
+    ('proxy_slow_transfer_grace_sec', '10', 'Seconds before the minimum transfer rate is enforced'),
+    ('proxy_max_response_buffer_mb', '100', 'Max buffered upstream response body in MB'),
+    ('proxy_max_request_body_mb', NULL, 'Max client request body in MB (empty = [server] max_body_mb)'),
+    ('proxy_ws_queue_frames', '256', 'WebSocket frames queued per direction before reading from the sender pauses'),
+    ('proxy_ws_max_buffered_kb', '16384', 'Max KB queued in a WebSocket tunnel before it is closed with 1011'),
+    ('proxy_ws_keepalive_sec', '30', 'Idle seconds before the gateway pings both WebSocket peers (0 = disabled)'),
+    ('mongo_ingest_batch_size', '200', 'Entries per bulk write when syncers ingest into MongoDB (max 1000)'),
+    ('mongo_ingest_batch_delay_ms', '250', 'Milliseconds between ingest bulk writes (0 = yield only)'),
+    ('security_headers_default', '{"enabled":false,"headers":{"strict-transport-security":{"value":"max-age=31536000; includeSubDomains"},"x-content-type-options":{"value":"nosniff"},"x-frame-options":{"value":"SAMEORIGIN"},"referrer-policy":{"value":"strict-origin-when-cross-origin"},"content-security-policy":{"value":"frame-ancestors \'self\'"}}}', 'Global security response header policy (JSON; routes may override)'),
+    ('server_health_disk_warn_percent', '85', 'Disk usage percentage highlighted on the server-health dashboard'),
+    ('route_approval_required', 'false', 'Queue route changes by users below permission 80 for approval'),
+    ('nginx_auto_regenerate', 'false', 'Regenerate and reload the full proxy nginx config when routes, DDNS hostnames or template settings drift from it'),
+    ('storage_growth_warning_days', '30', 'Warn when MongoDB storage growth reaches its limit within this many days'),
+    ('api_trace_until', '', 'Admin API request recorder deadline (empty = off; set via PUT /api/admin/api-trace/recorder)'),
+    ('api_trace_enabled_by', '', 'Admin who turned the API request recorder on'),
+    ('new_device_alerts', '{"rules":[],"quiet_macs":[],"group_randomized":true}', 'New device alert rules, quiet MAC list and randomized-MAC grouping (JSON)')
 ON DUPLICATE KEY UPDATE setting_key = setting_key;
 
 -- Nginx Template Settings (15 keys)
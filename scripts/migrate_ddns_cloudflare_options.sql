@@ -0,0 +1,14 @@
+-- Migration: Per-record Cloudflare proxied flag and TTL
+-- Run with: mariadb -u akihabara_admin -p < migrate_ddns_cloudflare_options.sql
+
+USE lacis_proxy;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS proxied BOOLEAN NULL
+COMMENT 'cloudflare: orange cloud (NULL keeps record state)'
+AFTER reported_at;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS ttl INT NULL
+COMMENT 'cloudflare: record TTL, 1 = auto (NULL keeps record TTL)'
+AFTER proxied;
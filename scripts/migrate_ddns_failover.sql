@@ -0,0 +1,27 @@
+-- Migration: DDNS failover to a secondary provider config
+-- Run with: mariadb -u akihabara_admin -p < migrate_ddns_failover.sql
+
+USE lacis_proxy;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS secondary_config_id INT NULL
+COMMENT 'failover: standby config for the same hostname'
+AFTER ttl;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS failover_threshold INT NOT NULL DEFAULT 3
+COMMENT 'failover: consecutive failures before switching'
+AFTER secondary_config_id;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS consecutive_failures INT NOT NULL DEFAULT 0
+AFTER failover_threshold;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS failover_active BOOLEAN NOT NULL DEFAULT FALSE
+COMMENT 'failover: updating via the secondary'
+AFTER consecutive_failures;
+
+ALTER TABLE ddns_configs
+ADD CONSTRAINT fk_ddns_secondary FOREIGN KEY IF NOT EXISTS (secondary_config_id)
+REFERENCES ddns_configs(id) ON DELETE SET NULL;
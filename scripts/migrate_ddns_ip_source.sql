@@ -0,0 +1,28 @@
+-- Migration: Per-DDNS IP source (poll / router / webhook)
+-- Run with: mariadb -u akihabara_admin -p < migrate_ddns_ip_source.sql
+
+USE lacis_proxy;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS ip_source VARCHAR(16) NOT NULL DEFAULT 'poll'
+COMMENT 'poll, router or webhook'
+AFTER status;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS openwrt_router_id VARCHAR(64) NULL
+COMMENT 'router source: OpenWrt router (else Omada link)'
+AFTER ip_source;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS report_token VARCHAR(64) NULL
+COMMENT 'webhook source: report-ip bearer token'
+AFTER openwrt_router_id;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS reported_ip VARCHAR(45) NULL
+COMMENT 'webhook source: last pushed address'
+AFTER report_token;
+
+ALTER TABLE ddns_configs
+ADD COLUMN IF NOT EXISTS reported_at TIMESTAMP NULL
+AFTER reported_ip;
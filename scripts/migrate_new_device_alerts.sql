@@ -0,0 +1,9 @@
+-- Migration: New device notifications
+-- Run with: mariadb -u akihabara_admin -p < migrate_new_device_alerts.sql
+
+USE lacis_proxy;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('discord_notify_new_device', 'false', 'Notify devices seen on the network for the first time to Discord'),
+    ('new_device_alerts', '{"rules":[],"quiet_macs":[],"group_randomized":true}', 'New device alert rules, quiet MAC list and randomized-MAC grouping (JSON)')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
@@ -0,0 +1,14 @@
+-- Migration: Runtime-editable permission floors
+-- Run with: mariadb -u akihabara_admin -p < migrate_permission_floors.sql
+--
+-- permission_floor_login is not inserted here: on first start the backend
+-- seeds it from auth.lacisoath_required_permission in the config file.
+
+USE lacis_proxy;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('permission_floor_read', '0', 'Minimum permission for read endpoints'),
+    ('permission_floor_operate', '50', 'Minimum permission for operate endpoints'),
+    ('permission_floor_admin', '80', 'Minimum permission for admin endpoints'),
+    ('permission_floor_dangerous', '100', 'Minimum permission for dangerous endpoints')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
@@ -0,0 +1,13 @@
+-- Migration: Proxy hardening limits (header size, idle/slow transfer, buffering)
+-- Run with: mariadb -u akihabara_admin -p < migrate_proxy_limits.sql
+
+USE lacis_proxy;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('proxy_max_response_header_kb', '64', 'Max upstream response header size in KB (502 when exceeded)'),
+    ('proxy_idle_timeout_sec', '30', 'Max seconds without body data from client or upstream'),
+    ('proxy_min_transfer_rate_bps', '128', 'Minimum body transfer rate in bytes/sec (0 = disabled)'),
+    ('proxy_slow_transfer_grace_sec', '10', 'Seconds before the minimum transfer rate is enforced'),
+    ('proxy_max_response_buffer_mb', '100', 'Max buffered upstream response body in MB'),
+    ('proxy_max_request_body_mb', '100', 'Max client request body in MB')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
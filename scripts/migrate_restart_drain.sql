@@ -0,0 +1,8 @@
+-- Migration: Pre-restart drain grace period
+-- Run with: mariadb -u akihabara_admin -p < migrate_restart_drain.sql
+
+USE lacis_proxy;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('restart_drain_grace_sec', '300', 'Seconds to wait for in-flight requests and syncs before restarting')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
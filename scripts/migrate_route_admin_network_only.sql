@@ -0,0 +1,9 @@
+-- Migration: admin_network_only flag on proxy_routes
+-- Run with: mariadb -u akihabara_admin -p < migrate_route_admin_network_only.sql
+
+USE lacis_proxy;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS admin_network_only BOOLEAN DEFAULT FALSE
+COMMENT 'Only reachable from admin-allowed networks (404 otherwise)'
+AFTER websocket_support;
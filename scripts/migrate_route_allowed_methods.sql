@@ -0,0 +1,9 @@
+-- Migration: per-route allowed HTTP methods (405 for anything else)
+-- Run with: mariadb -u akihabara_admin -p < migrate_route_allowed_methods.sql
+
+USE lacis_proxy;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS allowed_methods TEXT NULL
+COMMENT 'Allowed HTTP methods JSON list (NULL = all methods)'
+AFTER security_headers;
@@ -0,0 +1,42 @@
+-- Migration: Route ownership fields and change-approval workflow
+-- Run with: mariadb -u akihabara_admin -p < migrate_route_ownership.sql
+
+USE lacis_proxy;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS owner_name VARCHAR(100) NULL
+COMMENT 'Responsible person'
+AFTER websocket_support;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS owner_contact VARCHAR(500) NULL
+COMMENT 'Owner contact (https webhook URL or handle)'
+AFTER owner_name;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS team VARCHAR(100) NULL
+COMMENT 'Team tag'
+AFTER owner_contact;
+
+ALTER TABLE proxy_routes
+ADD INDEX IF NOT EXISTS idx_team (team);
+
+CREATE TABLE IF NOT EXISTS route_pending_changes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    route_id INT NULL COMMENT 'Target route (NULL for proposed creations until approved)',
+    action VARCHAR(20) NOT NULL COMMENT 'create or update',
+    payload TEXT NOT NULL COMMENT 'Proposed CreateRouteRequest / UpdateRouteRequest JSON',
+    status ENUM('pending', 'approved', 'rejected') DEFAULT 'pending',
+    proposed_by VARCHAR(255) NOT NULL,
+    proposed_by_permission INT NOT NULL,
+    reviewed_by VARCHAR(255) NULL,
+    review_comment VARCHAR(1000) NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    reviewed_at TIMESTAMP NULL,
+    INDEX idx_status (status),
+    INDEX idx_route (route_id)
+) ENGINE=InnoDB;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('route_approval_required', 'false', 'Queue route changes by users below permission 80 for approval')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
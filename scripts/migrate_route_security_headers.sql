@@ -0,0 +1,13 @@
+-- Migration: security header policy (global setting + per-route override)
+-- Run with: mariadb -u akihabara_admin -p < migrate_route_security_headers.sql
+
+USE lacis_proxy;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS security_headers TEXT NULL
+COMMENT 'Security header override JSON (NULL = global policy)'
+AFTER admin_network_only;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('security_headers_default', '{"enabled":false,"headers":{"strict-transport-security":{"value":"max-age=31536000; includeSubDomains"},"x-content-type-options":{"value":"nosniff"},"x-frame-options":{"value":"SAMEORIGIN"},"referrer-policy":{"value":"strict-origin-when-cross-origin"},"content-security-policy":{"value":"frame-ancestors \'self\'"}}}', 'Global security response header policy (JSON; routes may override)')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
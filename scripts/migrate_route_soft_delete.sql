@@ -0,0 +1,16 @@
+-- Migration: Soft delete for proxy_routes
+-- Run with: mariadb -u akihabara_admin -p < migrate_route_soft_delete.sql
+
+USE lacis_proxy;
+
+ALTER TABLE proxy_routes
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP NULL
+COMMENT 'Soft-delete timestamp (NULL = live)'
+AFTER websocket_support;
+
+ALTER TABLE proxy_routes
+ADD INDEX IF NOT EXISTS idx_deleted_at (deleted_at);
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('route_deleted_retention_days', '30', 'Days to keep soft-deleted routes before purging')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
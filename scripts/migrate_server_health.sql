@@ -0,0 +1,8 @@
+-- Migration: Server health disk usage threshold
+-- Run with: mariadb -u akihabara_admin -p < migrate_server_health.sql
+
+USE lacis_proxy;
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('server_health_disk_warn_percent', '85', 'Disk usage percentage highlighted on the server-health dashboard')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
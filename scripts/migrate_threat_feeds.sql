@@ -0,0 +1,13 @@
+-- Migration: CIDR block entries and threat feed settings
+-- Run with: mariadb -u akihabara_admin -p < migrate_threat_feeds.sql
+
+USE lacis_proxy;
+
+ALTER TABLE blocked_ips
+MODIFY COLUMN ip VARCHAR(64) NOT NULL COMMENT 'IPv4/IPv6 address or CIDR range';
+
+INSERT INTO settings (setting_key, setting_value, description) VALUES
+    ('threat_feeds', '[]', 'Threat feed subscriptions (JSON array of {name, url, reason})'),
+    ('threat_feed_interval_sec', '3600', 'Threat feed fetch interval in seconds'),
+    ('threat_feed_failure_threshold', '3', 'Consecutive feed fetch failures before alert')
+ON DUPLICATE KEY UPDATE setting_key = setting_key;
@@ -0,0 +1,36 @@
+-- Migration: WireGuard client-config profiles
+-- Run with: mariadb -u akihabara_admin -p < migrate_wg_profiles.sql
+
+USE lacis_proxy;
+
+-- WireGuard client-config profiles
+CREATE TABLE IF NOT EXISTS wg_config_profiles (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description VARCHAR(500) NULL,
+    dns VARCHAR(500) NULL COMMENT 'DNS template (NULL = omit DNS line)',
+    allowed_ips VARCHAR(1000) NOT NULL COMMENT 'AllowedIPs template',
+    persistent_keepalive INT NULL COMMENT 'NULL = request value, 0 = never emit',
+    mtu INT NULL,
+    scripts_allowed BOOLEAN DEFAULT FALSE COMMENT 'Allow PreUp/PostUp/PreDown/PostDown',
+    is_default BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB;
+
+-- Default profile reproduces the original generator output
+INSERT INTO wg_config_profiles (name, description, dns, allowed_ips, is_default) VALUES
+    ('default', 'Request DNS / AllowedIPs / keepalive as given', '{dns}', '{allowed_ips}', TRUE)
+ON DUPLICATE KEY UPDATE name = name;
+
+-- Profile each WireGuard peer was provisioned with
+CREATE TABLE IF NOT EXISTS wg_peer_profiles (
+    public_key VARCHAR(64) PRIMARY KEY,
+    controller_id VARCHAR(100) NOT NULL,
+    site_id VARCHAR(100) NOT NULL,
+    peer_name VARCHAR(255) NOT NULL,
+    profile_id INT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_profile (profile_id),
+    FOREIGN KEY (profile_id) REFERENCES wg_config_profiles(id)
+) ENGINE=InnoDB;